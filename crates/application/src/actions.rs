@@ -0,0 +1,73 @@
+//! 用户操作记录
+//!
+//! 控制器通过 [`log_action!`](crate::log_action) 记录高层用户操作（导航、命令分发、导入导出、
+//! 设置修改），事件目标为 [`ACTION_TARGET`]，由应用入口的操作日志层写入 `logs/actions.log`。
+//! 只应传入名称和ID，字段值（如客户名称）不应出现在操作记录中。
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[doc(hidden)]
+pub use tracing as __tracing;
+
+/// 操作记录的 tracing 目标
+pub const ACTION_TARGET: &str = "minicrm::action";
+
+/// 操作记录允许保留的字段，其余字段在写入操作日志时丢弃
+pub const ACTION_FIELDS: &[&str] = &[
+    "controller",
+    "action",
+    "correlation_id",
+    "command",
+    "entity",
+    "entity_id",
+    "route",
+    "setting",
+    "format",
+    "count",
+];
+
+static NEXT_CORRELATION: AtomicU32 = AtomicU32::new(1);
+
+/// 生成关联ID，同时写入操作日志和详细日志，用于对照两者
+pub fn next_correlation_id() -> String {
+    format!(
+        "{:x}-{:04x}",
+        std::process::id(),
+        NEXT_CORRELATION.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// 记录一次用户操作，返回关联ID
+///
+/// ```ignore
+/// log_action!("export", "export_csv", entity = "customers");
+/// log_action!("tasks", "update_status", entity_id = %task_id);
+/// ```
+#[macro_export]
+macro_rules! log_action {
+    ($controller:expr, $action:expr $(, $($field:tt)+)?) => {{
+        let correlation_id = $crate::actions::next_correlation_id();
+        $crate::actions::__tracing::info!(
+            target: $crate::actions::ACTION_TARGET,
+            controller = $controller,
+            action = $action,
+            correlation_id = %correlation_id
+            $(, $($field)+)?,
+            "用户操作"
+        );
+        correlation_id
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correlation_ids_are_unique() {
+        let first = next_correlation_id();
+        let second = next_correlation_id();
+        assert_ne!(first, second);
+        assert!(first.starts_with(&format!("{:x}-", std::process::id())));
+    }
+}
@@ -0,0 +1,409 @@
+//! 统计缓存模块
+//!
+//! 仪表盘的统计数据在数据未变化时不必每次重新计算。缓存按（统计类型, 周期）
+//! 保存结果，由领域事件有针对性地失效；超过有效期的数据先返回旧值，
+//! 同时在后台刷新，刷新完成后通知订阅的视图模型。
+
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+use chrono::{DateTime, Duration};
+use minicrm_core::{
+    Clock, CoreResult, DomainEvent, EntityKind, EventEnvelope, EventHandler, ReportPeriod,
+};
+use tracing::{debug, warn};
+
+/// 默认缓存有效期（分钟）
+pub const DEFAULT_TTL_MINUTES: i64 = 5;
+
+/// 统计类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatKind {
+    /// 客户统计
+    Customers,
+    /// 任务统计
+    Tasks,
+    /// 报价统计
+    Quotes,
+    /// 月度统计
+    Monthly,
+    /// 仪表盘快照（由以上各项组合）
+    Dashboard,
+}
+
+impl StatKind {
+    /// 某类实体变化后需要失效的统计类型
+    fn affected_by(entity: EntityKind) -> &'static [StatKind] {
+        match entity {
+            EntityKind::Customer => &[StatKind::Customers, StatKind::Monthly, StatKind::Dashboard],
+            EntityKind::Task => &[StatKind::Tasks, StatKind::Monthly, StatKind::Dashboard],
+            EntityKind::Quote => &[StatKind::Quotes, StatKind::Monthly, StatKind::Dashboard],
+            EntityKind::ServiceTicket => &[StatKind::Monthly, StatKind::Dashboard],
+            EntityKind::Supplier | EntityKind::Order | EntityKind::Opportunity => &[],
+        }
+    }
+}
+
+/// 缓存键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StatKey {
+    /// 统计类型
+    pub kind: StatKind,
+    /// 统计周期（与周期无关的统计为空）
+    pub range: Option<ReportPeriod>,
+}
+
+impl StatKey {
+    /// 与周期无关的统计
+    pub fn of(kind: StatKind) -> Self {
+        Self { kind, range: None }
+    }
+
+    /// 指定周期的统计
+    pub fn for_period(kind: StatKind, period: ReportPeriod) -> Self {
+        Self {
+            kind,
+            range: Some(period),
+        }
+    }
+}
+
+struct CacheEntry {
+    value: Arc<dyn Any + Send + Sync>,
+    stored_at: DateTime<chrono::Utc>,
+}
+
+type RefreshListener = Arc<dyn Fn(&StatKey) + Send + Sync>;
+
+struct CacheState {
+    entries: HashMap<StatKey, CacheEntry>,
+    refreshing: HashSet<StatKey>,
+    /// 每次失效递增，防止失效前开始的后台刷新写回旧数据
+    generation: u64,
+}
+
+/// 统计缓存
+pub struct StatisticsCache {
+    clock: Arc<dyn Clock>,
+    ttl: Duration,
+    state: Mutex<CacheState>,
+    listeners: RwLock<Vec<RefreshListener>>,
+}
+
+impl std::fmt::Debug for StatisticsCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StatisticsCache")
+            .field("ttl", &self.ttl)
+            .field("entries", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl StatisticsCache {
+    /// 创建统计缓存
+    pub fn new(clock: Arc<dyn Clock>, ttl: Duration) -> Self {
+        Self {
+            clock,
+            ttl,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                refreshing: HashSet::new(),
+                generation: 0,
+            }),
+            listeners: RwLock::new(Vec::new()),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// 缓存条目数
+    pub fn len(&self) -> usize {
+        self.state().entries.len()
+    }
+
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 是否缓存了指定统计
+    pub fn contains(&self, key: &StatKey) -> bool {
+        self.state().entries.contains_key(key)
+    }
+
+    /// 订阅后台刷新完成通知
+    pub fn subscribe<F>(&self, listener: F)
+    where
+        F: Fn(&StatKey) + Send + Sync + 'static,
+    {
+        if let Ok(mut listeners) = self.listeners.write() {
+            listeners.push(Arc::new(listener));
+        }
+    }
+
+    /// 读取缓存，未命中时计算
+    ///
+    /// - 命中且未过期：直接返回；
+    /// - 命中但已过期：立即返回旧值，并在后台重新计算；
+    /// - 未命中（或已失效）：同步计算并缓存。
+    pub async fn get_or_compute<T, F, Fut>(self: &Arc<Self>, key: StatKey, compute: F) -> CoreResult<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = CoreResult<T>> + Send + 'static,
+    {
+        let now = self.clock.now();
+        let (cached, stale, generation) = {
+            let mut state = self.state();
+            let generation = state.generation;
+            match state
+                .entries
+                .get(&key)
+                .and_then(|entry| Some((entry.value.downcast_ref::<T>()?.clone(), entry.stored_at)))
+            {
+                Some((value, stored_at)) => {
+                    let stale = now - stored_at >= self.ttl;
+                    // 同一键只保留一个后台刷新
+                    let start_refresh = stale && state.refreshing.insert(key);
+                    (Some(value), start_refresh, generation)
+                }
+                None => (None, false, generation),
+            }
+        };
+
+        match cached {
+            Some(value) => {
+                if stale {
+                    self.spawn_refresh(key, generation, compute);
+                }
+                Ok(value)
+            }
+            None => {
+                let value = compute().await?;
+                self.store(key, generation, value.clone());
+                Ok(value)
+            }
+        }
+    }
+
+    /// 跳过缓存直接计算，并以结果更新缓存（报表生成使用）
+    pub async fn refresh<T, F, Fut>(&self, key: StatKey, compute: F) -> CoreResult<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = CoreResult<T>>,
+    {
+        let generation = self.state().generation;
+        let value = compute().await?;
+        self.store(key, generation, value.clone());
+        Ok(value)
+    }
+
+    fn spawn_refresh<T, F, Fut>(self: &Arc<Self>, key: StatKey, generation: u64, compute: F)
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = CoreResult<T>> + Send + 'static,
+    {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            // 没有异步运行时：放弃刷新，下次读取时再尝试
+            self.state().refreshing.remove(&key);
+            return;
+        };
+
+        let cache = Arc::clone(self);
+        runtime.spawn(async move {
+            let result = compute().await;
+            cache.state().refreshing.remove(&key);
+            match result {
+                Ok(value) => {
+                    if cache.store(key, generation, value) {
+                        debug!("统计缓存已在后台刷新: {:?}", key);
+                        cache.notify(&key);
+                    }
+                }
+                Err(e) => warn!("统计缓存后台刷新失败 {:?}: {}", key, e),
+            }
+        });
+    }
+
+    /// 写入缓存；期间发生过失效则丢弃，返回是否写入
+    fn store<T: Send + Sync + 'static>(&self, key: StatKey, generation: u64, value: T) -> bool {
+        let stored_at = self.clock.now();
+        let mut state = self.state();
+        if state.generation != generation {
+            return false;
+        }
+        state.entries.insert(
+            key,
+            CacheEntry {
+                value: Arc::new(value),
+                stored_at,
+            },
+        );
+        true
+    }
+
+    fn notify(&self, key: &StatKey) {
+        let listeners = match self.listeners.read() {
+            Ok(listeners) => listeners.clone(),
+            Err(_) => return,
+        };
+        for listener in listeners {
+            listener(key);
+        }
+    }
+
+    /// 使某类统计的全部周期失效
+    pub fn invalidate(&self, kinds: &[StatKind]) {
+        if kinds.is_empty() {
+            return;
+        }
+        let mut state = self.state();
+        state.entries.retain(|key, _| !kinds.contains(&key.kind));
+        state.generation += 1;
+    }
+
+    /// 根据领域事件使相关统计失效
+    pub fn invalidate_for_event(&self, event: &DomainEvent) {
+        let kinds = StatKind::affected_by(event.entity_kind());
+        debug!("事件 {} 使统计失效: {:?}", event.event_type(), kinds);
+        self.invalidate(kinds);
+    }
+
+    /// 清空全部缓存（诊断界面使用）
+    pub fn invalidate_all(&self) {
+        let mut state = self.state();
+        state.entries.clear();
+        state.generation += 1;
+    }
+}
+
+impl EventHandler for StatisticsCache {
+    fn name(&self) -> &str {
+        "statistics_cache"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        self.invalidate_for_event(&envelope.event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use minicrm_core::{ManualClock, QuoteStatus};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use uuid::Uuid;
+
+    fn setup() -> (Arc<ManualClock>, Arc<StatisticsCache>) {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let cache = Arc::new(StatisticsCache::new(
+            clock.clone(),
+            Duration::minutes(DEFAULT_TTL_MINUTES),
+        ));
+        (clock, cache)
+    }
+
+    async fn fill(cache: &Arc<StatisticsCache>, key: StatKey, value: u32) -> CoreResult<u32> {
+        cache.get_or_compute(key, move || async move { Ok(value) }).await
+    }
+
+    #[tokio::test]
+    async fn test_event_targeted_invalidation() -> CoreResult<()> {
+        let (_clock, cache) = setup();
+        let customers = StatKey::of(StatKind::Customers);
+        let quotes = StatKey::of(StatKind::Quotes);
+        let tasks = StatKey::of(StatKind::Tasks);
+        let dashboard = StatKey::for_period(StatKind::Dashboard, ReportPeriod::containing(Utc::now()));
+        for key in [customers, quotes, tasks, dashboard] {
+            fill(&cache, key, 1).await?;
+        }
+
+        cache.invalidate_for_event(&DomainEvent::QuoteStatusChanged {
+            quote_id: Uuid::new_v4(),
+            customer_id: Uuid::new_v4(),
+            from: QuoteStatus::Sent,
+            to: QuoteStatus::Accepted,
+        });
+        assert!(!cache.contains(&quotes));
+        assert!(!cache.contains(&dashboard));
+        assert!(cache.contains(&customers));
+        assert!(cache.contains(&tasks));
+
+        cache.handle(&EventEnvelope::new(DomainEvent::EntityCreated {
+            entity: EntityKind::Customer,
+            id: Uuid::new_v4(),
+        }))?;
+        assert!(!cache.contains(&customers));
+        assert!(cache.contains(&tasks));
+
+        // 失效后重新计算
+        assert_eq!(fill(&cache, quotes, 2).await?, 2);
+
+        cache.invalidate_all();
+        assert!(cache.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ttl_expiry_serves_stale_then_refreshes() -> CoreResult<()> {
+        let (clock, cache) = setup();
+        let key = StatKey::of(StatKind::Tasks);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        cache.subscribe(move |key| {
+            let _ = tx.send(*key);
+        });
+
+        assert_eq!(fill(&cache, key, 1).await?, 1);
+        clock.advance(Duration::minutes(DEFAULT_TTL_MINUTES - 1));
+        assert_eq!(fill(&cache, key, 2).await?, 1);
+
+        // 过期：立即返回旧值，后台刷新完成后通知
+        clock.advance(Duration::minutes(1));
+        assert_eq!(fill(&cache, key, 3).await?, 1);
+        let refreshed = tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv())
+            .await
+            .ok()
+            .flatten();
+        assert_eq!(refreshed, Some(key));
+        assert_eq!(fill(&cache, key, 4).await?, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_single_background_refresh_per_key() -> CoreResult<()> {
+        let (clock, cache) = setup();
+        let key = StatKey::of(StatKind::Customers);
+        fill(&cache, key, 1).await?;
+        clock.advance(Duration::minutes(DEFAULT_TTL_MINUTES));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        cache.subscribe(move |_| {
+            let _ = tx.send(());
+        });
+        let calls = Arc::new(AtomicU32::new(0));
+        for _ in 0..3 {
+            let calls = calls.clone();
+            cache
+                .get_or_compute(key, move || async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(9_u32)
+                })
+                .await?;
+        }
+        tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv())
+            .await
+            .ok();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
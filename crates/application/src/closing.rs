@@ -0,0 +1,397 @@
+//! 月结
+//!
+//! 每月1日把上一个月的关键统计（报价、新增客户、应收账龄、工单SLA）锁定为快照。
+//! 已结账月份的统计和报表都从快照读取，之后补录或修改的单据不再改变这些数字；
+//! 只有未结账或已重新打开的月份实时计算。重新打开需要管理员并填写原因。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::NaiveTime;
+use minicrm_core::{
+    BusinessCalendar, Clock, CoreError, CoreResult, Job, JobSchedule, MonthlyClosing,
+    MonthlyClosingService, MonthlyStatistics, ReportPeriod, StatisticsService, StatisticsSource,
+    SystemClock,
+};
+use tracing::info;
+
+use crate::cache::{StatKind, StatisticsCache};
+use crate::commands::{CloseMonthCommand, CommandHandler, ReopenMonthCommand};
+use crate::session::CurrentUser;
+
+/// 优先读取月结快照的统计服务
+///
+/// 包装实时统计服务：已锁定的月份返回快照并标记为 [`StatisticsSource::Snapshot`]，
+/// 其余月份实时计算。
+pub struct ClosedMonthStatistics {
+    live: Arc<dyn StatisticsService + Send + Sync>,
+    closings: Arc<dyn MonthlyClosingService + Send + Sync>,
+}
+
+impl std::fmt::Debug for ClosedMonthStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClosedMonthStatistics").finish_non_exhaustive()
+    }
+}
+
+impl ClosedMonthStatistics {
+    /// 创建统计服务
+    pub fn new(
+        live: Arc<dyn StatisticsService + Send + Sync>,
+        closings: Arc<dyn MonthlyClosingService + Send + Sync>,
+    ) -> Self {
+        Self { live, closings }
+    }
+}
+
+#[async_trait]
+impl StatisticsService for ClosedMonthStatistics {
+    async fn monthly_statistics(&self, period: ReportPeriod) -> CoreResult<MonthlyStatistics> {
+        if let Some(closing) = self.closings.closing(period).await?.filter(|c| c.locked) {
+            return Ok(MonthlyStatistics {
+                source: StatisticsSource::Snapshot {
+                    closed_at: closing.closed_at,
+                    closed_by: closing.closed_by,
+                },
+                ..closing.statistics
+            });
+        }
+        let mut statistics = self.live.monthly_statistics(period).await?;
+        statistics.source = StatisticsSource::Live;
+        Ok(statistics)
+    }
+}
+
+/// 月结命令处理器
+pub struct MonthlyClosingHandlers {
+    live: Arc<dyn StatisticsService + Send + Sync>,
+    closings: Arc<dyn MonthlyClosingService + Send + Sync>,
+    current_user: CurrentUser,
+    cache: Arc<StatisticsCache>,
+    calendar: BusinessCalendar,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for MonthlyClosingHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MonthlyClosingHandlers")
+            .field("calendar", &self.calendar)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MonthlyClosingHandlers {
+    /// 创建处理器
+    ///
+    /// `live` 必须是实时统计服务，不能是 [`ClosedMonthStatistics`]，否则重新结账会读回旧快照。
+    pub fn new(
+        live: Arc<dyn StatisticsService + Send + Sync>,
+        closings: Arc<dyn MonthlyClosingService + Send + Sync>,
+        current_user: CurrentUser,
+        cache: Arc<StatisticsCache>,
+    ) -> Self {
+        Self {
+            live,
+            closings,
+            current_user,
+            cache,
+            calendar: BusinessCalendar::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置业务日历，“当前月份”按其本地日期计算
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 使用指定时钟（测试中固定当前时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 当前未结束的月份
+    fn open_period(&self) -> ReportPeriod {
+        self.calendar.period_containing(self.clock.now())
+    }
+
+    /// 结账指定月份（为空时为上一个月）
+    ///
+    /// # Errors
+    ///
+    /// 月份尚未结束时返回 [`CoreError::Validation`]，已锁定时返回 [`CoreError::Conflict`]。
+    pub async fn close(
+        &self,
+        period: Option<ReportPeriod>,
+        closed_by: Option<&str>,
+    ) -> CoreResult<MonthlyClosing> {
+        let open = self.open_period();
+        let period = period.unwrap_or_else(|| open.previous());
+        if period >= open {
+            return Err(CoreError::validation(format!(
+                "{} 尚未结束，不能月结",
+                period
+            )));
+        }
+        let mut statistics = self.live.monthly_statistics(period).await?;
+        statistics.source = StatisticsSource::Live;
+        let closing = self
+            .closings
+            .close_month(period, statistics, closed_by)
+            .await?;
+        self.cache.invalidate(&[StatKind::Monthly, StatKind::Dashboard]);
+        info!("{} 已月结", period);
+        Ok(closing)
+    }
+}
+
+#[async_trait]
+impl CommandHandler<CloseMonthCommand> for MonthlyClosingHandlers {
+    async fn handle(&self, command: CloseMonthCommand) -> CoreResult<MonthlyClosing> {
+        let username = self.current_user.username();
+        self.close(command.period, username.as_deref()).await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ReopenMonthCommand> for MonthlyClosingHandlers {
+    async fn handle(&self, command: ReopenMonthCommand) -> CoreResult<MonthlyClosing> {
+        let actor = self
+            .current_user
+            .username()
+            .ok_or_else(|| CoreError::permission("请先登录"))?;
+        let closing = self
+            .closings
+            .reopen_month(command.period, &actor, &command.reason)
+            .await?;
+        self.cache.invalidate(&[StatKind::Monthly, StatKind::Dashboard]);
+        info!("{} 已由 {} 重新打开: {}", command.period, actor, command.reason.trim());
+        Ok(closing)
+    }
+}
+
+/// 月结定时任务
+///
+/// 每月1日凌晨结账上一个月，早于月度报表任务，报表因此读取快照。
+/// 上一个月已结账时视为完成。
+pub struct MonthlyClosingJob {
+    handlers: Arc<MonthlyClosingHandlers>,
+}
+
+impl std::fmt::Debug for MonthlyClosingJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MonthlyClosingJob").finish_non_exhaustive()
+    }
+}
+
+impl MonthlyClosingJob {
+    /// 创建月结任务
+    pub fn new(handlers: Arc<MonthlyClosingHandlers>) -> Self {
+        Self { handlers }
+    }
+}
+
+#[async_trait]
+impl Job for MonthlyClosingJob {
+    fn name(&self) -> &str {
+        "monthly_closing"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::monthly(1, NaiveTime::from_hms_opt(1, 0, 0).unwrap_or_default())
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        match self.handlers.close(None, None).await {
+            Ok(_) | Err(CoreError::Conflict(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandBus;
+    use crate::session::PermissionGuard;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{ManualClock, User, UserRole};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    /// 可修改的实时统计（模拟月结后补录或修改历史报价）
+    #[derive(Default)]
+    struct LiveStats {
+        accepted_amount: Mutex<f64>,
+    }
+
+    #[async_trait]
+    impl StatisticsService for LiveStats {
+        async fn monthly_statistics(&self, _period: ReportPeriod) -> CoreResult<MonthlyStatistics> {
+            Ok(MonthlyStatistics {
+                quotes_accepted_amount: *self.accepted_amount.lock().unwrap(),
+                ..MonthlyStatistics::default()
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryClosings {
+        closings: Mutex<HashMap<ReportPeriod, MonthlyClosing>>,
+    }
+
+    #[async_trait]
+    impl MonthlyClosingService for MemoryClosings {
+        async fn closing(&self, period: ReportPeriod) -> CoreResult<Option<MonthlyClosing>> {
+            Ok(self.closings.lock().unwrap().get(&period).cloned())
+        }
+
+        async fn close_month(
+            &self,
+            period: ReportPeriod,
+            statistics: MonthlyStatistics,
+            closed_by: Option<&str>,
+        ) -> CoreResult<MonthlyClosing> {
+            let mut closings = self.closings.lock().unwrap();
+            if closings.get(&period).is_some_and(|c| c.locked) {
+                return Err(CoreError::conflict("已月结"));
+            }
+            let closing = MonthlyClosing {
+                period,
+                statistics,
+                locked: true,
+                closed_at: Utc.with_ymd_and_hms(2024, 6, 1, 1, 0, 0).unwrap(),
+                closed_by: closed_by.map(str::to_string),
+            };
+            closings.insert(period, closing.clone());
+            Ok(closing)
+        }
+
+        async fn reopen_month(
+            &self,
+            period: ReportPeriod,
+            _actor: &str,
+            _reason: &str,
+        ) -> CoreResult<MonthlyClosing> {
+            let mut closings = self.closings.lock().unwrap();
+            let closing = closings
+                .get_mut(&period)
+                .ok_or_else(|| CoreError::not_found("月结记录"))?;
+            closing.locked = false;
+            Ok(closing.clone())
+        }
+    }
+
+    struct Fixture {
+        live: Arc<LiveStats>,
+        statistics: ClosedMonthStatistics,
+        handlers: Arc<MonthlyClosingHandlers>,
+        session: CurrentUser,
+        commands: CommandBus,
+    }
+
+    fn fixture() -> Fixture {
+        let live = Arc::new(LiveStats::default());
+        let closings = Arc::new(MemoryClosings::default());
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 1, 1, 0, 0).unwrap(),
+        ));
+        let cache = Arc::new(StatisticsCache::new(clock.clone(), chrono::Duration::seconds(60)));
+        let session = CurrentUser::new();
+        let handlers = Arc::new(
+            MonthlyClosingHandlers::new(live.clone(), closings.clone(), session.clone(), cache)
+                .with_clock(clock),
+        );
+        let mut commands = CommandBus::new();
+        commands.add_guard(Arc::new(PermissionGuard::new(session.clone())));
+        commands.register::<CloseMonthCommand>(handlers.clone());
+        commands.register::<ReopenMonthCommand>(handlers.clone());
+        Fixture {
+            statistics: ClosedMonthStatistics::new(live.clone(), closings),
+            live,
+            handlers,
+            session,
+            commands,
+        }
+    }
+
+    fn user(role: UserRole) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: format!("{:?}", role).to_lowercase(),
+            display_name: String::new(),
+            role,
+            password_hash: String::new(),
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn may() -> ReportPeriod {
+        ReportPeriod::new(2024, 5).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_closed_month_ignores_later_edits() {
+        let f = fixture();
+        *f.live.accepted_amount.lock().unwrap() = 10_000.0;
+
+        // 结账前实时计算
+        let stats = f.statistics.monthly_statistics(may()).await.unwrap();
+        assert_eq!(stats.source, StatisticsSource::Live);
+
+        MonthlyClosingJob::new(f.handlers.clone()).run().await.unwrap();
+        // 再次运行视为已完成
+        MonthlyClosingJob::new(f.handlers.clone()).run().await.unwrap();
+
+        // 月结后修改五月的报价，五月的数字不变，六月仍实时计算
+        *f.live.accepted_amount.lock().unwrap() = 12_500.0;
+        let stats = f.statistics.monthly_statistics(may()).await.unwrap();
+        assert!(stats.is_snapshot());
+        assert!((stats.quotes_accepted_amount - 10_000.0).abs() < f64::EPSILON);
+        let june = f.statistics.monthly_statistics(may().next()).await.unwrap();
+        assert!(!june.is_snapshot());
+        assert!((june.quotes_accepted_amount - 12_500.0).abs() < f64::EPSILON);
+
+        // 当前月份不能结账
+        let err = f.handlers.close(Some(may().next()), None).await.unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_reopen_requires_admin_and_reclose_overwrites() {
+        let f = fixture();
+        *f.live.accepted_amount.lock().unwrap() = 10_000.0;
+        f.session.sign_in(user(UserRole::Admin));
+        f.commands.dispatch(CloseMonthCommand::default()).await.unwrap();
+        *f.live.accepted_amount.lock().unwrap() = 12_500.0;
+
+        let reopen = || ReopenMonthCommand {
+            period: may(),
+            reason: "补录五月报价".to_string(),
+        };
+        f.session.sign_in(user(UserRole::Sales));
+        let err = f.commands.dispatch(reopen()).await.unwrap_err();
+        assert!(matches!(err, CoreError::Permission(_)), "{err}");
+
+        f.session.sign_in(user(UserRole::Admin));
+        f.commands.dispatch(reopen()).await.unwrap();
+        let stats = f.statistics.monthly_statistics(may()).await.unwrap();
+        assert!(!stats.is_snapshot());
+        assert!((stats.quotes_accepted_amount - 12_500.0).abs() < f64::EPSILON);
+
+        let closing = f
+            .commands
+            .dispatch(CloseMonthCommand { period: Some(may()) })
+            .await
+            .unwrap();
+        assert_eq!(closing.closed_by.as_deref(), Some("admin"));
+        let stats = f.statistics.monthly_statistics(may()).await.unwrap();
+        assert!(stats.is_snapshot());
+        assert!((stats.quotes_accepted_amount - 12_500.0).abs() < f64::EPSILON);
+    }
+}
@@ -1,5 +1,1728 @@
 //! 命令模块
 //!
-//! 定义应用层的命令处理
+//! 定义应用层的命令及命令总线。界面、REST API等入口都通过同一条总线分发命令。
 
-// 暂时为空，后续实现
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    ArchiveManifest, ArchivePolicy, ArchiveRun, CancellationToken, CategoryChange, ChangesetSummary,
+    ConsistencyReport, CreatedLead, Currency, Customer, CoreError, CoreResult,
+    CustomerExportRequest,
+    DeletionBatch, DetectedMapping, DiagnosticsBundleSummary, EmailAddress, EntityKind,
+    ExportProgress, FieldError, FieldPolicy,
+    FieldRequirement, IdempotencyRecord, IdempotencyService, LocalDate,
+    KnowledgeArticle, Money, MonthlyClosing, Order, PhoneNumber, PricedProduct, Quote, QuoteItem,
+    QuoteStatus, QuoteTemplate, QuoteVerification, ReportPeriod, RestoreReport, ServiceTicket,
+    SettingsExportSummary, SettingsImportReport, SettingsSection, Task, TaskNote, TaskPriority,
+    TaskStatus, UserRole,
+};
+use minicrm_core::validation::{self, NAME_MAX_CHARS};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::consistency::DEFAULT_VIOLATION_LIMIT;
+use crate::import::SpreadsheetImport;
+use crate::pricing::PriceChange;
+use crate::queries::{ArchivePreviewQuery, PriceAdjustmentPreviewQuery};
+use crate::reports::ReportFormat;
+
+/// 命令接口
+pub trait Command: Send + 'static {
+    /// 命令名称（用于日志和权限映射）
+    const NAME: &'static str;
+
+    /// 执行命令所需的最低角色
+    ///
+    /// 默认为写操作（销售及以上）；删除、清除和设置类命令应要求管理员。
+    const REQUIRED_ROLE: UserRole = UserRole::Sales;
+
+    /// 命令执行结果类型
+    type Output: Send + 'static;
+
+    /// 命令作用的实体ID（写入操作日志，不记录其他字段）
+    fn entity_id(&self) -> Option<Uuid> {
+        None
+    }
+}
+
+/// 支持幂等键的命令
+///
+/// 同一次用户操作（重复点击“保存”、网络重试）携带相同的幂等键，经
+/// [`CommandBus::dispatch_idempotent`] 分发时只执行一次，之后直接返回保存的结果。
+pub trait Idempotent: Command + Serialize {
+    /// 幂等键（为空时按普通命令执行）
+    fn idempotency_key(&self) -> Option<Uuid>;
+}
+
+/// 可取消的耗时命令
+///
+/// 经 [`CommandBus::dispatch_cancellable`] 分发时由总线设置取消令牌，处理器在批次或行之间
+/// 检查，取消时返回 [`CoreError::Cancelled`] 并删除已写出的部分文件。
+pub trait Cancellable: Command {
+    /// 设置取消令牌
+    fn set_cancellation(&mut self, cancel: CancellationToken);
+}
+
+/// 命令处理器接口
+#[async_trait]
+pub trait CommandHandler<C: Command>: Send + Sync {
+    /// 处理命令
+    async fn handle(&self, command: C) -> CoreResult<C::Output>;
+}
+
+/// 待分发命令的元信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandInfo {
+    /// 命令名称
+    pub name: &'static str,
+    /// 所需角色
+    pub required_role: UserRole,
+}
+
+impl CommandInfo {
+    /// 获取命令类型的元信息
+    pub fn of<C: Command>() -> Self {
+        Self {
+            name: C::NAME,
+            required_role: C::REQUIRED_ROLE,
+        }
+    }
+}
+
+/// 命令守卫接口
+///
+/// 在命令分发前执行检查（如应用锁定、权限），返回错误时命令不会被处理。
+pub trait CommandGuard: Send + Sync {
+    /// 检查是否允许执行命令
+    fn check(&self, command: &CommandInfo) -> CoreResult<()>;
+}
+
+/// 命令总线
+#[derive(Default)]
+pub struct CommandBus {
+    handlers: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    guards: Vec<Arc<dyn CommandGuard>>,
+    idempotency: Option<Arc<dyn IdempotencyService + Send + Sync>>,
+    in_flight: Mutex<HashSet<Uuid>>,
+}
+
+impl std::fmt::Debug for CommandBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandBus")
+            .field("handlers", &self.handlers.len())
+            .field("guards", &self.guards.len())
+            .field("idempotency", &self.idempotency.is_some())
+            .finish()
+    }
+}
+
+/// 正在执行的幂等键，释放时移除
+struct InFlight<'a> {
+    keys: &'a Mutex<HashSet<Uuid>>,
+    key: Uuid,
+}
+
+impl<'a> InFlight<'a> {
+    fn acquire(keys: &'a Mutex<HashSet<Uuid>>, key: Uuid) -> CoreResult<Self> {
+        let mut held = keys
+            .lock()
+            .map_err(|_| CoreError::Other("幂等键锁已损坏".to_string()))?;
+        if !held.insert(key) {
+            return Err(CoreError::conflict("该操作正在处理中，请勿重复提交"));
+        }
+        Ok(Self { keys, key })
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut held) = self.keys.lock() {
+            held.remove(&self.key);
+        }
+    }
+}
+
+/// 请求内容的哈希（SHA-256，十六进制）
+fn request_hash<C: Serialize>(command: &C) -> CoreResult<String> {
+    Ok(hex::encode(Sha256::digest(serde_json::to_vec(command)?)))
+}
+
+impl CommandBus {
+    /// 创建新的命令总线
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册命令处理器（同一命令重复注册时后者覆盖前者）
+    pub fn register<C: Command>(&mut self, handler: Arc<dyn CommandHandler<C>>) {
+        self.handlers.insert(TypeId::of::<C>(), Box::new(handler));
+    }
+
+    /// 添加命令守卫
+    pub fn add_guard(&mut self, guard: Arc<dyn CommandGuard>) {
+        self.guards.push(guard);
+    }
+
+    /// 设置幂等记录服务（未设置时幂等键被忽略）
+    pub fn set_idempotency_service(&mut self, service: Arc<dyn IdempotencyService + Send + Sync>) {
+        self.idempotency = Some(service);
+    }
+
+    /// 分发命令
+    ///
+    /// # Errors
+    ///
+    /// 守卫拒绝、命令未注册处理器或处理失败时返回错误。
+    pub async fn dispatch<C: Command>(&self, command: C) -> CoreResult<C::Output> {
+        self.check_guards(&CommandInfo::of::<C>())?;
+        self.execute(command).await
+    }
+
+    /// 分发可取消的命令，返回执行结果的 future 和取消令牌
+    ///
+    /// 进度对话框的“取消”按钮调用令牌的 [`CancellationToken::cancel`]，之后 future 以
+    /// [`CoreError::Cancelled`] 结束；守卫拒绝等错误与 [`Self::dispatch`] 相同。
+    pub fn dispatch_cancellable<C: Cancellable>(
+        &self,
+        mut command: C,
+    ) -> (impl Future<Output = CoreResult<C::Output>> + '_, CancellationToken) {
+        let cancel = CancellationToken::new();
+        command.set_cancellation(cancel.clone());
+        (self.dispatch(command), cancel)
+    }
+
+    /// 按幂等键分发命令
+    ///
+    /// 已记录的键直接返回保存的结果而不重复执行；执行成功的结果保存
+    /// [`IDEMPOTENCY_TTL_HOURS`](minicrm_core::IDEMPOTENCY_TTL_HOURS) 小时。
+    ///
+    /// # Errors
+    ///
+    /// 同一键对应的命令或请求内容不一致、同一键的命令仍在执行时返回冲突错误；
+    /// 其余同 [`Self::dispatch`]。
+    pub async fn dispatch_idempotent<C>(&self, command: C) -> CoreResult<C::Output>
+    where
+        C: Idempotent,
+        C::Output: Serialize + DeserializeOwned,
+    {
+        let (Some(store), Some(key)) = (&self.idempotency, command.idempotency_key()) else {
+            return self.dispatch(command).await;
+        };
+        self.check_guards(&CommandInfo::of::<C>())?;
+
+        let request_hash = request_hash(&command)?;
+        let _in_flight = InFlight::acquire(&self.in_flight, key)?;
+        if let Some(record) = store.find_record(key).await? {
+            if record.command != C::NAME || record.request_hash != request_hash {
+                return Err(CoreError::conflict(
+                    "幂等键已用于内容不同的请求，请刷新后重新提交",
+                ));
+            }
+            debug!("重复提交，返回已保存的结果: {} ({})", C::NAME, key);
+            return Ok(serde_json::from_str(&record.result)?);
+        }
+
+        let output = self.execute(command).await?;
+        let record = IdempotencyRecord {
+            key,
+            command: C::NAME.to_string(),
+            request_hash,
+            result: serde_json::to_string(&output)?,
+            created_at: Utc::now(),
+        };
+        if let Err(e) = store.save_record(record).await {
+            warn!("保存幂等记录失败: {} ({}): {}", C::NAME, key, e);
+        }
+        Ok(output)
+    }
+
+    fn check_guards(&self, info: &CommandInfo) -> CoreResult<()> {
+        for guard in &self.guards {
+            guard.check(info)?;
+        }
+        Ok(())
+    }
+
+    async fn execute<C: Command>(&self, command: C) -> CoreResult<C::Output> {
+        let handler = self
+            .handlers
+            .get(&TypeId::of::<C>())
+            .and_then(|h| h.downcast_ref::<Arc<dyn CommandHandler<C>>>())
+            .cloned()
+            .ok_or_else(|| CoreError::configuration(format!("未注册命令处理器: {}", C::NAME)))?;
+
+        debug!("分发命令: {}", C::NAME);
+        match command.entity_id() {
+            Some(id) => {
+                crate::log_action!("command_bus", "dispatch", command = C::NAME, entity_id = %id)
+            }
+            None => crate::log_action!("command_bus", "dispatch", command = C::NAME),
+        };
+        handler.handle(command).await
+    }
+}
+
+/// 创建客户命令
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCustomerCommand {
+    /// 客户名称
+    pub name: String,
+    /// 联系人
+    pub contact_person: Option<String>,
+    /// 电话
+    pub phone: Option<String>,
+    /// 邮箱
+    pub email: Option<String>,
+    /// 地址
+    pub address: Option<String>,
+    /// 幂等键（同一次提交的重试使用相同的键）
+    #[serde(default)]
+    pub idempotency_key: Option<Uuid>,
+}
+
+impl CreateCustomerCommand {
+    /// 客户名称：必填，不超过 [`NAME_MAX_CHARS`] 个字
+    ///
+    /// # Errors
+    ///
+    /// 不符合时返回 [`CoreError::Validation`]。
+    pub fn check_name(name: &str) -> CoreResult<()> {
+        let name = validation::non_blank("客户名称", name)?;
+        validation::within_chars("客户名称", name, NAME_MAX_CHARS)
+    }
+
+    /// 电话：可为空，填写时须为 [`PhoneNumber`]
+    ///
+    /// # Errors
+    ///
+    /// 格式不正确时返回 [`CoreError::Validation`]。
+    pub fn check_phone(phone: &str) -> CoreResult<()> {
+        if phone.trim().is_empty() {
+            return Ok(());
+        }
+        PhoneNumber::try_from(phone).map(|_| ())
+    }
+
+    /// 邮箱：可为空，填写时须为 [`EmailAddress`]
+    ///
+    /// # Errors
+    ///
+    /// 格式不正确时返回 [`CoreError::Validation`]。
+    pub fn check_email(email: &str) -> CoreResult<()> {
+        if email.trim().is_empty() {
+            return Ok(());
+        }
+        EmailAddress::try_from(email).map(|_| ())
+    }
+
+    /// 保存前校验全部字段
+    ///
+    /// # Errors
+    ///
+    /// 有字段不符合时返回 [`CoreError::InvalidFields`]，列出全部不符合的字段。
+    pub fn validate(&self) -> CoreResult<()> {
+        let checks = [
+            ("name", Self::check_name(&self.name)),
+            ("phone", Self::check_phone(self.phone.as_deref().unwrap_or_default())),
+            ("email", Self::check_email(self.email.as_deref().unwrap_or_default())),
+        ];
+        collect_field_errors(checks)
+    }
+
+    /// 按字段策略校验：先检查必填和隐藏，再检查格式
+    ///
+    /// # Errors
+    ///
+    /// 有字段不符合时返回 [`CoreError::InvalidFields`]，列出全部不符合的字段。
+    pub fn validate_with_policy(&self, policy: &FieldPolicy) -> CoreResult<()> {
+        let phone = self.phone.as_deref();
+        let email = self.email.as_deref();
+        let checks = [
+            ("name", Self::check_name(&self.name)),
+            (
+                "contact_person",
+                policy.check("contact_person", self.contact_person.as_deref()),
+            ),
+            (
+                "phone",
+                policy
+                    .check("phone", phone)
+                    .and_then(|_| Self::check_phone(phone.unwrap_or_default())),
+            ),
+            (
+                "email",
+                policy
+                    .check("email", email)
+                    .and_then(|_| Self::check_email(email.unwrap_or_default())),
+            ),
+            ("address", policy.check("address", self.address.as_deref())),
+        ];
+        collect_field_errors(checks)
+    }
+}
+
+impl Command for CreateCustomerCommand {
+    const NAME: &'static str = "create_customer";
+    type Output = Customer;
+}
+
+impl Idempotent for CreateCustomerCommand {
+    fn idempotency_key(&self) -> Option<Uuid> {
+        self.idempotency_key
+    }
+}
+
+/// 新线索的首个跟进任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeadTask {
+    /// 任务标题
+    pub title: String,
+    /// 任务描述
+    pub description: Option<String>,
+    /// 优先级
+    pub priority: TaskPriority,
+    /// 截止日期（业务时区），为空时按配置的天数计算
+    pub due_on: Option<LocalDate>,
+}
+
+/// 新建线索命令：客户、首要联系人和可选的首个跟进任务一次提交
+///
+/// 校验错误的字段名带有所属步骤的前缀（联系人 `contact_`、任务 `task_`），
+/// 向导据此把错误显示在对应的步骤。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateLeadCommand {
+    /// 客户名称
+    pub name: String,
+    /// 地址
+    pub address: Option<String>,
+    /// 联系人姓名
+    pub contact_name: String,
+    /// 联系人电话
+    pub contact_phone: Option<String>,
+    /// 联系人邮箱
+    pub contact_email: Option<String>,
+    /// 首个跟进任务，为空时只新建客户和联系人
+    pub first_task: Option<LeadTask>,
+    /// 幂等键（同一次提交的重试使用相同的键）
+    #[serde(default)]
+    pub idempotency_key: Option<Uuid>,
+}
+
+impl CreateLeadCommand {
+    /// 联系人姓名：必填，不超过 [`NAME_MAX_CHARS`] 个字
+    ///
+    /// # Errors
+    ///
+    /// 不符合时返回 [`CoreError::Validation`]。
+    pub fn check_contact_name(name: &str) -> CoreResult<()> {
+        let name = validation::non_blank("联系人", name)?;
+        validation::within_chars("联系人", name, NAME_MAX_CHARS)
+    }
+
+    /// 任务标题：必填，不超过 [`NAME_MAX_CHARS`] 个字
+    ///
+    /// # Errors
+    ///
+    /// 不符合时返回 [`CoreError::Validation`]。
+    pub fn check_task_title(title: &str) -> CoreResult<()> {
+        let title = validation::non_blank("任务标题", title)?;
+        validation::within_chars("任务标题", title, NAME_MAX_CHARS)
+    }
+
+    /// 保存前校验全部字段
+    ///
+    /// # Errors
+    ///
+    /// 有字段不符合时返回 [`CoreError::InvalidFields`]，列出全部不符合的字段。
+    pub fn validate(&self) -> CoreResult<()> {
+        let phone = self.contact_phone.as_deref().unwrap_or_default();
+        let email = self.contact_email.as_deref().unwrap_or_default();
+        let task_title = self
+            .first_task
+            .as_ref()
+            .map_or(Ok(()), |task| Self::check_task_title(&task.title));
+        let checks = [
+            ("name", CreateCustomerCommand::check_name(&self.name)),
+            ("contact_name", Self::check_contact_name(&self.contact_name)),
+            ("contact_phone", CreateCustomerCommand::check_phone(phone)),
+            ("contact_email", CreateCustomerCommand::check_email(email)),
+            ("task_title", task_title),
+        ];
+        collect_field_errors(checks)
+    }
+}
+
+impl Command for CreateLeadCommand {
+    const NAME: &'static str = "create_lead";
+    type Output = CreatedLead;
+}
+
+impl Idempotent for CreateLeadCommand {
+    fn idempotency_key(&self) -> Option<Uuid> {
+        self.idempotency_key
+    }
+}
+
+/// 彻底删除客户命令
+///
+/// 客户的全部关联记录一并删除。有财务记录（已接受的报价、订单）时必须输入客户名称确认。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteCustomerCommand {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 用户输入的客户名称
+    #[serde(default)]
+    pub confirm_name: Option<String>,
+}
+
+impl Command for DeleteCustomerCommand {
+    const NAME: &'static str = "delete_customer";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = bool;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.customer_id)
+    }
+}
+
+/// 把客户移入回收站命令
+///
+/// 客户未完成的任务和草稿报价随客户一起删除，在回收站中作为一个批次显示。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoftDeleteCustomerCommand {
+    /// 客户ID
+    pub customer_id: Uuid,
+}
+
+impl Command for SoftDeleteCustomerCommand {
+    const NAME: &'static str = "soft_delete_customer";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = DeletionBatch;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.customer_id)
+    }
+}
+
+/// 从回收站恢复记录命令
+///
+/// 记录属于删除批次时恢复整个批次。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreEntityCommand {
+    /// 实体类型
+    pub entity: EntityKind,
+    /// 实体ID
+    pub id: Uuid,
+}
+
+impl Command for RestoreEntityCommand {
+    const NAME: &'static str = "restore_entity";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = RestoreReport;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.id)
+    }
+}
+
+/// 修改客户等级或标签命令
+///
+/// 一次修改一位客户，写入客户审计记录。携带客户端持有的版本时，客户已被修改或已删除
+/// 返回错误（撤销时据此判断能否还原）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeCustomerCategoryCommand {
+    /// 等级和标签的变更
+    pub change: CategoryChange,
+    /// 客户端持有的版本（客户的更新时间），不一致时返回冲突
+    pub expected_updated_at: Option<DateTime<Utc>>,
+}
+
+impl Command for ChangeCustomerCategoryCommand {
+    const NAME: &'static str = "change_customer_category";
+    type Output = Customer;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.change.customer_id)
+    }
+}
+
+/// 更新任务状态命令
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateTaskStatusCommand {
+    /// 任务ID
+    pub task_id: Uuid,
+    /// 新状态
+    pub status: TaskStatus,
+    /// 客户端持有的版本（任务的更新时间），不一致时返回冲突
+    pub expected_updated_at: Option<DateTime<Utc>>,
+}
+
+impl Command for UpdateTaskStatusCommand {
+    const NAME: &'static str = "update_task_status";
+    type Output = Task;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.task_id)
+    }
+}
+
+/// 分配任务命令
+///
+/// 把任务分配给用户并提醒负责人，`user_id` 为空时取消分配。不能分配给已停用的用户。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignTaskCommand {
+    /// 任务ID
+    pub task_id: Uuid,
+    /// 负责人（用户ID）
+    pub user_id: Option<Uuid>,
+}
+
+impl Command for AssignTaskCommand {
+    const NAME: &'static str = "assign_task";
+    type Output = Task;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.task_id)
+    }
+}
+
+/// 关注或取消关注任务命令（当前用户）
+///
+/// 关注后任务状态变更或有新备注时收到提醒。返回关注状态是否发生变化。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchTaskCommand {
+    /// 任务ID
+    pub task_id: Uuid,
+    /// `true` 为关注，`false` 为取消关注
+    pub watch: bool,
+}
+
+impl Command for WatchTaskCommand {
+    const NAME: &'static str = "watch_task";
+    const REQUIRED_ROLE: UserRole = UserRole::Viewer;
+    type Output = bool;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.task_id)
+    }
+}
+
+/// 添加任务备注命令
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddTaskNoteCommand {
+    /// 任务ID
+    pub task_id: Uuid,
+    /// 备注内容
+    pub body: String,
+}
+
+impl Command for AddTaskNoteCommand {
+    const NAME: &'static str = "add_task_note";
+    type Output = TaskNote;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.task_id)
+    }
+}
+
+/// 删除任务备注命令，返回删除的备注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteTaskNoteCommand {
+    /// 备注ID
+    pub note_id: Uuid,
+}
+
+impl Command for DeleteTaskNoteCommand {
+    const NAME: &'static str = "delete_task_note";
+    type Output = TaskNote;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.note_id)
+    }
+}
+
+/// 恢复已删除的任务备注命令（撤销删除）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreTaskNoteCommand {
+    /// 删除时返回的备注
+    pub note: TaskNote,
+}
+
+impl Command for RestoreTaskNoteCommand {
+    const NAME: &'static str = "restore_task_note";
+    type Output = TaskNote;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.note.id)
+    }
+}
+
+/// 校验报价单命令
+///
+/// 解析打印在报价单上的校验码，验证签名并返回系统中存储的字段供比对。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyQuoteCommand {
+    /// 扫描或粘贴得到的校验码
+    pub payload: String,
+}
+
+impl Command for VerifyQuoteCommand {
+    const NAME: &'static str = "verify_quote";
+    const REQUIRED_ROLE: UserRole = UserRole::Viewer;
+    type Output = QuoteVerification;
+}
+
+/// 从模板新建报价命令
+///
+/// 按产品的当前价格生成草稿报价；已停售、尚未定价或不存在的产品不加入报价，以逐行提示返回。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateQuoteFromTemplateCommand {
+    /// 模板ID
+    pub template_id: Uuid,
+    /// 客户ID
+    pub customer_id: Uuid,
+}
+
+impl Command for CreateQuoteFromTemplateCommand {
+    const NAME: &'static str = "create_quote_from_template";
+    type Output = TemplateQuote;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.template_id)
+    }
+}
+
+/// 模板明细行的提示
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateLineWarning {
+    /// 模板中的行号（从1开始）
+    pub line: usize,
+    /// 产品ID
+    pub product_id: Uuid,
+    /// 提示文本
+    pub message: String,
+}
+
+/// 从模板生成的报价
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateQuote {
+    /// 草稿报价
+    pub quote: Quote,
+    /// 未能加入报价的模板明细
+    pub warnings: Vec<TemplateLineWarning>,
+}
+
+impl TemplateQuote {
+    /// 按模板和产品当前价格生成草稿报价
+    ///
+    /// `prices` 与模板明细一一对应，产品不存在时为空；尚未定价的产品不加入报价。单价按折扣率折算并保留两位小数；
+    /// 报价编号由报价服务在保存时分配。
+    pub fn instantiate(
+        template: &QuoteTemplate,
+        customer_id: Uuid,
+        prices: &[Option<PricedProduct>],
+        now: DateTime<Utc>,
+        created_by: Option<String>,
+    ) -> Self {
+        let mut items = Vec::new();
+        let mut warnings = Vec::new();
+        for (index, (item, price)) in template.items.iter().zip(prices).enumerate() {
+            let warning = |message: String| TemplateLineWarning {
+                line: index + 1,
+                product_id: item.product_id,
+                message,
+            };
+            match price {
+                None => warnings.push(warning("产品不存在，未加入报价".to_string())),
+                Some(price) if price.retired => {
+                    warnings.push(warning(format!("产品「{}」已停售，未加入报价", price.name)))
+                }
+                Some(PricedProduct {
+                    name,
+                    unit_price: None,
+                    ..
+                }) => warnings.push(warning(format!("产品「{}」尚未定价，未加入报价", name))),
+                Some(PricedProduct {
+                    name,
+                    specification,
+                    unit_price: Some(unit_price),
+                    ..
+                }) => items.push(QuoteItem {
+                    id: Uuid::new_v4(),
+                    quote_id: Uuid::nil(),
+                    product_id: Some(item.product_id),
+                    product_name: name.clone(),
+                    specification: specification.clone(),
+                    unit: None,
+                    quantity: item.quantity,
+                    unit_price: (unit_price * (1.0 - item.discount) * 100.0).round() / 100.0,
+                    line_total: Money::ZERO,
+                }),
+            }
+        }
+
+        let mut quote = Quote {
+            id: Uuid::new_v4(),
+            quote_number: String::new(),
+            customer_id,
+            status: QuoteStatus::Draft,
+            total_amount: Money::ZERO,
+            currency: Currency::BASE,
+            items,
+            valid_until: now + chrono::Duration::days(i64::from(template.validity_days)),
+            remarks: template.terms.clone(),
+            created_at: now,
+            updated_at: now,
+            created_by: created_by.clone(),
+            updated_by: created_by,
+        };
+        // 明细的小计和所属报价在这里统一填写
+        quote.recalculate_totals();
+        Self { quote, warnings }
+    }
+}
+
+/// 新建产品命令
+///
+/// 创建产品并登记自当前时间起生效的售价，返回带售价的产品供报价明细直接使用。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateProductCommand {
+    /// 产品名称
+    pub name: String,
+    /// 规格
+    #[serde(default)]
+    pub specification: Option<String>,
+    /// 售价
+    pub price: Money,
+    /// 幂等键（同一次提交的重试使用相同的键）
+    #[serde(default)]
+    pub idempotency_key: Option<Uuid>,
+}
+
+impl CreateProductCommand {
+    /// 产品名称：必填，不超过 [`NAME_MAX_CHARS`] 个字
+    ///
+    /// # Errors
+    ///
+    /// 不符合时返回 [`CoreError::Validation`]。
+    pub fn check_name(name: &str) -> CoreResult<()> {
+        let name = validation::non_blank("产品名称", name)?;
+        validation::within_chars("产品名称", name, NAME_MAX_CHARS)
+    }
+
+    /// 售价：必须大于0
+    ///
+    /// # Errors
+    ///
+    /// 不大于0时返回 [`CoreError::Validation`]。
+    pub fn check_price(price: Money) -> CoreResult<()> {
+        validation::positive_amount("售价", price)
+    }
+
+    /// 保存前校验全部字段
+    ///
+    /// # Errors
+    ///
+    /// 有字段不符合时返回 [`CoreError::InvalidFields`]，列出全部不符合的字段。
+    pub fn validate(&self) -> CoreResult<()> {
+        let checks = [
+            ("name", Self::check_name(&self.name)),
+            ("price", Self::check_price(self.price)),
+        ];
+        collect_field_errors(checks)
+    }
+}
+
+/// 汇总各字段的校验结果
+fn collect_field_errors<'a, I>(checks: I) -> CoreResult<()>
+where
+    I: IntoIterator<Item = (&'a str, CoreResult<()>)>,
+{
+    let errors: Vec<FieldError> = checks
+        .into_iter()
+        .filter_map(|(field, result)| result.err().map(|e| validation::field_error(field, &e)))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(CoreError::invalid_fields(errors))
+    }
+}
+
+impl Command for CreateProductCommand {
+    const NAME: &'static str = "create_product";
+    type Output = PricedProduct;
+}
+
+impl Idempotent for CreateProductCommand {
+    fn idempotency_key(&self) -> Option<Uuid> {
+        self.idempotency_key
+    }
+}
+
+/// 从表格导入供应商命令
+///
+/// 不带映射提交时只识别表头，返回 [`SpreadsheetImport::Detected`] 供界面确认或调整；
+/// 带上确认后的映射再次提交时按名称新建或更新供应商。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSuppliersCommand {
+    /// 表格文件路径（`.xlsx`、`.xls` 或 `.ods`）
+    pub path: PathBuf,
+    /// 工作表（为空时为第一个工作表；带映射时以映射中的工作表为准）
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// 确认后的表头映射
+    #[serde(default)]
+    pub mapping: Option<DetectedMapping>,
+    /// 取消令牌（不序列化）
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+impl Command for ImportSuppliersCommand {
+    const NAME: &'static str = "import_suppliers";
+    type Output = SpreadsheetImport;
+}
+
+impl Cancellable for ImportSuppliersCommand {
+    fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+}
+
+/// 从表格导入产品命令
+///
+/// 两步提交同 [`ImportSuppliersCommand`]。按品名和规格新建产品或更新现价，会改变报价取价，
+/// 仅管理员可执行。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportProductsCommand {
+    /// 表格文件路径（`.xlsx`、`.xls` 或 `.ods`）
+    pub path: PathBuf,
+    /// 工作表（为空时为第一个工作表；带映射时以映射中的工作表为准）
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// 确认后的表头映射
+    #[serde(default)]
+    pub mapping: Option<DetectedMapping>,
+    /// 取消令牌（不序列化）
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+impl Command for ImportProductsCommand {
+    const NAME: &'static str = "import_products";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = SpreadsheetImport;
+}
+
+impl Cancellable for ImportProductsCommand {
+    fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+}
+
+/// 按类别批量调价命令
+///
+/// 对类别内产品的当前价格按百分比调整，自 `effective_from` 起生效。执行前可用
+/// [`preview`](Self::preview) 生成的查询预览变更，执行写入的价格与预览一致。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdjustCategoryPricesCommand {
+    /// 产品类别
+    pub category: String,
+    /// 调整百分比（`5` 表示上调5%）
+    pub percent: i32,
+    /// 生效时间
+    pub effective_from: DateTime<Utc>,
+}
+
+impl AdjustCategoryPricesCommand {
+    /// 预览本次调价的查询
+    pub fn preview(&self) -> PriceAdjustmentPreviewQuery {
+        PriceAdjustmentPreviewQuery {
+            category: self.category.clone(),
+            percent: self.percent,
+            effective_from: self.effective_from,
+        }
+    }
+}
+
+impl Command for AdjustCategoryPricesCommand {
+    const NAME: &'static str = "adjust_category_prices";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = Vec<PriceChange>;
+}
+
+/// 重新计价报价命令
+///
+/// 按报价创建时的产品价格重新计算明细单价，用于修正录入错误或补录价格后的历史报价。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepriceQuoteCommand {
+    /// 报价ID
+    pub quote_id: Uuid,
+}
+
+impl Command for RepriceQuoteCommand {
+    const NAME: &'static str = "reprice_quote";
+    type Output = Quote;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.quote_id)
+    }
+}
+
+/// 发送报价命令
+///
+/// 报价毛利率低于禁止阈值时须管理员批准；销售设置批准标志无效。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendQuoteCommand {
+    /// 报价ID
+    pub quote_id: Uuid,
+    /// 管理员批准低毛利发送
+    #[serde(default)]
+    pub approve_low_margin: bool,
+}
+
+impl Command for SendQuoteCommand {
+    const NAME: &'static str = "send_quote";
+    type Output = Quote;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.quote_id)
+    }
+}
+
+/// 接受报价命令
+///
+/// 接受前按客户信用额度检查；`override_credit` 只对管理员生效，越过额度时写入审计记录。
+/// 客户被冻结信用时一律不能接受。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptQuoteCommand {
+    /// 报价ID
+    pub quote_id: Uuid,
+    /// 管理员批准超出信用额度
+    #[serde(default)]
+    pub override_credit: bool,
+    /// 幂等键（同一次提交的重试使用相同的键）
+    #[serde(default)]
+    pub idempotency_key: Option<Uuid>,
+}
+
+impl Command for AcceptQuoteCommand {
+    const NAME: &'static str = "accept_quote";
+    type Output = Quote;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.quote_id)
+    }
+}
+
+impl Idempotent for AcceptQuoteCommand {
+    fn idempotency_key(&self) -> Option<Uuid> {
+        self.idempotency_key
+    }
+}
+
+/// 确认订单命令
+///
+/// 与 [`AcceptQuoteCommand`] 相同，确认前按客户信用额度检查。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmOrderCommand {
+    /// 待确认的订单
+    pub order: Order,
+    /// 管理员批准超出信用额度
+    #[serde(default)]
+    pub override_credit: bool,
+    /// 幂等键（重复点击“转为订单”时使用相同的键，只生成一张订单）
+    #[serde(default)]
+    pub idempotency_key: Option<Uuid>,
+}
+
+impl Command for ConfirmOrderCommand {
+    const NAME: &'static str = "confirm_order";
+    type Output = Order;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.order.id)
+    }
+}
+
+impl Idempotent for ConfirmOrderCommand {
+    fn idempotency_key(&self) -> Option<Uuid> {
+        self.idempotency_key
+    }
+}
+
+/// 设置客户信用额度命令
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetCreditTermsCommand {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 信用额度（本位币），为空表示不限额
+    pub credit_limit: Option<Money>,
+    /// 信用冻结
+    #[serde(default)]
+    pub credit_hold: bool,
+}
+
+impl Command for SetCreditTermsCommand {
+    const NAME: &'static str = "set_credit_terms";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = ();
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.customer_id)
+    }
+}
+
+/// 生成月度报表命令
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateMonthlyReportCommand {
+    /// 统计周期
+    pub period: ReportPeriod,
+    /// 输出格式
+    pub format: ReportFormat,
+    /// 输出文件路径
+    pub out_path: PathBuf,
+    /// 取消令牌（不序列化）
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+impl Command for GenerateMonthlyReportCommand {
+    const NAME: &'static str = "generate_monthly_report";
+    const REQUIRED_ROLE: UserRole = UserRole::Viewer;
+    type Output = PathBuf;
+}
+
+impl Cancellable for GenerateMonthlyReportCommand {
+    fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+}
+
+/// 月结命令
+///
+/// 计算指定月份的统计并锁定为快照，之后该月的统计和报表都从快照读取。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CloseMonthCommand {
+    /// 结账月份（为空时为上一个月，不能是当前或以后的月份）
+    #[serde(default)]
+    pub period: Option<ReportPeriod>,
+}
+
+impl Command for CloseMonthCommand {
+    const NAME: &'static str = "close_month";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = MonthlyClosing;
+}
+
+/// 重新打开已结账月份命令
+///
+/// 重新打开后该月恢复实时计算，下次月结覆盖原快照。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReopenMonthCommand {
+    /// 月份
+    pub period: ReportPeriod,
+    /// 原因（写入审计记录，不能为空）
+    pub reason: String,
+}
+
+impl Command for ReopenMonthCommand {
+    const NAME: &'static str = "reopen_month";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = MonthlyClosing;
+}
+
+/// 采用知识库文章命令
+///
+/// 把文章的处理方法填入工单的处理方式，并为文章记录一次采用。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyKnowledgeArticleCommand {
+    /// 工单ID
+    pub ticket_id: Uuid,
+    /// 文章ID
+    pub article_id: Uuid,
+}
+
+impl Command for ApplyKnowledgeArticleCommand {
+    const NAME: &'static str = "apply_knowledge_article";
+    type Output = ServiceTicket;
+
+    fn entity_id(&self) -> Option<Uuid> {
+        Some(self.ticket_id)
+    }
+}
+
+/// 保存知识库文章命令
+///
+/// 通常由关闭工单时的“保存为知识库文章”提交，创建人为当前用户。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveKnowledgeArticleCommand {
+    /// 文章（可由 [`KnowledgeArticle::from_ticket`] 预填）
+    pub article: KnowledgeArticle,
+}
+
+impl Command for SaveKnowledgeArticleCommand {
+    const NAME: &'static str = "save_knowledge_article";
+    type Output = KnowledgeArticle;
+}
+
+/// 导出数据归档命令
+///
+/// 归档包含全部客户数据和用户账号，仅管理员可执行。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportArchiveCommand {
+    /// 输出文件路径（`.minicrm.zip`）
+    pub out_path: PathBuf,
+    /// 取消令牌（不序列化）
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+impl Command for ExportArchiveCommand {
+    const NAME: &'static str = "export_archive";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = ArchiveManifest;
+}
+
+impl Cancellable for ExportArchiveCommand {
+    fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+}
+
+/// 导出客户列表命令
+///
+/// 按选择的范围（当前筛选结果、选中的行或全部客户）导出列表的可见列，返回写出的行数。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportCustomersCommand {
+    /// 导出范围、列和表头
+    pub request: CustomerExportRequest,
+    /// 输出文件路径（`.csv`）
+    pub out_path: PathBuf,
+    /// 已写出的行数（不序列化，进度对话框轮询）
+    #[serde(skip)]
+    pub progress: ExportProgress,
+    /// 取消令牌（不序列化）
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+impl Command for ExportCustomersCommand {
+    const NAME: &'static str = "export_customers";
+    const REQUIRED_ROLE: UserRole = UserRole::Viewer;
+    type Output = u64;
+}
+
+impl Cancellable for ExportCustomersCommand {
+    fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+}
+
+/// 立即归档命令
+///
+/// 界面先用 [`preview`](Self::preview) 显示将移出的记录并请用户确认，确认后以同一
+/// `as_of` 执行，移出的记录与预览一致。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveRecordsCommand {
+    /// 归档策略
+    pub policy: ArchivePolicy,
+    /// 预览时使用的时间
+    pub as_of: DateTime<Utc>,
+}
+
+impl ArchiveRecordsCommand {
+    /// 预览本次归档的查询
+    pub fn preview(&self) -> ArchivePreviewQuery {
+        ArchivePreviewQuery {
+            policy: self.policy.clone(),
+            as_of: self.as_of,
+        }
+    }
+}
+
+impl Command for ArchiveRecordsCommand {
+    const NAME: &'static str = "archive_records";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = ArchiveRun;
+}
+
+/// 导入数据归档命令
+///
+/// 会替换当前的数据库、附件和配置，导入完成后需重启应用。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportArchiveCommand {
+    /// 归档文件路径
+    pub path: PathBuf,
+    /// 取消令牌（不序列化）
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+impl Command for ImportArchiveCommand {
+    const NAME: &'static str = "import_archive";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = ArchiveManifest;
+}
+
+impl Cancellable for ImportArchiveCommand {
+    fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+}
+
+/// 导出变更包命令
+///
+/// 导出 `since` 之后主电脑上的修改和删除，拷贝到另一台电脑后用 [`ApplyChangesCommand`] 应用。
+/// `since` 通常取副本上次应用的变更包的导出时间。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportChangesCommand {
+    /// 变更起始时间（不含）
+    pub since: DateTime<Utc>,
+    /// 输出文件路径
+    pub out_path: PathBuf,
+}
+
+impl Command for ExportChangesCommand {
+    const NAME: &'static str = "export_changes";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = ChangesetSummary;
+}
+
+/// 应用变更包命令
+///
+/// 在只读副本上执行；变更包不比已应用的新时不做修改。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyChangesCommand {
+    /// 变更包文件路径
+    pub path: PathBuf,
+}
+
+impl Command for ApplyChangesCommand {
+    const NAME: &'static str = "apply_changes";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = ChangesetSummary;
+}
+
+/// 导出设置命令
+///
+/// 把配置（不含本机路径和密钥）、保存的搜索、仪表盘布局、列设置和快捷键导出为一个JSON文件。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSettingsCommand {
+    /// 输出文件路径
+    pub out_path: PathBuf,
+}
+
+impl Command for ExportSettingsCommand {
+    const NAME: &'static str = "export_settings";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = SettingsExportSummary;
+}
+
+/// 导入设置命令
+///
+/// 只应用用户选择的部分，逐部分返回结果。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSettingsCommand {
+    /// 设置文件路径
+    pub path: PathBuf,
+    /// 要应用的部分
+    pub sections: Vec<SettingsSection>,
+}
+
+impl Command for ImportSettingsCommand {
+    const NAME: &'static str = "import_settings";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = SettingsImportReport;
+}
+
+/// 导出诊断包命令
+///
+/// 诊断包包含日志、健康检查等运行信息，仅管理员可执行。`include_db_copy` 为真时附带
+/// 匿名化的数据库副本。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportDiagnosticsBundleCommand {
+    /// 输出文件路径（`.zip`）
+    pub out_path: PathBuf,
+    /// 是否附带匿名化的数据库副本
+    pub include_db_copy: bool,
+    /// 已写完的部分数（不序列化，进度对话框轮询）
+    #[serde(skip)]
+    pub progress: ExportProgress,
+    /// 取消令牌（不序列化）
+    #[serde(skip)]
+    pub cancel: CancellationToken,
+}
+
+impl Command for ExportDiagnosticsBundleCommand {
+    const NAME: &'static str = "export_diagnostics_bundle";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = DiagnosticsBundleSummary;
+}
+
+impl Cancellable for ExportDiagnosticsBundleCommand {
+    fn set_cancellation(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+}
+
+/// 修改字段要求命令
+///
+/// 防护规则拒绝的修改（实体本身依赖的字段、不可配置的字段）返回校验错误。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetFieldRequirementCommand {
+    /// 实体类型
+    pub entity: EntityKind,
+    /// 字段名
+    pub field: String,
+    /// 新的要求
+    pub requirement: FieldRequirement,
+}
+
+impl Command for SetFieldRequirementCommand {
+    const NAME: &'static str = "set_field_requirement";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = FieldPolicy;
+}
+
+/// 数据一致性检查命令
+///
+/// 检查数据库约束无法表达的业务规则（报价总额、收款、汇总表等），只读不修改数据。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckConsistencyCommand {
+    /// 要执行的检查名称（为空时执行全部检查）
+    #[serde(default)]
+    pub checks: Option<Vec<String>>,
+    /// 每项检查最多列出的问题数
+    #[serde(default = "default_violation_limit")]
+    pub limit_per_check: usize,
+}
+
+fn default_violation_limit() -> usize {
+    DEFAULT_VIOLATION_LIMIT
+}
+
+impl Default for CheckConsistencyCommand {
+    fn default() -> Self {
+        Self {
+            checks: None,
+            limit_per_check: DEFAULT_VIOLATION_LIMIT,
+        }
+    }
+}
+
+impl Command for CheckConsistencyCommand {
+    const NAME: &'static str = "check_consistency";
+    const REQUIRED_ROLE: UserRole = UserRole::Admin;
+    type Output = ConsistencyReport;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use minicrm_core::{DeliveryStatus, QuoteTemplateItem};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Notify;
+
+    fn price(name: &str, unit_price: f64, retired: bool) -> PricedProduct {
+        PricedProduct {
+            product_id: Uuid::new_v4(),
+            name: name.to_string(),
+            specification: Some("1220×2440×18mm".to_string()),
+            unit_price: Some(unit_price),
+            unit_cost: None,
+            retired,
+        }
+    }
+
+    fn template(items: Vec<QuoteTemplateItem>) -> QuoteTemplate {
+        let saved = Utc.with_ymd_and_hms(2024, 1, 5, 0, 0, 0).unwrap();
+        QuoteTemplate {
+            id: Uuid::new_v4(),
+            name: "全屋套餐".to_string(),
+            validity_days: 15,
+            terms: Some("含税含运费".to_string()),
+            items,
+            created_at: saved,
+            updated_at: saved,
+        }
+    }
+
+    fn line(product: &PricedProduct, quantity: f64, discount: f64) -> QuoteTemplateItem {
+        QuoteTemplateItem {
+            product_id: product.product_id,
+            quantity,
+            discount,
+        }
+    }
+
+    #[test]
+    fn test_instantiate_uses_current_prices() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let board = price("生态板", 128.0, false);
+        let hinge = price("铰链", 9.99, false);
+        let template = template(vec![line(&board, 10.0, 0.0), line(&hinge, 20.0, 0.05)]);
+
+        let result = TemplateQuote::instantiate(
+            &template,
+            Uuid::nil(),
+            &[Some(board), Some(hinge)],
+            now,
+            Some("sales".to_string()),
+        );
+        assert!(result.warnings.is_empty());
+        let quote = result.quote;
+        assert_eq!(quote.status, QuoteStatus::Draft);
+        assert_eq!(quote.customer_id, Uuid::nil());
+        let prices: Vec<f64> = quote.items.iter().map(|i| i.unit_price).collect();
+        assert_eq!(prices, vec![128.0, 9.49]);
+        assert_eq!(quote.total_amount, Money::from_yuan(1280.0 + 189.8));
+        assert_eq!(quote.valid_until, now + chrono::Duration::days(15));
+        assert_eq!(quote.remarks.as_deref(), Some("含税含运费"));
+        assert_eq!(quote.created_by.as_deref(), Some("sales"));
+    }
+
+    #[test]
+    fn test_instantiate_warns_on_retired_products() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let board = price("生态板", 128.0, false);
+        let retired = price("旧款拉手", 15.0, true);
+        let missing = price("已删除", 1.0, false);
+        let template = template(vec![
+            line(&retired, 4.0, 0.0),
+            line(&board, 10.0, 0.0),
+            line(&missing, 1.0, 0.0),
+        ]);
+
+        let result = TemplateQuote::instantiate(
+            &template,
+            Uuid::nil(),
+            &[Some(retired.clone()), Some(board), None],
+            now,
+            None,
+        );
+        assert_eq!(result.quote.items.len(), 1);
+        assert_eq!(result.quote.items[0].product_name, "生态板");
+        assert_eq!(
+            result.warnings,
+            vec![
+                TemplateLineWarning {
+                    line: 1,
+                    product_id: retired.product_id,
+                    message: "产品「旧款拉手」已停售，未加入报价".to_string(),
+                },
+                TemplateLineWarning {
+                    line: 3,
+                    product_id: missing.product_id,
+                    message: "产品不存在，未加入报价".to_string(),
+                },
+            ]
+        );
+    }
+
+    /// 逐行导出的测试命令
+    #[derive(Debug, Default)]
+    struct ExportRowsCommand {
+        rows: usize,
+        cancel: CancellationToken,
+    }
+
+    impl Command for ExportRowsCommand {
+        const NAME: &'static str = "export_rows";
+        const REQUIRED_ROLE: UserRole = UserRole::Viewer;
+        type Output = usize;
+    }
+
+    impl Cancellable for ExportRowsCommand {
+        fn set_cancellation(&mut self, cancel: CancellationToken) {
+            self.cancel = cancel;
+        }
+    }
+
+    /// 统计写出行数的导出处理器，写满10行时通知测试
+    #[derive(Default)]
+    struct CountingExport {
+        written: AtomicUsize,
+        progress: Notify,
+    }
+
+    #[async_trait]
+    impl CommandHandler<ExportRowsCommand> for CountingExport {
+        async fn handle(&self, command: ExportRowsCommand) -> CoreResult<usize> {
+            for _ in 0..command.rows {
+                command.cancel.check()?;
+                if self.written.fetch_add(1, Ordering::SeqCst) + 1 == 10 {
+                    self.progress.notify_one();
+                }
+                tokio::task::yield_now().await;
+            }
+            Ok(command.rows)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_cancellable_stops_midway() {
+        let handler = Arc::new(CountingExport::default());
+        let mut bus = CommandBus::new();
+        bus.register::<ExportRowsCommand>(handler.clone());
+
+        let (export, cancel) = bus.dispatch_cancellable(ExportRowsCommand {
+            rows: 80_000,
+            ..ExportRowsCommand::default()
+        });
+        let (result, ()) = tokio::join!(export, async {
+            handler.progress.notified().await;
+            cancel.cancel();
+        });
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        let written = handler.written.load(Ordering::SeqCst);
+        assert!((10..80_000).contains(&written), "{}", written);
+
+        // 每次分发使用新的令牌
+        let (export, _cancel) = bus.dispatch_cancellable(ExportRowsCommand {
+            rows: 5,
+            ..ExportRowsCommand::default()
+        });
+        assert_eq!(export.await.unwrap(), 5);
+    }
+
+    /// 内存中的幂等记录
+    #[derive(Default)]
+    struct MemoryIdempotency {
+        records: Mutex<HashMap<Uuid, IdempotencyRecord>>,
+    }
+
+    #[async_trait]
+    impl IdempotencyService for MemoryIdempotency {
+        async fn find_record(&self, key: Uuid) -> CoreResult<Option<IdempotencyRecord>> {
+            Ok(self.records.lock().unwrap().get(&key).cloned())
+        }
+
+        async fn save_record(&self, record: IdempotencyRecord) -> CoreResult<()> {
+            self.records.lock().unwrap().insert(record.key, record);
+            Ok(())
+        }
+    }
+
+    /// 记录执行次数的报价转订单处理器，每次执行生成新的订单ID
+    #[derive(Default)]
+    struct CountingConversion {
+        accepted: AtomicUsize,
+        confirmed: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CommandHandler<AcceptQuoteCommand> for CountingConversion {
+        async fn handle(&self, command: AcceptQuoteCommand) -> CoreResult<Quote> {
+            self.accepted.fetch_add(1, Ordering::SeqCst);
+            let now = Utc::now();
+            let mut quote =
+                TemplateQuote::instantiate(&template(vec![]), Uuid::nil(), &[], now, None).quote;
+            quote.id = command.quote_id;
+            quote.status = QuoteStatus::Accepted;
+            Ok(quote)
+        }
+    }
+
+    #[async_trait]
+    impl CommandHandler<ConfirmOrderCommand> for CountingConversion {
+        async fn handle(&self, command: ConfirmOrderCommand) -> CoreResult<Order> {
+            self.confirmed.fetch_add(1, Ordering::SeqCst);
+            Ok(Order {
+                id: Uuid::new_v4(),
+                ..command.order
+            })
+        }
+    }
+
+    fn order(quote_id: Uuid) -> Order {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        Order {
+            id: Uuid::nil(),
+            order_number: "SO-0001".to_string(),
+            customer_id: Uuid::nil(),
+            quote_id: Some(quote_id),
+            total_amount: Money::from_yuan(3130.0),
+            currency: Currency::BASE,
+            delivery_status: DeliveryStatus::default(),
+            delivery_date: None,
+            delivery_address: None,
+            vehicle: None,
+            driver: None,
+            signed_by: None,
+            delivered_at: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_convert_quote_replay_returns_stored_result() -> CoreResult<()> {
+        let handler = Arc::new(CountingConversion::default());
+        let mut bus = CommandBus::new();
+        bus.register::<AcceptQuoteCommand>(handler.clone());
+        bus.register::<ConfirmOrderCommand>(handler.clone());
+        bus.set_idempotency_service(Arc::new(MemoryIdempotency::default()));
+
+        // 重复点击“接受”和“转为订单”，各只执行一次
+        let quote_id = Uuid::new_v4();
+        let accept = AcceptQuoteCommand {
+            quote_id,
+            override_credit: false,
+            idempotency_key: Some(Uuid::new_v4()),
+        };
+        let first = bus.dispatch_idempotent(accept.clone()).await?;
+        let second = bus.dispatch_idempotent(accept).await?;
+        assert_eq!((first.id, second.id), (quote_id, quote_id));
+        assert_eq!(second.status, QuoteStatus::Accepted);
+        assert_eq!(handler.accepted.load(Ordering::SeqCst), 1);
+
+        let confirm = ConfirmOrderCommand {
+            order: order(quote_id),
+            override_credit: false,
+            idempotency_key: Some(Uuid::new_v4()),
+        };
+        let first = bus.dispatch_idempotent(confirm.clone()).await?;
+        let second = bus.dispatch_idempotent(confirm.clone()).await?;
+        assert_eq!(first, second);
+        assert_eq!(handler.confirmed.load(Ordering::SeqCst), 1);
+
+        // 同一键提交不同的订单内容
+        let changed = ConfirmOrderCommand {
+            order: Order {
+                total_amount: Money::from_yuan(1.0),
+                ..confirm.order
+            },
+            ..confirm
+        };
+        assert!(matches!(
+            bus.dispatch_idempotent(changed).await,
+            Err(CoreError::Conflict(_))
+        ));
+
+        // 不带键时按普通命令执行
+        bus.dispatch_idempotent(ConfirmOrderCommand {
+            order: order(quote_id),
+            override_credit: false,
+            idempotency_key: None,
+        })
+        .await?;
+        assert_eq!(handler.confirmed.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+}
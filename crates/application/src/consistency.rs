@@ -0,0 +1,371 @@
+//! 数据一致性检查
+//!
+//! SQLite 的完整性检查只保证文件结构正确，报价总额等于明细之和、收款不超过订单总额这类
+//! 业务规则没有约束保证。各项规则实现为 [`ConsistencyCheck`] 注册到 [`ConsistencyChecker`]，
+//! 诊断界面和命令行通过 [`CheckConsistencyCommand`] 执行，每周的定时任务也会执行一次。
+//!
+//! 警告表示可自动校正的偏差（如汇总表等待对账），错误表示数据已损坏，只有错误才会通知。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{NaiveTime, Weekday};
+use minicrm_core::{
+    Clock, ConsistencyCheck, ConsistencyCheckResult, ConsistencyReport, ConsistencySeverity,
+    CoreError, CoreResult, DesktopNotifier, Job, JobSchedule, SystemClock,
+};
+use tracing::{info, warn};
+
+use crate::commands::{CheckConsistencyCommand, CommandHandler};
+
+/// 每项检查默认最多列出的问题数
+pub const DEFAULT_VIOLATION_LIMIT: usize = 100;
+
+/// 一致性检查器
+#[derive(Clone)]
+pub struct ConsistencyChecker {
+    checks: Vec<Arc<dyn ConsistencyCheck + Send + Sync>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for ConsistencyChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsistencyChecker")
+            .field("checks", &self.names())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ConsistencyChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsistencyChecker {
+    /// 创建不含任何检查的检查器
+    pub fn new() -> Self {
+        Self {
+            checks: Vec::new(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 注册检查（同名检查被替换）
+    pub fn register(&mut self, check: Arc<dyn ConsistencyCheck + Send + Sync>) {
+        match self.checks.iter_mut().find(|c| c.name() == check.name()) {
+            Some(existing) => *existing = check,
+            None => self.checks.push(check),
+        }
+    }
+
+    /// 注册检查并返回检查器
+    pub fn with(mut self, check: Arc<dyn ConsistencyCheck + Send + Sync>) -> Self {
+        self.register(check);
+        self
+    }
+
+    /// 使用指定时钟（测试中固定检查时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 已注册的检查名称
+    pub fn names(&self) -> Vec<&str> {
+        self.checks.iter().map(|c| c.name()).collect()
+    }
+
+    /// 执行检查
+    ///
+    /// `names` 为空时执行全部检查，否则按注册顺序执行其中列出的检查。
+    ///
+    /// # Errors
+    ///
+    /// 名称中有未注册的检查时返回 [`CoreError::Validation`]（不执行任何检查），
+    /// 某项检查执行失败时返回该错误。
+    pub async fn run(
+        &self,
+        names: Option<&[String]>,
+        limit: usize,
+    ) -> CoreResult<ConsistencyReport> {
+        if let Some(names) = names {
+            let unknown: Vec<&str> = names
+                .iter()
+                .map(String::as_str)
+                .filter(|name| self.checks.iter().all(|c| c.name() != *name))
+                .collect();
+            if !unknown.is_empty() {
+                return Err(CoreError::validation(format!(
+                    "没有名为 {} 的一致性检查（可用：{}）",
+                    unknown.join("、"),
+                    self.names().join("、")
+                )));
+            }
+        }
+
+        let mut results = Vec::new();
+        for check in &self.checks {
+            if names.is_some_and(|names| names.iter().all(|n| n != check.name())) {
+                continue;
+            }
+            // 多取一个用于判断是否超出上限
+            let mut violations = check.run(limit.saturating_add(1)).await?;
+            let truncated = violations.len() > limit;
+            violations.truncate(limit);
+            results.push(ConsistencyCheckResult {
+                name: check.name().to_string(),
+                description: check.description().to_string(),
+                severity: check.severity(),
+                violations,
+                truncated,
+            });
+        }
+        Ok(ConsistencyReport {
+            results,
+            checked_at: self.clock.now(),
+        })
+    }
+}
+
+#[async_trait]
+impl CommandHandler<CheckConsistencyCommand> for ConsistencyChecker {
+    async fn handle(&self, command: CheckConsistencyCommand) -> CoreResult<ConsistencyReport> {
+        self.run(command.checks.as_deref(), command.limit_per_check)
+            .await
+    }
+}
+
+/// 报告的汇总文本，如“6 项检查：2 项错误，1 项警告”
+pub fn consistency_summary(report: &ConsistencyReport) -> String {
+    let errors = report.failed(ConsistencySeverity::Error).count();
+    let warnings = report.failed(ConsistencySeverity::Warning).count();
+    if errors == 0 && warnings == 0 {
+        return format!("{} 项检查全部通过", report.results.len());
+    }
+    format!(
+        "{} 项检查：{} 项错误，{} 项警告",
+        report.results.len(),
+        errors,
+        warnings
+    )
+}
+
+/// 每周一致性检查任务
+///
+/// 执行全部检查并记录汇总；发现错误时显示桌面通知，只有警告时不通知。
+pub struct ConsistencyCheckJob {
+    checker: Arc<ConsistencyChecker>,
+    notifier: Option<Arc<dyn DesktopNotifier + Send + Sync>>,
+}
+
+impl std::fmt::Debug for ConsistencyCheckJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsistencyCheckJob")
+            .field("checker", &self.checker)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConsistencyCheckJob {
+    /// 创建检查任务
+    pub fn new(checker: Arc<ConsistencyChecker>) -> Self {
+        Self {
+            checker,
+            notifier: None,
+        }
+    }
+
+    /// 设置桌面通知
+    pub fn with_notifier(mut self, notifier: Arc<dyn DesktopNotifier + Send + Sync>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+}
+
+#[async_trait]
+impl Job for ConsistencyCheckJob {
+    fn name(&self) -> &str {
+        "consistency_check"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::weekly(
+            Weekday::Sun,
+            NaiveTime::from_hms_opt(3, 0, 0).unwrap_or_default(),
+        )
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let report = self.checker.run(None, DEFAULT_VIOLATION_LIMIT).await?;
+        let summary = consistency_summary(&report);
+        for result in report.results.iter().filter(|r| !r.passed()) {
+            warn!(
+                "一致性检查 {} 发现 {}{} 个问题",
+                result.name,
+                result.violations.len(),
+                if result.truncated { "+" } else { "" }
+            );
+        }
+        info!("一致性检查完成: {}", summary);
+
+        if report.has_errors() {
+            if let Some(notifier) = &self.notifier {
+                if let Err(e) = notifier.notify("数据一致性检查发现错误", &summary) {
+                    warn!("一致性检查桌面通知失败: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandBus;
+    use crate::session::{CurrentUser, PermissionGuard};
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{ConsistencyViolation, ManualClock, User, UserRole};
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    /// 返回固定问题数的检查
+    struct FixedCheck {
+        name: &'static str,
+        severity: ConsistencySeverity,
+        found: usize,
+    }
+
+    #[async_trait]
+    impl ConsistencyCheck for FixedCheck {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "测试检查"
+        }
+
+        fn severity(&self) -> ConsistencySeverity {
+            self.severity
+        }
+
+        async fn run(&self, limit: usize) -> CoreResult<Vec<ConsistencyViolation>> {
+            Ok((0..self.found.min(limit))
+                .map(|i| ConsistencyViolation {
+                    ids: vec![Uuid::new_v4()],
+                    description: format!("问题 {}", i),
+                })
+                .collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct Notifications(Mutex<Vec<String>>);
+
+    impl DesktopNotifier for Notifications {
+        fn notify(&self, title: &str, body: &str) -> CoreResult<()> {
+            self.0.lock().unwrap().push(format!("{}: {}", title, body));
+            Ok(())
+        }
+    }
+
+    fn check(
+        name: &'static str,
+        severity: ConsistencySeverity,
+        found: usize,
+    ) -> Arc<dyn ConsistencyCheck + Send + Sync> {
+        Arc::new(FixedCheck {
+            name,
+            severity,
+            found,
+        })
+    }
+
+    fn checker(payments: usize, summary: usize) -> ConsistencyChecker {
+        ConsistencyChecker::new()
+            .with(check("payments_within_total", ConsistencySeverity::Error, payments))
+            .with(check("customer_summary", ConsistencySeverity::Warning, summary))
+            .with_clock(Arc::new(ManualClock::new(
+                Utc.with_ymd_and_hms(2024, 6, 2, 3, 0, 0).unwrap(),
+            )))
+    }
+
+    fn admin() -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: "admin".to_string(),
+            display_name: String::new(),
+            role: UserRole::Admin,
+            password_hash: String::new(),
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_command_selects_checks_and_limits_violations() {
+        let session = CurrentUser::new();
+        let mut commands = CommandBus::new();
+        commands.add_guard(Arc::new(PermissionGuard::new(session.clone())));
+        commands.register::<CheckConsistencyCommand>(Arc::new(checker(3, 1)));
+        session.sign_in(admin());
+
+        let report = commands
+            .dispatch(CheckConsistencyCommand {
+                checks: None,
+                limit_per_check: 2,
+            })
+            .await
+            .unwrap();
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results[0].violations.len(), 2);
+        assert!(report.results[0].truncated);
+        assert!(!report.results[1].truncated);
+        assert!(report.has_errors());
+        assert_eq!(consistency_summary(&report), "2 项检查：1 项错误，1 项警告");
+
+        let report = commands
+            .dispatch(CheckConsistencyCommand {
+                checks: Some(vec!["customer_summary".to_string()]),
+                ..CheckConsistencyCommand::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(report.results.len(), 1);
+        assert!(!report.has_errors());
+
+        let err = commands
+            .dispatch(CheckConsistencyCommand {
+                checks: Some(vec!["no_such_check".to_string()]),
+                ..CheckConsistencyCommand::default()
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+
+        // 报告可直接输出为JSON（命令行 --json）
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["results"][0]["severity"], "warning");
+    }
+
+    #[tokio::test]
+    async fn test_weekly_job_notifies_only_on_errors() {
+        let notifications = Arc::new(Notifications::default());
+        let job = |payments, summary| {
+            ConsistencyCheckJob::new(Arc::new(checker(payments, summary)))
+                .with_notifier(notifications.clone())
+        };
+
+        job(0, 4).run().await.unwrap();
+        assert!(notifications.0.lock().unwrap().is_empty());
+
+        job(1, 0).run().await.unwrap();
+        assert_eq!(
+            *notifications.0.lock().unwrap(),
+            vec!["数据一致性检查发现错误: 2 项检查：1 项错误，0 项警告".to_string()]
+        );
+    }
+}
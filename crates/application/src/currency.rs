@@ -0,0 +1,157 @@
+//! 本位币折算
+//!
+//! 统计和报表按单据日期生效的汇率把各币种金额折算为本位币后再合计。缺少汇率的单据
+//! 不按1折算，而是单独列出，由调用方提示补录汇率。
+
+use chrono::NaiveDate;
+use minicrm_core::{CoreResult, Currency, ExchangeRateService, Money, UnconvertedDocument};
+
+/// 按 `date` 当天生效的汇率把金额折算为本位币，缺少汇率时返回空
+///
+/// # Errors
+///
+/// 查询汇率失败时返回错误。
+pub async fn convert_to_base(
+    rates: &(dyn ExchangeRateService + Send + Sync),
+    amount: Money,
+    currency: Currency,
+    date: NaiveDate,
+) -> CoreResult<Option<Money>> {
+    if currency.is_base() {
+        return Ok(Some(amount));
+    }
+    Ok(rates
+        .rate_at(currency, date)
+        .await?
+        .map(|rate| amount.convert(rate.rate_to_base)))
+}
+
+/// 折算为本位币的金额合计
+///
+/// 逐张单据累加，缺少汇率的单据记入 [`BaseCurrencyTotal::missing`]，不计入合计。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BaseCurrencyTotal {
+    total: Money,
+    missing: Vec<UnconvertedDocument>,
+}
+
+impl BaseCurrencyTotal {
+    /// 创建空合计
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 累加一张单据
+    ///
+    /// # Errors
+    ///
+    /// 查询汇率失败时返回错误。
+    pub async fn add(
+        &mut self,
+        rates: &(dyn ExchangeRateService + Send + Sync),
+        kind: &str,
+        number: &str,
+        amount: Money,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> CoreResult<()> {
+        match convert_to_base(rates, amount, currency, date).await? {
+            Some(converted) => self.total = self.total + converted,
+            None => self.missing.push(UnconvertedDocument {
+                kind: kind.to_string(),
+                number: number.to_string(),
+                currency,
+                amount,
+                date,
+            }),
+        }
+        Ok(())
+    }
+
+    /// 本位币合计
+    pub fn total(&self) -> Money {
+        self.total
+    }
+
+    /// 缺少汇率的单据
+    pub fn missing(&self) -> &[UnconvertedDocument] {
+        &self.missing
+    }
+
+    /// 拆分为合计和缺少汇率的单据
+    pub fn into_parts(self) -> (Money, Vec<UnconvertedDocument>) {
+        (self.total, self.missing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use minicrm_core::{CoreError, ExchangeRate};
+
+    /// 只有美元汇率的汇率服务
+    struct UsdOnly;
+
+    #[async_trait]
+    impl ExchangeRateService for UsdOnly {
+        async fn set_rate(
+            &self,
+            _currency: Currency,
+            _rate_to_base: f64,
+            _effective_date: NaiveDate,
+        ) -> CoreResult<ExchangeRate> {
+            Err(CoreError::Other("只读".to_string()))
+        }
+
+        async fn rate_at(
+            &self,
+            currency: Currency,
+            date: NaiveDate,
+        ) -> CoreResult<Option<ExchangeRate>> {
+            Ok((currency == Currency::USD).then_some(ExchangeRate {
+                currency,
+                rate_to_base: 7.2,
+                effective_date: date,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_total_flags_missing_rates() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let eur: Currency = "EUR".parse().unwrap();
+        let mut total = BaseCurrencyTotal::new();
+        total
+            .add(
+                &UsdOnly,
+                "报价",
+                "Q-1",
+                Money::from_yuan(1_000.0),
+                Currency::CNY,
+                date,
+            )
+            .await
+            .unwrap();
+        total
+            .add(
+                &UsdOnly,
+                "报价",
+                "Q-2",
+                Money::from_yuan(100.0),
+                Currency::USD,
+                date,
+            )
+            .await
+            .unwrap();
+        total
+            .add(&UsdOnly, "订单", "SO-3", Money::from_yuan(50.0), eur, date)
+            .await
+            .unwrap();
+
+        assert_eq!(total.total(), Money::from_yuan(1_720.0));
+        assert_eq!(total.missing().len(), 1);
+        assert_eq!(total.missing()[0].number, "SO-3");
+        assert_eq!(total.missing()[0].currency, eur);
+    }
+}
@@ -0,0 +1,715 @@
+//! 每周摘要
+//!
+//! 每周汇总本周到期任务、逾期任务、即将过期的报价、上周新增客户、本周的客户纪念日和销售漏斗，
+//! 以短摘要发送桌面通知；配置了邮件服务和收件人时同时发送完整的HTML邮件。
+//! 章节沿用月度报表的 [`ReportSection`] 模型，渲染与报表一致。
+//!
+//! 调度计划为 [`JobSchedule::Weekly`]：电脑在运行时间关机时，本周内下次启动会补发，
+//! 同一ISO周内不会重复发送（需为调度器配置运行记录）。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use minicrm_core::{
+    AnniversaryService, BusinessCalendar, Clock, CoreResult, CustomerService, DateRange,
+    DesktopNotifier, Job, JobSchedule, LocalDate, Locale, MailService, OpportunityService,
+    Pagination, Pipeline, QueryFilter, Quote, QuoteService, ReminderKind, SystemClock, Task,
+    TaskService, UpcomingAnniversary,
+};
+use tracing::{info, warn};
+
+use crate::queries::DeliveryWeek;
+use crate::reports::{Report, ReportFormat, ReportSection, ReportTable};
+
+/// 摘要中列出的报价过期天数
+pub const EXPIRING_QUOTE_DAYS: u32 = 7;
+
+/// 摘要数据
+#[derive(Debug, Clone, Default)]
+pub struct DigestData {
+    /// 本周到期的未完成任务
+    pub tasks_due: Vec<Task>,
+    /// 逾期任务数
+    pub overdue_tasks: u64,
+    /// 即将过期的报价
+    pub expiring_quotes: Vec<Quote>,
+    /// 上周新增客户数
+    pub new_customers: u64,
+    /// 本周的联系人生日和首次成交纪念日
+    pub anniversaries: Vec<UpcomingAnniversary>,
+    /// 销售漏斗（未启用时为空）
+    pub pipeline: Option<Pipeline>,
+}
+
+/// 摘要数据来源
+#[async_trait]
+pub trait DigestSource: Send + Sync {
+    /// 收集 `week` 所在周的摘要数据
+    async fn collect(&self, calendar: &BusinessCalendar, week: DateRange)
+        -> CoreResult<DigestData>;
+}
+
+/// 基于核心服务接口的摘要数据来源
+pub struct ServiceDigestSource {
+    customers: Arc<dyn CustomerService + Send + Sync>,
+    tasks: Arc<dyn TaskService + Send + Sync>,
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    opportunities: Option<Arc<dyn OpportunityService + Send + Sync>>,
+    anniversaries: Option<Arc<dyn AnniversaryService + Send + Sync>>,
+}
+
+impl std::fmt::Debug for ServiceDigestSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServiceDigestSource")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ServiceDigestSource {
+    /// 创建摘要数据来源
+    pub fn new(
+        customers: Arc<dyn CustomerService + Send + Sync>,
+        tasks: Arc<dyn TaskService + Send + Sync>,
+        quotes: Arc<dyn QuoteService + Send + Sync>,
+    ) -> Self {
+        Self {
+            customers,
+            tasks,
+            quotes,
+            opportunities: None,
+            anniversaries: None,
+        }
+    }
+
+    /// 同时汇总销售漏斗
+    pub fn with_opportunities(
+        mut self,
+        opportunities: Arc<dyn OpportunityService + Send + Sync>,
+    ) -> Self {
+        self.opportunities = Some(opportunities);
+        self
+    }
+
+    /// 同时列出本周的客户纪念日
+    pub fn with_anniversaries(
+        mut self,
+        anniversaries: Arc<dyn AnniversaryService + Send + Sync>,
+    ) -> Self {
+        self.anniversaries = Some(anniversaries);
+        self
+    }
+}
+
+#[async_trait]
+impl DigestSource for ServiceDigestSource {
+    async fn collect(
+        &self,
+        calendar: &BusinessCalendar,
+        week: DateRange,
+    ) -> CoreResult<DigestData> {
+        let tasks_due = self
+            .tasks
+            .get_due_tasks(7)
+            .await?
+            .into_iter()
+            .filter(|t| t.due_date.is_some_and(|due| week.contains(due)))
+            .collect();
+        let last_week = DateRange::new(week.start - chrono::Duration::days(7), week.start);
+        let new_customers = self
+            .customers
+            .search_customers(
+                &QueryFilter::new()
+                    .with_date_range("created_at", last_week)
+                    .with_pagination(Pagination::new(1, 1)),
+            )
+            .await?
+            .total;
+        let pipeline = match &self.opportunities {
+            Some(opportunities) => Some(opportunities.pipeline().await?),
+            None => None,
+        };
+        let anniversaries = match &self.anniversaries {
+            Some(anniversaries) => {
+                anniversaries
+                    .upcoming_anniversaries(calendar.local_date(week.start), 6)
+                    .await?
+            }
+            None => Vec::new(),
+        };
+        Ok(DigestData {
+            tasks_due,
+            overdue_tasks: self.tasks.get_task_statistics().await?.overdue_tasks,
+            expiring_quotes: self.quotes.get_expiring_quotes(EXPIRING_QUOTE_DAYS).await?,
+            new_customers,
+            anniversaries,
+            pipeline,
+        })
+    }
+}
+
+/// 每周摘要
+#[derive(Debug, Clone)]
+pub struct WeeklyDigest {
+    /// 完整摘要（用于邮件）
+    pub report: Report,
+    /// 短摘要（用于桌面通知）
+    pub summary: String,
+}
+
+fn weekday_label(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "周一",
+        Weekday::Tue => "周二",
+        Weekday::Wed => "周三",
+        Weekday::Thu => "周四",
+        Weekday::Fri => "周五",
+        Weekday::Sat => "周六",
+        Weekday::Sun => "周日",
+    }
+}
+
+impl WeeklyDigest {
+    /// 组装 `monday` 所在周的摘要
+    pub fn assemble(
+        calendar: &BusinessCalendar,
+        monday: LocalDate,
+        data: &DigestData,
+        generated_at: DateTime<Utc>,
+    ) -> Self {
+        let week = monday.naive().iso_week();
+        let coverage = format!(
+            "{}年第{}周（{} 至 {}）",
+            week.year(),
+            week.week(),
+            monday.naive().format("%m-%d"),
+            monday.add_days(6).naive().format("%m-%d")
+        );
+
+        let mut tasks = ReportSection::new("本周任务")
+            .metric("本周到期", data.tasks_due.len())
+            .metric("已逾期", data.overdue_tasks);
+        let mut by_day: Vec<(LocalDate, Vec<&Task>)> = Vec::new();
+        let mut due: Vec<&Task> = data.tasks_due.iter().collect();
+        due.sort_by_key(|t| t.due_date);
+        for task in due {
+            let Some(at) = task.due_date else {
+                continue;
+            };
+            let date = calendar.local_date(at);
+            match by_day.last_mut() {
+                Some((day, day_tasks)) if *day == date => day_tasks.push(task),
+                _ => by_day.push((date, vec![task])),
+            }
+        }
+        tasks.table = Some(ReportTable {
+            headers: vec!["日期".to_string(), "任务数".to_string(), "任务".to_string()],
+            rows: by_day
+                .iter()
+                .map(|(date, day_tasks)| {
+                    vec![
+                        format!(
+                            "{} {}",
+                            date.naive().format("%m-%d"),
+                            weekday_label(date.naive().weekday())
+                        ),
+                        day_tasks.len().to_string(),
+                        day_tasks
+                            .iter()
+                            .map(|t| t.title.as_str())
+                            .collect::<Vec<_>>()
+                            .join("、"),
+                    ]
+                })
+                .collect(),
+        });
+
+        let mut quotes = ReportSection::new("即将过期的报价").metric(
+            format!("{}天内过期", EXPIRING_QUOTE_DAYS),
+            data.expiring_quotes.len(),
+        );
+        quotes.table = Some(ReportTable {
+            headers: vec![
+                "报价编号".to_string(),
+                "金额".to_string(),
+                "有效期".to_string(),
+            ],
+            rows: data
+                .expiring_quotes
+                .iter()
+                .map(|q| {
+                    vec![
+                        q.quote_number.clone(),
+                        Locale::ZhCn.money(q.total_amount),
+                        calendar.local_date(q.valid_until).naive().to_string(),
+                    ]
+                })
+                .collect(),
+        });
+
+        let customers = ReportSection::new("客户").metric("上周新增客户", data.new_customers);
+
+        let mut sections = vec![tasks, quotes, customers];
+        if !data.anniversaries.is_empty() {
+            let mut maintenance = ReportSection::new(ReminderKind::ContactBirthday.category())
+                .metric("本周纪念日", data.anniversaries.len());
+            maintenance.table = Some(ReportTable {
+                headers: vec!["日期".to_string(), "客户".to_string()],
+                rows: data
+                    .anniversaries
+                    .iter()
+                    .map(|a| {
+                        vec![
+                            format!(
+                                "{} {}",
+                                a.on.naive().format("%m-%d"),
+                                weekday_label(a.on.naive().weekday())
+                            ),
+                            a.title(),
+                        ]
+                    })
+                    .collect(),
+            });
+            sections.push(maintenance);
+        }
+        if let Some(pipeline) = &data.pipeline {
+            let mut funnel = ReportSection::new("销售漏斗")
+                .metric("未结束机会加权金额", pipeline.open_weighted_amount());
+            funnel.table = Some(ReportTable {
+                headers: vec![
+                    "阶段".to_string(),
+                    "机会数".to_string(),
+                    "预计金额".to_string(),
+                    "加权金额".to_string(),
+                ],
+                rows: pipeline
+                    .stages
+                    .iter()
+                    .map(|s| {
+                        vec![
+                            s.stage.label().to_string(),
+                            s.count.to_string(),
+                            s.total_amount.to_string(),
+                            s.weighted_amount.to_string(),
+                        ]
+                    })
+                    .collect(),
+            });
+            sections.push(funnel);
+        }
+
+        let mut summary = format!(
+            "本周到期任务 {} 项（已逾期 {} 项），{} 份报价即将过期，上周新增客户 {} 位",
+            data.tasks_due.len(),
+            data.overdue_tasks,
+            data.expiring_quotes.len(),
+            data.new_customers
+        );
+        if !data.anniversaries.is_empty() {
+            summary.push_str(&format!(
+                "，{} 个客户纪念日需维护",
+                data.anniversaries.len()
+            ));
+        }
+        Self {
+            report: Report {
+                title: "MiniCRM 每周摘要".to_string(),
+                period: monday.period(),
+                coverage: Some(coverage),
+                generated_at,
+                sections,
+            },
+            summary,
+        }
+    }
+}
+
+/// 每周摘要定时任务
+pub struct WeeklyDigestJob {
+    source: Arc<dyn DigestSource>,
+    notifier: Option<Arc<dyn DesktopNotifier + Send + Sync>>,
+    mailer: Option<Arc<dyn MailService + Send + Sync>>,
+    recipients: Vec<String>,
+    weekday: Weekday,
+    at: NaiveTime,
+    calendar: BusinessCalendar,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for WeeklyDigestJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeeklyDigestJob")
+            .field("recipients", &self.recipients)
+            .field("weekday", &self.weekday)
+            .field("at", &self.at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WeeklyDigestJob {
+    /// 创建每周摘要任务（默认每周一 08:00）
+    pub fn new(source: Arc<dyn DigestSource>) -> Self {
+        Self {
+            source,
+            notifier: None,
+            mailer: None,
+            recipients: Vec::new(),
+            weekday: Weekday::Mon,
+            at: NaiveTime::from_hms_opt(8, 0, 0).unwrap_or_default(),
+            calendar: BusinessCalendar::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置运行的星期和时间（业务时区）
+    pub fn with_schedule(mut self, weekday: Weekday, at: NaiveTime) -> Self {
+        self.weekday = weekday;
+        self.at = at;
+        self
+    }
+
+    /// 设置桌面通知
+    pub fn with_notifier(mut self, notifier: Arc<dyn DesktopNotifier + Send + Sync>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// 设置邮件服务及收件人
+    pub fn with_mailer(
+        mut self,
+        mailer: Arc<dyn MailService + Send + Sync>,
+        recipients: Vec<String>,
+    ) -> Self {
+        self.mailer = Some(mailer);
+        self.recipients = recipients;
+        self
+    }
+
+    /// 设置业务日历，“本周”按其本地日期计算
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 构建当前这一周的摘要
+    ///
+    /// # Errors
+    ///
+    /// 数据收集失败时返回错误。
+    pub async fn build(&self) -> CoreResult<WeeklyDigest> {
+        let now = self.clock.now();
+        let monday = DeliveryWeek::monday_of(self.calendar.local_date(now));
+        let data = self
+            .source
+            .collect(&self.calendar, DeliveryWeek::range(&self.calendar, monday))
+            .await?;
+        Ok(WeeklyDigest::assemble(&self.calendar, monday, &data, now))
+    }
+}
+
+#[async_trait]
+impl Job for WeeklyDigestJob {
+    fn name(&self) -> &str {
+        "weekly_digest"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::weekly(self.weekday, self.at)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let digest = self.build().await?;
+        let title = digest.report.coverage.as_deref().map_or_else(
+            || digest.report.title.clone(),
+            |c| format!("每周摘要 {}", c),
+        );
+
+        if let Some(notifier) = &self.notifier {
+            if let Err(e) = notifier.notify(&title, &digest.summary) {
+                warn!("每周摘要桌面通知失败: {}", e);
+            }
+        }
+        if let Some(mailer) = self.mailer.as_ref().filter(|_| !self.recipients.is_empty()) {
+            mailer
+                .send_html(
+                    &self.recipients,
+                    &title,
+                    &digest.report.render(ReportFormat::Html),
+                    &digest.report.render(ReportFormat::Markdown),
+                )
+                .await?;
+        }
+        info!("每周摘要已发送: {}", digest.summary);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::JobScheduler;
+    use chrono::Duration;
+    use minicrm_core::{
+        upcoming_within, AnniversaryKind, Currency, CustomerAnniversary, JobRunLog, ManualClock,
+        Money, OpportunityStage, PipelineStage, QuoteStatus, TaskPriority, TaskStatus,
+    };
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    fn shanghai(day: u32, hour: u32) -> DateTime<Utc> {
+        let calendar = BusinessCalendar::default();
+        calendar.to_utc(
+            chrono::NaiveDate::from_ymd_opt(2024, 3, day)
+                .and_then(|d| d.and_hms_opt(hour, 0, 0))
+                .unwrap_or_default(),
+        )
+    }
+
+    fn task(title: &str, due: DateTime<Utc>) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: None,
+            status: TaskStatus::Pending,
+            priority: TaskPriority::Medium,
+            customer_id: None,
+            supplier_id: None,
+            due_date: Some(due),
+            created_at: due,
+            updated_at: due,
+            created_by: None,
+            updated_by: None,
+            assigned_to: None,
+        }
+    }
+
+    fn quote(number: &str, amount: f64, valid_until: DateTime<Utc>) -> Quote {
+        Quote {
+            id: Uuid::new_v4(),
+            quote_number: number.to_string(),
+            customer_id: Uuid::new_v4(),
+            status: QuoteStatus::Sent,
+            total_amount: Money::from_yuan(amount),
+            currency: Currency::CNY,
+            items: Vec::new(),
+            valid_until,
+            remarks: None,
+            created_at: valid_until,
+            updated_at: valid_until,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    fn seeded() -> DigestData {
+        DigestData {
+            tasks_due: vec![
+                task("回访李总", shanghai(6, 15)),
+                task("寄送样板", shanghai(4, 10)),
+                task("确认交期", shanghai(6, 9)),
+            ],
+            overdue_tasks: 2,
+            expiring_quotes: vec![quote("BJ20240301-002", 12_880.5, shanghai(8, 23))],
+            new_customers: 5,
+            anniversaries: Vec::new(),
+            pipeline: Some(Pipeline {
+                stages: vec![PipelineStage {
+                    stage: OpportunityStage::Quoted,
+                    count: 3,
+                    total_amount: Money::from_yuan(90_000.0),
+                    weighted_amount: Money::from_yuan(45_000.0),
+                }],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_sections_from_seeded_data() {
+        let calendar = BusinessCalendar::default();
+        let monday = LocalDate::new(2024, 3, 4).unwrap();
+        let digest = WeeklyDigest::assemble(&calendar, monday, &seeded(), shanghai(4, 8));
+
+        assert_eq!(
+            digest.summary,
+            "本周到期任务 3 项（已逾期 2 项），1 份报价即将过期，上周新增客户 5 位"
+        );
+        assert_eq!(
+            digest.report.coverage.as_deref(),
+            Some("2024年第10周（03-04 至 03-10）")
+        );
+        let titles: Vec<&str> = digest
+            .report
+            .sections
+            .iter()
+            .map(|s| s.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            vec!["本周任务", "即将过期的报价", "客户", "销售漏斗"]
+        );
+
+        let tasks = digest.report.sections[0].table.as_ref().unwrap();
+        assert_eq!(
+            tasks.rows,
+            vec![
+                vec![
+                    "03-04 周一".to_string(),
+                    "1".to_string(),
+                    "寄送样板".to_string()
+                ],
+                vec![
+                    "03-06 周三".to_string(),
+                    "2".to_string(),
+                    "确认交期、回访李总".to_string()
+                ],
+            ]
+        );
+        let html = digest.report.render(ReportFormat::Html);
+        assert!(html.contains("2024年第10周"));
+        assert!(html.contains("BJ20240301-002"));
+        assert!(html.contains("¥45,000.00"));
+    }
+
+    #[test]
+    fn test_anniversary_section() {
+        let calendar = BusinessCalendar::default();
+        let monday = LocalDate::new(2024, 3, 4).unwrap();
+        let anniversary = |month, day| CustomerAnniversary {
+            customer_id: Uuid::new_v4(),
+            customer_name: "华东建材".to_string(),
+            contact_person: Some("王经理".to_string()),
+            phone: None,
+            owner_id: None,
+            kind: AnniversaryKind::Birthday,
+            date: chrono::NaiveDate::from_ymd_opt(1984, month, day).unwrap(),
+        };
+        let mut data = seeded();
+        // 只列出本周（周一至周日）的纪念日
+        data.anniversaries = upcoming_within(&[anniversary(3, 6), anniversary(3, 11)], monday, 6);
+
+        let digest = WeeklyDigest::assemble(&calendar, monday, &data, shanghai(4, 8));
+        assert!(digest.summary.ends_with("，1 个客户纪念日需维护"));
+        let section = digest
+            .report
+            .sections
+            .iter()
+            .find(|s| s.title == "客户维护")
+            .unwrap();
+        assert_eq!(
+            section.table.as_ref().unwrap().rows,
+            vec![vec![
+                "03-06 周三".to_string(),
+                "华东建材 王经理 生日（3月6日）".to_string()
+            ]]
+        );
+    }
+
+    /// 内存运行记录
+    #[derive(Default)]
+    struct MemoryRunLog(Mutex<HashMap<String, DateTime<Utc>>>);
+
+    #[async_trait]
+    impl JobRunLog for MemoryRunLog {
+        async fn last_run(&self, job_name: &str) -> CoreResult<Option<DateTime<Utc>>> {
+            Ok(self
+                .0
+                .lock()
+                .map(|runs| runs.get(job_name).copied())
+                .unwrap_or(None))
+        }
+
+        async fn record_run(
+            &self,
+            job_name: &str,
+            started_at: DateTime<Utc>,
+            _error: Option<&str>,
+        ) -> CoreResult<()> {
+            if let Ok(mut runs) = self.0.lock() {
+                runs.insert(job_name.to_string(), started_at);
+            }
+            Ok(())
+        }
+    }
+
+    struct SeededSource;
+
+    #[async_trait]
+    impl DigestSource for SeededSource {
+        async fn collect(
+            &self,
+            _calendar: &BusinessCalendar,
+            _week: DateRange,
+        ) -> CoreResult<DigestData> {
+            Ok(seeded())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifier(Mutex<Vec<String>>);
+
+    impl DesktopNotifier for RecordingNotifier {
+        fn notify(&self, title: &str, _body: &str) -> CoreResult<()> {
+            if let Ok(mut sent) = self.0.lock() {
+                sent.push(title.to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_without_duplicates() {
+        let clock = Arc::new(ManualClock::new(shanghai(5, 10)));
+        let notifier = Arc::new(RecordingNotifier::default());
+        let run_log = Arc::new(MemoryRunLog::default());
+        run_log
+            .record_run("weekly_digest", shanghai(4, 8) - Duration::days(7), None)
+            .await
+            .unwrap();
+        let scheduler = || {
+            JobScheduler::new()
+                .with_calendar(BusinessCalendar::default())
+                .with_run_log(run_log.clone())
+                .register(Arc::new(
+                    WeeklyDigestJob::new(Arc::new(SeededSource))
+                        .with_notifier(notifier.clone())
+                        .with_clock(clock.clone()),
+                ))
+        };
+        let sent = || notifier.0.lock().map(|s| s.clone()).unwrap_or_default();
+
+        // 周一关机，周二启动时补发
+        let first = scheduler();
+        assert_eq!(first.run_due(clock.now()).await.len(), 1);
+        assert_eq!(sent(), vec!["每周摘要 2024年第10周（03-04 至 03-10）"]);
+        assert!(first
+            .run_due(clock.now() + Duration::hours(1))
+            .await
+            .is_empty());
+
+        // 同一周内重启不会重复发送
+        clock.set(shanghai(7, 9));
+        assert!(scheduler().run_due(clock.now()).await.is_empty());
+        clock.set(shanghai(10, 23));
+        assert!(scheduler().run_due(clock.now()).await.is_empty());
+
+        // 下周一运行时间之前不发送，之后发送一次
+        clock.set(shanghai(11, 7));
+        let next = scheduler();
+        assert!(next.run_due(clock.now()).await.is_empty());
+        clock.set(shanghai(11, 8));
+        assert_eq!(next.run_due(clock.now()).await.len(), 1);
+        assert!(next
+            .run_due(clock.now() + Duration::hours(2))
+            .await
+            .is_empty());
+        assert_eq!(sent().len(), 2);
+        assert_eq!(sent()[1], "每周摘要 2024年第11周（03-11 至 03-17）");
+    }
+}
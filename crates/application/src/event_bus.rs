@@ -0,0 +1,93 @@
+//! 事件总线模块
+//!
+//! 提供进程内的领域事件发布与订阅
+
+use std::sync::{Arc, RwLock};
+
+use minicrm_core::{DomainEvent, EventEnvelope, EventHandler};
+use tracing::{debug, warn};
+
+/// 进程内事件总线
+///
+/// 事件按订阅顺序同步分发给各处理器，单个处理器失败只记录警告，不影响其余处理器。
+#[derive(Default, Clone)]
+pub struct EventBus {
+    handlers: Arc<RwLock<Vec<Arc<dyn EventHandler>>>>,
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("handlers", &self.handler_count())
+            .finish()
+    }
+}
+
+impl EventBus {
+    /// 创建新的事件总线
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 订阅事件
+    pub fn subscribe(&self, handler: Arc<dyn EventHandler>) {
+        if let Ok(mut handlers) = self.handlers.write() {
+            debug!("注册事件处理器: {}", handler.name());
+            handlers.push(handler);
+        }
+    }
+
+    /// 已注册的处理器数量
+    pub fn handler_count(&self) -> usize {
+        self.handlers.read().map(|h| h.len()).unwrap_or(0)
+    }
+
+    /// 发布领域事件
+    pub fn publish(&self, event: DomainEvent) {
+        self.publish_envelope(&EventEnvelope::new(event));
+    }
+
+    /// 发布由关联ID为 `correlation_id` 的操作引发的领域事件
+    pub fn publish_correlated(&self, event: DomainEvent, correlation_id: impl Into<String>) {
+        self.publish_envelope(&EventEnvelope::new(event).with_correlation_id(correlation_id));
+    }
+
+    /// 发布已包装的事件
+    pub fn publish_envelope(&self, envelope: &EventEnvelope) {
+        let handlers = match self.handlers.read() {
+            Ok(handlers) => handlers.clone(),
+            Err(_) => return,
+        };
+
+        debug!(
+            "发布事件 {} ({} 个处理器)",
+            envelope.event.event_type(),
+            handlers.len()
+        );
+
+        for handler in handlers {
+            if let Err(e) = handler.handle(envelope) {
+                warn!(
+                    "事件处理器 {} 处理 {} 失败: {}",
+                    handler.name(),
+                    envelope.event.event_type(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// 事件总线可作为发件箱分发任务的发布目标
+///
+/// 处理器失败已在总线内记录，不会使分发任务重试。
+impl EventHandler for EventBus {
+    fn name(&self) -> &str {
+        "event_bus"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> minicrm_core::CoreResult<()> {
+        self.publish_envelope(envelope);
+        Ok(())
+    }
+}
@@ -0,0 +1,759 @@
+//! 外部调用保护
+//!
+//! 邮件、Webhook等外部调用统一经过 [`ExternalCall::call`]：每次尝试限定超时，
+//! 调用方判定为临时性的错误按指数退避（带随机抖动）重试；同一操作连续失败达到阈值后
+//! 熔断，冷却期内直接返回错误，冷却结束后放行一次试探调用，成功即恢复。
+//!
+//! 每个操作的熔断状态和调用计数可通过 [`ExternalCall::statuses`] 在诊断信息界面查看。
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Clock, CoreError, CoreResult, MailService, SystemClock, WebhookRequest, WebhookTransport,
+};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+/// 重试与熔断策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// 单次尝试超时
+    pub timeout: Duration,
+    /// 最多尝试次数（含首次）
+    pub max_attempts: u32,
+    /// 首次重试前的等待时长，之后每次翻倍
+    pub base_delay: Duration,
+    /// 重试等待上限
+    pub max_delay: Duration,
+    /// 随机抖动比例（0.2 表示在等待时长上随机增加至多20%）
+    pub jitter: f64,
+    /// 连续失败多少次后熔断
+    pub failure_threshold: u32,
+    /// 熔断冷却时长
+    pub cooldown: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `retry` 次重试（从1开始）前的等待时长，`sample` 为 [0, 1) 内的随机数
+    pub fn backoff(&self, retry: u32, sample: f64) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(2_u32.pow(exponent))
+            .min(self.max_delay);
+        delay.mul_f64(1.0 + self.jitter.clamp(0.0, 1.0) * sample.clamp(0.0, 1.0))
+    }
+}
+
+/// 等待接口（测试中替换为推进模拟时钟）
+#[async_trait]
+pub trait Sleeper: Send + Sync {
+    /// 等待指定时长
+    async fn sleep(&self, duration: Duration);
+}
+
+/// 使用 tokio 定时器的等待
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioSleeper;
+
+#[async_trait]
+impl Sleeper for TokioSleeper {
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// 熔断状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BreakerState {
+    /// 正常调用
+    Closed,
+    /// 熔断中，调用直接失败
+    Open,
+    /// 冷却结束，等待试探调用
+    HalfOpen,
+}
+
+/// 单个操作的熔断状态和调用计数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperationStatus {
+    /// 操作名
+    pub operation: String,
+    /// 熔断状态
+    pub state: BreakerState,
+    /// 连续失败次数
+    pub consecutive_failures: u32,
+    /// 熔断结束时间
+    pub open_until: Option<DateTime<Utc>>,
+    /// 调用次数（每次 `call` 计一次）
+    pub calls: u64,
+    /// 失败的尝试次数
+    pub failures: u64,
+    /// 重试次数
+    pub retries: u64,
+    /// 超时次数
+    pub timeouts: u64,
+    /// 熔断拒绝的调用次数
+    pub rejected: u64,
+    /// 最近一次错误
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct OperationState {
+    consecutive_failures: u32,
+    open_until: Option<DateTime<Utc>>,
+    probing: bool,
+    calls: u64,
+    failures: u64,
+    retries: u64,
+    timeouts: u64,
+    rejected: u64,
+    last_error: Option<String>,
+}
+
+impl OperationState {
+    fn state(&self, now: DateTime<Utc>) -> BreakerState {
+        match self.open_until {
+            Some(until) if now < until => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+            None => BreakerState::Closed,
+        }
+    }
+}
+
+fn to_chrono(duration: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(duration).unwrap_or_else(|_| chrono::Duration::days(1))
+}
+
+/// 外部调用执行器
+pub struct ExternalCall {
+    clock: Arc<dyn Clock>,
+    sleeper: Arc<dyn Sleeper>,
+    operations: Mutex<BTreeMap<String, OperationState>>,
+    seed: AtomicU64,
+}
+
+impl std::fmt::Debug for ExternalCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalCall").finish_non_exhaustive()
+    }
+}
+
+impl Default for ExternalCall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExternalCall {
+    /// 创建执行器
+    pub fn new() -> Self {
+        let seed = Utc::now().timestamp_nanos_opt().unwrap_or(1) as u64 | 1;
+        Self {
+            clock: Arc::new(SystemClock),
+            sleeper: Arc::new(TokioSleeper),
+            operations: Mutex::new(BTreeMap::new()),
+            seed: AtomicU64::new(seed),
+        }
+    }
+
+    /// 设置时钟（熔断冷却按该时钟计算）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置重试等待方式
+    pub fn with_sleeper(mut self, sleeper: Arc<dyn Sleeper>) -> Self {
+        self.sleeper = sleeper;
+        self
+    }
+
+    /// 执行外部调用
+    ///
+    /// `attempt` 每次尝试调用一次；返回的错误经 `is_transient` 判定为临时性时才重试，
+    /// 超时总是视为临时性错误。只有临时性错误计入熔断的连续失败次数，
+    /// 其他错误说明对方服务可达，会重置连续失败次数。
+    ///
+    /// # Errors
+    ///
+    /// 熔断中、重试耗尽或遇到非临时性错误时返回错误。
+    pub async fn call<T, F, Fut>(
+        &self,
+        policy: &RetryPolicy,
+        operation: &str,
+        is_transient: impl Fn(&CoreError) -> bool,
+        mut attempt: F,
+    ) -> CoreResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = CoreResult<T>>,
+    {
+        self.update(operation, |state| state.calls += 1);
+        let mut attempts = 0;
+        loop {
+            self.admit(operation)?;
+            attempts += 1;
+
+            let (result, timed_out) = match tokio::time::timeout(policy.timeout, attempt()).await {
+                Ok(result) => (result, false),
+                Err(_) => (
+                    Err(CoreError::ExternalService(format!(
+                        "{} 超时（{}秒）",
+                        operation,
+                        policy.timeout.as_secs_f64()
+                    ))),
+                    true,
+                ),
+            };
+
+            let error = match result {
+                Ok(value) => {
+                    self.record_success(operation);
+                    return Ok(value);
+                }
+                Err(error) => error,
+            };
+
+            let transient = timed_out || is_transient(&error);
+            let opened = self.record_failure(operation, policy, &error, transient, timed_out);
+            if !transient || opened || attempts >= policy.max_attempts {
+                warn!(operation, attempts, transient, "外部调用失败: {}", error);
+                return Err(error);
+            }
+
+            let delay = policy.backoff(attempts, self.sample());
+            self.update(operation, |state| state.retries += 1);
+            debug!(operation, attempts, ?delay, "外部调用将重试: {}", error);
+            self.sleeper.sleep(delay).await;
+        }
+    }
+
+    /// 全部操作的状态（按操作名排序）
+    pub fn statuses(&self) -> Vec<OperationStatus> {
+        let now = self.clock.now();
+        self.operations
+            .lock()
+            .map(|operations| {
+                operations
+                    .iter()
+                    .map(|(name, state)| Self::status_of(name, state, now))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 单个操作的状态
+    pub fn status(&self, operation: &str) -> Option<OperationStatus> {
+        let now = self.clock.now();
+        self.operations.lock().ok().and_then(|operations| {
+            operations
+                .get(operation)
+                .map(|state| Self::status_of(operation, state, now))
+        })
+    }
+
+    fn status_of(name: &str, state: &OperationState, now: DateTime<Utc>) -> OperationStatus {
+        OperationStatus {
+            operation: name.to_string(),
+            state: state.state(now),
+            consecutive_failures: state.consecutive_failures,
+            open_until: state.open_until,
+            calls: state.calls,
+            failures: state.failures,
+            retries: state.retries,
+            timeouts: state.timeouts,
+            rejected: state.rejected,
+            last_error: state.last_error.clone(),
+        }
+    }
+
+    fn update<R>(&self, operation: &str, f: impl FnOnce(&mut OperationState) -> R) -> Option<R> {
+        self.operations
+            .lock()
+            .ok()
+            .map(|mut operations| f(operations.entry(operation.to_string()).or_default()))
+    }
+
+    /// 熔断检查：熔断中拒绝；冷却结束后只放行一个试探调用
+    fn admit(&self, operation: &str) -> CoreResult<()> {
+        let now = self.clock.now();
+        let rejected = self.update(operation, |state| match state.state(now) {
+            BreakerState::Closed => None,
+            BreakerState::HalfOpen if !state.probing => {
+                state.probing = true;
+                None
+            }
+            _ => {
+                state.rejected += 1;
+                Some(state.open_until.unwrap_or(now))
+            }
+        });
+        match rejected.flatten() {
+            None => Ok(()),
+            Some(until) => {
+                let seconds = (until - now).num_seconds().max(0);
+                Err(CoreError::ExternalService(format!(
+                    "{} 暂停调用：连续失败后熔断，{}秒后重试",
+                    operation, seconds
+                )))
+            }
+        }
+    }
+
+    fn record_success(&self, operation: &str) {
+        let recovered = self.update(operation, |state| {
+            let recovered = state.open_until.is_some();
+            state.consecutive_failures = 0;
+            state.open_until = None;
+            state.probing = false;
+            recovered
+        });
+        if recovered == Some(true) {
+            info!(operation, "外部调用已恢复");
+        }
+    }
+
+    /// 记录一次失败尝试，返回是否（重新）熔断
+    fn record_failure(
+        &self,
+        operation: &str,
+        policy: &RetryPolicy,
+        error: &CoreError,
+        transient: bool,
+        timed_out: bool,
+    ) -> bool {
+        let now = self.clock.now();
+        let open_until = now + to_chrono(policy.cooldown);
+        let opened = self.update(operation, |state| {
+            state.failures += 1;
+            if timed_out {
+                state.timeouts += 1;
+            }
+            state.last_error = Some(error.to_string());
+            let was_probing = std::mem::take(&mut state.probing);
+            if !transient {
+                state.consecutive_failures = 0;
+                state.open_until = None;
+                return false;
+            }
+            state.consecutive_failures += 1;
+            if was_probing || state.consecutive_failures >= policy.failure_threshold.max(1) {
+                state.open_until = Some(open_until);
+                return true;
+            }
+            false
+        });
+        if opened == Some(true) {
+            warn!(operation, until = %open_until, "外部调用熔断");
+        }
+        opened.unwrap_or(false)
+    }
+
+    /// [0, 1) 内的伪随机数（xorshift，只用于抖动）
+    fn sample(&self) -> f64 {
+        let mut x = self.seed.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.seed.store(x, Ordering::Relaxed);
+        (x >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+/// 外部服务错误视为临时性错误
+pub fn is_external_service_error(error: &CoreError) -> bool {
+    matches!(error, CoreError::ExternalService(_))
+}
+
+/// 带重试和熔断的邮件发送
+pub struct GuardedMailService {
+    inner: Arc<dyn MailService + Send + Sync>,
+    calls: Arc<ExternalCall>,
+    policy: RetryPolicy,
+}
+
+impl std::fmt::Debug for GuardedMailService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GuardedMailService")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GuardedMailService {
+    /// 操作名
+    pub const OPERATION: &'static str = "smtp";
+
+    /// 包装邮件发送器，使用默认策略
+    pub fn new(inner: Arc<dyn MailService + Send + Sync>, calls: Arc<ExternalCall>) -> Self {
+        Self {
+            inner,
+            calls,
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    /// 设置策略
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+#[async_trait]
+impl MailService for GuardedMailService {
+    async fn send_html(
+        &self,
+        recipients: &[String],
+        subject: &str,
+        html: &str,
+        text: &str,
+    ) -> CoreResult<()> {
+        self.calls
+            .call(
+                &self.policy,
+                Self::OPERATION,
+                is_external_service_error,
+                || self.inner.send_html(recipients, subject, html, text),
+            )
+            .await
+    }
+}
+
+/// 带熔断的Webhook发送通道
+///
+/// 投递队列本身会按退避时间重新投递，这里默认只尝试一次，主要作用是端点持续不可用时
+/// 熔断，避免每轮投递都对每条记录等待超时。5xx和429响应计为失败。
+pub struct GuardedWebhookTransport {
+    inner: Arc<dyn WebhookTransport + Send + Sync>,
+    calls: Arc<ExternalCall>,
+    policy: RetryPolicy,
+}
+
+impl std::fmt::Debug for GuardedWebhookTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GuardedWebhookTransport")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GuardedWebhookTransport {
+    /// 包装发送通道
+    pub fn new(inner: Arc<dyn WebhookTransport + Send + Sync>, calls: Arc<ExternalCall>) -> Self {
+        Self {
+            inner,
+            calls,
+            policy: RetryPolicy {
+                timeout: Duration::from_secs(15),
+                max_attempts: 1,
+                cooldown: Duration::from_secs(300),
+                ..RetryPolicy::default()
+            },
+        }
+    }
+
+    /// 设置策略
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 操作名（按主机区分，一个端点不可用不影响其他端点）
+    pub fn operation(url: &str) -> String {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let host = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+        format!("webhook:{}", host)
+    }
+}
+
+#[async_trait]
+impl WebhookTransport for GuardedWebhookTransport {
+    async fn post(&self, request: &WebhookRequest) -> CoreResult<u16> {
+        let operation = Self::operation(&request.url);
+        self.calls
+            .call(
+                &self.policy,
+                &operation,
+                is_external_service_error,
+                || async {
+                    let code = self.inner.post(request).await?;
+                    if code >= 500 || code == 429 {
+                        return Err(CoreError::ExternalService(format!("HTTP {}", code)));
+                    }
+                    Ok(code)
+                },
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use minicrm_core::ManualClock;
+    use std::collections::VecDeque;
+
+    /// 等待时推进模拟时钟并记录等待时长
+    struct ClockSleeper {
+        clock: Arc<ManualClock>,
+        slept: Mutex<Vec<Duration>>,
+    }
+
+    #[async_trait]
+    impl Sleeper for ClockSleeper {
+        async fn sleep(&self, duration: Duration) {
+            self.slept.lock().unwrap().push(duration);
+            self.clock.advance(to_chrono(duration));
+        }
+    }
+
+    /// 按脚本依次返回结果
+    struct Script {
+        results: Mutex<VecDeque<CoreResult<u32>>>,
+        invoked: Mutex<u32>,
+    }
+
+    impl Script {
+        fn new(results: Vec<CoreResult<u32>>) -> Self {
+            Self {
+                results: Mutex::new(results.into()),
+                invoked: Mutex::new(0),
+            }
+        }
+
+        async fn next(&self) -> CoreResult<u32> {
+            *self.invoked.lock().unwrap() += 1;
+            self.results.lock().unwrap().pop_front().unwrap_or(Ok(0))
+        }
+
+        fn invoked(&self) -> u32 {
+            *self.invoked.lock().unwrap()
+        }
+    }
+
+    fn transient() -> CoreResult<u32> {
+        Err(CoreError::ExternalService("连接被拒绝".to_string()))
+    }
+
+    fn setup() -> (Arc<ManualClock>, Arc<ClockSleeper>, ExternalCall) {
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap(),
+        ));
+        let sleeper = Arc::new(ClockSleeper {
+            clock: clock.clone(),
+            slept: Mutex::new(Vec::new()),
+        });
+        let calls = ExternalCall::new()
+            .with_clock(clock.clone())
+            .with_sleeper(sleeper.clone());
+        (clock, sleeper, calls)
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            timeout: Duration::from_secs(5),
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(3),
+            jitter: 0.0,
+            failure_threshold: 10,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_back_off_exponentially() {
+        let (clock, sleeper, calls) = setup();
+        let start = clock.now();
+        let script = Script::new(vec![transient(), transient(), transient(), Ok(7)]);
+
+        let value = calls
+            .call(&policy(), "smtp", is_external_service_error, || {
+                script.next()
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 7);
+        assert_eq!(script.invoked(), 4);
+        // 1s、2s，第三次达到上限 3s
+        assert_eq!(
+            *sleeper.slept.lock().unwrap(),
+            vec![
+                Duration::from_secs(1),
+                Duration::from_secs(2),
+                Duration::from_secs(3)
+            ]
+        );
+        assert_eq!(clock.now() - start, chrono::Duration::seconds(6));
+
+        let status = calls.status("smtp").unwrap();
+        assert_eq!(status.state, BreakerState::Closed);
+        assert_eq!((status.calls, status.failures, status.retries), (1, 3, 3));
+        assert_eq!(status.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_jitter_stays_within_bounds() {
+        let policy = RetryPolicy {
+            jitter: 0.5,
+            ..policy()
+        };
+        assert_eq!(policy.backoff(2, 0.0), Duration::from_secs(2));
+        assert_eq!(policy.backoff(2, 0.5), Duration::from_millis(2500));
+        assert_eq!(policy.backoff(10, 0.0), Duration::from_secs(3));
+
+        let (_clock, sleeper, calls) = setup();
+        let script = Script::new((0..4).map(|_| transient()).collect());
+        let result = calls
+            .call(&policy, "smtp", is_external_service_error, || script.next())
+            .await;
+        assert!(result.is_err());
+        assert_eq!(script.invoked(), 4);
+        let slept = sleeper.slept.lock().unwrap();
+        for (delay, base) in slept.iter().zip([1.0, 2.0, 3.0]) {
+            assert!(delay.as_secs_f64() >= base && delay.as_secs_f64() < base * 1.5);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_transient_errors_never_retry() {
+        let (_clock, sleeper, calls) = setup();
+        let script = Script::new(vec![Err(CoreError::validation("无效的收件人: x"))]);
+
+        let result = calls
+            .call(&policy(), "smtp", is_external_service_error, || {
+                script.next()
+            })
+            .await;
+        assert!(matches!(result, Err(CoreError::Validation(_))));
+        assert_eq!(script.invoked(), 1);
+        assert!(sleeper.slept.lock().unwrap().is_empty());
+        assert_eq!(calls.status("smtp").unwrap().consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_breaker_opens_probes_and_closes() {
+        let (clock, _sleeper, calls) = setup();
+        let policy = RetryPolicy {
+            max_attempts: 1,
+            failure_threshold: 2,
+            ..policy()
+        };
+        let script = Script::new(vec![transient(), transient(), transient(), Ok(1)]);
+        let run = || {
+            calls.call(&policy, "webhook:a", is_external_service_error, || {
+                script.next()
+            })
+        };
+
+        assert!(run().await.is_err());
+        assert_eq!(
+            calls.status("webhook:a").unwrap().state,
+            BreakerState::Closed
+        );
+        assert!(run().await.is_err());
+        assert_eq!(calls.status("webhook:a").unwrap().state, BreakerState::Open);
+
+        // 熔断中不调用
+        let rejected = run().await.unwrap_err();
+        assert!(rejected.to_string().contains("60秒后重试"));
+        assert_eq!(script.invoked(), 2);
+
+        // 冷却结束后试探失败，重新熔断
+        clock.advance(chrono::Duration::seconds(60));
+        assert_eq!(
+            calls.status("webhook:a").unwrap().state,
+            BreakerState::HalfOpen
+        );
+        assert!(run().await.is_err());
+        assert_eq!(script.invoked(), 3);
+        assert_eq!(calls.status("webhook:a").unwrap().state, BreakerState::Open);
+
+        // 试探成功后恢复
+        clock.advance(chrono::Duration::seconds(60));
+        assert_eq!(run().await.unwrap(), 1);
+        let status = calls.status("webhook:a").unwrap();
+        assert_eq!(status.state, BreakerState::Closed);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(status.rejected, 1);
+
+        // 其他操作不受影响
+        assert!(calls.status("smtp").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_is_transient() {
+        let (_clock, sleeper, calls) = setup();
+        let policy = RetryPolicy {
+            timeout: Duration::from_millis(10),
+            max_attempts: 2,
+            ..policy()
+        };
+        let result: CoreResult<u32> = calls
+            .call(
+                &policy,
+                "smtp",
+                |_| false,
+                std::future::pending::<CoreResult<u32>>,
+            )
+            .await;
+        assert!(result.unwrap_err().to_string().contains("超时"));
+        assert_eq!(sleeper.slept.lock().unwrap().len(), 1);
+        assert_eq!(calls.status("smtp").unwrap().timeouts, 2);
+    }
+
+    struct FixedTransport(u16);
+
+    #[async_trait]
+    impl WebhookTransport for FixedTransport {
+        async fn post(&self, _request: &WebhookRequest) -> CoreResult<u16> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guarded_webhook_counts_server_errors() {
+        let (_clock, _sleeper, calls) = setup();
+        let calls = Arc::new(calls);
+        let request = WebhookRequest {
+            url: "https://hooks.example.com/crm?id=1".to_string(),
+            headers: Vec::new(),
+            body: "{}".to_string(),
+        };
+
+        let ok = GuardedWebhookTransport::new(Arc::new(FixedTransport(404)), calls.clone());
+        assert_eq!(ok.post(&request).await.unwrap(), 404);
+
+        let failing = GuardedWebhookTransport::new(Arc::new(FixedTransport(503)), calls.clone());
+        for _ in 0..5 {
+            assert!(failing.post(&request).await.is_err());
+        }
+        let status = calls.status("webhook:hooks.example.com").unwrap();
+        assert_eq!(status.state, BreakerState::Open);
+        assert_eq!(status.last_error.as_deref(), Some("外部服务错误: HTTP 503"));
+    }
+}
@@ -1,5 +1,1853 @@
 //! 处理器模块
 //!
-//! 定义应用层的事件处理器
+//! 基于核心服务接口实现命令和查询处理器，并提供统一的注册入口。
 
-// 暂时为空，后续实现
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    ArchiveManifest, ArchivePreview, ArchiveRun, ArchiveTrigger, BusinessCalendar, ChangesetSummary,
+    ConsistencyCheck, CoreError, CoreResult, CreditCheckService, CreditOverride, Currency, Customer,
+    CustomerCategoryService, CustomerExportService, CustomerLevel, CustomerListReadModel,
+    CustomerListRow, CustomerService, CustomerTimelineReadModel, EntityKind, FieldPolicy,
+    FieldPolicyService, HeaderAliases, DuplicateCandidateSource, PossibleDuplicate,
+    DataArchiveService, DataSyncService, DeletionBatch, DeletionImpact, DeliveryService,
+    DiagnosticsBundleService, DiagnosticsBundleSummary, FieldError,
+    GlobalSearchSource, IdempotencyService, KnowledgeBaseService, LeadService, Money,
+    MonthlyClosingService,
+    OpportunityService, Order, PagedResult, Pagination, Pipeline, PricedProduct, PricingService,
+    Product, ProductService, PurchaseOrder, PurchaseOrderService, QueryFilter, Quote,
+    QuoteFingerprint, QuotePayloadCodec, QuoteService,
+    QuoteStatus, QuoteTemplateService, QuoteVerification, RecordArchiveService, ReportPeriod,
+    RestoreReport, SearchHit, SearchRanking, ServiceTicketService, SettingsExportSummary,
+    SettingsImportReport, SettingsTransferService, SpreadsheetReader, StatisticsService,
+    SupplierPayables, SupplierService, Task, TaskAudience, TaskCollaborationService, TaskNote,
+    TaskService,
+    TimelinePage, TrashService, UserRole, classify_duplicates, MAX_DUPLICATE_SUGGESTIONS,
+};
+use minicrm_core::dedup::{name_probe, phone_key};
+use uuid::Uuid;
+
+use crate::commands::{
+    AcceptQuoteCommand, AddTaskNoteCommand, AdjustCategoryPricesCommand, ApplyChangesCommand,
+    ApplyKnowledgeArticleCommand, ArchiveRecordsCommand, AssignTaskCommand,
+    ChangeCustomerCategoryCommand, CheckConsistencyCommand, CloseMonthCommand, CommandBus,
+    CommandHandler, ConfirmOrderCommand, CreateCustomerCommand, CreateLeadCommand,
+    CreateProductCommand,
+    CreateQuoteFromTemplateCommand, DeleteCustomerCommand, DeleteTaskNoteCommand,
+    ExportArchiveCommand, ExportDiagnosticsBundleCommand, ExportChangesCommand, ExportCustomersCommand, ExportSettingsCommand,
+    GenerateMonthlyReportCommand,
+    ImportArchiveCommand, ImportProductsCommand, ImportSettingsCommand, ImportSuppliersCommand,
+    RepriceQuoteCommand, ReopenMonthCommand, RestoreEntityCommand, RestoreTaskNoteCommand,
+    SaveKnowledgeArticleCommand,
+    SendQuoteCommand, SetCreditTermsCommand, SetFieldRequirementCommand, SoftDeleteCustomerCommand, TemplateQuote,
+    UpdateTaskStatusCommand, VerifyQuoteCommand, WatchTaskCommand,
+};
+use crate::queries::{
+    ArchivePreviewQuery, ArchiveRunsQuery, CustomerDeletionImpactQuery, CustomerListQuery,
+    CheckPossibleDuplicateQuery, CustomerTimelineQuery,
+    DashboardStats, DashboardStatsQuery, DeliveriesThisWeekQuery, DeliveryWeek, GetCustomerQuery,
+    GlobalSearchQuery, ListCustomersQuery, ListTasksQuery, PipelineQuery,
+    PriceAdjustmentPreviewQuery, QueryBus, QueryHandler, QuoteEditorQuery,
+    SupplierPayablesQuery, SupplierPurchaseOrdersQuery, TicketSuggestionsQuery, TrashContents,
+    TrashQuery,
+};
+use crate::cache::{StatKey, StatKind, StatisticsCache};
+use crate::closing::{ClosedMonthStatistics, MonthlyClosingHandlers};
+use crate::consistency::ConsistencyChecker;
+use crate::import::SpreadsheetImportHandlers;
+use crate::knowledge::KnowledgeBaseHandlers;
+use crate::leads::LeadHandlers;
+use crate::pricing::{
+    plan_price_adjustment, quote_margin, reprice_quote, MarginThresholds, PriceChange,
+    QuoteEditorData,
+};
+use crate::reports::ReportGenerator;
+use crate::session::CurrentUser;
+
+/// 客户命令与查询处理器
+pub struct CustomerHandlers {
+    service: Arc<dyn CustomerService + Send + Sync>,
+    field_policies: Option<Arc<dyn FieldPolicyService + Send + Sync>>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for CustomerHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerHandlers").finish_non_exhaustive()
+    }
+}
+
+impl CustomerHandlers {
+    /// 创建客户处理器
+    pub fn new(
+        service: Arc<dyn CustomerService + Send + Sync>,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            service,
+            field_policies: None,
+            current_user,
+        }
+    }
+
+    /// 启用字段策略（未启用时按默认要求校验，读取时不隐藏字段）
+    pub fn with_field_policies(
+        mut self,
+        field_policies: Arc<dyn FieldPolicyService + Send + Sync>,
+    ) -> Self {
+        self.field_policies = Some(field_policies);
+        self
+    }
+
+    async fn field_policy(&self) -> CoreResult<Option<FieldPolicy>> {
+        match &self.field_policies {
+            Some(service) => service.field_policy(EntityKind::Customer).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<CreateCustomerCommand> for CustomerHandlers {
+    async fn handle(&self, command: CreateCustomerCommand) -> CoreResult<Customer> {
+        match self.field_policy().await? {
+            Some(policy) => command.validate_with_policy(&policy)?,
+            None => command.validate()?,
+        }
+
+        let now = Utc::now();
+        let username = self.current_user.username();
+        let customer = Customer {
+            id: Uuid::new_v4(),
+            name: command.name.trim().to_string(),
+            contact_person: command.contact_person,
+            contact_birthday: None,
+            phone: command.phone,
+            email: command.email,
+            address: command.address,
+            level: CustomerLevel::Normal,
+            credit_limit: None,
+            credit_hold: false,
+            owner_id: self.current_user.user_id(),
+            created_at: now,
+            updated_at: now,
+            created_by: username.clone(),
+            updated_by: username,
+        };
+        self.service.create_customer(customer).await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<DeleteCustomerCommand> for CustomerHandlers {
+    async fn handle(&self, command: DeleteCustomerCommand) -> CoreResult<bool> {
+        let customer = self
+            .service
+            .get_customer_by_id(command.customer_id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("客户 {}", command.customer_id)))?;
+        let impact = self.service.get_deletion_impact(customer.id).await?;
+        if impact.has_financial_records()
+            && command.confirm_name.as_deref().map(str::trim) != Some(customer.name.as_str())
+        {
+            return Err(CoreError::invalid_fields(vec![FieldError::new(
+                "confirm_name",
+                "该客户有报价或订单等财务记录，请输入客户名称确认删除",
+            )]));
+        }
+        self.service.delete_customer(customer.id).await
+    }
+}
+
+#[async_trait]
+impl QueryHandler<CustomerDeletionImpactQuery> for CustomerHandlers {
+    async fn handle(&self, query: CustomerDeletionImpactQuery) -> CoreResult<DeletionImpact> {
+        self.service.get_deletion_impact(query.id).await
+    }
+}
+
+#[async_trait]
+impl QueryHandler<ListCustomersQuery> for CustomerHandlers {
+    async fn handle(&self, query: ListCustomersQuery) -> CoreResult<PagedResult<Customer>> {
+        let mut page = self.service.search_customers(&query.filter).await?;
+        if let Some(policy) = self.field_policy().await? {
+            page.items
+                .iter_mut()
+                .for_each(|customer| policy.redact_customer(customer));
+        }
+        Ok(page)
+    }
+}
+
+#[async_trait]
+impl QueryHandler<GetCustomerQuery> for CustomerHandlers {
+    async fn handle(&self, query: GetCustomerQuery) -> CoreResult<Option<Customer>> {
+        let mut customer = self.service.get_customer_by_id(query.id).await?;
+        if let (Some(customer), Some(policy)) = (customer.as_mut(), self.field_policy().await?) {
+            policy.redact_customer(customer);
+        }
+        Ok(customer)
+    }
+}
+
+/// 任务命令与查询处理器
+pub struct TaskHandlers {
+    service: Arc<dyn TaskService + Send + Sync>,
+    collaboration: Option<Arc<dyn TaskCollaborationService + Send + Sync>>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for TaskHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskHandlers").finish_non_exhaustive()
+    }
+}
+
+impl TaskHandlers {
+    /// 创建任务处理器
+    pub fn new(service: Arc<dyn TaskService + Send + Sync>, current_user: CurrentUser) -> Self {
+        Self {
+            service,
+            collaboration: None,
+            current_user,
+        }
+    }
+
+    /// 启用任务分配、关注和备注（未启用时状态变更不通知关注人）
+    pub fn with_collaboration(
+        mut self,
+        collaboration: Arc<dyn TaskCollaborationService + Send + Sync>,
+    ) -> Self {
+        self.collaboration = Some(collaboration);
+        self
+    }
+
+    fn collaboration(&self) -> CoreResult<&(dyn TaskCollaborationService + Send + Sync)> {
+        self.collaboration
+            .as_deref()
+            .ok_or_else(|| CoreError::configuration("未启用任务分配与关注"))
+    }
+
+    fn user_id(&self) -> CoreResult<Uuid> {
+        self.current_user
+            .user_id()
+            .ok_or_else(|| CoreError::permission("请先登录"))
+    }
+}
+
+#[async_trait]
+impl CommandHandler<UpdateTaskStatusCommand> for TaskHandlers {
+    async fn handle(&self, command: UpdateTaskStatusCommand) -> CoreResult<Task> {
+        let current = self
+            .service
+            .get_task_by_id(command.task_id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("任务 {}", command.task_id)))?;
+
+        if let Some(expected) = command.expected_updated_at {
+            if expected != current.updated_at {
+                return Err(CoreError::conflict(format!(
+                    "任务 {} 已被其他人修改，请刷新后重试",
+                    command.task_id
+                )));
+            }
+        }
+
+        let from = current.status;
+        let updated = self
+            .service
+            .update_task(Task {
+                status: command.status,
+                updated_at: Utc::now(),
+                updated_by: self.current_user.username(),
+                ..current
+            })
+            .await?;
+        if let Some(collaboration) = &self.collaboration {
+            collaboration
+                .record_status_change(updated.id, from, updated.status)
+                .await?;
+        }
+        Ok(updated)
+    }
+}
+
+#[async_trait]
+impl CommandHandler<AssignTaskCommand> for TaskHandlers {
+    async fn handle(&self, command: AssignTaskCommand) -> CoreResult<Task> {
+        self.collaboration()?
+            .assign_task(command.task_id, command.user_id)
+            .await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<WatchTaskCommand> for TaskHandlers {
+    async fn handle(&self, command: WatchTaskCommand) -> CoreResult<bool> {
+        let user_id = self.user_id()?;
+        let collaboration = self.collaboration()?;
+        if command.watch {
+            collaboration.watch_task(command.task_id, user_id).await
+        } else {
+            collaboration.unwatch_task(command.task_id, user_id).await
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<AddTaskNoteCommand> for TaskHandlers {
+    async fn handle(&self, command: AddTaskNoteCommand) -> CoreResult<TaskNote> {
+        self.collaboration()?
+            .add_task_note(command.task_id, &command.body, self.current_user.username())
+            .await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<DeleteTaskNoteCommand> for TaskHandlers {
+    async fn handle(&self, command: DeleteTaskNoteCommand) -> CoreResult<TaskNote> {
+        self.collaboration()?.delete_task_note(command.note_id).await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<RestoreTaskNoteCommand> for TaskHandlers {
+    async fn handle(&self, command: RestoreTaskNoteCommand) -> CoreResult<TaskNote> {
+        self.collaboration()?.restore_task_note(&command.note).await
+    }
+}
+
+#[async_trait]
+impl QueryHandler<ListTasksQuery> for TaskHandlers {
+    async fn handle(&self, query: ListTasksQuery) -> CoreResult<PagedResult<Task>> {
+        if !query.assigned_to_me && !query.watched_by_me {
+            return self.service.search_tasks(&query.filter).await;
+        }
+        let user_id = self.user_id()?;
+        let audience = TaskAudience {
+            assigned_to: query.assigned_to_me.then_some(user_id),
+            watched_by: query.watched_by_me.then_some(user_id),
+        };
+        self.collaboration()?
+            .search_tasks_for(audience, &query.filter.pagination)
+            .await
+    }
+}
+
+/// 客户等级和标签修改处理器
+pub struct CustomerCategoryHandler {
+    customers: Arc<dyn CustomerService + Send + Sync>,
+    categories: Arc<dyn CustomerCategoryService + Send + Sync>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for CustomerCategoryHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerCategoryHandler").finish_non_exhaustive()
+    }
+}
+
+impl CustomerCategoryHandler {
+    /// 创建处理器
+    pub fn new(
+        customers: Arc<dyn CustomerService + Send + Sync>,
+        categories: Arc<dyn CustomerCategoryService + Send + Sync>,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            customers,
+            categories,
+            current_user,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ChangeCustomerCategoryCommand> for CustomerCategoryHandler {
+    async fn handle(&self, command: ChangeCustomerCategoryCommand) -> CoreResult<Customer> {
+        let change = command.change;
+        let missing = || CoreError::not_found(format!("客户 {}", change.name));
+        let current = self
+            .customers
+            .get_customer_by_id(change.customer_id)
+            .await?
+            .ok_or_else(missing)?;
+        if command
+            .expected_updated_at
+            .is_some_and(|expected| expected != current.updated_at)
+            || current.level != change.level_before
+        {
+            return Err(CoreError::conflict(format!(
+                "客户 {} 已被其他人修改，请刷新后重试",
+                change.name
+            )));
+        }
+
+        let written = self
+            .categories
+            .apply_category_changes(
+                std::slice::from_ref(&change),
+                self.current_user.username().as_deref(),
+            )
+            .await?;
+        if written == 0 {
+            return Err(missing());
+        }
+        self.customers
+            .get_customer_by_id(change.customer_id)
+            .await?
+            .ok_or_else(missing)
+    }
+}
+
+/// 字段要求修改处理器
+pub struct FieldPolicyHandler {
+    service: Arc<dyn FieldPolicyService + Send + Sync>,
+}
+
+impl std::fmt::Debug for FieldPolicyHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FieldPolicyHandler").finish_non_exhaustive()
+    }
+}
+
+impl FieldPolicyHandler {
+    /// 创建处理器
+    pub fn new(service: Arc<dyn FieldPolicyService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<SetFieldRequirementCommand> for FieldPolicyHandler {
+    async fn handle(&self, command: SetFieldRequirementCommand) -> CoreResult<FieldPolicy> {
+        self.service
+            .set_field_requirement(command.entity, &command.field, command.requirement)
+            .await
+    }
+}
+
+/// 报价单校验处理器
+pub struct VerifyQuoteHandler {
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    codec: Arc<dyn QuotePayloadCodec>,
+}
+
+impl std::fmt::Debug for VerifyQuoteHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VerifyQuoteHandler").finish_non_exhaustive()
+    }
+}
+
+impl VerifyQuoteHandler {
+    /// 创建报价单校验处理器
+    pub fn new(
+        quotes: Arc<dyn QuoteService + Send + Sync>,
+        codec: Arc<dyn QuotePayloadCodec>,
+    ) -> Self {
+        Self { quotes, codec }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<VerifyQuoteCommand> for VerifyQuoteHandler {
+    async fn handle(&self, command: VerifyQuoteCommand) -> CoreResult<QuoteVerification> {
+        let claimed = self.codec.decode(&command.payload)?;
+
+        let filter = QueryFilter::new()
+            .with_string_filter("quote_number", claimed.quote_number.clone())
+            .with_pagination(Pagination::new(1, 10));
+        let stored = self
+            .quotes
+            .search_quotes(&filter)
+            .await?
+            .items
+            .iter()
+            .find(|q| q.quote_number == claimed.quote_number)
+            .map(QuoteFingerprint::from_quote);
+
+        Ok(QuoteVerification::compare(claimed, stored))
+    }
+}
+
+/// 从模板新建报价处理器
+pub struct QuoteTemplateHandler {
+    templates: Arc<dyn QuoteTemplateService + Send + Sync>,
+    pricing: Arc<dyn PricingService + Send + Sync>,
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for QuoteTemplateHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuoteTemplateHandler").finish_non_exhaustive()
+    }
+}
+
+impl QuoteTemplateHandler {
+    /// 创建从模板新建报价处理器
+    pub fn new(
+        templates: Arc<dyn QuoteTemplateService + Send + Sync>,
+        pricing: Arc<dyn PricingService + Send + Sync>,
+        quotes: Arc<dyn QuoteService + Send + Sync>,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            templates,
+            pricing,
+            quotes,
+            current_user,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<CreateQuoteFromTemplateCommand> for QuoteTemplateHandler {
+    async fn handle(&self, command: CreateQuoteFromTemplateCommand) -> CoreResult<TemplateQuote> {
+        let template = self
+            .templates
+            .get_template(command.template_id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("报价模板 {}", command.template_id)))?;
+
+        let now = Utc::now();
+        let mut prices = Vec::with_capacity(template.items.len());
+        for item in &template.items {
+            prices.push(self.pricing.priced_product(item.product_id, now).await?);
+        }
+        let draft = TemplateQuote::instantiate(
+            &template,
+            command.customer_id,
+            &prices,
+            now,
+            self.current_user.username(),
+        );
+        Ok(TemplateQuote {
+            quote: self.quotes.create_quote(draft.quote).await?,
+            warnings: draft.warnings,
+        })
+    }
+}
+
+/// 产品新建与调价处理器
+pub struct ProductPriceHandlers {
+    products: Arc<dyn ProductService + Send + Sync>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for ProductPriceHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProductPriceHandlers").finish_non_exhaustive()
+    }
+}
+
+impl ProductPriceHandlers {
+    /// 创建产品价格处理器
+    pub fn new(products: Arc<dyn ProductService + Send + Sync>, current_user: CurrentUser) -> Self {
+        Self {
+            products,
+            current_user,
+        }
+    }
+
+}
+
+#[async_trait]
+impl CommandHandler<CreateProductCommand> for ProductPriceHandlers {
+    async fn handle(&self, command: CreateProductCommand) -> CoreResult<PricedProduct> {
+        command.validate()?;
+
+        let now = Utc::now();
+        let product = self
+            .products
+            .create_product(Product {
+                id: Uuid::new_v4(),
+                name: command.name.trim().to_string(),
+                specification: command
+                    .specification
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty()),
+                category: None,
+                retired: false,
+                cost_price: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .await?;
+        self.products
+            .set_price(product.id, command.price, now, self.current_user.username())
+            .await?;
+        Ok(PricedProduct {
+            product_id: product.id,
+            name: product.name,
+            specification: product.specification,
+            unit_price: Some(command.price.as_yuan()),
+            unit_cost: None,
+            retired: false,
+        })
+    }
+}
+
+#[async_trait]
+impl QueryHandler<PriceAdjustmentPreviewQuery> for ProductPriceHandlers {
+    async fn handle(&self, query: PriceAdjustmentPreviewQuery) -> CoreResult<Vec<PriceChange>> {
+        plan_price_adjustment(
+            self.products.as_ref(),
+            &query.category,
+            query.percent,
+            query.effective_from,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<AdjustCategoryPricesCommand> for ProductPriceHandlers {
+    async fn handle(&self, command: AdjustCategoryPricesCommand) -> CoreResult<Vec<PriceChange>> {
+        let changes = plan_price_adjustment(
+            self.products.as_ref(),
+            &command.category,
+            command.percent,
+            command.effective_from,
+        )
+        .await?;
+        for change in &changes {
+            self.products
+                .set_price(
+                    change.product_id,
+                    change.new_price,
+                    command.effective_from,
+                    self.current_user.username(),
+                )
+                .await?;
+        }
+        Ok(changes)
+    }
+}
+
+/// 报价编辑器与发送处理器
+pub struct QuoteEditorHandlers {
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    pricing: Option<Arc<dyn PricingService + Send + Sync>>,
+    thresholds: MarginThresholds,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for QuoteEditorHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuoteEditorHandlers").finish_non_exhaustive()
+    }
+}
+
+impl QuoteEditorHandlers {
+    /// 创建报价编辑器处理器（未配置产品目录时不计算毛利）
+    pub fn new(
+        quotes: Arc<dyn QuoteService + Send + Sync>,
+        pricing: Option<Arc<dyn PricingService + Send + Sync>>,
+        thresholds: MarginThresholds,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            quotes,
+            pricing,
+            thresholds,
+            current_user,
+        }
+    }
+
+    async fn load(&self, id: Uuid) -> CoreResult<Quote> {
+        self.quotes
+            .get_quote_by_id(id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("报价 {}", id)))
+    }
+}
+
+#[async_trait]
+impl QueryHandler<QuoteEditorQuery> for QuoteEditorHandlers {
+    async fn handle(&self, query: QuoteEditorQuery) -> CoreResult<QuoteEditorData> {
+        let quote = self.load(query.quote_id).await?;
+        QuoteEditorData::assemble(self.pricing.as_deref(), quote, self.current_user.role()).await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<SendQuoteCommand> for QuoteEditorHandlers {
+    async fn handle(&self, command: SendQuoteCommand) -> CoreResult<Quote> {
+        let quote = self.load(command.quote_id).await?;
+        if let Some(pricing) = &self.pricing {
+            let margin = quote_margin(pricing.as_ref(), &quote).await?;
+            let approved = command.approve_low_margin
+                && self
+                    .current_user
+                    .role()
+                    .is_some_and(|role| role.satisfies(UserRole::Admin));
+            self.thresholds.check_send(&margin, approved)?;
+        }
+        self.quotes
+            .update_quote_status(quote.id, QuoteStatus::Sent)
+            .await
+    }
+}
+
+/// 重新计价报价处理器
+pub struct RepriceQuoteHandler {
+    pricing: Arc<dyn PricingService + Send + Sync>,
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for RepriceQuoteHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RepriceQuoteHandler").finish_non_exhaustive()
+    }
+}
+
+impl RepriceQuoteHandler {
+    /// 创建重新计价报价处理器
+    pub fn new(
+        pricing: Arc<dyn PricingService + Send + Sync>,
+        quotes: Arc<dyn QuoteService + Send + Sync>,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            pricing,
+            quotes,
+            current_user,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<RepriceQuoteCommand> for RepriceQuoteHandler {
+    async fn handle(&self, command: RepriceQuoteCommand) -> CoreResult<Quote> {
+        let quote = self
+            .quotes
+            .get_quote_by_id(command.quote_id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("报价 {}", command.quote_id)))?;
+        let repriced = reprice_quote(self.pricing.as_ref(), quote).await?;
+        self.quotes
+            .update_quote(Quote {
+                updated_by: self.current_user.username(),
+                ..repriced
+            })
+            .await
+    }
+}
+
+/// 仪表盘统计处理器
+///
+/// 各项统计分别缓存，领域事件只使受影响的部分失效。
+pub struct DashboardHandler {
+    customers: Arc<dyn CustomerService + Send + Sync>,
+    tasks: Arc<dyn TaskService + Send + Sync>,
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    statistics: Arc<dyn StatisticsService + Send + Sync>,
+    cache: Arc<StatisticsCache>,
+    calendar: BusinessCalendar,
+}
+
+impl std::fmt::Debug for DashboardHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DashboardHandler").finish_non_exhaustive()
+    }
+}
+
+impl DashboardHandler {
+    /// 创建仪表盘统计处理器
+    pub fn new(
+        customers: Arc<dyn CustomerService + Send + Sync>,
+        tasks: Arc<dyn TaskService + Send + Sync>,
+        quotes: Arc<dyn QuoteService + Send + Sync>,
+        statistics: Arc<dyn StatisticsService + Send + Sync>,
+        cache: Arc<StatisticsCache>,
+    ) -> Self {
+        Self {
+            customers,
+            tasks,
+            quotes,
+            statistics,
+            cache,
+            calendar: BusinessCalendar::default(),
+        }
+    }
+
+    /// 设置业务日历，未指定周期时按其本地日期确定"本月"
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    fn parts(&self) -> DashboardParts {
+        DashboardParts {
+            customers: self.customers.clone(),
+            tasks: self.tasks.clone(),
+            quotes: self.quotes.clone(),
+            statistics: self.statistics.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+/// 计算仪表盘所需的服务句柄（可移入后台刷新任务）
+#[derive(Clone)]
+struct DashboardParts {
+    customers: Arc<dyn CustomerService + Send + Sync>,
+    tasks: Arc<dyn TaskService + Send + Sync>,
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    statistics: Arc<dyn StatisticsService + Send + Sync>,
+    cache: Arc<StatisticsCache>,
+}
+
+impl DashboardParts {
+    /// 直接计算（不读写缓存）
+    async fn compute(&self, period: ReportPeriod) -> CoreResult<DashboardStats> {
+        Ok(DashboardStats {
+            customers: self.customers.get_customer_statistics().await?,
+            tasks: self.tasks.get_task_statistics().await?,
+            quotes: self.quotes.get_quote_statistics().await?,
+            period,
+            monthly: self.statistics.monthly_statistics(period).await?,
+        })
+    }
+
+    /// 按统计项读取缓存组合
+    async fn compose(self, period: ReportPeriod) -> CoreResult<DashboardStats> {
+        let customers = self.customers.clone();
+        let tasks = self.tasks.clone();
+        let quotes = self.quotes.clone();
+        let statistics = self.statistics.clone();
+        Ok(DashboardStats {
+            customers: self
+                .cache
+                .get_or_compute(StatKey::of(StatKind::Customers), move || async move {
+                    customers.get_customer_statistics().await
+                })
+                .await?,
+            tasks: self
+                .cache
+                .get_or_compute(StatKey::of(StatKind::Tasks), move || async move {
+                    tasks.get_task_statistics().await
+                })
+                .await?,
+            quotes: self
+                .cache
+                .get_or_compute(StatKey::of(StatKind::Quotes), move || async move {
+                    quotes.get_quote_statistics().await
+                })
+                .await?,
+            period,
+            monthly: self
+                .cache
+                .get_or_compute(
+                    StatKey::for_period(StatKind::Monthly, period),
+                    move || async move { statistics.monthly_statistics(period).await },
+                )
+                .await?,
+        })
+    }
+}
+
+#[async_trait]
+impl QueryHandler<DashboardStatsQuery> for DashboardHandler {
+    async fn handle(&self, query: DashboardStatsQuery) -> CoreResult<DashboardStats> {
+        let period = query
+            .period
+            .unwrap_or_else(|| self.calendar.period_containing(Utc::now()));
+        let key = StatKey::for_period(StatKind::Dashboard, period);
+        let parts = self.parts();
+
+        if query.bypass_cache {
+            return self.cache.refresh(key, || parts.compute(period)).await;
+        }
+        self.cache
+            .get_or_compute(key, move || parts.compose(period))
+            .await
+    }
+}
+
+/// 送货安排查询处理器
+pub struct DeliveryHandler {
+    service: Arc<dyn DeliveryService + Send + Sync>,
+    calendar: BusinessCalendar,
+}
+
+impl std::fmt::Debug for DeliveryHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliveryHandler")
+            .field("calendar", &self.calendar)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DeliveryHandler {
+    /// 创建送货安排查询处理器
+    pub fn new(
+        service: Arc<dyn DeliveryService + Send + Sync>,
+        calendar: BusinessCalendar,
+    ) -> Self {
+        Self { service, calendar }
+    }
+}
+
+#[async_trait]
+impl QueryHandler<DeliveriesThisWeekQuery> for DeliveryHandler {
+    async fn handle(&self, query: DeliveriesThisWeekQuery) -> CoreResult<DeliveryWeek> {
+        let date = query
+            .date
+            .unwrap_or_else(|| self.calendar.local_date(Utc::now()));
+        let orders = self
+            .service
+            .deliveries_between(DeliveryWeek::range(&self.calendar, date))
+            .await?;
+        Ok(DeliveryWeek::group(&self.calendar, date, orders))
+    }
+}
+
+/// 采购订单查询处理器
+pub struct PurchaseOrderHandler {
+    service: Arc<dyn PurchaseOrderService + Send + Sync>,
+}
+
+impl std::fmt::Debug for PurchaseOrderHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PurchaseOrderHandler").finish_non_exhaustive()
+    }
+}
+
+impl PurchaseOrderHandler {
+    /// 创建采购订单查询处理器
+    pub fn new(service: Arc<dyn PurchaseOrderService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl QueryHandler<SupplierPurchaseOrdersQuery> for PurchaseOrderHandler {
+    async fn handle(&self, query: SupplierPurchaseOrdersQuery) -> CoreResult<Vec<PurchaseOrder>> {
+        self.service
+            .purchase_orders_for_supplier(query.supplier_id)
+            .await
+    }
+}
+
+#[async_trait]
+impl QueryHandler<SupplierPayablesQuery> for PurchaseOrderHandler {
+    async fn handle(&self, query: SupplierPayablesQuery) -> CoreResult<SupplierPayables> {
+        self.service.supplier_payables(query.supplier_id).await
+    }
+}
+
+/// 销售漏斗查询处理器
+pub struct PipelineHandler {
+    service: Arc<dyn OpportunityService + Send + Sync>,
+}
+
+impl std::fmt::Debug for PipelineHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PipelineHandler").finish_non_exhaustive()
+    }
+}
+
+impl PipelineHandler {
+    /// 创建销售漏斗查询处理器
+    pub fn new(service: Arc<dyn OpportunityService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl QueryHandler<PipelineQuery> for PipelineHandler {
+    async fn handle(&self, _query: PipelineQuery) -> CoreResult<Pipeline> {
+        self.service.pipeline().await
+    }
+}
+
+/// 客户列表查询处理器
+pub struct CustomerListHandler {
+    read_model: Arc<dyn CustomerListReadModel + Send + Sync>,
+}
+
+impl std::fmt::Debug for CustomerListHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerListHandler").finish_non_exhaustive()
+    }
+}
+
+impl CustomerListHandler {
+    /// 创建客户列表查询处理器
+    pub fn new(read_model: Arc<dyn CustomerListReadModel + Send + Sync>) -> Self {
+        Self { read_model }
+    }
+}
+
+#[async_trait]
+impl QueryHandler<CustomerListQuery> for CustomerListHandler {
+    async fn handle(&self, query: CustomerListQuery) -> CoreResult<PagedResult<CustomerListRow>> {
+        self.read_model
+            .list_customers(&query.filter, &query.projection)
+            .await
+    }
+}
+
+/// 客户时间线查询处理器
+pub struct CustomerTimelineHandler {
+    read_model: Arc<dyn CustomerTimelineReadModel + Send + Sync>,
+}
+
+impl std::fmt::Debug for CustomerTimelineHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerTimelineHandler").finish_non_exhaustive()
+    }
+}
+
+impl CustomerTimelineHandler {
+    /// 创建客户时间线查询处理器
+    pub fn new(read_model: Arc<dyn CustomerTimelineReadModel + Send + Sync>) -> Self {
+        Self { read_model }
+    }
+}
+
+#[async_trait]
+impl QueryHandler<CustomerTimelineQuery> for CustomerTimelineHandler {
+    async fn handle(&self, query: CustomerTimelineQuery) -> CoreResult<TimelinePage> {
+        self.read_model.customer_timeline(&query.query).await
+    }
+}
+
+/// 客户列表导出命令处理器
+pub struct CustomerExportHandler {
+    service: Arc<dyn CustomerExportService + Send + Sync>,
+}
+
+impl std::fmt::Debug for CustomerExportHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerExportHandler").finish_non_exhaustive()
+    }
+}
+
+impl CustomerExportHandler {
+    /// 创建客户列表导出命令处理器
+    pub fn new(service: Arc<dyn CustomerExportService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ExportCustomersCommand> for CustomerExportHandler {
+    async fn handle(&self, command: ExportCustomersCommand) -> CoreResult<u64> {
+        self.service
+            .export_customers(
+                &command.request,
+                &command.out_path,
+                &command.progress,
+                &command.cancel,
+            )
+            .await
+    }
+}
+
+/// 全局搜索查询处理器
+pub struct GlobalSearchHandler {
+    source: Arc<dyn GlobalSearchSource + Send + Sync>,
+    ranking: SearchRanking,
+}
+
+impl std::fmt::Debug for GlobalSearchHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlobalSearchHandler")
+            .field("ranking", &self.ranking)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GlobalSearchHandler {
+    /// 创建全局搜索查询处理器（使用默认排序权重）
+    pub fn new(source: Arc<dyn GlobalSearchSource + Send + Sync>) -> Self {
+        Self {
+            source,
+            ranking: SearchRanking::default(),
+        }
+    }
+
+    /// 设置排序权重
+    pub fn with_ranking(mut self, ranking: SearchRanking) -> Self {
+        self.ranking = ranking;
+        self
+    }
+}
+
+#[async_trait]
+impl QueryHandler<GlobalSearchQuery> for GlobalSearchHandler {
+    async fn handle(&self, query: GlobalSearchQuery) -> CoreResult<Vec<SearchHit>> {
+        let text = query.text.trim();
+        if text.is_empty() || query.limit == 0 {
+            return Ok(Vec::new());
+        }
+        let candidates = self.source.search_candidates(text, query.limit).await?;
+        let limit = usize::try_from(query.limit).unwrap_or(usize::MAX);
+        Ok(self
+            .ranking
+            .rank(text, candidates, limit, query.debug_scores))
+    }
+}
+
+/// 可能重复客户查询每次粗筛的候选数
+const DUPLICATE_CANDIDATE_LIMIT: u32 = 20;
+
+/// 可能重复客户查询处理器
+pub struct DuplicateCheckHandler {
+    source: Arc<dyn DuplicateCandidateSource + Send + Sync>,
+}
+
+impl std::fmt::Debug for DuplicateCheckHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DuplicateCheckHandler").finish_non_exhaustive()
+    }
+}
+
+impl DuplicateCheckHandler {
+    /// 创建可能重复客户查询处理器
+    pub fn new(source: Arc<dyn DuplicateCandidateSource + Send + Sync>) -> Self {
+        Self { source }
+    }
+}
+
+#[async_trait]
+impl QueryHandler<CheckPossibleDuplicateQuery> for DuplicateCheckHandler {
+    async fn handle(
+        &self,
+        query: CheckPossibleDuplicateQuery,
+    ) -> CoreResult<Vec<PossibleDuplicate>> {
+        let probe = name_probe(&query.name);
+        let phone = phone_key(&query.phone);
+        if probe.is_none() && phone.is_none() {
+            return Ok(Vec::new());
+        }
+        // 多取几条，排除自身和去掉粗筛误命中后仍能凑满
+        let candidates = self
+            .source
+            .duplicate_candidates(
+                probe.as_deref(),
+                phone.as_deref(),
+                DUPLICATE_CANDIDATE_LIMIT,
+            )
+            .await?;
+        Ok(classify_duplicates(
+            &query.name,
+            &query.phone,
+            query.exclude_id,
+            candidates,
+            MAX_DUPLICATE_SUGGESTIONS,
+        ))
+    }
+}
+
+/// 数据归档命令处理器
+pub struct ArchiveHandler {
+    service: Arc<dyn DataArchiveService + Send + Sync>,
+}
+
+impl std::fmt::Debug for ArchiveHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveHandler").finish_non_exhaustive()
+    }
+}
+
+impl ArchiveHandler {
+    /// 创建数据归档命令处理器
+    pub fn new(service: Arc<dyn DataArchiveService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ExportArchiveCommand> for ArchiveHandler {
+    async fn handle(&self, command: ExportArchiveCommand) -> CoreResult<ArchiveManifest> {
+        self.service
+            .export_archive(&command.out_path, &command.cancel)
+            .await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ImportArchiveCommand> for ArchiveHandler {
+    async fn handle(&self, command: ImportArchiveCommand) -> CoreResult<ArchiveManifest> {
+        self.service
+            .import_archive(&command.path, &command.cancel)
+            .await
+    }
+}
+
+/// 差异同步命令处理器
+pub struct SyncHandler {
+    service: Arc<dyn DataSyncService + Send + Sync>,
+}
+
+impl std::fmt::Debug for SyncHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SyncHandler").finish_non_exhaustive()
+    }
+}
+
+impl SyncHandler {
+    /// 创建差异同步命令处理器
+    pub fn new(service: Arc<dyn DataSyncService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ExportChangesCommand> for SyncHandler {
+    async fn handle(&self, command: ExportChangesCommand) -> CoreResult<ChangesetSummary> {
+        if command.since > Utc::now() {
+            return Err(CoreError::validation("变更起始时间不能晚于当前时间"));
+        }
+        self.service
+            .export_changes(command.since, &command.out_path)
+            .await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ApplyChangesCommand> for SyncHandler {
+    async fn handle(&self, command: ApplyChangesCommand) -> CoreResult<ChangesetSummary> {
+        self.service.apply_changes(&command.path).await
+    }
+}
+
+/// 信用额度检查处理器
+///
+/// 接受报价和确认订单前检查客户信用；管理员勾选越过额度时，操作成功后写入审计记录。
+pub struct CreditHandlers {
+    credit: Arc<dyn CreditCheckService + Send + Sync>,
+    quotes: Arc<dyn QuoteService + Send + Sync>,
+    deliveries: Option<Arc<dyn DeliveryService + Send + Sync>>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for CreditHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CreditHandlers").finish_non_exhaustive()
+    }
+}
+
+impl CreditHandlers {
+    /// 创建信用额度检查处理器（未启用订单模块时不能确认订单）
+    pub fn new(
+        credit: Arc<dyn CreditCheckService + Send + Sync>,
+        quotes: Arc<dyn QuoteService + Send + Sync>,
+        deliveries: Option<Arc<dyn DeliveryService + Send + Sync>>,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            credit,
+            quotes,
+            deliveries,
+            current_user,
+        }
+    }
+
+    /// 检查客户能否再增加一笔单据，返回需要在操作成功后写入的越权记录
+    ///
+    /// 不限额的客户不折算金额，只检查冻结；`override_credit` 仅对管理员生效。
+    async fn admit(
+        &self,
+        customer_id: Uuid,
+        amount: Money,
+        currency: Currency,
+        created_at: DateTime<Utc>,
+        subject: String,
+        override_credit: bool,
+    ) -> CoreResult<Option<CreditOverride>> {
+        let profile = self.credit.credit_profile(customer_id).await?;
+        if profile.credit_limit.is_none() {
+            profile.check(Money::ZERO)?;
+            return Ok(None);
+        }
+        let date = created_at.date_naive();
+        let amount = self
+            .credit
+            .base_amount(amount, currency, date)
+            .await?
+            .ok_or_else(|| {
+                CoreError::business(format!(
+                    "缺少 {} 在 {} 的汇率，无法检查信用额度，请先补录汇率",
+                    currency, date
+                ))
+            })?;
+        let approver = self
+            .current_user
+            .get()
+            .filter(|user| override_credit && user.role.satisfies(UserRole::Admin));
+        match approver {
+            Some(user) => profile.override_credit(amount, &user.username, &subject),
+            None => profile.check(amount).map(|()| None),
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<AcceptQuoteCommand> for CreditHandlers {
+    async fn handle(&self, command: AcceptQuoteCommand) -> CoreResult<Quote> {
+        let quote = self
+            .quotes
+            .get_quote_by_id(command.quote_id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("报价 {}", command.quote_id)))?;
+        let entry = self
+            .admit(
+                quote.customer_id,
+                quote.total_amount,
+                quote.currency,
+                quote.created_at,
+                format!("报价 {}", quote.quote_number),
+                command.override_credit,
+            )
+            .await?;
+        let accepted = self
+            .quotes
+            .update_quote_status(quote.id, QuoteStatus::Accepted)
+            .await?;
+        if let Some(entry) = entry {
+            self.credit.record_override(&entry).await?;
+        }
+        Ok(accepted)
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ConfirmOrderCommand> for CreditHandlers {
+    async fn handle(&self, command: ConfirmOrderCommand) -> CoreResult<Order> {
+        let deliveries = self
+            .deliveries
+            .as_ref()
+            .ok_or_else(|| CoreError::business("未启用订单模块"))?;
+        let mut order = command.order;
+        let username = self.current_user.username();
+        order.created_by = username.clone();
+        order.updated_by = username;
+        let entry = self
+            .admit(
+                order.customer_id,
+                order.total_amount,
+                order.currency,
+                order.created_at,
+                format!("订单 {}", order.order_number),
+                command.override_credit,
+            )
+            .await?;
+        let confirmed = deliveries.confirm_order(order).await?;
+        if let Some(entry) = entry {
+            self.credit.record_override(&entry).await?;
+        }
+        Ok(confirmed)
+    }
+}
+
+#[async_trait]
+impl CommandHandler<SetCreditTermsCommand> for CreditHandlers {
+    async fn handle(&self, command: SetCreditTermsCommand) -> CoreResult<()> {
+        self.credit
+            .set_credit_terms(
+                command.customer_id,
+                command.credit_limit,
+                command.credit_hold,
+                self.current_user.username().as_deref(),
+            )
+            .await
+    }
+}
+
+/// 诊断包导出命令处理器
+pub struct DiagnosticsBundleHandler {
+    service: Arc<dyn DiagnosticsBundleService + Send + Sync>,
+}
+
+impl std::fmt::Debug for DiagnosticsBundleHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiagnosticsBundleHandler").finish_non_exhaustive()
+    }
+}
+
+impl DiagnosticsBundleHandler {
+    /// 创建诊断包导出命令处理器
+    pub fn new(service: Arc<dyn DiagnosticsBundleService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ExportDiagnosticsBundleCommand> for DiagnosticsBundleHandler {
+    async fn handle(
+        &self,
+        command: ExportDiagnosticsBundleCommand,
+    ) -> CoreResult<DiagnosticsBundleSummary> {
+        self.service
+            .export_bundle(
+                &command.out_path,
+                command.include_db_copy,
+                &command.progress,
+                &command.cancel,
+            )
+            .await
+    }
+}
+
+/// 设置导入导出命令处理器
+pub struct SettingsTransferHandler {
+    service: Arc<dyn SettingsTransferService + Send + Sync>,
+}
+
+impl std::fmt::Debug for SettingsTransferHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SettingsTransferHandler").finish_non_exhaustive()
+    }
+}
+
+impl SettingsTransferHandler {
+    /// 创建设置导入导出命令处理器
+    pub fn new(service: Arc<dyn SettingsTransferService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ExportSettingsCommand> for SettingsTransferHandler {
+    async fn handle(&self, command: ExportSettingsCommand) -> CoreResult<SettingsExportSummary> {
+        self.service.export_settings(&command.out_path).await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ImportSettingsCommand> for SettingsTransferHandler {
+    async fn handle(&self, command: ImportSettingsCommand) -> CoreResult<SettingsImportReport> {
+        if command.sections.is_empty() {
+            return Err(CoreError::validation("请至少选择一项要导入的设置"));
+        }
+        self.service
+            .import_settings(&command.path, &command.sections)
+            .await
+    }
+}
+
+/// 记录归档处理器
+pub struct RecordArchiveHandler {
+    service: Arc<dyn RecordArchiveService + Send + Sync>,
+}
+
+impl std::fmt::Debug for RecordArchiveHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordArchiveHandler").finish_non_exhaustive()
+    }
+}
+
+impl RecordArchiveHandler {
+    /// 创建记录归档处理器
+    pub fn new(service: Arc<dyn RecordArchiveService + Send + Sync>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl QueryHandler<ArchivePreviewQuery> for RecordArchiveHandler {
+    async fn handle(&self, query: ArchivePreviewQuery) -> CoreResult<ArchivePreview> {
+        self.service.preview(&query.policy, query.as_of).await
+    }
+}
+
+#[async_trait]
+impl QueryHandler<ArchiveRunsQuery> for RecordArchiveHandler {
+    async fn handle(&self, query: ArchiveRunsQuery) -> CoreResult<Vec<ArchiveRun>> {
+        self.service.runs(query.limit).await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ArchiveRecordsCommand> for RecordArchiveHandler {
+    async fn handle(&self, command: ArchiveRecordsCommand) -> CoreResult<ArchiveRun> {
+        self.service
+            .archive(&command.policy, command.as_of, ArchiveTrigger::Manual)
+            .await
+    }
+}
+
+/// 回收站处理器
+pub struct TrashHandlers {
+    service: Arc<dyn TrashService + Send + Sync>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for TrashHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrashHandlers").finish_non_exhaustive()
+    }
+}
+
+impl TrashHandlers {
+    /// 创建回收站处理器
+    pub fn new(service: Arc<dyn TrashService + Send + Sync>, current_user: CurrentUser) -> Self {
+        Self {
+            service,
+            current_user,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<SoftDeleteCustomerCommand> for TrashHandlers {
+    async fn handle(&self, command: SoftDeleteCustomerCommand) -> CoreResult<DeletionBatch> {
+        self.service
+            .soft_delete_customer(command.customer_id, self.current_user.username())
+            .await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<RestoreEntityCommand> for TrashHandlers {
+    async fn handle(&self, command: RestoreEntityCommand) -> CoreResult<RestoreReport> {
+        self.service.restore(command.entity, command.id).await
+    }
+}
+
+#[async_trait]
+impl QueryHandler<TrashQuery> for TrashHandlers {
+    async fn handle(&self, _query: TrashQuery) -> CoreResult<TrashContents> {
+        Ok(TrashContents {
+            items: self.service.trash_items().await?,
+            batches: self.service.deletion_batches().await?,
+        })
+    }
+}
+
+/// 应用服务集合，用于注册全部处理器
+#[derive(Clone)]
+pub struct ServiceSet {
+    /// 客户服务
+    pub customers: Arc<dyn CustomerService + Send + Sync>,
+    /// 任务服务
+    pub tasks: Arc<dyn TaskService + Send + Sync>,
+    /// 任务协作服务（为空时不能分配、关注任务）
+    pub task_collaboration: Option<Arc<dyn TaskCollaborationService + Send + Sync>>,
+    /// 报价服务
+    pub quotes: Arc<dyn QuoteService + Send + Sync>,
+    /// 月度统计服务（实时计算）
+    pub statistics: Arc<dyn StatisticsService + Send + Sync>,
+    /// 月结服务（为空时不能月结，所有月份都实时计算）
+    pub closings: Option<Arc<dyn MonthlyClosingService + Send + Sync>>,
+    /// 售后工单服务（未启用售后模块时为空）
+    pub tickets: Option<Arc<dyn ServiceTicketService + Send + Sync>>,
+    /// 售后知识库服务（为空时工单编辑器不推荐文章）
+    pub knowledge_base: Option<Arc<dyn KnowledgeBaseService + Send + Sync>>,
+    /// 报价单校验码编解码器（未配置密钥时为空）
+    pub quote_codec: Option<Arc<dyn QuotePayloadCodec>>,
+    /// 送货服务（未启用订单模块时为空）
+    pub deliveries: Option<Arc<dyn DeliveryService + Send + Sync>>,
+    /// 客户列表读取模型（为空时列表界面使用客户服务的固定列）
+    pub customer_list: Option<Arc<dyn CustomerListReadModel + Send + Sync>>,
+    /// 客户时间线读取模型（为空时客户详情不显示时间线）
+    pub customer_timeline: Option<Arc<dyn CustomerTimelineReadModel + Send + Sync>>,
+    /// 客户列表导出服务（为空时不能导出客户列表）
+    pub customer_export: Option<Arc<dyn CustomerExportService + Send + Sync>>,
+    /// 全局搜索数据源（为空时不能全局搜索）
+    pub global_search: Option<Arc<dyn GlobalSearchSource + Send + Sync>>,
+    /// 重复客户候选数据源（为空时新建客户不提示可能重复）
+    pub duplicate_candidates: Option<Arc<dyn DuplicateCandidateSource + Send + Sync>>,
+    /// 销售机会服务（未启用销售漏斗时为空）
+    pub opportunities: Option<Arc<dyn OpportunityService + Send + Sync>>,
+    /// 数据归档服务（未配置数据目录时为空）
+    pub archive: Option<Arc<dyn DataArchiveService + Send + Sync>>,
+    /// 差异同步服务（为空时不能导出或应用变更包）
+    pub sync: Option<Arc<dyn DataSyncService + Send + Sync>>,
+    /// 客户信用服务（为空时接受报价和确认订单不检查信用额度）
+    pub credit: Option<Arc<dyn CreditCheckService + Send + Sync>>,
+    /// 记录归档服务（为空时不能按保留期限归档）
+    pub record_archive: Option<Arc<dyn RecordArchiveService + Send + Sync>>,
+    /// 字段策略服务（为空时客户字段按默认要求校验，不能配置必填和隐藏）
+    pub field_policies: Option<Arc<dyn FieldPolicyService + Send + Sync>>,
+    /// 客户分类服务（为空时不能单独修改客户等级和标签）
+    pub customer_categories: Option<Arc<dyn CustomerCategoryService + Send + Sync>>,
+    /// 回收站服务（为空时不能软删除和恢复）
+    pub trash: Option<Arc<dyn TrashService + Send + Sync>>,
+    /// 设置导入导出服务（为空时不能在多台电脑间同步设置）
+    pub settings: Option<Arc<dyn SettingsTransferService + Send + Sync>>,
+    /// 诊断包导出服务（为空时不能导出诊断包）
+    pub diagnostics: Option<Arc<dyn DiagnosticsBundleService + Send + Sync>>,
+    /// 幂等记录服务（为空时命令的幂等键被忽略）
+    pub idempotency: Option<Arc<dyn IdempotencyService + Send + Sync>>,
+    /// 报价模板服务（未启用报价模板时为空）
+    pub quote_templates: Option<Arc<dyn QuoteTemplateService + Send + Sync>>,
+    /// 定价服务（未配置产品目录时为空，此时不能从模板新建报价）
+    pub pricing: Option<Arc<dyn PricingService + Send + Sync>>,
+    /// 产品目录服务（为空时不能批量调价）
+    pub products: Option<Arc<dyn ProductService + Send + Sync>>,
+    /// 供应商服务（为空时不能导入供应商）
+    pub suppliers: Option<Arc<dyn SupplierService + Send + Sync>>,
+    /// 采购订单服务（未启用采购模块时为空）
+    pub purchase_orders: Option<Arc<dyn PurchaseOrderService + Send + Sync>>,
+    /// 表格文件读取器（为空时不能从表格导入供应商和产品）
+    pub spreadsheets: Option<Arc<dyn SpreadsheetReader + Send + Sync>>,
+    /// 业务数据一致性检查（为空时不能执行一致性检查）
+    pub consistency_checks: Vec<Arc<dyn ConsistencyCheck + Send + Sync>>,
+    /// 表格导入的表头别名表
+    pub header_aliases: HeaderAliases,
+    /// 报价毛利预警阈值
+    pub margin_thresholds: MarginThresholds,
+    /// 线索服务（为空时不能使用新建线索向导）
+    pub leads: Option<Arc<dyn LeadService + Send + Sync>>,
+    /// 新建线索时首个跟进任务默认在几天后到期
+    pub first_task_due_days: u32,
+    /// 业务日历
+    pub calendar: BusinessCalendar,
+}
+
+impl std::fmt::Debug for ServiceSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServiceSet").finish_non_exhaustive()
+    }
+}
+
+/// 向命令总线和查询总线注册全部处理器
+///
+/// 写入类处理器从 `current_user` 读取用户名填写审计字段；
+/// 统计查询读写 `cache`，调用方负责将其订阅到事件总线。
+pub fn register_handlers(
+    services: &ServiceSet,
+    current_user: &CurrentUser,
+    cache: &Arc<StatisticsCache>,
+    commands: &mut CommandBus,
+    queries: &mut QueryBus,
+) {
+    let mut customers = CustomerHandlers::new(services.customers.clone(), current_user.clone());
+    if let Some(field_policies) = &services.field_policies {
+        customers = customers.with_field_policies(field_policies.clone());
+        commands.register::<SetFieldRequirementCommand>(Arc::new(FieldPolicyHandler::new(
+            field_policies.clone(),
+        )));
+    }
+    let customers = Arc::new(customers);
+    let mut tasks = TaskHandlers::new(services.tasks.clone(), current_user.clone());
+    if let Some(collaboration) = &services.task_collaboration {
+        tasks = tasks.with_collaboration(collaboration.clone());
+    }
+    let tasks = Arc::new(tasks);
+    let statistics: Arc<dyn StatisticsService + Send + Sync> = match &services.closings {
+        Some(closings) => Arc::new(ClosedMonthStatistics::new(
+            services.statistics.clone(),
+            closings.clone(),
+        )),
+        None => services.statistics.clone(),
+    };
+    let dashboard = Arc::new(DashboardHandler::new(
+        services.customers.clone(),
+        services.tasks.clone(),
+        services.quotes.clone(),
+        statistics,
+        cache.clone(),
+    )
+    .with_calendar(services.calendar));
+    let reports = Arc::new(ReportGenerator::new(dashboard.clone()));
+
+    commands.register::<CreateCustomerCommand>(customers.clone());
+    commands.register::<DeleteCustomerCommand>(customers.clone());
+    if let Some(idempotency) = &services.idempotency {
+        commands.set_idempotency_service(idempotency.clone());
+    }
+    commands.register::<UpdateTaskStatusCommand>(tasks.clone());
+    commands.register::<AssignTaskCommand>(tasks.clone());
+    commands.register::<WatchTaskCommand>(tasks.clone());
+    commands.register::<AddTaskNoteCommand>(tasks.clone());
+    commands.register::<DeleteTaskNoteCommand>(tasks.clone());
+    commands.register::<RestoreTaskNoteCommand>(tasks.clone());
+    if let Some(leads) = &services.leads {
+        commands.register::<CreateLeadCommand>(Arc::new(
+            LeadHandlers::new(leads.clone(), current_user.clone())
+                .with_first_task_due_days(services.first_task_due_days)
+                .with_calendar(services.calendar),
+        ));
+    }
+    if let Some(categories) = &services.customer_categories {
+        commands.register::<ChangeCustomerCategoryCommand>(Arc::new(
+            CustomerCategoryHandler::new(
+                services.customers.clone(),
+                categories.clone(),
+                current_user.clone(),
+            ),
+        ));
+    }
+    let quote_editor = Arc::new(QuoteEditorHandlers::new(
+        services.quotes.clone(),
+        services.pricing.clone(),
+        services.margin_thresholds,
+        current_user.clone(),
+    ));
+    commands.register::<SendQuoteCommand>(quote_editor.clone());
+    queries.register::<QuoteEditorQuery>(quote_editor);
+    commands.register::<GenerateMonthlyReportCommand>(reports);
+    if let Some(reader) = &services.spreadsheets {
+        let mut imports = SpreadsheetImportHandlers::new(
+            reader.clone(),
+            services.header_aliases.clone(),
+            current_user.clone(),
+        );
+        if let Some(suppliers) = &services.suppliers {
+            imports = imports.with_suppliers(suppliers.clone());
+        }
+        if let Some(products) = &services.products {
+            imports = imports.with_products(products.clone());
+        }
+        let imports = Arc::new(imports);
+        commands.register::<ImportSuppliersCommand>(imports.clone());
+        commands.register::<ImportProductsCommand>(imports);
+    }
+    if let Some(closings) = &services.closings {
+        let closing = Arc::new(
+            MonthlyClosingHandlers::new(
+                services.statistics.clone(),
+                closings.clone(),
+                current_user.clone(),
+                cache.clone(),
+            )
+            .with_calendar(services.calendar),
+        );
+        commands.register::<CloseMonthCommand>(closing.clone());
+        commands.register::<ReopenMonthCommand>(closing);
+    }
+    if !services.consistency_checks.is_empty() {
+        let checker = services
+            .consistency_checks
+            .iter()
+            .fold(ConsistencyChecker::new(), |checker, check| checker.with(check.clone()));
+        commands.register::<CheckConsistencyCommand>(Arc::new(checker));
+    }
+    if let (Some(tickets), Some(articles)) = (&services.tickets, &services.knowledge_base) {
+        let knowledge = Arc::new(KnowledgeBaseHandlers::new(
+            tickets.clone(),
+            articles.clone(),
+            current_user.clone(),
+        ));
+        commands.register::<ApplyKnowledgeArticleCommand>(knowledge.clone());
+        commands.register::<SaveKnowledgeArticleCommand>(knowledge.clone());
+        queries.register::<TicketSuggestionsQuery>(knowledge);
+    }
+    if let Some(codec) = &services.quote_codec {
+        commands.register::<VerifyQuoteCommand>(Arc::new(VerifyQuoteHandler::new(
+            services.quotes.clone(),
+            codec.clone(),
+        )));
+    }
+    if let (Some(templates), Some(pricing)) = (&services.quote_templates, &services.pricing) {
+        commands.register::<CreateQuoteFromTemplateCommand>(Arc::new(QuoteTemplateHandler::new(
+            templates.clone(),
+            pricing.clone(),
+            services.quotes.clone(),
+            current_user.clone(),
+        )));
+    }
+
+    if let Some(pricing) = &services.pricing {
+        commands.register::<RepriceQuoteCommand>(Arc::new(RepriceQuoteHandler::new(
+            pricing.clone(),
+            services.quotes.clone(),
+            current_user.clone(),
+        )));
+    }
+    if let Some(products) = &services.products {
+        let handler = Arc::new(ProductPriceHandlers::new(
+            products.clone(),
+            current_user.clone(),
+        ));
+        commands.register::<CreateProductCommand>(handler.clone());
+        commands.register::<AdjustCategoryPricesCommand>(handler.clone());
+        queries.register::<PriceAdjustmentPreviewQuery>(handler);
+    }
+
+    queries.register::<ListCustomersQuery>(customers.clone());
+    queries.register::<CustomerDeletionImpactQuery>(customers.clone());
+    queries.register::<GetCustomerQuery>(customers);
+    queries.register::<ListTasksQuery>(tasks);
+    queries.register::<DashboardStatsQuery>(dashboard);
+    if let Some(deliveries) = &services.deliveries {
+        queries.register::<DeliveriesThisWeekQuery>(Arc::new(DeliveryHandler::new(
+            deliveries.clone(),
+            services.calendar,
+        )));
+    }
+    if let Some(archive) = &services.archive {
+        let handler = Arc::new(ArchiveHandler::new(archive.clone()));
+        commands.register::<ExportArchiveCommand>(handler.clone());
+        commands.register::<ImportArchiveCommand>(handler);
+    }
+    if let Some(sync) = &services.sync {
+        let handler = Arc::new(SyncHandler::new(sync.clone()));
+        commands.register::<ExportChangesCommand>(handler.clone());
+        commands.register::<ApplyChangesCommand>(handler);
+    }
+    if let Some(credit) = &services.credit {
+        let handler = Arc::new(CreditHandlers::new(
+            credit.clone(),
+            services.quotes.clone(),
+            services.deliveries.clone(),
+            current_user.clone(),
+        ));
+        commands.register::<AcceptQuoteCommand>(handler.clone());
+        commands.register::<ConfirmOrderCommand>(handler.clone());
+        commands.register::<SetCreditTermsCommand>(handler);
+    }
+    if let Some(record_archive) = &services.record_archive {
+        let handler = Arc::new(RecordArchiveHandler::new(record_archive.clone()));
+        commands.register::<ArchiveRecordsCommand>(handler.clone());
+        queries.register::<ArchivePreviewQuery>(handler.clone());
+        queries.register::<ArchiveRunsQuery>(handler);
+    }
+    if let Some(trash) = &services.trash {
+        let handler = Arc::new(TrashHandlers::new(trash.clone(), current_user.clone()));
+        commands.register::<SoftDeleteCustomerCommand>(handler.clone());
+        commands.register::<RestoreEntityCommand>(handler.clone());
+        queries.register::<TrashQuery>(handler);
+    }
+    if let Some(settings) = &services.settings {
+        let handler = Arc::new(SettingsTransferHandler::new(settings.clone()));
+        commands.register::<ExportSettingsCommand>(handler.clone());
+        commands.register::<ImportSettingsCommand>(handler);
+    }
+    if let Some(diagnostics) = &services.diagnostics {
+        commands.register::<ExportDiagnosticsBundleCommand>(Arc::new(
+            DiagnosticsBundleHandler::new(diagnostics.clone()),
+        ));
+    }
+    if let Some(read_model) = &services.customer_list {
+        queries.register::<CustomerListQuery>(Arc::new(CustomerListHandler::new(
+            read_model.clone(),
+        )));
+    }
+    if let Some(read_model) = &services.customer_timeline {
+        queries.register::<CustomerTimelineQuery>(Arc::new(CustomerTimelineHandler::new(
+            read_model.clone(),
+        )));
+    }
+    if let Some(exporter) = &services.customer_export {
+        commands.register::<ExportCustomersCommand>(Arc::new(CustomerExportHandler::new(
+            exporter.clone(),
+        )));
+    }
+    if let Some(source) = &services.global_search {
+        queries.register::<GlobalSearchQuery>(Arc::new(GlobalSearchHandler::new(source.clone())));
+    }
+    if let Some(source) = &services.duplicate_candidates {
+        queries.register::<CheckPossibleDuplicateQuery>(Arc::new(DuplicateCheckHandler::new(
+            source.clone(),
+        )));
+    }
+    if let Some(purchase_orders) = &services.purchase_orders {
+        let handler = Arc::new(PurchaseOrderHandler::new(purchase_orders.clone()));
+        queries.register::<SupplierPurchaseOrdersQuery>(handler.clone());
+        queries.register::<SupplierPayablesQuery>(handler);
+    }
+    if let Some(opportunities) = &services.opportunities {
+        queries.register::<PipelineQuery>(Arc::new(PipelineHandler::new(opportunities.clone())));
+    }
+}
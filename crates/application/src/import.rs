@@ -0,0 +1,718 @@
+//! 表格导入
+//!
+//! 供应商和产品价目表按两步导入：第一次提交只读取工作表并识别表头，界面展示识别出的
+//! [`DetectedMapping`] 供用户确认或调整；第二次带上映射提交时逐行导入。单行数据有误
+//! 只记入 [`ImportReport::errors`]，不影响其他行。按批处理，批次之间检查取消；已导入的
+//! 批次在取消后保留。
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use minicrm_core::{
+    CancellationToken, CoreError, CoreResult, DetectedMapping, HeaderAliases, ImportField,
+    ImportReport, ImportRowError, ImportTarget, Money, Pagination, Product, ProductService,
+    QueryFilter, SpreadsheetReader, Supplier, SupplierLevel, SupplierService,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::commands::{CommandHandler, ImportProductsCommand, ImportSuppliersCommand};
+use crate::session::CurrentUser;
+
+/// 每批导入的行数
+pub const IMPORT_BATCH_SIZE: usize = 200;
+
+/// 导入命令的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SpreadsheetImport {
+    /// 识别出的表头映射，待确认
+    Detected(DetectedMapping),
+    /// 导入完成
+    Imported(ImportReport),
+}
+
+/// 单行的导入结果
+enum RowOutcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// 表格导入处理器
+pub struct SpreadsheetImportHandlers {
+    reader: Arc<dyn SpreadsheetReader + Send + Sync>,
+    suppliers: Option<Arc<dyn SupplierService + Send + Sync>>,
+    products: Option<Arc<dyn ProductService + Send + Sync>>,
+    aliases: HeaderAliases,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for SpreadsheetImportHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpreadsheetImportHandlers")
+            .field("aliases", &self.aliases)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SpreadsheetImportHandlers {
+    /// 创建处理器
+    pub fn new(
+        reader: Arc<dyn SpreadsheetReader + Send + Sync>,
+        aliases: HeaderAliases,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            reader,
+            suppliers: None,
+            products: None,
+            aliases,
+            current_user,
+        }
+    }
+
+    /// 启用供应商导入
+    pub fn with_suppliers(mut self, suppliers: Arc<dyn SupplierService + Send + Sync>) -> Self {
+        self.suppliers = Some(suppliers);
+        self
+    }
+
+    /// 启用产品导入
+    pub fn with_products(mut self, products: Arc<dyn ProductService + Send + Sync>) -> Self {
+        self.products = Some(products);
+        self
+    }
+
+    /// 读取工作表并识别表头
+    ///
+    /// # Errors
+    ///
+    /// 工作表不存在时返回 [`CoreError::NotFound`]，前几行中找不到表头时返回验证错误。
+    pub fn detect(
+        &self,
+        target: ImportTarget,
+        path: &Path,
+        sheet: Option<String>,
+    ) -> CoreResult<DetectedMapping> {
+        let sheets = self.reader.sheet_names(path)?;
+        let sheet = match sheet {
+            Some(sheet) => sheet,
+            None => sheets
+                .first()
+                .cloned()
+                .ok_or_else(|| CoreError::validation("表格文件中没有工作表"))?,
+        };
+        let rows = self.reader.read_sheet(path, &sheet)?;
+        let (header_row, columns) = self.aliases.detect(target, &rows).ok_or_else(|| {
+            CoreError::validation(format!("工作表“{}”中未找到可识别的表头行", sheet))
+        })?;
+        Ok(DetectedMapping {
+            target,
+            sheet,
+            sheets,
+            header_row,
+            columns,
+        })
+    }
+
+    /// 检查确认后的映射并读取工作表
+    fn confirmed_rows(
+        &self,
+        target: ImportTarget,
+        path: &Path,
+        mapping: &DetectedMapping,
+    ) -> CoreResult<Vec<Vec<String>>> {
+        if mapping.target != target {
+            return Err(CoreError::validation("表头映射与导入对象不一致"));
+        }
+        let missing = mapping.missing_required();
+        if !missing.is_empty() {
+            let labels: Vec<_> = missing.iter().map(ImportField::label).collect();
+            return Err(CoreError::validation(format!(
+                "请为以下字段选择列: {}",
+                labels.join("、")
+            )));
+        }
+        self.reader.read_sheet(path, &mapping.sheet)
+    }
+
+    async fn import_suppliers(
+        suppliers: &(dyn SupplierService + Send + Sync),
+        mapping: &DetectedMapping,
+        rows: &[Vec<String>],
+        cancel: &CancellationToken,
+    ) -> CoreResult<ImportReport> {
+        let mut report = ImportReport::default();
+        for (line, row) in data_rows(mapping, rows, cancel)? {
+            if line % IMPORT_BATCH_SIZE == 0 {
+                cancel.check()?;
+                debug!("供应商导入进行到第 {} 行", line);
+            }
+            let outcome = Self::import_supplier(suppliers, mapping, row).await;
+            record(&mut report, line, outcome);
+        }
+        info!(
+            "供应商导入完成: 新建 {}，更新 {}，失败 {}",
+            report.created,
+            report.updated,
+            report.errors.len()
+        );
+        Ok(report)
+    }
+
+    async fn import_supplier(
+        suppliers: &(dyn SupplierService + Send + Sync),
+        mapping: &DetectedMapping,
+        row: &[String],
+    ) -> CoreResult<Option<RowOutcome>> {
+        let value = |field| text(mapping, row, field);
+        let name = value(ImportField::SupplierName);
+        if name.is_none() && mapping_is_blank(mapping, row) {
+            return Ok(None);
+        }
+        let name = name.ok_or_else(|| CoreError::validation("供应商名称为空"))?;
+        let email = value(ImportField::Email);
+        if email.as_deref().is_some_and(|email| !email.contains('@')) {
+            return Err(CoreError::validation("邮箱格式不正确"));
+        }
+        let contact_person = value(ImportField::ContactPerson);
+        let phone = value(ImportField::Phone);
+        let address = value(ImportField::Address);
+
+        let existing = suppliers
+            .search_suppliers(&QueryFilter {
+                search: Some(name.clone()),
+                pagination: Pagination::new(1, 50),
+                ..QueryFilter::default()
+            })
+            .await?
+            .items
+            .into_iter()
+            .find(|s| s.name.trim().eq_ignore_ascii_case(&name));
+
+        let now = Utc::now();
+        let Some(mut supplier) = existing else {
+            suppliers
+                .create_supplier(Supplier {
+                    id: Uuid::new_v4(),
+                    name,
+                    contact_person,
+                    phone,
+                    email,
+                    address,
+                    level: SupplierLevel::Normal,
+                    created_at: now,
+                    updated_at: now,
+                })
+                .await?;
+            return Ok(Some(RowOutcome::Created));
+        };
+
+        // 表格中为空的字段保留原值
+        let mut changed = false;
+        for (current, new) in [
+            (&mut supplier.contact_person, contact_person),
+            (&mut supplier.phone, phone),
+            (&mut supplier.email, email),
+            (&mut supplier.address, address),
+        ] {
+            if new.is_some() && *current != new {
+                *current = new;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(Some(RowOutcome::Unchanged));
+        }
+        supplier.updated_at = now;
+        suppliers.update_supplier(supplier).await?;
+        Ok(Some(RowOutcome::Updated))
+    }
+
+    async fn import_products(
+        &self,
+        products: &(dyn ProductService + Send + Sync),
+        mapping: &DetectedMapping,
+        rows: &[Vec<String>],
+        cancel: &CancellationToken,
+    ) -> CoreResult<ImportReport> {
+        let mut known: HashMap<(String, Option<String>), Uuid> = products
+            .list_products(None)
+            .await?
+            .into_iter()
+            .map(|p| ((p.name, p.specification), p.id))
+            .collect();
+        let mut report = ImportReport::default();
+        for (line, row) in data_rows(mapping, rows, cancel)? {
+            if line % IMPORT_BATCH_SIZE == 0 {
+                cancel.check()?;
+                debug!("产品导入进行到第 {} 行", line);
+            }
+            let outcome = self.import_product(products, &mut known, mapping, row).await;
+            record(&mut report, line, outcome);
+        }
+        info!(
+            "产品导入完成: 新建 {}，更新价格 {}，失败 {}",
+            report.created,
+            report.updated,
+            report.errors.len()
+        );
+        Ok(report)
+    }
+
+    async fn import_product(
+        &self,
+        products: &(dyn ProductService + Send + Sync),
+        known: &mut HashMap<(String, Option<String>), Uuid>,
+        mapping: &DetectedMapping,
+        row: &[String],
+    ) -> CoreResult<Option<RowOutcome>> {
+        let value = |field| text(mapping, row, field);
+        let name = value(ImportField::ProductName);
+        if name.is_none() && mapping_is_blank(mapping, row) {
+            return Ok(None);
+        }
+        let name = name.ok_or_else(|| CoreError::validation("品名为空"))?;
+        let raw_price = value(ImportField::UnitPrice).unwrap_or_default();
+        let price = parse_price(&raw_price)
+            .ok_or_else(|| CoreError::validation(format!("单价“{}”无效", raw_price)))?;
+        let specification = match (
+            value(ImportField::Specification),
+            value(ImportField::Thickness),
+        ) {
+            (Some(spec), Some(thickness)) => Some(format!("{} {}", spec, thickness)),
+            (spec, thickness) => spec.or(thickness),
+        };
+
+        let now = Utc::now();
+        let username = self.current_user.username();
+        let key = (name, specification);
+        if let Some(&id) = known.get(&key) {
+            let current = products.price_at(id, now).await?.map(|p| p.price);
+            if current == Some(price) {
+                return Ok(Some(RowOutcome::Unchanged));
+            }
+            products.set_price(id, price, now, username).await?;
+            return Ok(Some(RowOutcome::Updated));
+        }
+
+        let (name, specification) = key;
+        let product = products
+            .create_product(Product {
+                id: Uuid::new_v4(),
+                name,
+                specification,
+                category: value(ImportField::Category),
+                retired: false,
+                cost_price: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .await?;
+        products.set_price(product.id, price, now, username).await?;
+        known.insert((product.name, product.specification), product.id);
+        Ok(Some(RowOutcome::Created))
+    }
+}
+
+/// 表头行之后的数据行及其行号（从1开始）
+fn data_rows<'a>(
+    mapping: &DetectedMapping,
+    rows: &'a [Vec<String>],
+    cancel: &CancellationToken,
+) -> CoreResult<impl Iterator<Item = (usize, &'a [String])>> {
+    cancel.check()?;
+    let start = (mapping.header_row + 1).min(rows.len());
+    Ok(rows[start..]
+        .iter()
+        .enumerate()
+        .map(move |(i, row)| (start + i + 1, row.as_slice())))
+}
+
+/// 字段所在列的文字（去掉首尾空白，空单元格为空）
+fn text(mapping: &DetectedMapping, row: &[String], field: ImportField) -> Option<String> {
+    mapping
+        .column_of(field)
+        .and_then(|column| row.get(column))
+        .map(|cell| cell.trim().to_string())
+        .filter(|cell| !cell.is_empty())
+}
+
+/// 已映射的列是否全为空
+fn mapping_is_blank(mapping: &DetectedMapping, row: &[String]) -> bool {
+    mapping
+        .columns
+        .iter()
+        .filter_map(|c| c.field.map(|field| text(mapping, row, field)))
+        .all(|value| value.is_none())
+}
+
+/// 解析单价，允许货币符号、千分位和“元”
+fn parse_price(text: &str) -> Option<Money> {
+    let cleaned: String = text
+        .chars()
+        .filter(|c| !matches!(c, '¥' | '￥' | ',' | '，' | '元') && !c.is_whitespace())
+        .collect();
+    cleaned
+        .parse::<f64>()
+        .ok()
+        .filter(|price| price.is_finite() && *price > 0.0)
+        .map(Money::from_yuan)
+}
+
+fn record(report: &mut ImportReport, row: usize, outcome: CoreResult<Option<RowOutcome>>) {
+    match outcome {
+        Ok(None) => report.blank_rows += 1,
+        Ok(Some(RowOutcome::Created)) => report.created += 1,
+        Ok(Some(RowOutcome::Updated)) => report.updated += 1,
+        Ok(Some(RowOutcome::Unchanged)) => report.unchanged += 1,
+        Err(e) => report.errors.push(ImportRowError {
+            row,
+            message: match e {
+                CoreError::Validation(message) => message,
+                other => other.to_string(),
+            },
+        }),
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ImportSuppliersCommand> for SpreadsheetImportHandlers {
+    async fn handle(&self, command: ImportSuppliersCommand) -> CoreResult<SpreadsheetImport> {
+        let suppliers = self
+            .suppliers
+            .as_ref()
+            .ok_or_else(|| CoreError::configuration("未启用供应商管理"))?;
+        let Some(mapping) = command.mapping else {
+            return self
+                .detect(ImportTarget::Suppliers, &command.path, command.sheet)
+                .map(SpreadsheetImport::Detected);
+        };
+        let rows = self.confirmed_rows(ImportTarget::Suppliers, &command.path, &mapping)?;
+        Self::import_suppliers(suppliers.as_ref(), &mapping, &rows, &command.cancel)
+            .await
+            .map(SpreadsheetImport::Imported)
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ImportProductsCommand> for SpreadsheetImportHandlers {
+    async fn handle(&self, command: ImportProductsCommand) -> CoreResult<SpreadsheetImport> {
+        let products = self
+            .products
+            .as_ref()
+            .ok_or_else(|| CoreError::configuration("未配置产品目录"))?;
+        let Some(mapping) = command.mapping else {
+            return self
+                .detect(ImportTarget::Products, &command.path, command.sheet)
+                .map(SpreadsheetImport::Detected);
+        };
+        let rows = self.confirmed_rows(ImportTarget::Products, &command.path, &mapping)?;
+        self.import_products(products.as_ref(), &mapping, &rows, &command.cancel)
+            .await
+            .map(SpreadsheetImport::Imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandBus;
+    use chrono::DateTime;
+    use minicrm_core::{PagedResult, ProductPrice, SupplierStatistics};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    /// 内存中的工作簿：（工作表名，行）
+    struct FakeWorkbook(Vec<(String, Vec<Vec<String>>)>);
+
+    impl SpreadsheetReader for FakeWorkbook {
+        fn sheet_names(&self, _path: &Path) -> CoreResult<Vec<String>> {
+            Ok(self.0.iter().map(|(name, _)| name.clone()).collect())
+        }
+
+        fn read_sheet(&self, _path: &Path, sheet: &str) -> CoreResult<Vec<Vec<String>>> {
+            self.0
+                .iter()
+                .find(|(name, _)| name == sheet)
+                .map(|(_, rows)| rows.clone())
+                .ok_or_else(|| CoreError::not_found(sheet.to_string()))
+        }
+    }
+
+    fn sheet(name: &str, data: &[&[&str]]) -> (String, Vec<Vec<String>>) {
+        let rows = data
+            .iter()
+            .map(|row| row.iter().map(ToString::to_string).collect())
+            .collect();
+        (name.to_string(), rows)
+    }
+
+    #[derive(Default)]
+    struct MemoryProducts {
+        products: Mutex<Vec<Product>>,
+        prices: Mutex<Vec<ProductPrice>>,
+    }
+
+    #[async_trait]
+    impl ProductService for MemoryProducts {
+        async fn create_product(&self, product: Product) -> CoreResult<Product> {
+            self.products.lock().unwrap().push(product.clone());
+            Ok(product)
+        }
+
+        async fn get_product(&self, id: Uuid) -> CoreResult<Option<Product>> {
+            Ok(self.products.lock().unwrap().iter().find(|p| p.id == id).cloned())
+        }
+
+        async fn list_products(&self, _category: Option<&str>) -> CoreResult<Vec<Product>> {
+            Ok(self.products.lock().unwrap().clone())
+        }
+
+        async fn set_price(
+            &self,
+            product_id: Uuid,
+            price: Money,
+            effective_from: DateTime<Utc>,
+            created_by: Option<String>,
+        ) -> CoreResult<ProductPrice> {
+            let price = ProductPrice {
+                id: Uuid::new_v4(),
+                product_id,
+                price,
+                cost_price: None,
+                effective_from,
+                created_by,
+                created_at: effective_from,
+            };
+            self.prices.lock().unwrap().push(price.clone());
+            Ok(price)
+        }
+
+        async fn set_cost_price(&self, id: Uuid, _cost: Option<Money>) -> CoreResult<Product> {
+            self.get_product(id)
+                .await?
+                .ok_or_else(|| CoreError::not_found("产品"))
+        }
+
+        async fn price_at(
+            &self,
+            product_id: Uuid,
+            at: DateTime<Utc>,
+        ) -> CoreResult<Option<ProductPrice>> {
+            Ok(self
+                .prices
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|p| p.product_id == product_id && p.effective_from <= at)
+                .max_by_key(|p| p.effective_from)
+                .cloned())
+        }
+
+        async fn price_history(&self, product_id: Uuid) -> CoreResult<Vec<ProductPrice>> {
+            Ok(self
+                .prices
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|p| p.product_id == product_id)
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct MemorySuppliers {
+        suppliers: Mutex<Vec<Supplier>>,
+    }
+
+    #[async_trait]
+    impl SupplierService for MemorySuppliers {
+        async fn create_supplier(&self, supplier: Supplier) -> CoreResult<Supplier> {
+            self.suppliers.lock().unwrap().push(supplier.clone());
+            Ok(supplier)
+        }
+
+        async fn update_supplier(&self, supplier: Supplier) -> CoreResult<Supplier> {
+            let mut suppliers = self.suppliers.lock().unwrap();
+            let existing = suppliers
+                .iter_mut()
+                .find(|s| s.id == supplier.id)
+                .ok_or_else(|| CoreError::not_found("供应商"))?;
+            *existing = supplier.clone();
+            Ok(supplier)
+        }
+
+        async fn get_supplier_by_id(&self, id: Uuid) -> CoreResult<Option<Supplier>> {
+            Ok(self.suppliers.lock().unwrap().iter().find(|s| s.id == id).cloned())
+        }
+
+        async fn delete_supplier(&self, _id: Uuid) -> CoreResult<bool> {
+            Ok(false)
+        }
+
+        async fn search_suppliers(
+            &self,
+            filter: &QueryFilter,
+        ) -> CoreResult<PagedResult<Supplier>> {
+            let keyword = filter.search.clone().unwrap_or_default();
+            let items: Vec<_> = self
+                .suppliers
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|s| s.name.contains(&keyword))
+                .cloned()
+                .collect();
+            let total = items.len() as u64;
+            Ok(PagedResult::new(items, total, &filter.pagination))
+        }
+
+        async fn update_supplier_level(
+            &self,
+            id: Uuid,
+            _level: SupplierLevel,
+        ) -> CoreResult<Supplier> {
+            self.get_supplier_by_id(id)
+                .await?
+                .ok_or_else(|| CoreError::not_found("供应商"))
+        }
+
+        async fn get_supplier_statistics(&self) -> CoreResult<SupplierStatistics> {
+            Ok(SupplierStatistics::default())
+        }
+    }
+
+    fn bus(workbook: FakeWorkbook) -> (CommandBus, Arc<MemoryProducts>, Arc<MemorySuppliers>) {
+        let products = Arc::new(MemoryProducts::default());
+        let suppliers = Arc::new(MemorySuppliers::default());
+        let handlers = Arc::new(
+            SpreadsheetImportHandlers::new(
+                Arc::new(workbook),
+                HeaderAliases::default(),
+                CurrentUser::new(),
+            )
+            .with_products(products.clone())
+            .with_suppliers(suppliers.clone()),
+        );
+        let mut commands = CommandBus::new();
+        commands.register::<ImportProductsCommand>(handlers.clone());
+        commands.register::<ImportSuppliersCommand>(handlers);
+        (commands, products, suppliers)
+    }
+
+    fn products_command(mapping: Option<DetectedMapping>) -> ImportProductsCommand {
+        ImportProductsCommand {
+            path: PathBuf::from("价目表.xlsx"),
+            sheet: Some("板材".to_string()),
+            mapping,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_step_product_import() {
+        let workbook = FakeWorkbook(vec![
+            sheet("说明", &[&["本表仅供参考"]]),
+            sheet(
+                "板材",
+                &[
+                    &["", "华美板材2024年5月报价单", "", "", ""],
+                    &["", "", "", "", ""],
+                    &[],
+                    &["", "单价(元)", "厚度", "品名", "规格"],
+                    &["", "128.5", "18mm", "多层板", "1220×2440"],
+                    &["", "¥96", "9mm", "颗粒板", "1220×2440"],
+                    &["", "", "", "", ""],
+                    &["", "面议", "5mm", "密度板", "1220×2440"],
+                    &["", "110", "18mm", "多层板", "1220×2440"],
+                    &["", "96", "9mm", "颗粒板", "1220×2440"],
+                ],
+            ),
+        ]);
+        let (commands, products, _) = bus(workbook);
+
+        let SpreadsheetImport::Detected(mapping) =
+            commands.dispatch(products_command(None)).await.unwrap()
+        else {
+            panic!("第一次提交应返回识别结果");
+        };
+        assert_eq!(mapping.sheet, "板材");
+        assert_eq!(mapping.sheets, vec!["说明", "板材"]);
+        assert_eq!(mapping.header_row, 3);
+        assert_eq!(mapping.column_of(ImportField::UnitPrice), Some(1));
+        assert_eq!(mapping.column_of(ImportField::ProductName), Some(3));
+        assert!(products.products.lock().unwrap().is_empty());
+
+        let SpreadsheetImport::Imported(report) =
+            commands.dispatch(products_command(Some(mapping))).await.unwrap()
+        else {
+            panic!("带映射提交应执行导入");
+        };
+        // 第二次出现的多层板更新价格，颗粒板价格相同不变
+        assert_eq!(report.created, 2);
+        assert_eq!(report.updated, 1);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.blank_rows, 1);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].row, 8);
+        assert!(report.errors[0].message.contains("面议"));
+
+        let catalog = products.products.lock().unwrap().clone();
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog[0].specification.as_deref(), Some("1220×2440 18mm"));
+    }
+
+    #[tokio::test]
+    async fn test_adjusted_mapping_required_and_supplier_upsert() {
+        let workbook = FakeWorkbook(vec![sheet(
+            "Sheet1",
+            &[
+                &["供应商名录"],
+                &["厂家", "电话", "邮箱", "备注"],
+                &["华美板材", "0571-8888", "sales@huamei.cn", ""],
+                &["华美板材", "0571-9999", "", ""],
+                &["恒通五金", "", "hengtong", ""],
+            ],
+        )]);
+        let (commands, _, suppliers) = bus(workbook);
+        let command = |mapping| ImportSuppliersCommand {
+            path: PathBuf::from("供应商.xlsx"),
+            sheet: None,
+            mapping,
+            cancel: CancellationToken::new(),
+        };
+
+        let SpreadsheetImport::Detected(mut mapping) =
+            commands.dispatch(command(None)).await.unwrap()
+        else {
+            panic!("第一次提交应返回识别结果");
+        };
+        assert_eq!(mapping.header_row, 1);
+
+        // 用户取消了名称列的映射
+        mapping.set_field(0, None);
+        let err = commands.dispatch(command(Some(mapping.clone()))).await.unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+
+        mapping.set_field(0, Some(ImportField::SupplierName));
+        let SpreadsheetImport::Imported(report) =
+            commands.dispatch(command(Some(mapping))).await.unwrap()
+        else {
+            panic!("带映射提交应执行导入");
+        };
+        assert_eq!((report.created, report.updated), (1, 1));
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].row, 5);
+
+        let stored = suppliers.suppliers.lock().unwrap().clone();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].phone.as_deref(), Some("0571-9999"));
+        assert_eq!(stored[0].email.as_deref(), Some("sales@huamei.cn"));
+    }
+}
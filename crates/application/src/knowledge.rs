@@ -0,0 +1,301 @@
+//! 售后知识库
+//!
+//! 工单编辑器按工单的问题分类和描述推荐知识库文章，采用后把文章的处理方法填入工单并
+//! 累计文章的采用次数；关闭工单时可把新的处理方法保存为文章，供以后的同类问题参考。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use minicrm_core::{
+    CoreError, CoreResult, KnowledgeArticle, KnowledgeBaseService, ServiceTicket,
+    ServiceTicketService, ServiceTicketStatus,
+};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::commands::{ApplyKnowledgeArticleCommand, CommandHandler, SaveKnowledgeArticleCommand};
+use crate::queries::{QueryHandler, TicketSuggestionsQuery};
+use crate::session::CurrentUser;
+
+/// 知识库命令和查询处理器
+pub struct KnowledgeBaseHandlers {
+    tickets: Arc<dyn ServiceTicketService + Send + Sync>,
+    articles: Arc<dyn KnowledgeBaseService + Send + Sync>,
+    current_user: CurrentUser,
+}
+
+impl std::fmt::Debug for KnowledgeBaseHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KnowledgeBaseHandlers").finish_non_exhaustive()
+    }
+}
+
+impl KnowledgeBaseHandlers {
+    /// 创建处理器
+    pub fn new(
+        tickets: Arc<dyn ServiceTicketService + Send + Sync>,
+        articles: Arc<dyn KnowledgeBaseService + Send + Sync>,
+        current_user: CurrentUser,
+    ) -> Self {
+        Self {
+            tickets,
+            articles,
+            current_user,
+        }
+    }
+
+    async fn ticket(&self, id: Uuid) -> CoreResult<ServiceTicket> {
+        self.tickets
+            .get_ticket_by_id(id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("工单 {}", id)))
+    }
+}
+
+#[async_trait]
+impl QueryHandler<TicketSuggestionsQuery> for KnowledgeBaseHandlers {
+    async fn handle(&self, query: TicketSuggestionsQuery) -> CoreResult<Vec<KnowledgeArticle>> {
+        let ticket = self.ticket(query.ticket_id).await?;
+        self.articles.suggest_articles(&ticket).await
+    }
+}
+
+#[async_trait]
+impl CommandHandler<ApplyKnowledgeArticleCommand> for KnowledgeBaseHandlers {
+    async fn handle(&self, command: ApplyKnowledgeArticleCommand) -> CoreResult<ServiceTicket> {
+        let ticket = self.ticket(command.ticket_id).await?;
+        if ticket.status == ServiceTicketStatus::Closed {
+            return Err(CoreError::business(format!(
+                "工单 {} 已关闭，不能修改处理方式",
+                ticket.ticket_number
+            )));
+        }
+        let article = self
+            .articles
+            .get_article(command.article_id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("知识库文章 {}", command.article_id)))?;
+
+        let ticket = self
+            .tickets
+            .update_ticket(ServiceTicket {
+                solution_method: Some(article.solution.clone()),
+                ..ticket
+            })
+            .await?;
+        self.articles.record_usage(article.id).await?;
+        info!("工单 {} 采用知识库文章「{}」", ticket.ticket_number, article.title);
+        Ok(ticket)
+    }
+}
+
+#[async_trait]
+impl CommandHandler<SaveKnowledgeArticleCommand> for KnowledgeBaseHandlers {
+    async fn handle(&self, command: SaveKnowledgeArticleCommand) -> CoreResult<KnowledgeArticle> {
+        let article = KnowledgeArticle {
+            created_by: command.article.created_by.or_else(|| self.current_user.username()),
+            ..command.article
+        };
+        self.articles.create_article(article).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandBus;
+    use crate::queries::QueryBus;
+    use crate::session::PermissionGuard;
+    use chrono::Utc;
+    use minicrm_core::{
+        PagedResult, QueryFilter, ServiceTicketStatistics, TaskPriority, User, UserRole,
+    };
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryTickets {
+        tickets: Mutex<HashMap<Uuid, ServiceTicket>>,
+    }
+
+    #[async_trait]
+    impl ServiceTicketService for MemoryTickets {
+        async fn create_ticket(&self, ticket: ServiceTicket) -> CoreResult<ServiceTicket> {
+            self.tickets.lock().unwrap().insert(ticket.id, ticket.clone());
+            Ok(ticket)
+        }
+
+        async fn update_ticket(&self, ticket: ServiceTicket) -> CoreResult<ServiceTicket> {
+            self.create_ticket(ticket).await
+        }
+
+        async fn get_ticket_by_id(&self, id: Uuid) -> CoreResult<Option<ServiceTicket>> {
+            Ok(self.tickets.lock().unwrap().get(&id).cloned())
+        }
+
+        async fn delete_ticket(&self, id: Uuid) -> CoreResult<bool> {
+            Ok(self.tickets.lock().unwrap().remove(&id).is_some())
+        }
+
+        async fn search_tickets(
+            &self,
+            filter: &QueryFilter,
+        ) -> CoreResult<PagedResult<ServiceTicket>> {
+            let items: Vec<_> = self.tickets.lock().unwrap().values().cloned().collect();
+            let total = items.len() as u64;
+            Ok(PagedResult::new(items, total, &filter.pagination))
+        }
+
+        async fn update_ticket_status(
+            &self,
+            id: Uuid,
+            status: ServiceTicketStatus,
+        ) -> CoreResult<ServiceTicket> {
+            let mut tickets = self.tickets.lock().unwrap();
+            let ticket = tickets
+                .get_mut(&id)
+                .ok_or_else(|| CoreError::not_found("工单"))?;
+            ticket.status = status;
+            Ok(ticket.clone())
+        }
+
+        async fn get_ticket_statistics(&self) -> CoreResult<ServiceTicketStatistics> {
+            Ok(ServiceTicketStatistics::default())
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryArticles {
+        articles: Mutex<HashMap<Uuid, KnowledgeArticle>>,
+    }
+
+    #[async_trait]
+    impl KnowledgeBaseService for MemoryArticles {
+        async fn create_article(&self, article: KnowledgeArticle) -> CoreResult<KnowledgeArticle> {
+            self.articles.lock().unwrap().insert(article.id, article.clone());
+            Ok(article)
+        }
+
+        async fn update_article(&self, article: KnowledgeArticle) -> CoreResult<KnowledgeArticle> {
+            self.create_article(article).await
+        }
+
+        async fn get_article(&self, id: Uuid) -> CoreResult<Option<KnowledgeArticle>> {
+            Ok(self.articles.lock().unwrap().get(&id).cloned())
+        }
+
+        async fn delete_article(&self, id: Uuid) -> CoreResult<bool> {
+            Ok(self.articles.lock().unwrap().remove(&id).is_some())
+        }
+
+        async fn list_articles(
+            &self,
+            category: Option<&str>,
+        ) -> CoreResult<Vec<KnowledgeArticle>> {
+            Ok(self
+                .articles
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|a| category.is_none_or(|c| a.problem_category == c))
+                .cloned()
+                .collect())
+        }
+
+        async fn suggest_articles(
+            &self,
+            ticket: &ServiceTicket,
+        ) -> CoreResult<Vec<KnowledgeArticle>> {
+            self.list_articles(Some(&ticket.problem_category)).await
+        }
+
+        async fn record_usage(&self, id: Uuid) -> CoreResult<KnowledgeArticle> {
+            let mut articles = self.articles.lock().unwrap();
+            let article = articles
+                .get_mut(&id)
+                .ok_or_else(|| CoreError::not_found("知识库文章"))?;
+            article.usage_count += 1;
+            Ok(article.clone())
+        }
+    }
+
+    fn ticket(status: ServiceTicketStatus) -> ServiceTicket {
+        ServiceTicket {
+            id: Uuid::new_v4(),
+            ticket_number: "SV20240603001".to_string(),
+            customer_id: Uuid::new_v4(),
+            problem_category: "变形".to_string(),
+            description: "柜门板边缘翘起".to_string(),
+            solution_method: None,
+            status,
+            priority: TaskPriority::Medium,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_copies_solution_and_counts_usage() {
+        let tickets = Arc::new(MemoryTickets::default());
+        let articles = Arc::new(MemoryArticles::default());
+        let session = CurrentUser::new();
+        let handlers = Arc::new(KnowledgeBaseHandlers::new(
+            tickets.clone(),
+            articles.clone(),
+            session.clone(),
+        ));
+        let mut commands = CommandBus::new();
+        commands.add_guard(Arc::new(PermissionGuard::new(session.clone())));
+        commands.register::<ApplyKnowledgeArticleCommand>(handlers.clone());
+        commands.register::<SaveKnowledgeArticleCommand>(handlers.clone());
+        let mut queries = QueryBus::new();
+        queries.register::<TicketSuggestionsQuery>(handlers);
+
+        let open = tickets
+            .create_ticket(ticket(ServiceTicketStatus::InProgress))
+            .await
+            .unwrap();
+        let mut draft = KnowledgeArticle::from_ticket(&open, None);
+        draft.solution = "更换柜门并加装拉直器".to_string();
+        session.sign_in(User {
+            id: Uuid::new_v4(),
+            username: "lisi".to_string(),
+            display_name: "李四".to_string(),
+            role: UserRole::Sales,
+            password_hash: String::new(),
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        let article = commands
+            .dispatch(SaveKnowledgeArticleCommand { article: draft })
+            .await
+            .unwrap();
+        assert_eq!(article.created_by.as_deref(), Some("lisi"));
+
+        let suggested = queries
+            .ask(TicketSuggestionsQuery { ticket_id: open.id })
+            .await
+            .unwrap();
+        assert_eq!(suggested, vec![article.clone()]);
+
+        let apply = |ticket_id| ApplyKnowledgeArticleCommand {
+            ticket_id,
+            article_id: article.id,
+        };
+        let updated = commands.dispatch(apply(open.id)).await.unwrap();
+        assert_eq!(updated.solution_method.as_deref(), Some("更换柜门并加装拉直器"));
+        let stored = articles.get_article(article.id).await.unwrap().unwrap();
+        assert_eq!(stored.usage_count, 1);
+
+        // 已关闭的工单不再修改，也不计入采用次数
+        let closed = tickets
+            .create_ticket(ticket(ServiceTicketStatus::Closed))
+            .await
+            .unwrap();
+        let err = commands.dispatch(apply(closed.id)).await.unwrap_err();
+        assert!(matches!(err, CoreError::Business(_)), "{err}");
+        let stored = articles.get_article(article.id).await.unwrap().unwrap();
+        assert_eq!(stored.usage_count, 1);
+    }
+}
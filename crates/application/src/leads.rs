@@ -0,0 +1,258 @@
+//! 新建线索
+//!
+//! 新客户的建档向导一次提交客户、首要联系人和首个跟进任务，由 [`LeadService`] 在同一事务中
+//! 写入。联系人的姓名和电话同时写入客户的联系人、电话字段；跟进任务关联到新客户，未填写
+//! 截止日期时按配置的天数计算。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use minicrm_core::{
+    BusinessCalendar, Clock, CoreResult, CreatedLead, Customer, CustomerContact, CustomerLevel,
+    LeadService, NewLead, SystemClock, Task, TaskStatus, DEFAULT_FIRST_TASK_DUE_DAYS,
+};
+use uuid::Uuid;
+
+use crate::commands::{CommandHandler, CreateLeadCommand};
+use crate::session::CurrentUser;
+
+/// 非空的可选文本
+fn trimmed(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// 新建线索处理器
+pub struct LeadHandlers {
+    service: Arc<dyn LeadService + Send + Sync>,
+    current_user: CurrentUser,
+    calendar: BusinessCalendar,
+    clock: Arc<dyn Clock>,
+    first_task_due_days: u32,
+}
+
+impl std::fmt::Debug for LeadHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LeadHandlers")
+            .field("first_task_due_days", &self.first_task_due_days)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LeadHandlers {
+    /// 创建处理器（首个跟进任务默认 [`DEFAULT_FIRST_TASK_DUE_DAYS`] 天后到期）
+    pub fn new(service: Arc<dyn LeadService + Send + Sync>, current_user: CurrentUser) -> Self {
+        Self {
+            service,
+            current_user,
+            calendar: BusinessCalendar::default(),
+            clock: Arc::new(SystemClock),
+            first_task_due_days: DEFAULT_FIRST_TASK_DUE_DAYS,
+        }
+    }
+
+    /// 设置未填写截止日期时，首个跟进任务在几天后到期
+    pub fn with_first_task_due_days(mut self, days: u32) -> Self {
+        self.first_task_due_days = days;
+        self
+    }
+
+    /// 设置业务日历，截止日期按其本地日期计算
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 设置时钟（测试使用）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 由命令生成要写入的客户、联系人和任务
+    fn new_lead(&self, command: CreateLeadCommand) -> NewLead {
+        let now = self.clock.now();
+        let username = self.current_user.username();
+        let owner_id = self.current_user.user_id();
+        let customer_id = Uuid::new_v4();
+        let contact = CustomerContact {
+            id: Uuid::new_v4(),
+            customer_id,
+            name: command.contact_name.trim().to_string(),
+            phone: trimmed(command.contact_phone),
+            email: trimmed(command.contact_email),
+            is_primary: true,
+            created_at: now,
+        };
+        let customer = Customer {
+            id: customer_id,
+            name: command.name.trim().to_string(),
+            contact_person: Some(contact.name.clone()),
+            contact_birthday: None,
+            phone: contact.phone.clone(),
+            email: contact.email.clone(),
+            address: trimmed(command.address),
+            level: CustomerLevel::Normal,
+            credit_limit: None,
+            credit_hold: false,
+            owner_id,
+            created_at: now,
+            updated_at: now,
+            created_by: username.clone(),
+            updated_by: username.clone(),
+        };
+        let first_task = command.first_task.map(|task| {
+            let due_on = task.due_on.unwrap_or_else(|| {
+                self.calendar
+                    .local_date(now)
+                    .add_days(i64::from(self.first_task_due_days))
+            });
+            Task {
+                id: Uuid::new_v4(),
+                title: task.title.trim().to_string(),
+                description: trimmed(task.description),
+                status: TaskStatus::Pending,
+                priority: task.priority,
+                customer_id: Some(customer_id),
+                supplier_id: None,
+                due_date: Some(self.calendar.start_of_day(due_on)),
+                created_at: now,
+                updated_at: now,
+                created_by: username.clone(),
+                updated_by: username,
+                assigned_to: owner_id,
+            }
+        });
+        NewLead {
+            customer,
+            contact,
+            first_task,
+        }
+    }
+}
+
+#[async_trait]
+impl CommandHandler<CreateLeadCommand> for LeadHandlers {
+    async fn handle(&self, command: CreateLeadCommand) -> CoreResult<CreatedLead> {
+        command.validate()?;
+        let lead = self.new_lead(command);
+        self.service.create_lead(lead).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{CommandBus, LeadTask};
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{CoreError, LocalDate, ManualClock, TaskPriority};
+    use std::sync::Mutex;
+
+    /// 模拟线索服务：保存收到的线索
+    #[derive(Default)]
+    struct MemoryLeads {
+        leads: Mutex<Vec<NewLead>>,
+    }
+
+    #[async_trait]
+    impl LeadService for MemoryLeads {
+        async fn create_lead(&self, lead: NewLead) -> CoreResult<CreatedLead> {
+            let created = CreatedLead {
+                customer_id: lead.customer.id,
+                contact_id: lead.contact.id,
+                task_id: lead.first_task.as_ref().map(|task| task.id),
+            };
+            self.leads.lock().unwrap().push(lead);
+            Ok(created)
+        }
+    }
+
+    fn command(first_task: Option<LeadTask>) -> CreateLeadCommand {
+        CreateLeadCommand {
+            name: " 华东建材 ".to_string(),
+            address: None,
+            contact_name: "王经理".to_string(),
+            contact_phone: Some("13800138000".to_string()),
+            contact_email: None,
+            first_task,
+            idempotency_key: None,
+        }
+    }
+
+    fn follow_up(due_on: Option<LocalDate>) -> LeadTask {
+        LeadTask {
+            title: "电话回访".to_string(),
+            description: None,
+            priority: TaskPriority::High,
+            due_on,
+        }
+    }
+
+    fn bus(leads: Arc<MemoryLeads>) -> CommandBus {
+        // 2024-06-03 10:00（北京时间）
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 6, 3, 2, 0, 0).unwrap());
+        let handlers = LeadHandlers::new(leads, CurrentUser::new())
+            .with_first_task_due_days(2)
+            .with_clock(Arc::new(clock));
+        let mut bus = CommandBus::new();
+        bus.register::<CreateLeadCommand>(Arc::new(handlers));
+        bus
+    }
+
+    #[tokio::test]
+    async fn test_default_due_date_and_created_ids() {
+        let leads = Arc::new(MemoryLeads::default());
+        let bus = bus(leads.clone());
+
+        let created = bus.dispatch(command(Some(follow_up(None)))).await.unwrap();
+        let stored = leads.leads.lock().unwrap().pop().unwrap();
+        let task = stored.first_task.unwrap();
+        assert_eq!(created.customer_id, stored.customer.id);
+        assert_eq!(created.contact_id, stored.contact.id);
+        assert_eq!(created.task_id, Some(task.id));
+        assert_eq!(stored.customer.name, "华东建材");
+        assert_eq!(stored.customer.contact_person.as_deref(), Some("王经理"));
+        assert!(stored.contact.is_primary);
+        assert_eq!(stored.contact.customer_id, created.customer_id);
+        assert_eq!(task.customer_id, Some(created.customer_id));
+        let calendar = BusinessCalendar::default();
+        assert_eq!(
+            calendar.local_date(task.due_date.unwrap()),
+            LocalDate::new(2024, 6, 5).unwrap()
+        );
+
+        // 填写了截止日期时不使用默认值；不填写任务时只建客户和联系人
+        let due_on = LocalDate::new(2024, 6, 20).unwrap();
+        bus.dispatch(command(Some(follow_up(Some(due_on)))))
+            .await
+            .unwrap();
+        let task = leads
+            .leads
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap()
+            .first_task
+            .unwrap();
+        assert_eq!(calendar.local_date(task.due_date.unwrap()), due_on);
+        let created = bus.dispatch(command(None)).await.unwrap();
+        assert_eq!(created.task_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_task_creates_nothing() {
+        let leads = Arc::new(MemoryLeads::default());
+        let bus = bus(leads.clone());
+        let mut task = follow_up(None);
+        task.title = "  ".to_string();
+
+        let err = bus.dispatch(command(Some(task))).await.unwrap_err();
+        let CoreError::InvalidFields(errors) = err else {
+            panic!("应返回字段错误: {err}");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["task_title"]);
+        assert!(leads.leads.lock().unwrap().is_empty());
+    }
+}
@@ -5,8 +5,59 @@
 #![deny(unsafe_code)]
 #![warn(missing_docs)]
 
+pub mod actions;
+pub mod cache;
+pub mod closing;
 pub mod commands;
+pub mod consistency;
+pub mod currency;
+pub mod digest;
+pub mod event_bus;
+pub mod external;
 pub mod handlers;
+pub mod import;
+pub mod knowledge;
+pub mod leads;
+pub mod lock;
+pub mod pricing;
 pub mod queries;
+pub mod recategorization;
+pub mod reports;
+pub mod scheduler;
+pub mod session;
 
 // 重新导出主要类型
+pub use cache::{StatKey, StatKind, StatisticsCache};
+pub use closing::{ClosedMonthStatistics, MonthlyClosingHandlers, MonthlyClosingJob};
+pub use commands::{Cancellable, Command, CommandBus, CommandGuard, CommandHandler, Idempotent};
+pub use consistency::{
+    consistency_summary, ConsistencyCheckJob, ConsistencyChecker, DEFAULT_VIOLATION_LIMIT,
+};
+pub use currency::{convert_to_base, BaseCurrencyTotal};
+pub use digest::{DigestData, DigestSource, ServiceDigestSource, WeeklyDigest, WeeklyDigestJob};
+pub use event_bus::EventBus;
+pub use external::{
+    BreakerState, ExternalCall, GuardedMailService, GuardedWebhookTransport, OperationStatus,
+    RetryPolicy,
+};
+pub use handlers::{register_handlers, ServiceSet};
+pub use import::{SpreadsheetImport, SpreadsheetImportHandlers};
+pub use knowledge::KnowledgeBaseHandlers;
+pub use leads::LeadHandlers;
+pub use lock::{AppLock, UnlockOutcome};
+pub use pricing::{
+    plan_price_adjustment, quote_margin, reprice_quote, LineMargin, MarginThresholds, PriceChange,
+    QuoteEditorData, QuoteMargin,
+};
+pub use queries::{
+    DeliveriesThisWeekQuery, DeliveryDay, DeliveryWeek, ListQueryHandler, PageSource,
+    PipelineQuery, Query, QueryBus, QueryHandler, TotalCount, TotalCountStrategy, TrashContents,
+    TrashQuery,
+};
+pub use recategorization::{
+    ApplyScope, EvaluationSnapshot, LevelThresholds, RecategorizationPreview,
+    RecategorizationReport, RecategorizationRules, RecategorizationWizard, TagCondition, TagRule,
+};
+pub use reports::{MonthlyReportJob, Report, ReportFormat, ReportGenerator, ReportSection};
+pub use scheduler::{JobRunOutcome, JobScheduler};
+pub use session::{CurrentUser, PermissionGuard};
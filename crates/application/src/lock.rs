@@ -0,0 +1,317 @@
+//! 应用锁模块
+//!
+//! 多人共用一台电脑时，离开前锁定应用；空闲超时后自动锁定。
+//! 锁定期间命令总线拒绝所有命令，解锁失败过多时按指数退避限制重试。
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Duration, Utc};
+use minicrm_core::{Clock, CoreError, CoreResult, PasscodeVerifier};
+use tracing::{info, warn};
+
+use crate::commands::{CommandGuard, CommandInfo};
+
+/// 开始限制重试前允许的失败次数
+pub const FREE_ATTEMPTS: u32 = 5;
+/// 重试等待时间上限（秒）
+pub const MAX_RETRY_DELAY_SECS: i64 = 300;
+
+/// 解锁结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnlockOutcome {
+    /// 已解锁
+    Unlocked,
+    /// 口令错误
+    WrongPasscode {
+        /// 下次允许尝试的时间（未触发限制时为空）
+        retry_after: Option<DateTime<Utc>>,
+    },
+    /// 尝试过于频繁
+    RateLimited {
+        /// 下次允许尝试的时间
+        retry_after: DateTime<Utc>,
+    },
+}
+
+#[derive(Debug)]
+struct LockState {
+    locked: bool,
+    last_activity: DateTime<Utc>,
+    failed_attempts: u32,
+    retry_after: Option<DateTime<Utc>>,
+}
+
+/// 应用锁
+pub struct AppLock {
+    verifier: Arc<dyn PasscodeVerifier>,
+    clock: Arc<dyn Clock>,
+    idle_timeout: Option<Duration>,
+    state: Mutex<LockState>,
+}
+
+impl std::fmt::Debug for AppLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AppLock")
+            .field("idle_timeout", &self.idle_timeout)
+            .field("locked", &self.is_locked())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AppLock {
+    /// 创建应用锁
+    ///
+    /// 已设置口令时启动即处于锁定状态。
+    pub fn new(
+        verifier: Arc<dyn PasscodeVerifier>,
+        clock: Arc<dyn Clock>,
+        idle_timeout: Option<Duration>,
+    ) -> Self {
+        let state = LockState {
+            locked: verifier.is_configured(),
+            last_activity: clock.now(),
+            failed_attempts: 0,
+            retry_after: None,
+        };
+        Self {
+            verifier,
+            clock,
+            idle_timeout,
+            state: Mutex::new(state),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, LockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// 是否处于锁定状态
+    pub fn is_locked(&self) -> bool {
+        self.state().locked
+    }
+
+    /// 立即锁定（未设置口令时忽略）
+    pub fn lock(&self) {
+        if self.verifier.is_configured() {
+            self.state().locked = true;
+            info!("应用已锁定");
+        }
+    }
+
+    /// 记录用户操作
+    pub fn record_activity(&self) {
+        let now = self.clock.now();
+        let mut state = self.state();
+        if !state.locked {
+            state.last_activity = now;
+        }
+    }
+
+    /// 检查空闲超时，超时则锁定
+    ///
+    /// 返回检查后是否处于锁定状态。
+    pub fn check_idle(&self) -> bool {
+        let now = self.clock.now();
+        let mut state = self.state();
+        if !state.locked && self.verifier.is_configured() {
+            if let Some(timeout) = self.idle_timeout {
+                if now - state.last_activity >= timeout {
+                    state.locked = true;
+                    info!("空闲超时，应用已自动锁定");
+                }
+            }
+        }
+        state.locked
+    }
+
+    /// 使用口令解锁
+    ///
+    /// # Errors
+    ///
+    /// 口令校验过程出错时返回错误。
+    pub fn unlock(&self, passcode: &str) -> CoreResult<UnlockOutcome> {
+        let now = self.clock.now();
+        let mut state = self.state();
+        if !state.locked {
+            return Ok(UnlockOutcome::Unlocked);
+        }
+        if let Some(retry_after) = state.retry_after.filter(|t| now < *t) {
+            return Ok(UnlockOutcome::RateLimited { retry_after });
+        }
+
+        if self.verifier.verify(passcode)? {
+            state.locked = false;
+            state.failed_attempts = 0;
+            state.retry_after = None;
+            state.last_activity = now;
+            info!("应用已解锁");
+            return Ok(UnlockOutcome::Unlocked);
+        }
+
+        state.failed_attempts += 1;
+        let delay = retry_delay(state.failed_attempts);
+        state.retry_after = delay.map(|d| now + d);
+        warn!("应用解锁失败，累计 {} 次", state.failed_attempts);
+        Ok(UnlockOutcome::WrongPasscode {
+            retry_after: state.retry_after,
+        })
+    }
+}
+
+/// 计算第 `failures` 次失败后的等待时间
+///
+/// 前 [`FREE_ATTEMPTS`] 次失败不限制，之后从1秒起每次翻倍，最长5分钟。
+pub fn retry_delay(failures: u32) -> Option<Duration> {
+    if failures < FREE_ATTEMPTS {
+        return None;
+    }
+    let exponent = (failures - FREE_ATTEMPTS).min(16);
+    Some(Duration::seconds(
+        (1_i64 << exponent).min(MAX_RETRY_DELAY_SECS),
+    ))
+}
+
+impl CommandGuard for AppLock {
+    fn check(&self, command: &CommandInfo) -> CoreResult<()> {
+        if self.check_idle() {
+            return Err(CoreError::permission(format!(
+                "应用已锁定，无法执行命令: {}",
+                command.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, CommandBus, CommandHandler};
+    use async_trait::async_trait;
+    use minicrm_core::ManualClock;
+
+    struct FixedPasscode(&'static str);
+
+    impl PasscodeVerifier for FixedPasscode {
+        fn is_configured(&self) -> bool {
+            true
+        }
+
+        fn verify(&self, passcode: &str) -> CoreResult<bool> {
+            Ok(passcode == self.0)
+        }
+    }
+
+    struct Ping;
+
+    impl Command for Ping {
+        const NAME: &'static str = "ping";
+        type Output = ();
+    }
+
+    struct PingHandler;
+
+    #[async_trait]
+    impl CommandHandler<Ping> for PingHandler {
+        async fn handle(&self, _command: Ping) -> CoreResult<()> {
+            Ok(())
+        }
+    }
+
+    fn setup(idle_minutes: Option<i64>) -> (Arc<ManualClock>, Arc<AppLock>) {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let lock = Arc::new(AppLock::new(
+            Arc::new(FixedPasscode("2468")),
+            clock.clone(),
+            idle_minutes.map(Duration::minutes),
+        ));
+        (clock, lock)
+    }
+
+    #[test]
+    fn test_unlock_with_correct_passcode() -> CoreResult<()> {
+        let (_clock, lock) = setup(None);
+        assert!(lock.is_locked());
+
+        assert!(matches!(
+            lock.unlock("1111")?,
+            UnlockOutcome::WrongPasscode { retry_after: None }
+        ));
+        assert_eq!(lock.unlock("2468")?, UnlockOutcome::Unlocked);
+        assert!(!lock.is_locked());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limiting_after_five_failures() -> CoreResult<()> {
+        let (clock, lock) = setup(None);
+        for _ in 0..4 {
+            lock.unlock("0000")?;
+        }
+
+        // 第5次失败开始等待1秒，之后每次翻倍
+        let start = clock.now();
+        assert_eq!(
+            lock.unlock("0000")?,
+            UnlockOutcome::WrongPasscode {
+                retry_after: Some(start + Duration::seconds(1))
+            }
+        );
+        assert_eq!(
+            lock.unlock("2468")?,
+            UnlockOutcome::RateLimited {
+                retry_after: start + Duration::seconds(1)
+            }
+        );
+
+        clock.advance(Duration::seconds(1));
+        let now = clock.now();
+        assert_eq!(
+            lock.unlock("0000")?,
+            UnlockOutcome::WrongPasscode {
+                retry_after: Some(now + Duration::seconds(2))
+            }
+        );
+
+        clock.advance(Duration::seconds(2));
+        assert_eq!(lock.unlock("2468")?, UnlockOutcome::Unlocked);
+        assert_eq!(retry_delay(40), Some(Duration::seconds(MAX_RETRY_DELAY_SECS)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_timeout_locks() -> CoreResult<()> {
+        let (clock, lock) = setup(Some(10));
+        lock.unlock("2468")?;
+
+        clock.advance(Duration::minutes(9));
+        lock.record_activity();
+        clock.advance(Duration::minutes(9));
+        assert!(!lock.check_idle());
+
+        clock.advance(Duration::minutes(1));
+        assert!(lock.check_idle());
+        assert!(lock.is_locked());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_command_bus_refuses_while_locked() -> CoreResult<()> {
+        let (_clock, lock) = setup(None);
+        let mut bus = CommandBus::new();
+        bus.register::<Ping>(Arc::new(PingHandler));
+        bus.add_guard(lock.clone());
+
+        let err = bus.dispatch(Ping).await.err();
+        assert!(matches!(err, Some(CoreError::Permission(_))));
+
+        lock.unlock("2468")?;
+        bus.dispatch(Ping).await?;
+
+        lock.lock();
+        assert!(bus.dispatch(Ping).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+//! 产品定价
+//!
+//! 按类别批量调价的计划，以及按报价创建时间重新计价。批量调价的预览和执行共用
+//! [`plan_price_adjustment`]，执行时写入的价格与预览完全一致。
+//!
+//! 报价毛利按产品成本价计算，只对管理员显示；毛利率偏低的明细在编辑器中标出，
+//! 整张报价低于禁止阈值时须管理员批准才能发送。
+
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    CoreError, CoreResult, Money, PricingService, ProductService, Quote, QuoteItem, UserRole,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 批量调价中的一项变更
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceChange {
+    /// 产品ID
+    pub product_id: Uuid,
+    /// 产品名称
+    pub product_name: String,
+    /// 调整前的价格（生效时间当刻的价格）
+    pub old_price: Money,
+    /// 调整后的价格
+    pub new_price: Money,
+}
+
+/// 计算按类别批量调价的变更
+///
+/// 对类别内未停售、在 `effective_from` 已有价格的产品按百分比调整，四舍五入到分；
+/// 调整后价格不变的产品不列出。某个产品在 `effective_from` 恰好已有价格时返回验证错误，
+/// 避免执行到一半才发现冲突。
+pub async fn plan_price_adjustment(
+    products: &(dyn ProductService + Send + Sync),
+    category: &str,
+    percent: i32,
+    effective_from: DateTime<Utc>,
+) -> CoreResult<Vec<PriceChange>> {
+    let category = category.trim();
+    if category.is_empty() {
+        return Err(CoreError::validation("请选择产品类别"));
+    }
+    if percent == 0 || percent <= -100 {
+        return Err(CoreError::validation("调整比例须不为0且大于-100%"));
+    }
+
+    let mut changes = Vec::new();
+    for product in products.list_products(Some(category)).await? {
+        if product.retired {
+            continue;
+        }
+        let Some(current) = products.price_at(product.id, effective_from).await? else {
+            continue;
+        };
+        if current.effective_from == effective_from {
+            return Err(CoreError::validation(format!(
+                "产品「{}」在该生效时间已有价格",
+                product.name
+            )));
+        }
+        let new_price = current.price.adjusted_by_percent(percent);
+        if new_price != current.price && new_price.is_positive() {
+            changes.push(PriceChange {
+                product_id: product.id,
+                product_name: product.name,
+                old_price: current.price,
+                new_price,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// 按报价的创建时间重新计价
+///
+/// 关联了产品的明细按创建时刻生效的价格重新计算单价，手工录入或当时尚未定价的明细保持不变。
+/// 产品目录以本位币定价，其他币种的报价不套用目录价格，只在报价币种内重新汇总金额。
+pub async fn reprice_quote(
+    pricing: &(dyn PricingService + Send + Sync),
+    quote: Quote,
+) -> CoreResult<Quote> {
+    let mut items = Vec::with_capacity(quote.items.len());
+    for item in quote.items {
+        let unit_price = match item.product_id {
+            Some(product_id) if quote.currency.is_base() => pricing
+                .priced_product(product_id, quote.created_at)
+                .await?
+                .and_then(|p| p.unit_price),
+            _ => None,
+        };
+        items.push(QuoteItem {
+            unit_price: unit_price.unwrap_or(item.unit_price),
+            ..item
+        });
+    }
+    let mut quote = Quote { items, ..quote };
+    quote.recalculate_totals();
+    Ok(quote)
+}
+
+/// 报价明细的毛利
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineMargin {
+    /// 明细ID
+    pub item_id: Uuid,
+    /// 单位成本（未录入成本时为空）
+    pub unit_cost: Option<f64>,
+    /// 毛利金额（小计减成本×数量）
+    pub margin: Option<f64>,
+    /// 毛利率（百分比，小计为0时为空）
+    pub margin_percent: Option<f64>,
+}
+
+/// 报价的毛利
+///
+/// 合计只统计有成本的明细，缺少成本的明细数单独给出，由界面提示毛利不完整。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteMargin {
+    /// 各明细的毛利（顺序与报价明细一致）
+    pub lines: Vec<LineMargin>,
+    /// 有成本的明细毛利合计
+    pub margin: f64,
+    /// 有成本的明细毛利率（百分比），没有可计算的明细时为空
+    pub margin_percent: Option<f64>,
+    /// 缺少成本的明细数
+    pub missing_cost_lines: usize,
+}
+
+impl QuoteMargin {
+    /// 按各明细的单位成本计算毛利，`costs` 与 `items` 一一对应
+    pub fn from_costs(items: &[QuoteItem], costs: &[Option<f64>]) -> Self {
+        let mut lines = Vec::with_capacity(items.len());
+        let mut margin = 0.0;
+        let mut costed_amount = 0.0;
+        let mut missing_cost_lines = 0;
+        for (item, cost) in items.iter().zip(costs.iter().copied()) {
+            let amount = item.amount();
+            let line_margin = cost.map(|cost| amount - cost * item.quantity);
+            match line_margin {
+                Some(line_margin) => {
+                    margin += line_margin;
+                    costed_amount += amount;
+                }
+                None => missing_cost_lines += 1,
+            }
+            lines.push(LineMargin {
+                item_id: item.id,
+                unit_cost: cost,
+                margin: line_margin,
+                margin_percent: line_margin.and_then(|m| percent_of(m, amount)),
+            });
+        }
+        Self {
+            lines,
+            margin,
+            margin_percent: percent_of(margin, costed_amount),
+            missing_cost_lines,
+        }
+    }
+}
+
+fn percent_of(part: f64, whole: f64) -> Option<f64> {
+    (whole > 0.0).then(|| part / whole * 100.0)
+}
+
+/// 计算报价的毛利
+///
+/// 成本按报价创建时刻的价格期间取，与 [`reprice_quote`] 一致。产品目录以本位币计成本，
+/// 其他币种的报价及手工录入的明细视为缺少成本。
+pub async fn quote_margin(
+    pricing: &(dyn PricingService + Send + Sync),
+    quote: &Quote,
+) -> CoreResult<QuoteMargin> {
+    let mut costs = Vec::with_capacity(quote.items.len());
+    for item in &quote.items {
+        let cost = match item.product_id {
+            Some(product_id) if quote.currency.is_base() => pricing
+                .priced_product(product_id, quote.created_at)
+                .await?
+                .and_then(|p| p.unit_cost),
+            _ => None,
+        };
+        costs.push(cost);
+    }
+    Ok(QuoteMargin::from_costs(&quote.items, &costs))
+}
+
+/// 毛利预警阈值（百分比）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MarginThresholds {
+    /// 明细毛利率低于此值时在编辑器中标出
+    pub warn_below: f64,
+    /// 报价毛利率低于此值时须管理员批准才能发送
+    pub block_below: f64,
+}
+
+impl Default for MarginThresholds {
+    fn default() -> Self {
+        Self {
+            warn_below: 15.0,
+            block_below: 5.0,
+        }
+    }
+}
+
+impl MarginThresholds {
+    /// 明细毛利率是否偏低
+    pub fn is_low(&self, line: &LineMargin) -> bool {
+        line.margin_percent.is_some_and(|percent| percent < self.warn_below)
+    }
+
+    /// 报价毛利率是否低到须批准才能发送
+    pub fn requires_approval(&self, margin: &QuoteMargin) -> bool {
+        margin.margin_percent.is_some_and(|percent| percent < self.block_below)
+    }
+
+    /// 检查报价能否发送
+    ///
+    /// # Errors
+    ///
+    /// 毛利率低于禁止阈值且未经管理员批准时返回验证错误。
+    pub fn check_send(&self, margin: &QuoteMargin, approved: bool) -> CoreResult<()> {
+        if approved || !self.requires_approval(margin) {
+            return Ok(());
+        }
+        Err(CoreError::validation(format!(
+            "报价毛利率 {:.1}% 低于 {:.1}%，须管理员批准后才能发送",
+            margin.margin_percent.unwrap_or_default(),
+            self.block_below
+        )))
+    }
+}
+
+/// 报价编辑器的数据
+///
+/// 成本和毛利只对管理员组装，其他角色拿到的数据中不含这些字段。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteEditorData {
+    /// 报价
+    pub quote: Quote,
+    /// 毛利（非管理员或未配置产品目录时为空）
+    pub margin: Option<QuoteMargin>,
+}
+
+impl QuoteEditorData {
+    /// 按角色组装编辑器数据
+    ///
+    /// # Errors
+    ///
+    /// 查询产品成本失败时返回错误。
+    pub async fn assemble(
+        pricing: Option<&(dyn PricingService + Send + Sync)>,
+        quote: Quote,
+        role: Option<UserRole>,
+    ) -> CoreResult<Self> {
+        let margin = match pricing {
+            Some(pricing) if role.is_some_and(|role| role.satisfies(UserRole::Admin)) => {
+                Some(quote_margin(pricing, &quote).await?)
+            }
+            _ => None,
+        };
+        Ok(Self { quote, margin })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(quantity: f64, unit_price: f64) -> QuoteItem {
+        QuoteItem {
+            id: Uuid::new_v4(),
+            quote_id: Uuid::nil(),
+            product_id: Some(Uuid::new_v4()),
+            product_name: "生态板".to_string(),
+            specification: None,
+            unit: Some("张".to_string()),
+            quantity,
+            unit_price,
+            line_total: Money::from_yuan(quantity * unit_price),
+        }
+    }
+
+    #[test]
+    fn test_margin_skips_lines_without_cost() {
+        let items = vec![item(10.0, 128.0), item(5.0, 200.0), item(1.0, 300.0)];
+        let margin = QuoteMargin::from_costs(&items, &[Some(96.0), None, Some(300.0)]);
+
+        assert_eq!(margin.lines[0].margin, Some(320.0));
+        assert_eq!(margin.lines[0].margin_percent, Some(25.0));
+        assert_eq!(margin.lines[1].margin, None);
+        assert_eq!(margin.lines[2].margin_percent, Some(0.0));
+        assert_eq!(margin.missing_cost_lines, 1);
+        // 合计只算有成本的明细：320 / (1280 + 300)
+        assert!((margin.margin - 320.0).abs() < 1e-9);
+        assert!((margin.margin_percent.unwrap() - 320.0 / 1580.0 * 100.0).abs() < 1e-9);
+
+        let none = QuoteMargin::from_costs(&items, &[None, None, None]);
+        assert_eq!(none.margin_percent, None);
+        assert_eq!(none.missing_cost_lines, 3);
+    }
+
+    #[test]
+    fn test_thresholds_warn_then_block() {
+        let thresholds = MarginThresholds {
+            warn_below: 15.0,
+            block_below: 5.0,
+        };
+        let items = vec![item(1.0, 100.0), item(1.0, 100.0)];
+
+        // 一条明细10%：标出但可以发送
+        let low = QuoteMargin::from_costs(&items, &[Some(90.0), Some(70.0)]);
+        assert!(thresholds.is_low(&low.lines[0]));
+        assert!(!thresholds.is_low(&low.lines[1]));
+        assert!(thresholds.check_send(&low, false).is_ok());
+
+        // 合计4%：未经批准不能发送
+        let blocked = QuoteMargin::from_costs(&items, &[Some(96.0), Some(96.0)]);
+        assert!(thresholds.requires_approval(&blocked));
+        assert!(matches!(
+            thresholds.check_send(&blocked, false),
+            Err(CoreError::Validation(_))
+        ));
+        assert!(thresholds.check_send(&blocked, true).is_ok());
+
+        // 没有成本时无法判断，不拦截
+        let unknown = QuoteMargin::from_costs(&items, &[None, None]);
+        assert!(thresholds.check_send(&unknown, false).is_ok());
+    }
+}
@@ -2,4 +2,737 @@
 //!
 //! 定义应用层的查询处理
 
-// 暂时为空，后续实现
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Utc};
+use minicrm_core::{
+    constants::DEFAULT_EXACT_COUNT_THRESHOLD, ArchivePolicy, ArchivePreview, ArchiveRun,
+    BusinessCalendar, CoreError, CoreResult, Customer, CustomerListRow, CustomerStatistics,
+    DateRange, DeletionBatch, DeletionImpact, KnowledgeArticle, LocalDate, MonthlyStatistics, Order,
+    PagedResult, Pipeline, PossibleDuplicate, Projection, PurchaseOrder, QueryFilter,
+    QuoteStatistics, ReportPeriod, SearchHit, SupplierPayables, Task, TaskStatistics, TimelinePage,
+    TimelineQuery, TotalCountSource, TrashItem,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::pricing::{PriceChange, QuoteEditorData};
+
+/// 查询接口
+pub trait Query: Send + 'static {
+    /// 查询名称
+    const NAME: &'static str;
+
+    /// 查询结果类型
+    type Output: Send + 'static;
+}
+
+/// 查询处理器接口
+#[async_trait]
+pub trait QueryHandler<Q: Query>: Send + Sync {
+    /// 处理查询
+    async fn handle(&self, query: Q) -> CoreResult<Q::Output>;
+}
+
+/// 查询总线
+#[derive(Default)]
+pub struct QueryBus {
+    handlers: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl std::fmt::Debug for QueryBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryBus")
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+impl QueryBus {
+    /// 创建新的查询总线
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册查询处理器
+    pub fn register<Q: Query>(&mut self, handler: Arc<dyn QueryHandler<Q>>) {
+        self.handlers.insert(TypeId::of::<Q>(), Box::new(handler));
+    }
+
+    /// 执行查询
+    ///
+    /// # Errors
+    ///
+    /// 查询未注册处理器或处理失败时返回错误。
+    pub async fn ask<Q: Query>(&self, query: Q) -> CoreResult<Q::Output> {
+        let handler = self
+            .handlers
+            .get(&TypeId::of::<Q>())
+            .and_then(|h| h.downcast_ref::<Arc<dyn QueryHandler<Q>>>())
+            .cloned()
+            .ok_or_else(|| CoreError::configuration(format!("未注册查询处理器: {}", Q::NAME)))?;
+
+        handler.handle(query).await
+    }
+}
+
+/// 客户列表查询
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListCustomersQuery {
+    /// 过滤条件
+    pub filter: QueryFilter,
+}
+
+impl Query for ListCustomersQuery {
+    const NAME: &'static str = "list_customers";
+    type Output = PagedResult<Customer>;
+}
+
+/// 客户详情查询
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetCustomerQuery {
+    /// 客户ID
+    pub id: Uuid,
+}
+
+impl Query for GetCustomerQuery {
+    const NAME: &'static str = "get_customer";
+    type Output = Option<Customer>;
+}
+
+/// 报价编辑器查询（成本和毛利只对管理员返回）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteEditorQuery {
+    /// 报价ID
+    pub quote_id: Uuid,
+}
+
+impl Query for QuoteEditorQuery {
+    const NAME: &'static str = "quote_editor";
+    type Output = QuoteEditorData;
+}
+
+/// 客户列表查询（按列投影读取，隐藏的计算列不参与查询）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomerListQuery {
+    /// 过滤条件
+    pub filter: QueryFilter,
+    /// 可见列
+    pub projection: Projection,
+}
+
+impl Query for CustomerListQuery {
+    const NAME: &'static str = "customer_list";
+    type Output = PagedResult<CustomerListRow>;
+}
+
+/// 客户时间线查询（互动和报价、订单、收款、售后工单推导的事件按时间倒序合并）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerTimelineQuery {
+    /// 客户、分类和分页位置
+    pub query: TimelineQuery,
+}
+
+impl Query for CustomerTimelineQuery {
+    const NAME: &'static str = "customer_timeline";
+    type Output = TimelinePage;
+}
+
+/// 全局搜索查询
+///
+/// 客户、订单和报价混合排序：号码完全相同的记录优先于模糊匹配的名称，
+/// 同一记录在多个字段命中时只返回一条（见 [`SearchRanking`](minicrm_core::SearchRanking)）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalSearchQuery {
+    /// 关键词
+    pub text: String,
+    /// 最多返回的条数
+    #[serde(default = "default_search_limit")]
+    pub limit: u32,
+    /// 在结果中附带最终得分（调试排序权重用）
+    #[serde(default)]
+    pub debug_scores: bool,
+}
+
+fn default_search_limit() -> u32 {
+    20
+}
+
+impl GlobalSearchQuery {
+    /// 按关键词搜索，使用默认条数
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            limit: default_search_limit(),
+            debug_scores: false,
+        }
+    }
+}
+
+impl Query for GlobalSearchQuery {
+    const NAME: &'static str = "global_search";
+    type Output = Vec<SearchHit>;
+}
+
+/// 可能重复客户查询
+///
+/// 新建或编辑客户时按名称和电话查找可能重复的已有客户，最多返回
+/// [`MAX_DUPLICATE_SUGGESTIONS`](minicrm_core::MAX_DUPLICATE_SUGGESTIONS) 条，只用于提示，
+/// 不阻止保存。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckPossibleDuplicateQuery {
+    /// 正在输入的客户名称
+    pub name: String,
+    /// 正在输入的电话
+    #[serde(default)]
+    pub phone: String,
+    /// 正在编辑的客户（不提示其本身，新建时为空）
+    #[serde(default)]
+    pub exclude_id: Option<Uuid>,
+}
+
+impl Query for CheckPossibleDuplicateQuery {
+    const NAME: &'static str = "check_possible_duplicate";
+    type Output = Vec<PossibleDuplicate>;
+}
+
+/// 批量调价预览查询
+///
+/// 与 [`AdjustCategoryPricesCommand`](crate::commands::AdjustCategoryPricesCommand)
+/// 使用同一计算，返回将要写入的价格变更，不修改数据。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceAdjustmentPreviewQuery {
+    /// 产品类别
+    pub category: String,
+    /// 调整百分比（`5` 表示上调5%）
+    pub percent: i32,
+    /// 生效时间
+    pub effective_from: DateTime<Utc>,
+}
+
+impl Query for PriceAdjustmentPreviewQuery {
+    const NAME: &'static str = "price_adjustment_preview";
+    type Output = Vec<PriceChange>;
+}
+
+/// 归档预览查询
+///
+/// 与 [`ArchiveRecordsCommand`](crate::commands::ArchiveRecordsCommand) 使用同一筛选条件，
+/// 返回按当前策略将移出的各类记录数和估算大小，不修改数据。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivePreviewQuery {
+    /// 归档策略
+    pub policy: ArchivePolicy,
+    /// 按该时间计算截止时间
+    pub as_of: DateTime<Utc>,
+}
+
+impl Query for ArchivePreviewQuery {
+    const NAME: &'static str = "archive_preview";
+    type Output = ArchivePreview;
+}
+
+/// 归档历史查询（诊断信息界面）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveRunsQuery {
+    /// 最多返回条数
+    pub limit: usize,
+}
+
+impl Query for ArchiveRunsQuery {
+    const NAME: &'static str = "archive_runs";
+    type Output = Vec<ArchiveRun>;
+}
+
+/// 删除客户影响范围查询
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerDeletionImpactQuery {
+    /// 客户ID
+    pub id: Uuid,
+}
+
+impl Query for CustomerDeletionImpactQuery {
+    const NAME: &'static str = "customer_deletion_impact";
+    type Output = DeletionImpact;
+}
+
+/// 工单推荐文章查询（工单编辑器）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketSuggestionsQuery {
+    /// 工单ID
+    pub ticket_id: Uuid,
+}
+
+impl Query for TicketSuggestionsQuery {
+    const NAME: &'static str = "ticket_suggestions";
+    type Output = Vec<KnowledgeArticle>;
+}
+
+/// 回收站查询
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrashQuery;
+
+impl Query for TrashQuery {
+    const NAME: &'static str = "trash";
+    type Output = TrashContents;
+}
+
+/// 回收站内容
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashContents {
+    /// 回收站中的记录（删除时间新的在前）
+    pub items: Vec<TrashItem>,
+    /// 仍有记录在回收站中的删除批次
+    pub batches: Vec<DeletionBatch>,
+}
+
+/// 任务列表查询
+///
+/// 选择“分配给我的”或“我关注的”时按当前用户过滤，两项都选择时取交集，此时只使用
+/// `filter` 中的分页参数。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListTasksQuery {
+    /// 过滤条件
+    pub filter: QueryFilter,
+    /// 只列出分配给我的任务
+    #[serde(default)]
+    pub assigned_to_me: bool,
+    /// 只列出我关注的任务
+    #[serde(default)]
+    pub watched_by_me: bool,
+}
+
+impl Query for ListTasksQuery {
+    const NAME: &'static str = "list_tasks";
+    type Output = PagedResult<Task>;
+}
+
+/// 仪表盘统计查询
+///
+/// 月度报表使用同一查询，保证报表与仪表盘口径一致。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DashboardStatsQuery {
+    /// 月度统计周期（为空时取当月）
+    #[serde(default)]
+    pub period: Option<ReportPeriod>,
+    /// 跳过统计缓存，总是重新计算
+    #[serde(default)]
+    pub bypass_cache: bool,
+}
+
+impl DashboardStatsQuery {
+    /// 查询指定周期的统计
+    pub fn for_period(period: ReportPeriod) -> Self {
+        Self {
+            period: Some(period),
+            bypass_cache: false,
+        }
+    }
+
+    /// 跳过缓存查询指定周期的统计（报表生成使用）
+    pub fn uncached(period: ReportPeriod) -> Self {
+        Self {
+            period: Some(period),
+            bypass_cache: true,
+        }
+    }
+}
+
+impl Query for DashboardStatsQuery {
+    const NAME: &'static str = "dashboard_stats";
+    type Output = DashboardStats;
+}
+
+/// 仪表盘统计数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DashboardStats {
+    /// 客户统计
+    pub customers: CustomerStatistics,
+    /// 任务统计
+    pub tasks: TaskStatistics,
+    /// 报价统计
+    pub quotes: QuoteStatistics,
+    /// 月度统计周期
+    pub period: ReportPeriod,
+    /// 月度统计
+    pub monthly: MonthlyStatistics,
+}
+
+/// 本周送货查询
+///
+/// 按业务时区的自然周（周一至周日）列出已安排的送货。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeliveriesThisWeekQuery {
+    /// 周内任一本地日期（为空时取今天）
+    #[serde(default)]
+    pub date: Option<LocalDate>,
+}
+
+impl Query for DeliveriesThisWeekQuery {
+    const NAME: &'static str = "deliveries_this_week";
+    type Output = DeliveryWeek;
+}
+
+/// 某一天的送货
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryDay {
+    /// 本地日期
+    pub date: LocalDate,
+    /// 当天的送货订单（按送货时间升序）
+    pub orders: Vec<Order>,
+}
+
+/// 一周的送货安排
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryWeek {
+    /// 周一至周日，每天一项（没有送货的日期订单为空）
+    pub days: Vec<DeliveryDay>,
+}
+
+impl DeliveryWeek {
+    /// 包含 `date` 的那一周的周一
+    pub fn monday_of(date: LocalDate) -> LocalDate {
+        date.add_days(-i64::from(date.naive().weekday().num_days_from_monday()))
+    }
+
+    /// 包含 `date` 的那一周的UTC范围
+    pub fn range(calendar: &BusinessCalendar, date: LocalDate) -> DateRange {
+        let monday = Self::monday_of(date);
+        DateRange::new(
+            calendar.start_of_day(monday),
+            calendar.start_of_day(monday.add_days(7)),
+        )
+    }
+
+    /// 按本地送货日期分组
+    ///
+    /// 不在该周内或没有送货时间的订单会被忽略。
+    pub fn group(calendar: &BusinessCalendar, date: LocalDate, orders: Vec<Order>) -> Self {
+        let monday = Self::monday_of(date);
+        let mut days: Vec<DeliveryDay> = (0..7)
+            .map(|offset| DeliveryDay {
+                date: monday.add_days(offset),
+                orders: Vec::new(),
+            })
+            .collect();
+        for order in orders {
+            let Some(at) = order.delivery_date else {
+                continue;
+            };
+            let offset = calendar.local_date(at).days_since(monday);
+            if let Some(day) = usize::try_from(offset).ok().and_then(|i| days.get_mut(i)) {
+                day.orders.push(order);
+            }
+        }
+        for day in &mut days {
+            day.orders.sort_by_key(|o| o.delivery_date);
+        }
+        Self { days }
+    }
+
+    /// 本周送货总数
+    pub fn total(&self) -> usize {
+        self.days.iter().map(|d| d.orders.len()).sum()
+    }
+}
+
+/// 供应商采购订单查询（供应商详情的采购订单页）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplierPurchaseOrdersQuery {
+    /// 供应商ID
+    pub supplier_id: Uuid,
+}
+
+impl Query for SupplierPurchaseOrdersQuery {
+    const NAME: &'static str = "supplier_purchase_orders";
+    type Output = Vec<PurchaseOrder>;
+}
+
+/// 供应商应付查询
+///
+/// 与客户信用中的应收对应：已收货的采购金额计为应付，已发出未到货的部分单独列出。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplierPayablesQuery {
+    /// 供应商ID
+    pub supplier_id: Uuid,
+}
+
+impl Query for SupplierPayablesQuery {
+    const NAME: &'static str = "supplier_payables";
+    type Output = SupplierPayables;
+}
+
+/// 销售漏斗查询
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipelineQuery;
+
+impl Query for PipelineQuery {
+    const NAME: &'static str = "opportunity_pipeline";
+    type Output = Pipeline;
+}
+
+/// 列表总数统计策略
+///
+/// 翻页时不必每次都对整张表执行 `COUNT(*)`：
+/// - 无过滤条件的查询直接使用按表维护的缓存总数；
+/// - 有过滤条件的查询以 `exact_threshold + 1` 为上限计数，未超过上限即为精确值，
+///   超过上限（低选择性过滤）时返回估算值。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotalCountStrategy {
+    /// 总是精确计数
+    Exact,
+    /// 根据过滤条件自动选择
+    Auto {
+        /// 过滤查询精确计数的行数上限
+        exact_threshold: u64,
+    },
+}
+
+impl Default for TotalCountStrategy {
+    fn default() -> Self {
+        TotalCountStrategy::Auto {
+            exact_threshold: DEFAULT_EXACT_COUNT_THRESHOLD,
+        }
+    }
+}
+
+/// 总数统计结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TotalCount {
+    /// 总数
+    pub value: u64,
+    /// 是否为精确值
+    pub exact: bool,
+}
+
+impl TotalCountStrategy {
+    /// 按策略计算列表总数
+    pub async fn resolve(
+        &self,
+        filter: &QueryFilter,
+        source: &dyn TotalCountSource,
+    ) -> CoreResult<TotalCount> {
+        match *self {
+            TotalCountStrategy::Exact => Ok(TotalCount {
+                value: source.count_matching(filter, None).await?,
+                exact: true,
+            }),
+            TotalCountStrategy::Auto { exact_threshold } => {
+                if !filter.has_conditions() {
+                    return Ok(TotalCount {
+                        value: source.cached_total().await?,
+                        exact: false,
+                    });
+                }
+
+                let cap = exact_threshold.saturating_add(1);
+                let bounded = source.count_matching(filter, Some(cap)).await?;
+                if bounded <= exact_threshold {
+                    return Ok(TotalCount {
+                        value: bounded,
+                        exact: true,
+                    });
+                }
+
+                // 低选择性过滤：以缓存总数为上界给出估算值
+                let cached = source.cached_total().await?;
+                Ok(TotalCount {
+                    value: cached.max(bounded),
+                    exact: false,
+                })
+            }
+        }
+    }
+}
+
+/// 分页列表数据源
+#[async_trait]
+pub trait PageSource<T>: TotalCountSource {
+    /// 读取当前页数据
+    async fn fetch_page(&self, filter: &QueryFilter) -> CoreResult<Vec<T>>;
+}
+
+/// 列表查询处理器
+pub struct ListQueryHandler<T> {
+    source: Arc<dyn PageSource<T>>,
+    strategy: TotalCountStrategy,
+}
+
+impl<T> std::fmt::Debug for ListQueryHandler<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListQueryHandler")
+            .field("entity", &self.source.entity_kind())
+            .field("strategy", &self.strategy)
+            .finish()
+    }
+}
+
+impl<T: Send> ListQueryHandler<T> {
+    /// 创建列表查询处理器
+    pub fn new(source: Arc<dyn PageSource<T>>) -> Self {
+        Self {
+            source,
+            strategy: TotalCountStrategy::default(),
+        }
+    }
+
+    /// 设置总数统计策略
+    pub fn with_strategy(mut self, strategy: TotalCountStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// 执行列表查询
+    pub async fn handle(&self, filter: &QueryFilter) -> CoreResult<PagedResult<T>> {
+        let items = self.source.fetch_page(filter).await?;
+        let total = self.strategy.resolve(filter, self.source.as_ref()).await?;
+
+        Ok(if total.exact {
+            PagedResult::new(items, total.value, &filter.pagination)
+        } else {
+            PagedResult::with_estimated_total(items, total.value, &filter.pagination)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+    use minicrm_core::{Currency, DeliveryStatus, EntityKind, Money};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use uuid::Uuid;
+
+    struct FakeSource {
+        matching: u64,
+        cached: u64,
+        exact_calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl TotalCountSource for FakeSource {
+        fn entity_kind(&self) -> EntityKind {
+            EntityKind::Customer
+        }
+
+        async fn count_matching(&self, _filter: &QueryFilter, cap: Option<u64>) -> CoreResult<u64> {
+            self.exact_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(cap.map_or(self.matching, |cap| self.matching.min(cap)))
+        }
+
+        async fn cached_total(&self) -> CoreResult<u64> {
+            Ok(self.cached)
+        }
+    }
+
+    fn source(matching: u64, cached: u64) -> FakeSource {
+        FakeSource {
+            matching,
+            cached,
+            exact_calls: AtomicU64::new(0),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unfiltered_uses_cached_total() {
+        let source = source(52_300, 52_300);
+        let total = TotalCountStrategy::default()
+            .resolve(&QueryFilter::new(), &source)
+            .await
+            .unwrap();
+
+        assert_eq!(total.value, 52_300);
+        assert!(!total.exact);
+        assert_eq!(source.exact_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_selective_filter_counts_exactly() {
+        let source = source(42, 52_300);
+        let filter = QueryFilter::new().with_search("木业");
+        let total = TotalCountStrategy::Auto { exact_threshold: 100 }
+            .resolve(&filter, &source)
+            .await
+            .unwrap();
+
+        assert_eq!(total, TotalCount { value: 42, exact: true });
+    }
+
+    #[tokio::test]
+    async fn test_low_selectivity_filter_is_estimated() {
+        let source = source(30_000, 52_300);
+        let filter = QueryFilter::new().with_string_filter("level", "Normal");
+        let total = TotalCountStrategy::Auto { exact_threshold: 100 }
+            .resolve(&filter, &source)
+            .await
+            .unwrap();
+
+        assert_eq!(total, TotalCount { value: 52_300, exact: false });
+    }
+
+    fn delivery(number: &str, at: DateTime<Utc>) -> Order {
+        Order {
+            id: Uuid::new_v4(),
+            order_number: number.to_string(),
+            customer_id: Uuid::new_v4(),
+            quote_id: None,
+            total_amount: Money::from_yuan(3_000.0),
+            currency: Currency::CNY,
+            delivery_status: DeliveryStatus::Scheduled,
+            delivery_date: Some(at),
+            delivery_address: None,
+            vehicle: None,
+            driver: None,
+            signed_by: None,
+            delivered_at: None,
+            created_at: at,
+            updated_at: at,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_delivery_week_spans_month_boundary() {
+        let calendar = BusinessCalendar::default();
+        // 2024-08-01 是周四，所在周为 7月29日 至 8月4日
+        let thursday = LocalDate::new(2024, 8, 1).unwrap();
+        let range = DeliveryWeek::range(&calendar, thursday);
+        assert_eq!(range.start, Utc.with_ymd_and_hms(2024, 7, 28, 16, 0, 0).unwrap());
+        assert_eq!(range.end, Utc.with_ymd_and_hms(2024, 8, 4, 16, 0, 0).unwrap());
+
+        let orders = vec![
+            // 北京时间 8月1日 09:00
+            delivery("DD-3", Utc.with_ymd_and_hms(2024, 8, 1, 1, 0, 0).unwrap()),
+            // 北京时间 7月31日 07:30（UTC仍是7月30日）
+            delivery("DD-2", Utc.with_ymd_and_hms(2024, 7, 30, 23, 30, 0).unwrap()),
+            // 北京时间 7月29日 00:30，周一凌晨
+            delivery("DD-1", Utc.with_ymd_and_hms(2024, 7, 28, 16, 30, 0).unwrap()),
+            // 北京时间 8月1日 08:00，同一天较早
+            delivery("DD-0", Utc.with_ymd_and_hms(2024, 8, 1, 0, 0, 0).unwrap()),
+            // 北京时间 8月5日，下一周
+            delivery("DD-9", Utc.with_ymd_and_hms(2024, 8, 5, 1, 0, 0).unwrap()),
+        ];
+        let week = DeliveryWeek::group(&calendar, thursday, orders);
+
+        let dates: Vec<String> = week.days.iter().map(|d| d.date.to_string()).collect();
+        assert_eq!(
+            dates,
+            vec![
+                "2024-07-29", "2024-07-30", "2024-07-31", "2024-08-01", "2024-08-02",
+                "2024-08-03", "2024-08-04"
+            ]
+        );
+        let numbers = |i: usize| -> Vec<&str> {
+            week.days[i].orders.iter().map(|o| o.order_number.as_str()).collect()
+        };
+        assert_eq!(numbers(0), vec!["DD-1"]);
+        assert!(numbers(1).is_empty());
+        assert_eq!(numbers(2), vec!["DD-2"]);
+        assert_eq!(numbers(3), vec!["DD-0", "DD-3"]);
+        assert_eq!(week.total(), 4);
+    }
+}
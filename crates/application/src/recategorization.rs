@@ -0,0 +1,548 @@
+//! 客户批量重新分类
+//!
+//! 管理员设定等级评估阈值和标签规则后，先预览哪些客户的等级或标签会变化，
+//! 确认后对全部预览结果或勾选的客户执行。
+//!
+//! 预览和执行使用同一个评估时间：预览返回 [`EvaluationSnapshot`]（评估时间、变更数和
+//! 变更集合的指纹），执行时按该时间重新评估，结果与预览不一致（期间客户数据被修改）
+//! 时返回冲突错误，要求重新预览，而不是悄悄执行与预览不同的变更。
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use minicrm_core::{
+    CategoryChange, Clock, CoreError, CoreResult, CustomerCategoryService, CustomerLevel,
+    CustomerProfile, Money, PagedResult, Pagination, SystemClock,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// 每个事务写入的默认客户数
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// 等级评估阈值（按近12个月已接受报价金额）
+///
+/// 黑名单客户不参与评估。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelThresholds {
+    /// 达到该金额评为重要客户
+    pub important: Money,
+    /// 达到该金额评为VIP客户
+    pub vip: Money,
+}
+
+impl LevelThresholds {
+    /// 评估客户等级
+    pub fn evaluate(&self, profile: &CustomerProfile) -> CustomerLevel {
+        let total = profile.accepted_quote_total_12m;
+        if profile.level == CustomerLevel::Blacklist {
+            CustomerLevel::Blacklist
+        } else if total >= self.vip {
+            CustomerLevel::Vip
+        } else if total >= self.important {
+            CustomerLevel::Important
+        } else {
+            CustomerLevel::Normal
+        }
+    }
+}
+
+/// 标签规则的条件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TagCondition {
+    /// 超过指定天数没有互动（从未互动也算）
+    NoInteractionForDays(i64),
+    /// 近12个月已接受报价金额达到指定金额
+    QuoteTotalAtLeast(Money),
+    /// 评估后的等级为指定等级
+    Level(CustomerLevel),
+}
+
+/// 标签规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagRule {
+    /// 标签
+    pub tag: String,
+    /// 条件
+    pub condition: TagCondition,
+    /// 条件不满足时是否移除该标签
+    #[serde(default)]
+    pub remove_when_unmet: bool,
+}
+
+impl TagRule {
+    /// 满足条件时添加标签（条件不满足时保留已有标签）
+    pub fn add(tag: impl Into<String>, condition: TagCondition) -> Self {
+        Self {
+            tag: tag.into(),
+            condition,
+            remove_when_unmet: false,
+        }
+    }
+
+    /// 满足条件时添加标签，不满足时移除
+    pub fn sync(tag: impl Into<String>, condition: TagCondition) -> Self {
+        Self {
+            remove_when_unmet: true,
+            ..Self::add(tag, condition)
+        }
+    }
+
+    fn matches(&self, profile: &CustomerProfile, level: CustomerLevel, at: DateTime<Utc>) -> bool {
+        match &self.condition {
+            TagCondition::NoInteractionForDays(days) => profile
+                .last_interaction_at
+                .is_none_or(|last| at - last > Duration::days(*days)),
+            TagCondition::QuoteTotalAtLeast(amount) => profile.accepted_quote_total_12m >= *amount,
+            TagCondition::Level(expected) => level == *expected,
+        }
+    }
+}
+
+/// 重新分类规则
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecategorizationRules {
+    /// 等级评估阈值（为空时不调整等级）
+    pub levels: Option<LevelThresholds>,
+    /// 标签规则（按顺序应用）
+    pub tags: Vec<TagRule>,
+}
+
+impl RecategorizationRules {
+    /// 按 `at` 时刻评估单个客户，没有变化时返回 `None`
+    pub fn evaluate(&self, profile: &CustomerProfile, at: DateTime<Utc>) -> Option<CategoryChange> {
+        let level = self
+            .levels
+            .map_or(profile.level, |thresholds| thresholds.evaluate(profile));
+        let mut tags = profile.tags.clone();
+        for rule in &self.tags {
+            let tag = rule.tag.trim();
+            if tag.is_empty() {
+                continue;
+            }
+            if rule.matches(profile, level, at) {
+                tags.insert(tag.to_string());
+            } else if rule.remove_when_unmet {
+                tags.remove(tag);
+            }
+        }
+        (level != profile.level || tags != profile.tags).then(|| CategoryChange {
+            customer_id: profile.customer_id,
+            name: profile.name.clone(),
+            level_before: profile.level,
+            level_after: level,
+            tags_before: profile.tags.clone(),
+            tags_after: tags,
+        })
+    }
+}
+
+/// 预览时的评估快照，执行时原样传回
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvaluationSnapshot {
+    /// 评估时间（时间相关的规则以此为准）
+    pub evaluated_at: DateTime<Utc>,
+    /// 会变化的客户数
+    pub total: u64,
+    /// 变更集合的指纹
+    pub fingerprint: String,
+}
+
+/// 预览结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecategorizationPreview {
+    /// 评估快照
+    pub snapshot: EvaluationSnapshot,
+    /// 当前页的变更
+    pub changes: PagedResult<CategoryChange>,
+}
+
+/// 执行范围
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApplyScope {
+    /// 预览中的全部客户
+    AllPreviewed,
+    /// 预览中勾选的客户
+    Selected(BTreeSet<Uuid>),
+}
+
+/// 执行结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecategorizationReport {
+    /// 实际写入的客户数
+    pub applied: usize,
+    /// 事务批数
+    pub batches: usize,
+}
+
+fn fingerprint(changes: &[CategoryChange]) -> CoreResult<String> {
+    let mut hasher = Sha256::new();
+    for change in changes {
+        hasher.update(serde_json::to_vec(change)?);
+        hasher.update(b"\n");
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 客户批量重新分类向导
+#[derive(Clone)]
+pub struct RecategorizationWizard {
+    categories: Arc<dyn CustomerCategoryService + Send + Sync>,
+    clock: Arc<dyn Clock>,
+    batch_size: usize,
+}
+
+impl std::fmt::Debug for RecategorizationWizard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecategorizationWizard")
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RecategorizationWizard {
+    /// 创建向导
+    pub fn new(categories: Arc<dyn CustomerCategoryService + Send + Sync>) -> Self {
+        Self {
+            categories,
+            clock: Arc::new(SystemClock),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    /// 使用指定时钟（测试中固定评估时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置每个事务写入的客户数
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// 按 `at` 时刻评估全部客户
+    async fn changes_at(
+        &self,
+        rules: &RecategorizationRules,
+        at: DateTime<Utc>,
+    ) -> CoreResult<Vec<CategoryChange>> {
+        Ok(self
+            .categories
+            .customer_profiles()
+            .await?
+            .iter()
+            .filter_map(|profile| rules.evaluate(profile, at))
+            .collect())
+    }
+
+    /// 预览等级或标签会变化的客户（分页）
+    pub async fn preview(
+        &self,
+        rules: &RecategorizationRules,
+        pagination: &Pagination,
+    ) -> CoreResult<RecategorizationPreview> {
+        let evaluated_at = self.clock.now();
+        let changes = self.changes_at(rules, evaluated_at).await?;
+        let snapshot = EvaluationSnapshot {
+            evaluated_at,
+            total: changes.len() as u64,
+            fingerprint: fingerprint(&changes)?,
+        };
+        let page = changes
+            .into_iter()
+            .skip(pagination.offset() as usize)
+            .take(pagination.page_size as usize)
+            .collect();
+        Ok(RecategorizationPreview {
+            changes: PagedResult::new(page, snapshot.total, pagination),
+            snapshot,
+        })
+    }
+
+    /// 按预览快照执行变更
+    ///
+    /// 按快照的评估时间重新评估；与预览不一致时返回冲突错误。勾选了预览中不存在的客户时
+    /// 返回校验错误。变更按批写入，每批一个事务。
+    pub async fn apply(
+        &self,
+        rules: &RecategorizationRules,
+        snapshot: &EvaluationSnapshot,
+        scope: &ApplyScope,
+        actor: Option<&str>,
+    ) -> CoreResult<RecategorizationReport> {
+        let changes = self.changes_at(rules, snapshot.evaluated_at).await?;
+        if fingerprint(&changes)? != snapshot.fingerprint {
+            return Err(CoreError::conflict(
+                "客户数据在预览后发生了变化，请重新预览后再执行",
+            ));
+        }
+        let changes: Vec<CategoryChange> = match scope {
+            ApplyScope::AllPreviewed => changes,
+            ApplyScope::Selected(ids) => {
+                let selected: Vec<_> = changes
+                    .into_iter()
+                    .filter(|change| ids.contains(&change.customer_id))
+                    .collect();
+                if selected.len() != ids.len() {
+                    return Err(CoreError::validation("勾选的客户不在预览结果中"));
+                }
+                selected
+            }
+        };
+
+        let mut report = RecategorizationReport::default();
+        for batch in changes.chunks(self.batch_size) {
+            report.applied += self
+                .categories
+                .apply_category_changes(batch, actor)
+                .await?;
+            report.batches += 1;
+        }
+        tracing::info!(
+            "已重新分类 {} 个客户（{} 批）",
+            report.applied,
+            report.batches
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use minicrm_core::ManualClock;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// 内存中的客户分类数据，记录每批写入的大小
+    #[derive(Default)]
+    struct FakeCategories {
+        profiles: Mutex<Vec<CustomerProfile>>,
+        batches: Mutex<Vec<usize>>,
+        applied: Mutex<Vec<CategoryChange>>,
+    }
+
+    #[async_trait]
+    impl CustomerCategoryService for FakeCategories {
+        async fn customer_profiles(&self) -> CoreResult<Vec<CustomerProfile>> {
+            Ok(self.profiles.lock().unwrap().clone())
+        }
+
+        async fn apply_category_changes(
+            &self,
+            changes: &[CategoryChange],
+            _actor: Option<&str>,
+        ) -> CoreResult<usize> {
+            let mut profiles = self.profiles.lock().unwrap();
+            let index: HashMap<Uuid, usize> = profiles
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (p.customer_id, i))
+                .collect();
+            for change in changes {
+                let profile = &mut profiles[index[&change.customer_id]];
+                profile.level = change.level_after;
+                profile.tags = change.tags_after.clone();
+            }
+            self.batches.lock().unwrap().push(changes.len());
+            self.applied.lock().unwrap().extend_from_slice(changes);
+            Ok(changes.len())
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 9, 1, 9, 0, 0).unwrap()
+    }
+
+    /// 5000个客户：报价金额、最近互动时间按序号分布
+    fn seeded() -> Arc<FakeCategories> {
+        let profiles = (0..5000)
+            .map(|i: i64| CustomerProfile {
+                customer_id: Uuid::new_v4(),
+                name: format!("客户{:04}", i),
+                level: if i % 50 == 0 {
+                    CustomerLevel::Blacklist
+                } else {
+                    CustomerLevel::Normal
+                },
+                tags: BTreeSet::new(),
+                last_interaction_at: (i % 3 != 0).then(|| now() - Duration::days(i % 400)),
+                accepted_quote_total_12m: Money::from_cents((i % 10) * 1_000_000),
+            })
+            .collect();
+        Arc::new(FakeCategories {
+            profiles: Mutex::new(profiles),
+            ..FakeCategories::default()
+        })
+    }
+
+    fn rules() -> RecategorizationRules {
+        RecategorizationRules {
+            levels: Some(LevelThresholds {
+                important: Money::from_cents(5_000_000),
+                vip: Money::from_cents(8_000_000),
+            }),
+            tags: vec![
+                TagRule::add("长期未联系", TagCondition::NoInteractionForDays(180)),
+                TagRule::sync("重点跟进", TagCondition::Level(CustomerLevel::Vip)),
+            ],
+        }
+    }
+
+    fn wizard(categories: &Arc<FakeCategories>) -> RecategorizationWizard {
+        RecategorizationWizard::new(categories.clone())
+            .with_clock(Arc::new(ManualClock::new(now())))
+            .with_batch_size(400)
+    }
+
+    async fn all_previewed(
+        wizard: &RecategorizationWizard,
+        rules: &RecategorizationRules,
+    ) -> (EvaluationSnapshot, Vec<CategoryChange>) {
+        let mut changes = Vec::new();
+        let mut page = 1;
+        loop {
+            let preview = wizard
+                .preview(rules, &Pagination::new(page, 1000))
+                .await
+                .unwrap();
+            let done = preview.changes.items.len() < 1000;
+            changes.extend(preview.changes.items);
+            if done {
+                return (preview.snapshot, changes);
+            }
+            page += 1;
+        }
+    }
+
+    #[test]
+    fn test_rules_evaluate_levels_and_tags() {
+        let profile = CustomerProfile {
+            customer_id: Uuid::new_v4(),
+            name: "宏达家具".to_string(),
+            level: CustomerLevel::Normal,
+            tags: ["重点跟进".to_string()].into_iter().collect(),
+            last_interaction_at: Some(now() - Duration::days(200)),
+            accepted_quote_total_12m: Money::from_cents(6_000_000),
+        };
+        let change = rules().evaluate(&profile, now()).unwrap();
+        assert_eq!(change.level_after, CustomerLevel::Important);
+        assert_eq!(change.added_tags(), vec!["长期未联系"]);
+        assert_eq!(change.removed_tags(), vec!["重点跟进"]);
+
+        let recent = CustomerProfile {
+            last_interaction_at: Some(now() - Duration::days(10)),
+            level: CustomerLevel::Important,
+            tags: BTreeSet::new(),
+            ..profile.clone()
+        };
+        assert_eq!(rules().evaluate(&recent, now()), None);
+
+        let blacklisted = CustomerProfile {
+            level: CustomerLevel::Blacklist,
+            accepted_quote_total_12m: Money::from_cents(9_000_000),
+            ..recent
+        };
+        assert_eq!(rules().evaluate(&blacklisted, now()), None);
+    }
+
+    #[tokio::test]
+    async fn test_applied_changes_match_preview_in_batches() {
+        let categories = seeded();
+        let wizard = wizard(&categories);
+        let rules = rules();
+
+        let (snapshot, previewed) = all_previewed(&wizard, &rules).await;
+        assert_eq!(previewed.len() as u64, snapshot.total);
+        assert!(previewed.len() > 2000);
+
+        let report = wizard
+            .apply(&rules, &snapshot, &ApplyScope::AllPreviewed, Some("admin"))
+            .await
+            .unwrap();
+        assert_eq!(report.applied, previewed.len());
+        assert_eq!(*categories.applied.lock().unwrap(), previewed);
+
+        let batches = categories.batches.lock().unwrap().clone();
+        assert_eq!(report.batches, batches.len());
+        assert_eq!(batches.len(), previewed.len().div_ceil(400));
+        assert!(batches.iter().all(|size| *size <= 400));
+
+        // 执行后再预览不再有变化
+        let (after, remaining) = all_previewed(&wizard, &rules).await;
+        assert_eq!(after.total, 0);
+        assert!(remaining.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_apply_selected_subset() {
+        let categories = seeded();
+        let wizard = wizard(&categories);
+        let rules = rules();
+        let preview = wizard
+            .preview(&rules, &Pagination::new(1, 20))
+            .await
+            .unwrap();
+        let selected: BTreeSet<Uuid> = preview.changes.items[..5]
+            .iter()
+            .map(|change| change.customer_id)
+            .collect();
+
+        let report = wizard
+            .apply(
+                &rules,
+                &preview.snapshot,
+                &ApplyScope::Selected(selected.clone()),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.applied, 5);
+        assert_eq!(report.batches, 1);
+        let applied = categories.applied.lock().unwrap().clone();
+        assert_eq!(applied, preview.changes.items[..5].to_vec());
+
+        // 勾选了预览中不存在的客户
+        let preview = wizard
+            .preview(&rules, &Pagination::new(1, 20))
+            .await
+            .unwrap();
+        let unknown = ApplyScope::Selected([Uuid::new_v4()].into_iter().collect());
+        assert!(matches!(
+            wizard.apply(&rules, &preview.snapshot, &unknown, None).await,
+            Err(CoreError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_apply_rejects_diverged_snapshot() {
+        let categories = seeded();
+        let wizard = wizard(&categories);
+        let rules = rules();
+        let preview = wizard
+            .preview(&rules, &Pagination::new(1, 20))
+            .await
+            .unwrap();
+
+        // 预览后有客户新增了互动记录
+        {
+            let mut profiles = categories.profiles.lock().unwrap();
+            let stale = profiles
+                .iter_mut()
+                .find(|p| p.last_interaction_at.is_none() && p.level != CustomerLevel::Blacklist)
+                .unwrap();
+            stale.last_interaction_at = Some(now());
+        }
+        let result = wizard
+            .apply(&rules, &preview.snapshot, &ApplyScope::AllPreviewed, None)
+            .await;
+        assert!(matches!(result, Err(CoreError::Conflict(_))));
+        assert!(categories.batches.lock().unwrap().is_empty());
+    }
+}
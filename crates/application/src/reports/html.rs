@@ -0,0 +1,149 @@
+//! HTML报表渲染
+//!
+//! 生成不依赖外部资源的独立HTML：样式内联，图表为代码生成的SVG。
+
+use std::fmt::Write;
+
+use super::{BarChart, Report, ReportTable};
+
+/// 柱状图宽度
+const CHART_WIDTH: f64 = 640.0;
+/// 柱条高度
+const BAR_HEIGHT: f64 = 22.0;
+/// 柱条间距
+const BAR_GAP: f64 = 8.0;
+/// 标签区宽度
+const LABEL_WIDTH: f64 = 160.0;
+/// 数值区宽度
+const VALUE_WIDTH: f64 = 110.0;
+
+const STYLE: &str = "body{font-family:-apple-system,'Microsoft YaHei',sans-serif;color:#212529;\
+max-width:880px;margin:32px auto;padding:0 16px}\
+h1{color:#0056b3;border-bottom:2px solid #007bff;padding-bottom:8px}\
+h2{color:#495057;margin-top:32px}\
+.meta{color:#6c757d;font-size:13px}\
+table{border-collapse:collapse;margin:12px 0;min-width:360px}\
+th,td{border:1px solid #dee2e6;padding:6px 12px;text-align:left}\
+th{background:#f1f3f5}\
+td.num{text-align:right}\
+.empty{color:#6c757d;font-style:italic}";
+
+/// 渲染为独立HTML
+pub(super) fn render(report: &Report) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(out, "<title>{}</title>", escape(&report.title));
+    let _ = writeln!(out, "<style>{}</style>\n</head>\n<body>", STYLE);
+    let _ = writeln!(out, "<h1>{}</h1>", escape(&report.title));
+    let _ = writeln!(
+        out,
+        "<p class=\"meta\">统计周期：{}　生成时间：{}</p>",
+        report.coverage_label(),
+        report.generated_at.format("%Y-%m-%d %H:%M UTC")
+    );
+
+    for section in &report.sections {
+        out.push_str("<section>\n");
+        let _ = writeln!(out, "<h2>{}</h2>", escape(&section.title));
+
+        if !section.metrics.is_empty() {
+            out.push_str("<table>\n<tbody>\n");
+            for metric in &section.metrics {
+                let _ = writeln!(
+                    out,
+                    "<tr><th>{}</th><td class=\"num\">{}</td></tr>",
+                    escape(&metric.label),
+                    escape(&metric.value)
+                );
+            }
+            out.push_str("</tbody>\n</table>\n");
+        }
+
+        if let Some(table) = &section.table {
+            render_table(&mut out, table);
+        }
+        if let Some(chart) = &section.chart {
+            render_chart(&mut out, chart);
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_table(out: &mut String, table: &ReportTable) {
+    if table.rows.is_empty() {
+        out.push_str("<p class=\"empty\">本期无数据</p>\n");
+        return;
+    }
+
+    out.push_str("<table>\n<thead>\n<tr>");
+    for header in &table.headers {
+        let _ = write!(out, "<th>{}</th>", escape(header));
+    }
+    out.push_str("</tr>\n</thead>\n<tbody>\n");
+    for row in &table.rows {
+        out.push_str("<tr>");
+        for value in row {
+            let _ = write!(out, "<td>{}</td>", escape(value));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table>\n");
+}
+
+fn render_chart(out: &mut String, chart: &BarChart) {
+    let max = chart.bars.iter().map(|(_, v)| *v).fold(0.0_f64, f64::max);
+    let bar_area = CHART_WIDTH - LABEL_WIDTH - VALUE_WIDTH;
+    let height = chart.bars.len() as f64 * (BAR_HEIGHT + BAR_GAP) + BAR_GAP;
+
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" role=\"img\" aria-label=\"{t}\">",
+        w = CHART_WIDTH,
+        h = height,
+        t = escape(&chart.title)
+    );
+    for (index, (label, value)) in chart.bars.iter().enumerate() {
+        let y = BAR_GAP + index as f64 * (BAR_HEIGHT + BAR_GAP);
+        let width = if max > 0.0 { value / max * bar_area } else { 0.0 };
+        let text_y = y + BAR_HEIGHT * 0.7;
+        let _ = writeln!(
+            out,
+            "<text x=\"0\" y=\"{:.1}\" font-size=\"12\" fill=\"#495057\">{}</text>",
+            text_y,
+            escape(label)
+        );
+        let _ = writeln!(
+            out,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"3\" fill=\"#007bff\"></rect>",
+            LABEL_WIDTH, y, width, BAR_HEIGHT
+        );
+        let _ = writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"12\" fill=\"#212529\">{:.2}</text>",
+            LABEL_WIDTH + width + 6.0,
+            text_y,
+            value
+        );
+    }
+    out.push_str("</svg>\n");
+}
+
+/// 转义HTML特殊字符
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,56 @@
+//! Markdown报表渲染
+
+use std::fmt::Write;
+
+use super::{Report, ReportTable};
+
+/// 渲染为Markdown
+pub(super) fn render(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", report.title);
+    let _ = writeln!(
+        out,
+        "> 统计周期：{}　生成时间：{}\n",
+        report.coverage_label(),
+        report.generated_at.format("%Y-%m-%d %H:%M UTC")
+    );
+
+    for section in &report.sections {
+        let _ = writeln!(out, "## {}\n", section.title);
+
+        if !section.metrics.is_empty() {
+            out.push_str("| 指标 | 数值 |\n| --- | ---: |\n");
+            for metric in &section.metrics {
+                let _ = writeln!(out, "| {} | {} |", cell(&metric.label), cell(&metric.value));
+            }
+            out.push('\n');
+        }
+
+        if let Some(table) = &section.table {
+            render_table(&mut out, table);
+        }
+    }
+
+    out
+}
+
+fn render_table(out: &mut String, table: &ReportTable) {
+    if table.rows.is_empty() {
+        out.push_str("_本期无数据_\n\n");
+        return;
+    }
+
+    let header: Vec<String> = table.headers.iter().map(|h| cell(h)).collect();
+    let _ = writeln!(out, "| {} |", header.join(" | "));
+    let _ = writeln!(out, "|{}", " --- |".repeat(table.headers.len()));
+    for row in &table.rows {
+        let cells: Vec<String> = row.iter().map(|c| cell(c)).collect();
+        let _ = writeln!(out, "| {} |", cells.join(" | "));
+    }
+    out.push('\n');
+}
+
+/// 转义表格单元格中的竖线
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
@@ -0,0 +1,621 @@
+//! 统计报表模块
+//!
+//! 基于仪表盘统计查询生成月度管理报表，并渲染为Markdown或独立HTML。
+//! 报表数字与仪表盘来自同一查询处理器，避免两边口径不一致。金额均为本位币，
+//! 缺少汇率而未计入合计的单据单独列出。
+
+mod html;
+mod markdown;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveTime, Utc};
+use minicrm_core::{
+    BusinessCalendar, CancellationToken, CoreError, CoreResult, Job, JobSchedule, Locale, Money,
+    ReportPeriod, StatisticsSource,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::commands::{CommandHandler, GenerateMonthlyReportCommand};
+use crate::queries::{DashboardStats, DashboardStatsQuery, QueryHandler};
+
+/// 成交客户排行数量
+pub const TOP_CUSTOMER_LIMIT: usize = 10;
+
+/// 报表输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportFormat {
+    /// Markdown
+    Markdown,
+    /// 独立HTML（内联样式与SVG图表）
+    Html,
+}
+
+impl ReportFormat {
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+/// 指标项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metric {
+    /// 指标名称
+    pub label: String,
+    /// 格式化后的值
+    pub value: String,
+}
+
+/// 报表表格
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportTable {
+    /// 表头
+    pub headers: Vec<String>,
+    /// 数据行
+    pub rows: Vec<Vec<String>>,
+}
+
+/// 柱状图数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarChart {
+    /// 图表标题
+    pub title: String,
+    /// 柱条（标签，数值）
+    pub bars: Vec<(String, f64)>,
+}
+
+/// 报表章节
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSection {
+    /// 章节标题
+    pub title: String,
+    /// 关键指标
+    pub metrics: Vec<Metric>,
+    /// 明细表格
+    pub table: Option<ReportTable>,
+    /// 柱状图
+    pub chart: Option<BarChart>,
+}
+
+impl ReportSection {
+    pub(crate) fn new<S: Into<String>>(title: S) -> Self {
+        Self {
+            title: title.into(),
+            metrics: Vec::new(),
+            table: None,
+            chart: None,
+        }
+    }
+
+    pub(crate) fn metric<L: Into<String>, V: ToString>(mut self, label: L, value: V) -> Self {
+        self.metrics.push(Metric {
+            label: label.into(),
+            value: value.to_string(),
+        });
+        self
+    }
+}
+
+/// 月度报表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    /// 报表标题
+    pub title: String,
+    /// 统计周期
+    pub period: ReportPeriod,
+    /// 统计范围说明（如周报的“2024年第10周”），为空时显示统计周期
+    #[serde(default)]
+    pub coverage: Option<String>,
+    /// 生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 章节
+    pub sections: Vec<ReportSection>,
+}
+
+impl Report {
+    /// 从仪表盘统计构建报表
+    pub fn from_stats(stats: &DashboardStats, generated_at: DateTime<Utc>) -> Self {
+        let monthly = &stats.monthly;
+
+        let source = ReportSection::new("数据来源").metric(
+            "统计数据",
+            match &monthly.source {
+                StatisticsSource::Live => "实时计算（本月未结账）".to_string(),
+                StatisticsSource::Snapshot {
+                    closed_at,
+                    closed_by,
+                } => format!(
+                    "月结快照（{} {}结账）",
+                    closed_at.format("%Y-%m-%d %H:%M UTC"),
+                    closed_by.as_deref().unwrap_or("定时任务")
+                ),
+            },
+        );
+
+        let customers = ReportSection::new("客户增长")
+            .metric("期初客户数", monthly.customers_at_start)
+            .metric("本月新增客户", monthly.new_customers)
+            .metric("增长率", percent(monthly.customer_growth_rate()))
+            .metric("客户总数", stats.customers.total_customers);
+
+        let mut quotes = ReportSection::new("报价情况")
+            .metric("发出报价", monthly.quotes_issued)
+            .metric("报价总额", money(monthly.quotes_total_amount))
+            .metric("成交报价", monthly.quotes_accepted)
+            .metric("成交金额", money(monthly.quotes_accepted_amount))
+            .metric("成交率", percent(monthly.quote_success_rate()));
+        quotes.chart = Some(BarChart {
+            title: "报价金额".to_string(),
+            bars: vec![
+                ("报价总额".to_string(), monthly.quotes_total_amount),
+                ("成交金额".to_string(), monthly.quotes_accepted_amount),
+            ],
+        });
+
+        let top: Vec<_> = monthly.top_customers.iter().take(TOP_CUSTOMER_LIMIT).collect();
+        let mut top_customers = ReportSection::new("成交金额前十客户");
+        top_customers.table = Some(ReportTable {
+            headers: vec![
+                "排名".to_string(),
+                "客户".to_string(),
+                "成交报价".to_string(),
+                "成交金额".to_string(),
+            ],
+            rows: top
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    vec![
+                        (i + 1).to_string(),
+                        c.customer_name.clone(),
+                        c.accepted_quotes.to_string(),
+                        money(c.accepted_amount),
+                    ]
+                })
+                .collect(),
+        });
+        if !top.is_empty() {
+            top_customers.chart = Some(BarChart {
+                title: "客户成交金额".to_string(),
+                bars: top
+                    .iter()
+                    .map(|c| (c.customer_name.clone(), c.accepted_amount))
+                    .collect(),
+            });
+        }
+
+        let tasks = ReportSection::new("任务完成")
+            .metric("本月到期任务", monthly.tasks_due)
+            .metric("按时完成", monthly.tasks_completed)
+            .metric("完成率", percent(monthly.task_completion_rate()));
+
+        let tickets = ReportSection::new("售后工单SLA")
+            .metric("本月关闭工单", monthly.tickets_closed)
+            .metric("SLA内关闭", monthly.tickets_within_sla)
+            .metric("达标率", percent(monthly.ticket_sla_rate()));
+
+        let aging = &monthly.receivables_aging;
+        let mut receivables = ReportSection::new("应收账龄")
+            .metric("期末应收合计", money(aging.total()))
+            .metric("90天以上", money(aging.over_90_days));
+        receivables.chart = Some(BarChart {
+            title: "应收账龄".to_string(),
+            bars: vec![
+                ("30天以内".to_string(), aging.within_30_days),
+                ("31~60天".to_string(), aging.days_31_to_60),
+                ("61~90天".to_string(), aging.days_61_to_90),
+                ("90天以上".to_string(), aging.over_90_days),
+            ],
+        });
+
+        let mut sections = vec![
+            source,
+            customers,
+            quotes,
+            top_customers,
+            receivables,
+            tasks,
+            tickets,
+        ];
+        if !monthly.missing_rates.is_empty() {
+            let mut missing = ReportSection::new("缺少汇率的单据")
+                .metric("未计入金额合计的单据", monthly.missing_rates.len());
+            missing.table = Some(ReportTable {
+                headers: vec![
+                    "单据".to_string(),
+                    "编号".to_string(),
+                    "币种".to_string(),
+                    "金额".to_string(),
+                    "日期".to_string(),
+                ],
+                rows: monthly
+                    .missing_rates
+                    .iter()
+                    .map(|d| {
+                        vec![
+                            d.kind.clone(),
+                            d.number.clone(),
+                            d.currency.to_string(),
+                            Locale::ZhCn.money_in(d.amount, d.currency),
+                            d.date.format("%Y-%m-%d").to_string(),
+                        ]
+                    })
+                    .collect(),
+            });
+            sections.push(missing);
+        }
+
+        Self {
+            title: format!("MiniCRM 月度经营报表 {}", stats.period),
+            period: stats.period,
+            coverage: None,
+            generated_at,
+            sections,
+        }
+    }
+
+    /// 页眉中显示的统计范围
+    pub fn coverage_label(&self) -> String {
+        self.coverage
+            .clone()
+            .unwrap_or_else(|| self.period.to_string())
+    }
+
+    /// 按指定格式渲染
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => markdown::render(self),
+            ReportFormat::Html => html::render(self),
+        }
+    }
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.1}%", rate * 100.0)
+}
+
+fn money(amount: f64) -> String {
+    Locale::ZhCn.money(Money::from_yuan(amount))
+}
+
+/// 月度报表生成器
+pub struct ReportGenerator {
+    stats: Arc<dyn QueryHandler<DashboardStatsQuery>>,
+}
+
+impl std::fmt::Debug for ReportGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReportGenerator").finish_non_exhaustive()
+    }
+}
+
+impl ReportGenerator {
+    /// 使用仪表盘统计查询处理器创建生成器
+    pub fn new(stats: Arc<dyn QueryHandler<DashboardStatsQuery>>) -> Self {
+        Self { stats }
+    }
+
+    /// 构建指定周期的报表
+    ///
+    /// 报表数据总是跳过统计缓存重新计算。
+    pub async fn build(&self, period: ReportPeriod) -> CoreResult<Report> {
+        let stats = self
+            .stats
+            .handle(DashboardStatsQuery::uncached(period))
+            .await?;
+        Ok(Report::from_stats(&stats, Utc::now()))
+    }
+
+    /// 生成报表并写入文件
+    ///
+    /// 统计查询进行中也可以取消；开始写文件后不再响应取消。
+    ///
+    /// # Errors
+    ///
+    /// 统计查询失败或文件写入失败时返回错误，已取消时返回 [`CoreError::Cancelled`]。
+    pub async fn generate(
+        &self,
+        period: ReportPeriod,
+        format: ReportFormat,
+        out_path: &Path,
+        cancel: &CancellationToken,
+    ) -> CoreResult<PathBuf> {
+        cancel.check()?;
+        let report = tokio::select! {
+            report = self.build(period) => report?,
+            () = cancel.cancelled() => return Err(CoreError::Cancelled),
+        };
+        let content = report.render(format);
+        cancel.check()?;
+
+        if let Some(parent) = out_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| CoreError::Other(format!("创建报表目录失败: {}", e)))?;
+        }
+        std::fs::write(out_path, content)
+            .map_err(|e| CoreError::Other(format!("写入报表失败: {}", e)))?;
+
+        info!("月度报表已生成: {}", out_path.display());
+        Ok(out_path.to_path_buf())
+    }
+}
+
+#[async_trait]
+impl CommandHandler<GenerateMonthlyReportCommand> for ReportGenerator {
+    async fn handle(&self, command: GenerateMonthlyReportCommand) -> CoreResult<PathBuf> {
+        self.generate(
+            command.period,
+            command.format,
+            &command.out_path,
+            &command.cancel,
+        )
+        .await
+    }
+}
+
+/// 月度报表定时任务
+///
+/// 每月1日生成上一个月的报表，输出到报表目录。
+pub struct MonthlyReportJob {
+    generator: Arc<ReportGenerator>,
+    reports_dir: PathBuf,
+    format: ReportFormat,
+    calendar: BusinessCalendar,
+}
+
+impl std::fmt::Debug for MonthlyReportJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MonthlyReportJob")
+            .field("reports_dir", &self.reports_dir)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MonthlyReportJob {
+    /// 创建月度报表任务
+    pub fn new<P: Into<PathBuf>>(
+        generator: Arc<ReportGenerator>,
+        reports_dir: P,
+        format: ReportFormat,
+    ) -> Self {
+        Self {
+            generator,
+            reports_dir: reports_dir.into(),
+            format,
+            calendar: BusinessCalendar::default(),
+        }
+    }
+
+    /// 设置业务日历，"上个月"按其本地日期计算
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 指定周期的报表文件路径
+    pub fn report_path(&self, period: ReportPeriod) -> PathBuf {
+        self.reports_dir
+            .join(format!("monthly-report-{}.{}", period, self.format.extension()))
+    }
+}
+
+#[async_trait]
+impl Job for MonthlyReportJob {
+    fn name(&self) -> &str {
+        "monthly_report"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::monthly(1, NaiveTime::from_hms_opt(6, 0, 0).unwrap_or_default())
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let period = self.calendar.period_containing(Utc::now()).previous();
+        self.generator
+            .generate(
+                period,
+                self.format,
+                &self.report_path(period),
+                &CancellationToken::new(),
+            )
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minicrm_core::{
+        Currency, CustomerRanking, CustomerStatistics, MonthlyStatistics, UnconvertedDocument,
+    };
+    use uuid::Uuid;
+
+    struct SeededStats;
+
+    #[async_trait]
+    impl QueryHandler<DashboardStatsQuery> for SeededStats {
+        async fn handle(&self, query: DashboardStatsQuery) -> CoreResult<DashboardStats> {
+            if !query.bypass_cache {
+                return Err(CoreError::business("报表必须跳过统计缓存"));
+            }
+            let period = query
+                .period
+                .ok_or_else(|| CoreError::validation("缺少统计周期"))?;
+            Ok(DashboardStats {
+                customers: CustomerStatistics {
+                    total_customers: 132,
+                    ..CustomerStatistics::default()
+                },
+                period,
+                monthly: MonthlyStatistics {
+                    customers_at_start: 120,
+                    new_customers: 12,
+                    quotes_issued: 40,
+                    quotes_total_amount: 860_000.0,
+                    quotes_accepted: 18,
+                    quotes_accepted_amount: 412_500.5,
+                    top_customers: (1..=12)
+                        .map(|i| CustomerRanking {
+                            customer_id: Uuid::new_v4(),
+                            customer_name: format!("客户<{}>", i),
+                            accepted_quotes: 1,
+                            accepted_amount: 50_000.0 - f64::from(i) * 1_000.0,
+                        })
+                        .collect(),
+                    tasks_due: 50,
+                    tasks_completed: 45,
+                    tickets_closed: 20,
+                    tickets_within_sla: 19,
+                    missing_rates: Vec::new(),
+                    ..MonthlyStatistics::default()
+                },
+                ..DashboardStats::default()
+            })
+        }
+    }
+
+    async fn seeded_report() -> CoreResult<Report> {
+        let period = ReportPeriod::new(2024, 5).ok_or_else(|| CoreError::validation("周期无效"))?;
+        ReportGenerator::new(Arc::new(SeededStats)).build(period).await
+    }
+
+    /// 简单检查标签是否成对闭合
+    fn assert_well_formed(html: &str) {
+        const VOID: [&str; 3] = ["meta", "br", "!doctype"];
+        let mut stack: Vec<String> = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            let Some(end) = rest[start..].find('>').map(|e| start + e) else {
+                panic!("标签未闭合: {}", &rest[start..]);
+            };
+            let tag = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+            if tag.ends_with('/') {
+                continue;
+            }
+            let name = tag
+                .trim_start_matches('/')
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            if VOID.contains(&name.as_str()) {
+                continue;
+            }
+            if tag.starts_with('/') {
+                assert_eq!(stack.pop().as_deref(), Some(name.as_str()));
+            } else {
+                stack.push(name);
+            }
+        }
+        assert!(stack.is_empty(), "存在未闭合的标签: {:?}", stack);
+    }
+
+    #[tokio::test]
+    async fn test_markdown_contains_key_figures() -> CoreResult<()> {
+        let markdown = seeded_report().await?.render(ReportFormat::Markdown);
+
+        assert!(markdown.contains("# MiniCRM 月度经营报表 2024-05"));
+        assert!(markdown.contains("| 成交率 | 45.0% |"));
+        assert!(markdown.contains("¥412,500.50"));
+        assert!(markdown.contains("| 10 | 客户<10> |"));
+        assert!(!markdown.contains("客户<11>"));
+        assert!(markdown.contains("95.0%"));
+        assert!(markdown.contains("实时计算"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_source_is_marked() -> CoreResult<()> {
+        let mut stats = SeededStats
+            .handle(DashboardStatsQuery::uncached(
+                ReportPeriod::new(2024, 5).ok_or_else(|| CoreError::validation("周期无效"))?,
+            ))
+            .await?;
+        stats.monthly.source = StatisticsSource::Snapshot {
+            closed_at: chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 6, 1, 1, 0, 0)
+                .single()
+                .ok_or_else(|| CoreError::validation("时间无效"))?,
+            closed_by: None,
+        };
+        let markdown = Report::from_stats(&stats, Utc::now()).render(ReportFormat::Markdown);
+        assert!(markdown.contains("月结快照（2024-06-01 01:00 UTC 定时任务结账）"));
+        assert!(markdown.contains("应收账龄"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_html_is_well_formed_with_charts() -> CoreResult<()> {
+        let html = seeded_report().await?.render(ReportFormat::Html);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("45.0%"));
+        assert!(html.contains("¥412,500.50"));
+        assert!(html.contains("客户&lt;1&gt;"));
+        assert!(html.contains("<svg"));
+        assert_well_formed(&html);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_rates_listed_separately() -> CoreResult<()> {
+        let mut stats = SeededStats
+            .handle(DashboardStatsQuery::uncached(
+                ReportPeriod::new(2024, 5).ok_or_else(|| CoreError::validation("周期无效"))?,
+            ))
+            .await?;
+        let report = Report::from_stats(&stats, Utc::now());
+        assert!(report.sections.iter().all(|s| s.title != "缺少汇率的单据"));
+
+        stats.monthly.missing_rates.push(UnconvertedDocument {
+            kind: "报价".to_string(),
+            number: "Q-2024-0518".to_string(),
+            currency: Currency::USD,
+            amount: Money::from_yuan(1_234.5),
+            date: chrono::NaiveDate::from_ymd_opt(2024, 5, 18)
+                .ok_or_else(|| CoreError::validation("日期无效"))?,
+        });
+        let markdown = Report::from_stats(&stats, Utc::now()).render(ReportFormat::Markdown);
+        assert!(markdown.contains("缺少汇率的单据"));
+        assert!(markdown.contains("| 报价 | Q-2024-0518 | USD | USD 1,234.50 | 2024-05-18 |"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_writes_file() -> CoreResult<()> {
+        let dir = std::env::temp_dir().join(format!("minicrm-report-{}", Uuid::new_v4()));
+        let job = MonthlyReportJob::new(
+            Arc::new(ReportGenerator::new(Arc::new(SeededStats))),
+            &dir,
+            ReportFormat::Html,
+        );
+        let period = ReportPeriod::new(2024, 5).ok_or_else(|| CoreError::validation("周期无效"))?;
+        let path = job.report_path(period);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = job
+            .generator
+            .generate(period, ReportFormat::Html, &path, &cancel)
+            .await;
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        assert!(!path.exists());
+
+        job.generator
+            .generate(period, ReportFormat::Html, &path, &CancellationToken::new())
+            .await?;
+        assert!(path.ends_with("monthly-report-2024-05.html"));
+        assert!(path.exists());
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+//! 任务调度模块
+//!
+//! 按调度计划执行注册的后台任务。配置运行记录后，上次运行时间在重启后仍然有效。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use minicrm_core::{BusinessCalendar, Job, JobRunLog};
+use tracing::{error, info, warn};
+
+/// 单次任务运行结果
+#[derive(Debug, Clone)]
+pub struct JobRunOutcome {
+    /// 任务名称
+    pub job_name: String,
+    /// 开始时间
+    pub started_at: DateTime<Utc>,
+    /// 是否成功
+    pub success: bool,
+    /// 错误信息（如果有）
+    pub error: Option<String>,
+}
+
+/// 后台任务调度器
+#[derive(Default)]
+pub struct JobScheduler {
+    jobs: Vec<Arc<dyn Job>>,
+    calendar: BusinessCalendar,
+    last_runs: Mutex<HashMap<String, DateTime<Utc>>>,
+    run_log: Option<Arc<dyn JobRunLog>>,
+}
+
+impl std::fmt::Debug for JobScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobScheduler")
+            .field("jobs", &self.jobs.iter().map(|j| j.name()).collect::<Vec<_>>())
+            .field("calendar", &self.calendar)
+            .finish()
+    }
+}
+
+impl JobScheduler {
+    /// 创建新的调度器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置业务日历，每日/每月任务的运行时间按其本地时间解释
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 设置运行记录，任务的上次运行时间从中读取并在每次运行后写入
+    pub fn with_run_log(mut self, run_log: Arc<dyn JobRunLog>) -> Self {
+        self.run_log = Some(run_log);
+        self
+    }
+
+    /// 注册任务
+    pub fn register(mut self, job: Arc<dyn Job>) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// 已注册的任务名称
+    pub fn job_names(&self) -> Vec<String> {
+        self.jobs.iter().map(|j| j.name().to_string()).collect()
+    }
+
+    /// 查询任务上次运行时间
+    pub fn last_run(&self, job_name: &str) -> Option<DateTime<Utc>> {
+        self.last_runs
+            .lock()
+            .ok()
+            .and_then(|runs| runs.get(job_name).copied())
+    }
+
+    /// 上次运行时间，本进程内未运行过时从运行记录读取
+    async fn recorded_last_run(&self, job_name: &str) -> Option<DateTime<Utc>> {
+        if let Some(at) = self.last_run(job_name) {
+            return Some(at);
+        }
+        let run_log = self.run_log.as_ref()?;
+        match run_log.last_run(job_name).await {
+            Ok(at) => {
+                if let (Some(at), Ok(mut runs)) = (at, self.last_runs.lock()) {
+                    runs.insert(job_name.to_string(), at);
+                }
+                at
+            }
+            Err(e) => {
+                warn!("读取任务 {} 的运行记录失败: {}", job_name, e);
+                None
+            }
+        }
+    }
+
+    /// 执行在 `now` 时刻到期的全部任务
+    pub async fn run_due(&self, now: DateTime<Utc>) -> Vec<JobRunOutcome> {
+        let mut outcomes = Vec::new();
+
+        for job in &self.jobs {
+            let last_run = self.recorded_last_run(job.name()).await;
+            if !job.schedule().is_due_in(&self.calendar, last_run, now) {
+                continue;
+            }
+            outcomes.push(self.execute(job.as_ref(), now).await);
+        }
+
+        outcomes
+    }
+
+    /// 立即执行指定名称的任务（忽略调度计划）
+    pub async fn run_now(&self, job_name: &str, now: DateTime<Utc>) -> Option<JobRunOutcome> {
+        let job = self.jobs.iter().find(|j| j.name() == job_name)?;
+        Some(self.execute(job.as_ref(), now).await)
+    }
+
+    async fn execute(&self, job: &dyn Job, now: DateTime<Utc>) -> JobRunOutcome {
+        info!("运行后台任务: {}", job.name());
+        let result = job.run().await;
+
+        if let Ok(mut runs) = self.last_runs.lock() {
+            runs.insert(job.name().to_string(), now);
+        }
+        if let Some(run_log) = &self.run_log {
+            let error = result.as_ref().err().map(ToString::to_string);
+            if let Err(e) = run_log
+                .record_run(job.name(), now, error.as_deref())
+                .await
+            {
+                warn!("记录任务 {} 的运行失败: {}", job.name(), e);
+            }
+        }
+
+        match result {
+            Ok(()) => JobRunOutcome {
+                job_name: job.name().to_string(),
+                started_at: now,
+                success: true,
+                error: None,
+            },
+            Err(e) => {
+                error!("后台任务 {} 运行失败: {}", job.name(), e);
+                JobRunOutcome {
+                    job_name: job.name().to_string(),
+                    started_at: now,
+                    success: false,
+                    error: Some(e.to_string()),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! 会话模块
+//!
+//! 保存当前登录用户，并按命令所需角色在命令总线上执行权限检查。
+
+use std::sync::{Arc, RwLock};
+
+use minicrm_core::{CoreError, CoreResult, User, UserRole};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::commands::{CommandGuard, CommandInfo};
+
+/// 当前登录用户
+///
+/// 可克隆的共享句柄，上下文、处理器和权限守卫持有同一份会话。
+#[derive(Debug, Clone, Default)]
+pub struct CurrentUser {
+    inner: Arc<RwLock<Option<User>>>,
+}
+
+impl CurrentUser {
+    /// 创建未登录的会话
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登录
+    pub fn sign_in(&self, user: User) {
+        info!("用户已登录: {}", user.username);
+        if let Ok(mut current) = self.inner.write() {
+            *current = Some(user);
+        }
+    }
+
+    /// 注销
+    pub fn sign_out(&self) {
+        if let Ok(mut current) = self.inner.write() {
+            *current = None;
+        }
+    }
+
+    /// 当前用户
+    pub fn get(&self) -> Option<User> {
+        self.inner.read().ok().and_then(|current| current.clone())
+    }
+
+    /// 当前用户ID
+    pub fn user_id(&self) -> Option<Uuid> {
+        self.get().map(|user| user.id)
+    }
+
+    /// 当前用户名（用于审计字段）
+    pub fn username(&self) -> Option<String> {
+        self.get().map(|user| user.username)
+    }
+
+    /// 当前角色
+    pub fn role(&self) -> Option<UserRole> {
+        self.get().map(|user| user.role)
+    }
+}
+
+/// 权限守卫
+///
+/// 根据命令声明的 [`Command::REQUIRED_ROLE`](crate::commands::Command::REQUIRED_ROLE)
+/// 检查当前用户角色：删除、清除和设置类命令需要管理员，写操作需要销售及以上。
+#[derive(Debug, Clone)]
+pub struct PermissionGuard {
+    current_user: CurrentUser,
+}
+
+impl PermissionGuard {
+    /// 创建权限守卫
+    pub fn new(current_user: CurrentUser) -> Self {
+        Self { current_user }
+    }
+}
+
+impl CommandGuard for PermissionGuard {
+    fn check(&self, command: &CommandInfo) -> CoreResult<()> {
+        let role = self
+            .current_user
+            .role()
+            .ok_or_else(|| CoreError::permission("请先登录"))?;
+
+        if role.satisfies(command.required_role) {
+            Ok(())
+        } else {
+            Err(CoreError::permission(format!(
+                "当前角色无权执行该操作: {}",
+                command.name
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{
+        Command, CommandBus, CommandHandler, RestoreEntityCommand, SoftDeleteCustomerCommand,
+    };
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use minicrm_core::{DeletionBatch, EntityKind, RestoreReport};
+    use uuid::Uuid;
+
+    struct Purge;
+    impl Command for Purge {
+        const NAME: &'static str = "purge";
+        const REQUIRED_ROLE: UserRole = UserRole::Admin;
+        type Output = ();
+    }
+
+    struct Edit;
+    impl Command for Edit {
+        const NAME: &'static str = "edit";
+        type Output = ();
+    }
+
+    struct Read;
+    impl Command for Read {
+        const NAME: &'static str = "read";
+        const REQUIRED_ROLE: UserRole = UserRole::Viewer;
+        type Output = ();
+    }
+
+    fn user(role: UserRole) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: format!("{:?}", role).to_lowercase(),
+            display_name: String::new(),
+            role,
+            password_hash: String::new(),
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// 通过权限检查后总是返回未找到的回收站处理器
+    struct MissingTrash;
+
+    #[async_trait]
+    impl CommandHandler<SoftDeleteCustomerCommand> for MissingTrash {
+        async fn handle(&self, _command: SoftDeleteCustomerCommand) -> CoreResult<DeletionBatch> {
+            Err(CoreError::not_found("客户"))
+        }
+    }
+
+    #[async_trait]
+    impl CommandHandler<RestoreEntityCommand> for MissingTrash {
+        async fn handle(&self, _command: RestoreEntityCommand) -> CoreResult<RestoreReport> {
+            Err(CoreError::not_found("客户"))
+        }
+    }
+
+    fn allowed(guard: &PermissionGuard, info: CommandInfo) -> bool {
+        guard.check(&info).is_ok()
+    }
+
+    #[test]
+    fn test_denial_per_role() {
+        let session = CurrentUser::new();
+        let guard = PermissionGuard::new(session.clone());
+
+        // 未登录时全部拒绝
+        assert!(matches!(
+            guard.check(&CommandInfo::of::<Read>()),
+            Err(CoreError::Permission(_))
+        ));
+
+        let cases = [
+            (UserRole::Viewer, [false, false, true]),
+            (UserRole::Sales, [false, true, true]),
+            (UserRole::Admin, [true, true, true]),
+        ];
+        for (role, [purge, edit, read]) in cases {
+            session.sign_in(user(role));
+            assert_eq!(allowed(&guard, CommandInfo::of::<Purge>()), purge, "{:?}", role);
+            assert_eq!(allowed(&guard, CommandInfo::of::<Edit>()), edit, "{:?}", role);
+            assert_eq!(allowed(&guard, CommandInfo::of::<Read>()), read, "{:?}", role);
+        }
+
+        session.sign_out();
+        assert!(!allowed(&guard, CommandInfo::of::<Edit>()));
+    }
+
+    #[tokio::test]
+    async fn test_sales_cannot_delete_or_restore_customers() {
+        let session = CurrentUser::new();
+        let mut bus = CommandBus::new();
+        bus.add_guard(Arc::new(PermissionGuard::new(session.clone())));
+        bus.register::<SoftDeleteCustomerCommand>(Arc::new(MissingTrash));
+        bus.register::<RestoreEntityCommand>(Arc::new(MissingTrash));
+        let id = Uuid::new_v4();
+        let delete = || SoftDeleteCustomerCommand { customer_id: id };
+        let restore = || RestoreEntityCommand {
+            entity: EntityKind::Customer,
+            id,
+        };
+
+        session.sign_in(user(UserRole::Sales));
+        assert!(matches!(
+            bus.dispatch(delete()).await,
+            Err(CoreError::Permission(_))
+        ));
+        assert!(matches!(
+            bus.dispatch(restore()).await,
+            Err(CoreError::Permission(_))
+        ));
+
+        // 管理员通过权限检查，交给处理器
+        session.sign_in(user(UserRole::Admin));
+        assert!(matches!(
+            bus.dispatch(delete()).await,
+            Err(CoreError::NotFound(_))
+        ));
+        assert!(matches!(
+            bus.dispatch(restore()).await,
+            Err(CoreError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+//! 客户活跃度评分模块
+//!
+//! 按近12个月的互动、报价和收款计算0~100的活跃度分数。每类活动的得分由最近一次的
+//! 间隔（近因）和次数（频率）加权组成，三类再按权重合并。
+//!
+//! 分数低于风险线、且近12个月曾达到高分线的客户标记为“流失风险”；从未活跃过的新客户
+//! 分数低也不标记。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 活跃度权重
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityWeights {
+    /// 互动记录的权重
+    pub interactions: u32,
+    /// 报价的权重
+    pub quotes: u32,
+    /// 收款的权重
+    pub payments: u32,
+    /// 每类活动中近因所占的百分比（其余为频率）
+    pub recency_percent: u32,
+    /// 最近一次活动超过此天数时近因得分为零
+    pub recency_days: u32,
+    /// 近12个月达到此次数时频率得满分
+    pub full_frequency: u32,
+}
+
+impl Default for ActivityWeights {
+    fn default() -> Self {
+        Self {
+            interactions: 40,
+            quotes: 30,
+            payments: 30,
+            recency_percent: 60,
+            recency_days: 180,
+            full_frequency: 12,
+        }
+    }
+}
+
+/// 单类活动在近12个月的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelActivity {
+    /// 次数
+    pub count: u32,
+    /// 最近一次的时间
+    pub last_at: Option<DateTime<Utc>>,
+}
+
+/// 客户近12个月的活动统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityStats {
+    /// 互动记录
+    pub interactions: ChannelActivity,
+    /// 新建的报价
+    pub quotes: ChannelActivity,
+    /// 收款
+    pub payments: ChannelActivity,
+}
+
+/// 流失风险规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChurnRule {
+    /// 分数低于此值视为不活跃
+    pub risk_below: u8,
+    /// 近12个月最高分不低于此值视为曾经活跃
+    pub high_at_least: u8,
+}
+
+impl Default for ChurnRule {
+    fn default() -> Self {
+        Self {
+            risk_below: 50,
+            high_at_least: 70,
+        }
+    }
+}
+
+impl ChurnRule {
+    /// 当前分数和近12个月的最高分是否构成流失风险
+    pub fn is_at_risk(&self, score: u8, peak: u8) -> bool {
+        score < self.risk_below && peak >= self.high_at_least
+    }
+}
+
+/// 活跃度评分器
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityScorer {
+    /// 权重
+    #[serde(default)]
+    pub weights: ActivityWeights,
+    /// 流失风险规则
+    #[serde(default)]
+    pub churn: ChurnRule,
+}
+
+impl ActivityScorer {
+    /// 以指定权重和流失风险规则创建评分器
+    pub fn new(weights: ActivityWeights, churn: ChurnRule) -> Self {
+        Self { weights, churn }
+    }
+
+    /// 计算 `now` 时刻的活跃度分数（0~100）
+    pub fn score(&self, stats: &ActivityStats, now: DateTime<Utc>) -> u8 {
+        let w = &self.weights;
+        let channels = [
+            (w.interactions, &stats.interactions),
+            (w.quotes, &stats.quotes),
+            (w.payments, &stats.payments),
+        ];
+        let total_weight: u32 = channels.iter().map(|(weight, _)| weight).sum();
+        if total_weight == 0 {
+            return 0;
+        }
+        let weighted: f64 = channels
+            .iter()
+            .map(|(weight, channel)| f64::from(*weight) * self.channel_score(channel, now))
+            .sum();
+        let score = (weighted / f64::from(total_weight) * 100.0).round();
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let score = score.clamp(0.0, 100.0) as u8;
+        score
+    }
+
+    /// 单类活动的得分（0~1）
+    fn channel_score(&self, channel: &ChannelActivity, now: DateTime<Utc>) -> f64 {
+        let w = &self.weights;
+        let recency = match channel.last_at {
+            Some(last_at) if w.recency_days > 0 => {
+                #[allow(clippy::cast_precision_loss)]
+                let days = (now - last_at).num_days().max(0) as f64;
+                (1.0 - days / f64::from(w.recency_days)).max(0.0)
+            }
+            _ => 0.0,
+        };
+        let frequency = if w.full_frequency == 0 {
+            0.0
+        } else {
+            f64::from(channel.count.min(w.full_frequency)) / f64::from(w.full_frequency)
+        };
+        let recency_share = f64::from(w.recency_percent.min(100)) / 100.0;
+        recency_share * recency + (1.0 - recency_share) * frequency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 12, 31, 9, 0, 0).unwrap()
+    }
+
+    /// 每30天一次，`skip` 为最近跳过的次数
+    fn monthly(skip: u32) -> ChannelActivity {
+        ChannelActivity {
+            count: 12 - skip,
+            last_at: Some(now() - Duration::days(30 * i64::from(skip))),
+        }
+    }
+
+    #[test]
+    fn steady_customer_scores_full() {
+        let stats = ActivityStats {
+            interactions: monthly(0),
+            quotes: monthly(0),
+            payments: monthly(0),
+        };
+        assert_eq!(ActivityScorer::default().score(&stats, now()), 100);
+    }
+
+    #[test]
+    fn customer_stopped_four_months_ago() {
+        let stats = ActivityStats {
+            interactions: monthly(4),
+            quotes: monthly(4),
+            payments: monthly(4),
+        };
+        let scorer = ActivityScorer::default();
+        let score = scorer.score(&stats, now());
+        assert_eq!(score, 47);
+        assert!(scorer.churn.is_at_risk(score, 100));
+    }
+
+    #[test]
+    fn brand_new_customer_is_not_at_risk() {
+        let stats = ActivityStats {
+            interactions: ChannelActivity {
+                count: 1,
+                last_at: Some(now() - Duration::days(3)),
+            },
+            quotes: ChannelActivity {
+                count: 1,
+                last_at: Some(now() - Duration::days(1)),
+            },
+            payments: ChannelActivity::default(),
+        };
+        let scorer = ActivityScorer::default();
+        let score = scorer.score(&stats, now());
+        assert_eq!(score, 44);
+        assert!(!scorer.churn.is_at_risk(score, score));
+        assert_eq!(scorer.score(&ActivityStats::default(), now()), 0);
+    }
+
+    #[test]
+    fn weights_are_configurable() {
+        let stats = ActivityStats {
+            interactions: monthly(0),
+            ..ActivityStats::default()
+        };
+        let interactions_only = ActivityScorer::new(
+            ActivityWeights {
+                quotes: 0,
+                payments: 0,
+                ..ActivityWeights::default()
+            },
+            ChurnRule::default(),
+        );
+        assert_eq!(interactions_only.score(&stats, now()), 100);
+        assert_eq!(ActivityScorer::default().score(&stats, now()), 40);
+    }
+}
@@ -0,0 +1,215 @@
+//! 客户纪念日模块
+//!
+//! 联系人生日和首次成交纪念日每年重复，提醒任务、仪表盘“近期需维护的客户”卡片和每周摘要
+//! 都按这里的规则计算下一次日期：
+//!
+//! - 日期按业务时区的本地日历计算，当天的纪念日仍算“即将到来”
+//! - 2月29日的纪念日在非闰年按2月28日计算
+//! - 首次成交日取客户最早一份已接受报价的日期
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::calendar::LocalDate;
+use crate::entity::ReminderKind;
+
+/// 仪表盘“近期需维护的客户”卡片向后查看的天数
+pub const ANNIVERSARY_LOOKAHEAD_DAYS: u32 = 14;
+
+/// 默认提前提醒的天数
+pub const DEFAULT_ANNIVERSARY_LEAD_DAYS: u32 = 3;
+
+/// 纪念日类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AnniversaryKind {
+    /// 联系人生日
+    Birthday,
+    /// 首次成交纪念日
+    FirstDeal,
+}
+
+impl AnniversaryKind {
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnniversaryKind::Birthday => "生日",
+            AnniversaryKind::FirstDeal => "合作纪念日",
+        }
+    }
+
+    /// 对应的提醒类型
+    pub fn reminder_kind(&self) -> ReminderKind {
+        match self {
+            AnniversaryKind::Birthday => ReminderKind::ContactBirthday,
+            AnniversaryKind::FirstDeal => ReminderKind::DealAnniversary,
+        }
+    }
+}
+
+/// 纪念日在指定年份的日期（2月29日在非闰年为2月28日）
+pub fn occurrence_in(date: NaiveDate, year: i32) -> NaiveDate {
+    date.with_year(year)
+        .or_else(|| NaiveDate::from_ymd_opt(year, 2, 28))
+        .unwrap_or(date)
+}
+
+/// 客户的一个纪念日
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerAnniversary {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub customer_name: String,
+    /// 联系人
+    pub contact_person: Option<String>,
+    /// 电话
+    pub phone: Option<String>,
+    /// 负责人（用户ID），为空时提醒所有用户可见
+    pub owner_id: Option<Uuid>,
+    /// 纪念日类型
+    pub kind: AnniversaryKind,
+    /// 原始日期（生日或首次成交日）
+    pub date: NaiveDate,
+}
+
+impl CustomerAnniversary {
+    /// `today` 当天或之后的下一次纪念日
+    pub fn next_occurrence(&self, today: LocalDate) -> UpcomingAnniversary {
+        let today = today.naive();
+        let this_year = occurrence_in(self.date, today.year());
+        let on = if this_year >= today {
+            this_year
+        } else {
+            occurrence_in(self.date, today.year() + 1)
+        };
+        UpcomingAnniversary {
+            anniversary: self.clone(),
+            on: LocalDate::from(on),
+            days_until: (on - today).num_days(),
+            years: on.year() - self.date.year(),
+        }
+    }
+}
+
+/// 即将到来的纪念日
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpcomingAnniversary {
+    /// 纪念日
+    pub anniversary: CustomerAnniversary,
+    /// 日期
+    pub on: LocalDate,
+    /// 距今天数（当天为0）
+    pub days_until: i64,
+    /// 周年数（生日为年龄，首次成交为合作年数）
+    pub years: i32,
+}
+
+impl UpcomingAnniversary {
+    /// 提醒标题，如“华东建材 王经理 生日（3月8日）”
+    pub fn title(&self) -> String {
+        let who = match &self.anniversary.contact_person {
+            Some(person) if !person.trim().is_empty() => {
+                format!("{} {}", self.anniversary.customer_name, person.trim())
+            }
+            _ => self.anniversary.customer_name.clone(),
+        };
+        let what = match self.anniversary.kind {
+            AnniversaryKind::Birthday => self.anniversary.kind.label().to_string(),
+            AnniversaryKind::FirstDeal => format!("合作{}周年", self.years),
+        };
+        format!(
+            "{} {}（{}月{}日）",
+            who,
+            what,
+            self.on.naive().month(),
+            self.on.naive().day()
+        )
+    }
+}
+
+/// `today` 起 `days` 天内（含首尾两天）的纪念日，按日期排序
+pub fn upcoming_within(
+    anniversaries: &[CustomerAnniversary],
+    today: LocalDate,
+    days: u32,
+) -> Vec<UpcomingAnniversary> {
+    let mut upcoming: Vec<UpcomingAnniversary> = anniversaries
+        .iter()
+        .map(|a| a.next_occurrence(today))
+        .filter(|u| u.days_until <= i64::from(days))
+        .collect();
+    upcoming.sort_by(|a, b| {
+        (a.on, &a.anniversary.customer_name).cmp(&(b.on, &b.anniversary.customer_name))
+    });
+    upcoming
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn today(year: i32, month: u32, day: u32) -> LocalDate {
+        LocalDate::new(year, month, day).unwrap()
+    }
+
+    fn anniversary(name: &str, kind: AnniversaryKind, on: NaiveDate) -> CustomerAnniversary {
+        CustomerAnniversary {
+            customer_id: Uuid::new_v4(),
+            customer_name: name.to_string(),
+            contact_person: Some("王经理".to_string()),
+            phone: None,
+            owner_id: None,
+            kind,
+            date: on,
+        }
+    }
+
+    #[test]
+    fn test_leap_day_maps_to_february_28() {
+        let birthday = anniversary("华东建材", AnniversaryKind::Birthday, date(1988, 2, 29));
+        assert_eq!(occurrence_in(date(1988, 2, 29), 2023), date(2023, 2, 28));
+        assert_eq!(occurrence_in(date(1988, 2, 29), 2024), date(2024, 2, 29));
+
+        let next = birthday.next_occurrence(today(2023, 2, 1));
+        assert_eq!(next.on, today(2023, 2, 28));
+        assert_eq!(next.days_until, 27);
+        assert_eq!(next.title(), "华东建材 王经理 生日（2月28日）");
+
+        // 非闰年的2月28日已过，下一次是闰年的2月29日
+        let next = birthday.next_occurrence(today(2023, 3, 1));
+        assert_eq!(next.on, today(2024, 2, 29));
+        assert_eq!(next.years, 36);
+    }
+
+    #[test]
+    fn test_lookahead_window_boundaries() {
+        let list = vec![
+            anniversary("当天", AnniversaryKind::Birthday, date(1980, 6, 1)),
+            anniversary("第14天", AnniversaryKind::FirstDeal, date(2020, 6, 15)),
+            anniversary("第15天", AnniversaryKind::Birthday, date(1990, 6, 16)),
+            anniversary("昨天", AnniversaryKind::Birthday, date(1985, 5, 31)),
+        ];
+        let upcoming = upcoming_within(&list, today(2024, 6, 1), ANNIVERSARY_LOOKAHEAD_DAYS);
+        let names: Vec<&str> = upcoming
+            .iter()
+            .map(|u| u.anniversary.customer_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["当天", "第14天"]);
+        assert_eq!(upcoming[0].days_until, 0);
+        assert_eq!(upcoming[1].days_until, 14);
+        assert_eq!(upcoming[1].title(), "第14天 王经理 合作4周年（6月15日）");
+
+        // 跨年：12月下旬看次年1月初
+        let upcoming = upcoming_within(&list[3..], today(2024, 12, 25), 14);
+        assert!(upcoming.is_empty());
+        let new_year = anniversary("元旦", AnniversaryKind::Birthday, date(1979, 1, 2));
+        let upcoming = upcoming_within(&[new_year], today(2024, 12, 25), 14);
+        assert_eq!(upcoming[0].on, today(2025, 1, 2));
+        assert_eq!(upcoming[0].days_until, 8);
+    }
+}
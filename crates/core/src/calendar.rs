@@ -0,0 +1,174 @@
+//! 业务日历模块
+//!
+//! 到期日、"今天到期"、本月统计等按业务时区的本地日历计算，
+//! 避免UTC日期在凌晨或月初前后把任务算到错误的一天
+
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CoreError, CoreResult};
+use crate::types::ReportPeriod;
+
+/// 默认业务时区
+pub const DEFAULT_BUSINESS_TIMEZONE: &str = "Asia/Shanghai";
+
+/// 业务时区下的本地日期
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LocalDate(NaiveDate);
+
+impl LocalDate {
+    /// 创建本地日期，日期无效时返回 `None`
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        NaiveDate::from_ymd_opt(year, month, day).map(Self)
+    }
+
+    /// 底层日期
+    pub fn naive(&self) -> NaiveDate {
+        self.0
+    }
+
+    /// 偏移指定天数
+    pub fn add_days(&self, days: i64) -> Self {
+        Self(self.0 + Duration::days(days))
+    }
+
+    /// 两个日期相差的天数（`self - other`）
+    pub fn days_since(&self, other: LocalDate) -> i64 {
+        (self.0 - other.0).num_days()
+    }
+
+    /// 日期所在的统计周期
+    pub fn period(&self) -> ReportPeriod {
+        ReportPeriod {
+            year: self.0.year(),
+            month: self.0.month(),
+        }
+    }
+}
+
+impl From<NaiveDate> for LocalDate {
+    fn from(date: NaiveDate) -> Self {
+        Self(date)
+    }
+}
+
+impl std::fmt::Display for LocalDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.format("%Y-%m-%d"))
+    }
+}
+
+/// 业务日历
+///
+/// 持有业务时区，负责UTC时刻与本地日期之间的换算。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusinessCalendar {
+    tz: Tz,
+}
+
+impl Default for BusinessCalendar {
+    fn default() -> Self {
+        Self {
+            tz: chrono_tz::Asia::Shanghai,
+        }
+    }
+}
+
+impl BusinessCalendar {
+    /// 使用指定时区创建日历
+    pub fn new(tz: Tz) -> Self {
+        Self { tz }
+    }
+
+    /// 按IANA时区名创建日历（如 `Asia/Shanghai`）
+    ///
+    /// # Errors
+    ///
+    /// 时区名无法识别时返回配置错误。
+    pub fn from_name(name: &str) -> CoreResult<Self> {
+        name.parse::<Tz>()
+            .map(Self::new)
+            .map_err(|_| CoreError::configuration(format!("未知的业务时区: {}", name)))
+    }
+
+    /// UTC日历
+    pub fn utc() -> Self {
+        Self::new(chrono_tz::UTC)
+    }
+
+    /// 业务时区
+    pub fn timezone(&self) -> Tz {
+        self.tz
+    }
+
+    /// 时刻对应的本地日期
+    pub fn local_date(&self, at: DateTime<Utc>) -> LocalDate {
+        LocalDate(at.with_timezone(&self.tz).date_naive())
+    }
+
+    /// 时刻对应的本地时间
+    pub fn local_time(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        at.with_timezone(&self.tz).naive_local()
+    }
+
+    /// 本地时间换算为UTC时刻
+    ///
+    /// 夏令时重叠时取较早的时刻，跳过的时间顺延到跳变之后。
+    pub fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self.tz.from_local_datetime(&local) {
+            LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at.with_timezone(&Utc),
+            LocalResult::None => self.to_utc(local + Duration::hours(1)),
+        }
+    }
+
+    /// 本地日期零点对应的UTC时刻
+    pub fn start_of_day(&self, date: LocalDate) -> DateTime<Utc> {
+        self.to_utc(date.0.and_hms_opt(0, 0, 0).unwrap_or_default())
+    }
+
+    /// 本地日期的UTC范围 `[开始, 结束)`
+    pub fn day_bounds(&self, date: LocalDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        (self.start_of_day(date), self.start_of_day(date.add_days(1)))
+    }
+
+    /// 包含指定时刻的本地统计周期
+    pub fn period_containing(&self, at: DateTime<Utc>) -> ReportPeriod {
+        self.local_date(at).period()
+    }
+
+    /// 统计周期的UTC范围 `[开始, 结束)`
+    pub fn period_bounds(&self, period: ReportPeriod) -> (DateTime<Utc>, DateTime<Utc>) {
+        let first = |p: ReportPeriod| {
+            LocalDate::new(p.year, p.month, 1).unwrap_or(LocalDate(NaiveDate::MIN))
+        };
+        (
+            self.start_of_day(first(period)),
+            self.start_of_day(first(period.next())),
+        )
+    }
+
+    /// 到期时间是否落在 `now` 所在的本地当天
+    pub fn due_today(&self, due: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.local_date(due) == self.local_date(now)
+    }
+
+    /// 截至本地日期 `today`，到期时间是否已逾期
+    ///
+    /// 到期当天不算逾期，从次日零点起才算。
+    pub fn overdue_as_of(&self, due: DateTime<Utc>, today: LocalDate) -> bool {
+        self.local_date(due) < today
+    }
+
+    /// 到期时间是否在今天起 `days` 天之内（含今天，不含已逾期）
+    pub fn due_within(&self, due: DateTime<Utc>, now: DateTime<Utc>, days: i64) -> bool {
+        let today = self.local_date(now);
+        let due = self.local_date(due);
+        due >= today && due <= today.add_days(days)
+    }
+
+    /// 自 `since` 起到 `now` 经过的本地日历天数
+    pub fn aging_days(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+        self.local_date(now).days_since(self.local_date(since))
+    }
+}
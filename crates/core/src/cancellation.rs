@@ -0,0 +1,76 @@
+//! 取消令牌模块
+//!
+//! 导出、报表、归档等耗时操作接收一个 [`CancellationToken`]，在批次或行之间检查；
+//! 界面进度对话框的“取消”按钮调用 [`CancellationToken::cancel`]。操作被取消时返回
+//! [`CoreError::Cancelled`]，并删除已写出的部分文件。
+
+use crate::error::{CoreError, CoreResult};
+
+/// 取消令牌
+///
+/// 克隆的令牌共享同一取消状态；默认值为未取消的新令牌。
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(tokio_util::sync::CancellationToken);
+
+impl CancellationToken {
+    /// 创建未取消的令牌
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消（可重复调用）
+    pub fn cancel(&self) {
+        self.0.cancel();
+    }
+
+    /// 是否已请求取消
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    /// 在批次或行之间检查，已取消时返回错误
+    ///
+    /// # Errors
+    ///
+    /// 已请求取消时返回 [`CoreError::Cancelled`]。
+    pub fn check(&self) -> CoreResult<()> {
+        if self.is_cancelled() {
+            Err(CoreError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 等待取消请求
+    pub async fn cancelled(&self) {
+        self.0.cancelled().await;
+    }
+
+    /// 子令牌：随本令牌一起取消，单独取消子令牌不影响本令牌
+    pub fn child(&self) -> Self {
+        Self(self.0.child_token())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_propagates_to_clones_and_children() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        let child = token.child();
+        assert!(token.check().is_ok());
+
+        child.cancel();
+        assert!(child.is_cancelled());
+        assert!(!token.is_cancelled());
+
+        let child = token.child();
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(child.is_cancelled());
+        assert!(matches!(token.check(), Err(CoreError::Cancelled)));
+    }
+}
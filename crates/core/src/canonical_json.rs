@@ -0,0 +1,555 @@
+//! 规范JSON模块
+//!
+//! 报价修订、事件发件箱和变更包会长期保存实体的JSON，之后再重放或比较。这些实体的字段名
+//! 都用 `#[serde(rename)]` 固定，Rust 字段改名不影响已保存的JSON；每种实体有结构版本号，
+//! 与JSON一起保存，读取旧版本时先经 [`CanonicalEntity::upgrade`] 逐级转为当前结构。
+//!
+//! 修改规范实体的字段时：
+//! - 新增字段须带 `#[serde(default)]`（旧JSON缺少该字段仍可读取），并加入 `FIELDS`；
+//! - 删除字段或改变字段含义须提升 `SCHEMA_VERSION`，并在 `upgrade` 中转换上一版本的JSON。
+//!
+//! `tests/fixtures/canonical` 下的各版本样例必须一直能读取，不要修改已有的样例文件。
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::entity::{
+    Address, Customer, CustomerContact, Interaction, KnowledgeArticle, Opportunity, Order, Product,
+    ProductPrice, PurchaseQuote, Quote, QuoteItem, Reminder, ServiceTicket, Supplier, Task,
+    TaskNote,
+};
+use crate::error::{CoreError, CoreResult};
+use crate::events::EventEnvelope;
+use crate::money::Money;
+
+/// 以规范JSON保存的实体
+pub trait CanonicalEntity: Serialize + DeserializeOwned {
+    /// 实体名称（写入变更包文件头，不能修改）
+    const ENTITY: &'static str;
+
+    /// 当前结构版本
+    const SCHEMA_VERSION: u32;
+
+    /// 当前版本序列化后的全部字段
+    const FIELDS: &'static [&'static str];
+
+    /// 把 `from` 版本的JSON转为 `from + 1` 版本
+    ///
+    /// # Errors
+    ///
+    /// 默认没有升级方法（实体只有一个版本），返回 [`CoreError::Validation`]。
+    fn upgrade(from: u32, value: Value) -> CoreResult<Value> {
+        let _ = value;
+        Err(CoreError::validation(format!(
+            "{} 没有从结构版本 {} 升级的方法",
+            Self::ENTITY,
+            from
+        )))
+    }
+}
+
+/// 序列化为当前版本（[`CanonicalEntity::SCHEMA_VERSION`]）的规范JSON
+///
+/// # Errors
+///
+/// 序列化失败时返回 [`CoreError::Serialization`]。
+pub fn to_canonical_json<E: CanonicalEntity>(entity: &E) -> CoreResult<Value> {
+    Ok(serde_json::to_value(entity)?)
+}
+
+/// 读取 `version` 版本的规范JSON，旧版本先逐级升级
+///
+/// # Errors
+///
+/// 版本为0或比当前版本新（由更新的应用写入）时返回 [`CoreError::Validation`]，
+/// 升级或解析失败时返回错误。
+pub fn from_canonical_json<E: CanonicalEntity>(version: u32, value: Value) -> CoreResult<E> {
+    if version == 0 || version > E::SCHEMA_VERSION {
+        return Err(CoreError::validation(format!(
+            "{} 的结构版本 {} 不受支持（本机为 {}），请先升级应用",
+            E::ENTITY,
+            version,
+            E::SCHEMA_VERSION
+        )));
+    }
+    let mut value = value;
+    for from in version..E::SCHEMA_VERSION {
+        value = E::upgrade(from, value)?;
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// 第2版明细增加小计，读取第1版时按明细升级
+impl CanonicalEntity for Quote {
+    const ENTITY: &'static str = "quote";
+    const SCHEMA_VERSION: u32 = 2;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "quote_number",
+        "customer_id",
+        "status",
+        "total_amount",
+        "currency",
+        "items",
+        "valid_until",
+        "remarks",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+    ];
+
+    fn upgrade(from: u32, value: Value) -> CoreResult<Value> {
+        let mut value = value;
+        let quote_id = value.get("id").cloned().unwrap_or(Value::Null);
+        if let Some(items) = value.get_mut("items").and_then(Value::as_array_mut) {
+            for item in items {
+                *item = QuoteItem::upgrade(from, item.take())?;
+                item["quote_id"] = quote_id.clone();
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// 报价明细随报价保存，结构变化时同时提升报价的版本
+///
+/// 第2版增加所属报价、计量单位和小计（按数量×单价四舍五入到分）。
+impl CanonicalEntity for QuoteItem {
+    const ENTITY: &'static str = "quote_item";
+    const SCHEMA_VERSION: u32 = 2;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "quote_id",
+        "product_id",
+        "product_name",
+        "specification",
+        "unit",
+        "quantity",
+        "unit_price",
+        "line_total",
+    ];
+
+    fn upgrade(from: u32, value: Value) -> CoreResult<Value> {
+        if from != 1 {
+            return Err(CoreError::validation(format!(
+                "{} 没有从结构版本 {} 升级的方法",
+                Self::ENTITY,
+                from
+            )));
+        }
+        let mut value = value;
+        let number = |key: &str| value.get(key).and_then(Value::as_f64).unwrap_or(0.0);
+        let line_total = Money::from_yuan(number("quantity") * number("unit_price"));
+        value["line_total"] = Value::from(line_total.as_yuan());
+        Ok(value)
+    }
+}
+
+impl CanonicalEntity for Address {
+    const ENTITY: &'static str = "address";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] =
+        &["province", "city", "district", "street", "contact", "phone"];
+}
+
+impl CanonicalEntity for Customer {
+    const ENTITY: &'static str = "customer";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "contact_person",
+        "contact_birthday",
+        "phone",
+        "email",
+        "address",
+        "level",
+        "credit_limit",
+        "credit_hold",
+        "owner_id",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+    ];
+}
+
+impl CanonicalEntity for CustomerContact {
+    const ENTITY: &'static str = "customer_contact";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "customer_id",
+        "name",
+        "phone",
+        "email",
+        "is_primary",
+        "created_at",
+    ];
+}
+
+impl CanonicalEntity for Supplier {
+    const ENTITY: &'static str = "supplier";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "contact_person",
+        "phone",
+        "email",
+        "address",
+        "level",
+        "created_at",
+        "updated_at",
+    ];
+}
+
+impl CanonicalEntity for PurchaseQuote {
+    const ENTITY: &'static str = "purchase_quote";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "supplier_id",
+        "product_id",
+        "unit_price",
+        "moq",
+        "valid_until",
+        "received_at",
+        "notes",
+    ];
+}
+
+impl CanonicalEntity for Product {
+    const ENTITY: &'static str = "product";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "specification",
+        "category",
+        "retired",
+        "cost_price",
+        "created_at",
+        "updated_at",
+    ];
+}
+
+impl CanonicalEntity for ProductPrice {
+    const ENTITY: &'static str = "product_price";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "product_id",
+        "price",
+        "cost_price",
+        "effective_from",
+        "created_by",
+        "created_at",
+    ];
+}
+
+impl CanonicalEntity for Task {
+    const ENTITY: &'static str = "task";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "title",
+        "description",
+        "status",
+        "priority",
+        "customer_id",
+        "supplier_id",
+        "due_date",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+        "assigned_to",
+    ];
+}
+
+impl CanonicalEntity for TaskNote {
+    const ENTITY: &'static str = "task_note";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &["id", "task_id", "body", "author", "created_at"];
+}
+
+impl CanonicalEntity for Opportunity {
+    const ENTITY: &'static str = "opportunity";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "customer_id",
+        "title",
+        "stage",
+        "expected_amount",
+        "probability",
+        "expected_close_date",
+        "quote_id",
+        "lost_reason",
+        "notes",
+        "owner_id",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+    ];
+}
+
+/// 送货地址随订单保存，地址结构变化时同时提升订单的版本
+impl CanonicalEntity for Order {
+    const ENTITY: &'static str = "order";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "order_number",
+        "customer_id",
+        "quote_id",
+        "total_amount",
+        "currency",
+        "delivery_status",
+        "delivery_date",
+        "delivery_address",
+        "vehicle",
+        "driver",
+        "signed_by",
+        "delivered_at",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+    ];
+}
+
+impl CanonicalEntity for Interaction {
+    const ENTITY: &'static str = "interaction";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "customer_id",
+        "kind",
+        "content",
+        "occurred_at",
+        "created_by",
+    ];
+}
+
+impl CanonicalEntity for Reminder {
+    const ENTITY: &'static str = "reminder";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "kind",
+        "entity",
+        "entity_id",
+        "title",
+        "due_at",
+        "created_at",
+        "dismissed_at",
+        "recipient",
+        "snoozed_until",
+    ];
+}
+
+impl CanonicalEntity for ServiceTicket {
+    const ENTITY: &'static str = "service_ticket";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "ticket_number",
+        "customer_id",
+        "problem_category",
+        "description",
+        "solution_method",
+        "status",
+        "priority",
+        "created_at",
+        "updated_at",
+    ];
+}
+
+impl CanonicalEntity for KnowledgeArticle {
+    const ENTITY: &'static str = "knowledge_article";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "title",
+        "problem_category",
+        "symptoms",
+        "solution",
+        "product_categories",
+        "created_by",
+        "usage_count",
+        "created_at",
+        "updated_at",
+    ];
+}
+
+impl CanonicalEntity for EventEnvelope {
+    const ENTITY: &'static str = "event";
+    const SCHEMA_VERSION: u32 = 1;
+    const FIELDS: &'static [&'static str] = &["id", "occurred_at", "event", "correlation_id"];
+}
+
+/// 各种领域事件的字段（事件随信封保存，结构变化时提升信封的版本）
+pub const DOMAIN_EVENT_FIELDS: &[(&str, &[&str])] = &[
+    ("EntityCreated", &["entity", "id"]),
+    ("EntityUpdated", &["entity", "id"]),
+    ("EntitySoftDeleted", &["entity", "id"]),
+    ("EntityRestored", &["entity", "id"]),
+    ("EntityPurged", &["entity", "id", "was_soft_deleted"]),
+    ("QuoteStatusChanged", &["quote_id", "customer_id", "from", "to"]),
+    ("QuoteCreatedFromOpportunity", &["quote_id", "opportunity_id"]),
+    ("InteractionLogged", &["interaction_id", "customer_id"]),
+    ("PaymentRecorded", &["payment_id", "order_id", "customer_id"]),
+    ("TaskAssigned", &["task_id", "assignee"]),
+    ("TaskStatusChanged", &["task_id", "from", "to"]),
+    ("TaskNoteAdded", &["task_id", "note_id"]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const QUOTE_V1: &str = include_str!("../tests/fixtures/canonical/quote.v1.json");
+    const QUOTE_V2: &str = include_str!("../tests/fixtures/canonical/quote.v2.json");
+    const ADDRESS_V1: &str = include_str!("../tests/fixtures/canonical/address.v1.json");
+    const EVENTS_V1: &str = include_str!("../tests/fixtures/canonical/events.v1.json");
+    const CUSTOMER_V1: &str = include_str!("../tests/fixtures/canonical/customer.v1.json");
+    const CUSTOMER_CONTACT_V1: &str =
+        include_str!("../tests/fixtures/canonical/customer_contact.v1.json");
+    const SUPPLIER_V1: &str = include_str!("../tests/fixtures/canonical/supplier.v1.json");
+    const PURCHASE_QUOTE_V1: &str =
+        include_str!("../tests/fixtures/canonical/purchase_quote.v1.json");
+    const PRODUCT_V1: &str = include_str!("../tests/fixtures/canonical/product.v1.json");
+    const PRODUCT_PRICE_V1: &str =
+        include_str!("../tests/fixtures/canonical/product_price.v1.json");
+    const TASK_V1: &str = include_str!("../tests/fixtures/canonical/task.v1.json");
+    const TASK_NOTE_V1: &str = include_str!("../tests/fixtures/canonical/task_note.v1.json");
+    const OPPORTUNITY_V1: &str = include_str!("../tests/fixtures/canonical/opportunity.v1.json");
+    const ORDER_V1: &str = include_str!("../tests/fixtures/canonical/order.v1.json");
+    const INTERACTION_V1: &str = include_str!("../tests/fixtures/canonical/interaction.v1.json");
+    const REMINDER_V1: &str = include_str!("../tests/fixtures/canonical/reminder.v1.json");
+    const SERVICE_TICKET_V1: &str =
+        include_str!("../tests/fixtures/canonical/service_ticket.v1.json");
+    const KNOWLEDGE_ARTICLE_V1: &str =
+        include_str!("../tests/fixtures/canonical/knowledge_article.v1.json");
+
+    fn keys(value: &Value) -> BTreeSet<&str> {
+        value
+            .as_object()
+            .expect("规范JSON应为对象")
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn documented(fields: &[&'static str]) -> BTreeSet<&'static str> {
+        fields.iter().copied().collect()
+    }
+
+    /// 读取样例后重新序列化，字段集必须与文档一致
+    fn round_trip<E: CanonicalEntity>(version: u32, fixture: &str) -> (E, Value) {
+        let value: Value = serde_json::from_str(fixture).unwrap();
+        let entity: E = from_canonical_json(version, value).unwrap();
+        let current = to_canonical_json(&entity).unwrap();
+        assert_eq!(keys(&current), documented(E::FIELDS), "{}", E::ENTITY);
+        (entity, current)
+    }
+
+    #[test]
+    fn test_v1_fixtures_deserialize_with_documented_fields() {
+        let (quote, current) = round_trip::<Quote>(1, QUOTE_V1);
+        assert_eq!(quote.quote_number, "BJ20240615-003");
+        assert_eq!(quote.items.len(), 2);
+        for item in current["items"].as_array().unwrap() {
+            assert_eq!(keys(item), documented(QuoteItem::FIELDS));
+        }
+        // 第1版明细升级后带上小计和所属报价
+        let totals: Vec<Money> = quote.items.iter().map(|item| item.line_total).collect();
+        assert_eq!(totals, vec![Money::from_yuan(1200.0), Money::from_yuan(850.0)]);
+        assert!(quote.items.iter().all(|item| item.quote_id == quote.id));
+        quote.validate_totals().unwrap();
+
+        let (address, _) = round_trip::<Address>(1, ADDRESS_V1);
+        assert_eq!(address.one_line(), "浙江省杭州市余杭区良渚街道玉架山路18号");
+
+        let envelopes: Vec<Value> = serde_json::from_str(EVENTS_V1).unwrap();
+        let mut seen = BTreeSet::new();
+        for value in envelopes {
+            let envelope: EventEnvelope = from_canonical_json(1, value).unwrap();
+            let current = to_canonical_json(&envelope).unwrap();
+            assert_eq!(keys(&current), documented(EventEnvelope::FIELDS));
+
+            let (tag, body) = current["event"].as_object().unwrap().iter().next().unwrap();
+            let (_, fields) = DOMAIN_EVENT_FIELDS
+                .iter()
+                .find(|(name, _)| *name == tag.as_str())
+                .unwrap_or_else(|| panic!("未记录字段的事件: {tag}"));
+            assert_eq!(keys(body), documented(fields), "{tag}");
+            seen.insert(tag.clone());
+        }
+        assert_eq!(seen.len(), DOMAIN_EVENT_FIELDS.len());
+    }
+
+    /// 只有第1版的实体：读取样例后原样写回
+    fn unchanged<E: CanonicalEntity>(fixture: &str) -> E {
+        let (entity, current) = round_trip::<E>(1, fixture);
+        assert_eq!(current, serde_json::from_str::<Value>(fixture).unwrap(), "{}", E::ENTITY);
+        entity
+    }
+
+    #[test]
+    fn test_entity_v1_fixtures_round_trip_unchanged() {
+        let customer: Customer = unchanged(CUSTOMER_V1);
+        assert_eq!(customer.credit_limit, Some(Money::from_yuan(50000.0)));
+        let contact: CustomerContact = unchanged(CUSTOMER_CONTACT_V1);
+        assert_eq!(contact.customer_id, customer.id);
+        let supplier: Supplier = unchanged(SUPPLIER_V1);
+        assert_eq!(supplier.name, "临沂鲁南木业");
+        let product: Product = unchanged(PRODUCT_V1);
+        let price: ProductPrice = unchanged(PRODUCT_PRICE_V1);
+        assert_eq!(price.product_id, product.id);
+        let purchase: PurchaseQuote = unchanged(PURCHASE_QUOTE_V1);
+        assert_eq!(purchase.supplier_id, supplier.id);
+        let task: Task = unchanged(TASK_V1);
+        assert_eq!(task.customer_id, Some(customer.id));
+        let note: TaskNote = unchanged(TASK_NOTE_V1);
+        assert_eq!(note.task_id, task.id);
+        let opportunity: Opportunity = unchanged(OPPORTUNITY_V1);
+        assert_eq!(opportunity.weighted_amount(), Money::from_yuan(76800.0));
+        let order: Order = unchanged(ORDER_V1);
+        assert_eq!(
+            order.delivery_address.map(|address| address.one_line()),
+            Some("浙江省杭州市余杭区良渚街道玉架山路18号".to_string())
+        );
+        let interaction: Interaction = unchanged(INTERACTION_V1);
+        assert_eq!(interaction.customer_id, customer.id);
+        let reminder: Reminder = unchanged(REMINDER_V1);
+        assert_eq!(reminder.entity_id, task.id);
+        let ticket: ServiceTicket = unchanged(SERVICE_TICKET_V1);
+        let article: KnowledgeArticle = unchanged(KNOWLEDGE_ARTICLE_V1);
+        assert_eq!(article.problem_category, ticket.problem_category);
+    }
+
+    #[test]
+    fn test_current_quote_fixture_round_trips_unchanged() {
+        let (quote, current) = round_trip::<Quote>(Quote::SCHEMA_VERSION, QUOTE_V2);
+        assert_eq!(current, serde_json::from_str::<Value>(QUOTE_V2).unwrap());
+        assert_eq!(quote.items[1].unit.as_deref(), Some("米"));
+        quote.validate_totals().unwrap();
+    }
+
+    #[test]
+    fn test_versions_outside_range_are_rejected() {
+        let value: Value = serde_json::from_str(ADDRESS_V1).unwrap();
+        for version in [0, Address::SCHEMA_VERSION + 1] {
+            let err = from_canonical_json::<Address>(version, value.clone()).unwrap_err();
+            assert!(matches!(err, CoreError::Validation(_)), "{err}");
+        }
+        assert!(Address::upgrade(1, value).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! 时钟抽象模块
+//!
+//! 业务代码通过时钟接口获取当前时间，便于测试中模拟时间流逝
+
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// 时钟接口
+pub trait Clock: Send + Sync {
+    /// 当前时间
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// 手动时钟（用于测试）
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    /// 创建停在指定时刻的时钟
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// 设置当前时间
+    pub fn set(&self, now: DateTime<Utc>) {
+        if let Ok(mut current) = self.now.lock() {
+            *current = now;
+        }
+    }
+
+    /// 时间前进指定时长
+    pub fn advance(&self, by: Duration) {
+        if let Ok(mut current) = self.now.lock() {
+            *current += by;
+        }
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now.lock().map_or_else(|_| Utc::now(), |now| *now)
+    }
+}
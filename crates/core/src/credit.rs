@@ -0,0 +1,177 @@
+//! 客户信用额度模块
+//!
+//! 信用占用 = 已送达未收款的应收款 + 已确认未送达的订单中未收款的部分（均折算为本位币）。
+//! 接受报价或确认订单前，用占用加上本次金额与额度比较：正好等于额度时允许，超出时报错并给出差额。
+//! 冻结（credit_hold）的客户一律不允许，管理员也不能越过。
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{CoreError, CoreResult};
+use crate::money::Money;
+
+/// 客户当前的信用状况
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreditProfile {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub customer_name: String,
+    /// 信用额度，为空表示不限额
+    pub credit_limit: Option<Money>,
+    /// 是否冻结
+    pub credit_hold: bool,
+    /// 已送达订单的未收款
+    pub outstanding_receivables: Money,
+    /// 已确认未送达订单的未收款
+    pub unpaid_confirmed: Money,
+    /// 因缺少汇率未计入占用的订单数
+    pub missing_rate_orders: u64,
+}
+
+impl CreditProfile {
+    /// 当前信用占用
+    pub fn exposure(&self) -> Money {
+        self.outstanding_receivables + self.unpaid_confirmed
+    }
+
+    /// 剩余可用额度（已超额时为零），不限额时为空
+    pub fn available(&self) -> Option<Money> {
+        self.credit_limit
+            .map(|limit| std::cmp::max(limit - self.exposure(), Money::ZERO))
+    }
+
+    /// 再增加 `amount` 后超出额度的部分；不超额或不限额时为空
+    pub fn shortfall(&self, amount: Money) -> Option<Money> {
+        let limit = self.credit_limit?;
+        let after = self.exposure() + amount;
+        (after > limit).then(|| after - limit)
+    }
+
+    /// 检查能否再增加 `amount` 的占用
+    ///
+    /// 冻结优先于额度检查；超额时的业务错误中包含差额。
+    pub fn check(&self, amount: Money) -> CoreResult<()> {
+        self.ensure_not_held()?;
+        match self.shortfall(amount) {
+            Some(shortfall) => Err(CoreError::business(format!(
+                "客户“{}”信用额度不足：额度 {}，已占用 {}，本次 {}，超出 {}",
+                self.customer_name,
+                self.credit_limit.unwrap_or(Money::ZERO),
+                self.exposure(),
+                amount,
+                shortfall
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// 管理员越过额度检查
+    ///
+    /// 冻结的客户仍然不允许；超额时返回需要写入审计的越权记录，未超额时为空。
+    pub fn override_credit(
+        &self,
+        amount: Money,
+        approver: &str,
+        subject: &str,
+    ) -> CoreResult<Option<CreditOverride>> {
+        self.ensure_not_held()?;
+        Ok(self.shortfall(amount).map(|shortfall| CreditOverride {
+            customer_id: self.customer_id,
+            approver: approver.to_string(),
+            subject: subject.to_string(),
+            amount,
+            shortfall,
+        }))
+    }
+
+    fn ensure_not_held(&self) -> CoreResult<()> {
+        if self.credit_hold {
+            return Err(CoreError::business(format!(
+                "客户“{}”已被冻结信用，不能接受报价或确认订单",
+                self.customer_name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 管理员越过信用额度的记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreditOverride {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 批准人（用户名）
+    pub approver: String,
+    /// 单据说明，如“报价 Q-2024-001”
+    pub subject: String,
+    /// 本次金额（本位币）
+    pub amount: Money,
+    /// 超出额度的部分
+    pub shortfall: Money,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(limit: Option<i64>, hold: bool) -> CreditProfile {
+        CreditProfile {
+            customer_id: Uuid::new_v4(),
+            customer_name: "华美家具".to_string(),
+            credit_limit: limit.map(Money::from_cents),
+            credit_hold: hold,
+            outstanding_receivables: Money::from_cents(600_000),
+            unpaid_confirmed: Money::from_cents(300_000),
+            missing_rate_orders: 0,
+        }
+    }
+
+    #[test]
+    fn exact_limit_is_allowed() {
+        let profile = profile(Some(1_000_000), false);
+        assert_eq!(profile.exposure(), Money::from_cents(900_000));
+        assert_eq!(profile.available(), Some(Money::from_cents(100_000)));
+
+        assert!(profile.check(Money::from_cents(100_000)).is_ok());
+        assert_eq!(profile.shortfall(Money::from_cents(100_000)), None);
+
+        let err = profile.check(Money::from_cents(100_001)).unwrap_err();
+        assert!(matches!(err, CoreError::Business(_)));
+        assert!(err.to_string().contains("超出 ¥0.01"), "{err}");
+    }
+
+    #[test]
+    fn no_limit_never_blocks() {
+        let profile = profile(None, false);
+        assert!(profile.check(Money::from_cents(i64::from(u32::MAX))).is_ok());
+        assert_eq!(profile.available(), None);
+    }
+
+    #[test]
+    fn hold_takes_precedence_over_limit_and_override() {
+        let held = profile(None, true);
+        let err = held.check(Money::ZERO).unwrap_err();
+        assert!(err.to_string().contains("冻结"), "{err}");
+        assert!(held
+            .override_credit(Money::from_cents(1), "admin", "报价 Q-1")
+            .is_err());
+    }
+
+    #[test]
+    fn override_reports_shortfall_only_when_exceeding() {
+        let profile = profile(Some(1_000_000), false);
+        assert_eq!(
+            profile
+                .override_credit(Money::from_cents(100_000), "admin", "报价 Q-1")
+                .unwrap(),
+            None
+        );
+        let entry = profile
+            .override_credit(Money::from_cents(250_000), "admin", "报价 Q-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.shortfall, Money::from_cents(150_000));
+        assert_eq!(entry.approver, "admin");
+    }
+}
@@ -0,0 +1,301 @@
+//! 开料计算模块
+//!
+//! 按面积估算客户开料清单需要多少张整板：板件总面积加上损耗后，除以单张板材按利用率
+//! 折算的可用面积，再向上取整。这只是估算，不做二维排版；板件允许旋转放置。
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CoreError, CoreResult};
+
+const MM2_PER_M2: f64 = 1_000_000.0;
+
+/// 板材尺寸（毫米）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SheetSize {
+    /// 长
+    pub length_mm: u32,
+    /// 宽
+    pub width_mm: u32,
+}
+
+impl Default for SheetSize {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl SheetSize {
+    /// 标准板 1220×2440
+    pub const STANDARD: SheetSize = SheetSize {
+        length_mm: 2440,
+        width_mm: 1220,
+    };
+
+    /// 单张面积（平方米）
+    pub fn area_m2(&self) -> f64 {
+        f64::from(self.length_mm) * f64::from(self.width_mm) / MM2_PER_M2
+    }
+
+    /// 板件能否（允许旋转）放进这张板
+    pub fn fits(&self, piece: &CutPiece) -> bool {
+        let (long, short) = (
+            self.length_mm.max(self.width_mm),
+            self.length_mm.min(self.width_mm),
+        );
+        piece.length_mm.max(piece.width_mm) <= long && piece.length_mm.min(piece.width_mm) <= short
+    }
+
+    /// 从产品规格中读取板材尺寸，如“1220×2440×18 E0”取前两个数
+    ///
+    /// 支持 `×`、`x`、`X`、`*` 作为分隔符；规格中没有尺寸时返回 `None`。
+    pub fn from_specification(specification: &str) -> Option<Self> {
+        let mut numbers = specification.split(['×', 'x', 'X', '*']).map(str::trim);
+        let first = numbers.next()?;
+        let first: u32 = first
+            .rsplit(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok()?;
+        let second: u32 = numbers
+            .next()?
+            .split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok()?;
+        (first > 0 && second > 0).then(|| Self {
+            length_mm: first.max(second),
+            width_mm: first.min(second),
+        })
+    }
+}
+
+/// 开料清单中的一种板件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CutPiece {
+    /// 长（毫米）
+    pub length_mm: u32,
+    /// 宽（毫米）
+    pub width_mm: u32,
+    /// 数量
+    pub quantity: u32,
+}
+
+impl CutPiece {
+    /// 创建板件
+    pub const fn new(length_mm: u32, width_mm: u32, quantity: u32) -> Self {
+        Self {
+            length_mm,
+            width_mm,
+            quantity,
+        }
+    }
+
+    /// 该种板件的总面积（平方米）
+    pub fn area_m2(&self) -> f64 {
+        f64::from(self.length_mm) * f64::from(self.width_mm) * f64::from(self.quantity)
+            / MM2_PER_M2
+    }
+}
+
+/// 开料计算结果
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CutResult {
+    /// 需要的整板张数（向上取整）
+    pub sheets: u32,
+    /// 板件总面积（平方米，不含损耗）
+    pub pieces_area_m2: f64,
+    /// 整板总面积（平方米）
+    pub sheets_area_m2: f64,
+    /// 预计余料面积（平方米）
+    pub offcut_m2: f64,
+    /// 实际利用率（板件总面积 / 整板总面积，百分比）
+    pub utilization_percent: f64,
+    /// 实际利用率低于提醒阈值
+    pub low_utilization: bool,
+}
+
+/// 开料计算器
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CutCalculator {
+    /// 板材尺寸
+    pub sheet: SheetSize,
+    /// 损耗（锯路、修边）百分比，加在板件总面积上
+    pub waste_percent: f64,
+    /// 单张板材可利用的面积百分比
+    pub utilization_percent: f64,
+    /// 实际利用率低于此百分比时提醒（开料清单太零碎，按面积估算偏差大）
+    pub min_utilization_percent: f64,
+}
+
+impl Default for CutCalculator {
+    fn default() -> Self {
+        Self {
+            sheet: SheetSize::STANDARD,
+            waste_percent: 5.0,
+            utilization_percent: 85.0,
+            min_utilization_percent: 60.0,
+        }
+    }
+}
+
+impl CutCalculator {
+    /// 使用指定板材尺寸，其余参数取默认值
+    pub fn for_sheet(sheet: SheetSize) -> Self {
+        Self {
+            sheet,
+            ..Self::default()
+        }
+    }
+
+    /// 计算开料清单需要的整板张数
+    ///
+    /// # Errors
+    ///
+    /// 清单为空、板件尺寸或数量为0、板件比整板还大，或参数超出范围时返回验证错误。
+    pub fn calculate(&self, pieces: &[CutPiece]) -> CoreResult<CutResult> {
+        if !(self.utilization_percent > 0.0 && self.utilization_percent <= 100.0) {
+            return Err(CoreError::validation("板材利用率须在0到100%之间"));
+        }
+        if !(self.waste_percent >= 0.0 && self.waste_percent.is_finite()) {
+            return Err(CoreError::validation("损耗比例不能为负数"));
+        }
+        if self.sheet.length_mm == 0 || self.sheet.width_mm == 0 {
+            return Err(CoreError::validation("板材尺寸不能为0"));
+        }
+        if pieces.is_empty() {
+            return Err(CoreError::validation("开料清单为空"));
+        }
+        for (index, piece) in pieces.iter().enumerate() {
+            if piece.length_mm == 0 || piece.width_mm == 0 || piece.quantity == 0 {
+                return Err(CoreError::validation(format!(
+                    "第 {} 行板件的尺寸和数量不能为0",
+                    index + 1
+                )));
+            }
+            if !self.sheet.fits(piece) {
+                return Err(CoreError::validation(format!(
+                    "第 {} 行板件 {}×{} 超出板材尺寸 {}×{}",
+                    index + 1,
+                    piece.length_mm,
+                    piece.width_mm,
+                    self.sheet.width_mm,
+                    self.sheet.length_mm
+                )));
+            }
+        }
+
+        let pieces_area_m2: f64 = pieces.iter().map(CutPiece::area_m2).sum();
+        let required = pieces_area_m2 * (1.0 + self.waste_percent / 100.0);
+        let usable_per_sheet = self.sheet.area_m2() * self.utilization_percent / 100.0;
+        // 消除浮点误差，恰好用满时不多算一张
+        let sheets = ((required / usable_per_sheet) - 1e-9).ceil().max(1.0) as u32;
+        let sheets_area_m2 = f64::from(sheets) * self.sheet.area_m2();
+        let utilization_percent = pieces_area_m2 / sheets_area_m2 * 100.0;
+        Ok(CutResult {
+            sheets,
+            pieces_area_m2,
+            sheets_area_m2,
+            offcut_m2: sheets_area_m2 - pieces_area_m2,
+            utilization_percent,
+            low_utilization: utilization_percent < self.min_utilization_percent,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calculator() -> CutCalculator {
+        CutCalculator {
+            sheet: SheetSize::STANDARD,
+            waste_percent: 0.0,
+            utilization_percent: 100.0,
+            min_utilization_percent: 60.0,
+        }
+    }
+
+    #[test]
+    fn test_sheet_counts_round_up() {
+        // 衣柜侧板 2400×600 共4块，面积正好两张整板的 96.8%
+        let wardrobe = [CutPiece::new(2400, 600, 4)];
+        let result = calculator().calculate(&wardrobe).unwrap();
+        assert_eq!(result.sheets, 2);
+        assert!((result.pieces_area_m2 - 5.76).abs() < 1e-9);
+        assert!((result.sheets_area_m2 - 5.953_6).abs() < 1e-9);
+        assert!((result.offcut_m2 - 0.193_6).abs() < 1e-9);
+        assert!(!result.low_utilization);
+
+        // 按默认损耗5%、利用率85%：5.76 × 1.05 / (2.9768 × 0.85) = 2.39 → 3张
+        let result = CutCalculator::default().calculate(&wardrobe).unwrap();
+        assert_eq!(result.sheets, 3);
+
+        // 正好一张整板不多算
+        let exact = [CutPiece::new(1220, 2440, 1)];
+        assert_eq!(calculator().calculate(&exact).unwrap().sheets, 1);
+
+        // 橱柜清单：门板、层板、背板
+        let cabinet = [
+            CutPiece::new(720, 450, 8),
+            CutPiece::new(560, 300, 12),
+            CutPiece::new(900, 700, 4),
+        ];
+        let result = CutCalculator::default().calculate(&cabinet).unwrap();
+        // (2.592 + 2.016 + 2.52) × 1.05 / 2.53028 = 2.958 → 3张
+        assert_eq!(result.sheets, 3);
+    }
+
+    #[test]
+    fn test_rejects_oversized_and_empty_pieces() {
+        let oversized = [CutPiece::new(600, 400, 2), CutPiece::new(2500, 600, 1)];
+        let err = calculator().calculate(&oversized).unwrap_err();
+        assert!(err.to_string().contains("第 2 行"));
+
+        // 旋转后放得下
+        let rotated = [CutPiece::new(1200, 2400, 1)];
+        assert!(calculator().calculate(&rotated).is_ok());
+
+        // 宽度超过板宽，旋转也放不下
+        let too_wide = [CutPiece::new(1300, 1300, 1)];
+        assert!(calculator().calculate(&too_wide).is_err());
+
+        assert!(calculator().calculate(&[]).is_err());
+        assert!(calculator().calculate(&[CutPiece::new(600, 400, 0)]).is_err());
+    }
+
+    #[test]
+    fn test_low_utilization_warning() {
+        // 一小块也要一整张板：利用率约4.8%
+        let small = [CutPiece::new(600, 240, 1)];
+        let result = calculator().calculate(&small).unwrap();
+        assert_eq!(result.sheets, 1);
+        assert!(result.low_utilization);
+
+        let mut lenient = calculator();
+        lenient.min_utilization_percent = 0.0;
+        assert!(!lenient.calculate(&small).unwrap().low_utilization);
+
+        // 1.5张的面积：两张板利用率约75%，高于60%
+        let half = [CutPiece::new(1220, 1220, 3)];
+        let result = calculator().calculate(&half).unwrap();
+        assert_eq!(result.sheets, 2);
+        assert!((result.utilization_percent - 75.0).abs() < 1e-9);
+        assert!(!result.low_utilization);
+    }
+
+    #[test]
+    fn test_sheet_size_from_specification() {
+        assert_eq!(
+            SheetSize::from_specification("1220×2440×18mm E0"),
+            Some(SheetSize::STANDARD)
+        );
+        assert_eq!(
+            SheetSize::from_specification("E1级 1830x915"),
+            Some(SheetSize {
+                length_mm: 1830,
+                width_mm: 915
+            })
+        );
+        assert_eq!(SheetSize::from_specification("18mm 多层板"), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! 重复客户检测模块
+//!
+//! 新建客户时用两类廉价的分块键找出可能重复的已有客户，在表单中提示而不阻止保存：
+//!
+//! - 电话完全相同（去掉分隔符和国家码后比较）
+//! - 规范化名称前缀相同：去掉空白、标点和“有限公司”等后缀并转为小写后，一方是另一方的前缀
+//!   （至少 [`MIN_NAME_KEY_CHARS`] 个字，避免“杭州”这类地名命中大量客户）
+//!
+//! 数据源只按键粗筛候选，分类、排除正在编辑的客户和截断由 [`classify_duplicates`] 完成。
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::validation::PhoneNumber;
+
+/// 最多提示的可能重复客户数
+pub const MAX_DUPLICATE_SUGGESTIONS: usize = 5;
+
+/// 名称前缀匹配至少需要的字数（规范化后）
+pub const MIN_NAME_KEY_CHARS: usize = 4;
+
+/// 规范化时去掉的公司名称后缀（长的在前）
+const COMPANY_SUFFIXES: &[&str] = &["股份有限公司", "有限责任公司", "有限公司", "公司"];
+
+/// 规范化名称：去掉空白和标点、转为小写，再去掉公司后缀
+pub fn name_key(name: &str) -> String {
+    let mut key: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if let Some(stripped) = COMPANY_SUFFIXES
+        .iter()
+        .find_map(|suffix| key.strip_suffix(suffix))
+    {
+        key.truncate(stripped.len());
+    }
+    key
+}
+
+/// 名称粗筛键：规范化名称的前 [`MIN_NAME_KEY_CHARS`] 个字，不足时为空
+///
+/// 可能重复的客户的名称一定包含这几个字，数据源按它做子串筛选。
+pub fn name_probe(name: &str) -> Option<String> {
+    let key = name_key(name);
+    (key.chars().count() >= MIN_NAME_KEY_CHARS)
+        .then(|| key.chars().take(MIN_NAME_KEY_CHARS).collect())
+}
+
+/// 电话比较键：格式正确时为去掉分隔符和国家码后的数字
+pub fn phone_key(phone: &str) -> Option<String> {
+    PhoneNumber::try_from(phone)
+        .ok()
+        .map(|number| number.as_str().to_string())
+}
+
+/// 遮盖电话中间的数字，如 `138****1234`；不足8位时只保留后4位
+pub fn mask_phone(phone: &str) -> String {
+    let digits = phone_key(phone).unwrap_or_else(|| phone.trim().to_string());
+    let chars: Vec<char> = digits.chars().collect();
+    let tail: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    if chars.len() >= 8 {
+        let head: String = chars[..3].iter().collect();
+        format!("{head}****{tail}")
+    } else {
+        format!("****{tail}")
+    }
+}
+
+/// 匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DuplicateMatch {
+    /// 电话完全相同
+    Phone,
+    /// 规范化名称前缀相同
+    NamePrefix,
+}
+
+/// 数据源粗筛出的候选客户
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateCandidate {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub name: String,
+    /// 电话
+    #[serde(default)]
+    pub phone: Option<String>,
+}
+
+/// 可能重复的已有客户
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PossibleDuplicate {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub name: String,
+    /// 电话
+    #[serde(default)]
+    pub phone: Option<String>,
+    /// 匹配方式（两种都命中时为电话）
+    pub matched: DuplicateMatch,
+}
+
+impl PossibleDuplicate {
+    /// 提示文字，如“杭州西湖装饰有限公司 (138****1234)”
+    pub fn label(&self) -> String {
+        match self.phone.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(phone) => format!("{} ({})", self.name, mask_phone(phone)),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// 从候选中找出与输入的名称或电话重复的客户
+///
+/// 排除 `exclude`（正在编辑的客户本身），同一客户只出现一次；电话命中的排在前面，
+/// 同类按名称排序，最多返回 `limit` 条。
+pub fn classify_duplicates(
+    name: &str,
+    phone: &str,
+    exclude: Option<Uuid>,
+    candidates: Vec<DuplicateCandidate>,
+    limit: usize,
+) -> Vec<PossibleDuplicate> {
+    let key = name_key(name);
+    let key = (key.chars().count() >= MIN_NAME_KEY_CHARS).then_some(key);
+    let phone = phone_key(phone);
+
+    let mut found: Vec<PossibleDuplicate> = Vec::new();
+    for candidate in candidates {
+        if Some(candidate.customer_id) == exclude
+            || found.iter().any(|d| d.customer_id == candidate.customer_id)
+        {
+            continue;
+        }
+        let phone_match =
+            phone.is_some() && candidate.phone.as_deref().and_then(phone_key) == phone;
+        let name_match = key.as_deref().is_some_and(|key| {
+            let other = name_key(&candidate.name);
+            other.chars().count() >= MIN_NAME_KEY_CHARS
+                && (other.starts_with(key) || key.starts_with(other.as_str()))
+        });
+        let matched = if phone_match {
+            DuplicateMatch::Phone
+        } else if name_match {
+            DuplicateMatch::NamePrefix
+        } else {
+            continue;
+        };
+        found.push(PossibleDuplicate {
+            customer_id: candidate.customer_id,
+            name: candidate.name,
+            phone: candidate.phone,
+            matched,
+        });
+    }
+    found.sort_by(|a, b| a.matched.cmp(&b.matched).then_with(|| a.name.cmp(&b.name)));
+    found.truncate(limit);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(name: &str, phone: Option<&str>) -> DuplicateCandidate {
+        DuplicateCandidate {
+            customer_id: Uuid::new_v4(),
+            name: name.to_string(),
+            phone: phone.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_keys_and_mask() {
+        assert_eq!(name_key(" 杭州西湖装饰有限公司 "), "杭州西湖装饰");
+        assert_eq!(name_key("杭州·西湖（装饰）股份有限公司"), "杭州西湖装饰");
+        assert_eq!(name_key("ABC Board Co."), "abcboardco");
+        assert_eq!(
+            name_probe("杭州西湖装饰有限公司").as_deref(),
+            Some("杭州西湖")
+        );
+        assert_eq!(name_probe("杭州公司"), None);
+        assert_eq!(
+            phone_key("+86 138-0013-1234").as_deref(),
+            Some("13800131234")
+        );
+        assert_eq!(phone_key("1380013"), None);
+        assert_eq!(mask_phone("13800131234"), "138****1234");
+        assert_eq!(mask_phone("0571-8888-1234"), "057****1234");
+        assert_eq!(mask_phone("1234"), "****1234");
+    }
+
+    #[test]
+    fn test_match_classes_and_self_exclusion() {
+        let same_phone = candidate("华东板材", Some("138 0013 1234"));
+        let longer_name = candidate("杭州西湖装饰有限公司", Some("13900000000"));
+        let shorter_name = candidate("杭州西湖", None);
+        let both = candidate("杭州西湖装饰工程", Some("13800131234"));
+        let unrelated = candidate("杭州滨江五金", Some("13700000000"));
+        let candidates = vec![
+            unrelated,
+            longer_name.clone(),
+            same_phone.clone(),
+            shorter_name.clone(),
+            both.clone(),
+            both.clone(),
+        ];
+
+        let found = classify_duplicates("杭州西湖装饰", "13800131234", None, candidates.clone(), 5);
+        let summary: Vec<(Uuid, DuplicateMatch)> =
+            found.iter().map(|d| (d.customer_id, d.matched)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (same_phone.customer_id, DuplicateMatch::Phone),
+                (both.customer_id, DuplicateMatch::Phone),
+                (shorter_name.customer_id, DuplicateMatch::NamePrefix),
+                (longer_name.customer_id, DuplicateMatch::NamePrefix),
+            ]
+        );
+        assert_eq!(found[3].label(), "杭州西湖装饰有限公司 (139****0000)");
+        assert_eq!(found[2].label(), "杭州西湖");
+
+        // 编辑已有客户时不提示其本身
+        let editing = classify_duplicates(
+            "杭州西湖装饰有限公司",
+            "13900000000",
+            Some(longer_name.customer_id),
+            candidates.clone(),
+            5,
+        );
+        let ids: Vec<Uuid> = editing.iter().map(|d| d.customer_id).collect();
+        assert_eq!(ids, vec![shorter_name.customer_id, both.customer_id]);
+
+        // 名称太短、电话不完整时都不匹配
+        assert!(classify_duplicates("杭州", "1380013", None, candidates, 5).is_empty());
+    }
+
+    #[test]
+    fn test_result_cap() {
+        let candidates: Vec<DuplicateCandidate> = (0..8)
+            .map(|i| candidate(&format!("杭州西湖装饰{i}号店"), None))
+            .collect();
+        let found = classify_duplicates(
+            "杭州西湖装饰",
+            "",
+            None,
+            candidates,
+            MAX_DUPLICATE_SUGGESTIONS,
+        );
+        assert_eq!(found.len(), MAX_DUPLICATE_SUGGESTIONS);
+        assert_eq!(found[0].name, "杭州西湖装饰0号店");
+    }
+}
@@ -0,0 +1,214 @@
+//! 枚举的显示名称
+//!
+//! 客户等级、任务状态与优先级、报价状态、工单状态在列表、看板、报价单PDF、CSV导出、
+//! 报表和提示中都通过 [`DisplayName`] 显示，每种语言只有这里一份名称，
+//! 同时给出界面徽标使用的色调。新增枚举值时必须在这里补齐名称和色调。
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{CustomerLevel, QuoteStatus, ServiceTicketStatus, TaskPriority, TaskStatus};
+use crate::formatting::Locale;
+
+/// 徽标色调（界面按主题映射为颜色）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BadgeTone {
+    /// 中性（灰色）
+    Neutral,
+    /// 提示（强调色）
+    Info,
+    /// 成功（绿色）
+    Success,
+    /// 危险（红色）
+    Danger,
+}
+
+impl BadgeTone {
+    /// 色调标识（界面样式使用）
+    pub fn token(&self) -> &'static str {
+        match self {
+            BadgeTone::Neutral => "neutral",
+            BadgeTone::Info => "info",
+            BadgeTone::Success => "success",
+            BadgeTone::Danger => "danger",
+        }
+    }
+}
+
+/// 枚举的本地化显示名称和徽标色调
+pub trait DisplayName {
+    /// 指定语言的显示名称
+    fn display_name(&self, locale: Locale) -> &'static str;
+
+    /// 徽标色调
+    fn badge(&self) -> BadgeTone;
+}
+
+impl DisplayName for CustomerLevel {
+    fn display_name(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Self::Normal, Locale::ZhCn) => "普通",
+            (Self::Normal, Locale::EnUs) => "Normal",
+            (Self::Vip, Locale::ZhCn) => "VIP",
+            (Self::Vip, Locale::EnUs) => "VIP",
+            (Self::Important, Locale::ZhCn) => "重要",
+            (Self::Important, Locale::EnUs) => "Key account",
+            (Self::Blacklist, Locale::ZhCn) => "黑名单",
+            (Self::Blacklist, Locale::EnUs) => "Blacklisted",
+        }
+    }
+
+    fn badge(&self) -> BadgeTone {
+        match self {
+            Self::Normal => BadgeTone::Neutral,
+            Self::Vip | Self::Important => BadgeTone::Info,
+            Self::Blacklist => BadgeTone::Danger,
+        }
+    }
+}
+
+impl DisplayName for TaskStatus {
+    fn display_name(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Self::Pending, Locale::ZhCn) => "待处理",
+            (Self::Pending, Locale::EnUs) => "To do",
+            (Self::InProgress, Locale::ZhCn) => "进行中",
+            (Self::InProgress, Locale::EnUs) => "In progress",
+            (Self::Completed, Locale::ZhCn) => "已完成",
+            (Self::Completed, Locale::EnUs) => "Done",
+            (Self::Cancelled, Locale::ZhCn) => "已取消",
+            (Self::Cancelled, Locale::EnUs) => "Cancelled",
+        }
+    }
+
+    fn badge(&self) -> BadgeTone {
+        match self {
+            Self::Pending | Self::Cancelled => BadgeTone::Neutral,
+            Self::InProgress => BadgeTone::Info,
+            Self::Completed => BadgeTone::Success,
+        }
+    }
+}
+
+impl DisplayName for TaskPriority {
+    fn display_name(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Self::Low, Locale::ZhCn) => "低",
+            (Self::Low, Locale::EnUs) => "Low",
+            (Self::Medium, Locale::ZhCn) => "中",
+            (Self::Medium, Locale::EnUs) => "Medium",
+            (Self::High, Locale::ZhCn) => "高",
+            (Self::High, Locale::EnUs) => "High",
+            (Self::Urgent, Locale::ZhCn) => "紧急",
+            (Self::Urgent, Locale::EnUs) => "Urgent",
+        }
+    }
+
+    fn badge(&self) -> BadgeTone {
+        match self {
+            Self::Low | Self::Medium => BadgeTone::Neutral,
+            Self::High => BadgeTone::Info,
+            Self::Urgent => BadgeTone::Danger,
+        }
+    }
+}
+
+impl DisplayName for QuoteStatus {
+    fn display_name(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Self::Draft, Locale::ZhCn) => "草稿",
+            (Self::Draft, Locale::EnUs) => "Draft",
+            (Self::Sent, Locale::ZhCn) => "已发送",
+            (Self::Sent, Locale::EnUs) => "Sent",
+            (Self::Accepted, Locale::ZhCn) => "已接受",
+            (Self::Accepted, Locale::EnUs) => "Accepted",
+            (Self::Rejected, Locale::ZhCn) => "已拒绝",
+            (Self::Rejected, Locale::EnUs) => "Rejected",
+            (Self::Expired, Locale::ZhCn) => "已过期",
+            (Self::Expired, Locale::EnUs) => "Expired",
+        }
+    }
+
+    fn badge(&self) -> BadgeTone {
+        match self {
+            Self::Draft | Self::Expired => BadgeTone::Neutral,
+            Self::Sent => BadgeTone::Info,
+            Self::Accepted => BadgeTone::Success,
+            Self::Rejected => BadgeTone::Danger,
+        }
+    }
+}
+
+impl DisplayName for ServiceTicketStatus {
+    fn display_name(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Self::New, Locale::ZhCn) => "新建",
+            (Self::New, Locale::EnUs) => "New",
+            (Self::InProgress, Locale::ZhCn) => "处理中",
+            (Self::InProgress, Locale::EnUs) => "In progress",
+            (Self::PendingCustomerConfirmation, Locale::ZhCn) => "待客户确认",
+            (Self::PendingCustomerConfirmation, Locale::EnUs) => "Awaiting customer",
+            (Self::Closed, Locale::ZhCn) => "已关闭",
+            (Self::Closed, Locale::EnUs) => "Closed",
+        }
+    }
+
+    fn badge(&self) -> BadgeTone {
+        match self {
+            Self::New | Self::InProgress => BadgeTone::Info,
+            Self::PendingCustomerConfirmation => BadgeTone::Neutral,
+            Self::Closed => BadgeTone::Success,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use strum::IntoEnumIterator;
+
+    const LOCALES: [Locale; 2] = [Locale::ZhCn, Locale::EnUs];
+
+    /// 每个取值在每种语言下都有名称和色调，同一语言内名称不重复
+    fn assert_complete<T>(name: &str)
+    where
+        T: IntoEnumIterator + DisplayName + std::fmt::Debug,
+    {
+        let variants: Vec<T> = T::iter().collect();
+        assert!(!variants.is_empty(), "{name}");
+        for locale in LOCALES {
+            let mut seen = HashSet::new();
+            for variant in &variants {
+                let label = variant.display_name(locale);
+                assert!(!label.trim().is_empty(), "{name}::{variant:?} {locale:?}");
+                assert!(seen.insert(label), "{name} 的名称重复：{label}");
+                assert!(!variant.badge().token().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_variant_has_labels_and_badge() {
+        assert_complete::<CustomerLevel>("CustomerLevel");
+        assert_complete::<TaskStatus>("TaskStatus");
+        assert_complete::<TaskPriority>("TaskPriority");
+        assert_complete::<QuoteStatus>("QuoteStatus");
+        assert_complete::<ServiceTicketStatus>("ServiceTicketStatus");
+    }
+
+    #[test]
+    fn test_labels_match_existing_wording() {
+        assert_eq!(TaskStatus::InProgress.display_name(Locale::ZhCn), "进行中");
+        assert_eq!(
+            ServiceTicketStatus::InProgress.display_name(Locale::ZhCn),
+            "处理中"
+        );
+        assert_eq!(
+            CustomerLevel::Blacklist.display_name(Locale::ZhCn),
+            "黑名单"
+        );
+        assert_eq!(QuoteStatus::Sent.display_name(Locale::EnUs), "Sent");
+        assert_eq!(TaskPriority::Urgent.badge(), BadgeTone::Danger);
+    }
+}
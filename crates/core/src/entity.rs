@@ -2,35 +2,99 @@
 //!
 //! 定义系统中的核心业务实体，包括客户、供应商、任务、报价等
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use strum::EnumIter;
 use uuid::Uuid;
 
+use crate::error::{CoreError, CoreResult};
+use crate::events::EntityKind;
+use crate::money::{Currency, Money};
+
 /// 客户实体
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Customer {
     /// 客户ID
+    #[serde(rename = "id")]
     pub id: Uuid,
     /// 客户名称
+    #[serde(rename = "name")]
     pub name: String,
     /// 联系人
+    #[serde(rename = "contact_person")]
     pub contact_person: Option<String>,
+    /// 联系人生日（用于客户维护提醒）
+    #[serde(rename = "contact_birthday", default)]
+    pub contact_birthday: Option<NaiveDate>,
     /// 电话
+    #[serde(rename = "phone")]
     pub phone: Option<String>,
     /// 邮箱
+    #[serde(rename = "email")]
     pub email: Option<String>,
     /// 地址
+    #[serde(rename = "address")]
     pub address: Option<String>,
     /// 客户等级
+    #[serde(rename = "level")]
     pub level: CustomerLevel,
+    /// 信用额度（本位币），为空表示不限额
+    #[serde(rename = "credit_limit", default)]
+    pub credit_limit: Option<Money>,
+    /// 信用冻结，冻结后不能接受报价或确认订单
+    #[serde(rename = "credit_hold", default)]
+    pub credit_hold: bool,
+    /// 负责人（用户ID），为空表示未分配
+    #[serde(rename = "owner_id", default)]
+    pub owner_id: Option<Uuid>,
     /// 创建时间
+    #[serde(rename = "created_at")]
     pub created_at: DateTime<Utc>,
     /// 更新时间
+    #[serde(rename = "updated_at")]
     pub updated_at: DateTime<Utc>,
+    /// 创建人（用户名）
+    #[serde(rename = "created_by", default)]
+    pub created_by: Option<String>,
+    /// 最后修改人（用户名）
+    #[serde(rename = "updated_by", default)]
+    pub updated_by: Option<String>,
+}
+
+/// 客户联系人
+///
+/// 首要联系人的姓名和电话同时写入客户的联系人、电话字段，列表和报价沿用原有字段。
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerContact {
+    /// 联系人ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 客户ID
+    #[serde(rename = "customer_id")]
+    pub customer_id: Uuid,
+    /// 姓名
+    #[serde(rename = "name")]
+    pub name: String,
+    /// 电话
+    #[serde(rename = "phone")]
+    pub phone: Option<String>,
+    /// 邮箱
+    #[serde(rename = "email")]
+    pub email: Option<String>,
+    /// 是否首要联系人（每个客户至多一个）
+    #[serde(rename = "is_primary")]
+    pub is_primary: bool,
+    /// 创建时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
 }
 
 /// 客户等级
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 pub enum CustomerLevel {
     /// 普通客户
     Normal,
@@ -42,31 +106,186 @@ pub enum CustomerLevel {
     Blacklist,
 }
 
+impl CustomerLevel {
+    /// 全部等级
+    pub const ALL: [CustomerLevel; 4] = [
+        CustomerLevel::Normal,
+        CustomerLevel::Vip,
+        CustomerLevel::Important,
+        CustomerLevel::Blacklist,
+    ];
+
+    /// 存储名称（与过滤条件中的等级值一致）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Normal => "Normal",
+            Self::Vip => "Vip",
+            Self::Important => "Important",
+            Self::Blacklist => "Blacklist",
+        }
+    }
+
+    /// 从存储名称解析（不区分大小写）
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(value))
+    }
+}
+
+/// 客户关系类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RelationKind {
+    /// `from` 是 `to` 的上级公司
+    ParentOf,
+    /// `from` 是 `to` 的分支机构（门店）
+    BranchOf,
+    /// `from` 由 `to` 推荐而来
+    ReferredBy,
+    /// 合作伙伴（不区分方向）
+    Partner,
+}
+
+impl RelationKind {
+    /// 类型名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ParentOf => "parent_of",
+            Self::BranchOf => "branch_of",
+            Self::ReferredBy => "referred_by",
+            Self::Partner => "partner",
+        }
+    }
+
+    /// 从类型名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "parent_of" => Some(Self::ParentOf),
+            "branch_of" => Some(Self::BranchOf),
+            "referred_by" => Some(Self::ReferredBy),
+            "partner" => Some(Self::Partner),
+            _ => None,
+        }
+    }
+
+    /// 是否属于上下级层级关系
+    pub fn is_hierarchy(&self) -> bool {
+        matches!(self, Self::ParentOf | Self::BranchOf)
+    }
+
+    /// 关系另一方相对于当前客户的角色（`from_is_self` 表示当前客户为关系发起方）
+    pub fn role_of_other(&self, from_is_self: bool) -> RelationRole {
+        match (self, from_is_self) {
+            (Self::ParentOf, true) | (Self::BranchOf, false) => RelationRole::Branch,
+            (Self::ParentOf, false) | (Self::BranchOf, true) => RelationRole::Parent,
+            (Self::ReferredBy, true) => RelationRole::Referrer,
+            (Self::ReferredBy, false) => RelationRole::Referral,
+            (Self::Partner, _) => RelationRole::Partner,
+        }
+    }
+}
+
+/// 关联客户相对于当前客户的角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RelationRole {
+    /// 上级公司
+    Parent,
+    /// 分支机构
+    Branch,
+    /// 推荐人
+    Referrer,
+    /// 经其推荐的客户
+    Referral,
+    /// 合作伙伴
+    Partner,
+}
+
+impl RelationRole {
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Parent => "上级公司",
+            Self::Branch => "分支机构",
+            Self::Referrer => "推荐人",
+            Self::Referral => "推荐的客户",
+            Self::Partner => "合作伙伴",
+        }
+    }
+}
+
+/// 客户关系
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomerRelation {
+    /// 关系ID
+    pub id: Uuid,
+    /// 关系发起方客户ID
+    pub from_id: Uuid,
+    /// 关系指向的客户ID
+    pub to_id: Uuid,
+    /// 关系类型
+    pub kind: RelationKind,
+    /// 备注
+    pub note: Option<String>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl CustomerRelation {
+    /// 层级关系中的（上级, 下级），非层级关系返回 `None`
+    pub fn hierarchy_edge(&self) -> Option<(Uuid, Uuid)> {
+        match self.kind {
+            RelationKind::ParentOf => Some((self.from_id, self.to_id)),
+            RelationKind::BranchOf => Some((self.to_id, self.from_id)),
+            RelationKind::ReferredBy | RelationKind::Partner => None,
+        }
+    }
+
+    /// 关系的另一方
+    pub fn other_party(&self, customer_id: Uuid) -> Uuid {
+        if self.from_id == customer_id {
+            self.to_id
+        } else {
+            self.from_id
+        }
+    }
+}
+
 /// 供应商实体
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Supplier {
     /// 供应商ID
+    #[serde(rename = "id")]
     pub id: Uuid,
     /// 供应商名称
+    #[serde(rename = "name")]
     pub name: String,
     /// 联系人
+    #[serde(rename = "contact_person")]
     pub contact_person: Option<String>,
     /// 电话
+    #[serde(rename = "phone")]
     pub phone: Option<String>,
     /// 邮箱
+    #[serde(rename = "email")]
     pub email: Option<String>,
     /// 地址
+    #[serde(rename = "address")]
     pub address: Option<String>,
     /// 供应商等级
+    #[serde(rename = "level")]
     pub level: SupplierLevel,
     /// 创建时间
+    #[serde(rename = "created_at")]
     pub created_at: DateTime<Utc>,
     /// 更新时间
+    #[serde(rename = "updated_at")]
     pub updated_at: DateTime<Utc>,
 }
 
 /// 供应商等级
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SupplierLevel {
     /// 普通供应商
     Normal,
@@ -78,33 +297,154 @@ pub enum SupplierLevel {
     Suspended,
 }
 
+/// 供应商报价（采购询价结果）
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PurchaseQuote {
+    /// 报价ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 供应商ID
+    #[serde(rename = "supplier_id")]
+    pub supplier_id: Uuid,
+    /// 产品ID
+    #[serde(rename = "product_id")]
+    pub product_id: Uuid,
+    /// 含税单价
+    #[serde(rename = "unit_price")]
+    pub unit_price: Money,
+    /// 最小起订量（无要求时为空）
+    #[serde(rename = "moq")]
+    pub moq: Option<u32>,
+    /// 报价有效期
+    #[serde(rename = "valid_until")]
+    pub valid_until: DateTime<Utc>,
+    /// 收到报价的时间
+    #[serde(rename = "received_at")]
+    pub received_at: DateTime<Utc>,
+    /// 备注
+    #[serde(rename = "notes")]
+    pub notes: Option<String>,
+}
+
+impl PurchaseQuote {
+    /// 截至 `now` 是否已过期（有效期当刻仍视为有效）
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.valid_until < now
+    }
+}
+
+/// 产品
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    /// 产品ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 产品名称
+    #[serde(rename = "name")]
+    pub name: String,
+    /// 规格
+    #[serde(rename = "specification", default)]
+    pub specification: Option<String>,
+    /// 产品类别（批量调价按类别进行）
+    #[serde(rename = "category", default)]
+    pub category: Option<String>,
+    /// 是否已停售
+    #[serde(rename = "retired", default)]
+    pub retired: bool,
+    /// 当前成本价（仅管理员可见，用于计算报价毛利）
+    #[serde(rename = "cost_price", default)]
+    pub cost_price: Option<Money>,
+    /// 创建时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    #[serde(rename = "updated_at")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 产品价格（自生效时间起有效，直到下一条价格生效）
+///
+/// 价格只追加不修改，按报价创建时间取价即可复现历史报价。成本价随价格期间记录，
+/// 修改成本价只更新当期的价格记录。
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductPrice {
+    /// 价格ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 产品ID
+    #[serde(rename = "product_id")]
+    pub product_id: Uuid,
+    /// 含税单价
+    #[serde(rename = "price")]
+    pub price: Money,
+    /// 该价格期间的成本价（未录入成本时为空）
+    #[serde(rename = "cost_price", default)]
+    pub cost_price: Option<Money>,
+    /// 生效时间
+    #[serde(rename = "effective_from")]
+    pub effective_from: DateTime<Utc>,
+    /// 录入人（用户名）
+    #[serde(rename = "created_by", default)]
+    pub created_by: Option<String>,
+    /// 录入时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+}
+
 /// 任务实体
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Task {
     /// 任务ID
+    #[serde(rename = "id")]
     pub id: Uuid,
     /// 任务标题
+    #[serde(rename = "title")]
     pub title: String,
     /// 任务描述
+    #[serde(rename = "description")]
     pub description: Option<String>,
     /// 任务状态
+    #[serde(rename = "status")]
     pub status: TaskStatus,
     /// 优先级
+    #[serde(rename = "priority")]
     pub priority: TaskPriority,
     /// 关联客户ID
+    #[serde(rename = "customer_id")]
     pub customer_id: Option<Uuid>,
     /// 关联供应商ID
+    #[serde(rename = "supplier_id")]
     pub supplier_id: Option<Uuid>,
     /// 截止日期
+    #[serde(rename = "due_date")]
     pub due_date: Option<DateTime<Utc>>,
     /// 创建时间
+    #[serde(rename = "created_at")]
     pub created_at: DateTime<Utc>,
     /// 更新时间
+    #[serde(rename = "updated_at")]
     pub updated_at: DateTime<Utc>,
+    /// 创建人（用户名）
+    #[serde(rename = "created_by", default)]
+    pub created_by: Option<String>,
+    /// 最后修改人（用户名）
+    #[serde(rename = "updated_by", default)]
+    pub updated_by: Option<String>,
+    /// 负责人（用户ID，未分配时为空）
+    #[serde(rename = "assigned_to", default)]
+    pub assigned_to: Option<Uuid>,
 }
 
 /// 任务状态
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 pub enum TaskStatus {
     /// 待处理
     Pending,
@@ -116,8 +456,35 @@ pub enum TaskStatus {
     Cancelled,
 }
 
+impl TaskStatus {
+    /// 全部状态（看板列顺序）
+    pub const ALL: [TaskStatus; 4] = [
+        Self::Pending,
+        Self::InProgress,
+        Self::Completed,
+        Self::Cancelled,
+    ];
+
+    /// 状态名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::InProgress => "in_progress",
+            Self::Completed => "completed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// 从状态名称解析（不区分大小写）
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(value))
+    }
+}
+
 /// 任务优先级
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 pub enum TaskPriority {
     /// 低优先级
     Low,
@@ -129,29 +496,236 @@ pub enum TaskPriority {
     Urgent,
 }
 
+impl TaskPriority {
+    /// 全部优先级
+    pub const ALL: [TaskPriority; 4] = [Self::Low, Self::Medium, Self::High, Self::Urgent];
+
+    /// 优先级名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Urgent => "urgent",
+        }
+    }
+
+    /// 从优先级名称解析（不区分大小写）
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|priority| priority.as_str().eq_ignore_ascii_case(value))
+    }
+}
+
+/// 任务备注
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskNote {
+    /// 备注ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 任务ID
+    #[serde(rename = "task_id")]
+    pub task_id: Uuid,
+    /// 备注内容
+    #[serde(rename = "body")]
+    pub body: String,
+    /// 记录人（用户名）
+    #[serde(rename = "author", default)]
+    pub author: Option<String>,
+    /// 记录时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+}
+
 /// 报价实体
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Quote {
     /// 报价ID
+    #[serde(rename = "id")]
     pub id: Uuid,
     /// 报价编号
+    #[serde(rename = "quote_number")]
     pub quote_number: String,
     /// 客户ID
+    #[serde(rename = "customer_id")]
     pub customer_id: Uuid,
     /// 报价状态
+    #[serde(rename = "status")]
     pub status: QuoteStatus,
-    /// 总金额
-    pub total_amount: f64,
+    /// 总金额（JSON中以元保存）
+    #[serde(rename = "total_amount", with = "crate::money::yuan")]
+    pub total_amount: Money,
+    /// 币种（明细单价和总金额均以此币种计）
+    #[serde(rename = "currency", default)]
+    pub currency: Currency,
+    /// 报价明细（只有总金额的报价为空）
+    #[serde(rename = "items", default)]
+    pub items: Vec<QuoteItem>,
     /// 有效期
+    #[serde(rename = "valid_until")]
     pub valid_until: DateTime<Utc>,
+    /// 备注及条款
+    #[serde(rename = "remarks", default)]
+    pub remarks: Option<String>,
+    /// 创建时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    #[serde(rename = "updated_at")]
+    pub updated_at: DateTime<Utc>,
+    /// 创建人（用户名）
+    #[serde(rename = "created_by", default)]
+    pub created_by: Option<String>,
+    /// 最后修改人（用户名）
+    #[serde(rename = "updated_by", default)]
+    pub updated_by: Option<String>,
+}
+
+/// 报价明细行
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteItem {
+    /// 明细ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 所属报价ID
+    #[serde(rename = "quote_id", default)]
+    pub quote_id: Uuid,
+    /// 产品ID（手工录入的明细为空）
+    #[serde(rename = "product_id", default)]
+    pub product_id: Option<Uuid>,
+    /// 产品名称
+    #[serde(rename = "product_name")]
+    pub product_name: String,
+    /// 规格
+    #[serde(rename = "specification", default)]
+    pub specification: Option<String>,
+    /// 计量单位（张、米、套）
+    #[serde(rename = "unit", default)]
+    pub unit: Option<String>,
+    /// 数量
+    #[serde(rename = "quantity")]
+    pub quantity: f64,
+    /// 单价
+    #[serde(rename = "unit_price")]
+    pub unit_price: f64,
+    /// 小计（JSON中以元保存）
+    #[serde(rename = "line_total", with = "crate::money::yuan")]
+    pub line_total: Money,
+}
+
+impl QuoteItem {
+    /// 数量×单价（未取整）
+    pub fn amount(&self) -> f64 {
+        self.quantity * self.unit_price
+    }
+
+    /// 按数量和单价计算的小计，四舍五入到分
+    pub fn calculated_line_total(&self) -> Money {
+        Money::from_yuan(self.amount())
+    }
+
+    /// 校验小计与数量×单价一致
+    ///
+    /// # Errors
+    ///
+    /// 不一致时返回 [`CoreError::Validation`]。
+    pub fn validate_line_total(&self) -> CoreResult<()> {
+        let expected = self.calculated_line_total();
+        if self.line_total != expected {
+            return Err(CoreError::validation(format!(
+                "明细「{}」小计 {} 与数量×单价 {} 不一致",
+                self.product_name, self.line_total, expected
+            )));
+        }
+        Ok(())
+    }
+
+    /// 明细合计（各行小计之和）
+    pub fn total(items: &[QuoteItem]) -> Money {
+        items.iter().map(|item| item.line_total).sum()
+    }
+}
+
+impl Quote {
+    /// 按数量和单价重新计算各行小计和总金额，明细归属到本报价
+    ///
+    /// 没有明细的报价保留原总金额。
+    pub fn recalculate_totals(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        for item in &mut self.items {
+            item.quote_id = self.id;
+            item.line_total = item.calculated_line_total();
+        }
+        self.total_amount = QuoteItem::total(&self.items);
+    }
+
+    /// 校验各行小计和总金额与明细一致（没有明细时不校验）
+    ///
+    /// # Errors
+    ///
+    /// 小计与数量×单价不符、或总金额与明细合计不符时返回 [`CoreError::Validation`]。
+    pub fn validate_totals(&self) -> CoreResult<()> {
+        if self.items.is_empty() {
+            return Ok(());
+        }
+        self.items.iter().try_for_each(QuoteItem::validate_line_total)?;
+        let total = QuoteItem::total(&self.items);
+        if self.total_amount != total {
+            return Err(CoreError::validation(format!(
+                "报价总额 {} 与明细合计 {} 不一致",
+                self.total_amount, total
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 报价模板
+///
+/// 保存常用的产品组合。从模板新建报价时按产品的当前价格计价，而不是保存模板时的价格。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteTemplate {
+    /// 模板ID
+    pub id: Uuid,
+    /// 模板名称
+    pub name: String,
+    /// 默认有效天数
+    pub validity_days: u32,
+    /// 默认备注及条款
+    #[serde(default)]
+    pub terms: Option<String>,
+    /// 模板明细
+    #[serde(default)]
+    pub items: Vec<QuoteTemplateItem>,
     /// 创建时间
     pub created_at: DateTime<Utc>,
     /// 更新时间
     pub updated_at: DateTime<Utc>,
 }
 
+/// 报价模板明细行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteTemplateItem {
+    /// 产品ID
+    pub product_id: Uuid,
+    /// 默认数量
+    pub quantity: f64,
+    /// 默认折扣率（0.1 表示优惠10%）
+    #[serde(default)]
+    pub discount: f64,
+}
+
 /// 报价状态
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 pub enum QuoteStatus {
     /// 草稿
     Draft,
@@ -165,33 +739,617 @@ pub enum QuoteStatus {
     Expired,
 }
 
+/// 销售机会阶段
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum OpportunityStage {
+    /// 线索
+    #[default]
+    Lead,
+    /// 已联系
+    Contacted,
+    /// 需求分析
+    NeedsAnalysis,
+    /// 已报价
+    Quoted,
+    /// 赢单
+    Won,
+    /// 输单
+    Lost,
+}
+
+impl OpportunityStage {
+    /// 全部阶段（漏斗顺序）
+    pub const ALL: [OpportunityStage; 6] = [
+        Self::Lead,
+        Self::Contacted,
+        Self::NeedsAnalysis,
+        Self::Quoted,
+        Self::Won,
+        Self::Lost,
+    ];
+
+    /// 阶段名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lead => "lead",
+            Self::Contacted => "contacted",
+            Self::NeedsAnalysis => "needs_analysis",
+            Self::Quoted => "quoted",
+            Self::Won => "won",
+            Self::Lost => "lost",
+        }
+    }
+
+    /// 从阶段名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|stage| stage.as_str() == value)
+    }
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Lead => "线索",
+            Self::Contacted => "已联系",
+            Self::NeedsAnalysis => "需求分析",
+            Self::Quoted => "已报价",
+            Self::Won => "赢单",
+            Self::Lost => "输单",
+        }
+    }
+
+    /// 是否已结束（赢单或输单）
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Self::Won | Self::Lost)
+    }
+
+    /// 是否允许转换到目标阶段
+    ///
+    /// 未结束的机会可以在各阶段间前后调整或结束，已结束的机会不能再变更阶段。
+    pub fn can_transition_to(&self, next: OpportunityStage) -> bool {
+        !self.is_closed() && *self != next
+    }
+}
+
+/// 销售机会
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Opportunity {
+    /// 机会ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 客户ID
+    #[serde(rename = "customer_id")]
+    pub customer_id: Uuid,
+    /// 标题
+    #[serde(rename = "title")]
+    pub title: String,
+    /// 当前阶段
+    #[serde(rename = "stage")]
+    pub stage: OpportunityStage,
+    /// 预计金额
+    #[serde(rename = "expected_amount")]
+    pub expected_amount: Money,
+    /// 成交概率（0-100）
+    #[serde(rename = "probability")]
+    pub probability: u8,
+    /// 预计成交日期
+    #[serde(rename = "expected_close_date")]
+    pub expected_close_date: Option<DateTime<Utc>>,
+    /// 关联报价ID
+    #[serde(rename = "quote_id")]
+    pub quote_id: Option<Uuid>,
+    /// 输单原因
+    #[serde(rename = "lost_reason")]
+    pub lost_reason: Option<String>,
+    /// 备注
+    #[serde(rename = "notes")]
+    pub notes: Option<String>,
+    /// 负责人（用户ID），为空表示未分配
+    #[serde(rename = "owner_id", default)]
+    pub owner_id: Option<Uuid>,
+    /// 创建时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    #[serde(rename = "updated_at")]
+    pub updated_at: DateTime<Utc>,
+    /// 创建人（用户名）
+    #[serde(rename = "created_by")]
+    pub created_by: Option<String>,
+    /// 最后修改人（用户名）
+    #[serde(rename = "updated_by")]
+    pub updated_by: Option<String>,
+}
+
+impl Opportunity {
+    /// 按成交概率加权的金额
+    pub fn weighted_amount(&self) -> Money {
+        self.expected_amount.percent(self.probability)
+    }
+}
+
+/// 每种实体最多可定义的（未停用的）自定义字段数
+pub const MAX_CUSTOM_FIELDS_PER_ENTITY: usize = 20;
+
+/// 自定义字段类型
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CustomFieldType {
+    /// 文本
+    Text,
+    /// 数字
+    Number,
+    /// 日期（`YYYY-MM-DD`）
+    Date,
+    /// 单选
+    Select {
+        /// 可选值（按显示顺序）
+        options: Vec<String>,
+    },
+}
+
+impl CustomFieldType {
+    /// 类型名称（不含选项）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Number => "number",
+            Self::Date => "date",
+            Self::Select { .. } => "select",
+        }
+    }
+
+    /// 从类型名称和选项解析
+    pub fn parse(value: &str, options: Vec<String>) -> Option<Self> {
+        match value {
+            "text" => Some(Self::Text),
+            "number" => Some(Self::Number),
+            "date" => Some(Self::Date),
+            "select" => Some(Self::Select { options }),
+            _ => None,
+        }
+    }
+
+    /// 校验输入并转换为存储格式
+    ///
+    /// 数字去掉多余的零（`30.50` 存为 `30.5`），日期统一为 `YYYY-MM-DD`，
+    /// 单选值必须是定义中的选项之一。返回错误时附带原因。
+    pub fn encode(&self, input: &str) -> Result<String, String> {
+        let input = input.trim();
+        match self {
+            Self::Text => Ok(input.to_string()),
+            Self::Number => match input.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(n.to_string()),
+                _ => Err(format!("「{}」不是有效的数字", input)),
+            },
+            Self::Date => chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .map_err(|_| format!("「{}」不是有效的日期（YYYY-MM-DD）", input)),
+            Self::Select { options } => options
+                .iter()
+                .find(|o| o.as_str() == input)
+                .cloned()
+                .ok_or_else(|| format!("「{}」不在可选值中", input)),
+        }
+    }
+}
+
+/// 自定义字段定义
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomFieldDefinition {
+    /// 定义ID
+    pub id: Uuid,
+    /// 所属实体类型
+    pub entity: EntityKind,
+    /// 字段键（小写字母开头，仅含小写字母、数字和下划线），定义后不可修改
+    pub key: String,
+    /// 显示名称
+    pub label: String,
+    /// 字段类型
+    pub field_type: CustomFieldType,
+    /// 是否必填
+    pub required: bool,
+    /// 显示顺序
+    pub sort_order: u32,
+    /// 是否已停用（停用的字段保留已有值，但不再显示和校验）
+    pub retired: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl CustomFieldDefinition {
+    /// 字段键是否合法
+    pub fn is_valid_key(key: &str) -> bool {
+        let mut chars = key.chars();
+        key.len() <= 32
+            && chars.next().is_some_and(|c| c.is_ascii_lowercase())
+            && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    }
+}
+
+/// 结构化地址
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Address {
+    /// 省份
+    #[serde(rename = "province")]
+    pub province: String,
+    /// 城市
+    #[serde(rename = "city")]
+    pub city: String,
+    /// 区县
+    #[serde(rename = "district", default)]
+    pub district: Option<String>,
+    /// 街道及门牌
+    #[serde(rename = "street")]
+    pub street: String,
+    /// 收货联系人
+    #[serde(rename = "contact", default)]
+    pub contact: Option<String>,
+    /// 联系电话
+    #[serde(rename = "phone", default)]
+    pub phone: Option<String>,
+}
+
+impl Address {
+    /// 单行显示（省市区街道）
+    pub fn one_line(&self) -> String {
+        format!(
+            "{}{}{}{}",
+            self.province,
+            self.city,
+            self.district.as_deref().unwrap_or(""),
+            self.street
+        )
+    }
+}
+
+/// 送货状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    /// 未安排
+    #[default]
+    Unscheduled,
+    /// 已安排
+    Scheduled,
+    /// 送货中
+    OutForDelivery,
+    /// 已送达
+    Delivered,
+    /// 送货失败
+    Failed,
+}
+
+impl DeliveryStatus {
+    /// 状态名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Unscheduled => "unscheduled",
+            DeliveryStatus::Scheduled => "scheduled",
+            DeliveryStatus::OutForDelivery => "out_for_delivery",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    /// 从状态名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "unscheduled" => Some(DeliveryStatus::Unscheduled),
+            "scheduled" => Some(DeliveryStatus::Scheduled),
+            "out_for_delivery" => Some(DeliveryStatus::OutForDelivery),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+
+    /// 中文显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Unscheduled => "未安排",
+            DeliveryStatus::Scheduled => "已安排",
+            DeliveryStatus::OutForDelivery => "送货中",
+            DeliveryStatus::Delivered => "已送达",
+            DeliveryStatus::Failed => "送货失败",
+        }
+    }
+
+    /// 是否允许转换到 `next`
+    ///
+    /// 已安排、送货中和送货失败的订单可以改期（回到已安排），已送达为终态。
+    pub fn can_transition_to(self, next: DeliveryStatus) -> bool {
+        matches!(
+            (self, next),
+            (
+                Self::Unscheduled | Self::Scheduled | Self::OutForDelivery | Self::Failed,
+                Self::Scheduled
+            ) | (Self::Scheduled, Self::OutForDelivery)
+                | (Self::Scheduled | Self::OutForDelivery, Self::Delivered)
+                | (Self::OutForDelivery, Self::Failed)
+        )
+    }
+
+    /// 是否已安排但尚未送达
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            DeliveryStatus::Scheduled | DeliveryStatus::OutForDelivery
+        )
+    }
+}
+
+/// 销售订单实体
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+    /// 订单ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 订单编号
+    #[serde(rename = "order_number")]
+    pub order_number: String,
+    /// 客户ID
+    #[serde(rename = "customer_id")]
+    pub customer_id: Uuid,
+    /// 来源报价ID
+    #[serde(rename = "quote_id", default)]
+    pub quote_id: Option<Uuid>,
+    /// 订单金额
+    #[serde(rename = "total_amount")]
+    pub total_amount: Money,
+    /// 币种（与来源报价一致，收款须使用同一币种）
+    #[serde(rename = "currency", default)]
+    pub currency: Currency,
+    /// 送货状态
+    #[serde(rename = "delivery_status", default)]
+    pub delivery_status: DeliveryStatus,
+    /// 约定送货时间
+    #[serde(rename = "delivery_date", default)]
+    pub delivery_date: Option<DateTime<Utc>>,
+    /// 送货地址
+    #[serde(rename = "delivery_address", default)]
+    pub delivery_address: Option<Address>,
+    /// 车辆
+    #[serde(rename = "vehicle", default)]
+    pub vehicle: Option<String>,
+    /// 司机
+    #[serde(rename = "driver", default)]
+    pub driver: Option<String>,
+    /// 签收人
+    #[serde(rename = "signed_by", default)]
+    pub signed_by: Option<String>,
+    /// 实际送达时间
+    #[serde(rename = "delivered_at", default)]
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// 创建时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    #[serde(rename = "updated_at")]
+    pub updated_at: DateTime<Utc>,
+    /// 创建人（用户名）
+    #[serde(rename = "created_by", default)]
+    pub created_by: Option<String>,
+    /// 最后修改人（用户名）
+    #[serde(rename = "updated_by", default)]
+    pub updated_by: Option<String>,
+}
+
+impl Order {
+    /// 截至 `now` 送货是否已逾期（约定时间已过仍未送达）
+    pub fn is_delivery_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.delivery_status.is_pending() && self.delivery_date.is_some_and(|date| date < now)
+    }
+}
+
+/// 互动类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InteractionKind {
+    /// 电话
+    Call,
+    /// 拜访
+    Visit,
+    /// 消息（微信、短信等）
+    Message,
+    /// 送货
+    Delivery,
+    /// 其他备注
+    Note,
+}
+
+impl InteractionKind {
+    /// 类型名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InteractionKind::Call => "call",
+            InteractionKind::Visit => "visit",
+            InteractionKind::Message => "message",
+            InteractionKind::Delivery => "delivery",
+            InteractionKind::Note => "note",
+        }
+    }
+
+    /// 从类型名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "call" => Some(InteractionKind::Call),
+            "visit" => Some(InteractionKind::Visit),
+            "message" => Some(InteractionKind::Message),
+            "delivery" => Some(InteractionKind::Delivery),
+            "note" => Some(InteractionKind::Note),
+            _ => None,
+        }
+    }
+}
+
+/// 客户互动记录
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// 记录ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 客户ID
+    #[serde(rename = "customer_id")]
+    pub customer_id: Uuid,
+    /// 互动类型
+    #[serde(rename = "kind")]
+    pub kind: InteractionKind,
+    /// 内容
+    #[serde(rename = "content")]
+    pub content: String,
+    /// 发生时间
+    #[serde(rename = "occurred_at")]
+    pub occurred_at: DateTime<Utc>,
+    /// 记录人（用户名）
+    #[serde(rename = "created_by", default)]
+    pub created_by: Option<String>,
+}
+
+/// 提醒类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReminderKind {
+    /// 送货逾期
+    OverdueDelivery,
+    /// 任务已分配给接收人
+    TaskAssigned,
+    /// 接收人关注的任务有新动态（状态变更、新备注）
+    TaskActivity,
+    /// 任务逾期已达升级天数
+    TaskEscalated,
+    /// 联系人生日临近
+    ContactBirthday,
+    /// 首次成交纪念日临近
+    DealAnniversary,
+}
+
+impl ReminderKind {
+    /// 类型名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReminderKind::OverdueDelivery => "overdue_delivery",
+            ReminderKind::TaskAssigned => "task_assigned",
+            ReminderKind::TaskActivity => "task_activity",
+            ReminderKind::TaskEscalated => "task_escalated",
+            ReminderKind::ContactBirthday => "contact_birthday",
+            ReminderKind::DealAnniversary => "deal_anniversary",
+        }
+    }
+
+    /// 从类型名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "overdue_delivery" => Some(ReminderKind::OverdueDelivery),
+            "task_assigned" => Some(ReminderKind::TaskAssigned),
+            "task_activity" => Some(ReminderKind::TaskActivity),
+            "task_escalated" => Some(ReminderKind::TaskEscalated),
+            "contact_birthday" => Some(ReminderKind::ContactBirthday),
+            "deal_anniversary" => Some(ReminderKind::DealAnniversary),
+            _ => None,
+        }
+    }
+
+    /// 通知和摘要中的分类
+    pub fn category(&self) -> &'static str {
+        match self {
+            ReminderKind::OverdueDelivery => "送货",
+            ReminderKind::TaskAssigned
+            | ReminderKind::TaskActivity
+            | ReminderKind::TaskEscalated => "任务",
+            ReminderKind::ContactBirthday | ReminderKind::DealAnniversary => "客户维护",
+        }
+    }
+}
+
+/// 提醒
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reminder {
+    /// 提醒ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 提醒类型
+    #[serde(rename = "kind")]
+    pub kind: ReminderKind,
+    /// 关联实体类型
+    #[serde(rename = "entity")]
+    pub entity: EntityKind,
+    /// 关联实体ID
+    #[serde(rename = "entity_id")]
+    pub entity_id: Uuid,
+    /// 提醒标题
+    #[serde(rename = "title")]
+    pub title: String,
+    /// 提醒时间
+    #[serde(rename = "due_at")]
+    pub due_at: DateTime<Utc>,
+    /// 创建时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+    /// 关闭时间（未关闭为空）
+    #[serde(rename = "dismissed_at", default)]
+    pub dismissed_at: Option<DateTime<Utc>>,
+    /// 接收人（用户ID，为空时所有用户可见）
+    #[serde(rename = "recipient", default)]
+    pub recipient: Option<Uuid>,
+    /// 推迟到（未推迟为空）
+    #[serde(rename = "snoozed_until", default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+}
+
+impl Reminder {
+    /// 在 `now` 时刻是否处于推迟中
+    pub fn is_snoozed(&self, now: DateTime<Utc>) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now)
+    }
+}
+
 /// 售后服务工单实体
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceTicket {
     /// 工单ID
+    #[serde(rename = "id")]
     pub id: Uuid,
     /// 工单编号
+    #[serde(rename = "ticket_number")]
     pub ticket_number: String,
     /// 客户ID
+    #[serde(rename = "customer_id")]
     pub customer_id: Uuid,
     /// 问题分类
+    #[serde(rename = "problem_category")]
     pub problem_category: String,
     /// 问题描述
+    #[serde(rename = "description")]
     pub description: String,
     /// 处理方式
+    #[serde(rename = "solution_method")]
     pub solution_method: Option<String>,
     /// 工单状态
+    #[serde(rename = "status")]
     pub status: ServiceTicketStatus,
     /// 优先级
+    #[serde(rename = "priority")]
     pub priority: TaskPriority,
     /// 创建时间
+    #[serde(rename = "created_at")]
     pub created_at: DateTime<Utc>,
     /// 更新时间
+    #[serde(rename = "updated_at")]
     pub updated_at: DateTime<Utc>,
 }
 
 /// 售后工单状态
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter)]
 pub enum ServiceTicketStatus {
     /// 新建
     New,
@@ -202,3 +1360,146 @@ pub enum ServiceTicketStatus {
     /// 已关闭
     Closed,
 }
+
+/// 知识库文章标题的最大字数（由工单生成时截取问题描述）
+pub const ARTICLE_TITLE_MAX_CHARS: usize = 30;
+
+/// 售后知识库文章
+///
+/// 记录一类问题的现象和处理方法，处理工单时按问题分类和描述推荐。
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KnowledgeArticle {
+    /// 文章ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 标题
+    #[serde(rename = "title")]
+    pub title: String,
+    /// 问题分类（与工单的问题分类一致，如“变形”“开裂”“色差”）
+    #[serde(rename = "problem_category")]
+    pub problem_category: String,
+    /// 问题现象
+    #[serde(rename = "symptoms")]
+    pub symptoms: String,
+    /// 处理方法
+    #[serde(rename = "solution")]
+    pub solution: String,
+    /// 适用的产品类别
+    #[serde(rename = "product_categories", default)]
+    pub product_categories: Vec<String>,
+    /// 创建人
+    #[serde(rename = "created_by")]
+    pub created_by: Option<String>,
+    /// 被工单采用的次数
+    #[serde(rename = "usage_count", default)]
+    pub usage_count: u64,
+    /// 创建时间
+    #[serde(rename = "created_at")]
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    #[serde(rename = "updated_at")]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl KnowledgeArticle {
+    /// 由已解决的工单生成文章草稿
+    ///
+    /// 标题取问题描述的第一行（过长时截断），现象为问题描述，处理方法为工单的处理方式。
+    pub fn from_ticket(ticket: &ServiceTicket, created_by: Option<String>) -> Self {
+        let first_line = ticket.description.lines().next().unwrap_or_default().trim();
+        let mut title: String = first_line.chars().take(ARTICLE_TITLE_MAX_CHARS).collect();
+        if first_line.chars().count() > ARTICLE_TITLE_MAX_CHARS {
+            title.push('…');
+        }
+        if title.is_empty() {
+            title = ticket.problem_category.clone();
+        }
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            title,
+            problem_category: ticket.problem_category.clone(),
+            symptoms: ticket.description.trim().to_string(),
+            solution: ticket
+                .solution_method
+                .as_deref()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            product_categories: Vec::new(),
+            created_by,
+            usage_count: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// 用户角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UserRole {
+    /// 只读用户
+    Viewer,
+    /// 销售（可新增、修改业务数据）
+    Sales,
+    /// 管理员（可删除数据、修改设置）
+    Admin,
+}
+
+impl UserRole {
+    fn rank(self) -> u8 {
+        match self {
+            UserRole::Viewer => 0,
+            UserRole::Sales => 1,
+            UserRole::Admin => 2,
+        }
+    }
+
+    /// 是否满足所需角色
+    pub fn satisfies(self, required: UserRole) -> bool {
+        self.rank() >= required.rank()
+    }
+
+    /// 角色名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Viewer => "viewer",
+            UserRole::Sales => "sales",
+            UserRole::Admin => "admin",
+        }
+    }
+
+    /// 从角色名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(UserRole::Viewer),
+            "sales" => Some(UserRole::Sales),
+            "admin" => Some(UserRole::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// 用户实体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    /// 用户ID
+    pub id: Uuid,
+    /// 登录名
+    pub username: String,
+    /// 显示名称
+    pub display_name: String,
+    /// 角色
+    pub role: UserRole,
+    /// 密码哈希（argon2）
+    #[serde(skip_serializing, default)]
+    pub password_hash: String,
+    /// 是否启用
+    pub active: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+}
@@ -2,6 +2,7 @@
 //!
 //! 定义系统中的错误类型和错误处理机制
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// 核心错误类型
@@ -15,6 +16,14 @@ pub enum CoreError {
     #[error("验证错误: {0}")]
     Validation(String),
 
+    /// 字段级验证错误
+    #[error("验证错误: {}", format_field_errors(.0))]
+    InvalidFields(Vec<FieldError>),
+
+    /// 并发修改冲突（版本不一致）
+    #[error("数据冲突: {0}")]
+    Conflict(String),
+
     /// 业务逻辑错误
     #[error("业务逻辑错误: {0}")]
     Business(String),
@@ -39,11 +48,42 @@ pub enum CoreError {
     #[error("序列化错误: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    /// 操作被用户取消
+    #[error("操作已取消")]
+    Cancelled,
+
     /// 其他错误
     #[error("未知错误: {0}")]
     Other(String),
 }
 
+/// 字段验证错误
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// 字段名
+    pub field: String,
+    /// 错误信息
+    pub message: String,
+}
+
+impl FieldError {
+    /// 创建字段验证错误
+    pub fn new<F: Into<String>, M: Into<String>>(field: F, message: M) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+fn format_field_errors(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 /// 数据库错误类型
 #[derive(Error, Debug)]
 pub enum DatabaseError {
@@ -86,6 +126,16 @@ impl CoreError {
         CoreError::Validation(message.into())
     }
 
+    /// 创建字段级验证错误
+    pub fn invalid_fields(errors: Vec<FieldError>) -> Self {
+        CoreError::InvalidFields(errors)
+    }
+
+    /// 创建并发修改冲突错误
+    pub fn conflict<S: Into<String>>(message: S) -> Self {
+        CoreError::Conflict(message.into())
+    }
+
     /// 创建业务逻辑错误
     pub fn business<S: Into<String>>(message: S) -> Self {
         CoreError::Business(message.into())
@@ -105,6 +155,11 @@ impl CoreError {
     pub fn configuration<S: Into<String>>(message: S) -> Self {
         CoreError::Configuration(message.into())
     }
+
+    /// 是否为用户取消（界面不应作为失败提示）
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, CoreError::Cancelled)
+    }
 }
 
 impl DatabaseError {
@@ -0,0 +1,313 @@
+//! 领域事件模块
+//!
+//! 定义系统中的领域事件及事件处理接口
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entity::{QuoteStatus, TaskStatus};
+use crate::error::CoreResult;
+
+/// 实体类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EntityKind {
+    /// 客户
+    Customer,
+    /// 供应商
+    Supplier,
+    /// 任务
+    Task,
+    /// 报价
+    Quote,
+    /// 售后工单
+    ServiceTicket,
+    /// 销售订单
+    Order,
+    /// 销售机会
+    Opportunity,
+}
+
+impl EntityKind {
+    /// 全部实体类型
+    pub const ALL: [EntityKind; 7] = [
+        EntityKind::Customer,
+        EntityKind::Supplier,
+        EntityKind::Task,
+        EntityKind::Quote,
+        EntityKind::ServiceTicket,
+        EntityKind::Order,
+        EntityKind::Opportunity,
+    ];
+
+    /// 实体对应的数据表名
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            EntityKind::Customer => "customers",
+            EntityKind::Supplier => "suppliers",
+            EntityKind::Task => "tasks",
+            EntityKind::Quote => "quotes",
+            EntityKind::ServiceTicket => "service_tickets",
+            EntityKind::Order => "orders",
+            EntityKind::Opportunity => "opportunities",
+        }
+    }
+
+    /// 根据数据表名解析实体类型
+    pub fn from_table_name(table: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.table_name() == table)
+    }
+}
+
+/// 领域事件
+///
+/// 字段名用 `rename` 固定，修改字段前先看 [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DomainEvent {
+    /// 实体已创建
+    #[serde(rename = "EntityCreated")]
+    EntityCreated {
+        /// 实体类型
+        #[serde(rename = "entity")]
+        entity: EntityKind,
+        /// 实体ID
+        #[serde(rename = "id")]
+        id: Uuid,
+    },
+    /// 实体已更新
+    #[serde(rename = "EntityUpdated")]
+    EntityUpdated {
+        /// 实体类型
+        #[serde(rename = "entity")]
+        entity: EntityKind,
+        /// 实体ID
+        #[serde(rename = "id")]
+        id: Uuid,
+    },
+    /// 实体已软删除（移入回收站）
+    #[serde(rename = "EntitySoftDeleted")]
+    EntitySoftDeleted {
+        /// 实体类型
+        #[serde(rename = "entity")]
+        entity: EntityKind,
+        /// 实体ID
+        #[serde(rename = "id")]
+        id: Uuid,
+    },
+    /// 实体已从回收站恢复
+    #[serde(rename = "EntityRestored")]
+    EntityRestored {
+        /// 实体类型
+        #[serde(rename = "entity")]
+        entity: EntityKind,
+        /// 实体ID
+        #[serde(rename = "id")]
+        id: Uuid,
+    },
+    /// 实体已被彻底删除
+    #[serde(rename = "EntityPurged")]
+    EntityPurged {
+        /// 实体类型
+        #[serde(rename = "entity")]
+        entity: EntityKind,
+        /// 实体ID
+        #[serde(rename = "id")]
+        id: Uuid,
+        /// 删除前是否已处于软删除状态
+        #[serde(rename = "was_soft_deleted")]
+        was_soft_deleted: bool,
+    },
+    /// 报价状态已变更
+    #[serde(rename = "QuoteStatusChanged")]
+    QuoteStatusChanged {
+        /// 报价ID
+        #[serde(rename = "quote_id")]
+        quote_id: Uuid,
+        /// 客户ID
+        #[serde(rename = "customer_id")]
+        customer_id: Uuid,
+        /// 原状态
+        #[serde(rename = "from")]
+        from: QuoteStatus,
+        /// 新状态
+        #[serde(rename = "to")]
+        to: QuoteStatus,
+    },
+    /// 已由销售机会生成报价
+    #[serde(rename = "QuoteCreatedFromOpportunity")]
+    QuoteCreatedFromOpportunity {
+        /// 报价ID
+        #[serde(rename = "quote_id")]
+        quote_id: Uuid,
+        /// 销售机会ID
+        #[serde(rename = "opportunity_id")]
+        opportunity_id: Uuid,
+    },
+    /// 已记录客户互动
+    #[serde(rename = "InteractionLogged")]
+    InteractionLogged {
+        /// 互动记录ID
+        #[serde(rename = "interaction_id")]
+        interaction_id: Uuid,
+        /// 客户ID
+        #[serde(rename = "customer_id")]
+        customer_id: Uuid,
+    },
+    /// 已登记订单收款
+    #[serde(rename = "PaymentRecorded")]
+    PaymentRecorded {
+        /// 收款记录ID
+        #[serde(rename = "payment_id")]
+        payment_id: Uuid,
+        /// 订单ID
+        #[serde(rename = "order_id")]
+        order_id: Uuid,
+        /// 客户ID
+        #[serde(rename = "customer_id")]
+        customer_id: Uuid,
+    },
+    /// 任务已分配
+    #[serde(rename = "TaskAssigned")]
+    TaskAssigned {
+        /// 任务ID
+        #[serde(rename = "task_id")]
+        task_id: Uuid,
+        /// 负责人（用户ID）
+        #[serde(rename = "assignee")]
+        assignee: Uuid,
+    },
+    /// 任务状态已变更
+    #[serde(rename = "TaskStatusChanged")]
+    TaskStatusChanged {
+        /// 任务ID
+        #[serde(rename = "task_id")]
+        task_id: Uuid,
+        /// 原状态
+        #[serde(rename = "from")]
+        from: TaskStatus,
+        /// 新状态
+        #[serde(rename = "to")]
+        to: TaskStatus,
+    },
+    /// 已添加任务备注
+    #[serde(rename = "TaskNoteAdded")]
+    TaskNoteAdded {
+        /// 任务ID
+        #[serde(rename = "task_id")]
+        task_id: Uuid,
+        /// 备注ID
+        #[serde(rename = "note_id")]
+        note_id: Uuid,
+    },
+}
+
+impl DomainEvent {
+    /// 事件类型名称
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DomainEvent::EntityCreated { .. } => "entity_created",
+            DomainEvent::EntityUpdated { .. } => "entity_updated",
+            DomainEvent::EntitySoftDeleted { .. } => "entity_soft_deleted",
+            DomainEvent::EntityRestored { .. } => "entity_restored",
+            DomainEvent::EntityPurged { .. } => "entity_purged",
+            DomainEvent::QuoteStatusChanged { .. } => "quote_status_changed",
+            DomainEvent::QuoteCreatedFromOpportunity { .. } => "quote_created_from_opportunity",
+            DomainEvent::InteractionLogged { .. } => "interaction_logged",
+            DomainEvent::PaymentRecorded { .. } => "payment_recorded",
+            DomainEvent::TaskAssigned { .. } => "task_assigned",
+            DomainEvent::TaskStatusChanged { .. } => "task_status_changed",
+            DomainEvent::TaskNoteAdded { .. } => "task_note_added",
+        }
+    }
+
+    /// 事件关联的实体类型
+    pub fn entity_kind(&self) -> EntityKind {
+        match self {
+            DomainEvent::EntityCreated { entity, .. }
+            | DomainEvent::EntityUpdated { entity, .. }
+            | DomainEvent::EntitySoftDeleted { entity, .. }
+            | DomainEvent::EntityRestored { entity, .. }
+            | DomainEvent::EntityPurged { entity, .. } => *entity,
+            DomainEvent::QuoteStatusChanged { .. }
+            | DomainEvent::QuoteCreatedFromOpportunity { .. } => EntityKind::Quote,
+            DomainEvent::InteractionLogged { .. } => EntityKind::Customer,
+            DomainEvent::PaymentRecorded { .. } => EntityKind::Order,
+            DomainEvent::TaskAssigned { .. }
+            | DomainEvent::TaskStatusChanged { .. }
+            | DomainEvent::TaskNoteAdded { .. } => EntityKind::Task,
+        }
+    }
+
+    /// 事件关联的实体ID
+    pub fn entity_id(&self) -> Uuid {
+        match self {
+            DomainEvent::EntityCreated { id, .. }
+            | DomainEvent::EntityUpdated { id, .. }
+            | DomainEvent::EntitySoftDeleted { id, .. }
+            | DomainEvent::EntityRestored { id, .. }
+            | DomainEvent::EntityPurged { id, .. } => *id,
+            DomainEvent::QuoteStatusChanged { quote_id, .. }
+            | DomainEvent::QuoteCreatedFromOpportunity { quote_id, .. } => *quote_id,
+            DomainEvent::InteractionLogged { customer_id, .. } => *customer_id,
+            DomainEvent::PaymentRecorded { order_id, .. } => *order_id,
+            DomainEvent::TaskAssigned { task_id, .. }
+            | DomainEvent::TaskStatusChanged { task_id, .. }
+            | DomainEvent::TaskNoteAdded { task_id, .. } => *task_id,
+        }
+    }
+}
+
+/// 事件信封
+///
+/// 携带事件本身及发生时间等元数据。字段名用 `rename` 固定，修改字段前先看
+/// [`crate::canonical_json`] 的约定。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    /// 事件ID
+    #[serde(rename = "id")]
+    pub id: Uuid,
+    /// 发生时间
+    #[serde(rename = "occurred_at")]
+    pub occurred_at: DateTime<Utc>,
+    /// 事件内容
+    #[serde(rename = "event")]
+    pub event: DomainEvent,
+    /// 引发事件的操作的关联ID（界面据此识别自己乐观更新过的修改）
+    #[serde(rename = "correlation_id", default)]
+    pub correlation_id: Option<String>,
+}
+
+impl EventEnvelope {
+    /// 包装领域事件
+    pub fn new(event: DomainEvent) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            occurred_at: Utc::now(),
+            event,
+            correlation_id: None,
+        }
+    }
+
+    /// 记录引发事件的操作的关联ID
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// 包装在指定时间发生的领域事件
+    pub fn at(event: DomainEvent, occurred_at: DateTime<Utc>) -> Self {
+        Self {
+            occurred_at,
+            ..Self::new(event)
+        }
+    }
+}
+
+/// 领域事件处理器接口
+pub trait EventHandler: Send + Sync {
+    /// 处理器名称，用于日志
+    fn name(&self) -> &str;
+
+    /// 处理事件
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()>;
+}
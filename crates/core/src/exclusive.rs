@@ -0,0 +1,82 @@
+//! 独占操作守卫模块
+//!
+//! 备份、数据库整理、记录归档等需要独占数据库文件的操作同一时间只能进行一个，
+//! 后开始的操作获取失败时跳过或提示稍后再试。
+
+use std::sync::{Arc, Mutex};
+
+use crate::error::{CoreError, CoreResult};
+
+/// 独占操作守卫
+#[derive(Debug, Default)]
+pub struct ExclusiveGuard {
+    holder: Mutex<Option<String>>,
+}
+
+impl ExclusiveGuard {
+    /// 创建守卫
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 尝试开始独占操作，返回的租约释放时结束
+    ///
+    /// # Errors
+    ///
+    /// 已有其他独占操作进行中时返回冲突错误。
+    pub fn try_acquire(self: &Arc<Self>, operation: &str) -> CoreResult<ExclusiveLease> {
+        let mut holder = self
+            .holder
+            .lock()
+            .map_err(|_| CoreError::Other("独占操作状态不可用".to_string()))?;
+        if let Some(current) = holder.as_deref() {
+            return Err(CoreError::conflict(format!(
+                "{}正在进行，请稍后再试",
+                current
+            )));
+        }
+        *holder = Some(operation.to_string());
+        Ok(ExclusiveLease {
+            guard: Arc::clone(self),
+        })
+    }
+
+    /// 正在进行的独占操作
+    pub fn holder(&self) -> Option<String> {
+        self.holder.lock().ok().and_then(|holder| holder.clone())
+    }
+}
+
+/// 独占操作租约
+#[derive(Debug)]
+pub struct ExclusiveLease {
+    guard: Arc<ExclusiveGuard>,
+}
+
+impl Drop for ExclusiveLease {
+    fn drop(&mut self) {
+        if let Ok(mut holder) = self.guard.holder.lock() {
+            *holder = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lease_is_exclusive_until_dropped() {
+        let guard = Arc::new(ExclusiveGuard::new());
+        let lease = guard.try_acquire("数据库备份").unwrap();
+        assert_eq!(guard.holder().as_deref(), Some("数据库备份"));
+
+        let err = guard.try_acquire("数据归档").unwrap_err();
+        assert!(matches!(err, CoreError::Conflict(_)));
+        assert_eq!(err.to_string(), "数据冲突: 数据库备份正在进行，请稍后再试");
+
+        drop(lease);
+        assert_eq!(guard.holder(), None);
+        assert!(guard.try_acquire("数据归档").is_ok());
+    }
+}
@@ -0,0 +1,296 @@
+//! 字段策略模块
+//!
+//! 每种实体的标准字段可以设为可选、必填或隐藏：必填的字段保存时不能为空；隐藏的字段
+//! 不在表单和详情中出现，保存时也不能填写。客户名称等实体本身依赖的字段始终必填，
+//! ID、状态等不在可配置字段之列，均不能修改。
+//!
+//! 策略保存在 `field_policies` 表中，只记录与默认值（可选）不同的字段。
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity::Customer;
+use crate::error::{CoreError, CoreResult, FieldError};
+use crate::events::EntityKind;
+
+/// 字段要求
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FieldRequirement {
+    /// 可选
+    #[default]
+    Optional,
+    /// 必填
+    Required,
+    /// 隐藏
+    Hidden,
+}
+
+impl FieldRequirement {
+    /// 全部要求（按显示顺序）
+    pub const ALL: [FieldRequirement; 3] = [
+        FieldRequirement::Optional,
+        FieldRequirement::Required,
+        FieldRequirement::Hidden,
+    ];
+
+    /// 存储名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldRequirement::Optional => "optional",
+            FieldRequirement::Required => "required",
+            FieldRequirement::Hidden => "hidden",
+        }
+    }
+
+    /// 从存储名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.as_str() == value)
+    }
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            FieldRequirement::Optional => "可选",
+            FieldRequirement::Required => "必填",
+            FieldRequirement::Hidden => "隐藏",
+        }
+    }
+}
+
+/// 可配置的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolicyField {
+    /// 字段名（与命令校验错误中的字段名一致）
+    pub key: &'static str,
+    /// 显示名称
+    pub label: &'static str,
+    /// 是否为实体本身依赖的字段（始终必填）
+    pub intrinsic: bool,
+}
+
+const CUSTOMER_FIELDS: &[PolicyField] = &[
+    PolicyField {
+        key: "name",
+        label: "客户名称",
+        intrinsic: true,
+    },
+    PolicyField {
+        key: "contact_person",
+        label: "联系人",
+        intrinsic: false,
+    },
+    PolicyField {
+        key: "phone",
+        label: "电话",
+        intrinsic: false,
+    },
+    PolicyField {
+        key: "email",
+        label: "邮箱",
+        intrinsic: false,
+    },
+    PolicyField {
+        key: "address",
+        label: "地址",
+        intrinsic: false,
+    },
+];
+
+/// 实体类型的可配置字段（按表单顺序），没有可配置字段的类型为空
+pub fn policy_fields(entity: EntityKind) -> &'static [PolicyField] {
+    match entity {
+        EntityKind::Customer => CUSTOMER_FIELDS,
+        _ => &[],
+    }
+}
+
+/// 一种实体的字段策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldPolicy {
+    /// 实体类型
+    pub entity: EntityKind,
+    /// 与默认值不同的字段要求（按字段名）
+    pub fields: BTreeMap<String, FieldRequirement>,
+}
+
+impl FieldPolicy {
+    /// 全部字段均为默认要求的策略
+    pub fn new(entity: EntityKind) -> Self {
+        Self {
+            entity,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    fn field(&self, key: &str) -> Option<&'static PolicyField> {
+        policy_fields(self.entity).iter().find(|f| f.key == key)
+    }
+
+    /// 字段的要求：实体本身依赖的字段为必填，未配置的字段为可选
+    pub fn requirement(&self, key: &str) -> FieldRequirement {
+        match self.field(key) {
+            Some(field) if field.intrinsic => FieldRequirement::Required,
+            Some(_) => self.fields.get(key).copied().unwrap_or_default(),
+            None => FieldRequirement::Optional,
+        }
+    }
+
+    /// 是否必填
+    pub fn is_required(&self, key: &str) -> bool {
+        self.requirement(key) == FieldRequirement::Required
+    }
+
+    /// 是否隐藏
+    pub fn is_hidden(&self, key: &str) -> bool {
+        self.requirement(key) == FieldRequirement::Hidden
+    }
+
+    /// 修改字段要求
+    ///
+    /// # Errors
+    ///
+    /// 字段不可配置，或把实体本身依赖的字段设为必填以外的要求时返回
+    /// [`CoreError::Validation`]。
+    pub fn set(&mut self, key: &str, requirement: FieldRequirement) -> CoreResult<()> {
+        let field = self
+            .field(key)
+            .ok_or_else(|| CoreError::validation(format!("字段 {} 不能配置", key)))?;
+        if field.intrinsic && requirement != FieldRequirement::Required {
+            return Err(CoreError::validation(format!(
+                "{}始终必填，不能设为{}",
+                field.label,
+                requirement.label()
+            )));
+        }
+        if requirement == FieldRequirement::Optional || field.intrinsic {
+            self.fields.remove(key);
+        } else {
+            self.fields.insert(key.to_string(), requirement);
+        }
+        Ok(())
+    }
+
+    /// 按策略检查一个字段的值：必填的字段不能为空，隐藏的字段不能填写
+    ///
+    /// # Errors
+    ///
+    /// 不符合时返回 [`CoreError::Validation`]。
+    pub fn check(&self, key: &str, value: Option<&str>) -> CoreResult<()> {
+        let filled = value.is_some_and(|v| !v.trim().is_empty());
+        let label = self.field(key).map_or(key, |f| f.label);
+        match self.requirement(key) {
+            FieldRequirement::Required if !filled => {
+                Err(CoreError::validation(format!("{}不能为空", label)))
+            }
+            FieldRequirement::Hidden if filled => {
+                Err(CoreError::validation(format!("{}已隐藏，不能填写", label)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 按策略检查多个字段，列出全部不符合的字段
+    ///
+    /// # Errors
+    ///
+    /// 有字段不符合时返回 [`CoreError::InvalidFields`]。
+    pub fn validate(&self, values: &[(&str, Option<&str>)]) -> CoreResult<()> {
+        let errors: Vec<FieldError> = values
+            .iter()
+            .filter_map(|(key, value)| {
+                self.check(key, *value)
+                    .err()
+                    .map(|e| crate::validation::field_error(key, &e))
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CoreError::invalid_fields(errors))
+        }
+    }
+
+    /// 清除隐藏字段的值（详情等读取模型使用）
+    pub fn redact_customer(&self, customer: &mut Customer) {
+        let fields = [
+            ("contact_person", &mut customer.contact_person),
+            ("phone", &mut customer.phone),
+            ("email", &mut customer.email),
+            ("address", &mut customer.address),
+        ];
+        for (key, value) in fields {
+            if self.is_hidden(key) {
+                *value = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enforcement_per_requirement() {
+        let mut policy = FieldPolicy::new(EntityKind::Customer);
+        policy.set("phone", FieldRequirement::Required).unwrap();
+        policy.set("email", FieldRequirement::Hidden).unwrap();
+
+        // 可选：填不填都可以
+        assert!(policy.check("address", None).is_ok());
+        assert!(policy.check("address", Some("杭州")).is_ok());
+        // 必填：空白视为未填
+        assert!(policy.check("phone", Some("13800138000")).is_ok());
+        assert!(matches!(
+            policy.check("phone", Some("  ")),
+            Err(CoreError::Validation(message)) if message == "电话不能为空"
+        ));
+        // 隐藏：不能填写，空值可以
+        assert!(policy.check("email", None).is_ok());
+        assert!(matches!(
+            policy.check("email", Some("a@b.cn")),
+            Err(CoreError::Validation(message)) if message == "邮箱已隐藏，不能填写"
+        ));
+
+        let Err(CoreError::InvalidFields(errors)) = policy.validate(&[
+            ("name", Some("华东板材")),
+            ("phone", None),
+            ("email", Some("a@b.cn")),
+        ]) else {
+            panic!("expected field errors");
+        };
+        let keys: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(keys, vec!["phone", "email"]);
+
+        // 改回可选后不再记录
+        policy.set("phone", FieldRequirement::Optional).unwrap();
+        assert_eq!(policy.fields.len(), 1);
+    }
+
+    #[test]
+    fn test_guard_rails() {
+        let mut policy = FieldPolicy::new(EntityKind::Customer);
+        assert!(policy.is_required("name"));
+        for requirement in [FieldRequirement::Optional, FieldRequirement::Hidden] {
+            assert!(matches!(
+                policy.set("name", requirement),
+                Err(CoreError::Validation(_))
+            ));
+        }
+        assert!(policy.set("name", FieldRequirement::Required).is_ok());
+        assert!(policy.fields.is_empty());
+        for key in ["id", "status", "level"] {
+            assert!(policy.set(key, FieldRequirement::Hidden).is_err());
+        }
+        assert!(matches!(
+            FieldPolicy::new(EntityKind::Order).set("phone", FieldRequirement::Required),
+            Err(CoreError::Validation(_))
+        ));
+        // 存储中残留的记录也不能让名称变为非必填
+        policy
+            .fields
+            .insert("name".to_string(), FieldRequirement::Hidden);
+        assert_eq!(policy.requirement("name"), FieldRequirement::Required);
+    }
+}
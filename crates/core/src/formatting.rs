@@ -0,0 +1,356 @@
+//! 本地化格式
+//!
+//! 金额、日期、相对时间和面积的显示格式。界面、报价单PDF、CSV导出和报表都使用这里的
+//! 纯函数，同一数值在各处显示一致。界面使用中文；报价单PDF使用内置西文字体，按英文格式。
+
+use chrono::{DateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::money::{Currency, Money};
+
+/// 显示语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Locale {
+    /// 简体中文
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    /// 英文（美国）
+    #[serde(rename = "en-US")]
+    EnUs,
+}
+
+/// 日期格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    /// 完整日期（2024年6月15日 / June 15, 2024）
+    Long,
+    /// 数字日期（2024-06-15）
+    Short,
+    /// 数字日期和时间（2024-06-15 14:30）
+    ShortWithTime,
+}
+
+/// 相对时间显示的最大天数，超过时显示完整日期
+const RELATIVE_MAX_DAYS: i64 = 30;
+
+const UPPER_DIGITS: [char; 10] = ['零', '壹', '贰', '叁', '肆', '伍', '陆', '柒', '捌', '玖'];
+const UPPER_UNITS: [&str; 4] = ["仟", "佰", "拾", ""];
+const WAN: u64 = 10_000;
+const YI: u64 = 100_000_000;
+
+/// 整数部分按千分位分组（12345 → 12,345）
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+impl Locale {
+    /// 金额（¥12,345.50 / CN¥12,345.50）
+    pub fn money(self, amount: Money) -> String {
+        let sign = if amount.cents() < 0 { "-" } else { "" };
+        let cents = amount.cents().unsigned_abs();
+        let symbol = match self {
+            Self::ZhCn => "¥",
+            Self::EnUs => "CN¥",
+        };
+        format!(
+            "{sign}{symbol}{}.{:02}",
+            group_thousands(cents / 100),
+            cents % 100
+        )
+    }
+
+    /// 指定币种的金额，人民币同 [`money`](Self::money)，其他币种以代码开头（USD 1,234.50）
+    pub fn money_in(self, amount: Money, currency: Currency) -> String {
+        if currency == Currency::CNY {
+            return self.money(amount);
+        }
+        let sign = if amount.cents() < 0 { "-" } else { "" };
+        let cents = amount.cents().unsigned_abs();
+        format!(
+            "{sign}{currency} {}.{:02}",
+            group_thousands(cents / 100),
+            cents % 100
+        )
+    }
+
+    /// 日期，按传入时间所在时区显示
+    pub fn date<Tz: TimeZone>(self, at: &DateTime<Tz>, style: DateStyle) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let pattern = match (self, style) {
+            (Self::ZhCn, DateStyle::Long) => "%Y年%-m月%-d日",
+            (Self::EnUs, DateStyle::Long) => "%B %-d, %Y",
+            (_, DateStyle::Short) => "%Y-%m-%d",
+            (_, DateStyle::ShortWithTime) => "%Y-%m-%d %H:%M",
+        };
+        at.format(pattern).to_string()
+    }
+
+    /// 相对日期（今天、明天、3天前），按日历日计算；相差超过30天时显示完整日期
+    pub fn relative<Tz: TimeZone>(self, at: &DateTime<Tz>, now: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let days = (at.date_naive() - now.date_naive()).num_days();
+        if days.abs() > RELATIVE_MAX_DAYS {
+            return self.date(at, DateStyle::Long);
+        }
+        match self {
+            Self::ZhCn => match days {
+                0 => "今天".to_string(),
+                1 => "明天".to_string(),
+                2 => "后天".to_string(),
+                -1 => "昨天".to_string(),
+                -2 => "前天".to_string(),
+                d if d > 0 => format!("{d}天后"),
+                d => format!("{}天前", -d),
+            },
+            Self::EnUs => match days {
+                0 => "today".to_string(),
+                1 => "tomorrow".to_string(),
+                -1 => "yesterday".to_string(),
+                d if d > 0 => format!("in {d} days"),
+                d => format!("{} days ago", -d),
+            },
+        }
+    }
+
+    /// 面积（平方米），保留两位小数（12.50平方米 / 12.50 m²）
+    pub fn area(self, square_meters: f64) -> String {
+        if !square_meters.is_finite() {
+            return "-".to_string();
+        }
+        let rounded = format!("{:.2}", square_meters.abs());
+        let (integer, fraction) = rounded.split_once('.').unwrap_or((rounded.as_str(), "00"));
+        let integer = integer
+            .parse::<u64>()
+            .map_or_else(|_| integer.to_string(), group_thousands);
+        let sign = if square_meters < 0.0 && rounded != "0.00" {
+            "-"
+        } else {
+            ""
+        };
+        match self {
+            Self::ZhCn => format!("{sign}{integer}.{fraction}平方米"),
+            Self::EnUs => format!("{sign}{integer}.{fraction} m²"),
+        }
+    }
+}
+
+/// 不超过9999的数字的大写（不含单位）
+fn upper_section(n: u64) -> String {
+    let digits = [n / 1000 % 10, n / 100 % 10, n / 10 % 10, n % 10];
+    let mut out = String::new();
+    let mut pending_zero = false;
+    for (digit, unit) in digits.into_iter().zip(UPPER_UNITS) {
+        if digit == 0 {
+            pending_zero = !out.is_empty();
+            continue;
+        }
+        if pending_zero {
+            out.push('零');
+            pending_zero = false;
+        }
+        out.push(UPPER_DIGITS[digit as usize]);
+        out.push_str(unit);
+    }
+    out
+}
+
+/// 整数的大写，按万、亿分节，节内或节间的空位读“零”
+fn upper_integer(n: u64) -> String {
+    let (head, rest, scale) = if n >= YI {
+        (format!("{}亿", upper_integer(n / YI)), n % YI, YI)
+    } else if n >= WAN {
+        (format!("{}万", upper_section(n / WAN)), n % WAN, WAN)
+    } else {
+        return upper_section(n);
+    };
+    if rest == 0 {
+        return head;
+    }
+    let zero = if rest < scale / 10 { "零" } else { "" };
+    format!("{head}{zero}{}", upper_integer(rest))
+}
+
+/// 金额的中文大写（壹万贰仟叁佰肆拾伍元伍角整），用于报价单和收据
+///
+/// 没有分时以“整”结尾；有元和分而没有角时读“零”（壹拾元零伍分）；负数前加“负”。
+pub fn money_chinese_upper(amount: Money) -> String {
+    let cents = amount.cents().unsigned_abs();
+    let (yuan, jiao, fen) = (cents / 100, cents / 10 % 10, cents % 10);
+    let mut out = String::new();
+    if amount.cents() < 0 {
+        out.push('负');
+    }
+    if yuan > 0 {
+        out.push_str(&upper_integer(yuan));
+        out.push('元');
+    } else if cents == 0 {
+        out.push_str("零元");
+    }
+    if jiao > 0 {
+        out.push(UPPER_DIGITS[jiao as usize]);
+        out.push('角');
+    } else if yuan > 0 && fen > 0 {
+        out.push('零');
+    }
+    if fen > 0 {
+        out.push(UPPER_DIGITS[fen as usize]);
+        out.push('分');
+    } else {
+        out.push('整');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    #[test]
+    fn test_money() {
+        let cases = [
+            (0, "¥0.00", "CN¥0.00"),
+            (5, "¥0.05", "CN¥0.05"),
+            (99_999, "¥999.99", "CN¥999.99"),
+            (100_000, "¥1,000.00", "CN¥1,000.00"),
+            (1_234_550, "¥12,345.50", "CN¥12,345.50"),
+            (-1_234_550, "-¥12,345.50", "-CN¥12,345.50"),
+            (-1, "-¥0.01", "-CN¥0.01"),
+            (10_000_000_000, "¥100,000,000.00", "CN¥100,000,000.00"),
+            (123_456_789_012, "¥1,234,567,890.12", "CN¥1,234,567,890.12"),
+        ];
+        for (cents, zh, en) in cases {
+            let amount = Money::from_cents(cents);
+            assert_eq!(Locale::ZhCn.money(amount), zh);
+            assert_eq!(Locale::EnUs.money(amount), en);
+        }
+        assert_eq!(
+            Locale::EnUs.money_in(Money::from_cents(123_450), Currency::USD),
+            "USD 1,234.50"
+        );
+        assert_eq!(
+            Locale::ZhCn.money_in(Money::from_cents(123_450), Currency::CNY),
+            "¥1,234.50"
+        );
+    }
+
+    #[test]
+    fn test_money_chinese_upper() {
+        let cases = [
+            (0, "零元整"),
+            (1, "壹分"),
+            (50, "伍角整"),
+            (55, "伍角伍分"),
+            (100, "壹元整"),
+            (1_005, "壹拾元零伍分"),
+            (1_050, "壹拾元伍角整"),
+            (1_234_550, "壹万贰仟叁佰肆拾伍元伍角整"),
+            (1_234_556, "壹万贰仟叁佰肆拾伍元伍角陆分"),
+            (100_100, "壹仟零壹元整"),
+            (101_000, "壹仟零壹拾元整"),
+            (2_000_000, "贰万元整"),
+            (2_010_000, "贰万零壹佰元整"),
+            (2_100_000, "贰万壹仟元整"),
+            (10_001_000, "壹拾万零壹拾元整"),
+            (10_000_000_000, "壹亿元整"),
+            (10_000_000_500, "壹亿零伍元整"),
+            (10_001_000_000, "壹亿零壹万元整"),
+            (12_000_000_000, "壹亿贰仟万元整"),
+            (
+                123_456_789_012,
+                "壹拾贰亿叁仟肆佰伍拾陆万柒仟捌佰玖拾元壹角贰分",
+            ),
+            (100_000_000_000_000, "壹万亿元整"),
+            (-1_234_550, "负壹万贰仟叁佰肆拾伍元伍角整"),
+            (-5, "负伍分"),
+        ];
+        for (cents, expected) in cases {
+            assert_eq!(
+                money_chinese_upper(Money::from_cents(cents)),
+                expected,
+                "{cents} 分"
+            );
+        }
+    }
+
+    #[test]
+    fn test_date() {
+        let shanghai = FixedOffset::east_opt(8 * 3600).unwrap();
+        let at = Utc
+            .with_ymd_and_hms(2024, 6, 14, 17, 30, 0)
+            .unwrap()
+            .with_timezone(&shanghai);
+        let cases = [
+            (DateStyle::Long, "2024年6月15日", "June 15, 2024"),
+            (DateStyle::Short, "2024-06-15", "2024-06-15"),
+            (
+                DateStyle::ShortWithTime,
+                "2024-06-15 01:30",
+                "2024-06-15 01:30",
+            ),
+        ];
+        for (style, zh, en) in cases {
+            assert_eq!(Locale::ZhCn.date(&at, style), zh);
+            assert_eq!(Locale::EnUs.date(&at, style), en);
+        }
+        let new_year = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            Locale::ZhCn.date(&new_year, DateStyle::Long),
+            "2025年1月1日"
+        );
+    }
+
+    #[test]
+    fn test_relative() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 9, 0, 0).unwrap();
+        let cases = [
+            (0, "今天", "today"),
+            (1, "明天", "tomorrow"),
+            (2, "后天", "in 2 days"),
+            (-1, "昨天", "yesterday"),
+            (-2, "前天", "2 days ago"),
+            (-3, "3天前", "3 days ago"),
+            (7, "7天后", "in 7 days"),
+            (30, "30天后", "in 30 days"),
+            (-31, "2024年5月15日", "May 15, 2024"),
+        ];
+        for (days, zh, en) in cases {
+            let at = now + chrono::Duration::days(days);
+            assert_eq!(Locale::ZhCn.relative(&at, &now), zh, "{days} 天");
+            assert_eq!(Locale::EnUs.relative(&at, &now), en, "{days} 天");
+        }
+        // 按日历日计算：昨晚23点是“昨天”，不是“今天”
+        let late = Utc.with_ymd_and_hms(2024, 6, 14, 23, 0, 0).unwrap();
+        assert_eq!(Locale::ZhCn.relative(&late, &now), "昨天");
+    }
+
+    #[test]
+    fn test_area() {
+        let cases = [
+            (0.0, "0.00平方米", "0.00 m²"),
+            (12.5, "12.50平方米", "12.50 m²"),
+            (2.976, "2.98平方米", "2.98 m²"),
+            (1234.567, "1,234.57平方米", "1,234.57 m²"),
+            (-3.2, "-3.20平方米", "-3.20 m²"),
+            (-0.001, "0.00平方米", "0.00 m²"),
+            (f64::NAN, "-", "-"),
+        ];
+        for (value, zh, en) in cases {
+            assert_eq!(Locale::ZhCn.area(value), zh, "{value}");
+            assert_eq!(Locale::EnUs.area(value), en, "{value}");
+        }
+    }
+}
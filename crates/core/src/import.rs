@@ -0,0 +1,377 @@
+//! 表格导入模块
+//!
+//! 供应商发来的价目表版式各不相同：表头上方常有合并单元格的标题和日期，列的顺序和叫法
+//! 也不统一。导入时先在前几行中找表头行——非空单元格中至少六成能按别名表识别为字段——
+//! 得到列与字段的对应关系，交给界面确认或调整后再逐行导入。
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// 表头行中可识别单元格的最低比例
+pub const HEADER_MATCH_RATIO: f64 = 0.6;
+
+/// 查找表头行时扫描的最大行数
+pub const HEADER_SCAN_ROWS: usize = 20;
+
+/// 导入对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportTarget {
+    /// 供应商
+    Suppliers,
+    /// 产品
+    Products,
+}
+
+impl ImportTarget {
+    /// 可导入的字段
+    pub fn fields(&self) -> &'static [ImportField] {
+        match self {
+            Self::Suppliers => &[
+                ImportField::SupplierName,
+                ImportField::ContactPerson,
+                ImportField::Phone,
+                ImportField::Email,
+                ImportField::Address,
+            ],
+            Self::Products => &[
+                ImportField::ProductName,
+                ImportField::Specification,
+                ImportField::Thickness,
+                ImportField::Category,
+                ImportField::UnitPrice,
+            ],
+        }
+    }
+
+    /// 必须映射的字段
+    pub fn required(&self) -> &'static [ImportField] {
+        match self {
+            Self::Suppliers => &[ImportField::SupplierName],
+            Self::Products => &[ImportField::ProductName, ImportField::UnitPrice],
+        }
+    }
+}
+
+/// 导入字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ImportField {
+    /// 供应商名称
+    SupplierName,
+    /// 联系人
+    ContactPerson,
+    /// 电话
+    Phone,
+    /// 邮箱
+    Email,
+    /// 地址
+    Address,
+    /// 品名
+    ProductName,
+    /// 规格
+    Specification,
+    /// 厚度（导入时并入规格）
+    Thickness,
+    /// 类别
+    Category,
+    /// 单价
+    UnitPrice,
+}
+
+impl ImportField {
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::SupplierName => "供应商名称",
+            Self::ContactPerson => "联系人",
+            Self::Phone => "电话",
+            Self::Email => "邮箱",
+            Self::Address => "地址",
+            Self::ProductName => "品名",
+            Self::Specification => "规格",
+            Self::Thickness => "厚度",
+            Self::Category => "类别",
+            Self::UnitPrice => "单价",
+        }
+    }
+}
+
+/// 表头别名表
+///
+/// 比较前去掉括号中的单位（“单价(元)”按“单价”识别）、空白和冒号，不区分大小写。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HeaderAliases {
+    aliases: BTreeMap<ImportField, Vec<String>>,
+}
+
+impl Default for HeaderAliases {
+    fn default() -> Self {
+        let table: [(ImportField, &[&str]); 10] = [
+            (
+                ImportField::SupplierName,
+                &["供应商", "供应商名称", "厂家", "厂商", "公司名称", "单位名称", "名称"],
+            ),
+            (ImportField::ContactPerson, &["联系人", "业务员", "对接人"]),
+            (ImportField::Phone, &["电话", "联系电话", "手机", "手机号"]),
+            (ImportField::Email, &["邮箱", "电子邮箱", "email", "e-mail"]),
+            (ImportField::Address, &["地址", "联系地址", "厂址"]),
+            (
+                ImportField::ProductName,
+                &["品名", "产品名称", "商品名称", "产品", "名称"],
+            ),
+            (ImportField::Specification, &["规格", "规格型号", "型号", "尺寸"]),
+            (ImportField::Thickness, &["厚度", "厚"]),
+            (ImportField::Category, &["类别", "分类", "品类"]),
+            (
+                ImportField::UnitPrice,
+                &["单价", "含税单价", "价格", "报价", "售价"],
+            ),
+        ];
+        Self {
+            aliases: table
+                .iter()
+                .map(|(field, aliases)| {
+                    (*field, aliases.iter().map(ToString::to_string).collect())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl HeaderAliases {
+    /// 为字段增加别名
+    pub fn with_alias<S: Into<String>>(mut self, field: ImportField, alias: S) -> Self {
+        self.aliases.entry(field).or_default().push(alias.into());
+        self
+    }
+
+    /// 按别名识别表头单元格
+    pub fn field_for(&self, target: ImportTarget, header: &str) -> Option<ImportField> {
+        let header = normalize(header);
+        if header.is_empty() {
+            return None;
+        }
+        target.fields().iter().copied().find(|field| {
+            self.aliases
+                .get(field)
+                .is_some_and(|aliases| aliases.iter().any(|alias| normalize(alias) == header))
+        })
+    }
+
+    /// 查找表头行
+    ///
+    /// 返回前 [`HEADER_SCAN_ROWS`] 行中第一个满足条件的行号（从0开始）和各列的映射：
+    /// 至少两个非空单元格，且能识别的不少于 [`HEADER_MATCH_RATIO`]。同一字段出现在多列时
+    /// 只映射第一列。
+    pub fn detect(
+        &self,
+        target: ImportTarget,
+        rows: &[Vec<String>],
+    ) -> Option<(usize, Vec<ColumnMapping>)> {
+        rows.iter()
+            .take(HEADER_SCAN_ROWS)
+            .enumerate()
+            .find_map(|(index, row)| {
+                let columns = self.map_columns(target, row);
+                let filled = columns.iter().filter(|c| !c.header.is_empty()).count();
+                let matched = columns.iter().filter(|c| c.field.is_some()).count();
+                #[allow(clippy::cast_precision_loss)]
+                let ratio = matched as f64 / filled.max(1) as f64;
+                (filled >= 2 && ratio >= HEADER_MATCH_RATIO).then_some((index, columns))
+            })
+    }
+
+    fn map_columns(&self, target: ImportTarget, row: &[String]) -> Vec<ColumnMapping> {
+        let mut columns: Vec<ColumnMapping> = Vec::with_capacity(row.len());
+        for (column, header) in row.iter().enumerate() {
+            let field = self
+                .field_for(target, header)
+                .filter(|field| columns.iter().all(|c| c.field != Some(*field)));
+            columns.push(ColumnMapping {
+                column,
+                header: header.trim().to_string(),
+                field,
+            });
+        }
+        columns
+    }
+}
+
+/// 去掉括号中的内容、空白和冒号，转为小写
+fn normalize(header: &str) -> String {
+    let mut depth = 0usize;
+    let mut normalized = String::with_capacity(header.len());
+    for c in header.chars() {
+        match c {
+            '(' | '（' | '[' | '【' => depth += 1,
+            ')' | '）' | ']' | '】' => depth = depth.saturating_sub(1),
+            ':' | '：' | '*' => {}
+            c if c.is_whitespace() => {}
+            c if depth == 0 => normalized.extend(c.to_lowercase()),
+            _ => {}
+        }
+    }
+    normalized
+}
+
+/// 列映射
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    /// 列号（从0开始）
+    pub column: usize,
+    /// 表头文字
+    pub header: String,
+    /// 对应的字段（不导入时为空）
+    pub field: Option<ImportField>,
+}
+
+/// 识别出的表头映射
+///
+/// 界面展示给用户确认，可调整后随导入命令再次提交。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetectedMapping {
+    /// 导入对象
+    pub target: ImportTarget,
+    /// 导入的工作表
+    pub sheet: String,
+    /// 工作簿中的全部工作表
+    pub sheets: Vec<String>,
+    /// 表头行号（从0开始，数据从下一行开始）
+    pub header_row: usize,
+    /// 各列映射
+    pub columns: Vec<ColumnMapping>,
+}
+
+impl DetectedMapping {
+    /// 字段所在的列
+    pub fn column_of(&self, field: ImportField) -> Option<usize> {
+        self.columns
+            .iter()
+            .find(|c| c.field == Some(field))
+            .map(|c| c.column)
+    }
+
+    /// 尚未映射的必填字段
+    pub fn missing_required(&self) -> Vec<ImportField> {
+        self.target
+            .required()
+            .iter()
+            .copied()
+            .filter(|field| self.column_of(*field).is_none())
+            .collect()
+    }
+
+    /// 调整某列对应的字段，该字段原先所在的列改为不导入
+    pub fn set_field(&mut self, column: usize, field: Option<ImportField>) {
+        for mapping in &mut self.columns {
+            if mapping.column == column {
+                mapping.field = field;
+            } else if field.is_some() && mapping.field == field {
+                mapping.field = None;
+            }
+        }
+    }
+}
+
+/// 导入失败的行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportRowError {
+    /// 工作表中的行号（从1开始，与表格软件一致）
+    pub row: usize,
+    /// 原因
+    pub message: String,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// 新建的记录数
+    pub created: usize,
+    /// 更新的记录数
+    pub updated: usize,
+    /// 内容未变化的记录数
+    pub unchanged: usize,
+    /// 跳过的空行数
+    pub blank_rows: usize,
+    /// 失败的行
+    pub errors: Vec<ImportRowError>,
+}
+
+impl ImportReport {
+    /// 成功导入的记录数（含未变化的）
+    pub fn imported(&self) -> usize {
+        self.created + self.updated + self.unchanged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(data: &[&[&str]]) -> Vec<Vec<String>> {
+        data.iter()
+            .map(|row| row.iter().map(ToString::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_shifted_header_below_title() {
+        let sheet = rows(&[
+            &["华美板材2024年5月报价单", "", "", ""],
+            &["报价日期：2024-05-06", "", "", ""],
+            &[],
+            &["单价(元)", "规格", "品名", "备注"],
+            &["128", "1220×2440", "多层板", ""],
+        ]);
+        let (row, columns) = HeaderAliases::default()
+            .detect(ImportTarget::Products, &sheet)
+            .unwrap();
+        assert_eq!(row, 3);
+        assert_eq!(columns[0].field, Some(ImportField::UnitPrice));
+        assert_eq!(columns[1].field, Some(ImportField::Specification));
+        assert_eq!(columns[2].field, Some(ImportField::ProductName));
+        assert_eq!(columns[3].field, None);
+        assert_eq!(columns[3].header, "备注");
+    }
+
+    #[test]
+    fn test_ratio_threshold_and_custom_alias() {
+        // 四个非空单元格只识别两个，不足六成
+        let sheet = rows(&[&["品名", "板号", "等级", "单价"], &["颗粒板", "A1", "E0", "98"]]);
+        let aliases = HeaderAliases::default();
+        assert!(aliases.detect(ImportTarget::Products, &sheet).is_none());
+
+        let aliases = aliases.with_alias(ImportField::Specification, "板号");
+        let (row, columns) = aliases.detect(ImportTarget::Products, &sheet).unwrap();
+        assert_eq!(row, 0);
+        assert_eq!(columns[1].field, Some(ImportField::Specification));
+
+        // 别名表可序列化到配置文件
+        let json = serde_json::to_string(&aliases).unwrap();
+        let parsed: HeaderAliases = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, aliases);
+    }
+
+    #[test]
+    fn test_adjust_mapping() {
+        let sheet = rows(&[&["名称", "厚度", "价格"]]);
+        let (header_row, columns) = HeaderAliases::default()
+            .detect(ImportTarget::Products, &sheet)
+            .unwrap();
+        let mut mapping = DetectedMapping {
+            target: ImportTarget::Products,
+            sheet: "Sheet1".to_string(),
+            sheets: vec!["Sheet1".to_string()],
+            header_row,
+            columns,
+        };
+        assert!(mapping.missing_required().is_empty());
+
+        mapping.set_field(1, Some(ImportField::UnitPrice));
+        assert_eq!(mapping.column_of(ImportField::UnitPrice), Some(1));
+        assert_eq!(mapping.columns[2].field, None);
+        mapping.set_field(1, None);
+        assert_eq!(mapping.missing_required(), vec![ImportField::UnitPrice]);
+    }
+}
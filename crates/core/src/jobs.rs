@@ -0,0 +1,158 @@
+//! 后台任务接口模块
+//!
+//! 定义可由任务调度器执行的后台任务及其调度计划
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+
+use crate::calendar::BusinessCalendar;
+use crate::error::CoreResult;
+
+/// 任务调度计划
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobSchedule {
+    /// 每天在指定时间（业务时区）运行一次
+    Daily {
+        /// 运行时间
+        at: NaiveTime,
+    },
+    /// 按固定间隔运行
+    Interval {
+        /// 间隔时长
+        every: Duration,
+    },
+    /// 每月指定日期和时间（业务时区）运行一次
+    ///
+    /// 日期超过当月天数时在月末运行。
+    Monthly {
+        /// 每月第几天（1-31）
+        day: u32,
+        /// 运行时间
+        at: NaiveTime,
+    },
+    /// 每周指定星期和时间（业务时区）运行一次
+    ///
+    /// 同一ISO周内最多运行一次；错过运行时间（如电脑关机）时在本周内补运行。
+    Weekly {
+        /// 星期几
+        weekday: Weekday,
+        /// 运行时间
+        at: NaiveTime,
+    },
+}
+
+impl JobSchedule {
+    /// 创建每日运行计划
+    pub fn daily(at: NaiveTime) -> Self {
+        JobSchedule::Daily { at }
+    }
+
+    /// 创建固定间隔运行计划
+    pub fn every(every: Duration) -> Self {
+        JobSchedule::Interval { every }
+    }
+
+    /// 创建每月运行计划
+    pub fn monthly(day: u32, at: NaiveTime) -> Self {
+        JobSchedule::Monthly {
+            day: day.clamp(1, 31),
+            at,
+        }
+    }
+
+    /// 创建每周运行计划
+    pub fn weekly(weekday: Weekday, at: NaiveTime) -> Self {
+        JobSchedule::Weekly { weekday, at }
+    }
+
+    /// 判断任务在 `now` 时刻是否应当运行（按UTC日历）
+    ///
+    /// * `last_run` - 上次运行时间，从未运行过为 `None`
+    pub fn is_due(&self, last_run: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        self.is_due_in(&BusinessCalendar::utc(), last_run, now)
+    }
+
+    /// 判断任务在 `now` 时刻是否应当运行，运行时间按业务日历的本地时间解释
+    ///
+    /// * `last_run` - 上次运行时间，从未运行过为 `None`
+    pub fn is_due_in(
+        &self,
+        calendar: &BusinessCalendar,
+        last_run: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        match self {
+            JobSchedule::Daily { at } => {
+                let today = calendar.local_date(now).naive();
+                let today_slot = calendar.to_utc(today.and_time(*at));
+                if now < today_slot {
+                    return false;
+                }
+                last_run.is_none_or(|last| last < today_slot)
+            }
+            JobSchedule::Interval { every } => {
+                last_run.is_none_or(|last| now - last >= *every)
+            }
+            JobSchedule::Monthly { day, at } => {
+                let today = calendar.local_date(now).naive();
+                let Some(date) = monthly_date(today.year(), today.month(), *day) else {
+                    return false;
+                };
+                let month_slot = calendar.to_utc(date.and_time(*at));
+                if now < month_slot {
+                    return false;
+                }
+                last_run.is_none_or(|last| last < month_slot)
+            }
+            JobSchedule::Weekly { weekday, at } => {
+                let today = calendar.local_date(now).naive();
+                let offset = i64::from(weekday.num_days_from_monday())
+                    - i64::from(today.weekday().num_days_from_monday());
+                let week_slot = calendar.to_utc((today + Duration::days(offset)).and_time(*at));
+                if now < week_slot {
+                    return false;
+                }
+                last_run.is_none_or(|last| {
+                    calendar.local_date(last).naive().iso_week() != today.iso_week()
+                })
+            }
+        }
+    }
+}
+
+/// 计算指定月份的运行日期，超过月末时取月末
+fn monthly_date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    (1..=day)
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+}
+
+/// 后台任务接口
+#[async_trait]
+pub trait Job: Send + Sync {
+    /// 任务名称（唯一）
+    fn name(&self) -> &str;
+
+    /// 调度计划
+    fn schedule(&self) -> JobSchedule;
+
+    /// 执行任务
+    async fn run(&self) -> CoreResult<()>;
+}
+
+/// 任务运行记录接口
+///
+/// 调度器用它在重启后恢复各任务的上次运行时间，避免重复运行或漏掉补运行。
+#[async_trait]
+pub trait JobRunLog: Send + Sync {
+    /// 任务上次运行的开始时间，从未运行过为 `None`
+    async fn last_run(&self, job_name: &str) -> CoreResult<Option<DateTime<Utc>>>;
+
+    /// 记录一次运行，`error` 为空表示运行成功
+    async fn record_run(
+        &self,
+        job_name: &str,
+        started_at: DateTime<Utc>,
+        error: Option<&str>,
+    ) -> CoreResult<()>;
+}
@@ -5,15 +5,89 @@
 #![deny(unsafe_code)]
 #![warn(missing_docs)]
 
+pub mod activity;
+pub mod anniversary;
+pub mod calendar;
+pub mod cancellation;
+pub mod canonical_json;
+pub mod clock;
+pub mod credit;
+pub mod cutting;
+pub mod dedup;
+pub mod display;
 pub mod entity;
 pub mod error;
+pub mod formatting;
+pub mod import;
+pub mod events;
+pub mod exclusive;
+pub mod field_policy;
+pub mod jobs;
+pub mod money;
+pub mod purchasing;
 pub mod repository;
+pub mod revision;
+pub mod search;
+pub mod security;
 pub mod service;
+pub mod size_growth;
+pub mod staleness;
+pub mod timeline;
 pub mod types;
+pub mod validation;
+pub mod verification;
+pub mod working_time;
 
 // 重新导出核心类型
+pub use activity::{ActivityScorer, ActivityStats, ActivityWeights, ChannelActivity, ChurnRule};
+pub use anniversary::{
+    occurrence_in, upcoming_within, AnniversaryKind, CustomerAnniversary, UpcomingAnniversary,
+    ANNIVERSARY_LOOKAHEAD_DAYS, DEFAULT_ANNIVERSARY_LEAD_DAYS,
+};
+pub use calendar::{BusinessCalendar, LocalDate, DEFAULT_BUSINESS_TIMEZONE};
+pub use cancellation::CancellationToken;
+pub use canonical_json::{from_canonical_json, to_canonical_json, CanonicalEntity};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use credit::{CreditOverride, CreditProfile};
+pub use cutting::{CutCalculator, CutPiece, CutResult, SheetSize};
+pub use dedup::{
+    classify_duplicates, DuplicateCandidate, DuplicateMatch, PossibleDuplicate,
+    MAX_DUPLICATE_SUGGESTIONS,
+};
+pub use display::{BadgeTone, DisplayName};
 pub use entity::*;
-pub use error::{CoreError, CoreResult};
+pub use error::{CoreError, CoreResult, FieldError};
+pub use formatting::{money_chinese_upper, DateStyle, Locale};
+pub use events::*;
+pub use exclusive::{ExclusiveGuard, ExclusiveLease};
+pub use field_policy::{policy_fields, FieldPolicy, FieldRequirement, PolicyField};
+pub use import::{
+    ColumnMapping, DetectedMapping, HeaderAliases, ImportField, ImportReport, ImportRowError,
+    ImportTarget,
+};
+pub use jobs::*;
+pub use money::{Currency, Money};
+pub use purchasing::{
+    PurchaseOrder, PurchaseOrderLine, PurchaseOrderStatus, PurchaseReceipt, StockMovement,
+    StockMovementKind, SupplierPayables, PURCHASE_ORDER_PREFIX,
+};
 pub use repository::*;
+pub use revision::{FieldChange, ItemChange, QuoteDiff, QuoteRevision, MAX_QUOTE_REVISIONS};
+pub use search::{SearchCandidate, SearchField, SearchHit, SearchQueryClass, SearchRanking};
+pub use security::PasscodeVerifier;
 pub use service::*;
+pub use size_growth::{
+    detect_size_growth, size_growth, size_history_cutoff, SizeGrowth, SizeGrowthThresholds,
+    SizeGrowthWindow, SizeSample, TableGrowth, SIZE_GROWTH_TOP_TABLES, SIZE_HISTORY_RETENTION_DAYS,
+};
+pub use staleness::{
+    Freshness, StalenessEvaluator, StalenessKind, StalenessRule, StalenessThresholds,
+};
+pub use timeline::{
+    TimelineCategory, TimelineCursor, TimelineEventType, TimelineItem, TimelinePage,
+    TimelineQuery, TIMELINE_PAGE_SIZE,
+};
 pub use types::*;
+pub use validation::{EmailAddress, PhoneNumber};
+pub use verification::*;
+pub use working_time::{HolidayEntry, HolidayKind, SlaEvaluator, SlaPolicy, WorkingCalendar};
@@ -0,0 +1,338 @@
+//! 金额类型模块
+//!
+//! 以"分"为单位的整数金额，避免浮点误差在比价和汇总时累积。
+//! 金额本身不带币种，币种由所属单据（报价、订单、收款）记录；不同币种的金额
+//! 只在统计、应收和报表中按汇率折算为本位币后相加。
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CoreError;
+use crate::formatting::Locale;
+
+/// 金额（单位：分，即币种的最小单位；币种由所属单据记录）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Money(i64);
+
+impl Money {
+    /// 零元
+    pub const ZERO: Money = Money(0);
+
+    /// 以分创建金额
+    pub const fn from_cents(cents: i64) -> Self {
+        Self(cents)
+    }
+
+    /// 以元创建金额，四舍五入到分
+    ///
+    /// 与 [`as_yuan`](Self::as_yuan) 互逆：绝对值小于 2^50 分的金额转为元（如写入 `REAL` 列）
+    /// 再转回时不丢失精度。
+    pub fn from_yuan(yuan: f64) -> Self {
+        Self((yuan * 100.0).round() as i64)
+    }
+
+    /// 金额（分）
+    pub const fn cents(&self) -> i64 {
+        self.0
+    }
+
+    /// 金额（元）
+    pub fn as_yuan(&self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    /// 是否为正数
+    pub const fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    /// 相加，溢出时返回 `None`
+    pub const fn checked_add(self, rhs: Money) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(cents) => Some(Self(cents)),
+            None => None,
+        }
+    }
+
+    /// 相减，溢出时返回 `None`
+    pub const fn checked_sub(self, rhs: Money) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(cents) => Some(Self(cents)),
+            None => None,
+        }
+    }
+
+    /// 乘以整数倍数（如件数），溢出时返回 `None`
+    pub const fn checked_mul(self, factor: i64) -> Option<Self> {
+        match self.0.checked_mul(factor) {
+            Some(cents) => Some(Self(cents)),
+            None => None,
+        }
+    }
+
+    /// 按百分比折算的金额，四舍五入到分
+    pub fn percent(&self, percent: u8) -> Self {
+        let product = i128::from(self.0) * i128::from(percent);
+        Self(((product + product.signum() * 50) / 100) as i64)
+    }
+
+    /// 按百分比调整后的金额（`5` 表示上调5%，`-5` 表示下调5%），四舍五入到分
+    pub fn adjusted_by_percent(&self, percent: i32) -> Self {
+        let product = i128::from(self.0) * (100 + i128::from(percent));
+        Self(((product + product.signum() * 50) / 100) as i64)
+    }
+
+    /// 按汇率折算的金额，四舍五入到分
+    pub fn convert(&self, rate: f64) -> Self {
+        Self((self.0 as f64 * rate).round() as i64)
+    }
+
+    /// 多个金额的平均值，四舍五入到分；为空时返回 `None`
+    pub fn average<I: IntoIterator<Item = Money>>(amounts: I) -> Option<Self> {
+        let (sum, count) = amounts
+            .into_iter()
+            .fold((0i128, 0i128), |(sum, count), m| (sum + i128::from(m.0), count + 1));
+        if count == 0 {
+            return None;
+        }
+        Some(Self((sum as f64 / count as f64).round() as i64))
+    }
+}
+
+impl std::ops::Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl std::iter::Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Self {
+        iter.fold(Money::ZERO, |acc, m| acc + m)
+    }
+}
+
+/// 按界面默认格式显示（¥12,345.67）
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Locale::ZhCn.money(*self))
+    }
+}
+
+/// 解析输入的金额（元）
+///
+/// 可带 `¥` 前缀、“元”后缀和千位分隔的逗号，最多两位小数，如 `¥1,280.5`。
+impl std::str::FromStr for Money {
+    type Err = CoreError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || CoreError::validation("金额格式不正确");
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let text = text.trim_start_matches(['¥', '￥']).trim_end_matches('元').trim();
+        let text = text.replace(',', "");
+        let (yuan, fen) = text.split_once('.').unwrap_or((&text, ""));
+        if yuan.is_empty()
+            || fen.len() > 2
+            || (text.contains('.') && fen.is_empty())
+            || !yuan.chars().chain(fen.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let fen = format!("{:0<2}", fen);
+        let cents = yuan
+            .parse::<i64>()
+            .ok()
+            .and_then(|yuan| yuan.checked_mul(100))
+            .and_then(|cents| cents.checked_add(fen.parse::<i64>().ok()?))
+            .ok_or_else(invalid)?;
+        Ok(Self(if negative { -cents } else { cents }))
+    }
+}
+
+/// 以元（浮点数）序列化金额
+///
+/// 用于早期以元保存的字段（如 [`Quote::total_amount`](crate::Quote)），保持JSON快照和
+/// 规范化哈希不变：`#[serde(with = "crate::money::yuan")]`。
+pub mod yuan {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::Money;
+
+    /// 序列化为元
+    pub fn serialize<S: Serializer>(amount: &Money, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(amount.as_yuan())
+    }
+
+    /// 从元反序列化，四舍五入到分
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        f64::deserialize(deserializer).map(Money::from_yuan)
+    }
+}
+
+/// 币种（ISO 4217 三位字母代码）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    /// 人民币
+    pub const CNY: Currency = Currency(*b"CNY");
+    /// 美元
+    pub const USD: Currency = Currency(*b"USD");
+    /// 本位币（账簿、统计和报表使用的币种）
+    pub const BASE: Currency = Currency::CNY;
+
+    /// 币种代码
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+
+    /// 是否为本位币
+    pub fn is_base(&self) -> bool {
+        *self == Self::BASE
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::BASE
+    }
+}
+
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Currency {
+    type Err = CoreError;
+
+    /// 解析币种代码（不区分大小写）
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let code = code.trim().to_ascii_uppercase();
+        match <[u8; 3]>::try_from(code.as_bytes()) {
+            Ok(bytes) if bytes.iter().all(u8::is_ascii_uppercase) => Ok(Self(bytes)),
+            _ => Err(CoreError::validation(format!("无效的币种代码: {}", code))),
+        }
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = CoreError;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        code.parse()
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency_parse_and_convert() {
+        assert_eq!("usd".parse::<Currency>().unwrap(), Currency::USD);
+        assert!("US".parse::<Currency>().is_err());
+        assert!("U$D".parse::<Currency>().is_err());
+        assert!(Currency::default().is_base());
+        assert_eq!(
+            serde_json::to_string(&Currency::USD).unwrap(),
+            "\"USD\""
+        );
+        assert!(serde_json::from_str::<Currency>("\"12\"").is_err());
+
+        assert_eq!(Money::from_cents(10_000).convert(7.1234), Money::from_cents(71_234));
+    }
+
+    /// 固定种子的伪随机金额（xorshift），覆盖各数量级，另加边界值
+    fn sample_amounts() -> Vec<Money> {
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        let mut amounts: Vec<Money> = [0, 1, -1, 99, 100, 101, -1_234_567, (1 << 50) - 1]
+            .into_iter()
+            .map(Money::from_cents)
+            .collect();
+        for _ in 0..10_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let magnitude = 10_i64.pow((state % 16) as u32);
+            let cents = (state >> 8) as i64 % magnitude;
+            amounts.push(Money::from_cents(if state & 1 == 0 { cents } else { -cents }));
+        }
+        amounts
+    }
+
+    #[test]
+    fn test_round_trips_keep_every_cent() {
+        #[derive(Serialize, Deserialize)]
+        struct Doc {
+            #[serde(with = "yuan")]
+            total: Money,
+        }
+
+        for amount in sample_amounts() {
+            // REAL 列和以元保存的JSON字段
+            assert_eq!(Money::from_yuan(amount.as_yuan()), amount, "{}", amount.cents());
+            let json = serde_json::to_string(&Doc { total: amount }).unwrap();
+            let doc: Doc = serde_json::from_str(&json).unwrap();
+            assert_eq!(doc.total, amount, "{json}");
+            // 以分保存的JSON字段
+            let json = serde_json::to_string(&amount).unwrap();
+            assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), amount);
+            // 显示后再解析
+            assert_eq!(amount.to_string().parse::<Money>().unwrap(), amount);
+        }
+    }
+
+    #[test]
+    fn test_checked_arithmetic_and_display() {
+        let amount = Money::from_cents(1_234_567);
+        assert_eq!(amount.to_string(), "¥12,345.67");
+        assert_eq!(Money::from_cents(-5).to_string(), "-¥0.05");
+        assert_eq!(
+            amount.checked_add(Money::from_cents(33)),
+            Some(Money::from_cents(1_234_600))
+        );
+        assert_eq!(amount.checked_sub(amount), Some(Money::ZERO));
+        assert_eq!(amount.checked_mul(3), Some(Money::from_cents(3_703_701)));
+        assert_eq!(Money::from_cents(i64::MAX).checked_add(Money::from_cents(1)), None);
+        assert_eq!(Money::from_cents(i64::MIN).checked_sub(Money::from_cents(1)), None);
+        assert_eq!(amount.checked_mul(i64::MAX), None);
+    }
+
+    #[test]
+    fn test_money_parse() {
+        for (text, cents) in [
+            ("128", 12_800),
+            ("¥1,280.5", 128_050),
+            (" 96.05元 ", 9_605),
+            ("-3.2", -320),
+            ("0", 0),
+        ] {
+            assert_eq!(text.parse::<Money>().unwrap(), Money::from_cents(cents), "{text}");
+        }
+        for text in ["", "¥", "12.", ".5", "1.234", "12a", "1e3", "99999999999999999999"] {
+            assert!(text.parse::<Money>().is_err(), "{text}");
+        }
+    }
+}
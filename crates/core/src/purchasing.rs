@@ -0,0 +1,429 @@
+//! 采购订单模块
+//!
+//! 向供应商下的采购订单与销售订单对应：草稿发出后按实际到货分批收货，每次收货按产品
+//! 生成入库的库存变动，全部明细收齐后订单变为已收货。取消只允许在尚未收货时进行。
+//! 应付金额按已收货的明细计算（与应收按已送达订单计算一致）。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{CoreError, CoreResult};
+use crate::money::Money;
+
+/// 采购订单号前缀（编号按本地日期递增，如 `PO20240701-001`）
+pub const PURCHASE_ORDER_PREFIX: &str = "PO";
+
+/// 采购订单状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PurchaseOrderStatus {
+    /// 草稿
+    #[default]
+    Draft,
+    /// 已发出
+    Sent,
+    /// 部分收货
+    PartiallyReceived,
+    /// 已收货
+    Received,
+    /// 已取消
+    Cancelled,
+}
+
+impl PurchaseOrderStatus {
+    /// 状态名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PurchaseOrderStatus::Draft => "draft",
+            PurchaseOrderStatus::Sent => "sent",
+            PurchaseOrderStatus::PartiallyReceived => "partially_received",
+            PurchaseOrderStatus::Received => "received",
+            PurchaseOrderStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// 从状态名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(PurchaseOrderStatus::Draft),
+            "sent" => Some(PurchaseOrderStatus::Sent),
+            "partially_received" => Some(PurchaseOrderStatus::PartiallyReceived),
+            "received" => Some(PurchaseOrderStatus::Received),
+            "cancelled" => Some(PurchaseOrderStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// 中文显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            PurchaseOrderStatus::Draft => "草稿",
+            PurchaseOrderStatus::Sent => "已发出",
+            PurchaseOrderStatus::PartiallyReceived => "部分收货",
+            PurchaseOrderStatus::Received => "已收货",
+            PurchaseOrderStatus::Cancelled => "已取消",
+        }
+    }
+
+    /// 是否允许转换到 `next`
+    ///
+    /// 草稿只能发出或取消；已发出的订单收货后变为部分收货或已收货，尚未收货时可以取消；
+    /// 部分收货可以继续收货。已收货和已取消为终态。
+    pub fn can_transition_to(self, next: PurchaseOrderStatus) -> bool {
+        matches!(
+            (self, next),
+            (Self::Draft, Self::Sent)
+                | (Self::Draft | Self::Sent, Self::Cancelled)
+                | (
+                    Self::Sent | Self::PartiallyReceived,
+                    Self::PartiallyReceived | Self::Received
+                )
+        )
+    }
+
+    /// 是否已发出但尚未收齐
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            PurchaseOrderStatus::Sent | PurchaseOrderStatus::PartiallyReceived
+        )
+    }
+}
+
+/// 采购订单明细
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurchaseOrderLine {
+    /// 产品ID
+    pub product_id: Uuid,
+    /// 订购数量
+    pub quantity: u32,
+    /// 约定单价
+    pub unit_price: Money,
+    /// 已收数量
+    #[serde(default)]
+    pub received_quantity: u32,
+}
+
+impl PurchaseOrderLine {
+    /// 尚未收货的数量
+    pub fn remaining(&self) -> u32 {
+        self.quantity.saturating_sub(self.received_quantity)
+    }
+
+    /// 订购金额
+    pub fn amount(&self) -> Money {
+        Money::from_cents(self.unit_price.cents() * i64::from(self.quantity))
+    }
+
+    /// 已收货金额
+    pub fn received_amount(&self) -> Money {
+        Money::from_cents(self.unit_price.cents() * i64::from(self.received_quantity))
+    }
+}
+
+/// 一次收货中某个产品的到货数量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurchaseReceipt {
+    /// 产品ID
+    pub product_id: Uuid,
+    /// 到货数量
+    pub quantity: u32,
+}
+
+/// 采购订单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PurchaseOrder {
+    /// 采购订单ID
+    pub id: Uuid,
+    /// 采购订单号（创建时分配）
+    #[serde(default)]
+    pub po_number: String,
+    /// 供应商ID
+    pub supplier_id: Uuid,
+    /// 明细
+    pub lines: Vec<PurchaseOrderLine>,
+    /// 状态
+    #[serde(default)]
+    pub status: PurchaseOrderStatus,
+    /// 预计到货日期
+    #[serde(default)]
+    pub expected_date: Option<DateTime<Utc>>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+    /// 创建人（用户名）
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+impl PurchaseOrder {
+    /// 订单金额
+    pub fn total_amount(&self) -> Money {
+        self.lines.iter().map(PurchaseOrderLine::amount).sum()
+    }
+
+    /// 已收货金额
+    pub fn received_amount(&self) -> Money {
+        self.lines
+            .iter()
+            .map(PurchaseOrderLine::received_amount)
+            .sum()
+    }
+
+    /// 已发出尚未到货部分的金额（草稿和已取消的订单为零）
+    pub fn on_order_amount(&self) -> Money {
+        if !self.status.is_open() {
+            return Money::ZERO;
+        }
+        self.total_amount() - self.received_amount()
+    }
+
+    /// 是否全部收齐
+    pub fn is_fully_received(&self) -> bool {
+        self.lines.iter().all(|line| line.remaining() == 0)
+    }
+
+    /// 校验新建的采购订单：至少一行明细，数量为正，单价不为负
+    pub fn validate(&self) -> CoreResult<()> {
+        if self.lines.is_empty() {
+            return Err(CoreError::validation("采购订单至少需要一行明细"));
+        }
+        if self.lines.iter().any(|line| line.quantity == 0) {
+            return Err(CoreError::validation("采购数量必须大于0"));
+        }
+        if self.lines.iter().any(|line| line.unit_price < Money::ZERO) {
+            return Err(CoreError::validation("采购单价不能为负数"));
+        }
+        Ok(())
+    }
+
+    /// 登记一次收货并更新状态，返回每个产品实际入库的数量
+    ///
+    /// 同一产品有多行明细时按明细顺序依次收满。到货数量超过未收数量、产品不在订单中
+    /// 或订单当前状态不能收货时返回错误，订单保持不变。
+    pub fn receive(&mut self, receipts: &[PurchaseReceipt]) -> CoreResult<Vec<PurchaseReceipt>> {
+        let receipts: Vec<PurchaseReceipt> = receipts
+            .iter()
+            .copied()
+            .filter(|r| r.quantity > 0)
+            .collect();
+        if receipts.is_empty() {
+            return Err(CoreError::validation("收货数量必须大于0"));
+        }
+        if !self
+            .status
+            .can_transition_to(PurchaseOrderStatus::PartiallyReceived)
+        {
+            return Err(CoreError::business(format!(
+                "采购订单 {} 当前为「{}」，不能收货",
+                self.po_number,
+                self.status.label()
+            )));
+        }
+
+        let mut lines = self.lines.clone();
+        for receipt in &receipts {
+            if !lines
+                .iter()
+                .any(|line| line.product_id == receipt.product_id)
+            {
+                return Err(CoreError::validation(format!(
+                    "产品 {} 不在采购订单 {} 中",
+                    receipt.product_id, self.po_number
+                )));
+            }
+            let remaining: u32 = lines
+                .iter()
+                .filter(|line| line.product_id == receipt.product_id)
+                .map(PurchaseOrderLine::remaining)
+                .sum();
+            if receipt.quantity > remaining {
+                return Err(CoreError::validation(format!(
+                    "产品 {} 到货 {}，超过未收数量 {}",
+                    receipt.product_id, receipt.quantity, remaining
+                )));
+            }
+            let mut left = receipt.quantity;
+            for line in lines
+                .iter_mut()
+                .filter(|line| line.product_id == receipt.product_id)
+            {
+                let take = left.min(line.remaining());
+                line.received_quantity += take;
+                left -= take;
+            }
+        }
+
+        self.lines = lines;
+        self.status = if self.is_fully_received() {
+            PurchaseOrderStatus::Received
+        } else {
+            PurchaseOrderStatus::PartiallyReceived
+        };
+        Ok(receipts)
+    }
+}
+
+/// 库存变动类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StockMovementKind {
+    /// 采购入库
+    Purchase,
+    /// 手工调整
+    Adjustment,
+}
+
+impl StockMovementKind {
+    /// 类型名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StockMovementKind::Purchase => "purchase",
+            StockMovementKind::Adjustment => "adjustment",
+        }
+    }
+
+    /// 从类型名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "purchase" => Some(StockMovementKind::Purchase),
+            "adjustment" => Some(StockMovementKind::Adjustment),
+            _ => None,
+        }
+    }
+}
+
+/// 库存变动（入库为正，出库为负）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StockMovement {
+    /// 变动ID
+    pub id: Uuid,
+    /// 产品ID
+    pub product_id: Uuid,
+    /// 变动类型
+    pub kind: StockMovementKind,
+    /// 数量
+    pub quantity: i64,
+    /// 来源单据ID（采购入库为采购订单）
+    #[serde(default)]
+    pub reference_id: Option<Uuid>,
+    /// 发生时间
+    pub occurred_at: DateTime<Utc>,
+    /// 操作人（用户名）
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+/// 供应商应付情况
+///
+/// 目前不登记向供应商的付款，应付按已收货的金额计。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupplierPayables {
+    /// 供应商ID
+    pub supplier_id: Uuid,
+    /// 已收货的应付金额
+    pub outstanding_payables: Money,
+    /// 已发出尚未到货部分的金额
+    pub on_order: Money,
+    /// 未收齐的采购订单数
+    pub open_orders: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(lines: Vec<(Uuid, u32, i64)>) -> PurchaseOrder {
+        let now = Utc::now();
+        PurchaseOrder {
+            id: Uuid::new_v4(),
+            po_number: "PO-2024-0001".to_string(),
+            supplier_id: Uuid::new_v4(),
+            lines: lines
+                .into_iter()
+                .map(|(product_id, quantity, cents)| PurchaseOrderLine {
+                    product_id,
+                    quantity,
+                    unit_price: Money::from_cents(cents),
+                    received_quantity: 0,
+                })
+                .collect(),
+            status: PurchaseOrderStatus::Sent,
+            expected_date: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+        }
+    }
+
+    fn receipt(product_id: Uuid, quantity: u32) -> PurchaseReceipt {
+        PurchaseReceipt {
+            product_id,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        use PurchaseOrderStatus::*;
+        assert!(Draft.can_transition_to(Sent));
+        assert!(Draft.can_transition_to(Cancelled));
+        assert!(Sent.can_transition_to(Cancelled));
+        assert!(PartiallyReceived.can_transition_to(Received));
+        assert!(!Draft.can_transition_to(Received));
+        assert!(!PartiallyReceived.can_transition_to(Cancelled));
+        assert!(!Received.can_transition_to(PartiallyReceived));
+        assert!(!Cancelled.can_transition_to(Sent));
+        for status in [Draft, Sent, PartiallyReceived, Received, Cancelled] {
+            assert_eq!(PurchaseOrderStatus::parse(status.as_str()), Some(status));
+        }
+    }
+
+    #[test]
+    fn test_partial_receiving() {
+        let board = Uuid::new_v4();
+        let edge = Uuid::new_v4();
+        // 同一产品分两行下单
+        let mut po = order(vec![
+            (board, 10, 12_000),
+            (edge, 50, 300),
+            (board, 5, 11_000),
+        ]);
+        assert_eq!(
+            po.total_amount(),
+            Money::from_cents(120_000 + 15_000 + 55_000)
+        );
+
+        let received = po.receive(&[receipt(board, 12), receipt(edge, 0)]).unwrap();
+        assert_eq!(received, vec![receipt(board, 12)]);
+        assert_eq!(po.status, PurchaseOrderStatus::PartiallyReceived);
+        assert_eq!(po.lines[0].received_quantity, 10);
+        assert_eq!(po.lines[2].received_quantity, 2);
+        assert_eq!(po.received_amount(), Money::from_cents(120_000 + 22_000));
+        assert_eq!(po.on_order_amount(), Money::from_cents(15_000 + 33_000));
+
+        // 超收和订单外的产品不改变订单
+        let before = po.clone();
+        assert!(po.receive(&[receipt(board, 4)]).is_err());
+        assert!(po.receive(&[receipt(Uuid::new_v4(), 1)]).is_err());
+        assert!(po.receive(&[receipt(edge, 0)]).is_err());
+        assert_eq!(po, before);
+
+        po.receive(&[receipt(board, 3), receipt(edge, 50)]).unwrap();
+        assert_eq!(po.status, PurchaseOrderStatus::Received);
+        assert!(po.is_fully_received());
+        assert_eq!(po.on_order_amount(), Money::ZERO);
+        assert!(po.receive(&[receipt(board, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_draft_cannot_receive() {
+        let product = Uuid::new_v4();
+        let mut po = order(vec![(product, 1, 100)]);
+        po.status = PurchaseOrderStatus::Draft;
+        assert!(po.receive(&[receipt(product, 1)]).is_err());
+
+        po.lines.clear();
+        assert!(po.validate().is_err());
+    }
+}
@@ -5,6 +5,7 @@
 use crate::{
     entity::*,
     error::CoreResult,
+    events::EntityKind,
     types::{PagedResult, QueryFilter},
 };
 use async_trait::async_trait;
@@ -32,6 +33,23 @@ pub trait Repository<T, ID> {
     async fn find_with_filter(&self, filter: &QueryFilter) -> CoreResult<PagedResult<T>>;
 }
 
+/// 列表总数来源
+///
+/// 为列表查询提供精确计数和缓存总数两种统计方式
+#[async_trait]
+pub trait TotalCountSource: Send + Sync {
+    /// 对应的实体类型
+    fn entity_kind(&self) -> EntityKind;
+
+    /// 统计匹配过滤条件的记录数
+    ///
+    /// `cap` 为计数上限，达到上限后立即停止扫描并返回上限值
+    async fn count_matching(&self, filter: &QueryFilter, cap: Option<u64>) -> CoreResult<u64>;
+
+    /// 读取按表维护的缓存总数（不含已软删除记录）
+    async fn cached_total(&self) -> CoreResult<u64>;
+}
+
 /// 客户仓储接口
 #[async_trait]
 pub trait CustomerRepository: Repository<Customer, Uuid> {
@@ -0,0 +1,196 @@
+//! 报价修订模块
+//!
+//! 非草稿报价每次保存都会留存完整快照，便于比较谈判过程中各版本的差异
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entity::{Quote, QuoteItem};
+
+/// 每个报价保留的修订数上限
+pub const MAX_QUOTE_REVISIONS: usize = 20;
+
+/// 报价修订
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteRevision {
+    /// 报价ID
+    pub quote_id: Uuid,
+    /// 修订号（从1开始递增）
+    pub revision_no: u32,
+    /// 保存时的完整快照
+    pub snapshot: Quote,
+    /// 保存人（用户名）
+    pub saved_by: Option<String>,
+    /// 保存时间
+    pub saved_at: DateTime<Utc>,
+}
+
+/// 字段变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// 字段名
+    pub field: String,
+    /// 原值
+    pub old: String,
+    /// 新值
+    pub new: String,
+}
+
+/// 明细行变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ItemChange {
+    /// 新增明细
+    Added {
+        /// 新增的明细
+        item: QuoteItem,
+    },
+    /// 删除明细
+    Removed {
+        /// 删除的明细
+        item: QuoteItem,
+    },
+    /// 修改明细
+    Changed {
+        /// 修改前
+        old: QuoteItem,
+        /// 修改后
+        new: QuoteItem,
+        /// 变化的字段
+        fields: Vec<FieldChange>,
+    },
+}
+
+impl ItemChange {
+    /// 金额变化（新 - 旧）
+    pub fn amount_delta(&self) -> f64 {
+        match self {
+            Self::Added { item } => item.amount(),
+            Self::Removed { item } => -item.amount(),
+            Self::Changed { old, new, .. } => new.amount() - old.amount(),
+        }
+    }
+}
+
+/// 两个修订之间的差异
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteDiff {
+    /// 起始修订号
+    pub from_revision: u32,
+    /// 目标修订号
+    pub to_revision: u32,
+    /// 报价字段变化
+    pub fields: Vec<FieldChange>,
+    /// 明细行变化
+    pub items: Vec<ItemChange>,
+}
+
+impl QuoteDiff {
+    /// 比较两个修订
+    ///
+    /// 明细按ID匹配，仅调整顺序不视为变化。
+    pub fn between(from: &QuoteRevision, to: &QuoteRevision) -> Self {
+        let (a, b) = (&from.snapshot, &to.snapshot);
+        let mut fields = Vec::new();
+        push_change(&mut fields, "quote_number", &a.quote_number, &b.quote_number);
+        push_change(&mut fields, "customer_id", &a.customer_id, &b.customer_id);
+        push_change(&mut fields, "status", &format!("{:?}", a.status), &format!("{:?}", b.status));
+        push_change(
+            &mut fields,
+            "total_amount",
+            &format!("{:.2}", a.total_amount.as_yuan()),
+            &format!("{:.2}", b.total_amount.as_yuan()),
+        );
+        push_change(
+            &mut fields,
+            "valid_until",
+            &a.valid_until.date_naive(),
+            &b.valid_until.date_naive(),
+        );
+        push_change(
+            &mut fields,
+            "remarks",
+            &a.remarks.as_deref().unwrap_or(""),
+            &b.remarks.as_deref().unwrap_or(""),
+        );
+
+        let mut items = Vec::new();
+        for old in &a.items {
+            match b.items.iter().find(|item| item.id == old.id) {
+                None => items.push(ItemChange::Removed { item: old.clone() }),
+                Some(new) if !same_line(old, new) => items.push(ItemChange::Changed {
+                    old: old.clone(),
+                    new: new.clone(),
+                    fields: item_fields(old, new),
+                }),
+                Some(_) => {}
+            }
+        }
+        for new in &b.items {
+            if !a.items.iter().any(|item| item.id == new.id) {
+                items.push(ItemChange::Added { item: new.clone() });
+            }
+        }
+
+        Self {
+            from_revision: from.revision_no,
+            to_revision: to.revision_no,
+            fields,
+            items,
+        }
+    }
+
+    /// 是否没有任何差异
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.items.is_empty()
+    }
+}
+
+fn push_change<T: ToString + PartialEq>(out: &mut Vec<FieldChange>, field: &str, old: &T, new: &T) {
+    if old != new {
+        out.push(FieldChange {
+            field: field.to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+        });
+    }
+}
+
+/// 明细内容是否相同（所属报价ID不参与比较，旧快照中的明细可能未填写）
+fn same_line(old: &QuoteItem, new: &QuoteItem) -> bool {
+    *old == QuoteItem {
+        quote_id: old.quote_id,
+        ..new.clone()
+    }
+}
+
+fn item_fields(old: &QuoteItem, new: &QuoteItem) -> Vec<FieldChange> {
+    let mut fields = Vec::new();
+    push_change(&mut fields, "product_name", &old.product_name, &new.product_name);
+    push_change(
+        &mut fields,
+        "specification",
+        &old.specification.clone().unwrap_or_default(),
+        &new.specification.clone().unwrap_or_default(),
+    );
+    push_change(
+        &mut fields,
+        "unit",
+        &old.unit.clone().unwrap_or_default(),
+        &new.unit.clone().unwrap_or_default(),
+    );
+    push_change(&mut fields, "quantity", &old.quantity.to_string(), &new.quantity.to_string());
+    push_change(
+        &mut fields,
+        "unit_price",
+        &format!("{:.2}", old.unit_price),
+        &format!("{:.2}", new.unit_price),
+    );
+    push_change(
+        &mut fields,
+        "amount",
+        &format!("{:.2}", old.amount()),
+        &format!("{:.2}", new.amount()),
+    );
+    fields
+}
@@ -0,0 +1,381 @@
+//! 全局搜索排序模块
+//!
+//! 全文索引的 bm25 只反映文本相关度，输入完整手机号时模糊匹配的名称可能排在号码完全相同的
+//! 客户前面。这里先按查询的形态分类，再把精确命中的加分与全文得分合成一个可比较的分数：
+//!
+//! - 全是数字：电话、报价单号、订单号完全相同或前缀相同时大幅加分
+//! - 含“公司”或“厂”：公司名称命中时加分
+//! - 不超过三个汉字：姓名以查询开头时加分（通常是在找联系人）
+//!
+//! 同一记录在多个字段命中时合并为一条，取最高分。
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::events::EntityKind;
+
+/// 全文得分的权重
+pub const DEFAULT_FTS_WEIGHT: f64 = 10.0;
+
+/// 没有全文得分的命中（短查询、号码匹配）按此相关度计算
+pub const DEFAULT_SUBSTRING_RELEVANCE: f64 = 0.5;
+
+/// 数字查询与电话或单号完全相同
+pub const DEFAULT_EXACT_NUMBER_BONUS: f64 = 100.0;
+
+/// 数字查询是电话或单号的前缀
+pub const DEFAULT_NUMBER_PREFIX_BONUS: f64 = 40.0;
+
+/// 含“公司/厂”的查询命中公司名称
+pub const DEFAULT_COMPANY_BONUS: f64 = 30.0;
+
+/// 短中文查询是姓名的前缀
+pub const DEFAULT_NAME_PREFIX_BONUS: f64 = 30.0;
+
+/// 姓名或公司名称与查询完全相同
+pub const DEFAULT_EXACT_TEXT_BONUS: f64 = 50.0;
+
+/// 按姓名前缀加分的最大汉字数
+const SHORT_NAME_MAX_CHARS: usize = 3;
+
+/// 查询的形态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchQueryClass {
+    /// 全是数字（忽略空格和连字符），优先电话和单号
+    Number,
+    /// 含“公司”或“厂”，优先公司名称
+    Company,
+    /// 不超过三个汉字，优先姓名前缀
+    ShortName,
+    /// 其他
+    General,
+}
+
+impl SearchQueryClass {
+    /// 判断查询的形态
+    pub fn classify(query: &str) -> Self {
+        let query = query.trim();
+        if is_number(query) && query.chars().any(|c| c.is_ascii_digit()) {
+            return Self::Number;
+        }
+        if query.contains("公司") || query.contains('厂') {
+            return Self::Company;
+        }
+        let chars = query.chars().count();
+        if chars > 0 && chars <= SHORT_NAME_MAX_CHARS && query.chars().all(is_cjk) {
+            return Self::ShortName;
+        }
+        Self::General
+    }
+}
+
+/// 命中的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SearchField {
+    /// 客户姓名
+    Name,
+    /// 公司名称
+    Company,
+    /// 电话
+    Phone,
+    /// 报价单号
+    QuoteNumber,
+    /// 订单号
+    OrderNumber,
+    /// 工单号
+    TicketNumber,
+}
+
+impl SearchField {
+    /// 是否为号码类字段
+    pub fn is_number(self) -> bool {
+        matches!(
+            self,
+            Self::Phone | Self::QuoteNumber | Self::OrderNumber | Self::TicketNumber
+        )
+    }
+}
+
+/// 搜索候选（数据源返回，未排序，同一记录可能出现多次）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchCandidate {
+    /// 记录类型
+    pub entity: EntityKind,
+    /// 记录ID
+    pub id: Uuid,
+    /// 标题（客户名称、单号等）
+    pub title: String,
+    /// 副标题（公司、所属客户等）
+    pub subtitle: Option<String>,
+    /// 命中的字段
+    pub field: SearchField,
+    /// 命中字段的值
+    pub value: String,
+    /// 全文索引的 bm25 得分（越小越相关），非全文命中时为空
+    pub bm25: Option<f64>,
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// 记录类型
+    pub entity: EntityKind,
+    /// 记录ID
+    pub id: Uuid,
+    /// 标题
+    pub title: String,
+    /// 副标题
+    pub subtitle: Option<String>,
+    /// 命中的字段（去重后）
+    pub matched_fields: Vec<SearchField>,
+    /// 最终得分，只在调试时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+/// 搜索排序权重
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchRanking {
+    /// 全文得分的权重，见 [`DEFAULT_FTS_WEIGHT`]
+    pub fts_weight: f64,
+    /// 非全文命中的相关度，见 [`DEFAULT_SUBSTRING_RELEVANCE`]
+    pub substring_relevance: f64,
+    /// 号码完全相同的加分，见 [`DEFAULT_EXACT_NUMBER_BONUS`]
+    pub exact_number_bonus: f64,
+    /// 号码前缀相同的加分，见 [`DEFAULT_NUMBER_PREFIX_BONUS`]
+    pub number_prefix_bonus: f64,
+    /// 公司名称命中的加分，见 [`DEFAULT_COMPANY_BONUS`]
+    pub company_bonus: f64,
+    /// 姓名前缀命中的加分，见 [`DEFAULT_NAME_PREFIX_BONUS`]
+    pub name_prefix_bonus: f64,
+    /// 名称完全相同的加分，见 [`DEFAULT_EXACT_TEXT_BONUS`]
+    pub exact_text_bonus: f64,
+}
+
+impl Default for SearchRanking {
+    fn default() -> Self {
+        Self {
+            fts_weight: DEFAULT_FTS_WEIGHT,
+            substring_relevance: DEFAULT_SUBSTRING_RELEVANCE,
+            exact_number_bonus: DEFAULT_EXACT_NUMBER_BONUS,
+            number_prefix_bonus: DEFAULT_NUMBER_PREFIX_BONUS,
+            company_bonus: DEFAULT_COMPANY_BONUS,
+            name_prefix_bonus: DEFAULT_NAME_PREFIX_BONUS,
+            exact_text_bonus: DEFAULT_EXACT_TEXT_BONUS,
+        }
+    }
+}
+
+impl SearchRanking {
+    /// 单个候选的得分
+    pub fn score(&self, query: &str, class: SearchQueryClass, candidate: &SearchCandidate) -> f64 {
+        let relevance = candidate.bm25.map_or(self.substring_relevance, |bm25| {
+            // bm25 为负数，越小越相关；映射到 0~1
+            let x = (-bm25).max(0.0);
+            x / (1.0 + x)
+        });
+        let mut score = self.fts_weight * relevance;
+
+        let query = query.trim();
+        if candidate.field.is_number() {
+            if class == SearchQueryClass::Number {
+                let (query, value) = (number_key(query), number_key(&candidate.value));
+                if value == query {
+                    score += self.exact_number_bonus;
+                } else if value.starts_with(&query) {
+                    score += self.number_prefix_bonus;
+                }
+            }
+            return score;
+        }
+
+        let value = candidate.value.trim();
+        if value.to_lowercase() == query.to_lowercase() {
+            score += self.exact_text_bonus;
+        }
+        match (class, candidate.field) {
+            (SearchQueryClass::Company, SearchField::Company) if value.contains(query) => {
+                score += self.company_bonus;
+            }
+            (SearchQueryClass::ShortName, SearchField::Name) if value.starts_with(query) => {
+                score += self.name_prefix_bonus;
+            }
+            _ => {}
+        }
+        score
+    }
+
+    /// 合并同一记录的候选并按得分排序，最多返回 `limit` 条
+    ///
+    /// `with_scores` 为真时在结果中附带最终得分，便于调整权重。
+    pub fn rank(
+        &self,
+        query: &str,
+        candidates: Vec<SearchCandidate>,
+        limit: usize,
+        with_scores: bool,
+    ) -> Vec<SearchHit> {
+        let class = SearchQueryClass::classify(query);
+        let mut merged: Vec<(f64, SearchHit)> = Vec::new();
+        for candidate in candidates {
+            let score = self.score(query, class, &candidate);
+            let existing = merged
+                .iter_mut()
+                .find(|(_, hit)| hit.entity == candidate.entity && hit.id == candidate.id);
+            match existing {
+                Some((best, hit)) => {
+                    if !hit.matched_fields.contains(&candidate.field) {
+                        hit.matched_fields.push(candidate.field);
+                        hit.matched_fields.sort();
+                    }
+                    *best = best.max(score);
+                }
+                None => merged.push((
+                    score,
+                    SearchHit {
+                        entity: candidate.entity,
+                        id: candidate.id,
+                        title: candidate.title,
+                        subtitle: candidate.subtitle,
+                        matched_fields: vec![candidate.field],
+                        score: None,
+                    },
+                )),
+            }
+        }
+        merged.sort_by(|(a, hit_a), (b, hit_b)| {
+            b.total_cmp(a)
+                .then_with(|| hit_a.title.cmp(&hit_b.title))
+                .then_with(|| hit_a.id.cmp(&hit_b.id))
+        });
+        merged
+            .into_iter()
+            .take(limit)
+            .map(|(score, mut hit)| {
+                if with_scores {
+                    hit.score = Some(score);
+                }
+                hit
+            })
+            .collect()
+    }
+}
+
+/// 号码比较用的键：去掉空格、连字符和加号，字母转小写
+fn number_key(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '+')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 是否只由数字、空格、连字符和加号组成
+fn is_number(value: &str) -> bool {
+    value
+        .chars()
+        .all(|c| c.is_ascii_digit() || c.is_whitespace() || c == '-' || c == '+')
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: Uuid, field: SearchField, value: &str, bm25: Option<f64>) -> SearchCandidate {
+        SearchCandidate {
+            entity: EntityKind::Customer,
+            id,
+            title: value.to_string(),
+            subtitle: None,
+            field,
+            value: value.to_string(),
+            bm25,
+        }
+    }
+
+    #[test]
+    fn classifies_queries() {
+        assert_eq!(SearchQueryClass::classify("13800000000"), SearchQueryClass::Number);
+        assert_eq!(SearchQueryClass::classify("138-0000 0000"), SearchQueryClass::Number);
+        assert_eq!(SearchQueryClass::classify("华美家具有限公司"), SearchQueryClass::Company);
+        assert_eq!(SearchQueryClass::classify("东莞板材厂"), SearchQueryClass::Company);
+        assert_eq!(SearchQueryClass::classify("张伟"), SearchQueryClass::ShortName);
+        assert_eq!(SearchQueryClass::classify("张伟明先生"), SearchQueryClass::General);
+        assert_eq!(SearchQueryClass::classify("BJ2024"), SearchQueryClass::General);
+        assert_eq!(SearchQueryClass::classify("  "), SearchQueryClass::General);
+    }
+
+    #[test]
+    fn exact_phone_beats_fuzzy_name() {
+        let (named, owner) = (Uuid::new_v4(), Uuid::new_v4());
+        let candidates = vec![
+            // 全文索引很相关的名称命中
+            candidate(named, SearchField::Name, "13800000000号仓库", Some(-8.0)),
+            candidate(owner, SearchField::Phone, "138-0000-0000", None),
+        ];
+        let hits = SearchRanking::default().rank("13800000000", candidates, 10, false);
+        assert_eq!(hits[0].id, owner);
+        assert_eq!(hits[1].id, named);
+        assert_eq!(hits[0].score, None);
+    }
+
+    #[test]
+    fn class_boosts_apply_to_matching_fields() {
+        let ranking = SearchRanking::default();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let company = vec![
+            candidate(a, SearchField::Name, "华美家具有限公司采购部", Some(-5.0)),
+            candidate(b, SearchField::Company, "华美家具有限公司", Some(-1.0)),
+        ];
+        let hits = ranking.rank("华美家具有限公司", company, 10, true);
+        assert_eq!(hits[0].id, b);
+        assert!(hits[0].score.unwrap() > DEFAULT_COMPANY_BONUS);
+
+        let name = vec![
+            candidate(a, SearchField::Name, "王张伟", None),
+            candidate(b, SearchField::Name, "张伟明", None),
+        ];
+        let hits = ranking.rank("张伟", name, 10, false);
+        assert_eq!(hits[0].id, b);
+    }
+
+    #[test]
+    fn multi_field_matches_are_merged() {
+        let id = Uuid::new_v4();
+        let candidates = vec![
+            candidate(id, SearchField::Company, "华美家具", Some(-1.0)),
+            candidate(id, SearchField::Name, "华美家具", Some(-2.0)),
+            candidate(id, SearchField::Name, "华美家具", Some(-2.0)),
+        ];
+        let hits = SearchRanking::default().rank("华美家具", candidates, 10, true);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].matched_fields,
+            vec![SearchField::Name, SearchField::Company]
+        );
+        let best = DEFAULT_EXACT_TEXT_BONUS + DEFAULT_FTS_WEIGHT * 2.0 / 3.0;
+        assert!((hits[0].score.unwrap() - best).abs() < 1e-9);
+    }
+
+    #[test]
+    fn boosts_are_configurable() {
+        let (named, owner) = (Uuid::new_v4(), Uuid::new_v4());
+        let candidates = vec![
+            candidate(named, SearchField::Name, "13800000000", None),
+            candidate(owner, SearchField::Phone, "13800000000", None),
+        ];
+        let ranking = SearchRanking {
+            exact_number_bonus: 0.0,
+            ..SearchRanking::default()
+        };
+        let hits = ranking.rank("13800000000", candidates, 1, false);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, named);
+    }
+}
@@ -0,0 +1,16 @@
+//! 安全接口模块
+//!
+//! 定义应用锁等安全功能使用的抽象接口
+
+use crate::error::CoreResult;
+
+/// 应用口令校验接口
+///
+/// 实现方只保存口令的哈希值，不得保存明文。
+pub trait PasscodeVerifier: Send + Sync {
+    /// 是否已设置口令（未设置时应用锁不生效）
+    fn is_configured(&self) -> bool;
+
+    /// 校验口令
+    fn verify(&self, passcode: &str) -> CoreResult<bool>;
+}
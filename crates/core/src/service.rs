@@ -3,11 +3,31 @@
 //! 定义业务逻辑层的抽象接口
 
 use crate::{
+    anniversary::{upcoming_within, CustomerAnniversary, UpcomingAnniversary},
+    calendar::LocalDate,
+    cancellation::CancellationToken,
+    credit::{CreditOverride, CreditProfile},
+    dedup::DuplicateCandidate,
     entity::*,
     error::CoreResult,
-    types::{PagedResult, QueryFilter},
+    events::EntityKind,
+    field_policy::{FieldPolicy, FieldRequirement},
+    money::{Currency, Money},
+    purchasing::{
+        PurchaseOrder, PurchaseReceipt, StockMovement, StockMovementKind, SupplierPayables,
+    },
+    revision::{QuoteDiff, QuoteRevision},
+    search::SearchCandidate,
+    timeline::{TimelinePage, TimelineQuery},
+    types::{DateRange, PagedResult, Pagination, Projection, QueryFilter, ReportPeriod},
+    working_time::HolidayEntry,
 };
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// 客户服务接口
@@ -22,9 +42,12 @@ pub trait CustomerService {
     /// 根据ID获取客户
     async fn get_customer_by_id(&self, id: Uuid) -> CoreResult<Option<Customer>>;
 
-    /// 删除客户
+    /// 删除客户（连同全部关联记录）
     async fn delete_customer(&self, id: Uuid) -> CoreResult<bool>;
 
+    /// 预览删除客户会一并删除的关联记录
+    async fn get_deletion_impact(&self, id: Uuid) -> CoreResult<DeletionImpact>;
+
     /// 搜索客户
     async fn search_customers(&self, filter: &QueryFilter) -> CoreResult<PagedResult<Customer>>;
 
@@ -35,6 +58,325 @@ pub trait CustomerService {
     async fn get_customer_statistics(&self) -> CoreResult<CustomerStatistics>;
 }
 
+/// 新线索的首个跟进任务默认在几天后到期
+pub const DEFAULT_FIRST_TASK_DUE_DAYS: u32 = 3;
+
+/// 新线索：客户、首要联系人和可选的首个跟进任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewLead {
+    /// 客户
+    pub customer: Customer,
+    /// 首要联系人
+    pub contact: CustomerContact,
+    /// 首个跟进任务（关联到客户）
+    pub first_task: Option<Task>,
+}
+
+/// 新建线索的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreatedLead {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 联系人ID
+    pub contact_id: Uuid,
+    /// 任务ID（未填写任务时为空）
+    pub task_id: Option<Uuid>,
+}
+
+/// 线索服务接口
+#[async_trait]
+pub trait LeadService {
+    /// 新建线索
+    ///
+    /// 客户、联系人和任务在同一事务中写入，任一步失败时都不保留任何记录。
+    async fn create_lead(&self, lead: NewLead) -> CoreResult<CreatedLead>;
+}
+
+/// 客户列表行（只包含投影中的列，值为显示文本）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerListRow {
+    /// 客户ID
+    pub id: Uuid,
+    /// 各列的显示值（按列ID），值为空的列不出现
+    pub cells: std::collections::BTreeMap<String, String>,
+}
+
+/// 客户列表读取模型
+///
+/// 列表界面按列投影读取，隐藏的计算列不会执行对应的子查询。
+#[async_trait]
+pub trait CustomerListReadModel {
+    /// 按过滤条件和列投影读取客户列表
+    async fn list_customers(
+        &self,
+        filter: &QueryFilter,
+        projection: &Projection,
+    ) -> CoreResult<PagedResult<CustomerListRow>>;
+}
+
+/// 列表导出范围
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum ExportScope {
+    /// 当前筛选结果（按筛选条件和排序重新查询，不分页）
+    Filtered {
+        /// 列表当前的过滤条件（分页参数被忽略）
+        filter: QueryFilter,
+    },
+    /// 多选的行（按界面上的顺序导出）
+    Selection {
+        /// 选中的记录ID
+        ids: Vec<Uuid>,
+    },
+    /// 整张表
+    All,
+}
+
+/// 导出进度
+///
+/// 导出过程中累加已写出的行数，克隆的计数器共享同一进度，供进度对话框轮询。
+#[derive(Debug, Clone, Default)]
+pub struct ExportProgress(Arc<AtomicU64>);
+
+impl ExportProgress {
+    /// 创建从零开始的计数器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 累加已写出的行数
+    pub fn add(&self, rows: u64) {
+        self.0.fetch_add(rows, Ordering::Relaxed);
+    }
+
+    /// 已写出的行数
+    pub fn rows_written(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 客户列表导出请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerExportRequest {
+    /// 导出范围
+    pub scope: ExportScope,
+    /// 导出的列（与列表的可见列相同）
+    pub projection: Projection,
+    /// 表头（与投影的列一一对应）
+    pub headers: Vec<String>,
+}
+
+/// 客户列表导出服务
+///
+/// 筛选结果和整张表分批读取、逐批写出，内存占用与行数无关。
+#[async_trait]
+pub trait CustomerExportService {
+    /// 把客户列表导出为CSV文件，返回写出的数据行数
+    ///
+    /// 每写一批累加 `progress` 并检查取消令牌；取消或失败时删除未完成的文件。
+    async fn export_customers(
+        &self,
+        request: &CustomerExportRequest,
+        out_path: &Path,
+        progress: &ExportProgress,
+        cancel: &CancellationToken,
+    ) -> CoreResult<u64>;
+}
+
+/// 全局搜索数据源
+///
+/// 只负责找出候选记录，排序和去重由 [`SearchRanking`](crate::search::SearchRanking) 完成。
+#[async_trait]
+pub trait GlobalSearchSource {
+    /// 读取与关键词匹配的候选（未排序，同一记录可能在多个字段各出现一次），
+    /// 每类数据源最多 `limit` 条
+    async fn search_candidates(&self, text: &str, limit: u32) -> CoreResult<Vec<SearchCandidate>>;
+}
+
+/// 重复客户候选数据源
+///
+/// 只按分块键粗筛，分类和排除见 [`classify_duplicates`](crate::dedup::classify_duplicates)。
+#[async_trait]
+pub trait DuplicateCandidateSource {
+    /// 读取名称包含 `name_probe` 或电话与 `phone` 相同的客户（未删除），最多 `limit` 条
+    async fn duplicate_candidates(
+        &self,
+        name_probe: Option<&str>,
+        phone: Option<&str>,
+        limit: u32,
+    ) -> CoreResult<Vec<DuplicateCandidate>>;
+}
+
+/// 删除客户的影响范围（各类关联记录的条数）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletionImpact {
+    /// 任务
+    pub tasks: u64,
+    /// 报价（含已接受的报价）
+    pub quotes: u64,
+    /// 已接受的报价
+    pub accepted_quotes: u64,
+    /// 报价修订记录
+    pub quote_revisions: u64,
+    /// 销售订单
+    pub orders: u64,
+    /// 销售机会
+    pub opportunities: u64,
+    /// 互动记录
+    pub interactions: u64,
+    /// 客户关系
+    pub relations: u64,
+    /// 附件
+    pub attachments: u64,
+}
+
+impl DeletionImpact {
+    /// 是否包含财务记录（已接受的报价、订单）
+    ///
+    /// 有财务记录时删除需要输入客户名称确认。
+    pub fn has_financial_records(&self) -> bool {
+        self.accepted_quotes > 0 || self.orders > 0
+    }
+
+    /// 各类关联记录（名称, 条数），只包含条数不为零的项
+    pub fn entries(&self) -> Vec<(&'static str, u64)> {
+        [
+            ("任务", self.tasks),
+            ("报价", self.quotes),
+            ("其中已接受的报价", self.accepted_quotes),
+            ("报价修订记录", self.quote_revisions),
+            ("销售订单", self.orders),
+            ("销售机会", self.opportunities),
+            ("互动记录", self.interactions),
+            ("客户关系", self.relations),
+            ("附件", self.attachments),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .collect()
+    }
+}
+
+/// 客户分类依据（重新分类时按它评估等级和标签）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerProfile {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub name: String,
+    /// 当前等级
+    pub level: CustomerLevel,
+    /// 当前标签
+    pub tags: std::collections::BTreeSet<String>,
+    /// 最近一次互动时间
+    pub last_interaction_at: Option<DateTime<Utc>>,
+    /// 近12个月已接受报价的金额
+    pub accepted_quote_total_12m: Money,
+}
+
+/// 单个客户的等级和标签变更
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryChange {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub name: String,
+    /// 变更前的等级
+    pub level_before: CustomerLevel,
+    /// 变更后的等级
+    pub level_after: CustomerLevel,
+    /// 变更前的标签
+    pub tags_before: std::collections::BTreeSet<String>,
+    /// 变更后的标签
+    pub tags_after: std::collections::BTreeSet<String>,
+}
+
+impl CategoryChange {
+    /// 等级是否变化
+    pub fn level_changed(&self) -> bool {
+        self.level_before != self.level_after
+    }
+
+    /// 新增的标签
+    pub fn added_tags(&self) -> Vec<&str> {
+        self.tags_after
+            .difference(&self.tags_before)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// 移除的标签
+    pub fn removed_tags(&self) -> Vec<&str> {
+        self.tags_before
+            .difference(&self.tags_after)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// 客户分类服务接口
+#[async_trait]
+pub trait CustomerCategoryService {
+    /// 读取全部未删除客户的分类依据（按名称排序）
+    async fn customer_profiles(&self) -> CoreResult<Vec<CustomerProfile>>;
+
+    /// 在一个事务中写入一批变更，同时写入审计记录并发布客户更新事件
+    ///
+    /// 返回实际写入的客户数。
+    async fn apply_category_changes(
+        &self,
+        changes: &[CategoryChange],
+        actor: Option<&str>,
+    ) -> CoreResult<usize>;
+}
+
+/// 客户信用服务接口
+///
+/// 占用按订单未收款折算为本位币计算，见 [`CreditProfile`]。
+#[async_trait]
+pub trait CreditCheckService {
+    /// 客户当前的信用状况
+    async fn credit_profile(&self, customer_id: Uuid) -> CoreResult<CreditProfile>;
+
+    /// 设置信用额度和冻结状态，变化的字段写入客户审计记录
+    async fn set_credit_terms(
+        &self,
+        customer_id: Uuid,
+        credit_limit: Option<Money>,
+        credit_hold: bool,
+        actor: Option<&str>,
+    ) -> CoreResult<()>;
+
+    /// 按 `date` 当天的汇率把单据金额折算为本位币，缺少汇率时返回空
+    async fn base_amount(
+        &self,
+        amount: Money,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> CoreResult<Option<Money>>;
+
+    /// 写入越过信用额度的审计记录
+    async fn record_override(&self, entry: &CreditOverride) -> CoreResult<()>;
+}
+
+/// 节假日服务接口
+///
+/// 节假日表由设置界面维护，工作时间日历（SLA、到期提醒、周期任务顺延）据此计算。
+#[async_trait]
+pub trait HolidayService: Send + Sync {
+    /// 指定年份的节假日和调休上班日（按日期排序）
+    async fn holidays_in_year(&self, year: i32) -> CoreResult<Vec<HolidayEntry>>;
+
+    /// 全部节假日和调休上班日（按日期排序）
+    async fn all_holidays(&self) -> CoreResult<Vec<HolidayEntry>>;
+
+    /// 新增或修改某一天
+    async fn save_holiday(&self, entry: HolidayEntry) -> CoreResult<()>;
+
+    /// 删除某一天，返回之前是否存在
+    async fn remove_holiday(&self, date: LocalDate) -> CoreResult<bool>;
+}
+
 /// 供应商服务接口
 #[async_trait]
 pub trait SupplierService {
@@ -88,6 +430,105 @@ pub trait TaskService {
     async fn get_task_statistics(&self) -> CoreResult<TaskStatistics>;
 }
 
+/// 任务列表的人员过滤条件（“分配给我的”“我关注的”），两项都设置时取交集
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskAudience {
+    /// 只列出分配给该用户的任务
+    #[serde(default)]
+    pub assigned_to: Option<Uuid>,
+    /// 只列出该用户关注的任务
+    #[serde(default)]
+    pub watched_by: Option<Uuid>,
+}
+
+/// 提醒服务接口
+///
+/// 关闭和推迟都只修改单条提醒，界面可以先更新再等待结果，失败时恢复。
+#[async_trait]
+pub trait ReminderService {
+    /// 用户可见的未关闭提醒：发给该用户的和不指定接收人的（按提醒时间升序）
+    async fn active_reminders(&self, user_id: Uuid) -> CoreResult<Vec<Reminder>>;
+
+    /// 关闭提醒，返回提醒是否存在且此前未关闭
+    async fn dismiss_reminder(&self, id: Uuid, at: DateTime<Utc>) -> CoreResult<bool>;
+
+    /// 推迟提醒到 `until`，返回提醒是否存在且未关闭
+    async fn snooze_reminder(&self, id: Uuid, until: DateTime<Utc>) -> CoreResult<bool>;
+}
+
+/// 客户纪念日服务接口
+///
+/// 联系人生日和首次成交纪念日，供仪表盘卡片和每周摘要列出近期需维护的客户。
+#[async_trait]
+pub trait AnniversaryService {
+    /// 未删除客户的全部纪念日
+    async fn customer_anniversaries(&self) -> CoreResult<Vec<CustomerAnniversary>>;
+
+    /// `today` 起 `days` 天内（含首尾两天）的纪念日，按日期排序
+    async fn upcoming_anniversaries(
+        &self,
+        today: LocalDate,
+        days: u32,
+    ) -> CoreResult<Vec<UpcomingAnniversary>> {
+        Ok(upcoming_within(&self.customer_anniversaries().await?, today, days))
+    }
+}
+
+/// 任务协作服务接口
+///
+/// 管理任务的负责人、关注人和备注。分配、状态变更和新备注作为领域事件记录，
+/// 由通知处理器提醒负责人或关注人。
+#[async_trait]
+pub trait TaskCollaborationService {
+    /// 把任务分配给用户，为空时取消分配
+    ///
+    /// 用户不存在或已停用时返回校验错误。
+    async fn assign_task(&self, task_id: Uuid, user_id: Option<Uuid>) -> CoreResult<Task>;
+
+    /// 关注任务，返回此前是否未关注
+    async fn watch_task(&self, task_id: Uuid, user_id: Uuid) -> CoreResult<bool>;
+
+    /// 取消关注任务，返回此前是否已关注
+    async fn unwatch_task(&self, task_id: Uuid, user_id: Uuid) -> CoreResult<bool>;
+
+    /// 任务的关注人
+    async fn task_watchers(&self, task_id: Uuid) -> CoreResult<Vec<Uuid>>;
+
+    /// 添加任务备注
+    async fn add_task_note(
+        &self,
+        task_id: Uuid,
+        body: &str,
+        author: Option<String>,
+    ) -> CoreResult<TaskNote>;
+
+    /// 删除任务备注，返回删除的备注（用于撤销）
+    ///
+    /// 备注不存在时返回 [`CoreError::NotFound`](crate::CoreError::NotFound)。
+    async fn delete_task_note(&self, note_id: Uuid) -> CoreResult<TaskNote>;
+
+    /// 按原ID和记录时间重新写入已删除的备注
+    ///
+    /// 任务已不存在时返回 [`CoreError::NotFound`](crate::CoreError::NotFound)，
+    /// 备注仍然存在时返回 [`CoreError::Conflict`](crate::CoreError::Conflict)。
+    async fn restore_task_note(&self, note: &TaskNote) -> CoreResult<TaskNote>;
+
+    /// 记录任务状态变更，通知关注人
+    async fn record_status_change(
+        &self,
+        task_id: Uuid,
+        from: TaskStatus,
+        to: TaskStatus,
+    ) -> CoreResult<()>;
+
+    /// 按人员过滤任务（按截止日期升序，未设置截止日期的排在最后）
+    async fn search_tasks_for(
+        &self,
+        audience: TaskAudience,
+        pagination: &Pagination,
+    ) -> CoreResult<PagedResult<Task>>;
+}
+
 /// 报价服务接口
 #[async_trait]
 pub trait QuoteService {
@@ -114,6 +555,105 @@ pub trait QuoteService {
 
     /// 获取报价统计信息
     async fn get_quote_statistics(&self) -> CoreResult<QuoteStatistics>;
+
+    /// 获取报价的修订历史（按修订号升序）
+    async fn get_revisions(&self, quote_id: Uuid) -> CoreResult<Vec<QuoteRevision>>;
+
+    /// 比较两个修订
+    async fn diff_revisions(&self, quote_id: Uuid, a: u32, b: u32) -> CoreResult<QuoteDiff>;
+
+    /// 恢复到历史修订
+    ///
+    /// 恢复会生成新的修订，不改写已有历史。
+    async fn restore_revision(&self, quote_id: Uuid, revision_no: u32) -> CoreResult<Quote>;
+}
+
+/// 产品在某一时刻的售价
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PricedProduct {
+    /// 产品ID
+    pub product_id: Uuid,
+    /// 产品名称
+    pub name: String,
+    /// 规格
+    #[serde(default)]
+    pub specification: Option<String>,
+    /// 单价（尚无生效价格时为空）
+    pub unit_price: Option<f64>,
+    /// 成本价（未录入成本时为空）
+    #[serde(default)]
+    pub unit_cost: Option<f64>,
+    /// 是否已停售
+    pub retired: bool,
+}
+
+/// 定价服务接口
+///
+/// 由产品目录实现。新建报价按当前时间取价，重新计价已有报价时按报价的创建时间取价。
+#[async_trait]
+pub trait PricingService {
+    /// 产品在 `at` 时刻的售价，产品不存在时返回空
+    async fn priced_product(
+        &self,
+        product_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> CoreResult<Option<PricedProduct>>;
+}
+
+/// 产品目录服务接口
+#[async_trait]
+pub trait ProductService {
+    /// 创建产品
+    async fn create_product(&self, product: Product) -> CoreResult<Product>;
+
+    /// 根据ID获取产品
+    async fn get_product(&self, id: Uuid) -> CoreResult<Option<Product>>;
+
+    /// 产品列表（按名称排序），指定类别时只返回该类别的产品
+    async fn list_products(&self, category: Option<&str>) -> CoreResult<Vec<Product>>;
+
+    /// 设置自 `effective_from` 起生效的价格
+    ///
+    /// 价格须为正数；同一产品同一生效时间只能有一条价格，重复时返回冲突错误。
+    async fn set_price(
+        &self,
+        product_id: Uuid,
+        price: Money,
+        effective_from: DateTime<Utc>,
+        created_by: Option<String>,
+    ) -> CoreResult<ProductPrice>;
+
+    /// 设置产品的当前成本价，同时更新当期价格记录的成本价；为空时清除
+    async fn set_cost_price(&self, product_id: Uuid, cost: Option<Money>) -> CoreResult<Product>;
+
+    /// `at` 时刻生效的价格（生效时间等于 `at` 的价格视为已生效）
+    async fn price_at(
+        &self,
+        product_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> CoreResult<Option<ProductPrice>>;
+
+    /// 价格历史（按生效时间降序）
+    async fn price_history(&self, product_id: Uuid) -> CoreResult<Vec<ProductPrice>>;
+}
+
+/// 报价模板服务接口
+#[async_trait]
+pub trait QuoteTemplateService {
+    /// 创建模板
+    async fn create_template(&self, template: QuoteTemplate) -> CoreResult<QuoteTemplate>;
+
+    /// 更新模板（明细整体替换）
+    async fn update_template(&self, template: QuoteTemplate) -> CoreResult<QuoteTemplate>;
+
+    /// 根据ID获取模板
+    async fn get_template(&self, id: Uuid) -> CoreResult<Option<QuoteTemplate>>;
+
+    /// 全部模板（按名称排序）
+    async fn list_templates(&self) -> CoreResult<Vec<QuoteTemplate>>;
+
+    /// 删除模板
+    async fn delete_template(&self, id: Uuid) -> CoreResult<bool>;
 }
 
 /// 售后服务接口
@@ -145,8 +685,1473 @@ pub trait ServiceTicketService {
     async fn get_ticket_statistics(&self) -> CoreResult<ServiceTicketStatistics>;
 }
 
+/// 工单编辑器中推荐的知识库文章数
+pub const MAX_ARTICLE_SUGGESTIONS: usize = 5;
+
+/// 售后知识库服务接口
+#[async_trait]
+pub trait KnowledgeBaseService {
+    /// 创建文章（标题和处理方法不能为空）
+    async fn create_article(&self, article: KnowledgeArticle) -> CoreResult<KnowledgeArticle>;
+
+    /// 更新文章（采用次数不随之修改）
+    async fn update_article(&self, article: KnowledgeArticle) -> CoreResult<KnowledgeArticle>;
+
+    /// 根据ID获取文章
+    async fn get_article(&self, id: Uuid) -> CoreResult<Option<KnowledgeArticle>>;
+
+    /// 删除文章
+    async fn delete_article(&self, id: Uuid) -> CoreResult<bool>;
+
+    /// 文章列表（按采用次数降序），指定分类时只返回该分类
+    async fn list_articles(&self, category: Option<&str>) -> CoreResult<Vec<KnowledgeArticle>>;
+
+    /// 按工单的问题分类和描述推荐文章，最多 [`MAX_ARTICLE_SUGGESTIONS`] 篇
+    ///
+    /// 同分类的文章排在前面（描述相近的优先，其余按采用次数），其他分类只推荐描述相近的。
+    async fn suggest_articles(&self, ticket: &ServiceTicket) -> CoreResult<Vec<KnowledgeArticle>>;
+
+    /// 记录一次采用，返回更新后的文章
+    async fn record_usage(&self, id: Uuid) -> CoreResult<KnowledgeArticle>;
+}
+
+/// 新建用户参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewUser {
+    /// 登录名
+    pub username: String,
+    /// 显示名称
+    pub display_name: String,
+    /// 角色
+    pub role: UserRole,
+    /// 初始密码
+    pub password: String,
+}
+
+/// 停用用户时转交的记录数
+///
+/// 只统计未结束的记录：待处理和进行中的任务、未赢单或输单的销售机会，以及未删除的客户。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReassignmentCounts {
+    /// 任务数
+    pub tasks: u32,
+    /// 销售机会数
+    pub opportunities: u32,
+    /// 客户数
+    pub customers: u32,
+}
+
+impl ReassignmentCounts {
+    /// 合计
+    pub fn total(&self) -> u32 {
+        self.tasks + self.opportunities + self.customers
+    }
+
+    /// 是否没有需要转交的记录
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+/// 停用用户的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDeactivation {
+    /// 停用后的用户
+    pub user: User,
+    /// 接手的用户，为空表示已取消分配
+    pub reassigned_to: Option<Uuid>,
+    /// 实际转交的记录数
+    pub reassigned: ReassignmentCounts,
+}
+
+/// 用户服务接口
+#[async_trait]
+pub trait UserService {
+    /// 创建用户
+    async fn create_user(&self, user: NewUser) -> CoreResult<User>;
+
+    /// 停用前预览需要转交的记录数（确认对话框先显示）
+    async fn deactivation_preview(&self, id: Uuid) -> CoreResult<ReassignmentCounts>;
+
+    /// 停用用户，并把其未结束的任务、销售机会和客户转交给 `reassign_to`
+    ///
+    /// 转交和停用在同一事务中完成。接手的用户不存在或已停用时返回校验错误；
+    /// `reassign_to` 为空时取消分配，策略不允许且有待转交的记录时同样返回校验错误。
+    async fn deactivate_user(
+        &self,
+        id: Uuid,
+        reassign_to: Option<Uuid>,
+    ) -> CoreResult<UserDeactivation>;
+
+    /// 修改密码
+    async fn change_password(&self, id: Uuid, new_password: &str) -> CoreResult<()>;
+
+    /// 校验用户名和密码，成功时返回用户
+    ///
+    /// 用户不存在、密码错误或用户已停用时返回权限错误。
+    async fn authenticate(&self, username: &str, password: &str) -> CoreResult<User>;
+
+    /// 列出全部用户
+    async fn list_users(&self) -> CoreResult<Vec<User>>;
+}
+
+/// 供应商比价结果（单个供应商）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SupplierPriceComparison {
+    /// 供应商ID
+    pub supplier_id: Uuid,
+    /// 范围内最近一次报价
+    pub latest: PurchaseQuote,
+    /// 范围内报价均价
+    pub average_price: Money,
+    /// 范围内报价次数
+    pub quote_count: u32,
+    /// 最近一次报价是否已过期
+    pub expired: bool,
+}
+
+/// 供应商报价服务接口
+#[async_trait]
+pub trait PurchaseQuoteService {
+    /// 登记供应商报价
+    async fn record_purchase_quote(&self, quote: PurchaseQuote) -> CoreResult<PurchaseQuote>;
+
+    /// 产品在各供应商处的最新报价
+    async fn latest_quotes_for_product(&self, product_id: Uuid) -> CoreResult<Vec<PurchaseQuote>>;
+
+    /// 比较各供应商在时间范围内的报价
+    ///
+    /// 每个供应商一行，按最新单价升序排列，已过期的报价会被标记。
+    async fn compare_suppliers(
+        &self,
+        product_id: Uuid,
+        range: DateRange,
+    ) -> CoreResult<Vec<SupplierPriceComparison>>;
+}
+
+/// 库存服务接口
+#[async_trait]
+pub trait InventoryService {
+    /// 登记一条库存变动
+    async fn record_stock_movement(&self, movement: StockMovement) -> CoreResult<StockMovement>;
+
+    /// 产品当前库存（全部变动之和）
+    async fn stock_on_hand(&self, product_id: Uuid) -> CoreResult<i64>;
+
+    /// 来源单据产生的库存变动（按发生时间升序）
+    async fn stock_movements_for(
+        &self,
+        reference_id: Uuid,
+        kind: StockMovementKind,
+    ) -> CoreResult<Vec<StockMovement>>;
+}
+
+/// 采购订单服务接口
+///
+/// 状态转换按 [`PurchaseOrderStatus::can_transition_to`](crate::PurchaseOrderStatus::can_transition_to)
+/// 校验。收货时每个产品生成一条采购入库的库存变动，与订单更新在同一事务中写入。
+#[async_trait]
+pub trait PurchaseOrderService {
+    /// 创建草稿采购订单，分配采购订单号
+    async fn create_purchase_order(&self, order: PurchaseOrder) -> CoreResult<PurchaseOrder>;
+
+    /// 根据ID获取采购订单
+    async fn get_purchase_order(&self, id: Uuid) -> CoreResult<Option<PurchaseOrder>>;
+
+    /// 供应商的采购订单（按创建时间倒序）
+    async fn purchase_orders_for_supplier(
+        &self,
+        supplier_id: Uuid,
+    ) -> CoreResult<Vec<PurchaseOrder>>;
+
+    /// 发出草稿采购订单
+    async fn send_purchase_order(&self, id: Uuid) -> CoreResult<PurchaseOrder>;
+
+    /// 登记收货，按收货数量更新为部分收货或已收货
+    async fn receive_purchase_order(
+        &self,
+        id: Uuid,
+        receipts: Vec<PurchaseReceipt>,
+        actor: Option<&str>,
+    ) -> CoreResult<PurchaseOrder>;
+
+    /// 取消尚未收货的采购订单
+    async fn cancel_purchase_order(&self, id: Uuid) -> CoreResult<PurchaseOrder>;
+
+    /// 供应商的应付情况
+    async fn supplier_payables(&self, supplier_id: Uuid) -> CoreResult<SupplierPayables>;
+}
+
+/// 客户摘要（关联客户等列表中显示）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerSummary {
+    /// 客户ID
+    pub id: Uuid,
+    /// 客户名称
+    pub name: String,
+    /// 电话
+    pub phone: Option<String>,
+}
+
+/// 关联客户
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelatedCustomer {
+    /// 关系
+    pub relation: CustomerRelation,
+    /// 关系另一方
+    pub other: CustomerSummary,
+}
+
+/// 按角色分组的关联客户
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelatedCustomerGroup {
+    /// 关联客户相对于当前客户的角色
+    pub role: RelationRole,
+    /// 该角色下的关联客户（按名称排序）
+    pub customers: Vec<RelatedCustomer>,
+}
+
+/// 客户关系服务接口
+#[async_trait]
+pub trait CustomerRelationService {
+    /// 添加客户关系
+    ///
+    /// # Errors
+    ///
+    /// 客户与自身建立关系时返回校验错误；同类关系已存在时返回冲突错误；
+    /// 层级关系会使客户成为自己的上级时返回业务错误。
+    async fn add_relation(&self, relation: CustomerRelation) -> CoreResult<CustomerRelation>;
+
+    /// 删除客户关系
+    async fn remove_relation(&self, relation_id: Uuid) -> CoreResult<()>;
+
+    /// 客户的全部关联客户，按角色分组
+    async fn related_customers(&self, customer_id: Uuid) -> CoreResult<Vec<RelatedCustomerGroup>>;
+}
+
+/// 销售漏斗中的一个阶段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipelineStage {
+    /// 阶段
+    pub stage: OpportunityStage,
+    /// 机会数量
+    pub count: u32,
+    /// 预计金额合计
+    pub total_amount: Money,
+    /// 按成交概率加权的金额合计
+    pub weighted_amount: Money,
+}
+
+/// 销售漏斗（按阶段顺序，包含没有机会的阶段）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pipeline {
+    /// 各阶段汇总
+    pub stages: Vec<PipelineStage>,
+}
+
+impl Pipeline {
+    /// 未结束机会的加权金额合计
+    pub fn open_weighted_amount(&self) -> Money {
+        self.stages
+            .iter()
+            .filter(|s| !s.stage.is_closed())
+            .map(|s| s.weighted_amount)
+            .sum()
+    }
+}
+
+/// 销售机会服务接口
+#[async_trait]
+pub trait OpportunityService {
+    /// 创建销售机会
+    async fn create_opportunity(&self, opportunity: Opportunity) -> CoreResult<Opportunity>;
+
+    /// 更新销售机会
+    ///
+    /// 已赢单或输单的机会只能修改备注。阶段须通过 [`change_stage`](Self::change_stage) 修改。
+    async fn update_opportunity(&self, opportunity: Opportunity) -> CoreResult<Opportunity>;
+
+    /// 根据ID获取销售机会
+    async fn get_opportunity(&self, id: Uuid) -> CoreResult<Option<Opportunity>>;
+
+    /// 关联报价
+    async fn link_quote(&self, id: Uuid, quote_id: Uuid) -> CoreResult<Opportunity>;
+
+    /// 变更阶段
+    ///
+    /// 赢单要求已关联一份已接受的报价；输单必须填写原因。
+    async fn change_stage(
+        &self,
+        id: Uuid,
+        stage: OpportunityStage,
+        lost_reason: Option<&str>,
+    ) -> CoreResult<Opportunity>;
+
+    /// 销售漏斗汇总
+    async fn pipeline(&self) -> CoreResult<Pipeline>;
+}
+
+/// 送货安排
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliverySchedule {
+    /// 约定送货时间
+    pub delivery_date: DateTime<Utc>,
+    /// 送货地址
+    pub address: Address,
+    /// 车辆
+    pub vehicle: Option<String>,
+    /// 司机
+    pub driver: Option<String>,
+}
+
+/// 送货服务接口
+///
+/// 状态转换按 [`DeliveryStatus::can_transition_to`] 校验，每次变更都会写入客户互动记录。
+#[async_trait]
+pub trait DeliveryService {
+    /// 为未安排送货的订单安排送货
+    async fn schedule_delivery(
+        &self,
+        order_id: Uuid,
+        schedule: DeliverySchedule,
+    ) -> CoreResult<Order>;
+
+    /// 标记为送货中
+    async fn dispatch_delivery(&self, order_id: Uuid) -> CoreResult<Order>;
+
+    /// 标记为已送达
+    async fn mark_delivered(
+        &self,
+        order_id: Uuid,
+        signed_by: &str,
+        delivered_at: DateTime<Utc>,
+    ) -> CoreResult<Order>;
+
+    /// 标记为送货失败
+    async fn mark_delivery_failed(&self, order_id: Uuid, reason: &str) -> CoreResult<Order>;
+
+    /// 改期，必须填写原因
+    async fn reschedule(
+        &self,
+        order_id: Uuid,
+        delivery_date: DateTime<Utc>,
+        reason: &str,
+    ) -> CoreResult<Order>;
+
+    /// 确认订单（写入未安排送货的新订单），信用额度检查由调用方在确认前完成
+    async fn confirm_order(&self, order: Order) -> CoreResult<Order>;
+
+    /// 约定送货时间落在范围内的订单（按送货时间升序）
+    async fn deliveries_between(&self, range: DateRange) -> CoreResult<Vec<Order>>;
+}
+
+/// 客户互动记录服务接口
+#[async_trait]
+pub trait InteractionService {
+    /// 写入一条互动记录（同时更新客户汇总）
+    async fn log_interaction(&self, interaction: Interaction) -> CoreResult<Interaction>;
+}
+
+/// 客户时间线读取模型
+///
+/// 合并互动记录和由报价、订单、收款、售后工单推导的事件（见 [`crate::timeline`]）。
+#[async_trait]
+pub trait CustomerTimelineReadModel {
+    /// 按时间倒序读取一页时间线
+    async fn customer_timeline(&self, query: &TimelineQuery) -> CoreResult<TimelinePage>;
+}
+
+/// 实体上的一个自定义字段及其值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomFieldEntry {
+    /// 字段定义
+    pub definition: CustomFieldDefinition,
+    /// 存储格式的值，未填写时为空
+    pub value: Option<String>,
+}
+
+/// 字段策略服务接口
+///
+/// 修改时检查防护规则（见 [`FieldPolicy::set`]），只保存与默认值不同的字段。
+#[async_trait]
+pub trait FieldPolicyService {
+    /// 实体类型的字段策略（未配置时全部为默认要求）
+    async fn field_policy(&self, entity: EntityKind) -> CoreResult<FieldPolicy>;
+
+    /// 修改一个字段的要求，返回修改后的策略
+    async fn set_field_requirement(
+        &self,
+        entity: EntityKind,
+        key: &str,
+        requirement: FieldRequirement,
+    ) -> CoreResult<FieldPolicy>;
+}
+
+/// 自定义字段服务接口
+///
+/// 字段定义按实体类型管理；停用的字段保留已有值，但不再出现在详情、导出和校验中。
+#[async_trait]
+pub trait CustomFieldService {
+    /// 定义新字段（追加在末尾），字段键在同一实体类型内唯一（含已停用的字段）
+    async fn define_field(
+        &self,
+        definition: CustomFieldDefinition,
+    ) -> CoreResult<CustomFieldDefinition>;
+
+    /// 按给定的字段键顺序重新排列，须列出全部未停用的字段
+    async fn reorder_fields(
+        &self,
+        entity: EntityKind,
+        keys: &[String],
+    ) -> CoreResult<Vec<CustomFieldDefinition>>;
+
+    /// 停用字段
+    async fn retire_field(&self, entity: EntityKind, key: &str) -> CoreResult<()>;
+
+    /// 实体类型下未停用的字段（按显示顺序）
+    async fn fields(&self, entity: EntityKind) -> CoreResult<Vec<CustomFieldDefinition>>;
+
+    /// 写入实体的自定义字段值（按字段键），空值表示清除
+    ///
+    /// 值按字段类型校验；写入后必填字段仍为空时返回字段级验证错误。
+    async fn set_values(
+        &self,
+        entity: EntityKind,
+        entity_id: Uuid,
+        values: &[(String, String)],
+    ) -> CoreResult<()>;
+
+    /// 实体的自定义字段（未停用的字段，按显示顺序）
+    async fn values(
+        &self,
+        entity: EntityKind,
+        entity_id: Uuid,
+    ) -> CoreResult<Vec<CustomFieldEntry>>;
+}
+
+/// 归档中的一个文件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// 归档内路径（如 `database/minicrm.db`、`attachments/...`）
+    pub path: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// SHA-256 校验和（十六进制）
+    pub sha256: String,
+}
+
+/// 归档清单（`manifest.json`）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// 归档格式版本
+    pub format_version: u32,
+    /// 导出时的应用版本
+    pub app_version: String,
+    /// 导出时的数据库结构版本
+    pub schema_version: u32,
+    /// 导出时间
+    pub created_at: DateTime<Utc>,
+    /// 归档中的文件
+    pub entries: Vec<ArchiveEntry>,
+}
+
+/// 数据归档服务接口
+///
+/// 把数据库、附件和配置打包为单个 `.minicrm.zip` 文件，用于迁移到另一台电脑。
+#[async_trait]
+pub trait DataArchiveService {
+    /// 导出归档，返回写入的清单
+    ///
+    /// 每写入一个文件检查一次取消令牌；取消时删除未完成的归档文件。
+    async fn export_archive(
+        &self,
+        out_path: &Path,
+        cancel: &CancellationToken,
+    ) -> CoreResult<ArchiveManifest>;
+
+    /// 导入归档并执行待应用的迁移
+    ///
+    /// 归档不完整、校验和不符或结构版本比当前应用新时返回错误，且不会覆盖任何现有文件。
+    /// 解压期间可以取消；开始替换现有文件后不再响应取消。
+    async fn import_archive(
+        &self,
+        path: &Path,
+        cancel: &CancellationToken,
+    ) -> CoreResult<ArchiveManifest>;
+}
+
+/// 变更包摘要
+///
+/// 变更包包含 `since` 之后主电脑上修改或删除的业务记录；`until` 为导出时间，
+/// 也是副本应用后记录的高水位时间。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangesetSummary {
+    /// 变更起始时间（不含）
+    pub since: DateTime<Utc>,
+    /// 导出时间
+    pub until: DateTime<Utc>,
+    /// 新增或修改的记录数
+    pub upserts: u64,
+    /// 删除的记录数
+    pub deletes: u64,
+    /// 变更包不比已应用的新，未做任何修改
+    pub skipped: bool,
+}
+
+/// 差异同步服务接口
+///
+/// 主电脑导出变更包，只读副本（如另一台笔记本）应用变更包后与主电脑的业务数据一致。
+#[async_trait]
+pub trait DataSyncService {
+    /// 导出 `since` 之后的变更
+    async fn export_changes(
+        &self,
+        since: DateTime<Utc>,
+        out_path: &Path,
+    ) -> CoreResult<ChangesetSummary>;
+
+    /// 应用变更包
+    ///
+    /// 按依赖顺序（客户、任务、报价、订单、收款）写入，重复应用结果相同；
+    /// 变更包不比已应用的新时不做修改，返回 `skipped` 为真的摘要。
+    async fn apply_changes(&self, path: &Path) -> CoreResult<ChangesetSummary>;
+
+    /// 已应用的变更包的导出时间（即下次导出的起始时间），未应用过时为空
+    async fn high_water(&self) -> CoreResult<Option<DateTime<Utc>>>;
+}
+
+/// 可按保留期限归档的记录类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveEntity {
+    /// 客户互动记录（按发生时间）
+    Interactions,
+    /// 已完成或已取消的任务（按完成时间）
+    Tasks,
+    /// 已送达的订单（按送达时间）
+    Orders,
+}
+
+impl ArchiveEntity {
+    /// 全部类型
+    pub const ALL: [ArchiveEntity; 3] = [
+        ArchiveEntity::Interactions,
+        ArchiveEntity::Tasks,
+        ArchiveEntity::Orders,
+    ];
+
+    /// 数据表名
+    pub fn table_name(self) -> &'static str {
+        match self {
+            ArchiveEntity::Interactions => "interactions",
+            ArchiveEntity::Tasks => "tasks",
+            ArchiveEntity::Orders => "orders",
+        }
+    }
+
+    /// 显示名称
+    pub fn label(self) -> &'static str {
+        match self {
+            ArchiveEntity::Interactions => "互动记录",
+            ArchiveEntity::Tasks => "已完成任务",
+            ArchiveEntity::Orders => "已送达订单",
+        }
+    }
+}
+
+/// 归档策略：各类型记录保留的月数，未列出或为0的类型不归档
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivePolicy {
+    /// 保留月数
+    pub cutoff_months: std::collections::BTreeMap<ArchiveEntity, u32>,
+}
+
+impl ArchivePolicy {
+    /// 在 `now` 时刻早于该时间的记录将被归档
+    pub fn cutoff(&self, entity: ArchiveEntity, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.cutoff_months.get(&entity) {
+            Some(&months) if months > 0 => now.checked_sub_months(chrono::Months::new(months)),
+            _ => None,
+        }
+    }
+}
+
+/// 单个类型的归档数量
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveCount {
+    /// 记录类型
+    pub entity: ArchiveEntity,
+    /// 截止时间
+    pub cutoff: DateTime<Utc>,
+    /// 记录数
+    pub rows: u64,
+    /// 估算大小（字段内容字节数，不含索引）
+    pub estimated_bytes: u64,
+}
+
+/// 归档预览
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivePreview {
+    /// 按该时间计算截止时间
+    pub as_of: DateTime<Utc>,
+    /// 各类型将移出的记录
+    pub counts: Vec<ArchiveCount>,
+}
+
+impl ArchivePreview {
+    /// 记录总数
+    pub fn total_rows(&self) -> u64 {
+        self.counts.iter().map(|c| c.rows).sum()
+    }
+
+    /// 估算总大小
+    pub fn total_bytes(&self) -> u64 {
+        self.counts.iter().map(|c| c.estimated_bytes).sum()
+    }
+}
+
+/// 归档触发方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveTrigger {
+    /// 用户点击“立即归档”
+    Manual,
+    /// 定时任务
+    Scheduled,
+}
+
+/// 归档运行结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveRunStatus {
+    /// 已完成
+    Completed,
+    /// 其他独占操作进行中，本次跳过
+    Skipped,
+    /// 失败（已回滚）
+    Failed,
+}
+
+/// 一次归档运行记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveRun {
+    /// 开始时间
+    pub started_at: DateTime<Utc>,
+    /// 结束时间
+    pub finished_at: DateTime<Utc>,
+    /// 触发方式
+    pub trigger: ArchiveTrigger,
+    /// 结果
+    pub status: ArchiveRunStatus,
+    /// 各类型移出的记录
+    pub counts: Vec<ArchiveCount>,
+    /// 跳过或失败的原因
+    pub message: Option<String>,
+}
+
+/// 一次存储清理记录（附件孤儿文件和过期临时文件）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageGcRun {
+    /// 运行时间
+    pub ran_at: DateTime<Utc>,
+    /// 是否只预览（未删除文件）
+    pub dry_run: bool,
+    /// 没有记录引用的附件文件数
+    pub orphan_blobs: u64,
+    /// 过期的临时文件数
+    pub temp_files: u64,
+    /// 释放（预览时为可释放）的字节数
+    pub bytes: u64,
+}
+
+/// 一次保留策略执行记录（审计记录和已分发事件的清理）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionRun {
+    /// 运行时间
+    pub ran_at: DateTime<Utc>,
+    /// 按月汇总后删除的审计记录行数
+    pub audit_rows: u64,
+    /// 删除的已分发事件行数
+    pub outbox_rows: u64,
+}
+
+/// 记录归档服务接口
+///
+/// 按归档策略把超过保留期限的记录移到归档表，列表和统计不再包含这些记录。
+/// 每次运行（包括跳过和失败）都追加到归档历史。
+#[async_trait]
+pub trait RecordArchiveService {
+    /// 预览按策略在 `as_of` 时刻将归档的记录
+    async fn preview(
+        &self,
+        policy: &ArchivePolicy,
+        as_of: DateTime<Utc>,
+    ) -> CoreResult<ArchivePreview>;
+
+    /// 按策略归档，与同一 `as_of` 的预览移出相同的记录
+    ///
+    /// 备份、整理等独占操作进行中时不归档，返回状态为 [`ArchiveRunStatus::Skipped`] 的记录。
+    async fn archive(
+        &self,
+        policy: &ArchivePolicy,
+        as_of: DateTime<Utc>,
+        trigger: ArchiveTrigger,
+    ) -> CoreResult<ArchiveRun>;
+
+    /// 最近的归档运行记录（新的在前）
+    async fn runs(&self, limit: usize) -> CoreResult<Vec<ArchiveRun>>;
+}
+
+/// 回收站中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashItem {
+    /// 实体类型
+    pub entity: EntityKind,
+    /// 实体ID
+    pub id: Uuid,
+    /// 显示名称（客户名称、任务标题等）
+    pub label: String,
+    /// 删除时间
+    pub deleted_at: DateTime<Utc>,
+    /// 所属删除批次，升级前删除的记录为空
+    pub batch_id: Option<Uuid>,
+}
+
+/// 删除批次
+///
+/// 一次删除操作连带删除的全部记录共用一个批次，恢复时整批恢复。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeletionBatch {
+    /// 批次ID
+    pub id: Uuid,
+    /// 用户删除的记录类型
+    pub root_entity: EntityKind,
+    /// 用户删除的记录ID
+    pub root_id: Uuid,
+    /// 用户删除的记录名称
+    pub label: String,
+    /// 删除时间
+    pub deleted_at: DateTime<Utc>,
+    /// 删除人
+    pub deleted_by: Option<String>,
+    /// 各表删除的记录数（按表名）
+    pub counts: std::collections::BTreeMap<String, u64>,
+}
+
+/// 恢复结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreReport {
+    /// 恢复的批次，按单条记录恢复时为空
+    pub batch_id: Option<Uuid>,
+    /// 恢复的记录
+    pub restored: Vec<(EntityKind, Uuid)>,
+}
+
+impl RestoreReport {
+    /// 各表恢复的记录数（按表名）
+    pub fn counts(&self) -> std::collections::BTreeMap<String, u64> {
+        let mut counts = std::collections::BTreeMap::new();
+        for (entity, _) in &self.restored {
+            *counts.entry(entity.table_name().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// 回收站服务接口
+///
+/// 软删除按批次记录：删除客户时其未完成的任务和草稿报价与客户同批删除，恢复任一条记录都
+/// 恢复整批，此前或此后因其他原因删除的记录保持删除状态。
+#[async_trait]
+pub trait TrashService {
+    /// 把客户连同未完成的任务和草稿报价作为一个批次移入回收站
+    async fn soft_delete_customer(
+        &self,
+        id: Uuid,
+        deleted_by: Option<String>,
+    ) -> CoreResult<DeletionBatch>;
+
+    /// 把单条记录移入回收站（单独成为一个批次）
+    async fn soft_delete(
+        &self,
+        entity: EntityKind,
+        id: Uuid,
+        deleted_by: Option<String>,
+    ) -> CoreResult<DeletionBatch>;
+
+    /// 从回收站恢复记录；记录属于删除批次时恢复整个批次
+    async fn restore(&self, entity: EntityKind, id: Uuid) -> CoreResult<RestoreReport>;
+
+    /// 回收站中的记录（删除时间新的在前）
+    async fn trash_items(&self) -> CoreResult<Vec<TrashItem>>;
+
+    /// 仍有记录在回收站中的删除批次（删除时间新的在前）
+    async fn deletion_batches(&self) -> CoreResult<Vec<DeletionBatch>>;
+}
+
+/// 设置文件中的部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsSection {
+    /// 应用配置（不含本机路径和密钥）
+    Config,
+    /// 保存的搜索
+    SavedSearches,
+    /// 仪表盘布局
+    DashboardLayouts,
+    /// 列表的列设置
+    ColumnLayouts,
+    /// 快捷键设置
+    Shortcuts,
+}
+
+impl SettingsSection {
+    /// 全部部分
+    pub const ALL: [SettingsSection; 5] = [
+        SettingsSection::Config,
+        SettingsSection::SavedSearches,
+        SettingsSection::DashboardLayouts,
+        SettingsSection::ColumnLayouts,
+        SettingsSection::Shortcuts,
+    ];
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingsSection::Config => "应用配置",
+            SettingsSection::SavedSearches => "保存的搜索",
+            SettingsSection::DashboardLayouts => "仪表盘布局",
+            SettingsSection::ColumnLayouts => "列设置",
+            SettingsSection::Shortcuts => "快捷键",
+        }
+    }
+}
+
+/// 设置导出结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingsExportSummary {
+    /// 设置文件格式版本
+    pub schema_version: u32,
+    /// 导出的部分
+    pub sections: Vec<SettingsSection>,
+    /// 未导出的本机路径和密钥（配置项路径，如 `digest.smtp.password`）
+    pub redacted: Vec<String>,
+}
+
+/// 单个部分的导入状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionImportStatus {
+    /// 已应用
+    Applied,
+    /// 未应用（文件中没有该部分）
+    Skipped,
+    /// 内容无效，未应用
+    Failed,
+}
+
+/// 单个部分的导入结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionImportResult {
+    /// 部分
+    pub section: SettingsSection,
+    /// 状态
+    pub status: SectionImportStatus,
+    /// 说明
+    pub message: String,
+}
+
+/// 设置导入结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingsImportReport {
+    /// 设置文件格式版本
+    pub schema_version: u32,
+    /// 各部分的结果（按选择的顺序）
+    pub sections: Vec<SectionImportResult>,
+    /// 需要在本机重新填写的密钥（配置项路径）
+    pub requires_reentry: Vec<String>,
+}
+
+/// 设置导入导出服务接口
+///
+/// 在多台电脑间同步配置、保存的搜索和界面布局。本机路径和密钥不会导出，
+/// 导入时保留本机原有的值，本机没有的需重新填写。
+#[async_trait]
+pub trait SettingsTransferService {
+    /// 导出设置文件
+    async fn export_settings(&self, out_path: &Path) -> CoreResult<SettingsExportSummary>;
+
+    /// 导入设置文件中选定的部分
+    ///
+    /// 文件版本比当前应用新时返回错误，且不修改任何设置。
+    async fn import_settings(
+        &self,
+        path: &Path,
+        sections: &[SettingsSection],
+    ) -> CoreResult<SettingsImportReport>;
+}
+
+/// 诊断包中附带的最近日志文件数（不含操作日志）
+pub const DIAGNOSTIC_LOG_FILES: usize = 5;
+
+/// 诊断包导出结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsBundleSummary {
+    /// 包内的文件（按写入顺序）
+    pub entries: Vec<String>,
+    /// 生成失败、以错误说明代替的部分
+    pub failed: Vec<String>,
+    /// 是否附带了匿名化的数据库副本
+    pub includes_database: bool,
+}
+
+/// 诊断包导出服务接口
+///
+/// 把排查问题需要的版本、健康检查、迁移、连接池、日志、慢查询和一致性检查结果打包为一个
+/// zip文件发给技术支持。包内不含密钥和未经处理的个人数据；只有明确选择时才附带数据库副本，
+/// 副本中的姓名、电话、邮箱已打乱，金额按数量级归档。
+#[async_trait]
+pub trait DiagnosticsBundleService {
+    /// 导出诊断包
+    ///
+    /// 每写完一部分累加 `progress` 并检查取消令牌；取消或失败时删除未完成的文件。
+    /// 单个部分生成失败时写入错误说明，不中止导出。
+    async fn export_bundle(
+        &self,
+        out_path: &Path,
+        include_db_copy: bool,
+        progress: &ExportProgress,
+        cancel: &CancellationToken,
+    ) -> CoreResult<DiagnosticsBundleSummary>;
+}
+
+/// 全文索引与基础表的一致性
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchIndexStatus {
+    /// 基础表名
+    pub table: String,
+    /// 基础表行数
+    pub base_rows: u64,
+    /// 索引中的行数
+    pub indexed_rows: u64,
+    /// 抽样比对的行数
+    pub sampled: u64,
+    /// 抽样中索引内容缺失或与基础表不一致的行数
+    pub mismatched: u64,
+}
+
+impl SearchIndexStatus {
+    /// 索引是否与基础表一致
+    pub fn is_consistent(&self) -> bool {
+        self.base_rows == self.indexed_rows && self.mismatched == 0
+    }
+}
+
+/// 全文索引重建进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchIndexProgress {
+    /// 已重建的行数
+    pub rebuilt: u64,
+    /// 需要重建的总行数
+    pub total: u64,
+}
+
+impl SearchIndexProgress {
+    /// 完成百分比（0-100）
+    pub fn percent(&self) -> u8 {
+        if self.total == 0 {
+            return 100;
+        }
+        (self.rebuilt.min(self.total) * 100 / self.total) as u8
+    }
+}
+
+/// 查询计划中的一步（`EXPLAIN QUERY PLAN` 的一行）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanRow {
+    /// 步骤ID
+    pub id: i64,
+    /// 父步骤ID（顶层为0）
+    pub parent: i64,
+    /// 步骤说明（如 `SCAN customers`、`SEARCH tasks USING INDEX ...`）
+    pub detail: String,
+}
+
+impl PlanRow {
+    /// 全表扫描时返回表名（使用索引的扫描不算）
+    pub fn full_scan_table(&self) -> Option<&str> {
+        let rest = self.detail.strip_prefix("SCAN ")?;
+        // 旧版SQLite输出 `SCAN TABLE t`
+        let rest = rest.strip_prefix("TABLE ").unwrap_or(rest);
+        if rest.contains(" USING ") {
+            return None;
+        }
+        rest.split_whitespace().next()
+    }
+}
+
+/// 超过阈值的慢查询
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowQuery {
+    /// SQL语句
+    pub sql: String,
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+    /// 执行计划（每条语句在一次会话中只捕获一次）
+    pub plan: Vec<PlanRow>,
+}
+
+/// 建议的连接池最大连接数下限
+pub const POOL_SIZE_FLOOR: u32 = 2;
+
+/// 建议的最大连接数在需求之上预留的连接数
+pub const POOL_SIZE_HEADROOM: u32 = 2;
+
+/// 获取连接等待达到该毫秒数视为连接不够用
+pub const POOL_WAIT_NOTICEABLE_MS: u64 = 50;
+
+/// 一次运行期间的连接池使用情况
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolUsageSummary {
+    /// 开始时间
+    pub started_at: DateTime<Utc>,
+    /// 结束时间
+    pub ended_at: DateTime<Utc>,
+    /// 本次运行配置的最大连接数
+    pub max_connections: u32,
+    /// 同时借出的连接数峰值
+    pub peak_checkouts: u32,
+    /// 借出连接的次数
+    pub checkouts: u64,
+    /// 获取连接的累计等待时间（毫秒）
+    pub total_wait_ms: u64,
+    /// 获取连接的最长等待时间（毫秒）
+    pub max_wait_ms: u64,
+    /// 获取连接超时的次数
+    pub timeouts: u64,
+    /// 因空闲超时或超过生命周期被关闭的连接数
+    pub evictions: u64,
+}
+
+impl PoolUsageSummary {
+    /// 连接池是否被用满：借出数达到上限，且有调用方明显等待或超时
+    pub fn saturated(&self) -> bool {
+        self.peak_checkouts >= self.max_connections
+            && (self.max_wait_ms >= POOL_WAIT_NOTICEABLE_MS || self.timeouts > 0)
+    }
+
+    /// 本次运行实际需要的连接数（被用满时至少比上限多一个）
+    pub fn demand(&self) -> u32 {
+        if self.saturated() {
+            self.peak_checkouts.max(self.max_connections) + 1
+        } else {
+            self.peak_checkouts
+        }
+    }
+}
+
+/// 连接池最大连接数建议
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSizeSuggestion {
+    /// 当前配置的最大连接数
+    pub current: u32,
+    /// 建议的最大连接数
+    pub suggested: u32,
+    /// 历史记录中同时借出的连接数峰值
+    pub observed_peak: u32,
+    /// 参考的运行次数
+    pub sessions: usize,
+    /// 是否有运行中连接池被用满
+    pub saturated: bool,
+}
+
+impl PoolSizeSuggestion {
+    /// 根据最近几次运行的使用情况计算建议，没有记录时为空
+    ///
+    /// 建议值为各次运行需求（[`PoolUsageSummary::demand`]）的最大值加
+    /// [`POOL_SIZE_HEADROOM`]，不低于 [`POOL_SIZE_FLOOR`]，不超过 `hard_cap`。
+    pub fn from_history(
+        history: &[PoolUsageSummary],
+        current: u32,
+        hard_cap: u32,
+    ) -> Option<Self> {
+        let demand = history.iter().map(PoolUsageSummary::demand).max()?;
+        Some(Self {
+            current,
+            suggested: (demand + POOL_SIZE_HEADROOM)
+                .clamp(POOL_SIZE_FLOOR, hard_cap.max(POOL_SIZE_FLOOR)),
+            observed_peak: history.iter().map(|s| s.peak_checkouts).max().unwrap_or(0),
+            sessions: history.len(),
+            saturated: history.iter().any(PoolUsageSummary::saturated),
+        })
+    }
+
+    /// 建议值与当前配置是否不同
+    pub fn changes(&self) -> bool {
+        self.suggested != self.current
+    }
+}
+
+/// 单个迁移的回滚检查结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MigrationReversibility {
+    /// 回滚后结构与迁移前一致，重新迁移后与直接迁移一致
+    Reversible,
+    /// 没有回滚SQL
+    Irreversible,
+    /// 回滚或重新迁移失败，或结构与预期不一致（附原因）
+    Broken(String),
+}
+
+/// 单个迁移的回滚检查
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationCheck {
+    /// 迁移版本号
+    pub version: u32,
+    /// 迁移名称
+    pub name: String,
+    /// 检查结果
+    pub result: MigrationReversibility,
+    /// 回滚会删除的表和列（`表` 或 `表.列`），其中的数据回滚后无法恢复
+    pub drops: Vec<String>,
+}
+
+impl MigrationCheck {
+    /// 回滚是否会删除数据（只做标记，不算有误）
+    pub fn destructive(&self) -> bool {
+        !self.drops.is_empty()
+    }
+}
+
+/// 全部迁移的回滚检查报告
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReversibilityReport {
+    /// 按版本号升序的检查结果
+    pub checks: Vec<MigrationCheck>,
+}
+
+impl ReversibilityReport {
+    /// 回滚有误的迁移
+    pub fn broken(&self) -> impl Iterator<Item = &MigrationCheck> {
+        self.checks
+            .iter()
+            .filter(|check| matches!(check.result, MigrationReversibility::Broken(_)))
+    }
+
+    /// 是否没有回滚有误的迁移（没有回滚SQL的迁移不算失败）
+    pub fn passed(&self) -> bool {
+        self.broken().next().is_none()
+    }
+}
+
+/// 一致性问题的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsistencySeverity {
+    /// 可自动校正的偏差（如汇总表尚未对账）
+    Warning,
+    /// 数据已损坏（如收款超过订单总额），需要人工处理
+    Error,
+}
+
+/// 一致性检查发现的单个问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyViolation {
+    /// 涉及的记录ID
+    pub ids: Vec<Uuid>,
+    /// 问题说明
+    pub description: String,
+}
+
+/// 业务数据一致性检查
+///
+/// 每项检查验证一条数据库约束无法表达的业务规则。
+#[async_trait]
+pub trait ConsistencyCheck {
+    /// 检查名称（命令行和诊断界面按名称选择，发布后不应修改）
+    fn name(&self) -> &str;
+
+    /// 检查内容说明
+    fn description(&self) -> &str;
+
+    /// 发现问题时的严重程度
+    fn severity(&self) -> ConsistencySeverity;
+
+    /// 执行检查，最多返回 `limit` 个问题
+    async fn run(&self, limit: usize) -> CoreResult<Vec<ConsistencyViolation>>;
+}
+
+/// 单项一致性检查的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyCheckResult {
+    /// 检查名称
+    pub name: String,
+    /// 检查内容说明
+    pub description: String,
+    /// 严重程度
+    pub severity: ConsistencySeverity,
+    /// 发现的问题
+    pub violations: Vec<ConsistencyViolation>,
+    /// 问题数超过上限，只列出了前面的部分
+    pub truncated: bool,
+}
+
+impl ConsistencyCheckResult {
+    /// 是否没有发现问题
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// 一致性检查报告
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    /// 各项检查结果（按注册顺序）
+    pub results: Vec<ConsistencyCheckResult>,
+    /// 检查时间
+    pub checked_at: DateTime<Utc>,
+}
+
+impl ConsistencyReport {
+    /// 发现指定严重程度问题的检查
+    pub fn failed(
+        &self,
+        severity: ConsistencySeverity,
+    ) -> impl Iterator<Item = &ConsistencyCheckResult> {
+        self.results
+            .iter()
+            .filter(move |result| result.severity == severity && !result.passed())
+    }
+
+    /// 是否发现了数据损坏（警告不算）
+    pub fn has_errors(&self) -> bool {
+        self.failed(ConsistencySeverity::Error).next().is_some()
+    }
+}
+
+/// 幂等记录的保留时长（小时）
+pub const IDEMPOTENCY_TTL_HOURS: i64 = 24;
+
+/// 已执行命令的幂等记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    /// 幂等键（由发起操作的视图模型生成）
+    pub key: Uuid,
+    /// 命令名称
+    pub command: String,
+    /// 请求内容的哈希
+    pub request_hash: String,
+    /// 序列化后的执行结果（JSON）
+    pub result: String,
+    /// 记录时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 幂等记录服务接口
+///
+/// 命令总线用它识别重复提交；记录超过 [`IDEMPOTENCY_TTL_HOURS`] 后视为不存在。
+#[async_trait]
+pub trait IdempotencyService {
+    /// 查找未过期的记录
+    async fn find_record(&self, key: Uuid) -> CoreResult<Option<IdempotencyRecord>>;
+
+    /// 保存记录（同一键的过期记录会被覆盖）
+    async fn save_record(&self, record: IdempotencyRecord) -> CoreResult<()>;
+}
+
+/// 邮件发送接口
+#[async_trait]
+pub trait MailService {
+    /// 发送HTML邮件，`text` 为不支持HTML的客户端显示的纯文本正文
+    async fn send_html(
+        &self,
+        recipients: &[String],
+        subject: &str,
+        html: &str,
+        text: &str,
+    ) -> CoreResult<()>;
+}
+
+/// Webhook推送请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookRequest {
+    /// 推送地址
+    pub url: String,
+    /// 请求头
+    pub headers: Vec<(String, String)>,
+    /// JSON正文
+    pub body: String,
+}
+
+/// Webhook发送通道
+#[async_trait]
+pub trait WebhookTransport {
+    /// 发送请求并返回HTTP状态码，连接失败或超时返回错误
+    async fn post(&self, request: &WebhookRequest) -> CoreResult<u16>;
+}
+
+/// 桌面通知接口
+pub trait DesktopNotifier {
+    /// 显示一条桌面通知
+    fn notify(&self, title: &str, body: &str) -> CoreResult<()>;
+}
+
+/// 用系统默认程序打开链接（`tel:` 交给拨号软件，网址交给浏览器）
+pub trait UriOpener {
+    /// 打开链接，没有可用程序时返回错误
+    fn open_uri(&self, uri: &str) -> CoreResult<()>;
+}
+
+/// 系统剪贴板
+pub trait ClipboardWriter {
+    /// 把文本写入剪贴板
+    fn set_text(&self, text: &str) -> CoreResult<()>;
+}
+
+/// 解码后的图片（RGBA8，按行排列）
+#[derive(Clone, PartialEq, Eq)]
+pub struct DecodedImage {
+    /// 宽度（像素）
+    pub width: u32,
+    /// 高度（像素）
+    pub height: u32,
+    /// 像素数据，长度为 `width * height * 4`
+    pub rgba: Vec<u8>,
+}
+
+impl std::fmt::Debug for DecodedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecodedImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 附件图片预览接口
+///
+/// 解码是耗时的阻塞操作，调用方应在界面线程之外调用。
+/// 返回 `None` 表示无法预览（非图片、文件损坏或尺寸过大），界面显示通用图标。
+pub trait AttachmentPreviewService {
+    /// 获取附件缩略图（首次请求时生成并缓存）
+    fn thumbnail(&self, hash: &str, mime: &str) -> CoreResult<Option<DecodedImage>>;
+
+    /// 加载附件原图（已按EXIF方向校正）
+    fn full_image(&self, hash: &str, mime: &str) -> CoreResult<Option<DecodedImage>>;
+}
+
+/// 表格文件读取接口
+///
+/// 单元格统一读为文本：数字按原值（不带千分位），合并单元格只有左上角有值，其余为空。
+/// 读取整个工作表是阻塞操作。
+pub trait SpreadsheetReader {
+    /// 工作簿中的工作表名称（按工作簿中的顺序）
+    fn sheet_names(&self, path: &Path) -> CoreResult<Vec<String>>;
+
+    /// 读取工作表的全部行（从第一行开始，包括空行）
+    fn read_sheet(&self, path: &Path, sheet: &str) -> CoreResult<Vec<Vec<String>>>;
+}
+
+/// 汇率（1单位外币折合的本位币金额）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    /// 币种
+    pub currency: Currency,
+    /// 折合本位币的汇率
+    pub rate_to_base: f64,
+    /// 生效日期（到下一次设置前一直有效）
+    pub effective_date: NaiveDate,
+}
+
+/// 缺少汇率、无法折算为本位币的单据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnconvertedDocument {
+    /// 单据类型（报价、订单等）
+    pub kind: String,
+    /// 单据编号
+    pub number: String,
+    /// 币种
+    pub currency: Currency,
+    /// 单据币种的金额
+    pub amount: Money,
+    /// 单据日期
+    pub date: NaiveDate,
+}
+
+/// 汇率服务接口
+///
+/// 单据金额始终以单据币种保存和计算，只有统计、应收和报表按单据日期生效的汇率折算为本位币。
+#[async_trait]
+pub trait ExchangeRateService {
+    /// 设置自 `effective_date` 起生效的汇率，同一日期已有汇率时覆盖
+    async fn set_rate(
+        &self,
+        currency: Currency,
+        rate_to_base: f64,
+        effective_date: NaiveDate,
+    ) -> CoreResult<ExchangeRate>;
+
+    /// `date` 当天生效的汇率；本位币恒为1，尚未设置汇率时返回空
+    async fn rate_at(
+        &self,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> CoreResult<Option<ExchangeRate>>;
+}
+
+/// 月度统计服务接口
+///
+/// 仪表盘和月度报表共用同一组统计口径。
+#[async_trait]
+pub trait StatisticsService {
+    /// 获取指定周期的月度统计
+    async fn monthly_statistics(&self, period: ReportPeriod) -> CoreResult<MonthlyStatistics>;
+}
+
+/// 月结记录
+///
+/// 月结后该月的统计从快照读取，之后补录或修改的单据不再影响已结账月份的数字。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyClosing {
+    /// 统计周期
+    pub period: ReportPeriod,
+    /// 结账时的统计快照
+    pub statistics: MonthlyStatistics,
+    /// 是否锁定（重新打开后为否，下次月结覆盖快照）
+    pub locked: bool,
+    /// 结账时间
+    pub closed_at: DateTime<Utc>,
+    /// 结账人（定时任务结账时为空）
+    pub closed_by: Option<String>,
+}
+
+/// 月结服务接口
+#[async_trait]
+pub trait MonthlyClosingService {
+    /// 指定月份的月结记录（包括已重新打开的），从未结账时为空
+    async fn closing(&self, period: ReportPeriod) -> CoreResult<Option<MonthlyClosing>>;
+
+    /// 保存统计快照并锁定
+    ///
+    /// 该月已锁定时返回冲突错误；已重新打开时覆盖原快照。
+    async fn close_month(
+        &self,
+        period: ReportPeriod,
+        statistics: MonthlyStatistics,
+        closed_by: Option<&str>,
+    ) -> CoreResult<MonthlyClosing>;
+
+    /// 重新打开已锁定的月份，并写入审计记录
+    async fn reopen_month(
+        &self,
+        period: ReportPeriod,
+        actor: &str,
+        reason: &str,
+    ) -> CoreResult<MonthlyClosing>;
+}
+
 /// 客户统计信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CustomerStatistics {
     /// 总客户数
     pub total_customers: u64,
@@ -157,7 +2162,7 @@ pub struct CustomerStatistics {
 }
 
 /// 供应商统计信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SupplierStatistics {
     /// 总供应商数
     pub total_suppliers: u64,
@@ -168,7 +2173,7 @@ pub struct SupplierStatistics {
 }
 
 /// 任务统计信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TaskStatistics {
     /// 总任务数
     pub total_tasks: u64,
@@ -183,20 +2188,20 @@ pub struct TaskStatistics {
 }
 
 /// 报价统计信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QuoteStatistics {
     /// 总报价数
     pub total_quotes: u64,
     /// 各状态报价数量
     pub quotes_by_status: std::collections::HashMap<String, u64>,
     /// 本月报价总金额
-    pub total_amount_this_month: f64,
+    pub total_amount_this_month: Money,
     /// 报价成功率
     pub success_rate: f64,
 }
 
 /// 售后工单统计信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServiceTicketStatistics {
     /// 总工单数
     pub total_tickets: u64,
@@ -207,3 +2212,122 @@ pub struct ServiceTicketStatistics {
     /// 平均处理时间（小时）
     pub average_resolution_time: f64,
 }
+
+/// 月度统计信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonthlyStatistics {
+    /// 期初客户数
+    pub customers_at_start: u64,
+    /// 本期新增客户数
+    pub new_customers: u64,
+    /// 本期发出报价数
+    pub quotes_issued: u64,
+    /// 本期报价总金额（本位币）
+    pub quotes_total_amount: f64,
+    /// 本期成交报价数
+    pub quotes_accepted: u64,
+    /// 本期成交金额（本位币）
+    pub quotes_accepted_amount: f64,
+    /// 成交金额最高的客户（降序）
+    pub top_customers: Vec<CustomerRanking>,
+    /// 本期到期任务数
+    pub tasks_due: u64,
+    /// 本期按时完成任务数
+    pub tasks_completed: u64,
+    /// 本期关闭工单数
+    pub tickets_closed: u64,
+    /// 在SLA时限内关闭的工单数
+    pub tickets_within_sla: u64,
+    /// 缺少汇率、未计入金额合计的单据
+    #[serde(default)]
+    pub missing_rates: Vec<UnconvertedDocument>,
+    /// 期末应收账龄
+    #[serde(default)]
+    pub receivables_aging: ReceivablesAging,
+    /// 数据来源（月结快照或实时计算）
+    #[serde(default)]
+    pub source: StatisticsSource,
+}
+
+/// 期末应收账龄（按送达日期计，本位币）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReceivablesAging {
+    /// 30天以内
+    pub within_30_days: f64,
+    /// 31~60天
+    pub days_31_to_60: f64,
+    /// 61~90天
+    pub days_61_to_90: f64,
+    /// 90天以上
+    pub over_90_days: f64,
+}
+
+impl ReceivablesAging {
+    /// 应收合计
+    pub fn total(&self) -> f64 {
+        self.within_30_days + self.days_31_to_60 + self.days_61_to_90 + self.over_90_days
+    }
+}
+
+/// 统计数据来源
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatisticsSource {
+    /// 实时计算（未结账或已重新打开的月份）
+    #[default]
+    Live,
+    /// 月结快照
+    Snapshot {
+        /// 结账时间
+        closed_at: DateTime<Utc>,
+        /// 结账人
+        closed_by: Option<String>,
+    },
+}
+
+impl MonthlyStatistics {
+    /// 是否来自月结快照
+    pub fn is_snapshot(&self) -> bool {
+        matches!(self.source, StatisticsSource::Snapshot { .. })
+    }
+
+    /// 客户增长率（相对期初）
+    pub fn customer_growth_rate(&self) -> f64 {
+        ratio(self.new_customers, self.customers_at_start)
+    }
+
+    /// 报价成交率
+    pub fn quote_success_rate(&self) -> f64 {
+        ratio(self.quotes_accepted, self.quotes_issued)
+    }
+
+    /// 任务完成率
+    pub fn task_completion_rate(&self) -> f64 {
+        ratio(self.tasks_completed, self.tasks_due)
+    }
+
+    /// 工单SLA达标率
+    pub fn ticket_sla_rate(&self) -> f64 {
+        ratio(self.tickets_within_sla, self.tickets_closed)
+    }
+}
+
+fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+/// 客户成交排名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerRanking {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub customer_name: String,
+    /// 成交报价数
+    pub accepted_quotes: u64,
+    /// 成交金额
+    pub accepted_amount: f64,
+}
@@ -0,0 +1,330 @@
+//! 数据库增长检测模块
+//!
+//! 健康监控每次执行后记录数据库文件（含WAL）大小和各表行数。检测时分别按最近24小时和
+//! 最近7天计算平均每天的增长量和增长比例，超过阈值时给出警告，并按行数增量列出增长最快的
+//! 几张表，便于定位失控的导入或同步。
+//!
+//! 窗口内的记录覆盖不到窗口的四分之一时不计算该窗口，避免刚启动时把短时间的写入放大成
+//! 每天的增长。
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 大小记录保留天数
+pub const SIZE_HISTORY_RETENTION_DAYS: i64 = 90;
+
+/// 警告中列出的增长最快的表数
+pub const SIZE_GROWTH_TOP_TABLES: usize = 3;
+
+const MB: f64 = 1024.0 * 1024.0;
+
+/// 一次数据库大小记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeSample {
+    /// 记录时间
+    pub taken_at: DateTime<Utc>,
+    /// 数据库文件大小（字节）
+    pub db_bytes: u64,
+    /// WAL文件大小（字节）
+    pub wal_bytes: u64,
+    /// 各表行数
+    pub table_rows: BTreeMap<String, u64>,
+}
+
+impl SizeSample {
+    /// 数据库文件和WAL文件的总大小（字节）
+    pub fn total_bytes(&self) -> u64 {
+        self.db_bytes + self.wal_bytes
+    }
+}
+
+/// 增长警告阈值，任一项超过即警告
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SizeGrowthThresholds {
+    /// 平均每天增长的MB数
+    pub mb_per_day: f64,
+    /// 平均每天增长的百分比
+    pub percent_per_day: f64,
+}
+
+impl Default for SizeGrowthThresholds {
+    fn default() -> Self {
+        Self {
+            mb_per_day: 200.0,
+            percent_per_day: 50.0,
+        }
+    }
+}
+
+/// 检测窗口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SizeGrowthWindow {
+    /// 最近24小时
+    Day,
+    /// 最近7天
+    Week,
+}
+
+impl SizeGrowthWindow {
+    /// 全部窗口（按检测顺序）
+    pub const ALL: [SizeGrowthWindow; 2] = [SizeGrowthWindow::Day, SizeGrowthWindow::Week];
+
+    /// 窗口长度
+    pub fn duration(&self) -> Duration {
+        match self {
+            SizeGrowthWindow::Day => Duration::hours(24),
+            SizeGrowthWindow::Week => Duration::days(7),
+        }
+    }
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            SizeGrowthWindow::Day => "最近24小时",
+            SizeGrowthWindow::Week => "最近7天",
+        }
+    }
+}
+
+/// 一张表的行数增量
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableGrowth {
+    /// 表名
+    pub table: String,
+    /// 新增行数
+    pub rows: u64,
+}
+
+/// 一个窗口内的增长情况
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeGrowth {
+    /// 检测窗口
+    pub window: SizeGrowthWindow,
+    /// 窗口起点的总大小（字节）
+    pub from_bytes: u64,
+    /// 最新的总大小（字节）
+    pub to_bytes: u64,
+    /// 平均每天增长的MB数
+    pub mb_per_day: f64,
+    /// 平均每天增长的百分比
+    pub percent_per_day: f64,
+    /// 行数增长最快的表（最多 [`SIZE_GROWTH_TOP_TABLES`] 张）
+    pub top_tables: Vec<TableGrowth>,
+}
+
+impl SizeGrowth {
+    /// 计算两次记录之间的增长，记录时间相同或倒序时为空
+    pub fn between(
+        window: SizeGrowthWindow,
+        earlier: &SizeSample,
+        latest: &SizeSample,
+    ) -> Option<Self> {
+        let elapsed = latest.taken_at - earlier.taken_at;
+        if elapsed <= Duration::zero() {
+            return None;
+        }
+        let days = elapsed.num_seconds() as f64 / 86_400.0;
+        let from_bytes = earlier.total_bytes();
+        let to_bytes = latest.total_bytes();
+        let grown = to_bytes as f64 - from_bytes as f64;
+        let percent_per_day = if from_bytes == 0 {
+            0.0
+        } else {
+            grown / from_bytes as f64 * 100.0 / days
+        };
+
+        let mut top_tables: Vec<TableGrowth> = latest
+            .table_rows
+            .iter()
+            .filter_map(|(table, &rows)| {
+                let before = earlier.table_rows.get(table).copied().unwrap_or(0);
+                (rows > before).then(|| TableGrowth {
+                    table: table.clone(),
+                    rows: rows - before,
+                })
+            })
+            .collect();
+        top_tables.sort_by(|a, b| b.rows.cmp(&a.rows).then_with(|| a.table.cmp(&b.table)));
+        top_tables.truncate(SIZE_GROWTH_TOP_TABLES);
+
+        Some(Self {
+            window,
+            from_bytes,
+            to_bytes,
+            mb_per_day: grown / MB / days,
+            percent_per_day,
+            top_tables,
+        })
+    }
+
+    /// 是否超过阈值
+    pub fn exceeds(&self, thresholds: &SizeGrowthThresholds) -> bool {
+        self.mb_per_day > thresholds.mb_per_day || self.percent_per_day > thresholds.percent_per_day
+    }
+
+    /// 警告内容
+    pub fn message(&self) -> String {
+        let mut message = format!(
+            "数据库{}从 {:.1} MB 增长到 {:.1} MB，平均每天增长 {:.1} MB（{:.1}%）",
+            self.window.label(),
+            self.from_bytes as f64 / MB,
+            self.to_bytes as f64 / MB,
+            self.mb_per_day,
+            self.percent_per_day
+        );
+        if !self.top_tables.is_empty() {
+            let tables: Vec<String> = self
+                .top_tables
+                .iter()
+                .map(|t| format!("{} +{} 行", t.table, t.rows))
+                .collect();
+            message.push_str(&format!("。增长最快的表：{}", tables.join("、")));
+        }
+        message
+    }
+}
+
+/// 计算某个窗口的增长：以窗口内最早的记录为起点，最新的记录为终点
+///
+/// 记录不必有序。窗口内的记录覆盖不到窗口长度的四分之一时为空。
+pub fn size_growth(history: &[SizeSample], window: SizeGrowthWindow) -> Option<SizeGrowth> {
+    let latest = history.iter().max_by_key(|s| s.taken_at)?;
+    let start = latest.taken_at - window.duration();
+    let earliest = history
+        .iter()
+        .filter(|s| s.taken_at >= start)
+        .min_by_key(|s| s.taken_at)?;
+    if latest.taken_at - earliest.taken_at < window.duration() / 4 {
+        return None;
+    }
+    SizeGrowth::between(window, earliest, latest)
+}
+
+/// 检测异常增长，按最近24小时、最近7天的顺序返回第一个超过阈值的窗口
+pub fn detect_size_growth(
+    history: &[SizeSample],
+    thresholds: &SizeGrowthThresholds,
+) -> Option<SizeGrowth> {
+    SizeGrowthWindow::ALL
+        .iter()
+        .filter_map(|&window| size_growth(history, window))
+        .find(|growth| growth.exceeds(thresholds))
+}
+
+/// 超过保留期的记录的截止时间（早于该时间的记录可以删除）
+pub fn size_history_cutoff(now: DateTime<Utc>) -> DateTime<Utc> {
+    now - Duration::days(SIZE_HISTORY_RETENTION_DAYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const MB_BYTES: u64 = 1024 * 1024;
+
+    fn sample(hours: i64, db_mb: u64, rows: &[(&str, u64)]) -> SizeSample {
+        SizeSample {
+            taken_at: Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap() + Duration::hours(hours),
+            db_bytes: db_mb * MB_BYTES,
+            wal_bytes: 0,
+            table_rows: rows.iter().map(|(t, n)| (t.to_string(), *n)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_threshold_triggering() {
+        let thresholds = SizeGrowthThresholds {
+            mb_per_day: 100.0,
+            percent_per_day: 20.0,
+        };
+        // 一周内每天增长 10 MB（1000 MB 的 1%）
+        let steady: Vec<_> = (0..=7)
+            .map(|day| sample(day * 24, 1000 + day as u64 * 10, &[]))
+            .collect();
+        let growth = size_growth(&steady, SizeGrowthWindow::Week).unwrap();
+        assert!((growth.mb_per_day - 10.0).abs() < 1e-9);
+        assert!((growth.percent_per_day - 10.0 / 1000.0 * 100.0).abs() < 1e-9);
+        assert_eq!(detect_size_growth(&steady, &thresholds), None);
+
+        // 最后12小时增长 80 MB，按天折算为 160 MB/天
+        let mut burst = steady.clone();
+        burst.push(sample(7 * 24 + 12, 1150, &[]));
+        let growth = detect_size_growth(&burst, &thresholds).unwrap();
+        assert_eq!(growth.window, SizeGrowthWindow::Day);
+        assert!(growth.mb_per_day > 100.0);
+
+        // 小数据库按比例触发：一周从 10 MB 增长到 40 MB
+        let small: Vec<_> = (0..=7)
+            .map(|day| sample(day * 24, 10 + day as u64 * 30 / 7, &[]))
+            .collect();
+        let growth = detect_size_growth(&small, &thresholds).unwrap();
+        assert!(growth.mb_per_day < 100.0);
+        assert!(growth.percent_per_day > 20.0);
+
+        // 刚开始记录时覆盖时间太短，不折算
+        let fresh = [sample(0, 100, &[]), sample(1, 200, &[])];
+        assert_eq!(size_growth(&fresh, SizeGrowthWindow::Day), None);
+        assert_eq!(detect_size_growth(&fresh, &thresholds), None);
+        assert_eq!(detect_size_growth(&[], &thresholds), None);
+    }
+
+    #[test]
+    fn test_top_table_attribution() {
+        let history = [
+            sample(
+                0,
+                500,
+                &[
+                    ("customers", 800),
+                    ("interactions", 10_000),
+                    ("quotes", 300),
+                ],
+            ),
+            sample(
+                24,
+                900,
+                &[
+                    ("audit_log", 2_000),
+                    ("customers", 805),
+                    ("interactions", 60_000),
+                    ("quotes", 250),
+                    ("tickets", 2_000),
+                ],
+            ),
+        ];
+        let growth = detect_size_growth(&history, &SizeGrowthThresholds::default()).unwrap();
+        // 行数减少的表不计入，增量相同时按表名排序
+        assert_eq!(
+            growth.top_tables,
+            vec![
+                TableGrowth {
+                    table: "interactions".to_string(),
+                    rows: 50_000
+                },
+                TableGrowth {
+                    table: "audit_log".to_string(),
+                    rows: 2_000
+                },
+                TableGrowth {
+                    table: "tickets".to_string(),
+                    rows: 2_000
+                },
+            ]
+        );
+        assert_eq!(
+            growth.message(),
+            "数据库最近24小时从 500.0 MB 增长到 900.0 MB，平均每天增长 400.0 MB（80.0%）。\
+             增长最快的表：interactions +50000 行、audit_log +2000 行、tickets +2000 行"
+        );
+    }
+
+    #[test]
+    fn test_history_cutoff() {
+        let now = Utc.with_ymd_and_hms(2024, 9, 1, 0, 0, 0).unwrap();
+        let cutoff = size_history_cutoff(now);
+        assert_eq!(cutoff, Utc.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,362 @@
+//! 陈旧度评估模块
+//!
+//! 报价列表、任务看板和工单列表上的“久未跟进”徽标，以及提醒任务的升级档，都用同一个
+//! [`StalenessEvaluator`] 计算，避免各处各自判断天数：
+//!
+//! - 已发送的报价：自发送（最后更新）起经过的天数
+//! - 未完成的任务：自截止日期起逾期的天数
+//! - 未关闭的工单：自最后更新起经过的天数
+//!
+//! 天数按业务时区的本地日历天计算，达到“关注”天数为 [`Freshness::Aging`]，达到“陈旧”
+//! 天数为 [`Freshness::Stale`]，两档阈值按类型分别配置。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::BusinessCalendar;
+use crate::display::BadgeTone;
+use crate::entity::{Quote, QuoteStatus, ServiceTicket, ServiceTicketStatus, Task, TaskStatus};
+
+/// 需要评估陈旧度的记录类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StalenessKind {
+    /// 已发送未答复的报价
+    SentQuote,
+    /// 已逾期的任务
+    OverdueTask,
+    /// 久未更新的工单
+    TicketNoUpdate,
+}
+
+/// 陈旧程度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Freshness {
+    /// 正常
+    #[default]
+    Ok,
+    /// 需要关注（天数）
+    Aging(u32),
+    /// 已陈旧，需要升级处理（天数）
+    Stale(u32),
+}
+
+impl Freshness {
+    /// 经过的天数（正常时为空）
+    pub fn days(&self) -> Option<u32> {
+        match self {
+            Freshness::Ok => None,
+            Freshness::Aging(days) | Freshness::Stale(days) => Some(*days),
+        }
+    }
+
+    /// 是否已陈旧
+    pub fn is_stale(&self) -> bool {
+        matches!(self, Freshness::Stale(_))
+    }
+
+    /// 徽标色调（正常为中性，需要关注为提示色，陈旧为红色）
+    pub fn badge(&self) -> BadgeTone {
+        match self {
+            Freshness::Ok => BadgeTone::Neutral,
+            Freshness::Aging(_) => BadgeTone::Info,
+            Freshness::Stale(_) => BadgeTone::Danger,
+        }
+    }
+
+    /// 提示文字，如“这个报价已发送 20 天无回应”（正常时为空）
+    pub fn message(&self, kind: StalenessKind) -> Option<String> {
+        let days = self.days()?;
+        Some(match kind {
+            StalenessKind::SentQuote => format!("这个报价已发送 {days} 天无回应"),
+            StalenessKind::OverdueTask => format!("任务已逾期 {days} 天"),
+            StalenessKind::TicketNoUpdate => format!("工单已 {days} 天未更新"),
+        })
+    }
+}
+
+/// 一类记录的两档阈值（天）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StalenessRule {
+    /// 达到此天数时需要关注
+    pub aging_days: u32,
+    /// 达到此天数时已陈旧
+    pub stale_days: u32,
+}
+
+impl StalenessRule {
+    /// 按经过的天数分档
+    pub fn classify(&self, days: i64) -> Freshness {
+        let Ok(days) = u32::try_from(days) else {
+            return Freshness::Ok;
+        };
+        if days >= self.stale_days {
+            Freshness::Stale(days)
+        } else if days >= self.aging_days && days > 0 {
+            Freshness::Aging(days)
+        } else {
+            Freshness::Ok
+        }
+    }
+}
+
+/// 各类记录的陈旧阈值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StalenessThresholds {
+    /// 已发送报价的跟进天数
+    pub sent_quote: StalenessRule,
+    /// 任务逾期的升级天数
+    pub overdue_task: StalenessRule,
+    /// 工单未更新的天数
+    pub ticket_no_update: StalenessRule,
+}
+
+impl Default for StalenessThresholds {
+    fn default() -> Self {
+        Self {
+            sent_quote: StalenessRule {
+                aging_days: 7,
+                stale_days: 14,
+            },
+            overdue_task: StalenessRule {
+                aging_days: 1,
+                stale_days: 3,
+            },
+            ticket_no_update: StalenessRule {
+                aging_days: 3,
+                stale_days: 7,
+            },
+        }
+    }
+}
+
+impl StalenessThresholds {
+    /// 记录类型对应的阈值
+    pub fn rule(&self, kind: StalenessKind) -> StalenessRule {
+        match kind {
+            StalenessKind::SentQuote => self.sent_quote,
+            StalenessKind::OverdueTask => self.overdue_task,
+            StalenessKind::TicketNoUpdate => self.ticket_no_update,
+        }
+    }
+}
+
+/// 陈旧度评估
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StalenessEvaluator {
+    calendar: BusinessCalendar,
+    thresholds: StalenessThresholds,
+}
+
+impl StalenessEvaluator {
+    /// 以业务日历和阈值创建评估器
+    pub fn new(calendar: BusinessCalendar, thresholds: StalenessThresholds) -> Self {
+        Self {
+            calendar,
+            thresholds,
+        }
+    }
+
+    /// 当前阈值
+    pub fn thresholds(&self) -> &StalenessThresholds {
+        &self.thresholds
+    }
+
+    /// 评估一条记录
+    ///
+    /// `active` 为记录当前状态是否需要跟进（如报价已发送、任务未完成），`since`
+    /// 为起算时间（发送、截止或最后更新时间），不需要跟进或没有起算时间时为正常。
+    pub fn evaluate(
+        &self,
+        kind: StalenessKind,
+        active: bool,
+        since: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Freshness {
+        match since.filter(|_| active) {
+            Some(since) => self
+                .thresholds
+                .rule(kind)
+                .classify(self.calendar.aging_days(since, now)),
+            None => Freshness::Ok,
+        }
+    }
+
+    /// 报价：只评估已发送的报价，发送后报价不再修改，以最后更新时间作为发送时间
+    pub fn quote(&self, quote: &Quote, now: DateTime<Utc>) -> Freshness {
+        self.evaluate(
+            StalenessKind::SentQuote,
+            quote.status == QuoteStatus::Sent,
+            Some(quote.updated_at),
+            now,
+        )
+    }
+
+    /// 任务：只评估待处理和进行中的任务，截止当天不算逾期
+    pub fn task(&self, task: &Task, now: DateTime<Utc>) -> Freshness {
+        self.evaluate(
+            StalenessKind::OverdueTask,
+            matches!(task.status, TaskStatus::Pending | TaskStatus::InProgress),
+            task.due_date,
+            now,
+        )
+    }
+
+    /// 工单：只评估未关闭的工单
+    pub fn ticket(&self, ticket: &ServiceTicket, now: DateTime<Utc>) -> Freshness {
+        self.evaluate(
+            StalenessKind::TicketNoUpdate,
+            ticket.status != ServiceTicketStatus::Closed,
+            Some(ticket.updated_at),
+            now,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn evaluator() -> StalenessEvaluator {
+        StalenessEvaluator::new(BusinessCalendar::default(), StalenessThresholds::default())
+    }
+
+    /// 上海时间2024年7月1日10点
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 1, 2, 0, 0).unwrap()
+    }
+
+    fn after_days(days: i64) -> DateTime<Utc> {
+        start() + Duration::days(days)
+    }
+
+    #[test]
+    fn test_threshold_boundaries_per_kind() {
+        let staleness = evaluator();
+        let cases = [
+            (StalenessKind::SentQuote, 6, Freshness::Ok),
+            (StalenessKind::SentQuote, 7, Freshness::Aging(7)),
+            (StalenessKind::SentQuote, 13, Freshness::Aging(13)),
+            (StalenessKind::SentQuote, 14, Freshness::Stale(14)),
+            (StalenessKind::OverdueTask, 0, Freshness::Ok),
+            (StalenessKind::OverdueTask, 1, Freshness::Aging(1)),
+            (StalenessKind::OverdueTask, 2, Freshness::Aging(2)),
+            (StalenessKind::OverdueTask, 3, Freshness::Stale(3)),
+            (StalenessKind::TicketNoUpdate, 2, Freshness::Ok),
+            (StalenessKind::TicketNoUpdate, 3, Freshness::Aging(3)),
+            (StalenessKind::TicketNoUpdate, 7, Freshness::Stale(7)),
+        ];
+        for (kind, days, expected) in cases {
+            assert_eq!(
+                staleness.evaluate(kind, true, Some(start()), after_days(days)),
+                expected,
+                "{kind:?} {days}"
+            );
+        }
+
+        // 按本地日历天计算：当晚23:59仍是第0天，次日0点起算第1天
+        let late_evening = Utc.with_ymd_and_hms(2024, 7, 1, 15, 59, 0).unwrap();
+        let midnight = Utc.with_ymd_and_hms(2024, 7, 1, 16, 0, 0).unwrap();
+        let overdue =
+            |now| staleness.evaluate(StalenessKind::OverdueTask, true, Some(start()), now);
+        assert_eq!(overdue(late_evening), Freshness::Ok);
+        assert_eq!(overdue(midnight), Freshness::Aging(1));
+
+        // 不需要跟进、没有起算时间或尚未到期时为正常
+        let stale_now = after_days(30);
+        assert_eq!(
+            staleness.evaluate(StalenessKind::SentQuote, false, Some(start()), stale_now),
+            Freshness::Ok
+        );
+        assert_eq!(
+            staleness.evaluate(StalenessKind::OverdueTask, true, None, stale_now),
+            Freshness::Ok
+        );
+        assert_eq!(
+            staleness.evaluate(StalenessKind::OverdueTask, true, Some(stale_now), start()),
+            Freshness::Ok
+        );
+    }
+
+    #[test]
+    fn test_custom_thresholds_and_messages() {
+        let thresholds = StalenessThresholds {
+            sent_quote: StalenessRule {
+                aging_days: 3,
+                stale_days: 20,
+            },
+            ..StalenessThresholds::default()
+        };
+        let staleness = StalenessEvaluator::new(BusinessCalendar::default(), thresholds);
+        let quote = |days| {
+            staleness.evaluate(
+                StalenessKind::SentQuote,
+                true,
+                Some(start()),
+                after_days(days),
+            )
+        };
+        assert_eq!(quote(3), Freshness::Aging(3));
+        assert_eq!(quote(19), Freshness::Aging(19));
+        assert_eq!(quote(20), Freshness::Stale(20));
+
+        assert_eq!(
+            quote(20).message(StalenessKind::SentQuote).as_deref(),
+            Some("这个报价已发送 20 天无回应")
+        );
+        assert_eq!(
+            Freshness::Stale(5)
+                .message(StalenessKind::OverdueTask)
+                .as_deref(),
+            Some("任务已逾期 5 天")
+        );
+        assert_eq!(Freshness::Ok.message(StalenessKind::TicketNoUpdate), None);
+        assert_eq!(Freshness::Aging(3).badge(), BadgeTone::Info);
+        assert_eq!(Freshness::Stale(3).badge(), BadgeTone::Danger);
+    }
+
+    #[test]
+    fn test_entity_status_decides_applicability() {
+        let staleness = evaluator();
+        let mut task = Task {
+            id: uuid::Uuid::new_v4(),
+            title: "上门复尺".to_string(),
+            description: None,
+            status: TaskStatus::InProgress,
+            priority: crate::entity::TaskPriority::High,
+            customer_id: None,
+            supplier_id: None,
+            due_date: Some(start()),
+            created_at: start(),
+            updated_at: start(),
+            created_by: None,
+            updated_by: None,
+            assigned_to: None,
+        };
+        assert_eq!(staleness.task(&task, after_days(5)), Freshness::Stale(5));
+        task.status = TaskStatus::Completed;
+        assert_eq!(staleness.task(&task, after_days(5)), Freshness::Ok);
+
+        let mut quote = Quote {
+            id: uuid::Uuid::new_v4(),
+            quote_number: "Q202407-0001".to_string(),
+            customer_id: uuid::Uuid::new_v4(),
+            status: QuoteStatus::Sent,
+            total_amount: crate::money::Money::from_yuan(12_800.0),
+            currency: crate::money::Currency::CNY,
+            items: Vec::new(),
+            valid_until: after_days(30),
+            remarks: None,
+            created_at: start(),
+            updated_at: start(),
+            created_by: None,
+            updated_by: None,
+        };
+        assert_eq!(
+            staleness.quote(&quote, after_days(20)),
+            Freshness::Stale(20)
+        );
+        quote.status = QuoteStatus::Draft;
+        assert_eq!(staleness.quote(&quote, after_days(20)), Freshness::Ok);
+    }
+}
@@ -0,0 +1,295 @@
+//! 客户时间线模块
+//!
+//! 客户详情的时间线合并手工记录的互动和从其他单据推导的事件：报价新建、发送、接受，
+//! 订单送达，收款，售后工单开单和关闭。推导的事件不复制到互动表，每次读取时从来源表
+//! 查询，单据修改后时间线随之变化。
+//!
+//! 时间线按时间倒序排列，以（时间，事件类型，记录ID）做键集分页：下一页从上一页最后一条
+//! 之后继续，翻页期间写入的新记录不会让后面的页重复或遗漏。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entity::InteractionKind;
+use crate::events::EntityKind;
+use crate::money::{Currency, Money};
+
+/// 时间线每页条数
+pub const TIMELINE_PAGE_SIZE: usize = 50;
+
+/// 时间线事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TimelineEventType {
+    /// 手工记录的互动
+    Interaction,
+    /// 新建报价
+    QuoteCreated,
+    /// 报价已发送
+    QuoteSent,
+    /// 报价已接受
+    QuoteAccepted,
+    /// 订单已送达
+    OrderDelivered,
+    /// 收到付款
+    PaymentReceived,
+    /// 售后开单
+    TicketOpened,
+    /// 售后关闭
+    TicketClosed,
+}
+
+impl TimelineEventType {
+    /// 全部事件类型
+    pub const ALL: [TimelineEventType; 8] = [
+        TimelineEventType::Interaction,
+        TimelineEventType::QuoteCreated,
+        TimelineEventType::QuoteSent,
+        TimelineEventType::QuoteAccepted,
+        TimelineEventType::OrderDelivered,
+        TimelineEventType::PaymentReceived,
+        TimelineEventType::TicketOpened,
+        TimelineEventType::TicketClosed,
+    ];
+
+    /// 类型名称（分页键的一部分，不要修改）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelineEventType::Interaction => "interaction",
+            TimelineEventType::QuoteCreated => "quote_created",
+            TimelineEventType::QuoteSent => "quote_sent",
+            TimelineEventType::QuoteAccepted => "quote_accepted",
+            TimelineEventType::OrderDelivered => "order_delivered",
+            TimelineEventType::PaymentReceived => "payment_received",
+            TimelineEventType::TicketOpened => "ticket_opened",
+            TimelineEventType::TicketClosed => "ticket_closed",
+        }
+    }
+
+    /// 从类型名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.as_str() == value)
+    }
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimelineEventType::Interaction => "互动",
+            TimelineEventType::QuoteCreated => "新建报价",
+            TimelineEventType::QuoteSent => "报价已发送",
+            TimelineEventType::QuoteAccepted => "报价已接受",
+            TimelineEventType::OrderDelivered => "订单已送达",
+            TimelineEventType::PaymentReceived => "收到付款",
+            TimelineEventType::TicketOpened => "售后开单",
+            TimelineEventType::TicketClosed => "售后关闭",
+        }
+    }
+
+    /// 所属分类
+    pub fn category(&self) -> TimelineCategory {
+        match self {
+            TimelineEventType::Interaction => TimelineCategory::Interactions,
+            TimelineEventType::QuoteCreated
+            | TimelineEventType::QuoteSent
+            | TimelineEventType::QuoteAccepted => TimelineCategory::Quotes,
+            TimelineEventType::OrderDelivered | TimelineEventType::PaymentReceived => {
+                TimelineCategory::Orders
+            }
+            TimelineEventType::TicketOpened | TimelineEventType::TicketClosed => {
+                TimelineCategory::Service
+            }
+        }
+    }
+
+    /// 点击后跳转的实体类型
+    pub fn entity(&self) -> EntityKind {
+        match self.category() {
+            TimelineCategory::Interactions => EntityKind::Customer,
+            TimelineCategory::Quotes => EntityKind::Quote,
+            TimelineCategory::Orders => EntityKind::Order,
+            TimelineCategory::Service => EntityKind::ServiceTicket,
+        }
+    }
+}
+
+/// 时间线分类（界面筛选使用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TimelineCategory {
+    /// 互动
+    Interactions,
+    /// 报价
+    Quotes,
+    /// 订单和收款
+    Orders,
+    /// 售后
+    Service,
+}
+
+impl TimelineCategory {
+    /// 全部分类
+    pub const ALL: [TimelineCategory; 4] = [
+        TimelineCategory::Interactions,
+        TimelineCategory::Quotes,
+        TimelineCategory::Orders,
+        TimelineCategory::Service,
+    ];
+
+    /// 分类包含的事件类型
+    pub fn event_types(&self) -> Vec<TimelineEventType> {
+        TimelineEventType::ALL
+            .into_iter()
+            .filter(|event| event.category() == *self)
+            .collect()
+    }
+}
+
+/// 分页位置：上一页最后一条的（时间，事件类型，记录ID）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineCursor {
+    /// 发生时间
+    pub occurred_at: DateTime<Utc>,
+    /// 事件类型
+    pub event: TimelineEventType,
+    /// 来源记录ID
+    pub id: Uuid,
+}
+
+/// 时间线中的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineItem {
+    /// 事件类型
+    pub event: TimelineEventType,
+    /// 来源记录ID（互动、报价、订单、收款或工单的ID）
+    pub id: Uuid,
+    /// 发生时间（精确到毫秒）
+    pub occurred_at: DateTime<Utc>,
+    /// 跳转目标的ID（报价、订单或工单；互动为客户）
+    pub entity_id: Uuid,
+    /// 互动类型（仅互动）
+    pub interaction_kind: Option<InteractionKind>,
+    /// 单据编号（报价、订单、工单）
+    pub number: Option<String>,
+    /// 金额（报价、订单、收款）
+    pub amount: Option<Money>,
+    /// 金额的币种
+    pub currency: Currency,
+    /// 内容（互动内容、工单的问题分类）
+    pub content: Option<String>,
+}
+
+impl TimelineItem {
+    /// 该记录之后的分页位置
+    pub fn cursor(&self) -> TimelineCursor {
+        TimelineCursor {
+            occurred_at: self.occurred_at,
+            event: self.event,
+            id: self.id,
+        }
+    }
+
+    /// 图标提示（与界面图标资源名对应）
+    pub fn icon(&self) -> &'static str {
+        match (self.event, self.interaction_kind) {
+            (TimelineEventType::Interaction, Some(InteractionKind::Call)) => "phone",
+            (TimelineEventType::Interaction, Some(InteractionKind::Visit)) => "visit",
+            (TimelineEventType::Interaction, Some(InteractionKind::Message)) => "message",
+            (TimelineEventType::Interaction, Some(InteractionKind::Delivery)) => "truck",
+            (TimelineEventType::Interaction, _) => "note",
+            (TimelineEventType::QuoteCreated, _) => "quote",
+            (TimelineEventType::QuoteSent, _) => "send",
+            (TimelineEventType::QuoteAccepted, _) => "check",
+            (TimelineEventType::OrderDelivered, _) => "truck",
+            (TimelineEventType::PaymentReceived, _) => "payment",
+            (TimelineEventType::TicketOpened, _) => "ticket",
+            (TimelineEventType::TicketClosed, _) => "ticket-closed",
+        }
+    }
+}
+
+/// 时间线查询
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineQuery {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 只读取这些分类（为空时读取全部）
+    pub categories: Vec<TimelineCategory>,
+    /// 从该位置之后读取（第一页为空）
+    pub after: Option<TimelineCursor>,
+    /// 每页条数
+    pub limit: usize,
+}
+
+impl TimelineQuery {
+    /// 读取客户时间线的第一页
+    pub fn new(customer_id: Uuid) -> Self {
+        Self {
+            customer_id,
+            categories: Vec::new(),
+            after: None,
+            limit: TIMELINE_PAGE_SIZE,
+        }
+    }
+
+    /// 只读取指定分类
+    pub fn with_categories(mut self, categories: &[TimelineCategory]) -> Self {
+        self.categories = categories.to_vec();
+        self
+    }
+
+    /// 从分页位置之后读取
+    pub fn after(mut self, cursor: TimelineCursor) -> Self {
+        self.after = Some(cursor);
+        self
+    }
+
+    /// 设置每页条数
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.max(1);
+        self
+    }
+
+    /// 查询包含的事件类型
+    pub fn event_types(&self) -> Vec<TimelineEventType> {
+        TimelineEventType::ALL
+            .into_iter()
+            .filter(|event| {
+                self.categories.is_empty() || self.categories.contains(&event.category())
+            })
+            .collect()
+    }
+}
+
+/// 时间线的一页
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimelinePage {
+    /// 按时间倒序的记录
+    pub items: Vec<TimelineItem>,
+    /// 下一页的位置（没有更多记录时为空）
+    pub next: Option<TimelineCursor>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_event_types() {
+        let customer = Uuid::new_v4();
+        let all = TimelineQuery::new(customer);
+        assert_eq!(all.event_types(), TimelineEventType::ALL.to_vec());
+
+        let quotes = TimelineQuery::new(customer).with_categories(&[TimelineCategory::Quotes]);
+        assert_eq!(
+            quotes.event_types(),
+            vec![
+                TimelineEventType::QuoteCreated,
+                TimelineEventType::QuoteSent,
+                TimelineEventType::QuoteAccepted
+            ]
+        );
+        for event in TimelineEventType::ALL {
+            assert_eq!(TimelineEventType::parse(event.as_str()), Some(event));
+        }
+        assert_eq!(TimelineEventType::parse("unknown"), None);
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 定义系统中使用的通用类型和常量
 
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -53,6 +54,13 @@ pub struct PagedResult<T> {
     pub page_size: u32,
     /// 总页数
     pub total_pages: u32,
+    /// 总记录数是否为精确值（为 `false` 时界面应显示为"约 N 条"）
+    #[serde(default = "default_total_exact")]
+    pub is_total_exact: bool,
+}
+
+fn default_total_exact() -> bool {
+    true
 }
 
 impl<T> PagedResult<T> {
@@ -66,6 +74,15 @@ impl<T> PagedResult<T> {
             page: pagination.page,
             page_size: pagination.page_size,
             total_pages,
+            is_total_exact: true,
+        }
+    }
+
+    /// 创建总记录数为估算值的分页结果
+    pub fn with_estimated_total(items: Vec<T>, estimated_total: u64, pagination: &Pagination) -> Self {
+        Self {
+            is_total_exact: false,
+            ..Self::new(items, estimated_total, pagination)
         }
     }
 
@@ -81,7 +98,7 @@ impl<T> PagedResult<T> {
 }
 
 /// 排序方向
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum SortDirection {
     /// 升序
     #[default]
@@ -91,7 +108,7 @@ pub enum SortDirection {
 }
 
 /// 排序参数
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SortBy {
     /// 排序字段
     pub field: String,
@@ -154,6 +171,97 @@ pub enum FilterValue {
         /// 结束日期
         end: Option<chrono::DateTime<chrono::Utc>>,
     },
+    /// 比较（`字段 运算符 值`）
+    Compare {
+        /// 运算符
+        op: CompareOp,
+        /// 比较的值
+        value: ScalarValue,
+    },
+    /// 范围（含两端，上下限的值类型须一致）
+    Between {
+        /// 下限
+        low: ScalarValue,
+        /// 上限
+        high: ScalarValue,
+    },
+    /// 模糊匹配（`%` 匹配任意多个字符，`_` 匹配单个字符）
+    Like(String),
+    /// 不在字符串列表中（字段为空的记录也算不在列表中）
+    NotInStrings(Vec<String>),
+    /// 不在整数列表中（字段为空的记录也算不在列表中）
+    NotInIntegers(Vec<i64>),
+}
+
+/// 比较运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompareOp {
+    /// 大于
+    Gt,
+    /// 大于等于
+    Gte,
+    /// 小于
+    Lt,
+    /// 小于等于
+    Lte,
+    /// 不等于
+    Ne,
+}
+
+impl CompareOp {
+    /// SQL运算符
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+            Self::Ne => "<>",
+        }
+    }
+}
+
+/// 比较和范围条件中的值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScalarValue {
+    /// 字符串
+    String(String),
+    /// 整数
+    Integer(i64),
+    /// 浮点数
+    Float(f64),
+    /// 时间
+    DateTime(DateTime<Utc>),
+}
+
+impl From<&str> for ScalarValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for ScalarValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for ScalarValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<f64> for ScalarValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<DateTime<Utc>> for ScalarValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        Self::DateTime(value)
+    }
 }
 
 impl QueryFilter {
@@ -179,6 +287,100 @@ impl QueryFilter {
         self
     }
 
+    /// 添加日期范围过滤器
+    pub fn with_date_range<K: Into<String>>(mut self, key: K, range: DateRange) -> Self {
+        self.filters.insert(
+            key.into(),
+            FilterValue::DateRange {
+                start: Some(range.start),
+                end: Some(range.end),
+            },
+        );
+        self
+    }
+
+    /// 添加比较过滤器
+    pub fn with_compare<K: Into<String>, V: Into<ScalarValue>>(
+        mut self,
+        key: K,
+        op: CompareOp,
+        value: V,
+    ) -> Self {
+        self.filters.insert(
+            key.into(),
+            FilterValue::Compare {
+                op,
+                value: value.into(),
+            },
+        );
+        self
+    }
+
+    /// 添加“大于”过滤器
+    pub fn with_gt<K: Into<String>, V: Into<ScalarValue>>(self, key: K, value: V) -> Self {
+        self.with_compare(key, CompareOp::Gt, value)
+    }
+
+    /// 添加“大于等于”过滤器
+    pub fn with_gte<K: Into<String>, V: Into<ScalarValue>>(self, key: K, value: V) -> Self {
+        self.with_compare(key, CompareOp::Gte, value)
+    }
+
+    /// 添加“小于”过滤器
+    pub fn with_lt<K: Into<String>, V: Into<ScalarValue>>(self, key: K, value: V) -> Self {
+        self.with_compare(key, CompareOp::Lt, value)
+    }
+
+    /// 添加“小于等于”过滤器
+    pub fn with_lte<K: Into<String>, V: Into<ScalarValue>>(self, key: K, value: V) -> Self {
+        self.with_compare(key, CompareOp::Lte, value)
+    }
+
+    /// 添加范围过滤器（含两端）
+    pub fn with_between<K: Into<String>, V: Into<ScalarValue>>(
+        mut self,
+        key: K,
+        low: V,
+        high: V,
+    ) -> Self {
+        self.filters.insert(
+            key.into(),
+            FilterValue::Between {
+                low: low.into(),
+                high: high.into(),
+            },
+        );
+        self
+    }
+
+    /// 添加模糊匹配过滤器
+    pub fn with_like<K: Into<String>, V: Into<String>>(mut self, key: K, pattern: V) -> Self {
+        self.filters
+            .insert(key.into(), FilterValue::Like(pattern.into()));
+        self
+    }
+
+    /// 添加“不在字符串列表中”过滤器
+    pub fn with_not_in<K, I, V>(mut self, key: K, values: I) -> Self
+    where
+        K: Into<String>,
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        self.filters.insert(
+            key.into(),
+            FilterValue::NotInStrings(values.into_iter().map(Into::into).collect()),
+        );
+        self
+    }
+
+    /// 添加“不在整数列表中”过滤器
+    pub fn with_not_in_integers<K: Into<String>>(mut self, key: K, values: Vec<i64>) -> Self {
+        self.filters
+            .insert(key.into(), FilterValue::NotInIntegers(values));
+        self
+    }
+
     /// 添加搜索关键词
     pub fn with_search<S: Into<String>>(mut self, search: S) -> Self {
         self.search = Some(search.into());
@@ -196,6 +398,150 @@ impl QueryFilter {
         self.pagination = pagination;
         self
     }
+
+    /// 是否包含任何过滤条件（过滤器或搜索关键词）
+    pub fn has_conditions(&self) -> bool {
+        !self.filters.is_empty() || self.search.as_ref().is_some_and(|s| !s.trim().is_empty())
+    }
+}
+
+/// 列表查询的列投影
+///
+/// 只列出需要显示的列；未列出的计算列（如需要子查询汇总的列）不参与查询。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Projection {
+    /// 列ID（按显示顺序）
+    pub columns: Vec<String>,
+}
+
+impl Projection {
+    /// 以列ID创建投影
+    pub fn new<I, S>(columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            columns: columns.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// 是否包含指定列
+    pub fn includes(&self, column: &str) -> bool {
+        self.columns.iter().any(|c| c == column)
+    }
+}
+
+/// 统计周期（自然月）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ReportPeriod {
+    /// 年份
+    pub year: i32,
+    /// 月份（1-12）
+    pub month: u32,
+}
+
+impl ReportPeriod {
+    /// 创建统计周期，月份无效时返回 `None`
+    pub fn new(year: i32, month: u32) -> Option<Self> {
+        NaiveDate::from_ymd_opt(year, month, 1).map(|_| Self { year, month })
+    }
+
+    /// 包含指定时刻的统计周期
+    pub fn containing(at: DateTime<Utc>) -> Self {
+        Self {
+            year: at.year(),
+            month: at.month(),
+        }
+    }
+
+    /// 上一个统计周期
+    pub fn previous(&self) -> Self {
+        if self.month == 1 {
+            Self {
+                year: self.year - 1,
+                month: 12,
+            }
+        } else {
+            Self {
+                year: self.year,
+                month: self.month - 1,
+            }
+        }
+    }
+
+    /// 下一个统计周期
+    pub fn next(&self) -> Self {
+        if self.month == 12 {
+            Self {
+                year: self.year + 1,
+                month: 1,
+            }
+        } else {
+            Self {
+                year: self.year,
+                month: self.month + 1,
+            }
+        }
+    }
+
+    /// 周期开始时刻（含）
+    pub fn start(&self) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(self.year, self.month, 1)
+            .unwrap_or_default()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default()
+            .and_utc()
+    }
+
+    /// 周期结束时刻（不含）
+    pub fn end(&self) -> DateTime<Utc> {
+        self.next().start()
+    }
+
+    /// 时刻是否落在周期内
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        at >= self.start() && at < self.end()
+    }
+}
+
+impl Default for ReportPeriod {
+    fn default() -> Self {
+        Self::containing(Utc::now())
+    }
+}
+
+impl std::fmt::Display for ReportPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+/// 时间范围 `[start, end)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateRange {
+    /// 开始时刻（含）
+    pub start: DateTime<Utc>,
+    /// 结束时刻（不含）
+    pub end: DateTime<Utc>,
+}
+
+impl DateRange {
+    /// 创建时间范围
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    /// 时刻是否落在范围内
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        at >= self.start && at < self.end
+    }
+}
+
+impl From<ReportPeriod> for DateRange {
+    fn from(period: ReportPeriod) -> Self {
+        Self::new(period.start(), period.end())
+    }
 }
 
 /// 系统配置常量
@@ -211,4 +557,7 @@ pub mod constants {
 
     /// 默认查询超时时间（秒）
     pub const DEFAULT_QUERY_TIMEOUT: u64 = 30;
+
+    /// 过滤查询使用精确计数的默认行数上限
+    pub const DEFAULT_EXACT_COUNT_THRESHOLD: u64 = 10_000;
 }
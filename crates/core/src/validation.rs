@@ -0,0 +1,191 @@
+//! 字段校验模块
+//!
+//! 电话、邮箱等值类型和必填、长度等单字段规则。新建命令保存时的校验和表单输入时的
+//! 即时提示调用这里的同一组函数，两处的判断不会不一致；跨字段的规则只在命令中校验。
+
+use std::fmt;
+
+use crate::error::{CoreError, CoreResult, FieldError};
+use crate::money::Money;
+
+/// 客户、产品名称的最大字数
+pub const NAME_MAX_CHARS: usize = 100;
+
+/// 必填：去掉首尾空白后不能为空，返回去掉空白后的文字
+///
+/// # Errors
+///
+/// 为空时返回 [`CoreError::Validation`]。
+pub fn non_blank<'a>(label: &str, text: &'a str) -> CoreResult<&'a str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(CoreError::validation(format!("{}不能为空", label)));
+    }
+    Ok(text)
+}
+
+/// 最大字数（按字符计，去掉首尾空白后比较）
+///
+/// # Errors
+///
+/// 超过时返回 [`CoreError::Validation`]。
+pub fn within_chars(label: &str, text: &str, max: usize) -> CoreResult<()> {
+    if text.trim().chars().count() > max {
+        return Err(CoreError::validation(format!("{}不能超过{}个字", label, max)));
+    }
+    Ok(())
+}
+
+/// 金额必须大于0
+///
+/// # Errors
+///
+/// 不大于0时返回 [`CoreError::Validation`]。
+pub fn positive_amount(label: &str, amount: Money) -> CoreResult<()> {
+    if !amount.is_positive() {
+        return Err(CoreError::validation(format!("{}必须大于0", label)));
+    }
+    Ok(())
+}
+
+/// 校验错误的提示文字（不含“验证错误”前缀）
+pub fn validation_message(err: &CoreError) -> String {
+    match err {
+        CoreError::Validation(message) => message.clone(),
+        CoreError::InvalidFields(errors) => errors
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect::<Vec<_>>()
+            .join("；"),
+        other => other.to_string(),
+    }
+}
+
+/// 把单字段规则的错误转为字段错误
+pub fn field_error(field: &str, err: &CoreError) -> FieldError {
+    FieldError::new(field, validation_message(err))
+}
+
+/// 电话号码
+///
+/// 接受手机号（11位，1开头）、带区号的固定电话（0开头，共10至12位）和400/800热线，
+/// 可带 `+86` 前缀及空格、横线、括号分隔。保存为去掉分隔符和国家码后的数字。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhoneNumber(String);
+
+impl PhoneNumber {
+    /// 数字文本
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for PhoneNumber {
+    type Error = CoreError;
+
+    fn try_from(text: &str) -> CoreResult<Self> {
+        let compact: String = text
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '(' | ')' | '（' | '）'))
+            .collect();
+        let digits = compact
+            .strip_prefix("+86")
+            .or_else(|| compact.strip_prefix("0086"))
+            .unwrap_or(&compact);
+        let valid = digits.chars().all(|c| c.is_ascii_digit())
+            && match digits.as_bytes() {
+                [b'1', b'3'..=b'9', ..] => digits.len() == 11,
+                [b'0', ..] => (10..=12).contains(&digits.len()),
+                [b'4' | b'8', b'0', b'0', ..] => digits.len() == 10,
+                _ => false,
+            };
+        if !valid {
+            return Err(CoreError::validation("电话号码格式不正确"));
+        }
+        Ok(Self(digits.to_string()))
+    }
+}
+
+impl fmt::Display for PhoneNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// 邮箱地址
+///
+/// 只检查基本格式：一个 `@`，前后都不为空，域名至少两段且不含空段，不含空白。
+/// 域名部分转为小写保存。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmailAddress(String);
+
+impl EmailAddress {
+    /// 邮箱文本
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for EmailAddress {
+    type Error = CoreError;
+
+    fn try_from(text: &str) -> CoreResult<Self> {
+        let text = text.trim();
+        let parts = text
+            .split_once('@')
+            .filter(|(local, domain)| {
+                !local.is_empty()
+                    && !domain.contains('@')
+                    && domain.contains('.')
+                    && domain.split('.').all(|label| !label.is_empty())
+            })
+            .filter(|_| text.len() <= 254 && !text.chars().any(char::is_whitespace));
+        match parts {
+            Some((local, domain)) => Ok(Self(format!("{}@{}", local, domain.to_lowercase()))),
+            None => Err(CoreError::validation("邮箱格式不正确")),
+        }
+    }
+}
+
+impl fmt::Display for EmailAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phone_numbers() {
+        for (text, digits) in [
+            ("138 0013 8000", "13800138000"),
+            ("+86 138-0013-8000", "13800138000"),
+            ("0571-87654321", "057187654321"),
+            ("(010) 8765 4321", "01087654321"),
+            ("400-820-8820", "4008208820"),
+        ] {
+            assert_eq!(PhoneNumber::try_from(text).unwrap().as_str(), digits, "{text}");
+        }
+        for text in ["", "12800138000", "1380013800", "87654321", "138001380001", "电话"] {
+            assert!(PhoneNumber::try_from(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_email_and_text_rules() {
+        let email = EmailAddress::try_from(" Sales@HuaMei.COM ").unwrap();
+        assert_eq!(email.as_str(), "Sales@huamei.com");
+        for text in ["sales", "@huamei.com", "sales@huamei", "sales@@huamei.com", "a b@c.cn"] {
+            assert!(EmailAddress::try_from(text).is_err(), "{text}");
+        }
+
+        assert_eq!(non_blank("客户名称", "  华美家具 ").unwrap(), "华美家具");
+        let err = non_blank("客户名称", "   ").unwrap_err();
+        assert_eq!(validation_message(&err), "客户名称不能为空");
+        assert!(within_chars("备注", "一二三", 3).is_ok());
+        let err = within_chars("备注", "一二三四", 3).unwrap_err();
+        assert_eq!(field_error("note", &err).message, "备注不能超过3个字");
+    }
+}
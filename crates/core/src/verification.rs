@@ -0,0 +1,64 @@
+//! 单据校验模块
+//!
+//! 定义打印单据上防篡改校验码的载荷与编解码接口
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{entity::Quote, error::CoreResult};
+
+/// 报价单关键字段指纹
+///
+/// 打印在报价单二维码中，用于核对单据是否被改动。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteFingerprint {
+    /// 报价编号
+    pub quote_number: String,
+    /// 总金额（分）
+    pub total_cents: i64,
+    /// 有效期
+    pub valid_until: NaiveDate,
+}
+
+impl QuoteFingerprint {
+    /// 从报价实体提取指纹
+    pub fn from_quote(quote: &Quote) -> Self {
+        Self {
+            quote_number: quote.quote_number.clone(),
+            total_cents: quote.total_amount.cents(),
+            valid_until: quote.valid_until.date_naive(),
+        }
+    }
+}
+
+/// 报价单校验结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteVerification {
+    /// 校验码中携带的字段
+    pub claimed: QuoteFingerprint,
+    /// 系统中存储的字段（报价不存在时为空）
+    pub stored: Option<QuoteFingerprint>,
+    /// 两者是否一致
+    pub matches: bool,
+}
+
+impl QuoteVerification {
+    /// 比对校验码字段与存储字段
+    pub fn compare(claimed: QuoteFingerprint, stored: Option<QuoteFingerprint>) -> Self {
+        let matches = stored.as_ref() == Some(&claimed);
+        Self {
+            claimed,
+            stored,
+            matches,
+        }
+    }
+}
+
+/// 报价单校验码编解码接口
+pub trait QuotePayloadCodec: Send + Sync {
+    /// 生成带签名的校验码
+    fn encode(&self, fingerprint: &QuoteFingerprint) -> CoreResult<String>;
+
+    /// 解析校验码并验证签名
+    fn decode(&self, payload: &str) -> CoreResult<QuoteFingerprint>;
+}
@@ -0,0 +1,408 @@
+//! 工作时间模块
+//!
+//! 在业务日历（时区）之上加入每周休息日、节假日和调休上班日，以及每天的上下班时间。
+//! 工单SLA、任务“即将到期”提醒和周期任务的节假日顺延都按工作时间计算，
+//! 周末和春节等长假不计入时限。
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::{BusinessCalendar, LocalDate};
+use crate::entity::TaskPriority;
+use crate::error::{CoreError, CoreResult};
+
+/// 节假日类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HolidayKind {
+    /// 放假（即使是工作日也休息）
+    Holiday,
+    /// 调休上班（即使是休息日也上班）
+    Workday,
+}
+
+impl HolidayKind {
+    /// 类型名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Holiday => "holiday",
+            Self::Workday => "workday",
+        }
+    }
+
+    /// 从类型名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        [Self::Holiday, Self::Workday]
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+    }
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Holiday => "放假",
+            Self::Workday => "调休上班",
+        }
+    }
+}
+
+/// 节假日表中的一天
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HolidayEntry {
+    /// 日期（业务时区）
+    pub date: LocalDate,
+    /// 名称，如“春节”
+    pub name: String,
+    /// 放假或调休上班
+    pub kind: HolidayKind,
+}
+
+/// 工作时间日历
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkingCalendar {
+    calendar: BusinessCalendar,
+    rest_days: Vec<Weekday>,
+    work_start: NaiveTime,
+    work_end: NaiveTime,
+    overrides: BTreeMap<LocalDate, HolidayKind>,
+}
+
+impl Default for WorkingCalendar {
+    fn default() -> Self {
+        Self {
+            calendar: BusinessCalendar::default(),
+            rest_days: vec![Weekday::Sat, Weekday::Sun],
+            work_start: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+            work_end: NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default(),
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl WorkingCalendar {
+    /// 创建工作时间日历
+    ///
+    /// # Errors
+    ///
+    /// 一周七天都是休息日，或下班时间不晚于上班时间时返回配置错误。
+    pub fn new(
+        calendar: BusinessCalendar,
+        rest_days: Vec<Weekday>,
+        work_start: NaiveTime,
+        work_end: NaiveTime,
+    ) -> CoreResult<Self> {
+        let mut rest_days = rest_days;
+        rest_days.sort_by_key(Weekday::num_days_from_monday);
+        rest_days.dedup();
+        if rest_days.len() >= 7 {
+            return Err(CoreError::configuration("每周至少要有一个工作日"));
+        }
+        if work_end <= work_start {
+            return Err(CoreError::configuration("下班时间必须晚于上班时间"));
+        }
+        Ok(Self {
+            calendar,
+            rest_days,
+            work_start,
+            work_end,
+            overrides: BTreeMap::new(),
+        })
+    }
+
+    /// 加入节假日表（同一天后出现的记录覆盖先出现的）
+    #[must_use]
+    pub fn with_holidays<'a, I>(mut self, entries: I) -> Self
+    where
+        I: IntoIterator<Item = &'a HolidayEntry>,
+    {
+        for entry in entries {
+            self.overrides.insert(entry.date, entry.kind);
+        }
+        self
+    }
+
+    /// 业务日历（时区）
+    pub fn calendar(&self) -> BusinessCalendar {
+        self.calendar
+    }
+
+    /// 每周休息日
+    pub fn rest_days(&self) -> &[Weekday] {
+        &self.rest_days
+    }
+
+    /// 是否为工作日（节假日表优先于每周休息日）
+    pub fn is_business_day(&self, date: LocalDate) -> bool {
+        match self.overrides.get(&date) {
+            Some(HolidayKind::Holiday) => false,
+            Some(HolidayKind::Workday) => true,
+            None => !self.rest_days.contains(&date.naive().weekday()),
+        }
+    }
+
+    /// 当天或之后的第一个工作日（周期任务遇节假日顺延）
+    pub fn next_business_day(&self, date: LocalDate) -> LocalDate {
+        let mut date = date;
+        while !self.is_business_day(date) {
+            date = date.add_days(1);
+        }
+        date
+    }
+
+    /// 从 `date` 起数 `days` 个工作日后的日期（`date` 本身不计入）
+    pub fn add_business_days(&self, date: LocalDate, days: u32) -> LocalDate {
+        let mut date = date;
+        for _ in 0..days {
+            date = self.next_business_day(date.add_days(1));
+        }
+        date
+    }
+
+    /// 工作日的上班时段（UTC），非工作日为空
+    fn work_window(&self, date: LocalDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        self.is_business_day(date).then(|| {
+            (
+                self.calendar.to_utc(date.naive().and_time(self.work_start)),
+                self.calendar.to_utc(date.naive().and_time(self.work_end)),
+            )
+        })
+    }
+
+    /// 从 `start` 起经过 `duration` 工作时间后的时刻
+    ///
+    /// 下班后、休息日和节假日不计时；`start` 不在上班时间时从下一个上班时刻开始计。
+    pub fn add_business_time(&self, start: DateTime<Utc>, duration: Duration) -> DateTime<Utc> {
+        let mut remaining = duration;
+        if remaining <= Duration::zero() {
+            return start;
+        }
+        let mut date = self.calendar.local_date(start);
+        loop {
+            if let Some((open, close)) = self.work_window(date) {
+                let from = open.max(start);
+                if from < close {
+                    let available = close - from;
+                    if remaining <= available {
+                        return from + remaining;
+                    }
+                    remaining -= available;
+                }
+            }
+            date = date.add_days(1);
+        }
+    }
+
+    /// 从 `start` 起经过 `hours` 个工作小时后的时刻
+    pub fn add_business_hours(&self, start: DateTime<Utc>, hours: i64) -> DateTime<Utc> {
+        self.add_business_time(start, Duration::hours(hours))
+    }
+
+    /// `a` 到 `b` 之间的工作时间，`b` 不晚于 `a` 时为0
+    pub fn business_time_between(&self, a: DateTime<Utc>, b: DateTime<Utc>) -> Duration {
+        let mut total = Duration::zero();
+        let mut date = self.calendar.local_date(a);
+        let last = self.calendar.local_date(b);
+        while date <= last {
+            if let Some((open, close)) = self.work_window(date) {
+                let (from, to) = (open.max(a), close.min(b));
+                if from < to {
+                    total += to - from;
+                }
+            }
+            date = date.add_days(1);
+        }
+        total
+    }
+
+    /// `a` 到 `b` 之间的工作小时数
+    pub fn business_hours_between(&self, a: DateTime<Utc>, b: DateTime<Utc>) -> f64 {
+        self.business_time_between(a, b).num_seconds() as f64 / 3600.0
+    }
+
+    /// 到期时间是否在今天起 `days` 个工作日之内（含今天，不含已逾期）
+    ///
+    /// 节假日不占用提醒窗口：周五看“3个工作日内到期”会包含下周三。
+    pub fn due_within_business_days(
+        &self,
+        due: DateTime<Utc>,
+        now: DateTime<Utc>,
+        days: u32,
+    ) -> bool {
+        let today = self.calendar.local_date(now);
+        let due = self.calendar.local_date(due);
+        due >= today && due <= self.add_business_days(today, days)
+    }
+}
+
+/// 工单SLA时限（工作小时）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlaPolicy {
+    /// 低优先级
+    pub low_hours: i64,
+    /// 中等优先级
+    pub medium_hours: i64,
+    /// 高优先级
+    pub high_hours: i64,
+    /// 紧急
+    pub urgent_hours: i64,
+}
+
+impl Default for SlaPolicy {
+    fn default() -> Self {
+        Self {
+            low_hours: 72,
+            medium_hours: 48,
+            high_hours: 24,
+            urgent_hours: 8,
+        }
+    }
+}
+
+impl SlaPolicy {
+    /// 优先级对应的时限（工作小时）
+    pub fn hours_for(&self, priority: TaskPriority) -> i64 {
+        match priority {
+            TaskPriority::Low => self.low_hours,
+            TaskPriority::Medium => self.medium_hours,
+            TaskPriority::High => self.high_hours,
+            TaskPriority::Urgent => self.urgent_hours,
+        }
+    }
+}
+
+/// 工单SLA评估
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlaEvaluator {
+    calendar: WorkingCalendar,
+    policy: SlaPolicy,
+}
+
+impl SlaEvaluator {
+    /// 以工作时间日历和时限创建评估器
+    pub fn new(calendar: WorkingCalendar, policy: SlaPolicy) -> Self {
+        Self { calendar, policy }
+    }
+
+    /// 工单的SLA到期时刻
+    pub fn due_at(&self, opened_at: DateTime<Utc>, priority: TaskPriority) -> DateTime<Utc> {
+        self.calendar
+            .add_business_hours(opened_at, self.policy.hours_for(priority))
+    }
+
+    /// 处理耗时（工作小时）
+    pub fn elapsed_hours(&self, opened_at: DateTime<Utc>, resolved_at: DateTime<Utc>) -> f64 {
+        self.calendar.business_hours_between(opened_at, resolved_at)
+    }
+
+    /// 是否在SLA时限内解决
+    pub fn met(
+        &self,
+        opened_at: DateTime<Utc>,
+        resolved_at: DateTime<Utc>,
+        priority: TaskPriority,
+    ) -> bool {
+        resolved_at <= self.due_at(opened_at, priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> LocalDate {
+        LocalDate::new(2026, month, day).unwrap()
+    }
+
+    /// 上海时间
+    fn at(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        BusinessCalendar::default().to_utc(
+            date(month, day)
+                .naive()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap(),
+        )
+    }
+
+    fn holiday(month: u32, day: u32, kind: HolidayKind) -> HolidayEntry {
+        HolidayEntry {
+            date: date(month, day),
+            name: "测试".to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_friday_evening_to_monday_morning() {
+        let calendar = WorkingCalendar::default();
+        // 2026-03-06 是星期五
+        let opened = at(3, 6, 17, 0);
+        let resolved = at(3, 9, 10, 0);
+        assert!((calendar.business_hours_between(opened, resolved) - 2.0).abs() < 1e-9);
+        assert_eq!(calendar.add_business_hours(opened, 2), resolved);
+
+        let evaluator = SlaEvaluator::new(calendar, SlaPolicy::default());
+        assert!(evaluator.met(opened, resolved, TaskPriority::Urgent));
+        assert_eq!(evaluator.due_at(opened, TaskPriority::Urgent), at(3, 9, 16, 0));
+    }
+
+    #[test]
+    fn test_weekend_bridged_by_holiday_monday() {
+        // 2026-04-04（周六）至04-06（周一）清明节放假
+        let calendar = WorkingCalendar::default().with_holidays(&[
+            holiday(4, 4, HolidayKind::Holiday),
+            holiday(4, 5, HolidayKind::Holiday),
+            holiday(4, 6, HolidayKind::Holiday),
+        ]);
+        assert!(!calendar.is_business_day(date(4, 6)));
+        assert_eq!(calendar.next_business_day(date(4, 4)), date(4, 7));
+
+        let opened = at(4, 3, 16, 30);
+        let resolved = at(4, 7, 11, 15);
+        assert!((calendar.business_hours_between(opened, resolved) - 3.75).abs() < 1e-9);
+        assert_eq!(calendar.add_business_hours(opened, 4), at(4, 7, 11, 30));
+        // 下班后开始计时，从下一个上班时刻起算
+        assert_eq!(calendar.add_business_hours(at(4, 3, 20, 0), 1), at(4, 7, 10, 0));
+        assert_eq!(
+            calendar.business_time_between(resolved, opened),
+            Duration::zero()
+        );
+    }
+
+    #[test]
+    fn test_makeup_workday_and_due_soon() {
+        // 2026-02-14（周六）调休上班，02-15起春节放假
+        let mut entries = vec![holiday(2, 14, HolidayKind::Workday)];
+        entries.extend((15..=23).map(|day| holiday(2, day, HolidayKind::Holiday)));
+        let calendar = WorkingCalendar::default().with_holidays(&entries);
+
+        assert!(calendar.is_business_day(date(2, 14)));
+        assert_eq!(calendar.add_business_days(date(2, 13), 2), date(2, 24));
+        assert_eq!(calendar.add_business_hours(at(2, 13, 17, 0), 2), at(2, 14, 10, 0));
+
+        let now = at(2, 13, 9, 0);
+        assert!(calendar.due_within_business_days(at(2, 24, 12, 0), now, 2));
+        assert!(!calendar.due_within_business_days(at(2, 25, 12, 0), now, 2));
+        assert!(!calendar.due_within_business_days(at(2, 12, 12, 0), now, 2));
+    }
+
+    #[test]
+    fn test_rejects_invalid_configuration() {
+        let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let six = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let all_days = vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ];
+        let shanghai = BusinessCalendar::default();
+        assert!(WorkingCalendar::new(shanghai, all_days, nine, six).is_err());
+        assert!(WorkingCalendar::new(shanghai, vec![Weekday::Sun], six, nine).is_err());
+        let six_day_week = WorkingCalendar::new(shanghai, vec![Weekday::Sun], nine, six).unwrap();
+        assert!(six_day_week.is_business_day(date(3, 7)));
+    }
+}
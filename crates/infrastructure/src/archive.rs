@@ -0,0 +1,828 @@
+//! 数据归档模块
+//!
+//! 把数据库快照、附件目录和配置目录打包为单个 `.minicrm.zip`，用于迁移到另一台电脑。
+//! 导入时先把全部文件解压到各目标旁的暂存位置并逐一校验，全部通过后才替换现有文件；
+//! 替换或迁移失败时恢复原有文件。
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use minicrm_core::{
+    ArchiveEntry, ArchiveManifest, CancellationToken, Clock, CoreError, CoreResult,
+    DataArchiveService, SystemClock,
+};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::database::pool::DatabasePoolBuilder;
+use crate::database::{schema, DatabaseConnection, MigrationManager};
+use crate::repository::support::to_core;
+
+/// 归档文件扩展名
+pub const ARCHIVE_EXTENSION: &str = "minicrm.zip";
+
+/// 当前归档格式版本
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const DATABASE_ENTRY: &str = "database/minicrm.db";
+const ATTACHMENTS_PREFIX: &str = "attachments/";
+const CONFIG_PREFIX: &str = "config/";
+
+/// 归档内容无效（在覆盖任何文件之前返回）
+fn invalid(message: impl Into<String>) -> anyhow::Error {
+    CoreError::validation(message).into()
+}
+
+/// 归档涉及的本地位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivePaths {
+    /// 数据库文件
+    pub database: PathBuf,
+    /// 附件目录
+    pub attachments_dir: PathBuf,
+    /// 配置目录（界面状态、应用口令等）
+    pub config_dir: PathBuf,
+}
+
+/// 数据归档服务
+#[derive(Clone)]
+pub struct DataArchiver {
+    connection: DatabaseConnection,
+    paths: ArchivePaths,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for DataArchiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataArchiver")
+            .field("paths", &self.paths)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DataArchiver {
+    /// 创建数据归档服务
+    pub fn new(connection: DatabaseConnection, paths: ArchivePaths) -> Self {
+        Self::with_clock(connection, paths, Arc::new(SystemClock))
+    }
+
+    /// 使用指定时钟创建（用于测试）
+    pub fn with_clock(
+        connection: DatabaseConnection,
+        paths: ArchivePaths,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            connection,
+            paths,
+            clock,
+        }
+    }
+
+    /// 导出归档
+    ///
+    /// 数据库通过 `VACUUM INTO` 生成一致的快照，不影响正在使用的连接；
+    /// 归档先写入临时文件，完成后再改名为目标文件；每写入一个文件检查一次取消令牌，
+    /// 取消时删除临时文件，目标文件不受影响。
+    pub fn export_to(
+        &self,
+        out_path: &Path,
+        cancel: &CancellationToken,
+    ) -> Result<ArchiveManifest> {
+        info!("正在导出数据归档到: {:?}", out_path);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建归档目录: {:?}", parent))?;
+        }
+
+        let snapshot = TempPath::new(sibling(out_path, "snapshot.db"));
+        self.connection
+            .execute("VACUUM INTO ?1", [snapshot.0.to_string_lossy().as_ref()])
+            .context("无法生成数据库快照")?;
+        let schema_version = MigrationManager::new(self.connection.clone())
+            .get_current_version()
+            .context("无法读取数据库结构版本")?;
+
+        let partial = TempPath::new(sibling(out_path, "partial"));
+        let file = File::create(&partial.0)
+            .with_context(|| format!("无法创建归档文件: {:?}", partial.0))?;
+        let mut zip = ZipWriter::new(BufWriter::new(file));
+
+        let mut entries = vec![add_file(&mut zip, DATABASE_ENTRY, &snapshot.0)?];
+        for (prefix, dir) in [
+            (ATTACHMENTS_PREFIX, &self.paths.attachments_dir),
+            (CONFIG_PREFIX, &self.paths.config_dir),
+        ] {
+            for (name, path) in list_files(dir)? {
+                cancel.check()?;
+                entries.push(add_file(&mut zip, &format!("{}{}", prefix, name), &path)?);
+            }
+        }
+
+        let manifest = ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version,
+            created_at: self.clock.now(),
+            entries,
+        };
+        cancel.check()?;
+        zip.start_file(MANIFEST_NAME, entry_options(0))?;
+        serde_json::to_writer_pretty(&mut zip, &manifest)?;
+        let file = zip
+            .finish()?
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .context("无法写入归档文件")?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&partial.0, out_path)
+            .with_context(|| format!("无法保存归档文件: {:?}", out_path))?;
+        info!("数据归档导出完成，共 {} 个文件", manifest.entries.len());
+        Ok(manifest)
+    }
+
+    /// 导入归档
+    ///
+    /// 导入会替换数据库文件，调用方应在导入前关闭其他数据库连接，导入后重启应用。
+    /// 解压期间每个文件检查一次取消令牌，取消时删除暂存文件；开始替换后不再响应取消。
+    pub fn import_from(&self, path: &Path, cancel: &CancellationToken) -> Result<ArchiveManifest> {
+        info!("正在导入数据归档: {:?}", path);
+        let file = File::open(path).with_context(|| format!("无法打开归档文件: {:?}", path))?;
+        let mut zip = ZipArchive::new(BufReader::new(file))
+            .map_err(|e| invalid(format!("不是有效的归档文件: {}", e)))?;
+
+        let manifest = read_manifest(&mut zip)?;
+        let latest = schema::latest_version();
+        if manifest.schema_version > latest {
+            return Err(invalid(format!(
+                "归档来自更新版本的应用（数据库结构版本 {}，当前支持 {}），请先升级应用",
+                manifest.schema_version, latest
+            )));
+        }
+        check_entries(&mut zip, &manifest)?;
+
+        // 解压到各目标旁的暂存位置，保证之后的改名不跨文件系统
+        let staged_database = TempPath::new(sibling(&self.paths.database, "import"));
+        let staged_attachments = TempPath::new(sibling(&self.paths.attachments_dir, "import"));
+        let staged_config = TempPath::new(sibling(&self.paths.config_dir, "import"));
+        fs::create_dir_all(&staged_attachments.0)?;
+        fs::create_dir_all(&staged_config.0)?;
+
+        for entry in &manifest.entries {
+            cancel.check()?;
+            let target = if entry.path == DATABASE_ENTRY {
+                staged_database.0.clone()
+            } else if let Some(rel) = entry.path.strip_prefix(ATTACHMENTS_PREFIX) {
+                staged_attachments
+                    .0
+                    .join(relative_path(rel).unwrap_or_default())
+            } else if let Some(rel) = entry.path.strip_prefix(CONFIG_PREFIX) {
+                staged_config.0.join(relative_path(rel).unwrap_or_default())
+            } else {
+                return Err(invalid(format!("归档包含未知文件: {}", entry.path)));
+            };
+            extract_file(&mut zip, entry, &target)?;
+        }
+        check_database(&staged_database.0)?;
+        cancel.check()?;
+
+        let wal = append_suffix(&self.paths.database, "-wal");
+        let shm = append_suffix(&self.paths.database, "-shm");
+        let replaced = replace_all(&[
+            (
+                Some(staged_database.0.as_path()),
+                self.paths.database.as_path(),
+            ),
+            (None, wal.as_path()),
+            (None, shm.as_path()),
+            (
+                Some(staged_attachments.0.as_path()),
+                self.paths.attachments_dir.as_path(),
+            ),
+            (
+                Some(staged_config.0.as_path()),
+                self.paths.config_dir.as_path(),
+            ),
+        ])?;
+
+        if let Err(e) = migrate(&self.paths.database) {
+            warn!("导入后数据库迁移失败，已恢复原有数据: {}", e);
+            roll_back(&replaced);
+            return Err(e);
+        }
+        for r in &replaced {
+            if let Some(backup) = &r.backup {
+                if let Err(e) = remove_path(backup) {
+                    warn!("无法删除替换前的文件 {:?}: {}", backup, e);
+                }
+            }
+        }
+
+        info!(
+            "数据归档导入完成（结构版本 {} → {}）",
+            manifest.schema_version, latest
+        );
+        Ok(manifest)
+    }
+}
+
+#[async_trait]
+impl DataArchiveService for DataArchiver {
+    async fn export_archive(
+        &self,
+        out_path: &Path,
+        cancel: &CancellationToken,
+    ) -> CoreResult<ArchiveManifest> {
+        self.export_to(out_path, cancel).map_err(to_core)
+    }
+
+    async fn import_archive(
+        &self,
+        path: &Path,
+        cancel: &CancellationToken,
+    ) -> CoreResult<ArchiveManifest> {
+        self.import_from(path, cancel).map_err(to_core)
+    }
+}
+
+/// 写入时计算 SHA-256 的包装
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> (W, String) {
+        (self.inner, hex::encode(self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 离开作用域时删除的临时文件或目录
+pub(crate) struct TempPath(pub(crate) PathBuf);
+
+impl TempPath {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        // 清理上次中断留下的文件
+        let _ = remove_path(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = remove_path(&self.0);
+    }
+}
+
+/// 已替换的目标（用于回滚）
+struct Replaced {
+    target: PathBuf,
+    backup: Option<PathBuf>,
+    installed: bool,
+}
+
+fn entry_options(size: u64) -> FileOptions {
+    FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(size >= u64::from(u32::MAX))
+}
+
+/// 把文件流式写入归档
+fn add_file<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    path: &Path,
+) -> Result<ArchiveEntry> {
+    let mut file = File::open(path).with_context(|| format!("无法读取文件: {:?}", path))?;
+    let size = file.metadata()?.len();
+    zip.start_file(name, entry_options(size))?;
+    let mut writer = HashingWriter::new(&mut *zip);
+    let size = io::copy(&mut file, &mut writer)?;
+    let (_, sha256) = writer.finish();
+    Ok(ArchiveEntry {
+        path: name.to_string(),
+        size,
+        sha256,
+    })
+}
+
+/// 从归档解压一个文件并核对大小和校验和
+fn extract_file<R: io::Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    entry: &ArchiveEntry,
+    target: &Path,
+) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut source = zip
+        .by_name(&entry.path)
+        .map_err(|e| invalid(format!("归档缺少文件 {}: {}", entry.path, e)))?;
+    let file = File::create(target).with_context(|| format!("无法写入文件: {:?}", target))?;
+    let mut writer = HashingWriter::new(BufWriter::new(file));
+    let size = io::copy(&mut source, &mut writer).map_err(|e| {
+        invalid(format!(
+            "文件 {} 读取失败，归档可能已损坏: {}",
+            entry.path, e
+        ))
+    })?;
+    let (file, sha256) = writer.finish();
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    if size != entry.size || sha256 != entry.sha256 {
+        return Err(invalid(format!(
+            "文件 {} 校验失败，归档可能已损坏",
+            entry.path
+        )));
+    }
+    Ok(())
+}
+
+fn read_manifest<R: io::Read + Seek>(zip: &mut ZipArchive<R>) -> Result<ArchiveManifest> {
+    let file = zip
+        .by_name(MANIFEST_NAME)
+        .map_err(|_| invalid("归档缺少清单文件，可能不完整"))?;
+    let manifest: ArchiveManifest =
+        serde_json::from_reader(file).map_err(|e| invalid(format!("归档清单无法解析: {}", e)))?;
+    if manifest.format_version != ARCHIVE_FORMAT_VERSION {
+        return Err(invalid(format!(
+            "不支持的归档格式版本: {}",
+            manifest.format_version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// 核对归档中的文件与清单一致，且路径都落在预期的位置
+fn check_entries<R: io::Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    manifest: &ArchiveManifest,
+) -> Result<()> {
+    let mut listed = BTreeSet::new();
+    for entry in &manifest.entries {
+        let valid = entry.path == DATABASE_ENTRY
+            || [ATTACHMENTS_PREFIX, CONFIG_PREFIX].iter().any(|prefix| {
+                entry
+                    .path
+                    .strip_prefix(prefix)
+                    .and_then(relative_path)
+                    .is_some()
+            });
+        if !valid || !listed.insert(entry.path.as_str()) {
+            return Err(invalid(format!("归档清单包含无效路径: {}", entry.path)));
+        }
+    }
+    if !listed.contains(DATABASE_ENTRY) {
+        return Err(invalid("归档缺少数据库文件"));
+    }
+
+    let mut present = BTreeSet::new();
+    for i in 0..zip.len() {
+        let file = zip
+            .by_index(i)
+            .map_err(|e| invalid(format!("归档目录已损坏: {}", e)))?;
+        if !file.is_dir() && file.name() != MANIFEST_NAME {
+            present.insert(file.name().to_string());
+        }
+    }
+    if present
+        .iter()
+        .map(String::as_str)
+        .ne(listed.iter().copied())
+    {
+        return Err(invalid("归档内容与清单不一致，可能不完整"));
+    }
+    Ok(())
+}
+
+/// 检查暂存的数据库文件完整
+fn check_database(path: &Path) -> Result<()> {
+    let conn = rusqlite::Connection::open(path)?;
+    let result: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| invalid(format!("归档中的数据库无法打开: {}", e)))?;
+    if result != "ok" {
+        return Err(invalid(format!("归档中的数据库已损坏: {}", result)));
+    }
+    Ok(())
+}
+
+/// 对导入后的数据库执行待应用的迁移
+fn migrate(database: &Path) -> Result<()> {
+    let pool = DatabasePoolBuilder::new(database.to_string_lossy())
+        .max_connections(1)
+        .build()?;
+    MigrationManager::new(DatabaseConnection::new(pool))
+        .add_migrations(schema::builtin_migrations())
+        .migrate(None)
+        .context("导入后数据库迁移失败")
+}
+
+/// 依次用暂存文件替换目标（`None` 表示只移走目标），任一步失败时回滚已完成的替换
+fn replace_all(replacements: &[(Option<&Path>, &Path)]) -> Result<Vec<Replaced>> {
+    let mut done = Vec::new();
+    for (staged, target) in replacements {
+        if let Err(e) = replace_one(*staged, target, &mut done) {
+            roll_back(&done);
+            return Err(e);
+        }
+    }
+    Ok(done)
+}
+
+fn replace_one(staged: Option<&Path>, target: &Path, done: &mut Vec<Replaced>) -> Result<()> {
+    let backup = if target.exists() {
+        let backup = sibling(target, "replaced");
+        let _ = remove_path(&backup);
+        fs::rename(target, &backup).with_context(|| format!("无法移走原有文件: {:?}", target))?;
+        Some(backup)
+    } else {
+        None
+    };
+    done.push(Replaced {
+        target: target.to_path_buf(),
+        backup,
+        installed: false,
+    });
+    if let Some(staged) = staged {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(staged, target).with_context(|| format!("无法替换文件: {:?}", target))?;
+        if let Some(last) = done.last_mut() {
+            last.installed = true;
+        }
+    }
+    Ok(())
+}
+
+fn roll_back(done: &[Replaced]) {
+    for r in done.iter().rev() {
+        if r.installed {
+            let _ = remove_path(&r.target);
+        }
+        if let Some(backup) = &r.backup {
+            if let Err(e) = fs::rename(backup, &r.target) {
+                warn!("无法恢复原有文件 {:?}: {}", r.target, e);
+            }
+        }
+    }
+}
+
+/// 目录下的全部文件（相对路径以 `/` 分隔，按路径排序）；目录不存在时为空
+fn list_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    if dir.is_dir() {
+        collect_files(dir, "", &mut files)?;
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("无法读取目录: {:?}", dir))? {
+        let entry = entry?;
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|name| anyhow::anyhow!("文件名不是有效的UTF-8: {:?}", name))?;
+        let path = entry.path();
+        let rel = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, &format!("{}/", rel), files)?;
+        } else {
+            files.push((rel, path));
+        }
+    }
+    Ok(())
+}
+
+/// 把归档内的相对路径转换为本地路径，拒绝绝对路径和 `..`
+fn relative_path(rel: &str) -> Option<PathBuf> {
+    if rel.is_empty() || rel.contains('\\') {
+        return None;
+    }
+    let path = PathBuf::from(rel);
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_)))
+        .then_some(path)
+}
+
+/// 与 `path` 同目录的临时路径
+pub(crate) fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+fn append_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    let result = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbUuid;
+    use chrono::Utc;
+    use rusqlite::params;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    struct TestContext {
+        _dir: TempDir,
+        paths: ArchivePaths,
+        archiver: DataArchiver,
+    }
+
+    fn create_context() -> TestContext {
+        let dir = TempDir::new().unwrap();
+        let paths = ArchivePaths {
+            database: dir.path().join("data").join("minicrm.db"),
+            attachments_dir: dir.path().join("data").join("attachments"),
+            config_dir: dir.path().join("config"),
+        };
+        fs::create_dir_all(paths.database.parent().unwrap()).unwrap();
+        let pool = DatabasePoolBuilder::new(paths.database.to_string_lossy())
+            .build()
+            .unwrap();
+        let connection = DatabaseConnection::new(pool);
+        MigrationManager::new(connection.clone())
+            .add_migrations(schema::builtin_migrations())
+            .migrate(None)
+            .unwrap();
+        let archiver = DataArchiver::new(connection, paths.clone());
+        TestContext {
+            _dir: dir,
+            paths,
+            archiver,
+        }
+    }
+
+    fn seed(context: &TestContext) {
+        let now = Utc::now().to_rfc3339();
+        for name in ["华东板材", "宏达家具", "木立方"] {
+            context
+                .archiver
+                .connection
+                .execute(
+                    "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                    params![DbUuid(Uuid::new_v4()), name, now],
+                )
+                .unwrap();
+        }
+        let nested = context.paths.attachments_dir.join("quotes");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("Q-001.pdf"), b"%PDF-1.4 quote").unwrap();
+        fs::write(
+            context.paths.attachments_dir.join("logo.png"),
+            [0u8, 1, 2, 3],
+        )
+        .unwrap();
+        fs::create_dir_all(&context.paths.config_dir).unwrap();
+        fs::write(context.paths.config_dir.join("ui-state.json"), b"{}").unwrap();
+    }
+
+    fn customer_count(database: &Path) -> i64 {
+        rusqlite::Connection::open(database)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM customers", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn hashes(dir: &Path) -> Vec<(String, String)> {
+        list_files(dir)
+            .unwrap()
+            .into_iter()
+            .map(|(name, path)| (name, hex::encode(Sha256::digest(fs::read(path).unwrap()))))
+            .collect()
+    }
+
+    /// 复制归档，按需修改清单或某个文件的内容
+    fn rewrite_archive(
+        source: &Path,
+        target: &Path,
+        edit_manifest: impl Fn(&mut ArchiveManifest),
+        edit_file: Option<(&str, &[u8])>,
+    ) {
+        let mut zip = ZipArchive::new(File::open(source).unwrap()).unwrap();
+        let mut manifest = read_manifest(&mut zip).unwrap();
+        edit_manifest(&mut manifest);
+        let mut out = ZipWriter::new(File::create(target).unwrap());
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).unwrap();
+            let name = file.name().to_string();
+            if name == MANIFEST_NAME {
+                continue;
+            }
+            out.start_file(name.as_str(), entry_options(0)).unwrap();
+            match edit_file {
+                Some((path, content)) if path == name => out.write_all(content).unwrap(),
+                _ => {
+                    io::copy(&mut file, &mut out).unwrap();
+                }
+            }
+        }
+        out.start_file(MANIFEST_NAME, entry_options(0)).unwrap();
+        serde_json::to_writer(&mut out, &manifest).unwrap();
+        out.finish().unwrap();
+    }
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let source = create_context();
+        seed(&source);
+        let out_dir = TempDir::new().unwrap();
+        let archive = out_dir.path().join(format!("backup.{}", ARCHIVE_EXTENSION));
+
+        let exported = source
+            .archiver
+            .export_to(&archive, &CancellationToken::new())
+            .unwrap();
+        assert_eq!(exported.schema_version, schema::latest_version());
+        assert_eq!(exported.entries.len(), 4);
+        assert!(!sibling(&archive, "partial").exists());
+
+        let target = create_context();
+        let imported = target
+            .archiver
+            .import_from(&archive, &CancellationToken::new())
+            .unwrap();
+        assert_eq!(imported, exported);
+
+        assert_eq!(customer_count(&target.paths.database), 3);
+        assert_eq!(
+            hashes(&target.paths.attachments_dir),
+            hashes(&source.paths.attachments_dir)
+        );
+        assert_eq!(
+            hashes(&target.paths.config_dir),
+            hashes(&source.paths.config_dir)
+        );
+        let pool = DatabasePoolBuilder::new(target.paths.database.to_string_lossy())
+            .build()
+            .unwrap();
+        let version = MigrationManager::new(DatabaseConnection::new(pool))
+            .get_current_version()
+            .unwrap();
+        assert_eq!(version, schema::latest_version());
+    }
+
+    #[test]
+    fn test_corrupt_archive_overwrites_nothing() {
+        let source = create_context();
+        seed(&source);
+        let out_dir = TempDir::new().unwrap();
+        let archive = out_dir.path().join("backup.minicrm.zip");
+        source
+            .archiver
+            .export_to(&archive, &CancellationToken::new())
+            .unwrap();
+
+        let target = create_context();
+        fs::create_dir_all(&target.paths.attachments_dir).unwrap();
+        fs::write(target.paths.attachments_dir.join("keep.txt"), b"keep").unwrap();
+        let before = hashes(&target.paths.attachments_dir);
+
+        // 截断的归档
+        let truncated = out_dir.path().join("truncated.minicrm.zip");
+        let bytes = fs::read(&archive).unwrap();
+        fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        // 内容被篡改的附件
+        let tampered = out_dir.path().join("tampered.minicrm.zip");
+        rewrite_archive(
+            &archive,
+            &tampered,
+            |_| {},
+            Some(("attachments/logo.png", b"not a logo")),
+        );
+        // 清单中列出但归档里缺失的文件
+        let incomplete = out_dir.path().join("incomplete.minicrm.zip");
+        rewrite_archive(
+            &archive,
+            &incomplete,
+            |m| {
+                m.entries.push(ArchiveEntry {
+                    path: "attachments/missing.pdf".to_string(),
+                    size: 1,
+                    sha256: String::new(),
+                })
+            },
+            None,
+        );
+
+        for path in [&truncated, &tampered, &incomplete] {
+            let result = target
+                .archiver
+                .import_from(path, &CancellationToken::new())
+                .map_err(to_core);
+            assert!(
+                matches!(result, Err(CoreError::Validation(_))),
+                "{:?}: {:?}",
+                path,
+                result
+            );
+            assert_eq!(hashes(&target.paths.attachments_dir), before);
+            assert_eq!(customer_count(&target.paths.database), 0);
+        }
+    }
+
+    #[test]
+    fn test_newer_schema_version_rejected() {
+        let source = create_context();
+        let out_dir = TempDir::new().unwrap();
+        let archive = out_dir.path().join("backup.minicrm.zip");
+        source
+            .archiver
+            .export_to(&archive, &CancellationToken::new())
+            .unwrap();
+        let newer = out_dir.path().join("newer.minicrm.zip");
+        rewrite_archive(
+            &archive,
+            &newer,
+            |m| m.schema_version = schema::latest_version() + 1,
+            None,
+        );
+
+        let target = create_context();
+        let result = target
+            .archiver
+            .import_from(&newer, &CancellationToken::new())
+            .map_err(to_core);
+        assert!(matches!(result, Err(CoreError::Validation(ref m)) if m.contains("更新版本")));
+        // 归档内路径不能指向目标目录之外
+        assert_eq!(relative_path("../evil"), None);
+        assert_eq!(relative_path("/etc/passwd"), None);
+        assert!(relative_path("quotes/Q-001.pdf").is_some());
+    }
+
+    #[test]
+    fn test_cancelled_export_and_import_leave_no_files() {
+        let source = create_context();
+        seed(&source);
+        let out_dir = TempDir::new().unwrap();
+        let archive = out_dir.path().join("backup.minicrm.zip");
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = source.archiver.export_to(&archive, &cancel).map_err(to_core);
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        assert!(!archive.exists());
+        assert!(!sibling(&archive, "partial").exists());
+        assert!(!sibling(&archive, "snapshot.db").exists());
+
+        source
+            .archiver
+            .export_to(&archive, &CancellationToken::new())
+            .unwrap();
+        let target = create_context();
+        let result = target.archiver.import_from(&archive, &cancel).map_err(to_core);
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        assert_eq!(customer_count(&target.paths.database), 0);
+        assert!(!sibling(&target.paths.database, "import").exists());
+        assert!(!sibling(&target.paths.attachments_dir, "import").exists());
+    }
+}
@@ -0,0 +1,828 @@
+//! 附件存储模块
+//!
+//! 附件按内容的SHA-256哈希保存在附件目录下（`<hash>`），相同内容只保存一份。
+//! 写入时先写到暂存目录（[`STAGING_DIR`]），写完再改名到位，附件目录下不会出现写了
+//! 一半的文件；崩溃后残留在暂存目录中的文件由存储清理任务删除。
+//! 图片附件在首次请求缩略图时生成 `<hash>.thumb.jpg` 并缓存在原文件旁，
+//! 生成前按EXIF方向校正，手机照片不会横着显示；删除附件时缩略图一并删除。
+//! 损坏或尺寸过大的图片不生成缩略图，界面显示通用图标。
+//!
+//! 上传时按文件头识别实际类型：可执行文件一律拒绝，扩展名不在允许列表中的须管理员放行，
+//! 声明类型与实际类型不符的照常保存但标记出来。打开附件时先以清理后的文件名复制到
+//! 打开目录，再交给系统默认程序，不直接使用原始文件名拼接路径。
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageReader, Limits};
+use minicrm_core::{AttachmentPreviewService, CoreError, CoreResult, DecodedImage, UserRole};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::repository::support::to_core;
+
+/// 缩略图最长边（像素）
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// 缩略图文件后缀
+pub const THUMBNAIL_SUFFIX: &str = ".thumb.jpg";
+
+/// 附件目录下的暂存目录，写入中的附件先保存在这里
+pub const STAGING_DIR: &str = ".incoming";
+
+/// 缩略图JPEG质量
+const THUMBNAIL_QUALITY: u8 = 85;
+
+/// 默认允许解码的最大边长（像素）
+const DEFAULT_MAX_DIMENSION: u32 = 12_000;
+
+/// 默认允许解码的最大文件大小
+const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 支持预览的图片类型
+const PREVIEW_MIME_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/jpg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+];
+
+/// 默认允许上传的扩展名（图片、PDF、Office文档、纯文本）
+pub const DEFAULT_ALLOWED_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "bmp", "pdf", "doc", "docx", "xls", "xlsx", "ppt",
+    "pptx", "txt",
+];
+
+/// 默认单个附件的大小上限
+pub const DEFAULT_MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
+
+/// 一律拒绝的可执行文件和脚本扩展名，管理员也不能放行
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "com", "scr", "pif", "cpl", "msi", "msp", "bat", "cmd", "ps1", "vbs", "vbe",
+    "js", "jse", "wsf", "wsh", "hta", "lnk", "reg", "jar", "sh", "app", "apk", "so", "dylib",
+];
+
+/// Windows保留的设备名，用作文件名时无法打开
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// 清理后文件名的最大字符数
+const MAX_FILE_NAME_CHARS: usize = 120;
+
+/// 系统默认程序的启动命令
+#[cfg(target_os = "windows")]
+pub(crate) const OPENER: &str = "explorer";
+#[cfg(target_os = "macos")]
+pub(crate) const OPENER: &str = "open";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub(crate) const OPENER: &str = "xdg-open";
+
+/// 是否为可生成缩略图的图片类型
+pub fn is_previewable(mime: &str) -> bool {
+    let mime = mime.split(';').next().unwrap_or_default().trim();
+    PREVIEW_MIME_TYPES
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(mime))
+}
+
+/// 附件上传策略
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentPolicy {
+    /// 允许的扩展名（小写，不含点）
+    pub allowed_extensions: Vec<String>,
+    /// 单个附件的大小上限（字节）
+    pub max_upload_bytes: u64,
+}
+
+impl Default for AttachmentPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_extensions: DEFAULT_ALLOWED_EXTENSIONS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            max_upload_bytes: DEFAULT_MAX_UPLOAD_BYTES,
+        }
+    }
+}
+
+impl AttachmentPolicy {
+    /// 扩展名是否在允许列表中
+    pub fn allows(&self, extension: &str) -> bool {
+        self.allowed_extensions
+            .iter()
+            .any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+}
+
+/// 已保存的附件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredAttachment {
+    /// 内容哈希
+    pub hash: String,
+    /// 清理后的文件名
+    pub file_name: String,
+    /// 上传时声明的MIME类型
+    pub declared_mime: String,
+    /// 按文件头识别的MIME类型，无法识别（如纯文本）时为空
+    pub sniffed_mime: Option<String>,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 声明类型与识别类型不符
+    pub mime_mismatch: bool,
+}
+
+impl StoredAttachment {
+    /// 预览使用的MIME类型，能识别时以文件内容为准
+    pub fn mime(&self) -> &str {
+        self.sniffed_mime.as_deref().unwrap_or(&self.declared_mime)
+    }
+}
+
+/// 附件存储
+#[derive(Debug, Clone)]
+pub struct AttachmentStore {
+    root: PathBuf,
+    max_dimension: u32,
+    max_bytes: u64,
+    policy: AttachmentPolicy,
+    open_dir: PathBuf,
+}
+
+impl AttachmentStore {
+    /// 创建附件存储（目录在首次写入时创建）
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            max_dimension: DEFAULT_MAX_DIMENSION,
+            max_bytes: DEFAULT_MAX_BYTES,
+            policy: AttachmentPolicy::default(),
+            open_dir: std::env::temp_dir().join("minicrm-attachments"),
+        }
+    }
+
+    /// 设置上传策略
+    pub fn with_policy(mut self, policy: AttachmentPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 设置打开附件时存放副本的目录（默认在系统临时目录下）
+    pub fn with_open_dir(mut self, open_dir: impl Into<PathBuf>) -> Self {
+        self.open_dir = open_dir.into();
+        self
+    }
+
+    /// 设置允许解码的最大边长，超过时按无法预览处理
+    pub fn with_max_dimension(mut self, max_dimension: u32) -> Self {
+        self.max_dimension = max_dimension;
+        self
+    }
+
+    /// 设置允许解码的最大文件大小，超过时按无法预览处理
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// 附件目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 暂存目录
+    pub fn staging_dir(&self) -> PathBuf {
+        self.root.join(STAGING_DIR)
+    }
+
+    /// 打开附件时存放副本的目录
+    pub fn open_dir(&self) -> &Path {
+        &self.open_dir
+    }
+
+    /// 保存附件内容，返回内容哈希
+    pub fn put(&self, content: &[u8]) -> Result<String> {
+        let hash = hex::encode(Sha256::digest(content));
+        let path = self.root.join(&hash);
+        if path.exists() {
+            return Ok(hash);
+        }
+        let staging = self.staging_dir();
+        fs::create_dir_all(&staging)
+            .with_context(|| format!("无法创建附件目录: {:?}", staging))?;
+        let partial = staging.join(format!("{}.{}", hash, Uuid::new_v4().simple()));
+        write_via(&partial, &path, |file| file.write_all(content))
+            .with_context(|| format!("无法写入附件: {:?}", path))?;
+        Ok(hash)
+    }
+
+    /// 校验并保存上传的附件
+    ///
+    /// 超过大小上限或是可执行文件时一律拒绝；扩展名不在允许列表中时，只有传入
+    /// `allowlist_override` 且角色为管理员才放行。声明类型与文件头不符时照常保存，
+    /// 在返回结果中标记。
+    pub fn ingest(
+        &self,
+        file_name: &str,
+        declared_mime: &str,
+        content: &[u8],
+        allowlist_override: Option<UserRole>,
+    ) -> Result<StoredAttachment> {
+        let file_name = sanitize_file_name(file_name);
+        let size = content.len() as u64;
+        if size > self.policy.max_upload_bytes {
+            return Err(CoreError::validation(format!(
+                "附件“{}”过大：{} 字节，上限 {} 字节",
+                file_name, size, self.policy.max_upload_bytes
+            ))
+            .into());
+        }
+
+        let extension = extension_of(&file_name);
+        let sniffed = infer::get(content);
+        let is_executable = EXECUTABLE_EXTENSIONS.contains(&extension.as_str())
+            || sniffed.as_ref().is_some_and(|kind| kind.matcher_type() == infer::MatcherType::App)
+            || content.starts_with(b"#!");
+        if is_executable {
+            return Err(
+                CoreError::validation(format!("不允许上传可执行文件：“{}”", file_name)).into(),
+            );
+        }
+
+        if !self.policy.allows(&extension) {
+            match allowlist_override {
+                Some(role) if role.satisfies(UserRole::Admin) => {
+                    warn!("管理员放行了不在允许列表中的附件: {}", file_name);
+                }
+                Some(_) => {
+                    return Err(CoreError::permission("只有管理员可以放行该类型的附件").into());
+                }
+                None => {
+                    return Err(CoreError::validation(format!(
+                        "不支持的附件类型：“{}”",
+                        file_name
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        let sniffed_mime = sniffed.map(|kind| kind.mime_type().to_string());
+        let mime_mismatch = sniffed_mime
+            .as_deref()
+            .is_some_and(|sniffed| !mime_matches(declared_mime, sniffed));
+        if mime_mismatch {
+            warn!(
+                "附件“{}”声明为 {}，实际为 {}",
+                file_name,
+                declared_mime,
+                sniffed_mime.as_deref().unwrap_or_default()
+            );
+        }
+
+        Ok(StoredAttachment {
+            hash: self.put(content)?,
+            file_name,
+            declared_mime: declared_mime.to_string(),
+            sniffed_mime,
+            size,
+            mime_mismatch,
+        })
+    }
+
+    /// 以清理后的文件名在打开目录中准备附件副本，返回副本路径
+    ///
+    /// 每个附件一个子目录，同名不同内容的附件互不覆盖。
+    pub fn open_path(&self, hash: &str, file_name: &str) -> Result<PathBuf> {
+        let file_name = sanitize_file_name(file_name);
+        if EXECUTABLE_EXTENSIONS.contains(&extension_of(&file_name).as_str()) {
+            return Err(
+                CoreError::validation(format!("不允许打开可执行文件：“{}”", file_name)).into(),
+            );
+        }
+        let source = self.blob_path(hash)?;
+        if !source.exists() {
+            return Err(CoreError::not_found(format!("附件 {}", hash)).into());
+        }
+        let dir = self.open_dir.join(hash);
+        fs::create_dir_all(&dir).with_context(|| format!("无法创建目录: {:?}", dir))?;
+        let target = dir.join(file_name);
+        if !target.exists() {
+            let content = self.read(hash)?;
+            write_atomically(&target, |file| file.write_all(&content))
+                .with_context(|| format!("无法写入附件副本: {:?}", target))?;
+        }
+        Ok(target)
+    }
+
+    /// 用系统默认程序打开附件
+    pub fn open_attachment(&self, hash: &str, file_name: &str) -> Result<PathBuf> {
+        let path = self.open_path(hash, file_name)?;
+        let mut child = Command::new(OPENER)
+            .arg(&path)
+            .spawn()
+            .with_context(|| format!("无法打开附件: {:?}", path))?;
+        // 回收子进程，避免留下僵尸进程
+        std::thread::spawn(move || child.wait());
+        Ok(path)
+    }
+
+    /// 附件文件路径
+    pub fn blob_path(&self, hash: &str) -> Result<PathBuf> {
+        Ok(self.root.join(checked(hash)?))
+    }
+
+    /// 缩略图文件路径（不保证已生成）
+    pub fn thumbnail_path(&self, hash: &str) -> Result<PathBuf> {
+        Ok(self
+            .root
+            .join(format!("{}{}", checked(hash)?, THUMBNAIL_SUFFIX)))
+    }
+
+    /// 读取附件内容
+    pub fn read(&self, hash: &str) -> Result<Vec<u8>> {
+        let path = self.blob_path(hash)?;
+        match fs::read(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Err(CoreError::not_found(format!("附件 {}", hash)).into())
+            }
+            other => other.with_context(|| format!("无法读取附件: {:?}", path)),
+        }
+    }
+
+    /// 删除附件及其缩略图，附件不存在时返回 `false`
+    pub fn delete(&self, hash: &str) -> Result<bool> {
+        remove_if_exists(&self.thumbnail_path(hash)?)?;
+        remove_if_exists(&self.blob_path(hash)?)
+    }
+
+    /// 获取缩略图路径，首次请求时生成
+    ///
+    /// 非图片类型、损坏或尺寸过大的图片返回 `None`。
+    pub fn ensure_thumbnail(&self, hash: &str, mime: &str) -> Result<Option<PathBuf>> {
+        let thumbnail = self.thumbnail_path(hash)?;
+        if thumbnail.exists() {
+            return Ok(Some(thumbnail));
+        }
+        if !is_previewable(mime) {
+            return Ok(None);
+        }
+
+        let source = self.blob_path(hash)?;
+        if !source.exists() {
+            return Err(CoreError::not_found(format!("附件 {}", hash)).into());
+        }
+        let Some(image) = self.decode(&source) else {
+            return Ok(None);
+        };
+        let image = if image.width().max(image.height()) > THUMBNAIL_SIZE {
+            image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        } else {
+            image
+        };
+        let rgb = image.to_rgb8();
+        write_atomically(&thumbnail, |file| {
+            rgb.write_with_encoder(JpegEncoder::new_with_quality(file, THUMBNAIL_QUALITY))
+                .map_err(io::Error::other)
+        })
+        .with_context(|| format!("无法写入缩略图: {:?}", thumbnail))?;
+        debug!("已生成缩略图: {:?}", thumbnail);
+        Ok(Some(thumbnail))
+    }
+
+    /// 解码图片并按EXIF方向校正，失败时记录日志并返回 `None`
+    fn decode(&self, path: &Path) -> Option<DynamicImage> {
+        match self.try_decode(path) {
+            Ok(image) => Some(image),
+            Err(e) => {
+                warn!("无法解码图片 {:?}: {:#}", path, e);
+                None
+            }
+        }
+    }
+
+    fn try_decode(&self, path: &Path) -> Result<DynamicImage> {
+        let size = fs::metadata(path)?.len();
+        if size > self.max_bytes {
+            anyhow::bail!("文件过大（{} 字节）", size);
+        }
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_dimension);
+        limits.max_image_height = Some(self.max_dimension);
+
+        let mut reader = ImageReader::open(path)?.with_guessed_format()?;
+        reader.limits(limits);
+        let mut decoder = reader.into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        Ok(image)
+    }
+}
+
+impl AttachmentPreviewService for AttachmentStore {
+    fn thumbnail(&self, hash: &str, mime: &str) -> CoreResult<Option<DecodedImage>> {
+        let Some(path) = self.ensure_thumbnail(hash, mime).map_err(to_core)? else {
+            return Ok(None);
+        };
+        Ok(self.decode(&path).map(into_decoded))
+    }
+
+    fn full_image(&self, hash: &str, mime: &str) -> CoreResult<Option<DecodedImage>> {
+        if !is_previewable(mime) {
+            return Ok(None);
+        }
+        let path = self.blob_path(hash).map_err(to_core)?;
+        if !path.exists() {
+            return Err(CoreError::not_found(format!("附件 {}", hash)));
+        }
+        Ok(self.decode(&path).map(into_decoded))
+    }
+}
+
+fn into_decoded(image: DynamicImage) -> DecodedImage {
+    let rgba = image.into_rgba8();
+    DecodedImage {
+        width: rgba.width(),
+        height: rgba.height(),
+        rgba: rgba.into_raw(),
+    }
+}
+
+/// 清理文件名：去掉路径分隔符和控制字符，替换Windows不允许的字符，
+/// 去掉首尾的点和空格；结果为空时使用“附件”
+pub fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .filter(|c| !c.is_control() && *c != '/' && *c != '\\')
+        .map(|c| match c {
+            ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            other => other,
+        })
+        .collect();
+    let trimmed = cleaned.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    let mut name: String = if trimmed.chars().count() > MAX_FILE_NAME_CHARS {
+        // 超长时保留扩展名
+        let extension = extension_of(trimmed);
+        let keep = MAX_FILE_NAME_CHARS.saturating_sub(extension.chars().count() + 1);
+        let stem: String = trimmed.chars().take(keep).collect();
+        format!("{}.{}", stem.trim_end_matches('.'), extension)
+    } else {
+        trimmed.to_string()
+    };
+    if name.is_empty() {
+        name = "附件".to_string();
+    }
+    let stem = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// 小写的扩展名，没有时为空
+fn extension_of(file_name: &str) -> String {
+    Path::new(file_name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+/// 声明的MIME类型与识别结果是否一致
+///
+/// 未声明或声明为通用二进制时不比较；Office文档按容器格式（ZIP、OLE）识别时视为一致。
+fn mime_matches(declared: &str, sniffed: &str) -> bool {
+    let normalize = |mime: &str| {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        match mime.to_ascii_lowercase().as_str() {
+            "image/jpg" | "image/pjpeg" => "image/jpeg".to_string(),
+            other => other.to_string(),
+        }
+    };
+    let declared = normalize(declared);
+    let sniffed = normalize(sniffed);
+    if declared.is_empty() || declared == "application/octet-stream" || declared == sniffed {
+        return true;
+    }
+    match sniffed.as_str() {
+        "application/zip" => {
+            declared.starts_with("application/vnd.openxmlformats-officedocument.")
+        }
+        "application/x-ole-storage" | "application/msword" | "application/vnd.ms-excel"
+        | "application/vnd.ms-powerpoint" => {
+            declared == "application/msword" || declared.starts_with("application/vnd.ms-")
+        }
+        _ => false,
+    }
+}
+
+/// 校验哈希格式，避免拼接出附件目录以外的路径
+fn checked(hash: &str) -> Result<&str> {
+    if hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(hash)
+    } else {
+        Err(CoreError::validation(format!("无效的附件标识: {}", hash)).into())
+    }
+}
+
+/// 先写入同目录的临时文件，完成后再改名，避免留下写了一半的文件
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    write_via(Path::new(&partial), path, write)
+}
+
+/// 先写入 `partial`，完成后改名为 `path`（两者须在同一文件系统）
+fn write_via(
+    partial: &Path,
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let result = File::create(partial).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+    });
+    match result.and_then(|()| fs::rename(partial, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(partial);
+            Err(e)
+        }
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("无法删除文件: {:?}", path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use tempfile::TempDir;
+
+    const RED: Rgb<u8> = Rgb([220, 20, 20]);
+    const BLUE: Rgb<u8> = Rgb([20, 20, 220]);
+
+    /// 左半红、右半蓝的JPEG，并写入指定的EXIF方向
+    fn photo(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, _| if x < width / 2 { RED } else { BLUE });
+        let mut jpeg = Vec::new();
+        image
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, 95))
+            .unwrap();
+
+        // APP1: "Exif\0\0" + 大端TIFF头 + 只含方向标签(0x0112)的IFD0
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let length = u16::try_from(exif.len() + 2).unwrap();
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&length.to_be_bytes());
+        segment.extend_from_slice(&exif);
+        jpeg.splice(2..2, segment);
+        jpeg
+    }
+
+    fn is_red(image: &DecodedImage, x: u32, y: u32) -> bool {
+        let offset = ((y * image.width + x) * 4) as usize;
+        let pixel = &image.rgba[offset..offset + 3];
+        pixel[0] > 150 && pixel[2] < 100
+    }
+
+    #[test]
+    fn test_exif_orientation_is_applied() {
+        let dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path());
+        // 方向6：需顺时针旋转90°，原图左侧转到上方
+        let hash = store.put(&photo(600, 300, 6)).unwrap();
+
+        let full = store.full_image(&hash, "image/jpeg").unwrap().unwrap();
+        assert_eq!((full.width, full.height), (300, 600));
+        assert!(is_red(&full, 150, 50));
+        assert!(!is_red(&full, 150, 550));
+
+        let thumbnail = store.thumbnail(&hash, "image/jpeg").unwrap().unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (128, 256));
+        assert!(is_red(&thumbnail, 64, 20));
+    }
+
+    #[test]
+    fn test_thumbnail_generated_lazily_and_cached() {
+        let dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path());
+        let hash = store.put(&photo(400, 200, 1)).unwrap();
+        let thumbnail_path = store.thumbnail_path(&hash).unwrap();
+        assert!(!thumbnail_path.exists());
+
+        let first = store.thumbnail(&hash, "image/jpeg").unwrap().unwrap();
+        assert!(thumbnail_path.exists());
+        assert_eq!((first.width, first.height), (256, 128));
+
+        // 第二次直接读取缓存，不再解码原图
+        fs::write(store.blob_path(&hash).unwrap(), b"overwritten").unwrap();
+        let second = store.thumbnail(&hash, "image/jpeg").unwrap().unwrap();
+        assert_eq!((second.width, second.height), (256, 128));
+
+        // 小图不放大
+        let small = store.put(&photo(40, 20, 1)).unwrap();
+        let small = store.thumbnail(&small, "image/jpeg").unwrap().unwrap();
+        assert_eq!((small.width, small.height), (40, 20));
+    }
+
+    #[test]
+    fn test_corrupt_and_oversized_images_fall_back() {
+        let dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path()).with_max_dimension(500);
+
+        let corrupt = store.put(b"\xFF\xD8\xFF\xE0 not really a jpeg").unwrap();
+        assert_eq!(store.thumbnail(&corrupt, "image/jpeg").unwrap(), None);
+        assert!(!store.thumbnail_path(&corrupt).unwrap().exists());
+        assert_eq!(store.full_image(&corrupt, "image/jpeg").unwrap(), None);
+
+        let oversized = store.put(&photo(600, 300, 1)).unwrap();
+        assert_eq!(store.thumbnail(&oversized, "image/jpeg").unwrap(), None);
+        assert_eq!(store.full_image(&oversized, "image/jpeg").unwrap(), None);
+
+        let document = store.put(b"%PDF-1.7").unwrap();
+        assert_eq!(store.thumbnail(&document, "application/pdf").unwrap(), None);
+
+        assert!(matches!(
+            store.thumbnail("../../etc/passwd", "image/jpeg"),
+            Err(CoreError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_delete_removes_thumbnail() {
+        let dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path());
+        let hash = store.put(&photo(400, 200, 1)).unwrap();
+        store
+            .ensure_thumbnail(&hash, "image/jpeg")
+            .unwrap()
+            .unwrap();
+
+        assert!(store.delete(&hash).unwrap());
+        assert!(!store.blob_path(&hash).unwrap().exists());
+        assert!(!store.thumbnail_path(&hash).unwrap().exists());
+        assert!(!store.delete(&hash).unwrap());
+        assert_eq!(stored_files(dir.path()), 0);
+    }
+
+    /// 附件目录下的文件数（不含暂存目录）
+    fn stored_files(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name() != STAGING_DIR)
+            .count()
+    }
+
+    fn rejection(result: Result<StoredAttachment>) -> CoreError {
+        to_core(result.unwrap_err())
+    }
+
+    fn png() -> Vec<u8> {
+        let mut png = Vec::new();
+        RgbImage::from_pixel(4, 4, RED)
+            .write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn test_ingest_flags_sniff_mismatch() {
+        let dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path());
+
+        // 图片改名为PDF：照常保存，标记不符，预览按实际类型
+        let disguised = store
+            .ingest("报价单.pdf", "application/pdf", &png(), None)
+            .unwrap();
+        assert!(disguised.mime_mismatch);
+        assert_eq!(disguised.sniffed_mime.as_deref(), Some("image/png"));
+        assert_eq!(disguised.mime(), "image/png");
+        assert!(store.blob_path(&disguised.hash).unwrap().exists());
+
+        let jpeg = store
+            .ingest("现场.jpg", "image/jpg", &photo(40, 20, 1), None)
+            .unwrap();
+        assert!(!jpeg.mime_mismatch);
+
+        // 纯文本无法按文件头识别，不算不符
+        let note = store
+            .ingest("备注.txt", "text/plain", "板材规格：1220×2440".as_bytes(), None)
+            .unwrap();
+        assert_eq!(note.sniffed_mime, None);
+        assert!(!note.mime_mismatch);
+        assert_eq!(note.mime(), "text/plain");
+    }
+
+    #[test]
+    fn test_ingest_hard_rejects() {
+        let dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path()).with_policy(AttachmentPolicy {
+            max_upload_bytes: 1024,
+            ..AttachmentPolicy::default()
+        });
+        let admin = Some(UserRole::Admin);
+
+        let mut elf = b"\x7FELF\x02\x01\x01".to_vec();
+        elf.resize(64, 0);
+        let cases: [(&str, &[u8]); 4] = [
+            ("setup.exe", b"just text"),
+            ("合同.pdf", b"MZ\x90\0\x03\0\0\0\x04\0\0\0"),
+            ("说明.txt", &elf),
+            ("清单.txt", b"#!/bin/sh\nrm -rf ~\n"),
+        ];
+        for (name, content) in cases {
+            let err = rejection(store.ingest(name, "application/pdf", content, admin));
+            assert!(matches!(err, CoreError::Validation(_)), "{name}: {err}");
+            assert!(err.to_string().contains("可执行文件"), "{name}: {err}");
+        }
+
+        let err = rejection(store.ingest("扫描.pdf", "application/pdf", &[b'%'; 1025], admin));
+        assert!(err.to_string().contains("过大"), "{err}");
+        assert!(store.ingest("扫描.pdf", "application/pdf", &[b'%'; 1024], None).is_ok());
+
+        // 被拒绝的内容不落盘
+        assert_eq!(stored_files(dir.path()), 1);
+    }
+
+    #[test]
+    fn test_allowlist_override_requires_admin() {
+        let dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path());
+        let drawing = b"AC1032 drawing";
+
+        let err = rejection(store.ingest("柜体.dwg", "image/vnd.dwg", drawing, None));
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+        let sales = Some(UserRole::Sales);
+        let err = rejection(store.ingest("柜体.dwg", "image/vnd.dwg", drawing, sales));
+        assert!(matches!(err, CoreError::Permission(_)), "{err}");
+
+        let stored = store
+            .ingest("柜体.dwg", "image/vnd.dwg", drawing, Some(UserRole::Admin))
+            .unwrap();
+        assert_eq!(stored.file_name, "柜体.dwg");
+        assert_eq!(store.read(&stored.hash).unwrap(), drawing);
+
+        let custom = AttachmentStore::new(dir.path()).with_policy(AttachmentPolicy {
+            allowed_extensions: vec![".DWG".to_string()],
+            ..AttachmentPolicy::default()
+        });
+        assert!(custom.ingest("柜体.dwg", "image/vnd.dwg", drawing, None).is_ok());
+        assert!(custom.ingest("图片.png", "image/png", &png(), None).is_err());
+    }
+
+    #[test]
+    fn test_file_name_sanitization() {
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "etcpasswd");
+        assert_eq!(sanitize_file_name("..\\..\\报价单.pdf"), "报价单.pdf");
+        assert_eq!(sanitize_file_name("发票\u{0}\r\n.pdf"), "发票.pdf");
+        assert_eq!(sanitize_file_name("a:b*c?.txt"), "a_b_c_.txt");
+        assert_eq!(sanitize_file_name("  .hidden. "), "hidden");
+        assert_eq!(sanitize_file_name("CON.txt"), "_CON.txt");
+        assert_eq!(sanitize_file_name("/\\.."), "附件");
+        let long = sanitize_file_name(&format!("{}.pdf", "长".repeat(300)));
+        assert_eq!(long.chars().count(), MAX_FILE_NAME_CHARS);
+        assert!(long.ends_with("长.pdf"));
+    }
+
+    #[test]
+    fn test_open_path_uses_sanitized_name() {
+        let dir = TempDir::new().unwrap();
+        let open_dir = TempDir::new().unwrap();
+        let store = AttachmentStore::new(dir.path()).with_open_dir(open_dir.path());
+        let stored = store
+            .ingest("../现场\n照片.png", "image/png", &png(), None)
+            .unwrap();
+        assert_eq!(stored.file_name, "现场照片.png");
+
+        let path = store.open_path(&stored.hash, "../../../evil/现场照片.png").unwrap();
+        assert_eq!(path, open_dir.path().join(&stored.hash).join("evil现场照片.png"));
+        assert_eq!(fs::read(&path).unwrap(), png());
+
+        let err = to_core(store.open_path(&stored.hash, "照片.exe").unwrap_err());
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+        let err = to_core(store.open_path(&"0".repeat(64), "照片.png").unwrap_err());
+        assert!(matches!(err, CoreError::NotFound(_)), "{err}");
+    }
+}
@@ -0,0 +1,332 @@
+//! 数据库副本匿名化
+//!
+//! 诊断包附带数据库副本前，在副本上按字段名打乱个人数据：姓名、电话、邮箱和备注等自由文本
+//! 替换为随机盐的哈希值（同一原值得到同一替换值，关联和去重关系不变），密码哈希、Webhook
+//! 密钥和事件内容等直接清空，生日置空，金额按数量级归档。处理后优化全文索引并整理文件，
+//! 旧值不会留在空闲页或索引段中。只能用于副本，不能用于正在使用的数据库。
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, Transaction};
+use tracing::info;
+use uuid::Uuid;
+
+/// 字段的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Treatment {
+    /// 姓名、公司名、用户名等
+    Name,
+    /// 电话号码（保留位数）
+    Phone,
+    /// 邮箱地址
+    Email,
+    /// 地址、备注等自由文本
+    Text,
+    /// 密钥和可能含有原始数据的内容，直接清空
+    Clear,
+    /// 日期类个人数据，置空
+    Null,
+    /// 金额，按数量级归档
+    Amount,
+}
+
+/// 按字段名确定处理方式（各表同名字段的处理相同）
+fn treatment(column: &str) -> Option<Treatment> {
+    let treatment = match column {
+        "name" | "company" | "display_name" | "username" | "driver" | "signed_by"
+        | "created_by" | "updated_by" | "saved_by" | "deleted_by" | "closed_by" | "actor"
+        | "author" => Treatment::Name,
+        "phone" | "mobile" => Treatment::Phone,
+        "email" | "sender" => Treatment::Email,
+        "address" | "delivery_address" | "notes" | "note" | "content" | "body" | "description"
+        | "title" | "subject" | "symptoms" | "solution" | "solution_method" | "lost_reason"
+        | "label" | "tag" | "reason" | "terms" | "value" | "vehicle" => Treatment::Text,
+        "password_hash" | "secret" | "url" | "payload" | "snapshot" | "result" | "old_value"
+        | "new_value" | "last_error" | "attachments" => Treatment::Clear,
+        "contact_birthday" => Treatment::Null,
+        "amount"
+        | "total_amount"
+        | "expected_amount"
+        | "outstanding_amount"
+        | "accepted_quote_total_12m"
+        | "credit_limit"
+        | "price"
+        | "unit_price"
+        | "line_total"
+        | "cost_price" => Treatment::Amount,
+        _ => return None,
+    };
+    Some(treatment)
+}
+
+/// 金额归档到所在数量级的下限（如 12345 → 10000）
+fn bucket(amount: i64) -> i64 {
+    if amount == 0 {
+        return 0;
+    }
+    amount.signum() * 10_i64.pow(amount.unsigned_abs().ilog10())
+}
+
+/// 匿名化结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnonymizeReport {
+    /// 处理了字段的表
+    pub tables: Vec<String>,
+    /// 替换或清空的文本值数
+    pub scrambled: u64,
+    /// 归档的金额数
+    pub bucketed: u64,
+}
+
+/// 带随机盐的替换值生成器（盐不保存，替换值无法按字典反查）
+struct Scrambler {
+    salt: Uuid,
+}
+
+impl Scrambler {
+    fn token(&self, value: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.salt.hash(&mut hasher);
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn replace(&self, treatment: Treatment, value: &str) -> String {
+        let token = self.token(value);
+        match treatment {
+            Treatment::Phone => {
+                let digits = value
+                    .chars()
+                    .filter(char::is_ascii_digit)
+                    .count()
+                    .clamp(7, 20);
+                let all = format!("{:020}", token);
+                all[all.len() - digits..].to_string()
+            }
+            Treatment::Email => format!("{:012x}@example.invalid", token & 0xffff_ffff_ffff),
+            Treatment::Text => format!("已匿名 {:012x}", token & 0xffff_ffff_ffff),
+            Treatment::Clear => String::new(),
+            _ => format!("匿名{:012x}", token & 0xffff_ffff_ffff),
+        }
+    }
+}
+
+/// 需要处理的普通表（不含虚拟表、全文索引的内部表和SQLite内部表）及全文索引表
+fn list_tables(conn: &Connection) -> Result<(Vec<String>, Vec<String>)> {
+    let mut stmt = conn.prepare(
+        "SELECT name, sql LIKE 'CREATE VIRTUAL TABLE%' FROM sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let all: Vec<(String, bool)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let virtual_tables: Vec<String> = all
+        .iter()
+        .filter(|(_, is_virtual)| *is_virtual)
+        .map(|(name, _)| name.clone())
+        .collect();
+    let tables = all
+        .into_iter()
+        .filter(|(name, is_virtual)| {
+            !is_virtual
+                && !virtual_tables
+                    .iter()
+                    .any(|vt| name.starts_with(&format!("{vt}_")))
+        })
+        .map(|(name, _)| name)
+        .collect();
+    Ok((tables, virtual_tables))
+}
+
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{table}\")"))?;
+    let columns = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(columns)
+}
+
+/// 处理一个字段，返回（替换的文本数，归档的金额数）
+fn anonymize_column(
+    tx: &Transaction<'_>,
+    scrambler: &Scrambler,
+    table: &str,
+    column: &str,
+    treatment: Treatment,
+) -> Result<(u64, u64)> {
+    if treatment == Treatment::Null {
+        let changed = tx.execute(
+            &format!("UPDATE \"{table}\" SET \"{column}\" = NULL WHERE \"{column}\" IS NOT NULL"),
+            [],
+        )?;
+        return Ok((changed as u64, 0));
+    }
+    let rows: Vec<(i64, Value)> = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT rowid, \"{column}\" FROM \"{table}\" WHERE \"{column}\" IS NOT NULL"
+        ))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        rows
+    };
+    let mut update = tx.prepare(&format!(
+        "UPDATE \"{table}\" SET \"{column}\" = ?1 WHERE rowid = ?2"
+    ))?;
+    let (mut scrambled, mut bucketed) = (0, 0);
+    for (rowid, value) in rows {
+        match (treatment, value) {
+            (Treatment::Amount, Value::Integer(amount)) => {
+                update.execute(params![bucket(amount), rowid])?;
+                bucketed += 1;
+            }
+            (Treatment::Amount, Value::Real(amount)) => {
+                update.execute(params![bucket(amount as i64) as f64, rowid])?;
+                bucketed += 1;
+            }
+            (Treatment::Amount, _) => {}
+            (_, Value::Text(text)) if !text.is_empty() => {
+                update.execute(params![scrambler.replace(treatment, &text), rowid])?;
+                scrambled += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok((scrambled, bucketed))
+}
+
+/// 匿名化 `path` 处的数据库副本
+///
+/// # Errors
+///
+/// 无法打开副本或写入失败时返回错误，此时副本内容不完整，不应再使用。
+pub fn anonymize_copy(path: &Path) -> Result<AnonymizeReport> {
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("无法打开数据库副本: {}", path.display()))?;
+    // 副本需要是单个文件
+    conn.pragma_update_and_check(None, "journal_mode", "DELETE", |row| {
+        row.get::<_, String>(0)
+    })?;
+    let scrambler = Scrambler {
+        salt: Uuid::new_v4(),
+    };
+    let (tables, virtual_tables) = list_tables(&conn)?;
+    let mut report = AnonymizeReport::default();
+
+    let tx = conn.transaction()?;
+    for table in tables {
+        let mut touched = false;
+        for column in columns(&tx, &table)? {
+            let Some(treatment) = treatment(&column) else {
+                continue;
+            };
+            let (scrambled, bucketed) =
+                anonymize_column(&tx, &scrambler, &table, &column, treatment)
+                    .with_context(|| format!("无法匿名化字段 {table}.{column}"))?;
+            report.scrambled += scrambled;
+            report.bucketed += bucketed;
+            touched = true;
+        }
+        if touched {
+            report.tables.push(table);
+        }
+    }
+    tx.commit()?;
+
+    // 合并全文索引段，删除的旧词条不再留在索引中
+    for table in &virtual_tables {
+        let is_fts5: bool = conn.query_row(
+            "SELECT sql LIKE '%USING fts5%' FROM sqlite_master WHERE name = ?1",
+            [table],
+            |row| row.get(0),
+        )?;
+        if is_fts5 {
+            conn.execute(
+                &format!("INSERT INTO \"{table}\"(\"{table}\") VALUES ('optimize')"),
+                [],
+            )
+            .with_context(|| format!("无法优化全文索引 {table}"))?;
+        }
+    }
+    // 重写文件，旧值不会留在空闲页中
+    conn.execute_batch("VACUUM").context("无法整理数据库副本")?;
+
+    info!(
+        "数据库副本已匿名化: {} 个表, 替换 {} 个值, 归档 {} 个金额",
+        report.tables.len(),
+        report.scrambled,
+        report.bucketed
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConnection;
+    use crate::test_support::migrated_pool;
+
+    #[test]
+    fn test_bucket() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(7), 1);
+        assert_eq!(bucket(12_345), 10_000);
+        assert_eq!(bucket(100_000), 100_000);
+        assert_eq!(bucket(-5_600), -1_000);
+    }
+
+    #[test]
+    fn test_anonymize_copy_removes_personal_data() {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let now = "2024-07-01T09:00:00.000000Z";
+        for (id, name) in [("c1", "哨兵客户甲"), ("c2", "哨兵客户乙")] {
+            connection
+                .execute(
+                    "INSERT INTO customers (id, name, phone, email, credit_limit, created_at,
+                                            updated_at)
+                     VALUES (?1, ?2, '13912345678', 'sentinel@example.com', 1234567, ?3, ?3)",
+                    params![id, name, now],
+                )
+                .unwrap();
+        }
+        let copy = temp_dir.path().join("copy.db");
+        connection
+            .execute("VACUUM INTO ?1", [copy.to_string_lossy().as_ref()])
+            .unwrap();
+
+        let report = anonymize_copy(&copy).unwrap();
+        assert!(report.tables.contains(&"customers".to_string()));
+        assert_eq!(report.bucketed, 2);
+
+        let bytes = std::fs::read(&copy).unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        for sentinel in ["哨兵客户", "13912345678", "sentinel@example.com"] {
+            assert!(!text.contains(sentinel), "副本中仍有 {sentinel}");
+        }
+        let conn = Connection::open(&copy).unwrap();
+        let (names, phone, limit): (i64, String, i64) = conn
+            .query_row(
+                "SELECT COUNT(DISTINCT name), MIN(phone), MIN(credit_limit) FROM customers",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(names, 2);
+        assert_eq!(phone.len(), 11);
+        assert_eq!(limit, 1_000_000);
+        // 全文索引随客户名称更新
+        let indexed: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM customers_fts WHERE name LIKE '匿名%'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(indexed, 2);
+    }
+}
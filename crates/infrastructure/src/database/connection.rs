@@ -2,27 +2,112 @@
 //!
 //! 提供数据库连接的高级封装和事务管理。
 
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Instant;
+
 use anyhow::{Context, Result};
+use minicrm_core::PlanRow;
 use rusqlite::{Transaction, TransactionBehavior};
 use tracing::{debug, error};
 
+use super::file_guard::{DatabaseFileGuard, UnitOfWork};
 use super::pool::{DatabaseConnection as PooledConnection, DatabasePool};
+use super::slow_query::{plan_row, SlowQueryLog};
 
 /// 数据库连接封装
 #[derive(Clone, Debug)]
 pub struct DatabaseConnection {
     pool: DatabasePool,
+    guard: Option<Arc<DatabaseFileGuard>>,
+    slow_queries: Option<Arc<SlowQueryLog>>,
+}
+
+/// 从连接池取出的连接
+///
+/// 设置了文件守卫时，持有期间算作一个工作单元，归还连接后结束。
+pub struct GuardedConnection {
+    // 先归还连接再结束工作单元
+    conn: PooledConnection,
+    _work: Option<UnitOfWork>,
+}
+
+impl std::fmt::Debug for GuardedConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GuardedConnection").finish_non_exhaustive()
+    }
+}
+
+impl Deref for GuardedConnection {
+    type Target = rusqlite::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl DerefMut for GuardedConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
 }
 
 impl DatabaseConnection {
     /// 创建新的数据库连接管理器
     pub fn new(pool: DatabasePool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            guard: None,
+            slow_queries: None,
+        }
+    }
+
+    /// 设置数据库文件守卫，检测外部程序对数据库文件的修改
+    pub fn with_file_guard(mut self, guard: Arc<DatabaseFileGuard>) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// 数据库文件守卫
+    pub fn file_guard(&self) -> Option<&Arc<DatabaseFileGuard>> {
+        self.guard.as_ref()
+    }
+
+    /// 设置慢查询记录器，查询耗时超过阈值时记录并捕获执行计划
+    pub fn with_slow_query_log(mut self, log: Arc<SlowQueryLog>) -> Self {
+        self.slow_queries = Some(log);
+        self
+    }
+
+    /// 慢查询记录器
+    pub fn slow_query_log(&self) -> Option<&Arc<SlowQueryLog>> {
+        self.slow_queries.as_ref()
+    }
+
+    fn observe(&self, conn: &rusqlite::Connection, sql: &str, started: Instant) {
+        if let Some(log) = &self.slow_queries {
+            log.observe(conn, sql, started.elapsed());
+        }
     }
 
     /// 获取连接池中的连接
-    pub fn get_connection(&self) -> Result<PooledConnection> {
-        self.pool.get().context("无法从连接池获取数据库连接")
+    ///
+    /// 数据库已因外部修改切换为只读时，取出的连接拒绝写入。
+    pub fn get_connection(&self) -> Result<GuardedConnection> {
+        let work = self.guard.as_ref().map(|guard| guard.begin());
+        let conn = self.pool.get().context("无法从连接池获取数据库连接")?;
+        if self.guard.as_ref().is_some_and(|guard| guard.is_read_only()) {
+            conn.pragma_update(None, "query_only", true)
+                .context("无法设置连接只读状态")?;
+        }
+        Ok(GuardedConnection { conn, _work: work })
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        match &self.guard {
+            Some(guard) => guard.ensure_writable(),
+            None => Ok(()),
+        }
     }
 
     /// 执行事务
@@ -38,6 +123,7 @@ impl DatabaseConnection {
     where
         F: FnOnce(&Transaction<'_>) -> Result<R>,
     {
+        self.ensure_writable()?;
         let mut conn = self.get_connection()?;
 
         debug!("开始数据库事务");
@@ -104,6 +190,7 @@ impl DatabaseConnection {
     where
         P: rusqlite::Params,
     {
+        self.ensure_writable()?;
         let conn = self.get_connection()?;
 
         debug!("执行SQL: {}", sql);
@@ -134,9 +221,10 @@ impl DatabaseConnection {
         let conn = self.get_connection()?;
 
         debug!("执行查询: {}", sql);
-        let result = conn
-            .query_row(sql, params, f)
-            .with_context(|| format!("查询执行失败: {}", sql))?;
+        let started = Instant::now();
+        let result = conn.query_row(sql, params, f);
+        self.observe(&conn, sql, started);
+        let result = result.with_context(|| format!("查询执行失败: {}", sql))?;
 
         debug!("查询执行成功");
         Ok(result)
@@ -161,6 +249,7 @@ impl DatabaseConnection {
         let conn = self.get_connection()?;
 
         debug!("执行批量查询: {}", sql);
+        let started = Instant::now();
         let mut stmt = conn
             .prepare(sql)
             .with_context(|| format!("SQL语句准备失败: {}", sql))?;
@@ -173,11 +262,32 @@ impl DatabaseConnection {
         for row in rows {
             results.push(row.context("行数据处理失败")?);
         }
+        self.observe(&conn, sql, started);
 
         debug!("批量查询执行成功，返回 {} 行", results.len());
         Ok(results)
     }
 
+    /// 获取查询的执行计划（`EXPLAIN QUERY PLAN`）
+    ///
+    /// # Errors
+    ///
+    /// 如果SQL无效或参数不匹配，将返回错误。
+    pub fn explain_query_plan<P>(&self, sql: &str, params: P) -> Result<Vec<PlanRow>>
+    where
+        P: rusqlite::Params,
+    {
+        let conn = self.get_connection()?;
+        let mut stmt = conn
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+            .with_context(|| format!("SQL语句准备失败: {}", sql))?;
+        let rows = stmt
+            .query_map(params, plan_row)
+            .with_context(|| format!("获取执行计划失败: {}", sql))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("执行计划读取失败")
+    }
+
     /// 检查表是否存在
     ///
     /// # Arguments
@@ -197,6 +307,18 @@ impl DatabaseConnection {
         Ok(count > 0)
     }
 
+    /// 检查SQL函数是否可用
+    ///
+    /// 用于在加载了扩展（如 spellfix1）时选择增强的查询路径。
+    ///
+    /// # Errors
+    ///
+    /// 如果查询失败，将返回错误。
+    pub fn has_function(&self, name: &str) -> Result<bool> {
+        let conn = self.get_connection()?;
+        super::extensions::function_exists(&conn, name).context("查询SQL函数列表失败")
+    }
+
     /// 获取表的列信息
     ///
     /// # Arguments
@@ -239,25 +361,17 @@ pub struct ColumnInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::database::pool::{DatabasePoolBuilder, DatabasePoolConfig};
-    use tempfile::tempdir;
-
-    fn create_test_connection() -> DatabaseConnection {
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-
-        let config = DatabasePoolConfig {
-            database_path: db_path.to_string_lossy().to_string(),
-            ..Default::default()
-        };
+    use crate::test_support::empty_pool;
+    use tempfile::TempDir;
 
-        let pool = DatabasePoolBuilder::new(config).build().unwrap();
-        DatabaseConnection::new(pool)
+    fn create_test_connection() -> (TempDir, DatabaseConnection) {
+        let (temp_dir, pool) = empty_pool();
+        (temp_dir, DatabaseConnection::new(pool))
     }
 
     #[tokio::test]
     async fn test_execute_sql() {
-        let conn = create_test_connection();
+        let (_dir, conn) = create_test_connection();
 
         // 创建测试表
         let affected = conn
@@ -272,7 +386,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_transaction() {
-        let conn = create_test_connection();
+        let (_dir, conn) = create_test_connection();
 
         // 创建测试表
         conn.execute(
@@ -300,7 +414,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_table_exists() {
-        let conn = create_test_connection();
+        let (_dir, conn) = create_test_connection();
 
         // 表不存在
         assert!(!conn.table_exists("non_existent_table").unwrap());
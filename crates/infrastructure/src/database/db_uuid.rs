@@ -0,0 +1,201 @@
+//! UUID列适配
+//!
+//! 业务表的ID以TEXT存储，统一写入小写带连字符的规范形式；读取时兼容
+//! 大写、花括号和无连字符等历史格式，无法解析时返回带列名的查询错误。
+
+use minicrm_core::error::DatabaseError;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Type, ValueRef};
+use rusqlite::{Row, RowIndex, ToSql};
+use uuid::Uuid;
+
+/// 以规范文本形式读写的UUID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DbUuid(pub Uuid);
+
+impl From<Uuid> for DbUuid {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl From<DbUuid> for Uuid {
+    fn from(id: DbUuid) -> Self {
+        id.0
+    }
+}
+
+impl ToSql for DbUuid {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(canonical(self.0)))
+    }
+}
+
+impl FromSql for DbUuid {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let text = value.as_str()?;
+        parse(text).map(Self).ok_or_else(|| {
+            FromSqlError::Other(Box::new(DatabaseError::Query(format!(
+                "值 '{}' 不是有效的UUID",
+                text
+            ))))
+        })
+    }
+}
+
+/// UUID的规范文本形式（小写、带连字符）
+pub fn canonical(id: Uuid) -> String {
+    id.hyphenated().to_string()
+}
+
+/// 解析数据库中的UUID文本，兼容大小写、花括号和首尾空白
+pub fn parse(value: &str) -> Option<Uuid> {
+    let trimmed = value.trim();
+    let inner = trimmed
+        .strip_prefix('{')
+        .and_then(|v| v.strip_suffix('}'))
+        .unwrap_or(trimmed);
+    Uuid::parse_str(inner).ok()
+}
+
+/// 读取UUID列
+///
+/// # Errors
+///
+/// 值不是有效UUID时返回 `DatabaseError::Query`，错误信息包含列名。
+pub fn get_uuid<I: RowIndex>(row: &Row<'_>, index: I) -> rusqlite::Result<Uuid> {
+    let statement = row.as_ref();
+    let index = index.idx(statement)?;
+    let raw = row.get_ref(index)?;
+    DbUuid::column_result(raw).map(Uuid::from).map_err(|err| {
+        let column = statement.column_name(index).unwrap_or("?");
+        let detail = match err {
+            FromSqlError::Other(inner) => inner.to_string(),
+            other => other.to_string(),
+        };
+        rusqlite::Error::FromSqlConversionFailure(
+            index,
+            raw.data_type(),
+            Box::new(DatabaseError::Query(format!("列 {}: {}", column, detail))),
+        )
+    })
+}
+
+/// 读取可为空的UUID列
+///
+/// # Errors
+///
+/// 值非空且不是有效UUID时返回 `DatabaseError::Query`。
+pub fn get_optional_uuid<I: RowIndex>(row: &Row<'_>, index: I) -> rusqlite::Result<Option<Uuid>> {
+    let statement = row.as_ref();
+    let index = index.idx(statement)?;
+    if row.get_ref(index)?.data_type() == Type::Null {
+        return Ok(None);
+    }
+    get_uuid(row, index).map(Some)
+}
+
+/// 规范化已有ID列的SQL（去除空白和花括号、转小写、补齐连字符）
+pub(crate) fn normalize_column_sql(table: &str, column: &str) -> String {
+    let stripped = format!("lower(trim(trim({}), '{{}}'))", column);
+    format!(
+        "UPDATE {table} SET {column} = {stripped}
+            WHERE {column} <> {stripped} AND length({stripped}) IN (32, 36);
+         UPDATE {table} SET {column} =
+                substr({column}, 1, 8) || '-' || substr({column}, 9, 4) || '-' ||
+                substr({column}, 13, 4) || '-' || substr({column}, 17, 4) || '-' ||
+                substr({column}, 21)
+            WHERE length({column}) = 32 AND instr({column}, '-') = 0;
+        ",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{schema, DatabaseConnection, MigrationManager};
+    use crate::repository::SqliteUserService;
+    use crate::test_support::empty_pool;
+    use minicrm_core::{NewUser, UserRole, UserService};
+    use tempfile::TempDir;
+
+    fn create_test_connection() -> (TempDir, DatabaseConnection) {
+        let (temp_dir, pool) = empty_pool();
+        (temp_dir, DatabaseConnection::new(pool))
+    }
+
+    fn insert_user(connection: &DatabaseConnection, id: &str, username: &str) {
+        connection
+            .execute(
+                "INSERT INTO users (id, username, display_name, role, password_hash, created_at, updated_at)
+                 VALUES (?1, ?2, ?2, 'sales', 'x', '2024-06-01T00:00:00Z', '2024-06-01T00:00:00Z')",
+                rusqlite::params![id, username],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_tolerates_legacy_forms() {
+        let id = Uuid::new_v4();
+        let upper = canonical(id).to_uppercase();
+        assert_eq!(parse(&upper), Some(id));
+        assert_eq!(parse(&format!(" {{{}}} ", canonical(id))), Some(id));
+        assert_eq!(parse(&id.simple().to_string()), Some(id));
+        assert_eq!(parse("CUST-0001"), None);
+        assert_eq!(canonical(id), canonical(id).to_lowercase());
+    }
+
+    #[tokio::test]
+    async fn test_lookups_after_normalization() {
+        let (_dir, connection) = create_test_connection();
+        let migrations = MigrationManager::new(connection.clone())
+            .add_migrations(schema::builtin_migrations());
+        migrations.migrate(Some(5)).unwrap();
+
+        // 历史导入写入的大写和花括号ID
+        let (mixed, braced) = (Uuid::new_v4(), Uuid::new_v4());
+        insert_user(&connection, &canonical(mixed).to_uppercase(), "mixed");
+        insert_user(&connection, &format!("{{{}}}", canonical(braced)), "braced");
+
+        migrations.migrate(None).unwrap();
+
+        let stored: Vec<String> = connection
+            .query_map("SELECT id FROM users ORDER BY username", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, vec![canonical(braced), canonical(mixed)]);
+
+        let users = SqliteUserService::new(connection.clone());
+        assert_eq!(users.deactivate_user(mixed, None).await.unwrap().user.username, "mixed");
+        assert_eq!(users.deactivate_user(braced, None).await.unwrap().user.username, "braced");
+
+        let created = users
+            .create_user(NewUser {
+                username: "lisi".to_string(),
+                display_name: "李四".to_string(),
+                role: UserRole::Sales,
+                password: "secret-123".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            users.deactivate_user(created.id, None).await.unwrap().user.username,
+            "lisi"
+        );
+    }
+
+    #[test]
+    fn test_invalid_value_names_column() {
+        let (_dir, connection) = create_test_connection();
+        MigrationManager::new(connection.clone())
+            .add_migrations(schema::builtin_migrations())
+            .migrate(None)
+            .unwrap();
+        insert_user(&connection, "LEGACY-42", "legacy");
+
+        let err = connection
+            .query_map("SELECT id FROM users", [], |row| get_uuid(row, "id"))
+            .unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("列 id"), "{}", message);
+        assert!(message.contains("LEGACY-42"), "{}", message);
+    }
+}
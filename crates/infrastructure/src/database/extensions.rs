@@ -0,0 +1,91 @@
+//! SQLite 可加载扩展
+//!
+//! 按配置为每个连接加载扩展（如 spellfix1、trigram 分词器），并提供函数可用性探测。
+//! 扩展加载需要 `sqlite-extensions` 功能；加载失败只记录警告，调用方通过
+//! [`function_exists`] 判断是否使用增强的查询路径。
+//!
+//! 这是本 crate 中唯一允许 `unsafe` 的模块：加载扩展等同于执行任意本地代码，
+//! 因此只加载配置文件中列出的路径，且加载期间才临时开启扩展加载。
+
+#![allow(unsafe_code)]
+
+use std::path::PathBuf;
+
+use rusqlite::Connection;
+use tracing::warn;
+
+/// 为连接加载配置的扩展，返回成功加载的数量
+///
+/// 单个扩展加载失败时记录警告并继续，连接仍可正常使用。
+pub fn load_all(conn: &Connection, paths: &[PathBuf]) -> usize {
+    if paths.is_empty() {
+        return 0;
+    }
+    #[cfg(feature = "sqlite-extensions")]
+    {
+        let mut loaded = 0;
+        for path in paths {
+            match load_one(conn, path) {
+                Ok(()) => loaded += 1,
+                Err(e) => warn!("加载SQLite扩展失败，将使用基础查询: {:?}: {}", path, e),
+            }
+        }
+        loaded
+    }
+    #[cfg(not(feature = "sqlite-extensions"))]
+    {
+        let _ = conn;
+        warn!(
+            "未启用 sqlite-extensions 功能，忽略配置的 {} 个SQLite扩展",
+            paths.len()
+        );
+        0
+    }
+}
+
+#[cfg(feature = "sqlite-extensions")]
+fn load_one(conn: &Connection, path: &std::path::Path) -> rusqlite::Result<()> {
+    // SAFETY: 只加载管理员在配置文件中列出的扩展；守卫在加载结束后立即关闭扩展加载，
+    // SQL 中的 `load_extension()` 在其他时候无法调用。
+    unsafe {
+        let _guard = rusqlite::LoadExtensionGuard::new(conn)?;
+        conn.load_extension(path, None)
+    }
+}
+
+/// SQL 函数是否可用（内置、扩展或应用注册的函数）
+///
+/// # Errors
+///
+/// 查询失败时返回错误。
+pub fn function_exists(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_function_list WHERE name = lower(?1))",
+        [name],
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_function_probe() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(function_exists(&conn, "json_extract").unwrap());
+        assert!(function_exists(&conn, "JSON_EXTRACT").unwrap());
+        assert!(!function_exists(&conn, "editdist3").unwrap());
+    }
+
+    #[test]
+    fn test_missing_extension_degrades() {
+        let conn = Connection::open_in_memory().unwrap();
+        let loaded = load_all(&conn, &[PathBuf::from("/nonexistent/spellfix1")]);
+        assert_eq!(loaded, 0);
+        // 连接不受影响，增强函数探测为不可用
+        assert!(!function_exists(&conn, "editdist3").unwrap());
+        let one: i64 = conn.query_row("SELECT 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(one, 1);
+    }
+}
@@ -0,0 +1,424 @@
+//! 数据库文件外部修改检测
+//!
+//! 运行期间用其他工具打开并修改数据库文件，会让缓存、统计和界面上的数据与文件不一致。
+//! 守卫持有一个专用的只读连接，在每个工作单元（从连接池取出连接到归还）开始时读取
+//! `PRAGMA data_version`：该值只在其他连接提交后变化。本程序的工作单元都经过守卫计数，
+//! 最后一个进行中的工作单元结束时重新记录 data_version 作为基准；下一个工作单元开始时
+//! 若 data_version 与基准不同，且其间本程序没有完成任何工作单元，则判定为外部修改。
+//! 本程序与外部程序在同一段时间内都有写入时无法区分，按本程序的修改处理。
+//!
+//! 数据库文件被替换（inode 变化）也视为外部修改。检测到外部修改后通知订阅者；
+//! 配置为升级只读时，此后取出的连接都设为 `query_only`，直到重新启动。
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use tracing::warn;
+
+/// 数据库文件的标识信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStamp {
+    /// inode（非Unix平台为空）
+    pub inode: Option<u64>,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 修改时间
+    pub modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    /// 读取文件当前的标识信息
+    ///
+    /// # Errors
+    ///
+    /// 文件不存在或无法读取元数据时返回错误。
+    pub fn read(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("无法读取数据库文件信息: {}", path.display()))?;
+        #[cfg(unix)]
+        let inode = {
+            use std::os::unix::fs::MetadataExt;
+            Some(metadata.ino())
+        };
+        #[cfg(not(unix))]
+        let inode = None;
+        Ok(Self {
+            inode,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// 检测到的外部修改
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalChange {
+    /// 上次检查时的文件信息
+    pub before: FileStamp,
+    /// 本次检查时的文件信息
+    pub after: FileStamp,
+    /// 数据库文件已被替换
+    pub file_replaced: bool,
+    /// 已切换为只读
+    pub read_only: bool,
+}
+
+impl ExternalChange {
+    /// 界面提示文本
+    pub fn message(&self) -> String {
+        if self.read_only {
+            "数据库被外部程序修改，已切换为只读。请关闭其他程序后重新启动 MiniCRM".to_string()
+        } else {
+            "数据库被外部程序修改，已重新加载数据".to_string()
+        }
+    }
+}
+
+type ChangeListener = Arc<dyn Fn(&ExternalChange) + Send + Sync>;
+
+/// 上次检查的结果
+struct Baseline {
+    data_version: i64,
+    finished: u64,
+    stamp: FileStamp,
+}
+
+/// 数据库文件守卫
+pub struct DatabaseFileGuard {
+    path: PathBuf,
+    monitor: Mutex<Connection>,
+    baseline: Mutex<Baseline>,
+    /// 已开始的工作单元数
+    started: AtomicU64,
+    /// 已结束的工作单元数
+    finished: AtomicU64,
+    escalate_read_only: bool,
+    read_only: AtomicBool,
+    listeners: RwLock<Vec<ChangeListener>>,
+}
+
+impl std::fmt::Debug for DatabaseFileGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseFileGuard")
+            .field("path", &self.path)
+            .field("read_only", &self.is_read_only())
+            .finish_non_exhaustive()
+    }
+}
+
+fn open_monitor(path: &Path) -> Result<Connection> {
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .with_context(|| format!("无法打开数据库文件检测连接: {}", path.display()))
+}
+
+fn data_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA data_version", [], |row| row.get(0))
+        .context("无法读取数据库 data_version")
+}
+
+impl DatabaseFileGuard {
+    /// 打开数据库文件守卫，以当前状态为基准
+    ///
+    /// `escalate_read_only` 为真时，检测到外部修改后切换为只读直到重新启动。
+    ///
+    /// # Errors
+    ///
+    /// 数据库文件不存在或无法打开检测连接时返回错误。
+    pub fn open<P: AsRef<Path>>(path: P, escalate_read_only: bool) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let monitor = open_monitor(&path)?;
+        let baseline = Baseline {
+            data_version: data_version(&monitor)?,
+            finished: 0,
+            stamp: FileStamp::read(&path)?,
+        };
+        Ok(Self {
+            path,
+            monitor: Mutex::new(monitor),
+            baseline: Mutex::new(baseline),
+            started: AtomicU64::new(0),
+            finished: AtomicU64::new(0),
+            escalate_read_only,
+            read_only: AtomicBool::new(false),
+            listeners: RwLock::new(Vec::new()),
+        })
+    }
+
+    fn monitor(&self) -> MutexGuard<'_, Connection> {
+        self.monitor
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn baseline(&self) -> MutexGuard<'_, Baseline> {
+        self.baseline
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// 订阅外部修改通知
+    pub fn subscribe<F>(&self, listener: F)
+    where
+        F: Fn(&ExternalChange) + Send + Sync + 'static,
+    {
+        if let Ok(mut listeners) = self.listeners.write() {
+            listeners.push(Arc::new(listener));
+        }
+    }
+
+    /// 是否已切换为只读
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    /// 已切换为只读时返回错误
+    ///
+    /// # Errors
+    ///
+    /// 检测到外部修改并已切换为只读时返回错误。
+    pub fn ensure_writable(&self) -> Result<()> {
+        if self.is_read_only() {
+            bail!("数据库被外部程序修改，MiniCRM 已切换为只读。请关闭其他程序后重新启动");
+        }
+        Ok(())
+    }
+
+    /// 开始一个工作单元
+    ///
+    /// 开始前检查外部修改；检查本身失败只记录警告，不影响本次操作。
+    pub fn begin(self: &Arc<Self>) -> UnitOfWork {
+        if let Err(e) = self.check() {
+            warn!("检查数据库文件状态失败: {}", e);
+        }
+        self.started.fetch_add(1, Ordering::SeqCst);
+        UnitOfWork {
+            guard: self.clone(),
+        }
+    }
+
+    /// 没有进行中的工作单元时，以当前 data_version 作为本程序修改后的基准
+    fn settle(&self, finished: u64) -> Result<()> {
+        if self.started.load(Ordering::SeqCst) != finished {
+            return Ok(());
+        }
+        let version = data_version(&self.monitor())?;
+        if self.finished.load(Ordering::SeqCst) != finished
+            || self.started.load(Ordering::SeqCst) != finished
+        {
+            return Ok(());
+        }
+        let mut baseline = self.baseline();
+        baseline.data_version = version;
+        baseline.finished = finished;
+        Ok(())
+    }
+
+    /// 检查自上次检查以来是否有外部修改
+    ///
+    /// 本程序有工作单元正在进行时跳过检查，留到下一个工作单元开始时再判断。
+    ///
+    /// # Errors
+    ///
+    /// 读取 data_version 或文件信息失败时返回错误。
+    pub fn check(&self) -> Result<Option<ExternalChange>> {
+        let finished = self.finished.load(Ordering::SeqCst);
+        if self.started.load(Ordering::SeqCst) != finished {
+            return Ok(None);
+        }
+        let version = data_version(&self.monitor())?;
+        if self.started.load(Ordering::SeqCst) != finished {
+            return Ok(None);
+        }
+        let stamp = FileStamp::read(&self.path)?;
+
+        let mut baseline = self.baseline();
+        let file_replaced = stamp.inode.is_some() && stamp.inode != baseline.stamp.inode;
+        let external =
+            file_replaced || (version != baseline.data_version && finished == baseline.finished);
+        let before = std::mem::replace(&mut baseline.stamp, stamp.clone());
+        baseline.finished = finished;
+        baseline.data_version = version;
+        if file_replaced {
+            // 原连接仍指向被替换前的文件，换成新文件后重新取基准
+            let monitor = open_monitor(&self.path)?;
+            baseline.data_version = data_version(&monitor)?;
+            *self.monitor() = monitor;
+        }
+        drop(baseline);
+        if !external {
+            return Ok(None);
+        }
+
+        if self.escalate_read_only {
+            self.read_only.store(true, Ordering::SeqCst);
+        }
+        let change = ExternalChange {
+            before,
+            after: stamp,
+            file_replaced,
+            read_only: self.is_read_only(),
+        };
+        warn!(
+            "检测到数据库被外部程序修改: {}（文件被替换: {}，只读: {}）",
+            self.path.display(),
+            change.file_replaced,
+            change.read_only
+        );
+        let listeners = match self.listeners.read() {
+            Ok(listeners) => listeners.clone(),
+            Err(_) => Vec::new(),
+        };
+        for listener in listeners {
+            listener(&change);
+        }
+        Ok(Some(change))
+    }
+}
+
+/// 工作单元
+///
+/// 从连接池取出连接时开始，归还连接时结束；结束前的提交都算作本程序的修改。
+pub struct UnitOfWork {
+    guard: Arc<DatabaseFileGuard>,
+}
+
+impl std::fmt::Debug for UnitOfWork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnitOfWork").finish_non_exhaustive()
+    }
+}
+
+impl Drop for UnitOfWork {
+    fn drop(&mut self) {
+        let finished = self.guard.finished.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Err(e) = self.guard.settle(finished) {
+            warn!("记录数据库文件状态失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::pool::DatabasePoolBuilder;
+    use crate::database::DatabaseConnection;
+    use tempfile::TempDir;
+
+    fn create_database(
+        escalate: bool,
+    ) -> (TempDir, PathBuf, DatabaseConnection, Arc<DatabaseFileGuard>) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let pool = DatabasePoolBuilder::new(db_path.to_string_lossy())
+            .build()
+            .unwrap();
+        let plain = DatabaseConnection::new(pool.clone());
+        plain
+            .execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)", [])
+            .unwrap();
+        let guard = Arc::new(DatabaseFileGuard::open(&db_path, escalate).unwrap());
+        let connection = DatabaseConnection::new(pool).with_file_guard(guard.clone());
+        (temp_dir, db_path, connection, guard)
+    }
+
+    fn count_changes(guard: &DatabaseFileGuard) -> Arc<AtomicU64> {
+        let count = Arc::new(AtomicU64::new(0));
+        guard.subscribe({
+            let count = count.clone();
+            move |_| {
+                count.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        count
+    }
+
+    fn external_write(path: &Path) {
+        let raw = Connection::open(path).unwrap();
+        raw.execute("INSERT INTO notes (body) VALUES ('外部写入')", [])
+            .unwrap();
+    }
+
+    fn note_count(connection: &DatabaseConnection) -> i64 {
+        connection
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_own_writes_are_not_reported() {
+        let (_dir, _path, connection, guard) = create_database(false);
+        let changes = count_changes(&guard);
+
+        connection
+            .execute("INSERT INTO notes (body) VALUES ('本程序')", [])
+            .unwrap();
+        connection
+            .with_transaction(|tx| {
+                tx.execute("INSERT INTO notes (body) VALUES ('事务')", [])?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(note_count(&connection), 2);
+        assert_eq!(note_count(&connection), 2);
+        assert_eq!(changes.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_external_write_detected() {
+        let (_dir, path, connection, guard) = create_database(false);
+        let changes = count_changes(&guard);
+        assert_eq!(note_count(&connection), 0);
+
+        external_write(&path);
+        // 下一个工作单元开始时发现外部修改，读到的是外部写入后的数据
+        assert_eq!(note_count(&connection), 1);
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+        assert!(!guard.is_read_only());
+
+        // 只报告一次，之后本程序的写入照常进行
+        connection
+            .execute("INSERT INTO notes (body) VALUES ('本程序')", [])
+            .unwrap();
+        assert_eq!(note_count(&connection), 2);
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_escalates_to_read_only() {
+        let (_dir, path, connection, guard) = create_database(true);
+        let reported = Arc::new(Mutex::new(None));
+        guard.subscribe({
+            let reported = reported.clone();
+            move |change: &ExternalChange| {
+                *reported.lock().unwrap() = Some(change.clone());
+            }
+        });
+
+        external_write(&path);
+        assert_eq!(note_count(&connection), 1);
+        assert!(guard.is_read_only());
+        let change = reported.lock().unwrap().clone().unwrap();
+        assert!(change.read_only);
+        assert!(change.message().contains("只读"));
+
+        // 通过封装的写入给出说明，直接使用连接写入也被 SQLite 拒绝
+        let err = connection
+            .execute("INSERT INTO notes (body) VALUES ('本程序')", [])
+            .unwrap_err();
+        assert!(err.to_string().contains("只读"));
+        let conn = connection.get_connection().unwrap();
+        assert!(
+            conn.execute("INSERT INTO notes (body) VALUES ('直接写入')", [])
+                .is_err()
+        );
+        drop(conn);
+        assert_eq!(note_count(&connection), 1);
+    }
+}
@@ -0,0 +1,378 @@
+//! 全文索引维护
+//!
+//! 全文索引由触发器与基础表保持同步，但事务中途崩溃或关闭触发器导入的旧数据会让索引
+//! 缺行，搜索随之静默漏掉记录。[`FtsMaintenance::verify`] 比较行数并抽样比对索引内容，
+//! [`FtsMaintenance::rebuild`] 按批从基础表重建索引。
+//!
+//! 索引使用 trigram 分词并保存自身内容（基础表没有整数主键，`VACUUM` 可能重排 rowid，
+//! 不能作为外部内容表），因此重建按批删除并重新写入，而不是 FTS5 的 `rebuild` 命令。
+//! 每批在独立事务中执行，重建期间界面和搜索仍可使用。
+
+use std::sync::mpsc::Sender;
+
+use anyhow::{anyhow, Result};
+use minicrm_core::{SearchIndexProgress, SearchIndexStatus};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, Params};
+use tracing::info;
+
+use super::DatabaseConnection;
+
+/// 每批重建的行数
+const REBUILD_BATCH_SIZE: u32 = 500;
+
+/// 抽样比对的目标行数
+const SAMPLE_SIZE: u64 = 200;
+
+/// 全文索引表定义
+struct FtsTable {
+    /// 基础表（主键列为 `id`）
+    base: &'static str,
+    /// 全文索引表
+    index: &'static str,
+    /// 索引中保存基础表主键的列
+    key: &'static str,
+    /// 索引的列（与基础表同名）
+    columns: &'static [&'static str],
+}
+
+/// 全部全文索引
+const FTS_TABLES: &[FtsTable] = &[
+    FtsTable {
+        base: "customers",
+        index: "customers_fts",
+        key: "customer_id",
+        columns: &["name", "company"],
+    },
+    FtsTable {
+        base: "knowledge_articles",
+        index: "knowledge_articles_fts",
+        key: "article_id",
+        columns: &["title", "symptoms", "solution"],
+    },
+];
+
+impl FtsTable {
+    fn find(table: &str) -> Result<&'static Self> {
+        FTS_TABLES
+            .iter()
+            .find(|t| t.base == table || t.index == table)
+            .ok_or_else(|| anyhow!("未知的全文索引表: {}", table))
+    }
+
+    fn column_list(&self) -> String {
+        self.columns.join(", ")
+    }
+
+    /// 抽样行的索引内容与基础表一致的条件
+    fn same_content(&self) -> String {
+        self.columns
+            .iter()
+            .map(|c| format!("f.{c} IS b.{c}"))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+}
+
+/// 全文索引维护
+#[derive(Clone)]
+pub struct FtsMaintenance {
+    connection: DatabaseConnection,
+}
+
+impl std::fmt::Debug for FtsMaintenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FtsMaintenance").finish_non_exhaustive()
+    }
+}
+
+impl FtsMaintenance {
+    /// 创建全文索引维护
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 检查全部全文索引与基础表是否一致
+    ///
+    /// 比较行数，并按 rowid 等间隔抽样比对索引内容；尚未建立的索引不列出。
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn verify(&self) -> Result<Vec<SearchIndexStatus>> {
+        let mut statuses = Vec::with_capacity(FTS_TABLES.len());
+        for table in FTS_TABLES {
+            if self.connection.table_exists(table.index)? {
+                statuses.push(self.verify_table(table)?);
+            }
+        }
+        Ok(statuses)
+    }
+
+    fn verify_table(&self, table: &FtsTable) -> Result<SearchIndexStatus> {
+        let conn = self.connection.get_connection()?;
+        let base_rows = count(&conn, &format!("SELECT COUNT(*) FROM {}", table.base), [])?;
+        let indexed_rows = count(&conn, &format!("SELECT COUNT(*) FROM {}", table.index), [])?;
+        let step = i64::try_from((base_rows / SAMPLE_SIZE).max(1)).unwrap_or(1);
+        let sampled = count(
+            &conn,
+            &format!("SELECT COUNT(*) FROM {} WHERE rowid % ?1 = 0", table.base),
+            [step],
+        )?;
+        let matched = count(
+            &conn,
+            &format!(
+                "SELECT COUNT(DISTINCT b.id) FROM {index} f JOIN {base} b ON b.id = f.{key} \
+                 WHERE b.rowid % ?1 = 0 AND {same}",
+                index = table.index,
+                base = table.base,
+                key = table.key,
+                same = table.same_content(),
+            ),
+            [step],
+        )?;
+
+        Ok(SearchIndexStatus {
+            table: table.base.to_string(),
+            base_rows,
+            indexed_rows,
+            sampled,
+            mismatched: sampled.saturating_sub(matched),
+        })
+    }
+
+    /// 从基础表重建全文索引
+    ///
+    /// `table` 可以是基础表名或索引表名。按主键顺序分批重写索引行，每批完成后通过
+    /// `progress` 报告进度，最后清除基础表中已不存在的行，返回重建后的一致性检查结果。
+    ///
+    /// # Errors
+    ///
+    /// 表名未知或写入失败时返回错误；已完成的批次保留，可再次重建。
+    pub fn rebuild(
+        &self,
+        table: &str,
+        progress: Option<&Sender<SearchIndexProgress>>,
+    ) -> Result<SearchIndexStatus> {
+        let table = FtsTable::find(table)?;
+        let total: i64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM {}", table.base),
+            [],
+            |row| row.get(0),
+        )?;
+        let total = u64::try_from(total).unwrap_or_default();
+        let report = |rebuilt: u64| {
+            if let Some(tx) = progress {
+                // 界面已关闭时忽略，重建继续执行
+                let _ = tx.send(SearchIndexProgress { rebuilt, total });
+            }
+        };
+        report(0);
+
+        let batch = format!(
+            "SELECT id FROM {} WHERE (?1 IS NULL OR id > ?1) ORDER BY id LIMIT ?2",
+            table.base
+        );
+        let mut last_key = Value::Null;
+        let mut rebuilt = 0u64;
+        loop {
+            let written = self.connection.with_transaction(|tx| {
+                tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE {} IN ({batch})",
+                        table.index, table.key
+                    ),
+                    params![last_key, REBUILD_BATCH_SIZE],
+                )?;
+                let written = tx.execute(
+                    &format!(
+                        "INSERT INTO {index} ({key}, {columns}) \
+                         SELECT id, {columns} FROM {base} WHERE id IN ({batch})",
+                        index = table.index,
+                        key = table.key,
+                        columns = table.column_list(),
+                        base = table.base,
+                    ),
+                    params![last_key, REBUILD_BATCH_SIZE],
+                )?;
+                let next: Value = tx.query_row(
+                    &format!("SELECT MAX(id) FROM ({batch})"),
+                    params![last_key, REBUILD_BATCH_SIZE],
+                    |row| row.get(0),
+                )?;
+                Ok((written, next))
+            })?;
+            match written {
+                (0, _) | (_, Value::Null) => break,
+                (count, next) => {
+                    rebuilt += count as u64;
+                    last_key = next;
+                    report(rebuilt.min(total));
+                }
+            }
+        }
+
+        let removed = self.connection.execute(
+            &format!(
+                "DELETE FROM {} WHERE {} NOT IN (SELECT id FROM {})",
+                table.index, table.key, table.base
+            ),
+            [],
+        )?;
+        report(total);
+        info!(
+            "全文索引已重建: {}，写入 {} 行，清除 {} 行",
+            table.index, rebuilt, removed
+        );
+        self.verify_table(table)
+    }
+}
+
+fn count<P: Params>(conn: &Connection, sql: &str, params: P) -> Result<u64> {
+    let rows: i64 = conn.query_row(sql, params, |row| row.get(0))?;
+    Ok(u64::try_from(rows).unwrap_or_default())
+}
+
+/// 一致性检查结果的说明文本
+pub fn describe(status: &SearchIndexStatus) -> String {
+    if status.is_consistent() {
+        format!(
+            "{}: {} 行，抽样 {} 行一致",
+            status.table, status.base_rows, status.sampled
+        )
+    } else {
+        format!(
+            "{}: 基础表 {} 行，索引 {} 行，抽样 {} 行中 {} 行不一致",
+            status.table, status.base_rows, status.indexed_rows, status.sampled, status.mismatched
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::pool::DatabasePool;
+    use crate::database::{DatabaseHealthChecker, DbUuid};
+    use crate::repository::CustomerListStore;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::{CustomerListReadModel, Projection, QueryFilter};
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn create_test_db() -> (TempDir, DatabasePool, DatabaseConnection) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool.clone());
+        (temp_dir, pool, connection)
+    }
+
+    fn insert_customer(connection: &DatabaseConnection, name: &str, company: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = "2024-03-01T09:00:00.000000Z";
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, company, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?4)",
+                params![DbUuid(id), name, company, now],
+            )
+            .unwrap();
+        id
+    }
+
+    async fn search(connection: &DatabaseConnection, keyword: &str) -> Vec<Uuid> {
+        CustomerListStore::new(connection.clone())
+            .list_customers(
+                &QueryFilter::new().with_search(keyword),
+                &Projection::new(["name"]),
+            )
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|row| row.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_triggers_keep_index_in_sync() {
+        let (_dir, _pool, connection) = create_test_db();
+        let id = insert_customer(&connection, "王建国", "华东板材有限公司");
+        insert_customer(&connection, "李娜", "杭州五金");
+        connection
+            .execute(
+                "UPDATE customers SET company = '华南板材' WHERE id = ?1",
+                [DbUuid(id)],
+            )
+            .unwrap();
+
+        let statuses = FtsMaintenance::new(connection).verify().unwrap();
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses.iter().all(SearchIndexStatus::is_consistent));
+        assert_eq!(statuses[0].base_rows, 2);
+        assert_eq!(statuses[0].sampled, 2);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_repairs_missing_rows() {
+        let (_dir, pool, connection) = create_test_db();
+        for i in 0..1200 {
+            insert_customer(&connection, &format!("客户{i:04}"), "批量导入");
+        }
+        let missing = insert_customer(&connection, "赵志强", "宁波红木家具厂");
+        assert_eq!(search(&connection, "红木家具").await, vec![missing]);
+
+        // 模拟关闭触发器导入或崩溃后留下的索引缺行
+        connection
+            .execute(
+                "DELETE FROM customers_fts WHERE customer_id = ?1",
+                [DbUuid(missing)],
+            )
+            .unwrap();
+        assert!(search(&connection, "红木家具").await.is_empty());
+
+        let maintenance = FtsMaintenance::new(connection.clone());
+        let status = maintenance.verify().unwrap().remove(0);
+        assert!(!status.is_consistent());
+        assert_eq!(status.base_rows, status.indexed_rows + 1);
+        let checker = DatabaseHealthChecker::new(connection.clone(), pool);
+        let deep = checker.check_health_deep();
+        let check = deep
+            .checks
+            .iter()
+            .find(|c| c.name == "全文索引检查")
+            .unwrap();
+        assert!(!check.passed);
+        assert!(!deep.healthy);
+
+        let (tx, rx) = mpsc::channel();
+        let rebuilt = maintenance.rebuild("customers", Some(&tx)).unwrap();
+        assert!(rebuilt.is_consistent(), "{}", describe(&rebuilt));
+        let progress: Vec<SearchIndexProgress> = rx.try_iter().collect();
+        assert_eq!(progress.first().map(|p| p.rebuilt), Some(0));
+        assert!(progress.len() >= 4, "1201 行应分 3 批报告进度");
+        assert_eq!(progress.last().map(SearchIndexProgress::percent), Some(100));
+
+        assert_eq!(search(&connection, "红木家具").await, vec![missing]);
+        assert!(checker.check_health_deep().healthy);
+        assert!(maintenance.rebuild("orders", None).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_removes_orphaned_rows() {
+        let (_dir, _pool, connection) = create_test_db();
+        insert_customer(&connection, "王建国", "华东板材");
+        connection
+            .execute(
+                "INSERT INTO customers_fts (customer_id, name, company)
+                 VALUES ('deleted', '已删除客户', '旧公司')",
+                [],
+            )
+            .unwrap();
+        let maintenance = FtsMaintenance::new(connection);
+        assert!(!maintenance.verify().unwrap()[0].is_consistent());
+
+        let status = maintenance.rebuild("customers_fts", None).unwrap();
+        assert_eq!(status.indexed_rows, 1);
+        assert!(status.is_consistent());
+    }
+}
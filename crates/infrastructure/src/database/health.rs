@@ -3,19 +3,58 @@
 //! 提供数据库连接状态监控和健康检查功能。
 
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use minicrm_core::{Clock, SystemClock};
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info, warn};
 
 use super::connection::DatabaseConnection;
+use super::fts::{self, FtsMaintenance};
 use super::pool::{DatabasePool, DatabasePoolExt, PoolStats};
 
+/// 默认深度检查间隔（小时）
+pub const DEFAULT_DEEP_CHECK_INTERVAL_HOURS: i64 = 24;
+
+/// WAL文件大小告警阈值（MB）
+const WAL_WARNING_MB: f64 = 256.0;
+
 /// 数据库健康检查器
-#[derive(Debug)]
+///
+/// 常规检查只执行开销小的项目（连接、`quick_check`、WAL和磁盘），
+/// 全量完整性、外键和全文索引检查属于深度检查，结果缓存在检查器中。
 pub struct DatabaseHealthChecker {
     connection: DatabaseConnection,
     pool: DatabasePool,
+    clock: Arc<dyn Clock>,
+    deep_interval: Duration,
+    last_deep: Mutex<Option<DeepCheckCache>>,
+}
+
+impl std::fmt::Debug for DatabaseHealthChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseHealthChecker")
+            .field("deep_interval", &self.deep_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 缓存的深度检查结果
+#[derive(Debug, Clone)]
+struct DeepCheckCache {
+    checked_at: DateTime<Utc>,
+    checks: Vec<HealthCheck>,
+}
+
+/// 检查层级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheckTier {
+    /// 常规检查（每次都执行）
+    Cheap,
+    /// 深度检查（扫描整个数据库文件）
+    Deep,
 }
 
 /// 健康检查结果
@@ -33,6 +72,10 @@ pub struct HealthCheckResult {
     pub checks: Vec<HealthCheck>,
     /// 错误信息（如果有）
     pub error: Option<String>,
+    /// 最近一次深度检查时间（从未执行时为空）
+    pub deep_checked_at: Option<DateTime<Utc>>,
+    /// 深度检查结果的缓存时长（秒），本次刚执行时为0
+    pub deep_result_age_secs: Option<i64>,
 }
 
 /// 连接池健康状态
@@ -51,6 +94,8 @@ pub struct PoolHealthStatus {
 pub struct HealthCheck {
     /// 检查名称
     pub name: String,
+    /// 产生该项结果的检查层级
+    pub tier: HealthCheckTier,
     /// 检查是否通过
     pub passed: bool,
     /// 检查耗时（毫秒）
@@ -64,15 +109,59 @@ pub struct HealthCheck {
 impl DatabaseHealthChecker {
     /// 创建新的健康检查器
     pub fn new(connection: DatabaseConnection, pool: DatabasePool) -> Self {
-        Self { connection, pool }
+        Self {
+            connection,
+            pool,
+            clock: Arc::new(SystemClock),
+            deep_interval: Duration::hours(DEFAULT_DEEP_CHECK_INTERVAL_HOURS),
+            last_deep: Mutex::new(None),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
-    /// 执行完整的健康检查
+    /// 设置周期检查中深度检查的间隔
+    pub fn with_deep_interval(mut self, interval: Duration) -> Self {
+        self.deep_interval = interval;
+        self
+    }
+
+    /// 执行常规健康检查
+    ///
+    /// 只运行开销小的检查和 `PRAGMA quick_check`，附带缓存的深度检查结果。
     pub fn check_health(&self) -> HealthCheckResult {
+        self.run_checks(false)
+    }
+
+    /// 执行深度健康检查（全量完整性、外键和全文索引检查），并缓存结果
+    pub fn check_health_deep(&self) -> HealthCheckResult {
+        self.run_checks(true)
+    }
+
+    /// 周期监控使用的检查：距上次深度检查超过间隔时执行深度检查
+    pub fn check_health_periodic(&self) -> HealthCheckResult {
+        self.run_checks(self.deep_check_due())
+    }
+
+    /// 是否需要执行深度检查
+    pub fn deep_check_due(&self) -> bool {
+        self.cached_deep()
+            .is_none_or(|cache| self.clock.now() - cache.checked_at >= self.deep_interval)
+    }
+
+    fn cached_deep(&self) -> Option<DeepCheckCache> {
+        self.last_deep.lock().ok().and_then(|cache| cache.clone())
+    }
+
+    fn run_checks(&self, deep: bool) -> HealthCheckResult {
         let start_time = Instant::now();
-        let timestamp = Utc::now();
+        let timestamp = self.clock.now();
 
-        debug!("开始数据库健康检查");
+        debug!("开始数据库健康检查（深度: {}）", deep);
 
         let mut checks = Vec::new();
         let mut overall_healthy = true;
@@ -92,8 +181,8 @@ impl DatabaseHealthChecker {
         }
         checks.push(connection_check);
 
-        // 3. 数据库完整性检查
-        let integrity_check = self.check_database_integrity();
+        // 3. 快速完整性检查
+        let integrity_check = self.check_quick_integrity();
         if !integrity_check.passed {
             overall_healthy = false;
         }
@@ -114,6 +203,39 @@ impl DatabaseHealthChecker {
         }
         checks.push(disk_check);
 
+        // 6. WAL文件大小检查
+        let wal_check = self.check_wal_size();
+        if !wal_check.passed {
+            warn!("WAL文件过大: {:?}", wal_check.error);
+        }
+        checks.push(wal_check);
+
+        // 7. 深度检查（执行或取缓存）
+        let deep_result = if deep {
+            let deep_checks = vec![
+                self.check_full_integrity(),
+                self.check_foreign_keys(),
+                self.check_search_index(),
+            ];
+            let cache = DeepCheckCache {
+                checked_at: timestamp,
+                checks: deep_checks,
+            };
+            if let Ok(mut last) = self.last_deep.lock() {
+                *last = Some(cache.clone());
+            }
+            info!("数据库深度检查完成");
+            Some(cache)
+        } else {
+            self.cached_deep()
+        };
+        if let Some(cache) = &deep_result {
+            if cache.checks.iter().any(|check| !check.passed) {
+                overall_healthy = false;
+            }
+            checks.extend(cache.checks.iter().cloned());
+        }
+
         let response_time_ms = start_time.elapsed().as_millis() as u64;
 
         let result = HealthCheckResult {
@@ -123,6 +245,10 @@ impl DatabaseHealthChecker {
             response_time_ms,
             checks,
             error: error_message,
+            deep_checked_at: deep_result.as_ref().map(|cache| cache.checked_at),
+            deep_result_age_secs: deep_result
+                .as_ref()
+                .map(|cache| (timestamp - cache.checked_at).num_seconds()),
         };
 
         if overall_healthy {
@@ -137,7 +263,9 @@ impl DatabaseHealthChecker {
     /// 检查连接池健康状态
     fn check_pool_health(&self) -> PoolHealthStatus {
         let stats = self.pool.get_stats();
-        let utilization = (stats.connections as f64 / stats.max_connections as f64) * 100.0;
+        // 空闲连接不算占用
+        let in_use = stats.connections.saturating_sub(stats.idle_connections);
+        let utilization = (in_use as f64 / stats.max_connections as f64) * 100.0;
 
         // 连接池使用率超过90%认为不健康
         let healthy = utilization < 90.0 && self.pool.health_check().is_ok();
@@ -162,6 +290,7 @@ impl DatabaseHealthChecker {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
                 HealthCheck {
                     name,
+                    tier: HealthCheckTier::Cheap,
                     passed: result == 1,
                     duration_ms,
                     details: Some(format!("查询结果: {}", result)),
@@ -172,6 +301,7 @@ impl DatabaseHealthChecker {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
                 HealthCheck {
                     name,
+                    tier: HealthCheckTier::Cheap,
                     passed: false,
                     duration_ms,
                     details: None,
@@ -181,37 +311,78 @@ impl DatabaseHealthChecker {
         }
     }
 
-    /// 数据库完整性检查
-    fn check_database_integrity(&self) -> HealthCheck {
+    /// 快速完整性检查（`PRAGMA quick_check`，不校验索引内容）
+    fn check_quick_integrity(&self) -> HealthCheck {
         let start_time = Instant::now();
-        let name = "数据库完整性检查".to_string();
+        let outcome = self.pragma_rows("PRAGMA quick_check").map(|rows| {
+            let passed = rows == ["ok"];
+            (passed, rows.join("; "))
+        });
+        finish_check("快速完整性检查", HealthCheckTier::Cheap, start_time, outcome)
+    }
 
-        match self
-            .connection
-            .query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0))
-        {
-            Ok(result) => {
-                let duration_ms = start_time.elapsed().as_millis() as u64;
-                let passed = result == "ok";
-                HealthCheck {
-                    name,
-                    passed,
-                    duration_ms,
-                    details: Some(format!("完整性检查结果: {}", result)),
-                    error: if passed { None } else { Some(result) },
-                }
-            }
-            Err(e) => {
-                let duration_ms = start_time.elapsed().as_millis() as u64;
-                HealthCheck {
-                    name,
-                    passed: false,
-                    duration_ms,
-                    details: None,
-                    error: Some(e.to_string()),
-                }
+    /// 全量完整性检查（`PRAGMA integrity_check`）
+    fn check_full_integrity(&self) -> HealthCheck {
+        let start_time = Instant::now();
+        let outcome = self.pragma_rows("PRAGMA integrity_check").map(|rows| {
+            let passed = rows == ["ok"];
+            (passed, rows.join("; "))
+        });
+        finish_check("数据库完整性检查", HealthCheckTier::Deep, start_time, outcome)
+    }
+
+    /// 外键一致性检查
+    fn check_foreign_keys(&self) -> HealthCheck {
+        let start_time = Instant::now();
+        let outcome = self.pragma_rows("PRAGMA foreign_key_check").map(|rows| {
+            if rows.is_empty() {
+                (true, "未发现外键违规".to_string())
+            } else {
+                (false, format!("{} 条外键违规，涉及表: {}", rows.len(), rows.join(", ")))
             }
-        }
+        });
+        finish_check("外键检查", HealthCheckTier::Deep, start_time, outcome)
+    }
+
+    /// 全文索引与基础表的一致性检查
+    fn check_search_index(&self) -> HealthCheck {
+        let start_time = Instant::now();
+        let outcome = FtsMaintenance::new(self.connection.clone())
+            .verify()
+            .map(|statuses| {
+                let passed = statuses.iter().all(|status| status.is_consistent());
+                let details = if statuses.is_empty() {
+                    "未建立全文索引".to_string()
+                } else {
+                    statuses.iter().map(fts::describe).collect::<Vec<_>>().join("; ")
+                };
+                (passed, details)
+            });
+        finish_check("全文索引检查", HealthCheckTier::Deep, start_time, outcome)
+    }
+
+    /// WAL文件大小检查
+    fn check_wal_size(&self) -> HealthCheck {
+        let start_time = Instant::now();
+        let outcome = self
+            .connection
+            .query_row("PRAGMA database_list", [], |row| row.get::<_, String>(2))
+            .map(|file| {
+                let wal_bytes = if file.is_empty() {
+                    0
+                } else {
+                    std::fs::metadata(format!("{}-wal", file)).map_or(0, |m| m.len())
+                };
+                let wal_mb = wal_bytes as f64 / (1024.0 * 1024.0);
+                (wal_mb < WAL_WARNING_MB, format!("WAL文件大小: {:.2} MB", wal_mb))
+            });
+        finish_check("WAL文件检查", HealthCheckTier::Cheap, start_time, outcome)
+    }
+
+    /// 执行PRAGMA并收集每行第一列
+    fn pragma_rows(&self, sql: &str) -> Result<Vec<String>> {
+        self.connection
+            .query_map(sql, [], |row| row.get::<_, String>(0))
     }
 
     /// 性能检查
@@ -231,6 +402,7 @@ impl DatabaseHealthChecker {
                 let passed = duration_ms < 100;
                 HealthCheck {
                     name,
+                    tier: HealthCheckTier::Cheap,
                     passed,
                     duration_ms,
                     details: Some(format!(
@@ -248,6 +420,7 @@ impl DatabaseHealthChecker {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
                 HealthCheck {
                     name,
+                    tier: HealthCheckTier::Cheap,
                     passed: false,
                     duration_ms,
                     details: None,
@@ -283,6 +456,7 @@ impl DatabaseHealthChecker {
 
                 HealthCheck {
                     name,
+                    tier: HealthCheckTier::Cheap,
                     passed,
                     duration_ms,
                     details: Some(format!(
@@ -300,6 +474,7 @@ impl DatabaseHealthChecker {
                 let duration_ms = start_time.elapsed().as_millis() as u64;
                 HealthCheck {
                     name,
+                    tier: HealthCheckTier::Cheap,
                     passed: false,
                     duration_ms,
                     details: None,
@@ -352,6 +527,34 @@ impl DatabaseHealthChecker {
     }
 }
 
+/// 根据检查结果（是否通过、详情）生成检查项
+fn finish_check(
+    name: &str,
+    tier: HealthCheckTier,
+    start_time: Instant,
+    outcome: Result<(bool, String)>,
+) -> HealthCheck {
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+    match outcome {
+        Ok((passed, details)) => HealthCheck {
+            name: name.to_string(),
+            tier,
+            passed,
+            duration_ms,
+            error: if passed { None } else { Some(details.clone()) },
+            details: Some(details),
+        },
+        Err(e) => HealthCheck {
+            name: name.to_string(),
+            tier,
+            passed: false,
+            duration_ms,
+            details: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
 /// 数据库统计信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseStats {
@@ -372,39 +575,31 @@ pub struct DatabaseStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::database::pool::{DatabasePoolBuilder, DatabasePoolConfig};
-    use tempfile::tempdir;
-
-    fn create_test_health_checker() -> DatabaseHealthChecker {
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir.path().join("test.db");
-
-        let config = DatabasePoolConfig {
-            database_path: db_path.to_string_lossy().to_string(),
-            ..Default::default()
-        };
+    use crate::database::pool::DatabasePoolBuilder;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::ManualClock;
+    use tempfile::{tempdir, TempDir};
 
-        let pool = DatabasePoolBuilder::new(config).build().unwrap();
+    fn create_test_health_checker() -> (TempDir, DatabaseHealthChecker) {
+        let (temp_dir, pool) = migrated_pool();
         let connection = DatabaseConnection::new(pool.clone());
-
-        DatabaseHealthChecker::new(connection, pool)
+        (temp_dir, DatabaseHealthChecker::new(connection, pool))
     }
 
     #[tokio::test]
     async fn test_health_check() {
-        let checker = create_test_health_checker();
+        let (_dir, checker) = create_test_health_checker();
 
         let result = checker.check_health();
 
         // 基本健康检查应该通过
         assert!(result.healthy);
-        assert!(result.response_time_ms > 0);
         assert!(!result.checks.is_empty());
     }
 
     #[tokio::test]
     async fn test_quick_health_check() {
-        let checker = create_test_health_checker();
+        let (_dir, checker) = create_test_health_checker();
 
         let result = checker.quick_health_check().unwrap();
         assert!(result);
@@ -412,7 +607,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_database_stats() {
-        let checker = create_test_health_checker();
+        let (_dir, checker) = create_test_health_checker();
 
         let stats = checker.get_database_stats().unwrap();
 
@@ -421,4 +616,102 @@ mod tests {
         assert!(stats.page_size > 0);
         assert!(stats.database_size_bytes > 0);
     }
+
+    fn create_tiered_checker() -> (TempDir, DatabasePool, DatabaseHealthChecker) {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        // 单连接，便于在唯一的连接上安装SQL跟踪
+        let pool = DatabasePoolBuilder::new(db_path.to_string_lossy())
+            .max_connections(1)
+            .build()
+            .unwrap();
+        let checker = DatabaseHealthChecker::new(DatabaseConnection::new(pool.clone()), pool.clone());
+        (temp_dir, pool, checker)
+    }
+
+    fn tiers(result: &HealthCheckResult, tier: HealthCheckTier) -> Vec<String> {
+        result
+            .checks
+            .iter()
+            .filter(|check| check.tier == tier)
+            .map(|check| check.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_tier_selection() {
+        let (_dir, _pool, checker) = create_tiered_checker();
+
+        let cheap = checker.check_health();
+        assert!(tiers(&cheap, HealthCheckTier::Deep).is_empty());
+        assert!(tiers(&cheap, HealthCheckTier::Cheap).contains(&"快速完整性检查".to_string()));
+        assert_eq!(cheap.deep_checked_at, None);
+
+        let deep = checker.check_health_deep();
+        assert!(deep.healthy);
+        assert_eq!(
+            tiers(&deep, HealthCheckTier::Deep),
+            vec![
+                "数据库完整性检查".to_string(),
+                "外键检查".to_string(),
+                "全文索引检查".to_string()
+            ]
+        );
+        assert_eq!(deep.deep_result_age_secs, Some(0));
+
+        // 常规检查附带缓存的深度结果
+        let cached = checker.check_health();
+        assert_eq!(tiers(&cached, HealthCheckTier::Deep).len(), 3);
+        assert_eq!(cached.deep_checked_at, deep.deep_checked_at);
+    }
+
+    #[test]
+    fn test_deep_cache_expiry() {
+        let (_dir, _pool, checker) = create_tiered_checker();
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let checker = checker
+            .with_clock(clock.clone())
+            .with_deep_interval(Duration::hours(24));
+
+        assert!(checker.deep_check_due());
+        let first = checker.check_health_periodic();
+        assert_eq!(first.deep_result_age_secs, Some(0));
+
+        clock.advance(Duration::hours(23));
+        assert!(!checker.deep_check_due());
+        let cached = checker.check_health_periodic();
+        assert_eq!(cached.deep_result_age_secs, Some(23 * 3600));
+        assert_eq!(cached.deep_checked_at, first.deep_checked_at);
+
+        clock.advance(Duration::hours(1));
+        assert!(checker.deep_check_due());
+        let refreshed = checker.check_health_periodic();
+        assert_eq!(refreshed.deep_result_age_secs, Some(0));
+        assert_eq!(refreshed.deep_checked_at, Some(clock.now()));
+    }
+
+    static TRACED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn record_sql(sql: &str) {
+        TRACED.lock().unwrap().push(sql.to_string());
+    }
+
+    fn traced(pragma: &str) -> bool {
+        TRACED.lock().unwrap().iter().any(|sql| sql.contains(pragma))
+    }
+
+    #[test]
+    fn test_cheap_path_runs_quick_check() {
+        let (_dir, pool, checker) = create_tiered_checker();
+        pool.get().unwrap().trace(Some(record_sql));
+
+        checker.check_health();
+        assert!(traced("PRAGMA quick_check"));
+        assert!(!traced("PRAGMA integrity_check"));
+        assert!(!traced("PRAGMA foreign_key_check"));
+
+        checker.check_health_deep();
+        assert!(traced("PRAGMA integrity_check"));
+        assert!(traced("PRAGMA foreign_key_check"));
+    }
 }
@@ -1,13 +1,25 @@
 //! 数据库迁移管理
 //!
 //! 提供数据库schema版本管理和自动迁移功能。
+//!
+//! [`MigrationManager::verify_reversibility`] 在内存中的临时数据库上逐个检查迁移的回滚SQL，
+//! 诊断信息界面和 `minicrm migrate --verify` 显示检查报告。
+//!
+//! 引入迁移之前的版本直接建表，数据库中没有迁移记录。[`MigrationManager::baseline_if_needed`]
+//! 核对这类数据库的结构后补写迁移记录，之后的迁移才不会重复建表。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::Sender;
+use std::time::Instant;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use minicrm_core::{MigrationCheck, MigrationReversibility, ReversibilityReport};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
 use super::connection::DatabaseConnection;
+use super::pool::{DatabasePoolBuilder, PoolConfig};
 
 /// 数据库迁移管理器
 pub struct MigrationManager {
@@ -43,6 +55,107 @@ pub struct MigrationRecord {
     pub execution_time_ms: u64,
 }
 
+/// 引入迁移之前的版本创建的业务表
+const LEGACY_TABLES: [&str; 3] = ["customers", "tasks", "quotes"];
+
+/// 迁移进度事件
+///
+/// 首次启动新版本时迁移可能耗时较长，界面据此显示启动进度；未连接界面时只写日志。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationProgress {
+    /// 开始迁移
+    Started {
+        /// 当前版本
+        from_version: u32,
+        /// 目标版本
+        to_version: u32,
+        /// 待执行的迁移数
+        total: usize,
+    },
+    /// 开始执行单个迁移
+    Applying {
+        /// 序号（从1开始）
+        index: usize,
+        /// 待执行的迁移数
+        total: usize,
+        /// 迁移版本号
+        version: u32,
+        /// 迁移名称
+        name: String,
+    },
+    /// 单个迁移执行完成
+    Applied {
+        /// 序号（从1开始）
+        index: usize,
+        /// 待执行的迁移数
+        total: usize,
+        /// 迁移版本号
+        version: u32,
+        /// 执行耗时（毫秒）
+        elapsed_ms: u64,
+    },
+    /// 全部迁移完成
+    Finished {
+        /// 当前版本
+        version: u32,
+    },
+}
+
+impl MigrationProgress {
+    /// 进度说明文本
+    pub fn message(&self) -> String {
+        match self {
+            Self::Started {
+                from_version,
+                to_version,
+                total,
+            } => format!(
+                "开始数据库迁移：从版本 {} 到版本 {}，共 {} 项",
+                from_version, to_version, total
+            ),
+            Self::Applying {
+                index,
+                total,
+                version,
+                name,
+            } => format!("({}/{}) 正在执行迁移 v{}: {}", index, total, version, name),
+            Self::Applied {
+                index,
+                total,
+                version,
+                elapsed_ms,
+            } => format!(
+                "({}/{}) 迁移 v{} 完成，耗时: {}ms",
+                index, total, version, elapsed_ms
+            ),
+            Self::Finished { version } => format!("数据库迁移完成，当前版本: {}", version),
+        }
+    }
+
+    /// 已完成的比例（0.0 - 1.0）
+    pub fn fraction(&self) -> f32 {
+        match self {
+            Self::Started { .. } => 0.0,
+            Self::Applying { index, total, .. } => (*index - 1) as f32 / (*total).max(1) as f32,
+            Self::Applied { index, total, .. } => *index as f32 / (*total).max(1) as f32,
+            Self::Finished { .. } => 1.0,
+        }
+    }
+}
+
+/// 进度输出：总是写日志，连接了界面时同时发送事件
+struct ProgressSink<'a>(Option<&'a Sender<MigrationProgress>>);
+
+impl ProgressSink<'_> {
+    fn emit(&self, event: MigrationProgress) {
+        info!("{}", event.message());
+        if let Some(tx) = self.0 {
+            // 界面已关闭时忽略，迁移继续执行
+            let _ = tx.send(event);
+        }
+    }
+}
+
 impl MigrationManager {
     /// 创建新的迁移管理器
     pub fn new(connection: DatabaseConnection) -> Self {
@@ -52,6 +165,28 @@ impl MigrationManager {
         }
     }
 
+    /// 在内存中的临时数据库上创建迁移管理器（检查迁移用，不读写任何数据库文件）
+    ///
+    /// # Errors
+    ///
+    /// 无法创建临时数据库时返回错误。
+    pub fn in_memory() -> Result<Self> {
+        // 内存数据库随连接关闭而消失，只用一个连接且不回收
+        let pool = DatabasePoolBuilder::new(":memory:")
+            .with_config(PoolConfig {
+                max_connections: 1,
+                min_idle: Some(1),
+                idle_timeout: None,
+                max_lifetime: None,
+                warm_up: 0,
+                enable_mmap: false,
+                ..PoolConfig::default()
+            })
+            .build()
+            .context("无法创建临时数据库")?;
+        Ok(Self::new(DatabaseConnection::new(pool)))
+    }
+
     /// 添加迁移
     pub fn add_migration(mut self, migration: Migration) -> Self {
         self.migrations.push(migration);
@@ -89,28 +224,25 @@ impl MigrationManager {
         Ok(())
     }
 
-    /// 获取当前数据库版本
+    /// 获取当前数据库版本（迁移记录表不存在时为0）
     pub fn get_current_version(&self) -> Result<u32> {
-        match self
+        if !self.connection.table_exists("schema_migrations")? {
+            return Ok(0);
+        }
+        let version = self
             .connection
             .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
                 row.get::<_, Option<u32>>(0)
-            }) {
-            Ok(Some(version)) => Ok(version),
-            Ok(None) => Ok(0), // 没有迁移记录，版本为0
-            Err(_e) => {
-                // 如果表不存在，返回版本0
-                if _e.to_string().contains("no such table") {
-                    Ok(0)
-                } else {
-                    Err(_e)
-                }
-            }
-        }
+            })?;
+        // 没有迁移记录，版本为0
+        Ok(version.unwrap_or(0))
     }
 
     /// 获取已应用的迁移记录
     pub fn get_applied_migrations(&self) -> Result<Vec<MigrationRecord>> {
+        if !self.connection.table_exists("schema_migrations")? {
+            return Ok(Vec::new());
+        }
         self.connection.query_map(
             "SELECT version, name, applied_at, execution_time_ms FROM schema_migrations ORDER BY version",
             [],
@@ -129,40 +261,123 @@ impl MigrationManager {
         )
     }
 
+    /// 为引入迁移之前创建的数据库补写迁移记录
+    ///
+    /// 只处理旧版数据库：业务表（客户、任务、报价）已存在，但迁移记录表不存在或为空。
+    /// 先核对实际结构（表的列、外键和索引）与迁移到 `assumed_version` 后的结构一致，再写入
+    /// 该版本及之前各迁移的记录，不执行迁移SQL。返回是否补写了记录。
+    ///
+    /// # Errors
+    ///
+    /// 结构不一致时返回错误且不写入任何记录，不会猜测如何升级；查询失败时返回错误。
+    pub fn baseline_if_needed(&self, assumed_version: u32) -> Result<bool> {
+        for table in LEGACY_TABLES {
+            if !self.connection.table_exists(table)? {
+                return Ok(false);
+            }
+        }
+        // 迁移记录表不存在时版本为0
+        if self.get_current_version()? > 0 {
+            return Ok(false);
+        }
+
+        let expected = self.expected_schema(assumed_version)?;
+        let actual = SchemaSnapshot::capture(&self.connection)?;
+        let differences = actual.differences(&expected);
+        if !differences.is_empty() {
+            anyhow::bail!(
+                "旧版数据库的结构与 v{} 不一致（{}），无法自动升级。请先复制保存当前数据库文件，\
+                 再从备份目录恢复最近一次正常使用时的备份，或联系技术支持",
+                assumed_version,
+                differences.join("、")
+            );
+        }
+
+        self.initialize()?;
+        let baseline: Vec<&Migration> = self
+            .migrations
+            .iter()
+            .filter(|m| m.version <= assumed_version)
+            .collect();
+        let applied_at = Utc::now().to_rfc3339();
+        self.connection.with_transaction(|tx| {
+            for migration in &baseline {
+                tx.execute(
+                    "INSERT INTO schema_migrations (version, name, applied_at, execution_time_ms)
+                     VALUES (?1, ?2, ?3, 0)",
+                    rusqlite::params![migration.version, migration.name, applied_at],
+                )?;
+            }
+            Ok(())
+        })?;
+        info!(
+            "检测到引入迁移之前创建的数据库，结构与 v{} 一致，已补写 {} 条迁移记录（未执行迁移SQL）",
+            assumed_version,
+            baseline.len()
+        );
+        Ok(true)
+    }
+
+    /// 迁移到 `version` 后的结构（在内存中的临时数据库上执行）
+    fn expected_schema(&self, version: u32) -> Result<SchemaSnapshot> {
+        let scratch = self.scratch()?;
+        for migration in self.migrations.iter().take_while(|m| m.version <= version) {
+            scratch
+                .apply_migration(migration)
+                .with_context(|| format!("迁移 v{} 执行失败", migration.version))?;
+        }
+        SchemaSnapshot::capture(&scratch.connection)
+    }
+
     /// 执行迁移到指定版本
     ///
     /// # Arguments
     ///
     /// * `target_version` - 目标版本，None表示迁移到最新版本
     pub fn migrate(&self, target_version: Option<u32>) -> Result<()> {
+        self.run(target_version, ProgressSink(None))
+    }
+
+    /// 执行迁移并通过 `tx` 报告进度
+    ///
+    /// 每个迁移在独立事务中执行，迁移开始后不能取消。
+    pub fn migrate_with_progress(
+        &self,
+        target_version: Option<u32>,
+        tx: Sender<MigrationProgress>,
+    ) -> Result<()> {
+        self.run(target_version, ProgressSink(Some(&tx)))
+    }
+
+    fn run(&self, target_version: Option<u32>, sink: ProgressSink<'_>) -> Result<()> {
         self.initialize()?;
 
         let current_version = self.get_current_version()?;
         let target = target_version
             .unwrap_or_else(|| self.migrations.iter().map(|m| m.version).max().unwrap_or(0));
 
-        info!(
-            "开始数据库迁移：从版本 {} 到版本 {}",
-            current_version, target
-        );
-
         if current_version == target {
             info!("数据库已是最新版本 {}", target);
             return Ok(());
         }
 
         if current_version < target {
-            self.migrate_up(current_version, target)?;
+            self.migrate_up(current_version, target, &sink)?;
         } else {
-            self.migrate_down(current_version, target)?;
+            self.migrate_down(current_version, target, &sink)?;
         }
 
-        info!("数据库迁移完成，当前版本: {}", target);
+        sink.emit(MigrationProgress::Finished { version: target });
         Ok(())
     }
 
     /// 向上迁移
-    fn migrate_up(&self, from_version: u32, to_version: u32) -> Result<()> {
+    fn migrate_up(
+        &self,
+        from_version: u32,
+        to_version: u32,
+        sink: &ProgressSink<'_>,
+    ) -> Result<()> {
         let migrations_to_apply: Vec<_> = self
             .migrations
             .iter()
@@ -174,15 +389,18 @@ impl MigrationManager {
             return Ok(());
         }
 
-        for migration in migrations_to_apply {
-            self.apply_migration(migration)?;
-        }
-
-        Ok(())
+        self.run_steps(from_version, to_version, &migrations_to_apply, sink, |m| {
+            self.apply_migration(m)
+        })
     }
 
     /// 向下迁移
-    fn migrate_down(&self, from_version: u32, to_version: u32) -> Result<()> {
+    fn migrate_down(
+        &self,
+        from_version: u32,
+        to_version: u32,
+        sink: &ProgressSink<'_>,
+    ) -> Result<()> {
         let migrations_to_revert: Vec<_> = self
             .migrations
             .iter()
@@ -198,22 +416,54 @@ impl MigrationManager {
             return Ok(());
         }
 
-        for migration in migrations_to_revert {
-            self.revert_migration(migration)?;
-        }
+        self.run_steps(from_version, to_version, &migrations_to_revert, sink, |m| {
+            self.revert_migration(m)
+        })
+    }
 
+    /// 依次执行迁移步骤并报告进度
+    fn run_steps(
+        &self,
+        from_version: u32,
+        to_version: u32,
+        migrations: &[&Migration],
+        sink: &ProgressSink<'_>,
+        step: impl Fn(&Migration) -> Result<()>,
+    ) -> Result<()> {
+        let total = migrations.len();
+        sink.emit(MigrationProgress::Started {
+            from_version,
+            to_version,
+            total,
+        });
+        for (i, migration) in migrations.iter().enumerate() {
+            sink.emit(MigrationProgress::Applying {
+                index: i + 1,
+                total,
+                version: migration.version,
+                name: migration.name.clone(),
+            });
+            let started = Instant::now();
+            step(migration)?;
+            sink.emit(MigrationProgress::Applied {
+                index: i + 1,
+                total,
+                version: migration.version,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            });
+        }
         Ok(())
     }
 
     /// 应用单个迁移
     fn apply_migration(&self, migration: &Migration) -> Result<()> {
-        info!("应用迁移 v{}: {}", migration.version, migration.name);
+        debug!("应用迁移 v{}: {}", migration.version, migration.name);
 
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         self.connection.with_transaction(|tx| {
-            // 执行迁移SQL
-            tx.execute(&migration.up_sql, [])?;
+            // 执行迁移SQL（可包含多条语句）
+            tx.execute_batch(&migration.up_sql)?;
 
             // 记录迁移
             let execution_time = start_time.elapsed().as_millis() as u64;
@@ -230,11 +480,6 @@ impl MigrationManager {
             Ok(())
         })?;
 
-        info!(
-            "迁移 v{} 应用成功，耗时: {}ms",
-            migration.version,
-            start_time.elapsed().as_millis()
-        );
         Ok(())
     }
 
@@ -247,11 +492,11 @@ impl MigrationManager {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("迁移 v{} 没有提供回滚SQL", migration.version))?;
 
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         self.connection.with_transaction(|tx| {
-            // 执行回滚SQL
-            tx.execute(down_sql, [])?;
+            // 执行回滚SQL（可包含多条语句）
+            tx.execute_batch(down_sql)?;
 
             // 删除迁移记录
             tx.execute(
@@ -297,6 +542,233 @@ impl MigrationManager {
             is_up_to_date: current_version == latest_version,
         })
     }
+
+    /// 检查每个迁移能否回滚
+    ///
+    /// 在内存中的临时数据库上依次执行每个迁移，有回滚SQL时回滚后再重新迁移：回滚后的结构与
+    /// 迁移前不同、或重新迁移后与直接迁移不同时记为有误。回滚会删除表或列时只做标记。
+    /// 不读写当前数据库。
+    ///
+    /// # Errors
+    ///
+    /// 无法创建临时数据库或迁移本身执行失败时返回错误。
+    pub fn verify_reversibility(&self) -> Result<ReversibilityReport> {
+        let mut scratch = self.scratch()?;
+        let mut before = SchemaSnapshot::capture(&scratch.connection)?;
+        let mut checks = Vec::with_capacity(self.migrations.len());
+        for migration in &self.migrations {
+            scratch
+                .apply_migration(migration)
+                .with_context(|| format!("迁移 v{} 执行失败", migration.version))?;
+            let after = SchemaSnapshot::capture(&scratch.connection)?;
+            let result = scratch.check_revert(migration, &before, &after)?;
+            if matches!(result, MigrationReversibility::Broken(_)) {
+                // 回滚有误后临时数据库的状态不确定，重新建一个直接迁移到该版本
+                scratch = self.scratch()?;
+                for step in self.migrations.iter().take_while(|m| m.version <= migration.version) {
+                    scratch.apply_migration(step)?;
+                }
+            }
+            let drops = match result {
+                MigrationReversibility::Irreversible => Vec::new(),
+                _ => after.dropped_since(&before),
+            };
+            checks.push(MigrationCheck {
+                version: migration.version,
+                name: migration.name.clone(),
+                result,
+                drops,
+            });
+            before = after;
+        }
+        Ok(ReversibilityReport { checks })
+    }
+
+    /// 使用相同迁移的空白临时数据库
+    fn scratch(&self) -> Result<Self> {
+        let mut scratch = Self::in_memory()?;
+        scratch.migrations = self.migrations.clone();
+        scratch.initialize()?;
+        Ok(scratch)
+    }
+
+    /// 回滚刚执行的迁移并重新迁移，与迁移前后的结构比较
+    fn check_revert(
+        &self,
+        migration: &Migration,
+        before: &SchemaSnapshot,
+        after: &SchemaSnapshot,
+    ) -> Result<MigrationReversibility> {
+        if migration.down_sql.is_none() {
+            return Ok(MigrationReversibility::Irreversible);
+        }
+        if let Err(e) = self.revert_migration(migration) {
+            return Ok(MigrationReversibility::Broken(format!("回滚失败: {:#}", e)));
+        }
+        let reverted = SchemaSnapshot::capture(&self.connection)?;
+        let differences = reverted.differences(before);
+        if !differences.is_empty() {
+            return Ok(MigrationReversibility::Broken(format!(
+                "回滚后结构与迁移前不同: {}",
+                differences.join("、")
+            )));
+        }
+        if let Err(e) = self.apply_migration(migration) {
+            return Ok(MigrationReversibility::Broken(format!("重新迁移失败: {:#}", e)));
+        }
+        let differences = SchemaSnapshot::capture(&self.connection)?.differences(after);
+        if !differences.is_empty() {
+            return Ok(MigrationReversibility::Broken(format!(
+                "重新迁移后结构与直接迁移不同: {}",
+                differences.join("、")
+            )));
+        }
+        Ok(MigrationReversibility::Reversible)
+    }
+}
+
+/// 数据库结构快照
+///
+/// 普通表按列（名称、类型、非空、默认值、主键）和外键比较，索引按所在表、列和唯一性比较，
+/// 不受建表语句格式的影响；虚拟表、部分索引、触发器和视图按合并空白后的建表语句比较。
+/// 不含迁移记录表和SQLite内部表。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaSnapshot {
+    /// `类型 名称` → 结构描述
+    objects: BTreeMap<String, String>,
+    /// 普通表 → 列名
+    columns: BTreeMap<String, Vec<String>>,
+    /// 虚拟表（其影子表不计入删除的表）
+    virtual_tables: BTreeSet<String>,
+}
+
+impl SchemaSnapshot {
+    /// 读取当前数据库的结构
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn capture(connection: &DatabaseConnection) -> Result<Self> {
+        let rows = connection.query_map(
+            "SELECT type, name, tbl_name, sql FROM sqlite_master
+             WHERE name NOT LIKE 'sqlite_%' AND name <> 'schema_migrations'",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                ))
+            },
+        )?;
+        let mut snapshot = Self::default();
+        for (kind, name, table, sql) in rows {
+            let sql = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+            if kind == "index" && !sql.to_uppercase().contains(" WHERE ") {
+                let columns = connection.query_map(
+                    "SELECT name FROM pragma_index_info(?1) ORDER BY seqno",
+                    [&name],
+                    |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()),
+                )?;
+                let unique = sql.to_uppercase().starts_with("CREATE UNIQUE");
+                snapshot.objects.insert(
+                    format!("{} {}", kind, name),
+                    format!(
+                        "{}({}){}",
+                        table,
+                        columns.join(", "),
+                        if unique { " unique" } else { "" }
+                    ),
+                );
+                continue;
+            }
+            let definition = if kind == "table" && !sql.starts_with("CREATE VIRTUAL TABLE") {
+                let columns = connection.get_table_columns(&name)?;
+                let keys = connection.query_map(
+                    "SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?1)",
+                    [&name],
+                    |row| {
+                        Ok(format!(
+                            "{} -> {}.{}",
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, Option<String>>(2)?.unwrap_or_default()
+                        ))
+                    },
+                )?;
+                let definition = format!(
+                    "{}; {}",
+                    columns
+                        .iter()
+                        .map(|c| {
+                            format!(
+                                "{} {} {} {} {}",
+                                c.name,
+                                c.data_type,
+                                c.not_null,
+                                c.default_value.as_deref().unwrap_or("NULL"),
+                                c.primary_key
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    keys.join(", ")
+                );
+                snapshot
+                    .columns
+                    .insert(name.clone(), columns.into_iter().map(|c| c.name).collect());
+                definition
+            } else {
+                if kind == "table" {
+                    snapshot.virtual_tables.insert(name.clone());
+                }
+                sql
+            };
+            snapshot.objects.insert(format!("{} {}", kind, name), definition);
+        }
+        Ok(snapshot)
+    }
+
+    /// 与 `other` 不同的对象（`类型 名称`）
+    pub fn differences(&self, other: &Self) -> Vec<String> {
+        let mut keys: Vec<_> = self.objects.keys().chain(other.objects.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        keys.into_iter()
+            .filter(|key| self.objects.get(*key) != other.objects.get(*key))
+            .cloned()
+            .collect()
+    }
+
+    /// 相对 `before` 新增的表和列（`表` 或 `表.列`），回滚到 `before` 时其中的数据会被删除
+    pub fn dropped_since(&self, before: &Self) -> Vec<String> {
+        let mut drops = Vec::new();
+        for (table, columns) in &self.columns {
+            if self.is_shadow_table(table) {
+                continue;
+            }
+            match before.columns.get(table) {
+                None => drops.push(table.clone()),
+                Some(existing) => drops.extend(
+                    columns
+                        .iter()
+                        .filter(|column| !existing.contains(column))
+                        .map(|column| format!("{}.{}", table, column)),
+                ),
+            }
+        }
+        drops
+    }
+
+    /// 是否为虚拟表（如全文索引）自动创建的影子表
+    fn is_shadow_table(&self, table: &str) -> bool {
+        self.virtual_tables.iter().any(|name| {
+            table
+                .strip_prefix(name.as_str())
+                .is_some_and(|rest| rest.starts_with('_'))
+        })
+    }
 }
 
 /// 迁移状态
@@ -353,27 +825,17 @@ macro_rules! migration {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::database::pool::{DatabasePoolBuilder, DatabasePoolConfig};
-    use tempfile::tempdir;
-
-    fn create_test_migration_manager() -> MigrationManager {
-        let temp_dir = tempdir().unwrap();
-        let db_path = temp_dir.path().join("test.db");
+    use crate::test_support::empty_pool;
+    use tempfile::{tempdir, TempDir};
 
-        let config = DatabasePoolConfig {
-            database_path: db_path.to_string_lossy().to_string(),
-            ..Default::default()
-        };
-
-        let pool = DatabasePoolBuilder::new(config).build().unwrap();
-        let connection = DatabaseConnection::new(pool);
-
-        MigrationManager::new(connection)
+    fn create_test_migration_manager() -> (TempDir, MigrationManager) {
+        let (temp_dir, pool) = empty_pool();
+        (temp_dir, MigrationManager::new(DatabaseConnection::new(pool)))
     }
 
     #[tokio::test]
     async fn test_migration_initialization() {
-        let manager = create_test_migration_manager();
+        let (_dir, manager) = create_test_migration_manager();
 
         // 初始化应该成功
         manager.initialize().unwrap();
@@ -384,7 +846,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_migration_application() {
-        let manager = create_test_migration_manager().add_migration(migration!(
+        let (_dir, manager) = create_test_migration_manager();
+        let manager = manager.add_migration(migration!(
             1,
             "create_users_table",
             "创建用户表",
@@ -403,7 +866,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_migration_status() {
-        let manager = create_test_migration_manager()
+        let (_dir, manager) = create_test_migration_manager();
+        let manager = manager
             .add_migration(migration!(
                 1,
                 "create_users_table",
@@ -432,4 +896,276 @@ mod tests {
         assert!(!status.is_up_to_date);
         assert_eq!(status.pending_migrations.len(), 1);
     }
+
+    fn three_migrations() -> (TempDir, MigrationManager) {
+        let (temp_dir, manager) = create_test_migration_manager();
+        let manager = manager.add_migrations(vec![
+            migration!(
+                1,
+                "create_users",
+                "创建用户表",
+                "CREATE TABLE users (id INTEGER)"
+            ),
+            migration!(
+                2,
+                "create_posts",
+                "创建文章表",
+                "CREATE TABLE posts (id INTEGER)"
+            ),
+            migration!(
+                3,
+                "create_tags",
+                "创建标签表",
+                "CREATE TABLE tags (id INTEGER)"
+            ),
+        ]);
+        (temp_dir, manager)
+    }
+
+    #[test]
+    fn test_migration_progress_events() {
+        let (_dir, manager) = three_migrations();
+        let (tx, rx) = std::sync::mpsc::channel();
+        manager.migrate_with_progress(None, tx).unwrap();
+
+        let events: Vec<MigrationProgress> = rx.iter().collect();
+        assert_eq!(events.len(), 8);
+        assert_eq!(
+            events[0],
+            MigrationProgress::Started {
+                from_version: 0,
+                to_version: 3,
+                total: 3
+            }
+        );
+        for (i, version) in (1..=3).enumerate() {
+            assert!(matches!(
+                &events[1 + i * 2],
+                MigrationProgress::Applying { index, total: 3, version: v, .. }
+                    if *index == i + 1 && *v == version
+            ));
+            assert!(matches!(
+                &events[2 + i * 2],
+                MigrationProgress::Applied { index, total: 3, version: v, .. }
+                    if *index == i + 1 && *v == version
+            ));
+        }
+        assert_eq!(events[7], MigrationProgress::Finished { version: 3 });
+        // 第二项开始时已完成三分之一
+        assert!((events[3].fraction() - 1.0 / 3.0).abs() < f32::EPSILON);
+    }
+
+    /// 收集日志输出
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if let Ok(mut logs) = self.0.lock() {
+                logs.extend_from_slice(buf);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for CapturedLogs {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_migration_progress_logged_without_sink() {
+        let (_dir, manager) = three_migrations();
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || manager.migrate(None).unwrap());
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("共 3 项"));
+        assert!(output.contains("(2/3) 正在执行迁移 v2: create_posts"));
+        assert!(output.contains("(3/3) 迁移 v3 完成"));
+        assert!(output.contains("数据库迁移完成，当前版本: 3"));
+    }
+
+    #[test]
+    fn test_verify_reversibility() {
+        let report = MigrationManager::in_memory()
+            .unwrap()
+            .add_migrations(vec![
+                migration!(
+                    1,
+                    "create_customers",
+                    "创建客户表",
+                    "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT NOT NULL)"
+                ),
+                migration!(
+                    2,
+                    "customer_phone",
+                    "客户电话及索引",
+                    "ALTER TABLE customers ADD COLUMN phone TEXT;
+                     CREATE INDEX idx_customers_phone ON customers(phone);",
+                    "DROP INDEX idx_customers_phone;
+                     ALTER TABLE customers DROP COLUMN phone;"
+                ),
+                // 回滚漏删了索引
+                migration!(
+                    3,
+                    "customer_name_index",
+                    "客户名称索引",
+                    "CREATE INDEX idx_customers_name ON customers(name)",
+                    "SELECT 1"
+                ),
+                migration!(
+                    4,
+                    "create_notes",
+                    "创建备注表",
+                    "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)",
+                    "DROP TABLE notes"
+                ),
+                migration!(5, "rename_notes", "备注表改名", "DROP TABLE notes", "SELECT 1"),
+            ])
+            .verify_reversibility()
+            .unwrap();
+
+        let results: Vec<_> = report.checks.iter().map(|c| &c.result).collect();
+        assert_eq!(results[0], &MigrationReversibility::Irreversible);
+        assert_eq!(results[1], &MigrationReversibility::Reversible);
+        assert_eq!(report.checks[1].drops, vec!["customers.phone".to_string()]);
+        assert!(matches!(
+            results[2],
+            MigrationReversibility::Broken(reason) if reason.contains("index idx_customers_name")
+        ));
+        // 有误的迁移之后继续检查
+        assert_eq!(results[3], &MigrationReversibility::Reversible);
+        assert_eq!(report.checks[3].drops, vec!["notes".to_string()]);
+        assert!(!report.checks[4].destructive());
+        assert!(matches!(results[4], MigrationReversibility::Broken(_)));
+        assert_eq!(
+            report.broken().map(|c| c.version).collect::<Vec<_>>(),
+            vec![3, 5]
+        );
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn test_builtin_migrations_reversible() {
+        let report = MigrationManager::in_memory()
+            .unwrap()
+            .add_migrations(crate::database::schema::builtin_migrations())
+            .verify_reversibility()
+            .unwrap();
+        let broken: Vec<_> = report.broken().collect();
+        assert!(broken.is_empty(), "回滚有误的迁移: {:?}", broken);
+        assert_eq!(report.checks.len(), crate::database::schema::latest_version() as usize);
+        assert_eq!(report.checks[0].result, MigrationReversibility::Irreversible);
+        let last = report.checks.last().unwrap();
+        assert_eq!(last.drops, vec!["quote_items".to_string()]);
+    }
+    /// 引入迁移之前的版本建表的语句（与当时的 `DatabaseManager` 相同，格式与迁移v1不同）
+    const LEGACY_SCHEMA: &str = "
+        CREATE TABLE customers (id TEXT PRIMARY KEY, name TEXT NOT NULL, company TEXT,
+            email TEXT, phone TEXT, address TEXT, notes TEXT,
+            created_at TEXT NOT NULL, updated_at TEXT NOT NULL);
+        CREATE TABLE tasks (id TEXT PRIMARY KEY, customer_id TEXT NOT NULL, title TEXT NOT NULL,
+            description TEXT, status TEXT NOT NULL DEFAULT 'pending',
+            priority TEXT NOT NULL DEFAULT 'medium', due_date TEXT, completed_at TEXT,
+            created_at TEXT NOT NULL, updated_at TEXT NOT NULL,
+            FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE);
+        CREATE TABLE quotes (id TEXT PRIMARY KEY, customer_id TEXT NOT NULL, title TEXT NOT NULL,
+            description TEXT, total_amount REAL NOT NULL, currency TEXT NOT NULL DEFAULT 'CNY',
+            status TEXT NOT NULL DEFAULT 'draft', valid_until TEXT,
+            created_at TEXT NOT NULL, updated_at TEXT NOT NULL,
+            FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE);
+        CREATE INDEX idx_customers_email ON customers (email);
+        CREATE INDEX idx_customers_company ON customers (company);
+        CREATE INDEX idx_tasks_customer_id ON tasks (customer_id);
+        CREATE INDEX idx_tasks_status ON tasks (status);
+        CREATE INDEX idx_tasks_due_date ON tasks (due_date);
+        CREATE INDEX idx_quotes_customer_id ON quotes (customer_id);
+        CREATE INDEX idx_quotes_status ON quotes (status);
+        INSERT INTO customers (id, name, created_at, updated_at)
+            VALUES ('c1', '华南木业', '2023-05-01T00:00:00Z', '2023-05-01T00:00:00Z');
+    ";
+
+    /// 手工建立的旧版数据库，只带前两个内置迁移
+    fn legacy_database(dir: &tempfile::TempDir, extra_sql: &str) -> MigrationManager {
+        let pool = DatabasePoolBuilder::new(dir.path().join("legacy.db").to_string_lossy())
+            .build()
+            .unwrap();
+        let connection = DatabaseConnection::new(pool);
+        connection
+            .get_connection()
+            .unwrap()
+            .execute_batch(&format!("{}{}", LEGACY_SCHEMA, extra_sql))
+            .unwrap();
+        MigrationManager::new(connection).add_migrations(
+            crate::database::schema::builtin_migrations()
+                .into_iter()
+                .take(2)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_baseline_legacy_database() {
+        let dir = tempdir().unwrap();
+        let manager = legacy_database(&dir, "");
+
+        assert!(manager.baseline_if_needed(1).unwrap());
+        let applied = manager.get_applied_migrations().unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(
+            (applied[0].version, applied[0].name.as_str()),
+            (1, "baseline_core_tables")
+        );
+        // 已有迁移记录后不再处理
+        assert!(!manager.baseline_if_needed(1).unwrap());
+
+        // 之后的迁移正常执行，已有数据保留
+        manager.migrate(None).unwrap();
+        assert_eq!(manager.get_current_version().unwrap(), 2);
+        let columns = manager.connection.get_table_columns("customers").unwrap();
+        assert!(columns.iter().any(|c| c.name == "deleted_at"));
+        let counted: i64 = manager
+            .connection
+            .query_row(
+                "SELECT row_count FROM table_counters WHERE table_name = 'customers'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(counted, 1);
+
+        // 全新的空数据库不是旧版数据库
+        let (_empty_dir, empty) = create_test_migration_manager();
+        let empty = empty.add_migrations(crate::database::schema::builtin_migrations());
+        assert!(!empty.baseline_if_needed(1).unwrap());
+    }
+
+    #[test]
+    fn test_baseline_aborts_on_mutated_legacy_schema() {
+        let dir = tempdir().unwrap();
+        let manager = legacy_database(
+            &dir,
+            "ALTER TABLE customers ADD COLUMN wechat TEXT; DROP INDEX idx_quotes_status;",
+        );
+
+        let err = manager.baseline_if_needed(1).unwrap_err().to_string();
+        assert!(err.contains("table customers"), "{err}");
+        assert!(err.contains("index idx_quotes_status"), "{err}");
+        assert!(err.contains("恢复最近一次正常使用时的备份"), "{err}");
+        // 没有写入任何迁移记录
+        assert_eq!(manager.get_current_version().unwrap(), 0);
+        assert!(!manager.connection.table_exists("schema_migrations").unwrap());
+    }
 }
@@ -2,13 +2,30 @@
 //!
 //! 提供SQLite数据库连接、连接池管理和基础数据库操作。
 
+pub mod anonymize;
 pub mod connection;
+pub mod db_uuid;
+pub mod extensions;
+pub mod file_guard;
+pub mod fts;
 pub mod health;
 pub mod migrations;
 pub mod pool;
+pub mod pool_usage;
+pub mod schema;
+pub mod size_history;
+pub mod slow_query;
+pub mod time;
 
 // 重新导出主要类型
-pub use connection::DatabaseConnection;
-pub use health::DatabaseHealthChecker;
-pub use migrations::MigrationManager;
-pub use pool::{DatabasePool, DatabasePoolConfig};
+pub use anonymize::{anonymize_copy, AnonymizeReport};
+pub use connection::{DatabaseConnection, GuardedConnection};
+pub use db_uuid::DbUuid;
+pub use file_guard::{DatabaseFileGuard, ExternalChange, FileStamp, UnitOfWork};
+pub use fts::FtsMaintenance;
+pub use health::{DatabaseHealthChecker, HealthCheckTier};
+pub use migrations::{MigrationManager, MigrationProgress, SchemaSnapshot};
+pub use pool::{DatabasePool, DatabasePoolBuilder, PoolConfig};
+pub use pool_usage::{PoolUsageMetrics, PoolUsageStore, POOL_USAGE_HISTORY};
+pub use size_history::{DatabaseSizeMonitor, SizeHistoryStore, DEFAULT_SIZE_MONITOR_MINUTES};
+pub use slow_query::{SlowQueryLog, DEFAULT_SLOW_QUERY_THRESHOLD};
@@ -3,15 +3,21 @@
 //! 提供数据库连接池的创建、配置和管理功能。
 //! 使用 r2d2 连接池来管理 SQLite 连接。
 
-use std::time::Duration;
+use std::path::PathBuf;
+use std::sync::{Arc, Barrier};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::Connection;
-use tracing::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
 
-use crate::database::health::{DatabaseHealth, PoolStatus};
+use crate::database::extensions;
+use crate::database::pool_usage::PoolUsageMetrics;
+
+/// 预热时并行建立连接的线程数上限
+const MAX_WARM_UP_THREADS: u32 = 4;
 
 /// SQLite 数据库连接池类型别名
 pub type DatabasePool = Pool<SqliteConnectionManager>;
@@ -32,6 +38,12 @@ pub struct PoolConfig {
     pub idle_timeout: Option<u64>,
     /// 连接最大生命周期（秒）
     pub max_lifetime: Option<u64>,
+    /// 构建时预先建立并验证的连接数（0表示不预热）
+    pub warm_up: u32,
+    /// 是否启用内存映射读取（`PRAGMA mmap_size`），低内存机器可关闭
+    pub enable_mmap: bool,
+    /// 每个连接加载的SQLite扩展（需要 `sqlite-extensions` 功能，加载失败时忽略）
+    pub extensions: Vec<PathBuf>,
 }
 
 impl Default for PoolConfig {
@@ -42,6 +54,9 @@ impl Default for PoolConfig {
             connection_timeout: 30,
             idle_timeout: Some(600), // 10 分钟
             max_lifetime: Some(1800), // 30 分钟
+            warm_up: 0,
+            enable_mmap: true,
+            extensions: Vec::new(),
         }
     }
 }
@@ -50,6 +65,7 @@ impl Default for PoolConfig {
 pub struct DatabasePoolBuilder {
     database_path: String,
     config: PoolConfig,
+    usage_metrics: Option<Arc<PoolUsageMetrics>>,
 }
 
 impl DatabasePoolBuilder {
@@ -58,6 +74,7 @@ impl DatabasePoolBuilder {
         Self {
             database_path: database_path.as_ref().to_string(),
             config: PoolConfig::default(),
+            usage_metrics: None,
         }
     }
 
@@ -85,6 +102,33 @@ impl DatabasePoolBuilder {
         self
     }
 
+    /// 构建时预热 `connections` 个连接
+    ///
+    /// 最小空闲连接数会相应提高，避免预热的连接被回收。
+    pub fn warm_up(mut self, connections: u32) -> Self {
+        self.config.warm_up = connections;
+        self.config.min_idle = Some(self.config.min_idle.unwrap_or(0).max(connections));
+        self
+    }
+
+    /// 设置是否启用内存映射读取
+    pub fn enable_mmap(mut self, enabled: bool) -> Self {
+        self.config.enable_mmap = enabled;
+        self
+    }
+
+    /// 设置每个连接加载的SQLite扩展
+    pub fn extensions(mut self, paths: Vec<PathBuf>) -> Self {
+        self.config.extensions = paths;
+        self
+    }
+
+    /// 把借出、归还、超时和关闭连接的事件计入 `metrics`
+    pub fn usage_metrics(mut self, metrics: Arc<PoolUsageMetrics>) -> Self {
+        self.usage_metrics = Some(metrics);
+        self
+    }
+
     /// 构建连接池
     pub fn build(self) -> Result<DatabasePool> {
         info!(
@@ -93,8 +137,11 @@ impl DatabasePoolBuilder {
         );
 
         // 创建连接管理器
+        let enable_mmap = self.config.enable_mmap;
+        let extension_paths = self.config.extensions.clone();
         let manager = SqliteConnectionManager::file(&self.database_path)
-            .with_init(|conn| {
+            .with_init(move |conn| {
+                let started = Instant::now();
                 // 配置 SQLite 连接
                 conn.execute_batch(
                     "
@@ -103,9 +150,13 @@ impl DatabasePoolBuilder {
                     PRAGMA synchronous = NORMAL;
                     PRAGMA cache_size = -64000;
                     PRAGMA temp_store = MEMORY;
-                    PRAGMA mmap_size = 268435456;
                     ",
                 )?;
+                if enable_mmap {
+                    conn.execute_batch("PRAGMA mmap_size = 268435456;")?;
+                }
+                extensions::load_all(conn, &extension_paths);
+                debug!("SQLite连接初始化耗时 {:?}", started.elapsed());
                 Ok(())
             });
 
@@ -126,16 +177,24 @@ impl DatabasePoolBuilder {
             builder = builder.max_lifetime(Some(Duration::from_secs(max_lifetime)));
         }
 
+        if let Some(metrics) = &self.usage_metrics {
+            builder = builder.event_handler(metrics.event_handler());
+        }
+
         let pool = builder
             .build(manager)
             .context("无法创建数据库连接池")?;
 
         // 测试连接
         let conn = pool.get().context("无法获取数据库连接进行测试")?;
-        conn.execute("SELECT 1", [])
+        conn.query_row("SELECT 1", [], |row| row.get::<_, i32>(0))
             .context("数据库连接测试失败")?;
         drop(conn);
 
+        if self.config.warm_up > 0 {
+            warm_up_pool(&pool, self.config.warm_up)?;
+        }
+
         info!(
             "数据库连接池创建成功: 最大连接数={}, 当前连接数={}",
             self.config.max_connections,
@@ -146,10 +205,107 @@ impl DatabasePoolBuilder {
     }
 }
 
+/// 并行建立并验证 `count` 个连接，完成后全部归还为空闲连接
+fn warm_up_pool(pool: &DatabasePool, count: u32) -> Result<()> {
+    let count = count.min(pool.max_size());
+    let threads = count.min(MAX_WARM_UP_THREADS);
+    let started = Instant::now();
+    // 所有线程持有连接直到全部建立，保证预热的是不同的连接
+    let barrier = Barrier::new(threads as usize);
+
+    let results: Vec<Result<()>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|worker| {
+                let share = count / threads + u32::from(worker < count % threads);
+                let barrier = &barrier;
+                scope.spawn(move || -> Result<()> {
+                    let mut held = Vec::new();
+                    let mut outcome = Ok(());
+                    for _ in 0..share {
+                        let acquired = Instant::now();
+                        match acquire_validated(pool) {
+                            Ok(conn) => {
+                                debug!("预热连接就绪，耗时 {:?}", acquired.elapsed());
+                                held.push(conn);
+                            }
+                            Err(e) => {
+                                outcome = Err(e);
+                                break;
+                            }
+                        }
+                    }
+                    barrier.wait();
+                    outcome
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_else(|_| Err(anyhow::anyhow!("预热线程异常退出"))))
+            .collect()
+    });
+    results.into_iter().collect::<Result<()>>()?;
+
+    info!(
+        "数据库连接预热完成: {} 个连接，耗时 {:?}",
+        count,
+        started.elapsed()
+    );
+    Ok(())
+}
+
+/// 获取一个连接并执行验证查询
+fn acquire_validated(pool: &DatabasePool) -> Result<DatabaseConnection> {
+    let conn = pool.get().context("预热数据库连接失败")?;
+    conn.query_row("SELECT 1", [], |row| row.get::<_, i32>(0))
+        .context("预热连接验证失败")?;
+    Ok(conn)
+}
+
+/// 连接池统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStats {
+    /// 当前连接数（含借出和空闲的连接）
+    pub connections: u32,
+    /// 空闲连接数
+    pub idle_connections: u32,
+    /// 最大连接数
+    pub max_connections: u32,
+}
+
+/// 连接池状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStatus {
+    /// 是否已有可用连接
+    pub healthy: bool,
+    /// 最大连接数
+    pub total_connections: u32,
+    /// 当前连接数
+    pub active_connections: u32,
+    /// 空闲连接数
+    pub idle_connections: u32,
+    /// 当前连接数占最大连接数的百分比
+    pub utilization_percentage: f64,
+}
+
+/// 连接池健康信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseHealth {
+    /// 全部检查是否通过
+    pub healthy: bool,
+    /// 连接池状态
+    pub pool_status: PoolStatus,
+    /// 各项检查：名称、是否通过、失败原因
+    pub checks: Vec<(String, bool, Option<String>)>,
+}
+
 /// 数据库连接池扩展 trait
 ///
 /// 为连接池提供额外的管理功能
 pub trait DatabasePoolExt {
+    /// 获取连接池统计
+    fn get_stats(&self) -> PoolStats;
+
     /// 获取连接池健康状态
     fn get_pool_status(&self) -> PoolStatus;
 
@@ -161,6 +317,15 @@ pub trait DatabasePoolExt {
 }
 
 impl DatabasePoolExt for DatabasePool {
+    fn get_stats(&self) -> PoolStats {
+        let state = self.state();
+        PoolStats {
+            connections: state.connections,
+            idle_connections: state.idle_connections,
+            max_connections: self.max_size(),
+        }
+    }
+
     fn get_pool_status(&self) -> PoolStatus {
         let state = self.state();
         let utilization = if self.max_size() > 0 {
@@ -322,6 +487,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_warm_up_avoids_new_connections() -> Result<()> {
+        let temp_file = NamedTempFile::new()?;
+        let db_path = temp_file.path().to_str().unwrap();
+
+        let pool = DatabasePoolBuilder::new(db_path)
+            .max_connections(5)
+            .warm_up(2)
+            .build()?;
+
+        let before = pool.state();
+        assert!(before.idle_connections >= 2);
+
+        let conn = pool.get()?;
+        let result: i32 = conn.query_row("SELECT 1", [], |row| row.get(0))?;
+        assert_eq!(result, 1);
+
+        let after = pool.state();
+        assert_eq!(after.connections, before.connections);
+        assert_eq!(after.idle_connections, before.idle_connections - 1);
+
+        Ok(())
+    }
+
     #[test]
     fn test_pool_with_config() -> Result<()> {
         let temp_file = NamedTempFile::new()?;
@@ -333,6 +522,9 @@ mod tests {
             connection_timeout: 15,
             idle_timeout: Some(300),
             max_lifetime: Some(900),
+            warm_up: 0,
+            enable_mmap: false,
+            extensions: Vec::new(),
         };
 
         let pool = DatabasePoolBuilder::new(db_path)
@@ -0,0 +1,332 @@
+//! 连接池使用统计
+//!
+//! 通过连接池事件记录本次运行中同时借出的连接数峰值、获取连接的等待时间和超时次数，以及
+//! 因空闲超时或超过生命周期被关闭的连接数。退出时把本次运行的摘要写入 `pool_usage_stats`
+//! 表（只保留最近 [`POOL_USAGE_HISTORY`] 次），诊断界面据此建议最大连接数。
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use minicrm_core::{PoolSizeSuggestion, PoolUsageSummary};
+use r2d2::event::{CheckinEvent, CheckoutEvent, HandleEvent, ReleaseEvent, TimeoutEvent};
+use rusqlite::{params, Row};
+
+use crate::database::DatabaseConnection;
+use crate::database::time::{parse_time, time_key};
+
+/// 保留的运行记录数
+pub const POOL_USAGE_HISTORY: usize = 20;
+
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// 连接池使用统计（一次运行共用一个）
+#[derive(Debug)]
+pub struct PoolUsageMetrics {
+    started_at: DateTime<Utc>,
+    max_connections: u32,
+    in_use: AtomicU32,
+    peak: AtomicU32,
+    checkouts: AtomicU64,
+    total_wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+    timeouts: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl PoolUsageMetrics {
+    /// 开始统计，`max_connections` 为本次运行的最大连接数配置
+    pub fn new(max_connections: u32, started_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at,
+            max_connections,
+            in_use: AtomicU32::new(0),
+            peak: AtomicU32::new(0),
+            checkouts: AtomicU64::new(0),
+            total_wait_micros: AtomicU64::new(0),
+            max_wait_micros: AtomicU64::new(0),
+            timeouts: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// 借出一个连接，`waited` 为获取连接的等待时间
+    pub fn checked_out(&self, waited: Duration) {
+        let in_use = self.in_use.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak.fetch_max(in_use, Ordering::Relaxed);
+        self.checkouts.fetch_add(1, Ordering::Relaxed);
+        let waited = micros(waited);
+        self.total_wait_micros.fetch_add(waited, Ordering::Relaxed);
+        self.max_wait_micros.fetch_max(waited, Ordering::Relaxed);
+    }
+
+    /// 归还一个连接
+    pub fn checked_in(&self) {
+        // 统计开始前借出的连接归还时不减到负数
+        let _ = self
+            .in_use
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    /// 获取连接超时
+    pub fn timed_out(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 连接被关闭
+    pub fn evicted(&self) {
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 当前借出的连接数
+    pub fn in_use(&self) -> u32 {
+        self.in_use.load(Ordering::Relaxed)
+    }
+
+    /// 截至 `ended_at` 的使用摘要
+    pub fn summary(&self, ended_at: DateTime<Utc>) -> PoolUsageSummary {
+        PoolUsageSummary {
+            started_at: self.started_at,
+            ended_at,
+            max_connections: self.max_connections,
+            peak_checkouts: self.peak.load(Ordering::Relaxed),
+            checkouts: self.checkouts.load(Ordering::Relaxed),
+            total_wait_ms: self.total_wait_micros.load(Ordering::Relaxed) / 1000,
+            max_wait_ms: self.max_wait_micros.load(Ordering::Relaxed) / 1000,
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 连接池事件处理器
+    pub(crate) fn event_handler(self: &Arc<Self>) -> Box<dyn HandleEvent> {
+        Box::new(UsageEvents(self.clone()))
+    }
+}
+
+/// 把连接池事件计入统计
+#[derive(Debug)]
+struct UsageEvents(Arc<PoolUsageMetrics>);
+
+impl HandleEvent for UsageEvents {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        self.0.checked_out(event.duration());
+    }
+
+    fn handle_checkin(&self, _event: CheckinEvent) {
+        self.0.checked_in();
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        self.0.timed_out();
+    }
+
+    fn handle_release(&self, _event: ReleaseEvent) {
+        self.0.evicted();
+    }
+}
+
+fn count(row: &Row<'_>, index: usize) -> rusqlite::Result<u64> {
+    Ok(u64::try_from(row.get::<_, i64>(index)?).unwrap_or(0))
+}
+
+fn row_to_summary(row: &Row<'_>) -> rusqlite::Result<PoolUsageSummary> {
+    Ok(PoolUsageSummary {
+        started_at: parse_time(0, &row.get::<_, String>(0)?)?,
+        ended_at: parse_time(1, &row.get::<_, String>(1)?)?,
+        max_connections: row.get(2)?,
+        peak_checkouts: row.get(3)?,
+        checkouts: count(row, 4)?,
+        total_wait_ms: count(row, 5)?,
+        max_wait_ms: count(row, 6)?,
+        timeouts: count(row, 7)?,
+        evictions: count(row, 8)?,
+    })
+}
+
+fn sql_count(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// 连接池使用记录存储
+#[derive(Debug, Clone)]
+pub struct PoolUsageStore {
+    connection: DatabaseConnection,
+}
+
+impl PoolUsageStore {
+    /// 创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 保存一次运行的摘要，只保留最近 [`POOL_USAGE_HISTORY`] 次
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn record(&self, summary: &PoolUsageSummary) -> Result<()> {
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "INSERT INTO pool_usage_stats
+                         (started_at, ended_at, max_connections, peak_checkouts, checkouts,
+                          total_wait_ms, max_wait_ms, timeouts, evictions)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        time_key(summary.started_at),
+                        time_key(summary.ended_at),
+                        summary.max_connections,
+                        summary.peak_checkouts,
+                        sql_count(summary.checkouts),
+                        sql_count(summary.total_wait_ms),
+                        sql_count(summary.max_wait_ms),
+                        sql_count(summary.timeouts),
+                        sql_count(summary.evictions),
+                    ],
+                )?;
+                tx.execute(
+                    "DELETE FROM pool_usage_stats WHERE id NOT IN
+                         (SELECT id FROM pool_usage_stats ORDER BY id DESC LIMIT ?1)",
+                    [sql_count(POOL_USAGE_HISTORY as u64)],
+                )?;
+                Ok(())
+            })
+            .context("无法保存连接池使用记录")
+    }
+
+    /// 最近几次运行的摘要（最新的在前）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn history(&self) -> Result<Vec<PoolUsageSummary>> {
+        self.connection.query_map(
+            "SELECT started_at, ended_at, max_connections, peak_checkouts, checkouts,
+                    total_wait_ms, max_wait_ms, timeouts, evictions
+             FROM pool_usage_stats ORDER BY id DESC LIMIT ?1",
+            [sql_count(POOL_USAGE_HISTORY as u64)],
+            row_to_summary,
+        )
+    }
+
+    /// 根据最近几次运行建议最大连接数，没有记录时为空
+    ///
+    /// 建议值不低于2，不超过 `hard_cap`（见 [`PoolSizeSuggestion::from_history`]）。
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn suggest_pool_size(
+        &self,
+        current: u32,
+        hard_cap: u32,
+    ) -> Result<Option<PoolSizeSuggestion>> {
+        Ok(PoolSizeSuggestion::from_history(&self.history()?, current, hard_cap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::pool::DatabasePoolBuilder;
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use minicrm_core::POOL_SIZE_FLOOR;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, PoolUsageStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        (temp_dir, PoolUsageStore::new(connection))
+    }
+
+    /// 模拟一次运行：`bursts` 为每次同时借出的连接数及其中最长的等待
+    fn session(day: u32, max_connections: u32, bursts: &[(u32, u64)]) -> PoolUsageSummary {
+        let started_at = Utc.with_ymd_and_hms(2024, 6, day, 8, 0, 0).unwrap();
+        let metrics = PoolUsageMetrics::new(max_connections, started_at);
+        for &(concurrent, wait_ms) in bursts {
+            for i in 0..concurrent {
+                let waited = if i + 1 == concurrent { wait_ms } else { 1 };
+                metrics.checked_out(Duration::from_millis(waited));
+            }
+            for _ in 0..concurrent {
+                metrics.checked_in();
+            }
+        }
+        metrics.summary(started_at + chrono::Duration::hours(9))
+    }
+
+    #[test]
+    fn test_suggestions_for_usage_patterns() {
+        // 平时只有一两个连接同时使用
+        let steady: Vec<_> = (1..=5)
+            .map(|day| session(day, 10, &[(1, 2), (2, 3), (1, 1)]))
+            .collect();
+        assert_eq!(steady[0].peak_checkouts, 2);
+        assert_eq!(steady[0].checkouts, 4);
+        let suggestion = PoolSizeSuggestion::from_history(&steady, 10, 16).unwrap();
+        assert_eq!(suggestion.suggested, 4);
+        assert_eq!(suggestion.observed_peak, 2);
+        assert!(!suggestion.saturated);
+        assert!(suggestion.changes());
+
+        // 某天导入时把连接池用满，调用方等待了近一秒
+        let mut history = steady.clone();
+        history.push(session(6, 4, &[(1, 2), (4, 800)]));
+        let suggestion = PoolSizeSuggestion::from_history(&history, 4, 16).unwrap();
+        assert!(suggestion.saturated);
+        assert_eq!(suggestion.suggested, 4 + 1 + 2);
+        // 不超过上限
+        let capped = PoolSizeSuggestion::from_history(&history, 4, 6).unwrap();
+        assert_eq!(capped.suggested, 6);
+
+        // 几乎不用数据库时也不低于下限
+        let idle = [session(7, 10, &[(1, 0)])];
+        let suggestion = PoolSizeSuggestion::from_history(&idle, 10, 16).unwrap();
+        assert_eq!(suggestion.suggested, (1 + 2).max(POOL_SIZE_FLOOR));
+        let tiny_cap = PoolSizeSuggestion::from_history(&idle, 10, 1).unwrap();
+        assert_eq!(tiny_cap.suggested, POOL_SIZE_FLOOR);
+        assert_eq!(PoolSizeSuggestion::from_history(&[], 10, 16), None);
+    }
+
+    #[test]
+    fn test_history_round_trip_and_pool_events() {
+        let (_dir, store) = create_test_store();
+        assert_eq!(store.suggest_pool_size(10, 16).unwrap(), None);
+
+        let sessions: Vec<_> = (1..=25)
+            .map(|day| session(day, 10, &[(day % 3 + 1, 5)]))
+            .collect();
+        for summary in &sessions {
+            store.record(summary).unwrap();
+        }
+        let history = store.history().unwrap();
+        assert_eq!(history.len(), POOL_USAGE_HISTORY);
+        assert_eq!(history[0], sessions[24]);
+        assert_eq!(history[POOL_USAGE_HISTORY - 1], sessions[5]);
+        let suggestion = store.suggest_pool_size(10, 16).unwrap().unwrap();
+        assert_eq!((suggestion.suggested, suggestion.sessions), (5, POOL_USAGE_HISTORY));
+
+        // 连接池事件计入统计
+        let dir = TempDir::new().unwrap();
+        let metrics = Arc::new(PoolUsageMetrics::new(4, Utc::now()));
+        let pool = DatabasePoolBuilder::new(dir.path().join("events.db").to_string_lossy())
+            .max_connections(4)
+            .usage_metrics(metrics.clone())
+            .build()
+            .unwrap();
+        let held: Vec<_> = (0..3).map(|_| pool.get().unwrap()).collect();
+        assert_eq!(metrics.in_use(), 3);
+        drop(held);
+        assert_eq!(metrics.in_use(), 0);
+        let summary = metrics.summary(Utc::now());
+        assert_eq!(summary.peak_checkouts, 3);
+        assert!(summary.checkouts >= 3);
+        assert_eq!(summary.max_connections, 4);
+    }
+}
@@ -0,0 +1,1402 @@
+//! 内置数据库迁移
+//!
+//! 按版本号维护应用使用的全部schema迁移。
+
+use super::db_uuid::normalize_column_sql;
+use super::migrations::Migration;
+use crate::migration;
+
+/// 以TEXT存储UUID的列（表, 列）
+const UUID_COLUMNS: &[(&str, &str)] = &[
+    ("customers", "id"),
+    ("tasks", "id"),
+    ("tasks", "customer_id"),
+    ("quotes", "id"),
+    ("quotes", "customer_id"),
+    ("webhook_endpoints", "id"),
+    ("webhook_deliveries", "id"),
+    ("webhook_deliveries", "endpoint_id"),
+    ("webhook_deliveries", "event_id"),
+    ("users", "id"),
+    ("quote_revisions", "quote_id"),
+];
+
+/// 规范化全部UUID列的SQL
+fn normalize_uuid_columns() -> String {
+    UUID_COLUMNS
+        .iter()
+        .map(|(table, column)| normalize_column_sql(table, column))
+        .collect()
+}
+
+/// 记录删除的表（表, 是否软删除）
+const JOURNALED_TABLES: &[(&str, bool)] = &[
+    ("customers", true),
+    ("tasks", true),
+    ("quotes", true),
+    ("orders", true),
+    ("order_payments", false),
+];
+
+/// 删除日志触发器：硬删除记为 `hard`，软删除和恢复分别记为 `soft`、`restore`
+fn deletion_journal_triggers() -> String {
+    let now = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+    let mut sql = String::new();
+    for (table, soft) in JOURNALED_TABLES {
+        sql.push_str(&format!(
+            "CREATE TRIGGER {table}_journal_delete AFTER DELETE ON {table} BEGIN
+                INSERT INTO deletion_journal (entity, entity_id, kind, recorded_at)
+                VALUES ('{table}', OLD.id, 'hard', {now});
+            END;\n"
+        ));
+        if *soft {
+            sql.push_str(&format!(
+                "CREATE TRIGGER {table}_journal_soft AFTER UPDATE OF deleted_at ON {table}
+                WHEN OLD.deleted_at IS NOT NEW.deleted_at BEGIN
+                    INSERT INTO deletion_journal (entity, entity_id, kind, recorded_at)
+                    VALUES ('{table}', NEW.id,
+                            CASE WHEN NEW.deleted_at IS NULL THEN 'restore' ELSE 'soft' END,
+                            {now});
+                END;\n"
+            ));
+        }
+    }
+    sql
+}
+
+/// 差异同步：收款修改时间、删除日志及副本已应用的高水位时间
+fn differential_sync_sql() -> String {
+    format!(
+        r#"
+        ALTER TABLE order_payments ADD COLUMN updated_at TEXT;
+        UPDATE order_payments SET updated_at = paid_at;
+        CREATE TRIGGER order_payments_touch_insert AFTER INSERT ON order_payments
+        WHEN NEW.updated_at IS NULL BEGIN
+            UPDATE order_payments SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            WHERE id = NEW.id;
+        END;
+        CREATE TRIGGER order_payments_touch_update AFTER UPDATE ON order_payments
+        WHEN NEW.updated_at IS OLD.updated_at
+            AND (NEW.order_id IS NOT OLD.order_id OR NEW.amount IS NOT OLD.amount
+                 OR NEW.paid_at IS NOT OLD.paid_at OR NEW.currency IS NOT OLD.currency)
+        BEGIN
+            UPDATE order_payments SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            WHERE id = NEW.id;
+        END;
+        CREATE TABLE deletion_journal (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            kind TEXT NOT NULL CHECK (kind IN ('hard', 'soft', 'restore')),
+            recorded_at TEXT NOT NULL
+        );
+        CREATE INDEX idx_deletion_journal_entity ON deletion_journal(entity, recorded_at);
+        CREATE TABLE sync_state (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            high_water TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );
+        {}"#,
+        deletion_journal_triggers()
+    )
+}
+
+/// 引入迁移之前的版本直接建立的结构对应的迁移版本
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// 获取全部内置迁移（按版本号升序）
+pub fn builtin_migrations() -> Vec<Migration> {
+    vec![
+        migration!(
+            1,
+            "baseline_core_tables",
+            "基础业务表：客户、任务、报价",
+            r#"
+            CREATE TABLE IF NOT EXISTS customers (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                company TEXT,
+                email TEXT,
+                phone TEXT,
+                address TEXT,
+                notes TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS tasks (
+                id TEXT PRIMARY KEY,
+                customer_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                priority TEXT NOT NULL DEFAULT 'medium',
+                due_date TEXT,
+                completed_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            CREATE TABLE IF NOT EXISTS quotes (
+                id TEXT PRIMARY KEY,
+                customer_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT,
+                total_amount REAL NOT NULL,
+                currency TEXT NOT NULL DEFAULT 'CNY',
+                status TEXT NOT NULL DEFAULT 'draft',
+                valid_until TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_customers_email ON customers(email);
+            CREATE INDEX IF NOT EXISTS idx_customers_company ON customers(company);
+            CREATE INDEX IF NOT EXISTS idx_tasks_customer_id ON tasks(customer_id);
+            CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status);
+            CREATE INDEX IF NOT EXISTS idx_tasks_due_date ON tasks(due_date);
+            CREATE INDEX IF NOT EXISTS idx_quotes_customer_id ON quotes(customer_id);
+            CREATE INDEX IF NOT EXISTS idx_quotes_status ON quotes(status);
+            "#
+        ),
+        migration!(
+            2,
+            "soft_delete_and_table_counters",
+            "软删除列及按表维护的记录总数缓存",
+            r#"
+            ALTER TABLE customers ADD COLUMN deleted_at TEXT;
+            ALTER TABLE tasks ADD COLUMN deleted_at TEXT;
+            ALTER TABLE quotes ADD COLUMN deleted_at TEXT;
+            CREATE TABLE table_counters (
+                table_name TEXT PRIMARY KEY,
+                row_count INTEGER NOT NULL DEFAULT 0,
+                reconciled_at TEXT,
+                updated_at TEXT NOT NULL
+            );
+            INSERT INTO table_counters (table_name, row_count, reconciled_at, updated_at)
+                SELECT 'customers', COUNT(*), datetime('now'), datetime('now') FROM customers;
+            INSERT INTO table_counters (table_name, row_count, reconciled_at, updated_at)
+                SELECT 'tasks', COUNT(*), datetime('now'), datetime('now') FROM tasks;
+            INSERT INTO table_counters (table_name, row_count, reconciled_at, updated_at)
+                SELECT 'quotes', COUNT(*), datetime('now'), datetime('now') FROM quotes;
+            "#,
+            r#"
+            DROP TABLE table_counters;
+            ALTER TABLE quotes DROP COLUMN deleted_at;
+            ALTER TABLE tasks DROP COLUMN deleted_at;
+            ALTER TABLE customers DROP COLUMN deleted_at;
+            "#
+        ),
+        migration!(
+            3,
+            "webhook_tables",
+            "Webhook端点配置及投递记录",
+            r#"
+            CREATE TABLE webhook_endpoints (
+                id TEXT PRIMARY KEY,
+                url TEXT NOT NULL,
+                secret TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                event_types TEXT NOT NULL DEFAULT '[]',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE webhook_deliveries (
+                id TEXT PRIMARY KEY,
+                endpoint_id TEXT NOT NULL,
+                event_id TEXT NOT NULL,
+                event_type TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                response_code INTEGER,
+                retry_count INTEGER NOT NULL DEFAULT 0,
+                next_attempt_at TEXT,
+                last_error TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE (endpoint_id, event_id),
+                FOREIGN KEY (endpoint_id) REFERENCES webhook_endpoints (id) ON DELETE CASCADE
+            );
+            CREATE INDEX idx_webhook_deliveries_pending
+                ON webhook_deliveries(status, next_attempt_at);
+            "#,
+            r#"
+            DROP TABLE webhook_deliveries;
+            DROP TABLE webhook_endpoints;
+            "#
+        ),
+        migration!(
+            4,
+            "users_and_audit_fields",
+            "用户账号表及业务表的创建人/修改人字段",
+            r#"
+            CREATE TABLE users (
+                id TEXT PRIMARY KEY,
+                username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                display_name TEXT NOT NULL,
+                role TEXT NOT NULL DEFAULT 'viewer',
+                password_hash TEXT NOT NULL,
+                active INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            ALTER TABLE customers ADD COLUMN created_by TEXT;
+            ALTER TABLE customers ADD COLUMN updated_by TEXT;
+            ALTER TABLE tasks ADD COLUMN created_by TEXT;
+            ALTER TABLE tasks ADD COLUMN updated_by TEXT;
+            ALTER TABLE quotes ADD COLUMN created_by TEXT;
+            ALTER TABLE quotes ADD COLUMN updated_by TEXT;
+            "#,
+            r#"
+            ALTER TABLE quotes DROP COLUMN updated_by;
+            ALTER TABLE quotes DROP COLUMN created_by;
+            ALTER TABLE tasks DROP COLUMN updated_by;
+            ALTER TABLE tasks DROP COLUMN created_by;
+            ALTER TABLE customers DROP COLUMN updated_by;
+            ALTER TABLE customers DROP COLUMN created_by;
+            DROP TABLE users;
+            "#
+        ),
+        migration!(
+            5,
+            "quote_revisions",
+            "报价修订历史（非草稿报价每次保存的完整快照）",
+            r#"
+            CREATE TABLE quote_revisions (
+                quote_id TEXT NOT NULL,
+                revision_no INTEGER NOT NULL,
+                snapshot TEXT NOT NULL,
+                saved_by TEXT,
+                saved_at TEXT NOT NULL,
+                PRIMARY KEY (quote_id, revision_no)
+            );
+            "#,
+            r#"
+            DROP TABLE quote_revisions;
+            "#
+        ),
+        migration!(
+            6,
+            "normalize_uuid_ids",
+            "将历史导入的ID统一为小写带连字符的UUID文本",
+            normalize_uuid_columns(),
+            "-- 规范化不可逆，回滚时保留规范形式"
+        ),
+        migration!(
+            7,
+            "purchase_quotes",
+            "供应商报价（采购比价）",
+            r#"
+            CREATE TABLE purchase_quotes (
+                id TEXT PRIMARY KEY,
+                supplier_id TEXT NOT NULL,
+                product_id TEXT NOT NULL,
+                unit_price INTEGER NOT NULL,
+                moq INTEGER,
+                valid_until TEXT NOT NULL,
+                received_at TEXT NOT NULL,
+                notes TEXT
+            );
+            CREATE INDEX idx_purchase_quotes_product
+                ON purchase_quotes(product_id, supplier_id, received_at);
+            "#,
+            r#"
+            DROP TABLE purchase_quotes;
+            "#
+        ),
+        migration!(
+            8,
+            "orders_deliveries_reminders",
+            "销售订单及送货安排、客户互动记录、提醒",
+            r#"
+            CREATE TABLE orders (
+                id TEXT PRIMARY KEY,
+                order_number TEXT NOT NULL UNIQUE,
+                customer_id TEXT NOT NULL,
+                quote_id TEXT,
+                total_amount INTEGER NOT NULL DEFAULT 0,
+                delivery_status TEXT NOT NULL DEFAULT 'unscheduled',
+                delivery_date TEXT,
+                delivery_address TEXT,
+                vehicle TEXT,
+                driver TEXT,
+                signed_by TEXT,
+                delivered_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                created_by TEXT,
+                updated_by TEXT,
+                deleted_at TEXT
+            );
+            CREATE INDEX idx_orders_customer_id ON orders(customer_id);
+            CREATE INDEX idx_orders_delivery ON orders(delivery_status, delivery_date);
+            CREATE TABLE interactions (
+                id TEXT PRIMARY KEY,
+                customer_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                content TEXT NOT NULL,
+                occurred_at TEXT NOT NULL,
+                created_by TEXT
+            );
+            CREATE INDEX idx_interactions_customer ON interactions(customer_id, occurred_at);
+            CREATE TABLE reminders (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                due_at TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                dismissed_at TEXT,
+                UNIQUE (kind, entity_id, due_at)
+            );
+            "#,
+            r#"
+            DROP TABLE reminders;
+            DROP TABLE interactions;
+            DROP TABLE orders;
+            "#
+        ),
+        migration!(
+            9,
+            "customer_relations",
+            "客户关系：上下级公司、推荐、合作伙伴",
+            r#"
+            CREATE TABLE customer_relations (
+                id TEXT PRIMARY KEY,
+                from_id TEXT NOT NULL,
+                to_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                note TEXT,
+                created_at TEXT NOT NULL,
+                UNIQUE (from_id, to_id, kind),
+                CHECK (from_id <> to_id)
+            );
+            CREATE INDEX idx_customer_relations_to ON customer_relations(to_id);
+            "#,
+            r#"
+            DROP TABLE customer_relations;
+            "#
+        ),
+        migration!(
+            10,
+            "opportunities",
+            "销售机会",
+            r#"
+            CREATE TABLE opportunities (
+                id TEXT PRIMARY KEY,
+                customer_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                stage TEXT NOT NULL DEFAULT 'lead',
+                expected_amount INTEGER NOT NULL DEFAULT 0,
+                probability INTEGER NOT NULL DEFAULT 0 CHECK (probability BETWEEN 0 AND 100),
+                expected_close_date TEXT,
+                quote_id TEXT,
+                lost_reason TEXT,
+                notes TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                created_by TEXT,
+                updated_by TEXT,
+                deleted_at TEXT
+            );
+            CREATE INDEX idx_opportunities_customer_id ON opportunities(customer_id);
+            CREATE INDEX idx_opportunities_stage ON opportunities(stage);
+            CREATE INDEX idx_opportunities_quote_id ON opportunities(quote_id);
+            "#,
+            r#"
+            DROP TABLE opportunities;
+            "#
+        ),
+        migration!(
+            11,
+            "custom_fields",
+            "自定义字段定义与字段值",
+            r#"
+            CREATE TABLE custom_field_definitions (
+                id TEXT PRIMARY KEY,
+                entity_type TEXT NOT NULL,
+                field_key TEXT NOT NULL,
+                label TEXT NOT NULL,
+                field_type TEXT NOT NULL,
+                options TEXT,
+                required INTEGER NOT NULL DEFAULT 0,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                retired_at TEXT,
+                created_at TEXT NOT NULL,
+                UNIQUE (entity_type, field_key)
+            );
+            CREATE TABLE custom_field_values (
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                field_key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (entity_type, entity_id, field_key)
+            );
+            CREATE INDEX idx_custom_field_values_lookup
+                ON custom_field_values(entity_type, field_key, value);
+            "#,
+            r#"
+            DROP TABLE custom_field_values;
+            DROP TABLE custom_field_definitions;
+            "#
+        ),
+        migration!(
+            12,
+            "idempotency_records",
+            "命令幂等记录",
+            r#"
+            CREATE TABLE idempotency_records (
+                idempotency_key TEXT PRIMARY KEY,
+                command TEXT NOT NULL,
+                request_hash TEXT NOT NULL,
+                result TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_idempotency_records_created_at
+                ON idempotency_records(created_at);
+            "#,
+            r#"
+            DROP TABLE idempotency_records;
+            "#
+        ),
+        migration!(
+            13,
+            "quote_templates",
+            "报价模板及模板明细",
+            r#"
+            CREATE TABLE quote_templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                validity_days INTEGER NOT NULL,
+                terms TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE quote_template_items (
+                template_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                product_id TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                discount REAL NOT NULL DEFAULT 0,
+                PRIMARY KEY (template_id, position),
+                FOREIGN KEY (template_id) REFERENCES quote_templates (id) ON DELETE CASCADE
+            );
+            "#,
+            r#"
+            DROP TABLE quote_template_items;
+            DROP TABLE quote_templates;
+            "#
+        ),
+        migration!(
+            14,
+            "job_runs",
+            "后台任务运行记录",
+            r#"
+            CREATE TABLE job_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_name TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                error TEXT
+            );
+            CREATE INDEX idx_job_runs_name_started ON job_runs(job_name, started_at);
+            "#,
+            r#"
+            DROP TABLE job_runs;
+            "#
+        ),
+        migration!(
+            15,
+            "product_prices",
+            "产品目录及按生效时间记录的产品价格",
+            r#"
+            CREATE TABLE products (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                specification TEXT,
+                category TEXT,
+                retired INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_products_category ON products(category);
+            CREATE TABLE product_prices (
+                id TEXT PRIMARY KEY,
+                product_id TEXT NOT NULL,
+                price INTEGER NOT NULL,
+                effective_from TEXT NOT NULL,
+                created_by TEXT,
+                created_at TEXT NOT NULL,
+                UNIQUE (product_id, effective_from),
+                FOREIGN KEY (product_id) REFERENCES products (id) ON DELETE CASCADE
+            );
+            "#,
+            r#"
+            DROP TABLE product_prices;
+            DROP TABLE products;
+            "#
+        ),
+        migration!(
+            16,
+            "customers_fts",
+            "客户名称和公司的全文索引（trigram分词，支持中文子串搜索）",
+            r#"
+            CREATE VIRTUAL TABLE customers_fts USING fts5(
+                customer_id UNINDEXED,
+                name,
+                company,
+                tokenize = 'trigram'
+            );
+            CREATE TRIGGER customers_fts_insert AFTER INSERT ON customers BEGIN
+                INSERT INTO customers_fts (customer_id, name, company)
+                VALUES (NEW.id, NEW.name, NEW.company);
+            END;
+            CREATE TRIGGER customers_fts_delete AFTER DELETE ON customers BEGIN
+                DELETE FROM customers_fts WHERE customer_id = OLD.id;
+            END;
+            CREATE TRIGGER customers_fts_update AFTER UPDATE OF id, name, company ON customers
+            BEGIN
+                DELETE FROM customers_fts WHERE customer_id = OLD.id;
+                INSERT INTO customers_fts (customer_id, name, company)
+                VALUES (NEW.id, NEW.name, NEW.company);
+            END;
+            INSERT INTO customers_fts (customer_id, name, company)
+            SELECT id, name, company FROM customers;
+            "#,
+            r#"
+            DROP TRIGGER customers_fts_update;
+            DROP TRIGGER customers_fts_delete;
+            DROP TRIGGER customers_fts_insert;
+            DROP TABLE customers_fts;
+            "#
+        ),
+        migration!(
+            17,
+            "record_archive",
+            "按保留期限归档的互动记录、任务、订单及归档运行历史",
+            r#"
+            CREATE TABLE archived_interactions AS SELECT * FROM interactions WHERE 0;
+            ALTER TABLE archived_interactions ADD COLUMN archived_at TEXT;
+            CREATE TABLE archived_tasks AS SELECT * FROM tasks WHERE 0;
+            ALTER TABLE archived_tasks ADD COLUMN archived_at TEXT;
+            CREATE TABLE archived_orders AS SELECT * FROM orders WHERE 0;
+            ALTER TABLE archived_orders ADD COLUMN archived_at TEXT;
+            CREATE TABLE archive_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                finished_at TEXT NOT NULL,
+                run_trigger TEXT NOT NULL,
+                status TEXT NOT NULL,
+                counts TEXT NOT NULL DEFAULT '[]',
+                message TEXT
+            );
+            CREATE INDEX idx_archive_runs_started ON archive_runs(started_at);
+            "#,
+            r#"
+            DROP TABLE archive_runs;
+            DROP TABLE archived_orders;
+            DROP TABLE archived_tasks;
+            DROP TABLE archived_interactions;
+            "#
+        ),
+        migration!(
+            18,
+            "customer_summary",
+            "订单收款记录及客户列表汇总表",
+            r#"
+            CREATE TABLE order_payments (
+                id TEXT PRIMARY KEY,
+                order_id TEXT NOT NULL,
+                amount INTEGER NOT NULL,
+                paid_at TEXT NOT NULL,
+                created_by TEXT,
+                FOREIGN KEY (order_id) REFERENCES orders (id) ON DELETE CASCADE
+            );
+            CREATE INDEX idx_order_payments_order ON order_payments(order_id);
+            CREATE TABLE customer_summary (
+                customer_id TEXT PRIMARY KEY,
+                last_interaction_at TEXT,
+                open_task_count INTEGER NOT NULL DEFAULT 0,
+                outstanding_amount INTEGER NOT NULL DEFAULT 0,
+                accepted_quote_total_12m INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            INSERT INTO customer_summary (
+                customer_id, last_interaction_at, open_task_count, outstanding_amount,
+                accepted_quote_total_12m, updated_at
+            )
+            SELECT c.id,
+                (SELECT MAX(i.occurred_at) FROM interactions i WHERE i.customer_id = c.id),
+                (SELECT COUNT(*) FROM tasks t
+                 WHERE t.customer_id = c.id AND t.deleted_at IS NULL
+                   AND lower(t.status) NOT IN ('completed', 'cancelled')),
+                COALESCE((SELECT SUM(o.total_amount) FROM orders o
+                          WHERE o.customer_id = c.id AND o.deleted_at IS NULL), 0),
+                COALESCE((SELECT CAST(ROUND(SUM(q.total_amount) * 100) AS INTEGER) FROM quotes q
+                          WHERE q.customer_id = c.id AND q.deleted_at IS NULL
+                            AND q.status = 'accepted'
+                            AND julianday(q.updated_at) >= julianday('now', '-12 months')), 0),
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            FROM customers c;
+            "#,
+            r#"
+            DROP TABLE customer_summary;
+            DROP TABLE order_payments;
+            "#
+        ),
+        migration!(
+            19,
+            "multi_currency",
+            "订单和收款的币种、汇率表、客户汇总中缺少汇率的单据数",
+            r#"
+            ALTER TABLE orders ADD COLUMN currency TEXT NOT NULL DEFAULT 'CNY';
+            ALTER TABLE archived_orders ADD COLUMN currency TEXT NOT NULL DEFAULT 'CNY';
+            ALTER TABLE order_payments ADD COLUMN currency TEXT NOT NULL DEFAULT 'CNY';
+            ALTER TABLE customer_summary
+                ADD COLUMN missing_rate_documents INTEGER NOT NULL DEFAULT 0;
+            CREATE TABLE exchange_rates (
+                currency TEXT NOT NULL,
+                rate_to_base REAL NOT NULL,
+                effective_date TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (currency, effective_date)
+            );
+            "#,
+            r#"
+            DROP TABLE exchange_rates;
+            ALTER TABLE customer_summary DROP COLUMN missing_rate_documents;
+            ALTER TABLE order_payments DROP COLUMN currency;
+            ALTER TABLE archived_orders DROP COLUMN currency;
+            ALTER TABLE orders DROP COLUMN currency;
+            "#
+        ),
+        migration!(
+            20,
+            "deletion_batches",
+            "软删除批次：同一次删除连带删除的记录共用批次ID，恢复时整批恢复",
+            r#"
+            ALTER TABLE customers ADD COLUMN deletion_batch_id TEXT;
+            ALTER TABLE tasks ADD COLUMN deletion_batch_id TEXT;
+            ALTER TABLE quotes ADD COLUMN deletion_batch_id TEXT;
+            ALTER TABLE orders ADD COLUMN deletion_batch_id TEXT;
+            ALTER TABLE opportunities ADD COLUMN deletion_batch_id TEXT;
+            ALTER TABLE archived_tasks ADD COLUMN deletion_batch_id TEXT;
+            ALTER TABLE archived_orders ADD COLUMN deletion_batch_id TEXT;
+            CREATE INDEX idx_customers_deletion_batch ON customers(deletion_batch_id);
+            CREATE INDEX idx_tasks_deletion_batch ON tasks(deletion_batch_id);
+            CREATE INDEX idx_quotes_deletion_batch ON quotes(deletion_batch_id);
+            CREATE INDEX idx_orders_deletion_batch ON orders(deletion_batch_id);
+            CREATE INDEX idx_opportunities_deletion_batch ON opportunities(deletion_batch_id);
+            CREATE TABLE deletion_batches (
+                id TEXT PRIMARY KEY,
+                root_entity TEXT NOT NULL,
+                root_id TEXT NOT NULL,
+                label TEXT NOT NULL,
+                deleted_at TEXT NOT NULL,
+                deleted_by TEXT,
+                counts TEXT NOT NULL DEFAULT '{}'
+            );
+            "#,
+            r#"
+            DROP TABLE deletion_batches;
+            DROP INDEX idx_opportunities_deletion_batch;
+            DROP INDEX idx_orders_deletion_batch;
+            DROP INDEX idx_quotes_deletion_batch;
+            DROP INDEX idx_tasks_deletion_batch;
+            DROP INDEX idx_customers_deletion_batch;
+            ALTER TABLE archived_orders DROP COLUMN deletion_batch_id;
+            ALTER TABLE archived_tasks DROP COLUMN deletion_batch_id;
+            ALTER TABLE opportunities DROP COLUMN deletion_batch_id;
+            ALTER TABLE orders DROP COLUMN deletion_batch_id;
+            ALTER TABLE quotes DROP COLUMN deletion_batch_id;
+            ALTER TABLE tasks DROP COLUMN deletion_batch_id;
+            ALTER TABLE customers DROP COLUMN deletion_batch_id;
+            "#
+        ),
+        migration!(
+            21,
+            "product_cost_price",
+            "产品成本价（按分存储），随价格期间记录在价格表中",
+            r#"
+            ALTER TABLE products ADD COLUMN cost_price INTEGER;
+            ALTER TABLE product_prices ADD COLUMN cost_price INTEGER;
+            "#,
+            r#"
+            ALTER TABLE product_prices DROP COLUMN cost_price;
+            ALTER TABLE products DROP COLUMN cost_price;
+            "#
+        ),
+        migration!(
+            22,
+            "event_outbox",
+            "事件发件箱：与业务修改同一事务写入，由分发任务发布后标记",
+            r#"
+            CREATE TABLE event_outbox (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                event_id TEXT NOT NULL UNIQUE,
+                event_type TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                dispatched_at TEXT
+            );
+            CREATE INDEX idx_event_outbox_pending ON event_outbox(dispatched_at, seq);
+            "#,
+            r#"
+            DROP INDEX idx_event_outbox_pending;
+            DROP TABLE event_outbox;
+            "#
+        ),
+        migration!(
+            23,
+            "task_assignment",
+            "任务负责人、关注人和备注；提醒增加接收人",
+            r#"
+            ALTER TABLE tasks ADD COLUMN assigned_to TEXT;
+            ALTER TABLE archived_tasks ADD COLUMN assigned_to TEXT;
+            CREATE INDEX idx_tasks_assigned_to ON tasks(assigned_to);
+            CREATE TABLE task_watchers (
+                task_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (task_id, user_id)
+            );
+            CREATE INDEX idx_task_watchers_user ON task_watchers(user_id);
+            CREATE TABLE task_notes (
+                id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                body TEXT NOT NULL,
+                author TEXT,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_task_notes_task ON task_notes(task_id, created_at);
+            CREATE TABLE reminders_new (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                due_at TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                dismissed_at TEXT,
+                recipient_id TEXT
+            );
+            INSERT INTO reminders_new
+                (id, kind, entity_type, entity_id, title, due_at, created_at, dismissed_at)
+                SELECT id, kind, entity_type, entity_id, title, due_at, created_at, dismissed_at
+                FROM reminders;
+            DROP TABLE reminders;
+            ALTER TABLE reminders_new RENAME TO reminders;
+            CREATE UNIQUE INDEX idx_reminders_dedupe
+                ON reminders(kind, entity_id, due_at, IFNULL(recipient_id, ''));
+            CREATE INDEX idx_reminders_recipient ON reminders(recipient_id, dismissed_at);
+            "#,
+            r#"
+            CREATE TABLE reminders_old (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                title TEXT NOT NULL,
+                due_at TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                dismissed_at TEXT,
+                UNIQUE (kind, entity_id, due_at)
+            );
+            INSERT INTO reminders_old
+                SELECT id, kind, entity_type, entity_id, title, due_at, created_at, dismissed_at
+                FROM reminders WHERE recipient_id IS NULL;
+            DROP TABLE reminders;
+            ALTER TABLE reminders_old RENAME TO reminders;
+            DROP TABLE task_notes;
+            DROP TABLE task_watchers;
+            DROP INDEX idx_tasks_assigned_to;
+            ALTER TABLE archived_tasks DROP COLUMN assigned_to;
+            ALTER TABLE tasks DROP COLUMN assigned_to;
+            "#
+        ),
+        migration!(
+            24,
+            "customer_categories",
+            "客户等级、客户标签及分类变更审计记录",
+            r#"
+            ALTER TABLE customers ADD COLUMN level TEXT NOT NULL DEFAULT 'Normal';
+            CREATE INDEX idx_customers_level ON customers(level);
+            CREATE TABLE customer_tags (
+                customer_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (customer_id, tag),
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            CREATE INDEX idx_customer_tags_tag ON customer_tags(tag);
+            CREATE TABLE customer_audit (
+                id TEXT PRIMARY KEY,
+                customer_id TEXT NOT NULL,
+                field TEXT NOT NULL,
+                old_value TEXT,
+                new_value TEXT,
+                actor TEXT,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            CREATE INDEX idx_customer_audit_customer ON customer_audit(customer_id, created_at);
+            "#,
+            r#"
+            DROP TABLE customer_audit;
+            DROP TABLE customer_tags;
+            DROP INDEX idx_customers_level;
+            ALTER TABLE customers DROP COLUMN level;
+            "#
+        ),
+        migration!(
+            25,
+            "holidays",
+            "节假日和调休上班日（预置2026年法定节假日）",
+            r#"
+            CREATE TABLE holidays (
+                date TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                kind TEXT NOT NULL CHECK (kind IN ('holiday', 'workday'))
+            );
+            INSERT INTO holidays (date, name, kind) VALUES
+                ('2026-01-01', '元旦', 'holiday'),
+                ('2026-01-02', '元旦', 'holiday'),
+                ('2026-01-03', '元旦', 'holiday'),
+                ('2026-01-04', '元旦调休', 'workday'),
+                ('2026-02-14', '春节调休', 'workday'),
+                ('2026-02-15', '春节', 'holiday'),
+                ('2026-02-16', '春节', 'holiday'),
+                ('2026-02-17', '春节', 'holiday'),
+                ('2026-02-18', '春节', 'holiday'),
+                ('2026-02-19', '春节', 'holiday'),
+                ('2026-02-20', '春节', 'holiday'),
+                ('2026-02-21', '春节', 'holiday'),
+                ('2026-02-22', '春节', 'holiday'),
+                ('2026-02-23', '春节', 'holiday'),
+                ('2026-02-28', '春节调休', 'workday'),
+                ('2026-04-04', '清明节', 'holiday'),
+                ('2026-04-05', '清明节', 'holiday'),
+                ('2026-04-06', '清明节', 'holiday'),
+                ('2026-05-01', '劳动节', 'holiday'),
+                ('2026-05-02', '劳动节', 'holiday'),
+                ('2026-05-03', '劳动节', 'holiday'),
+                ('2026-05-04', '劳动节', 'holiday'),
+                ('2026-05-05', '劳动节', 'holiday'),
+                ('2026-05-09', '劳动节调休', 'workday'),
+                ('2026-06-19', '端午节', 'holiday'),
+                ('2026-06-20', '端午节', 'holiday'),
+                ('2026-06-21', '端午节', 'holiday'),
+                ('2026-09-20', '国庆节调休', 'workday'),
+                ('2026-09-25', '中秋节', 'holiday'),
+                ('2026-09-26', '中秋节', 'holiday'),
+                ('2026-09-27', '中秋节', 'holiday'),
+                ('2026-10-01', '国庆节', 'holiday'),
+                ('2026-10-02', '国庆节', 'holiday'),
+                ('2026-10-03', '国庆节', 'holiday'),
+                ('2026-10-04', '国庆节', 'holiday'),
+                ('2026-10-05', '国庆节', 'holiday'),
+                ('2026-10-06', '国庆节', 'holiday'),
+                ('2026-10-07', '国庆节', 'holiday'),
+                ('2026-10-10', '国庆节调休', 'workday');
+            "#,
+            r#"
+            DROP TABLE holidays;
+            "#
+        ),
+        migration!(
+            26,
+            "differential_sync",
+            "差异同步：删除日志、收款修改时间、副本已应用的变更包时间",
+            differential_sync_sql(),
+            r#"
+            DROP TRIGGER order_payments_journal_delete;
+            DROP TRIGGER orders_journal_soft;
+            DROP TRIGGER orders_journal_delete;
+            DROP TRIGGER quotes_journal_soft;
+            DROP TRIGGER quotes_journal_delete;
+            DROP TRIGGER tasks_journal_soft;
+            DROP TRIGGER tasks_journal_delete;
+            DROP TRIGGER customers_journal_soft;
+            DROP TRIGGER customers_journal_delete;
+            DROP TABLE sync_state;
+            DROP TABLE deletion_journal;
+            DROP TRIGGER order_payments_touch_update;
+            DROP TRIGGER order_payments_touch_insert;
+            ALTER TABLE order_payments DROP COLUMN updated_at;
+            "#
+        ),
+        migration!(
+            27,
+            "customer_credit",
+            "客户信用额度和信用冻结",
+            r#"
+            ALTER TABLE customers ADD COLUMN credit_limit INTEGER;
+            ALTER TABLE customers ADD COLUMN credit_hold INTEGER NOT NULL DEFAULT 0;
+            "#,
+            r#"
+            ALTER TABLE customers DROP COLUMN credit_hold;
+            ALTER TABLE customers DROP COLUMN credit_limit;
+            "#
+        ),
+        migration!(
+            28,
+            "customer_activity",
+            "客户活跃度分数、流失风险标记及分数变化记录",
+            r#"
+            ALTER TABLE customer_summary ADD COLUMN activity_score INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE customer_summary ADD COLUMN churn_risk INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE customer_summary ADD COLUMN activity_scored_at TEXT;
+            CREATE INDEX idx_customer_summary_churn ON customer_summary(churn_risk);
+            CREATE TABLE customer_activity_scores (
+                customer_id TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                scored_at TEXT NOT NULL,
+                PRIMARY KEY (customer_id, scored_at),
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            "#,
+            r#"
+            DROP TABLE customer_activity_scores;
+            DROP INDEX idx_customer_summary_churn;
+            ALTER TABLE customer_summary DROP COLUMN activity_scored_at;
+            ALTER TABLE customer_summary DROP COLUMN churn_risk;
+            ALTER TABLE customer_summary DROP COLUMN activity_score;
+            "#
+        ),
+        migration!(
+            29,
+            "monthly_closings",
+            "月结统计快照及重新打开、结账的审计记录",
+            r#"
+            CREATE TABLE monthly_closings (
+                period TEXT PRIMARY KEY,
+                statistics TEXT NOT NULL,
+                locked INTEGER NOT NULL DEFAULT 1,
+                closed_at TEXT NOT NULL,
+                closed_by TEXT
+            );
+            CREATE TABLE monthly_closing_audit (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                period TEXT NOT NULL,
+                action TEXT NOT NULL,
+                actor TEXT,
+                reason TEXT,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_monthly_closing_audit_period ON monthly_closing_audit(period);
+            "#,
+            r#"
+            DROP TABLE monthly_closing_audit;
+            DROP TABLE monthly_closings;
+            "#
+        ),
+        migration!(
+            30,
+            "knowledge_articles",
+            "售后知识库文章及标题、现象、处理方法的全文索引（trigram分词）",
+            r#"
+            CREATE TABLE knowledge_articles (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                problem_category TEXT NOT NULL,
+                symptoms TEXT NOT NULL DEFAULT '',
+                solution TEXT NOT NULL,
+                product_categories TEXT NOT NULL DEFAULT '[]',
+                created_by TEXT,
+                usage_count INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_knowledge_articles_category
+                ON knowledge_articles(problem_category, usage_count);
+            CREATE VIRTUAL TABLE knowledge_articles_fts USING fts5(
+                article_id UNINDEXED,
+                title,
+                symptoms,
+                solution,
+                tokenize = 'trigram'
+            );
+            CREATE TRIGGER knowledge_articles_fts_insert AFTER INSERT ON knowledge_articles BEGIN
+                INSERT INTO knowledge_articles_fts (article_id, title, symptoms, solution)
+                VALUES (NEW.id, NEW.title, NEW.symptoms, NEW.solution);
+            END;
+            CREATE TRIGGER knowledge_articles_fts_delete AFTER DELETE ON knowledge_articles BEGIN
+                DELETE FROM knowledge_articles_fts WHERE article_id = OLD.id;
+            END;
+            CREATE TRIGGER knowledge_articles_fts_update
+            AFTER UPDATE OF id, title, symptoms, solution ON knowledge_articles
+            BEGIN
+                DELETE FROM knowledge_articles_fts WHERE article_id = OLD.id;
+                INSERT INTO knowledge_articles_fts (article_id, title, symptoms, solution)
+                VALUES (NEW.id, NEW.title, NEW.symptoms, NEW.solution);
+            END;
+            "#,
+            r#"
+            DROP TRIGGER knowledge_articles_fts_update;
+            DROP TRIGGER knowledge_articles_fts_delete;
+            DROP TRIGGER knowledge_articles_fts_insert;
+            DROP TABLE knowledge_articles_fts;
+            DROP TABLE knowledge_articles;
+            "#
+        ),
+        migration!(
+            31,
+            "canonical_json_versions",
+            "报价修订快照和发件箱事件记录规范JSON的结构版本",
+            r#"
+            ALTER TABLE quote_revisions ADD COLUMN schema_version INTEGER NOT NULL DEFAULT 1;
+            ALTER TABLE event_outbox ADD COLUMN schema_version INTEGER NOT NULL DEFAULT 1;
+            "#,
+            r#"
+            ALTER TABLE event_outbox DROP COLUMN schema_version;
+            ALTER TABLE quote_revisions DROP COLUMN schema_version;
+            "#
+        ),
+        migration!(
+            32,
+            "pool_usage_stats",
+            "记录每次运行的连接池使用情况，用于建议最大连接数",
+            r#"
+            CREATE TABLE pool_usage_stats (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at TEXT NOT NULL,
+                ended_at TEXT NOT NULL,
+                max_connections INTEGER NOT NULL,
+                peak_checkouts INTEGER NOT NULL,
+                checkouts INTEGER NOT NULL,
+                total_wait_ms INTEGER NOT NULL,
+                max_wait_ms INTEGER NOT NULL,
+                timeouts INTEGER NOT NULL,
+                evictions INTEGER NOT NULL
+            );
+            "#,
+            r#"
+            DROP TABLE pool_usage_stats;
+            "#
+        ),
+        migration!(
+            33,
+            "intake_log",
+            "售后邮箱收件记录，按Message-ID避免同一封邮件重复建单",
+            r#"
+            CREATE TABLE intake_log (
+                message_id TEXT PRIMARY KEY,
+                ticket_id TEXT NOT NULL,
+                ticket_number TEXT NOT NULL,
+                sender TEXT,
+                customer_id TEXT,
+                subject TEXT NOT NULL DEFAULT '',
+                attachments TEXT NOT NULL DEFAULT '[]',
+                received_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_intake_log_ticket_number ON intake_log(ticket_number);
+            "#,
+            r#"
+            DROP TABLE intake_log;
+            "#
+        ),
+        migration!(
+            34,
+            "size_history",
+            "记录数据库文件大小和各表行数的变化，用于发现异常增长",
+            r#"
+            CREATE TABLE size_history (
+                taken_at TEXT PRIMARY KEY,
+                db_bytes INTEGER NOT NULL,
+                wal_bytes INTEGER NOT NULL,
+                table_rows TEXT NOT NULL DEFAULT '{}'
+            );
+            "#,
+            r#"
+            DROP TABLE size_history;
+            "#
+        ),
+        migration!(
+            35,
+            "service_tickets",
+            "售后工单（关闭时间供客户时间线显示）",
+            r#"
+            CREATE TABLE service_tickets (
+                id TEXT PRIMARY KEY,
+                ticket_number TEXT NOT NULL UNIQUE,
+                customer_id TEXT NOT NULL,
+                problem_category TEXT NOT NULL,
+                description TEXT NOT NULL DEFAULT '',
+                solution_method TEXT,
+                status TEXT NOT NULL DEFAULT 'New',
+                priority TEXT NOT NULL DEFAULT 'Medium',
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                closed_at TEXT
+            );
+            CREATE INDEX idx_service_tickets_customer ON service_tickets(customer_id, created_at);
+            "#,
+            r#"
+            DROP TABLE service_tickets;
+            "#
+        ),
+        migration!(
+            36,
+            "field_policies",
+            "各实体标准字段的可选、必填、隐藏设置（只保存非默认值）",
+            r#"
+            CREATE TABLE field_policies (
+                entity TEXT NOT NULL,
+                field TEXT NOT NULL,
+                requirement TEXT NOT NULL,
+                PRIMARY KEY (entity, field)
+            );
+            "#,
+            r#"
+            DROP TABLE field_policies;
+            "#
+        ),
+        migration!(
+            37,
+            "storage_gc_log",
+            "附件孤儿文件和临时文件清理记录（诊断信息显示）",
+            r#"
+            CREATE TABLE storage_gc_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ran_at TEXT NOT NULL,
+                dry_run INTEGER NOT NULL,
+                orphan_blobs INTEGER NOT NULL,
+                temp_files INTEGER NOT NULL,
+                bytes INTEGER NOT NULL
+            );
+            "#,
+            r#"
+            DROP TABLE storage_gc_log;
+            "#
+        ),
+        migration!(
+            38,
+            "reminder_snooze",
+            "提醒推迟：推迟期间不计入未读提醒数",
+            r#"
+            ALTER TABLE reminders ADD COLUMN snoozed_until TEXT;
+            "#,
+            r#"
+            ALTER TABLE reminders DROP COLUMN snoozed_until;
+            "#
+        ),
+        migration!(
+            39,
+            "retention",
+            "审计记录按月汇总表、保留策略执行记录",
+            r#"
+            CREATE TABLE change_log_monthly (
+                entity_type TEXT NOT NULL,
+                month TEXT NOT NULL,
+                field TEXT NOT NULL,
+                change_count INTEGER NOT NULL,
+                PRIMARY KEY (entity_type, month, field)
+            );
+            CREATE INDEX idx_customer_audit_created ON customer_audit(created_at);
+            CREATE TABLE retention_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                ran_at TEXT NOT NULL,
+                audit_rows INTEGER NOT NULL,
+                outbox_rows INTEGER NOT NULL
+            );
+            "#,
+            r#"
+            DROP TABLE retention_log;
+            DROP INDEX idx_customer_audit_created;
+            DROP TABLE change_log_monthly;
+            "#
+        ),
+        migration!(
+            40,
+            "purchase_orders",
+            "采购订单及明细、库存变动、单据编号序列",
+            r#"
+            CREATE TABLE document_sequences (
+                prefix TEXT NOT NULL,
+                period TEXT NOT NULL,
+                last_value INTEGER NOT NULL,
+                PRIMARY KEY (prefix, period)
+            );
+            CREATE TABLE purchase_orders (
+                id TEXT PRIMARY KEY,
+                po_number TEXT NOT NULL UNIQUE,
+                supplier_id TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'draft',
+                expected_date TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                created_by TEXT,
+                deleted_at TEXT
+            );
+            CREATE INDEX idx_purchase_orders_supplier ON purchase_orders(supplier_id, created_at);
+            CREATE TABLE purchase_order_lines (
+                purchase_order_id TEXT NOT NULL,
+                line_no INTEGER NOT NULL,
+                product_id TEXT NOT NULL,
+                quantity INTEGER NOT NULL,
+                unit_price INTEGER NOT NULL,
+                received_quantity INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (purchase_order_id, line_no),
+                FOREIGN KEY (purchase_order_id) REFERENCES purchase_orders (id) ON DELETE CASCADE
+            );
+            CREATE TABLE stock_movements (
+                id TEXT PRIMARY KEY,
+                product_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                quantity INTEGER NOT NULL,
+                reference_id TEXT,
+                occurred_at TEXT NOT NULL,
+                created_by TEXT
+            );
+            CREATE INDEX idx_stock_movements_product ON stock_movements(product_id);
+            CREATE INDEX idx_stock_movements_reference ON stock_movements(reference_id);
+            "#,
+            r#"
+            DROP TABLE stock_movements;
+            DROP TABLE purchase_order_lines;
+            DROP TABLE purchase_orders;
+            DROP TABLE document_sequences;
+            "#
+        ),
+        migration!(
+            41,
+            "attachment_references",
+            "附件引用登记和已删除附件标记",
+            r#"
+            CREATE TABLE attachment_references (
+                hash TEXT NOT NULL,
+                source TEXT NOT NULL,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (hash, source, entity_type, entity_id)
+            );
+            CREATE INDEX idx_attachment_references_entity
+                ON attachment_references(entity_type, entity_id);
+            CREATE TABLE attachment_tombstones (
+                hash TEXT PRIMARY KEY,
+                deleted_at TEXT NOT NULL,
+                deleted_by TEXT
+            );
+            "#,
+            r#"
+            DROP TABLE attachment_tombstones;
+            DROP TABLE attachment_references;
+            "#
+        ),
+        migration!(
+            42,
+            "user_reassignment",
+            "客户和销售机会负责人，停用用户时的转交记录",
+            r#"
+            ALTER TABLE customers ADD COLUMN owner_id TEXT;
+            ALTER TABLE opportunities ADD COLUMN owner_id TEXT;
+            CREATE INDEX idx_customers_owner_id ON customers(owner_id);
+            CREATE INDEX idx_opportunities_owner_id ON opportunities(owner_id);
+            CREATE TABLE user_reassignment_audit (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                reassigned_to TEXT,
+                entity_type TEXT NOT NULL,
+                record_count INTEGER NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_user_reassignment_audit_user
+                ON user_reassignment_audit(user_id, created_at);
+            "#,
+            r#"
+            DROP TABLE user_reassignment_audit;
+            DROP INDEX idx_opportunities_owner_id;
+            DROP INDEX idx_customers_owner_id;
+            ALTER TABLE opportunities DROP COLUMN owner_id;
+            ALTER TABLE customers DROP COLUMN owner_id;
+            "#
+        ),
+        migration!(
+            43,
+            "contact_birthday",
+            "联系人生日，用于客户维护提醒",
+            r#"
+            ALTER TABLE customers ADD COLUMN contact_birthday TEXT;
+            "#,
+            r#"
+            ALTER TABLE customers DROP COLUMN contact_birthday;
+            "#
+        ),
+        migration!(
+            44,
+            "customer_contacts",
+            "客户联系人，每个客户至多一个首要联系人",
+            r#"
+            CREATE TABLE customer_contacts (
+                id TEXT PRIMARY KEY,
+                customer_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                phone TEXT,
+                email TEXT,
+                is_primary INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
+            );
+            CREATE INDEX idx_customer_contacts_customer ON customer_contacts(customer_id);
+            CREATE UNIQUE INDEX idx_customer_contacts_primary
+                ON customer_contacts(customer_id) WHERE is_primary = 1;
+            "#,
+            r#"
+            DROP TABLE customer_contacts;
+            "#
+        ),
+        migration!(
+            45,
+            "quote_items",
+            "报价明细：板材按品种、厚度和数量逐行计价，小计以分保存；已有报价的明细取自最新修订版本",
+            r#"
+            CREATE TABLE quote_items (
+                id TEXT PRIMARY KEY,
+                quote_id TEXT NOT NULL,
+                line_no INTEGER NOT NULL,
+                product_id TEXT,
+                product_name TEXT NOT NULL,
+                specification TEXT,
+                unit TEXT,
+                quantity REAL NOT NULL,
+                unit_price REAL NOT NULL,
+                line_total INTEGER NOT NULL,
+                FOREIGN KEY (quote_id) REFERENCES quotes (id) ON DELETE CASCADE
+            );
+            CREATE UNIQUE INDEX idx_quote_items_quote ON quote_items(quote_id, line_no);
+            INSERT INTO quote_items (id, quote_id, line_no, product_id, product_name,
+                                     specification, unit, quantity, unit_price, line_total)
+            SELECT lower(json_extract(i.value, '$.id')), q.id, i.key,
+                   lower(json_extract(i.value, '$.product_id')),
+                   json_extract(i.value, '$.product_name'),
+                   json_extract(i.value, '$.specification'),
+                   json_extract(i.value, '$.unit'),
+                   json_extract(i.value, '$.quantity'),
+                   json_extract(i.value, '$.unit_price'),
+                   CAST(ROUND(COALESCE(json_extract(i.value, '$.line_total'),
+                                       json_extract(i.value, '$.quantity')
+                                       * json_extract(i.value, '$.unit_price')) * 100)
+                        AS INTEGER)
+            FROM quote_revisions r
+            JOIN quotes q ON q.id = r.quote_id
+            JOIN json_each(r.snapshot, '$.items') i
+            WHERE r.revision_no = (SELECT MAX(revision_no) FROM quote_revisions
+                                   WHERE quote_id = r.quote_id);
+            "#,
+            r#"
+            DROP TABLE quote_items;
+            "#
+        ),
+    ]
+}
+
+/// 内置迁移的最新版本号
+pub fn latest_version() -> u32 {
+    builtin_migrations()
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or(0)
+}
@@ -0,0 +1,373 @@
+//! 数据库大小记录
+//!
+//! 健康监控每次执行后把数据库文件（含WAL）大小和各表行数写入 `size_history` 表，超过
+//! 保留期（[`SIZE_HISTORY_RETENTION_DAYS`](minicrm_core::SIZE_HISTORY_RETENTION_DAYS) 天）的
+//! 记录随写入一起删除。增长超过阈值时写日志并显示桌面通知（同一窗口的警告每天最多通知
+//! 一次），诊断界面据此绘制大小变化曲线。
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use minicrm_core::{
+    detect_size_growth, size_history_cutoff, Clock, CoreResult, DesktopNotifier, Job, JobSchedule,
+    SizeGrowth, SizeGrowthThresholds, SizeGrowthWindow, SizeSample, SystemClock,
+};
+use rusqlite::{params, Row};
+use tracing::{info, warn};
+
+use crate::database::DatabaseConnection;
+use crate::database::time::{parse_time, time_key};
+
+/// 默认记录间隔（分钟）
+pub const DEFAULT_SIZE_MONITOR_MINUTES: i64 = 60;
+
+fn count(row: &Row<'_>, index: usize) -> rusqlite::Result<u64> {
+    Ok(u64::try_from(row.get::<_, i64>(index)?).unwrap_or(0))
+}
+
+fn sql_count(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+fn row_to_sample(row: &Row<'_>) -> rusqlite::Result<SizeSample> {
+    let table_rows: String = row.get(3)?;
+    Ok(SizeSample {
+        taken_at: parse_time(0, &row.get::<_, String>(0)?)?,
+        db_bytes: count(row, 1)?,
+        wal_bytes: count(row, 2)?,
+        table_rows: serde_json::from_str(&table_rows).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+    })
+}
+
+/// SQL标识符（表名）加引号
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// 数据库大小记录存储
+#[derive(Debug, Clone)]
+pub struct SizeHistoryStore {
+    connection: DatabaseConnection,
+}
+
+impl SizeHistoryStore {
+    /// 创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 读取当前的数据库文件大小、WAL大小和各表行数
+    ///
+    /// 内存数据库的文件大小按页数计算。虚拟表（全文索引）不统计行数。
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn measure(&self, taken_at: DateTime<Utc>) -> Result<SizeSample> {
+        let file = self
+            .connection
+            .query_row("PRAGMA database_list", [], |row| row.get::<_, String>(2))?;
+        let (db_bytes, wal_bytes) = if file.is_empty() {
+            let pages = self
+                .connection
+                .query_row("PRAGMA page_count", [], |row| row.get::<_, i64>(0))?;
+            let page_size = self
+                .connection
+                .query_row("PRAGMA page_size", [], |row| row.get::<_, i64>(0))?;
+            (u64::try_from(pages * page_size).unwrap_or(0), 0)
+        } else {
+            (
+                std::fs::metadata(&file).map_or(0, |m| m.len()),
+                std::fs::metadata(format!("{}-wal", file)).map_or(0, |m| m.len()),
+            )
+        };
+
+        let tables: Vec<String> = self.connection.query_map(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+               AND sql NOT LIKE 'CREATE VIRTUAL TABLE%'
+             ORDER BY name",
+            [],
+            |row| row.get(0),
+        )?;
+        let mut table_rows = BTreeMap::new();
+        for table in tables {
+            let rows = self.connection.query_row(
+                &format!("SELECT COUNT(*) FROM {}", quote_ident(&table)),
+                [],
+                |row| count(row, 0),
+            )?;
+            table_rows.insert(table, rows);
+        }
+
+        Ok(SizeSample {
+            taken_at,
+            db_bytes,
+            wal_bytes,
+            table_rows,
+        })
+    }
+
+    /// 保存一次记录，并删除超过保留期的记录，返回删除的条数
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn record(&self, sample: &SizeSample) -> Result<usize> {
+        let table_rows = serde_json::to_string(&sample.table_rows).context("无法序列化各表行数")?;
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "INSERT OR REPLACE INTO size_history (taken_at, db_bytes, wal_bytes, table_rows)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        time_key(sample.taken_at),
+                        sql_count(sample.db_bytes),
+                        sql_count(sample.wal_bytes),
+                        table_rows,
+                    ],
+                )?;
+                let pruned = tx.execute(
+                    "DELETE FROM size_history WHERE taken_at < ?1",
+                    [time_key(size_history_cutoff(sample.taken_at))],
+                )?;
+                Ok(pruned)
+            })
+            .context("无法保存数据库大小记录")
+    }
+
+    /// `since` 之后的记录（按时间先后）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn history(&self, since: DateTime<Utc>) -> Result<Vec<SizeSample>> {
+        self.connection.query_map(
+            "SELECT taken_at, db_bytes, wal_bytes, table_rows
+             FROM size_history WHERE taken_at >= ?1 ORDER BY taken_at",
+            [time_key(since)],
+            row_to_sample,
+        )
+    }
+}
+
+/// 数据库增长监控任务
+///
+/// 每次执行记录一次大小，再按最近7天的记录检测增长。
+pub struct DatabaseSizeMonitor {
+    store: SizeHistoryStore,
+    thresholds: SizeGrowthThresholds,
+    interval: Duration,
+    clock: Arc<dyn Clock>,
+    notifier: Option<Arc<dyn DesktopNotifier + Send + Sync>>,
+    last_notified: Mutex<Option<(SizeGrowthWindow, DateTime<Utc>)>>,
+}
+
+impl std::fmt::Debug for DatabaseSizeMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseSizeMonitor")
+            .field("thresholds", &self.thresholds)
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DatabaseSizeMonitor {
+    /// 创建监控任务
+    pub fn new(store: SizeHistoryStore, thresholds: SizeGrowthThresholds) -> Self {
+        Self {
+            store,
+            thresholds,
+            interval: Duration::minutes(DEFAULT_SIZE_MONITOR_MINUTES),
+            clock: Arc::new(SystemClock),
+            notifier: None,
+            last_notified: Mutex::new(None),
+        }
+    }
+
+    /// 设置记录间隔
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 设置时钟
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置桌面通知
+    pub fn with_notifier(mut self, notifier: Arc<dyn DesktopNotifier + Send + Sync>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// 记录一次大小并检测增长，超过阈值时返回增长情况
+    ///
+    /// # Errors
+    ///
+    /// 读取或保存记录失败时返回错误。
+    pub fn tick(&self) -> Result<Option<SizeGrowth>> {
+        let now = self.clock.now();
+        let sample = self.store.measure(now)?;
+        let pruned = self.store.record(&sample)?;
+        if pruned > 0 {
+            info!("已删除 {} 条过期的数据库大小记录", pruned);
+        }
+
+        let history = self
+            .store
+            .history(now - SizeGrowthWindow::Week.duration())?;
+        let Some(growth) = detect_size_growth(&history, &self.thresholds) else {
+            return Ok(None);
+        };
+        let message = growth.message();
+        warn!("数据库增长过快: {}", message);
+        if self.should_notify(growth.window, now) {
+            if let Some(notifier) = &self.notifier {
+                if let Err(e) = notifier.notify("数据库增长过快", &message) {
+                    warn!("数据库增长桌面通知失败: {}", e);
+                }
+            }
+        }
+        Ok(Some(growth))
+    }
+
+    /// 同一窗口的警告每天最多通知一次
+    fn should_notify(&self, window: SizeGrowthWindow, now: DateTime<Utc>) -> bool {
+        let mut last = self
+            .last_notified
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if matches!(*last, Some((w, at)) if w == window && now - at < Duration::hours(24)) {
+            return false;
+        }
+        *last = Some((window, now));
+        true
+    }
+}
+
+#[async_trait]
+impl Job for DatabaseSizeMonitor {
+    fn name(&self) -> &str {
+        "database_size_monitor"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(self.interval)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        self.tick()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use minicrm_core::{ManualClock, SIZE_HISTORY_RETENTION_DAYS};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, SizeHistoryStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        (temp_dir, SizeHistoryStore::new(connection))
+    }
+
+    fn sample(at: DateTime<Utc>, db_mb: u64, rows: &[(&str, u64)]) -> SizeSample {
+        SizeSample {
+            taken_at: at,
+            db_bytes: db_mb * 1024 * 1024,
+            wal_bytes: 4096,
+            table_rows: rows.iter().map(|(t, n)| (t.to_string(), *n)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_record_round_trip_and_pruning() {
+        let (_dir, store) = create_test_store();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        for day in 0..=SIZE_HISTORY_RETENTION_DAYS {
+            let pruned = store
+                .record(&sample(
+                    start + Duration::days(day),
+                    100,
+                    &[("customers", 10)],
+                ))
+                .unwrap();
+            assert_eq!(pruned, 0);
+        }
+        let all = store.history(start).unwrap();
+        assert_eq!(all.len(), SIZE_HISTORY_RETENTION_DAYS as usize + 1);
+        assert_eq!(all[0].table_rows.get("customers"), Some(&10));
+        assert_eq!(all[0].total_bytes(), 100 * 1024 * 1024 + 4096);
+
+        // 再过两天，最早的两条超过保留期
+        let later = start + Duration::days(SIZE_HISTORY_RETENTION_DAYS + 2);
+        assert_eq!(store.record(&sample(later, 120, &[])).unwrap(), 2);
+        let remaining = store.history(start).unwrap();
+        assert_eq!(remaining[0].taken_at, start + Duration::days(2));
+        assert_eq!(remaining.last().unwrap().taken_at, later);
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifier(Mutex<Vec<String>>);
+
+    impl DesktopNotifier for RecordingNotifier {
+        fn notify(&self, _title: &str, body: &str) -> CoreResult<()> {
+            self.0.lock().unwrap().push(body.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_monitor_warns_with_top_tables() {
+        let (_dir, store) = create_test_store();
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 2, 0, 0, 0).unwrap(),
+        ));
+        // 一天前的数据库很小，各表几乎为空（20小时前的记录让一小时后仍有一天的窗口可比）
+        for hours in [24, 20] {
+            store
+                .record(&SizeSample {
+                    taken_at: clock.now() - Duration::hours(hours),
+                    db_bytes: 4096,
+                    wal_bytes: 0,
+                    table_rows: BTreeMap::new(),
+                })
+                .unwrap();
+        }
+
+        let notifier = Arc::new(RecordingNotifier::default());
+        let monitor = DatabaseSizeMonitor::new(
+            store.clone(),
+            SizeGrowthThresholds {
+                mb_per_day: 1000.0,
+                percent_per_day: 10.0,
+            },
+        )
+        .with_clock(clock.clone())
+        .with_notifier(notifier.clone());
+
+        let growth = monitor.tick().unwrap().unwrap();
+        assert_eq!(growth.window, SizeGrowthWindow::Day);
+        assert!(growth.top_tables.len() <= 3);
+        assert!(growth.top_tables.iter().all(|t| t.rows > 0));
+        assert_eq!(notifier.0.lock().unwrap().len(), 1);
+        assert_eq!(store.history(clock.now()).unwrap().len(), 1);
+
+        // 一小时后仍然超过阈值，当天不再通知
+        clock.advance(Duration::hours(1));
+        assert!(monitor.tick().unwrap().is_some());
+        assert_eq!(notifier.0.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,319 @@
+//! 慢查询记录
+//!
+//! 查询耗时超过阈值时记录到慢查询列表，并用 `EXPLAIN QUERY PLAN` 捕获执行计划写入日志。
+//! 同一条SQL在一次会话中只捕获一次计划（按语句哈希去重），避免反复刷屏。
+//! 捕获时不绑定参数（按NULL处理），SQLite选择计划时基本不依赖参数值。
+//!
+//! 启用 `query-plan-assertions` 特性后，可以登记“必须走索引”的语句：
+//! 这些语句对大表做全表扫描时直接panic，让测试失败。
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use minicrm_core::{PlanRow, SlowQuery};
+use rusqlite::Connection;
+use tracing::warn;
+
+/// 默认慢查询阈值
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(200);
+
+/// 慢查询列表保留的条数
+const MAX_ENTRIES: usize = 50;
+
+/// 捕获SQL语句的执行计划（不绑定参数）
+pub(crate) fn explain_unbound(conn: &Connection, sql: &str) -> rusqlite::Result<Vec<PlanRow>> {
+    let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+    let mut rows = stmt.raw_query();
+    let mut plan = Vec::new();
+    while let Some(row) = rows.next()? {
+        plan.push(plan_row(row)?);
+    }
+    Ok(plan)
+}
+
+/// 读取 `EXPLAIN QUERY PLAN` 的一行
+pub(crate) fn plan_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<PlanRow> {
+    Ok(PlanRow {
+        id: row.get(0)?,
+        parent: row.get(1)?,
+        detail: row.get(3)?,
+    })
+}
+
+fn statement_hash(sql: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    sql.trim().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug, Default)]
+struct SlowQueryState {
+    entries: VecDeque<SlowQuery>,
+    plans: HashMap<u64, Vec<PlanRow>>,
+}
+
+/// 慢查询记录器（一次会话共用一个）
+#[derive(Debug)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+    state: Mutex<SlowQueryState>,
+    #[cfg(feature = "query-plan-assertions")]
+    assertions: Mutex<IndexAssertions>,
+}
+
+impl Default for SlowQueryLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_SLOW_QUERY_THRESHOLD)
+    }
+}
+
+impl SlowQueryLog {
+    /// 创建记录器，耗时达到 `threshold` 的查询视为慢查询
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            state: Mutex::new(SlowQueryState::default()),
+            #[cfg(feature = "query-plan-assertions")]
+            assertions: Mutex::new(IndexAssertions::default()),
+        }
+    }
+
+    /// 慢查询阈值
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// 最近的慢查询（最新的在前）
+    pub fn entries(&self) -> Vec<SlowQuery> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.entries.iter().rev().cloned().collect()
+    }
+
+    /// 本次会话已捕获执行计划的语句数
+    pub fn captured_plans(&self) -> usize {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.plans.len()
+    }
+
+    /// 清空慢查询列表（已捕获的计划保留，不会重复捕获）
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.entries.clear();
+    }
+
+    /// 记录一次查询耗时
+    pub(crate) fn observe(&self, conn: &Connection, sql: &str, elapsed: Duration) {
+        #[cfg(feature = "query-plan-assertions")]
+        self.check_index(conn, sql);
+
+        if elapsed < self.threshold {
+            return;
+        }
+        let duration_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let plan = state
+            .plans
+            .entry(statement_hash(sql))
+            .or_insert_with(|| {
+                let plan = explain_unbound(conn, sql).unwrap_or_else(|e| {
+                    warn!("无法获取执行计划: {}", e);
+                    Vec::new()
+                });
+                let steps: Vec<&str> = plan.iter().map(|row| row.detail.as_str()).collect();
+                warn!(
+                    "慢查询（{} ms）: {}；执行计划: {}",
+                    duration_ms,
+                    sql,
+                    steps.join(" / ")
+                );
+                plan
+            })
+            .clone();
+
+        if state.entries.len() == MAX_ENTRIES {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(SlowQuery {
+            sql: sql.to_string(),
+            duration_ms,
+            plan,
+        });
+    }
+}
+
+/// 大表的默认行数下限
+#[cfg(feature = "query-plan-assertions")]
+pub const DEFAULT_LARGE_TABLE_ROWS: u64 = 1000;
+
+#[cfg(feature = "query-plan-assertions")]
+#[derive(Debug)]
+struct IndexAssertions {
+    large_table_rows: u64,
+    required: std::collections::HashSet<u64>,
+    checked: std::collections::HashSet<u64>,
+}
+
+#[cfg(feature = "query-plan-assertions")]
+impl Default for IndexAssertions {
+    fn default() -> Self {
+        Self {
+            large_table_rows: DEFAULT_LARGE_TABLE_ROWS,
+            required: std::collections::HashSet::new(),
+            checked: std::collections::HashSet::new(),
+        }
+    }
+}
+
+#[cfg(feature = "query-plan-assertions")]
+impl SlowQueryLog {
+    /// 登记必须走索引的语句
+    pub fn require_index(&self, sql: &str) {
+        let mut assertions = self.assertions.lock().unwrap_or_else(|e| e.into_inner());
+        assertions.required.insert(statement_hash(sql));
+    }
+
+    /// 设置大表的行数下限，行数更少的表允许全表扫描
+    pub fn set_large_table_rows(&self, rows: u64) {
+        let mut assertions = self.assertions.lock().unwrap_or_else(|e| e.into_inner());
+        assertions.large_table_rows = rows;
+    }
+
+    /// 登记的语句第一次执行时检查计划，大表全表扫描时panic
+    fn check_index(&self, conn: &Connection, sql: &str) {
+        let hash = statement_hash(sql);
+        let large_table_rows = {
+            let mut assertions = self.assertions.lock().unwrap_or_else(|e| e.into_inner());
+            if !assertions.required.contains(&hash) || !assertions.checked.insert(hash) {
+                return;
+            }
+            assertions.large_table_rows
+        };
+        let Ok(plan) = explain_unbound(conn, sql) else {
+            return;
+        };
+        for table in plan.iter().filter_map(PlanRow::full_scan_table) {
+            let rows: u64 = conn
+                .query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap_or(0);
+            assert!(
+                rows < large_table_rows,
+                "必须走索引的查询对 {} 做了全表扫描（{} 行）: {}",
+                table,
+                rows,
+                sql
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::pool::DatabasePoolBuilder;
+    use crate::database::DatabaseConnection;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn create_connection(dir: &TempDir, log: Arc<SlowQueryLog>) -> DatabaseConnection {
+        let pool = DatabasePoolBuilder::new(dir.path().join("test.db").to_string_lossy())
+            .build()
+            .unwrap();
+        let connection = DatabaseConnection::new(pool).with_slow_query_log(log);
+        connection
+            .execute(
+                "CREATE TABLE parts (id INTEGER PRIMARY KEY, name TEXT, code TEXT)",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute("CREATE INDEX idx_parts_code ON parts(code)", [])
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn test_plan_shows_scan_and_search() {
+        let dir = TempDir::new().unwrap();
+        let connection = create_connection(&dir, Arc::new(SlowQueryLog::default()));
+
+        let unindexed = connection
+            .explain_query_plan("SELECT id FROM parts WHERE name = ?1", ["板材"])
+            .unwrap();
+        assert_eq!(unindexed[0].full_scan_table(), Some("parts"));
+        assert!(unindexed[0].detail.starts_with("SCAN"));
+
+        let indexed = connection
+            .explain_query_plan("SELECT id FROM parts WHERE code = ?1", ["P-01"])
+            .unwrap();
+        assert!(indexed[0].detail.starts_with("SEARCH"));
+        assert!(indexed[0].detail.contains("idx_parts_code"));
+        assert_eq!(indexed[0].full_scan_table(), None);
+    }
+
+    #[test]
+    fn test_plan_captured_once_per_statement() {
+        let dir = TempDir::new().unwrap();
+        let log = Arc::new(SlowQueryLog::new(Duration::ZERO));
+        let connection = create_connection(&dir, log.clone());
+
+        let by_name = "SELECT COUNT(*) FROM parts WHERE name = ?1";
+        for name in ["板材", "封边条", "五金"] {
+            let _: i64 = connection
+                .query_row(by_name, [name], |row| row.get(0))
+                .unwrap();
+        }
+        connection
+            .query_map("SELECT id FROM parts WHERE code = ?1", ["P-01"], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap();
+
+        let entries = log.entries();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(log.captured_plans(), 2);
+        assert!(entries[0].plan[0].detail.starts_with("SEARCH"));
+        assert!(entries[1..]
+            .iter()
+            .all(|entry| entry.plan[0].full_scan_table() == Some("parts")));
+
+        // 未超过阈值的查询不记录
+        let quiet = Arc::new(SlowQueryLog::new(Duration::from_secs(60)));
+        let dir = TempDir::new().unwrap();
+        let connection = create_connection(&dir, quiet.clone());
+        let _: i64 = connection
+            .query_row(by_name, ["板材"], |row| row.get(0))
+            .unwrap();
+        assert!(quiet.entries().is_empty());
+        assert_eq!(quiet.captured_plans(), 0);
+    }
+
+    #[cfg(feature = "query-plan-assertions")]
+    #[test]
+    #[should_panic(expected = "全表扫描")]
+    fn test_required_index_fails_on_large_scan() {
+        let dir = TempDir::new().unwrap();
+        let log = Arc::new(SlowQueryLog::default());
+        log.set_large_table_rows(2);
+        let connection = create_connection(&dir, log.clone());
+        for code in ["P-01", "P-02", "P-03"] {
+            connection
+                .execute("INSERT INTO parts (name, code) VALUES ('板材', ?1)", [code])
+                .unwrap();
+        }
+
+        let by_code = "SELECT id FROM parts WHERE code = ?1";
+        log.require_index(by_code);
+        connection
+            .query_map(by_code, ["P-01"], |row| row.get::<_, i64>(0))
+            .unwrap();
+
+        let by_name = "SELECT id FROM parts WHERE name = ?1";
+        log.require_index(by_name);
+        let _ = connection.query_map(by_name, ["板材"], |row| row.get::<_, i64>(0));
+    }
+}
@@ -0,0 +1,42 @@
+//! 时间列适配
+//!
+//! 时间以RFC 3339文本存储，写入时统一为UTC、微秒精度并以 `Z` 结尾，按字符串比较
+//! 即按时间先后排序；读取时接受任意时区偏移，无法解析时返回带列号的转换错误。
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Row, RowIndex};
+
+/// 可按字符串排序的时间文本
+pub fn time_key(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// 解析第 `index` 列读出的时间文本
+pub fn parse_time(index: usize, value: &str) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        })
+}
+
+/// 读取时间列
+pub fn get_time<I: RowIndex>(row: &Row<'_>, index: I) -> rusqlite::Result<DateTime<Utc>> {
+    let index = index.idx(row.as_ref())?;
+    parse_time(index, &row.get::<_, String>(index)?)
+}
+
+/// 读取可为空的时间列
+pub fn get_optional_time<I: RowIndex>(
+    row: &Row<'_>,
+    index: I,
+) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    let index = index.idx(row.as_ref())?;
+    row.get::<_, Option<String>>(index)?
+        .map(|value| parse_time(index, &value))
+        .transpose()
+}
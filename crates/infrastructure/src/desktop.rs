@@ -0,0 +1,69 @@
+//! 桌面集成
+//!
+//! 通过系统命令打开链接和写入剪贴板：链接交给系统默认程序（与打开附件相同），
+//! 剪贴板使用各平台自带的命令（Linux 上需要安装 `wl-copy` 或 `xclip`）。
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use minicrm_core::{ClipboardWriter, CoreError, CoreResult, UriOpener};
+
+use crate::attachments::OPENER;
+
+/// 用系统默认程序打开链接
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemUriOpener;
+
+impl UriOpener for SystemUriOpener {
+    fn open_uri(&self, uri: &str) -> CoreResult<()> {
+        let mut child = Command::new(OPENER)
+            .arg(uri)
+            .spawn()
+            .map_err(|e| CoreError::Other(format!("无法打开链接 {}: {}", uri, e)))?;
+        // 回收子进程，避免留下僵尸进程
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+/// 系统剪贴板（通过平台命令写入）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClipboard;
+
+impl SystemClipboard {
+    /// 写入剪贴板的命令及参数
+    fn command() -> Command {
+        #[cfg(target_os = "windows")]
+        let command = Command::new("clip");
+        #[cfg(target_os = "macos")]
+        let command = Command::new("pbcopy");
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        let command = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            Command::new("wl-copy")
+        } else {
+            let mut command = Command::new("xclip");
+            command.args(["-selection", "clipboard"]);
+            command
+        };
+        command
+    }
+}
+
+impl ClipboardWriter for SystemClipboard {
+    fn set_text(&self, text: &str) -> CoreResult<()> {
+        let failed = |e: std::io::Error| CoreError::Other(format!("无法写入剪贴板: {}", e));
+        let mut child = Self::command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(failed)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).map_err(failed)?;
+        }
+        let status = child.wait().map_err(failed)?;
+        if !status.success() {
+            return Err(CoreError::Other(format!("无法写入剪贴板: {}", status)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,424 @@
+//! CSV导出
+//!
+//! 导出客户、供应商列表。文件以 UTF-8 BOM 开头，Excel 可直接打开；
+//! 固定列之后按显示顺序追加未停用的自定义字段。导出到文件时可以取消，取消后删除
+//! 已写出的部分文件。
+//!
+//! 行数不定的列表导出使用 [`CsvFileWriter`] 逐批追加，不在内存中保留整张表。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use minicrm_core::{
+    CancellationToken, CoreError, CoreResult, CustomFieldDefinition, Customer, DateStyle,
+    DisplayName, Locale, Supplier, SupplierLevel,
+};
+use tracing::warn;
+use uuid::Uuid;
+
+/// 各实体的自定义字段值（按实体ID和字段键）
+pub type CustomValues = HashMap<Uuid, BTreeMap<String, String>>;
+
+/// 待导出的表格
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvTable {
+    /// 表头
+    pub headers: Vec<String>,
+    /// 数据行
+    pub rows: Vec<Vec<String>>,
+}
+
+fn supplier_level(level: SupplierLevel) -> &'static str {
+    match level {
+        SupplierLevel::Normal => "普通",
+        SupplierLevel::Premium => "优质",
+        SupplierLevel::Strategic => "战略合作",
+        SupplierLevel::Suspended => "暂停合作",
+    }
+}
+
+/// 未停用的自定义字段（按显示顺序）
+fn active_fields(fields: &[CustomFieldDefinition]) -> Vec<&CustomFieldDefinition> {
+    let mut active: Vec<&CustomFieldDefinition> = fields.iter().filter(|f| !f.retired).collect();
+    active.sort_by_key(|f| f.sort_order);
+    active
+}
+
+/// 转义单元格：含逗号、引号或换行时加引号
+fn escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+impl CsvTable {
+    /// 客户列表
+    pub fn customers(
+        customers: &[Customer],
+        fields: &[CustomFieldDefinition],
+        values: &CustomValues,
+    ) -> Self {
+        let fields = active_fields(fields);
+        let mut table = Self {
+            headers: [
+                "客户名称",
+                "联系人",
+                "电话",
+                "邮箱",
+                "地址",
+                "客户等级",
+                "创建时间",
+            ]
+            .map(String::from)
+            .to_vec(),
+            rows: Vec::with_capacity(customers.len()),
+        };
+        table.headers.extend(fields.iter().map(|f| f.label.clone()));
+        for customer in customers {
+            let mut row = vec![
+                customer.name.clone(),
+                customer.contact_person.clone().unwrap_or_default(),
+                customer.phone.clone().unwrap_or_default(),
+                customer.email.clone().unwrap_or_default(),
+                customer.address.clone().unwrap_or_default(),
+                customer.level.display_name(Locale::ZhCn).to_string(),
+                Locale::ZhCn.date(&customer.created_at, DateStyle::Short),
+            ];
+            row.extend(custom_cells(&fields, values.get(&customer.id)));
+            table.rows.push(row);
+        }
+        table
+    }
+
+    /// 供应商列表
+    pub fn suppliers(
+        suppliers: &[Supplier],
+        fields: &[CustomFieldDefinition],
+        values: &CustomValues,
+    ) -> Self {
+        let fields = active_fields(fields);
+        let mut table = Self {
+            headers: [
+                "供应商名称",
+                "联系人",
+                "电话",
+                "邮箱",
+                "地址",
+                "供应商等级",
+                "创建时间",
+            ]
+            .map(String::from)
+            .to_vec(),
+            rows: Vec::with_capacity(suppliers.len()),
+        };
+        table.headers.extend(fields.iter().map(|f| f.label.clone()));
+        for supplier in suppliers {
+            let mut row = vec![
+                supplier.name.clone(),
+                supplier.contact_person.clone().unwrap_or_default(),
+                supplier.phone.clone().unwrap_or_default(),
+                supplier.email.clone().unwrap_or_default(),
+                supplier.address.clone().unwrap_or_default(),
+                supplier_level(supplier.level).to_string(),
+                Locale::ZhCn.date(&supplier.created_at, DateStyle::Short),
+            ];
+            row.extend(custom_cells(&fields, values.get(&supplier.id)));
+            table.rows.push(row);
+        }
+        table
+    }
+
+    /// 写出CSV（UTF-8 BOM，CRLF换行）
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all("\u{feff}".as_bytes())?;
+        for line in std::iter::once(&self.headers).chain(&self.rows) {
+            write_line(&mut writer, line)?;
+        }
+        writer.flush()
+    }
+
+    /// 写出CSV，每写一行之前检查取消令牌，返回写出的数据行数
+    ///
+    /// # Errors
+    ///
+    /// 已取消时返回 [`CoreError::Cancelled`]，写入失败时返回错误。
+    pub fn write_cancellable<W: Write>(
+        &self,
+        mut writer: W,
+        cancel: &CancellationToken,
+    ) -> CoreResult<usize> {
+        cancel.check()?;
+        writer
+            .write_all("\u{feff}".as_bytes())
+            .and_then(|()| write_line(&mut writer, &self.headers))
+            .map_err(write_error)?;
+        for row in &self.rows {
+            cancel.check()?;
+            write_line(&mut writer, row).map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)?;
+        Ok(self.rows.len())
+    }
+
+    /// 导出到文件，返回写出的数据行数
+    ///
+    /// # Errors
+    ///
+    /// 已取消时返回 [`CoreError::Cancelled`]，写入失败时返回错误；两种情况都会删除
+    /// 已写出的部分文件。
+    pub fn export_file(&self, path: &Path, cancel: &CancellationToken) -> CoreResult<usize> {
+        let file = File::create(path).map_err(write_error)?;
+        let result = self.write_cancellable(BufWriter::new(file), cancel);
+        if result.is_err() {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("无法删除未完成的导出文件 {:?}: {}", path, e);
+            }
+        }
+        result
+    }
+}
+
+/// 逐行写出的CSV文件
+///
+/// 创建时写入 BOM 和表头；未调用 [`Self::finish`] 就丢弃（如取消或写入失败）时删除文件。
+#[derive(Debug)]
+pub struct CsvFileWriter {
+    path: PathBuf,
+    writer: Option<BufWriter<File>>,
+    rows: u64,
+}
+
+impl CsvFileWriter {
+    /// 创建文件并写入表头
+    ///
+    /// # Errors
+    ///
+    /// 无法创建或写入文件时返回错误。
+    pub fn create(path: &Path, headers: &[String]) -> CoreResult<Self> {
+        let file = File::create(path).map_err(write_error)?;
+        let mut this = Self {
+            path: path.to_path_buf(),
+            writer: Some(BufWriter::new(file)),
+            rows: 0,
+        };
+        let writer = this.writer()?;
+        writer
+            .write_all("\u{feff}".as_bytes())
+            .and_then(|()| write_line(writer, headers))
+            .map_err(write_error)?;
+        Ok(this)
+    }
+
+    fn writer(&mut self) -> CoreResult<&mut BufWriter<File>> {
+        self.writer
+            .as_mut()
+            .ok_or_else(|| CoreError::Other("导出文件已关闭".to_string()))
+    }
+
+    /// 追加一行数据
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn write_row(&mut self, row: &[String]) -> CoreResult<()> {
+        write_line(self.writer()?, row).map_err(write_error)?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// 已写出的数据行数
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// 写完并关闭文件，返回数据行数
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误（文件被删除）。
+    pub fn finish(mut self) -> CoreResult<u64> {
+        self.writer()?.flush().map_err(write_error)?;
+        self.writer = None;
+        Ok(self.rows)
+    }
+}
+
+impl Drop for CsvFileWriter {
+    fn drop(&mut self) {
+        // 先关闭文件再删除（Windows 上不能删除打开的文件）
+        if self.writer.take().is_some() {
+            if let Err(e) = fs::remove_file(&self.path) {
+                warn!("无法删除未完成的导出文件 {:?}: {}", self.path, e);
+            }
+        }
+    }
+}
+
+fn write_line<W: Write>(writer: &mut W, line: &[String]) -> io::Result<()> {
+    let cells: Vec<String> = line.iter().map(|c| escape(c)).collect();
+    writer.write_all(cells.join(",").as_bytes())?;
+    writer.write_all(b"\r\n")
+}
+
+fn write_error(err: io::Error) -> CoreError {
+    CoreError::Other(format!("写入导出文件失败: {}", err))
+}
+
+fn custom_cells<'a>(
+    fields: &'a [&CustomFieldDefinition],
+    values: Option<&'a BTreeMap<String, String>>,
+) -> impl Iterator<Item = String> + 'a {
+    fields.iter().map(move |f| {
+        values
+            .and_then(|v| v.get(&f.key))
+            .cloned()
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{CustomFieldType, CustomerLevel, EntityKind};
+
+    fn field(key: &str, label: &str, sort_order: u32, retired: bool) -> CustomFieldDefinition {
+        CustomFieldDefinition {
+            id: Uuid::new_v4(),
+            entity: EntityKind::Customer,
+            key: key.to_string(),
+            label: label.to_string(),
+            field_type: CustomFieldType::Text,
+            required: false,
+            sort_order,
+            retired,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_custom_columns_follow_sort_order() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap();
+        let customer = Customer {
+            id: Uuid::new_v4(),
+            name: "华东板材, 上海".to_string(),
+            contact_person: Some("王经理".to_string()),
+            contact_birthday: None,
+            phone: None,
+            email: None,
+            address: None,
+            level: CustomerLevel::Vip,
+            credit_limit: None,
+            credit_hold: false,
+            owner_id: None,
+            created_at: at,
+            updated_at: at,
+            created_by: None,
+            updated_by: None,
+        };
+        let fields = vec![
+            field("credit_days", "月结天数", 2, false),
+            field("legacy_code", "旧编码", 0, true),
+            field("credit_code", "信用代码", 1, false),
+        ];
+        let mut values = CustomValues::new();
+        values.insert(
+            customer.id,
+            BTreeMap::from([
+                ("credit_days".to_string(), "30".to_string()),
+                ("legacy_code".to_string(), "A-01".to_string()),
+            ]),
+        );
+
+        let table = CsvTable::customers(&[customer], &fields, &values);
+        assert_eq!(&table.headers[7..], ["信用代码", "月结天数"]);
+        assert_eq!(&table.rows[0][7..], ["", "30"]);
+
+        let mut out = Vec::new();
+        table.write_to(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with('\u{feff}'));
+        assert_eq!(
+            text.lines().nth(1),
+            Some("\"华东板材, 上海\",王经理,,,,VIP,2024-03-01,,30")
+        );
+    }
+    /// 统计写入行数的输出，写满指定行数后请求取消
+    struct RowCountingSink {
+        rows: usize,
+        cancel_after: usize,
+        cancel: CancellationToken,
+    }
+
+    impl Write for RowCountingSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.ends_with(b"\r\n") {
+                self.rows += 1;
+                if self.rows == self.cancel_after {
+                    self.cancel.cancel();
+                }
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn large_table(rows: usize) -> CsvTable {
+        CsvTable {
+            headers: vec!["客户名称".to_string()],
+            rows: (0..rows).map(|i| vec![format!("客户{}", i)]).collect(),
+        }
+    }
+
+    #[test]
+    fn test_cancel_export_midway() {
+        let table = large_table(1000);
+        let cancel = CancellationToken::new();
+        let mut sink = RowCountingSink {
+            rows: 0,
+            // 表头加10行数据
+            cancel_after: 11,
+            cancel: cancel.clone(),
+        };
+        let result = table.write_cancellable(&mut sink, &cancel);
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        assert_eq!(sink.rows, 11);
+
+        let cancel = CancellationToken::new();
+        let mut sink = RowCountingSink {
+            rows: 0,
+            cancel_after: usize::MAX,
+            cancel: cancel.clone(),
+        };
+        assert_eq!(table.write_cancellable(&mut sink, &cancel).unwrap(), 1000);
+        assert_eq!(sink.rows, 1001);
+    }
+
+    #[test]
+    fn test_cancelled_export_removes_partial_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("customers.csv");
+        let table = large_table(5);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = table.export_file(&path, &cancel).unwrap_err();
+        assert!(err.is_cancelled());
+        assert!(!path.exists());
+
+        let written = table.export_file(&path, &CancellationToken::new()).unwrap();
+        assert_eq!(written, 5);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 6);
+    }
+}
@@ -0,0 +1,268 @@
+//! iCalendar日程导出
+//!
+//! 将有截止日期的任务和已安排的送货导出为 `.ics` 订阅源（RFC 5545），
+//! 供手机日历订阅。任务按业务时区导出为全天事件，送货导出为一小时的事件。
+
+use chrono::{DateTime, Duration, Utc};
+use minicrm_core::{BusinessCalendar, DeliveryStatus, Order, Task, TaskStatus};
+
+/// 单行最大字节数（不含换行）
+const MAX_LINE_OCTETS: usize = 75;
+/// 送货事件时长（分钟）
+const DELIVERY_DURATION_MINUTES: i64 = 60;
+
+/// iCalendar订阅源
+#[derive(Debug, Clone)]
+pub struct CalendarFeed {
+    name: String,
+    calendar: BusinessCalendar,
+    events: Vec<Vec<String>>,
+}
+
+fn utc_stamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// 转义TEXT类型的属性值
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+/// 按75字节折行，不拆分多字节字符
+fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        // 续行以一个空格开头，空格占用一个字节
+        if width + len > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += len;
+    }
+    out.push_str("\r\n");
+}
+
+impl CalendarFeed {
+    /// 创建订阅源
+    pub fn new(name: impl Into<String>, calendar: BusinessCalendar) -> Self {
+        Self {
+            name: name.into(),
+            calendar,
+            events: Vec::new(),
+        }
+    }
+
+    /// 添加任务（无截止日期或已完成、已取消的任务跳过）
+    pub fn add_tasks(&mut self, tasks: &[Task]) -> &mut Self {
+        for task in tasks {
+            let Some(due) = task.due_date else {
+                continue;
+            };
+            if matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled) {
+                continue;
+            }
+            let day = self.calendar.local_date(due);
+            let mut lines = vec![
+                format!("UID:task-{}@minicrm", task.id),
+                format!("DTSTAMP:{}", utc_stamp(task.updated_at)),
+                format!("DTSTART;VALUE=DATE:{}", day.naive().format("%Y%m%d")),
+                format!(
+                    "DTEND;VALUE=DATE:{}",
+                    day.add_days(1).naive().format("%Y%m%d")
+                ),
+                format!("SUMMARY:{}", escape(&task.title)),
+                "CATEGORIES:任务".to_string(),
+            ];
+            if let Some(description) = &task.description {
+                lines.push(format!("DESCRIPTION:{}", escape(description)));
+            }
+            self.events.push(lines);
+        }
+        self
+    }
+
+    /// 添加送货（未安排送货或已送达的订单跳过）
+    pub fn add_deliveries(&mut self, orders: &[Order]) -> &mut Self {
+        for order in orders {
+            let Some(start) = order.delivery_date else {
+                continue;
+            };
+            if matches!(
+                order.delivery_status,
+                DeliveryStatus::Unscheduled | DeliveryStatus::Delivered
+            ) {
+                continue;
+            }
+            let mut lines = vec![
+                format!("UID:delivery-{}@minicrm", order.id),
+                format!("DTSTAMP:{}", utc_stamp(order.updated_at)),
+                format!("DTSTART:{}", utc_stamp(start)),
+                format!(
+                    "DTEND:{}",
+                    utc_stamp(start + Duration::minutes(DELIVERY_DURATION_MINUTES))
+                ),
+                format!(
+                    "SUMMARY:{}",
+                    escape(&format!(
+                        "送货 {}（{}）",
+                        order.order_number,
+                        order.delivery_status.label()
+                    ))
+                ),
+                "CATEGORIES:送货".to_string(),
+            ];
+            if let Some(address) = &order.delivery_address {
+                lines.push(format!("LOCATION:{}", escape(&address.one_line())));
+            }
+            let crew: Vec<&str> = [order.vehicle.as_deref(), order.driver.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect();
+            if !crew.is_empty() {
+                lines.push(format!("DESCRIPTION:{}", escape(&crew.join(" / "))));
+            }
+            self.events.push(lines);
+        }
+        self
+    }
+
+    /// 事件数量
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// 是否没有任何事件
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// 生成 `.ics` 文本（CRLF换行）
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for line in [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "PRODID:-//MiniCRM//Calendar Feed//ZH",
+            "CALSCALE:GREGORIAN",
+        ] {
+            fold(line, &mut out);
+        }
+        fold(&format!("X-WR-CALNAME:{}", escape(&self.name)), &mut out);
+        fold(
+            &format!("X-WR-TIMEZONE:{}", self.calendar.timezone().name()),
+            &mut out,
+        );
+        for event in &self.events {
+            fold("BEGIN:VEVENT", &mut out);
+            for line in event {
+                fold(line, &mut out);
+            }
+            fold("END:VEVENT", &mut out);
+        }
+        fold("END:VCALENDAR", &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use minicrm_core::{Address, Currency, Money, TaskPriority};
+    use uuid::Uuid;
+
+    fn task(title: &str, due: Option<DateTime<Utc>>, status: TaskStatus) -> Task {
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+        Task {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: None,
+            status,
+            priority: TaskPriority::Medium,
+            customer_id: None,
+            supplier_id: None,
+            due_date: due,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+            assigned_to: None,
+        }
+    }
+
+    fn order(status: DeliveryStatus, at: Option<DateTime<Utc>>) -> Order {
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+        Order {
+            id: Uuid::new_v4(),
+            order_number: "DD20240701-001".to_string(),
+            customer_id: Uuid::new_v4(),
+            quote_id: None,
+            total_amount: Money::from_yuan(9_800.0),
+            currency: Currency::CNY,
+            delivery_status: status,
+            delivery_date: at,
+            delivery_address: Some(Address {
+                province: "广东省".to_string(),
+                city: "佛山市".to_string(),
+                district: Some("顺德区".to_string()),
+                street: "乐从镇罗浮宫家具博览中心东区A座三楼318号，卸货请走北门".to_string(),
+                ..Address::default()
+            }),
+            vehicle: Some("粤E·12345".to_string()),
+            driver: Some("老李".to_string()),
+            signed_by: None,
+            delivered_at: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_feed_contains_tasks_and_deliveries() {
+        // 北京时间7月2日00:30到期，按本地日期应为7月2日
+        let due = Utc.with_ymd_and_hms(2024, 7, 1, 16, 30, 0).unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 7, 3, 1, 0, 0).unwrap();
+        let mut feed = CalendarFeed::new("MiniCRM", BusinessCalendar::default());
+        feed.add_tasks(&[
+            task("回访王总; 确认板材规格", Some(due), TaskStatus::Pending),
+            task("已完成的任务", Some(due), TaskStatus::Completed),
+            task("没有截止日期", None, TaskStatus::Pending),
+        ])
+        .add_deliveries(&[
+            order(DeliveryStatus::Scheduled, Some(at)),
+            order(DeliveryStatus::Unscheduled, None),
+            order(DeliveryStatus::Delivered, Some(at)),
+        ]);
+        assert_eq!(feed.len(), 2);
+
+        let ics = feed.render();
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240702\r\n"));
+        assert!(ics.contains("SUMMARY:回访王总\\; 确认板材规格\r\n"));
+        assert!(ics.contains("DTSTART:20240703T010000Z\r\nDTEND:20240703T020000Z\r\n"));
+        assert!(ics.contains("X-WR-TIMEZONE:Asia/Shanghai"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+
+        // 折行后还原的地址与原文一致
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains(
+            "LOCATION:广东省佛山市顺德区乐从镇罗浮宫家具博览中心东区A座三楼318号，卸货请走北门"
+        ));
+    }
+}
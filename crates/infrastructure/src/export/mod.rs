@@ -0,0 +1,14 @@
+//! 单据导出模块
+//!
+//! 提供报价单PDF生成、打印校验二维码、日程订阅源以及客户、供应商列表的CSV导出等功能。
+
+pub mod csv;
+pub mod ical;
+pub mod qr;
+pub mod quote_pdf;
+
+// 重新导出主要类型
+pub use csv::{CsvFileWriter, CsvTable, CustomValues};
+pub use ical::CalendarFeed;
+pub use qr::{render_qr, HmacQuotePayloadCodec, QrImage, MAX_PAYLOAD_BYTES};
+pub use quote_pdf::QuotePdfExporter;
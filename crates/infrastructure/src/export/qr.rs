@@ -0,0 +1,253 @@
+//! 报价单校验二维码
+//!
+//! 校验码格式为 `MCQ1|报价编号|总金额(分)|有效期(YYYYMMDD)|签名`，
+//! 签名为HMAC-SHA256的前16字节（十六进制）。二维码按M级纠错生成，
+//! 载荷长度不得超过版本10的字节模式容量，保证打印后仍易于扫描。
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use hmac::{Hmac, Mac};
+use minicrm_core::{CoreError, CoreResult, QuoteFingerprint, QuotePayloadCodec};
+use qrcode::{Color, EcLevel, QrCode, Version};
+use sha2::Sha256;
+
+/// 载荷格式前缀
+pub const PAYLOAD_PREFIX: &str = "MCQ1";
+/// 载荷最大字节数（版本10、M级纠错、字节模式的容量）
+pub const MAX_PAYLOAD_BYTES: usize = 213;
+/// 二维码最大版本
+pub const MAX_QR_VERSION: i16 = 10;
+/// 签名截断长度（字节）
+const SIGNATURE_BYTES: usize = 16;
+/// 字段分隔符
+const SEPARATOR: char = '|';
+/// 有效期格式
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// 基于HMAC的报价单校验码编解码器
+#[derive(Clone)]
+pub struct HmacQuotePayloadCodec {
+    secret: Vec<u8>,
+}
+
+impl std::fmt::Debug for HmacQuotePayloadCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacQuotePayloadCodec").finish_non_exhaustive()
+    }
+}
+
+impl HmacQuotePayloadCodec {
+    /// 使用应用密钥创建编解码器
+    ///
+    /// # Errors
+    ///
+    /// 密钥为空时返回配置错误。
+    pub fn new<S: AsRef<[u8]>>(secret: S) -> CoreResult<Self> {
+        let secret = secret.as_ref();
+        if secret.is_empty() {
+            return Err(CoreError::configuration("未配置报价单校验密钥"));
+        }
+        Ok(Self {
+            secret: secret.to_vec(),
+        })
+    }
+
+    fn mac(&self, body: &str) -> CoreResult<Hmac<Sha256>> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .map_err(|e| CoreError::configuration(format!("校验密钥无效: {}", e)))?;
+        mac.update(body.as_bytes());
+        Ok(mac)
+    }
+}
+
+fn payload_body(fingerprint: &QuoteFingerprint) -> String {
+    format!(
+        "{}{sep}{}{sep}{}{sep}{}",
+        PAYLOAD_PREFIX,
+        fingerprint.quote_number,
+        fingerprint.total_cents,
+        fingerprint.valid_until.format(DATE_FORMAT),
+        sep = SEPARATOR
+    )
+}
+
+fn invalid_payload() -> CoreError {
+    CoreError::validation("校验码格式无效")
+}
+
+impl QuotePayloadCodec for HmacQuotePayloadCodec {
+    fn encode(&self, fingerprint: &QuoteFingerprint) -> CoreResult<String> {
+        if fingerprint.quote_number.is_empty() || fingerprint.quote_number.contains(SEPARATOR) {
+            return Err(CoreError::validation(format!(
+                "报价编号不能为空且不能包含 '{}'",
+                SEPARATOR
+            )));
+        }
+
+        let body = payload_body(fingerprint);
+        let digest = self.mac(&body)?.finalize().into_bytes();
+        let payload = format!(
+            "{}{}{}",
+            body,
+            SEPARATOR,
+            hex::encode(&digest[..SIGNATURE_BYTES])
+        );
+
+        if payload.len() > MAX_PAYLOAD_BYTES {
+            return Err(CoreError::validation(format!(
+                "校验码长度 {} 超过二维码容量上限 {}",
+                payload.len(),
+                MAX_PAYLOAD_BYTES
+            )));
+        }
+        Ok(payload)
+    }
+
+    fn decode(&self, payload: &str) -> CoreResult<QuoteFingerprint> {
+        let payload = payload.trim();
+        if payload.len() > MAX_PAYLOAD_BYTES {
+            return Err(invalid_payload());
+        }
+
+        let (body, signature) = payload.rsplit_once(SEPARATOR).ok_or_else(invalid_payload)?;
+        let fields: Vec<&str> = body.split(SEPARATOR).collect();
+        let [prefix, quote_number, total, valid_until] = fields[..] else {
+            return Err(invalid_payload());
+        };
+        if prefix != PAYLOAD_PREFIX || quote_number.is_empty() {
+            return Err(invalid_payload());
+        }
+
+        let signature = hex::decode(signature).map_err(|_| invalid_payload())?;
+        if signature.len() != SIGNATURE_BYTES {
+            return Err(invalid_payload());
+        }
+        self.mac(body)?
+            .verify_truncated_left(&signature)
+            .map_err(|_| CoreError::validation("校验码签名不匹配，单据可能已被篡改"))?;
+
+        Ok(QuoteFingerprint {
+            quote_number: quote_number.to_string(),
+            total_cents: total.parse().map_err(|_| invalid_payload())?,
+            valid_until: NaiveDate::parse_from_str(valid_until, DATE_FORMAT)
+                .map_err(|_| invalid_payload())?,
+        })
+    }
+}
+
+/// 二维码灰度图像
+#[derive(Debug, Clone)]
+pub struct QrImage {
+    /// 宽度（像素，与高度相同）
+    pub size: usize,
+    /// 8位灰度像素，按行排列
+    pub pixels: Vec<u8>,
+}
+
+/// 将载荷渲染为二维码灰度图像
+///
+/// 每个模块放大为 `scale` 像素，四周保留4个模块的静区。
+///
+/// # Errors
+///
+/// 载荷超出版本10容量或无法编码时返回错误。
+pub fn render_qr(payload: &str, scale: usize) -> Result<QrImage> {
+    if payload.len() > MAX_PAYLOAD_BYTES {
+        return Err(anyhow!(
+            "二维码载荷过长: {} > {}",
+            payload.len(),
+            MAX_PAYLOAD_BYTES
+        ));
+    }
+
+    let code = QrCode::with_error_correction_level(payload.as_bytes(), EcLevel::M)
+        .map_err(|e| anyhow!("二维码编码失败: {:?}", e))?;
+    match code.version() {
+        Version::Normal(v) if v <= MAX_QR_VERSION => {}
+        other => return Err(anyhow!("二维码版本超出上限: {:?}", other)),
+    }
+
+    let scale = scale.max(1);
+    let quiet = 4;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let size = (modules + quiet * 2) * scale;
+    let mut pixels = vec![255u8; size * size];
+
+    for (index, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let x0 = (index % modules + quiet) * scale;
+        let y0 = (index / modules + quiet) * scale;
+        for y in y0..y0 + scale {
+            pixels[y * size + x0..y * size + x0 + scale].fill(0);
+        }
+    }
+
+    Ok(QrImage { size, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(quote_number: &str) -> QuoteFingerprint {
+        QuoteFingerprint {
+            quote_number: quote_number.to_string(),
+            total_cents: 1_288_050,
+            valid_until: NaiveDate::from_ymd_opt(2024, 6, 30).unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> CoreResult<()> {
+        let codec = HmacQuotePayloadCodec::new("app-secret")?;
+        let original = fingerprint("Q-2024-0001");
+
+        let payload = codec.encode(&original)?;
+        assert!(payload.starts_with("MCQ1|Q-2024-0001|1288050|20240630|"));
+        assert_eq!(codec.decode(&payload)?, original);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_total_is_rejected() -> CoreResult<()> {
+        let codec = HmacQuotePayloadCodec::new("app-secret")?;
+        let payload = codec.encode(&fingerprint("Q-2024-0001"))?;
+        let tampered = payload.replace("|1288050|", "|1088050|");
+
+        let err = codec.decode(&tampered).err();
+        assert!(matches!(err, Some(CoreError::Validation(_))));
+
+        // 不同密钥签出的校验码同样无效
+        let other = HmacQuotePayloadCodec::new("other-secret")?;
+        assert!(other.decode(&payload).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_payload_length_budget() -> Result<()> {
+        let codec = HmacQuotePayloadCodec::new("app-secret")?;
+
+        // 固定部分：前缀、金额、日期、签名和分隔符
+        let fixed = codec.encode(&fingerprint("Q"))?.len() - 1;
+        let longest = "Q".repeat(MAX_PAYLOAD_BYTES - fixed);
+        let payload = codec.encode(&fingerprint(&longest))?;
+        assert_eq!(payload.len(), MAX_PAYLOAD_BYTES);
+        assert!(render_qr(&payload, 2).is_ok());
+
+        let too_long = format!("{}X", longest);
+        assert!(codec.encode(&fingerprint(&too_long)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_has_quiet_zone() -> Result<()> {
+        let image = render_qr("MCQ1|Q-1|100|20240101|00", 3)?;
+        assert_eq!(image.pixels.len(), image.size * image.size);
+        assert!(image.pixels[..image.size * 12].iter().all(|p| *p == 255));
+        assert!(image.pixels.contains(&0));
+        Ok(())
+    }
+}
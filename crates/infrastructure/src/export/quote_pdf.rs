@@ -0,0 +1,183 @@
+//! 报价单PDF导出
+//!
+//! 生成A4报价单，并在页脚嵌入防篡改校验二维码。
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use minicrm_core::{DateStyle, Locale, Quote, QuoteFingerprint, QuotePayloadCodec};
+use printpdf::{
+    BuiltinFont, ColorBits, ColorSpace, Image, ImageTransform, ImageXObject, Mm, PdfDocument, Px,
+};
+
+use super::qr::render_qr;
+
+/// 页面宽度
+const PAGE_WIDTH: f32 = 210.0;
+/// 页面高度
+const PAGE_HEIGHT: f32 = 297.0;
+/// 页边距
+const MARGIN: f32 = 20.0;
+/// 页脚二维码边长（毫米）
+const QR_SIZE_MM: f32 = 30.0;
+/// 二维码模块放大倍数
+const QR_SCALE: usize = 4;
+/// 报价单文字格式（内置字体只含西文字符）
+const PDF_LOCALE: Locale = Locale::EnUs;
+
+/// 报价单PDF导出器
+pub struct QuotePdfExporter {
+    codec: Arc<dyn QuotePayloadCodec>,
+}
+
+impl std::fmt::Debug for QuotePdfExporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuotePdfExporter").finish_non_exhaustive()
+    }
+}
+
+impl QuotePdfExporter {
+    /// 创建导出器
+    pub fn new(codec: Arc<dyn QuotePayloadCodec>) -> Self {
+        Self { codec }
+    }
+
+    /// 生成报价单PDF
+    ///
+    /// # Errors
+    ///
+    /// 校验码生成失败或PDF写入失败时返回错误。
+    pub fn render(&self, quote: &Quote, customer_name: &str) -> Result<Vec<u8>> {
+        let payload = self
+            .codec
+            .encode(&QuoteFingerprint::from_quote(quote))
+            .map_err(|e| anyhow!("生成校验码失败: {}", e))?;
+
+        let (doc, page, layer) =
+            PdfDocument::new(&quote.quote_number, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "quote");
+        let layer = doc.get_page(page).get_layer(layer);
+        let font = doc
+            .add_builtin_font(BuiltinFont::Helvetica)
+            .map_err(|e| anyhow!("加载字体失败: {}", e))?;
+        let bold = doc
+            .add_builtin_font(BuiltinFont::HelveticaBold)
+            .map_err(|e| anyhow!("加载字体失败: {}", e))?;
+
+        // 正文
+        let mut y = PAGE_HEIGHT - MARGIN - 10.0;
+        layer.use_text("QUOTATION", 20.0, Mm(MARGIN), Mm(y), &bold);
+        y -= 14.0;
+        for line in [
+            format!("Quote No.: {}", quote.quote_number),
+            format!("Customer: {}", customer_name),
+            format!(
+                "Total: {}",
+                PDF_LOCALE.money_in(quote.total_amount, quote.currency)
+            ),
+            format!(
+                "Valid until: {}",
+                PDF_LOCALE.date(&quote.valid_until, DateStyle::Short)
+            ),
+            format!(
+                "Issued: {}",
+                PDF_LOCALE.date(&quote.created_at, DateStyle::Short)
+            ),
+        ] {
+            layer.use_text(line, 11.0, Mm(MARGIN), Mm(y), &font);
+            y -= 7.0;
+        }
+
+        // 页脚校验二维码
+        let qr = render_qr(&payload, QR_SCALE)?;
+        let dpi = qr.size as f32 / (QR_SIZE_MM / 25.4);
+        let image = Image::from(ImageXObject {
+            width: Px(qr.size),
+            height: Px(qr.size),
+            color_space: ColorSpace::Greyscale,
+            bits_per_component: ColorBits::Bit8,
+            interpolate: false,
+            image_data: qr.pixels,
+            image_filter: None,
+            smask: None,
+            clipping_bbox: None,
+        });
+        image.add_to_layer(
+            layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(PAGE_WIDTH - MARGIN - QR_SIZE_MM)),
+                translate_y: Some(Mm(MARGIN)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+        layer.use_text(
+            "Scan to verify this quote",
+            8.0,
+            Mm(PAGE_WIDTH - MARGIN - QR_SIZE_MM),
+            Mm(MARGIN - 4.0),
+            &font,
+        );
+
+        doc.save_to_bytes()
+            .map_err(|e| anyhow!("写入PDF失败: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::HmacQuotePayloadCodec;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{Currency, Money, QuoteStatus};
+    use uuid::Uuid;
+
+    fn quote() -> Quote {
+        let now = Utc::now();
+        Quote {
+            id: Uuid::new_v4(),
+            quote_number: "Q-2024-0001".to_string(),
+            customer_id: Uuid::new_v4(),
+            status: QuoteStatus::Sent,
+            total_amount: Money::from_yuan(12_880.5),
+            currency: Currency::CNY,
+            items: Vec::new(),
+            valid_until: Utc
+                .with_ymd_and_hms(2024, 6, 30, 0, 0, 0)
+                .single()
+                .unwrap_or(now),
+            remarks: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_pdf_embeds_qr_image() -> Result<()> {
+        let codec = Arc::new(HmacQuotePayloadCodec::new("app-secret")?);
+        let exporter = QuotePdfExporter::new(codec.clone());
+        let quote = quote();
+
+        let bytes = exporter.render(&quote, "Demo Timber Co.")?;
+        let document = lopdf::Document::load_mem(&bytes)?;
+        let images = document
+            .objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| {
+                stream
+                    .dict
+                    .get(b"Subtype")
+                    .and_then(lopdf::Object::as_name)
+                    .is_ok_and(|name| name == b"Image")
+            })
+            .count();
+        assert_eq!(images, 1);
+
+        // 打印在单据上的校验码可以还原出报价字段
+        let payload = codec.encode(&QuoteFingerprint::from_quote(&quote))?;
+        assert_eq!(codec.decode(&payload)?, QuoteFingerprint::from_quote(&quote));
+        Ok(())
+    }
+}
@@ -0,0 +1,673 @@
+//! 邮件工单收件
+//!
+//! [`EmailIntakeJob`] 按设置的间隔轮询售后邮箱，把未读邮件转换为售后工单：发件人按
+//! 邮箱地址匹配客户，匹配不到时工单分类为“未知客户”，等待人工关联；主题作为描述的
+//! 第一行，正文接在后面，附件保存到附件存储。
+//!
+//! 处理过的邮件标记为已读，并按 Message-ID 记录在 `intake_log` 表中。邮件被重新标记为
+//! 未读或上次标记已读失败时不会重复建单；建单失败的邮件保持未读，下次轮询重试。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Clock, CoreResult, Job, JobSchedule, ServiceTicket, ServiceTicketService, ServiceTicketStatus,
+    SystemClock, TaskPriority,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::mime::{parse_email, ParsedEmail};
+use crate::attachments::AttachmentStore;
+use crate::database::db_uuid::get_uuid;
+use crate::database::{DatabaseConnection, DbUuid};
+
+/// 匹配到客户的邮件工单分类
+pub const EMAIL_TICKET_CATEGORY: &str = "邮件报修";
+/// 发件人未匹配到客户的工单分类（工单的客户ID为空UUID）
+pub const UNKNOWN_CUSTOMER_CATEGORY: &str = "未知客户";
+/// 邮件工单编号前缀
+pub const EMAIL_TICKET_PREFIX: &str = "EM";
+
+/// 收件箱中的一封原始邮件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawEmail {
+    /// 邮件在邮箱中的UID
+    pub uid: u32,
+    /// 原始邮件内容（RFC 5322）
+    pub raw: Vec<u8>,
+}
+
+/// 邮件来源（默认为 [`ImapMailbox`](super::imap::ImapMailbox)，测试中替换为固定邮件）
+#[async_trait]
+pub trait MailboxSource: Send + Sync {
+    /// 读取未读邮件（不改变已读状态）
+    async fn fetch_unseen(&self) -> Result<Vec<RawEmail>>;
+
+    /// 把邮件标记为已读
+    async fn mark_seen(&self, uids: &[u32]) -> Result<()>;
+}
+
+/// 一次轮询的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntakeSummary {
+    /// 新建的工单数
+    pub created: usize,
+    /// 其中发件人未匹配到客户的工单数
+    pub unmatched: usize,
+    /// 已处理过的邮件数（只标记已读）
+    pub duplicates: usize,
+    /// 处理失败的邮件数（保持未读）
+    pub failed: usize,
+}
+
+/// 收件记录中保存的附件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntakeAttachment {
+    /// 附件存储中的内容哈希
+    pub hash: String,
+    /// 文件名
+    pub file_name: String,
+    /// 文件大小（字节）
+    pub size: u64,
+}
+
+/// 收件记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntakeRecord {
+    /// 邮件的 Message-ID（没有时为内容哈希）
+    pub message_id: String,
+    /// 创建的工单ID
+    pub ticket_id: Uuid,
+    /// 工单编号
+    pub ticket_number: String,
+    /// 发件人地址
+    pub sender: Option<String>,
+    /// 匹配到的客户
+    pub customer_id: Option<Uuid>,
+    /// 邮件主题
+    pub subject: String,
+    /// 保存的附件
+    pub attachments: Vec<IntakeAttachment>,
+    /// 收件时间
+    pub received_at: DateTime<Utc>,
+}
+
+/// 邮件工单收件
+pub struct EmailIntake {
+    connection: DatabaseConnection,
+    mailbox: Arc<dyn MailboxSource>,
+    tickets: Arc<dyn ServiceTicketService + Send + Sync>,
+    attachments: AttachmentStore,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for EmailIntake {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailIntake")
+            .field("attachments", &self.attachments.root())
+            .finish_non_exhaustive()
+    }
+}
+
+impl EmailIntake {
+    /// 创建收件服务
+    pub fn new(
+        connection: DatabaseConnection,
+        mailbox: Arc<dyn MailboxSource>,
+        tickets: Arc<dyn ServiceTicketService + Send + Sync>,
+        attachments: AttachmentStore,
+    ) -> Self {
+        Self {
+            connection,
+            mailbox,
+            tickets,
+            attachments,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中固定收件时间和工单编号）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 轮询一次邮箱
+    ///
+    /// 新建工单和已处理过的邮件标记为已读，处理失败的邮件保持未读。
+    ///
+    /// # Errors
+    ///
+    /// 读取邮箱或标记已读失败时返回错误。
+    pub async fn poll(&self) -> Result<IntakeSummary> {
+        let emails = self.mailbox.fetch_unseen().await?;
+        let mut summary = IntakeSummary::default();
+        let mut seen = Vec::new();
+        for email in emails {
+            match self.intake(&email.raw).await {
+                Ok(Some(record)) => {
+                    summary.created += 1;
+                    if record.customer_id.is_none() {
+                        summary.unmatched += 1;
+                    }
+                    seen.push(email.uid);
+                }
+                Ok(None) => {
+                    summary.duplicates += 1;
+                    seen.push(email.uid);
+                }
+                Err(e) => {
+                    warn!("邮件 {} 转换为工单失败: {:#}", email.uid, e);
+                    summary.failed += 1;
+                }
+            }
+        }
+        self.mailbox.mark_seen(&seen).await?;
+        Ok(summary)
+    }
+
+    /// 把一封邮件转换为工单，已处理过的邮件返回 `None`
+    ///
+    /// 先按 Message-ID 登记收件记录再建单，建单失败时删除记录，下次可以重试。
+    /// 不允许保存的附件（如可执行文件）跳过并记录日志，不影响建单。
+    ///
+    /// # Errors
+    ///
+    /// 数据库操作或创建工单失败时返回错误。
+    pub async fn intake(&self, raw: &[u8]) -> Result<Option<IntakeRecord>> {
+        let email = parse_email(raw);
+        let message_id = email
+            .message_id
+            .clone()
+            .unwrap_or_else(|| format!("<sha256:{}>", hex::encode(Sha256::digest(raw))));
+        if self.is_logged(&message_id)? {
+            return Ok(None);
+        }
+
+        let now = self.clock.now();
+        let customer_id = match &email.from_address {
+            Some(address) => self.match_customer(address)?,
+            None => None,
+        };
+        let attachments = self.store_attachments(&email);
+        let ticket_number = self.next_ticket_number(now)?;
+        let record = IntakeRecord {
+            message_id,
+            ticket_id: Uuid::new_v4(),
+            ticket_number,
+            sender: email.from_address.clone(),
+            customer_id,
+            subject: email.subject.clone(),
+            attachments,
+            received_at: now,
+        };
+        if !self.log(&record)? {
+            // 并发的轮询已经登记了同一封邮件
+            return Ok(None);
+        }
+
+        let ticket = ServiceTicket {
+            id: record.ticket_id,
+            ticket_number: record.ticket_number.clone(),
+            customer_id: customer_id.unwrap_or(Uuid::nil()),
+            problem_category: if customer_id.is_some() {
+                EMAIL_TICKET_CATEGORY
+            } else {
+                UNKNOWN_CUSTOMER_CATEGORY
+            }
+            .to_string(),
+            description: ticket_description(&email, &record),
+            solution_method: None,
+            status: ServiceTicketStatus::New,
+            priority: TaskPriority::Medium,
+            created_at: now,
+            updated_at: now,
+        };
+        if let Err(e) = self.tickets.create_ticket(ticket).await {
+            self.connection.execute(
+                "DELETE FROM intake_log WHERE message_id = ?1",
+                [&record.message_id],
+            )?;
+            return Err(anyhow::Error::new(e).context("创建邮件工单失败"));
+        }
+        info!(
+            "邮件 {} 已转换为工单 {}",
+            record.message_id, record.ticket_number
+        );
+        Ok(Some(record))
+    }
+
+    /// 按 Message-ID 查询收件记录
+    pub fn record(&self, message_id: &str) -> Result<Option<IntakeRecord>> {
+        let records = self.connection.query_map(
+            "SELECT message_id, ticket_id, ticket_number, sender, customer_id, subject,
+                    attachments, received_at
+             FROM intake_log WHERE message_id = ?1",
+            [message_id],
+            |row| {
+                let customer_id: Option<DbUuid> = row.get(4)?;
+                let attachments: String = row.get(6)?;
+                let received_at: String = row.get(7)?;
+                Ok(IntakeRecord {
+                    message_id: row.get(0)?,
+                    ticket_id: get_uuid(row, 1)?,
+                    ticket_number: row.get(2)?,
+                    sender: row.get(3)?,
+                    customer_id: customer_id.map(Uuid::from),
+                    subject: row.get(5)?,
+                    attachments: serde_json::from_str(&attachments).unwrap_or_default(),
+                    received_at: DateTime::parse_from_rfc3339(&received_at)
+                        .map(|t| t.with_timezone(&Utc))
+                        .unwrap_or_default(),
+                })
+            },
+        )?;
+        Ok(records.into_iter().next())
+    }
+
+    fn is_logged(&self, message_id: &str) -> Result<bool> {
+        self.connection.query_row(
+            "SELECT EXISTS(SELECT 1 FROM intake_log WHERE message_id = ?1)",
+            [message_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// 按邮箱地址匹配客户（忽略大小写，多个客户使用同一地址时取最近更新的）
+    fn match_customer(&self, address: &str) -> Result<Option<Uuid>> {
+        let ids = self.connection.query_map(
+            "SELECT id FROM customers
+             WHERE deleted_at IS NULL AND lower(trim(email)) = ?1
+             ORDER BY updated_at DESC LIMIT 1",
+            [address.trim().to_lowercase()],
+            |row| get_uuid(row, 0),
+        )?;
+        Ok(ids.into_iter().next())
+    }
+
+    fn store_attachments(&self, email: &ParsedEmail) -> Vec<IntakeAttachment> {
+        email
+            .attachments
+            .iter()
+            .filter_map(|attachment| {
+                match self.attachments.ingest(
+                    &attachment.file_name,
+                    &attachment.mime,
+                    &attachment.content,
+                    None,
+                ) {
+                    Ok(stored) => Some(IntakeAttachment {
+                        hash: stored.hash,
+                        file_name: stored.file_name,
+                        size: stored.size,
+                    }),
+                    Err(e) => {
+                        warn!("跳过邮件附件“{}”: {:#}", attachment.file_name, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// 当天的下一个工单编号，如 `EM20240603001`
+    fn next_ticket_number(&self, now: DateTime<Utc>) -> Result<String> {
+        let prefix = format!("{}{}", EMAIL_TICKET_PREFIX, now.format("%Y%m%d"));
+        let count: i64 = self.connection.query_row(
+            "SELECT COUNT(*) FROM intake_log WHERE ticket_number LIKE ?1 || '%'",
+            [&prefix],
+            |row| row.get(0),
+        )?;
+        Ok(format!("{}{:03}", prefix, count + 1))
+    }
+
+    /// 登记收件记录，同一 Message-ID 已登记时返回 `false`
+    fn log(&self, record: &IntakeRecord) -> Result<bool> {
+        let attachments =
+            serde_json::to_string(&record.attachments).context("无法序列化邮件附件列表")?;
+        let inserted = self.connection.execute(
+            "INSERT OR IGNORE INTO intake_log
+                (message_id, ticket_id, ticket_number, sender, customer_id, subject,
+                 attachments, received_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                record.message_id,
+                DbUuid(record.ticket_id),
+                record.ticket_number,
+                record.sender,
+                record.customer_id.map(DbUuid),
+                record.subject,
+                attachments,
+                record.received_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+}
+
+/// 工单描述：主题作为第一行，之后是正文；未知客户附上发件人，便于人工关联
+fn ticket_description(email: &ParsedEmail, record: &IntakeRecord) -> String {
+    let mut description = if email.subject.is_empty() {
+        "（无主题）".to_string()
+    } else {
+        email.subject.clone()
+    };
+    if !email.body.is_empty() {
+        description.push_str("\n\n");
+        description.push_str(&email.body);
+    }
+    if record.customer_id.is_none() {
+        let sender = match (&email.from_name, &email.from_address) {
+            (Some(name), Some(address)) => format!("{} <{}>", name, address),
+            (None, Some(address)) => address.clone(),
+            (Some(name), None) => name.clone(),
+            (None, None) => "未知".to_string(),
+        };
+        description.push_str(&format!("\n\n发件人：{}", sender));
+    }
+    if !record.attachments.is_empty() {
+        let names: Vec<&str> = record
+            .attachments
+            .iter()
+            .map(|a| a.file_name.as_str())
+            .collect();
+        description.push_str(&format!("\n\n附件：{}", names.join("、")));
+    }
+    description
+}
+
+/// 定时轮询邮箱的任务
+pub struct EmailIntakeJob {
+    intake: Arc<EmailIntake>,
+    interval: chrono::Duration,
+}
+
+impl std::fmt::Debug for EmailIntakeJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailIntakeJob")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EmailIntakeJob {
+    /// 创建轮询任务，默认每5分钟运行一次
+    pub fn new(intake: Arc<EmailIntake>) -> Self {
+        Self {
+            intake,
+            interval: chrono::Duration::minutes(5),
+        }
+    }
+
+    /// 设置轮询间隔
+    pub fn with_interval(mut self, interval: chrono::Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+#[async_trait]
+impl Job for EmailIntakeJob {
+    fn name(&self) -> &str {
+        "email_intake"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(self.interval)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let summary = self.intake.poll().await?;
+        if summary != IntakeSummary::default() {
+            info!("邮件收件: {:?}", summary);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use minicrm_core::{CoreError, ManualClock, PagedResult, QueryFilter, ServiceTicketStatistics};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// 固定邮件的收件箱，记录标记为已读的UID
+    #[derive(Default)]
+    struct FixtureMailbox {
+        unseen: Mutex<Vec<RawEmail>>,
+        seen: Mutex<Vec<u32>>,
+    }
+
+    impl FixtureMailbox {
+        fn deliver(&self, uid: u32, raw: &str) {
+            self.unseen.lock().unwrap().push(RawEmail {
+                uid,
+                raw: raw.as_bytes().to_vec(),
+            });
+        }
+    }
+
+    #[async_trait]
+    impl MailboxSource for FixtureMailbox {
+        async fn fetch_unseen(&self) -> Result<Vec<RawEmail>> {
+            Ok(self.unseen.lock().unwrap().clone())
+        }
+
+        async fn mark_seen(&self, uids: &[u32]) -> Result<()> {
+            self.unseen
+                .lock()
+                .unwrap()
+                .retain(|email| !uids.contains(&email.uid));
+            self.seen.lock().unwrap().extend_from_slice(uids);
+            Ok(())
+        }
+    }
+
+    /// 保存在内存中的工单
+    #[derive(Default)]
+    struct MemoryTickets(Mutex<Vec<ServiceTicket>>);
+
+    #[async_trait]
+    impl ServiceTicketService for MemoryTickets {
+        async fn create_ticket(&self, ticket: ServiceTicket) -> CoreResult<ServiceTicket> {
+            self.0.lock().unwrap().push(ticket.clone());
+            Ok(ticket)
+        }
+
+        async fn update_ticket(&self, ticket: ServiceTicket) -> CoreResult<ServiceTicket> {
+            Ok(ticket)
+        }
+
+        async fn get_ticket_by_id(&self, id: Uuid) -> CoreResult<Option<ServiceTicket>> {
+            Ok(self.0.lock().unwrap().iter().find(|t| t.id == id).cloned())
+        }
+
+        async fn delete_ticket(&self, _id: Uuid) -> CoreResult<bool> {
+            Ok(false)
+        }
+
+        async fn search_tickets(
+            &self,
+            _filter: &QueryFilter,
+        ) -> CoreResult<PagedResult<ServiceTicket>> {
+            Err(CoreError::business("not used"))
+        }
+
+        async fn update_ticket_status(
+            &self,
+            _id: Uuid,
+            _status: ServiceTicketStatus,
+        ) -> CoreResult<ServiceTicket> {
+            Err(CoreError::business("not used"))
+        }
+
+        async fn get_ticket_statistics(&self) -> CoreResult<ServiceTicketStatistics> {
+            Err(CoreError::business("not used"))
+        }
+    }
+
+    struct Fixture {
+        _dir: TempDir,
+        connection: DatabaseConnection,
+        mailbox: Arc<FixtureMailbox>,
+        tickets: Arc<MemoryTickets>,
+        attachments: AttachmentStore,
+        intake: EmailIntake,
+    }
+
+    fn setup() -> Fixture {
+        let (dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let mailbox = Arc::new(FixtureMailbox::default());
+        let tickets = Arc::new(MemoryTickets::default());
+        let attachments = AttachmentStore::new(dir.path().join("attachments"));
+        let intake = EmailIntake::new(
+            connection.clone(),
+            mailbox.clone(),
+            tickets.clone(),
+            attachments.clone(),
+        )
+        .with_clock(Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 3, 9, 30, 0).unwrap(),
+        )));
+        Fixture {
+            _dir: dir,
+            connection,
+            mailbox,
+            tickets,
+            attachments,
+            intake,
+        }
+    }
+
+    fn add_customer(connection: &DatabaseConnection, name: &str, email: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, email, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?4)",
+                rusqlite::params![DbUuid(id), name, email, Utc::now().to_rfc3339()],
+            )
+            .unwrap();
+        id
+    }
+
+    /// GB2312编码的主题和正文，带一个PDF附件
+    const KNOWN_SENDER: &str = "From: =?GB2312?B?1cXI/Q==?= <ZhangSan@Example.com>\r\n\
+        Subject: =?gb2312?B?t+Kx37v60uzP7A==?=\r\n\
+        Message-ID: <abc123@example.com>\r\n\
+        Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+        \r\n\
+        --b1\r\n\
+        Content-Type: text/plain; charset=gb2312\r\n\
+        Content-Transfer-Encoding: quoted-printable\r\n\
+        \r\n\
+        =D0=CD=BA=C5 EB-200\r\n\
+        --b1\r\n\
+        Content-Type: application/pdf; name=\"=?utf-8?B?5oql5L+u5Y2VLnBkZg==?=\"\r\n\
+        Content-Disposition: attachment\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        JVBERi0xLjQK\r\n\
+        --b1--\r\n";
+
+    /// 没有 Message-ID 的陌生发件人
+    const UNKNOWN_SENDER: &str = "From: \"Li Si\" <lisi@unknown.cn>\r\n\
+        Subject: Quote request\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        \r\n\
+        Please call me back.\r\n";
+
+    #[tokio::test]
+    async fn test_fixture_emails_become_tickets() {
+        let fixture = setup();
+        let customer = add_customer(&fixture.connection, "张三", " zhangsan@example.com");
+        fixture.mailbox.deliver(7, KNOWN_SENDER);
+        fixture.mailbox.deliver(8, UNKNOWN_SENDER);
+
+        let summary = fixture.intake.poll().await.unwrap();
+        assert_eq!(
+            summary,
+            IntakeSummary {
+                created: 2,
+                unmatched: 1,
+                ..IntakeSummary::default()
+            }
+        );
+        assert_eq!(*fixture.mailbox.seen.lock().unwrap(), vec![7, 8]);
+
+        let tickets = fixture.tickets.0.lock().unwrap().clone();
+        assert_eq!(tickets.len(), 2);
+        let known = &tickets[0];
+        assert_eq!(known.ticket_number, "EM20240603001");
+        assert_eq!(known.customer_id, customer);
+        assert_eq!(known.problem_category, EMAIL_TICKET_CATEGORY);
+        assert_eq!(known.status, ServiceTicketStatus::New);
+        assert_eq!(
+            known.description,
+            "封边机异响\n\n型号 EB-200\n\n附件：报修单.pdf"
+        );
+
+        let unknown = &tickets[1];
+        assert_eq!(unknown.ticket_number, "EM20240603002");
+        assert_eq!(unknown.customer_id, Uuid::nil());
+        assert_eq!(unknown.problem_category, UNKNOWN_CUSTOMER_CATEGORY);
+        assert_eq!(
+            unknown.description,
+            "Quote request\n\nPlease call me back.\n\n发件人：Li Si <lisi@unknown.cn>"
+        );
+
+        // 附件保存在附件存储中，收件记录保留哈希
+        let record = fixture
+            .intake
+            .record("<abc123@example.com>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.ticket_id, known.id);
+        assert_eq!(record.customer_id, Some(customer));
+        assert_eq!(record.sender.as_deref(), Some("zhangsan@example.com"));
+        assert_eq!(record.attachments.len(), 1);
+        assert_eq!(record.attachments[0].file_name, "报修单.pdf");
+        assert_eq!(
+            fixture
+                .attachments
+                .read(&record.attachments[0].hash)
+                .unwrap(),
+            b"%PDF-1.4\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_id_dedupes_tickets() {
+        let fixture = setup();
+        fixture.mailbox.deliver(7, KNOWN_SENDER);
+        fixture.mailbox.deliver(8, UNKNOWN_SENDER);
+        fixture.intake.poll().await.unwrap();
+
+        // 邮件被重新标记为未读（或标记已读失败）后再次出现
+        fixture.mailbox.deliver(7, KNOWN_SENDER);
+        fixture.mailbox.deliver(8, UNKNOWN_SENDER);
+        let summary = fixture.intake.poll().await.unwrap();
+        assert_eq!(summary.created, 0);
+        assert_eq!(summary.duplicates, 2);
+        assert!(fixture.mailbox.unseen.lock().unwrap().is_empty());
+        assert_eq!(fixture.tickets.0.lock().unwrap().len(), 2);
+
+        // 没有 Message-ID 时按内容去重
+        assert!(fixture
+            .intake
+            .intake(UNKNOWN_SENDER.as_bytes())
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
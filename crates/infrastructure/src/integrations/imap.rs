@@ -0,0 +1,283 @@
+//! IMAP收件
+//!
+//! 只实现工单收件需要的命令：登录、选择邮箱、搜索未读邮件、读取原始邮件（`BODY.PEEK[]`，
+//! 读取时不改变已读状态）和标记已读。连接使用隐式TLS（通常为993端口），读取和标记
+//! 已读各建立一个连接，完成后登出。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+use tracing::debug;
+
+use super::email_intake::{MailboxSource, RawEmail};
+
+/// 连接和每条命令的超时时间
+pub const IMAP_TIMEOUT: Duration = Duration::from_secs(30);
+/// 每次轮询最多读取的邮件数（其余留到下次）
+pub const MAX_MESSAGES_PER_POLL: usize = 50;
+
+/// IMAP收件箱设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImapSettings {
+    /// 服务器地址
+    pub host: String,
+    /// 端口（隐式TLS通常为993）
+    pub port: u16,
+    /// 登录用户名
+    pub username: String,
+    /// 登录密码或授权码
+    pub password: String,
+    /// 邮箱文件夹
+    pub mailbox: String,
+}
+
+/// IMAP收件箱
+#[derive(Clone)]
+pub struct ImapMailbox {
+    settings: ImapSettings,
+    connector: TlsConnector,
+}
+
+impl std::fmt::Debug for ImapMailbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImapMailbox")
+            .field("host", &self.settings.host)
+            .field("username", &self.settings.username)
+            .field("mailbox", &self.settings.mailbox)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ImapMailbox {
+    /// 按设置创建收件箱（不会立即连接服务器）
+    pub fn new(settings: ImapSettings) -> Self {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Self {
+            settings,
+            connector: TlsConnector::from(Arc::new(config)),
+        }
+    }
+
+    /// 连接、登录并选择邮箱
+    async fn open(&self) -> Result<ImapSession<tokio_rustls::client::TlsStream<TcpStream>>> {
+        let host = self.settings.host.trim();
+        let server_name = ServerName::try_from(host.to_string())
+            .with_context(|| format!("无效的IMAP服务器: {}", host))?;
+        let tcp =
+            tokio::time::timeout(IMAP_TIMEOUT, TcpStream::connect((host, self.settings.port)))
+                .await
+                .map_err(|_| anyhow!("连接IMAP服务器超时: {}", host))?
+                .with_context(|| format!("无法连接IMAP服务器: {}", host))?;
+        let stream = self
+            .connector
+            .connect(server_name, tcp)
+            .await
+            .with_context(|| format!("IMAP服务器TLS握手失败: {}", host))?;
+
+        let mut session = ImapSession::new(stream);
+        session.greeting().await?;
+        session
+            .command(&format!(
+                "LOGIN {} {}",
+                quote(&self.settings.username),
+                quote(&self.settings.password)
+            ))
+            .await
+            .context("IMAP登录失败，请检查用户名和授权码")?;
+        session
+            .command(&format!("SELECT {}", quote(&self.settings.mailbox)))
+            .await
+            .with_context(|| format!("无法打开邮箱文件夹: {}", self.settings.mailbox))?;
+        Ok(session)
+    }
+}
+
+#[async_trait]
+impl MailboxSource for ImapMailbox {
+    async fn fetch_unseen(&self) -> Result<Vec<RawEmail>> {
+        let mut session = self.open().await?;
+        let uids: Vec<u32> = session
+            .command("UID SEARCH UNSEEN")
+            .await?
+            .iter()
+            .filter_map(|response| response.text.strip_prefix("* SEARCH"))
+            .flat_map(|ids| ids.split_whitespace().filter_map(|id| id.parse().ok()))
+            .take(MAX_MESSAGES_PER_POLL)
+            .collect();
+        if uids.is_empty() {
+            session.logout().await;
+            return Ok(Vec::new());
+        }
+
+        let responses = session
+            .command(&format!("UID FETCH {} (UID BODY.PEEK[])", uid_set(&uids)))
+            .await?;
+        session.logout().await;
+        let emails = responses
+            .into_iter()
+            .filter(|response| response.text.contains(" FETCH "))
+            .filter_map(|mut response| {
+                let uid = fetch_uid(&response.text)?;
+                let raw = response.literals.pop()?;
+                Some(RawEmail { uid, raw })
+            })
+            .collect();
+        Ok(emails)
+    }
+
+    async fn mark_seen(&self, uids: &[u32]) -> Result<()> {
+        if uids.is_empty() {
+            return Ok(());
+        }
+        let mut session = self.open().await?;
+        session
+            .command(&format!(
+                "UID STORE {} +FLAGS.SILENT (\\Seen)",
+                uid_set(uids)
+            ))
+            .await
+            .context("无法标记邮件为已读")?;
+        session.logout().await;
+        Ok(())
+    }
+}
+
+/// 一条未标记的响应（含其中的字面量）
+#[derive(Debug, Default)]
+struct ImapResponse {
+    text: String,
+    literals: Vec<Vec<u8>>,
+}
+
+/// 已连接的IMAP会话
+struct ImapSession<S> {
+    stream: BufReader<S>,
+    next_tag: u32,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ImapSession<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            next_tag: 1,
+        }
+    }
+
+    async fn greeting(&mut self) -> Result<()> {
+        let line = self.read_line().await?;
+        if !line.starts_with("* OK") && !line.starts_with("* PREAUTH") {
+            bail!("IMAP服务器拒绝连接: {}", line.trim());
+        }
+        Ok(())
+    }
+
+    /// 发送命令并读取到对应的完成响应，返回期间的未标记响应
+    async fn command(&mut self, command: &str) -> Result<Vec<ImapResponse>> {
+        let tag = format!("A{:03}", self.next_tag);
+        self.next_tag += 1;
+        let verb = command.split_whitespace().next().unwrap_or_default();
+        debug!("IMAP命令: {} {}", tag, verb);
+        let line = format!("{} {}\r\n", tag, command);
+        tokio::time::timeout(IMAP_TIMEOUT, async {
+            self.stream.get_mut().write_all(line.as_bytes()).await?;
+            self.stream.get_mut().flush().await
+        })
+        .await
+        .map_err(|_| anyhow!("IMAP命令超时: {}", verb))??;
+
+        let mut responses = Vec::new();
+        loop {
+            let response = tokio::time::timeout(IMAP_TIMEOUT, self.read_response())
+                .await
+                .map_err(|_| anyhow!("IMAP命令超时: {}", verb))??;
+            let Some(status) = response.text.strip_prefix(&format!("{} ", tag)) else {
+                responses.push(response);
+                continue;
+            };
+            if status.starts_with("OK") {
+                return Ok(responses);
+            }
+            bail!("IMAP命令 {} 失败: {}", verb, status.trim());
+        }
+    }
+
+    /// 读取一条完整响应，`{n}` 结尾的行后面紧跟n字节的字面量
+    async fn read_response(&mut self) -> Result<ImapResponse> {
+        let mut response = ImapResponse::default();
+        loop {
+            let line = self.read_line().await?;
+            let line = line.trim_end_matches(['\r', '\n']);
+            response.text.push_str(line);
+            let Some(size) = literal_size(line) else {
+                return Ok(response);
+            };
+            let mut literal = vec![0; size];
+            self.stream
+                .read_exact(&mut literal)
+                .await
+                .context("读取IMAP响应失败")?;
+            response.literals.push(literal);
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = Vec::new();
+        let read = self
+            .stream
+            .read_until(b'\n', &mut line)
+            .await
+            .context("读取IMAP响应失败")?;
+        if read == 0 {
+            bail!("IMAP服务器关闭了连接");
+        }
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    }
+
+    /// 登出（失败不影响本次结果）
+    async fn logout(&mut self) {
+        if let Err(e) = self.command("LOGOUT").await {
+            debug!("IMAP登出失败: {}", e);
+        }
+    }
+}
+
+/// 行尾字面量的长度，如 `* 1 FETCH (UID 7 BODY[] {2048}`
+fn literal_size(line: &str) -> Option<usize> {
+    let open = line.strip_suffix('}')?.rfind('{')?;
+    line[open + 1..line.len() - 1].parse().ok()
+}
+
+/// FETCH响应中的UID
+fn fetch_uid(text: &str) -> Option<u32> {
+    let start = text.find("UID ")? + 4;
+    text[start..]
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn uid_set(uids: &[u32]) -> String {
+    uids.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// IMAP带引号字符串
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,114 @@
+//! SMTP邮件发送
+//!
+//! 通过 STARTTLS 连接配置的邮件服务器，正文同时包含HTML和纯文本两部分。
+//!
+//! 收件人无效、服务器明确拒绝（5xx）不是外部服务错误，重试不会成功；
+//! 只有连接失败、超时和临时拒绝（4xx）返回 [`CoreError::ExternalService`]。
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use minicrm_core::{CoreError, CoreResult, MailService};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// SMTP服务器设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmtpSettings {
+    /// 服务器地址
+    pub host: String,
+    /// 端口（STARTTLS 通常为587）
+    pub port: u16,
+    /// 登录用户名
+    pub username: String,
+    /// 登录密码或授权码
+    pub password: String,
+    /// 发件人（如 `MiniCRM <crm@example.com>`）
+    pub from: String,
+}
+
+/// SMTP邮件发送器
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl std::fmt::Debug for SmtpMailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmtpMailer")
+            .field("from", &self.from.to_string())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SmtpMailer {
+    /// 按设置创建发送器（不会立即连接服务器）
+    ///
+    /// # Errors
+    ///
+    /// 服务器地址或发件人格式无效时返回错误。
+    pub fn new(settings: &SmtpSettings) -> Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)
+            .with_context(|| format!("无效的SMTP服务器: {}", settings.host))?
+            .port(settings.port)
+            .credentials(Credentials::new(
+                settings.username.clone(),
+                settings.password.clone(),
+            ))
+            .build();
+        let from = settings
+            .from
+            .parse()
+            .with_context(|| format!("无效的发件人: {}", settings.from))?;
+        Ok(Self { transport, from })
+    }
+
+    async fn send(
+        &self,
+        recipients: &[String],
+        subject: &str,
+        html: &str,
+        text: &str,
+    ) -> CoreResult<()> {
+        let mut builder = Message::builder().from(self.from.clone()).subject(subject);
+        for recipient in recipients {
+            let mailbox: Mailbox = recipient
+                .parse()
+                .map_err(|_| CoreError::validation(format!("无效的收件人: {}", recipient)))?;
+            builder = builder.to(mailbox);
+        }
+        let message = builder
+            .multipart(MultiPart::alternative_plain_html(
+                text.to_string(),
+                html.to_string(),
+            ))
+            .map_err(|e| CoreError::validation(format!("构建邮件失败: {}", e)))?;
+        self.transport.send(message).await.map_err(|e| {
+            if e.is_permanent() {
+                CoreError::business(format!("邮件服务器拒绝发送: {}", e))
+            } else {
+                CoreError::ExternalService(format!("发送邮件失败: {}", e))
+            }
+        })?;
+        info!("邮件已发送: {}（{}位收件人）", subject, recipients.len());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MailService for SmtpMailer {
+    async fn send_html(
+        &self,
+        recipients: &[String],
+        subject: &str,
+        html: &str,
+        text: &str,
+    ) -> CoreResult<()> {
+        if recipients.is_empty() {
+            return Ok(());
+        }
+        self.send(recipients, subject, html, text).await
+    }
+}
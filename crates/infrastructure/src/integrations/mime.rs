@@ -0,0 +1,660 @@
+//! 邮件解析
+//!
+//! 把收件箱中的原始邮件（RFC 5322）解析为发件人、主题、正文和附件，供工单收件使用。
+//! 支持 multipart 嵌套、base64 与 quoted-printable 传输编码，以及 RFC 2047 编码的
+//! 主题、发件人和附件名。字符集按标签解码，GB2312、GBK 按其超集 GB18030 解码；
+//! 未声明字符集且不是有效 UTF-8 的内容也按 GB18030 解码（国内邮件客户端常见）。
+//!
+//! 解析尽力而为：格式不规范的部分按纯文本处理，不会因为单个部分损坏而丢弃整封邮件。
+
+use std::collections::HashMap;
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use encoding_rs::{Encoding, GB18030};
+
+/// 不要求填充的base64解码器（部分客户端省略了末尾的 `=`）
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// 解析后的邮件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedEmail {
+    /// Message-ID（含尖括号），没有时为空
+    pub message_id: Option<String>,
+    /// 发件人地址（小写）
+    pub from_address: Option<String>,
+    /// 发件人显示名称
+    pub from_name: Option<String>,
+    /// 主题
+    pub subject: String,
+    /// 正文（优先纯文本，只有HTML时去掉标签）
+    pub body: String,
+    /// 附件
+    pub attachments: Vec<EmailAttachment>,
+}
+
+/// 邮件附件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailAttachment {
+    /// 文件名
+    pub file_name: String,
+    /// 声明的MIME类型
+    pub mime: String,
+    /// 解码后的内容
+    pub content: Vec<u8>,
+}
+
+/// 解析原始邮件
+pub fn parse_email(raw: &[u8]) -> ParsedEmail {
+    let (header_bytes, body) = split_header_body(raw);
+    let headers = parse_headers(header_bytes);
+
+    let (from_name, from_address) = header(&headers, "from")
+        .map(|value| parse_mailbox(&decode_words(value)))
+        .unwrap_or_default();
+    let mut parts = Parts::default();
+    walk_part(&headers, body, &mut parts);
+
+    let body = match (parts.text, parts.html) {
+        (Some(text), _) if !text.trim().is_empty() => text,
+        (_, Some(html)) => html_to_text(&html),
+        (text, None) => text.unwrap_or_default(),
+    };
+
+    ParsedEmail {
+        message_id: header(&headers, "message-id")
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string),
+        from_address,
+        from_name,
+        subject: header(&headers, "subject")
+            .map(|value| decode_words(value).trim().to_string())
+            .unwrap_or_default(),
+        body: normalize_newlines(&body).trim().to_string(),
+        attachments: parts.attachments,
+    }
+}
+
+/// 遍历过程中收集的正文和附件
+#[derive(Default)]
+struct Parts {
+    text: Option<String>,
+    html: Option<String>,
+    attachments: Vec<EmailAttachment>,
+}
+
+/// 处理一个MIME部分，multipart 递归处理其中的子部分
+fn walk_part(headers: &[(String, String)], body: &[u8], parts: &mut Parts) {
+    let content_type = header(headers, "content-type")
+        .map(HeaderValue::parse)
+        .unwrap_or_else(|| HeaderValue::plain("text/plain"));
+    let mime = content_type.value.to_ascii_lowercase();
+
+    if mime.starts_with("multipart/") {
+        if let Some(boundary) = content_type.param("boundary") {
+            for part in split_multipart(body, boundary) {
+                let (header_bytes, part_body) = split_header_body(part);
+                walk_part(&parse_headers(header_bytes), part_body, parts);
+            }
+            return;
+        }
+    }
+
+    let disposition = header(headers, "content-disposition").map(HeaderValue::parse);
+    let file_name = disposition
+        .as_ref()
+        .and_then(|d| d.param("filename"))
+        .or_else(|| content_type.param("name"))
+        .map(|name| decode_words(name).trim().to_string())
+        .filter(|name| !name.is_empty());
+    let is_attachment = disposition
+        .as_ref()
+        .is_some_and(|d| d.value.eq_ignore_ascii_case("attachment"));
+    let content = decode_transfer(header(headers, "content-transfer-encoding"), body);
+
+    let is_text = mime == "text/plain" || mime == "text/html";
+    if is_text && !is_attachment && file_name.is_none() {
+        let text = decode_charset(&content, content_type.param("charset"));
+        let slot = if mime == "text/plain" {
+            &mut parts.text
+        } else {
+            &mut parts.html
+        };
+        // 同类型的多个正文部分（如分段转发）依次拼接
+        match slot {
+            Some(existing) => {
+                existing.push_str("\n\n");
+                existing.push_str(&text);
+            }
+            None => *slot = Some(text),
+        }
+        return;
+    }
+
+    let file_name = file_name.unwrap_or_else(|| {
+        let extension = if mime == "message/rfc822" {
+            "eml"
+        } else {
+            mime.rsplit('/').next().unwrap_or("bin")
+        };
+        format!("附件{}.{}", parts.attachments.len() + 1, extension)
+    });
+    parts.attachments.push(EmailAttachment {
+        file_name,
+        mime,
+        content,
+    });
+}
+
+/// 按第一个空行分开头部和正文
+fn split_header_body(raw: &[u8]) -> (&[u8], &[u8]) {
+    let mut pos = 0;
+    while pos < raw.len() {
+        let end = line_end(raw, pos);
+        if trim_line_end(&raw[pos..end]).is_empty() {
+            return (&raw[..pos], &raw[end..]);
+        }
+        pos = end;
+    }
+    (raw, &[])
+}
+
+/// 从 `pos` 开始的一行的结束位置（含换行符）
+fn line_end(bytes: &[u8], pos: usize) -> usize {
+    bytes[pos..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |i| pos + i + 1)
+}
+
+fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// 解析头部（名称转为小写，折行合并为一行）
+fn parse_headers(bytes: &[u8]) -> Vec<(String, String)> {
+    let text = decode_charset(bytes, None);
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// 带参数的头部值，如 `text/plain; charset="gb2312"`
+#[derive(Debug)]
+struct HeaderValue {
+    value: String,
+    params: HashMap<String, String>,
+}
+
+impl HeaderValue {
+    fn plain(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            params: HashMap::new(),
+        }
+    }
+
+    fn parse(raw: &str) -> Self {
+        let mut segments = split_params(raw).into_iter();
+        let value = segments.next().unwrap_or_default().trim().to_string();
+
+        // RFC 2231 续行参数（name*0、name*1*…）按序号拼接
+        let mut continued: Vec<(String, usize, bool, String)> = Vec::new();
+        let mut params = HashMap::new();
+        for segment in segments {
+            let Some((name, value)) = segment.split_once('=') else {
+                continue;
+            };
+            let name = name.trim().to_ascii_lowercase();
+            let value = unquote(value.trim());
+            let (name, extended) = match name.strip_suffix('*') {
+                Some(name) => (name.to_string(), true),
+                None => (name, false),
+            };
+            match name.split_once('*') {
+                Some((base, index)) if index.parse::<usize>().is_ok() => {
+                    let index = index.parse().unwrap_or_default();
+                    continued.push((base.to_string(), index, extended, value));
+                }
+                _ if extended => {
+                    params.insert(name, decode_extended(&value));
+                }
+                _ => {
+                    params.insert(name, value);
+                }
+            }
+        }
+
+        continued.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        let mut joined: HashMap<String, (Option<String>, Vec<u8>)> = HashMap::new();
+        for (name, index, extended, value) in continued {
+            let entry = joined.entry(name).or_default();
+            if !extended {
+                entry.1.extend_from_slice(value.as_bytes());
+                continue;
+            }
+            let value = if index == 0 {
+                // 第一段带有 charset'language' 前缀
+                let mut pieces = value.splitn(3, '\'');
+                match (pieces.next(), pieces.next(), pieces.next()) {
+                    (Some(charset), Some(_), Some(rest)) => {
+                        entry.0 = Some(charset.to_string());
+                        rest.to_string()
+                    }
+                    _ => value,
+                }
+            } else {
+                value
+            };
+            entry.1.extend(percent_decode(&value));
+        }
+        for (name, (charset, bytes)) in joined {
+            params
+                .entry(name)
+                .or_insert_with(|| decode_charset(&bytes, charset.as_deref()));
+        }
+
+        Self { value, params }
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+}
+
+/// 按分号拆分头部值（忽略引号内的分号）
+fn split_params(raw: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in raw.chars() {
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => {
+                current.push(c);
+                escaped = true;
+            }
+            '"' => {
+                current.push(c);
+                quoted = !quoted;
+            }
+            ';' if !quoted => segments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+    segments
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+/// 解码 RFC 2231 扩展参数值 `charset'language'%XX…`
+fn decode_extended(value: &str) -> String {
+    let mut pieces = value.splitn(3, '\'');
+    match (pieces.next(), pieces.next(), pieces.next()) {
+        (Some(charset), Some(_), Some(rest)) => {
+            decode_charset(&percent_decode(rest), Some(charset))
+        }
+        _ => decode_charset(&percent_decode(value), None),
+    }
+}
+
+fn percent_decode(value: &str) -> Vec<u8> {
+    unescape_hex(value.as_bytes(), b'%')
+}
+
+/// 解码 `<escape>XX` 形式的十六进制转义，无效的转义原样保留
+fn unescape_hex(input: &[u8], escape: u8) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == escape {
+            if let Some(byte) = input.get(i + 1..i + 3).and_then(hex_byte) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(input[i]);
+        i += 1;
+    }
+    out
+}
+
+fn hex_byte(digits: &[u8]) -> Option<u8> {
+    let digit = |d: u8| (d as char).to_digit(16);
+    Some((digit(digits[0])? * 16 + digit(digits[1])?) as u8)
+}
+
+/// 解码 quoted-printable（先去掉行尾 `=` 软换行）
+fn decode_quoted_printable(input: &[u8]) -> Vec<u8> {
+    let mut joined = Vec::with_capacity(input.len());
+    let mut pos = 0;
+    while pos < input.len() {
+        let end = line_end(input, pos);
+        let line = &input[pos..end];
+        let content = trim_line_end(line);
+        let trimmed = content.trim_ascii_end();
+        match trimmed.strip_suffix(b"=") {
+            Some(soft) => joined.extend_from_slice(soft),
+            None => joined.extend_from_slice(line),
+        }
+        pos = end;
+    }
+    unescape_hex(&joined, b'=')
+}
+
+/// 按分隔线拆分 multipart 正文，返回各部分（不含前言和结语）
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut pos = 0;
+    while pos < body.len() {
+        let end = line_end(body, pos);
+        let line = trim_line_end(&body[pos..end]);
+        if let Some(rest) = line.strip_prefix(delimiter.as_bytes()) {
+            let closing = rest.starts_with(b"--");
+            if closing || rest.iter().all(u8::is_ascii_whitespace) {
+                if let Some(start) = start {
+                    // 分隔线前的换行属于分隔线
+                    parts.push(trim_line_end(&body[start..pos]));
+                }
+                if closing {
+                    return parts;
+                }
+                start = Some(end);
+            }
+        }
+        pos = end;
+    }
+    // 缺少结束分隔线时保留最后一部分
+    if let Some(start) = start {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+/// 按传输编码解码内容
+fn decode_transfer(encoding: Option<&str>, body: &[u8]) -> Vec<u8> {
+    match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+        Some("base64") => {
+            let compact: Vec<u8> = body
+                .iter()
+                .copied()
+                .filter(|b| !b.is_ascii_whitespace())
+                .collect();
+            BASE64.decode(compact).unwrap_or_else(|_| body.to_vec())
+        }
+        Some("quoted-printable") => decode_quoted_printable(body),
+        _ => body.to_vec(),
+    }
+}
+
+/// 按字符集解码文本
+///
+/// 没有字符集时优先按 UTF-8，不是有效 UTF-8 时按 GB18030。GB2312、GBK 的标签在
+/// encoding_rs 中对应的解码器与 GB18030 相同，可以解出超出 GB2312 范围的字。
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    let encoding = match charset.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(label) => Encoding::for_label(label.as_bytes()).unwrap_or(GB18030),
+        None => match std::str::from_utf8(bytes) {
+            Ok(text) => return text.to_string(),
+            Err(_) => GB18030,
+        },
+    };
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// 解码 RFC 2047 编码字 `=?charset?B|Q?text?=`，相邻编码字之间的空白忽略
+fn decode_words(value: &str) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    let mut pending_space = String::new();
+    let mut last_was_word = false;
+    while !rest.is_empty() {
+        let Some(start) = rest.find("=?") else {
+            out.push_str(&pending_space);
+            out.push_str(rest);
+            return out;
+        };
+        let (before, candidate) = rest.split_at(start);
+        match decode_word(candidate) {
+            Some((decoded, consumed)) => {
+                if !(last_was_word && before.trim().is_empty()) {
+                    out.push_str(&pending_space);
+                    out.push_str(before);
+                }
+                pending_space.clear();
+                out.push_str(&decoded);
+                last_was_word = true;
+                rest = &candidate[consumed..];
+                let spaces = rest.len() - rest.trim_start().len();
+                pending_space.push_str(&rest[..spaces]);
+                rest = &rest[spaces..];
+            }
+            None => {
+                out.push_str(&pending_space);
+                pending_space.clear();
+                out.push_str(before);
+                out.push_str("=?");
+                last_was_word = false;
+                rest = &candidate[2..];
+            }
+        }
+    }
+    out.push_str(&pending_space);
+    out
+}
+
+/// 解码以 `=?` 开头的一个编码字，返回解码结果和消耗的长度
+fn decode_word(input: &str) -> Option<(String, usize)> {
+    let inner = input.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let text = &inner[..end];
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    // 去掉 RFC 2231 的语言后缀，如 utf-8*zh
+    let charset = charset.split('*').next().unwrap_or(charset);
+    let bytes = match encoding {
+        "B" | "b" => BASE64.decode(text).ok()?,
+        "Q" | "q" => unescape_hex(text.replace('_', " ").as_bytes(), b'='),
+        _ => return None,
+    };
+    let consumed = input.len() - inner.len() + end + 2;
+    Some((decode_charset(&bytes, Some(charset)), consumed))
+}
+
+/// 解析 `显示名称 <地址>` 或单独的地址
+fn parse_mailbox(value: &str) -> (Option<String>, Option<String>) {
+    let value = value.trim();
+    let (name, address) = match (value.rfind('<'), value.rfind('>')) {
+        (Some(open), Some(close)) if open < close => {
+            (value[..open].trim(), value[open + 1..close].trim())
+        }
+        _ => ("", value),
+    };
+    let name = unquote(name).trim().to_string();
+    let address = address.to_ascii_lowercase();
+    (
+        Some(name).filter(|n| !n.is_empty()),
+        Some(address).filter(|a| a.contains('@')),
+    )
+}
+
+/// 统一换行为 `\n`
+fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// 把HTML正文转换为纯文本（去掉标签、样式和脚本，块级元素换行）
+fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[open + 1..open + close].trim().to_ascii_lowercase();
+        rest = &rest[open + close + 1..];
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if !tag.starts_with('/') && (name == "style" || name == "script") {
+            let end_tag = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&end_tag) {
+                Some(end) => {
+                    let after = &rest[end..];
+                    after.find('>').map_or("", |i| &after[i + 1..])
+                }
+                None => "",
+            };
+            continue;
+        }
+        if matches!(
+            name.as_str(),
+            "br" | "p" | "div" | "tr" | "li" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+        ) {
+            out.push('\n');
+        }
+    }
+    out.push_str(rest);
+
+    let text = out
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    // 合并连续空行
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() && lines.last().is_none_or(|l| l.trim().is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gb18030_multipart_with_attachment() {
+        // 主题、发件人和正文都是 GB2312/GBK 编码，正文含软换行
+        let raw = "From: =?GB2312?B?1cXI/Q==?= <ZhangSan@Example.com>\r\n\
+                   Subject: =?gb2312?B?t+Kx37v60uzP7A==?=\r\n\
+                   Message-ID: <abc123@example.com>\r\n\
+                   MIME-Version: 1.0\r\n\
+                   Content-Type: multipart/mixed;\r\n\tboundary=\"----=_Part_1\"\r\n\
+                   \r\n\
+                   This is a multi-part message in MIME format.\r\n\
+                   ------=_Part_1\r\n\
+                   Content-Type: multipart/alternative; boundary=alt\r\n\
+                   \r\n\
+                   --alt\r\n\
+                   Content-Type: text/plain; charset=\"GBK\"\r\n\
+                   Content-Transfer-Encoding: quoted-printable\r\n\
+                   \r\n\
+                   =C4=FA=BA=C3=A3=AC=B7=E2=B1=DF=BB=FA=BF=AA=BB=FA=BA=F3=D3=D0=D2=EC=CF=EC=A1=\r\n\
+                   =A3\r\n\
+                   =D0=CD=BA=C5 EB-200=A3=AC=C7=EB=BE=A1=BF=EC=B0=B2=C5=C5=A1=A3\r\n\
+                   --alt\r\n\
+                   Content-Type: text/html; charset=utf-8\r\n\
+                   \r\n\
+                   <p>ignored</p>\r\n\
+                   --alt--\r\n\
+                   ------=_Part_1\r\n\
+                   Content-Type: image/jpeg; name*=UTF-8''%E7%8E%B0%E5%9C%BA.jpg\r\n\
+                   Content-Disposition: attachment\r\n\
+                   Content-Transfer-Encoding: base64\r\n\
+                   \r\n\
+                   /9j/4AAQ\r\n\
+                   SkZJRg\r\n\
+                   ------=_Part_1--\r\n";
+
+        let email = parse_email(raw.as_bytes());
+        assert_eq!(email.message_id.as_deref(), Some("<abc123@example.com>"));
+        assert_eq!(email.from_address.as_deref(), Some("zhangsan@example.com"));
+        assert_eq!(email.from_name.as_deref(), Some("张三"));
+        assert_eq!(email.subject, "封边机异响");
+        assert_eq!(
+            email.body,
+            "您好，封边机开机后有异响。\n型号 EB-200，请尽快安排。"
+        );
+        assert_eq!(email.attachments.len(), 1);
+        let attachment = &email.attachments[0];
+        assert_eq!(attachment.file_name, "现场.jpg");
+        assert_eq!(attachment.mime, "image/jpeg");
+        assert_eq!(attachment.content[..4], [0xFF, 0xD8, 0xFF, 0xE0]);
+    }
+
+    #[test]
+    fn test_html_only_and_undeclared_charset() {
+        let mut raw = b"From: service@example.com\r\n\
+                        Subject: =?utf-8?Q?=E6=8A=A5=E4=BF=AE?= =?utf-8?Q?_=E7=94=B3=E8=AF=B7?=\r\n\
+                        Content-Type: text/html\r\n\
+                        \r\n"
+            .to_vec();
+        raw.extend_from_slice(
+            &GB18030
+                .encode("<html><style>p{}</style><p>板材&amp;五金</p><br>谢谢</html>")
+                .0,
+        );
+
+        let email = parse_email(&raw);
+        assert_eq!(email.message_id, None);
+        assert_eq!(email.from_name, None);
+        assert_eq!(email.from_address.as_deref(), Some("service@example.com"));
+        assert_eq!(email.subject, "报修 申请");
+        assert_eq!(email.body, "板材&五金\n\n谢谢");
+        assert!(email.attachments.is_empty());
+    }
+}
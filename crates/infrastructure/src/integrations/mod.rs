@@ -0,0 +1,22 @@
+//! 外部集成模块
+//!
+//! 提供Webhook推送、邮件发送、售后邮箱收件等与外部系统对接的功能，需启用 `integrations` 特性。
+
+pub mod email_intake;
+pub mod imap;
+pub mod mail;
+pub mod mime;
+pub mod webhook;
+
+// 重新导出主要类型
+pub use email_intake::{
+    EmailIntake, EmailIntakeJob, IntakeAttachment, IntakeRecord, IntakeSummary, MailboxSource,
+    RawEmail,
+};
+pub use imap::{ImapMailbox, ImapSettings};
+pub use mail::{SmtpMailer, SmtpSettings};
+pub use webhook::{
+    sign_payload, DeliveryStatus, HttpWebhookTransport, NewWebhookEndpoint, WebhookDelivery,
+    WebhookDeliveryJob, WebhookDispatcher, WebhookEndpoint, WebhookEndpointService,
+    WebhookPayload,
+};
@@ -0,0 +1,834 @@
+//! Webhook推送
+//!
+//! 订阅领域事件，将事件及实体快照以签名JSON推送到用户配置的端点。
+//! 事件到达时只登记投递记录，实际发送由 [`WebhookDeliveryJob`] 定期执行，
+//! 失败的投递按指数退避重试，最多5次。
+//!
+//! 请求经由 [`WebhookTransport`] 发送，默认为 [`HttpWebhookTransport`]；应用层可替换为
+//! 带熔断的发送通道，端点持续不可用时不再逐条等待超时。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use minicrm_core::{
+    to_canonical_json, CoreError, CoreResult, EventEnvelope, EventHandler, Job, JobSchedule,
+    WebhookRequest, WebhookTransport,
+};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{get_optional_time, get_time};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::snapshot::SnapshotProvider;
+
+/// 签名请求头
+pub const SIGNATURE_HEADER: &str = "X-MiniCRM-Signature";
+/// 事件类型请求头
+pub const EVENT_HEADER: &str = "X-MiniCRM-Event";
+/// 投递ID请求头
+pub const DELIVERY_HEADER: &str = "X-MiniCRM-Delivery";
+
+/// 最大重试次数（不含首次投递，第 `MAX_RETRIES + 1` 次失败后放弃）
+pub const MAX_RETRIES: u32 = 5;
+/// 请求超时时间
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// 匹配全部事件类型的通配符
+pub const ALL_EVENTS: &str = "*";
+
+/// 计算负载的HMAC-SHA256签名（十六进制）
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    // HMAC接受任意长度的密钥，此处不会失败
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return String::new(),
+    };
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Webhook端点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    /// 端点ID
+    pub id: Uuid,
+    /// 推送地址
+    pub url: String,
+    /// 签名密钥
+    pub secret: String,
+    /// 是否启用
+    pub enabled: bool,
+    /// 订阅的事件类型
+    pub event_types: Vec<String>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WebhookEndpoint {
+    /// 端点是否订阅了指定事件
+    pub fn accepts(&self, event_type: &str) -> bool {
+        self.enabled
+            && self
+                .event_types
+                .iter()
+                .any(|t| t == event_type || t == ALL_EVENTS)
+    }
+}
+
+/// 新建端点参数
+#[derive(Debug, Clone)]
+pub struct NewWebhookEndpoint {
+    /// 推送地址
+    pub url: String,
+    /// 签名密钥
+    pub secret: String,
+    /// 订阅的事件类型
+    pub event_types: Vec<String>,
+}
+
+/// 投递状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    /// 待投递（含等待重试）
+    Pending,
+    /// 已投递
+    Delivered,
+    /// 重试耗尽
+    Failed,
+}
+
+impl DeliveryStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "delivered" => DeliveryStatus::Delivered,
+            "failed" => DeliveryStatus::Failed,
+            _ => DeliveryStatus::Pending,
+        }
+    }
+}
+
+/// 投递记录
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    /// 投递ID
+    pub id: Uuid,
+    /// 端点ID
+    pub endpoint_id: Uuid,
+    /// 事件ID
+    pub event_id: Uuid,
+    /// 事件类型
+    pub event_type: String,
+    /// 负载JSON
+    pub payload: String,
+    /// 状态
+    pub status: DeliveryStatus,
+    /// 最近一次响应码
+    pub response_code: Option<u16>,
+    /// 已失败次数（首次投递失败记为1）
+    pub retry_count: u32,
+    /// 下次尝试时间
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// 最近一次错误
+    pub last_error: Option<String>,
+}
+
+/// 推送负载
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// 事件ID
+    pub event_id: Uuid,
+    /// 事件类型
+    pub event_type: String,
+    /// 事件发生时间
+    pub occurred_at: DateTime<Utc>,
+    /// 负载生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 实体类型（数据表名）
+    pub entity_type: String,
+    /// 实体ID
+    pub entity_id: Uuid,
+    /// 事件详情
+    pub event: serde_json::Value,
+    /// 实体快照（实体已被删除时为空）
+    pub snapshot: Option<serde_json::Value>,
+}
+
+/// Webhook端点管理服务
+#[derive(Debug, Clone)]
+pub struct WebhookEndpointService {
+    connection: DatabaseConnection,
+}
+
+impl WebhookEndpointService {
+    /// 创建端点管理服务
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 新建端点
+    pub fn create(&self, endpoint: NewWebhookEndpoint) -> Result<WebhookEndpoint> {
+        if !(endpoint.url.starts_with("http://") || endpoint.url.starts_with("https://")) {
+            return Err(anyhow!("Webhook地址必须以 http:// 或 https:// 开头"));
+        }
+        if endpoint.secret.trim().is_empty() {
+            return Err(anyhow!("Webhook签名密钥不能为空"));
+        }
+
+        let now = Utc::now();
+        let created = WebhookEndpoint {
+            id: Uuid::new_v4(),
+            url: endpoint.url,
+            secret: endpoint.secret,
+            enabled: true,
+            event_types: endpoint.event_types,
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.connection.execute(
+            "INSERT INTO webhook_endpoints (id, url, secret, enabled, event_types, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 1, ?4, ?5, ?5)",
+            rusqlite::params![
+                DbUuid(created.id),
+                created.url,
+                created.secret,
+                serde_json::to_string(&created.event_types)?,
+                now.to_rfc3339(),
+            ],
+        )?;
+
+        info!("已创建Webhook端点: {}", created.url);
+        Ok(created)
+    }
+
+    /// 启用或停用端点
+    pub fn set_enabled(&self, id: Uuid, enabled: bool) -> Result<()> {
+        let affected = self.connection.execute(
+            "UPDATE webhook_endpoints SET enabled = ?2, updated_at = ?3 WHERE id = ?1",
+            rusqlite::params![DbUuid(id), enabled, Utc::now().to_rfc3339()],
+        )?;
+        if affected == 0 {
+            return Err(anyhow!("Webhook端点不存在: {}", id));
+        }
+        Ok(())
+    }
+
+    /// 更新订阅的事件类型
+    pub fn set_event_types(&self, id: Uuid, event_types: &[String]) -> Result<()> {
+        let affected = self.connection.execute(
+            "UPDATE webhook_endpoints SET event_types = ?2, updated_at = ?3 WHERE id = ?1",
+            rusqlite::params![
+                DbUuid(id),
+                serde_json::to_string(event_types)?,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        if affected == 0 {
+            return Err(anyhow!("Webhook端点不存在: {}", id));
+        }
+        Ok(())
+    }
+
+    /// 删除端点（连同其投递记录）
+    pub fn delete(&self, id: Uuid) -> Result<bool> {
+        let affected = self.connection.execute(
+            "DELETE FROM webhook_endpoints WHERE id = ?1",
+            [DbUuid(id)],
+        )?;
+        Ok(affected > 0)
+    }
+
+    /// 查询全部端点
+    pub fn list(&self) -> Result<Vec<WebhookEndpoint>> {
+        self.connection.query_map(
+            "SELECT id, url, secret, enabled, event_types, created_at, updated_at
+             FROM webhook_endpoints ORDER BY created_at",
+            [],
+            |row| {
+                let event_types: String = row.get("event_types")?;
+                Ok(WebhookEndpoint {
+                    id: get_uuid(row, "id")?,
+                    url: row.get("url")?,
+                    secret: row.get("secret")?,
+                    enabled: row.get("enabled")?,
+                    event_types: serde_json::from_str(&event_types).unwrap_or_default(),
+                    created_at: get_time(row, "created_at")?,
+                    updated_at: get_time(row, "updated_at")?,
+                })
+            },
+        )
+    }
+}
+
+/// 单次投递运行汇总
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeliveryRunSummary {
+    /// 投递成功数
+    pub delivered: usize,
+    /// 等待重试数
+    pub retrying: usize,
+    /// 重试耗尽数
+    pub failed: usize,
+}
+
+/// HTTP发送通道
+#[derive(Debug, Clone)]
+pub struct HttpWebhookTransport {
+    client: reqwest::Client,
+}
+
+impl HttpWebhookTransport {
+    /// 创建发送通道，请求超时为 [`REQUEST_TIMEOUT`]
+    ///
+    /// # Errors
+    ///
+    /// 如果HTTP客户端创建失败，将返回错误。
+    pub fn new() -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("无法创建Webhook HTTP客户端")?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl WebhookTransport for HttpWebhookTransport {
+    async fn post(&self, request: &WebhookRequest) -> CoreResult<u16> {
+        let mut builder = self.client.post(&request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let response = builder
+            .body(request.body.clone())
+            .send()
+            .await
+            .map_err(|e| CoreError::ExternalService(e.to_string()))?;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// Webhook分发器
+pub struct WebhookDispatcher {
+    connection: DatabaseConnection,
+    endpoints: WebhookEndpointService,
+    snapshots: Arc<dyn SnapshotProvider>,
+    transport: Arc<dyn WebhookTransport + Send + Sync>,
+    backoff_base: chrono::Duration,
+}
+
+impl std::fmt::Debug for WebhookDispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookDispatcher")
+            .field("backoff_base", &self.backoff_base)
+            .finish()
+    }
+}
+
+impl WebhookDispatcher {
+    /// 创建分发器
+    ///
+    /// # Errors
+    ///
+    /// 如果HTTP客户端创建失败，将返回错误。
+    pub fn new(connection: DatabaseConnection, snapshots: Arc<dyn SnapshotProvider>) -> Result<Self> {
+        Ok(Self {
+            endpoints: WebhookEndpointService::new(connection.clone()),
+            connection,
+            snapshots,
+            transport: Arc::new(HttpWebhookTransport::new()?),
+            backoff_base: chrono::Duration::seconds(30),
+        })
+    }
+
+    /// 设置发送通道
+    pub fn with_transport(mut self, transport: Arc<dyn WebhookTransport + Send + Sync>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// 设置重试退避基准时长（第n次重试等待 基准 × 2^(n-1)）
+    pub fn with_backoff_base(mut self, base: chrono::Duration) -> Self {
+        self.backoff_base = base;
+        self
+    }
+
+    /// 计算第 `retry_count` 次失败后的等待时长
+    pub fn backoff_delay(&self, retry_count: u32) -> chrono::Duration {
+        let exponent = retry_count.saturating_sub(1).min(16);
+        self.backoff_base * 2_i32.pow(exponent)
+    }
+
+    /// 为事件登记投递记录
+    ///
+    /// 同一事件对同一端点只登记一次，重复分发的事件会被忽略。
+    pub fn enqueue(&self, envelope: &EventEnvelope) -> Result<usize> {
+        let event_type = envelope.event.event_type();
+        let endpoints: Vec<_> = self
+            .endpoints
+            .list()?
+            .into_iter()
+            .filter(|e| e.accepts(event_type))
+            .collect();
+
+        if endpoints.is_empty() {
+            return Ok(0);
+        }
+
+        let kind = envelope.event.entity_kind();
+        let entity_id = envelope.event.entity_id();
+        let payload = WebhookPayload {
+            event_id: envelope.id,
+            event_type: event_type.to_string(),
+            occurred_at: envelope.occurred_at,
+            generated_at: Utc::now(),
+            entity_type: kind.table_name().to_string(),
+            entity_id,
+            event: to_canonical_json(envelope)?["event"].take(),
+            snapshot: self.snapshots.snapshot(kind, entity_id)?,
+        };
+        let body = serde_json::to_string(&payload)?;
+        let now = Utc::now().to_rfc3339();
+
+        let mut enqueued = 0;
+        for endpoint in endpoints {
+            enqueued += self.connection.execute(
+                "INSERT OR IGNORE INTO webhook_deliveries
+                    (id, endpoint_id, event_id, event_type, payload, status, next_attempt_at, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6, ?6, ?6)",
+                rusqlite::params![
+                    DbUuid(Uuid::new_v4()),
+                    DbUuid(endpoint.id),
+                    DbUuid(envelope.id),
+                    event_type,
+                    body,
+                    now,
+                ],
+            )?;
+        }
+
+        debug!("事件 {} 登记了 {} 条Webhook投递", event_type, enqueued);
+        Ok(enqueued)
+    }
+
+    /// 查询投递记录
+    pub fn deliveries(&self) -> Result<Vec<WebhookDelivery>> {
+        self.connection.query_map(
+            "SELECT id, endpoint_id, event_id, event_type, payload, status, response_code,
+                    retry_count, next_attempt_at, last_error
+             FROM webhook_deliveries ORDER BY created_at",
+            [],
+            |row| {
+                Ok(WebhookDelivery {
+                    id: get_uuid(row, "id")?,
+                    endpoint_id: get_uuid(row, "endpoint_id")?,
+                    event_id: get_uuid(row, "event_id")?,
+                    event_type: row.get("event_type")?,
+                    payload: row.get("payload")?,
+                    status: DeliveryStatus::parse(&row.get::<_, String>("status")?),
+                    response_code: row.get("response_code")?,
+                    retry_count: row.get("retry_count")?,
+                    next_attempt_at: get_optional_time(row, "next_attempt_at")?,
+                    last_error: row.get("last_error")?,
+                })
+            },
+        )
+    }
+
+    /// 发送 `now` 时刻到期的全部待投递记录
+    pub async fn deliver_due(&self, now: DateTime<Utc>) -> Result<DeliveryRunSummary> {
+        let due = self.connection.query_map(
+            "SELECT d.id, d.event_type, d.payload, d.retry_count, e.url, e.secret
+             FROM webhook_deliveries d
+             JOIN webhook_endpoints e ON e.id = d.endpoint_id
+             WHERE d.status = 'pending' AND e.enabled = 1
+               AND (d.next_attempt_at IS NULL OR d.next_attempt_at <= ?1)
+             ORDER BY d.created_at",
+            [now.to_rfc3339()],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, u32>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            },
+        )?;
+
+        let mut summary = DeliveryRunSummary::default();
+        for (id, event_type, payload, retry_count, url, secret) in due {
+            let request = WebhookRequest {
+                headers: vec![
+                    ("Content-Type".to_string(), "application/json".to_string()),
+                    (
+                        SIGNATURE_HEADER.to_string(),
+                        sign_payload(&secret, payload.as_bytes()),
+                    ),
+                    (EVENT_HEADER.to_string(), event_type),
+                    (DELIVERY_HEADER.to_string(), id.clone()),
+                ],
+                url: url.clone(),
+                body: payload,
+            };
+
+            let (code, error) = match self.transport.post(&request).await {
+                Ok(code) if (200..300).contains(&code) => (Some(code), None),
+                Ok(code) => (Some(code), Some(format!("HTTP {}", code))),
+                Err(e) => (None, Some(e.to_string())),
+            };
+
+            match error {
+                None => {
+                    self.connection.execute(
+                        "UPDATE webhook_deliveries
+                         SET status = 'delivered', response_code = ?2, next_attempt_at = NULL,
+                             last_error = NULL, updated_at = ?3
+                         WHERE id = ?1",
+                        rusqlite::params![id, code, now.to_rfc3339()],
+                    )?;
+                    summary.delivered += 1;
+                }
+                Some(error) => {
+                    let retries = retry_count + 1;
+                    let (status, next_attempt) = if retries > MAX_RETRIES {
+                        (DeliveryStatus::Failed, None)
+                    } else {
+                        (
+                            DeliveryStatus::Pending,
+                            Some((now + self.backoff_delay(retries)).to_rfc3339()),
+                        )
+                    };
+                    warn!("Webhook投递失败 (第 {} 次): {} {}", retries, url, error);
+
+                    self.connection.execute(
+                        "UPDATE webhook_deliveries
+                         SET status = ?2, response_code = ?3, retry_count = ?4,
+                             next_attempt_at = ?5, last_error = ?6, updated_at = ?7
+                         WHERE id = ?1",
+                        rusqlite::params![
+                            id,
+                            status.as_str(),
+                            code,
+                            retries,
+                            next_attempt,
+                            error,
+                            now.to_rfc3339()
+                        ],
+                    )?;
+
+                    if status == DeliveryStatus::Failed {
+                        summary.failed += 1;
+                    } else {
+                        summary.retrying += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+impl EventHandler for WebhookDispatcher {
+    fn name(&self) -> &str {
+        "webhook_dispatcher"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        self.enqueue(envelope)?;
+        Ok(())
+    }
+}
+
+/// Webhook投递任务
+#[derive(Debug, Clone)]
+pub struct WebhookDeliveryJob {
+    dispatcher: Arc<WebhookDispatcher>,
+    interval: chrono::Duration,
+}
+
+impl WebhookDeliveryJob {
+    /// 创建投递任务，默认每30秒运行一次
+    pub fn new(dispatcher: Arc<WebhookDispatcher>) -> Self {
+        Self {
+            dispatcher,
+            interval: chrono::Duration::seconds(30),
+        }
+    }
+}
+
+#[async_trait]
+impl Job for WebhookDeliveryJob {
+    fn name(&self) -> &str {
+        "webhook_delivery"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(self.interval)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let summary = self.dispatcher.deliver_due(Utc::now()).await?;
+        if summary != DeliveryRunSummary::default() {
+            info!("Webhook投递: {:?}", summary);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::outbox::{self, EventOutboxStore, OutboxDispatcher};
+    use crate::repository::snapshot::TableSnapshotProvider;
+    use crate::test_support::migrated_pool;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use minicrm_core::{DomainEvent, EntityKind, QuoteStatus};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    #[derive(Clone, Default)]
+    struct Captured {
+        requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        responses: Arc<Mutex<Vec<StatusCode>>>,
+    }
+
+    async fn receive(State(state): State<Captured>, headers: HeaderMap, body: String) -> StatusCode {
+        state.requests.lock().unwrap().push((headers, body));
+        let mut responses = state.responses.lock().unwrap();
+        if responses.is_empty() {
+            StatusCode::OK
+        } else {
+            responses.remove(0)
+        }
+    }
+
+    async fn spawn_server(responses: Vec<StatusCode>) -> (String, Captured) {
+        let captured = Captured::default();
+        *captured.responses.lock().unwrap() = responses;
+
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(captured.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        (format!("http://{}/hook", addr), captured)
+    }
+
+    fn setup() -> (TempDir, DatabaseConnection, WebhookDispatcher) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let snapshots = Arc::new(TableSnapshotProvider::new(connection.clone()));
+        let dispatcher = WebhookDispatcher::new(connection.clone(), snapshots)
+            .unwrap()
+            .with_backoff_base(chrono::Duration::seconds(10));
+        (temp_dir, connection, dispatcher)
+    }
+
+    fn quote_accepted() -> EventEnvelope {
+        EventEnvelope::new(DomainEvent::QuoteStatusChanged {
+            quote_id: Uuid::new_v4(),
+            customer_id: Uuid::new_v4(),
+            from: QuoteStatus::Sent,
+            to: QuoteStatus::Accepted,
+        })
+    }
+
+    fn new_endpoint(url: &str) -> NewWebhookEndpoint {
+        NewWebhookEndpoint {
+            url: url.to_string(),
+            secret: "s3cret".to_string(),
+            event_types: vec!["quote_status_changed".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_signature_is_stable() {
+        let a = sign_payload("key", b"{}");
+        assert_eq!(a, sign_payload("key", b"{}"));
+        assert_ne!(a, sign_payload("other", b"{}"));
+        assert!(a.starts_with("sha256="));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed() {
+        let (_dir, connection, dispatcher) = setup();
+        let (url, captured) = spawn_server(vec![]).await;
+        WebhookEndpointService::new(connection)
+            .create(new_endpoint(&url))
+            .unwrap();
+
+        let envelope = quote_accepted();
+        assert_eq!(dispatcher.enqueue(&envelope).unwrap(), 1);
+        let summary = dispatcher.deliver_due(Utc::now()).await.unwrap();
+        assert_eq!(summary.delivered, 1);
+
+        let requests = captured.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let (headers, body) = &requests[0];
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            sign_payload("s3cret", body.as_bytes())
+        );
+
+        let payload: WebhookPayload = serde_json::from_str(body).unwrap();
+        assert_eq!(payload.event_id, envelope.id);
+        assert_eq!(payload.event_type, "quote_status_changed");
+        assert_eq!(payload.entity_type, EntityKind::Quote.table_name());
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_retries_with_backoff() {
+        let (_dir, connection, dispatcher) = setup();
+        let (url, captured) = spawn_server(vec![
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+        ])
+        .await;
+        WebhookEndpointService::new(connection)
+            .create(new_endpoint(&url))
+            .unwrap();
+        dispatcher.enqueue(&quote_accepted()).unwrap();
+
+        let start = Utc::now();
+        assert_eq!(dispatcher.deliver_due(start).await.unwrap().retrying, 1);
+
+        // 退避期内不会重试
+        let early = start + chrono::Duration::seconds(5);
+        assert_eq!(
+            dispatcher.deliver_due(early).await.unwrap(),
+            DeliveryRunSummary::default()
+        );
+
+        // 第二次失败后等待时间翻倍
+        let second = start + chrono::Duration::seconds(10);
+        assert_eq!(dispatcher.deliver_due(second).await.unwrap().retrying, 1);
+        let delivery = &dispatcher.deliveries().unwrap()[0];
+        assert_eq!(delivery.retry_count, 2);
+        assert_eq!(delivery.response_code, Some(502));
+        assert_eq!(
+            delivery.next_attempt_at.map(|t| t.timestamp()),
+            Some((second + chrono::Duration::seconds(20)).timestamp())
+        );
+
+        let third = second + chrono::Duration::seconds(20);
+        assert_eq!(dispatcher.deliver_due(third).await.unwrap().delivered, 1);
+        assert_eq!(captured.requests.lock().unwrap().len(), 3);
+        assert_eq!(
+            dispatcher.deliveries().unwrap()[0].status,
+            DeliveryStatus::Delivered
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_give_up_after_max_attempts() {
+        let (_dir, connection, dispatcher) = setup();
+        let attempts = MAX_RETRIES as usize + 1;
+        let (url, captured) =
+            spawn_server(vec![StatusCode::INTERNAL_SERVER_ERROR; attempts + 1]).await;
+        WebhookEndpointService::new(connection)
+            .create(new_endpoint(&url))
+            .unwrap();
+        dispatcher.enqueue(&quote_accepted()).unwrap();
+
+        // 首次投递加 MAX_RETRIES 次重试，最后一次重试前仍在等待
+        let mut now = Utc::now();
+        for _ in 0..MAX_RETRIES {
+            dispatcher.deliver_due(now).await.unwrap();
+            now += chrono::Duration::days(1);
+        }
+        assert_eq!(
+            dispatcher.deliveries().unwrap()[0].status,
+            DeliveryStatus::Pending
+        );
+
+        assert_eq!(dispatcher.deliver_due(now).await.unwrap().failed, 1);
+        let delivery = &dispatcher.deliveries().unwrap()[0];
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.retry_count, MAX_RETRIES + 1);
+
+        // 放弃后不再投递
+        now += chrono::Duration::days(1);
+        dispatcher.deliver_due(now).await.unwrap();
+        assert_eq!(captured.requests.lock().unwrap().len(), attempts);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_endpoint_receives_nothing() {
+        let (_dir, connection, dispatcher) = setup();
+        let (url, captured) = spawn_server(vec![]).await;
+        let service = WebhookEndpointService::new(connection);
+        let endpoint = service.create(new_endpoint(&url)).unwrap();
+        service.set_enabled(endpoint.id, false).unwrap();
+
+        assert_eq!(dispatcher.enqueue(&quote_accepted()).unwrap(), 0);
+        dispatcher.deliver_due(Utc::now()).await.unwrap();
+        assert!(captured.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribed_event_is_ignored() {
+        let (_dir, connection, dispatcher) = setup();
+        let (url, _captured) = spawn_server(vec![]).await;
+        WebhookEndpointService::new(connection)
+            .create(new_endpoint(&url))
+            .unwrap();
+
+        let created = EventEnvelope::new(DomainEvent::EntityCreated {
+            entity: EntityKind::Customer,
+            id: Uuid::new_v4(),
+        });
+        assert_eq!(dispatcher.enqueue(&created).unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_outbox_replay_does_not_duplicate_deliveries() {
+        let (_dir, connection, dispatcher) = setup();
+        let (url, captured) = spawn_server(vec![]).await;
+        WebhookEndpointService::new(connection.clone())
+            .create(new_endpoint(&url))
+            .unwrap();
+        let dispatcher = Arc::new(dispatcher);
+        let outbox = EventOutboxStore::new(connection.clone());
+
+        let envelope = quote_accepted();
+        connection
+            .with_transaction(|tx| outbox::record(tx, &envelope))
+            .unwrap();
+        // 发布后、标记已分发前退出
+        dispatcher.handle(&envelope).unwrap();
+        assert_eq!(dispatcher.deliveries().unwrap().len(), 1);
+
+        // 重启后重放同一事件，投递记录按事件ID去重
+        let restarted = OutboxDispatcher::new(outbox.clone(), dispatcher.clone());
+        assert_eq!(restarted.dispatch_pending().unwrap(), 1);
+        assert!(outbox.pending(10).unwrap().is_empty());
+        assert_eq!(dispatcher.deliveries().unwrap().len(), 1);
+
+        dispatcher.deliver_due(Utc::now()).await.unwrap();
+        assert_eq!(captured.requests.lock().unwrap().len(), 1);
+    }
+}
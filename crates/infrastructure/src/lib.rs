@@ -5,10 +5,27 @@
 #![deny(unsafe_code)]
 #![warn(missing_docs)]
 
+pub mod archive;
+pub mod attachments;
 pub mod database;
+pub mod desktop;
+pub mod export;
+#[cfg(feature = "integrations")]
+pub mod integrations;
 pub mod repository;
+pub mod security;
+pub mod spreadsheet;
+pub mod storage_gc;
+pub mod sync;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 // 重新导出主要类型
+pub use attachments::{AttachmentPolicy, AttachmentStore, StoredAttachment};
 pub use database::{
-    DatabaseConnection, DatabaseHealthChecker, DatabasePool, DatabasePoolConfig, MigrationManager,
+    DatabaseConnection, DatabaseFileGuard, DatabaseHealthChecker, DatabasePool, ExternalChange,
+    MigrationManager, PoolConfig,
 };
+pub use desktop::{SystemClipboard, SystemUriOpener};
+pub use spreadsheet::WorkbookReader;
+pub use storage_gc::{StorageGc, StorageGcReport};
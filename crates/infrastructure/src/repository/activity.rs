@@ -0,0 +1,589 @@
+//! 客户活跃度存储
+//!
+//! 活跃度分数和流失风险标记保存在 `customer_summary` 的 `activity_score`、`churn_risk` 列，
+//! 供客户列表筛选和仪表盘读取。分数变化另记在 `customer_activity_scores`（只在分数变化时
+//! 写入一行），不写入客户审计记录；“近12个月最高分”由这张表得出。
+//!
+//! 分数在互动、报价、收款等事件后按客户重新计算；近因得分随时间衰减，因此另有每周任务
+//! 重新计算全部客户。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Months, NaiveTime, Utc, Weekday};
+use minicrm_core::{
+    ActivityScorer, ActivityStats, ChannelActivity, Clock, CoreResult, DomainEvent, EntityKind,
+    EventEnvelope, EventHandler, Job, JobSchedule, Money, SystemClock,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{get_optional_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::customer_summary::{self, rate_sql};
+
+/// 近12个月窗口的起始时间
+fn window_start(now: DateTime<Utc>) -> String {
+    time_key(now.checked_sub_months(Months::new(12)).unwrap_or(now))
+}
+
+/// 单类活动的次数和最近时间（`?1` 为客户ID，`?2` 为窗口起始时间）
+fn channel_sql(from: &str, at: &str) -> String {
+    format!(
+        "(SELECT COUNT(*) {from} AND julianday({at}) >= julianday(?2)),
+         (SELECT {at} {from} AND julianday({at}) >= julianday(?2)
+          ORDER BY julianday({at}) DESC LIMIT 1)"
+    )
+}
+
+fn stats_sql() -> String {
+    format!(
+        "SELECT {}, {}, {}",
+        channel_sql("FROM interactions i WHERE i.customer_id = ?1", "i.occurred_at"),
+        channel_sql(
+            "FROM quotes q WHERE q.customer_id = ?1 AND q.deleted_at IS NULL",
+            "q.created_at"
+        ),
+        channel_sql(
+            "FROM order_payments p JOIN orders o ON o.id = p.order_id
+             WHERE o.customer_id = ?1 AND o.deleted_at IS NULL",
+            "p.paid_at"
+        ),
+    )
+}
+
+fn channel(row: &Row<'_>, index: usize) -> rusqlite::Result<ChannelActivity> {
+    Ok(ChannelActivity {
+        count: u32::try_from(row.get::<_, i64>(index)?).unwrap_or(u32::MAX),
+        last_at: get_optional_time(row, index + 1)?,
+    })
+}
+
+/// 客户近12个月的活动统计
+///
+/// # Errors
+///
+/// 查询失败时返回错误。
+pub fn activity_stats(
+    conn: &Connection,
+    customer_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<ActivityStats> {
+    Ok(conn.query_row(
+        &stats_sql(),
+        params![DbUuid(customer_id), window_start(now)],
+        |row| {
+            Ok(ActivityStats {
+                interactions: channel(row, 0)?,
+                quotes: channel(row, 2)?,
+                payments: channel(row, 4)?,
+            })
+        },
+    )?)
+}
+
+/// 一个客户的评分结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivityScore {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 分数
+    pub score: u8,
+    /// 近12个月的最高分（含本次）
+    pub peak: u8,
+    /// 流失风险
+    pub churn_risk: bool,
+    /// 分数与上次记录不同
+    pub changed: bool,
+}
+
+/// 流失风险客户
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtRiskCustomer {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub name: String,
+    /// 当前分数
+    pub score: u8,
+    /// 历史订单总额（本位币，缺少汇率的订单不计入）
+    pub lifetime_value: Money,
+}
+
+/// 全部客户重新评分的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActivityRefresh {
+    /// 评分的客户数
+    pub scored: usize,
+    /// 分数变化的客户数
+    pub changed: usize,
+    /// 有流失风险的客户数
+    pub at_risk: usize,
+}
+
+/// 重新计算客户的活跃度并写入（客户不存在时返回 `None`）
+///
+/// # Errors
+///
+/// 查询或写入失败时返回错误。
+pub fn score_customer(
+    conn: &Connection,
+    scorer: &ActivityScorer,
+    customer_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Option<ActivityScore>> {
+    let id = DbUuid(customer_id);
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM customers WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !exists {
+        return Ok(None);
+    }
+
+    let score = scorer.score(&activity_stats(conn, customer_id, now)?, now);
+    let (last, peak): (Option<i64>, Option<i64>) = conn.query_row(
+        "SELECT
+            (SELECT score FROM customer_activity_scores WHERE customer_id = ?1
+             ORDER BY scored_at DESC LIMIT 1),
+            (SELECT MAX(score) FROM customer_activity_scores
+             WHERE customer_id = ?1 AND julianday(scored_at) >= julianday(?2))",
+        params![id, window_start(now)],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let changed = last != Some(i64::from(score));
+    if changed {
+        conn.execute(
+            "INSERT OR REPLACE INTO customer_activity_scores (customer_id, score, scored_at)
+             VALUES (?1, ?2, ?3)",
+            params![id, score, time_key(now)],
+        )?;
+    }
+    let peak = peak
+        .and_then(|peak| u8::try_from(peak).ok())
+        .map_or(score, |peak| peak.max(score));
+    let churn_risk = scorer.churn.is_at_risk(score, peak);
+
+    let update = |conn: &Connection| {
+        conn.execute(
+            "UPDATE customer_summary
+             SET activity_score = ?2, churn_risk = ?3, activity_scored_at = ?4
+             WHERE customer_id = ?1",
+            params![id, score, churn_risk, time_key(now)],
+        )
+    };
+    if update(conn)? == 0 {
+        customer_summary::refresh_customer(conn, customer_id, now)?;
+        update(conn)?;
+    }
+    Ok(Some(ActivityScore {
+        customer_id,
+        score,
+        peak,
+        churn_risk,
+        changed,
+    }))
+}
+
+/// 事件影响活跃度的客户（与活跃度无关的事件返回 `None`）
+fn affected_customer(conn: &Connection, event: &DomainEvent) -> Result<Option<Uuid>> {
+    Ok(match event {
+        DomainEvent::InteractionLogged { customer_id, .. }
+        | DomainEvent::PaymentRecorded { customer_id, .. }
+        | DomainEvent::QuoteStatusChanged { customer_id, .. } => Some(*customer_id),
+        DomainEvent::EntityCreated { entity, id }
+        | DomainEvent::EntitySoftDeleted { entity, id }
+        | DomainEvent::EntityRestored { entity, id } => match entity {
+            EntityKind::Customer => Some(*id),
+            EntityKind::Quote | EntityKind::Order => customer_summary::owner(conn, *entity, *id)?,
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// 客户活跃度存储
+#[derive(Clone)]
+pub struct ActivityScoreStore {
+    connection: DatabaseConnection,
+    scorer: ActivityScorer,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for ActivityScoreStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActivityScoreStore")
+            .field("scorer", &self.scorer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ActivityScoreStore {
+    /// 以默认权重创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            scorer: ActivityScorer::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定的权重和流失风险规则
+    pub fn with_scorer(mut self, scorer: ActivityScorer) -> Self {
+        self.scorer = scorer;
+        self
+    }
+
+    /// 设置时钟（测试使用）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 重新计算单个客户的活跃度
+    ///
+    /// # Errors
+    ///
+    /// 查询或写入失败时返回错误。
+    pub fn refresh(&self, customer_id: Uuid) -> Result<Option<ActivityScore>> {
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| score_customer(tx, &self.scorer, customer_id, now))
+    }
+
+    /// 重新计算全部未删除客户的活跃度
+    ///
+    /// # Errors
+    ///
+    /// 查询或写入失败时返回错误。
+    pub fn refresh_all(&self) -> Result<ActivityRefresh> {
+        let now = self.clock.now();
+        self.connection.with_transaction(|tx| {
+            let ids = tx
+                .prepare("SELECT id FROM customers WHERE deleted_at IS NULL ORDER BY id")?
+                .query_map([], |row| get_uuid(row, 0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut result = ActivityRefresh::default();
+            for id in ids {
+                if let Some(score) = score_customer(tx, &self.scorer, id, now)? {
+                    result.scored += 1;
+                    result.changed += usize::from(score.changed);
+                    result.at_risk += usize::from(score.churn_risk);
+                }
+            }
+            Ok(result)
+        })
+    }
+
+    /// 有流失风险的客户数
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn at_risk_count(&self) -> Result<u64> {
+        self.connection.query_row(
+            "SELECT COUNT(*) FROM customer_summary s
+             JOIN customers c ON c.id = s.customer_id
+             WHERE s.churn_risk = 1 AND c.deleted_at IS NULL",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// 历史订单总额最高的流失风险客户
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn top_at_risk(&self, limit: usize) -> Result<Vec<AtRiskCustomer>> {
+        let sql = format!(
+            "SELECT c.id, c.name, s.activity_score,
+                COALESCE((SELECT CAST(SUM(ROUND(o.total_amount * {rate})) AS INTEGER)
+                          FROM orders o
+                          WHERE o.customer_id = c.id AND o.deleted_at IS NULL), 0) AS value
+             FROM customer_summary s
+             JOIN customers c ON c.id = s.customer_id
+             WHERE s.churn_risk = 1 AND c.deleted_at IS NULL
+             ORDER BY value DESC, c.name
+             LIMIT ?1",
+            rate = rate_sql("o")
+        );
+        self.connection
+            .query_map(
+                &sql,
+                [i64::try_from(limit).unwrap_or(i64::MAX)],
+                |row| {
+                    Ok(AtRiskCustomer {
+                        customer_id: get_uuid(row, 0)?,
+                        name: row.get(1)?,
+                        score: row.get(2)?,
+                        lifetime_value: Money::from_cents(row.get(3)?),
+                    })
+                },
+            )
+            .context("无法读取流失风险客户")
+    }
+}
+
+impl EventHandler for ActivityScoreStore {
+    fn name(&self) -> &str {
+        "activity_score"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        let now = self.clock.now();
+        self.connection.with_transaction(|tx| {
+            if let Some(customer_id) = affected_customer(tx, &envelope.event)? {
+                score_customer(tx, &self.scorer, customer_id, now)?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+/// 活跃度评分任务（每周运行）
+#[derive(Debug, Clone)]
+pub struct ActivityScoreJob {
+    store: ActivityScoreStore,
+    weekday: Weekday,
+    at: NaiveTime,
+}
+
+impl ActivityScoreJob {
+    /// 创建评分任务（默认每周一 04:00）
+    pub fn new(store: ActivityScoreStore) -> Self {
+        Self {
+            store,
+            weekday: Weekday::Mon,
+            at: NaiveTime::from_hms_opt(4, 0, 0).unwrap_or_default(),
+        }
+    }
+
+    /// 设置每周运行时间
+    pub fn run_at(mut self, weekday: Weekday, at: NaiveTime) -> Self {
+        self.weekday = weekday;
+        self.at = at;
+        self
+    }
+}
+
+#[async_trait]
+impl Job for ActivityScoreJob {
+    fn name(&self) -> &str {
+        "customer_activity_score"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::weekly(self.weekday, self.at)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let result = self.store.refresh_all()?;
+        info!(
+            "客户活跃度评分完成: {} 个客户, {} 个分数变化, {} 个有流失风险",
+            result.scored, result.changed, result.at_risk
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    struct Fixture {
+        _dir: TempDir,
+        connection: DatabaseConnection,
+        clock: Arc<ManualClock>,
+        store: ActivityScoreStore,
+    }
+
+    fn fixture() -> Fixture {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 12, 31, 9, 0, 0).unwrap(),
+        ));
+        Fixture {
+            _dir: temp_dir,
+            store: ActivityScoreStore::new(connection.clone()).with_clock(clock.clone()),
+            connection,
+            clock,
+        }
+    }
+
+    fn insert_customer(fixture: &Fixture, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DbUuid(id), name, "2023-01-01T00:00:00.000000Z"],
+            )
+            .unwrap();
+        id
+    }
+
+    /// 在 `at` 写入一次互动、一份报价和一笔收款
+    fn activity(fixture: &Fixture, customer: Uuid, at: DateTime<Utc>, amount: i64) {
+        let at = time_key(at);
+        let order = Uuid::new_v4();
+        for sql in [
+            "INSERT INTO interactions (id, customer_id, kind, content, occurred_at)
+             VALUES (?1, ?2, 'call', '回访', ?3)",
+            "INSERT INTO quotes (id, customer_id, title, total_amount, status, created_at,
+                                 updated_at)
+             VALUES (?1, ?2, '板材报价', 100.0, 'sent', ?3, ?3)",
+        ] {
+            fixture
+                .connection
+                .execute(sql, params![DbUuid(Uuid::new_v4()), DbUuid(customer), at])
+                .unwrap();
+        }
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO orders (id, order_number, customer_id, total_amount, created_at,
+                                     updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+                params![DbUuid(order), order.to_string(), DbUuid(customer), amount, at],
+            )
+            .unwrap();
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO order_payments (id, order_id, amount, paid_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![DbUuid(Uuid::new_v4()), DbUuid(order), amount, at],
+            )
+            .unwrap();
+    }
+
+    /// 近12个月每30天活动一次，跳过最近的 `skip` 次
+    fn monthly(fixture: &Fixture, customer: Uuid, skip: i64, amount: i64) {
+        let now = fixture.clock.now();
+        for k in skip..12 {
+            activity(fixture, customer, now - Duration::days(30 * k), amount);
+        }
+    }
+
+    fn summary(fixture: &Fixture, customer: Uuid) -> (u8, bool) {
+        fixture
+            .connection
+            .query_row(
+                "SELECT activity_score, churn_risk FROM customer_summary WHERE customer_id = ?1",
+                [DbUuid(customer)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_scores_for_activity_patterns() {
+        let fixture = fixture();
+        let steady = insert_customer(&fixture, "稳定客户");
+        monthly(&fixture, steady, 0, 100_000);
+        let stopped = insert_customer(&fixture, "停单客户");
+        monthly(&fixture, stopped, 4, 100_000);
+        let brand_new = insert_customer(&fixture, "新客户");
+        let now = fixture.clock.now();
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO interactions (id, customer_id, kind, content, occurred_at)
+                 VALUES (?1, ?2, 'call', '初次拜访', ?3)",
+                params![
+                    DbUuid(Uuid::new_v4()),
+                    DbUuid(brand_new),
+                    time_key(now - Duration::days(3))
+                ],
+            )
+            .unwrap();
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO quotes (id, customer_id, title, total_amount, status, created_at,
+                                     updated_at)
+                 VALUES (?1, ?2, '首单报价', 100.0, 'sent', ?3, ?3)",
+                params![
+                    DbUuid(Uuid::new_v4()),
+                    DbUuid(brand_new),
+                    time_key(now - Duration::days(1))
+                ],
+            )
+            .unwrap();
+
+        let result = fixture.store.refresh_all().unwrap();
+        assert_eq!(result.scored, 3);
+        assert_eq!(result.changed, 3);
+        assert_eq!(summary(&fixture, steady), (100, false));
+        // 没有更早的高分记录，分数低也不标记
+        assert_eq!(summary(&fixture, stopped), (47, false));
+        assert_eq!(summary(&fixture, brand_new), (44, false));
+
+        // 分数不变时不再写入变化记录
+        let again = fixture.store.refresh_all().unwrap();
+        assert_eq!(again.changed, 0);
+        let rows: i64 = fixture
+            .connection
+            .query_row("SELECT COUNT(*) FROM customer_activity_scores", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rows, 3);
+    }
+
+    #[test]
+    fn test_churn_flag_appears_and_clears_after_new_activity() {
+        let fixture = fixture();
+        let big = insert_customer(&fixture, "华美家具");
+        monthly(&fixture, big, 0, 500_000);
+        let small = insert_customer(&fixture, "木立方");
+        monthly(&fixture, small, 0, 100_000);
+        fixture.store.refresh_all().unwrap();
+        assert_eq!(summary(&fixture, big), (100, false));
+
+        // 5个月没有任何活动
+        fixture.clock.advance(Duration::days(150));
+        let result = fixture.store.refresh_all().unwrap();
+        assert_eq!(result.at_risk, 2);
+        let (score, at_risk) = summary(&fixture, big);
+        assert!(score < 50, "{score}");
+        assert!(at_risk);
+        assert_eq!(fixture.store.at_risk_count().unwrap(), 2);
+        let top = fixture.store.top_at_risk(1).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].name, "华美家具");
+        assert_eq!(top[0].lifetime_value, Money::from_cents(6_000_000));
+
+        // 恢复下单：收到事件后重新评分，标记清除
+        let now = fixture.clock.now();
+        for k in 0..3 {
+            activity(&fixture, big, now - Duration::days(10 * k), 500_000);
+        }
+        fixture
+            .store
+            .handle(&EventEnvelope::new(DomainEvent::InteractionLogged {
+                interaction_id: Uuid::new_v4(),
+                customer_id: big,
+            }))
+            .unwrap();
+        let (score, at_risk) = summary(&fixture, big);
+        assert!(score >= 50, "{score}");
+        assert!(!at_risk);
+        assert_eq!(fixture.store.at_risk_count().unwrap(), 1);
+        assert_eq!(fixture.store.top_at_risk(5).unwrap()[0].name, "木立方");
+    }
+}
@@ -0,0 +1,118 @@
+//! 客户纪念日存储
+//!
+//! 从客户表读取联系人生日，从报价表按客户取最早一份已接受报价的日期作为首次成交日，
+//! 换算为业务时区的本地日期后交给 [`minicrm_core::anniversary`] 计算下一次日期。
+//! 联系人保存在客户表的 `company` 列。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use minicrm_core::{
+    AnniversaryKind, AnniversaryService, BusinessCalendar, CoreResult, CustomerAnniversary,
+};
+use rusqlite::Row;
+use uuid::Uuid;
+
+use crate::database::db_uuid::{get_optional_uuid, get_uuid};
+use crate::database::DatabaseConnection;
+
+/// 客户纪念日存储
+#[derive(Debug, Clone)]
+pub struct AnniversaryStore {
+    connection: DatabaseConnection,
+    calendar: BusinessCalendar,
+}
+
+/// 一个客户的联系信息、生日和首次成交时间
+struct CustomerDates {
+    customer_id: Uuid,
+    customer_name: String,
+    contact_person: Option<String>,
+    phone: Option<String>,
+    owner_id: Option<Uuid>,
+    birthday: Option<NaiveDate>,
+    first_deal_at: Option<String>,
+}
+
+impl CustomerDates {
+    fn anniversary(&self, kind: AnniversaryKind, date: NaiveDate) -> CustomerAnniversary {
+        CustomerAnniversary {
+            customer_id: self.customer_id,
+            customer_name: self.customer_name.clone(),
+            contact_person: self.contact_person.clone(),
+            phone: self.phone.clone(),
+            owner_id: self.owner_id,
+            kind,
+            date,
+        }
+    }
+}
+
+fn row_to_dates(row: &Row<'_>) -> rusqlite::Result<CustomerDates> {
+    Ok(CustomerDates {
+        customer_id: get_uuid(row, 0)?,
+        customer_name: row.get(1)?,
+        contact_person: row.get(2)?,
+        phone: row.get(3)?,
+        owner_id: get_optional_uuid(row, 4)?,
+        birthday: row.get(5)?,
+        first_deal_at: row.get(6)?,
+    })
+}
+
+impl AnniversaryStore {
+    /// 创建纪念日存储（默认业务时区）
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            calendar: BusinessCalendar::default(),
+        }
+    }
+
+    /// 设置业务日历，首次成交日按其本地日期计算
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 未删除客户的全部纪念日（生日在前，首次成交在后）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn all(&self) -> Result<Vec<CustomerAnniversary>> {
+        let rows = self.connection.query_map(
+            "SELECT c.id, c.name, c.company, c.phone, c.owner_id, c.contact_birthday,
+                (SELECT MIN(q.updated_at) FROM quotes q
+                 WHERE q.customer_id = c.id AND q.deleted_at IS NULL
+                   AND lower(q.status) = 'accepted')
+             FROM customers c
+             WHERE c.deleted_at IS NULL
+             ORDER BY c.name",
+            [],
+            row_to_dates,
+        )?;
+        let mut anniversaries = Vec::new();
+        for row in rows {
+            if let Some(birthday) = row.birthday {
+                anniversaries.push(row.anniversary(AnniversaryKind::Birthday, birthday));
+            }
+            let first_deal = row
+                .first_deal_at
+                .as_deref()
+                .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                .map(|at| self.calendar.local_date(at.with_timezone(&Utc)));
+            if let Some(first_deal) = first_deal {
+                anniversaries.push(row.anniversary(AnniversaryKind::FirstDeal, first_deal.naive()));
+            }
+        }
+        Ok(anniversaries)
+    }
+}
+
+#[async_trait]
+impl AnniversaryService for AnniversaryStore {
+    async fn customer_anniversaries(&self) -> CoreResult<Vec<CustomerAnniversary>> {
+        Ok(self.all()?)
+    }
+}
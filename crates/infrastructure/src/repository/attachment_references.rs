@@ -0,0 +1,502 @@
+//! 附件引用登记
+//!
+//! 生成报价PDF时嵌入的图片、发送邮件时带的附件、报价修订快照中记录的附件，都按
+//! （附件哈希, 来源, 所属记录）登记到 `attachment_references`。删除附件分两步：
+//!
+//! - 没有引用时直接删除附件文件（及缩略图）
+//! - 仍有引用时只在 `attachment_tombstones` 中标记为已删除，附件文件保留，图库在“历史”
+//!   筛选下以灰色显示“已删除但被历史记录引用”
+//!
+//! 所属记录被彻底删除（[`DomainEvent::EntityPurged`]）时释放它的引用。存储清理任务把
+//! 引用表计入有效引用，引用全部释放后，已删除的附件成为孤儿附件，由清理任务删除文件并
+//! 清除删除标记。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Clock, CoreResult, DomainEvent, EntityKind, EventEnvelope, EventHandler, SystemClock,
+};
+use rusqlite::{params, OptionalExtension, Row, Transaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::attachments::AttachmentStore;
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+/// 引用附件的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttachmentReferenceSource {
+    /// 生成的报价PDF中嵌入的图片
+    QuotePdf,
+    /// 已发送邮件的附件
+    SentEmail,
+    /// 报价修订快照中记录的附件
+    QuoteRevision,
+}
+
+impl AttachmentReferenceSource {
+    /// 来源名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::QuotePdf => "quote_pdf",
+            Self::SentEmail => "sent_email",
+            Self::QuoteRevision => "quote_revision",
+        }
+    }
+
+    /// 从来源名称解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "quote_pdf" => Some(Self::QuotePdf),
+            "sent_email" => Some(Self::SentEmail),
+            "quote_revision" => Some(Self::QuoteRevision),
+            _ => None,
+        }
+    }
+}
+
+/// 一条附件引用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentReference {
+    /// 附件哈希
+    pub hash: String,
+    /// 来源
+    pub source: AttachmentReferenceSource,
+    /// 所属记录类型
+    pub entity: EntityKind,
+    /// 所属记录ID
+    pub entity_id: Uuid,
+    /// 登记时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 删除附件的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentDeletion {
+    /// 没有引用，附件文件已删除
+    Removed,
+    /// 仍被历史记录引用，只标记为已删除
+    Tombstoned {
+        /// 引用数
+        references: usize,
+    },
+}
+
+fn row_to_reference(row: &Row<'_>) -> rusqlite::Result<Option<AttachmentReference>> {
+    let source: String = row.get(1)?;
+    let entity: String = row.get(2)?;
+    let created_at: String = row.get(4)?;
+    // 无法识别的来源来自更新版本写入的数据，跳过
+    let (Some(source), Some(entity)) = (
+        AttachmentReferenceSource::parse(&source),
+        EntityKind::from_table_name(&entity),
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(AttachmentReference {
+        hash: row.get(0)?,
+        source,
+        entity,
+        entity_id: get_uuid(row, 3)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    4,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?,
+    }))
+}
+
+/// 附件引用存储
+#[derive(Clone)]
+pub struct AttachmentReferenceStore {
+    connection: DatabaseConnection,
+    attachments: AttachmentStore,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for AttachmentReferenceStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachmentReferenceStore")
+            .field("attachments", &self.attachments.root())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AttachmentReferenceStore {
+    /// 创建附件引用存储
+    pub fn new(connection: DatabaseConnection, attachments: AttachmentStore) -> Self {
+        Self {
+            connection,
+            attachments,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中固定当前时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 在调用方的事务中登记引用，已登记的忽略，返回新增的引用数
+    ///
+    /// 生成PDF、发送邮件、保存修订快照时与所属记录的写入放在同一事务中调用。
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn record(
+        tx: &Transaction<'_>,
+        source: AttachmentReferenceSource,
+        entity: EntityKind,
+        entity_id: Uuid,
+        hashes: &[String],
+        at: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut added = 0;
+        for hash in hashes {
+            added += tx.execute(
+                "INSERT OR IGNORE INTO attachment_references
+                     (hash, source, entity_type, entity_id, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    hash,
+                    source.as_str(),
+                    entity.table_name(),
+                    DbUuid(entity_id),
+                    time_key(at)
+                ],
+            )?;
+        }
+        Ok(added)
+    }
+
+    /// 登记引用（单独的事务），返回新增的引用数
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn add_references(
+        &self,
+        source: AttachmentReferenceSource,
+        entity: EntityKind,
+        entity_id: Uuid,
+        hashes: &[String],
+    ) -> Result<usize> {
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| Self::record(tx, source, entity, entity_id, hashes, now))
+    }
+
+    /// 在调用方的事务中释放记录的全部引用，返回释放的引用数
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn release(tx: &Transaction<'_>, entity: EntityKind, entity_id: Uuid) -> Result<usize> {
+        Ok(tx.execute(
+            "DELETE FROM attachment_references WHERE entity_type = ?1 AND entity_id = ?2",
+            params![entity.table_name(), DbUuid(entity_id)],
+        )?)
+    }
+
+    /// 附件的引用（按登记时间）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn references(&self, hash: &str) -> Result<Vec<AttachmentReference>> {
+        Ok(self
+            .connection
+            .query_map(
+                "SELECT hash, source, entity_type, entity_id, created_at
+                 FROM attachment_references WHERE hash = ?1 ORDER BY created_at, rowid",
+                [hash],
+                row_to_reference,
+            )?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// 删除附件：没有引用时删除文件，仍有引用时只标记为已删除
+    ///
+    /// # Errors
+    ///
+    /// 数据库读写或删除文件失败时返回错误。
+    pub fn delete_attachment(
+        &self,
+        hash: &str,
+        deleted_by: Option<&str>,
+    ) -> Result<AttachmentDeletion> {
+        let now = self.clock.now();
+        let references = self.connection.with_transaction(|tx| {
+            let references: i64 = tx.query_row(
+                "SELECT COUNT(*) FROM attachment_references WHERE hash = ?1",
+                [hash],
+                |row| row.get(0),
+            )?;
+            if references == 0 {
+                tx.execute("DELETE FROM attachment_tombstones WHERE hash = ?1", [hash])?;
+            } else {
+                tx.execute(
+                    "INSERT INTO attachment_tombstones (hash, deleted_at, deleted_by)
+                     VALUES (?1, ?2, ?3)
+                     ON CONFLICT(hash) DO NOTHING",
+                    params![hash, time_key(now), deleted_by],
+                )?;
+            }
+            Ok(usize::try_from(references).unwrap_or_default())
+        })?;
+        if references > 0 {
+            info!("附件 {} 仍有 {} 处引用，标记为已删除", hash, references);
+            return Ok(AttachmentDeletion::Tombstoned { references });
+        }
+        self.attachments.delete(hash)?;
+        info!("附件 {} 已删除", hash);
+        Ok(AttachmentDeletion::Removed)
+    }
+
+    /// 附件是否已标记为删除
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn is_tombstoned(&self, hash: &str) -> Result<bool> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT 1 FROM attachment_tombstones WHERE hash = ?1",
+                [hash],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// 全部已标记为删除的附件哈希（图库据此把附件显示为灰色）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn tombstoned(&self) -> Result<HashSet<String>> {
+        Ok(self
+            .connection
+            .query_map("SELECT hash FROM attachment_tombstones", [], |row| {
+                row.get(0)
+            })?
+            .into_iter()
+            .collect())
+    }
+}
+
+impl EventHandler for AttachmentReferenceStore {
+    fn name(&self) -> &str {
+        "attachment_references"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        if let DomainEvent::EntityPurged { entity, id, .. } = &envelope.event {
+            let released = self
+                .connection
+                .with_transaction(|tx| Self::release(tx, *entity, *id))
+                .map_err(to_core)?;
+            if released > 0 {
+                info!(
+                    "{} {} 已彻底删除，释放 {} 处附件引用",
+                    entity.table_name(),
+                    id,
+                    released
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage_gc::StorageGc;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, AttachmentReferenceStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let attachments = AttachmentStore::new(temp_dir.path().join("attachments"))
+            .with_open_dir(temp_dir.path().join("open"));
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap(),
+        ));
+        let store =
+            AttachmentReferenceStore::new(connection.clone(), attachments).with_clock(clock);
+        (temp_dir, connection, store)
+    }
+
+    fn purged(entity: EntityKind, id: Uuid) -> EventEnvelope {
+        EventEnvelope::new(DomainEvent::EntityPurged {
+            entity,
+            id,
+            was_soft_deleted: true,
+        })
+    }
+
+    #[test]
+    fn test_references_from_each_source() {
+        let (_dir, _connection, store) = create_test_store();
+        let quote_id = Uuid::new_v4();
+        let ticket_id = Uuid::new_v4();
+        let photo = store.attachments.put(b"cabinet photo").unwrap();
+        let drawing = store.attachments.put(b"drawing").unwrap();
+
+        let pdf = [photo.clone(), drawing.clone()];
+        assert_eq!(
+            store
+                .add_references(
+                    AttachmentReferenceSource::QuotePdf,
+                    EntityKind::Quote,
+                    quote_id,
+                    &pdf
+                )
+                .unwrap(),
+            2
+        );
+        // 重新生成同一份PDF不重复登记
+        assert_eq!(
+            store
+                .add_references(
+                    AttachmentReferenceSource::QuotePdf,
+                    EntityKind::Quote,
+                    quote_id,
+                    &pdf
+                )
+                .unwrap(),
+            0
+        );
+        store
+            .add_references(
+                AttachmentReferenceSource::SentEmail,
+                EntityKind::ServiceTicket,
+                ticket_id,
+                std::slice::from_ref(&photo),
+            )
+            .unwrap();
+        store
+            .add_references(
+                AttachmentReferenceSource::QuoteRevision,
+                EntityKind::Quote,
+                quote_id,
+                std::slice::from_ref(&photo),
+            )
+            .unwrap();
+
+        let sources: Vec<_> = store
+            .references(&photo)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.source, r.entity, r.entity_id))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                (
+                    AttachmentReferenceSource::QuotePdf,
+                    EntityKind::Quote,
+                    quote_id
+                ),
+                (
+                    AttachmentReferenceSource::SentEmail,
+                    EntityKind::ServiceTicket,
+                    ticket_id
+                ),
+                (
+                    AttachmentReferenceSource::QuoteRevision,
+                    EntityKind::Quote,
+                    quote_id
+                ),
+            ]
+        );
+        assert_eq!(store.references(&drawing).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_unreferenced_attachment_removed_at_once() {
+        let (_dir, _connection, store) = create_test_store();
+        let hash = store.attachments.put(b"unused scan").unwrap();
+        assert_eq!(
+            store.delete_attachment(&hash, Some("zhangsan")).unwrap(),
+            AttachmentDeletion::Removed
+        );
+        assert!(!store.attachments.blob_path(&hash).unwrap().exists());
+        assert!(!store.is_tombstoned(&hash).unwrap());
+    }
+
+    #[test]
+    fn test_tombstone_until_references_purged() {
+        let (_dir, connection, store) = create_test_store();
+        let quote_id = Uuid::new_v4();
+        let ticket_id = Uuid::new_v4();
+        let photo = store.attachments.put(b"cabinet photo").unwrap();
+        store
+            .add_references(
+                AttachmentReferenceSource::QuotePdf,
+                EntityKind::Quote,
+                quote_id,
+                std::slice::from_ref(&photo),
+            )
+            .unwrap();
+        store
+            .add_references(
+                AttachmentReferenceSource::SentEmail,
+                EntityKind::ServiceTicket,
+                ticket_id,
+                std::slice::from_ref(&photo),
+            )
+            .unwrap();
+
+        assert_eq!(
+            store.delete_attachment(&photo, Some("zhangsan")).unwrap(),
+            AttachmentDeletion::Tombstoned { references: 2 }
+        );
+        assert!(store.is_tombstoned(&photo).unwrap());
+        assert_eq!(store.tombstoned().unwrap(), HashSet::from([photo.clone()]));
+        let blob = store.attachments.blob_path(&photo).unwrap();
+        assert!(blob.exists());
+
+        // 附件早已超过宽限期，引用未全部释放前清理任务不删除
+        let gc_clock = Arc::new(ManualClock::new(Utc::now() + Duration::days(3)));
+        let gc = StorageGc::new(connection, store.attachments.clone()).with_clock(gc_clock);
+        store.handle(&purged(EntityKind::Quote, quote_id)).unwrap();
+        assert_eq!(gc.collect(false).unwrap().run.orphan_blobs, 0);
+        assert!(blob.exists());
+        assert!(store.is_tombstoned(&photo).unwrap());
+
+        // 其他记录的彻底删除不影响
+        store
+            .handle(&purged(EntityKind::Quote, Uuid::new_v4()))
+            .unwrap();
+        assert_eq!(store.references(&photo).unwrap().len(), 1);
+
+        store
+            .handle(&purged(EntityKind::ServiceTicket, ticket_id))
+            .unwrap();
+        assert!(store.references(&photo).unwrap().is_empty());
+        assert_eq!(gc.collect(false).unwrap().run.orphan_blobs, 1);
+        assert!(!blob.exists());
+        assert!(!store.is_tombstoned(&photo).unwrap());
+    }
+}
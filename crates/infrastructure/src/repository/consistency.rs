@@ -0,0 +1,448 @@
+//! 内置数据一致性检查
+//!
+//! 各项检查只读取数据，不做校正；汇总表和计数缓存的偏差由各自的每日对账任务校正，
+//! 这里报告为警告。其余检查发现的是数据损坏，报告为错误，需要人工处理。
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use minicrm_core::{
+    ConsistencyCheck, ConsistencySeverity, ConsistencyViolation, CoreResult, EntityKind,
+};
+
+use crate::database::{db_uuid, DatabaseConnection};
+use crate::repository::{CustomerSummaryStore, TableCounterStore};
+
+/// 最新修订版本的条件（`alias` 为 `quote_revisions` 的别名）
+fn latest_revision(alias: &str) -> String {
+    format!(
+        "{a}.revision_no = (SELECT MAX(revision_no) FROM quote_revisions
+                             WHERE quote_id = {a}.quote_id)",
+        a = alias
+    )
+}
+
+/// 报价总额与 `quote_items` 中小计（以分保存）的合计不一致的报价（没有明细的报价不检查）
+fn quote_totals_sql() -> String {
+    format!(
+        "SELECT q.id,
+                printf('报价 %s 总额 %.2f 与明细合计 %.2f 不一致',
+                       COALESCE((SELECT json_extract(r.snapshot, '$.quote_number')
+                                 FROM quote_revisions r
+                                 WHERE r.quote_id = q.id AND {latest}), q.title),
+                       q.total_amount, SUM(i.line_total) / 100.0)
+         FROM quotes q
+         JOIN quote_items i ON i.quote_id = q.id
+         WHERE q.deleted_at IS NULL
+         GROUP BY q.id
+         HAVING ROUND(q.total_amount * 100) <> SUM(i.line_total)
+         ORDER BY q.id
+         LIMIT ?1",
+        latest = latest_revision("r"),
+    )
+}
+
+const PAYMENTS_WITHIN_TOTAL_SQL: &str = "
+    SELECT o.id,
+           printf('订单 %s 已收款 %.2f 超过订单总额 %.2f',
+                  o.order_number, SUM(p.amount) / 100.0, o.total_amount / 100.0)
+    FROM orders o
+    JOIN order_payments p ON p.order_id = o.id
+    WHERE o.deleted_at IS NULL
+    GROUP BY o.id
+    HAVING SUM(p.amount) > o.total_amount
+    ORDER BY o.id
+    LIMIT ?1";
+
+const ORDER_QUOTES_SQL: &str = "
+    SELECT o.id || COALESCE(',' || q.id, ''),
+           CASE WHEN q.id IS NULL THEN printf('订单 %s 引用的报价不存在', o.order_number)
+                ELSE printf('订单 %s 引用的报价状态为 %s，不是已接受', o.order_number, q.status)
+           END
+    FROM orders o
+    LEFT JOIN quotes q ON q.id = o.quote_id
+    WHERE o.deleted_at IS NULL AND o.quote_id IS NOT NULL
+      AND (q.id IS NULL OR lower(q.status) <> 'accepted')
+    ORDER BY o.id
+    LIMIT ?1";
+
+fn document_numbers_sql() -> String {
+    format!(
+        "SELECT group_concat(id), printf('%s编号 %s 被 %d 条记录使用', kind, number, COUNT(*))
+         FROM (
+             SELECT r.quote_id AS id, '报价' AS kind,
+                    json_extract(r.snapshot, '$.quote_number') AS number
+             FROM quote_revisions r WHERE {latest}
+             UNION ALL
+             SELECT id, '订单', order_number FROM orders
+             UNION ALL
+             SELECT id, '订单', order_number FROM archived_orders
+         )
+         WHERE number IS NOT NULL AND number <> ''
+         GROUP BY kind, number
+         HAVING COUNT(*) > 1
+         ORDER BY kind, number
+         LIMIT ?1",
+        latest = latest_revision("r"),
+    )
+}
+
+/// 以SQL查询实现的检查
+///
+/// 查询的第一列为逗号分隔的相关记录ID，第二列为问题说明，`?1` 为数量上限。
+struct SqlCheck {
+    connection: DatabaseConnection,
+    name: &'static str,
+    description: &'static str,
+    severity: ConsistencySeverity,
+    sql: String,
+}
+
+#[async_trait]
+impl ConsistencyCheck for SqlCheck {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn severity(&self) -> ConsistencySeverity {
+        self.severity
+    }
+
+    async fn run(&self, limit: usize) -> CoreResult<Vec<ConsistencyViolation>> {
+        let violations = self
+            .connection
+            .query_map(
+                &self.sql,
+                [i64::try_from(limit).unwrap_or(i64::MAX)],
+                |row| {
+                    let ids: Option<String> = row.get(0)?;
+                    Ok(ConsistencyViolation {
+                        ids: ids
+                            .unwrap_or_default()
+                            .split(',')
+                            .filter_map(db_uuid::parse)
+                            .collect(),
+                        description: row.get(1)?,
+                    })
+                },
+            )
+            .with_context(|| format!("一致性检查 {} 执行失败", self.name))?;
+        Ok(violations)
+    }
+}
+
+/// 客户汇总与源表一致
+struct CustomerSummaryCheck {
+    store: CustomerSummaryStore,
+}
+
+#[async_trait]
+impl ConsistencyCheck for CustomerSummaryCheck {
+    fn name(&self) -> &str {
+        "customer_summary"
+    }
+
+    fn description(&self) -> &str {
+        "客户汇总与按源表计算的结果一致"
+    }
+
+    fn severity(&self) -> ConsistencySeverity {
+        ConsistencySeverity::Warning
+    }
+
+    async fn run(&self, limit: usize) -> CoreResult<Vec<ConsistencyViolation>> {
+        Ok(self
+            .store
+            .drift()?
+            .drifted
+            .into_iter()
+            .take(limit)
+            .map(|drift| ConsistencyViolation {
+                ids: vec![drift.actual.customer_id],
+                description: match drift.stored {
+                    Some(_) => "客户汇总与源表不一致，等待每日对账校正".to_string(),
+                    None => "客户缺少汇总记录，等待每日对账补齐".to_string(),
+                },
+            })
+            .collect())
+    }
+}
+
+/// 表计数缓存与真实记录数一致
+struct TableCounterCheck {
+    connection: DatabaseConnection,
+    store: TableCounterStore,
+}
+
+#[async_trait]
+impl ConsistencyCheck for TableCounterCheck {
+    fn name(&self) -> &str {
+        "table_counters"
+    }
+
+    fn description(&self) -> &str {
+        "表计数缓存与未删除记录数一致"
+    }
+
+    fn severity(&self) -> ConsistencySeverity {
+        ConsistencySeverity::Warning
+    }
+
+    async fn run(&self, limit: usize) -> CoreResult<Vec<ConsistencyViolation>> {
+        // 只检查已登记且存在的表
+        let tables = self.connection.query_map(
+            "SELECT c.table_name FROM table_counters c
+             JOIN sqlite_master m ON m.type = 'table' AND m.name = c.table_name
+             ORDER BY c.table_name",
+            [],
+            |row| row.get::<_, String>(0),
+        )?;
+
+        let mut violations = Vec::new();
+        for kind in tables.iter().filter_map(|t| EntityKind::from_table_name(t)) {
+            if violations.len() >= limit {
+                break;
+            }
+            let result = self.store.check(kind)?;
+            if result.drifted() {
+                violations.push(ConsistencyViolation {
+                    ids: Vec::new(),
+                    description: format!(
+                        "{} 计数缓存为 {}，实际 {} 条",
+                        result.table_name, result.cached_before, result.actual
+                    ),
+                });
+            }
+        }
+        Ok(violations)
+    }
+}
+
+/// 内置的一致性检查（按执行顺序）
+///
+/// - `quote_totals`：报价总额等于报价明细的小计合计
+/// - `payments_within_total`：订单收款合计不超过订单总额
+/// - `orders_reference_accepted_quotes`：订单引用的报价存在且已接受
+/// - `document_numbers_unique`：报价编号、订单编号（含归档订单）没有重复
+/// - `customer_summary`：客户汇总与源表一致（警告）
+/// - `table_counters`：表计数缓存与真实记录数一致（警告）
+pub fn builtin_consistency_checks(
+    connection: DatabaseConnection,
+) -> Vec<Arc<dyn ConsistencyCheck + Send + Sync>> {
+    let sql = |name, description, sql: String| -> Arc<dyn ConsistencyCheck + Send + Sync> {
+        Arc::new(SqlCheck {
+            connection: connection.clone(),
+            name,
+            description,
+            severity: ConsistencySeverity::Error,
+            sql,
+        })
+    };
+    vec![
+        sql("quote_totals", "报价总额等于明细合计", quote_totals_sql()),
+        sql(
+            "payments_within_total",
+            "订单收款合计不超过订单总额",
+            PAYMENTS_WITHIN_TOTAL_SQL.to_string(),
+        ),
+        sql(
+            "orders_reference_accepted_quotes",
+            "订单引用的报价存在且已接受",
+            ORDER_QUOTES_SQL.to_string(),
+        ),
+        sql(
+            "document_numbers_unique",
+            "报价编号和订单编号没有重复",
+            document_numbers_sql(),
+        ),
+        Arc::new(CustomerSummaryCheck {
+            store: CustomerSummaryStore::new(connection.clone()),
+        }),
+        Arc::new(TableCounterCheck {
+            store: TableCounterStore::new(connection.clone()),
+            connection,
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbUuid;
+    use crate::test_support::migrated_pool;
+    use chrono::{SecondsFormat, Utc};
+    use rusqlite::params;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    /// 各项检查均通过的数据库：一个客户、一张已接受的报价和由它生成的部分收款订单
+    fn create_fixture() -> (TempDir, DatabaseConnection) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+        let (customer, quote, order) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, '测试客户', ?2, ?2)",
+                params![DbUuid(customer), now],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO quotes
+                     (id, customer_id, title, total_amount, status, created_at, updated_at)
+                 VALUES (?1, ?2, '板材报价', 333.0, 'accepted', ?3, ?3)",
+                params![DbUuid(quote), DbUuid(customer), now],
+            )
+            .unwrap();
+        let snapshot = serde_json::json!({
+            "id": quote,
+            "quote_number": "Q-2024-0001",
+            "total_amount": 333.0,
+            "items": [
+                { "quantity": 2.0, "unit_price": 150.5 },
+                { "quantity": 10.0, "unit_price": 3.2 },
+            ],
+        });
+        connection
+            .execute(
+                "INSERT INTO quote_revisions (quote_id, revision_no, snapshot, saved_at)
+                 VALUES (?1, 1, ?2, ?3)",
+                params![DbUuid(quote), snapshot.to_string(), now],
+            )
+            .unwrap();
+        for (line_no, (quantity, unit_price, line_total)) in
+            [(2.0, 150.5, 30_100), (10.0, 3.2, 3_200)].into_iter().enumerate()
+        {
+            connection
+                .execute(
+                    "INSERT INTO quote_items (id, quote_id, line_no, product_name, quantity,
+                                              unit_price, line_total)
+                     VALUES (?1, ?2, ?3, '生态板', ?4, ?5, ?6)",
+                    params![
+                        DbUuid(Uuid::new_v4()),
+                        DbUuid(quote),
+                        line_no as i64,
+                        quantity,
+                        unit_price,
+                        line_total
+                    ],
+                )
+                .unwrap();
+        }
+        connection
+            .execute(
+                "INSERT INTO orders
+                     (id, order_number, customer_id, quote_id, total_amount, created_at, updated_at)
+                 VALUES (?1, 'SO-0001', ?2, ?3, 33300, ?4, ?4)",
+                params![DbUuid(order), DbUuid(customer), DbUuid(quote), now],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO order_payments (id, order_id, amount, paid_at)
+                 VALUES (?1, ?2, 10000, ?3)",
+                params![DbUuid(Uuid::new_v4()), DbUuid(order), now],
+            )
+            .unwrap();
+
+        CustomerSummaryStore::new(connection.clone())
+            .reconcile()
+            .unwrap();
+        let counters = TableCounterStore::new(connection.clone());
+        for kind in [EntityKind::Customer, EntityKind::Task, EntityKind::Quote] {
+            counters.reconcile(kind).unwrap();
+        }
+        (temp_dir, connection)
+    }
+
+    /// 发现问题的检查及问题数
+    async fn failing(connection: &DatabaseConnection) -> Vec<(String, usize)> {
+        let mut failing = Vec::new();
+        for check in builtin_consistency_checks(connection.clone()) {
+            let violations = check.run(10).await.unwrap();
+            if !violations.is_empty() {
+                failing.push((check.name().to_string(), violations.len()));
+            }
+        }
+        failing
+    }
+
+    #[tokio::test]
+    async fn test_each_corruption_is_reported_by_its_check() {
+        let (_dir, connection) = create_fixture();
+        assert!(failing(&connection).await.is_empty());
+
+        let archived = Uuid::new_v4();
+        let cases = [
+            (
+                "quote_totals",
+                "UPDATE quotes SET total_amount = 334.0".to_string(),
+            ),
+            (
+                "payments_within_total",
+                "UPDATE order_payments SET amount = 40000".to_string(),
+            ),
+            (
+                "orders_reference_accepted_quotes",
+                "UPDATE quotes SET status = 'sent'".to_string(),
+            ),
+            (
+                "document_numbers_unique",
+                format!(
+                    "INSERT INTO archived_orders
+                         (id, order_number, customer_id, total_amount, created_at, updated_at)
+                     SELECT '{}', order_number, customer_id, total_amount, created_at, updated_at
+                     FROM orders",
+                    archived
+                ),
+            ),
+            (
+                "customer_summary",
+                "UPDATE customer_summary SET open_task_count = 3".to_string(),
+            ),
+            (
+                "table_counters",
+                "UPDATE table_counters SET row_count = 5 WHERE table_name = 'customers'"
+                    .to_string(),
+            ),
+            (
+                "quote_totals",
+                "UPDATE quote_items SET line_total = 30000 WHERE line_no = 0".to_string(),
+            ),
+        ];
+        for (check, corruption) in &cases {
+            let (_dir, connection) = create_fixture();
+            connection.execute(corruption, []).unwrap();
+            if *check != "customer_summary" {
+                // 汇总随源表变化属于正常偏差，先对账，只看被破坏的规则
+                CustomerSummaryStore::new(connection.clone())
+                    .reconcile()
+                    .unwrap();
+            }
+            assert_eq!(
+                failing(&connection).await,
+                vec![(check.to_string(), 1)],
+                "{}",
+                check
+            );
+        }
+
+        // 重复编号的问题列出所有相关记录
+        let (_dir, connection) = create_fixture();
+        let (_, corruption) = &cases[3];
+        connection.execute(corruption, []).unwrap();
+        let checks = builtin_consistency_checks(connection.clone());
+        let violations = checks[3].run(10).await.unwrap();
+        assert_eq!(violations[0].ids.len(), 2);
+        assert!(violations[0].ids.contains(&archived));
+        assert!(violations[0].description.contains("SO-0001"));
+    }
+}
@@ -0,0 +1,330 @@
+//! 表记录总数缓存
+//!
+//! 在 `table_counters` 表中按表维护未删除记录数，由领域事件增减，
+//! 并由每日对账任务用真实的 `COUNT(*)` 校正。
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{NaiveTime, Utc};
+use minicrm_core::{
+    CoreResult, DomainEvent, EntityKind, EventEnvelope, EventHandler, Job, JobSchedule,
+};
+use tracing::{info, warn};
+
+use crate::database::DatabaseConnection;
+
+/// 表记录总数存储
+#[derive(Debug, Clone)]
+pub struct TableCounterStore {
+    connection: DatabaseConnection,
+}
+
+/// 单表对账结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CounterReconciliation {
+    /// 表名
+    pub table_name: String,
+    /// 对账前的缓存值
+    pub cached_before: u64,
+    /// 真实记录数
+    pub actual: u64,
+}
+
+impl CounterReconciliation {
+    /// 缓存值是否存在偏差
+    pub fn drifted(&self) -> bool {
+        self.cached_before != self.actual
+    }
+}
+
+impl TableCounterStore {
+    /// 创建新的计数存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 读取表的缓存总数，未登记的表返回0
+    pub fn get(&self, table_name: &str) -> Result<u64> {
+        let count = self
+            .connection
+            .query_map(
+                "SELECT row_count FROM table_counters WHERE table_name = ?1",
+                [table_name],
+                |row| row.get::<_, i64>(0),
+            )?
+            .into_iter()
+            .next()
+            .unwrap_or(0);
+
+        Ok(u64::try_from(count).unwrap_or(0))
+    }
+
+    /// 调整表的缓存总数，结果不会小于0
+    pub fn adjust(&self, table_name: &str, delta: i64) -> Result<()> {
+        self.connection
+            .execute(
+                r#"
+                INSERT INTO table_counters (table_name, row_count, updated_at)
+                VALUES (?1, MAX(?2, 0), ?3)
+                ON CONFLICT(table_name) DO UPDATE SET
+                    row_count = MAX(row_count + ?2, 0),
+                    updated_at = ?3
+                "#,
+                rusqlite::params![table_name, delta, Utc::now().to_rfc3339()],
+            )
+            .with_context(|| format!("无法更新表计数: {}", table_name))?;
+        Ok(())
+    }
+
+    /// 对比缓存总数与真实的 `COUNT(*)`（不校正）
+    pub fn check(&self, kind: EntityKind) -> Result<CounterReconciliation> {
+        let table_name = kind.table_name();
+        let cached_before = self.get(table_name)?;
+
+        let actual: i64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE deleted_at IS NULL", table_name),
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(CounterReconciliation {
+            table_name: table_name.to_string(),
+            cached_before,
+            actual: u64::try_from(actual).unwrap_or(0),
+        })
+    }
+
+    /// 用真实的 `COUNT(*)` 校正单表计数
+    pub fn reconcile(&self, kind: EntityKind) -> Result<CounterReconciliation> {
+        let result = self.check(kind)?;
+        let table_name = kind.table_name();
+
+        let now = Utc::now().to_rfc3339();
+        self.connection.execute(
+            r#"
+            INSERT INTO table_counters (table_name, row_count, reconciled_at, updated_at)
+            VALUES (?1, ?2, ?3, ?3)
+            ON CONFLICT(table_name) DO UPDATE SET
+                row_count = ?2,
+                reconciled_at = ?3,
+                updated_at = ?3
+            "#,
+            rusqlite::params![
+                table_name,
+                i64::try_from(result.actual).unwrap_or(i64::MAX),
+                now
+            ],
+        )?;
+
+        if result.drifted() {
+            warn!(
+                "表计数存在偏差并已校正: {} 缓存={} 实际={}",
+                table_name, result.cached_before, result.actual
+            );
+        }
+
+        Ok(result)
+    }
+
+    fn delta_for(event: &DomainEvent) -> i64 {
+        match event {
+            DomainEvent::EntityCreated { .. } | DomainEvent::EntityRestored { .. } => 1,
+            DomainEvent::EntitySoftDeleted { .. } => -1,
+            // 回收站中的记录已在软删除时扣减
+            DomainEvent::EntityPurged {
+                was_soft_deleted, ..
+            } => {
+                if *was_soft_deleted {
+                    0
+                } else {
+                    -1
+                }
+            }
+            DomainEvent::EntityUpdated { .. }
+            | DomainEvent::QuoteStatusChanged { .. }
+            | DomainEvent::QuoteCreatedFromOpportunity { .. }
+            | DomainEvent::InteractionLogged { .. }
+            | DomainEvent::PaymentRecorded { .. }
+            | DomainEvent::TaskAssigned { .. }
+            | DomainEvent::TaskStatusChanged { .. }
+            | DomainEvent::TaskNoteAdded { .. } => 0,
+        }
+    }
+}
+
+impl EventHandler for TableCounterStore {
+    fn name(&self) -> &str {
+        "table_counters"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        let delta = Self::delta_for(&envelope.event);
+        if delta != 0 {
+            self.adjust(envelope.event.entity_kind().table_name(), delta)?;
+        }
+        Ok(())
+    }
+}
+
+/// 表计数对账任务（每日运行）
+#[derive(Debug, Clone)]
+pub struct CounterReconciliationJob {
+    store: TableCounterStore,
+    tables: Vec<EntityKind>,
+    run_at: NaiveTime,
+}
+
+impl CounterReconciliationJob {
+    /// 创建对账任务
+    pub fn new(store: TableCounterStore, tables: Vec<EntityKind>) -> Self {
+        Self {
+            store,
+            tables,
+            run_at: NaiveTime::from_hms_opt(3, 0, 0).unwrap_or_default(),
+        }
+    }
+
+    /// 设置每日运行时间
+    pub fn run_at(mut self, at: NaiveTime) -> Self {
+        self.run_at = at;
+        self
+    }
+
+    /// 对全部表执行对账
+    pub fn reconcile_all(&self) -> Result<Vec<CounterReconciliation>> {
+        self.tables
+            .iter()
+            .map(|kind| self.store.reconcile(*kind))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Job for CounterReconciliationJob {
+    fn name(&self) -> &str {
+        "table_counter_reconciliation"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::daily(self.run_at)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let results = self.reconcile_all()?;
+        let drifted = results.iter().filter(|r| r.drifted()).count();
+        info!("表计数对账完成: {} 张表, {} 张存在偏差", results.len(), drifted);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbUuid;
+    use crate::test_support::migrated_pool;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, TableCounterStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = TableCounterStore::new(connection.clone());
+        (temp_dir, connection, store)
+    }
+
+    fn insert_customer(connection: &DatabaseConnection, id: Uuid) {
+        let now = Utc::now().to_rfc3339();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                rusqlite::params![DbUuid(id), "测试客户", now],
+            )
+            .unwrap();
+    }
+
+    fn emit(store: &TableCounterStore, event: DomainEvent) {
+        store.handle(&EventEnvelope::new(event)).unwrap();
+    }
+
+    #[test]
+    fn test_counter_follows_lifecycle_events() {
+        let (_dir, _connection, store) = create_test_store();
+        let entity = EntityKind::Customer;
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        emit(&store, DomainEvent::EntityCreated { entity, id: a });
+        emit(&store, DomainEvent::EntityCreated { entity, id: b });
+        assert_eq!(store.get("customers").unwrap(), 2);
+
+        // 软删除后扣减，恢复后加回
+        emit(&store, DomainEvent::EntitySoftDeleted { entity, id: a });
+        assert_eq!(store.get("customers").unwrap(), 1);
+        emit(&store, DomainEvent::EntityRestored { entity, id: a });
+        assert_eq!(store.get("customers").unwrap(), 2);
+
+        // 彻底删除回收站中的记录不再重复扣减
+        emit(&store, DomainEvent::EntitySoftDeleted { entity, id: b });
+        emit(
+            &store,
+            DomainEvent::EntityPurged {
+                entity,
+                id: b,
+                was_soft_deleted: true,
+            },
+        );
+        assert_eq!(store.get("customers").unwrap(), 1);
+
+        // 直接彻底删除未软删除的记录需要扣减
+        emit(
+            &store,
+            DomainEvent::EntityPurged {
+                entity,
+                id: a,
+                was_soft_deleted: false,
+            },
+        );
+        assert_eq!(store.get("customers").unwrap(), 0);
+
+        // 计数不会变为负数
+        emit(&store, DomainEvent::EntitySoftDeleted { entity, id: a });
+        assert_eq!(store.get("customers").unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reconciliation_job_fixes_skewed_counter() {
+        let (_dir, connection, store) = create_test_store();
+
+        for _ in 0..3 {
+            insert_customer(&connection, Uuid::new_v4());
+        }
+        let deleted = Uuid::new_v4();
+        insert_customer(&connection, deleted);
+        connection
+            .execute(
+                "UPDATE customers SET deleted_at = ?1 WHERE id = ?2",
+                rusqlite::params![Utc::now().to_rfc3339(), deleted.to_string()],
+            )
+            .unwrap();
+
+        // 人为制造偏差
+        store.adjust("customers", 97).unwrap();
+        assert_eq!(store.get("customers").unwrap(), 97);
+
+        let job = CounterReconciliationJob::new(store.clone(), vec![EntityKind::Customer]);
+        let results = job.reconcile_all().unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].drifted());
+        assert_eq!(results[0].actual, 3);
+
+        job.run().await.unwrap();
+        assert_eq!(store.get("customers").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_daily_schedule() {
+        let (_dir, _connection, store) = create_test_store();
+        let job = CounterReconciliationJob::new(store, vec![EntityKind::Customer]);
+        assert!(matches!(job.schedule(), JobSchedule::Daily { .. }));
+    }
+}
@@ -0,0 +1,341 @@
+//! 客户信用存储
+//!
+//! 信用额度和冻结状态保存在 `customers.credit_limit`、`customers.credit_hold`；占用按订单
+//! 未收款（订单金额减已收款，不小于零）折算为本位币，以是否已送达区分应收款和已确认未送达的部分。
+//! 额度变化和管理员越过额度的记录都写入 `customer_audit`。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, CreditCheckService, CreditOverride, CreditProfile, Currency,
+    Money, SystemClock,
+};
+use rusqlite::{params, OptionalExtension};
+use uuid::Uuid;
+
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::customer_summary::rate_sql;
+use crate::repository::exchange_rates::ExchangeRateStore;
+use crate::repository::support::to_core;
+
+/// 额度的审计文本，不限额时为空
+fn limit_text(limit: Option<Money>) -> Option<String> {
+    limit.map(|limit| limit.to_string())
+}
+
+/// 客户信用存储
+#[derive(Clone)]
+pub struct CreditStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for CreditStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CreditStore").finish_non_exhaustive()
+    }
+}
+
+impl CreditStore {
+    /// 创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中固定当前时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn load_profile(&self, customer_id: Uuid) -> Result<Option<CreditProfile>> {
+        let balance = "MAX(o.total_amount - COALESCE((SELECT SUM(p.amount) FROM order_payments p
+                                                     WHERE p.order_id = o.id), 0), 0)";
+        let rate = rate_sql("o");
+        let sql = format!(
+            "SELECT c.name, c.credit_limit, c.credit_hold,
+                COALESCE(CAST(SUM(CASE WHEN o.delivery_status = 'delivered'
+                                       THEN ROUND({balance} * {rate}) END) AS INTEGER), 0),
+                COALESCE(CAST(SUM(CASE WHEN o.delivery_status <> 'delivered'
+                                       THEN ROUND({balance} * {rate}) END) AS INTEGER), 0),
+                COUNT(CASE WHEN {balance} <> 0 AND {rate} IS NULL THEN 1 END)
+             FROM customers c
+             LEFT JOIN orders o ON o.customer_id = c.id AND o.deleted_at IS NULL
+             WHERE c.id = ?1 AND c.deleted_at IS NULL
+             GROUP BY c.id"
+        );
+        let conn = self.connection.get_connection()?;
+        let profile = conn
+            .query_row(&sql, [DbUuid(customer_id)], |row| {
+                Ok(CreditProfile {
+                    customer_id,
+                    customer_name: row.get(0)?,
+                    credit_limit: row.get::<_, Option<i64>>(1)?.map(Money::from_cents),
+                    credit_hold: row.get(2)?,
+                    outstanding_receivables: Money::from_cents(row.get(3)?),
+                    unpaid_confirmed: Money::from_cents(row.get(4)?),
+                    missing_rate_orders: u64::try_from(row.get::<_, i64>(5)?).unwrap_or(0),
+                })
+            })
+            .optional()?;
+        Ok(profile)
+    }
+}
+
+#[async_trait]
+impl CreditCheckService for CreditStore {
+    async fn credit_profile(&self, customer_id: Uuid) -> CoreResult<CreditProfile> {
+        self.load_profile(customer_id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("客户 {}", customer_id)))
+    }
+
+    async fn set_credit_terms(
+        &self,
+        customer_id: Uuid,
+        credit_limit: Option<Money>,
+        credit_hold: bool,
+        actor: Option<&str>,
+    ) -> CoreResult<()> {
+        if credit_limit.is_some_and(|limit| limit < Money::ZERO) {
+            return Err(CoreError::validation("信用额度不能为负数"));
+        }
+        let now = time_key(self.clock.now());
+        self.connection
+            .with_transaction(|tx| {
+                let id = DbUuid(customer_id);
+                let (limit_before, hold_before): (Option<i64>, bool) = tx
+                    .query_row(
+                        "SELECT credit_limit, credit_hold FROM customers
+                         WHERE id = ?1 AND deleted_at IS NULL",
+                        [id],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()?
+                    .ok_or_else(|| CoreError::not_found(format!("客户 {}", customer_id)))?;
+                let limit_before = limit_before.map(Money::from_cents);
+                tx.execute(
+                    "UPDATE customers SET credit_limit = ?2, credit_hold = ?3,
+                         updated_at = ?4, updated_by = ?5
+                     WHERE id = ?1",
+                    params![id, credit_limit.map(|l| l.cents()), credit_hold, now, actor],
+                )?;
+
+                let mut audit = tx.prepare_cached(
+                    "INSERT INTO customer_audit
+                         (id, customer_id, field, old_value, new_value, actor, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )?;
+                if limit_before != credit_limit {
+                    audit.execute(params![
+                        DbUuid(Uuid::new_v4()),
+                        id,
+                        "credit_limit",
+                        limit_text(limit_before),
+                        limit_text(credit_limit),
+                        actor,
+                        now
+                    ])?;
+                }
+                if hold_before != credit_hold {
+                    audit.execute(params![
+                        DbUuid(Uuid::new_v4()),
+                        id,
+                        "credit_hold",
+                        hold_before.to_string(),
+                        credit_hold.to_string(),
+                        actor,
+                        now
+                    ])?;
+                }
+                Ok(())
+            })
+            .map_err(to_core)
+    }
+
+    async fn base_amount(
+        &self,
+        amount: Money,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> CoreResult<Option<Money>> {
+        if currency.is_base() {
+            return Ok(Some(amount));
+        }
+        let rate = ExchangeRateStore::new(self.connection.clone())
+            .at(currency, date)
+            .map_err(to_core)?;
+        Ok(rate.map(|rate| amount.convert(rate.rate_to_base)))
+    }
+
+    async fn record_override(&self, entry: &CreditOverride) -> CoreResult<()> {
+        let now = time_key(self.clock.now());
+        self.connection
+            .execute(
+                "INSERT INTO customer_audit
+                     (id, customer_id, field, old_value, new_value, actor, created_at)
+                 VALUES (?1, ?2, 'credit_override', ?3, ?4, ?5, ?6)",
+                params![
+                    DbUuid(Uuid::new_v4()),
+                    DbUuid(entry.customer_id),
+                    format!("超出 {}", entry.shortfall),
+                    format!("{} {}", entry.subject, entry.amount),
+                    entry.approver,
+                    now
+                ],
+            )
+            .map_err(to_core)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    struct Fixture {
+        _dir: TempDir,
+        connection: DatabaseConnection,
+        store: CreditStore,
+    }
+
+    fn fixture() -> Fixture {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 9, 1, 9, 0, 0).unwrap(),
+        ));
+        Fixture {
+            _dir: temp_dir,
+            store: CreditStore::new(connection.clone()).with_clock(clock),
+            connection,
+        }
+    }
+
+    fn insert_customer(fixture: &Fixture, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DbUuid(id), name, "2024-01-01T00:00:00.000000Z"],
+            )
+            .unwrap();
+        id
+    }
+
+    fn insert_order(fixture: &Fixture, customer_id: Uuid, amount: i64, status: &str, paid: i64) {
+        let id = Uuid::new_v4();
+        let now = "2024-08-01T00:00:00.000000Z";
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO orders
+                     (id, order_number, customer_id, total_amount, delivery_status,
+                      created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                params![DbUuid(id), id.to_string(), DbUuid(customer_id), amount, status, now],
+            )
+            .unwrap();
+        if paid > 0 {
+            fixture
+                .connection
+                .execute(
+                    "INSERT INTO order_payments (id, order_id, amount, paid_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![DbUuid(Uuid::new_v4()), DbUuid(id), paid, now],
+                )
+                .unwrap();
+        }
+    }
+
+    fn audit_rows(fixture: &Fixture, customer_id: Uuid) -> Vec<(String, Option<String>, String)> {
+        fixture
+            .connection
+            .query_map(
+                "SELECT field, old_value, COALESCE(actor, '') FROM customer_audit
+                 WHERE customer_id = ?1 ORDER BY field",
+                [DbUuid(customer_id)],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_exposure_splits_delivered_and_confirmed() {
+        let fixture = fixture();
+        let customer = insert_customer(&fixture, "华美家具");
+        insert_order(&fixture, customer, 500_000, "delivered", 200_000);
+        insert_order(&fixture, customer, 300_000, "scheduled", 0);
+        // 多收的订单不抵扣其它订单的占用
+        insert_order(&fixture, customer, 100_000, "delivered", 150_000);
+
+        let profile = fixture.store.credit_profile(customer).await.unwrap();
+        assert_eq!(profile.outstanding_receivables, Money::from_cents(300_000));
+        assert_eq!(profile.unpaid_confirmed, Money::from_cents(300_000));
+        assert_eq!(profile.exposure(), Money::from_cents(600_000));
+        assert_eq!(profile.credit_limit, None);
+        assert!(!profile.credit_hold);
+
+        let missing = fixture.store.credit_profile(Uuid::new_v4()).await;
+        assert!(matches!(missing, Err(CoreError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_credit_terms_and_override_audit_trail() {
+        let fixture = fixture();
+        let customer = insert_customer(&fixture, "华美家具");
+        insert_order(&fixture, customer, 900_000, "delivered", 0);
+        fixture
+            .store
+            .set_credit_terms(customer, Some(Money::from_cents(1_000_000)), false, Some("boss"))
+            .await
+            .unwrap();
+
+        let profile = fixture.store.credit_profile(customer).await.unwrap();
+        assert_eq!(profile.credit_limit, Some(Money::from_cents(1_000_000)));
+        assert!(profile.check(Money::from_cents(100_000)).is_ok());
+        let entry = profile
+            .override_credit(Money::from_cents(150_000), "admin", "报价 Q-2024-001")
+            .unwrap()
+            .unwrap();
+        fixture.store.record_override(&entry).await.unwrap();
+
+        fixture
+            .store
+            .set_credit_terms(customer, Some(Money::from_cents(1_000_000)), true, Some("boss"))
+            .await
+            .unwrap();
+        assert!(fixture.store.credit_profile(customer).await.unwrap().credit_hold);
+
+        assert_eq!(
+            audit_rows(&fixture, customer),
+            vec![
+                ("credit_hold".to_string(), Some("false".to_string()), "boss".to_string()),
+                ("credit_limit".to_string(), None, "boss".to_string()),
+                (
+                    "credit_override".to_string(),
+                    Some("超出 ¥500.00".to_string()),
+                    "admin".to_string()
+                ),
+            ]
+        );
+
+        let negative = fixture
+            .store
+            .set_credit_terms(customer, Some(Money::from_cents(-1)), false, None)
+            .await;
+        assert!(matches!(negative, Err(CoreError::Validation(_))));
+    }
+}
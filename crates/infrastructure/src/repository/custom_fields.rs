@@ -0,0 +1,717 @@
+//! 自定义字段存储
+//!
+//! 字段定义按实体类型保存在 `custom_field_definitions`，字段值以存储格式的文本保存在
+//! `custom_field_values`。停用的字段保留已有值，但不再参与显示、导出和必填校验。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, CustomFieldDefinition, CustomFieldEntry, CustomFieldService,
+    CustomFieldType, DomainEvent, EntityKind, EventEnvelope, EventHandler, FieldError, SystemClock,
+    MAX_CUSTOM_FIELDS_PER_ENTITY,
+};
+use rusqlite::{params, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const DEFINITION_COLUMNS: &str =
+    "id, entity_type, field_key, label, field_type, options, required, sort_order, retired_at, created_at";
+
+/// 自定义字段存储
+#[derive(Clone)]
+pub struct CustomFieldStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for CustomFieldStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomFieldStore").finish_non_exhaustive()
+    }
+}
+
+fn row_to_definition(row: &Row<'_>) -> rusqlite::Result<Option<CustomFieldDefinition>> {
+    let entity: String = row.get(1)?;
+    let field_type: String = row.get(4)?;
+    let options: Option<String> = row.get(5)?;
+    let retired_at: Option<String> = row.get(8)?;
+    let created_at: String = row.get(9)?;
+    let options: Vec<String> = options
+        .map(|o| serde_json::from_str(&o))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, Box::new(e))
+        })?
+        .unwrap_or_default();
+    // 无法识别的实体或类型来自更新版本写入的数据，跳过
+    let (Some(entity), Some(field_type)) = (
+        EntityKind::from_table_name(&entity),
+        CustomFieldType::parse(&field_type, options),
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(CustomFieldDefinition {
+        id: get_uuid(row, 0)?,
+        entity,
+        key: row.get(2)?,
+        label: row.get(3)?,
+        field_type,
+        required: row.get(6)?,
+        sort_order: row.get(7)?,
+        retired: retired_at.is_some(),
+        created_at: parse_time(9, &created_at)?,
+    }))
+}
+
+impl CustomFieldStore {
+    /// 创建自定义字段存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 实体类型下的字段定义（按显示顺序）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn definitions(
+        &self,
+        entity: EntityKind,
+        include_retired: bool,
+    ) -> Result<Vec<CustomFieldDefinition>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM custom_field_definitions
+                     WHERE entity_type = ?1 AND (?2 OR retired_at IS NULL)
+                     ORDER BY sort_order, created_at",
+                    DEFINITION_COLUMNS
+                ),
+                params![entity.table_name(), include_retired],
+                row_to_definition,
+            )?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// 实体的全部字段值（含已停用字段的值），按字段键
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn raw_values(
+        &self,
+        entity: EntityKind,
+        entity_id: Uuid,
+    ) -> Result<BTreeMap<String, String>> {
+        Ok(self
+            .connection
+            .query_map(
+                "SELECT field_key, value FROM custom_field_values
+                 WHERE entity_type = ?1 AND entity_id = ?2",
+                params![entity.table_name(), DbUuid(entity_id)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .into_iter()
+            .collect())
+    }
+
+    /// 实体类型下全部实体的字段值（用于导出），按实体ID和字段键
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn values_by_entity(
+        &self,
+        entity: EntityKind,
+    ) -> Result<HashMap<Uuid, BTreeMap<String, String>>> {
+        let rows: Vec<(Uuid, String, String)> = self.connection.query_map(
+            "SELECT entity_id, field_key, value FROM custom_field_values WHERE entity_type = ?1",
+            [entity.table_name()],
+            |row| Ok((get_uuid(row, 0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let mut values: HashMap<Uuid, BTreeMap<String, String>> = HashMap::new();
+        for (id, key, value) in rows {
+            values.entry(id).or_default().insert(key, value);
+        }
+        Ok(values)
+    }
+
+    /// 删除实体的全部字段值，返回删除的条数
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn delete_values(&self, entity: EntityKind, entity_id: Uuid) -> Result<usize> {
+        self.connection.execute(
+            "DELETE FROM custom_field_values WHERE entity_type = ?1 AND entity_id = ?2",
+            params![entity.table_name(), DbUuid(entity_id)],
+        )
+    }
+
+    fn validate_definition(definition: &CustomFieldDefinition) -> CoreResult<()> {
+        if !CustomFieldDefinition::is_valid_key(&definition.key) {
+            return Err(CoreError::validation(
+                "字段键须以小写字母开头，只能包含小写字母、数字和下划线，最长32个字符",
+            ));
+        }
+        if definition.label.trim().is_empty() {
+            return Err(CoreError::validation("字段名称不能为空"));
+        }
+        if let CustomFieldType::Select { options } = &definition.field_type {
+            if options.is_empty() || options.iter().any(|o| o.trim().is_empty()) {
+                return Err(CoreError::validation("单选字段至少需要一个非空的可选值"));
+            }
+            let mut seen = options.clone();
+            seen.sort();
+            seen.dedup();
+            if seen.len() != options.len() {
+                return Err(CoreError::validation("单选字段的可选值不能重复"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CustomFieldService for CustomFieldStore {
+    async fn define_field(
+        &self,
+        definition: CustomFieldDefinition,
+    ) -> CoreResult<CustomFieldDefinition> {
+        let definition = CustomFieldDefinition {
+            label: definition.label.trim().to_string(),
+            field_type: match definition.field_type {
+                CustomFieldType::Select { options } => CustomFieldType::Select {
+                    options: options.iter().map(|o| o.trim().to_string()).collect(),
+                },
+                other => other,
+            },
+            ..definition
+        };
+        Self::validate_definition(&definition)?;
+
+        let existing = self.definitions(definition.entity, true).map_err(to_core)?;
+        if existing.iter().any(|d| d.key == definition.key) {
+            return Err(CoreError::conflict(format!(
+                "字段键「{}」已被使用（含已停用的字段）",
+                definition.key
+            )));
+        }
+        if existing.iter().filter(|d| !d.retired).count() >= MAX_CUSTOM_FIELDS_PER_ENTITY {
+            return Err(CoreError::business(format!(
+                "每种实体最多定义{}个自定义字段，请先停用不再使用的字段",
+                MAX_CUSTOM_FIELDS_PER_ENTITY
+            )));
+        }
+
+        let definition = CustomFieldDefinition {
+            sort_order: existing.iter().map(|d| d.sort_order + 1).max().unwrap_or(0),
+            retired: false,
+            created_at: self.clock.now(),
+            ..definition
+        };
+        let options = match &definition.field_type {
+            CustomFieldType::Select { options } => {
+                Some(serde_json::to_string(options).map_err(|e| CoreError::Other(e.to_string()))?)
+            }
+            _ => None,
+        };
+        self.connection
+            .execute(
+                &format!(
+                    "INSERT INTO custom_field_definitions ({})
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, NULL, ?9)",
+                    DEFINITION_COLUMNS
+                ),
+                params![
+                    DbUuid(definition.id),
+                    definition.entity.table_name(),
+                    definition.key,
+                    definition.label,
+                    definition.field_type.as_str(),
+                    options,
+                    definition.required,
+                    definition.sort_order,
+                    time_key(definition.created_at),
+                ],
+            )
+            .map_err(to_core)?;
+        Ok(definition)
+    }
+
+    async fn reorder_fields(
+        &self,
+        entity: EntityKind,
+        keys: &[String],
+    ) -> CoreResult<Vec<CustomFieldDefinition>> {
+        let active = self.definitions(entity, false).map_err(to_core)?;
+        let mut expected: Vec<&str> = active.iter().map(|d| d.key.as_str()).collect();
+        let mut given: Vec<&str> = keys.iter().map(String::as_str).collect();
+        expected.sort_unstable();
+        given.sort_unstable();
+        if expected != given {
+            return Err(CoreError::validation(
+                "排序须列出全部未停用的字段，且每个字段只出现一次",
+            ));
+        }
+
+        self.connection
+            .with_transaction(|tx| {
+                for (index, key) in keys.iter().enumerate() {
+                    tx.execute(
+                        "UPDATE custom_field_definitions SET sort_order = ?3
+                         WHERE entity_type = ?1 AND field_key = ?2",
+                        params![entity.table_name(), key, index],
+                    )?;
+                }
+                Ok(())
+            })
+            .map_err(to_core)?;
+        self.definitions(entity, false).map_err(to_core)
+    }
+
+    async fn retire_field(&self, entity: EntityKind, key: &str) -> CoreResult<()> {
+        let updated = self
+            .connection
+            .execute(
+                "UPDATE custom_field_definitions SET retired_at = ?3
+                 WHERE entity_type = ?1 AND field_key = ?2 AND retired_at IS NULL",
+                params![entity.table_name(), key, time_key(self.clock.now())],
+            )
+            .map_err(to_core)?;
+        if updated == 0 {
+            return Err(CoreError::not_found(format!("自定义字段 {}", key)));
+        }
+        Ok(())
+    }
+
+    async fn fields(&self, entity: EntityKind) -> CoreResult<Vec<CustomFieldDefinition>> {
+        self.definitions(entity, false).map_err(to_core)
+    }
+
+    async fn set_values(
+        &self,
+        entity: EntityKind,
+        entity_id: Uuid,
+        values: &[(String, String)],
+    ) -> CoreResult<()> {
+        let definitions = self.definitions(entity, false).map_err(to_core)?;
+        let mut errors = Vec::new();
+        let mut changes: Vec<(&str, Option<String>)> = Vec::new();
+        for (key, input) in values {
+            let Some(definition) = definitions.iter().find(|d| &d.key == key) else {
+                errors.push(FieldError::new(key.as_str(), "字段未定义或已停用"));
+                continue;
+            };
+            if input.trim().is_empty() {
+                changes.push((key.as_str(), None));
+                continue;
+            }
+            match definition.field_type.encode(input) {
+                Ok(value) => changes.push((key.as_str(), Some(value))),
+                Err(message) => errors.push(FieldError::new(key.as_str(), message)),
+            }
+        }
+
+        let mut merged = self.raw_values(entity, entity_id).map_err(to_core)?;
+        for (key, value) in &changes {
+            match value {
+                Some(value) => merged.insert(key.to_string(), value.clone()),
+                None => merged.remove(*key),
+            };
+        }
+        for definition in definitions.iter().filter(|d| d.required) {
+            if !merged.contains_key(&definition.key)
+                && !errors.iter().any(|e| e.field == definition.key)
+            {
+                errors.push(FieldError::new(
+                    definition.key.as_str(),
+                    format!("{}为必填项", definition.label),
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(CoreError::invalid_fields(errors));
+        }
+
+        self.connection
+            .with_transaction(|tx| {
+                for (key, value) in &changes {
+                    match value {
+                        Some(value) => tx.execute(
+                            "INSERT INTO custom_field_values (entity_type, entity_id, field_key, value)
+                             VALUES (?1, ?2, ?3, ?4)
+                             ON CONFLICT (entity_type, entity_id, field_key)
+                             DO UPDATE SET value = excluded.value",
+                            params![entity.table_name(), DbUuid(entity_id), key, value],
+                        )?,
+                        None => tx.execute(
+                            "DELETE FROM custom_field_values
+                             WHERE entity_type = ?1 AND entity_id = ?2 AND field_key = ?3",
+                            params![entity.table_name(), DbUuid(entity_id), key],
+                        )?,
+                    };
+                }
+                Ok(())
+            })
+            .map_err(to_core)
+    }
+
+    async fn values(
+        &self,
+        entity: EntityKind,
+        entity_id: Uuid,
+    ) -> CoreResult<Vec<CustomFieldEntry>> {
+        let definitions = self.definitions(entity, false).map_err(to_core)?;
+        let mut values = self.raw_values(entity, entity_id).map_err(to_core)?;
+        Ok(definitions
+            .into_iter()
+            .map(|definition| CustomFieldEntry {
+                value: values.remove(&definition.key),
+                definition,
+            })
+            .collect())
+    }
+}
+
+impl EventHandler for CustomFieldStore {
+    fn name(&self) -> &str {
+        "custom_fields"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        if let DomainEvent::EntityPurged { entity, id, .. } = &envelope.event {
+            let removed = self.delete_values(*entity, *id)?;
+            if removed > 0 {
+                info!(
+                    "{} {} 已彻底删除，清理 {} 个自定义字段值",
+                    entity.table_name(),
+                    id,
+                    removed
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::filter::FilterTranslator;
+    use crate::test_support::migrated_pool;
+    use chrono::Utc;
+    use minicrm_core::QueryFilter;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, CustomFieldStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = CustomFieldStore::new(connection.clone());
+        (temp_dir, connection, store)
+    }
+
+    fn insert_customer(connection: &DatabaseConnection, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DbUuid(id), name, now],
+            )
+            .unwrap();
+        id
+    }
+
+    fn field(key: &str, label: &str, field_type: CustomFieldType) -> CustomFieldDefinition {
+        CustomFieldDefinition {
+            id: Uuid::new_v4(),
+            entity: EntityKind::Customer,
+            key: key.to_string(),
+            label: label.to_string(),
+            field_type,
+            required: false,
+            sort_order: 0,
+            retired: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn terms() -> CustomFieldType {
+        CustomFieldType::Select {
+            options: vec![
+                "现结".to_string(),
+                "月结30天".to_string(),
+                "月结60天".to_string(),
+            ],
+        }
+    }
+
+    fn set(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn invalid_keys(result: CoreResult<()>) -> Vec<String> {
+        match result {
+            Err(CoreError::InvalidFields(errors)) => errors.into_iter().map(|e| e.field).collect(),
+            other => panic!("预期字段验证错误，实际为 {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_values_validated_by_field_type() {
+        let (_dir, connection, store) = create_test_store();
+        let customer = insert_customer(&connection, "华东板材");
+        store
+            .define_field(CustomFieldDefinition {
+                required: true,
+                ..field("credit_code", "信用代码", CustomFieldType::Text)
+            })
+            .await
+            .unwrap();
+        store
+            .define_field(field("credit_days", "月结天数", CustomFieldType::Number))
+            .await
+            .unwrap();
+        store
+            .define_field(field("signed_on", "签约日期", CustomFieldType::Date))
+            .await
+            .unwrap();
+        store
+            .define_field(field("payment_terms", "结算方式", terms()))
+            .await
+            .unwrap();
+
+        let result = store
+            .set_values(
+                EntityKind::Customer,
+                customer,
+                &set(&[
+                    ("credit_days", "三十"),
+                    ("signed_on", "2024-02-30"),
+                    ("payment_terms", "货到付款"),
+                    ("unknown", "x"),
+                ]),
+            )
+            .await;
+        assert_eq!(
+            invalid_keys(result),
+            vec![
+                "credit_days",
+                "signed_on",
+                "payment_terms",
+                "unknown",
+                "credit_code"
+            ]
+        );
+        assert!(store
+            .raw_values(EntityKind::Customer, customer)
+            .unwrap()
+            .is_empty());
+
+        store
+            .set_values(
+                EntityKind::Customer,
+                customer,
+                &set(&[
+                    ("credit_code", " 91310000MA1FL0XX0X "),
+                    ("credit_days", "30.50"),
+                    ("signed_on", "2024-2-5"),
+                    ("payment_terms", "月结30天"),
+                ]),
+            )
+            .await
+            .unwrap();
+        let values: Vec<(String, Option<String>)> = store
+            .values(EntityKind::Customer, customer)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.definition.key, e.value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (
+                    "credit_code".to_string(),
+                    Some("91310000MA1FL0XX0X".to_string())
+                ),
+                ("credit_days".to_string(), Some("30.5".to_string())),
+                ("signed_on".to_string(), Some("2024-02-05".to_string())),
+                ("payment_terms".to_string(), Some("月结30天".to_string())),
+            ]
+        );
+
+        // 清除必填字段
+        let result = store
+            .set_values(EntityKind::Customer, customer, &set(&[("credit_code", "")]))
+            .await;
+        assert_eq!(invalid_keys(result), vec!["credit_code"]);
+    }
+
+    #[tokio::test]
+    async fn test_retired_fields_keep_values_but_stop_rendering() {
+        let (_dir, connection, store) = create_test_store();
+        let customer = insert_customer(&connection, "宏达家具");
+        for (key, label) in [("credit_code", "信用代码"), ("region", "片区")] {
+            store
+                .define_field(field(key, label, CustomFieldType::Text))
+                .await
+                .unwrap();
+        }
+        store
+            .set_values(
+                EntityKind::Customer,
+                customer,
+                &set(&[("credit_code", "9131"), ("region", "华东")]),
+            )
+            .await
+            .unwrap();
+
+        store
+            .retire_field(EntityKind::Customer, "credit_code")
+            .await
+            .unwrap();
+        let keys: Vec<String> = store
+            .values(EntityKind::Customer, customer)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.definition.key)
+            .collect();
+        assert_eq!(keys, vec!["region"]);
+        assert_eq!(
+            store
+                .raw_values(EntityKind::Customer, customer)
+                .unwrap()
+                .get("credit_code")
+                .map(String::as_str),
+            Some("9131")
+        );
+        // 停用的字段不能再写入，字段键也不能复用
+        let result = store
+            .set_values(
+                EntityKind::Customer,
+                customer,
+                &set(&[("credit_code", "1")]),
+            )
+            .await;
+        assert_eq!(invalid_keys(result), vec!["credit_code"]);
+        assert!(matches!(
+            store
+                .define_field(field("credit_code", "信用代码", CustomFieldType::Text))
+                .await,
+            Err(CoreError::Conflict(_))
+        ));
+        assert!(matches!(
+            store
+                .retire_field(EntityKind::Customer, "credit_code")
+                .await,
+            Err(CoreError::NotFound(_))
+        ));
+
+        // 停用的字段不计入上限
+        for i in 1..MAX_CUSTOM_FIELDS_PER_ENTITY {
+            store
+                .define_field(field(
+                    &format!("extra_{}", i),
+                    "附加",
+                    CustomFieldType::Text,
+                ))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            store
+                .define_field(field("one_too_many", "超出", CustomFieldType::Text))
+                .await,
+            Err(CoreError::Business(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_filter_and_reorder_by_select_value() {
+        let (_dir, connection, store) = create_test_store();
+        store
+            .define_field(field("region", "片区", CustomFieldType::Text))
+            .await
+            .unwrap();
+        store
+            .define_field(field("payment_terms", "结算方式", terms()))
+            .await
+            .unwrap();
+        for (name, term) in [
+            ("华东板材", "月结30天"),
+            ("宏达家具", "现结"),
+            ("木立方", "月结30天"),
+        ] {
+            let id = insert_customer(&connection, name);
+            store
+                .set_values(EntityKind::Customer, id, &set(&[("payment_terms", term)]))
+                .await
+                .unwrap();
+        }
+        insert_customer(&connection, "未填写");
+
+        let translator = FilterTranslator::new(EntityKind::Customer, "c.id");
+        let filter = QueryFilter::new().with_string_filter("custom.payment_terms", "月结30天");
+        let sql = translator.translate(&filter).unwrap();
+        let conn = connection.get_connection().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT c.name FROM customers c{} ORDER BY c.name",
+                sql.where_clause()
+            ))
+            .unwrap();
+        let names: Vec<String> = stmt
+            .query_map(rusqlite::params_from_iter(sql.params.iter()), |row| {
+                row.get(0)
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let mut expected = vec!["华东板材".to_string(), "木立方".to_string()];
+        expected.sort();
+        assert_eq!(names, expected);
+
+        let reordered = store
+            .reorder_fields(
+                EntityKind::Customer,
+                &["payment_terms".to_string(), "region".to_string()],
+            )
+            .await
+            .unwrap();
+        let keys: Vec<&str> = reordered.iter().map(|d| d.key.as_str()).collect();
+        assert_eq!(keys, vec!["payment_terms", "region"]);
+        assert!(matches!(
+            store
+                .reorder_fields(EntityKind::Customer, &["region".to_string()])
+                .await,
+            Err(CoreError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,345 @@
+//! 客户等级与标签存储
+//!
+//! 分类依据取自 `customers.level`、`customer_tags` 和 `customer_summary` 中的最近互动时间、
+//! 近12个月已接受报价金额。批量变更按批在事务中写入，每个客户的等级和标签变化记入
+//! `customer_audit`，并登记客户更新事件到事件发件箱。
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    CategoryChange, Clock, CoreResult, CustomerCategoryService, CustomerLevel, CustomerProfile,
+    DomainEvent, EntityKind, EventEnvelope, Money, SystemClock,
+};
+use rusqlite::{params, Transaction};
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::outbox;
+use crate::repository::support::to_core;
+
+/// 标签集合的审计文本
+fn tags_text(tags: &BTreeSet<String>) -> String {
+    tags.iter().map(String::as_str).collect::<Vec<_>>().join(",")
+}
+
+/// 客户等级与标签存储
+#[derive(Clone)]
+pub struct CustomerCategoryStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for CustomerCategoryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerCategoryStore").finish_non_exhaustive()
+    }
+}
+
+impl CustomerCategoryStore {
+    /// 创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中固定当前时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 客户的标签
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn tags_of(&self, customer_id: Uuid) -> Result<BTreeSet<String>> {
+        Ok(self
+            .connection
+            .query_map(
+                "SELECT tag FROM customer_tags WHERE customer_id = ?1",
+                [DbUuid(customer_id)],
+                |row| row.get(0),
+            )?
+            .into_iter()
+            .collect())
+    }
+
+    fn write_change(
+        tx: &Transaction<'_>,
+        change: &CategoryChange,
+        actor: Option<&str>,
+        now: &str,
+    ) -> Result<bool> {
+        let id = DbUuid(change.customer_id);
+        let updated = tx.execute(
+            "UPDATE customers SET level = ?2, updated_at = ?3, updated_by = ?4
+             WHERE id = ?1 AND deleted_at IS NULL",
+            params![id, change.level_after.as_str(), now, actor],
+        )?;
+        if updated == 0 {
+            return Ok(false);
+        }
+
+        let mut audit = tx.prepare_cached(
+            "INSERT INTO customer_audit
+                 (id, customer_id, field, old_value, new_value, actor, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        if change.level_changed() {
+            audit.execute(params![
+                DbUuid(Uuid::new_v4()),
+                id,
+                "level",
+                change.level_before.as_str(),
+                change.level_after.as_str(),
+                actor,
+                now
+            ])?;
+        }
+        if change.tags_before != change.tags_after {
+            for tag in change.removed_tags() {
+                tx.execute(
+                    "DELETE FROM customer_tags WHERE customer_id = ?1 AND tag = ?2",
+                    params![id, tag],
+                )?;
+            }
+            for tag in change.added_tags() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO customer_tags (customer_id, tag, created_at)
+                     VALUES (?1, ?2, ?3)",
+                    params![id, tag, now],
+                )?;
+            }
+            audit.execute(params![
+                DbUuid(Uuid::new_v4()),
+                id,
+                "tags",
+                tags_text(&change.tags_before),
+                tags_text(&change.tags_after),
+                actor,
+                now
+            ])?;
+        }
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl CustomerCategoryService for CustomerCategoryStore {
+    async fn customer_profiles(&self) -> CoreResult<Vec<CustomerProfile>> {
+        let mut tags: HashMap<Uuid, BTreeSet<String>> = HashMap::new();
+        for (customer_id, tag) in self
+            .connection
+            .query_map("SELECT customer_id, tag FROM customer_tags", [], |row| {
+                Ok((get_uuid(row, 0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(to_core)?
+        {
+            tags.entry(customer_id).or_default().insert(tag);
+        }
+
+        self.connection
+            .query_map(
+                "SELECT c.id, c.name, c.level, s.last_interaction_at,
+                        COALESCE(s.accepted_quote_total_12m, 0)
+                 FROM customers c
+                 LEFT JOIN customer_summary s ON s.customer_id = c.id
+                 WHERE c.deleted_at IS NULL
+                 ORDER BY c.name, c.id",
+                [],
+                |row| {
+                    let customer_id = get_uuid(row, 0)?;
+                    let level: String = row.get(2)?;
+                    let last_interaction_at: Option<String> = row.get(3)?;
+                    Ok(CustomerProfile {
+                        customer_id,
+                        name: row.get(1)?,
+                        level: CustomerLevel::parse(&level).unwrap_or(CustomerLevel::Normal),
+                        tags: tags.remove(&customer_id).unwrap_or_default(),
+                        last_interaction_at: last_interaction_at
+                            .map(|value| parse_time(3, &value))
+                            .transpose()?,
+                        accepted_quote_total_12m: Money::from_cents(row.get(4)?),
+                    })
+                },
+            )
+            .map_err(to_core)
+    }
+
+    async fn apply_category_changes(
+        &self,
+        changes: &[CategoryChange],
+        actor: Option<&str>,
+    ) -> CoreResult<usize> {
+        let at = self.clock.now();
+        let now = time_key(at);
+        self.connection
+            .with_transaction(|tx| {
+                let mut applied = 0;
+                for change in changes {
+                    if Self::write_change(tx, change, actor, &now)? {
+                        let event = DomainEvent::EntityUpdated {
+                            entity: EntityKind::Customer,
+                            id: change.customer_id,
+                        };
+                        outbox::record(tx, &EventEnvelope::at(event, at))?;
+                        applied += 1;
+                    }
+                }
+                Ok(applied)
+            })
+            .map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::outbox::EventOutboxStore;
+    use crate::test_support::migrated_pool;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    struct Fixture {
+        _dir: TempDir,
+        connection: DatabaseConnection,
+        store: CustomerCategoryStore,
+    }
+
+    fn fixture() -> Fixture {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 9, 1, 9, 0, 0).unwrap(),
+        ));
+        Fixture {
+            _dir: temp_dir,
+            store: CustomerCategoryStore::new(connection.clone()).with_clock(clock),
+            connection,
+        }
+    }
+
+    fn insert_customer(fixture: &Fixture, name: &str, quote_total: i64) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = "2024-01-01T00:00:00.000000Z";
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DbUuid(id), name, now],
+            )
+            .unwrap();
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO customer_summary
+                     (customer_id, last_interaction_at, accepted_quote_total_12m, updated_at)
+                 VALUES (?1, ?2, ?3, ?2)",
+                params![DbUuid(id), now, quote_total],
+            )
+            .unwrap();
+        id
+    }
+
+    fn tags(values: &[&str]) -> BTreeSet<String> {
+        values.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_profiles_and_apply_changes() {
+        let fixture = fixture();
+        let hongda = insert_customer(&fixture, "宏达家具", 8_000_000);
+        let mulifang = insert_customer(&fixture, "木立方", 0);
+        fixture
+            .connection
+            .execute(
+                "INSERT INTO customer_tags (customer_id, tag, created_at) VALUES (?1, '展会', ?2)",
+                params![DbUuid(mulifang), "2024-01-01T00:00:00Z"],
+            )
+            .unwrap();
+
+        let profiles = fixture.store.customer_profiles().await.unwrap();
+        assert_eq!(profiles.len(), 2);
+        let hongda_profile = profiles.iter().find(|p| p.customer_id == hongda).unwrap();
+        assert_eq!(hongda_profile.level, CustomerLevel::Normal);
+        assert_eq!(
+            hongda_profile.accepted_quote_total_12m,
+            Money::from_cents(8_000_000)
+        );
+        assert!(hongda_profile.last_interaction_at.is_some());
+        let mulifang_profile = profiles.iter().find(|p| p.customer_id == mulifang).unwrap();
+        assert_eq!(mulifang_profile.tags, tags(&["展会"]));
+
+        let changes = vec![
+            CategoryChange {
+                customer_id: hongda,
+                name: "宏达家具".to_string(),
+                level_before: CustomerLevel::Normal,
+                level_after: CustomerLevel::Vip,
+                tags_before: BTreeSet::new(),
+                tags_after: BTreeSet::new(),
+            },
+            CategoryChange {
+                customer_id: mulifang,
+                name: "木立方".to_string(),
+                level_before: CustomerLevel::Normal,
+                level_after: CustomerLevel::Normal,
+                tags_before: tags(&["展会"]),
+                tags_after: tags(&["长期未联系"]),
+            },
+            CategoryChange {
+                customer_id: Uuid::new_v4(),
+                name: "已删除".to_string(),
+                level_before: CustomerLevel::Normal,
+                level_after: CustomerLevel::Vip,
+                tags_before: BTreeSet::new(),
+                tags_after: BTreeSet::new(),
+            },
+        ];
+        let applied = fixture
+            .store
+            .apply_category_changes(&changes, Some("admin"))
+            .await
+            .unwrap();
+        assert_eq!(applied, 2);
+
+        let profiles = fixture.store.customer_profiles().await.unwrap();
+        let hongda_profile = profiles.iter().find(|p| p.customer_id == hongda).unwrap();
+        assert_eq!(hongda_profile.level, CustomerLevel::Vip);
+        assert_eq!(
+            fixture.store.tags_of(mulifang).unwrap(),
+            tags(&["长期未联系"])
+        );
+
+        let audit: Vec<(String, String, String)> = fixture
+            .connection
+            .query_map(
+                "SELECT field, old_value, new_value FROM customer_audit
+                 WHERE actor = 'admin' ORDER BY field",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            audit,
+            vec![
+                ("level".to_string(), "Normal".to_string(), "Vip".to_string()),
+                ("tags".to_string(), "展会".to_string(), "长期未联系".to_string()),
+            ]
+        );
+
+        let pending = EventOutboxStore::new(fixture.connection.clone())
+            .pending(10)
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+    }
+}
@@ -0,0 +1,380 @@
+//! 客户彻底删除
+//!
+//! 外键级联只覆盖任务和报价，订单、销售机会、互动记录、客户关系、自定义字段值和
+//! 附件文件都不会随之删除。删除前用分组查询统计影响范围供确认对话框预览；
+//! 删除在同一事务中清理全部关联记录，提交后再删除客户的附件目录。
+//!
+//! 移入回收站（软删除）见 [`crate::repository::trash`]。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use minicrm_core::{DeletionImpact, EntityKind};
+use rusqlite::{params, Transaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::database::{DatabaseConnection, DbUuid};
+
+/// 客户附件在附件目录中的子目录
+pub const CUSTOMER_ATTACHMENTS_DIR: &str = "customers";
+
+/// 属于客户的报价
+const CUSTOMER_QUOTES: &str = "SELECT id FROM quotes WHERE customer_id = ?1";
+
+/// 客户及其报价、订单、任务、销售机会的ID（提醒和自定义字段值按这些ID关联）
+const CUSTOMER_ENTITIES: &str = "
+    SELECT ?1
+    UNION ALL SELECT id FROM quotes WHERE customer_id = ?1
+    UNION ALL SELECT id FROM orders WHERE customer_id = ?1
+    UNION ALL SELECT id FROM tasks WHERE customer_id = ?1
+    UNION ALL SELECT id FROM opportunities WHERE customer_id = ?1";
+
+/// 客户删除存储
+#[derive(Clone)]
+pub struct CustomerDeletionStore {
+    connection: DatabaseConnection,
+    attachments_dir: Option<PathBuf>,
+}
+
+impl std::fmt::Debug for CustomerDeletionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerDeletionStore")
+            .field("attachments_dir", &self.attachments_dir)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 目录中的文件数（含子目录，目录不存在时为0）
+fn count_files(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                count_files(&path)
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+impl CustomerDeletionStore {
+    /// 创建客户删除存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            attachments_dir: None,
+        }
+    }
+
+    /// 设置附件目录（客户附件保存在 `<附件目录>/customers/<客户ID>/`）
+    pub fn with_attachments_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.attachments_dir = Some(dir.into());
+        self
+    }
+
+    /// 客户的附件目录
+    pub fn attachments_path(&self, customer_id: Uuid) -> Option<PathBuf> {
+        self.attachments_dir.as_ref().map(|dir| {
+            dir.join(CUSTOMER_ATTACHMENTS_DIR)
+                .join(customer_id.to_string())
+        })
+    }
+
+    /// 统计删除客户会一并删除的关联记录
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn impact(&self, customer_id: Uuid) -> Result<DeletionImpact> {
+        let conn = self.connection.get_connection()?;
+        let id = DbUuid(customer_id);
+        let mut impact = DeletionImpact::default();
+
+        let mut stmt = conn.prepare(
+            "SELECT 'tasks', COUNT(*) FROM tasks WHERE customer_id = ?1
+             UNION ALL SELECT 'orders', COUNT(*) FROM orders WHERE customer_id = ?1
+             UNION ALL SELECT 'opportunities', COUNT(*) FROM opportunities WHERE customer_id = ?1
+             UNION ALL SELECT 'interactions', COUNT(*) FROM interactions WHERE customer_id = ?1
+             UNION ALL SELECT 'relations', COUNT(*) FROM customer_relations
+                 WHERE from_id = ?1 OR to_id = ?1",
+        )?;
+        let rows = stmt.query_map([&id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+        })?;
+        for row in rows {
+            let (table, count) = row?;
+            match table.as_str() {
+                "tasks" => impact.tasks = count,
+                "orders" => impact.orders = count,
+                "opportunities" => impact.opportunities = count,
+                "interactions" => impact.interactions = count,
+                _ => impact.relations = count,
+            }
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT status, COUNT(*) FROM quotes WHERE customer_id = ?1 GROUP BY status",
+        )?;
+        let rows = stmt.query_map([&id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+        })?;
+        for row in rows {
+            let (status, count) = row?;
+            impact.quotes += count;
+            if status == "accepted" {
+                impact.accepted_quotes = count;
+            }
+        }
+
+        impact.quote_revisions = conn.query_row(
+            &format!("SELECT COUNT(*) FROM quote_revisions WHERE quote_id IN ({CUSTOMER_QUOTES})"),
+            [&id],
+            |row| row.get(0),
+        )?;
+        impact.attachments = self
+            .attachments_path(customer_id)
+            .map_or(0, |dir| count_files(&dir));
+        Ok(impact)
+    }
+
+    /// 彻底删除客户及全部关联记录，客户不存在时返回 `false`
+    ///
+    /// 数据库记录在同一事务中删除；附件目录在事务提交后删除，删除失败只记录警告。
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误，此时不会删除任何记录和附件。
+    pub fn hard_delete(&self, customer_id: Uuid) -> Result<bool> {
+        let deleted = self
+            .connection
+            .with_transaction(|tx| Self::delete_rows(tx, customer_id))?;
+        if !deleted {
+            return Ok(false);
+        }
+
+        if let Some(dir) = self.attachments_path(customer_id) {
+            if dir.exists() {
+                if let Err(e) = fs::remove_dir_all(&dir) {
+                    warn!("删除客户附件目录失败: {}: {}", dir.display(), e);
+                }
+            }
+        }
+        info!("客户 {} 已彻底删除", customer_id);
+        Ok(true)
+    }
+
+    fn delete_rows(tx: &Transaction<'_>, customer_id: Uuid) -> Result<bool> {
+        let id = DbUuid(customer_id);
+        tx.execute(
+            &format!("DELETE FROM reminders WHERE entity_id IN ({CUSTOMER_ENTITIES})"),
+            [&id],
+        )?;
+        tx.execute(
+            &format!(
+                "DELETE FROM custom_field_values
+                 WHERE (entity_type = ?2 AND entity_id = ?1)
+                    OR (entity_type = ?3 AND entity_id IN (SELECT id FROM orders WHERE customer_id = ?1))
+                    OR (entity_type = ?4 AND entity_id IN ({CUSTOMER_QUOTES}))"
+            ),
+            params![
+                &id,
+                EntityKind::Customer.table_name(),
+                EntityKind::Order.table_name(),
+                EntityKind::Quote.table_name(),
+            ],
+        )?;
+        tx.execute(
+            &format!("DELETE FROM quote_revisions WHERE quote_id IN ({CUSTOMER_QUOTES})"),
+            [&id],
+        )?;
+        for sql in [
+            "DELETE FROM orders WHERE customer_id = ?1",
+            "DELETE FROM opportunities WHERE customer_id = ?1",
+            "DELETE FROM interactions WHERE customer_id = ?1",
+            "DELETE FROM customer_relations WHERE from_id = ?1 OR to_id = ?1",
+        ] {
+            tx.execute(sql, [&id])?;
+        }
+        // 任务、报价和联系人由外键级联删除
+        Ok(tx.execute("DELETE FROM customers WHERE id = ?1", [&id])? > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, CustomerDeletionStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = CustomerDeletionStore::new(connection.clone())
+            .with_attachments_dir(temp_dir.path().join("attachments"));
+        (temp_dir, connection, store)
+    }
+
+    /// 插入一个客户及每种关联记录，返回客户ID
+    fn seed_customer(connection: &DatabaseConnection, store: &CustomerDeletionStore) -> Uuid {
+        let customer = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let (quote, accepted, order) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let now = "2024-03-01T09:00:00.000000Z";
+        let conn = connection.get_connection().unwrap();
+        for (id, name) in [(customer, "测试客户"), (other, "关联客户")] {
+            conn.execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DbUuid(id), name, now],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO tasks (id, customer_id, title, created_at, updated_at)
+             VALUES (?1, ?2, '回访', ?3, ?3)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(customer), now],
+        )
+        .unwrap();
+        for (id, status) in [(quote, "draft"), (accepted, "accepted")] {
+            conn.execute(
+                "INSERT INTO quotes (id, customer_id, title, total_amount, status, created_at, updated_at)
+                 VALUES (?1, ?2, '报价', 100.0, ?3, ?4, ?4)",
+                params![DbUuid(id), DbUuid(customer), status, now],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO quote_revisions (quote_id, revision_no, snapshot, saved_at)
+             VALUES (?1, 1, '{}', ?2)",
+            params![DbUuid(accepted), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO orders (id, order_number, customer_id, quote_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            // 订单号唯一，同一测试中可插入多个客户
+            params![
+                DbUuid(order),
+                format!("SO-{}", order.simple()),
+                DbUuid(customer),
+                DbUuid(accepted),
+                now
+            ],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO opportunities (id, customer_id, title, created_at, updated_at)
+             VALUES (?1, ?2, '新厂房', ?3, ?3)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(customer), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO interactions (id, customer_id, kind, content, occurred_at)
+             VALUES (?1, ?2, 'call', '电话沟通', ?3)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(customer), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO customer_relations (id, from_id, to_id, kind, created_at)
+             VALUES (?1, ?2, ?3, 'referred', ?4)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(other), DbUuid(customer), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO reminders (id, kind, entity_type, entity_id, title, due_at, created_at)
+             VALUES (?1, 'delivery_overdue', 'orders', ?2, '送货逾期', ?3, ?3)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(order), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO custom_field_values (entity_type, entity_id, field_key, value)
+             VALUES ('customers', ?1, 'region', '华东')",
+            params![DbUuid(customer)],
+        )
+        .unwrap();
+
+        let dir = store.attachments_path(customer).unwrap();
+        fs::create_dir_all(dir.join("quotes")).unwrap();
+        fs::write(dir.join("license.pdf"), b"pdf").unwrap();
+        fs::write(dir.join("quotes").join("Q-0001.pdf"), b"pdf").unwrap();
+        customer
+    }
+
+    fn count(connection: &DatabaseConnection, sql: &str) -> u64 {
+        connection
+            .get_connection()
+            .unwrap()
+            .query_row(sql, [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_impact_counts_every_dependent() {
+        let (_dir, connection, store) = create_test_store();
+        let customer = seed_customer(&connection, &store);
+
+        let impact = store.impact(customer).unwrap();
+        assert_eq!(
+            impact,
+            DeletionImpact {
+                tasks: 1,
+                quotes: 2,
+                accepted_quotes: 1,
+                quote_revisions: 1,
+                orders: 1,
+                opportunities: 1,
+                interactions: 1,
+                relations: 1,
+                attachments: 2,
+            }
+        );
+        assert!(impact.has_financial_records());
+        assert_eq!(
+            store.impact(Uuid::new_v4()).unwrap(),
+            DeletionImpact::default()
+        );
+    }
+
+    #[test]
+    fn test_hard_delete_leaves_no_orphans() {
+        let (_dir, connection, store) = create_test_store();
+        let customer = seed_customer(&connection, &store);
+        let kept = seed_customer(&connection, &store);
+
+        assert!(store.hard_delete(customer).unwrap());
+        assert!(!store.hard_delete(customer).unwrap());
+
+        assert_eq!(store.impact(customer).unwrap(), DeletionImpact::default());
+        assert!(!store.attachments_path(customer).unwrap().exists());
+        // 每类关联记录只剩另一个客户的一条
+        for table in [
+            "tasks",
+            "orders",
+            "opportunities",
+            "interactions",
+            "quote_revisions",
+            "reminders",
+            "custom_field_values",
+        ] {
+            assert_eq!(
+                count(&connection, &format!("SELECT COUNT(*) FROM {table}")),
+                1,
+                "{table}"
+            );
+        }
+        assert_eq!(count(&connection, "SELECT COUNT(*) FROM quotes"), 2);
+        // 被删除客户作为关联方的关系一并删除
+        assert_eq!(
+            count(&connection, "SELECT COUNT(*) FROM customer_relations"),
+            1
+        );
+        assert_eq!(store.impact(kept).unwrap().attachments, 2);
+    }
+}
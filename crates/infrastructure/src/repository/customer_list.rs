@@ -0,0 +1,680 @@
+//! 客户列表读取模型
+//!
+//! 按列投影生成查询：只选择界面上可见的列，订单总额等需要相关子查询的
+//! 计算列在隐藏时完全不出现在SQL中。最近联系、未完成任务、未收款等汇总列
+//! 读取 `customer_summary` 表（见 [`customer_summary`](crate::repository::customer_summary)）。
+//!
+//! 导出使用与列表相同的投影、过滤条件和排序，但不分页：在同一查询上逐行读取并分批
+//! 交给调用方写出（见 [`CustomerListStore::for_each_chunk`]）。
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    CancellationToken, CoreError, CoreResult, CustomerExportRequest, CustomerExportService,
+    CustomerListReadModel, CustomerListRow, EntityKind, ExportProgress, ExportScope, PagedResult,
+    Projection, QueryFilter, SortDirection,
+};
+use rusqlite::types::Value;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::export::CsvFileWriter;
+use crate::repository::filter::{FilterTranslator, SqlFilter};
+use crate::repository::query_builder::like_pattern;
+use crate::repository::support::to_core;
+
+/// 可查询的列（列ID, SQL表达式）
+///
+/// 汇总列来自连接的 `customer_summary s`，其余计算列使用相关子查询，只在投影包含时计算。
+const COLUMNS: &[(&str, &str)] = &[
+    ("name", "c.name"),
+    ("company", "c.company"),
+    ("phone", "c.phone"),
+    ("email", "c.email"),
+    ("address", "c.address"),
+    ("created_at", "substr(c.created_at, 1, 10)"),
+    ("last_contact", "substr(s.last_interaction_at, 1, 10)"),
+    ("open_tasks", "CAST(COALESCE(s.open_task_count, 0) AS TEXT)"),
+    ("outstanding", "printf('%.2f', COALESCE(s.outstanding_amount, 0) / 100.0)"),
+    ("accepted_quotes_12m", "printf('%.2f', COALESCE(s.accepted_quote_total_12m, 0) / 100.0)"),
+    ("activity_score", "CAST(COALESCE(s.activity_score, 0) AS TEXT)"),
+    (
+        "order_total",
+        "(SELECT printf('%.2f', SUM(o.total_amount) / 100.0) FROM orders o \
+         WHERE o.customer_id = c.id AND o.deleted_at IS NULL)",
+    ),
+];
+
+/// 导出时每批读取的行数
+pub const EXPORT_CHUNK_ROWS: usize = 500;
+
+/// 使用全文索引搜索的最短关键词长度（trigram 分词，更短的关键词使用 LIKE）
+pub(crate) const FTS_MIN_CHARS: usize = 3;
+
+/// 把关键词转为全文索引的短语查询（按子串匹配，不解析查询语法）
+pub(crate) fn fts_phrase(search: &str) -> String {
+    format!("\"{}\"", search.replace('"', "\"\""))
+}
+
+/// 客户列表存储
+#[derive(Clone)]
+pub struct CustomerListStore {
+    connection: DatabaseConnection,
+}
+
+impl std::fmt::Debug for CustomerListStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerListStore").finish_non_exhaustive()
+    }
+}
+
+/// 投影中可查询的列（按投影顺序，未知列忽略）
+fn projected_columns(projection: &Projection) -> Vec<(&'static str, &'static str)> {
+    projection
+        .columns
+        .iter()
+        .filter_map(|id| COLUMNS.iter().find(|(column, _)| column == id).copied())
+        .collect()
+}
+
+/// 生成选择列表（首列固定为客户ID）
+pub fn select_list(projection: &Projection) -> String {
+    std::iter::once("c.id".to_string())
+        .chain(
+            projected_columns(projection)
+                .into_iter()
+                .map(|(_, expr)| expr.to_string()),
+        )
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 排序子句：按列表的排序列排序，未排序或列不可查询时按名称；最后按ID保证顺序稳定
+pub fn order_clause(filter: &QueryFilter) -> String {
+    let sort = filter.sort_by.as_ref().and_then(|sort| {
+        COLUMNS
+            .iter()
+            .find(|(column, _)| *column == sort.field)
+            .map(|(_, expr)| (*expr, &sort.direction))
+    });
+    match sort {
+        Some((expr, SortDirection::Asc)) => format!(" ORDER BY {} ASC, c.id", expr),
+        Some((expr, SortDirection::Desc)) => format!(" ORDER BY {} DESC, c.id", expr),
+        None => " ORDER BY c.name, c.id".to_string(),
+    }
+}
+
+/// 读取一行（首列为客户ID，之后按投影顺序）
+fn read_row(
+    row: &rusqlite::Row<'_>,
+    columns: &[(&str, &str)],
+) -> rusqlite::Result<CustomerListRow> {
+    let mut cells = BTreeMap::new();
+    for (index, (column, _)) in columns.iter().enumerate() {
+        if let Some(value) = row.get::<_, Option<String>>(index + 1)? {
+            cells.insert((*column).to_string(), value);
+        }
+    }
+    Ok(CustomerListRow {
+        id: get_uuid(row, 0)?,
+        cells,
+    })
+}
+
+impl CustomerListStore {
+    /// 创建客户列表存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    fn translator() -> FilterTranslator {
+        FilterTranslator::new(EntityKind::Customer, "c.id")
+            .column("name", "c.name")
+            .column("company", "c.company")
+            .column("email", "c.email")
+            .column("phone", "c.phone")
+            .column("churn_risk", "COALESCE(s.churn_risk, 0)")
+    }
+
+    /// 过滤条件和搜索关键词对应的SQL条件
+    fn sql_filter(filter: &QueryFilter) -> CoreResult<SqlFilter> {
+        let mut sql_filter = Self::translator().translate(filter)?;
+        if let Some(search) = filter.search.as_deref().map(str::trim) {
+            if search.chars().count() >= FTS_MIN_CHARS {
+                sql_filter.clauses.push(
+                    "c.id IN (SELECT customer_id FROM customers_fts WHERE customers_fts MATCH ?)"
+                        .to_string(),
+                );
+                sql_filter.params.push(Value::Text(fts_phrase(search)));
+            } else if !search.is_empty() {
+                sql_filter.clauses.push(
+                    "(c.name LIKE ? ESCAPE '\\' OR c.company LIKE ? ESCAPE '\\')".to_string(),
+                );
+                let pattern = Value::Text(like_pattern(search));
+                sql_filter.params.push(pattern.clone());
+                sql_filter.params.push(pattern);
+            }
+        }
+        Ok(sql_filter)
+    }
+
+    /// 按过滤条件和列投影读取一页客户
+    ///
+    /// # Errors
+    ///
+    /// 过滤条件不合法或查询失败时返回错误。
+    pub fn list(
+        &self,
+        filter: &QueryFilter,
+        projection: &Projection,
+    ) -> Result<PagedResult<CustomerListRow>> {
+        let sql_filter = Self::sql_filter(filter)?;
+        let where_clause = sql_filter.where_clause();
+        let columns = projected_columns(projection);
+
+        let conn = self.connection.get_connection()?;
+        let total: u64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM customers c \
+                 LEFT JOIN customer_summary s ON s.customer_id = c.id{}",
+                where_clause
+            ),
+            rusqlite::params_from_iter(sql_filter.params.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut params = sql_filter.params;
+        params.push(Value::Integer(i64::from(filter.pagination.limit())));
+        params.push(Value::Integer(i64::from(filter.pagination.offset())));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM customers c \
+             LEFT JOIN customer_summary s ON s.customer_id = c.id{}{} LIMIT ? OFFSET ?",
+            select_list(projection),
+            where_clause,
+            order_clause(filter)
+        ))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(params.iter()), |row| {
+                read_row(row, &columns)
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(PagedResult::new(rows, total, &filter.pagination))
+    }
+
+    /// 按导出范围分批读取客户，每批最多 `chunk_rows` 行，返回读取的总行数
+    ///
+    /// 筛选结果和整张表在同一查询上逐行读取，不一次读入全部行；选中的行按 `ids` 的顺序
+    /// 返回，已不存在的客户被跳过。
+    ///
+    /// # Errors
+    ///
+    /// 过滤条件不合法、查询失败或 `on_chunk` 返回错误时返回该错误（不再读取后续的行）。
+    pub fn for_each_chunk<F>(
+        &self,
+        scope: &ExportScope,
+        projection: &Projection,
+        chunk_rows: usize,
+        mut on_chunk: F,
+    ) -> CoreResult<u64>
+    where
+        F: FnMut(&[CustomerListRow]) -> CoreResult<()>,
+    {
+        let chunk_rows = chunk_rows.max(1);
+        let columns = projected_columns(projection);
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        let from = "FROM customers c LEFT JOIN customer_summary s ON s.customer_id = c.id";
+        let mut total = 0;
+
+        if let ExportScope::Selection { ids } = scope {
+            for batch in ids.chunks(chunk_rows) {
+                let mut stmt = conn
+                    .prepare(&format!(
+                        "SELECT {} {} WHERE c.id IN ({})",
+                        select_list(projection),
+                        from,
+                        vec!["?"; batch.len()].join(", ")
+                    ))
+                    .map_err(sql_error)?;
+                let mut found: HashMap<Uuid, CustomerListRow> = stmt
+                    .query_map(
+                        rusqlite::params_from_iter(batch.iter().copied().map(DbUuid)),
+                        |row| read_row(row, &columns),
+                    )
+                    .map_err(sql_error)?
+                    .map(|row| row.map(|row| (row.id, row)))
+                    .collect::<rusqlite::Result<_>>()
+                    .map_err(sql_error)?;
+                // 按界面上的顺序排列
+                let chunk: Vec<CustomerListRow> =
+                    batch.iter().filter_map(|id| found.remove(id)).collect();
+                if !chunk.is_empty() {
+                    on_chunk(&chunk)?;
+                    total += chunk.len() as u64;
+                }
+            }
+            return Ok(total);
+        }
+
+        let (sql_filter, order) = match scope {
+            ExportScope::Filtered { filter } => (Self::sql_filter(filter)?, order_clause(filter)),
+            _ => (SqlFilter::default(), order_clause(&QueryFilter::default())),
+        };
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} {}{}{}",
+                select_list(projection),
+                from,
+                sql_filter.where_clause(),
+                order
+            ))
+            .map_err(sql_error)?;
+        let mut rows = stmt
+            .query(rusqlite::params_from_iter(sql_filter.params.iter()))
+            .map_err(sql_error)?;
+        let mut chunk = Vec::with_capacity(chunk_rows);
+        while let Some(row) = rows.next().map_err(sql_error)? {
+            chunk.push(read_row(row, &columns).map_err(sql_error)?);
+            if chunk.len() == chunk_rows {
+                on_chunk(&chunk)?;
+                total += chunk.len() as u64;
+                chunk.clear();
+            }
+        }
+        if !chunk.is_empty() {
+            on_chunk(&chunk)?;
+            total += chunk.len() as u64;
+        }
+        Ok(total)
+    }
+
+    /// 把客户列表导出为CSV文件，返回写出的数据行数
+    ///
+    /// # Errors
+    ///
+    /// 已取消时返回 [`CoreError::Cancelled`]，读取或写入失败时返回错误；两种情况都会
+    /// 删除未完成的文件。
+    pub fn export_csv(
+        &self,
+        request: &CustomerExportRequest,
+        out_path: &Path,
+        progress: &ExportProgress,
+        cancel: &CancellationToken,
+    ) -> CoreResult<u64> {
+        cancel.check()?;
+        let mut writer = CsvFileWriter::create(out_path, &request.headers)?;
+        let columns = &request.projection.columns;
+        self.for_each_chunk(
+            &request.scope,
+            &request.projection,
+            EXPORT_CHUNK_ROWS,
+            |chunk| {
+                cancel.check()?;
+                for row in chunk {
+                    let cells: Vec<String> = columns
+                        .iter()
+                        .map(|column| row.cells.get(column).cloned().unwrap_or_default())
+                        .collect();
+                    writer.write_row(&cells)?;
+                }
+                progress.add(chunk.len() as u64);
+                Ok(())
+            },
+        )?;
+        writer.finish()
+    }
+}
+
+fn sql_error(err: rusqlite::Error) -> CoreError {
+    CoreError::Other(err.to_string())
+}
+
+#[async_trait]
+impl CustomerListReadModel for CustomerListStore {
+    async fn list_customers(
+        &self,
+        filter: &QueryFilter,
+        projection: &Projection,
+    ) -> CoreResult<PagedResult<CustomerListRow>> {
+        self.list(filter, projection).map_err(to_core)
+    }
+}
+
+#[async_trait]
+impl CustomerExportService for CustomerListStore {
+    async fn export_customers(
+        &self,
+        request: &CustomerExportRequest,
+        out_path: &Path,
+        progress: &ExportProgress,
+        cancel: &CancellationToken,
+    ) -> CoreResult<u64> {
+        self.export_csv(request, out_path, progress, cancel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbUuid;
+    use crate::test_support::migrated_pool;
+    use rusqlite::params;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, CustomerListStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = CustomerListStore::new(connection.clone());
+        (temp_dir, connection, store)
+    }
+
+    #[test]
+    fn test_hidden_computed_columns_are_not_queried() {
+        let visible = select_list(&Projection::new(["name", "phone"]));
+        assert_eq!(visible, "c.id, c.name, c.phone");
+        assert!(!visible.contains("interactions"));
+        assert!(!visible.contains("orders"));
+
+        let with_totals = select_list(&Projection::new(["order_total", "name", "removed"]));
+        assert!(with_totals.contains("FROM orders"));
+        assert!(!with_totals.contains("interactions"));
+    }
+
+    #[tokio::test]
+    async fn test_rows_contain_only_projected_columns() {
+        let (_dir, connection, store) = create_test_store();
+        let id = Uuid::new_v4();
+        let now = "2024-03-01T09:00:00.000000Z";
+        let conn = connection.get_connection().unwrap();
+        conn.execute(
+            "INSERT INTO customers (id, name, phone, created_at, updated_at)
+             VALUES (?1, '华东板材', '0571-1234', ?2, ?2)",
+            params![DbUuid(id), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO orders (id, order_number, customer_id, total_amount, created_at, updated_at)
+             VALUES (?1, 'SO-0001', ?2, 1280000, ?3, ?3)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(id), now],
+        )
+        .unwrap();
+        drop(conn);
+
+        let page = store
+            .list_customers(
+                &QueryFilter::new(),
+                &Projection::new(["name", "order_total"]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        let cells = &page.items[0].cells;
+        assert_eq!(cells.get("name").map(String::as_str), Some("华东板材"));
+        assert_eq!(
+            cells.get("order_total").map(String::as_str),
+            Some("12800.00")
+        );
+        assert!(!cells.contains_key("phone"));
+    }
+
+    #[tokio::test]
+    async fn test_summary_columns_read_customer_summary() {
+        let (_dir, connection, store) = create_test_store();
+        let id = Uuid::new_v4();
+        let now = "2024-03-01T09:00:00.000000Z";
+        let conn = connection.get_connection().unwrap();
+        conn.execute(
+            "INSERT INTO customers (id, name, created_at, updated_at)
+             VALUES (?1, '华东板材', ?2, ?2)",
+            params![DbUuid(id), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO tasks (id, customer_id, title, created_at, updated_at)
+             VALUES (?1, ?2, '回访', ?3, ?3)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(id), now],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO interactions (id, customer_id, kind, content, occurred_at)
+             VALUES (?1, ?2, 'call', '电话回访', ?3)",
+            params![DbUuid(Uuid::new_v4()), DbUuid(id), now],
+        )
+        .unwrap();
+        drop(conn);
+
+        let projection = Projection::new(["name", "last_contact", "open_tasks", "outstanding"]);
+        assert!(!select_list(&projection).contains("interactions"));
+
+        // 汇总尚未刷新时按空值显示
+        let page = store
+            .list_customers(&QueryFilter::new(), &projection)
+            .await
+            .unwrap();
+        let cells = &page.items[0].cells;
+        assert!(!cells.contains_key("last_contact"));
+        assert_eq!(cells.get("open_tasks").map(String::as_str), Some("0"));
+
+        connection
+            .with_transaction(|tx| {
+                crate::repository::customer_summary::refresh_customer(tx, id, chrono::Utc::now())
+            })
+            .unwrap();
+        let page = store
+            .list_customers(&QueryFilter::new(), &projection)
+            .await
+            .unwrap();
+        let cells = &page.items[0].cells;
+        assert_eq!(
+            cells.get("last_contact").map(String::as_str),
+            Some("2024-03-01")
+        );
+        assert_eq!(cells.get("open_tasks").map(String::as_str), Some("1"));
+        assert_eq!(cells.get("outstanding").map(String::as_str), Some("0.00"));
+    }
+
+    #[tokio::test]
+    async fn test_churn_risk_filter() {
+        let (_dir, connection, store) = create_test_store();
+        let now = "2024-03-01T09:00:00.000000Z";
+        let mut at_risk = Uuid::nil();
+        for (name, churn_risk) in [("华东板材", true), ("木立方", false), ("新客户", false)] {
+            let id = Uuid::new_v4();
+            connection
+                .execute(
+                    "INSERT INTO customers (id, name, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?3)",
+                    params![DbUuid(id), name, now],
+                )
+                .unwrap();
+            if name == "新客户" {
+                continue;
+            }
+            connection
+                .execute(
+                    "INSERT INTO customer_summary
+                         (customer_id, activity_score, churn_risk, updated_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![DbUuid(id), if churn_risk { 35 } else { 80 }, churn_risk, now],
+                )
+                .unwrap();
+            if churn_risk {
+                at_risk = id;
+            }
+        }
+
+        let mut filter = QueryFilter::new();
+        filter
+            .filters
+            .insert("churn_risk".to_string(), minicrm_core::FilterValue::Boolean(true));
+        let page = store
+            .list_customers(&filter, &Projection::new(["name", "activity_score"]))
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].id, at_risk);
+        assert_eq!(
+            page.items[0].cells.get("activity_score").map(String::as_str),
+            Some("35")
+        );
+
+        filter
+            .filters
+            .insert("churn_risk".to_string(), minicrm_core::FilterValue::Boolean(false));
+        let page = store
+            .list_customers(&filter, &Projection::new(["name"]))
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+    }
+
+    fn seed_customers(connection: &DatabaseConnection, names: &[&str]) -> Vec<Uuid> {
+        let now = "2024-03-01T09:00:00.000000Z";
+        names
+            .iter()
+            .map(|name| {
+                let id = Uuid::new_v4();
+                connection
+                    .execute(
+                        "INSERT INTO customers (id, name, phone, created_at, updated_at)
+                         VALUES (?1, ?2, '0571-1234', ?3, ?3)",
+                        params![DbUuid(id), name, now],
+                    )
+                    .unwrap();
+                id
+            })
+            .collect()
+    }
+
+    fn export_request(scope: ExportScope) -> CustomerExportRequest {
+        CustomerExportRequest {
+            scope,
+            projection: Projection::new(["name", "phone"]),
+            headers: vec!["客户名称".to_string(), "电话".to_string()],
+        }
+    }
+
+    fn exported_names(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').next().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_export_each_scope() {
+        let (dir, connection, store) = create_test_store();
+        let ids = seed_customers(&connection, &["华东板材", "木立方", "华南五金", "新客户"]);
+        let path = dir.path().join("customers.csv");
+
+        // 全部：按名称排序
+        let progress = ExportProgress::new();
+        let written = store
+            .export_customers(
+                &export_request(ExportScope::All),
+                &path,
+                &progress,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, 4);
+        assert_eq!(progress.rows_written(), 4);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("\u{feff}客户名称,电话\r\n"));
+        assert_eq!(exported_names(&path).len(), 4);
+
+        // 当前筛选结果：与列表相同的搜索条件和排序，不分页
+        let filter = QueryFilter::new()
+            .with_search("华")
+            .with_sort(minicrm_core::SortBy::new("name", SortDirection::Desc))
+            .with_pagination(minicrm_core::Pagination::new(1, 1));
+        let page = store.list(&filter, &Projection::new(["name"])).unwrap();
+        assert_eq!(page.total, 2);
+        let written = store
+            .export_customers(
+                &export_request(ExportScope::Filtered {
+                    filter: filter.clone(),
+                }),
+                &path,
+                &ExportProgress::new(),
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(exported_names(&path), ["华南五金", "华东板材"]);
+        assert_eq!(
+            page.items[0].cells.get("name").map(String::as_str),
+            Some("华南五金")
+        );
+
+        // 选中的行：保持界面上的顺序，已删除的客户被跳过
+        let selection = vec![ids[3], Uuid::new_v4(), ids[0], ids[1]];
+        let mut chunks = Vec::new();
+        let read = store
+            .for_each_chunk(
+                &ExportScope::Selection { ids: selection },
+                &Projection::new(["name"]),
+                2,
+                |chunk| {
+                    chunks.push(chunk.iter().map(|row| row.id).collect::<Vec<_>>());
+                    Ok(())
+                },
+            )
+            .unwrap();
+        assert_eq!(read, 3);
+        assert_eq!(chunks, vec![vec![ids[3]], vec![ids[0], ids[1]]]);
+    }
+
+    #[test]
+    fn test_cancel_midway_removes_partial_file() {
+        let (dir, connection, store) = create_test_store();
+        let names: Vec<String> = (0..1200).map(|i| format!("客户{:04}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        seed_customers(&connection, &names);
+        let path = dir.path().join("customers.csv");
+
+        let cancel = CancellationToken::new();
+        let progress = ExportProgress::new();
+        let watcher = progress.clone();
+        let request = export_request(ExportScope::All);
+        let mut writer = CsvFileWriter::create(&path, &request.headers).unwrap();
+        // 写完第一批后取消
+        let result = store.for_each_chunk(
+            &request.scope,
+            &request.projection,
+            EXPORT_CHUNK_ROWS,
+            |chunk| {
+                cancel.check()?;
+                for row in chunk {
+                    writer.write_row(&[row.cells["name"].clone()])?;
+                }
+                watcher.add(chunk.len() as u64);
+                cancel.cancel();
+                Ok(())
+            },
+        );
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        assert_eq!(progress.rows_written(), EXPORT_CHUNK_ROWS as u64);
+        assert!(path.exists());
+        drop(writer);
+        assert!(!path.exists());
+
+        // 通过导出服务取消同样不留下文件
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = store
+            .export_csv(&request, &path, &ExportProgress::new(), &cancel)
+            .unwrap_err();
+        assert!(err.is_cancelled());
+        assert!(!path.exists());
+    }
+}
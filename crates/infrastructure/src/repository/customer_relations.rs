@@ -0,0 +1,486 @@
+//! 客户关系存储
+//!
+//! 记录客户之间的上下级、推荐和合作关系。上下级关系不允许成环，
+//! 客户被彻底删除后其全部关系随之清理。
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, CustomerRelation, CustomerRelationService, CustomerSummary,
+    DomainEvent, EntityKind, EventEnvelope, EventHandler, RelatedCustomer, RelatedCustomerGroup,
+    RelationKind, RelationRole, SystemClock,
+};
+use rusqlite::{params, OptionalExtension, Row};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{get_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const RELATION_COLUMNS: &str = "id, from_id, to_id, kind, note, created_at";
+
+/// 由 `?1` 出发沿上下级关系向上的全部祖先（含自身）
+const ANCESTORS: &str = "
+    WITH RECURSIVE edges(parent, child) AS (
+        SELECT from_id, to_id FROM customer_relations WHERE kind = 'parent_of'
+        UNION ALL
+        SELECT to_id, from_id FROM customer_relations WHERE kind = 'branch_of'
+    ),
+    ancestors(id) AS (
+        SELECT ?1
+        UNION
+        SELECT edges.parent FROM edges JOIN ancestors ON edges.child = ancestors.id
+    )";
+
+/// 客户关系存储
+#[derive(Clone)]
+pub struct CustomerRelationStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for CustomerRelationStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerRelationStore")
+            .finish_non_exhaustive()
+    }
+}
+
+fn row_to_relation(row: &Row<'_>) -> rusqlite::Result<Option<CustomerRelation>> {
+    let kind: String = row.get(3)?;
+    // 无法识别的类型来自更新版本写入的数据，跳过
+    let Some(kind) = RelationKind::parse(&kind) else {
+        return Ok(None);
+    };
+    Ok(Some(CustomerRelation {
+        id: get_uuid(row, 0)?,
+        from_id: get_uuid(row, 1)?,
+        to_id: get_uuid(row, 2)?,
+        kind,
+        note: row.get(4)?,
+        created_at: get_time(row, 5)?,
+    }))
+}
+
+/// 两条关系是否表达同一事实
+fn same_fact(a: &CustomerRelation, b: &CustomerRelation) -> bool {
+    match (a.kind, b.kind) {
+        (RelationKind::Partner, RelationKind::Partner) => true,
+        (RelationKind::ReferredBy, RelationKind::ReferredBy) => {
+            a.from_id == b.from_id && a.to_id == b.to_id
+        }
+        _ => a.hierarchy_edge().is_some() && a.hierarchy_edge() == b.hierarchy_edge(),
+    }
+}
+
+impl CustomerRelationStore {
+    /// 创建客户关系存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 写入一条关系
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn insert(&self, relation: &CustomerRelation) -> Result<()> {
+        self.connection.execute(
+            &format!(
+                "INSERT INTO customer_relations ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                RELATION_COLUMNS
+            ),
+            params![
+                DbUuid(relation.id),
+                DbUuid(relation.from_id),
+                DbUuid(relation.to_id),
+                relation.kind.as_str(),
+                relation.note,
+                time_key(relation.created_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 两个客户之间的全部关系（不分方向）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn between(&self, a: Uuid, b: Uuid) -> Result<Vec<CustomerRelation>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM customer_relations
+                     WHERE (from_id = ?1 AND to_id = ?2) OR (from_id = ?2 AND to_id = ?1)",
+                    RELATION_COLUMNS
+                ),
+                [DbUuid(a), DbUuid(b)],
+                row_to_relation,
+            )?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// 客户参与的全部关系
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn for_customer(&self, customer_id: Uuid) -> Result<Vec<CustomerRelation>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM customer_relations
+                     WHERE from_id = ?1 OR to_id = ?1 ORDER BY created_at",
+                    RELATION_COLUMNS
+                ),
+                [DbUuid(customer_id)],
+                row_to_relation,
+            )?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// `ancestor` 是否为 `customer_id` 的上级（含间接上级及自身）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn is_ancestor(&self, ancestor: Uuid, customer_id: Uuid) -> Result<bool> {
+        let count: i64 = self.connection.query_row(
+            &format!("{} SELECT COUNT(*) FROM ancestors WHERE id = ?2", ANCESTORS),
+            [DbUuid(customer_id), DbUuid(ancestor)],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// 删除客户参与的全部关系，返回删除的条数
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn delete_for_customer(&self, customer_id: Uuid) -> Result<usize> {
+        self.connection.execute(
+            "DELETE FROM customer_relations WHERE from_id = ?1 OR to_id = ?1",
+            [DbUuid(customer_id)],
+        )
+    }
+
+    /// 未删除客户的摘要
+    fn summary(&self, customer_id: Uuid) -> Result<Option<CustomerSummary>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT id, name, phone FROM customers WHERE id = ?1 AND deleted_at IS NULL",
+                [DbUuid(customer_id)],
+                |row| {
+                    Ok(CustomerSummary {
+                        id: get_uuid(row, 0)?,
+                        name: row.get(1)?,
+                        phone: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn require_customer(&self, customer_id: Uuid) -> CoreResult<CustomerSummary> {
+        self.summary(customer_id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("客户 {}", customer_id)))
+    }
+}
+
+#[async_trait]
+impl CustomerRelationService for CustomerRelationStore {
+    async fn add_relation(&self, relation: CustomerRelation) -> CoreResult<CustomerRelation> {
+        if relation.from_id == relation.to_id {
+            return Err(CoreError::validation("客户不能与自身建立关系"));
+        }
+        let from = self.require_customer(relation.from_id)?;
+        let to = self.require_customer(relation.to_id)?;
+
+        let existing = self
+            .between(relation.from_id, relation.to_id)
+            .map_err(to_core)?;
+        if existing.iter().any(|r| same_fact(r, &relation)) {
+            return Err(CoreError::conflict(format!(
+                "「{}」与「{}」之间已存在该关系",
+                from.name, to.name
+            )));
+        }
+        if let Some((parent, child)) = relation.hierarchy_edge() {
+            if self.is_ancestor(child, parent).map_err(to_core)? {
+                return Err(CoreError::business(
+                    "上下级关系不能成环：下级客户不能同时是自己的上级",
+                ));
+            }
+        }
+
+        let relation = CustomerRelation {
+            note: relation
+                .note
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty()),
+            created_at: self.clock.now(),
+            ..relation
+        };
+        self.insert(&relation).map_err(to_core)?;
+        debug!(
+            "客户关系: {} -[{}]-> {}",
+            from.name,
+            relation.kind.as_str(),
+            to.name
+        );
+        Ok(relation)
+    }
+
+    async fn remove_relation(&self, relation_id: Uuid) -> CoreResult<()> {
+        let removed = self
+            .connection
+            .execute(
+                "DELETE FROM customer_relations WHERE id = ?1",
+                [DbUuid(relation_id)],
+            )
+            .map_err(to_core)?;
+        if removed == 0 {
+            return Err(CoreError::not_found(format!("客户关系 {}", relation_id)));
+        }
+        Ok(())
+    }
+
+    async fn related_customers(&self, customer_id: Uuid) -> CoreResult<Vec<RelatedCustomerGroup>> {
+        let mut groups: BTreeMap<RelationRole, Vec<RelatedCustomer>> = BTreeMap::new();
+        for relation in self.for_customer(customer_id).map_err(to_core)? {
+            // 回收站中的客户不显示
+            let Some(other) = self
+                .summary(relation.other_party(customer_id))
+                .map_err(to_core)?
+            else {
+                continue;
+            };
+            let role = relation.kind.role_of_other(relation.from_id == customer_id);
+            groups
+                .entry(role)
+                .or_default()
+                .push(RelatedCustomer { relation, other });
+        }
+        Ok(groups
+            .into_iter()
+            .map(|(role, mut customers)| {
+                customers.sort_by(|a, b| a.other.name.cmp(&b.other.name));
+                RelatedCustomerGroup { role, customers }
+            })
+            .collect())
+    }
+}
+
+impl EventHandler for CustomerRelationStore {
+    fn name(&self) -> &str {
+        "customer_relations"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        if let DomainEvent::EntityPurged {
+            entity: EntityKind::Customer,
+            id,
+            ..
+        } = &envelope.event
+        {
+            let removed = self.delete_for_customer(*id)?;
+            if removed > 0 {
+                info!("客户 {} 已彻底删除，清理 {} 条客户关系", id, removed);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, CustomerRelationStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = CustomerRelationStore::new(connection.clone());
+        (temp_dir, connection, store)
+    }
+
+    fn insert_customer(connection: &DatabaseConnection, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DbUuid(id), name, now],
+            )
+            .unwrap();
+        id
+    }
+
+    fn relation(from_id: Uuid, to_id: Uuid, kind: RelationKind) -> CustomerRelation {
+        CustomerRelation {
+            id: Uuid::new_v4(),
+            from_id,
+            to_id,
+            kind,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_self_and_duplicate_relations() {
+        let (_dir, connection, store) = create_test_store();
+        let a = insert_customer(&connection, "红星家居");
+        let b = insert_customer(&connection, "红星家居·南湖店");
+
+        assert!(matches!(
+            store
+                .add_relation(relation(a, a, RelationKind::Partner))
+                .await,
+            Err(CoreError::Validation(_))
+        ));
+
+        store
+            .add_relation(relation(a, b, RelationKind::ParentOf))
+            .await
+            .unwrap();
+        // 同类重复、以及反向表达的同一上下级关系都视为重复
+        for duplicate in [
+            relation(a, b, RelationKind::ParentOf),
+            relation(b, a, RelationKind::BranchOf),
+        ] {
+            assert!(matches!(
+                store.add_relation(duplicate).await,
+                Err(CoreError::Conflict(_))
+            ));
+        }
+        // 合作关系不区分方向
+        store
+            .add_relation(relation(a, b, RelationKind::Partner))
+            .await
+            .unwrap();
+        assert!(matches!(
+            store
+                .add_relation(relation(b, a, RelationKind::Partner))
+                .await,
+            Err(CoreError::Conflict(_))
+        ));
+        // 不同类型的关系可以共存
+        store
+            .add_relation(relation(b, a, RelationKind::ReferredBy))
+            .await
+            .unwrap();
+
+        let groups = store.related_customers(b).await.unwrap();
+        let roles: Vec<RelationRole> = groups.iter().map(|g| g.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                RelationRole::Parent,
+                RelationRole::Referrer,
+                RelationRole::Partner
+            ]
+        );
+        assert_eq!(groups[0].customers[0].other.name, "红星家居");
+    }
+
+    #[tokio::test]
+    async fn test_hierarchy_cycle_detection() {
+        let (_dir, connection, store) = create_test_store();
+        let group = insert_customer(&connection, "集团总部");
+        let region = insert_customer(&connection, "华东区");
+        let store_a = insert_customer(&connection, "苏州门店");
+
+        store
+            .add_relation(relation(group, region, RelationKind::ParentOf))
+            .await
+            .unwrap();
+        store
+            .add_relation(relation(store_a, region, RelationKind::BranchOf))
+            .await
+            .unwrap();
+        assert!(store.is_ancestor(group, store_a).unwrap());
+
+        // 三级之下的门店不能成为集团的上级，无论以哪种方向表达
+        for cyclic in [
+            relation(store_a, group, RelationKind::ParentOf),
+            relation(group, store_a, RelationKind::BranchOf),
+        ] {
+            assert!(matches!(
+                store.add_relation(cyclic).await,
+                Err(CoreError::Business(_))
+            ));
+        }
+        // 推荐关系不受层级约束
+        store
+            .add_relation(relation(group, store_a, RelationKind::ReferredBy))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_relations_removed_when_customer_purged() {
+        let (_dir, connection, store) = create_test_store();
+        let a = insert_customer(&connection, "老客户");
+        let b = insert_customer(&connection, "被推荐客户");
+        let c = insert_customer(&connection, "合作方");
+
+        store
+            .add_relation(relation(b, a, RelationKind::ReferredBy))
+            .await
+            .unwrap();
+        let kept = store
+            .add_relation(relation(b, c, RelationKind::Partner))
+            .await
+            .unwrap();
+
+        connection
+            .execute("DELETE FROM customers WHERE id = ?1", [DbUuid(a)])
+            .unwrap();
+        store
+            .handle(&EventEnvelope::new(DomainEvent::EntityPurged {
+                entity: EntityKind::Customer,
+                id: a,
+                was_soft_deleted: false,
+            }))
+            .unwrap();
+
+        assert!(store.for_customer(a).unwrap().is_empty());
+        let remaining = store.for_customer(b).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, kept.id);
+
+        store.remove_relation(kept.id).await.unwrap();
+        assert!(matches!(
+            store.remove_relation(kept.id).await,
+            Err(CoreError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,699 @@
+//! 客户列表汇总
+//!
+//! `customer_summary` 表按客户保存列表需要的汇总值：最近互动时间、未完成任务数、
+//! 未收款金额和近12个月已接受报价金额。客户列表只连接这一张表，不再逐行执行相关子查询。
+//!
+//! 汇总由领域事件维护，每个事件只按源表重新计算所属客户的一行。写入互动记录、收款的存储
+//! 在自身事务中调用 [`apply_event`]；任务、报价等其他变更通过事件总线订阅
+//! [`CustomerSummaryStore`]。每日对账任务重新计算全部客户，偏差以警告记录并校正；
+//! 滑出12个月窗口的报价金额也在对账时移出。
+//!
+//! 金额按单据日期生效的汇率折算为本位币。缺少汇率的单据不计入金额，而是记在
+//! `missing_rate_documents` 中，由界面提示补录汇率。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Months, NaiveTime, Utc};
+use minicrm_core::{
+    Clock, CoreResult, Currency, DomainEvent, EntityKind, EventEnvelope, EventHandler, Job,
+    JobSchedule, Money, SystemClock,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, DbUuid};
+
+/// 单据折合本位币的汇率表达式（`alias` 为带 `currency`、`created_at` 列的单据表别名），
+/// 缺少汇率时为 NULL
+pub(crate) fn rate_sql(alias: &str) -> String {
+    format!(
+        "(CASE WHEN {a}.currency = '{base}' THEN 1.0 ELSE
+            (SELECT r.rate_to_base FROM exchange_rates r
+             WHERE r.currency = {a}.currency AND r.effective_date <= substr({a}.created_at, 1, 10)
+             ORDER BY r.effective_date DESC LIMIT 1) END)",
+        a = alias,
+        base = Currency::BASE.as_str()
+    )
+}
+
+/// 按源表计算汇总值（`?1` 为近12个月报价的起始时间），调用方追加筛选条件
+fn compute_sql() -> String {
+    let balance = "(o.total_amount - COALESCE((SELECT SUM(p.amount) FROM order_payments p
+                                              WHERE p.order_id = o.id), 0))";
+    let orders = "FROM orders o WHERE o.customer_id = c.id AND o.deleted_at IS NULL";
+    let quotes = "FROM quotes q
+                  WHERE q.customer_id = c.id AND q.deleted_at IS NULL
+                    AND q.status = 'accepted'
+                    AND julianday(q.updated_at) >= julianday(?1)";
+    format!(
+        "SELECT c.id,
+            (SELECT MAX(i.occurred_at) FROM interactions i WHERE i.customer_id = c.id),
+            (SELECT COUNT(*) FROM tasks t
+             WHERE t.customer_id = c.id AND t.deleted_at IS NULL
+               AND lower(t.status) NOT IN ('completed', 'cancelled')),
+            COALESCE((SELECT CAST(SUM(ROUND({balance} * {order_rate})) AS INTEGER) {orders}), 0),
+            COALESCE((SELECT CAST(SUM(ROUND(q.total_amount * 100 * {quote_rate})) AS INTEGER)
+                      {quotes}), 0),
+            (SELECT COUNT(*) {orders} AND {balance} <> 0 AND {order_rate} IS NULL)
+              + (SELECT COUNT(*) {quotes} AND {quote_rate} IS NULL)
+         FROM customers c",
+        balance = balance,
+        orders = orders,
+        quotes = quotes,
+        order_rate = rate_sql("o"),
+        quote_rate = rate_sql("q"),
+    )
+}
+
+const SUMMARY_COLUMNS: &str = "customer_id, last_interaction_at, open_task_count, \
+     outstanding_amount, accepted_quote_total_12m, missing_rate_documents";
+
+/// 客户汇总值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomerSummary {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 最近互动时间
+    pub last_interaction_at: Option<DateTime<Utc>>,
+    /// 未完成任务数
+    pub open_task_count: u64,
+    /// 未收款金额（订单总额减已收款，本位币）
+    pub outstanding_amount: Money,
+    /// 近12个月已接受报价金额（本位币）
+    pub accepted_quote_total_12m: Money,
+    /// 缺少汇率、未计入上述金额的单据数
+    pub missing_rate_documents: u64,
+}
+
+/// 对账发现的单个客户偏差
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryDrift {
+    /// 对账前保存的汇总（缺失时为 `None`）
+    pub stored: Option<CustomerSummary>,
+    /// 按源表重新计算的汇总
+    pub actual: CustomerSummary,
+}
+
+/// 对账结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SummaryReconciliation {
+    /// 检查的客户数
+    pub checked: usize,
+    /// 存在偏差并已校正的客户
+    pub drifted: Vec<SummaryDrift>,
+}
+
+/// 近12个月报价窗口的起始时间
+fn quote_window_start(now: DateTime<Utc>) -> String {
+    time_key(now.checked_sub_months(Months::new(12)).unwrap_or(now))
+}
+
+fn row_to_summary(row: &Row<'_>) -> rusqlite::Result<CustomerSummary> {
+    let last_interaction_at = row
+        .get::<_, Option<String>>(1)?
+        .map(|value| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        1,
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })
+        })
+        .transpose()?;
+    Ok(CustomerSummary {
+        customer_id: get_uuid(row, 0)?,
+        last_interaction_at,
+        open_task_count: u64::try_from(row.get::<_, i64>(2)?).unwrap_or(0),
+        outstanding_amount: Money::from_cents(row.get(3)?),
+        accepted_quote_total_12m: Money::from_cents(row.get(4)?),
+        missing_rate_documents: u64::try_from(row.get::<_, i64>(5)?).unwrap_or(0),
+    })
+}
+
+/// 按源表重新计算客户的汇总并写入（客户不存在时不写入）
+///
+/// # Errors
+///
+/// 查询或写入失败时返回错误。
+pub fn refresh_customer(conn: &Connection, customer_id: Uuid, now: DateTime<Utc>) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO customer_summary ({}, updated_at)
+             SELECT *, ?3 FROM ({} WHERE c.id = ?2) WHERE true
+             ON CONFLICT(customer_id) DO UPDATE SET
+                 last_interaction_at = excluded.last_interaction_at,
+                 open_task_count = excluded.open_task_count,
+                 outstanding_amount = excluded.outstanding_amount,
+                 accepted_quote_total_12m = excluded.accepted_quote_total_12m,
+                 missing_rate_documents = excluded.missing_rate_documents,
+                 updated_at = excluded.updated_at",
+            SUMMARY_COLUMNS,
+            compute_sql()
+        ),
+        params![quote_window_start(now), DbUuid(customer_id), time_key(now)],
+    )
+    .with_context(|| format!("无法刷新客户汇总: {}", customer_id))?;
+    Ok(())
+}
+
+/// 记录所属的客户
+pub(crate) fn owner(conn: &Connection, kind: EntityKind, id: Uuid) -> Result<Option<Uuid>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT customer_id FROM {} WHERE id = ?1", kind.table_name()),
+            [DbUuid(id)],
+            |row| get_uuid(row, 0),
+        )
+        .optional()?)
+}
+
+/// 按领域事件刷新受影响客户的汇总
+///
+/// 写入源表的存储在同一事务中调用，汇总与源表同时提交。重复处理同一事件没有副作用。
+///
+/// # Errors
+///
+/// 查询或写入失败时返回错误。
+pub fn apply_event(conn: &Connection, event: &DomainEvent, now: DateTime<Utc>) -> Result<()> {
+    let customer_id = match event {
+        DomainEvent::InteractionLogged { customer_id, .. }
+        | DomainEvent::PaymentRecorded { customer_id, .. }
+        | DomainEvent::QuoteStatusChanged { customer_id, .. } => Some(*customer_id),
+        DomainEvent::EntityCreated { entity, id }
+        | DomainEvent::EntityUpdated { entity, id }
+        | DomainEvent::EntitySoftDeleted { entity, id }
+        | DomainEvent::EntityRestored { entity, id } => match entity {
+            EntityKind::Customer => Some(*id),
+            EntityKind::Task | EntityKind::Quote | EntityKind::Order => owner(conn, *entity, *id)?,
+            _ => None,
+        },
+        DomainEvent::TaskStatusChanged { task_id, .. } => owner(conn, EntityKind::Task, *task_id)?,
+        // 彻底删除后无法找到所属客户，由每日对账校正
+        DomainEvent::EntityPurged { .. }
+        | DomainEvent::QuoteCreatedFromOpportunity { .. }
+        | DomainEvent::TaskAssigned { .. }
+        | DomainEvent::TaskNoteAdded { .. } => None,
+    };
+    if let Some(customer_id) = customer_id {
+        refresh_customer(conn, customer_id, now)?;
+    }
+    Ok(())
+}
+
+/// 对比保存的汇总与按源表计算的结果
+fn find_drift(conn: &Connection, now: DateTime<Utc>) -> Result<SummaryReconciliation> {
+    let mut stored = conn
+        .prepare(&format!("SELECT {} FROM customer_summary", SUMMARY_COLUMNS))?
+        .query_map([], row_to_summary)?
+        .map(|row| row.map(|summary| (summary.customer_id, summary)))
+        .collect::<rusqlite::Result<std::collections::HashMap<_, _>>>()?;
+    let actual = conn
+        .prepare(&format!("{} ORDER BY c.id", compute_sql()))?
+        .query_map([quote_window_start(now)], row_to_summary)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut result = SummaryReconciliation {
+        checked: actual.len(),
+        drifted: Vec::new(),
+    };
+    for summary in actual {
+        let before = stored.remove(&summary.customer_id);
+        if before.as_ref() != Some(&summary) {
+            result.drifted.push(SummaryDrift {
+                stored: before,
+                actual: summary,
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// 客户汇总存储
+#[derive(Clone)]
+pub struct CustomerSummaryStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for CustomerSummaryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomerSummaryStore").finish_non_exhaustive()
+    }
+}
+
+impl CustomerSummaryStore {
+    /// 创建客户汇总存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置时钟（测试使用）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 读取保存的汇总
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn get(&self, customer_id: Uuid) -> Result<Option<CustomerSummary>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM customer_summary WHERE customer_id = ?1",
+                    SUMMARY_COLUMNS
+                ),
+                [DbUuid(customer_id)],
+                row_to_summary,
+            )
+            .optional()?)
+    }
+
+    /// 按源表计算汇总（不写入）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn compute(&self, customer_id: Uuid) -> Result<Option<CustomerSummary>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                &format!("{} WHERE c.id = ?2", compute_sql()),
+                params![quote_window_start(self.clock.now()), DbUuid(customer_id)],
+                row_to_summary,
+            )
+            .optional()?)
+    }
+
+    /// 重新计算并写入单个客户的汇总
+    ///
+    /// # Errors
+    ///
+    /// 查询或写入失败时返回错误。
+    pub fn refresh(&self, customer_id: Uuid) -> Result<()> {
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| refresh_customer(tx, customer_id, now))
+    }
+
+    /// 重新计算全部客户的汇总，返回存在偏差的客户（不校正）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn drift(&self) -> Result<SummaryReconciliation> {
+        let conn = self.connection.get_connection()?;
+        find_drift(&conn, self.clock.now())
+    }
+
+    /// 重新计算全部客户的汇总，校正并返回存在偏差的客户
+    ///
+    /// # Errors
+    ///
+    /// 查询或写入失败时返回错误。
+    pub fn reconcile(&self) -> Result<SummaryReconciliation> {
+        let now = self.clock.now();
+        let result = self.connection.with_transaction(|tx| {
+            let result = find_drift(tx, now)?;
+            for drift in &result.drifted {
+                refresh_customer(tx, drift.actual.customer_id, now)?;
+            }
+            Ok(result)
+        })?;
+
+        for drift in &result.drifted {
+            warn!(
+                "客户汇总存在偏差并已校正: {} 保存={:?} 实际={:?}",
+                drift.actual.customer_id, drift.stored, drift.actual
+            );
+        }
+        Ok(result)
+    }
+}
+
+impl EventHandler for CustomerSummaryStore {
+    fn name(&self) -> &str {
+        "customer_summary"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| apply_event(tx, &envelope.event, now))?;
+        Ok(())
+    }
+}
+
+/// 客户汇总对账任务（每日运行）
+#[derive(Debug, Clone)]
+pub struct CustomerSummaryReconciliationJob {
+    store: CustomerSummaryStore,
+    run_at: NaiveTime,
+}
+
+impl CustomerSummaryReconciliationJob {
+    /// 创建对账任务
+    pub fn new(store: CustomerSummaryStore) -> Self {
+        Self {
+            store,
+            run_at: NaiveTime::from_hms_opt(3, 30, 0).unwrap_or_default(),
+        }
+    }
+
+    /// 设置每日运行时间
+    pub fn run_at(mut self, at: NaiveTime) -> Self {
+        self.run_at = at;
+        self
+    }
+}
+
+#[async_trait]
+impl Job for CustomerSummaryReconciliationJob {
+    fn name(&self) -> &str {
+        "customer_summary_reconciliation"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::daily(self.run_at)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let result = self.store.reconcile()?;
+        info!(
+            "客户汇总对账完成: {} 个客户, {} 个存在偏差",
+            result.checked,
+            result.drifted.len()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{ExchangeRateStore, OrderStore};
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, NaiveDate, TimeZone};
+    use minicrm_core::{
+        Address, DeliverySchedule, DeliveryService, DeliveryStatus, ExchangeRate, ManualClock,
+        Order, QuoteStatus,
+    };
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, Arc<ManualClock>) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap(),
+        ));
+        (temp_dir, connection, clock)
+    }
+
+    fn insert_customer(connection: &DatabaseConnection, id: Uuid, now: DateTime<Utc>) {
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, '测试客户', ?2, ?2)",
+                params![DbUuid(id), time_key(now)],
+            )
+            .unwrap();
+    }
+
+    fn insert_task(connection: &DatabaseConnection, customer: Uuid, now: DateTime<Utc>) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO tasks (id, customer_id, title, status, created_at, updated_at)
+                 VALUES (?1, ?2, '回访', 'pending', ?3, ?3)",
+                params![DbUuid(id), DbUuid(customer), time_key(now)],
+            )
+            .unwrap();
+        id
+    }
+
+    fn insert_quote(
+        connection: &DatabaseConnection,
+        customer: Uuid,
+        amount: f64,
+        at: DateTime<Utc>,
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO quotes
+                     (id, customer_id, title, total_amount, status, created_at, updated_at)
+                 VALUES (?1, ?2, '板材报价', ?3, 'sent', ?4, ?4)",
+                params![DbUuid(id), DbUuid(customer), amount, time_key(at)],
+            )
+            .unwrap();
+        id
+    }
+
+    fn accept_quote(
+        connection: &DatabaseConnection,
+        store: &CustomerSummaryStore,
+        quote: Uuid,
+        customer: Uuid,
+        at: DateTime<Utc>,
+    ) {
+        connection
+            .execute(
+                "UPDATE quotes SET status = 'accepted', updated_at = ?2 WHERE id = ?1",
+                params![DbUuid(quote), time_key(at)],
+            )
+            .unwrap();
+        store
+            .handle(&EventEnvelope::new(DomainEvent::QuoteStatusChanged {
+                quote_id: quote,
+                customer_id: customer,
+                from: QuoteStatus::Sent,
+                to: QuoteStatus::Accepted,
+            }))
+            .unwrap();
+    }
+
+    fn order(customer: Uuid, cents: i64, now: DateTime<Utc>) -> Order {
+        Order {
+            id: Uuid::new_v4(),
+            order_number: format!("SO-{}", &Uuid::new_v4().simple().to_string()[..8]),
+            customer_id: customer,
+            quote_id: None,
+            total_amount: Money::from_cents(cents),
+            currency: Currency::CNY,
+            delivery_status: DeliveryStatus::Unscheduled,
+            delivery_date: None,
+            delivery_address: None,
+            vehicle: None,
+            driver: None,
+            signed_by: None,
+            delivered_at: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    fn assert_fresh(store: &CustomerSummaryStore, customer: Uuid) -> CustomerSummary {
+        let stored = store.get(customer).unwrap().unwrap();
+        assert_eq!(Some(stored.clone()), store.compute(customer).unwrap());
+        stored
+    }
+
+    #[tokio::test]
+    async fn test_summary_follows_every_mutation() {
+        let (_dir, connection, clock) = create_test_store();
+        let store = CustomerSummaryStore::new(connection.clone()).with_clock(clock.clone());
+        let orders = OrderStore::new(connection.clone()).with_clock(clock.clone());
+        let now = clock.now();
+        let customer = Uuid::new_v4();
+        insert_customer(&connection, customer, now);
+        store
+            .handle(&EventEnvelope::new(DomainEvent::EntityCreated {
+                entity: EntityKind::Customer,
+                id: customer,
+            }))
+            .unwrap();
+        assert_eq!(assert_fresh(&store, customer).open_task_count, 0);
+
+        // 任务创建与状态变更
+        let task = insert_task(&connection, customer, now);
+        insert_task(&connection, customer, now);
+        store
+            .handle(&EventEnvelope::new(DomainEvent::EntityCreated {
+                entity: EntityKind::Task,
+                id: task,
+            }))
+            .unwrap();
+        assert_eq!(assert_fresh(&store, customer).open_task_count, 2);
+        connection
+            .execute(
+                "UPDATE tasks SET status = 'completed' WHERE id = ?1",
+                [DbUuid(task)],
+            )
+            .unwrap();
+        store
+            .handle(&EventEnvelope::new(DomainEvent::EntityUpdated {
+                entity: EntityKind::Task,
+                id: task,
+            }))
+            .unwrap();
+        assert_eq!(assert_fresh(&store, customer).open_task_count, 1);
+
+        // 订单与收款（收款在订单存储的事务中刷新汇总）
+        let sale = order(customer, 1_280_000, now);
+        orders.insert(&sale).unwrap();
+        store
+            .handle(&EventEnvelope::new(DomainEvent::EntityCreated {
+                entity: EntityKind::Order,
+                id: sale.id,
+            }))
+            .unwrap();
+        assert_eq!(
+            assert_fresh(&store, customer).outstanding_amount,
+            Money::from_cents(1_280_000)
+        );
+        orders
+            .record_payment(
+                sale.id,
+                Money::from_cents(500_000),
+                Currency::CNY,
+                Some("财务".to_string()),
+            )
+            .unwrap();
+        assert_eq!(
+            assert_fresh(&store, customer).outstanding_amount,
+            Money::from_cents(780_000)
+        );
+
+        // 互动记录（安排送货时在订单存储的事务中写入）
+        let logged_at = now + Duration::hours(2);
+        clock.set(logged_at);
+        orders
+            .schedule_delivery(
+                sale.id,
+                DeliverySchedule {
+                    delivery_date: now + Duration::days(2),
+                    address: Address {
+                        province: "广东省".to_string(),
+                        city: "佛山市".to_string(),
+                        district: None,
+                        street: "乐从镇家具城3号".to_string(),
+                        contact: None,
+                        phone: None,
+                    },
+                    vehicle: None,
+                    driver: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            assert_fresh(&store, customer).last_interaction_at,
+            Some(logged_at)
+        );
+
+        // 报价接受：近12个月内计入，窗口外不计入
+        let recent = insert_quote(&connection, customer, 2_000.5, now);
+        accept_quote(&connection, &store, recent, customer, now);
+        let old = insert_quote(&connection, customer, 900.0, now);
+        accept_quote(&connection, &store, old, customer, now - Duration::days(400));
+        let summary = assert_fresh(&store, customer);
+        assert_eq!(summary.accepted_quote_total_12m, Money::from_cents(200_050));
+
+        assert!(store.reconcile().unwrap().drifted.is_empty());
+    }
+
+    #[test]
+    fn test_foreign_currency_converted_or_flagged() {
+        let (_dir, connection, clock) = create_test_store();
+        let store = CustomerSummaryStore::new(connection.clone()).with_clock(clock.clone());
+        let orders = OrderStore::new(connection.clone()).with_clock(clock.clone());
+        let now = clock.now();
+        let customer = Uuid::new_v4();
+        insert_customer(&connection, customer, now);
+        ExchangeRateStore::new(connection.clone())
+            .set(&ExchangeRate {
+                currency: Currency::USD,
+                rate_to_base: 7.2,
+                effective_date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+            })
+            .unwrap();
+
+        let mut usd = order(customer, 10_000, now);
+        usd.currency = Currency::USD;
+        orders.insert(&usd).unwrap();
+        let mut eur = order(customer, 5_000, now);
+        eur.currency = "EUR".parse().unwrap();
+        orders.insert(&eur).unwrap();
+        orders.insert(&order(customer, 30_000, now)).unwrap();
+
+        let quote = insert_quote(&connection, customer, 100.0, now);
+        connection
+            .execute(
+                "UPDATE quotes SET currency = 'USD' WHERE id = ?1",
+                [DbUuid(quote)],
+            )
+            .unwrap();
+        accept_quote(&connection, &store, quote, customer, now);
+
+        // 美元按7.2折算，欧元缺少汇率不计入金额而计入缺汇率单据
+        let summary = assert_fresh(&store, customer);
+        assert_eq!(summary.outstanding_amount, Money::from_cents(72_000 + 30_000));
+        assert_eq!(summary.accepted_quote_total_12m, Money::from_cents(72_000));
+        assert_eq!(summary.missing_rate_documents, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconciliation_fixes_skewed_row() {
+        let (_dir, connection, clock) = create_test_store();
+        let store = CustomerSummaryStore::new(connection.clone()).with_clock(clock.clone());
+        let (skewed, missing) = (Uuid::new_v4(), Uuid::new_v4());
+        for customer in [skewed, missing] {
+            insert_customer(&connection, customer, clock.now());
+            insert_task(&connection, customer, clock.now());
+        }
+        store.refresh(skewed).unwrap();
+
+        // 人为制造偏差
+        connection
+            .execute(
+                "UPDATE customer_summary SET open_task_count = 42, outstanding_amount = -1
+                 WHERE customer_id = ?1",
+                [DbUuid(skewed)],
+            )
+            .unwrap();
+
+        let result = store.reconcile().unwrap();
+        assert_eq!(result.checked, 2);
+        assert_eq!(result.drifted.len(), 2);
+        let drift = result
+            .drifted
+            .iter()
+            .find(|d| d.actual.customer_id == skewed)
+            .unwrap();
+        assert_eq!(drift.stored.as_ref().unwrap().open_task_count, 42);
+        assert_eq!(drift.actual.open_task_count, 1);
+        assert_eq!(store.get(skewed).unwrap(), store.compute(skewed).unwrap());
+        assert_eq!(store.get(missing).unwrap().unwrap().open_task_count, 1);
+
+        let job = CustomerSummaryReconciliationJob::new(store.clone());
+        job.run().await.unwrap();
+        assert!(store.reconcile().unwrap().drifted.is_empty());
+    }
+}
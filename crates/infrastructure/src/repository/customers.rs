@@ -0,0 +1,369 @@
+//! 客户仓储
+//!
+//! [`CustomerRepository`] 的SQLite实现，增删改查由 [`GenericRepository`] 按 [`Customer`] 的
+//! [`EntityMapper`] 完成。联系人保存在 `customers.company` 列，与线索和客户列表一致；
+//! 删除时连同未完成的任务和草稿报价一起移入回收站，已删除的客户不会被查到。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreResult, Customer, CustomerLevel, CustomerRepository, EntityKind, Money, PagedResult,
+    QueryFilter, Repository,
+};
+use rusqlite::types::Value;
+use rusqlite::Row;
+use uuid::Uuid;
+
+use crate::database::db_uuid::{canonical, get_uuid};
+use crate::database::time::{get_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::customer_list::{fts_phrase, FTS_MIN_CHARS};
+use crate::repository::generic::{EntityMapper, GenericRepository};
+use crate::repository::query_builder::like_pattern;
+
+impl EntityMapper for Customer {
+    const KIND: EntityKind = EntityKind::Customer;
+    const TABLE: &'static str = "customers";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "name",
+        "company",
+        "phone",
+        "email",
+        "address",
+        "level",
+        "credit_limit",
+        "credit_hold",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+        "owner_id",
+        "contact_birthday",
+    ];
+    const ORDER_BY: &'static str = "name";
+    const SOFT_DELETE: bool = true;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn to_params(&self) -> Vec<Value> {
+        vec![
+            Value::Text(canonical(self.id)),
+            Value::Text(self.name.clone()),
+            Value::from(self.contact_person.clone()),
+            Value::from(self.phone.clone()),
+            Value::from(self.email.clone()),
+            Value::from(self.address.clone()),
+            Value::Text(self.level.as_str().to_string()),
+            Value::from(self.credit_limit.map(|limit| limit.cents())),
+            Value::from(self.credit_hold),
+            Value::Text(time_key(self.created_at)),
+            Value::Text(time_key(self.updated_at)),
+            Value::from(self.created_by.clone()),
+            Value::from(self.updated_by.clone()),
+            Value::from(self.owner_id.map(canonical)),
+            Value::from(
+                self.contact_birthday
+                    .map(|birthday| birthday.format("%F").to_string()),
+            ),
+        ]
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let level: String = row.get(6)?;
+        let level = CustomerLevel::parse(&level).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                6,
+                rusqlite::types::Type::Text,
+                format!("未知的客户等级: {level}").into(),
+            )
+        })?;
+        Ok(Customer {
+            id: get_uuid(row, 0)?,
+            name: row.get(1)?,
+            contact_person: row.get(2)?,
+            phone: row.get(3)?,
+            email: row.get(4)?,
+            address: row.get(5)?,
+            level,
+            credit_limit: row.get::<_, Option<i64>>(7)?.map(Money::from_cents),
+            credit_hold: row.get(8)?,
+            created_at: get_time(row, 9)?,
+            updated_at: get_time(row, 10)?,
+            created_by: row.get(11)?,
+            updated_by: row.get(12)?,
+            owner_id: row.get::<_, Option<DbUuid>>(13)?.map(Uuid::from),
+            contact_birthday: row.get(14)?,
+        })
+    }
+
+    /// 关键词匹配名称、联系人、电话或邮箱
+    ///
+    /// 名称和联系人足够长时使用全文索引，电话和邮箱按子串匹配。
+    fn search_condition(keyword: &str) -> Option<(String, Vec<Value>)> {
+        let pattern = Value::Text(like_pattern(keyword));
+        Some(if keyword.chars().count() >= FTS_MIN_CHARS {
+            (
+                "(id IN (SELECT customer_id FROM customers_fts WHERE customers_fts MATCH ?) \
+                 OR phone LIKE ? ESCAPE '\\' OR email LIKE ? ESCAPE '\\')"
+                    .to_string(),
+                vec![Value::Text(fts_phrase(keyword)), pattern.clone(), pattern],
+            )
+        } else {
+            (
+                "(name LIKE ? ESCAPE '\\' OR company LIKE ? ESCAPE '\\' \
+                 OR phone LIKE ? ESCAPE '\\' OR email LIKE ? ESCAPE '\\')"
+                    .to_string(),
+                vec![pattern.clone(), pattern.clone(), pattern.clone(), pattern],
+            )
+        })
+    }
+}
+
+/// 基于SQLite的客户仓储
+#[derive(Debug, Clone)]
+pub struct SqliteCustomerRepository {
+    inner: GenericRepository<Customer>,
+}
+
+impl SqliteCustomerRepository {
+    /// 创建客户仓储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            inner: GenericRepository::new(connection),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.inner = self.inner.with_clock(clock);
+        self
+    }
+
+    /// 按名称排序的第一个匹配的客户
+    fn find_first(&self, condition: &str, value: &str) -> CoreResult<Option<Customer>> {
+        Ok(self
+            .inner
+            .find_where(condition, vec![Value::Text(value.to_string())])?
+            .into_iter()
+            .next())
+    }
+}
+
+#[async_trait]
+impl Repository<Customer, Uuid> for SqliteCustomerRepository {
+    async fn find_by_id(&self, id: Uuid) -> CoreResult<Option<Customer>> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn save(&self, entity: &Customer) -> CoreResult<Customer> {
+        self.inner.save(entity).await
+    }
+
+    async fn update(&self, entity: &Customer) -> CoreResult<Customer> {
+        self.inner.update(entity).await
+    }
+
+    async fn delete_by_id(&self, id: Uuid) -> CoreResult<bool> {
+        self.inner.delete_by_id(id).await
+    }
+
+    async fn find_all(&self) -> CoreResult<Vec<Customer>> {
+        self.inner.find_all().await
+    }
+
+    async fn find_with_filter(&self, filter: &QueryFilter) -> CoreResult<PagedResult<Customer>> {
+        self.inner.find_with_filter(filter).await
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for SqliteCustomerRepository {
+    async fn find_by_name(&self, name: &str) -> CoreResult<Vec<Customer>> {
+        self.inner
+            .find_where("name = ?", vec![Value::Text(name.to_string())])
+    }
+
+    async fn find_by_phone(&self, phone: &str) -> CoreResult<Option<Customer>> {
+        self.find_first("phone = ?", phone)
+    }
+
+    async fn find_by_email(&self, email: &str) -> CoreResult<Option<Customer>> {
+        self.find_first("lower(email) = lower(?)", email)
+    }
+
+    async fn find_by_level(&self, level: &CustomerLevel) -> CoreResult<Vec<Customer>> {
+        self.inner
+            .find_where("level = ?", vec![Value::Text(level.as_str().to_string())])
+    }
+
+    async fn search(&self, keyword: &str) -> CoreResult<Vec<Customer>> {
+        let keyword = keyword.trim();
+        match Customer::search_condition(keyword).filter(|_| !keyword.is_empty()) {
+            Some((clause, params)) => self.inner.find_where(&clause, params),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use minicrm_core::{CoreError, FilterValue, ManualClock, Pagination};
+    use tempfile::TempDir;
+
+    fn create_test_repository() -> (TempDir, SqliteCustomerRepository) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 6, 3, 2, 0, 0).unwrap());
+        let repository = SqliteCustomerRepository::new(connection).with_clock(Arc::new(clock));
+        (temp_dir, repository)
+    }
+
+    fn customer(name: &str, phone: &str, level: CustomerLevel) -> Customer {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 8, 30, 0).unwrap();
+        Customer {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            contact_person: Some("王经理".to_string()),
+            contact_birthday: NaiveDate::from_ymd_opt(1980, 3, 15),
+            phone: Some(phone.to_string()),
+            email: Some(format!("{}@example.com", phone)),
+            address: Some("杭州市西湖区".to_string()),
+            level,
+            credit_limit: Some(Money::from_cents(5_000_000)),
+            credit_hold: false,
+            owner_id: Some(Uuid::new_v4()),
+            created_at: now,
+            updated_at: now,
+            created_by: Some("admin".to_string()),
+            updated_by: Some("admin".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_update_and_delete_round_trip() {
+        let (_dir, repository) = create_test_repository();
+        let mut saved = repository
+            .save(&customer("华东建材", "13800138000", CustomerLevel::Vip))
+            .await
+            .unwrap();
+        assert_eq!(
+            repository.find_by_id(saved.id).await.unwrap(),
+            Some(saved.clone())
+        );
+
+        saved.level = CustomerLevel::Important;
+        saved.credit_limit = None;
+        saved.credit_hold = true;
+        saved.contact_birthday = None;
+        repository.update(&saved).await.unwrap();
+        assert_eq!(
+            repository.find_by_id(saved.id).await.unwrap(),
+            Some(saved.clone())
+        );
+
+        assert!(repository.delete_by_id(saved.id).await.unwrap());
+        assert_eq!(repository.find_by_id(saved.id).await.unwrap(), None);
+        assert!(!repository.delete_by_id(saved.id).await.unwrap());
+        let err = repository.update(&saved).await.unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)), "{err}");
+        let missing = customer("不存在", "13900000000", CustomerLevel::Normal);
+        let err = repository.update(&missing).await.unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_level_is_a_mapping_error() {
+        let (_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let repository = SqliteCustomerRepository::new(connection.clone());
+        let saved = repository
+            .save(&customer("华东建材", "13800138000", CustomerLevel::Vip))
+            .await
+            .unwrap();
+        connection
+            .execute(
+                "UPDATE customers SET level = 'platinum' WHERE id = ?1",
+                [DbUuid(saved.id)],
+            )
+            .unwrap();
+
+        // 无法识别的等级不能当作普通客户读出，否则下次保存会把错误的值写回
+        assert!(repository.find_by_id(saved.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_customer_queries() {
+        let (_dir, repository) = create_test_repository();
+        let east = customer("华东建材", "13800138000", CustomerLevel::Vip);
+        let south = customer("华南家具", "13900139000", CustomerLevel::Normal);
+        let north = customer("北方五金", "13700137000", CustomerLevel::Vip);
+        for entity in [&east, &south, &north] {
+            repository.save(entity).await.unwrap();
+        }
+
+        let names = |customers: Vec<Customer>| -> Vec<String> {
+            customers.into_iter().map(|c| c.name).collect()
+        };
+        assert_eq!(repository.find_all().await.unwrap().len(), 3);
+        assert_eq!(
+            names(repository.find_by_name("华南家具").await.unwrap()),
+            ["华南家具"]
+        );
+        assert_eq!(
+            repository.find_by_phone("13700137000").await.unwrap(),
+            Some(north.clone())
+        );
+        assert_eq!(
+            repository
+                .find_by_email("13800138000@EXAMPLE.com")
+                .await
+                .unwrap(),
+            Some(east.clone())
+        );
+        assert_eq!(
+            names(repository.find_by_level(&CustomerLevel::Vip).await.unwrap()),
+            ["北方五金", "华东建材"]
+        );
+        // 短关键词按子串匹配，长关键词使用全文索引，电话始终按子串匹配
+        assert_eq!(
+            names(repository.search("华").await.unwrap()),
+            ["华东建材", "华南家具"]
+        );
+        assert_eq!(
+            names(repository.search("华南家").await.unwrap()),
+            ["华南家具"]
+        );
+        assert_eq!(
+            names(repository.search("0137").await.unwrap()),
+            ["北方五金"]
+        );
+        // 通配符按字面匹配
+        assert!(repository.search("%").await.unwrap().is_empty());
+        assert!(repository.search("_").await.unwrap().is_empty());
+
+        let mut filter = QueryFilter::new();
+        filter
+            .filters
+            .insert("level".to_string(), FilterValue::String("Vip".to_string()));
+        filter.pagination = Pagination {
+            page: 2,
+            page_size: 1,
+        };
+        let page = repository.find_with_filter(&filter).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.total_pages, 2);
+        assert_eq!(names(page.items), ["华东建材"]);
+
+        repository.delete_by_id(east.id).await.unwrap();
+        assert_eq!(repository.find_by_phone("13800138000").await.unwrap(), None);
+        assert_eq!(repository.find_with_filter(&filter).await.unwrap().total, 1);
+    }
+}
@@ -0,0 +1,187 @@
+//! 汇率存储
+//!
+//! `exchange_rates` 表按币种和生效日期保存折合本位币的汇率，某一日期适用最近一次
+//! 生效的汇率。本位币不登记汇率，恒为1。
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use minicrm_core::{CoreError, CoreResult, Currency, ExchangeRate, ExchangeRateService};
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::DatabaseConnection;
+use crate::repository::support::to_core;
+
+/// 日期文本格式
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// 汇率存储
+#[derive(Clone)]
+pub struct ExchangeRateStore {
+    connection: DatabaseConnection,
+}
+
+impl std::fmt::Debug for ExchangeRateStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExchangeRateStore").finish_non_exhaustive()
+    }
+}
+
+impl ExchangeRateStore {
+    /// 创建汇率存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 写入汇率，同一币种同一生效日期已有汇率时覆盖
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn set(&self, rate: &ExchangeRate) -> Result<()> {
+        self.connection
+            .execute(
+                "INSERT INTO exchange_rates (currency, rate_to_base, effective_date, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(currency, effective_date) DO UPDATE SET
+                     rate_to_base = ?2,
+                     updated_at = ?4",
+                params![
+                    rate.currency.as_str(),
+                    rate.rate_to_base,
+                    rate.effective_date.format(DATE_FORMAT).to_string(),
+                    Utc::now().to_rfc3339(),
+                ],
+            )
+            .with_context(|| format!("无法保存汇率: {}", rate.currency))?;
+        Ok(())
+    }
+
+    /// 币种在 `date` 当天生效的汇率（不含本位币）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn at(&self, currency: Currency, date: NaiveDate) -> Result<Option<ExchangeRate>> {
+        let conn = self.connection.get_connection()?;
+        let row = conn
+            .query_row(
+                "SELECT rate_to_base, effective_date FROM exchange_rates
+                 WHERE currency = ?1 AND effective_date <= ?2
+                 ORDER BY effective_date DESC LIMIT 1",
+                params![currency.as_str(), date.format(DATE_FORMAT).to_string()],
+                |row| Ok((row.get::<_, f64>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        row.map(|(rate_to_base, effective_date)| {
+            Ok(ExchangeRate {
+                currency,
+                rate_to_base,
+                effective_date: NaiveDate::parse_from_str(&effective_date, DATE_FORMAT)
+                    .with_context(|| format!("汇率生效日期无效: {}", effective_date))?,
+            })
+        })
+        .transpose()
+    }
+}
+
+#[async_trait]
+impl ExchangeRateService for ExchangeRateStore {
+    async fn set_rate(
+        &self,
+        currency: Currency,
+        rate_to_base: f64,
+        effective_date: NaiveDate,
+    ) -> CoreResult<ExchangeRate> {
+        if currency.is_base() {
+            return Err(CoreError::validation(format!(
+                "{} 是本位币，汇率固定为1",
+                currency
+            )));
+        }
+        if !rate_to_base.is_finite() || rate_to_base <= 0.0 {
+            return Err(CoreError::validation("汇率必须大于0"));
+        }
+        let rate = ExchangeRate {
+            currency,
+            rate_to_base,
+            effective_date,
+        };
+        self.set(&rate).map_err(to_core)?;
+        Ok(rate)
+    }
+
+    async fn rate_at(
+        &self,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> CoreResult<Option<ExchangeRate>> {
+        if currency.is_base() {
+            return Ok(Some(ExchangeRate {
+                currency,
+                rate_to_base: 1.0,
+                effective_date: date,
+            }));
+        }
+        self.at(currency, date).map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, ExchangeRateStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        (temp_dir, ExchangeRateStore::new(connection))
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rate_at_uses_latest_effective_rate() {
+        let (_dir, store) = create_test_store();
+        store
+            .set_rate(Currency::USD, 7.1, date(3, 1))
+            .await
+            .unwrap();
+        store
+            .set_rate(Currency::USD, 7.2, date(5, 1))
+            .await
+            .unwrap();
+        // 同一生效日期覆盖
+        store
+            .set_rate(Currency::USD, 7.25, date(5, 1))
+            .await
+            .unwrap();
+
+        let rate = |d| {
+            let store = store.clone();
+            async move { store.rate_at(Currency::USD, d).await.unwrap() }
+        };
+        assert_eq!(rate(date(2, 28)).await, None);
+        assert_eq!(rate(date(3, 1)).await.unwrap().rate_to_base, 7.1);
+        assert_eq!(rate(date(4, 30)).await.unwrap().rate_to_base, 7.1);
+        let may = rate(date(6, 1)).await.unwrap();
+        assert_eq!(may.rate_to_base, 7.25);
+        assert_eq!(may.effective_date, date(5, 1));
+
+        let base = store.rate_at(Currency::CNY, date(1, 1)).await.unwrap();
+        assert_eq!(base.unwrap().rate_to_base, 1.0);
+
+        assert!(store
+            .set_rate(Currency::CNY, 2.0, date(1, 1))
+            .await
+            .is_err());
+        assert!(store
+            .set_rate(Currency::USD, 0.0, date(1, 1))
+            .await
+            .is_err());
+    }
+}
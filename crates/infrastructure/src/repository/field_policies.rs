@@ -0,0 +1,134 @@
+//! 字段策略存储
+//!
+//! `field_policies` 表按（实体类型，字段名）保存非默认的字段要求；改回可选时删除该行。
+//! 防护规则在 [`FieldPolicy::set`] 中检查，存储只负责读写。
+
+use async_trait::async_trait;
+use minicrm_core::{CoreResult, EntityKind, FieldPolicy, FieldPolicyService, FieldRequirement};
+use rusqlite::params;
+use tracing::info;
+
+use crate::database::DatabaseConnection;
+use crate::repository::support::to_core;
+
+/// 字段策略存储
+#[derive(Debug, Clone)]
+pub struct FieldPolicyStore {
+    connection: DatabaseConnection,
+}
+
+impl FieldPolicyStore {
+    /// 创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    fn load(&self, entity: EntityKind) -> anyhow::Result<FieldPolicy> {
+        let rows = self.connection.query_map(
+            "SELECT field, requirement FROM field_policies WHERE entity = ?1 ORDER BY field",
+            params![entity.table_name()],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )?;
+        let mut policy = FieldPolicy::new(entity);
+        for (field, requirement) in rows {
+            // 无法识别的要求来自更新版本写入的数据，或字段已不可配置，按默认处理
+            if let Some(requirement) = FieldRequirement::parse(&requirement) {
+                let _ = policy.set(&field, requirement);
+            }
+        }
+        Ok(policy)
+    }
+}
+
+#[async_trait]
+impl FieldPolicyService for FieldPolicyStore {
+    async fn field_policy(&self, entity: EntityKind) -> CoreResult<FieldPolicy> {
+        self.load(entity).map_err(to_core)
+    }
+
+    async fn set_field_requirement(
+        &self,
+        entity: EntityKind,
+        key: &str,
+        requirement: FieldRequirement,
+    ) -> CoreResult<FieldPolicy> {
+        let mut policy = self.load(entity).map_err(to_core)?;
+        policy.set(key, requirement)?;
+        let result = match policy.fields.get(key) {
+            Some(requirement) => self.connection.execute(
+                "INSERT INTO field_policies (entity, field, requirement) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(entity, field) DO UPDATE SET requirement = excluded.requirement",
+                params![entity.table_name(), key, requirement.as_str()],
+            ),
+            None => self.connection.execute(
+                "DELETE FROM field_policies WHERE entity = ?1 AND field = ?2",
+                params![entity.table_name(), key],
+            ),
+        };
+        result.map_err(to_core)?;
+        info!(
+            "字段要求已修改: {}.{} -> {}",
+            entity.table_name(),
+            key,
+            requirement.as_str()
+        );
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::CoreError;
+    use tempfile::TempDir;
+
+    fn create_store() -> (TempDir, FieldPolicyStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        (temp_dir, FieldPolicyStore::new(connection))
+    }
+
+    #[tokio::test]
+    async fn test_policy_round_trip_and_guard_rails() {
+        let (_dir, store) = create_store();
+        let customer = EntityKind::Customer;
+        assert_eq!(
+            store.field_policy(customer).await.unwrap(),
+            FieldPolicy::new(customer)
+        );
+
+        store
+            .set_field_requirement(customer, "phone", FieldRequirement::Required)
+            .await
+            .unwrap();
+        store
+            .set_field_requirement(customer, "email", FieldRequirement::Hidden)
+            .await
+            .unwrap();
+        let policy = store.field_policy(customer).await.unwrap();
+        assert!(policy.is_required("phone"));
+        assert!(policy.is_hidden("email"));
+
+        // 改回可选时删除记录
+        let policy = store
+            .set_field_requirement(customer, "phone", FieldRequirement::Optional)
+            .await
+            .unwrap();
+        assert_eq!(policy.fields.len(), 1);
+        assert_eq!(store.field_policy(customer).await.unwrap(), policy);
+
+        // 防护规则拒绝的修改不写入
+        assert!(matches!(
+            store
+                .set_field_requirement(customer, "name", FieldRequirement::Hidden)
+                .await,
+            Err(CoreError::Validation(_))
+        ));
+        assert!(store
+            .set_field_requirement(customer, "id", FieldRequirement::Hidden)
+            .await
+            .is_err());
+        assert_eq!(store.field_policy(customer).await.unwrap(), policy);
+    }
+}
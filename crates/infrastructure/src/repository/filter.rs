@@ -0,0 +1,401 @@
+//! 过滤条件转换
+//!
+//! 把 [`QueryFilter`] 中的过滤条件转换为 SQL `WHERE` 子句。普通字段只接受登记过的列；
+//! `custom.<字段键>` 形式的条件转换为对 `custom_field_values` 的 `EXISTS` 子查询；排除列表
+//! 和不等于转换为 `NOT EXISTS`，没有该字段值的记录也算不在列表中、不等于给定值。
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use minicrm_core::{
+    CompareOp, CoreError, CoreResult, CustomFieldDefinition, EntityKind, FilterValue, QueryFilter,
+    ScalarValue,
+};
+use rusqlite::types::Value;
+
+/// 自定义字段过滤条件的键前缀
+pub const CUSTOM_FIELD_PREFIX: &str = "custom.";
+
+/// 转换后的过滤条件（占位符均为 `?`，参数按出现顺序排列）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SqlFilter {
+    /// 各条件（以 `AND` 连接）
+    pub clauses: Vec<String>,
+    /// 参数
+    pub params: Vec<Value>,
+}
+
+impl SqlFilter {
+    /// `WHERE` 子句（无条件时为空字符串）
+    pub fn where_clause(&self) -> String {
+        if self.clauses.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", self.clauses.join(" AND "))
+        }
+    }
+}
+
+/// 过滤条件转换器
+#[derive(Debug, Clone)]
+pub struct FilterTranslator {
+    entity: EntityKind,
+    id_column: String,
+    columns: BTreeMap<String, String>,
+}
+
+impl FilterTranslator {
+    /// 创建转换器，`id_column` 为主查询中实体ID列的SQL表达式（如 `c.id`）
+    pub fn new(entity: EntityKind, id_column: impl Into<String>) -> Self {
+        Self {
+            entity,
+            id_column: id_column.into(),
+            columns: BTreeMap::new(),
+        }
+    }
+
+    /// 登记可过滤的列
+    pub fn column(mut self, key: impl Into<String>, sql: impl Into<String>) -> Self {
+        self.columns.insert(key.into(), sql.into());
+        self
+    }
+
+    /// 转换过滤条件（按键排序，保证生成的SQL稳定）
+    ///
+    /// # Errors
+    ///
+    /// 过滤键未登记、自定义字段键不合法或值类型不适用时返回验证错误。
+    pub fn translate(&self, filter: &QueryFilter) -> CoreResult<SqlFilter> {
+        let mut result = SqlFilter::default();
+        let mut keys: Vec<&String> = filter.filters.keys().collect();
+        keys.sort();
+        for key in keys {
+            let value = &filter.filters[key];
+            if let Some(field_key) = key.strip_prefix(CUSTOM_FIELD_PREFIX) {
+                if !CustomFieldDefinition::is_valid_key(field_key) {
+                    return Err(CoreError::validation(format!("不支持的过滤条件: {}", key)));
+                }
+                let (exists, (condition, params)) = match value {
+                    FilterValue::NotInStrings(items) => (
+                        "NOT EXISTS",
+                        condition("v.value", &FilterValue::StringList(items.clone()), true)?,
+                    ),
+                    FilterValue::NotInIntegers(items) => (
+                        "NOT EXISTS",
+                        condition("v.value", &FilterValue::IntegerList(items.clone()), true)?,
+                    ),
+                    FilterValue::Compare {
+                        op: CompareOp::Ne,
+                        value,
+                    } => {
+                        let (target, param) = compared("v.value", value, true);
+                        ("NOT EXISTS", (format!("{} = ?", target), vec![param]))
+                    }
+                    _ => ("EXISTS", condition("v.value", value, true)?),
+                };
+                result.clauses.push(format!(
+                    "{} (SELECT 1 FROM custom_field_values v \
+                     WHERE v.entity_type = ? AND v.entity_id = {} AND v.field_key = ? AND {})",
+                    exists, self.id_column, condition
+                ));
+                result
+                    .params
+                    .push(Value::Text(self.entity.table_name().to_string()));
+                result.params.push(Value::Text(field_key.to_string()));
+                result.params.extend(params);
+            } else {
+                let column = self
+                    .columns
+                    .get(key)
+                    .ok_or_else(|| CoreError::validation(format!("不支持的过滤条件: {}", key)))?;
+                let (condition, params) = condition(column, value, false)?;
+                result.clauses.push(condition);
+                result.params.extend(params);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// 时间参数：普通列按存储格式（RFC 3339），自定义字段值按日期
+fn time_param(at: &DateTime<Utc>, custom: bool) -> Value {
+    if custom {
+        Value::Text(at.format("%Y-%m-%d").to_string())
+    } else {
+        Value::Text(at.to_rfc3339_opts(SecondsFormat::Micros, true))
+    }
+}
+
+/// 比较的目标和参数；自定义字段值以文本存储，按数值比较时先转换为数值
+fn compared(target: &str, value: &ScalarValue, custom: bool) -> (String, Value) {
+    match value {
+        ScalarValue::Integer(i) if custom => {
+            (format!("CAST({} AS REAL)", target), Value::Integer(*i))
+        }
+        ScalarValue::Float(f) if custom => (format!("CAST({} AS REAL)", target), Value::Real(*f)),
+        ScalarValue::Integer(i) => (target.to_string(), Value::Integer(*i)),
+        ScalarValue::Float(f) => (target.to_string(), Value::Real(*f)),
+        ScalarValue::String(s) => (target.to_string(), Value::Text(s.clone())),
+        ScalarValue::DateTime(at) => (target.to_string(), time_param(at, custom)),
+    }
+}
+
+/// 单个条件；自定义字段值以文本存储，参数统一转换为存储格式
+fn condition(target: &str, value: &FilterValue, custom: bool) -> CoreResult<(String, Vec<Value>)> {
+    let scalar = |v: &FilterValue| -> CoreResult<Value> {
+        Ok(match (v, custom) {
+            (FilterValue::String(s), _) => Value::Text(s.clone()),
+            (FilterValue::Integer(i), false) => Value::Integer(*i),
+            (FilterValue::Integer(i), true) => Value::Text(i.to_string()),
+            (FilterValue::Float(f), false) => Value::Real(*f),
+            (FilterValue::Float(f), true) => Value::Text(f.to_string()),
+            (FilterValue::Boolean(b), false) => Value::Integer(i64::from(*b)),
+            _ => return Err(CoreError::validation("过滤条件的值类型不适用")),
+        })
+    };
+    match value {
+        FilterValue::StringList(items) if items.is_empty() => Ok(("1 = 0".to_string(), vec![])),
+        FilterValue::IntegerList(items) if items.is_empty() => Ok(("1 = 0".to_string(), vec![])),
+        FilterValue::NotInStrings(items) if items.is_empty() => Ok(("1 = 1".to_string(), vec![])),
+        FilterValue::NotInIntegers(items) if items.is_empty() => Ok(("1 = 1".to_string(), vec![])),
+        FilterValue::StringList(items) => Ok((
+            in_list(target, items.len()),
+            items.iter().map(|s| Value::Text(s.clone())).collect(),
+        )),
+        FilterValue::IntegerList(items) => Ok((
+            in_list(target, items.len()),
+            items
+                .iter()
+                .map(|i| scalar(&FilterValue::Integer(*i)))
+                .collect::<CoreResult<_>>()?,
+        )),
+        FilterValue::NotInStrings(items) => Ok((
+            not_in_list(target, items.len()),
+            items.iter().map(|s| Value::Text(s.clone())).collect(),
+        )),
+        FilterValue::NotInIntegers(items) => Ok((
+            not_in_list(target, items.len()),
+            items
+                .iter()
+                .map(|i| scalar(&FilterValue::Integer(*i)))
+                .collect::<CoreResult<_>>()?,
+        )),
+        FilterValue::DateRange { start, end } => {
+            let mut conditions = Vec::new();
+            let mut params = Vec::new();
+            if let Some(start) = start {
+                conditions.push(format!("{} >= ?", target));
+                params.push(time_param(start, custom));
+            }
+            if let Some(end) = end {
+                conditions.push(format!("{} <= ?", target));
+                params.push(time_param(end, custom));
+            }
+            if conditions.is_empty() {
+                conditions.push("1 = 1".to_string());
+            }
+            Ok((conditions.join(" AND "), params))
+        }
+        FilterValue::Compare { op, value } => {
+            let (target, param) = compared(target, value, custom);
+            Ok((format!("{} {} ?", target, op.as_sql()), vec![param]))
+        }
+        FilterValue::Between { low, high } => {
+            if std::mem::discriminant(low) != std::mem::discriminant(high) {
+                return Err(CoreError::validation("范围上下限的值类型不一致"));
+            }
+            let (target, low) = compared(target, low, custom);
+            let (_, high) = compared(&target, high, custom);
+            Ok((format!("{} BETWEEN ? AND ?", target), vec![low, high]))
+        }
+        FilterValue::Like(pattern) => Ok((
+            format!("{} LIKE ?", target),
+            vec![Value::Text(pattern.clone())],
+        )),
+        other => Ok((format!("{} = ?", target), vec![scalar(other)?])),
+    }
+}
+
+fn in_list(target: &str, len: usize) -> String {
+    format!("{} IN ({})", target, vec!["?"; len].join(", "))
+}
+
+fn not_in_list(target: &str, len: usize) -> String {
+    format!(
+        "({} IS NULL OR {} NOT IN ({}))",
+        target,
+        target,
+        vec!["?"; len].join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{DatabaseConnection, DbUuid};
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use rusqlite::{params, params_from_iter};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_translate_columns_and_custom_fields() {
+        let translator =
+            FilterTranslator::new(EntityKind::Customer, "c.id").column("level", "c.level");
+        let filter = QueryFilter::new()
+            .with_string_filter("level", "vip")
+            .with_string_filter("custom.payment_terms", "月结30天");
+        let sql = translator.translate(&filter).unwrap();
+        assert_eq!(
+            sql.where_clause(),
+            " WHERE EXISTS (SELECT 1 FROM custom_field_values v \
+             WHERE v.entity_type = ? AND v.entity_id = c.id AND v.field_key = ? AND v.value = ?) \
+             AND c.level = ?"
+        );
+        assert_eq!(
+            sql.params,
+            vec![
+                Value::Text("customers".to_string()),
+                Value::Text("payment_terms".to_string()),
+                Value::Text("月结30天".to_string()),
+                Value::Text("vip".to_string()),
+            ]
+        );
+
+        for key in ["phone", "custom.Bad-Key"] {
+            let filter = QueryFilter::new().with_string_filter(key, "x");
+            assert!(matches!(
+                translator.translate(&filter),
+                Err(CoreError::Validation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_translate_comparison_operators() {
+        let translator = FilterTranslator::new(EntityKind::Quote, "q.id")
+            .column("total_amount", "q.total_amount")
+            .column("valid_until", "q.valid_until")
+            .column("remarks", "q.remarks")
+            .column("status", "q.status");
+        let low = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let high = Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap();
+        let filter = QueryFilter::new()
+            .with_gt("total_amount", 10_000)
+            .with_between("valid_until", low, high)
+            .with_like("remarks", "%木业%")
+            .with_not_in("status", ["rejected", "expired"])
+            .with_lte("custom.discount_rate", 0.5);
+        let sql = translator.translate(&filter).unwrap();
+        assert_eq!(
+            sql.where_clause(),
+            " WHERE EXISTS (SELECT 1 FROM custom_field_values v \
+             WHERE v.entity_type = ? AND v.entity_id = q.id AND v.field_key = ? \
+             AND CAST(v.value AS REAL) <= ?) \
+             AND q.remarks LIKE ? \
+             AND (q.status IS NULL OR q.status NOT IN (?, ?)) \
+             AND q.total_amount > ? \
+             AND q.valid_until BETWEEN ? AND ?"
+        );
+        assert_eq!(
+            sql.params,
+            vec![
+                Value::Text("quotes".to_string()),
+                Value::Text("discount_rate".to_string()),
+                Value::Real(0.5),
+                Value::Text("%木业%".to_string()),
+                Value::Text("rejected".to_string()),
+                Value::Text("expired".to_string()),
+                Value::Integer(10_000),
+                Value::Text("2024-06-01T00:00:00.000000Z".to_string()),
+                Value::Text("2024-06-30T00:00:00.000000Z".to_string()),
+            ]
+        );
+
+        // 排除空列表时不过滤
+        let filter = QueryFilter::new().with_not_in_integers("total_amount", Vec::new());
+        assert_eq!(
+            translator.translate(&filter).unwrap().where_clause(),
+            " WHERE 1 = 1"
+        );
+
+        // 上下限类型不一致
+        let mut filter = QueryFilter::new();
+        filter.filters.insert(
+            "total_amount".to_string(),
+            FilterValue::Between {
+                low: ScalarValue::Integer(100),
+                high: ScalarValue::String("abc".to_string()),
+            },
+        );
+        assert!(matches!(
+            translator.translate(&filter),
+            Err(CoreError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_custom_field_exclusions_include_missing_values() {
+        let (_dir, pool) = migrated_pool();
+        let conn = DatabaseConnection::new(pool).get_connection().unwrap();
+        let customers = [
+            ("华东建材", Some("月结30天")),
+            ("华南家具", Some("现结")),
+            ("北方五金", None),
+        ];
+        for (name, terms) in customers {
+            let id = DbUuid(Uuid::new_v4());
+            conn.execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, ?2, '2024-06-01T00:00:00Z', '2024-06-01T00:00:00Z')",
+                params![id, name],
+            )
+            .unwrap();
+            if let Some(terms) = terms {
+                conn.execute(
+                    "INSERT INTO custom_field_values (entity_type, entity_id, field_key, value)
+                     VALUES ('customers', ?1, 'payment_terms', ?2)",
+                    params![id, terms],
+                )
+                .unwrap();
+            }
+        }
+
+        let translator = FilterTranslator::new(EntityKind::Customer, "c.id");
+        let names = |filter: QueryFilter| -> Vec<String> {
+            let sql = translator.translate(&filter).unwrap();
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT c.name FROM customers c{} ORDER BY c.name",
+                    sql.where_clause()
+                ))
+                .unwrap();
+            stmt.query_map(params_from_iter(sql.params), |row| row.get(0))
+                .unwrap()
+                .collect::<rusqlite::Result<_>>()
+                .unwrap()
+        };
+        // 没有该字段值的记录也算不在列表中
+        assert_eq!(
+            names(QueryFilter::new().with_not_in("custom.payment_terms", ["月结30天"])),
+            ["北方五金", "华南家具"]
+        );
+        assert_eq!(
+            names(QueryFilter::new().with_not_in("custom.payment_terms", Vec::<String>::new())),
+            ["北方五金", "华东建材", "华南家具"]
+        );
+        assert_eq!(
+            names(QueryFilter::new().with_string_filter("custom.payment_terms", "现结")),
+            ["华南家具"]
+        );
+        // 不等于与排除列表一致
+        assert_eq!(
+            names(QueryFilter::new().with_compare(
+                "custom.payment_terms",
+                CompareOp::Ne,
+                "月结30天"
+            )),
+            ["北方五金", "华南家具"]
+        );
+    }
+}
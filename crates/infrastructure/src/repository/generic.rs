@@ -1,18 +1,94 @@
 //! 通用Repository实现
 //!
-//! 提供基于SQLite的通用数据访问实现。
+//! 提供基于SQLite的通用数据访问实现。[`EntityMapper`] 给出实体对应的表、列以及与行之间的
+//! 转换，[`GenericRepository`] 据此实现 [`Repository`] 的增删改查：新建和修改在同一事务中
+//! 刷新客户汇总、登记事件发件箱；支持回收站的实体删除时移入回收站（见 [`TrashStore`]），
+//! 查询时不包含已删除的记录。
 
 use std::marker::PhantomData;
+use std::sync::Arc;
 
-use crate::database::DatabaseConnection;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Clock, CoreError, CoreResult, DomainEvent, EntityKind, EventEnvelope, PagedResult, QueryFilter,
+    Repository, SystemClock, TrashService,
+};
+use rusqlite::types::Value;
+use rusqlite::{Row, Transaction};
+use uuid::Uuid;
+
+use crate::database::db_uuid::canonical;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::filter::SqlFilter;
+use crate::repository::query_builder::{like_condition, QueryBuilder};
+use crate::repository::support::to_core;
+use crate::repository::trash::TrashStore;
+use crate::repository::{customer_summary, outbox};
+
+/// 实体与数据表的映射
+pub trait EntityMapper: Sized + Send + Sync {
+    /// 实体类型
+    const KIND: EntityKind;
+
+    /// 数据表名
+    const TABLE: &'static str;
+
+    /// 列名，首列为主键 `id`，也是可过滤、可排序的字段
+    const COLUMNS: &'static [&'static str];
+
+    /// 默认排序
+    const ORDER_BY: &'static str = "id";
+
+    /// 搜索关键词按子串匹配的列
+    const SEARCH_COLUMNS: &'static [&'static str] = &[];
+
+    /// 是否支持移入回收站（表中有 `deleted_at` 列）
+    const SOFT_DELETE: bool = false;
+
+    /// 实体ID
+    fn id(&self) -> Uuid;
+
+    /// 按 [`EntityMapper::COLUMNS`] 的顺序给出写入的值
+    fn to_params(&self) -> Vec<Value>;
+
+    /// 从按 [`EntityMapper::COLUMNS`] 选择的行读取实体
+    ///
+    /// # Errors
+    ///
+    /// 列值无法转换时返回错误。
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self>;
+
+    /// 搜索关键词对应的条件（占位符为 `?`），为空时忽略关键词
+    ///
+    /// 默认在 [`EntityMapper::SEARCH_COLUMNS`] 中按子串匹配。
+    fn search_condition(keyword: &str) -> Option<(String, Vec<Value>)> {
+        like_condition(Self::SEARCH_COLUMNS, keyword)
+    }
+}
 
 /// 通用Repository实现
-///
-/// 这是一个占位符实现，将在后续任务中完善。
 pub struct GenericRepository<T> {
-    #[allow(dead_code)]
     connection: DatabaseConnection,
-    _phantom: PhantomData<T>,
+    clock: Arc<dyn Clock>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for GenericRepository<T> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: self.connection.clone(),
+            clock: self.clock.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for GenericRepository<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GenericRepository").finish_non_exhaustive()
+    }
 }
 
 impl<T> GenericRepository<T> {
@@ -20,9 +96,343 @@ impl<T> GenericRepository<T> {
     pub fn new(connection: DatabaseConnection) -> Self {
         Self {
             connection,
+            clock: Arc::new(SystemClock),
             _phantom: PhantomData,
         }
     }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl<T: EntityMapper> GenericRepository<T> {
+    /// 未删除记录的条件
+    fn live_clause() -> Option<&'static str> {
+        T::SOFT_DELETE.then_some("deleted_at IS NULL")
+    }
+
+    /// 列表查询：映射的列均可过滤和排序
+    fn query_builder() -> QueryBuilder {
+        let builder = T::COLUMNS.iter().fold(
+            QueryBuilder::new(T::KIND, "id").default_sort(T::ORDER_BY),
+            |builder, column| builder.column(*column, *column),
+        );
+        let builder = builder.search(T::search_condition);
+        match Self::live_clause() {
+            Some(clause) => builder.condition(clause),
+            None => builder,
+        }
+    }
+
+    /// 按条件（占位符为 `?`）查询未删除的记录，按默认排序
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn find_where(&self, condition: &str, params: Vec<Value>) -> CoreResult<Vec<T>> {
+        let sql_filter = SqlFilter {
+            clauses: Self::live_clause()
+                .map(str::to_string)
+                .into_iter()
+                .chain(std::iter::once(condition.to_string()))
+                .collect(),
+            params,
+        };
+        self.connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM {}{} ORDER BY {}, id",
+                    T::COLUMNS.join(", "),
+                    T::TABLE,
+                    sql_filter.where_clause(),
+                    T::ORDER_BY
+                ),
+                rusqlite::params_from_iter(sql_filter.params.iter()),
+                T::from_row,
+            )
+            .map_err(to_core)
+    }
+
+    /// 在同一事务中刷新客户汇总并记录事件
+    fn publish(tx: &Transaction<'_>, event: DomainEvent, now: DateTime<Utc>) -> Result<()> {
+        customer_summary::apply_event(tx, &event, now)?;
+        outbox::record(tx, &EventEnvelope::at(event, now))
+    }
+
+    fn insert(tx: &Transaction<'_>, entity: &T) -> Result<()> {
+        let placeholders = (1..=T::COLUMNS.len())
+            .map(|index| format!("?{}", index))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tx.execute(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                T::TABLE,
+                T::COLUMNS.join(", "),
+                placeholders
+            ),
+            rusqlite::params_from_iter(entity.to_params()),
+        )
+        .with_context(|| format!("无法写入 {}", T::TABLE))?;
+        Ok(())
+    }
+
+    /// 修改未删除的记录，返回是否找到
+    fn update_row(tx: &Transaction<'_>, entity: &T) -> Result<bool> {
+        let assignments = T::COLUMNS
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(index, column)| format!("{} = ?{}", column, index + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let live = Self::live_clause()
+            .map(|clause| format!(" AND {}", clause))
+            .unwrap_or_default();
+        let changed = tx
+            .execute(
+                &format!(
+                    "UPDATE {} SET {} WHERE id = ?1{}",
+                    T::TABLE,
+                    assignments,
+                    live
+                ),
+                rusqlite::params_from_iter(entity.to_params()),
+            )
+            .with_context(|| format!("无法修改 {}", T::TABLE))?;
+        Ok(changed > 0)
+    }
+}
+
+#[async_trait]
+impl<T: EntityMapper + Clone + 'static> Repository<T, Uuid> for GenericRepository<T> {
+    async fn find_by_id(&self, id: Uuid) -> CoreResult<Option<T>> {
+        Ok(self
+            .find_where("id = ?", vec![Value::Text(canonical(id))])?
+            .pop())
+    }
+
+    async fn save(&self, entity: &T) -> CoreResult<T> {
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| {
+                Self::insert(tx, entity)?;
+                Self::publish(
+                    tx,
+                    DomainEvent::EntityCreated {
+                        entity: T::KIND,
+                        id: entity.id(),
+                    },
+                    now,
+                )
+            })
+            .map_err(to_core)?;
+        Ok(entity.clone())
+    }
+
+    async fn update(&self, entity: &T) -> CoreResult<T> {
+        let now = self.clock.now();
+        let found = self
+            .connection
+            .with_transaction(|tx| {
+                if !Self::update_row(tx, entity)? {
+                    return Ok(false);
+                }
+                Self::publish(
+                    tx,
+                    DomainEvent::EntityUpdated {
+                        entity: T::KIND,
+                        id: entity.id(),
+                    },
+                    now,
+                )?;
+                Ok(true)
+            })
+            .map_err(to_core)?;
+        if !found {
+            return Err(CoreError::not_found(format!(
+                "{} {}",
+                T::TABLE,
+                entity.id()
+            )));
+        }
+        Ok(entity.clone())
+    }
+
+    async fn delete_by_id(&self, id: Uuid) -> CoreResult<bool> {
+        if !T::SOFT_DELETE {
+            let deleted = self
+                .connection
+                .execute(
+                    &format!("DELETE FROM {} WHERE id = ?1", T::TABLE),
+                    [DbUuid(id)],
+                )
+                .map_err(to_core)?;
+            return Ok(deleted > 0);
+        }
+        if self.find_by_id(id).await?.is_none() {
+            return Ok(false);
+        }
+        TrashStore::new(self.connection.clone())
+            .with_clock(self.clock.clone())
+            .soft_delete(T::KIND, id, None)
+            .await?;
+        Ok(true)
+    }
+
+    async fn find_all(&self) -> CoreResult<Vec<T>> {
+        self.find_where("1 = 1", Vec::new())
+    }
+
+    async fn find_with_filter(&self, filter: &QueryFilter) -> CoreResult<PagedResult<T>> {
+        let query = Self::query_builder().build(filter)?;
+        let where_clause = query.where_clause();
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        let total: u64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {}{}", T::TABLE, where_clause),
+                rusqlite::params_from_iter(query.count_params()),
+                |row| row.get(0),
+            )
+            .map_err(|e| to_core(e.into()))?;
+
+        let items = conn
+            .prepare(&format!(
+                "SELECT {} FROM {}{}{}",
+                T::COLUMNS.join(", "),
+                T::TABLE,
+                where_clause,
+                query.page_clause()
+            ))
+            .and_then(|mut stmt| {
+                stmt.query_map(rusqlite::params_from_iter(query.page_params()), T::from_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(|e| to_core(e.into()))?;
+        Ok(PagedResult::new(items, total, &filter.pagination))
+    }
 }
 
-// TODO: 在后续任务中实现具体的CRUD操作
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{NaiveDate, TimeZone};
+    use minicrm_core::{Customer, CustomerLevel, Money, Task, TaskPriority, TaskStatus};
+    use tempfile::TempDir;
+
+    fn create_test_connection() -> (TempDir, DatabaseConnection) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        (temp_dir, connection)
+    }
+
+    fn customer() -> Customer {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 8, 30, 0).unwrap();
+        Customer {
+            id: Uuid::new_v4(),
+            name: "华东建材".to_string(),
+            contact_person: Some("王经理".to_string()),
+            contact_birthday: NaiveDate::from_ymd_opt(1980, 3, 15),
+            phone: Some("13800138000".to_string()),
+            email: None,
+            address: None,
+            level: CustomerLevel::Vip,
+            credit_limit: Some(Money::from_cents(5_000_000)),
+            credit_hold: false,
+            owner_id: Some(Uuid::new_v4()),
+            created_at: now,
+            updated_at: now,
+            created_by: Some("admin".to_string()),
+            updated_by: None,
+        }
+    }
+
+    fn task(customer_id: Uuid) -> Task {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 8, 30, 0).unwrap();
+        Task {
+            id: Uuid::new_v4(),
+            title: "电话回访".to_string(),
+            description: Some("确认报价".to_string()),
+            status: TaskStatus::InProgress,
+            priority: TaskPriority::High,
+            customer_id: Some(customer_id),
+            supplier_id: None,
+            due_date: Some(now + chrono::Duration::days(2)),
+            created_at: now,
+            updated_at: now,
+            created_by: Some("admin".to_string()),
+            updated_by: None,
+            assigned_to: None,
+        }
+    }
+
+    /// 保存、读取、删除各一次，删除后读不到且不能重复删除
+    async fn assert_round_trip<T>(repository: &GenericRepository<T>, entity: T)
+    where
+        T: EntityMapper + Clone + PartialEq + std::fmt::Debug + 'static,
+    {
+        let id = entity.id();
+        repository.save(&entity).await.unwrap();
+        assert_eq!(repository.find_by_id(id).await.unwrap(), Some(entity));
+        assert!(repository.delete_by_id(id).await.unwrap());
+        assert_eq!(repository.find_by_id(id).await.unwrap(), None);
+        assert!(!repository.delete_by_id(id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_customer_and_task_round_trip() {
+        let (_dir, connection) = create_test_connection();
+        let customers = GenericRepository::<Customer>::new(connection.clone());
+        let tasks = GenericRepository::<Task>::new(connection.clone());
+        let owner = customer();
+        customers.save(&owner).await.unwrap();
+
+        assert_round_trip(&tasks, task(owner.id)).await;
+        assert_round_trip(&customers, customer()).await;
+
+        // 已删除的任务进入回收站，不能再修改
+        let deleted: i64 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM tasks WHERE deleted_at IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(deleted, 1);
+        let mut missing = task(owner.id);
+        missing.title = "不存在".to_string();
+        let err = tasks.update(&missing).await.unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_find_with_filter_uses_mapped_columns() {
+        let (_dir, connection) = create_test_connection();
+        let customers = GenericRepository::<Customer>::new(connection.clone());
+        let tasks = GenericRepository::<Task>::new(connection);
+        let owner = customer();
+        customers.save(&owner).await.unwrap();
+        let mut done = task(owner.id);
+        done.title = "寄送样品".to_string();
+        done.status = TaskStatus::Completed;
+        tasks.save(&done).await.unwrap();
+        tasks.save(&task(owner.id)).await.unwrap();
+
+        let mut filter = QueryFilter::new();
+        filter.search = Some("样品".to_string());
+        let page = tasks.find_with_filter(&filter).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items, vec![done]);
+
+        filter.search = None;
+        filter.filters.insert(
+            "unknown".to_string(),
+            minicrm_core::FilterValue::String("x".to_string()),
+        );
+        assert!(tasks.find_with_filter(&filter).await.is_err());
+    }
+}
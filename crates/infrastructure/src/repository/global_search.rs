@@ -0,0 +1,375 @@
+//! 全局搜索数据源
+//!
+//! 客户名称和公司走全文索引（不足三个字时按子串匹配），电话、订单号、报价单号按去掉
+//! 分隔符后的子串匹配。这里只找出候选，排序和去重见 [`SearchRanking`](minicrm_core::SearchRanking)。
+//! 报价单号取自最近一次保存的修订快照，草稿报价没有快照，搜不到单号。
+//!
+//! 同一数据源也为新建客户时的重复提示提供候选（见 [`minicrm_core::dedup`]）。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    CoreResult, DuplicateCandidate, DuplicateCandidateSource, EntityKind, GlobalSearchSource,
+    SearchCandidate, SearchField,
+};
+use rusqlite::params;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::DatabaseConnection;
+use crate::repository::customer_list::{fts_phrase, FTS_MIN_CHARS};
+use crate::repository::support::to_core;
+
+/// 号码匹配至少需要的数字位数
+const MIN_NUMBER_DIGITS: usize = 3;
+
+/// 去掉电话和单号中的分隔符，与查询中去掉的字符一致
+fn stripped(column: &str) -> String {
+    format!("REPLACE(REPLACE(REPLACE(lower({column}), '-', ''), ' ', ''), '+', '')")
+}
+
+/// 全局搜索数据源
+#[derive(Clone)]
+pub struct GlobalSearchStore {
+    connection: DatabaseConnection,
+}
+
+impl std::fmt::Debug for GlobalSearchStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlobalSearchStore").finish_non_exhaustive()
+    }
+}
+
+impl GlobalSearchStore {
+    /// 创建全局搜索数据源
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 读取与关键词匹配的候选
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn candidates(&self, text: &str, limit: u32) -> Result<Vec<SearchCandidate>> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut candidates = self.customer_names(text, limit)?;
+        let key: String = text
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-' && *c != '+')
+            .flat_map(char::to_lowercase)
+            .collect();
+        if key.chars().filter(char::is_ascii_digit).count() >= MIN_NUMBER_DIGITS {
+            candidates.extend(self.numbers(&key, limit)?);
+        }
+        Ok(candidates)
+    }
+
+    /// 读取可能重复的客户候选：名称包含粗筛键，或电话（去掉分隔符和国家码）相同
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn duplicate_candidates(
+        &self,
+        name_probe: Option<&str>,
+        phone: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<DuplicateCandidate>> {
+        if name_probe.is_none() && phone.is_none() {
+            return Ok(Vec::new());
+        }
+        self.connection.query_map(
+            &format!(
+                "SELECT id, name, phone FROM customers \
+                 WHERE deleted_at IS NULL \
+                   AND ((?1 IS NOT NULL AND instr(lower(name), ?1) > 0) \
+                        OR (?2 IS NOT NULL AND {phone} IN (?2, '86' || ?2))) \
+                 ORDER BY name LIMIT ?3",
+                phone = stripped("phone")
+            ),
+            params![name_probe, phone, limit],
+            |row| {
+                Ok(DuplicateCandidate {
+                    customer_id: get_uuid(row, 0)?,
+                    name: row.get(1)?,
+                    phone: row.get(2)?,
+                })
+            },
+        )
+    }
+
+    /// 名称和公司命中的客户，每个命中的字段一条
+    fn customer_names(&self, text: &str, limit: u32) -> Result<Vec<SearchCandidate>> {
+        type Row = (uuid::Uuid, String, Option<String>, Option<f64>);
+        let map = |row: &rusqlite::Row<'_>| -> rusqlite::Result<Row> {
+            Ok((get_uuid(row, 0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        };
+        let rows = if text.chars().count() >= FTS_MIN_CHARS {
+            self.connection.query_map(
+                "SELECT c.id, c.name, c.company, bm25(customers_fts) FROM customers_fts \
+                 JOIN customers c ON c.id = customers_fts.customer_id \
+                 WHERE customers_fts MATCH ?1 AND c.deleted_at IS NULL \
+                 ORDER BY bm25(customers_fts) LIMIT ?2",
+                params![fts_phrase(text), limit],
+                map,
+            )?
+        } else {
+            self.connection.query_map(
+                "SELECT id, name, company, NULL FROM customers \
+                 WHERE deleted_at IS NULL \
+                   AND (instr(lower(name), lower(?1)) > 0 \
+                        OR instr(lower(COALESCE(company, '')), lower(?1)) > 0) \
+                 ORDER BY name LIMIT ?2",
+                params![text, limit],
+                map,
+            )?
+        };
+
+        let needle = text.to_lowercase();
+        let mut candidates = Vec::new();
+        for (id, name, company, bm25) in rows {
+            let candidate = |field, value: &str| SearchCandidate {
+                entity: EntityKind::Customer,
+                id,
+                title: name.clone(),
+                subtitle: company.clone(),
+                field,
+                value: value.to_string(),
+                bm25,
+            };
+            let in_name = name.to_lowercase().contains(&needle);
+            let in_company = company
+                .as_deref()
+                .is_some_and(|company| company.to_lowercase().contains(&needle));
+            if in_name || !in_company {
+                candidates.push(candidate(SearchField::Name, &name));
+            }
+            if let Some(company) = company.as_deref().filter(|_| in_company) {
+                candidates.push(candidate(SearchField::Company, company));
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// 电话、订单号和报价单号命中的记录
+    fn numbers(&self, key: &str, limit: u32) -> Result<Vec<SearchCandidate>> {
+        let mut candidates = self.connection.query_map(
+            &format!(
+                "SELECT id, name, company, phone FROM customers \
+                 WHERE deleted_at IS NULL AND instr({}, ?1) > 0 LIMIT ?2",
+                stripped("phone")
+            ),
+            params![key, limit],
+            |row| {
+                Ok(SearchCandidate {
+                    entity: EntityKind::Customer,
+                    id: get_uuid(row, 0)?,
+                    title: row.get(1)?,
+                    subtitle: row.get(2)?,
+                    field: SearchField::Phone,
+                    value: row.get(3)?,
+                    bm25: None,
+                })
+            },
+        )?;
+
+        candidates.extend(self.connection.query_map(
+            &format!(
+                "SELECT o.id, o.order_number, c.name FROM orders o \
+                 LEFT JOIN customers c ON c.id = o.customer_id \
+                 WHERE o.deleted_at IS NULL AND instr({}, ?1) > 0 LIMIT ?2",
+                stripped("o.order_number")
+            ),
+            params![key, limit],
+            |row| number_candidate(row, EntityKind::Order, SearchField::OrderNumber),
+        )?);
+
+        candidates.extend(self.connection.query_map(
+            &format!(
+                "SELECT id, quote_number, customer_name FROM ( \
+                     SELECT q.id, json_extract(r.snapshot, '$.quote_number') AS quote_number, \
+                            c.name AS customer_name \
+                     FROM quote_revisions r \
+                     JOIN quotes q ON q.id = r.quote_id AND q.deleted_at IS NULL \
+                     LEFT JOIN customers c ON c.id = q.customer_id \
+                     WHERE r.revision_no = (SELECT MAX(revision_no) FROM quote_revisions \
+                                            WHERE quote_id = r.quote_id) \
+                 ) WHERE instr({}, ?1) > 0 LIMIT ?2",
+                stripped("quote_number")
+            ),
+            params![key, limit],
+            |row| number_candidate(row, EntityKind::Quote, SearchField::QuoteNumber),
+        )?);
+        Ok(candidates)
+    }
+}
+
+/// 单据号命中的候选：列依次为ID、单号、客户名称
+fn number_candidate(
+    row: &rusqlite::Row<'_>,
+    entity: EntityKind,
+    field: SearchField,
+) -> rusqlite::Result<SearchCandidate> {
+    let number: String = row.get(1)?;
+    Ok(SearchCandidate {
+        entity,
+        id: get_uuid(row, 0)?,
+        title: number.clone(),
+        subtitle: row.get(2)?,
+        field,
+        value: number,
+        bm25: None,
+    })
+}
+
+#[async_trait]
+impl GlobalSearchSource for GlobalSearchStore {
+    async fn search_candidates(&self, text: &str, limit: u32) -> CoreResult<Vec<SearchCandidate>> {
+        self.candidates(text, limit).map_err(to_core)
+    }
+}
+
+#[async_trait]
+impl DuplicateCandidateSource for GlobalSearchStore {
+    async fn duplicate_candidates(
+        &self,
+        name_probe: Option<&str>,
+        phone: Option<&str>,
+        limit: u32,
+    ) -> CoreResult<Vec<DuplicateCandidate>> {
+        GlobalSearchStore::duplicate_candidates(self, name_probe, phone, limit).map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbUuid;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::{SearchHit, SearchRanking};
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    const NOW: &str = "2024-03-01T09:00:00.000000Z";
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, GlobalSearchStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = GlobalSearchStore::new(connection.clone());
+        (temp_dir, connection, store)
+    }
+
+    fn insert_customer(
+        connection: &DatabaseConnection,
+        name: &str,
+        company: Option<&str>,
+        phone: Option<&str>,
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, company, phone, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+                params![DbUuid(id), name, company, phone, NOW],
+            )
+            .unwrap();
+        id
+    }
+
+    fn search(store: &GlobalSearchStore, text: &str) -> Vec<SearchHit> {
+        SearchRanking::default().rank(text, store.candidates(text, 20).unwrap(), 10, true)
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<Uuid> {
+        hits.iter().map(|hit| hit.id).collect()
+    }
+
+    #[test]
+    fn test_ranking_per_query_class() {
+        let (_dir, connection, store) = create_test_store();
+        let named = insert_customer(&connection, "138", Some("一三八建材"), None);
+        let owner = insert_customer(&connection, "李强", None, Some("138-0000-0000"));
+        let company = insert_customer(&connection, "刘洋", Some("华美家具有限公司"), None);
+        let lookalike = insert_customer(&connection, "华美家具有限公司采购", None, None);
+        let zhang = insert_customer(&connection, "张伟明", None, None);
+        let wang = insert_customer(&connection, "王张伟", None, None);
+        let order = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO orders
+                     (id, order_number, customer_id, total_amount, created_at, updated_at)
+                 VALUES (?1, 'SO-1380001', ?2, 0, ?3, ?3)",
+                params![DbUuid(order), DbUuid(named), NOW],
+            )
+            .unwrap();
+
+        // 完整手机号：号码完全相同的客户排第一
+        let hits = search(&store, "13800000000");
+        assert_eq!(hits[0].id, owner);
+        assert_eq!(hits[0].matched_fields, vec![SearchField::Phone]);
+
+        // 短数字：名称完全相同的客户在前，电话和订单号前缀命中随后
+        let hits = search(&store, "138");
+        assert_eq!(hits[0].id, named);
+        assert!(ids(&hits).contains(&owner));
+        assert!(hits.iter().any(|hit| hit.entity == EntityKind::Order));
+        assert!(hits.iter().all(|hit| hit.score.is_some()));
+
+        // 含“公司”：公司名称命中的客户排在名称相似的客户前
+        let hits = search(&store, "华美家具有限公司");
+        assert_eq!(ids(&hits)[..2], [company, lookalike]);
+
+        // 短中文：姓名以查询开头的在前
+        let hits = search(&store, "张伟");
+        assert_eq!(ids(&hits), vec![zhang, wang]);
+    }
+
+    #[test]
+    fn test_multi_field_matches_are_deduped() {
+        let (_dir, connection, store) = create_test_store();
+        let id = insert_customer(&connection, "华美家具", Some("华美家具厂"), Some("0571-8888"));
+
+        let candidates = store.candidates("华美家具", 20).unwrap();
+        assert_eq!(candidates.len(), 2);
+        let hits = search(&store, "华美家具");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, id);
+        assert_eq!(
+            hits[0].matched_fields,
+            vec![SearchField::Name, SearchField::Company]
+        );
+
+        // 已删除的客户不出现
+        connection
+            .execute("UPDATE customers SET deleted_at = ?1", [NOW])
+            .unwrap();
+        assert!(search(&store, "华美家具").is_empty());
+        assert!(search(&store, "05718888").is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_candidates() {
+        let (_dir, connection, store) = create_test_store();
+        let by_name = insert_customer(&connection, "杭州西湖装饰有限公司", None, None);
+        let by_phone = insert_customer(&connection, "华东板材", None, Some("+86 138-0013-1234"));
+        insert_customer(&connection, "杭州滨江五金", None, Some("13700000000"));
+        let deleted = insert_customer(&connection, "杭州西湖装饰工程", None, None);
+        connection
+            .execute(
+                "UPDATE customers SET deleted_at = ?1 WHERE id = ?2",
+                params![NOW, DbUuid(deleted)],
+            )
+            .unwrap();
+
+        let found = store
+            .duplicate_candidates(Some("杭州西湖"), Some("13800131234"), 20)
+            .unwrap();
+        let ids: Vec<Uuid> = found.iter().map(|c| c.customer_id).collect();
+        assert_eq!(ids, vec![by_phone, by_name]);
+        assert!(store.duplicate_candidates(None, None, 20).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+//! 节假日存储
+//!
+//! 节假日表按日期保存放假和调休上班日，迁移时预置当年的法定节假日，之后在设置界面维护。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use minicrm_core::{CoreError, CoreResult, HolidayEntry, HolidayKind, HolidayService, LocalDate};
+use rusqlite::{params, Params, Row};
+
+use crate::database::DatabaseConnection;
+use crate::repository::support::to_core;
+
+fn date_key(date: LocalDate) -> String {
+    date.to_string()
+}
+
+fn row_to_entry(row: &Row<'_>) -> rusqlite::Result<Option<HolidayEntry>> {
+    let date: String = row.get(0)?;
+    let name: String = row.get(1)?;
+    let kind: String = row.get(2)?;
+    let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    // 无法识别的类型来自更新版本写入的数据，跳过
+    Ok(HolidayKind::parse(&kind).map(|kind| HolidayEntry {
+        date: date.into(),
+        name,
+        kind,
+    }))
+}
+
+/// 节假日存储
+#[derive(Debug, Clone)]
+pub struct HolidayStore {
+    connection: DatabaseConnection,
+}
+
+impl HolidayStore {
+    /// 创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    fn entries<P: Params>(&self, sql: &str, params: P) -> Result<Vec<HolidayEntry>> {
+        Ok(self
+            .connection
+            .query_map(sql, params, row_to_entry)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl HolidayService for HolidayStore {
+    async fn holidays_in_year(&self, year: i32) -> CoreResult<Vec<HolidayEntry>> {
+        self.entries(
+            "SELECT date, name, kind FROM holidays WHERE date >= ?1 AND date < ?2 ORDER BY date",
+            params![format!("{:04}-01-01", year), format!("{:04}-01-01", year + 1)],
+        )
+        .map_err(to_core)
+    }
+
+    async fn all_holidays(&self) -> CoreResult<Vec<HolidayEntry>> {
+        self.entries("SELECT date, name, kind FROM holidays ORDER BY date", [])
+            .map_err(to_core)
+    }
+
+    async fn save_holiday(&self, entry: HolidayEntry) -> CoreResult<()> {
+        let name = entry.name.trim();
+        if name.is_empty() {
+            return Err(CoreError::validation("节假日名称不能为空"));
+        }
+        self.connection
+            .execute(
+                "INSERT INTO holidays (date, name, kind) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(date) DO UPDATE SET name = excluded.name, kind = excluded.kind",
+                params![date_key(entry.date), name, entry.kind.as_str()],
+            )
+            .map_err(to_core)?;
+        Ok(())
+    }
+
+    async fn remove_holiday(&self, date: LocalDate) -> CoreResult<bool> {
+        let removed = self
+            .connection
+            .execute("DELETE FROM holidays WHERE date = ?1", [date_key(date)])
+            .map_err(to_core)?;
+        Ok(removed > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::WorkingCalendar;
+    use tempfile::TempDir;
+
+    fn create_store() -> (TempDir, HolidayStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        (temp_dir, HolidayStore::new(connection))
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> LocalDate {
+        LocalDate::new(year, month, day).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_seeded_holidays_and_edits() {
+        let (_dir, store) = create_store();
+
+        let seeded = store.holidays_in_year(2026).await.unwrap();
+        assert!(seeded.iter().any(|entry| entry.name == "春节"));
+        let calendar = WorkingCalendar::default().with_holidays(&seeded);
+        assert!(!calendar.is_business_day(date(2026, 10, 1)));
+        assert!(calendar.is_business_day(date(2026, 2, 14)));
+        assert!(store.holidays_in_year(2025).await.unwrap().is_empty());
+
+        // 公司自定的年会放假
+        store
+            .save_holiday(HolidayEntry {
+                date: date(2027, 1, 22),
+                name: "年会".to_string(),
+                kind: HolidayKind::Holiday,
+            })
+            .await
+            .unwrap();
+        // 修改已有的一天
+        store
+            .save_holiday(HolidayEntry {
+                date: date(2026, 2, 28),
+                name: "盘点".to_string(),
+                kind: HolidayKind::Holiday,
+            })
+            .await
+            .unwrap();
+        let next_year = store.holidays_in_year(2027).await.unwrap();
+        assert_eq!(next_year.len(), 1);
+        assert_eq!(next_year[0].name, "年会");
+        let all = store.all_holidays().await.unwrap();
+        let stocktake = all.iter().find(|e| e.date == date(2026, 2, 28)).unwrap();
+        assert_eq!(stocktake.kind, HolidayKind::Holiday);
+        assert_eq!(all.len(), seeded.len() + 1);
+
+        assert!(store.remove_holiday(date(2027, 1, 22)).await.unwrap());
+        assert!(!store.remove_holiday(date(2027, 1, 22)).await.unwrap());
+        assert!(store
+            .save_holiday(HolidayEntry {
+                date: date(2027, 1, 23),
+                name: " ".to_string(),
+                kind: HolidayKind::Holiday,
+            })
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! 命令幂等记录存储
+//!
+//! 记录保存在 `idempotency_records`，超过 [`IDEMPOTENCY_TTL_HOURS`] 后查询时视为不存在，
+//! 由 [`IdempotencyCleanupJob`] 定期删除。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Duration;
+use minicrm_core::{
+    Clock, CoreResult, IdempotencyRecord, IdempotencyService, Job, JobSchedule, SystemClock,
+    IDEMPOTENCY_TTL_HOURS,
+};
+use rusqlite::{params, OptionalExtension};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+/// 幂等记录存储
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for IdempotencyStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdempotencyStore").finish_non_exhaustive()
+    }
+}
+
+impl IdempotencyStore {
+    /// 创建幂等记录存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 早于该时间的记录已过期
+    fn expiry_cutoff(&self) -> String {
+        time_key(self.clock.now() - Duration::hours(IDEMPOTENCY_TTL_HOURS))
+    }
+
+    /// 查找未过期的记录
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn find(&self, key: Uuid) -> Result<Option<IdempotencyRecord>> {
+        let cutoff = self.expiry_cutoff();
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT idempotency_key, command, request_hash, result, created_at
+                 FROM idempotency_records
+                 WHERE idempotency_key = ?1 AND created_at >= ?2",
+                params![DbUuid(key), cutoff],
+                |row| {
+                    let created_at: String = row.get(4)?;
+                    Ok(IdempotencyRecord {
+                        key: get_uuid(row, 0)?,
+                        command: row.get(1)?,
+                        request_hash: row.get(2)?,
+                        result: row.get(3)?,
+                        created_at: parse_time(4, &created_at)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// 保存记录（同一键的旧记录被覆盖）
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn save(&self, record: &IdempotencyRecord) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO idempotency_records
+                 (idempotency_key, command, request_hash, result, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                DbUuid(record.key),
+                record.command,
+                record.request_hash,
+                record.result,
+                time_key(record.created_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 删除过期记录，返回删除的条数
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn purge_expired(&self) -> Result<usize> {
+        self.connection.execute(
+            "DELETE FROM idempotency_records WHERE created_at < ?1",
+            [self.expiry_cutoff()],
+        )
+    }
+}
+
+#[async_trait]
+impl IdempotencyService for IdempotencyStore {
+    async fn find_record(&self, key: Uuid) -> CoreResult<Option<IdempotencyRecord>> {
+        self.find(key).map_err(to_core)
+    }
+
+    async fn save_record(&self, record: IdempotencyRecord) -> CoreResult<()> {
+        self.save(&record).map_err(to_core)
+    }
+}
+
+/// 过期幂等记录清理任务（每小时运行）
+#[derive(Debug, Clone)]
+pub struct IdempotencyCleanupJob {
+    store: IdempotencyStore,
+}
+
+impl IdempotencyCleanupJob {
+    /// 创建清理任务
+    pub fn new(store: IdempotencyStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Job for IdempotencyCleanupJob {
+    fn name(&self) -> &str {
+        "idempotency_cleanup"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(Duration::hours(1))
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let purged = self.store.purge_expired()?;
+        if purged > 0 {
+            info!("已清理 {} 条过期幂等记录", purged);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{DateTime, TimeZone, Utc};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, IdempotencyStore, Arc<ManualClock>) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+        ));
+        let store = IdempotencyStore::new(connection).with_clock(clock.clone());
+        (temp_dir, store, clock)
+    }
+
+    fn record(key: Uuid, created_at: DateTime<Utc>) -> IdempotencyRecord {
+        IdempotencyRecord {
+            key,
+            command: "create_customer".to_string(),
+            request_hash: "ab12".to_string(),
+            result: r#"{"id":"1"}"#.to_string(),
+            created_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_expire_and_are_purged() {
+        let (_dir, store, clock) = create_test_store();
+        let start = clock.now();
+        let (old, fresh) = (Uuid::new_v4(), Uuid::new_v4());
+        store.save_record(record(old, start)).await.unwrap();
+        store
+            .save_record(record(fresh, start + Duration::hours(12)))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.find_record(old).await.unwrap(),
+            Some(record(old, start))
+        );
+
+        // 超过24小时的记录不再返回，清理任务只删除过期记录
+        clock.set(start + Duration::hours(IDEMPOTENCY_TTL_HOURS) + Duration::minutes(1));
+        assert_eq!(store.find_record(old).await.unwrap(), None);
+        assert!(store.find_record(fresh).await.unwrap().is_some());
+
+        IdempotencyCleanupJob::new(store.clone())
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(store.purge_expired().unwrap(), 0);
+        assert!(store.find_record(fresh).await.unwrap().is_some());
+
+        // 过期的键可以重新使用
+        store.save_record(record(old, clock.now())).await.unwrap();
+        assert!(store.find_record(old).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,127 @@
+//! 库存变动存储
+//!
+//! 库存不单独保存余额，产品当前库存为 `stock_movements` 中全部变动之和。采购入库由
+//! [`PurchaseOrderStore`](crate::repository::PurchaseOrderStore) 在收货事务中通过
+//! [`InventoryStore::insert_movement`] 写入，手工调整通过 [`InventoryService`] 登记。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{CoreError, CoreResult, InventoryService, StockMovement, StockMovementKind};
+use rusqlite::{params, Row, Transaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const MOVEMENT_COLUMNS: &str =
+    "id, product_id, kind, quantity, reference_id, occurred_at, created_by";
+
+fn row_to_movement(row: &Row<'_>) -> rusqlite::Result<StockMovement> {
+    let kind: String = row.get(2)?;
+    let occurred_at: String = row.get(5)?;
+    Ok(StockMovement {
+        id: get_uuid(row, 0)?,
+        product_id: get_uuid(row, 1)?,
+        kind: StockMovementKind::parse(&kind).unwrap_or(StockMovementKind::Adjustment),
+        quantity: row.get(3)?,
+        reference_id: row.get::<_, Option<DbUuid>>(4)?.map(Uuid::from),
+        occurred_at: DateTime::parse_from_rfc3339(&occurred_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    5,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?,
+        created_by: row.get(6)?,
+    })
+}
+
+/// 库存变动存储
+#[derive(Debug, Clone)]
+pub struct InventoryStore {
+    connection: DatabaseConnection,
+}
+
+impl InventoryStore {
+    /// 创建库存存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 在调用方的事务中写入一条库存变动
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn insert_movement(tx: &Transaction<'_>, movement: &StockMovement) -> Result<()> {
+        tx.execute(
+            &format!(
+                "INSERT INTO stock_movements ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                MOVEMENT_COLUMNS
+            ),
+            params![
+                DbUuid(movement.id),
+                DbUuid(movement.product_id),
+                movement.kind.as_str(),
+                movement.quantity,
+                movement.reference_id.map(DbUuid),
+                time_key(movement.occurred_at),
+                movement.created_by,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InventoryService for InventoryStore {
+    async fn record_stock_movement(&self, movement: StockMovement) -> CoreResult<StockMovement> {
+        if movement.quantity == 0 {
+            return Err(CoreError::validation("库存变动数量不能为0"));
+        }
+        self.connection
+            .with_transaction(|tx| Self::insert_movement(tx, &movement))
+            .map_err(to_core)?;
+        info!(
+            "产品 {} 库存变动 {}：{}",
+            movement.product_id,
+            movement.kind.as_str(),
+            movement.quantity
+        );
+        Ok(movement)
+    }
+
+    async fn stock_on_hand(&self, product_id: Uuid) -> CoreResult<i64> {
+        self.connection
+            .query_row(
+                "SELECT COALESCE(SUM(quantity), 0) FROM stock_movements WHERE product_id = ?1",
+                [DbUuid(product_id)],
+                |row| row.get(0),
+            )
+            .map_err(to_core)
+    }
+
+    async fn stock_movements_for(
+        &self,
+        reference_id: Uuid,
+        kind: StockMovementKind,
+    ) -> CoreResult<Vec<StockMovement>> {
+        self.connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM stock_movements WHERE reference_id = ?1 AND kind = ?2
+                     ORDER BY occurred_at, rowid",
+                    MOVEMENT_COLUMNS
+                ),
+                params![DbUuid(reference_id), kind.as_str()],
+                row_to_movement,
+            )
+            .map_err(to_core)
+    }
+}
@@ -0,0 +1,135 @@
+//! 后台任务运行记录存储
+//!
+//! 每次运行追加一行到 `job_runs`，调度器重启后从中恢复各任务的上次运行时间。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{CoreResult, JobRunLog};
+use rusqlite::{params, OptionalExtension};
+
+use crate::database::DatabaseConnection;
+use crate::database::time::{parse_time, time_key};
+use crate::repository::support::to_core;
+
+/// 任务运行记录存储
+#[derive(Clone)]
+pub struct JobRunStore {
+    connection: DatabaseConnection,
+}
+
+impl std::fmt::Debug for JobRunStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobRunStore").finish_non_exhaustive()
+    }
+}
+
+impl JobRunStore {
+    /// 创建任务运行记录存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 任务上次运行的开始时间
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn last_started(&self, job_name: &str) -> Result<Option<DateTime<Utc>>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT started_at FROM job_runs
+                 WHERE job_name = ?1 ORDER BY started_at DESC LIMIT 1",
+                [job_name],
+                |row| {
+                    let started_at: String = row.get(0)?;
+                    parse_time(0, &started_at)
+                },
+            )
+            .optional()?)
+    }
+
+    /// 追加一条运行记录
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn append(
+        &self,
+        job_name: &str,
+        started_at: DateTime<Utc>,
+        error: Option<&str>,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO job_runs (job_name, started_at, success, error)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![job_name, time_key(started_at), error.is_none(), error],
+        )?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobRunLog for JobRunStore {
+    async fn last_run(&self, job_name: &str) -> CoreResult<Option<DateTime<Utc>>> {
+        self.last_started(job_name).map_err(to_core)
+    }
+
+    async fn record_run(
+        &self,
+        job_name: &str,
+        started_at: DateTime<Utc>,
+        error: Option<&str>,
+    ) -> CoreResult<()> {
+        self.append(job_name, started_at, error).map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::pool::DatabasePoolBuilder;
+    use crate::database::{schema, MigrationManager};
+    use chrono::{Duration, TimeZone};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_last_run_survives_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let open = || {
+            let pool = DatabasePoolBuilder::new(db_path.to_string_lossy())
+                .build()
+                .unwrap();
+            DatabaseConnection::new(pool)
+        };
+        let connection = open();
+        MigrationManager::new(connection.clone())
+            .add_migrations(schema::builtin_migrations())
+            .migrate(None)
+            .unwrap();
+
+        let store = JobRunStore::new(connection);
+        let monday = Utc.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).unwrap();
+        assert_eq!(store.last_run("weekly_digest").await.unwrap(), None);
+        store
+            .record_run("weekly_digest", monday, Some("邮件服务器无响应"))
+            .await
+            .unwrap();
+        store
+            .record_run("weekly_digest", monday + Duration::hours(1), None)
+            .await
+            .unwrap();
+        store
+            .record_run("monthly_report", monday + Duration::days(3), None)
+            .await
+            .unwrap();
+
+        let reopened = JobRunStore::new(open());
+        assert_eq!(
+            reopened.last_run("weekly_digest").await.unwrap(),
+            Some(monday + Duration::hours(1))
+        );
+    }
+}
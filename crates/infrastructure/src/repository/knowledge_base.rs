@@ -0,0 +1,409 @@
+//! 售后知识库存储
+//!
+//! 文章保存在 `knowledge_articles`，标题、现象和处理方法由触发器同步到 trigram 全文索引
+//! `knowledge_articles_fts`。推荐时把工单描述拆成三字片段组成 OR 查询，共有片段越多
+//! 排名越靠前，措辞不完全相同的描述也能匹配。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, KnowledgeArticle, KnowledgeBaseService, ServiceTicket,
+    SystemClock, MAX_ARTICLE_SUGGESTIONS,
+};
+use rusqlite::{params, Row};
+use uuid::Uuid;
+
+use super::customer_list::{fts_phrase, FTS_MIN_CHARS};
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const ARTICLE_COLUMNS: &str = "a.id, a.title, a.problem_category, a.symptoms, a.solution, \
+     a.product_categories, a.created_by, a.usage_count, a.created_at, a.updated_at";
+
+/// 推荐查询中最多使用的三字片段数
+const MAX_QUERY_TERMS: usize = 32;
+
+/// 售后知识库存储
+#[derive(Clone)]
+pub struct KnowledgeBaseStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for KnowledgeBaseStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KnowledgeBaseStore").finish_non_exhaustive()
+    }
+}
+
+fn conversion_error(
+    index: usize,
+    err: impl std::error::Error + Send + Sync + 'static,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(err))
+}
+
+fn row_to_article(row: &Row<'_>) -> rusqlite::Result<KnowledgeArticle> {
+    let product_categories: String = row.get(5)?;
+    let created_at: String = row.get(8)?;
+    let updated_at: String = row.get(9)?;
+    Ok(KnowledgeArticle {
+        id: get_uuid(row, 0)?,
+        title: row.get(1)?,
+        problem_category: row.get(2)?,
+        symptoms: row.get(3)?,
+        solution: row.get(4)?,
+        product_categories: serde_json::from_str(&product_categories)
+            .map_err(|e| conversion_error(5, e))?,
+        created_by: row.get(6)?,
+        usage_count: u64::try_from(row.get::<_, i64>(7)?).unwrap_or(0),
+        created_at: parse_time(8, &created_at)?,
+        updated_at: parse_time(9, &updated_at)?,
+    })
+}
+
+/// 把描述拆成三字片段的 OR 查询，描述太短时返回 `None`
+fn trigram_query(text: &str) -> Option<String> {
+    let mut seen = HashSet::new();
+    let mut terms = Vec::new();
+    'segments: for segment in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = segment.chars().collect();
+        for window in chars.windows(FTS_MIN_CHARS) {
+            let term = window.iter().collect::<String>().to_lowercase();
+            if seen.insert(term.clone()) {
+                terms.push(fts_phrase(&term));
+                if terms.len() == MAX_QUERY_TERMS {
+                    break 'segments;
+                }
+            }
+        }
+    }
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+/// 去掉首尾空白，检查必填项
+fn normalize(article: KnowledgeArticle) -> CoreResult<KnowledgeArticle> {
+    let article = KnowledgeArticle {
+        title: article.title.trim().to_string(),
+        problem_category: article.problem_category.trim().to_string(),
+        symptoms: article.symptoms.trim().to_string(),
+        solution: article.solution.trim().to_string(),
+        product_categories: article
+            .product_categories
+            .iter()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect(),
+        ..article
+    };
+    if article.title.is_empty() {
+        return Err(CoreError::validation("文章标题不能为空"));
+    }
+    if article.solution.is_empty() {
+        return Err(CoreError::validation("处理方法不能为空"));
+    }
+    Ok(article)
+}
+
+fn categories_json(article: &KnowledgeArticle) -> CoreResult<String> {
+    Ok(serde_json::to_string(&article.product_categories)?)
+}
+
+impl KnowledgeBaseStore {
+    /// 创建知识库存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn load(&self, id: Uuid) -> Result<Option<KnowledgeArticle>> {
+        let sql = format!(
+            "SELECT {} FROM knowledge_articles a WHERE a.id = ?1",
+            ARTICLE_COLUMNS
+        );
+        Ok(self
+            .connection
+            .query_map(&sql, [DbUuid(id)], row_to_article)?
+            .into_iter()
+            .next())
+    }
+}
+
+#[async_trait]
+impl KnowledgeBaseService for KnowledgeBaseStore {
+    async fn create_article(&self, article: KnowledgeArticle) -> CoreResult<KnowledgeArticle> {
+        let now = self.clock.now();
+        let article = normalize(KnowledgeArticle {
+            usage_count: 0,
+            created_at: now,
+            updated_at: now,
+            ..article
+        })?;
+        let categories = categories_json(&article)?;
+
+        self.connection
+            .execute(
+                "INSERT INTO knowledge_articles
+                     (id, title, problem_category, symptoms, solution, product_categories,
+                      created_by, usage_count, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8, ?9)",
+                params![
+                    DbUuid(article.id),
+                    article.title,
+                    article.problem_category,
+                    article.symptoms,
+                    article.solution,
+                    categories,
+                    article.created_by,
+                    time_key(article.created_at),
+                    time_key(article.updated_at),
+                ],
+            )
+            .map_err(to_core)?;
+        Ok(article)
+    }
+
+    async fn update_article(&self, article: KnowledgeArticle) -> CoreResult<KnowledgeArticle> {
+        let article = normalize(article)?;
+        let categories = categories_json(&article)?;
+        let updated = self
+            .connection
+            .execute(
+                "UPDATE knowledge_articles
+                 SET title = ?2, problem_category = ?3, symptoms = ?4, solution = ?5,
+                     product_categories = ?6, updated_at = ?7
+                 WHERE id = ?1",
+                params![
+                    DbUuid(article.id),
+                    article.title,
+                    article.problem_category,
+                    article.symptoms,
+                    article.solution,
+                    categories,
+                    time_key(self.clock.now()),
+                ],
+            )
+            .map_err(to_core)?;
+        if updated == 0 {
+            return Err(CoreError::not_found(format!("知识库文章 {}", article.id)));
+        }
+        self.load(article.id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("知识库文章 {}", article.id)))
+    }
+
+    async fn get_article(&self, id: Uuid) -> CoreResult<Option<KnowledgeArticle>> {
+        self.load(id).map_err(to_core)
+    }
+
+    async fn delete_article(&self, id: Uuid) -> CoreResult<bool> {
+        let deleted = self
+            .connection
+            .execute("DELETE FROM knowledge_articles WHERE id = ?1", [DbUuid(id)])
+            .map_err(to_core)?;
+        Ok(deleted > 0)
+    }
+
+    async fn list_articles(&self, category: Option<&str>) -> CoreResult<Vec<KnowledgeArticle>> {
+        let sql = format!(
+            "SELECT {} FROM knowledge_articles a
+             WHERE ?1 IS NULL OR a.problem_category = ?1
+             ORDER BY a.usage_count DESC, a.title",
+            ARTICLE_COLUMNS
+        );
+        self.connection
+            .query_map(&sql, [category.map(str::trim)], row_to_article)
+            .map_err(to_core)
+    }
+
+    async fn suggest_articles(&self, ticket: &ServiceTicket) -> CoreResult<Vec<KnowledgeArticle>> {
+        let category = ticket.problem_category.trim();
+        let limit = i64::try_from(MAX_ARTICLE_SUGGESTIONS).unwrap_or(i64::MAX);
+        let articles = match trigram_query(&ticket.description) {
+            Some(query) => {
+                let sql = format!(
+                    "SELECT {} FROM knowledge_articles a
+                     LEFT JOIN (
+                         SELECT article_id, bm25(knowledge_articles_fts) AS rank
+                         FROM knowledge_articles_fts WHERE knowledge_articles_fts MATCH ?1
+                     ) m ON m.article_id = a.id
+                     WHERE a.problem_category = ?2 OR m.article_id IS NOT NULL
+                     ORDER BY a.problem_category = ?2 DESC, m.rank IS NULL, m.rank,
+                              a.usage_count DESC, a.title
+                     LIMIT ?3",
+                    ARTICLE_COLUMNS
+                );
+                self.connection
+                    .query_map(&sql, params![query, category, limit], row_to_article)
+            }
+            None => {
+                let sql = format!(
+                    "SELECT {} FROM knowledge_articles a
+                     WHERE a.problem_category = ?1
+                     ORDER BY a.usage_count DESC, a.title
+                     LIMIT ?2",
+                    ARTICLE_COLUMNS
+                );
+                self.connection
+                    .query_map(&sql, params![category, limit], row_to_article)
+            }
+        };
+        articles.map_err(to_core)
+    }
+
+    async fn record_usage(&self, id: Uuid) -> CoreResult<KnowledgeArticle> {
+        let updated = self
+            .connection
+            .execute(
+                "UPDATE knowledge_articles SET usage_count = usage_count + 1 WHERE id = ?1",
+                [DbUuid(id)],
+            )
+            .map_err(to_core)?;
+        if updated == 0 {
+            return Err(CoreError::not_found(format!("知识库文章 {}", id)));
+        }
+        self.load(id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("知识库文章 {}", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{ManualClock, ServiceTicketStatus, TaskPriority};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, KnowledgeBaseStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap(),
+        ));
+        (temp_dir, KnowledgeBaseStore::new(connection).with_clock(clock))
+    }
+
+    fn article(title: &str, category: &str, symptoms: &str) -> KnowledgeArticle {
+        KnowledgeArticle {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            problem_category: category.to_string(),
+            symptoms: symptoms.to_string(),
+            solution: "按流程处理".to_string(),
+            product_categories: vec!["多层板".to_string()],
+            created_by: Some("张三".to_string()),
+            usage_count: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn ticket(category: &str, description: &str) -> ServiceTicket {
+        ServiceTicket {
+            id: Uuid::new_v4(),
+            ticket_number: "SV20240603001".to_string(),
+            customer_id: Uuid::new_v4(),
+            problem_category: category.to_string(),
+            description: description.to_string(),
+            solution_method: None,
+            status: ServiceTicketStatus::New,
+            priority: TaskPriority::Medium,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_suggestions_prefer_category_and_similar_description() {
+        let (_dir, store) = create_test_store();
+        let bending = store
+            .create_article(article("柜门板受潮弯曲", "变形", "柜门板安装后受潮弯曲，边缘翘起"))
+            .await
+            .unwrap();
+        let popular = store
+            .create_article(article("台面长期受压下沉", "变形", "台面中间下沉"))
+            .await
+            .unwrap();
+        store.record_usage(popular.id).await.unwrap();
+        let cracking = store
+            .create_article(article("封边开胶", "开裂", "柜门板边缘开胶翘起"))
+            .await
+            .unwrap();
+        store
+            .create_article(article("颜色偏黄", "色差", "批次间颜色不一致"))
+            .await
+            .unwrap();
+
+        let ids = |articles: Vec<KnowledgeArticle>| -> Vec<Uuid> {
+            articles.into_iter().map(|a| a.id).collect()
+        };
+        // 同分类中描述相近的在前，其次是采用次数多的，其他分类只列出描述相近的
+        let suggested = store
+            .suggest_articles(&ticket("变形", "客户反映柜门板边缘翘起，怀疑受潮"))
+            .await
+            .unwrap();
+        assert_eq!(ids(suggested), vec![bending.id, popular.id, cracking.id]);
+
+        // 描述太短时按采用次数列出同分类文章
+        let suggested = store.suggest_articles(&ticket("变形", "弯")).await.unwrap();
+        assert_eq!(ids(suggested), vec![popular.id, bending.id]);
+    }
+
+    #[tokio::test]
+    async fn test_article_crud_and_usage_count() {
+        let (_dir, store) = create_test_store();
+        let created = store
+            .create_article(article(" 柜门板受潮弯曲 ", "变形", "边缘翘起"))
+            .await
+            .unwrap();
+        assert_eq!(created.title, "柜门板受潮弯曲");
+        assert_eq!(store.get_article(created.id).await.unwrap(), Some(created.clone()));
+
+        assert_eq!(store.record_usage(created.id).await.unwrap().usage_count, 1);
+        assert_eq!(store.record_usage(created.id).await.unwrap().usage_count, 2);
+
+        // 更新内容不影响采用次数
+        let updated = store
+            .update_article(KnowledgeArticle {
+                solution: "更换柜门并加装拉直器".to_string(),
+                usage_count: 0,
+                ..created.clone()
+            })
+            .await
+            .unwrap();
+        assert_eq!(updated.usage_count, 2);
+        assert_eq!(updated.solution, "更换柜门并加装拉直器");
+        assert_eq!(store.list_articles(Some("变形")).await.unwrap(), vec![updated]);
+        assert!(store.list_articles(Some("开裂")).await.unwrap().is_empty());
+
+        let err = store
+            .create_article(KnowledgeArticle {
+                solution: " ".to_string(),
+                ..article("无处理方法", "变形", "")
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)));
+        let err = store.record_usage(Uuid::new_v4()).await.unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)));
+
+        assert!(store.delete_article(created.id).await.unwrap());
+        assert!(!store.delete_article(created.id).await.unwrap());
+    }
+}
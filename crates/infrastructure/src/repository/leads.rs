@@ -0,0 +1,275 @@
+//! 线索存储
+//!
+//! 新建线索时客户、首要联系人和首个跟进任务在同一事务中写入，并在同一事务中刷新客户汇总、
+//! 登记事件发件箱；任一步失败时整个事务回滚，不留下只建了一半的记录。
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use minicrm_core::{
+    CoreError, CoreResult, CreatedLead, Customer, CustomerContact, DomainEvent, EntityKind,
+    EventEnvelope, LeadService, NewLead, Task,
+};
+use rusqlite::{params, Transaction};
+use uuid::Uuid;
+
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+use crate::repository::{customer_summary, outbox};
+
+/// 线索存储
+#[derive(Debug, Clone)]
+pub struct LeadStore {
+    connection: DatabaseConnection,
+}
+
+impl LeadStore {
+    /// 创建线索存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 联系人和任务必须属于同一个客户，联系人必须是首要联系人
+    fn check_links(lead: &NewLead) -> CoreResult<()> {
+        let customer_id = lead.customer.id;
+        if lead.contact.customer_id != customer_id || !lead.contact.is_primary {
+            return Err(CoreError::validation("联系人必须是该客户的首要联系人"));
+        }
+        if let Some(task) = &lead.first_task {
+            if task.customer_id != Some(customer_id) {
+                return Err(CoreError::validation("跟进任务必须关联到该客户"));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_customer(tx: &Transaction<'_>, customer: &Customer) -> Result<()> {
+        tx.execute(
+            "INSERT INTO customers (id, name, company, phone, email, address, level, credit_limit,
+                                    credit_hold, created_at, updated_at, created_by, updated_by,
+                                    owner_id, contact_birthday)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                DbUuid(customer.id),
+                customer.name,
+                customer.contact_person,
+                customer.phone,
+                customer.email,
+                customer.address,
+                customer.level.as_str(),
+                customer.credit_limit.map(|limit| limit.cents()),
+                customer.credit_hold,
+                time_key(customer.created_at),
+                time_key(customer.updated_at),
+                customer.created_by,
+                customer.updated_by,
+                customer.owner_id.map(DbUuid),
+                customer.contact_birthday,
+            ],
+        )
+        .context("无法写入客户")?;
+        Ok(())
+    }
+
+    fn insert_contact(tx: &Transaction<'_>, contact: &CustomerContact) -> Result<()> {
+        tx.execute(
+            "INSERT INTO customer_contacts (id, customer_id, name, phone, email, is_primary,
+                                            created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                DbUuid(contact.id),
+                DbUuid(contact.customer_id),
+                contact.name,
+                contact.phone,
+                contact.email,
+                contact.is_primary,
+                time_key(contact.created_at),
+            ],
+        )
+        .context("无法写入联系人")?;
+        Ok(())
+    }
+
+    fn insert_task(tx: &Transaction<'_>, task: &Task) -> Result<()> {
+        tx.execute(
+            "INSERT INTO tasks (id, title, description, status, priority, customer_id, due_date,
+                                created_at, updated_at, created_by, updated_by, assigned_to)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                DbUuid(task.id),
+                task.title,
+                task.description,
+                task.status.as_str(),
+                task.priority.as_str(),
+                task.customer_id.map(DbUuid),
+                task.due_date.map(time_key),
+                time_key(task.created_at),
+                time_key(task.updated_at),
+                task.created_by,
+                task.updated_by,
+                task.assigned_to.map(DbUuid),
+            ],
+        )
+        .context("无法写入跟进任务")?;
+        Ok(())
+    }
+
+    /// 在同一事务中刷新客户汇总并记录事件
+    fn publish(tx: &Transaction<'_>, entity: EntityKind, id: Uuid) -> Result<()> {
+        let now = Utc::now();
+        let event = DomainEvent::EntityCreated { entity, id };
+        customer_summary::apply_event(tx, &event, now)?;
+        outbox::record(tx, &EventEnvelope::at(event, now))
+    }
+}
+
+#[async_trait]
+impl LeadService for LeadStore {
+    async fn create_lead(&self, lead: NewLead) -> CoreResult<CreatedLead> {
+        Self::check_links(&lead)?;
+        self.connection
+            .with_transaction(|tx| {
+                Self::insert_customer(tx, &lead.customer)?;
+                Self::insert_contact(tx, &lead.contact)?;
+                Self::publish(tx, EntityKind::Customer, lead.customer.id)?;
+                if let Some(task) = &lead.first_task {
+                    Self::insert_task(tx, task)?;
+                    Self::publish(tx, EntityKind::Task, task.id)?;
+                }
+                Ok(())
+            })
+            .map_err(to_core)?;
+        Ok(CreatedLead {
+            customer_id: lead.customer.id,
+            contact_id: lead.contact.id,
+            task_id: lead.first_task.as_ref().map(|task| task.id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::{CustomerLevel, TaskPriority, TaskStatus};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, LeadStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = LeadStore::new(connection.clone());
+        (temp_dir, connection, store)
+    }
+
+    fn new_lead() -> NewLead {
+        let now = Utc::now();
+        let customer_id = Uuid::new_v4();
+        NewLead {
+            customer: Customer {
+                id: customer_id,
+                name: "华东建材".to_string(),
+                contact_person: Some("王经理".to_string()),
+                contact_birthday: None,
+                phone: Some("13800138000".to_string()),
+                email: None,
+                address: None,
+                level: CustomerLevel::Normal,
+                credit_limit: None,
+                credit_hold: false,
+                owner_id: None,
+                created_at: now,
+                updated_at: now,
+                created_by: None,
+                updated_by: None,
+            },
+            contact: CustomerContact {
+                id: Uuid::new_v4(),
+                customer_id,
+                name: "王经理".to_string(),
+                phone: Some("13800138000".to_string()),
+                email: None,
+                is_primary: true,
+                created_at: now,
+            },
+            first_task: Some(Task {
+                id: Uuid::new_v4(),
+                title: "电话回访".to_string(),
+                description: None,
+                status: TaskStatus::Pending,
+                priority: TaskPriority::Medium,
+                customer_id: Some(customer_id),
+                supplier_id: None,
+                due_date: Some(now),
+                created_at: now,
+                updated_at: now,
+                created_by: None,
+                updated_by: None,
+                assigned_to: None,
+            }),
+        }
+    }
+
+    fn count(connection: &DatabaseConnection, table: &str) -> i64 {
+        connection
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_lead_writes_all_records() {
+        let (_dir, connection, store) = create_test_store();
+        let lead = new_lead();
+        let task_id = lead.first_task.as_ref().map(|task| task.id);
+
+        let created = store.create_lead(lead.clone()).await.unwrap();
+        assert_eq!(
+            created,
+            CreatedLead {
+                customer_id: lead.customer.id,
+                contact_id: lead.contact.id,
+                task_id,
+            }
+        );
+        let (primary, task_customer): (bool, String) = connection
+            .query_row(
+                "SELECT c.is_primary, t.customer_id
+                 FROM customer_contacts c JOIN tasks t ON t.customer_id = c.customer_id",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(primary);
+        assert_eq!(task_customer, lead.customer.id.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_task_failure_rolls_back_customer_and_contact() {
+        let (_dir, connection, store) = create_test_store();
+        let lead = new_lead();
+        let task_id = lead.first_task.as_ref().map(|task| task.id).unwrap();
+        // 预先占用任务ID，写入任务时主键冲突
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, '已有客户', ?2, ?2)",
+                params![DbUuid(task_id), "2024-07-01T09:00:00.000000Z"],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO tasks (id, customer_id, title, created_at, updated_at)
+                 VALUES (?1, ?1, '已有任务', ?2, ?2)",
+                params![DbUuid(task_id), "2024-07-01T09:00:00.000000Z"],
+            )
+            .unwrap();
+
+        assert!(store.create_lead(lead).await.is_err());
+        assert_eq!(count(&connection, "customers"), 1);
+        assert_eq!(count(&connection, "customer_contacts"), 0);
+        assert_eq!(count(&connection, "tasks"), 1);
+    }
+}
@@ -2,7 +2,103 @@
 //!
 //! 提供数据访问层的具体实现。
 
+pub mod activity;
+pub mod anniversaries;
+pub mod attachment_references;
+pub mod consistency;
+pub mod counters;
+pub mod credit;
+pub mod custom_fields;
+pub mod customer_categories;
+pub mod customer_deletion;
+pub mod customer_list;
+pub mod customer_relations;
+pub mod customer_summary;
+pub mod customers;
+pub mod exchange_rates;
+pub mod field_policies;
+pub mod filter;
 pub mod generic;
+pub mod global_search;
+pub mod holidays;
+pub mod idempotency;
+pub mod inventory;
+pub mod job_runs;
+pub mod knowledge_base;
+pub mod leads;
+pub mod monthly_closings;
+pub mod opportunities;
+pub mod orders;
+pub mod outbox;
+pub mod products;
+pub mod purchase_orders;
+pub mod purchase_quotes;
+pub mod query_builder;
+pub mod quote_items;
+pub mod quote_revisions;
+pub mod quote_templates;
+pub mod record_archive;
+pub mod reminders;
+pub mod retention;
+pub mod sequences;
+pub mod snapshot;
+pub mod support;
+pub mod tasks;
+pub mod timeline;
+pub mod trash;
+pub mod users;
 
 // 重新导出主要类型
-pub use generic::GenericRepository;
+pub use activity::{
+    ActivityRefresh, ActivityScore, ActivityScoreJob, ActivityScoreStore, AtRiskCustomer,
+};
+pub use anniversaries::AnniversaryStore;
+pub use attachment_references::{
+    AttachmentDeletion, AttachmentReference, AttachmentReferenceSource, AttachmentReferenceStore,
+};
+pub use consistency::builtin_consistency_checks;
+pub use counters::{CounterReconciliation, CounterReconciliationJob, TableCounterStore};
+pub use credit::CreditStore;
+pub use custom_fields::CustomFieldStore;
+pub use customer_categories::CustomerCategoryStore;
+pub use customer_deletion::CustomerDeletionStore;
+pub use customer_list::CustomerListStore;
+pub use customer_relations::CustomerRelationStore;
+pub use customer_summary::{
+    CustomerSummary, CustomerSummaryReconciliationJob, CustomerSummaryStore, SummaryDrift,
+    SummaryReconciliation,
+};
+pub use customers::SqliteCustomerRepository;
+pub use exchange_rates::ExchangeRateStore;
+pub use field_policies::FieldPolicyStore;
+pub use filter::{FilterTranslator, SqlFilter};
+pub use generic::{EntityMapper, GenericRepository};
+pub use global_search::GlobalSearchStore;
+pub use holidays::HolidayStore;
+pub use idempotency::{IdempotencyCleanupJob, IdempotencyStore};
+pub use inventory::InventoryStore;
+pub use job_runs::JobRunStore;
+pub use knowledge_base::KnowledgeBaseStore;
+pub use leads::LeadStore;
+pub use monthly_closings::MonthlyClosingStore;
+pub use opportunities::OpportunityStore;
+pub use orders::OrderStore;
+pub use outbox::{EventOutboxStore, OutboxDispatcher, OutboxEntry};
+pub use products::ProductStore;
+pub use purchase_orders::PurchaseOrderStore;
+pub use purchase_quotes::PurchaseQuoteStore;
+pub use query_builder::{ListQuery, QueryBuilder};
+pub use quote_items::QuoteItemStore;
+pub use quote_revisions::QuoteRevisionStore;
+pub use quote_templates::QuoteTemplateStore;
+pub use record_archive::{RecordArchiveJob, RecordArchiver};
+pub use reminders::{
+    AnniversaryReminderJob, OverdueDeliveryReminderJob, OverdueTaskEscalationJob, ReminderStore,
+};
+pub use retention::{MonthlyChangeCount, RetentionJob, RetentionPolicy};
+pub use sequences::next_document_number;
+pub use snapshot::{SnapshotProvider, TableSnapshotProvider};
+pub use tasks::{TaskNotifier, TaskStore};
+pub use timeline::TimelineStore;
+pub use trash::TrashStore;
+pub use users::SqliteUserService;
@@ -0,0 +1,285 @@
+//! 月结快照存储
+//!
+//! `monthly_closings` 按月份（`2024-05`）保存结账时的统计快照 JSON 和锁定标记；
+//! 结账、重新打开都写入 `monthly_closing_audit`。重新打开只清除锁定标记，快照保留到
+//! 下一次结账时覆盖。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Clock, CoreError, CoreResult, MonthlyClosing, MonthlyClosingService, MonthlyStatistics,
+    ReportPeriod, SystemClock,
+};
+use rusqlite::{params, OptionalExtension, Transaction};
+
+use crate::database::DatabaseConnection;
+use crate::database::time::time_key;
+use crate::repository::support::to_core;
+
+/// 月结快照存储
+#[derive(Clone)]
+pub struct MonthlyClosingStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for MonthlyClosingStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MonthlyClosingStore").finish_non_exhaustive()
+    }
+}
+
+impl MonthlyClosingStore {
+    /// 创建存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中固定当前时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 读取指定月份的月结记录
+    ///
+    /// # Errors
+    ///
+    /// 查询失败或快照无法解析时返回错误。
+    pub fn load(&self, period: ReportPeriod) -> Result<Option<MonthlyClosing>> {
+        let conn = self.connection.get_connection()?;
+        let row = conn
+            .query_row(
+                "SELECT statistics, locked, closed_at, closed_by FROM monthly_closings
+                 WHERE period = ?1",
+                [period.to_string()],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, bool>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+        row.map(|(statistics, locked, closed_at, closed_by)| {
+            Ok(MonthlyClosing {
+                period,
+                statistics: serde_json::from_str(&statistics)
+                    .with_context(|| format!("{} 的月结快照无法解析", period))?,
+                locked,
+                closed_at: DateTime::parse_from_rfc3339(&closed_at)
+                    .with_context(|| format!("{} 的结账时间无效", period))?
+                    .with_timezone(&Utc),
+                closed_by,
+            })
+        })
+        .transpose()
+    }
+
+    fn audit(
+        tx: &Transaction<'_>,
+        period: ReportPeriod,
+        action: &str,
+        actor: Option<&str>,
+        reason: Option<&str>,
+        at: &str,
+    ) -> Result<()> {
+        tx.execute(
+            "INSERT INTO monthly_closing_audit (period, action, actor, reason, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![period.to_string(), action, actor, reason, at],
+        )?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MonthlyClosingService for MonthlyClosingStore {
+    async fn closing(&self, period: ReportPeriod) -> CoreResult<Option<MonthlyClosing>> {
+        self.load(period).map_err(to_core)
+    }
+
+    async fn close_month(
+        &self,
+        period: ReportPeriod,
+        statistics: MonthlyStatistics,
+        closed_by: Option<&str>,
+    ) -> CoreResult<MonthlyClosing> {
+        let closed_at = self.clock.now();
+        let now = time_key(closed_at);
+        let snapshot = serde_json::to_string(&statistics)?;
+        self.connection
+            .with_transaction(|tx| {
+                let locked: Option<bool> = tx
+                    .query_row(
+                        "SELECT locked FROM monthly_closings WHERE period = ?1",
+                        [period.to_string()],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                if locked == Some(true) {
+                    return Err(CoreError::conflict(format!(
+                        "{} 已月结，需先重新打开才能再次结账",
+                        period
+                    ))
+                    .into());
+                }
+                tx.execute(
+                    "INSERT INTO monthly_closings (period, statistics, locked, closed_at, closed_by)
+                     VALUES (?1, ?2, 1, ?3, ?4)
+                     ON CONFLICT(period) DO UPDATE SET
+                         statistics = ?2, locked = 1, closed_at = ?3, closed_by = ?4",
+                    params![period.to_string(), snapshot, now, closed_by],
+                )?;
+                let action = if locked.is_some() { "reclose" } else { "close" };
+                Self::audit(tx, period, action, closed_by, None, &now)
+            })
+            .map_err(to_core)?;
+        Ok(MonthlyClosing {
+            period,
+            statistics,
+            locked: true,
+            closed_at,
+            closed_by: closed_by.map(str::to_string),
+        })
+    }
+
+    async fn reopen_month(
+        &self,
+        period: ReportPeriod,
+        actor: &str,
+        reason: &str,
+    ) -> CoreResult<MonthlyClosing> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(CoreError::validation("重新打开已结账月份须填写原因"));
+        }
+        let now = time_key(self.clock.now());
+        self.connection
+            .with_transaction(|tx| {
+                let locked: bool = tx
+                    .query_row(
+                        "SELECT locked FROM monthly_closings WHERE period = ?1",
+                        [period.to_string()],
+                        |row| row.get(0),
+                    )
+                    .optional()?
+                    .ok_or_else(|| CoreError::not_found(format!("{} 的月结记录", period)))?;
+                if !locked {
+                    return Err(CoreError::conflict(format!("{} 已处于打开状态", period)).into());
+                }
+                tx.execute(
+                    "UPDATE monthly_closings SET locked = 0 WHERE period = ?1",
+                    [period.to_string()],
+                )?;
+                Self::audit(tx, period, "reopen", Some(actor), Some(reason), &now)
+            })
+            .map_err(to_core)?;
+        self.load(period)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("{} 的月结记录", period)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use minicrm_core::{ManualClock, StatisticsSource};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, MonthlyClosingStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 1, 1, 0, 0).unwrap(),
+        ));
+        let store = MonthlyClosingStore::new(connection.clone()).with_clock(clock);
+        (temp_dir, connection, store)
+    }
+
+    fn stats(quotes_issued: u64) -> MonthlyStatistics {
+        MonthlyStatistics {
+            quotes_issued,
+            quotes_total_amount: 12_800.0,
+            ..MonthlyStatistics::default()
+        }
+    }
+
+    fn audit_actions(connection: &DatabaseConnection) -> Vec<String> {
+        connection
+            .query_map(
+                "SELECT action FROM monthly_closing_audit ORDER BY id",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_close_locks_snapshot() {
+        let (_dir, connection, store) = create_test_store();
+        let may = ReportPeriod::new(2024, 5).unwrap();
+        assert!(store.closing(may).await.unwrap().is_none());
+
+        store.close_month(may, stats(40), None).await.unwrap();
+        let closing = store.closing(may).await.unwrap().unwrap();
+        assert!(closing.locked);
+        assert_eq!(closing.statistics.quotes_issued, 40);
+        assert_eq!(closing.statistics.source, StatisticsSource::Live);
+        assert_eq!(
+            closing.closed_at,
+            Utc.with_ymd_and_hms(2024, 6, 1, 1, 0, 0).unwrap()
+        );
+
+        let err = store.close_month(may, stats(41), Some("admin")).await.unwrap_err();
+        assert!(matches!(err, CoreError::Conflict(_)), "{err}");
+        let closing = store.closing(may).await.unwrap().unwrap();
+        assert_eq!(closing.statistics.quotes_issued, 40);
+        assert_eq!(audit_actions(&connection), vec!["close"]);
+    }
+
+    #[tokio::test]
+    async fn test_reopen_then_reclose_overwrites() {
+        let (_dir, connection, store) = create_test_store();
+        let may = ReportPeriod::new(2024, 5).unwrap();
+
+        let err = store.reopen_month(may, "admin", "补录报价").await.unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)), "{err}");
+
+        store.close_month(may, stats(40), None).await.unwrap();
+        let err = store.reopen_month(may, "admin", "  ").await.unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+
+        let reopened = store.reopen_month(may, "admin", "补录报价").await.unwrap();
+        assert!(!reopened.locked);
+        assert_eq!(reopened.statistics.quotes_issued, 40);
+        assert!(store.reopen_month(may, "admin", "再次打开").await.is_err());
+
+        store.close_month(may, stats(41), Some("admin")).await.unwrap();
+        let closing = store.closing(may).await.unwrap().unwrap();
+        assert!(closing.locked);
+        assert_eq!(closing.statistics.quotes_issued, 41);
+        assert_eq!(closing.closed_by.as_deref(), Some("admin"));
+
+        assert_eq!(audit_actions(&connection), vec!["close", "reopen", "reclose"]);
+        let (actor, reason): (String, String) = connection
+            .query_row(
+                "SELECT actor, reason FROM monthly_closing_audit WHERE action = 'reopen'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((actor.as_str(), reason.as_str()), ("admin", "补录报价"));
+    }
+}
@@ -0,0 +1,582 @@
+//! 销售机会存储
+//!
+//! 基于 `opportunities` 表实现销售机会服务。由机会生成报价时通过领域事件
+//! 关联报价并推进到“已报价”阶段；已结束的机会只允许修改备注。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, DomainEvent, EventEnvelope, EventHandler, Money, Opportunity,
+    OpportunityService, OpportunityStage, Pipeline, PipelineStage, SystemClock,
+};
+use rusqlite::{params, OptionalExtension, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const OPPORTUNITY_COLUMNS: &str = "id, customer_id, title, stage, expected_amount, probability, \
+     expected_close_date, quote_id, lost_reason, notes, created_at, updated_at, created_by, \
+     updated_by, owner_id";
+
+/// 销售机会存储
+#[derive(Clone)]
+pub struct OpportunityStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for OpportunityStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpportunityStore").finish_non_exhaustive()
+    }
+}
+
+fn row_to_opportunity(row: &Row<'_>) -> rusqlite::Result<Opportunity> {
+    let stage: String = row.get(3)?;
+    let expected_close_date: Option<String> = row.get(6)?;
+    let created_at: String = row.get(10)?;
+    let updated_at: String = row.get(11)?;
+    Ok(Opportunity {
+        id: get_uuid(row, 0)?,
+        customer_id: get_uuid(row, 1)?,
+        title: row.get(2)?,
+        stage: OpportunityStage::parse(&stage).unwrap_or_default(),
+        expected_amount: Money::from_cents(row.get(4)?),
+        probability: row.get(5)?,
+        expected_close_date: expected_close_date.map(|v| parse_time(6, &v)).transpose()?,
+        quote_id: row.get::<_, Option<DbUuid>>(7)?.map(Uuid::from),
+        lost_reason: row.get(8)?,
+        notes: row.get(9)?,
+        created_at: parse_time(10, &created_at)?,
+        updated_at: parse_time(11, &updated_at)?,
+        created_by: row.get(12)?,
+        updated_by: row.get(13)?,
+        owner_id: row.get::<_, Option<DbUuid>>(14)?.map(Uuid::from),
+    })
+}
+
+/// 除备注和审计字段外的内容是否一致
+fn same_except_notes(a: &Opportunity, b: &Opportunity) -> bool {
+    a.customer_id == b.customer_id
+        && a.title == b.title
+        && a.stage == b.stage
+        && a.expected_amount == b.expected_amount
+        && a.probability == b.probability
+        && a.expected_close_date == b.expected_close_date
+        && a.quote_id == b.quote_id
+        && a.lost_reason == b.lost_reason
+        && a.owner_id == b.owner_id
+}
+
+impl OpportunityStore {
+    /// 创建销售机会存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 写入销售机会
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn insert(&self, opportunity: &Opportunity) -> Result<()> {
+        self.connection.execute(
+            &format!(
+                "INSERT INTO opportunities ({}) VALUES \
+                 (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                OPPORTUNITY_COLUMNS
+            ),
+            params![
+                DbUuid(opportunity.id),
+                DbUuid(opportunity.customer_id),
+                opportunity.title,
+                opportunity.stage.as_str(),
+                opportunity.expected_amount.cents(),
+                opportunity.probability,
+                opportunity.expected_close_date.map(time_key),
+                opportunity.quote_id.map(DbUuid),
+                opportunity.lost_reason,
+                opportunity.notes,
+                time_key(opportunity.created_at),
+                time_key(opportunity.updated_at),
+                opportunity.created_by,
+                opportunity.updated_by,
+                opportunity.owner_id.map(DbUuid),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 覆盖写入销售机会（不含创建信息）
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn update(&self, opportunity: &Opportunity) -> Result<()> {
+        self.connection.execute(
+            "UPDATE opportunities SET customer_id = ?2, title = ?3, stage = ?4,
+                 expected_amount = ?5, probability = ?6, expected_close_date = ?7, quote_id = ?8,
+                 lost_reason = ?9, notes = ?10, updated_at = ?11, updated_by = ?12, owner_id = ?13
+             WHERE id = ?1",
+            params![
+                DbUuid(opportunity.id),
+                DbUuid(opportunity.customer_id),
+                opportunity.title,
+                opportunity.stage.as_str(),
+                opportunity.expected_amount.cents(),
+                opportunity.probability,
+                opportunity.expected_close_date.map(time_key),
+                opportunity.quote_id.map(DbUuid),
+                opportunity.lost_reason,
+                opportunity.notes,
+                time_key(opportunity.updated_at),
+                opportunity.updated_by,
+                opportunity.owner_id.map(DbUuid),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 根据ID获取销售机会（不含已删除）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn get(&self, id: Uuid) -> Result<Option<Opportunity>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM opportunities WHERE id = ?1 AND deleted_at IS NULL",
+                    OPPORTUNITY_COLUMNS
+                ),
+                [DbUuid(id)],
+                row_to_opportunity,
+            )
+            .optional()?)
+    }
+
+    /// 报价是否存在且已被客户接受
+    fn quote_accepted(&self, quote_id: Uuid) -> Result<bool> {
+        let count: i64 = self.connection.query_row(
+            "SELECT COUNT(*) FROM quotes
+             WHERE id = ?1 AND status = 'accepted' AND deleted_at IS NULL",
+            [DbUuid(quote_id)],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    fn require(&self, id: Uuid) -> CoreResult<Opportunity> {
+        self.get(id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("销售机会 {}", id)))
+    }
+
+    fn validate(opportunity: &Opportunity) -> CoreResult<()> {
+        if opportunity.title.trim().is_empty() {
+            return Err(CoreError::validation("销售机会标题不能为空"));
+        }
+        if opportunity.probability > 100 {
+            return Err(CoreError::validation("成交概率须在0到100之间"));
+        }
+        if opportunity.expected_amount.cents() < 0 {
+            return Err(CoreError::validation("预计金额不能为负数"));
+        }
+        Ok(())
+    }
+
+    /// 报价由机会生成后关联报价，尚未报价的机会推进到“已报价”
+    fn on_quote_created(&self, opportunity_id: Uuid, quote_id: Uuid) -> CoreResult<()> {
+        let mut opportunity = self.require(opportunity_id)?;
+        if opportunity.stage.is_closed() {
+            return Ok(());
+        }
+        opportunity.quote_id = Some(quote_id);
+        if opportunity.stage < OpportunityStage::Quoted {
+            info!(
+                "销售机会「{}」已生成报价，阶段 {} -> {}",
+                opportunity.title,
+                opportunity.stage.as_str(),
+                OpportunityStage::Quoted.as_str()
+            );
+            opportunity.stage = OpportunityStage::Quoted;
+        }
+        opportunity.updated_at = self.clock.now();
+        self.update(&opportunity).map_err(to_core)
+    }
+}
+
+#[async_trait]
+impl OpportunityService for OpportunityStore {
+    async fn create_opportunity(&self, opportunity: Opportunity) -> CoreResult<Opportunity> {
+        Self::validate(&opportunity)?;
+        if opportunity.stage.is_closed() {
+            return Err(CoreError::business("新建的销售机会不能直接为赢单或输单"));
+        }
+        let now = self.clock.now();
+        let opportunity = Opportunity {
+            title: opportunity.title.trim().to_string(),
+            lost_reason: None,
+            created_at: now,
+            updated_at: now,
+            ..opportunity
+        };
+        self.insert(&opportunity).map_err(to_core)?;
+        Ok(opportunity)
+    }
+
+    async fn update_opportunity(&self, opportunity: Opportunity) -> CoreResult<Opportunity> {
+        Self::validate(&opportunity)?;
+        let current = self.require(opportunity.id)?;
+        if current.stage.is_closed() {
+            if !same_except_notes(&current, &opportunity) {
+                return Err(CoreError::business(format!(
+                    "销售机会已{}，只能修改备注",
+                    current.stage.label()
+                )));
+            }
+        } else if current.stage != opportunity.stage {
+            return Err(CoreError::validation("请通过变更阶段操作修改销售机会阶段"));
+        }
+        let opportunity = Opportunity {
+            created_at: current.created_at,
+            created_by: current.created_by,
+            updated_at: self.clock.now(),
+            ..opportunity
+        };
+        self.update(&opportunity).map_err(to_core)?;
+        Ok(opportunity)
+    }
+
+    async fn get_opportunity(&self, id: Uuid) -> CoreResult<Option<Opportunity>> {
+        self.get(id).map_err(to_core)
+    }
+
+    async fn link_quote(&self, id: Uuid, quote_id: Uuid) -> CoreResult<Opportunity> {
+        let mut opportunity = self.require(id)?;
+        if opportunity.stage.is_closed() {
+            return Err(CoreError::business(format!(
+                "销售机会已{}，不能再关联报价",
+                opportunity.stage.label()
+            )));
+        }
+        opportunity.quote_id = Some(quote_id);
+        opportunity.updated_at = self.clock.now();
+        self.update(&opportunity).map_err(to_core)?;
+        Ok(opportunity)
+    }
+
+    async fn change_stage(
+        &self,
+        id: Uuid,
+        stage: OpportunityStage,
+        lost_reason: Option<&str>,
+    ) -> CoreResult<Opportunity> {
+        let mut opportunity = self.require(id)?;
+        if !opportunity.stage.can_transition_to(stage) {
+            return Err(CoreError::business(format!(
+                "销售机会当前为「{}」，不能改为「{}」",
+                opportunity.stage.label(),
+                stage.label()
+            )));
+        }
+        match stage {
+            OpportunityStage::Won => {
+                let accepted = match opportunity.quote_id {
+                    Some(quote_id) => self.quote_accepted(quote_id).map_err(to_core)?,
+                    None => false,
+                };
+                if !accepted {
+                    return Err(CoreError::business("赢单前须关联一份客户已接受的报价"));
+                }
+                opportunity.probability = 100;
+            }
+            OpportunityStage::Lost => {
+                let reason = lost_reason.map(str::trim).unwrap_or_default();
+                if reason.is_empty() {
+                    return Err(CoreError::validation("输单必须填写原因"));
+                }
+                opportunity.lost_reason = Some(reason.to_string());
+                opportunity.probability = 0;
+            }
+            _ => {}
+        }
+        info!(
+            "销售机会「{}」阶段: {} -> {}",
+            opportunity.title,
+            opportunity.stage.as_str(),
+            stage.as_str()
+        );
+        opportunity.stage = stage;
+        opportunity.updated_at = self.clock.now();
+        self.update(&opportunity).map_err(to_core)?;
+        Ok(opportunity)
+    }
+
+    async fn pipeline(&self) -> CoreResult<Pipeline> {
+        // 金额不为负，整数除法即四舍五入（与 `Money::percent` 一致）
+        let rows = self
+            .connection
+            .query_map(
+                "SELECT stage, COUNT(*), SUM(expected_amount),
+                        SUM((expected_amount * probability + 50) / 100)
+                 FROM opportunities WHERE deleted_at IS NULL GROUP BY stage",
+                [],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, u32>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                },
+            )
+            .map_err(to_core)?;
+        let stages = OpportunityStage::ALL
+            .into_iter()
+            .map(|stage| {
+                let row = rows.iter().find(|(name, ..)| name == stage.as_str());
+                PipelineStage {
+                    stage,
+                    count: row.map_or(0, |r| r.1),
+                    total_amount: Money::from_cents(row.map_or(0, |r| r.2)),
+                    weighted_amount: Money::from_cents(row.map_or(0, |r| r.3)),
+                }
+            })
+            .collect();
+        Ok(Pipeline { stages })
+    }
+}
+
+impl EventHandler for OpportunityStore {
+    fn name(&self) -> &str {
+        "opportunities"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        if let DomainEvent::QuoteCreatedFromOpportunity {
+            quote_id,
+            opportunity_id,
+        } = &envelope.event
+        {
+            self.on_quote_created(*opportunity_id, *quote_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, OpportunityStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let store = OpportunityStore::new(connection.clone());
+        (temp_dir, connection, store)
+    }
+
+    fn insert_quote(connection: &DatabaseConnection, status: &str) -> Uuid {
+        let now = Utc::now().to_rfc3339();
+        let customer_id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DbUuid(customer_id), "测试客户", now],
+            )
+            .unwrap();
+        let quote_id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO quotes (id, customer_id, title, total_amount, status, created_at, updated_at)
+                 VALUES (?1, ?2, '门店整装', 10000.0, ?3, ?4, ?4)",
+                params![DbUuid(quote_id), DbUuid(customer_id), status, now],
+            )
+            .unwrap();
+        quote_id
+    }
+
+    fn opportunity(title: &str, yuan: f64, probability: u8) -> Opportunity {
+        let now = Utc::now();
+        Opportunity {
+            id: Uuid::new_v4(),
+            customer_id: Uuid::new_v4(),
+            title: title.to_string(),
+            stage: OpportunityStage::Lead,
+            expected_amount: Money::from_yuan(yuan),
+            probability,
+            expected_close_date: None,
+            quote_id: None,
+            lost_reason: None,
+            notes: None,
+            owner_id: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_won_requires_accepted_quote() {
+        let (_dir, connection, store) = create_test_store();
+        let opp = store
+            .create_opportunity(opportunity("展厅样品柜", 20_000.0, 40))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            store
+                .change_stage(opp.id, OpportunityStage::Won, None)
+                .await,
+            Err(CoreError::Business(_))
+        ));
+
+        // 报价尚未被接受
+        let sent = insert_quote(&connection, "sent");
+        store.link_quote(opp.id, sent).await.unwrap();
+        assert!(store
+            .change_stage(opp.id, OpportunityStage::Won, None)
+            .await
+            .is_err());
+
+        let accepted = insert_quote(&connection, "accepted");
+        store.link_quote(opp.id, accepted).await.unwrap();
+        let won = store
+            .change_stage(opp.id, OpportunityStage::Won, None)
+            .await
+            .unwrap();
+        assert_eq!(won.stage, OpportunityStage::Won);
+        assert_eq!(won.probability, 100);
+
+        // 已结束的机会只能修改备注
+        let mut edited = won.clone();
+        edited.notes = Some("客户要求月底前安装".to_string());
+        let saved = store.update_opportunity(edited.clone()).await.unwrap();
+        assert_eq!(saved.notes, edited.notes);
+        edited.expected_amount = Money::from_yuan(1.0);
+        assert!(matches!(
+            store.update_opportunity(edited).await,
+            Err(CoreError::Business(_))
+        ));
+        assert!(store
+            .change_stage(opp.id, OpportunityStage::Lost, Some("价格"))
+            .await
+            .is_err());
+
+        // 输单必须填写原因
+        let other = store
+            .create_opportunity(opportunity("办公家具", 8_000.0, 20))
+            .await
+            .unwrap();
+        assert!(matches!(
+            store
+                .change_stage(other.id, OpportunityStage::Lost, Some("  "))
+                .await,
+            Err(CoreError::Validation(_))
+        ));
+        let lost = store
+            .change_stage(other.id, OpportunityStage::Lost, Some("选择了竞品"))
+            .await
+            .unwrap();
+        assert_eq!(lost.lost_reason.as_deref(), Some("选择了竞品"));
+        assert_eq!(lost.probability, 0);
+    }
+
+    #[tokio::test]
+    async fn test_quote_created_event_bumps_stage() {
+        let (_dir, _connection, store) = create_test_store();
+        let lead = store
+            .create_opportunity(opportunity("酒店客房家具", 150_000.0, 30))
+            .await
+            .unwrap();
+        let quote_id = Uuid::new_v4();
+
+        store
+            .handle(&EventEnvelope::new(
+                DomainEvent::QuoteCreatedFromOpportunity {
+                    quote_id,
+                    opportunity_id: lead.id,
+                },
+            ))
+            .unwrap();
+        let bumped = store.get(lead.id).unwrap().unwrap();
+        assert_eq!(bumped.stage, OpportunityStage::Quoted);
+        assert_eq!(bumped.quote_id, Some(quote_id));
+
+        // 其他事件不影响机会
+        store
+            .handle(&EventEnvelope::new(DomainEvent::EntityCreated {
+                entity: minicrm_core::EntityKind::Quote,
+                id: Uuid::new_v4(),
+            }))
+            .unwrap();
+        assert_eq!(store.get(lead.id).unwrap().unwrap(), bumped);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_weighted_amounts() {
+        let (_dir, _connection, store) = create_test_store();
+        store
+            .create_opportunity(opportunity("A", 10_000.0, 10))
+            .await
+            .unwrap();
+        // 3333.33 × 33% = 1099.9989，四舍五入为1100.00
+        store
+            .create_opportunity(opportunity("B", 3_333.33, 33))
+            .await
+            .unwrap();
+        let quoted = store
+            .create_opportunity(opportunity("C", 50_000.0, 60))
+            .await
+            .unwrap();
+        store
+            .change_stage(quoted.id, OpportunityStage::Quoted, None)
+            .await
+            .unwrap();
+        let lost = store
+            .create_opportunity(opportunity("D", 9_999.0, 50))
+            .await
+            .unwrap();
+        store
+            .change_stage(lost.id, OpportunityStage::Lost, Some("预算取消"))
+            .await
+            .unwrap();
+
+        let pipeline = store.pipeline().await.unwrap();
+        assert_eq!(pipeline.stages.len(), OpportunityStage::ALL.len());
+        let lead = &pipeline.stages[0];
+        assert_eq!(lead.stage, OpportunityStage::Lead);
+        assert_eq!(lead.count, 2);
+        assert_eq!(lead.total_amount, Money::from_yuan(13_333.33));
+        assert_eq!(lead.weighted_amount, Money::from_yuan(2_100.0));
+        assert_eq!(pipeline.stages[1].count, 0);
+        assert_eq!(
+            pipeline.stages[3].weighted_amount,
+            Money::from_yuan(30_000.0)
+        );
+        assert_eq!(pipeline.stages[5].count, 1);
+        assert_eq!(pipeline.stages[5].weighted_amount, Money::ZERO);
+        assert_eq!(pipeline.open_weighted_amount(), Money::from_yuan(32_100.0));
+    }
+}
@@ -0,0 +1,864 @@
+//! 订单与送货存储
+//!
+//! 基于 `orders` 表实现送货服务。送货状态的每次变更与对应的客户互动记录
+//! 在同一事务中写入，送货地址以JSON保存。互动记录和收款在同一事务中刷新客户汇总。
+//! 订单金额以订单币种保存，收款必须与订单及来源报价使用同一币种。
+//! 互动记录和收款事件在同一事务中登记到事件发件箱。
+//! 其他来源的互动记录（如确认已拨打的电话）也通过本存储写入。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    from_canonical_json, to_canonical_json, Address, BusinessCalendar, CanonicalEntity, Clock,
+    CoreError, CoreResult, Currency, DateRange, DeliverySchedule, DeliveryService, DeliveryStatus,
+    DomainEvent, EntityKind, EventEnvelope, Interaction, InteractionKind, InteractionService,
+    Locale, Money, Order, SystemClock,
+};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, OptionalExtension, Row, Transaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::{canonical, get_uuid};
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::generic::EntityMapper;
+use crate::repository::support::to_core;
+use crate::repository::{customer_summary, outbox};
+
+/// 订单与送货存储
+#[derive(Clone)]
+pub struct OrderStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+    calendar: BusinessCalendar,
+}
+
+impl std::fmt::Debug for OrderStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderStore")
+            .field("calendar", &self.calendar)
+            .finish_non_exhaustive()
+    }
+}
+
+fn optional_time(row: &Row<'_>, index: usize) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    row.get::<_, Option<String>>(index)?
+        .map(|value| parse_time(index, &value))
+        .transpose()
+}
+
+/// 送货地址以当前版本的规范JSON保存，变更包按文件头记录的版本转换
+fn address_json(order: &Order) -> Result<Option<String>> {
+    Ok(order
+        .delivery_address
+        .as_ref()
+        .map(to_canonical_json)
+        .transpose()?
+        .map(|value| value.to_string()))
+}
+
+fn parse_address(json: &str) -> CoreResult<Address> {
+    from_canonical_json(Address::SCHEMA_VERSION, serde_json::from_str(json)?)
+}
+
+fn row_to_order(row: &Row<'_>) -> rusqlite::Result<Order> {
+    let status: String = row.get(5)?;
+    let address: Option<String> = row.get(7)?;
+    let created_at: String = row.get(12)?;
+    let updated_at: String = row.get(13)?;
+    let currency: String = row.get(16)?;
+    Ok(Order {
+        id: get_uuid(row, 0)?,
+        order_number: row.get(1)?,
+        customer_id: get_uuid(row, 2)?,
+        quote_id: row.get::<_, Option<DbUuid>>(3)?.map(Uuid::from),
+        total_amount: Money::from_cents(row.get(4)?),
+        currency: currency.parse::<Currency>().map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(16, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        delivery_status: DeliveryStatus::parse(&status).unwrap_or_default(),
+        delivery_date: optional_time(row, 6)?,
+        delivery_address: address
+            .map(|json| parse_address(&json))
+            .transpose()
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    7,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?,
+        vehicle: row.get(8)?,
+        driver: row.get(9)?,
+        signed_by: row.get(10)?,
+        delivered_at: optional_time(row, 11)?,
+        created_at: parse_time(12, &created_at)?,
+        updated_at: parse_time(13, &updated_at)?,
+        created_by: row.get(14)?,
+        updated_by: row.get(15)?,
+    })
+}
+
+/// 订单的列映射，变更包按此在规范JSON和整行之间转换
+impl EntityMapper for Order {
+    const KIND: EntityKind = EntityKind::Order;
+    const TABLE: &'static str = "orders";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "order_number",
+        "customer_id",
+        "quote_id",
+        "total_amount",
+        "delivery_status",
+        "delivery_date",
+        "delivery_address",
+        "vehicle",
+        "driver",
+        "signed_by",
+        "delivered_at",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+        "currency",
+    ];
+    const ORDER_BY: &'static str = "order_number";
+    const SOFT_DELETE: bool = true;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// 地址只有字符串字段，序列化不会失败
+    fn to_params(&self) -> Vec<Value> {
+        vec![
+            Value::Text(canonical(self.id)),
+            Value::Text(self.order_number.clone()),
+            Value::Text(canonical(self.customer_id)),
+            Value::from(self.quote_id.map(canonical)),
+            Value::from(self.total_amount.cents()),
+            Value::Text(self.delivery_status.as_str().to_string()),
+            Value::from(self.delivery_date.map(time_key)),
+            Value::from(address_json(self).ok().flatten()),
+            Value::from(self.vehicle.clone()),
+            Value::from(self.driver.clone()),
+            Value::from(self.signed_by.clone()),
+            Value::from(self.delivered_at.map(time_key)),
+            Value::Text(time_key(self.created_at)),
+            Value::Text(time_key(self.updated_at)),
+            Value::from(self.created_by.clone()),
+            Value::from(self.updated_by.clone()),
+            Value::Text(self.currency.as_str().to_string()),
+        ]
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        row_to_order(row)
+    }
+}
+
+fn row_to_interaction(row: &Row<'_>) -> rusqlite::Result<Interaction> {
+    let kind: String = row.get(2)?;
+    let occurred_at: String = row.get(4)?;
+    Ok(Interaction {
+        id: get_uuid(row, 0)?,
+        customer_id: get_uuid(row, 1)?,
+        kind: InteractionKind::parse(&kind).unwrap_or(InteractionKind::Note),
+        content: row.get(3)?,
+        occurred_at: parse_time(4, &occurred_at)?,
+        created_by: row.get(5)?,
+    })
+}
+
+fn required_text(value: &str, message: &str) -> CoreResult<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(CoreError::validation(message));
+    }
+    Ok(value.to_string())
+}
+
+impl OrderStore {
+    /// 创建订单存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+            calendar: BusinessCalendar::default(),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置业务日历，互动记录中的送货时间按其本地时间显示
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 写入订单
+    ///
+    /// # Errors
+    ///
+    /// 序列化或数据库写入失败时返回错误。
+    pub fn insert(&self, order: &Order) -> Result<()> {
+        let conn = self.connection.get_connection()?;
+        Self::insert_row(&conn, order)
+    }
+
+    fn insert_row(conn: &rusqlite::Connection, order: &Order) -> Result<()> {
+        let placeholders: Vec<_> = (1..=Order::COLUMNS.len())
+            .map(|i| format!("?{i}"))
+            .collect();
+        conn.execute(
+            &format!(
+                "INSERT INTO orders ({}) VALUES ({})",
+                Order::COLUMNS.join(", "),
+                placeholders.join(", ")
+            ),
+            params_from_iter(order.to_params()),
+        )?;
+        Ok(())
+    }
+
+    /// 根据ID获取订单（不含已删除）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn get(&self, id: Uuid) -> Result<Option<Order>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM orders WHERE id = ?1 AND deleted_at IS NULL",
+                    Order::COLUMNS.join(", ")
+                ),
+                [DbUuid(id)],
+                row_to_order,
+            )
+            .optional()?)
+    }
+
+    /// 截至 `now` 约定送货时间已过且尚未送达的订单
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn overdue_deliveries(&self, now: DateTime<Utc>) -> Result<Vec<Order>> {
+        self.connection.query_map(
+            &format!(
+                "SELECT {} FROM orders
+                 WHERE delivery_status IN ('scheduled', 'out_for_delivery')
+                   AND delivery_date < ?1 AND deleted_at IS NULL
+                 ORDER BY delivery_date",
+                Order::COLUMNS.join(", ")
+            ),
+            [time_key(now)],
+            row_to_order,
+        )
+    }
+
+    /// 客户的互动记录（按发生时间升序）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn interactions(&self, customer_id: Uuid) -> Result<Vec<Interaction>> {
+        self.connection.query_map(
+            "SELECT id, customer_id, kind, content, occurred_at, created_by
+             FROM interactions WHERE customer_id = ?1 ORDER BY occurred_at, rowid",
+            [DbUuid(customer_id)],
+            row_to_interaction,
+        )
+    }
+
+    fn require(&self, order_id: Uuid) -> CoreResult<Order> {
+        self.get(order_id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("订单 {}", order_id)))
+    }
+
+    fn local(&self, at: DateTime<Utc>) -> String {
+        self.calendar
+            .local_time(at)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    /// 校验状态转换，应用修改并连同互动记录一起写入
+    fn transition<F>(&self, order_id: Uuid, next: DeliveryStatus, apply: F) -> CoreResult<Order>
+    where
+        F: FnOnce(&mut Order) -> String,
+    {
+        let mut order = self.require(order_id)?;
+        let from = order.delivery_status;
+        if !from.can_transition_to(next) {
+            return Err(CoreError::business(format!(
+                "订单 {} 当前为「{}」，不能改为「{}」",
+                order.order_number,
+                from.label(),
+                next.label()
+            )));
+        }
+
+        let now = self.clock.now();
+        let content = apply(&mut order);
+        order.delivery_status = next;
+        order.updated_at = now;
+
+        self.connection
+            .with_transaction(|tx| {
+                Self::write_delivery(tx, &order)?;
+                Self::log_interaction(tx, &order, &content, now)
+            })
+            .map_err(to_core)?;
+
+        info!(
+            "订单 {} 送货状态: {} -> {}",
+            order.order_number,
+            from.as_str(),
+            next.as_str()
+        );
+        Ok(order)
+    }
+
+    fn write_delivery(tx: &Transaction<'_>, order: &Order) -> Result<()> {
+        let address = address_json(order)?;
+        tx.execute(
+            "UPDATE orders SET delivery_status = ?2, delivery_date = ?3, delivery_address = ?4,
+                 vehicle = ?5, driver = ?6, signed_by = ?7, delivered_at = ?8, updated_at = ?9
+             WHERE id = ?1",
+            params![
+                DbUuid(order.id),
+                order.delivery_status.as_str(),
+                order.delivery_date.map(time_key),
+                address,
+                order.vehicle,
+                order.driver,
+                order.signed_by,
+                order.delivered_at.map(time_key),
+                time_key(order.updated_at),
+            ],
+        )
+        .context("无法更新订单送货信息")?;
+        Ok(())
+    }
+
+    fn log_interaction(
+        tx: &Transaction<'_>,
+        order: &Order,
+        content: &str,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let interaction = Interaction {
+            id: Uuid::new_v4(),
+            customer_id: order.customer_id,
+            kind: InteractionKind::Delivery,
+            content: content.to_string(),
+            occurred_at: at,
+            created_by: order.updated_by.clone(),
+        };
+        Self::insert_interaction(tx, &interaction, at)
+    }
+
+    /// 写入互动记录，刷新客户汇总并登记事件（`now` 为登记时间）
+    fn insert_interaction(
+        tx: &Transaction<'_>,
+        interaction: &Interaction,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        tx.execute(
+            "INSERT INTO interactions (id, customer_id, kind, content, occurred_at, created_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                DbUuid(interaction.id),
+                DbUuid(interaction.customer_id),
+                interaction.kind.as_str(),
+                interaction.content,
+                time_key(interaction.occurred_at),
+                interaction.created_by,
+            ],
+        )
+        .context("无法写入互动记录")?;
+        let event = DomainEvent::InteractionLogged {
+            interaction_id: interaction.id,
+            customer_id: interaction.customer_id,
+        };
+        customer_summary::apply_event(tx, &event, now)?;
+        outbox::record(tx, &EventEnvelope::at(event, now))
+    }
+
+    /// 来源报价的币种（没有来源报价或报价已不存在时为空）
+    fn quote_currency(&self, order: &Order) -> Result<Option<String>> {
+        let Some(quote_id) = order.quote_id else {
+            return Ok(None);
+        };
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT currency FROM quotes WHERE id = ?1",
+                [DbUuid(quote_id)],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// 登记订单收款，返回收款记录ID
+    ///
+    /// # Errors
+    ///
+    /// 金额不大于0、币种与订单或来源报价不一致、订单不存在或写入失败时返回错误。
+    pub fn record_payment(
+        &self,
+        order_id: Uuid,
+        amount: Money,
+        currency: Currency,
+        recorded_by: Option<String>,
+    ) -> CoreResult<Uuid> {
+        if amount.cents() <= 0 {
+            return Err(CoreError::validation("收款金额必须大于0"));
+        }
+        let order = self.require(order_id)?;
+        let quote_currency = self.quote_currency(&order).map_err(to_core)?;
+        let expected = quote_currency.unwrap_or_else(|| order.currency.to_string());
+        if currency != order.currency || currency.as_str() != expected {
+            return Err(CoreError::validation(format!(
+                "收款币种 {} 与订单 {} 的币种 {} 不一致，不能混合币种收款",
+                currency, order.order_number, expected
+            )));
+        }
+        let now = self.clock.now();
+        let payment_id = Uuid::new_v4();
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "INSERT INTO order_payments
+                         (id, order_id, amount, currency, paid_at, created_by)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        DbUuid(payment_id),
+                        DbUuid(order_id),
+                        amount.cents(),
+                        currency.as_str(),
+                        time_key(now),
+                        recorded_by,
+                    ],
+                )
+                .context("无法写入收款记录")?;
+                let event = DomainEvent::PaymentRecorded {
+                    payment_id,
+                    order_id,
+                    customer_id: order.customer_id,
+                };
+                customer_summary::apply_event(tx, &event, now)?;
+                outbox::record(tx, &EventEnvelope::at(event, now))
+            })
+            .map_err(to_core)?;
+
+        info!(
+            "订单 {} 登记收款 {}",
+            order.order_number,
+            Locale::ZhCn.money_in(amount, currency)
+        );
+        Ok(payment_id)
+    }
+}
+
+#[async_trait]
+impl DeliveryService for OrderStore {
+    async fn schedule_delivery(
+        &self,
+        order_id: Uuid,
+        schedule: DeliverySchedule,
+    ) -> CoreResult<Order> {
+        let current = self.require(order_id)?;
+        if current.delivery_status != DeliveryStatus::Unscheduled {
+            return Err(CoreError::business(format!(
+                "订单 {} 已安排送货，请使用改期",
+                current.order_number
+            )));
+        }
+        let when = self.local(schedule.delivery_date);
+        self.transition(order_id, DeliveryStatus::Scheduled, |order| {
+            let line = schedule.address.one_line();
+            order.delivery_date = Some(schedule.delivery_date);
+            order.delivery_address = Some(schedule.address);
+            order.vehicle = schedule.vehicle;
+            order.driver = schedule.driver;
+            format!("订单 {} 安排送货：{}，{}", order.order_number, when, line)
+        })
+    }
+
+    async fn dispatch_delivery(&self, order_id: Uuid) -> CoreResult<Order> {
+        self.transition(order_id, DeliveryStatus::OutForDelivery, |order| {
+            format!(
+                "订单 {} 已发车，司机：{}",
+                order.order_number,
+                order.driver.as_deref().unwrap_or("-")
+            )
+        })
+    }
+
+    async fn mark_delivered(
+        &self,
+        order_id: Uuid,
+        signed_by: &str,
+        delivered_at: DateTime<Utc>,
+    ) -> CoreResult<Order> {
+        let signed_by = required_text(signed_by, "签收人不能为空")?;
+        let when = self.local(delivered_at);
+        self.transition(order_id, DeliveryStatus::Delivered, |order| {
+            let content = format!(
+                "订单 {} 已送达，{} 签收（{}）",
+                order.order_number, signed_by, when
+            );
+            order.signed_by = Some(signed_by);
+            order.delivered_at = Some(delivered_at);
+            content
+        })
+    }
+
+    async fn mark_delivery_failed(&self, order_id: Uuid, reason: &str) -> CoreResult<Order> {
+        let reason = required_text(reason, "请填写送货失败原因")?;
+        self.transition(order_id, DeliveryStatus::Failed, |order| {
+            format!("订单 {} 送货失败：{}", order.order_number, reason)
+        })
+    }
+
+    async fn reschedule(
+        &self,
+        order_id: Uuid,
+        delivery_date: DateTime<Utc>,
+        reason: &str,
+    ) -> CoreResult<Order> {
+        let reason = required_text(reason, "请填写改期原因")?;
+        let current = self.require(order_id)?;
+        if current.delivery_status == DeliveryStatus::Unscheduled {
+            return Err(CoreError::business(format!(
+                "订单 {} 尚未安排送货",
+                current.order_number
+            )));
+        }
+        let when = self.local(delivery_date);
+        let previous = current.delivery_date.map(|d| self.local(d));
+        self.transition(order_id, DeliveryStatus::Scheduled, |order| {
+            order.delivery_date = Some(delivery_date);
+            format!(
+                "订单 {} 送货改期：{} → {}，原因：{}",
+                order.order_number,
+                previous.as_deref().unwrap_or("-"),
+                when,
+                reason
+            )
+        })
+    }
+
+    async fn confirm_order(&self, order: Order) -> CoreResult<Order> {
+        if order.order_number.trim().is_empty() {
+            return Err(CoreError::validation("订单编号不能为空"));
+        }
+        if !order.total_amount.is_positive() {
+            return Err(CoreError::validation("订单金额必须大于0"));
+        }
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| {
+                Self::insert_row(tx, &order).context("无法写入订单")?;
+                let event = DomainEvent::EntityCreated {
+                    entity: EntityKind::Order,
+                    id: order.id,
+                };
+                customer_summary::apply_event(tx, &event, now)?;
+                outbox::record(tx, &EventEnvelope::at(event, now))
+            })
+            .map_err(to_core)?;
+        info!("确认订单 {}", order.order_number);
+        self.require(order.id)
+    }
+
+    async fn deliveries_between(&self, range: DateRange) -> CoreResult<Vec<Order>> {
+        self.connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM orders
+                     WHERE delivery_date >= ?1 AND delivery_date < ?2
+                       AND delivery_status != 'unscheduled' AND deleted_at IS NULL
+                     ORDER BY delivery_date, order_number",
+                    Order::COLUMNS.join(", ")
+                ),
+                params![time_key(range.start), time_key(range.end)],
+                row_to_order,
+            )
+            .map_err(to_core)
+    }
+}
+
+#[async_trait]
+impl InteractionService for OrderStore {
+    async fn log_interaction(&self, interaction: Interaction) -> CoreResult<Interaction> {
+        if interaction.content.trim().is_empty() {
+            return Err(CoreError::validation("互动内容不能为空"));
+        }
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| Self::insert_interaction(tx, &interaction, now))
+            .map_err(to_core)?;
+        info!(
+            "客户 {} 记录互动：{}",
+            interaction.customer_id,
+            interaction.kind.as_str()
+        );
+        Ok(interaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::outbox::EventOutboxStore;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::{Currency, ManualClock};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, OrderStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(june_30()));
+        (temp_dir, OrderStore::new(connection).with_clock(clock))
+    }
+
+    fn june_30() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 30, 8, 0, 0).unwrap()
+    }
+
+    fn order(number: &str) -> Order {
+        let now = june_30();
+        Order {
+            id: Uuid::new_v4(),
+            order_number: number.to_string(),
+            customer_id: Uuid::new_v4(),
+            quote_id: None,
+            total_amount: Money::from_yuan(12_800.0),
+            currency: Currency::CNY,
+            delivery_status: DeliveryStatus::Unscheduled,
+            delivery_date: None,
+            delivery_address: None,
+            vehicle: None,
+            driver: None,
+            signed_by: None,
+            delivered_at: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    fn schedule(at: DateTime<Utc>) -> DeliverySchedule {
+        DeliverySchedule {
+            delivery_date: at,
+            address: Address {
+                province: "广东省".to_string(),
+                city: "佛山市".to_string(),
+                district: Some("顺德区".to_string()),
+                street: "乐从镇家具城3号".to_string(),
+                contact: Some("王经理".to_string()),
+                phone: None,
+            },
+            vehicle: Some("粤E·12345".to_string()),
+            driver: Some("老李".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delivery_status_machine() {
+        let (_dir, store) = create_test_store();
+        let o = order("DD20240701-001");
+        store.insert(&o).unwrap();
+        let at = Utc.with_ymd_and_hms(2024, 7, 2, 2, 0, 0).unwrap();
+
+        // 未安排时不能直接发车或改期
+        assert!(matches!(
+            store.dispatch_delivery(o.id).await,
+            Err(CoreError::Business(_))
+        ));
+        assert!(matches!(
+            store.reschedule(o.id, at, "客户要求").await,
+            Err(CoreError::Business(_))
+        ));
+
+        let scheduled = store.schedule_delivery(o.id, schedule(at)).await.unwrap();
+        assert_eq!(scheduled.delivery_status, DeliveryStatus::Scheduled);
+        assert!(matches!(
+            store.schedule_delivery(o.id, schedule(at)).await,
+            Err(CoreError::Business(_))
+        ));
+
+        assert!(matches!(
+            store.reschedule(o.id, at, "  ").await,
+            Err(CoreError::Validation(_))
+        ));
+        let later = at + Duration::days(1);
+        store
+            .reschedule(o.id, later, "客户仓库未腾空")
+            .await
+            .unwrap();
+
+        store.dispatch_delivery(o.id).await.unwrap();
+        store.mark_delivery_failed(o.id, "路口限行").await.unwrap();
+        assert!(matches!(
+            store.mark_delivered(o.id, "王经理", later).await,
+            Err(CoreError::Business(_))
+        ));
+
+        store
+            .reschedule(o.id, later + Duration::days(1), "重新派车")
+            .await
+            .unwrap();
+        let delivered = store
+            .mark_delivered(o.id, "王经理", later + Duration::days(1))
+            .await
+            .unwrap();
+        assert_eq!(delivered.delivery_status, DeliveryStatus::Delivered);
+        assert_eq!(delivered.signed_by.as_deref(), Some("王经理"));
+        assert!(matches!(
+            store.reschedule(o.id, later, "再送一次").await,
+            Err(CoreError::Business(_))
+        ));
+
+        let stored = store.get(o.id).unwrap().unwrap();
+        assert_eq!(stored, delivered);
+        assert_eq!(
+            stored.delivery_address.map(|a| a.one_line()).as_deref(),
+            Some("广东省佛山市顺德区乐从镇家具城3号")
+        );
+
+        let log = store.interactions(o.customer_id).unwrap();
+        assert_eq!(log.len(), 6);
+        assert!(log.iter().all(|i| i.kind == InteractionKind::Delivery));
+        assert!(log[1].content.contains("客户仓库未腾空"));
+        assert!(log[0].content.contains("2024-07-02 10:00"));
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_between_and_overdue() {
+        let (_dir, store) = create_test_store();
+        let base = Utc.with_ymd_and_hms(2024, 7, 1, 1, 0, 0).unwrap();
+        let mut ids = Vec::new();
+        for (i, offset) in [0, 2, 9].into_iter().enumerate() {
+            let o = order(&format!("DD20240701-00{}", i + 1));
+            store.insert(&o).unwrap();
+            store
+                .schedule_delivery(o.id, schedule(base + Duration::days(offset)))
+                .await
+                .unwrap();
+            ids.push(o.id);
+        }
+        store.insert(&order("DD20240701-009")).unwrap();
+
+        let week = store
+            .deliveries_between(DateRange::new(base, base + Duration::days(7)))
+            .await
+            .unwrap();
+        let numbers: Vec<&str> = week.iter().map(|o| o.order_number.as_str()).collect();
+        assert_eq!(numbers, vec!["DD20240701-001", "DD20240701-002"]);
+
+        store.mark_delivered(ids[0], "张三", base).await.unwrap();
+        let overdue = store.overdue_deliveries(base + Duration::days(3)).unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].id, ids[1]);
+        assert!(overdue[0].is_delivery_overdue(base + Duration::days(3)));
+    }
+
+    #[test]
+    fn test_record_payment_rejects_mixed_currency() {
+        let (_dir, store) = create_test_store();
+        let mut o = order("DD20240701-010");
+        o.currency = Currency::USD;
+        store.insert(&o).unwrap();
+        assert_eq!(store.get(o.id).unwrap().unwrap().currency, Currency::USD);
+
+        assert!(matches!(
+            store.record_payment(o.id, Money::from_yuan(100.0), Currency::CNY, None),
+            Err(CoreError::Validation(_))
+        ));
+        store
+            .record_payment(o.id, Money::from_yuan(100.0), Currency::USD, None)
+            .unwrap();
+
+        let conn = store.connection.get_connection().unwrap();
+        let currency: String = conn
+            .query_row(
+                "SELECT currency FROM order_payments WHERE order_id = ?1",
+                [DbUuid(o.id)],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(currency, "USD");
+    }
+
+    #[test]
+    fn test_payment_event_recorded_in_outbox() {
+        let (_dir, store) = create_test_store();
+        let o = order("DD20240701-011");
+        store.insert(&o).unwrap();
+        let payment_id = store
+            .record_payment(o.id, Money::from_yuan(100.0), Currency::CNY, None)
+            .unwrap();
+
+        let pending = EventOutboxStore::new(store.connection.clone())
+            .pending(10)
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(matches!(
+            pending[0].envelope.event,
+            DomainEvent::PaymentRecorded { payment_id: id, .. } if id == payment_id
+        ));
+        assert_eq!(pending[0].envelope.occurred_at, june_30());
+    }
+
+    #[tokio::test]
+    async fn test_log_call_interaction() {
+        let (_dir, store) = create_test_store();
+        let customer_id = Uuid::new_v4();
+        let call = Interaction {
+            id: Uuid::new_v4(),
+            customer_id,
+            kind: InteractionKind::Call,
+            content: "拨打电话 13812345678".to_string(),
+            occurred_at: june_30() - Duration::minutes(5),
+            created_by: Some("sales".to_string()),
+        };
+        store.log_interaction(call.clone()).await.unwrap();
+        assert_eq!(store.interactions(customer_id).unwrap(), vec![call.clone()]);
+
+        let pending = EventOutboxStore::new(store.connection.clone())
+            .pending(10)
+            .unwrap();
+        assert!(matches!(
+            pending[0].envelope.event,
+            DomainEvent::InteractionLogged { interaction_id, .. } if interaction_id == call.id
+        ));
+
+        let err = store
+            .log_interaction(Interaction {
+                id: Uuid::new_v4(),
+                content: " ".to_string(),
+                ..call
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+    }
+}
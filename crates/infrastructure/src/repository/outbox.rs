@@ -0,0 +1,413 @@
+//! 事件发件箱
+//!
+//! 进程内事件总线在提交之后才发布，若程序在提交和发布之间退出，事件就会丢失。
+//! 写入业务数据的存储在同一事务中调用 [`record`] 把事件登记到 `event_outbox` 表，
+//! 由 [`OutboxDispatcher`] 按登记顺序发布到事件总线后标记为已分发；启动时先分发一次，
+//! 上次未分发的事件会被重放。
+//!
+//! 投递语义为“至少一次”：发布后、标记前退出时，重启后同一事件（事件ID不变）会再次发布，
+//! 订阅者必须幂等。Webhook 投递按（端点, 事件ID）去重；客户汇总按事件重新计算，重复处理
+//! 没有副作用。逾期提醒由后台任务扫描订单生成，不订阅事件，按（类型, 实体, 提醒时间）去重。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use minicrm_core::{
+    from_canonical_json, to_canonical_json, CanonicalEntity, Clock, CoreError, CoreResult,
+    EventEnvelope, EventHandler, Job, JobSchedule, SystemClock,
+};
+use rusqlite::{params, Connection};
+use tracing::{debug, info, warn};
+
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+/// 已分发事件的默认保留天数
+pub const DEFAULT_RETENTION_DAYS: i64 = 7;
+
+/// 每次轮询最多分发的事件数
+const BATCH_SIZE: usize = 100;
+
+/// 在业务事务中登记事件
+///
+/// 与业务修改一起提交或回滚。同一事件ID只登记一次。事件以规范JSON保存，并记录其结构版本，
+/// 升级应用后仍能重放旧版本登记的事件。
+///
+/// # Errors
+///
+/// 序列化或写入失败时返回错误。
+pub fn record(conn: &Connection, envelope: &EventEnvelope) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO event_outbox
+             (event_id, event_type, payload, schema_version, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            DbUuid(envelope.id),
+            envelope.event.event_type(),
+            to_canonical_json(envelope)
+                .context("无法序列化事件")?
+                .to_string(),
+            EventEnvelope::SCHEMA_VERSION,
+            time_key(envelope.occurred_at),
+        ],
+    )
+    .context("无法登记事件")?;
+    Ok(())
+}
+
+/// 发件箱中的事件
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    /// 登记顺序
+    pub seq: i64,
+    /// 事件
+    pub envelope: EventEnvelope,
+}
+
+/// 事件发件箱存储
+#[derive(Clone)]
+pub struct EventOutboxStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+    retention: Duration,
+}
+
+impl std::fmt::Debug for EventOutboxStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventOutboxStore")
+            .field("retention", &self.retention)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventOutboxStore {
+    /// 创建发件箱存储，已分发事件保留 [`DEFAULT_RETENTION_DAYS`] 天
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+            retention: Duration::days(DEFAULT_RETENTION_DAYS),
+        }
+    }
+
+    /// 使用指定时钟（测试用）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置已分发事件的保留时长
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// 按登记顺序列出未分发的事件
+    ///
+    /// # Errors
+    ///
+    /// 查询或解析失败时返回错误。
+    pub fn pending(&self, limit: usize) -> Result<Vec<OutboxEntry>> {
+        let rows = self.connection.query_map(
+            "SELECT seq, payload, schema_version FROM event_outbox
+             WHERE dispatched_at IS NULL ORDER BY seq LIMIT ?1",
+            [i64::try_from(limit).unwrap_or(i64::MAX)],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u32>(2)?,
+                ))
+            },
+        )?;
+        rows.into_iter()
+            .map(|(seq, payload, version)| {
+                let envelope = serde_json::from_str(&payload)
+                    .map_err(CoreError::from)
+                    .and_then(|value| from_canonical_json(version, value))
+                    .with_context(|| format!("无法解析发件箱事件 {}", seq))?;
+                Ok(OutboxEntry { seq, envelope })
+            })
+            .collect()
+    }
+
+    /// 标记事件已分发
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn mark_dispatched(&self, seq: i64) -> Result<()> {
+        self.connection.execute(
+            "UPDATE event_outbox SET dispatched_at = ?1 WHERE seq = ?2",
+            params![time_key(self.clock.now()), seq],
+        )?;
+        Ok(())
+    }
+
+    /// 删除超过保留期的已分发事件，返回删除的条数
+    ///
+    /// 未分发的事件无论多旧都保留。
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn prune(&self) -> Result<usize> {
+        self.connection.execute(
+            "DELETE FROM event_outbox WHERE dispatched_at IS NOT NULL AND created_at < ?1",
+            [time_key(self.clock.now() - self.retention)],
+        )
+    }
+
+    /// 删除一批登记时间早于 `cutoff` 的已分发事件，返回删除的条数（不超过 `limit`）
+    ///
+    /// 按登记顺序删除，未分发的事件无论多旧都保留。
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn prune_batch(&self, cutoff: DateTime<Utc>, limit: usize) -> Result<usize> {
+        self.connection.execute(
+            "DELETE FROM event_outbox WHERE seq IN (
+                 SELECT seq FROM event_outbox
+                 WHERE dispatched_at IS NOT NULL AND created_at < ?1
+                 ORDER BY seq LIMIT ?2)",
+            params![time_key(cutoff), i64::try_from(limit).unwrap_or(i64::MAX)],
+        )
+    }
+}
+
+/// 发件箱分发任务
+///
+/// 把未分发的事件按顺序交给 `sink`（通常是事件总线）。`sink` 返回错误时停止本轮分发，
+/// 保留顺序，下次轮询从该事件继续。
+#[derive(Clone)]
+pub struct OutboxDispatcher {
+    store: EventOutboxStore,
+    sink: Arc<dyn EventHandler>,
+    interval: Duration,
+}
+
+impl std::fmt::Debug for OutboxDispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutboxDispatcher")
+            .field("store", &self.store)
+            .field("sink", &self.sink.name())
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+impl OutboxDispatcher {
+    /// 创建分发任务，默认每5秒轮询一次
+    pub fn new(store: EventOutboxStore, sink: Arc<dyn EventHandler>) -> Self {
+        Self {
+            store,
+            sink,
+            interval: Duration::seconds(5),
+        }
+    }
+
+    /// 设置轮询间隔
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 分发全部未分发的事件，返回分发的条数
+    ///
+    /// 启动时调用一次即可重放上次未分发的事件。
+    ///
+    /// # Errors
+    ///
+    /// 读取或标记失败时返回错误。
+    pub fn dispatch_pending(&self) -> Result<usize> {
+        let mut dispatched = 0;
+        loop {
+            let entries = self.store.pending(BATCH_SIZE)?;
+            if entries.is_empty() {
+                break;
+            }
+            for entry in entries {
+                if let Err(e) = self.sink.handle(&entry.envelope) {
+                    warn!(
+                        "发布事件 {} 失败，稍后重试: {}",
+                        entry.envelope.event.event_type(),
+                        e
+                    );
+                    return Ok(dispatched);
+                }
+                self.store.mark_dispatched(entry.seq)?;
+                dispatched += 1;
+            }
+        }
+        if dispatched > 0 {
+            debug!("发件箱已分发 {} 个事件", dispatched);
+        }
+        Ok(dispatched)
+    }
+}
+
+#[async_trait]
+impl Job for OutboxDispatcher {
+    fn name(&self) -> &str {
+        "event_outbox"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(self.interval)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        self.dispatch_pending().map_err(to_core)?;
+        let pruned = self.store.prune().map_err(to_core)?;
+        if pruned > 0 {
+            info!("已清理 {} 条已分发的事件", pruned);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use minicrm_core::{DomainEvent, EntityKind, ManualClock};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    /// 记录收到的事件ID；`fail` 为真时拒绝事件
+    #[derive(Default)]
+    struct Recorder {
+        received: Mutex<Vec<Uuid>>,
+        fail: Mutex<bool>,
+    }
+
+    impl EventHandler for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+            if *self.fail.lock().unwrap() {
+                return Err(CoreError::business("订阅者暂不可用"));
+            }
+            self.received.lock().unwrap().push(envelope.id);
+            Ok(())
+        }
+    }
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, Arc<ManualClock>, EventOutboxStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 1, 8, 0, 0).unwrap(),
+        ));
+        let store = EventOutboxStore::new(connection.clone()).with_clock(clock.clone());
+        (temp_dir, connection, clock, store)
+    }
+
+    fn created(at: DateTime<Utc>) -> EventEnvelope {
+        EventEnvelope::at(
+            DomainEvent::EntityCreated {
+                entity: EntityKind::Customer,
+                id: Uuid::new_v4(),
+            },
+            at,
+        )
+    }
+
+    #[test]
+    fn test_record_commits_with_business_change() {
+        let (_dir, connection, clock, store) = create_test_store();
+        let kept = created(clock.now());
+        let rolled_back = created(clock.now());
+
+        connection
+            .with_transaction(|tx| record(tx, &kept))
+            .unwrap();
+        let result: Result<()> = connection.with_transaction(|tx| {
+            record(tx, &rolled_back)?;
+            anyhow::bail!("业务写入失败")
+        });
+        assert!(result.is_err());
+
+        let pending = store.pending(10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].envelope.id, kept.id);
+    }
+
+    #[test]
+    fn test_dispatch_in_order_and_retry_after_failure() {
+        let (_dir, connection, clock, store) = create_test_store();
+        let events: Vec<EventEnvelope> = (0..3).map(|_| created(clock.now())).collect();
+        for envelope in &events {
+            connection
+                .with_transaction(|tx| record(tx, envelope))
+                .unwrap();
+        }
+        let recorder = Arc::new(Recorder::default());
+        let dispatcher = OutboxDispatcher::new(store.clone(), recorder.clone());
+
+        *recorder.fail.lock().unwrap() = true;
+        assert_eq!(dispatcher.dispatch_pending().unwrap(), 0);
+        assert_eq!(store.pending(10).unwrap().len(), 3);
+
+        *recorder.fail.lock().unwrap() = false;
+        assert_eq!(dispatcher.dispatch_pending().unwrap(), 3);
+        let ids: Vec<Uuid> = events.iter().map(|e| e.id).collect();
+        assert_eq!(*recorder.received.lock().unwrap(), ids);
+        assert!(store.pending(10).unwrap().is_empty());
+        assert_eq!(dispatcher.dispatch_pending().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_undispatched_events_replayed_after_restart() {
+        let (_dir, connection, clock, store) = create_test_store();
+        let envelope = created(clock.now());
+        connection
+            .with_transaction(|tx| record(tx, &envelope))
+            .unwrap();
+        // 提交后、发布前退出：分发任务没有运行
+
+        let recorder = Arc::new(Recorder::default());
+        let restarted = OutboxDispatcher::new(store, recorder.clone());
+        assert_eq!(restarted.dispatch_pending().unwrap(), 1);
+        assert_eq!(*recorder.received.lock().unwrap(), vec![envelope.id]);
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_pending_and_recent() {
+        let (_dir, connection, clock, store) = create_test_store();
+        let old = created(clock.now());
+        let stuck = created(clock.now());
+        connection
+            .with_transaction(|tx| {
+                record(tx, &old)?;
+                record(tx, &stuck)
+            })
+            .unwrap();
+        let seq = store.pending(10).unwrap()[0].seq;
+        store.mark_dispatched(seq).unwrap();
+
+        clock.advance(Duration::days(DEFAULT_RETENTION_DAYS - 1));
+        assert_eq!(store.prune().unwrap(), 0);
+
+        clock.advance(Duration::days(2));
+        let recorder = Arc::new(Recorder::default());
+        *recorder.fail.lock().unwrap() = true;
+        OutboxDispatcher::new(store.clone(), recorder)
+            .run()
+            .await
+            .unwrap();
+        let count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM event_outbox", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(store.pending(10).unwrap()[0].envelope.id, stuck.id);
+    }
+}
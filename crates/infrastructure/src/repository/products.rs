@@ -0,0 +1,507 @@
+//! 产品目录存储
+//!
+//! 产品保存在 `products`，价格按生效时间追加到 `product_prices`，不修改已有价格。
+//! 某一时刻的价格是生效时间不晚于该时刻的最后一条，历史报价可据此按创建时间重新计价。
+//! 成本价保存在产品上，并随每条价格记录一份：新价格沿用当时的成本价，修改成本价时同步
+//! 更新当期及尚未生效的价格记录，历史价格期间的成本保持不变。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Clock, CoreError, CoreResult, Money, PricedProduct, PricingService, Product, ProductPrice,
+    ProductService, SystemClock,
+};
+use rusqlite::{params, Row};
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const PRODUCT_COLUMNS: &str =
+    "id, name, specification, category, retired, created_at, updated_at, cost_price";
+
+const PRICE_COLUMNS: &str =
+    "id, product_id, price, effective_from, created_by, created_at, cost_price";
+
+/// 产品目录存储
+#[derive(Clone)]
+pub struct ProductStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for ProductStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProductStore").finish_non_exhaustive()
+    }
+}
+
+fn row_to_product(row: &Row<'_>) -> rusqlite::Result<Product> {
+    let created_at: String = row.get(5)?;
+    let updated_at: String = row.get(6)?;
+    Ok(Product {
+        id: get_uuid(row, 0)?,
+        name: row.get(1)?,
+        specification: row.get(2)?,
+        category: row.get(3)?,
+        retired: row.get(4)?,
+        cost_price: row.get::<_, Option<i64>>(7)?.map(Money::from_cents),
+        created_at: parse_time(5, &created_at)?,
+        updated_at: parse_time(6, &updated_at)?,
+    })
+}
+
+fn row_to_price(row: &Row<'_>) -> rusqlite::Result<ProductPrice> {
+    let effective_from: String = row.get(3)?;
+    let created_at: String = row.get(5)?;
+    Ok(ProductPrice {
+        id: get_uuid(row, 0)?,
+        product_id: get_uuid(row, 1)?,
+        price: Money::from_cents(row.get(2)?),
+        cost_price: row.get::<_, Option<i64>>(6)?.map(Money::from_cents),
+        effective_from: parse_time(3, &effective_from)?,
+        created_by: row.get(4)?,
+        created_at: parse_time(5, &created_at)?,
+    })
+}
+
+impl ProductStore {
+    /// 创建产品目录存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn find_product(&self, id: Uuid) -> Result<Option<Product>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!("SELECT {} FROM products WHERE id = ?1", PRODUCT_COLUMNS),
+                [DbUuid(id)],
+                row_to_product,
+            )?
+            .into_iter()
+            .next())
+    }
+
+    fn find_price_at(&self, product_id: Uuid, at: DateTime<Utc>) -> Result<Option<ProductPrice>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM product_prices
+                     WHERE product_id = ?1 AND effective_from <= ?2
+                     ORDER BY effective_from DESC LIMIT 1",
+                    PRICE_COLUMNS
+                ),
+                params![DbUuid(product_id), time_key(at)],
+                row_to_price,
+            )?
+            .into_iter()
+            .next())
+    }
+
+    /// 同一生效时间是否已有价格
+    fn price_exists(&self, product_id: Uuid, effective_from: DateTime<Utc>) -> Result<bool> {
+        self.connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM product_prices
+                            WHERE product_id = ?1 AND effective_from = ?2)",
+            params![DbUuid(product_id), time_key(effective_from)],
+            |row| row.get(0),
+        )
+    }
+}
+
+#[async_trait]
+impl ProductService for ProductStore {
+    async fn create_product(&self, product: Product) -> CoreResult<Product> {
+        let now = self.clock.now();
+        let product = Product {
+            name: product.name.trim().to_string(),
+            created_at: now,
+            updated_at: now,
+            ..product
+        };
+        if product.name.is_empty() {
+            return Err(CoreError::validation("产品名称不能为空"));
+        }
+        if product.cost_price.is_some_and(|cost| cost.cents() < 0) {
+            return Err(CoreError::validation("成本价不能为负数"));
+        }
+        self.connection
+            .execute(
+                &format!(
+                    "INSERT INTO products ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    PRODUCT_COLUMNS
+                ),
+                params![
+                    DbUuid(product.id),
+                    product.name,
+                    product.specification,
+                    product.category,
+                    product.retired,
+                    time_key(product.created_at),
+                    time_key(product.updated_at),
+                    product.cost_price.map(|cost| cost.cents()),
+                ],
+            )
+            .map_err(to_core)?;
+        Ok(product)
+    }
+
+    async fn get_product(&self, id: Uuid) -> CoreResult<Option<Product>> {
+        self.find_product(id).map_err(to_core)
+    }
+
+    async fn list_products(&self, category: Option<&str>) -> CoreResult<Vec<Product>> {
+        let result = match category {
+            Some(category) => self.connection.query_map(
+                &format!(
+                    "SELECT {} FROM products WHERE category = ?1 ORDER BY name, id",
+                    PRODUCT_COLUMNS
+                ),
+                [category],
+                row_to_product,
+            ),
+            None => self.connection.query_map(
+                &format!("SELECT {} FROM products ORDER BY name, id", PRODUCT_COLUMNS),
+                [],
+                row_to_product,
+            ),
+        };
+        result.map_err(to_core)
+    }
+
+    async fn set_price(
+        &self,
+        product_id: Uuid,
+        price: Money,
+        effective_from: DateTime<Utc>,
+        created_by: Option<String>,
+    ) -> CoreResult<ProductPrice> {
+        if !price.is_positive() {
+            return Err(CoreError::validation("价格须大于0"));
+        }
+        let Some(product) = self.find_product(product_id).map_err(to_core)? else {
+            return Err(CoreError::not_found(format!("产品 {}", product_id)));
+        };
+        if self
+            .price_exists(product_id, effective_from)
+            .map_err(to_core)?
+        {
+            return Err(CoreError::conflict(format!(
+                "该产品在 {} 已有生效价格",
+                effective_from.format("%Y-%m-%d %H:%M")
+            )));
+        }
+
+        let entry = ProductPrice {
+            id: Uuid::new_v4(),
+            product_id,
+            price,
+            cost_price: product.cost_price,
+            effective_from,
+            created_by,
+            created_at: self.clock.now(),
+        };
+        self.connection
+            .execute(
+                &format!(
+                    "INSERT INTO product_prices ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    PRICE_COLUMNS
+                ),
+                params![
+                    DbUuid(entry.id),
+                    DbUuid(entry.product_id),
+                    entry.price.cents(),
+                    time_key(entry.effective_from),
+                    entry.created_by,
+                    time_key(entry.created_at),
+                    entry.cost_price.map(|cost| cost.cents()),
+                ],
+            )
+            .map_err(to_core)?;
+        Ok(entry)
+    }
+
+    async fn set_cost_price(&self, product_id: Uuid, cost: Option<Money>) -> CoreResult<Product> {
+        if cost.is_some_and(|cost| cost.cents() < 0) {
+            return Err(CoreError::validation("成本价不能为负数"));
+        }
+        let now = self.clock.now();
+        let cents = cost.map(|cost| cost.cents());
+        let updated = self
+            .connection
+            .with_transaction(|tx| {
+                let updated = tx.execute(
+                    "UPDATE products SET cost_price = ?2, updated_at = ?3 WHERE id = ?1",
+                    params![DbUuid(product_id), cents, time_key(now)],
+                )?;
+                tx.execute(
+                    "UPDATE product_prices SET cost_price = ?2
+                     WHERE product_id = ?1
+                       AND effective_from >= COALESCE(
+                           (SELECT MAX(effective_from) FROM product_prices
+                            WHERE product_id = ?1 AND effective_from <= ?3), '')",
+                    params![DbUuid(product_id), cents, time_key(now)],
+                )?;
+                Ok(updated)
+            })
+            .map_err(to_core)?;
+        if updated == 0 {
+            return Err(CoreError::not_found(format!("产品 {}", product_id)));
+        }
+        self.find_product(product_id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("产品 {}", product_id)))
+    }
+
+    async fn price_at(
+        &self,
+        product_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> CoreResult<Option<ProductPrice>> {
+        self.find_price_at(product_id, at).map_err(to_core)
+    }
+
+    async fn price_history(&self, product_id: Uuid) -> CoreResult<Vec<ProductPrice>> {
+        self.connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM product_prices WHERE product_id = ?1
+                     ORDER BY effective_from DESC",
+                    PRICE_COLUMNS
+                ),
+                [DbUuid(product_id)],
+                row_to_price,
+            )
+            .map_err(to_core)
+    }
+}
+
+#[async_trait]
+impl PricingService for ProductStore {
+    async fn priced_product(
+        &self,
+        product_id: Uuid,
+        at: DateTime<Utc>,
+    ) -> CoreResult<Option<PricedProduct>> {
+        let Some(product) = self.find_product(product_id).map_err(to_core)? else {
+            return Ok(None);
+        };
+        let price = self.find_price_at(product_id, at).map_err(to_core)?;
+        let unit_cost = match &price {
+            Some(price) => price.cost_price,
+            None => product.cost_price,
+        };
+        Ok(Some(PricedProduct {
+            product_id,
+            name: product.name,
+            specification: product.specification,
+            unit_price: price.map(|p| p.price.as_yuan()),
+            unit_cost: unit_cost.map(|cost| cost.as_yuan()),
+            retired: product.retired,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, ProductStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+        ));
+        let store = ProductStore::new(connection).with_clock(clock);
+        (temp_dir, store)
+    }
+
+    async fn product(store: &ProductStore, name: &str) -> Product {
+        let now = Utc::now();
+        store
+            .create_product(Product {
+                id: Uuid::new_v4(),
+                name: name.to_string(),
+                specification: Some("1220×2440×18mm".to_string()),
+                category: Some("板材".to_string()),
+                retired: false,
+                cost_price: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_price_at_exact_boundaries() {
+        let (_dir, store) = create_test_store();
+        let board = product(&store, "生态板").await;
+        let first = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+        store
+            .set_price(board.id, Money::from_yuan(128.0), first, None)
+            .await
+            .unwrap();
+        store
+            .set_price(
+                board.id,
+                Money::from_yuan(135.0),
+                second,
+                Some("admin".into()),
+            )
+            .await
+            .unwrap();
+
+        let price_at = |at| {
+            let store = store.clone();
+            async move { store.price_at(board.id, at).await.unwrap().map(|p| p.price) }
+        };
+        let tick = Duration::microseconds(1);
+        assert_eq!(price_at(first - tick).await, None);
+        assert_eq!(price_at(first).await, Some(Money::from_yuan(128.0)));
+        assert_eq!(price_at(second - tick).await, Some(Money::from_yuan(128.0)));
+        assert_eq!(price_at(second).await, Some(Money::from_yuan(135.0)));
+
+        let history = store.price_history(board.id).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].effective_from, second);
+        assert_eq!(history[0].created_by.as_deref(), Some("admin"));
+
+        let duplicate = store
+            .set_price(board.id, Money::from_yuan(140.0), second, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(duplicate, CoreError::Conflict(_)));
+        let negative = store
+            .set_price(board.id, Money::ZERO, first + Duration::days(1), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(negative, CoreError::Validation(_)));
+    }
+
+    #[tokio::test]
+    async fn test_priced_product_uses_requested_time() {
+        let (_dir, store) = create_test_store();
+        let board = product(&store, "生态板").await;
+        let unpriced = product(&store, "封边条").await;
+        let january = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let april = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+        store
+            .set_price(board.id, Money::from_yuan(128.0), january, None)
+            .await
+            .unwrap();
+        store
+            .set_price(board.id, Money::from_yuan(135.0), april, None)
+            .await
+            .unwrap();
+
+        // 二月创建的报价按二月的价格重新计价
+        let quote_created = Utc.with_ymd_and_hms(2024, 2, 15, 10, 0, 0).unwrap();
+        let priced = store
+            .priced_product(board.id, quote_created)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(priced.unit_price, Some(128.0));
+        let now = store
+            .priced_product(board.id, april)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(now.unit_price, Some(135.0));
+
+        let unpriced = store
+            .priced_product(unpriced.id, april)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(unpriced.unit_price, None);
+        assert!(store
+            .priced_product(Uuid::new_v4(), april)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cost_price_kept_per_price_period() {
+        let (_dir, store) = create_test_store();
+        let board = product(&store, "生态板").await;
+        let january = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let april = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+        store
+            .set_price(board.id, Money::from_yuan(128.0), january, None)
+            .await
+            .unwrap();
+        let updated = store
+            .set_cost_price(board.id, Some(Money::from_yuan(90.0)))
+            .await
+            .unwrap();
+        assert_eq!(updated.cost_price, Some(Money::from_yuan(90.0)));
+        store
+            .set_price(board.id, Money::from_yuan(135.0), april, None)
+            .await
+            .unwrap();
+
+        // 三月（当前时间）改成本价，一月起的当期价格和四月生效的价格都更新
+        store
+            .set_cost_price(board.id, Some(Money::from_yuan(95.0)))
+            .await
+            .unwrap();
+        let february = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let reader = store.clone();
+        let cost_at = |at| {
+            let store = reader.clone();
+            async move {
+                store
+                    .priced_product(board.id, at)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .unit_cost
+            }
+        };
+        assert_eq!(cost_at(february).await, Some(95.0));
+        assert_eq!(cost_at(april).await, Some(95.0));
+
+        // 四月之后再改成本价，三月的报价仍按当时的成本计算
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+        ));
+        let store = store.with_clock(clock);
+        store
+            .set_cost_price(board.id, Some(Money::from_yuan(100.0)))
+            .await
+            .unwrap();
+        assert_eq!(cost_at(february).await, Some(95.0));
+        assert_eq!(cost_at(april).await, Some(100.0));
+
+        let negative = store
+            .set_cost_price(board.id, Some(Money::from_cents(-1)))
+            .await
+            .unwrap_err();
+        assert!(matches!(negative, CoreError::Validation(_)));
+    }
+}
@@ -0,0 +1,521 @@
+//! 采购订单存储
+//!
+//! 基于 `purchase_orders` 和 `purchase_order_lines` 表实现采购订单服务。采购订单号在
+//! 创建事务中从单据编号序列取号。收货时明细的已收数量与采购入库的库存变动
+//! （[`InventoryStore::insert_movement`]）在同一事务中写入。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use minicrm_core::{
+    BusinessCalendar, Clock, CoreError, CoreResult, Money, PurchaseOrder, PurchaseOrderLine,
+    PurchaseOrderService, PurchaseOrderStatus, PurchaseReceipt, StockMovement, StockMovementKind,
+    SupplierPayables, SystemClock, PURCHASE_ORDER_PREFIX,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::inventory::InventoryStore;
+use crate::repository::sequences::next_document_number;
+use crate::repository::support::to_core;
+
+const PURCHASE_ORDER_COLUMNS: &str =
+    "id, po_number, supplier_id, status, expected_date, created_at, updated_at, created_by";
+
+/// 采购订单存储
+#[derive(Clone)]
+pub struct PurchaseOrderStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+    calendar: BusinessCalendar,
+}
+
+impl std::fmt::Debug for PurchaseOrderStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PurchaseOrderStore")
+            .field("calendar", &self.calendar)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 读取订单头（明细另行加载）
+fn row_to_header(row: &Row<'_>) -> rusqlite::Result<PurchaseOrder> {
+    let status: String = row.get(3)?;
+    let created_at: String = row.get(5)?;
+    let updated_at: String = row.get(6)?;
+    Ok(PurchaseOrder {
+        id: get_uuid(row, 0)?,
+        po_number: row.get(1)?,
+        supplier_id: get_uuid(row, 2)?,
+        lines: Vec::new(),
+        status: PurchaseOrderStatus::parse(&status).unwrap_or_default(),
+        expected_date: row
+            .get::<_, Option<String>>(4)?
+            .map(|value| parse_time(4, &value))
+            .transpose()?,
+        created_at: parse_time(5, &created_at)?,
+        updated_at: parse_time(6, &updated_at)?,
+        created_by: row.get(7)?,
+    })
+}
+
+fn row_to_line(row: &Row<'_>) -> rusqlite::Result<PurchaseOrderLine> {
+    Ok(PurchaseOrderLine {
+        product_id: get_uuid(row, 0)?,
+        quantity: row.get(1)?,
+        unit_price: Money::from_cents(row.get(2)?),
+        received_quantity: row.get(3)?,
+    })
+}
+
+impl PurchaseOrderStore {
+    /// 创建采购订单存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+            calendar: BusinessCalendar::default(),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 设置业务日历，采购订单号按其本地日期编号
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    fn load(conn: &Connection, id: Uuid) -> Result<Option<PurchaseOrder>> {
+        let header = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM purchase_orders WHERE id = ?1 AND deleted_at IS NULL",
+                    PURCHASE_ORDER_COLUMNS
+                ),
+                [DbUuid(id)],
+                row_to_header,
+            )
+            .optional()?;
+        header
+            .map(|mut order| {
+                order.lines = Self::load_lines(conn, id)?;
+                Ok(order)
+            })
+            .transpose()
+    }
+
+    fn load_lines(conn: &Connection, id: Uuid) -> Result<Vec<PurchaseOrderLine>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT product_id, quantity, unit_price, received_quantity
+             FROM purchase_order_lines WHERE purchase_order_id = ?1 ORDER BY line_no",
+        )?;
+        let lines = stmt
+            .query_map([DbUuid(id)], row_to_line)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(lines)
+    }
+
+    fn require(&self, id: Uuid) -> CoreResult<PurchaseOrder> {
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        Self::load(&conn, id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("采购订单 {}", id)))
+    }
+
+    fn check_transition(order: &PurchaseOrder, next: PurchaseOrderStatus) -> CoreResult<()> {
+        if order.status.can_transition_to(next) {
+            return Ok(());
+        }
+        Err(CoreError::business(format!(
+            "采购订单 {} 当前为「{}」，不能改为「{}」",
+            order.po_number,
+            order.status.label(),
+            next.label()
+        )))
+    }
+
+    fn write_status(tx: &Transaction<'_>, order: &PurchaseOrder) -> Result<()> {
+        tx.execute(
+            "UPDATE purchase_orders SET status = ?2, updated_at = ?3 WHERE id = ?1",
+            params![
+                DbUuid(order.id),
+                order.status.as_str(),
+                time_key(order.updated_at)
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 只修改状态的转换（发出、取消）
+    fn transition(&self, id: Uuid, next: PurchaseOrderStatus) -> CoreResult<PurchaseOrder> {
+        let mut order = self.require(id)?;
+        Self::check_transition(&order, next)?;
+        let from = order.status;
+        order.status = next;
+        order.updated_at = self.clock.now();
+        self.connection
+            .with_transaction(|tx| Self::write_status(tx, &order))
+            .map_err(to_core)?;
+        info!(
+            "采购订单 {} 状态: {} -> {}",
+            order.po_number,
+            from.as_str(),
+            next.as_str()
+        );
+        Ok(order)
+    }
+}
+
+#[async_trait]
+impl PurchaseOrderService for PurchaseOrderStore {
+    async fn create_purchase_order(&self, order: PurchaseOrder) -> CoreResult<PurchaseOrder> {
+        order.validate()?;
+        let mut order = order;
+        let now = self.clock.now();
+        order.status = PurchaseOrderStatus::Draft;
+        order.created_at = now;
+        order.updated_at = now;
+        for line in &mut order.lines {
+            line.received_quantity = 0;
+        }
+        let date = self.calendar.local_date(now);
+        order.po_number = self
+            .connection
+            .with_transaction(|tx| {
+                let number = next_document_number(tx, PURCHASE_ORDER_PREFIX, date)?;
+                tx.execute(
+                    &format!(
+                        "INSERT INTO purchase_orders ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        PURCHASE_ORDER_COLUMNS
+                    ),
+                    params![
+                        DbUuid(order.id),
+                        number,
+                        DbUuid(order.supplier_id),
+                        order.status.as_str(),
+                        order.expected_date.map(time_key),
+                        time_key(order.created_at),
+                        time_key(order.updated_at),
+                        order.created_by,
+                    ],
+                )
+                .context("无法写入采购订单")?;
+                let mut stmt = tx.prepare_cached(
+                    "INSERT INTO purchase_order_lines
+                         (purchase_order_id, line_no, product_id, quantity, unit_price)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?;
+                for (line_no, line) in order.lines.iter().enumerate() {
+                    stmt.execute(params![
+                        DbUuid(order.id),
+                        i64::try_from(line_no)?,
+                        DbUuid(line.product_id),
+                        line.quantity,
+                        line.unit_price.cents(),
+                    ])?;
+                }
+                Ok(number)
+            })
+            .map_err(to_core)?;
+        info!("创建采购订单 {}", order.po_number);
+        Ok(order)
+    }
+
+    async fn get_purchase_order(&self, id: Uuid) -> CoreResult<Option<PurchaseOrder>> {
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        Self::load(&conn, id).map_err(to_core)
+    }
+
+    async fn purchase_orders_for_supplier(
+        &self,
+        supplier_id: Uuid,
+    ) -> CoreResult<Vec<PurchaseOrder>> {
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        let load = || -> Result<Vec<PurchaseOrder>> {
+            let mut stmt = conn.prepare_cached(&format!(
+                "SELECT {} FROM purchase_orders
+                 WHERE supplier_id = ?1 AND deleted_at IS NULL
+                 ORDER BY created_at DESC, po_number DESC",
+                PURCHASE_ORDER_COLUMNS
+            ))?;
+            let mut orders = stmt
+                .query_map([DbUuid(supplier_id)], row_to_header)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for order in &mut orders {
+                order.lines = Self::load_lines(&conn, order.id)?;
+            }
+            Ok(orders)
+        };
+        load().map_err(to_core)
+    }
+
+    async fn send_purchase_order(&self, id: Uuid) -> CoreResult<PurchaseOrder> {
+        self.transition(id, PurchaseOrderStatus::Sent)
+    }
+
+    async fn receive_purchase_order(
+        &self,
+        id: Uuid,
+        receipts: Vec<PurchaseReceipt>,
+        actor: Option<&str>,
+    ) -> CoreResult<PurchaseOrder> {
+        let mut order = self.require(id)?;
+        let from = order.status;
+        let received = order.receive(&receipts)?;
+        let now = self.clock.now();
+        order.updated_at = now;
+
+        self.connection
+            .with_transaction(|tx| {
+                Self::write_status(tx, &order)?;
+                let mut stmt = tx.prepare_cached(
+                    "UPDATE purchase_order_lines SET received_quantity = ?3
+                     WHERE purchase_order_id = ?1 AND line_no = ?2",
+                )?;
+                for (line_no, line) in order.lines.iter().enumerate() {
+                    stmt.execute(params![
+                        DbUuid(order.id),
+                        i64::try_from(line_no)?,
+                        line.received_quantity
+                    ])?;
+                }
+                for receipt in &received {
+                    let movement = StockMovement {
+                        id: Uuid::new_v4(),
+                        product_id: receipt.product_id,
+                        kind: StockMovementKind::Purchase,
+                        quantity: i64::from(receipt.quantity),
+                        reference_id: Some(order.id),
+                        occurred_at: now,
+                        created_by: actor.map(str::to_string),
+                    };
+                    InventoryStore::insert_movement(tx, &movement)?;
+                }
+                Ok(())
+            })
+            .map_err(to_core)?;
+
+        info!(
+            "采购订单 {} 收货 {} 个产品，状态: {} -> {}",
+            order.po_number,
+            received.len(),
+            from.as_str(),
+            order.status.as_str()
+        );
+        Ok(order)
+    }
+
+    async fn cancel_purchase_order(&self, id: Uuid) -> CoreResult<PurchaseOrder> {
+        self.transition(id, PurchaseOrderStatus::Cancelled)
+    }
+
+    async fn supplier_payables(&self, supplier_id: Uuid) -> CoreResult<SupplierPayables> {
+        self.connection
+            .query_row(
+                "SELECT COALESCE(SUM(l.unit_price * l.received_quantity), 0),
+                        COALESCE(SUM(CASE WHEN o.status IN ('sent', 'partially_received')
+                                          THEN l.unit_price * (l.quantity - l.received_quantity)
+                                     END), 0),
+                        COUNT(DISTINCT CASE WHEN o.status IN ('sent', 'partially_received')
+                                            THEN o.id END)
+                 FROM purchase_orders o
+                 JOIN purchase_order_lines l ON l.purchase_order_id = o.id
+                 WHERE o.supplier_id = ?1 AND o.deleted_at IS NULL",
+                [DbUuid(supplier_id)],
+                |row| {
+                    Ok(SupplierPayables {
+                        supplier_id,
+                        outstanding_payables: Money::from_cents(row.get(0)?),
+                        on_order: Money::from_cents(row.get(1)?),
+                        open_orders: row.get(2)?,
+                    })
+                },
+            )
+            .map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{DateTime, TimeZone, Utc};
+    use minicrm_core::{InventoryService, ManualClock};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, PurchaseOrderStore, InventoryStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(july_1()));
+        let store = PurchaseOrderStore::new(connection.clone()).with_clock(clock);
+        (temp_dir, store, InventoryStore::new(connection))
+    }
+
+    fn july_1() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 1, 2, 0, 0).unwrap()
+    }
+
+    fn draft(supplier_id: Uuid, lines: &[(Uuid, u32, i64)]) -> PurchaseOrder {
+        PurchaseOrder {
+            id: Uuid::new_v4(),
+            po_number: String::new(),
+            supplier_id,
+            lines: lines
+                .iter()
+                .map(|&(product_id, quantity, cents)| PurchaseOrderLine {
+                    product_id,
+                    quantity,
+                    unit_price: Money::from_cents(cents),
+                    received_quantity: 0,
+                })
+                .collect(),
+            status: PurchaseOrderStatus::Draft,
+            expected_date: Some(Utc.with_ymd_and_hms(2024, 7, 8, 2, 0, 0).unwrap()),
+            created_at: july_1(),
+            updated_at: july_1(),
+            created_by: Some("buyer".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receiving_creates_stock_movements() {
+        let (_dir, store, inventory) = create_test_store();
+        let supplier = Uuid::new_v4();
+        let board = Uuid::new_v4();
+        let edge = Uuid::new_v4();
+        let po = store
+            .create_purchase_order(draft(supplier, &[(board, 20, 8_000), (edge, 100, 250)]))
+            .await
+            .unwrap();
+        assert_eq!(po.po_number, "PO20240701-001");
+
+        // 草稿不能收货
+        let early = store
+            .receive_purchase_order(
+                po.id,
+                vec![PurchaseReceipt {
+                    product_id: board,
+                    quantity: 5,
+                }],
+                None,
+            )
+            .await;
+        assert!(early.is_err());
+
+        store.send_purchase_order(po.id).await.unwrap();
+        let po = store
+            .receive_purchase_order(
+                po.id,
+                vec![PurchaseReceipt {
+                    product_id: board,
+                    quantity: 12,
+                }],
+                Some("warehouse"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(po.status, PurchaseOrderStatus::PartiallyReceived);
+        assert_eq!(inventory.stock_on_hand(board).await.unwrap(), 12);
+
+        // 超收被拒绝，库存不变
+        let over = store
+            .receive_purchase_order(
+                po.id,
+                vec![PurchaseReceipt {
+                    product_id: board,
+                    quantity: 9,
+                }],
+                None,
+            )
+            .await;
+        assert!(over.is_err());
+        assert_eq!(inventory.stock_on_hand(board).await.unwrap(), 12);
+
+        let po = store
+            .receive_purchase_order(
+                po.id,
+                vec![
+                    PurchaseReceipt {
+                        product_id: board,
+                        quantity: 8,
+                    },
+                    PurchaseReceipt {
+                        product_id: edge,
+                        quantity: 100,
+                    },
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(po.status, PurchaseOrderStatus::Received);
+        let reloaded = store.get_purchase_order(po.id).await.unwrap().unwrap();
+        assert_eq!(reloaded, po);
+
+        let movements = inventory
+            .stock_movements_for(po.id, StockMovementKind::Purchase)
+            .await
+            .unwrap();
+        let quantities: Vec<(Uuid, i64)> = movements
+            .iter()
+            .map(|m| (m.product_id, m.quantity))
+            .collect();
+        assert_eq!(quantities, vec![(board, 12), (board, 8), (edge, 100)]);
+        assert_eq!(movements[0].created_by.as_deref(), Some("warehouse"));
+        assert_eq!(inventory.stock_on_hand(edge).await.unwrap(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_payables() {
+        let (_dir, store, _inventory) = create_test_store();
+        let supplier = Uuid::new_v4();
+        let product = Uuid::new_v4();
+        let received = store
+            .create_purchase_order(draft(supplier, &[(product, 10, 1_000)]))
+            .await
+            .unwrap();
+        let cancelled = store
+            .create_purchase_order(draft(supplier, &[(product, 3, 1_000)]))
+            .await
+            .unwrap();
+        assert_eq!(cancelled.po_number, "PO20240701-002");
+
+        store.send_purchase_order(received.id).await.unwrap();
+        store
+            .receive_purchase_order(
+                received.id,
+                vec![PurchaseReceipt {
+                    product_id: product,
+                    quantity: 4,
+                }],
+                None,
+            )
+            .await
+            .unwrap();
+        // 已收货的订单不能取消，草稿可以
+        assert!(store.cancel_purchase_order(received.id).await.is_err());
+        let cancelled = store.cancel_purchase_order(cancelled.id).await.unwrap();
+        assert_eq!(cancelled.status, PurchaseOrderStatus::Cancelled);
+        assert!(store.send_purchase_order(cancelled.id).await.is_err());
+
+        let payables = store.supplier_payables(supplier).await.unwrap();
+        assert_eq!(payables.outstanding_payables, Money::from_cents(4_000));
+        assert_eq!(payables.on_order, Money::from_cents(6_000));
+        assert_eq!(payables.open_orders, 1);
+
+        let orders = store.purchase_orders_for_supplier(supplier).await.unwrap();
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].po_number, "PO20240701-002");
+    }
+}
@@ -0,0 +1,327 @@
+//! 供应商报价存储
+//!
+//! 记录供应商对我方的报价，按产品比较各供应商的最新价和均价。
+//! 单价以“分”为单位存为整数，时间以固定宽度的UTC文本存储以便按字符串比较。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, DateRange, Money, PurchaseQuote, PurchaseQuoteService,
+    SupplierPriceComparison, SystemClock,
+};
+use rusqlite::{params, Row};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{get_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const QUOTE_COLUMNS: &str =
+    "id, supplier_id, product_id, unit_price, moq, valid_until, received_at, notes";
+
+/// 每个供应商最新一条报价的窗口子查询（`rn = 1` 为最新）
+const RANKED_QUOTES: &str = "
+    SELECT id, supplier_id, product_id, unit_price, moq, valid_until, received_at, notes,
+           ROW_NUMBER() OVER (
+               PARTITION BY supplier_id ORDER BY received_at DESC, id DESC
+           ) AS rn,
+           AVG(unit_price) OVER (PARTITION BY supplier_id) AS avg_price,
+           COUNT(*) OVER (PARTITION BY supplier_id) AS quote_count
+    FROM purchase_quotes";
+
+/// 供应商报价存储
+#[derive(Clone)]
+pub struct PurchaseQuoteStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for PurchaseQuoteStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PurchaseQuoteStore").finish_non_exhaustive()
+    }
+}
+
+fn row_to_quote(row: &Row<'_>) -> rusqlite::Result<PurchaseQuote> {
+    Ok(PurchaseQuote {
+        id: get_uuid(row, 0)?,
+        supplier_id: get_uuid(row, 1)?,
+        product_id: get_uuid(row, 2)?,
+        unit_price: Money::from_cents(row.get(3)?),
+        moq: row.get(4)?,
+        valid_until: get_time(row, 5)?,
+        received_at: get_time(row, 6)?,
+        notes: row.get(7)?,
+    })
+}
+
+impl PurchaseQuoteStore {
+    /// 创建报价存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟判断过期（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 写入一条报价
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn insert(&self, quote: &PurchaseQuote) -> Result<()> {
+        self.connection.execute(
+            &format!(
+                "INSERT INTO purchase_quotes ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                QUOTE_COLUMNS
+            ),
+            params![
+                DbUuid(quote.id),
+                DbUuid(quote.supplier_id),
+                DbUuid(quote.product_id),
+                quote.unit_price.cents(),
+                quote.moq,
+                time_key(quote.valid_until),
+                time_key(quote.received_at),
+                quote.notes,
+            ],
+        )?;
+        debug!(
+            "供应商 {} 对产品 {} 报价 {}",
+            quote.supplier_id, quote.product_id, quote.unit_price
+        );
+        Ok(())
+    }
+
+    /// 产品在每个供应商处最近收到的一条报价（按单价升序）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn latest_per_supplier(&self, product_id: Uuid) -> Result<Vec<PurchaseQuote>> {
+        self.connection.query_map(
+            &format!(
+                "SELECT {} FROM ({} WHERE product_id = ?1)
+                 WHERE rn = 1 ORDER BY unit_price, supplier_id",
+                QUOTE_COLUMNS, RANKED_QUOTES
+            ),
+            [DbUuid(product_id)],
+            row_to_quote,
+        )
+    }
+
+    /// 按供应商汇总时间范围内收到的报价
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn compare(
+        &self,
+        product_id: Uuid,
+        range: DateRange,
+    ) -> Result<Vec<SupplierPriceComparison>> {
+        let now = self.clock.now();
+        self.connection.query_map(
+            &format!(
+                "SELECT {}, avg_price, quote_count
+                 FROM ({} WHERE product_id = ?1 AND received_at >= ?2 AND received_at < ?3)
+                 WHERE rn = 1 ORDER BY unit_price, supplier_id",
+                QUOTE_COLUMNS, RANKED_QUOTES
+            ),
+            params![
+                DbUuid(product_id),
+                time_key(range.start),
+                time_key(range.end)
+            ],
+            |row| {
+                let latest = row_to_quote(row)?;
+                let average: f64 = row.get(8)?;
+                Ok(SupplierPriceComparison {
+                    supplier_id: latest.supplier_id,
+                    expired: latest.is_expired(now),
+                    average_price: Money::from_cents(average.round() as i64),
+                    quote_count: row.get(9)?,
+                    latest,
+                })
+            },
+        )
+    }
+
+    fn validate(quote: &PurchaseQuote) -> CoreResult<()> {
+        if !quote.unit_price.is_positive() {
+            return Err(CoreError::validation("报价单价必须大于0"));
+        }
+        if quote.moq == Some(0) {
+            return Err(CoreError::validation("最小起订量必须大于0"));
+        }
+        if quote.valid_until < quote.received_at {
+            return Err(CoreError::validation("报价有效期不能早于收到报价的时间"));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PurchaseQuoteService for PurchaseQuoteStore {
+    async fn record_purchase_quote(&self, quote: PurchaseQuote) -> CoreResult<PurchaseQuote> {
+        Self::validate(&quote)?;
+        let quote = PurchaseQuote {
+            notes: quote
+                .notes
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty()),
+            ..quote
+        };
+        self.insert(&quote).map_err(to_core)?;
+        Ok(quote)
+    }
+
+    async fn latest_quotes_for_product(&self, product_id: Uuid) -> CoreResult<Vec<PurchaseQuote>> {
+        self.latest_per_supplier(product_id).map_err(to_core)
+    }
+
+    async fn compare_suppliers(
+        &self,
+        product_id: Uuid,
+        range: DateRange,
+    ) -> CoreResult<Vec<SupplierPriceComparison>> {
+        self.compare(product_id, range).map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, PurchaseQuoteStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        (temp_dir, PurchaseQuoteStore::new(connection))
+    }
+
+    fn june(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, day, 9, 0, 0).unwrap()
+    }
+
+    fn quote(
+        supplier_id: Uuid,
+        product_id: Uuid,
+        yuan: f64,
+        received_at: DateTime<Utc>,
+    ) -> PurchaseQuote {
+        PurchaseQuote {
+            id: Uuid::new_v4(),
+            supplier_id,
+            product_id,
+            unit_price: Money::from_yuan(yuan),
+            moq: Some(50),
+            valid_until: received_at + Duration::days(7),
+            received_at,
+            notes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latest_quote_per_supplier() {
+        let (_dir, store) = create_test_store();
+        let board = Uuid::new_v4();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        // 乱序写入，最新一条应按收到时间而非写入顺序确定
+        store
+            .record_purchase_quote(quote(a, board, 118.0, june(10)))
+            .await
+            .unwrap();
+        store
+            .record_purchase_quote(quote(a, board, 120.0, june(1)))
+            .await
+            .unwrap();
+        store
+            .record_purchase_quote(quote(b, board, 125.0, june(3)))
+            .await
+            .unwrap();
+        store
+            .record_purchase_quote(quote(b, board, 112.5, june(12)))
+            .await
+            .unwrap();
+        // 其他产品的报价不参与
+        store
+            .record_purchase_quote(quote(a, Uuid::new_v4(), 90.0, june(15)))
+            .await
+            .unwrap();
+
+        let latest = store.latest_quotes_for_product(board).await.unwrap();
+        let summary: Vec<(Uuid, i64)> = latest
+            .iter()
+            .map(|q| (q.supplier_id, q.unit_price.cents()))
+            .collect();
+        assert_eq!(summary, vec![(b, 11250), (a, 11800)]);
+        assert_eq!(latest[0].received_at, june(12));
+        assert_eq!(latest[0].moq, Some(50));
+
+        let range = DateRange::new(june(1), june(11));
+        let comparison = store.compare_suppliers(board, range).await.unwrap();
+        assert_eq!(comparison.len(), 2);
+        assert_eq!(comparison[0].supplier_id, a);
+        assert_eq!(comparison[0].latest.unit_price, Money::from_cents(11800));
+        assert_eq!(comparison[0].average_price, Money::from_cents(11900));
+        assert_eq!(comparison[0].quote_count, 2);
+        assert_eq!(comparison[1].supplier_id, b);
+        assert_eq!(comparison[1].latest.received_at, june(3));
+        assert_eq!(comparison[1].quote_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_expiry_flag_at_boundary() {
+        let (_dir, store) = create_test_store();
+        let clock = Arc::new(ManualClock::new(june(20)));
+        let store = store.with_clock(clock.clone());
+        let board = Uuid::new_v4();
+        let supplier = Uuid::new_v4();
+
+        let mut q = quote(supplier, board, 118.0, june(13));
+        q.valid_until = june(20);
+        store.record_purchase_quote(q).await.unwrap();
+        let range = DateRange::new(june(1), june(30));
+
+        // 有效期当刻仍然有效
+        let comparison = store.compare_suppliers(board, range).await.unwrap();
+        assert!(!comparison[0].expired);
+
+        clock.advance(Duration::microseconds(1));
+        let comparison = store.compare_suppliers(board, range).await.unwrap();
+        assert!(comparison[0].expired);
+    }
+
+    #[tokio::test]
+    async fn test_record_rejects_invalid_quotes() {
+        let (_dir, store) = create_test_store();
+        let mut q = quote(Uuid::new_v4(), Uuid::new_v4(), 0.0, june(1));
+        assert!(matches!(
+            store.record_purchase_quote(q.clone()).await,
+            Err(CoreError::Validation(_))
+        ));
+
+        q.unit_price = Money::from_yuan(100.0);
+        q.valid_until = june(1) - Duration::days(1);
+        assert!(matches!(
+            store.record_purchase_quote(q).await,
+            Err(CoreError::Validation(_))
+        ));
+    }
+}
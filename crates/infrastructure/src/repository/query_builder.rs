@@ -0,0 +1,359 @@
+//! 列表查询构建
+//!
+//! 把 [`QueryFilter`] 的过滤条件、搜索关键词、排序和分页转换为参数化的SQL子句。过滤条件由
+//! [`FilterTranslator`] 转换；排序只接受登记过的列，未登记的排序字段返回验证错误；分页
+//! 以 `LIMIT ? OFFSET ?` 参数给出。用户输入的值只作为参数传递，不拼接进SQL。
+
+use std::collections::BTreeMap;
+
+use minicrm_core::{CoreError, CoreResult, EntityKind, QueryFilter, SortDirection};
+use rusqlite::types::Value;
+
+use crate::repository::filter::{FilterTranslator, SqlFilter};
+
+/// 搜索关键词对应的条件（占位符为 `?`），返回 `None` 时忽略关键词
+pub type SearchCondition = fn(&str) -> Option<(String, Vec<Value>)>;
+
+/// 按子串匹配关键词的 `LIKE` 模式
+///
+/// 关键词中的 `%`、`_` 和 `\` 按字面匹配，条件中需要带上 `ESCAPE '\'`。
+pub fn like_pattern(keyword: &str) -> String {
+    let mut pattern = String::with_capacity(keyword.len() + 2);
+    pattern.push('%');
+    for c in keyword.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// 关键词在任一列中按子串匹配的条件（没有列时为 `None`）
+pub fn like_condition(columns: &[&str], keyword: &str) -> Option<(String, Vec<Value>)> {
+    if columns.is_empty() {
+        return None;
+    }
+    let clause = columns
+        .iter()
+        .map(|column| format!("{} LIKE ? ESCAPE '\\'", column))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let pattern = Value::Text(like_pattern(keyword));
+    Some((format!("({})", clause), vec![pattern; columns.len()]))
+}
+
+/// 构建好的查询子句
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQuery {
+    /// 过滤条件和搜索条件
+    pub filter: SqlFilter,
+    /// 排序子句（以空格开头）
+    pub order_by: String,
+    /// 每页条数
+    pub limit: u32,
+    /// 跳过的条数
+    pub offset: u32,
+}
+
+impl ListQuery {
+    /// `WHERE` 子句（无条件时为空字符串）
+    pub fn where_clause(&self) -> String {
+        self.filter.where_clause()
+    }
+
+    /// 统计总数时使用的参数
+    pub fn count_params(&self) -> &[Value] {
+        &self.filter.params
+    }
+
+    /// 读取一页时追加在查询末尾的排序和分页子句
+    pub fn page_clause(&self) -> String {
+        format!("{} LIMIT ? OFFSET ?", self.order_by)
+    }
+
+    /// 读取一页时使用的参数（过滤参数之后是每页条数和跳过的条数）
+    pub fn page_params(&self) -> Vec<Value> {
+        let mut params = self.filter.params.clone();
+        params.push(Value::Integer(i64::from(self.limit)));
+        params.push(Value::Integer(i64::from(self.offset)));
+        params
+    }
+}
+
+/// 列表查询构建器
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    translator: FilterTranslator,
+    id_column: String,
+    sort_columns: BTreeMap<String, String>,
+    default_sort: Option<String>,
+    conditions: Vec<String>,
+    search: Option<SearchCondition>,
+}
+
+impl QueryBuilder {
+    /// 创建构建器，`id_column` 为主查询中实体ID列的SQL表达式（如 `c.id`），也是最后的排序列
+    pub fn new(entity: EntityKind, id_column: impl Into<String>) -> Self {
+        let id_column = id_column.into();
+        Self {
+            translator: FilterTranslator::new(entity, id_column.clone()),
+            id_column,
+            sort_columns: BTreeMap::new(),
+            default_sort: None,
+            conditions: Vec::new(),
+            search: None,
+        }
+    }
+
+    /// 登记可过滤、可排序的列
+    pub fn column(mut self, key: impl Into<String>, sql: impl Into<String>) -> Self {
+        let (key, sql) = (key.into(), sql.into());
+        self.translator = self.translator.column(key.clone(), sql.clone());
+        self.sort_columns.insert(key, sql);
+        self
+    }
+
+    /// 未指定排序时的排序表达式（默认只按ID排序）
+    pub fn default_sort(mut self, sql: impl Into<String>) -> Self {
+        self.default_sort = Some(sql.into());
+        self
+    }
+
+    /// 始终附加的条件（不含参数，如 `deleted_at IS NULL`）
+    pub fn condition(mut self, clause: impl Into<String>) -> Self {
+        self.conditions.push(clause.into());
+        self
+    }
+
+    /// 搜索关键词的匹配方式（未设置时忽略关键词）
+    pub fn search(mut self, search: SearchCondition) -> Self {
+        self.search = Some(search);
+        self
+    }
+
+    /// 排序子句：按登记的列排序，最后按ID保证顺序稳定
+    fn order_clause(&self, filter: &QueryFilter) -> CoreResult<String> {
+        let Some(sort) = &filter.sort_by else {
+            return Ok(match &self.default_sort {
+                Some(sql) => format!(" ORDER BY {}, {}", sql, self.id_column),
+                None => format!(" ORDER BY {}", self.id_column),
+            });
+        };
+        let column = self
+            .sort_columns
+            .get(&sort.field)
+            .ok_or_else(|| CoreError::validation(format!("不支持的排序字段: {}", sort.field)))?;
+        let direction = match sort.direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        Ok(format!(
+            " ORDER BY {} {}, {}",
+            column, direction, self.id_column
+        ))
+    }
+
+    /// 转换查询条件
+    ///
+    /// # Errors
+    ///
+    /// 过滤条件或排序字段未登记、值类型不适用时返回验证错误。
+    pub fn build(&self, filter: &QueryFilter) -> CoreResult<ListQuery> {
+        let mut sql_filter = self.translator.translate(filter)?;
+        sql_filter
+            .clauses
+            .splice(0..0, self.conditions.iter().cloned());
+        let keyword = filter.search.as_deref().map(str::trim).unwrap_or_default();
+        if let Some((clause, params)) = self
+            .search
+            .filter(|_| !keyword.is_empty())
+            .and_then(|search| search(keyword))
+        {
+            sql_filter.clauses.push(clause);
+            sql_filter.params.extend(params);
+        }
+        Ok(ListQuery {
+            filter: sql_filter,
+            order_by: self.order_clause(filter)?,
+            limit: filter.pagination.limit(),
+            offset: filter.pagination.offset(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{FilterValue, Pagination, SortBy};
+
+    fn builder() -> QueryBuilder {
+        QueryBuilder::new(EntityKind::Task, "t.id")
+            .column("title", "t.title")
+            .column("priority", "t.priority")
+            .column("due_date", "t.due_date")
+            .default_sort("t.created_at")
+            .condition("t.deleted_at IS NULL")
+    }
+
+    fn filtered(key: &str, value: FilterValue) -> CoreResult<ListQuery> {
+        let mut filter = QueryFilter::new();
+        filter.filters.insert(key.to_string(), value);
+        builder().build(&filter)
+    }
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_scalar_and_list_values_are_parameters() {
+        let cases = [
+            (
+                FilterValue::String("'; DROP TABLE tasks; --".to_string()),
+                " WHERE t.deleted_at IS NULL AND t.title = ?",
+                vec![text("'; DROP TABLE tasks; --")],
+            ),
+            (
+                FilterValue::Integer(3),
+                " WHERE t.deleted_at IS NULL AND t.title = ?",
+                vec![Value::Integer(3)],
+            ),
+            (
+                FilterValue::Float(2.5),
+                " WHERE t.deleted_at IS NULL AND t.title = ?",
+                vec![Value::Real(2.5)],
+            ),
+            (
+                FilterValue::Boolean(true),
+                " WHERE t.deleted_at IS NULL AND t.title = ?",
+                vec![Value::Integer(1)],
+            ),
+            (
+                FilterValue::StringList(vec!["a".to_string(), "b".to_string()]),
+                " WHERE t.deleted_at IS NULL AND t.title IN (?, ?)",
+                vec![text("a"), text("b")],
+            ),
+            (
+                FilterValue::IntegerList(vec![1, 2, 3]),
+                " WHERE t.deleted_at IS NULL AND t.title IN (?, ?, ?)",
+                vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)],
+            ),
+            // 空列表不匹配任何记录
+            (
+                FilterValue::StringList(Vec::new()),
+                " WHERE t.deleted_at IS NULL AND 1 = 0",
+                vec![],
+            ),
+            (
+                FilterValue::IntegerList(Vec::new()),
+                " WHERE t.deleted_at IS NULL AND 1 = 0",
+                vec![],
+            ),
+        ];
+        for (value, where_clause, params) in cases {
+            let query = filtered("title", value).unwrap();
+            assert_eq!(query.where_clause(), where_clause);
+            assert_eq!(query.count_params(), params.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_date_ranges() {
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 6, 30, 16, 0, 0).unwrap();
+        let cases = [
+            (
+                Some(start),
+                Some(end),
+                " WHERE t.deleted_at IS NULL AND t.due_date >= ? AND t.due_date <= ?",
+                vec![
+                    text("2024-06-01T00:00:00.000000Z"),
+                    text("2024-06-30T16:00:00.000000Z"),
+                ],
+            ),
+            (
+                Some(start),
+                None,
+                " WHERE t.deleted_at IS NULL AND t.due_date >= ?",
+                vec![text("2024-06-01T00:00:00.000000Z")],
+            ),
+            (
+                None,
+                Some(end),
+                " WHERE t.deleted_at IS NULL AND t.due_date <= ?",
+                vec![text("2024-06-30T16:00:00.000000Z")],
+            ),
+            (None, None, " WHERE t.deleted_at IS NULL AND 1 = 1", vec![]),
+        ];
+        for (start, end, where_clause, params) in cases {
+            let query = filtered("due_date", FilterValue::DateRange { start, end }).unwrap();
+            assert_eq!(query.where_clause(), where_clause);
+            assert_eq!(query.count_params(), params.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_search_sort_and_pagination() {
+        let mut filter = QueryFilter::new();
+        filter.search = Some(" 样品 ".to_string());
+        filter.sort_by = Some(SortBy {
+            field: "priority".to_string(),
+            direction: SortDirection::Desc,
+        });
+        filter.pagination = Pagination {
+            page: 3,
+            page_size: 20,
+        };
+        let query = builder()
+            .search(|keyword| like_condition(&["t.title", "t.description"], keyword))
+            .build(&filter)
+            .unwrap();
+        assert_eq!(
+            query.where_clause(),
+            " WHERE t.deleted_at IS NULL \
+             AND (t.title LIKE ? ESCAPE '\\' OR t.description LIKE ? ESCAPE '\\')"
+        );
+        assert_eq!(
+            query.page_clause(),
+            " ORDER BY t.priority DESC, t.id LIMIT ? OFFSET ?"
+        );
+        assert_eq!(
+            query.page_params(),
+            vec![
+                text("%样品%"),
+                text("%样品%"),
+                Value::Integer(20),
+                Value::Integer(40)
+            ]
+        );
+
+        // 通配符按字面匹配
+        assert_eq!(like_pattern(r"100%_a\b"), r"%100\%\_a\\b%");
+
+        // 未设置搜索方式时忽略关键词；未排序时使用默认排序
+        filter.sort_by = None;
+        let query = builder().build(&filter).unwrap();
+        assert_eq!(query.where_clause(), " WHERE t.deleted_at IS NULL");
+        assert_eq!(query.order_by, " ORDER BY t.created_at, t.id");
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let mut filter = QueryFilter::new();
+        filter.sort_by = Some(SortBy {
+            field: "title; DROP TABLE tasks".to_string(),
+            direction: SortDirection::Asc,
+        });
+        assert!(matches!(
+            builder().build(&filter),
+            Err(CoreError::Validation(_))
+        ));
+        assert!(matches!(
+            filtered("status", FilterValue::String("done".to_string())),
+            Err(CoreError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,361 @@
+//! 报价明细存储
+//!
+//! 明细按行号保存在 `quote_items`，小计以分保存。保存明细时整体替换：删除原有明细后按顺序
+//! 重新写入，报价的总金额同时改为明细合计，两者在同一事务中完成。新建报价时用
+//! [`QuoteItemStore::write_items`] 在写入报价的事务中一起保存明细。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use minicrm_core::{
+    Clock, CoreError, CoreResult, DomainEvent, EntityKind, EventEnvelope, Money, QuoteItem,
+    SystemClock,
+};
+use rusqlite::{params, Connection, Row, Transaction};
+use uuid::Uuid;
+
+use crate::database::db_uuid::{get_optional_uuid, get_uuid};
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+use crate::repository::{customer_summary, outbox};
+
+const ITEM_COLUMNS: &str = "id, quote_id, product_id, product_name, specification, unit, \
+     quantity, unit_price, line_total";
+
+/// 报价明细存储
+#[derive(Clone)]
+pub struct QuoteItemStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for QuoteItemStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuoteItemStore").finish_non_exhaustive()
+    }
+}
+
+fn row_to_item(row: &Row<'_>) -> rusqlite::Result<QuoteItem> {
+    Ok(QuoteItem {
+        id: get_uuid(row, 0)?,
+        quote_id: get_uuid(row, 1)?,
+        product_id: get_optional_uuid(row, 2)?,
+        product_name: row.get(3)?,
+        specification: row.get(4)?,
+        unit: row.get(5)?,
+        quantity: row.get(6)?,
+        unit_price: row.get(7)?,
+        line_total: Money::from_cents(row.get(8)?),
+    })
+}
+
+impl QuoteItemStore {
+    /// 创建报价明细存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 读取报价的明细（按行号排序）
+    ///
+    /// # Errors
+    ///
+    /// 数据库读取失败时返回错误。
+    pub fn load_items(conn: &Connection, quote_id: Uuid) -> Result<Vec<QuoteItem>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM quote_items WHERE quote_id = ?1 ORDER BY line_no",
+            ITEM_COLUMNS
+        ))?;
+        let items = stmt
+            .query_map([DbUuid(quote_id)], row_to_item)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(items)
+    }
+
+    /// 在事务中替换报价的明细（明细按顺序编号，归属到 `quote_id`）
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn write_items(tx: &Transaction<'_>, quote_id: Uuid, items: &[QuoteItem]) -> Result<()> {
+        tx.execute(
+            "DELETE FROM quote_items WHERE quote_id = ?1",
+            [DbUuid(quote_id)],
+        )?;
+        let mut stmt = tx.prepare_cached(&format!(
+            "INSERT INTO quote_items ({}, line_no)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            ITEM_COLUMNS
+        ))?;
+        for (line_no, item) in items.iter().enumerate() {
+            stmt.execute(params![
+                DbUuid(item.id),
+                DbUuid(quote_id),
+                item.product_id.map(DbUuid),
+                item.product_name,
+                item.specification,
+                item.unit,
+                item.quantity,
+                item.unit_price,
+                item.line_total.cents(),
+                i64::try_from(line_no)?,
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// 报价的明细（按行号排序，没有明细时为空）
+    ///
+    /// # Errors
+    ///
+    /// 数据库读取失败时返回错误。
+    pub fn find_items_by_quote_id(&self, quote_id: Uuid) -> CoreResult<Vec<QuoteItem>> {
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        Self::load_items(&conn, quote_id).map_err(to_core)
+    }
+
+    /// 替换报价的明细，报价总金额改为明细合计，返回新的总金额
+    ///
+    /// 明细、总金额和事件在同一事务中写入。
+    ///
+    /// # Errors
+    ///
+    /// 小计与数量×单价不符时返回 [`CoreError::Validation`]，报价不存在时返回
+    /// [`CoreError::NotFound`]。
+    pub fn replace_items_for_quote(
+        &self,
+        quote_id: Uuid,
+        items: Vec<QuoteItem>,
+    ) -> CoreResult<Money> {
+        items.iter().try_for_each(QuoteItem::validate_line_total)?;
+        let total = QuoteItem::total(&items);
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| {
+                let updated = tx
+                    .execute(
+                        "UPDATE quotes SET total_amount = ?2, updated_at = ?3
+                         WHERE id = ?1 AND deleted_at IS NULL",
+                        params![DbUuid(quote_id), total.as_yuan(), time_key(now)],
+                    )
+                    .context("无法更新报价总金额")?;
+                if updated == 0 {
+                    return Err(CoreError::not_found(format!("报价 {}", quote_id)).into());
+                }
+                Self::write_items(tx, quote_id, &items).context("无法写入报价明细")?;
+                let event = DomainEvent::EntityUpdated {
+                    entity: EntityKind::Quote,
+                    id: quote_id,
+                };
+                customer_summary::apply_event(tx, &event, now)?;
+                outbox::record(tx, &EventEnvelope::at(event, now))
+            })
+            .map_err(to_core)?;
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{schema, MigrationManager};
+    use crate::test_support::{empty_pool, migrated_pool};
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, QuoteItemStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 15, 9, 0, 0).unwrap(),
+        ));
+        let store = QuoteItemStore::new(connection.clone()).with_clock(clock);
+        (temp_dir, connection, store)
+    }
+
+    fn insert_quote(connection: &DatabaseConnection) -> Uuid {
+        let (customer, quote) = (Uuid::new_v4(), Uuid::new_v4());
+        let conn = connection.get_connection().unwrap();
+        conn.execute(
+            "INSERT INTO customers (id, name, created_at, updated_at)
+             VALUES (?1, '华南木业', '2024-06-01T00:00:00Z', '2024-06-01T00:00:00Z')",
+            [DbUuid(customer)],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO quotes
+                 (id, customer_id, title, total_amount, status, created_at, updated_at)
+             VALUES (?1, ?2, 'BJ20240615-003', 0, 'draft',
+                     '2024-06-15T00:00:00Z', '2024-06-15T00:00:00Z')",
+            params![DbUuid(quote), DbUuid(customer)],
+        )
+        .unwrap();
+        quote
+    }
+
+    fn item(name: &str, unit: &str, quantity: f64, unit_price: f64) -> QuoteItem {
+        QuoteItem {
+            id: Uuid::new_v4(),
+            quote_id: Uuid::nil(),
+            product_id: None,
+            product_name: name.to_string(),
+            specification: Some("2440×1220×18mm".to_string()),
+            unit: Some(unit.to_string()),
+            quantity,
+            unit_price,
+            line_total: Money::from_yuan(quantity * unit_price),
+        }
+    }
+
+    fn stored_total(connection: &DatabaseConnection, quote: Uuid) -> f64 {
+        connection
+            .get_connection()
+            .unwrap()
+            .query_row(
+                "SELECT total_amount FROM quotes WHERE id = ?1",
+                [DbUuid(quote)],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migration_backfills_items_from_latest_revision() {
+        let (_dir, pool) = empty_pool();
+        let connection = DatabaseConnection::new(pool);
+        let migrations = MigrationManager::new(connection.clone())
+            .add_migrations(schema::builtin_migrations());
+        migrations.migrate(Some(44)).unwrap();
+
+        let quote = insert_quote(&connection);
+        let (board, edging) = (Uuid::new_v4(), Uuid::new_v4());
+        // 第1版为升级前的快照（没有单位和小计），第2版为最新版本
+        let revisions = [
+            serde_json::json!({
+                "quote_number": "BJ20240615-003",
+                "items": [
+                    { "id": board, "product_name": "生态板", "quantity": 5.0, "unit_price": 128.0 },
+                ],
+            }),
+            serde_json::json!({
+                "quote_number": "BJ20240615-003",
+                "items": [
+                    { "id": board, "product_name": "生态板", "specification": "2440×1220×18mm",
+                      "quantity": 10.0, "unit_price": 128.0 },
+                    { "id": edging, "product_name": "封边条", "unit": "米",
+                      "quantity": 3.3, "unit_price": 2.37, "line_total": 7.82 },
+                ],
+            }),
+        ];
+        for (revision_no, snapshot) in (1..).zip(revisions) {
+            connection
+                .execute(
+                    "INSERT INTO quote_revisions (quote_id, revision_no, snapshot, saved_at)
+                     VALUES (?1, ?2, ?3, '2024-06-15T00:00:00Z')",
+                    params![DbUuid(quote), revision_no, snapshot.to_string()],
+                )
+                .unwrap();
+        }
+        migrations.migrate(None).unwrap();
+
+        let conn = connection.get_connection().unwrap();
+        let items = QuoteItemStore::load_items(&conn, quote).unwrap();
+        assert_eq!(
+            items,
+            vec![
+                QuoteItem {
+                    id: board,
+                    quote_id: quote,
+                    product_id: None,
+                    product_name: "生态板".to_string(),
+                    specification: Some("2440×1220×18mm".to_string()),
+                    unit: None,
+                    quantity: 10.0,
+                    unit_price: 128.0,
+                    line_total: Money::from_cents(128_000),
+                },
+                QuoteItem {
+                    id: edging,
+                    quote_id: quote,
+                    product_id: None,
+                    product_name: "封边条".to_string(),
+                    specification: None,
+                    unit: Some("米".to_string()),
+                    quantity: 3.3,
+                    unit_price: 2.37,
+                    line_total: Money::from_cents(782),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_items_updates_total() {
+        let (_dir, connection, store) = create_test_store();
+        let quote = insert_quote(&connection);
+        assert!(store.find_items_by_quote_id(quote).unwrap().is_empty());
+
+        let items = vec![
+            item("生态板", "张", 10.0, 128.0),
+            item("封边条", "米", 3.3, 2.37),
+        ];
+        let total = store.replace_items_for_quote(quote, items.clone()).unwrap();
+        // 3.3 × 2.37 = 7.821，小计按行四舍五入为 7.82
+        assert_eq!(total, Money::from_cents(128_782));
+        assert!((stored_total(&connection, quote) - 1287.82).abs() < 1e-9);
+
+        let stored = store.find_items_by_quote_id(quote).unwrap();
+        let expected: Vec<QuoteItem> = items
+            .into_iter()
+            .map(|item| QuoteItem {
+                quote_id: quote,
+                ..item
+            })
+            .collect();
+        assert_eq!(stored, expected);
+
+        // 再次保存时整体替换
+        let total = store
+            .replace_items_for_quote(quote, vec![item("多层板", "张", 2.0, 96.5)])
+            .unwrap();
+        assert_eq!(total, Money::from_yuan(193.0));
+        let stored = store.find_items_by_quote_id(quote).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].product_name, "多层板");
+    }
+
+    #[test]
+    fn test_mismatched_or_missing_quote_is_rejected() {
+        let (_dir, connection, store) = create_test_store();
+        let quote = insert_quote(&connection);
+        store
+            .replace_items_for_quote(quote, vec![item("生态板", "张", 10.0, 128.0)])
+            .unwrap();
+
+        let mut wrong = item("生态板", "张", 10.0, 128.0);
+        wrong.line_total = Money::from_yuan(1200.0);
+        let err = store
+            .replace_items_for_quote(quote, vec![wrong])
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)), "{err}");
+        // 校验失败时不修改已保存的明细
+        assert_eq!(store.find_items_by_quote_id(quote).unwrap().len(), 1);
+        assert!((stored_total(&connection, quote) - 1280.0).abs() < 1e-9);
+
+        let err = store
+            .replace_items_for_quote(Uuid::new_v4(), vec![item("生态板", "张", 1.0, 128.0)])
+            .unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)), "{err}");
+    }
+}
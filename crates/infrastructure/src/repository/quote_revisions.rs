@@ -0,0 +1,340 @@
+//! 报价修订存储
+//!
+//! 非草稿报价每次保存时写入完整的规范JSON快照及其结构版本，每个报价只保留最近
+//! [`MAX_QUOTE_REVISIONS`] 个修订。恢复历史修订会追加新的修订，不改写历史。
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    from_canonical_json, to_canonical_json, CanonicalEntity, CoreError, CoreResult, Quote,
+    QuoteDiff, QuoteRevision, QuoteStatus, MAX_QUOTE_REVISIONS,
+};
+use rusqlite::{params, OptionalExtension, Row};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+/// 报价修订存储
+#[derive(Debug, Clone)]
+pub struct QuoteRevisionStore {
+    connection: DatabaseConnection,
+}
+
+/// 数据库中的修订行（quote_id, revision_no, snapshot, schema_version, saved_by, saved_at）
+type RawRevision = (Uuid, u32, String, u32, Option<String>, String);
+
+fn row_to_revision(row: &Row<'_>) -> rusqlite::Result<RawRevision> {
+    Ok((
+        get_uuid(row, 0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+    ))
+}
+
+fn decode(raw: RawRevision) -> Result<QuoteRevision> {
+    let (quote_id, revision_no, snapshot, schema_version, saved_by, saved_at) = raw;
+    let snapshot = serde_json::from_str(&snapshot).context("报价快照已损坏")?;
+    Ok(QuoteRevision {
+        quote_id,
+        revision_no,
+        snapshot: from_canonical_json(schema_version, snapshot)
+            .with_context(|| format!("无法读取报价 {} 的修订 {}", quote_id, revision_no))?,
+        saved_by,
+        saved_at: DateTime::parse_from_rfc3339(&saved_at)?.with_timezone(&Utc),
+    })
+}
+
+impl QuoteRevisionStore {
+    /// 创建修订存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 记录一次保存
+    ///
+    /// 草稿不产生修订，返回 `None`。
+    ///
+    /// # Errors
+    ///
+    /// 序列化或数据库写入失败时返回错误。
+    pub fn record(&self, quote: &Quote, saved_by: Option<&str>) -> Result<Option<QuoteRevision>> {
+        if quote.status == QuoteStatus::Draft {
+            return Ok(None);
+        }
+
+        let snapshot = to_canonical_json(quote)?.to_string();
+        let saved_at = Utc::now();
+        let quote_id = DbUuid(quote.id);
+
+        let revision_no = self.connection.with_transaction(|tx| {
+            let last: Option<u32> = tx.query_row(
+                "SELECT MAX(revision_no) FROM quote_revisions WHERE quote_id = ?1",
+                [&quote_id],
+                |row| row.get(0),
+            )?;
+            let revision_no = last.unwrap_or(0) + 1;
+            tx.execute(
+                "INSERT INTO quote_revisions
+                     (quote_id, revision_no, snapshot, schema_version, saved_by, saved_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    quote_id,
+                    revision_no,
+                    snapshot,
+                    Quote::SCHEMA_VERSION,
+                    saved_by,
+                    saved_at.to_rfc3339()
+                ],
+            )?;
+            // 只保留最近的修订
+            tx.execute(
+                "DELETE FROM quote_revisions WHERE quote_id = ?1 AND revision_no <= ?2",
+                params![
+                    quote_id,
+                    i64::from(revision_no) - MAX_QUOTE_REVISIONS as i64
+                ],
+            )?;
+            Ok(revision_no)
+        })?;
+
+        debug!("报价 {} 已保存修订 {}", quote.quote_number, revision_no);
+        Ok(Some(QuoteRevision {
+            quote_id: quote.id,
+            revision_no,
+            snapshot: quote.clone(),
+            saved_by: saved_by.map(str::to_string),
+            saved_at,
+        }))
+    }
+
+    /// 报价的全部修订（按修订号升序）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败或快照无法解析时返回错误。
+    pub fn list(&self, quote_id: Uuid) -> Result<Vec<QuoteRevision>> {
+        self.connection
+            .query_map(
+                "SELECT quote_id, revision_no, snapshot, schema_version, saved_by, saved_at
+                 FROM quote_revisions WHERE quote_id = ?1 ORDER BY revision_no",
+                [DbUuid(quote_id)],
+                row_to_revision,
+            )?
+            .into_iter()
+            .map(decode)
+            .collect()
+    }
+
+    /// 获取指定修订
+    ///
+    /// # Errors
+    ///
+    /// 查询失败或快照无法解析时返回错误。
+    pub fn get(&self, quote_id: Uuid, revision_no: u32) -> Result<Option<QuoteRevision>> {
+        let conn = self.connection.get_connection()?;
+        let raw = conn
+            .query_row(
+                "SELECT quote_id, revision_no, snapshot, schema_version, saved_by, saved_at
+                 FROM quote_revisions WHERE quote_id = ?1 AND revision_no = ?2",
+                params![DbUuid(quote_id), revision_no],
+                row_to_revision,
+            )
+            .optional()?;
+        raw.map(decode).transpose()
+    }
+
+    fn require(&self, quote_id: Uuid, revision_no: u32) -> CoreResult<QuoteRevision> {
+        self.get(quote_id, revision_no)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("报价 {} 的修订 {}", quote_id, revision_no)))
+    }
+
+    /// 比较两个修订
+    ///
+    /// # Errors
+    ///
+    /// 任一修订不存在时返回 `NotFound`。
+    pub fn diff(&self, quote_id: Uuid, a: u32, b: u32) -> CoreResult<QuoteDiff> {
+        let from = self.require(quote_id, a)?;
+        let to = self.require(quote_id, b)?;
+        Ok(QuoteDiff::between(&from, &to))
+    }
+
+    /// 恢复到历史修订
+    ///
+    /// 以历史快照为内容生成新修订并返回恢复后的报价，调用方负责写回报价表。
+    ///
+    /// # Errors
+    ///
+    /// 修订不存在时返回 `NotFound`，写入失败时返回错误。
+    pub fn restore(
+        &self,
+        quote_id: Uuid,
+        revision_no: u32,
+        saved_by: Option<&str>,
+    ) -> CoreResult<Quote> {
+        let revision = self.require(quote_id, revision_no)?;
+        let restored = Quote {
+            updated_at: Utc::now(),
+            updated_by: saved_by.map(str::to_string),
+            ..revision.snapshot
+        };
+        self.record(&restored, saved_by).map_err(to_core)?;
+        Ok(restored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::{Currency, ItemChange, Money, QuoteItem};
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, QuoteRevisionStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        (temp_dir, QuoteRevisionStore::new(connection))
+    }
+
+    fn item(name: &str, quantity: f64, unit_price: f64) -> QuoteItem {
+        QuoteItem {
+            id: Uuid::new_v4(),
+            quote_id: Uuid::nil(),
+            product_id: None,
+            product_name: name.to_string(),
+            specification: Some("2440×1220×18mm".to_string()),
+            unit: Some("张".to_string()),
+            quantity,
+            unit_price,
+            line_total: Money::from_yuan(quantity * unit_price),
+        }
+    }
+
+    fn quote(items: Vec<QuoteItem>) -> Quote {
+        let now = Utc::now();
+        Quote {
+            id: Uuid::new_v4(),
+            quote_number: "BJ20240615-003".to_string(),
+            customer_id: Uuid::new_v4(),
+            status: QuoteStatus::Sent,
+            total_amount: QuoteItem::total(&items),
+            currency: Currency::CNY,
+            items,
+            valid_until: now,
+            remarks: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshots_are_capped() {
+        let (_dir, store) = create_test_store();
+        let mut q = quote(vec![item("生态板", 10.0, 120.0)]);
+
+        q.status = QuoteStatus::Draft;
+        assert!(store.record(&q, None).unwrap().is_none());
+
+        q.status = QuoteStatus::Sent;
+        for i in 0..25 {
+            q.items[0].quantity = f64::from(i + 1);
+            store.record(&q, Some("zhangsan")).unwrap();
+        }
+
+        let revisions = store.list(q.id).unwrap();
+        assert_eq!(revisions.len(), MAX_QUOTE_REVISIONS);
+        assert_eq!(revisions.first().map(|r| r.revision_no), Some(6));
+        assert_eq!(revisions.last().map(|r| r.revision_no), Some(25));
+        assert_eq!(revisions[0].snapshot.items[0].quantity, 6.0);
+        assert_eq!(revisions[0].saved_by.as_deref(), Some("zhangsan"));
+    }
+
+    #[test]
+    fn test_item_level_diff_ignores_reordering() {
+        let (_dir, store) = create_test_store();
+        let board = item("生态板", 10.0, 120.0);
+        let plywood = item("多层板", 20.0, 85.0);
+        let edge = item("封边条", 100.0, 2.5);
+        let mut q = quote(vec![board.clone(), plywood.clone(), edge.clone()]);
+        store.record(&q, None).unwrap();
+
+        // 调整顺序、修改单价、删除一行、新增一行
+        let mut cheaper = plywood.clone();
+        cheaper.unit_price = 80.0;
+        let hinge = item("铰链", 50.0, 6.0);
+        q.items = vec![cheaper.clone(), board, hinge.clone()];
+        q.recalculate_totals();
+        store.record(&q, None).unwrap();
+
+        let diff = store.diff(q.id, 1, 2).unwrap();
+        assert_eq!(diff.items.len(), 3);
+        assert!(diff.items.contains(&ItemChange::Removed { item: edge }));
+        // 重新计算后明细归属到报价；旧修订中未填写归属不算变化
+        let hinge = QuoteItem {
+            quote_id: q.id,
+            ..hinge
+        };
+        assert!(diff.items.contains(&ItemChange::Added { item: hinge }));
+        let changed = diff
+            .items
+            .iter()
+            .find(|c| matches!(c, ItemChange::Changed { .. }))
+            .unwrap();
+        assert_eq!(changed.amount_delta(), -100.0);
+        let ItemChange::Changed { fields, .. } = changed else {
+            unreachable!()
+        };
+        let names: Vec<&str> = fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(names, vec!["unit_price", "amount"]);
+        assert!(diff.fields.iter().any(|f| f.field == "total_amount"));
+
+        assert!(matches!(store.diff(q.id, 1, 9), Err(CoreError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_restore_appends_revision() {
+        let (_dir, store) = create_test_store();
+        let mut q = quote(vec![item("生态板", 10.0, 120.0)]);
+        store.record(&q, None).unwrap();
+        q.items[0].unit_price = 110.0;
+        store.record(&q, None).unwrap();
+
+        let restored = store.restore(q.id, 1, Some("lisi")).unwrap();
+        assert_eq!(restored.items[0].unit_price, 120.0);
+        assert_eq!(restored.updated_by.as_deref(), Some("lisi"));
+
+        let revisions = store.list(q.id).unwrap();
+        assert_eq!(revisions.len(), 3);
+        assert_eq!(revisions[1].snapshot.items[0].unit_price, 110.0);
+        assert!(store.diff(q.id, 1, 3).unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_version_is_checked() {
+        let (_dir, store) = create_test_store();
+        let q = quote(vec![item("生态板", 10.0, 120.0)]);
+        store.record(&q, None).unwrap();
+        assert_eq!(store.get(q.id, 1).unwrap().unwrap().snapshot.items, q.items);
+
+        // 更新版本的应用写入的快照
+        store
+            .connection
+            .execute(
+                "UPDATE quote_revisions SET schema_version = ?1 WHERE quote_id = ?2",
+                params![Quote::SCHEMA_VERSION + 1, DbUuid(q.id)],
+            )
+            .unwrap();
+        let err = store.get(q.id, 1).unwrap_err();
+        assert!(format!("{:#}", err).contains("请先升级应用"), "{err:#}");
+    }
+}
@@ -0,0 +1,383 @@
+//! 报价模板存储
+//!
+//! 模板保存在 `quote_templates`，明细按顺序保存在 `quote_template_items`。
+//! 明细只记录产品ID、默认数量和折扣，价格在从模板新建报价时按当前价格计算。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, QuoteTemplate, QuoteTemplateItem, QuoteTemplateService,
+    SystemClock,
+};
+use rusqlite::{params, Row, Transaction};
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::support::to_core;
+
+const TEMPLATE_COLUMNS: &str = "id, name, validity_days, terms, created_at, updated_at";
+
+/// 报价模板存储
+#[derive(Clone)]
+pub struct QuoteTemplateStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for QuoteTemplateStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuoteTemplateStore").finish_non_exhaustive()
+    }
+}
+
+fn row_to_template(row: &Row<'_>) -> rusqlite::Result<QuoteTemplate> {
+    let created_at: String = row.get(4)?;
+    let updated_at: String = row.get(5)?;
+    Ok(QuoteTemplate {
+        id: get_uuid(row, 0)?,
+        name: row.get(1)?,
+        validity_days: row.get(2)?,
+        terms: row.get(3)?,
+        items: Vec::new(),
+        created_at: parse_time(4, &created_at)?,
+        updated_at: parse_time(5, &updated_at)?,
+    })
+}
+
+fn write_items(tx: &Transaction<'_>, template: &QuoteTemplate) -> Result<()> {
+    tx.execute(
+        "DELETE FROM quote_template_items WHERE template_id = ?1",
+        [DbUuid(template.id)],
+    )?;
+    for (position, item) in template.items.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO quote_template_items
+                 (template_id, position, product_id, quantity, discount)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                DbUuid(template.id),
+                position,
+                DbUuid(item.product_id),
+                item.quantity,
+                item.discount,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+impl QuoteTemplateStore {
+    /// 创建报价模板存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 按条件查询模板并填充明细
+    fn load(&self, condition: &str, id: Option<Uuid>) -> Result<Vec<QuoteTemplate>> {
+        let sql = format!(
+            "SELECT {} FROM quote_templates {} ORDER BY name",
+            TEMPLATE_COLUMNS, condition
+        );
+        let mut templates = match id {
+            Some(id) => self
+                .connection
+                .query_map(&sql, [DbUuid(id)], row_to_template)?,
+            None => self.connection.query_map(&sql, [], row_to_template)?,
+        };
+
+        let rows: Vec<(Uuid, QuoteTemplateItem)> = self.connection.query_map(
+            "SELECT template_id, product_id, quantity, discount
+             FROM quote_template_items ORDER BY template_id, position",
+            [],
+            |row| {
+                Ok((
+                    get_uuid(row, 0)?,
+                    QuoteTemplateItem {
+                        product_id: get_uuid(row, 1)?,
+                        quantity: row.get(2)?,
+                        discount: row.get(3)?,
+                    },
+                ))
+            },
+        )?;
+        let mut items: HashMap<Uuid, Vec<QuoteTemplateItem>> = HashMap::new();
+        for (template_id, item) in rows {
+            items.entry(template_id).or_default().push(item);
+        }
+        for template in &mut templates {
+            template.items = items.remove(&template.id).unwrap_or_default();
+        }
+        Ok(templates)
+    }
+
+    /// 名称是否已被其他模板使用
+    fn name_taken(&self, name: &str, except: Uuid) -> Result<bool> {
+        self.connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM quote_templates WHERE name = ?1 AND id <> ?2)",
+            params![name, DbUuid(except)],
+            |row| row.get(0),
+        )
+    }
+
+    fn validate(&self, template: &QuoteTemplate) -> CoreResult<()> {
+        if template.name.is_empty() {
+            return Err(CoreError::validation("模板名称不能为空"));
+        }
+        if template.validity_days == 0 {
+            return Err(CoreError::validation("有效天数须大于0"));
+        }
+        for (index, item) in template.items.iter().enumerate() {
+            if item.quantity <= 0.0 || !item.quantity.is_finite() {
+                return Err(CoreError::validation(format!(
+                    "第{}行：数量须大于0",
+                    index + 1
+                )));
+            }
+            if !(0.0..1.0).contains(&item.discount) {
+                return Err(CoreError::validation(format!(
+                    "第{}行：折扣率须在0到1之间",
+                    index + 1
+                )));
+            }
+        }
+        if self
+            .name_taken(&template.name, template.id)
+            .map_err(to_core)?
+        {
+            return Err(CoreError::conflict(format!(
+                "模板名称「{}」已被使用",
+                template.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl QuoteTemplateService for QuoteTemplateStore {
+    async fn create_template(&self, template: QuoteTemplate) -> CoreResult<QuoteTemplate> {
+        let now = self.clock.now();
+        let template = QuoteTemplate {
+            name: template.name.trim().to_string(),
+            created_at: now,
+            updated_at: now,
+            ..template
+        };
+        self.validate(&template)?;
+
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    &format!(
+                        "INSERT INTO quote_templates ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        TEMPLATE_COLUMNS
+                    ),
+                    params![
+                        DbUuid(template.id),
+                        template.name,
+                        template.validity_days,
+                        template.terms,
+                        time_key(template.created_at),
+                        time_key(template.updated_at),
+                    ],
+                )?;
+                write_items(tx, &template)
+            })
+            .map_err(to_core)?;
+        Ok(template)
+    }
+
+    async fn update_template(&self, template: QuoteTemplate) -> CoreResult<QuoteTemplate> {
+        let existing = self
+            .get_template(template.id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("报价模板 {}", template.id)))?;
+        let template = QuoteTemplate {
+            name: template.name.trim().to_string(),
+            created_at: existing.created_at,
+            updated_at: self.clock.now(),
+            ..template
+        };
+        self.validate(&template)?;
+
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "UPDATE quote_templates
+                     SET name = ?2, validity_days = ?3, terms = ?4, updated_at = ?5
+                     WHERE id = ?1",
+                    params![
+                        DbUuid(template.id),
+                        template.name,
+                        template.validity_days,
+                        template.terms,
+                        time_key(template.updated_at),
+                    ],
+                )?;
+                write_items(tx, &template)
+            })
+            .map_err(to_core)?;
+        Ok(template)
+    }
+
+    async fn get_template(&self, id: Uuid) -> CoreResult<Option<QuoteTemplate>> {
+        Ok(self
+            .load("WHERE id = ?1", Some(id))
+            .map_err(to_core)?
+            .into_iter()
+            .next())
+    }
+
+    async fn list_templates(&self) -> CoreResult<Vec<QuoteTemplate>> {
+        self.load("", None).map_err(to_core)
+    }
+
+    async fn delete_template(&self, id: Uuid) -> CoreResult<bool> {
+        let deleted = self
+            .connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "DELETE FROM quote_template_items WHERE template_id = ?1",
+                    [DbUuid(id)],
+                )?;
+                Ok(tx.execute("DELETE FROM quote_templates WHERE id = ?1", [DbUuid(id)])?)
+            })
+            .map_err(to_core)?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone, Utc};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, QuoteTemplateStore, Arc<ManualClock>) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap(),
+        ));
+        let store = QuoteTemplateStore::new(connection).with_clock(clock.clone());
+        (temp_dir, store, clock)
+    }
+
+    fn template(name: &str, items: Vec<QuoteTemplateItem>) -> QuoteTemplate {
+        QuoteTemplate {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            validity_days: 15,
+            terms: Some("含税含运费".to_string()),
+            items,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn item(quantity: f64, discount: f64) -> QuoteTemplateItem {
+        QuoteTemplateItem {
+            product_id: Uuid::new_v4(),
+            quantity,
+            discount,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_template_round_trip() {
+        let (_dir, store, clock) = create_test_store();
+        let items = vec![item(10.0, 0.0), item(2.0, 0.05), item(1.0, 0.1)];
+        let created = store
+            .create_template(template(" 全屋套餐 ", items.clone()))
+            .await
+            .unwrap();
+        assert_eq!(created.name, "全屋套餐");
+        assert_eq!(created.created_at, clock.now());
+        assert_eq!(
+            store.get_template(created.id).await.unwrap(),
+            Some(created.clone())
+        );
+
+        // 明细整体替换，顺序保持
+        clock.set(clock.now() + Duration::hours(1));
+        let updated = store
+            .update_template(QuoteTemplate {
+                validity_days: 30,
+                terms: None,
+                items: vec![items[2].clone(), items[0].clone()],
+                ..created.clone()
+            })
+            .await
+            .unwrap();
+        assert_eq!(updated.created_at, created.created_at);
+        assert_eq!(updated.updated_at, clock.now());
+        let loaded = store.get_template(created.id).await.unwrap().unwrap();
+        assert_eq!(loaded, updated);
+        assert_eq!(loaded.items, vec![items[2].clone(), items[0].clone()]);
+
+        store
+            .create_template(template("厨房套餐", vec![item(1.0, 0.0)]))
+            .await
+            .unwrap();
+        let names: Vec<String> = store
+            .list_templates()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["全屋套餐", "厨房套餐"]);
+
+        assert!(store.delete_template(created.id).await.unwrap());
+        assert!(!store.delete_template(created.id).await.unwrap());
+        assert_eq!(store.get_template(created.id).await.unwrap(), None);
+        assert_eq!(store.list_templates().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_template_validation() {
+        let (_dir, store, _clock) = create_test_store();
+        store
+            .create_template(template("卫浴套餐", Vec::new()))
+            .await
+            .unwrap();
+
+        let err = store
+            .create_template(template("卫浴套餐", Vec::new()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Conflict(_)));
+        let err = store
+            .create_template(template("阳台套餐", vec![item(0.0, 0.0)]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)));
+        let err = store
+            .create_template(template("阳台套餐", vec![item(1.0, 1.0)]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::Validation(_)));
+        let err = store
+            .update_template(template("阳台套餐", Vec::new()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)));
+    }
+}
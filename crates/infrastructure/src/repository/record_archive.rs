@@ -0,0 +1,572 @@
+//! 记录归档存储
+//!
+//! 按归档策略把超过保留期限的互动记录、已完成任务和已送达订单移到对应的 `archived_*` 表。
+//! 预览和归档使用同一筛选条件，同一 `as_of` 下归档移出的记录与预览完全一致。
+//!
+//! 归档前获取独占操作守卫，备份或整理进行中时本次跳过；每次运行都追加到 `archive_runs`。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    ArchiveCount, ArchiveEntity, ArchivePolicy, ArchivePreview, ArchiveRun, ArchiveRunStatus,
+    ArchiveTrigger, Clock, CoreError, CoreResult, ExclusiveGuard, Job, JobSchedule,
+    RecordArchiveService, SystemClock,
+};
+use rusqlite::{params, Connection};
+use tracing::{info, warn};
+
+use crate::database::DatabaseConnection;
+use crate::database::time::{get_time, time_key};
+use crate::repository::support::to_core;
+
+/// 归档在独占操作守卫中的名称
+const OPERATION: &str = "数据归档";
+
+/// 筛选条件（`?1` 为截止时间）
+fn condition(entity: ArchiveEntity) -> &'static str {
+    match entity {
+        ArchiveEntity::Interactions => "julianday(occurred_at) < julianday(?1)",
+        ArchiveEntity::Tasks => {
+            "lower(status) IN ('completed', 'cancelled') \
+             AND julianday(COALESCE(completed_at, updated_at)) < julianday(?1)"
+        }
+        ArchiveEntity::Orders => {
+            "delivery_status = 'delivered' \
+             AND julianday(COALESCE(delivered_at, updated_at)) < julianday(?1)"
+        }
+    }
+}
+
+fn trigger_str(trigger: ArchiveTrigger) -> &'static str {
+    match trigger {
+        ArchiveTrigger::Manual => "manual",
+        ArchiveTrigger::Scheduled => "scheduled",
+    }
+}
+
+fn parse_trigger(value: &str) -> ArchiveTrigger {
+    match value {
+        "manual" => ArchiveTrigger::Manual,
+        _ => ArchiveTrigger::Scheduled,
+    }
+}
+
+fn status_str(status: ArchiveRunStatus) -> &'static str {
+    match status {
+        ArchiveRunStatus::Completed => "completed",
+        ArchiveRunStatus::Skipped => "skipped",
+        ArchiveRunStatus::Failed => "failed",
+    }
+}
+
+fn parse_status(value: &str) -> ArchiveRunStatus {
+    match value {
+        "completed" => ArchiveRunStatus::Completed,
+        "skipped" => ArchiveRunStatus::Skipped,
+        _ => ArchiveRunStatus::Failed,
+    }
+}
+
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>("name"))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(names)
+}
+
+/// 统计将归档的记录数和估算大小
+fn count(conn: &Connection, entity: ArchiveEntity, cutoff: DateTime<Utc>) -> Result<ArchiveCount> {
+    let table = entity.table_name();
+    let size = columns(conn, table)?
+        .iter()
+        .map(|c| format!("IFNULL(length(CAST(\"{}\" AS BLOB)), 0)", c))
+        .collect::<Vec<_>>()
+        .join(" + ");
+    let (rows, bytes): (i64, i64) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM({}), 0) FROM {} WHERE {}",
+            size,
+            table,
+            condition(entity)
+        ),
+        [time_key(cutoff)],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(ArchiveCount {
+        entity,
+        cutoff,
+        rows: u64::try_from(rows).unwrap_or(0),
+        estimated_bytes: u64::try_from(bytes).unwrap_or(0),
+    })
+}
+
+/// 把符合条件的记录移到归档表
+fn move_rows(
+    conn: &Connection,
+    entity: ArchiveEntity,
+    cutoff: DateTime<Utc>,
+    archived_at: DateTime<Utc>,
+) -> Result<()> {
+    let table = entity.table_name();
+    let columns = columns(conn, table)?;
+    let list = columns
+        .iter()
+        .map(|c| format!("\"{}\"", c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let cutoff = time_key(cutoff);
+
+    // 软删除的记录已从表计数中扣除
+    if columns.iter().any(|c| c == "deleted_at") {
+        let live: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM {} WHERE {} AND deleted_at IS NULL",
+                table,
+                condition(entity)
+            ),
+            [&cutoff],
+            |row| row.get(0),
+        )?;
+        conn.execute(
+            "UPDATE table_counters SET row_count = MAX(row_count - ?2, 0), updated_at = ?3
+             WHERE table_name = ?1",
+            params![table, live, Utc::now().to_rfc3339()],
+        )?;
+    }
+
+    conn.execute(
+        &format!(
+            "INSERT INTO archived_{table} ({list}, archived_at) \
+             SELECT {list}, ?2 FROM {table} WHERE {cond}",
+            table = table,
+            list = list,
+            cond = condition(entity)
+        ),
+        params![cutoff, time_key(archived_at)],
+    )?;
+    conn.execute(
+        &format!("DELETE FROM {} WHERE {}", table, condition(entity)),
+        [&cutoff],
+    )?;
+    Ok(())
+}
+
+/// 记录归档存储
+#[derive(Clone)]
+pub struct RecordArchiver {
+    connection: DatabaseConnection,
+    guard: Arc<ExclusiveGuard>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for RecordArchiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordArchiver").finish_non_exhaustive()
+    }
+}
+
+impl RecordArchiver {
+    /// 创建记录归档存储，`guard` 与备份、整理共用
+    pub fn new(connection: DatabaseConnection, guard: Arc<ExclusiveGuard>) -> Self {
+        Self {
+            connection,
+            guard,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置时钟（用于测试）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 预览按策略在 `as_of` 时刻将归档的记录
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn preview_at(
+        &self,
+        policy: &ArchivePolicy,
+        as_of: DateTime<Utc>,
+    ) -> Result<ArchivePreview> {
+        let counts = self.connection.with_read_transaction(|tx| {
+            ArchiveEntity::ALL
+                .into_iter()
+                .filter_map(|entity| policy.cutoff(entity, as_of).map(|c| (entity, c)))
+                .map(|(entity, cutoff)| count(tx, entity, cutoff))
+                .collect::<Result<Vec<_>>>()
+        })?;
+        Ok(ArchivePreview { as_of, counts })
+    }
+
+    /// 按策略归档并记录运行历史
+    ///
+    /// # Errors
+    ///
+    /// 运行历史写入失败时返回错误；归档本身失败时回滚并返回失败状态的运行记录。
+    pub fn run(
+        &self,
+        policy: &ArchivePolicy,
+        as_of: DateTime<Utc>,
+        trigger: ArchiveTrigger,
+    ) -> Result<ArchiveRun> {
+        let started_at = self.clock.now();
+        let lease = match self.guard.try_acquire(OPERATION) {
+            Ok(lease) => lease,
+            Err(e) => {
+                let reason = match e {
+                    CoreError::Conflict(message) => message,
+                    other => other.to_string(),
+                };
+                info!("跳过数据归档: {}", reason);
+                let run = ArchiveRun {
+                    started_at,
+                    finished_at: started_at,
+                    trigger,
+                    status: ArchiveRunStatus::Skipped,
+                    counts: Vec::new(),
+                    message: Some(reason),
+                };
+                self.record(&run)?;
+                return Ok(run);
+            }
+        };
+
+        let result = self.connection.with_transaction(|tx| {
+            let mut counts = Vec::new();
+            for entity in ArchiveEntity::ALL {
+                let Some(cutoff) = policy.cutoff(entity, as_of) else {
+                    continue;
+                };
+                let counted = count(tx, entity, cutoff)?;
+                if counted.rows > 0 {
+                    move_rows(tx, entity, cutoff, started_at)?;
+                }
+                counts.push(counted);
+            }
+            Ok(counts)
+        });
+        drop(lease);
+
+        let (status, counts, message) = match result {
+            Ok(counts) => (ArchiveRunStatus::Completed, counts, None),
+            Err(e) => {
+                warn!("数据归档失败，已回滚: {:#}", e);
+                (
+                    ArchiveRunStatus::Failed,
+                    Vec::new(),
+                    Some(format!("{:#}", e)),
+                )
+            }
+        };
+        let run = ArchiveRun {
+            started_at,
+            finished_at: self.clock.now(),
+            trigger,
+            status,
+            counts,
+            message,
+        };
+        self.record(&run)?;
+        if status == ArchiveRunStatus::Completed {
+            info!(
+                "数据归档完成，共移出 {} 条记录",
+                run.counts.iter().map(|c| c.rows).sum::<u64>()
+            );
+        }
+        Ok(run)
+    }
+
+    fn record(&self, run: &ArchiveRun) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO archive_runs
+                (started_at, finished_at, run_trigger, status, counts, message)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                time_key(run.started_at),
+                time_key(run.finished_at),
+                trigger_str(run.trigger),
+                status_str(run.status),
+                serde_json::to_string(&run.counts)?,
+                run.message,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 最近的归档运行记录（新的在前）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn history(&self, limit: usize) -> Result<Vec<ArchiveRun>> {
+        self.connection.query_map(
+            "SELECT started_at, finished_at, run_trigger, status, counts, message
+             FROM archive_runs ORDER BY started_at DESC, id DESC LIMIT ?1",
+            [i64::try_from(limit).unwrap_or(i64::MAX)],
+            |row| {
+                let counts: String = row.get("counts")?;
+                Ok(ArchiveRun {
+                    started_at: get_time(row, "started_at")?,
+                    finished_at: get_time(row, "finished_at")?,
+                    trigger: parse_trigger(&row.get::<_, String>("run_trigger")?),
+                    status: parse_status(&row.get::<_, String>("status")?),
+                    counts: serde_json::from_str(&counts).unwrap_or_default(),
+                    message: row.get("message")?,
+                })
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl RecordArchiveService for RecordArchiver {
+    async fn preview(
+        &self,
+        policy: &ArchivePolicy,
+        as_of: DateTime<Utc>,
+    ) -> CoreResult<ArchivePreview> {
+        self.preview_at(policy, as_of).map_err(to_core)
+    }
+
+    async fn archive(
+        &self,
+        policy: &ArchivePolicy,
+        as_of: DateTime<Utc>,
+        trigger: ArchiveTrigger,
+    ) -> CoreResult<ArchiveRun> {
+        self.run(policy, as_of, trigger).map_err(to_core)
+    }
+
+    async fn runs(&self, limit: usize) -> CoreResult<Vec<ArchiveRun>> {
+        self.history(limit).map_err(to_core)
+    }
+}
+
+/// 定时归档任务
+///
+/// 独占操作进行中时本次跳过（记录在归档历史中），下一个周期再运行。
+#[derive(Debug, Clone)]
+pub struct RecordArchiveJob {
+    archiver: Arc<RecordArchiver>,
+    policy: ArchivePolicy,
+    schedule: JobSchedule,
+}
+
+impl RecordArchiveJob {
+    /// 创建定时归档任务
+    pub fn new(
+        archiver: Arc<RecordArchiver>,
+        policy: ArchivePolicy,
+        schedule: JobSchedule,
+    ) -> Self {
+        Self {
+            archiver,
+            policy,
+            schedule,
+        }
+    }
+}
+
+#[async_trait]
+impl Job for RecordArchiveJob {
+    fn name(&self) -> &str {
+        "record_archive"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        self.schedule.clone()
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let as_of = self.archiver.clock.now();
+        let run = self
+            .archiver
+            .archive(&self.policy, as_of, ArchiveTrigger::Scheduled)
+            .await?;
+        match run.status {
+            ArchiveRunStatus::Failed => Err(CoreError::Other(run.message.unwrap_or_default())),
+            ArchiveRunStatus::Completed | ArchiveRunStatus::Skipped => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbUuid;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn setup() -> (
+        TempDir,
+        DatabaseConnection,
+        Arc<ExclusiveGuard>,
+        RecordArchiver,
+    ) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let guard = Arc::new(ExclusiveGuard::new());
+        let clock = Arc::new(ManualClock::new(now()));
+        let archiver = RecordArchiver::new(connection.clone(), guard.clone()).with_clock(clock);
+        (temp_dir, connection, guard, archiver)
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 2, 0, 0).unwrap()
+    }
+
+    fn months_ago(months: i64) -> String {
+        (now() - Duration::days(months * 31)).to_rfc3339()
+    }
+
+    fn policy() -> ArchivePolicy {
+        ArchivePolicy {
+            cutoff_months: [(ArchiveEntity::Interactions, 12), (ArchiveEntity::Tasks, 6)]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn seed(connection: &DatabaseConnection) {
+        let customer = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, '华南木业', ?2, ?2)",
+                params![DbUuid(customer), months_ago(24)],
+            )
+            .unwrap();
+        for (content, at) in [("去年回访", months_ago(13)), ("上月回访", months_ago(1))] {
+            connection
+                .execute(
+                    "INSERT INTO interactions (id, customer_id, kind, content, occurred_at)
+                     VALUES (?1, ?2, 'call', ?3, ?4)",
+                    params![DbUuid(Uuid::new_v4()), DbUuid(customer), content, at],
+                )
+                .unwrap();
+        }
+        for (title, status, completed) in [
+            ("旧的已完成", "completed", Some(months_ago(7))),
+            ("旧的未完成", "pending", None),
+            ("新的已完成", "completed", Some(months_ago(2))),
+        ] {
+            connection
+                .execute(
+                    "INSERT INTO tasks
+                        (id, customer_id, title, status, completed_at, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                    params![
+                        DbUuid(Uuid::new_v4()),
+                        DbUuid(customer),
+                        title,
+                        status,
+                        completed,
+                        months_ago(12)
+                    ],
+                )
+                .unwrap();
+        }
+        // 订单未配置保留期限，不归档
+        connection
+            .execute(
+                "INSERT INTO orders (id, order_number, customer_id, delivery_status, delivered_at,
+                    created_at, updated_at)
+                 VALUES (?1, 'SO-0001', ?2, 'delivered', ?3, ?3, ?3)",
+                params![DbUuid(Uuid::new_v4()), DbUuid(customer), months_ago(30)],
+            )
+            .unwrap();
+    }
+
+    fn titles(connection: &DatabaseConnection, table: &str) -> Vec<String> {
+        connection
+            .query_map(
+                &format!("SELECT title FROM {} ORDER BY title", table),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_archive_moves_exactly_the_previewed_records() {
+        let (_dir, connection, _guard, archiver) = setup();
+        seed(&connection);
+
+        let preview = archiver.preview(&policy(), now()).await.unwrap();
+        let rows: Vec<_> = preview.counts.iter().map(|c| (c.entity, c.rows)).collect();
+        assert_eq!(
+            rows,
+            vec![(ArchiveEntity::Interactions, 1), (ArchiveEntity::Tasks, 1)]
+        );
+        assert!(preview.counts.iter().all(|c| c.estimated_bytes > 0));
+
+        let run = archiver
+            .archive(&policy(), now(), ArchiveTrigger::Manual)
+            .await
+            .unwrap();
+        assert_eq!(run.status, ArchiveRunStatus::Completed);
+        assert_eq!(run.counts, preview.counts);
+
+        assert_eq!(
+            titles(&connection, "tasks"),
+            vec!["新的已完成", "旧的未完成"]
+        );
+        assert_eq!(titles(&connection, "archived_tasks"), vec!["旧的已完成"]);
+        let archived: Vec<String> = connection
+            .query_map("SELECT content FROM archived_interactions", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(archived, vec!["去年回访"]);
+        let orders: i64 = connection
+            .query_row("SELECT COUNT(*) FROM orders", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(orders, 1);
+
+        // 再次预览没有可归档的记录
+        let again = archiver.preview(&policy(), now()).await.unwrap();
+        assert_eq!(again.total_rows(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_job_skips_while_exclusive_operation_runs() {
+        let (_dir, connection, guard, archiver) = setup();
+        seed(&connection);
+        let job = RecordArchiveJob::new(
+            Arc::new(archiver.clone()),
+            policy(),
+            JobSchedule::every(Duration::days(1)),
+        );
+
+        let backup = guard.try_acquire("数据库备份").unwrap();
+        job.run().await.unwrap();
+        assert_eq!(titles(&connection, "archived_tasks"), Vec::<String>::new());
+        drop(backup);
+
+        job.run().await.unwrap();
+        assert_eq!(titles(&connection, "archived_tasks"), vec!["旧的已完成"]);
+
+        let history = archiver.runs(10).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].status, ArchiveRunStatus::Completed);
+        assert_eq!(history[0].trigger, ArchiveTrigger::Scheduled);
+        assert_eq!(history[0].counts.len(), 2);
+        assert_eq!(history[1].status, ArchiveRunStatus::Skipped);
+        assert_eq!(
+            history[1].message.as_deref(),
+            Some("数据库备份正在进行，请稍后再试")
+        );
+        assert!(history[1].counts.is_empty());
+    }
+}
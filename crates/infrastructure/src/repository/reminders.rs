@@ -0,0 +1,710 @@
+//! 提醒存储
+//!
+//! 提醒按（类型, 实体, 提醒时间, 接收人）去重，后台任务重复扫描、事件重复投递都不会产生重复提醒。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use minicrm_core::{
+    upcoming_within, BusinessCalendar, Clock, CoreResult, EntityKind, Job, JobSchedule, Reminder,
+    ReminderKind, ReminderService, StalenessEvaluator, StalenessKind, SystemClock,
+    DEFAULT_ANNIVERSARY_LEAD_DAYS,
+};
+use rusqlite::{params, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::{get_optional_uuid, get_uuid};
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::anniversaries::AnniversaryStore;
+use crate::repository::orders::OrderStore;
+use crate::repository::tasks::TaskStore;
+
+const REMINDER_COLUMNS: &str = "id, kind, entity_type, entity_id, title, due_at, created_at, \
+                                dismissed_at, recipient_id, snoozed_until";
+
+/// 提醒存储
+#[derive(Debug, Clone)]
+pub struct ReminderStore {
+    connection: DatabaseConnection,
+}
+
+fn row_to_reminder(row: &Row<'_>) -> rusqlite::Result<Option<Reminder>> {
+    let kind: String = row.get(1)?;
+    let entity: String = row.get(2)?;
+    let due_at: String = row.get(5)?;
+    let created_at: String = row.get(6)?;
+    let dismissed_at: Option<String> = row.get(7)?;
+    let snoozed_until: Option<String> = row.get(9)?;
+    // 无法识别的类型来自更新版本写入的数据，跳过
+    let (Some(kind), Some(entity)) = (
+        ReminderKind::parse(&kind),
+        EntityKind::from_table_name(&entity),
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(Reminder {
+        id: get_uuid(row, 0)?,
+        kind,
+        entity,
+        entity_id: get_uuid(row, 3)?,
+        title: row.get(4)?,
+        due_at: parse_time(5, &due_at)?,
+        created_at: parse_time(6, &created_at)?,
+        dismissed_at: dismissed_at.map(|v| parse_time(7, &v)).transpose()?,
+        recipient: get_optional_uuid(row, 8)?,
+        snoozed_until: snoozed_until.map(|v| parse_time(9, &v)).transpose()?,
+    }))
+}
+
+impl ReminderStore {
+    /// 创建提醒存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 写入提醒，已存在相同（类型, 实体, 提醒时间, 接收人）的提醒时跳过
+    ///
+    /// 返回是否新建了提醒。
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn create_if_absent(&self, reminder: &Reminder) -> Result<bool> {
+        let inserted = self.connection.execute(
+            &format!(
+                "INSERT OR IGNORE INTO reminders ({})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                REMINDER_COLUMNS
+            ),
+            params![
+                DbUuid(reminder.id),
+                reminder.kind.as_str(),
+                reminder.entity.table_name(),
+                DbUuid(reminder.entity_id),
+                reminder.title,
+                time_key(reminder.due_at),
+                time_key(reminder.created_at),
+                reminder.dismissed_at.map(time_key),
+                reminder.recipient.map(DbUuid),
+                reminder.snoozed_until.map(time_key),
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// 未关闭的提醒（按提醒时间升序）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn active(&self) -> Result<Vec<Reminder>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM reminders WHERE dismissed_at IS NULL ORDER BY due_at",
+                    REMINDER_COLUMNS
+                ),
+                [],
+                row_to_reminder,
+            )?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// 用户可见的未关闭提醒：发给该用户的和不指定接收人的（按提醒时间升序）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn active_for(&self, user_id: Uuid) -> Result<Vec<Reminder>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM reminders
+                     WHERE dismissed_at IS NULL AND (recipient_id IS NULL OR recipient_id = ?1)
+                     ORDER BY due_at",
+                    REMINDER_COLUMNS
+                ),
+                [DbUuid(user_id)],
+                row_to_reminder,
+            )?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// 关闭提醒，返回提醒是否存在且此前未关闭
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn dismiss(&self, id: Uuid, at: DateTime<Utc>) -> Result<bool> {
+        let updated = self.connection.execute(
+            "UPDATE reminders SET dismissed_at = ?2 WHERE id = ?1 AND dismissed_at IS NULL",
+            params![DbUuid(id), time_key(at)],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// 推迟提醒，返回提醒是否存在且未关闭
+    ///
+    /// # Errors
+    ///
+    /// 数据库写入失败时返回错误。
+    pub fn snooze(&self, id: Uuid, until: DateTime<Utc>) -> Result<bool> {
+        let updated = self.connection.execute(
+            "UPDATE reminders SET snoozed_until = ?2 WHERE id = ?1 AND dismissed_at IS NULL",
+            params![DbUuid(id), time_key(until)],
+        )?;
+        Ok(updated > 0)
+    }
+}
+
+#[async_trait]
+impl ReminderService for ReminderStore {
+    async fn active_reminders(&self, user_id: Uuid) -> CoreResult<Vec<Reminder>> {
+        Ok(self.active_for(user_id)?)
+    }
+
+    async fn dismiss_reminder(&self, id: Uuid, at: DateTime<Utc>) -> CoreResult<bool> {
+        Ok(self.dismiss(id, at)?)
+    }
+
+    async fn snooze_reminder(&self, id: Uuid, until: DateTime<Utc>) -> CoreResult<bool> {
+        Ok(self.snooze(id, until)?)
+    }
+}
+
+/// 送货逾期提醒任务
+///
+/// 约定送货时间已过仍未送达的订单生成提醒；改期后按新的送货时间重新提醒。
+#[derive(Clone)]
+pub struct OverdueDeliveryReminderJob {
+    orders: OrderStore,
+    reminders: ReminderStore,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for OverdueDeliveryReminderJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OverdueDeliveryReminderJob")
+            .finish_non_exhaustive()
+    }
+}
+
+impl OverdueDeliveryReminderJob {
+    /// 创建送货逾期提醒任务
+    pub fn new(orders: OrderStore, reminders: ReminderStore) -> Self {
+        Self {
+            orders,
+            reminders,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 扫描逾期订单并生成提醒，返回新建的提醒数
+    ///
+    /// # Errors
+    ///
+    /// 查询或写入失败时返回错误。
+    pub fn scan(&self) -> Result<usize> {
+        let now = self.clock.now();
+        let mut created = 0;
+        for order in self.orders.overdue_deliveries(now)? {
+            let Some(due_at) = order.delivery_date else {
+                continue;
+            };
+            let reminder = Reminder {
+                id: Uuid::new_v4(),
+                kind: ReminderKind::OverdueDelivery,
+                entity: EntityKind::Order,
+                entity_id: order.id,
+                title: format!("订单 {} 已过约定送货时间，尚未送达", order.order_number),
+                due_at,
+                created_at: now,
+                dismissed_at: None,
+                recipient: None,
+                snoozed_until: None,
+            };
+            if self.reminders.create_if_absent(&reminder)? {
+                created += 1;
+            }
+        }
+        Ok(created)
+    }
+}
+
+#[async_trait]
+impl Job for OverdueDeliveryReminderJob {
+    fn name(&self) -> &str {
+        "overdue_delivery_reminders"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(Duration::minutes(30))
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let created = self.scan()?;
+        if created > 0 {
+            info!("新增 {} 条送货逾期提醒", created);
+        }
+        Ok(())
+    }
+}
+
+/// 任务逾期升级提醒任务
+///
+/// 逾期天数达到陈旧档（升级天数）的任务生成提醒，发给负责人，未分配时所有用户可见。
+/// 逾期程度由 [`StalenessEvaluator`] 判断，与任务看板上的逾期徽标口径一致；每个截止日期
+/// 只升级一次，改期后按新的截止日期重新计算。
+#[derive(Clone)]
+pub struct OverdueTaskEscalationJob {
+    tasks: TaskStore,
+    reminders: ReminderStore,
+    staleness: StalenessEvaluator,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for OverdueTaskEscalationJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OverdueTaskEscalationJob")
+            .finish_non_exhaustive()
+    }
+}
+
+impl OverdueTaskEscalationJob {
+    /// 创建任务逾期升级提醒任务
+    pub fn new(tasks: TaskStore, reminders: ReminderStore, staleness: StalenessEvaluator) -> Self {
+        Self {
+            tasks,
+            reminders,
+            staleness,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 扫描逾期任务并为达到升级天数的任务生成提醒，返回新建的提醒数
+    ///
+    /// # Errors
+    ///
+    /// 查询或写入失败时返回错误。
+    pub fn scan(&self) -> Result<usize> {
+        let now = self.clock.now();
+        let mut created = 0;
+        for task in self.tasks.overdue_tasks(now)? {
+            let freshness = self.staleness.task(&task, now);
+            let (Some(due_at), Some(message)) = (
+                task.due_date.filter(|_| freshness.is_stale()),
+                freshness.message(StalenessKind::OverdueTask),
+            ) else {
+                continue;
+            };
+            let reminder = Reminder {
+                id: Uuid::new_v4(),
+                kind: ReminderKind::TaskEscalated,
+                entity: EntityKind::Task,
+                entity_id: task.id,
+                title: format!("{}：{}", message, task.title),
+                due_at,
+                created_at: now,
+                dismissed_at: None,
+                recipient: task.assigned_to,
+                snoozed_until: None,
+            };
+            if self.reminders.create_if_absent(&reminder)? {
+                created += 1;
+            }
+        }
+        Ok(created)
+    }
+}
+
+#[async_trait]
+impl Job for OverdueTaskEscalationJob {
+    fn name(&self) -> &str {
+        "overdue_task_escalations"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(Duration::minutes(30))
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let created = self.scan()?;
+        if created > 0 {
+            info!("新增 {} 条任务逾期升级提醒", created);
+        }
+        Ok(())
+    }
+}
+
+/// 客户纪念日提醒任务
+///
+/// 联系人生日和首次成交纪念日提前 `lead_days` 天生成提醒，发给客户负责人，未分配时所有
+/// 用户可见。提醒时间为纪念日当天零点（业务时区），每年的纪念日只提醒一次。
+#[derive(Clone)]
+pub struct AnniversaryReminderJob {
+    anniversaries: AnniversaryStore,
+    reminders: ReminderStore,
+    calendar: BusinessCalendar,
+    lead_days: u32,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for AnniversaryReminderJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnniversaryReminderJob")
+            .field("lead_days", &self.lead_days)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AnniversaryReminderJob {
+    /// 创建客户纪念日提醒任务（默认提前3天）
+    pub fn new(anniversaries: AnniversaryStore, reminders: ReminderStore) -> Self {
+        Self {
+            anniversaries,
+            reminders,
+            calendar: BusinessCalendar::default(),
+            lead_days: DEFAULT_ANNIVERSARY_LEAD_DAYS,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置提前提醒的天数
+    pub fn with_lead_days(mut self, days: u32) -> Self {
+        self.lead_days = days;
+        self
+    }
+
+    /// 设置业务日历，纪念日按其本地日期计算
+    pub fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 使用指定时钟（测试中使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 扫描临近的纪念日并生成提醒，返回新建的提醒数
+    ///
+    /// # Errors
+    ///
+    /// 查询或写入失败时返回错误。
+    pub fn scan(&self) -> Result<usize> {
+        let now = self.clock.now();
+        let today = self.calendar.local_date(now);
+        let mut created = 0;
+        for upcoming in upcoming_within(&self.anniversaries.all()?, today, self.lead_days) {
+            let reminder = Reminder {
+                id: Uuid::new_v4(),
+                kind: upcoming.anniversary.kind.reminder_kind(),
+                entity: EntityKind::Customer,
+                entity_id: upcoming.anniversary.customer_id,
+                title: upcoming.title(),
+                due_at: self.calendar.start_of_day(upcoming.on),
+                created_at: now,
+                dismissed_at: None,
+                recipient: upcoming.anniversary.owner_id,
+                snoozed_until: None,
+            };
+            if self.reminders.create_if_absent(&reminder)? {
+                created += 1;
+            }
+        }
+        Ok(created)
+    }
+}
+
+#[async_trait]
+impl Job for AnniversaryReminderJob {
+    fn name(&self) -> &str {
+        "anniversary_reminders"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::every(Duration::hours(6))
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        let created = self.scan()?;
+        if created > 0 {
+            info!("新增 {} 条客户纪念日提醒", created);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use minicrm_core::{
+        Address, Currency, DeliverySchedule, DeliveryService, DeliveryStatus, ManualClock, Money,
+        Order,
+    };
+    use tempfile::TempDir;
+
+    fn create_test_job() -> (
+        TempDir,
+        OrderStore,
+        ReminderStore,
+        OverdueDeliveryReminderJob,
+        Arc<ManualClock>,
+    ) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(july(1, 0)));
+        let orders = OrderStore::new(connection.clone()).with_clock(clock.clone());
+        let reminders = ReminderStore::new(connection);
+        let job = OverdueDeliveryReminderJob::new(orders.clone(), reminders.clone())
+            .with_clock(clock.clone());
+        (temp_dir, orders, reminders, job, clock)
+    }
+
+    fn july(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, day, hour, 0, 0).unwrap()
+    }
+
+    fn scheduled_order(orders: &OrderStore, number: &str) -> Order {
+        let order = Order {
+            id: Uuid::new_v4(),
+            order_number: number.to_string(),
+            customer_id: Uuid::new_v4(),
+            quote_id: None,
+            total_amount: Money::from_yuan(5_600.0),
+            currency: Currency::CNY,
+            delivery_status: DeliveryStatus::Unscheduled,
+            delivery_date: None,
+            delivery_address: None,
+            vehicle: None,
+            driver: None,
+            signed_by: None,
+            delivered_at: None,
+            created_at: july(1, 0),
+            updated_at: july(1, 0),
+            created_by: None,
+            updated_by: None,
+        };
+        orders.insert(&order).unwrap();
+        order
+    }
+
+    fn schedule(at: DateTime<Utc>) -> DeliverySchedule {
+        DeliverySchedule {
+            delivery_date: at,
+            address: Address {
+                province: "江苏省".to_string(),
+                city: "苏州市".to_string(),
+                street: "工业园区星湖街88号".to_string(),
+                ..Address::default()
+            },
+            vehicle: None,
+            driver: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reminder_for_overdue_delivery() {
+        let (_dir, orders, reminders, job, clock) = create_test_job();
+        let late = scheduled_order(&orders, "DD20240701-001");
+        let on_time = scheduled_order(&orders, "DD20240701-002");
+        let delivered = scheduled_order(&orders, "DD20240701-003");
+        orders
+            .schedule_delivery(late.id, schedule(july(2, 2)))
+            .await
+            .unwrap();
+        orders
+            .schedule_delivery(on_time.id, schedule(july(5, 2)))
+            .await
+            .unwrap();
+        orders
+            .schedule_delivery(delivered.id, schedule(july(2, 1)))
+            .await
+            .unwrap();
+        orders
+            .mark_delivered(delivered.id, "张三", july(2, 1))
+            .await
+            .unwrap();
+
+        // 约定时间当刻尚未逾期
+        clock.set(july(2, 2));
+        assert_eq!(job.scan().unwrap(), 0);
+
+        clock.set(july(3, 0));
+        job.run().await.unwrap();
+        let active = reminders.active().unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].entity, EntityKind::Order);
+        assert_eq!(active[0].entity_id, late.id);
+        assert_eq!(active[0].due_at, july(2, 2));
+        assert!(active[0].title.contains("DD20240701-001"));
+
+        // 重复扫描不会重复提醒
+        assert_eq!(job.scan().unwrap(), 0);
+
+        // 推迟后仍在未关闭列表中，带推迟时间
+        assert!(reminders.snooze(active[0].id, july(3, 4)).unwrap());
+        let snoozed = reminders.active().unwrap();
+        assert_eq!(snoozed[0].snoozed_until, Some(july(3, 4)));
+        assert!(snoozed[0].is_snoozed(july(3, 0)));
+        assert!(!snoozed[0].is_snoozed(july(3, 4)));
+
+        // 改期后再次逾期，按新时间提醒
+        assert!(reminders.dismiss(active[0].id, july(3, 0)).unwrap());
+        orders
+            .reschedule(late.id, july(3, 6), "车辆故障")
+            .await
+            .unwrap();
+        clock.set(july(4, 0));
+        assert_eq!(job.scan().unwrap(), 1);
+        assert_eq!(reminders.active().unwrap()[0].due_at, july(3, 6));
+    }
+
+    #[tokio::test]
+    async fn test_escalation_for_stale_overdue_task() {
+        let (_dir, _orders, reminders, _job, clock) = create_test_job();
+        let connection = reminders.connection.clone();
+        let customer = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, '华南木业', ?2, ?2)",
+                params![DbUuid(customer), time_key(july(1, 0))],
+            )
+            .unwrap();
+        let insert = |title: &str, status: &str, assigned_to: Option<Uuid>| {
+            let id = Uuid::new_v4();
+            connection
+                .execute(
+                    "INSERT INTO tasks (id, customer_id, title, status, due_date, created_at,
+                                        updated_at, assigned_to)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7)",
+                    params![
+                        DbUuid(id),
+                        DbUuid(customer),
+                        title,
+                        status,
+                        time_key(july(1, 2)),
+                        time_key(july(1, 0)),
+                        assigned_to.map(DbUuid)
+                    ],
+                )
+                .unwrap();
+            id
+        };
+        let assignee = Uuid::new_v4();
+        let late = insert("上门复尺", "in_progress", Some(assignee));
+        insert("出安装图", "completed", None);
+        let job = OverdueTaskEscalationJob::new(
+            TaskStore::new(connection.clone()),
+            reminders.clone(),
+            StalenessEvaluator::default(),
+        )
+        .with_clock(clock.clone());
+
+        // 逾期2天（上海时间7月3日晚）：看板上为关注档，尚未升级
+        clock.set(july(3, 12));
+        assert_eq!(job.scan().unwrap(), 0);
+
+        clock.set(july(4, 0));
+        job.run().await.unwrap();
+        let active = reminders.active_for(assignee).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].kind, ReminderKind::TaskEscalated);
+        assert_eq!(active[0].entity_id, late);
+        assert_eq!(active[0].due_at, july(1, 2));
+        assert_eq!(active[0].title, "任务已逾期 3 天：上门复尺");
+
+        // 同一截止日期只升级一次
+        clock.set(july(6, 0));
+        assert_eq!(job.scan().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_anniversary_reminders_once_per_year() {
+        let (_dir, _orders, reminders, _job, clock) = create_test_job();
+        let connection = reminders.connection.clone();
+        let owner = Uuid::new_v4();
+        let customer = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, company, created_at, updated_at, owner_id,
+                                        contact_birthday)
+                 VALUES (?1, '华东建材', '王经理', ?2, ?2, ?3, '1980-07-05')",
+                params![DbUuid(customer), time_key(july(1, 0)), DbUuid(owner)],
+            )
+            .unwrap();
+        // 首次成交：2021年7月4日（上海时间）接受的报价；草稿报价不计
+        let first_deal = Utc.with_ymd_and_hms(2021, 7, 3, 18, 0, 0).unwrap();
+        let older_draft = first_deal - Duration::days(365);
+        for (status, at) in [("accepted", first_deal), ("draft", older_draft)] {
+            connection
+                .execute(
+                    "INSERT INTO quotes (id, customer_id, title, total_amount, status, created_at,
+                                         updated_at)
+                     VALUES (?1, ?2, '生态板', 1000, ?3, ?4, ?4)",
+                    params![
+                        DbUuid(Uuid::new_v4()),
+                        DbUuid(customer),
+                        status,
+                        time_key(at)
+                    ],
+                )
+                .unwrap();
+        }
+        let job = AnniversaryReminderJob::new(
+            AnniversaryStore::new(connection.clone()),
+            reminders.clone(),
+        )
+        .with_lead_days(3)
+        .with_clock(clock.clone());
+
+        // 7月1日：合作纪念日（7月4日）在3天内，生日（7月5日）尚未到提醒时间
+        clock.set(july(1, 0));
+        assert_eq!(job.scan().unwrap(), 1);
+        clock.set(july(2, 0));
+        job.run().await.unwrap();
+        let active = reminders.active_for(owner).unwrap();
+        assert_eq!(active.len(), 2);
+        assert_eq!(active[0].kind, ReminderKind::DealAnniversary);
+        assert_eq!(active[0].title, "华东建材 王经理 合作3周年（7月4日）");
+        assert_eq!(active[1].kind, ReminderKind::ContactBirthday);
+        assert_eq!(active[1].kind.category(), "客户维护");
+        assert!(reminders.active_for(Uuid::new_v4()).unwrap().is_empty());
+
+        // 同一年内关闭后不再重复提醒，次年重新提醒
+        for reminder in &active {
+            reminders.dismiss(reminder.id, july(2, 1)).unwrap();
+        }
+        clock.set(july(4, 0));
+        assert_eq!(job.scan().unwrap(), 0);
+        clock.set(Utc.with_ymd_and_hms(2025, 7, 2, 0, 0, 0).unwrap());
+        assert_eq!(job.scan().unwrap(), 2);
+    }
+}
@@ -0,0 +1,503 @@
+//! 审计记录和事件发件箱的保留策略
+//!
+//! 字段级审计记录（`customer_audit`）和事件发件箱（`event_outbox`）随使用持续增长，
+//! 一年后会远超业务数据。保留任务每天运行一次：
+//!
+//! - 早于保留月数的审计记录先按（实体类型, 月份, 字段）汇总到 `change_log_monthly`，
+//!   再删除原始记录，长期的修改趋势仍可查询。汇总和删除在同一事务中完成，中途退出不会
+//!   重复或遗漏计数。只清理整月，月份按UTC划分。
+//! - 早于保留天数的已分发事件直接删除，未分发的事件无论多旧都保留。
+//!
+//! 删除按批进行，每批之间暂停片刻，不会长时间占用数据库写锁。每次运行写入 `retention_log`，
+//! 诊断信息界面显示删除的行数。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveTime, TimeZone, Utc};
+use minicrm_core::{Clock, CoreError, CoreResult, Job, JobSchedule, RetentionRun, SystemClock};
+use rusqlite::{params, Row};
+use tracing::info;
+
+use crate::database::DatabaseConnection;
+use crate::database::time::time_key;
+use crate::repository::outbox::{EventOutboxStore, DEFAULT_RETENTION_DAYS};
+
+/// 默认审计记录保留月数
+pub const DEFAULT_AUDIT_MONTHS: u32 = 24;
+
+/// 默认每批删除的行数
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// 默认批间暂停（毫秒）
+pub const DEFAULT_BATCH_PAUSE_MS: u64 = 50;
+
+/// 字段级审计表（表名, 实体类型）；新增审计表时在这里加一条，表须有 `field` 和 `created_at` 列
+const AUDIT_TABLES: &[(&str, &str)] = &[("customer_audit", "customer")];
+
+fn sql_count(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+fn count(row: &Row<'_>, index: usize) -> rusqlite::Result<u64> {
+    Ok(u64::try_from(row.get::<_, i64>(index)?).unwrap_or(0))
+}
+
+fn row_to_run(row: &Row<'_>) -> rusqlite::Result<RetentionRun> {
+    let ran_at: String = row.get(0)?;
+    Ok(RetentionRun {
+        ran_at: DateTime::parse_from_rfc3339(&ran_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?,
+        audit_rows: count(row, 1)?,
+        outbox_rows: count(row, 2)?,
+    })
+}
+
+/// 保留策略
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// 审计记录保留的整月数（不含当月），为0时不清理
+    pub audit_months: u32,
+    /// 已分发事件的保留时长
+    pub outbox_retention: Duration,
+    /// 每批删除的行数
+    pub batch_size: usize,
+    /// 批间暂停
+    pub batch_pause: std::time::Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            audit_months: DEFAULT_AUDIT_MONTHS,
+            outbox_retention: Duration::days(DEFAULT_RETENTION_DAYS),
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_pause: std::time::Duration::from_millis(DEFAULT_BATCH_PAUSE_MS),
+        }
+    }
+}
+
+/// 某月某字段的修改次数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonthlyChangeCount {
+    /// 月份（`YYYY-MM`，UTC）
+    pub month: String,
+    /// 字段
+    pub field: String,
+    /// 修改次数
+    pub changes: u64,
+}
+
+/// 保留策略任务
+pub struct RetentionJob {
+    connection: DatabaseConnection,
+    outbox: EventOutboxStore,
+    policy: RetentionPolicy,
+    run_at: NaiveTime,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for RetentionJob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetentionJob")
+            .field("policy", &self.policy)
+            .field("run_at", &self.run_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RetentionJob {
+    /// 创建保留策略任务（默认策略，每天3点运行）
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            outbox: EventOutboxStore::new(connection.clone()),
+            connection,
+            policy: RetentionPolicy::default(),
+            run_at: NaiveTime::from_hms_opt(3, 0, 0).unwrap_or_default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置保留策略
+    pub fn with_policy(mut self, policy: RetentionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 设置每天的运行时间
+    pub fn run_at(mut self, at: NaiveTime) -> Self {
+        self.run_at = at;
+        self
+    }
+
+    /// 设置时钟
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 汇总并删除超过保留期的审计记录，删除超过保留期的已分发事件，记录本次运行
+    ///
+    /// # Errors
+    ///
+    /// 读写数据库失败时返回错误；已提交的批次不会回滚。
+    pub async fn enforce(&self) -> Result<RetentionRun> {
+        let now = self.clock.now();
+        let mut audit_rows = 0;
+        if let Some(cutoff) = self.audit_cutoff(now) {
+            for (table, entity_type) in AUDIT_TABLES {
+                loop {
+                    let deleted = self.summarize_batch(table, entity_type, cutoff)?;
+                    audit_rows += deleted as u64;
+                    if deleted < self.batch_size() {
+                        break;
+                    }
+                    tokio::time::sleep(self.policy.batch_pause).await;
+                }
+            }
+        }
+
+        let outbox_cutoff = now - self.policy.outbox_retention;
+        let mut outbox_rows = 0;
+        loop {
+            let deleted = self.outbox.prune_batch(outbox_cutoff, self.batch_size())?;
+            outbox_rows += deleted as u64;
+            if deleted < self.batch_size() {
+                break;
+            }
+            tokio::time::sleep(self.policy.batch_pause).await;
+        }
+
+        let run = RetentionRun {
+            ran_at: now,
+            audit_rows,
+            outbox_rows,
+        };
+        self.record(&run)?;
+        info!(
+            "保留策略执行完成: 审计记录 {} 行，已分发事件 {} 行",
+            run.audit_rows, run.outbox_rows
+        );
+        Ok(run)
+    }
+
+    /// 某类实体按月汇总的修改次数（按月份、字段排序）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn monthly_changes(&self, entity_type: &str) -> Result<Vec<MonthlyChangeCount>> {
+        self.connection.query_map(
+            "SELECT month, field, change_count FROM change_log_monthly
+             WHERE entity_type = ?1 ORDER BY month, field",
+            [entity_type],
+            |row| {
+                Ok(MonthlyChangeCount {
+                    month: row.get(0)?,
+                    field: row.get(1)?,
+                    changes: count(row, 2)?,
+                })
+            },
+        )
+    }
+
+    /// 最近的运行记录（新的在前）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn history(&self, limit: usize) -> Result<Vec<RetentionRun>> {
+        self.connection.query_map(
+            "SELECT ran_at, audit_rows, outbox_rows
+             FROM retention_log ORDER BY id DESC LIMIT ?1",
+            [sql_count(limit as u64)],
+            row_to_run,
+        )
+    }
+
+    fn batch_size(&self) -> usize {
+        self.policy.batch_size.max(1)
+    }
+
+    /// 审计记录的保留起点：当月1日（UTC）往前推保留月数
+    fn audit_cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.policy.audit_months == 0 {
+            return None;
+        }
+        Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .single()?
+            .checked_sub_months(Months::new(self.policy.audit_months))
+    }
+
+    /// 在一个事务中汇总并删除一批早于 `cutoff` 的审计记录，返回删除的行数
+    fn summarize_batch(
+        &self,
+        table: &str,
+        entity_type: &str,
+        cutoff: DateTime<Utc>,
+    ) -> Result<usize> {
+        let batch = format!(
+            "SELECT rowid FROM {table} WHERE created_at < ?1 ORDER BY created_at, rowid LIMIT ?2"
+        );
+        let limit = sql_count(self.batch_size() as u64);
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    &format!(
+                        "INSERT INTO change_log_monthly (entity_type, month, field, change_count)
+                         SELECT ?3, substr(created_at, 1, 7), field, COUNT(*) FROM {table}
+                         WHERE rowid IN ({batch})
+                         GROUP BY substr(created_at, 1, 7), field
+                         ON CONFLICT (entity_type, month, field)
+                         DO UPDATE SET change_count = change_count + excluded.change_count"
+                    ),
+                    params![time_key(cutoff), limit, entity_type],
+                )?;
+                let deleted = tx.execute(
+                    &format!("DELETE FROM {table} WHERE rowid IN ({batch})"),
+                    params![time_key(cutoff), limit],
+                )?;
+                Ok(deleted)
+            })
+            .with_context(|| format!("无法汇总清理审计记录: {}", table))
+    }
+
+    fn record(&self, run: &RetentionRun) -> Result<()> {
+        self.connection
+            .execute(
+                "INSERT INTO retention_log (ran_at, audit_rows, outbox_rows) VALUES (?1, ?2, ?3)",
+                params![
+                    time_key(run.ran_at),
+                    sql_count(run.audit_rows),
+                    sql_count(run.outbox_rows),
+                ],
+            )
+            .context("无法保存保留策略执行记录")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Job for RetentionJob {
+    fn name(&self) -> &str {
+        "retention"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::daily(self.run_at)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        self.enforce()
+            .await
+            .map_err(|e| CoreError::Other(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbUuid;
+    use crate::repository::outbox;
+    use crate::test_support::migrated_pool;
+    use minicrm_core::{DomainEvent, EntityKind, EventEnvelope, ManualClock};
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn create_job() -> (TempDir, DatabaseConnection, Arc<ManualClock>, RetentionJob) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 15, 3, 0, 0).unwrap(),
+        ));
+        let job = RetentionJob::new(connection.clone())
+            .with_policy(RetentionPolicy {
+                audit_months: 2,
+                batch_pause: std::time::Duration::ZERO,
+                ..RetentionPolicy::default()
+            })
+            .with_clock(clock.clone());
+        (temp_dir, connection, clock, job)
+    }
+
+    /// 写入一个客户及其审计记录（字段, 时间）
+    fn seed_audit(connection: &DatabaseConnection, rows: &[(&str, &str)]) {
+        let customer_id = Uuid::new_v4();
+        connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "INSERT INTO customers (id, name, created_at, updated_at)
+                     VALUES (?1, '华东板材', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+                    [DbUuid(customer_id)],
+                )?;
+                for (field, at) in rows {
+                    tx.execute(
+                        "INSERT INTO customer_audit (id, customer_id, field, created_at)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![DbUuid(Uuid::new_v4()), DbUuid(customer_id), field, at],
+                    )?;
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    fn audit_count(connection: &DatabaseConnection) -> i64 {
+        connection
+            .query_row("SELECT COUNT(*) FROM customer_audit", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_summary_matches_raw_rows() {
+        let (_dir, connection, _clock, job) = create_job();
+        seed_audit(
+            &connection,
+            &[
+                ("level", "2024-03-02T08:00:00.000000Z"),
+                ("level", "2024-03-20T08:00:00.000000Z"),
+                ("credit_limit", "2024-03-31T23:59:59.000000Z"),
+                ("level", "2024-04-10T08:00:00.000000Z"),
+                ("tag:展会", "2024-04-30T12:00:00.000000Z"),
+                ("level", "2024-05-01T00:00:00.000000Z"),
+                ("level", "2024-07-01T08:00:00.000000Z"),
+            ],
+        );
+        // 保留5月起的记录，之前的按原始记录统计应得到的汇总
+        let expected: Vec<MonthlyChangeCount> = connection
+            .query_map(
+                "SELECT substr(created_at, 1, 7), field, COUNT(*) FROM customer_audit
+                 WHERE created_at < '2024-05' GROUP BY 1, 2 ORDER BY 1, 2",
+                [],
+                |row| {
+                    Ok(MonthlyChangeCount {
+                        month: row.get(0)?,
+                        field: row.get(1)?,
+                        changes: count(row, 2)?,
+                    })
+                },
+            )
+            .unwrap();
+        assert_eq!(expected.len(), 4);
+
+        let run = job.enforce().await.unwrap();
+        assert_eq!(run.audit_rows, 5);
+        assert_eq!(job.monthly_changes("customer").unwrap(), expected);
+        assert_eq!(audit_count(&connection), 2);
+
+        // 再次运行没有新的过期记录，汇总不变
+        assert_eq!(job.enforce().await.unwrap().audit_rows, 0);
+        assert_eq!(job.monthly_changes("customer").unwrap(), expected);
+        assert_eq!(job.history(10).unwrap().len(), 2);
+        assert_eq!(job.history(10).unwrap()[1], run);
+    }
+
+    #[tokio::test]
+    async fn test_deletes_in_bounded_batches() {
+        let (_dir, connection, _clock, job) = create_job();
+        let job = job.with_policy(RetentionPolicy {
+            audit_months: 2,
+            batch_size: 2,
+            batch_pause: std::time::Duration::ZERO,
+            ..RetentionPolicy::default()
+        });
+        seed_audit(
+            &connection,
+            &[
+                ("level", "2024-01-05T08:00:00.000000Z"),
+                ("level", "2024-01-06T08:00:00.000000Z"),
+                ("level", "2024-02-05T08:00:00.000000Z"),
+                ("phone", "2024-02-06T08:00:00.000000Z"),
+                ("level", "2024-04-05T08:00:00.000000Z"),
+            ],
+        );
+        let cutoff = job.audit_cutoff(job.clock.now()).unwrap();
+        assert_eq!(cutoff, Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap());
+
+        // 每批最多删除2行，按时间从早到晚
+        let batches: Vec<usize> = (0..4)
+            .map(|_| {
+                job.summarize_batch("customer_audit", "customer", cutoff)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(batches, vec![2, 2, 1, 0]);
+        assert_eq!(audit_count(&connection), 0);
+
+        // 跨批次累加的汇总与一次汇总相同
+        let summary: Vec<(String, String, u64)> = job
+            .monthly_changes("customer")
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.month, c.field, c.changes))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("2024-01".to_string(), "level".to_string(), 2),
+                ("2024-02".to_string(), "level".to_string(), 1),
+                ("2024-02".to_string(), "phone".to_string(), 1),
+                ("2024-04".to_string(), "level".to_string(), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undispatched_events_never_pruned() {
+        let (_dir, connection, clock, job) = create_job();
+        let store = EventOutboxStore::new(connection.clone());
+        let envelopes: Vec<EventEnvelope> = (0..5)
+            .map(|_| {
+                EventEnvelope::at(
+                    DomainEvent::EntityCreated {
+                        entity: EntityKind::Customer,
+                        id: Uuid::new_v4(),
+                    },
+                    clock.now(),
+                )
+            })
+            .collect();
+        connection
+            .with_transaction(|tx| {
+                envelopes
+                    .iter()
+                    .try_for_each(|envelope| outbox::record(tx, envelope))
+            })
+            .unwrap();
+        for entry in store.pending(3).unwrap() {
+            store.mark_dispatched(entry.seq).unwrap();
+        }
+
+        // 保留期内不删除
+        let job = job.with_policy(RetentionPolicy {
+            batch_size: 2,
+            batch_pause: std::time::Duration::ZERO,
+            ..RetentionPolicy::default()
+        });
+        assert_eq!(job.enforce().await.unwrap().outbox_rows, 0);
+
+        // 一年后只删除已分发的3条，未分发的2条保留
+        clock.advance(Duration::days(365));
+        assert_eq!(job.enforce().await.unwrap().outbox_rows, 3);
+        let pending: Vec<Uuid> = store
+            .pending(10)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.envelope.id)
+            .collect();
+        assert_eq!(pending, vec![envelopes[3].id, envelopes[4].id]);
+        let remaining: i64 = connection
+            .query_row("SELECT COUNT(*) FROM event_outbox", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 2);
+    }
+}
@@ -0,0 +1,73 @@
+//! 单据编号序列
+//!
+//! 在 `document_sequences` 表中按（前缀，本地日期）递增，编号形如 `PO20240701-001`，
+//! 与销售订单号的格式一致。取号须在写入单据的同一事务中进行，事务回滚时编号不会被占用。
+
+use anyhow::Result;
+use minicrm_core::LocalDate;
+use rusqlite::{params, Transaction};
+
+/// 取下一个单据编号
+///
+/// # Errors
+///
+/// 数据库读写失败时返回错误。
+pub fn next_document_number(tx: &Transaction<'_>, prefix: &str, date: LocalDate) -> Result<String> {
+    let period = date.naive().format("%Y%m%d").to_string();
+    tx.execute(
+        "INSERT INTO document_sequences (prefix, period, last_value) VALUES (?1, ?2, 1)
+         ON CONFLICT(prefix, period) DO UPDATE SET last_value = last_value + 1",
+        params![prefix, period],
+    )?;
+    let value: i64 = tx.query_row(
+        "SELECT last_value FROM document_sequences WHERE prefix = ?1 AND period = ?2",
+        params![prefix, period],
+        |row| row.get(0),
+    )?;
+    Ok(format!("{prefix}{period}-{value:03}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseConnection;
+    use crate::test_support::migrated_pool;
+
+    #[test]
+    fn test_numbers_increment_per_prefix_and_day() {
+        let (_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let day = LocalDate::new(2024, 7, 1).unwrap();
+        let numbers = connection
+            .with_transaction(|tx| {
+                Ok(vec![
+                    next_document_number(tx, "PO", day)?,
+                    next_document_number(tx, "PO", day)?,
+                    next_document_number(tx, "RC", day)?,
+                    next_document_number(tx, "PO", day.add_days(1))?,
+                ])
+            })
+            .unwrap();
+        assert_eq!(
+            numbers,
+            vec![
+                "PO20240701-001",
+                "PO20240701-002",
+                "RC20240701-001",
+                "PO20240702-001"
+            ]
+        );
+
+        // 回滚的事务不占用编号
+        let rolled_back = connection.with_transaction(|tx| -> Result<()> {
+            next_document_number(tx, "PO", day)?;
+            anyhow::bail!("回滚")
+        });
+        assert!(rolled_back.is_err());
+        let next = connection
+            .with_transaction(|tx| next_document_number(tx, "PO", day))
+            .unwrap();
+        assert_eq!(next, "PO20240701-003");
+    }
+}
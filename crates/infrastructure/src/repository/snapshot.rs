@@ -0,0 +1,72 @@
+//! 实体快照读取
+//!
+//! 将任意实体表的一行读取为JSON对象，供Webhook、导出等场景使用。客户、任务和订单
+//! 读取为实体后以规范JSON输出（见 [`minicrm_core::canonical_json`]），其他实体按整行输出。
+
+use anyhow::Result;
+use minicrm_core::{to_canonical_json, CanonicalEntity, Customer, EntityKind, Order, Task};
+use rusqlite::types::ValueRef;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::generic::EntityMapper;
+
+/// 实体快照提供者
+pub trait SnapshotProvider: Send + Sync {
+    /// 读取实体当前快照，实体不存在时返回 `None`
+    fn snapshot(&self, kind: EntityKind, id: Uuid) -> Result<Option<Value>>;
+}
+
+/// 基于数据表的快照提供者
+#[derive(Debug, Clone)]
+pub struct TableSnapshotProvider {
+    connection: DatabaseConnection,
+}
+
+impl TableSnapshotProvider {
+    /// 创建快照提供者
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 读取实体并转为规范JSON
+    fn canonical<E: EntityMapper + CanonicalEntity>(&self, id: Uuid) -> Result<Option<Value>> {
+        let sql = format!(
+            "SELECT {} FROM {} WHERE id = ?1",
+            E::COLUMNS.join(", "),
+            E::TABLE
+        );
+        let entities = self.connection.query_map(&sql, [DbUuid(id)], E::from_row)?;
+        Ok(entities.first().map(to_canonical_json).transpose()?)
+    }
+}
+
+impl SnapshotProvider for TableSnapshotProvider {
+    fn snapshot(&self, kind: EntityKind, id: Uuid) -> Result<Option<Value>> {
+        match kind {
+            EntityKind::Customer => return self.canonical::<Customer>(id),
+            EntityKind::Task => return self.canonical::<Task>(id),
+            EntityKind::Order => return self.canonical::<Order>(id),
+            _ => {}
+        }
+        let sql = format!("SELECT * FROM {} WHERE id = ?1", kind.table_name());
+        let rows = self.connection.query_map(&sql, [DbUuid(id)], |row| {
+            let statement = row.as_ref();
+            let mut object = Map::new();
+            for (index, name) in statement.column_names().into_iter().enumerate() {
+                let value = match row.get_ref(index)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(i) => Value::from(i),
+                    ValueRef::Real(f) => Value::from(f),
+                    ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t).into_owned()),
+                    ValueRef::Blob(_) => Value::Null,
+                };
+                object.insert(name.to_string(), value);
+            }
+            Ok(Value::Object(object))
+        })?;
+
+        Ok(rows.into_iter().next())
+    }
+}
@@ -0,0 +1,12 @@
+//! 存储实现共用的辅助函数
+
+use minicrm_core::CoreError;
+
+/// 把存储层的错误转为业务错误
+///
+/// 错误链中带有 [`CoreError`]（如事务中返回的未找到、校验失败）时原样返回，其余错误
+/// 归为 [`CoreError::Other`]。
+pub fn to_core(err: anyhow::Error) -> CoreError {
+    err.downcast::<CoreError>()
+        .unwrap_or_else(|e| CoreError::Other(e.to_string()))
+}
@@ -0,0 +1,816 @@
+//! 任务协作存储
+//!
+//! 基于 `tasks.assigned_to`、`task_watchers` 和 `task_notes` 实现任务协作服务。
+//! 分配、状态变更和新备注在同一事务中登记到事件发件箱，由 [`TaskNotifier`]
+//! 转换为发给负责人或关注人的提醒。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Clock, CoreError, CoreResult, DisplayName, DomainEvent, EntityKind, EventEnvelope,
+    EventHandler, Locale, PagedResult, Pagination, Reminder, ReminderKind, SystemClock, Task,
+    TaskAudience, TaskCollaborationService, TaskNote, TaskPriority, TaskStatus,
+};
+use rusqlite::types::Value;
+use rusqlite::{params, OptionalExtension, Row, Transaction};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::database::db_uuid::{canonical, get_optional_uuid, get_uuid};
+use crate::database::time::{parse_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::generic::EntityMapper;
+use crate::repository::reminders::ReminderStore;
+use crate::repository::support::to_core;
+use crate::repository::{customer_summary, outbox};
+
+const TASK_COLUMNS: &str = "id, title, description, status, priority, customer_id, due_date, \
+     created_at, updated_at, created_by, updated_by, assigned_to";
+
+/// 提醒广播的缓冲条数（界面来不及处理时丢弃最早的提示，提醒本身已保存）
+const TOAST_CAPACITY: usize = 64;
+
+fn row_to_task(row: &Row<'_>) -> rusqlite::Result<Task> {
+    let status: String = row.get(3)?;
+    let priority: String = row.get(4)?;
+    let due_date: Option<String> = row.get(6)?;
+    let created_at: String = row.get(7)?;
+    let updated_at: String = row.get(8)?;
+    Ok(Task {
+        id: get_uuid(row, 0)?,
+        title: row.get(1)?,
+        description: row.get(2)?,
+        status: TaskStatus::parse(&status).unwrap_or(TaskStatus::Pending),
+        priority: TaskPriority::parse(&priority).unwrap_or(TaskPriority::Medium),
+        customer_id: get_optional_uuid(row, 5)?,
+        supplier_id: None,
+        due_date: due_date.map(|v| parse_time(6, &v)).transpose()?,
+        created_at: parse_time(7, &created_at)?,
+        updated_at: parse_time(8, &updated_at)?,
+        created_by: row.get(9)?,
+        updated_by: row.get(10)?,
+        assigned_to: get_optional_uuid(row, 11)?,
+    })
+}
+
+/// 任务表没有供应商列，`supplier_id` 不保存
+impl EntityMapper for Task {
+    const KIND: EntityKind = EntityKind::Task;
+    const TABLE: &'static str = "tasks";
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "title",
+        "description",
+        "status",
+        "priority",
+        "customer_id",
+        "due_date",
+        "created_at",
+        "updated_at",
+        "created_by",
+        "updated_by",
+        "assigned_to",
+    ];
+    const ORDER_BY: &'static str = "created_at";
+    const SEARCH_COLUMNS: &'static [&'static str] = &["title", "description"];
+    const SOFT_DELETE: bool = true;
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn to_params(&self) -> Vec<Value> {
+        vec![
+            Value::Text(canonical(self.id)),
+            Value::Text(self.title.clone()),
+            Value::from(self.description.clone()),
+            Value::Text(self.status.as_str().to_string()),
+            Value::Text(self.priority.as_str().to_string()),
+            Value::from(self.customer_id.map(canonical)),
+            Value::from(self.due_date.map(time_key)),
+            Value::Text(time_key(self.created_at)),
+            Value::Text(time_key(self.updated_at)),
+            Value::from(self.created_by.clone()),
+            Value::from(self.updated_by.clone()),
+            Value::from(self.assigned_to.map(canonical)),
+        ]
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        row_to_task(row)
+    }
+}
+
+fn row_to_note(row: &Row<'_>) -> rusqlite::Result<TaskNote> {
+    let created_at: String = row.get(4)?;
+    Ok(TaskNote {
+        id: get_uuid(row, 0)?,
+        task_id: get_uuid(row, 1)?,
+        body: row.get(2)?,
+        author: row.get(3)?,
+        created_at: parse_time(4, &created_at)?,
+    })
+}
+
+/// 任务协作存储
+#[derive(Clone)]
+pub struct TaskStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for TaskStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskStore").finish_non_exhaustive()
+    }
+}
+
+impl TaskStore {
+    /// 创建任务协作存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中固定当前时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 未删除的任务
+    fn find(&self, task_id: Uuid) -> Result<Option<Task>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+                    TASK_COLUMNS
+                ),
+                [DbUuid(task_id)],
+                row_to_task,
+            )?
+            .into_iter()
+            .next())
+    }
+
+    /// 截至 `now` 已过截止日期且未完成的任务
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn overdue_tasks(&self, now: DateTime<Utc>) -> Result<Vec<Task>> {
+        self.connection.query_map(
+            &format!(
+                "SELECT {} FROM tasks
+                 WHERE status IN ('pending', 'in_progress')
+                   AND due_date < ?1 AND deleted_at IS NULL
+                 ORDER BY due_date",
+                TASK_COLUMNS
+            ),
+            [time_key(now)],
+            row_to_task,
+        )
+    }
+
+    fn require(&self, task_id: Uuid) -> CoreResult<Task> {
+        self.find(task_id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("任务 {}", task_id)))
+    }
+
+    /// 用户是否存在且已启用
+    fn user_active(&self, user_id: Uuid) -> Result<Option<bool>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT active FROM users WHERE id = ?1",
+                [DbUuid(user_id)],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn record(tx: &Transaction<'_>, event: DomainEvent, at: DateTime<Utc>) -> Result<()> {
+        customer_summary::apply_event(tx, &event, at)?;
+        outbox::record(tx, &EventEnvelope::at(event, at))
+    }
+}
+
+#[async_trait]
+impl TaskCollaborationService for TaskStore {
+    async fn assign_task(&self, task_id: Uuid, user_id: Option<Uuid>) -> CoreResult<Task> {
+        let task = self.require(task_id)?;
+        if let Some(user_id) = user_id {
+            match self.user_active(user_id).map_err(to_core)? {
+                None => return Err(CoreError::validation(format!("用户不存在: {}", user_id))),
+                Some(false) => {
+                    return Err(CoreError::validation("该用户已停用，不能分配任务"));
+                }
+                Some(true) => {}
+            }
+        }
+        if task.assigned_to == user_id {
+            return Ok(task);
+        }
+
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "UPDATE tasks SET assigned_to = ?2, updated_at = ?3 WHERE id = ?1",
+                    params![DbUuid(task_id), user_id.map(DbUuid), time_key(now)],
+                )
+                .context("无法更新任务负责人")?;
+                match user_id {
+                    Some(assignee) => Self::record(
+                        tx,
+                        DomainEvent::TaskAssigned { task_id, assignee },
+                        now,
+                    ),
+                    None => Ok(()),
+                }
+            })
+            .map_err(to_core)?;
+
+        info!("任务 {} 负责人变更为 {:?}", task.title, user_id);
+        Ok(Task {
+            assigned_to: user_id,
+            updated_at: now,
+            ..task
+        })
+    }
+
+    async fn watch_task(&self, task_id: Uuid, user_id: Uuid) -> CoreResult<bool> {
+        self.require(task_id)?;
+        let inserted = self
+            .connection
+            .execute(
+                "INSERT OR IGNORE INTO task_watchers (task_id, user_id, created_at)
+                 VALUES (?1, ?2, ?3)",
+                params![DbUuid(task_id), DbUuid(user_id), time_key(self.clock.now())],
+            )
+            .map_err(to_core)?;
+        Ok(inserted > 0)
+    }
+
+    async fn unwatch_task(&self, task_id: Uuid, user_id: Uuid) -> CoreResult<bool> {
+        let deleted = self
+            .connection
+            .execute(
+                "DELETE FROM task_watchers WHERE task_id = ?1 AND user_id = ?2",
+                params![DbUuid(task_id), DbUuid(user_id)],
+            )
+            .map_err(to_core)?;
+        Ok(deleted > 0)
+    }
+
+    async fn task_watchers(&self, task_id: Uuid) -> CoreResult<Vec<Uuid>> {
+        watchers(&self.connection, task_id).map_err(to_core)
+    }
+
+    async fn add_task_note(
+        &self,
+        task_id: Uuid,
+        body: &str,
+        author: Option<String>,
+    ) -> CoreResult<TaskNote> {
+        let body = body.trim();
+        if body.is_empty() {
+            return Err(CoreError::validation("备注内容不能为空"));
+        }
+        self.require(task_id)?;
+
+        let note = TaskNote {
+            id: Uuid::new_v4(),
+            task_id,
+            body: body.to_string(),
+            author,
+            created_at: self.clock.now(),
+        };
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "INSERT INTO task_notes (id, task_id, body, author, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        DbUuid(note.id),
+                        DbUuid(task_id),
+                        note.body,
+                        note.author,
+                        time_key(note.created_at),
+                    ],
+                )
+                .context("无法写入任务备注")?;
+                Self::record(
+                    tx,
+                    DomainEvent::TaskNoteAdded {
+                        task_id,
+                        note_id: note.id,
+                    },
+                    note.created_at,
+                )
+            })
+            .map_err(to_core)?;
+        Ok(note)
+    }
+
+    async fn delete_task_note(&self, note_id: Uuid) -> CoreResult<TaskNote> {
+        let note = self
+            .connection
+            .query_map(
+                "SELECT id, task_id, body, author, created_at FROM task_notes WHERE id = ?1",
+                [DbUuid(note_id)],
+                row_to_note,
+            )
+            .map_err(to_core)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| CoreError::not_found(format!("备注 {}", note_id)))?;
+        self.connection
+            .execute("DELETE FROM task_notes WHERE id = ?1", [DbUuid(note_id)])
+            .map_err(to_core)?;
+        info!("已删除任务 {} 的备注 {}", note.task_id, note_id);
+        Ok(note)
+    }
+
+    async fn restore_task_note(&self, note: &TaskNote) -> CoreResult<TaskNote> {
+        self.require(note.task_id)?;
+        let inserted = self
+            .connection
+            .execute(
+                "INSERT OR IGNORE INTO task_notes (id, task_id, body, author, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    DbUuid(note.id),
+                    DbUuid(note.task_id),
+                    note.body,
+                    note.author,
+                    time_key(note.created_at),
+                ],
+            )
+            .map_err(to_core)?;
+        if inserted == 0 {
+            return Err(CoreError::conflict(format!("备注 {} 已存在", note.id)));
+        }
+        Ok(note.clone())
+    }
+
+    async fn record_status_change(
+        &self,
+        task_id: Uuid,
+        from: TaskStatus,
+        to: TaskStatus,
+    ) -> CoreResult<()> {
+        if from == to {
+            return Ok(());
+        }
+        let now = self.clock.now();
+        self.connection
+            .with_transaction(|tx| {
+                Self::record(tx, DomainEvent::TaskStatusChanged { task_id, from, to }, now)
+            })
+            .map_err(to_core)
+    }
+
+    async fn search_tasks_for(
+        &self,
+        audience: TaskAudience,
+        pagination: &Pagination,
+    ) -> CoreResult<PagedResult<Task>> {
+        let condition = "deleted_at IS NULL
+             AND (?1 IS NULL OR assigned_to = ?1)
+             AND (?2 IS NULL OR EXISTS (SELECT 1 FROM task_watchers w
+                                        WHERE w.task_id = tasks.id AND w.user_id = ?2))";
+        let assigned_to = audience.assigned_to.map(DbUuid);
+        let watched_by = audience.watched_by.map(DbUuid);
+
+        let total: i64 = self
+            .connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM tasks WHERE {}", condition),
+                params![assigned_to, watched_by],
+                |row| row.get(0),
+            )
+            .map_err(to_core)?;
+        let items = self
+            .connection
+            .query_map(
+                &format!(
+                    "SELECT {} FROM tasks WHERE {}
+                     ORDER BY due_date IS NULL, due_date, created_at
+                     LIMIT ?3 OFFSET ?4",
+                    TASK_COLUMNS, condition
+                ),
+                params![
+                    assigned_to,
+                    watched_by,
+                    pagination.page_size,
+                    pagination.offset()
+                ],
+                row_to_task,
+            )
+            .map_err(to_core)?;
+        Ok(PagedResult::new(
+            items,
+            u64::try_from(total).unwrap_or_default(),
+            pagination,
+        ))
+    }
+}
+
+fn watchers(connection: &DatabaseConnection, task_id: Uuid) -> Result<Vec<Uuid>> {
+    connection.query_map(
+        "SELECT user_id FROM task_watchers WHERE task_id = ?1 ORDER BY created_at",
+        [DbUuid(task_id)],
+        |row| get_uuid(row, 0),
+    )
+}
+
+/// 任务通知处理器
+///
+/// 订阅任务事件：分配时提醒负责人，状态变更和新备注提醒关注人。提醒以事件发生时间为
+/// 提醒时间，按（类型, 任务, 提醒时间, 接收人）去重，事件重复投递不会产生重复提醒。
+/// 新建的提醒同时广播给界面显示提示。
+pub struct TaskNotifier {
+    connection: DatabaseConnection,
+    reminders: ReminderStore,
+    toasts: broadcast::Sender<Reminder>,
+}
+
+impl std::fmt::Debug for TaskNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskNotifier").finish_non_exhaustive()
+    }
+}
+
+impl TaskNotifier {
+    /// 创建任务通知处理器
+    pub fn new(connection: DatabaseConnection) -> Self {
+        let (toasts, _) = broadcast::channel(TOAST_CAPACITY);
+        Self {
+            reminders: ReminderStore::new(connection.clone()),
+            connection,
+            toasts,
+        }
+    }
+
+    /// 订阅新建的提醒（界面按接收人是否为当前用户决定是否提示）
+    pub fn subscribe(&self) -> broadcast::Receiver<Reminder> {
+        self.toasts.subscribe()
+    }
+
+    fn task_title(&self, task_id: Uuid) -> Result<Option<String>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT title FROM tasks WHERE id = ?1",
+                [DbUuid(task_id)],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn notify(
+        &self,
+        kind: ReminderKind,
+        task_id: Uuid,
+        recipients: &[Uuid],
+        title: &str,
+        at: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut created = 0;
+        for recipient in recipients {
+            let reminder = Reminder {
+                id: Uuid::new_v4(),
+                kind,
+                entity: EntityKind::Task,
+                entity_id: task_id,
+                title: title.to_string(),
+                due_at: at,
+                created_at: at,
+                dismissed_at: None,
+                recipient: Some(*recipient),
+                snoozed_until: None,
+            };
+            if self.reminders.create_if_absent(&reminder)? {
+                created += 1;
+                // 没有界面订阅时发送失败，提醒已保存
+                let _ = self.toasts.send(reminder);
+            }
+        }
+        Ok(created)
+    }
+
+    fn dispatch(&self, envelope: &EventEnvelope) -> Result<()> {
+        let (kind, task_id, recipients) = match envelope.event {
+            DomainEvent::TaskAssigned { task_id, assignee } => {
+                (ReminderKind::TaskAssigned, task_id, vec![assignee])
+            }
+            DomainEvent::TaskStatusChanged { task_id, .. }
+            | DomainEvent::TaskNoteAdded { task_id, .. } => (
+                ReminderKind::TaskActivity,
+                task_id,
+                watchers(&self.connection, task_id)?,
+            ),
+            _ => return Ok(()),
+        };
+        if recipients.is_empty() {
+            return Ok(());
+        }
+        let Some(title) = self.task_title(task_id)? else {
+            warn!("任务 {} 已不存在，跳过通知", task_id);
+            return Ok(());
+        };
+        let message = match envelope.event {
+            DomainEvent::TaskStatusChanged { to, .. } => {
+                format!(
+                    "你关注的任务「{}」状态变为{}",
+                    title,
+                    to.display_name(Locale::ZhCn)
+                )
+            }
+            DomainEvent::TaskNoteAdded { .. } => format!("你关注的任务「{}」有新备注", title),
+            _ => format!("任务「{}」已分配给你", title),
+        };
+        self.notify(kind, task_id, &recipients, &message, envelope.occurred_at)?;
+        Ok(())
+    }
+}
+
+impl EventHandler for TaskNotifier {
+    fn name(&self) -> &str {
+        "task_notifier"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        self.dispatch(envelope).map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::outbox::{EventOutboxStore, OutboxDispatcher};
+    use crate::repository::users::SqliteUserService;
+    use crate::test_support::migrated_pool;
+    use chrono::TimeZone;
+    use minicrm_core::{ManualClock, NewUser, UserRole, UserService};
+    use tempfile::TempDir;
+
+    struct Fixture {
+        _dir: TempDir,
+        connection: DatabaseConnection,
+        store: TaskStore,
+        clock: Arc<ManualClock>,
+        notifier: Arc<TaskNotifier>,
+        reminders: ReminderStore,
+        users: SqliteUserService,
+    }
+
+    fn fixture() -> Fixture {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap(),
+        ));
+        Fixture {
+            _dir: temp_dir,
+            store: TaskStore::new(connection.clone()).with_clock(clock.clone()),
+            clock,
+            notifier: Arc::new(TaskNotifier::new(connection.clone())),
+            reminders: ReminderStore::new(connection.clone()),
+            users: SqliteUserService::new(connection.clone()),
+            connection,
+        }
+    }
+
+    impl Fixture {
+        async fn user(&self, username: &str) -> Uuid {
+            self.users
+                .create_user(NewUser {
+                    username: username.to_string(),
+                    display_name: username.to_string(),
+                    role: UserRole::Sales,
+                    password: "secret123".to_string(),
+                })
+                .await
+                .unwrap()
+                .id
+        }
+
+        fn task(&self, title: &str, due: Option<&str>) -> Uuid {
+            let (id, customer) = (Uuid::new_v4(), Uuid::new_v4());
+            self.connection
+                .execute(
+                    "INSERT INTO customers (id, name, created_at, updated_at)
+                     VALUES (?1, '华南木业', ?2, ?2)",
+                    params![DbUuid(customer), "2024-06-01T00:00:00.000000Z"],
+                )
+                .unwrap();
+            self.connection
+                .execute(
+                    "INSERT INTO tasks (id, customer_id, title, status, due_date, created_at,
+                                        updated_at)
+                     VALUES (?1, ?2, ?3, 'pending', ?4, ?5, ?5)",
+                    params![
+                        DbUuid(id),
+                        DbUuid(customer),
+                        title,
+                        due,
+                        "2024-06-01T00:00:00.000000Z"
+                    ],
+                )
+                .unwrap();
+            id
+        }
+
+        /// 分发发件箱中的事件
+        fn deliver(&self) -> usize {
+            OutboxDispatcher::new(
+                EventOutboxStore::new(self.connection.clone()),
+                self.notifier.clone(),
+            )
+            .dispatch_pending()
+            .unwrap()
+        }
+
+        fn reminders_for(&self, user: Uuid) -> Vec<Reminder> {
+            self.reminders.active_for(user).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notification_fan_out() {
+        let f = fixture();
+        let manager = f.user("manager").await;
+        let assignee = f.user("xiaowang").await;
+        let watcher = f.user("laoli").await;
+        let task = f.task("复测尺寸", None);
+        let mut toasts = f.notifier.subscribe();
+
+        f.store.watch_task(task, manager).await.unwrap();
+        assert!(!f.store.watch_task(task, manager).await.unwrap());
+        f.store.watch_task(task, watcher).await.unwrap();
+        let assigned = f.store.assign_task(task, Some(assignee)).await.unwrap();
+        assert_eq!(assigned.assigned_to, Some(assignee));
+        assert_eq!(f.deliver(), 1);
+
+        // 分配只提醒负责人
+        let to_assignee = f.reminders_for(assignee);
+        assert_eq!(to_assignee.len(), 1);
+        assert_eq!(to_assignee[0].kind, ReminderKind::TaskAssigned);
+        assert_eq!(to_assignee[0].title, "任务「复测尺寸」已分配给你");
+        assert!(f.reminders_for(manager).is_empty());
+        assert_eq!(toasts.try_recv().unwrap().recipient, Some(assignee));
+
+        // 状态变更和新备注只提醒关注人
+        f.store
+            .record_status_change(task, TaskStatus::Pending, TaskStatus::InProgress)
+            .await
+            .unwrap();
+        f.clock.advance(chrono::Duration::minutes(5));
+        f.store
+            .add_task_note(task, "客户要求改到下午", Some("xiaowang".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(f.deliver(), 2);
+        assert_eq!(f.reminders_for(assignee).len(), 1);
+        for user in [manager, watcher] {
+            let titles: Vec<String> = f.reminders_for(user).into_iter().map(|r| r.title).collect();
+            assert_eq!(
+                titles,
+                vec![
+                    "你关注的任务「复测尺寸」状态变为进行中",
+                    "你关注的任务「复测尺寸」有新备注",
+                ]
+            );
+        }
+
+        // 取消关注后不再提醒；重复投递不产生重复提醒
+        assert!(f.store.unwatch_task(task, watcher).await.unwrap());
+        assert_eq!(f.store.task_watchers(task).await.unwrap(), vec![manager]);
+        let envelope = EventEnvelope::new(DomainEvent::TaskStatusChanged {
+            task_id: task,
+            from: TaskStatus::InProgress,
+            to: TaskStatus::Completed,
+        });
+        f.notifier.handle(&envelope).unwrap();
+        f.notifier.handle(&envelope).unwrap();
+        assert_eq!(f.reminders_for(manager).len(), 3);
+        assert_eq!(f.reminders_for(watcher).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_restore_note() {
+        let f = fixture();
+        let task = f.task("复测尺寸", None);
+        let note = f
+            .store
+            .add_task_note(task, "客户要求改到下午", Some("xiaowang".to_string()))
+            .await
+            .unwrap();
+
+        let deleted = f.store.delete_task_note(note.id).await.unwrap();
+        assert_eq!(deleted, note);
+        assert!(matches!(
+            f.store.delete_task_note(note.id).await,
+            Err(CoreError::NotFound(_))
+        ));
+
+        // 按原ID和记录时间恢复，重复恢复返回冲突
+        assert_eq!(f.store.restore_task_note(&deleted).await.unwrap(), note);
+        assert!(matches!(
+            f.store.restore_task_note(&deleted).await,
+            Err(CoreError::Conflict(_))
+        ));
+
+        // 任务已删除时不能恢复
+        f.store.delete_task_note(note.id).await.unwrap();
+        f.connection
+            .execute(
+                "UPDATE tasks SET deleted_at = ?2 WHERE id = ?1",
+                params![DbUuid(task), "2024-07-01T10:00:00.000000Z"],
+            )
+            .unwrap();
+        assert!(matches!(
+            f.store.restore_task_note(&deleted).await,
+            Err(CoreError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assign_to_deactivated_user_is_rejected() {
+        let f = fixture();
+        let user = f.user("retired").await;
+        let task = f.task("安装回访", None);
+        f.users.deactivate_user(user, None).await.unwrap();
+
+        assert!(matches!(
+            f.store.assign_task(task, Some(user)).await,
+            Err(CoreError::Validation(_))
+        ));
+        assert!(matches!(
+            f.store.assign_task(task, Some(Uuid::new_v4())).await,
+            Err(CoreError::Validation(_))
+        ));
+        assert!(matches!(
+            f.store.assign_task(Uuid::new_v4(), None).await,
+            Err(CoreError::NotFound(_))
+        ));
+        assert_eq!(f.store.find(task).unwrap().unwrap().assigned_to, None);
+        assert_eq!(f.deliver(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_assigned_and_watched_filters() {
+        let f = fixture();
+        let me = f.user("me").await;
+        let other = f.user("other").await;
+        let mine = f.task("我的任务", Some("2024-07-05T00:00:00.000000Z"));
+        let mine_later = f.task("我的后续任务", None);
+        let watched = f.task("关注的任务", Some("2024-07-03T00:00:00.000000Z"));
+        let unrelated = f.task("别人的任务", None);
+        f.store.assign_task(mine, Some(me)).await.unwrap();
+        f.store.assign_task(mine_later, Some(me)).await.unwrap();
+        f.store.assign_task(watched, Some(other)).await.unwrap();
+        f.store.assign_task(unrelated, Some(other)).await.unwrap();
+        f.store.watch_task(watched, me).await.unwrap();
+        f.store.watch_task(mine, me).await.unwrap();
+
+        let ids = |page: PagedResult<Task>| {
+            page.items.into_iter().map(|t| t.id).collect::<Vec<_>>()
+        };
+        let page = Pagination::default();
+        let assigned = TaskAudience {
+            assigned_to: Some(me),
+            ..TaskAudience::default()
+        };
+        let watching = TaskAudience {
+            watched_by: Some(me),
+            ..TaskAudience::default()
+        };
+        assert_eq!(
+            ids(f.store.search_tasks_for(assigned, &page).await.unwrap()),
+            vec![mine, mine_later]
+        );
+        assert_eq!(
+            ids(f.store.search_tasks_for(watching, &page).await.unwrap()),
+            vec![watched, mine]
+        );
+        let both = TaskAudience {
+            assigned_to: Some(me),
+            watched_by: Some(me),
+        };
+        let result = f.store.search_tasks_for(both, &page).await.unwrap();
+        assert_eq!(result.total, 1);
+        assert_eq!(ids(result), vec![mine]);
+    }
+}
@@ -0,0 +1,428 @@
+//! 客户时间线读取模型
+//!
+//! 时间线由多个来源查询 `UNION ALL` 合并而成：互动表本身，以及从报价（新建时间、修订历史中
+//! 第一次保存为“已发送”“已接受”的时间）、订单送达时间、收款、售后工单开单和关闭时间推导的
+//! 事件。筛选分类时不需要的来源不出现在SQL中。
+//!
+//! 各表时间的写法不完全一致，合并前统一转换为毫秒精度的UTC文本，再按（时间，事件类型，
+//! 记录ID）倒序做键集分页（见 [`minicrm_core::timeline`]）。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    CoreError, CoreResult, Currency, CustomerTimelineReadModel, InteractionKind, Money,
+    TimelineEventType, TimelineItem, TimelinePage, TimelineQuery,
+};
+use rusqlite::params;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::{DatabaseConnection, DbUuid};
+
+/// 报价编号：取最新修订快照中的编号，没有修订时用报价标题
+const QUOTE_NUMBER: &str = "COALESCE((SELECT json_extract(v.snapshot, '$.quote_number')
+                                      FROM quote_revisions v WHERE v.quote_id = q.id
+                                      ORDER BY v.revision_no DESC LIMIT 1), q.title)";
+
+/// 报价金额（分）
+const QUOTE_AMOUNT: &str = "CAST(ROUND(q.total_amount * 100) AS INTEGER)";
+
+/// 统一为毫秒精度的UTC文本，保证合并后可以按文本排序
+fn ts(expr: &str) -> String {
+    format!("strftime('%Y-%m-%dT%H:%M:%fZ', {})", expr)
+}
+
+/// 分页位置中的时间（与 [`ts`] 的格式一致）
+fn cursor_key(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// 报价在修订历史中第一次保存为指定状态的时间
+fn quote_status_sql(event: TimelineEventType, status: &str) -> String {
+    format!(
+        "SELECT '{event}' AS event, q.id AS id, MIN({at}) AS at, q.id AS entity_id,
+                NULL AS sub, {QUOTE_NUMBER} AS number, {QUOTE_AMOUNT} AS amount,
+                q.currency AS currency, NULL AS content
+         FROM quotes q JOIN quote_revisions r ON r.quote_id = q.id
+         WHERE q.customer_id = ?1 AND q.deleted_at IS NULL
+           AND json_extract(r.snapshot, '$.status') = '{status}'
+         GROUP BY q.id",
+        event = event.as_str(),
+        at = ts("r.saved_at"),
+    )
+}
+
+/// 一种事件的来源查询（`?1` 为客户ID）
+fn source_sql(event: TimelineEventType) -> String {
+    match event {
+        TimelineEventType::Interaction => format!(
+            "SELECT '{}' AS event, i.id AS id, {} AS at, i.customer_id AS entity_id,
+                    i.kind AS sub, NULL AS number, NULL AS amount, NULL AS currency,
+                    i.content AS content
+             FROM interactions i WHERE i.customer_id = ?1",
+            event.as_str(),
+            ts("i.occurred_at")
+        ),
+        TimelineEventType::QuoteCreated => format!(
+            "SELECT '{}' AS event, q.id AS id, {} AS at, q.id AS entity_id, NULL AS sub,
+                    {QUOTE_NUMBER} AS number, {QUOTE_AMOUNT} AS amount,
+                    q.currency AS currency, NULL AS content
+             FROM quotes q WHERE q.customer_id = ?1 AND q.deleted_at IS NULL",
+            event.as_str(),
+            ts("q.created_at")
+        ),
+        TimelineEventType::QuoteSent => quote_status_sql(event, "Sent"),
+        TimelineEventType::QuoteAccepted => quote_status_sql(event, "Accepted"),
+        TimelineEventType::OrderDelivered => format!(
+            "SELECT '{}' AS event, o.id AS id, {} AS at, o.id AS entity_id, NULL AS sub,
+                    o.order_number AS number, o.total_amount AS amount,
+                    o.currency AS currency, NULL AS content
+             FROM orders o
+             WHERE o.customer_id = ?1 AND o.deleted_at IS NULL AND o.delivered_at IS NOT NULL",
+            event.as_str(),
+            ts("o.delivered_at")
+        ),
+        TimelineEventType::PaymentReceived => format!(
+            "SELECT '{}' AS event, p.id AS id, {} AS at, o.id AS entity_id, NULL AS sub,
+                    o.order_number AS number, p.amount AS amount,
+                    o.currency AS currency, NULL AS content
+             FROM order_payments p JOIN orders o ON o.id = p.order_id
+             WHERE o.customer_id = ?1 AND o.deleted_at IS NULL",
+            event.as_str(),
+            ts("p.paid_at")
+        ),
+        TimelineEventType::TicketOpened => format!(
+            "SELECT '{}' AS event, t.id AS id, {} AS at, t.id AS entity_id, NULL AS sub,
+                    t.ticket_number AS number, NULL AS amount, NULL AS currency,
+                    t.problem_category AS content
+             FROM service_tickets t WHERE t.customer_id = ?1",
+            event.as_str(),
+            ts("t.created_at")
+        ),
+        TimelineEventType::TicketClosed => format!(
+            "SELECT '{}' AS event, t.id AS id, {} AS at, t.id AS entity_id, NULL AS sub,
+                    t.ticket_number AS number, NULL AS amount, NULL AS currency,
+                    t.problem_category AS content
+             FROM service_tickets t WHERE t.customer_id = ?1 AND t.closed_at IS NOT NULL",
+            event.as_str(),
+            ts("t.closed_at")
+        ),
+    }
+}
+
+/// 合并查询（`?2`~`?4` 为分页位置，`?5` 为读取条数）
+fn timeline_sql(events: &[TimelineEventType]) -> String {
+    let sources: Vec<String> = events.iter().map(|&event| source_sql(event)).collect();
+    format!(
+        "SELECT event, id, at, entity_id, sub, number, amount, currency, content
+         FROM ({})
+         WHERE at IS NOT NULL AND (?2 IS NULL OR (at, event, id) < (?2, ?3, ?4))
+         ORDER BY at DESC, event DESC, id DESC
+         LIMIT ?5",
+        sources.join("\n UNION ALL\n ")
+    )
+}
+
+fn conversion_error(index: usize, message: String) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, message.into())
+}
+
+fn read_item(row: &rusqlite::Row<'_>) -> rusqlite::Result<TimelineItem> {
+    let event: String = row.get(0)?;
+    let event = TimelineEventType::parse(&event)
+        .ok_or_else(|| conversion_error(0, format!("未知的时间线事件: {}", event)))?;
+    let at: String = row.get(2)?;
+    let occurred_at = DateTime::parse_from_rfc3339(&at)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| conversion_error(2, e.to_string()))?;
+    let currency = match row.get::<_, Option<String>>(7)? {
+        Some(code) => code
+            .parse::<Currency>()
+            .map_err(|e| conversion_error(7, e.to_string()))?,
+        None => Currency::default(),
+    };
+    Ok(TimelineItem {
+        event,
+        id: get_uuid(row, 1)?,
+        occurred_at,
+        entity_id: get_uuid(row, 3)?,
+        interaction_kind: row
+            .get::<_, Option<String>>(4)?
+            .and_then(|kind| InteractionKind::parse(&kind)),
+        number: row.get(5)?,
+        amount: row.get::<_, Option<i64>>(6)?.map(Money::from_cents),
+        currency,
+        content: row.get(8)?,
+    })
+}
+
+/// 客户时间线存储
+#[derive(Clone)]
+pub struct TimelineStore {
+    connection: DatabaseConnection,
+}
+
+impl std::fmt::Debug for TimelineStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimelineStore").finish_non_exhaustive()
+    }
+}
+
+impl TimelineStore {
+    /// 创建客户时间线存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self { connection }
+    }
+
+    /// 读取一页时间线
+    pub fn timeline(&self, query: &TimelineQuery) -> Result<TimelinePage> {
+        let events = query.event_types();
+        if events.is_empty() {
+            return Ok(TimelinePage::default());
+        }
+        let after = query.after.as_ref();
+        let mut items = self.connection.query_map(
+            &timeline_sql(&events),
+            params![
+                DbUuid(query.customer_id),
+                after.map(|c| cursor_key(c.occurred_at)),
+                after.map(|c| c.event.as_str()),
+                after.map(|c| DbUuid(c.id)),
+                (query.limit + 1) as i64,
+            ],
+            read_item,
+        )?;
+        let next = if items.len() > query.limit {
+            items.truncate(query.limit);
+            items.last().map(TimelineItem::cursor)
+        } else {
+            None
+        };
+        Ok(TimelinePage { items, next })
+    }
+}
+
+#[async_trait]
+impl CustomerTimelineReadModel for TimelineStore {
+    async fn customer_timeline(&self, query: &TimelineQuery) -> CoreResult<TimelinePage> {
+        self.timeline(query)
+            .map_err(|e| CoreError::Other(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::time::time_key;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::TimelineCategory;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    struct Fixture {
+        _dir: TempDir,
+        connection: DatabaseConnection,
+        store: TimelineStore,
+        customer: Uuid,
+    }
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap() + Duration::hours(hours)
+    }
+
+    fn fixture() -> Fixture {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        Fixture {
+            _dir: temp_dir,
+            store: TimelineStore::new(connection.clone()),
+            connection,
+            customer: Uuid::new_v4(),
+        }
+    }
+
+    /// 为客户写入每种来源各一条记录，返回按时间倒序应出现的事件
+    fn seed(fixture: &Fixture) -> Vec<TimelineEventType> {
+        let c = &fixture.connection;
+        let customer = DbUuid(fixture.customer);
+        let other = DbUuid(Uuid::new_v4());
+        let quote = DbUuid(Uuid::new_v4());
+        let order = DbUuid(Uuid::new_v4());
+        let ticket = DbUuid(Uuid::new_v4());
+
+        c.execute(
+            "INSERT INTO customers (id, name, created_at, updated_at)
+             VALUES (?1, '华南木业', ?2, ?2)",
+            params![customer, time_key(at(0))],
+        )
+        .unwrap();
+        for (who, hours) in [(&customer, 0), (&other, 1)] {
+            c.execute(
+                "INSERT INTO interactions (id, customer_id, kind, content, occurred_at)
+                 VALUES (?1, ?2, 'call', '电话回访', ?3)",
+                params![DbUuid(Uuid::new_v4()), who, time_key(at(hours))],
+            )
+            .unwrap();
+        }
+        c.execute(
+            "INSERT INTO quotes (id, customer_id, title, total_amount, status, created_at,
+                                 updated_at)
+             VALUES (?1, ?2, '板材报价', 12000.5, 'accepted', ?3, ?3)",
+            params![quote, customer, time_key(at(2))],
+        )
+        .unwrap();
+        for (no, status, hours) in [(1, "Sent", 3), (2, "Sent", 4), (3, "Accepted", 5)] {
+            c.execute(
+                "INSERT INTO quote_revisions (quote_id, revision_no, snapshot, saved_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    quote,
+                    no,
+                    format!(r#"{{"quote_number":"Q-2024-001","status":"{}"}}"#, status),
+                    time_key(at(hours))
+                ],
+            )
+            .unwrap();
+        }
+        c.execute(
+            "INSERT INTO orders (id, order_number, customer_id, total_amount, delivered_at,
+                                 created_at, updated_at)
+             VALUES (?1, 'SO-001', ?2, 1200050, ?3, ?4, ?4)",
+            params![order, customer, time_key(at(6)), time_key(at(5))],
+        )
+        .unwrap();
+        c.execute(
+            "INSERT INTO order_payments (id, order_id, amount, paid_at) VALUES (?1, ?2, ?3, ?4)",
+            params![DbUuid(Uuid::new_v4()), order, 500000, time_key(at(7))],
+        )
+        .unwrap();
+        c.execute(
+            "INSERT INTO service_tickets (id, ticket_number, customer_id, problem_category,
+                                          status, created_at, updated_at, closed_at)
+             VALUES (?1, 'SH-001', ?2, '变形', 'Closed', ?3, ?4, ?4)",
+            params![ticket, customer, time_key(at(8)), time_key(at(9))],
+        )
+        .unwrap();
+
+        vec![
+            TimelineEventType::TicketClosed,
+            TimelineEventType::TicketOpened,
+            TimelineEventType::PaymentReceived,
+            TimelineEventType::OrderDelivered,
+            TimelineEventType::QuoteAccepted,
+            TimelineEventType::QuoteSent,
+            TimelineEventType::QuoteCreated,
+            TimelineEventType::Interaction,
+        ]
+    }
+
+    fn read_all(store: &TimelineStore, mut query: TimelineQuery) -> Vec<TimelineItem> {
+        let mut items = Vec::new();
+        loop {
+            let page = store.timeline(&query).unwrap();
+            assert!(page.items.len() <= query.limit);
+            items.extend(page.items);
+            match page.next {
+                Some(cursor) => query = query.after(cursor),
+                None => return items,
+            }
+        }
+    }
+
+    #[test]
+    fn test_merged_ordering_across_pages() {
+        let fixture = fixture();
+        let expected = seed(&fixture);
+
+        let first = fixture
+            .store
+            .timeline(&TimelineQuery::new(fixture.customer))
+            .unwrap();
+        assert_eq!(first.next, None);
+        let events: Vec<_> = first.items.iter().map(|item| item.event).collect();
+        assert_eq!(events, expected);
+
+        // 报价发送时间取第一次保存为“已发送”的修订，编号和金额来自报价
+        let sent = &first.items[5];
+        assert_eq!(sent.occurred_at, at(3));
+        assert_eq!(sent.number.as_deref(), Some("Q-2024-001"));
+        assert_eq!(sent.amount, Some(Money::from_cents(1_200_050)));
+        assert_eq!(sent.icon(), "send");
+        let call = &first.items[7];
+        assert_eq!(call.interaction_kind, Some(InteractionKind::Call));
+        assert_eq!(call.entity_id, fixture.customer);
+        assert_eq!(first.items[2].amount, Some(Money::from_cents(500_000)));
+
+        // 同一时间的多条记录按类型和ID稳定排序，跨页不重复不遗漏
+        for _ in 0..4 {
+            fixture
+                .connection
+                .execute(
+                    "INSERT INTO interactions (id, customer_id, kind, content, occurred_at)
+                     VALUES (?1, ?2, 'note', '同一时刻', ?3)",
+                    params![
+                        DbUuid(Uuid::new_v4()),
+                        DbUuid(fixture.customer),
+                        time_key(at(6))
+                    ],
+                )
+                .unwrap();
+        }
+        let all = fixture
+            .store
+            .timeline(&TimelineQuery::new(fixture.customer))
+            .unwrap()
+            .items;
+        assert_eq!(all.len(), 12);
+        for limit in [1, 3, 5] {
+            let paged = read_all(
+                &fixture.store,
+                TimelineQuery::new(fixture.customer).with_limit(limit),
+            );
+            assert_eq!(paged, all, "limit {}", limit);
+        }
+    }
+
+    #[test]
+    fn test_category_filters() {
+        let fixture = fixture();
+        seed(&fixture);
+
+        let quotes = read_all(
+            &fixture.store,
+            TimelineQuery::new(fixture.customer)
+                .with_categories(&[TimelineCategory::Quotes])
+                .with_limit(2),
+        );
+        let events: Vec<_> = quotes.iter().map(|item| item.event).collect();
+        assert_eq!(
+            events,
+            vec![
+                TimelineEventType::QuoteAccepted,
+                TimelineEventType::QuoteSent,
+                TimelineEventType::QuoteCreated
+            ]
+        );
+
+        let service = fixture
+            .store
+            .timeline(
+                &TimelineQuery::new(fixture.customer).with_categories(&[TimelineCategory::Service]),
+            )
+            .unwrap();
+        assert_eq!(service.items.len(), 2);
+        assert!(service
+            .items
+            .iter()
+            .all(|item| item.event.category() == TimelineCategory::Service
+                && item.number.as_deref() == Some("SH-001")));
+
+        // 其他客户的记录不出现
+        let empty = fixture
+            .store
+            .timeline(&TimelineQuery::new(Uuid::new_v4()))
+            .unwrap();
+        assert!(empty.items.is_empty());
+    }
+}
@@ -0,0 +1,640 @@
+//! 回收站
+//!
+//! 软删除按批次记录在 `deletion_batches` 表，被删除的记录以 `deletion_batch_id` 指向所属
+//! 批次。删除客户时，客户及其未完成的任务、草稿报价在同一事务中以同一批次删除；已在回收站
+//! 中的记录保留原批次。恢复任一记录时恢复其所在批次的全部记录，不涉及其他批次。升级前删除、
+//! 没有批次的记录按单条恢复。
+//!
+//! 删除和恢复在同一事务中调整表记录总数缓存并刷新所属客户的汇总。
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use minicrm_core::{
+    Clock, CoreError, CoreResult, DeletionBatch, EntityKind, RestoreReport, SystemClock, TrashItem,
+    TrashService,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{get_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::customer_summary;
+use crate::repository::support::to_core;
+
+/// 支持软删除的实体
+const TRASH_ENTITIES: [EntityKind; 5] = [
+    EntityKind::Customer,
+    EntityKind::Task,
+    EntityKind::Quote,
+    EntityKind::Order,
+    EntityKind::Opportunity,
+];
+
+/// 删除客户时连带删除的记录（`?3` 为客户ID）
+const CUSTOMER_CASCADE: [(EntityKind, &str); 2] = [
+    (
+        EntityKind::Task,
+        "customer_id = ?3 AND lower(status) NOT IN ('completed', 'cancelled')",
+    ),
+    (EntityKind::Quote, "customer_id = ?3 AND status = 'draft'"),
+];
+
+/// 显示名称所在的列
+fn label_column(entity: EntityKind) -> &'static str {
+    match entity {
+        EntityKind::Customer => "name",
+        EntityKind::Order => "order_number",
+        _ => "title",
+    }
+}
+
+/// 所属客户ID所在的列
+fn owner_column(entity: EntityKind) -> &'static str {
+    match entity {
+        EntityKind::Customer => "id",
+        _ => "customer_id",
+    }
+}
+
+fn ensure_trashable(entity: EntityKind) -> CoreResult<()> {
+    if TRASH_ENTITIES.contains(&entity) {
+        Ok(())
+    } else {
+        Err(CoreError::validation(format!(
+            "{} 不支持移入回收站",
+            entity.table_name()
+        )))
+    }
+}
+
+/// 调整表记录总数缓存
+fn adjust_counter(conn: &Connection, table: &str, delta: i64, now: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE table_counters SET row_count = MAX(row_count + ?2, 0), updated_at = ?3
+         WHERE table_name = ?1",
+        params![table, delta, now],
+    )?;
+    Ok(())
+}
+
+/// 把满足条件（`?3` 为条件参数）且未删除的记录标记为属于批次，返回这些记录的ID
+fn delete_where(
+    conn: &Connection,
+    entity: EntityKind,
+    condition: &str,
+    arg: Uuid,
+    batch: Uuid,
+    now: &str,
+) -> Result<Vec<Uuid>> {
+    let table = entity.table_name();
+    conn.execute(
+        &format!(
+            "UPDATE {} SET deleted_at = ?1, deletion_batch_id = ?2
+             WHERE deleted_at IS NULL AND {}",
+            table, condition
+        ),
+        params![now, DbUuid(batch), DbUuid(arg)],
+    )?;
+    let ids = conn
+        .prepare(&format!(
+            "SELECT id FROM {} WHERE deletion_batch_id = ?1",
+            table
+        ))?
+        .query_map([DbUuid(batch)], |row| get_uuid(row, 0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    adjust_counter(conn, table, -i64::try_from(ids.len())?, now)?;
+    Ok(ids)
+}
+
+/// 恢复满足条件（`?1` 为条件参数）的已删除记录，返回（记录ID，所属客户ID）
+fn restore_where(
+    conn: &Connection,
+    entity: EntityKind,
+    condition: &str,
+    arg: Uuid,
+    now: &str,
+) -> Result<Vec<(Uuid, Uuid)>> {
+    let table = entity.table_name();
+    let rows = conn
+        .prepare(&format!(
+            "SELECT id, {} FROM {} WHERE deleted_at IS NOT NULL AND {}",
+            owner_column(entity),
+            table,
+            condition
+        ))?
+        .query_map([DbUuid(arg)], |row| {
+            Ok((get_uuid(row, 0)?, get_uuid(row, 1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    conn.execute(
+        &format!(
+            "UPDATE {} SET deleted_at = NULL, deletion_batch_id = NULL
+             WHERE deleted_at IS NOT NULL AND {}",
+            table, condition
+        ),
+        [DbUuid(arg)],
+    )?;
+    adjust_counter(conn, table, i64::try_from(rows.len())?, now)?;
+    Ok(rows)
+}
+
+fn record_batch(conn: &Connection, batch: &DeletionBatch) -> Result<()> {
+    conn.execute(
+        "INSERT INTO deletion_batches
+             (id, root_entity, root_id, label, deleted_at, deleted_by, counts)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            DbUuid(batch.id),
+            batch.root_entity.table_name(),
+            DbUuid(batch.root_id),
+            batch.label,
+            time_key(batch.deleted_at),
+            batch.deleted_by,
+            serde_json::to_string(&batch.counts)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn row_to_batch(row: &Row<'_>) -> rusqlite::Result<DeletionBatch> {
+    let root_entity: String = row.get(1)?;
+    let counts: String = row.get(6)?;
+    Ok(DeletionBatch {
+        id: get_uuid(row, 0)?,
+        root_entity: EntityKind::from_table_name(&root_entity).unwrap_or(EntityKind::Customer),
+        root_id: get_uuid(row, 2)?,
+        label: row.get(3)?,
+        deleted_at: get_time(row, 4)?,
+        deleted_by: row.get(5)?,
+        counts: serde_json::from_str(&counts).unwrap_or_default(),
+    })
+}
+
+/// 回收站存储
+#[derive(Clone)]
+pub struct TrashStore {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for TrashStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrashStore").finish_non_exhaustive()
+    }
+}
+
+impl TrashStore {
+    /// 创建回收站存储
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 设置时钟（测试时使用手动时钟）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 记录的显示名称、删除时间和所属客户，记录不存在时为空
+    fn lookup(
+        &self,
+        entity: EntityKind,
+        id: Uuid,
+    ) -> Result<Option<(String, Option<String>, Uuid)>> {
+        let conn = self.connection.get_connection()?;
+        Ok(conn
+            .query_row(
+                &format!(
+                    "SELECT {}, deleted_at, {} FROM {} WHERE id = ?1",
+                    label_column(entity),
+                    owner_column(entity),
+                    entity.table_name()
+                ),
+                [DbUuid(id)],
+                |row| Ok((row.get(0)?, row.get(1)?, get_uuid(row, 2)?)),
+            )
+            .optional()?)
+    }
+
+    /// 移入回收站前的检查，返回显示名称和所属客户
+    fn require_live(&self, entity: EntityKind, id: Uuid) -> CoreResult<(String, Uuid)> {
+        ensure_trashable(entity)?;
+        match self.lookup(entity, id).map_err(to_core)? {
+            None => Err(CoreError::not_found(format!(
+                "{} {}",
+                entity.table_name(),
+                id
+            ))),
+            Some((label, Some(_), _)) => {
+                Err(CoreError::business(format!("“{}”已在回收站中", label)))
+            }
+            Some((label, None, owner)) => Ok((label, owner)),
+        }
+    }
+
+    /// 在一个事务中删除根记录及连带记录并登记批次
+    fn delete_batch(
+        &self,
+        entity: EntityKind,
+        id: Uuid,
+        label: String,
+        owner: Uuid,
+        cascade: &[(EntityKind, &str)],
+        deleted_by: Option<String>,
+    ) -> Result<DeletionBatch> {
+        let deleted_at = self.clock.now();
+        let now = time_key(deleted_at);
+        let batch_id = Uuid::new_v4();
+        let batch = self.connection.with_transaction(|tx| {
+            let mut counts = BTreeMap::new();
+            for (kind, condition, arg) in std::iter::once((entity, "id = ?3", id)).chain(
+                cascade
+                    .iter()
+                    .map(|(kind, condition)| (*kind, *condition, owner)),
+            ) {
+                let ids = delete_where(tx, kind, condition, arg, batch_id, &now)?;
+                if !ids.is_empty() {
+                    counts.insert(kind.table_name().to_string(), ids.len() as u64);
+                }
+            }
+            let batch = DeletionBatch {
+                id: batch_id,
+                root_entity: entity,
+                root_id: id,
+                label,
+                deleted_at,
+                deleted_by,
+                counts,
+            };
+            record_batch(tx, &batch)?;
+            customer_summary::refresh_customer(tx, owner, deleted_at)?;
+            Ok(batch)
+        })?;
+        info!(
+            "“{}”已移入回收站（批次 {}，{:?}）",
+            batch.label, batch.id, batch.counts
+        );
+        Ok(batch)
+    }
+
+    /// 恢复记录所在的批次；记录没有批次时只恢复该记录
+    fn restore_rows(&self, entity: EntityKind, id: Uuid) -> CoreResult<RestoreReport> {
+        ensure_trashable(entity)?;
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        let found: Option<(Option<String>, Option<DbUuid>)> = conn
+            .query_row(
+                &format!(
+                    "SELECT deleted_at, deletion_batch_id FROM {} WHERE id = ?1",
+                    entity.table_name()
+                ),
+                [DbUuid(id)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| to_core(e.into()))?;
+        drop(conn);
+        let batch_id = match found {
+            None => {
+                return Err(CoreError::not_found(format!(
+                    "{} {}",
+                    entity.table_name(),
+                    id
+                )))
+            }
+            Some((None, _)) => return Err(CoreError::business("该记录不在回收站中")),
+            Some((Some(_), batch)) => batch.map(Uuid::from),
+        };
+
+        let restored_at = self.clock.now();
+        let now = time_key(restored_at);
+        let report = self
+            .connection
+            .with_transaction(|tx| {
+                let mut restored = Vec::new();
+                let mut owners = Vec::new();
+                let targets: Vec<(EntityKind, &str, Uuid)> = match batch_id {
+                    Some(batch) => TRASH_ENTITIES
+                        .iter()
+                        .map(|kind| (*kind, "deletion_batch_id = ?1", batch))
+                        .collect(),
+                    None => vec![(entity, "id = ?1", id)],
+                };
+                for (kind, condition, arg) in targets {
+                    for (row_id, owner) in restore_where(tx, kind, condition, arg, &now)? {
+                        restored.push((kind, row_id));
+                        if !owners.contains(&owner) {
+                            owners.push(owner);
+                        }
+                    }
+                }
+                if let Some(batch) = batch_id {
+                    tx.execute(
+                        "DELETE FROM deletion_batches WHERE id = ?1",
+                        [DbUuid(batch)],
+                    )?;
+                }
+                for owner in owners {
+                    customer_summary::refresh_customer(tx, owner, restored_at)?;
+                }
+                Ok(RestoreReport { batch_id, restored })
+            })
+            .map_err(to_core)?;
+        info!("已从回收站恢复 {:?}", report.counts());
+        Ok(report)
+    }
+
+    /// 回收站中的记录（删除时间新的在前）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn items(&self) -> Result<Vec<TrashItem>> {
+        let conn = self.connection.get_connection()?;
+        let mut items = Vec::new();
+        for entity in TRASH_ENTITIES {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT id, {}, deleted_at, deletion_batch_id FROM {}
+                 WHERE deleted_at IS NOT NULL",
+                label_column(entity),
+                entity.table_name()
+            ))?;
+            let rows = stmt.query_map([], |row| {
+                Ok(TrashItem {
+                    entity,
+                    id: get_uuid(row, 0)?,
+                    label: row.get(1)?,
+                    deleted_at: get_time(row, 2)?,
+                    batch_id: row.get::<_, Option<DbUuid>>(3)?.map(Uuid::from),
+                })
+            })?;
+            for item in rows {
+                items.push(item.context("无法读取回收站记录")?);
+            }
+        }
+        items.sort_by_key(|item| Reverse(item.deleted_at));
+        Ok(items)
+    }
+
+    /// 仍有记录在回收站中的删除批次（删除时间新的在前）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn batches(&self) -> Result<Vec<DeletionBatch>> {
+        let live = TRASH_ENTITIES
+            .iter()
+            .map(|entity| {
+                format!(
+                    "EXISTS (SELECT 1 FROM {} WHERE deletion_batch_id = b.id)",
+                    entity.table_name()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let conn = self.connection.get_connection()?;
+        let batches = conn
+            .prepare(&format!(
+                "SELECT id, root_entity, root_id, label, deleted_at, deleted_by, counts
+                 FROM deletion_batches b WHERE {} ORDER BY deleted_at DESC",
+                live
+            ))?
+            .query_map([], row_to_batch)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(batches)
+    }
+}
+
+#[async_trait]
+impl TrashService for TrashStore {
+    async fn soft_delete_customer(
+        &self,
+        id: Uuid,
+        deleted_by: Option<String>,
+    ) -> CoreResult<DeletionBatch> {
+        let (label, owner) = self.require_live(EntityKind::Customer, id)?;
+        self.delete_batch(
+            EntityKind::Customer,
+            id,
+            label,
+            owner,
+            &CUSTOMER_CASCADE,
+            deleted_by,
+        )
+        .map_err(to_core)
+    }
+
+    async fn soft_delete(
+        &self,
+        entity: EntityKind,
+        id: Uuid,
+        deleted_by: Option<String>,
+    ) -> CoreResult<DeletionBatch> {
+        let (label, owner) = self.require_live(entity, id)?;
+        let cascade: &[(EntityKind, &str)] = if entity == EntityKind::Customer {
+            &CUSTOMER_CASCADE
+        } else {
+            &[]
+        };
+        self.delete_batch(entity, id, label, owner, cascade, deleted_by)
+            .map_err(to_core)
+    }
+
+    async fn restore(&self, entity: EntityKind, id: Uuid) -> CoreResult<RestoreReport> {
+        self.restore_rows(entity, id)
+    }
+
+    async fn trash_items(&self) -> CoreResult<Vec<TrashItem>> {
+        self.items().map_err(to_core)
+    }
+
+    async fn deletion_batches(&self) -> CoreResult<Vec<DeletionBatch>> {
+        self.batches().map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use chrono::{Duration, TimeZone, Utc};
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn create_test_store() -> (TempDir, DatabaseConnection, Arc<ManualClock>, TrashStore) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap(),
+        ));
+        let store = TrashStore::new(connection.clone()).with_clock(clock.clone());
+        (temp_dir, connection, clock, store)
+    }
+
+    fn insert_customer(connection: &DatabaseConnection, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, ?2, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+                params![DbUuid(id), name],
+            )
+            .unwrap();
+        id
+    }
+
+    fn insert_task(connection: &DatabaseConnection, customer: Uuid, status: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO tasks (id, customer_id, title, status, created_at, updated_at)
+                 VALUES (?1, ?2, '回访', ?3, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+                params![DbUuid(id), DbUuid(customer), status],
+            )
+            .unwrap();
+        id
+    }
+
+    fn insert_quote(connection: &DatabaseConnection, customer: Uuid, status: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO quotes
+                     (id, customer_id, title, total_amount, status, created_at, updated_at)
+                 VALUES (?1, ?2, '板材报价', 100.0, ?3,
+                         '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+                params![DbUuid(id), DbUuid(customer), status],
+            )
+            .unwrap();
+        id
+    }
+
+    fn is_deleted(connection: &DatabaseConnection, entity: EntityKind, id: Uuid) -> bool {
+        connection
+            .query_row(
+                &format!(
+                    "SELECT deleted_at IS NOT NULL FROM {} WHERE id = ?1",
+                    entity.table_name()
+                ),
+                [DbUuid(id)],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_restore_is_scoped_to_batch() {
+        let (_dir, connection, clock, store) = create_test_store();
+        let customer = insert_customer(&connection, "华美家具");
+        let open_task = insert_task(&connection, customer, "pending");
+        let earlier_task = insert_task(&connection, customer, "pending");
+        let later_task = insert_task(&connection, customer, "completed");
+        let draft = insert_quote(&connection, customer, "draft");
+        let sent = insert_quote(&connection, customer, "sent");
+
+        // 先单独删除一个任务
+        let earlier = store
+            .soft_delete(EntityKind::Task, earlier_task, Some("张三".to_string()))
+            .await
+            .unwrap();
+
+        clock.advance(Duration::hours(1));
+        let batch = store
+            .soft_delete_customer(customer, Some("李四".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(batch.label, "华美家具");
+        assert_eq!(
+            batch.counts,
+            BTreeMap::from([
+                ("customers".to_string(), 1),
+                ("quotes".to_string(), 1),
+                ("tasks".to_string(), 1),
+            ])
+        );
+        assert!(is_deleted(&connection, EntityKind::Quote, draft));
+        assert!(!is_deleted(&connection, EntityKind::Quote, sent));
+        assert!(!is_deleted(&connection, EntityKind::Task, later_task));
+        assert!(matches!(
+            store.soft_delete_customer(customer, None).await,
+            Err(CoreError::Business(_))
+        ));
+
+        // 客户删除之后又删除了一个已完成的任务
+        clock.advance(Duration::hours(1));
+        store
+            .soft_delete(EntityKind::Task, later_task, None)
+            .await
+            .unwrap();
+
+        let items = store.trash_items().await.unwrap();
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[0].id, later_task);
+        assert_eq!(store.deletion_batches().await.unwrap().len(), 3);
+
+        // 从批次中的任一条记录恢复都恢复整批
+        let report = store.restore(EntityKind::Task, open_task).await.unwrap();
+        assert_eq!(report.batch_id, Some(batch.id));
+        assert_eq!(report.counts(), batch.counts);
+        for (entity, id) in [
+            (EntityKind::Customer, customer),
+            (EntityKind::Task, open_task),
+            (EntityKind::Quote, draft),
+        ] {
+            assert!(!is_deleted(&connection, entity, id));
+        }
+        // 更早和更晚单独删除的任务保持删除
+        assert!(is_deleted(&connection, EntityKind::Task, earlier_task));
+        assert!(is_deleted(&connection, EntityKind::Task, later_task));
+        let remaining: Vec<Uuid> = store
+            .deletion_batches()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|b| b.id)
+            .collect();
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.contains(&earlier.id));
+
+        assert!(matches!(
+            store.restore(EntityKind::Customer, customer).await,
+            Err(CoreError::Business(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unbatched_rows_restore_individually() {
+        let (_dir, connection, _clock, store) = create_test_store();
+        let customer = insert_customer(&connection, "老客户");
+        let (a, b) = (
+            insert_task(&connection, customer, "pending"),
+            insert_task(&connection, customer, "pending"),
+        );
+        // 升级前的软删除没有批次
+        connection
+            .execute(
+                "UPDATE tasks SET deleted_at = '2024-01-02T00:00:00Z' WHERE customer_id = ?1",
+                [DbUuid(customer)],
+            )
+            .unwrap();
+
+        let report = store.restore(EntityKind::Task, a).await.unwrap();
+        assert_eq!(report.batch_id, None);
+        assert_eq!(report.restored, vec![(EntityKind::Task, a)]);
+        assert!(is_deleted(&connection, EntityKind::Task, b));
+
+        assert!(matches!(
+            store.soft_delete(EntityKind::Supplier, a, None).await,
+            Err(CoreError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,581 @@
+//! 用户账号存储
+//!
+//! 基于 `users` 表实现用户服务，密码以argon2哈希保存。
+//!
+//! 停用用户时在同一事务中把其未结束的任务、销售机会和客户转交给接手的用户。
+//! 转交不逐条写审计，每张表只在 `user_reassignment_audit` 中记一条汇总（含条数），
+//! 每条被转交的记录登记一个更新事件到事件发件箱，打开中的界面据此刷新。
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use minicrm_core::{
+    CoreError, CoreResult, DomainEvent, EntityKind, EventEnvelope, NewUser, ReassignmentCounts,
+    User, UserDeactivation, UserRole, UserService,
+};
+use rusqlite::{params, Connection, Row};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::db_uuid::get_uuid;
+use crate::database::time::{get_time, time_key};
+use crate::database::{DatabaseConnection, DbUuid};
+use crate::repository::outbox;
+use crate::repository::support::to_core;
+use crate::security::{hash_password, verify_password};
+
+const USER_COLUMNS: &str =
+    "id, username, display_name, role, password_hash, active, created_at, updated_at";
+
+/// 停用用户时转交的记录（实体类型，负责人列，未结束记录的条件）
+const REASSIGNED_RECORDS: &[(EntityKind, &str, &str)] = &[
+    (
+        EntityKind::Task,
+        "assigned_to",
+        "status IN ('pending', 'in_progress') AND deleted_at IS NULL",
+    ),
+    (
+        EntityKind::Opportunity,
+        "owner_id",
+        "stage NOT IN ('won', 'lost') AND deleted_at IS NULL",
+    ),
+    (EntityKind::Customer, "owner_id", "deleted_at IS NULL"),
+];
+
+/// 用户服务实现
+#[derive(Debug, Clone)]
+pub struct SqliteUserService {
+    connection: DatabaseConnection,
+    allow_unassigned: bool,
+}
+
+fn row_to_user(row: &Row<'_>) -> rusqlite::Result<User> {
+    let role: String = row.get(3)?;
+    Ok(User {
+        id: get_uuid(row, 0)?,
+        username: row.get(1)?,
+        display_name: row.get(2)?,
+        role: UserRole::parse(&role).unwrap_or(UserRole::Viewer),
+        password_hash: row.get(4)?,
+        active: row.get(5)?,
+        created_at: get_time(row, 6)?,
+        updated_at: get_time(row, 7)?,
+    })
+}
+
+impl SqliteUserService {
+    /// 创建用户服务
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            connection,
+            allow_unassigned: false,
+        }
+    }
+
+    /// 是否允许停用用户时不指定接手的用户（其记录改为未分配），默认不允许
+    pub fn with_unassigned_allowed(mut self, allowed: bool) -> Self {
+        self.allow_unassigned = allowed;
+        self
+    }
+
+    /// 是否已创建任何用户（首次启动时为 `false`）
+    pub fn has_users(&self) -> Result<bool> {
+        let count: i64 = self
+            .connection
+            .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+
+    fn find_by_id(&self, id: Uuid) -> Result<Option<User>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!("SELECT {} FROM users WHERE id = ?1", USER_COLUMNS),
+                [DbUuid(id)],
+                row_to_user,
+            )?
+            .into_iter()
+            .next())
+    }
+
+    fn find_by_username(&self, username: &str) -> Result<Option<User>> {
+        Ok(self
+            .connection
+            .query_map(
+                &format!("SELECT {} FROM users WHERE username = ?1", USER_COLUMNS),
+                [username.trim()],
+                row_to_user,
+            )?
+            .into_iter()
+            .next())
+    }
+
+    /// 用户名下未结束的记录ID
+    fn open_records(
+        conn: &Connection,
+        kind: EntityKind,
+        column: &str,
+        condition: &str,
+        user_id: Uuid,
+    ) -> Result<Vec<Uuid>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM {} WHERE {} = ?1 AND {} ORDER BY id",
+            kind.table_name(),
+            column,
+            condition
+        ))?;
+        let ids = stmt
+            .query_map([DbUuid(user_id)], |row| get_uuid(row, 0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(ids)
+    }
+
+    fn set_count(counts: &mut ReassignmentCounts, kind: EntityKind, count: usize) {
+        let count = u32::try_from(count).unwrap_or(u32::MAX);
+        match kind {
+            EntityKind::Task => counts.tasks = count,
+            EntityKind::Opportunity => counts.opportunities = count,
+            _ => counts.customers = count,
+        }
+    }
+
+    /// 检查接手的用户：必须存在、已启用且不是被停用的用户本人
+    fn check_target(&self, id: Uuid, target: Uuid) -> CoreResult<()> {
+        if target == id {
+            return Err(CoreError::validation("不能转交给要停用的用户本人"));
+        }
+        let target = self
+            .find_by_id(target)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::validation("接手的用户不存在"))?;
+        if !target.active {
+            return Err(CoreError::validation(format!(
+                "接手的用户已停用: {}",
+                target.username
+            )));
+        }
+        Ok(())
+    }
+
+    fn validate_password(password: &str) -> CoreResult<()> {
+        if password.chars().count() < 6 {
+            return Err(CoreError::validation("密码至少需要6个字符"));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserService for SqliteUserService {
+    async fn create_user(&self, user: NewUser) -> CoreResult<User> {
+        let username = user.username.trim().to_string();
+        if username.is_empty() {
+            return Err(CoreError::validation("用户名不能为空"));
+        }
+        Self::validate_password(&user.password)?;
+        if self.find_by_username(&username).map_err(to_core)?.is_some() {
+            return Err(CoreError::conflict(format!("用户名已存在: {}", username)));
+        }
+
+        let now = Utc::now();
+        let created = User {
+            id: Uuid::new_v4(),
+            username,
+            display_name: user.display_name,
+            role: user.role,
+            password_hash: hash_password(&user.password).map_err(to_core)?,
+            active: true,
+            created_at: now,
+            updated_at: now,
+        };
+        self.connection
+            .execute(
+                &format!(
+                    "INSERT INTO users ({}) VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?6)",
+                    USER_COLUMNS
+                ),
+                rusqlite::params![
+                    DbUuid(created.id),
+                    created.username,
+                    created.display_name,
+                    created.role.as_str(),
+                    created.password_hash,
+                    now.to_rfc3339(),
+                ],
+            )
+            .map_err(to_core)?;
+
+        info!("已创建用户: {} ({})", created.username, created.role.as_str());
+        Ok(created)
+    }
+
+    async fn deactivation_preview(&self, id: Uuid) -> CoreResult<ReassignmentCounts> {
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        let mut counts = ReassignmentCounts::default();
+        for (kind, column, condition) in REASSIGNED_RECORDS {
+            let ids = Self::open_records(&conn, *kind, column, condition, id).map_err(to_core)?;
+            Self::set_count(&mut counts, *kind, ids.len());
+        }
+        Ok(counts)
+    }
+
+    async fn deactivate_user(
+        &self,
+        id: Uuid,
+        reassign_to: Option<Uuid>,
+    ) -> CoreResult<UserDeactivation> {
+        if self.find_by_id(id).map_err(to_core)?.is_none() {
+            return Err(CoreError::not_found(format!("用户 {}", id)));
+        }
+        match reassign_to {
+            Some(target) => self.check_target(id, target)?,
+            None if !self.allow_unassigned && !self.deactivation_preview(id).await?.is_empty() => {
+                return Err(CoreError::validation(
+                    "该用户还有未结束的任务、销售机会或客户，请选择接手的用户",
+                ));
+            }
+            None => {}
+        }
+
+        let now = Utc::now();
+        let at = time_key(now);
+        let reassigned = self
+            .connection
+            .with_transaction(|tx| {
+                let mut counts = ReassignmentCounts::default();
+                for (kind, column, condition) in REASSIGNED_RECORDS {
+                    let ids = Self::open_records(tx, *kind, column, condition, id)?;
+                    Self::set_count(&mut counts, *kind, ids.len());
+                    if ids.is_empty() {
+                        continue;
+                    }
+                    let table = kind.table_name();
+                    tx.execute(
+                        &format!(
+                            "UPDATE {} SET {} = ?2, updated_at = ?3 WHERE {} = ?1 AND {}",
+                            table, column, column, condition
+                        ),
+                        params![DbUuid(id), reassign_to.map(DbUuid), at],
+                    )?;
+                    tx.execute(
+                        "INSERT INTO user_reassignment_audit
+                             (id, user_id, reassigned_to, entity_type, record_count, created_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![
+                            DbUuid(Uuid::new_v4()),
+                            DbUuid(id),
+                            reassign_to.map(DbUuid),
+                            table,
+                            ids.len() as i64,
+                            at
+                        ],
+                    )?;
+                    for record in ids {
+                        let event = DomainEvent::EntityUpdated {
+                            entity: *kind,
+                            id: record,
+                        };
+                        outbox::record(tx, &EventEnvelope::at(event, now))?;
+                    }
+                }
+                tx.execute(
+                    "UPDATE users SET active = 0, updated_at = ?2 WHERE id = ?1",
+                    params![DbUuid(id), now.to_rfc3339()],
+                )?;
+                Ok(counts)
+            })
+            .map_err(to_core)?;
+
+        let user = self
+            .find_by_id(id)
+            .map_err(to_core)?
+            .ok_or_else(|| CoreError::not_found(format!("用户 {}", id)))?;
+        info!(
+            "已停用用户 {}，转交任务 {} 条、销售机会 {} 条、客户 {} 个",
+            user.username, reassigned.tasks, reassigned.opportunities, reassigned.customers
+        );
+        Ok(UserDeactivation {
+            user,
+            reassigned_to: reassign_to,
+            reassigned,
+        })
+    }
+
+    async fn change_password(&self, id: Uuid, new_password: &str) -> CoreResult<()> {
+        Self::validate_password(new_password)?;
+        let hash = hash_password(new_password).map_err(to_core)?;
+        let changed = self
+            .connection
+            .execute(
+                "UPDATE users SET password_hash = ?2, updated_at = ?3 WHERE id = ?1",
+                rusqlite::params![DbUuid(id), hash, Utc::now().to_rfc3339()],
+            )
+            .map_err(to_core)?;
+        if changed == 0 {
+            return Err(CoreError::not_found(format!("用户 {}", id)));
+        }
+        Ok(())
+    }
+
+    async fn authenticate(&self, username: &str, password: &str) -> CoreResult<User> {
+        // 不区分“用户不存在”和“密码错误”，避免泄露账号信息
+        let denied = || CoreError::permission("用户名或密码错误");
+
+        let user = self
+            .find_by_username(username)
+            .map_err(to_core)?
+            .ok_or_else(denied)?;
+        if !verify_password(password, &user.password_hash).map_err(to_core)? {
+            return Err(denied());
+        }
+        if !user.active {
+            return Err(CoreError::permission(format!("用户已停用: {}", user.username)));
+        }
+        Ok(user)
+    }
+
+    async fn list_users(&self) -> CoreResult<Vec<User>> {
+        self.connection
+            .query_map(
+                &format!("SELECT {} FROM users ORDER BY username", USER_COLUMNS),
+                [],
+                row_to_user,
+            )
+            .map_err(to_core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use tempfile::TempDir;
+
+    fn create_test_service() -> (TempDir, SqliteUserService) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+
+        (temp_dir, SqliteUserService::new(connection))
+    }
+
+    fn new_user(username: &str, role: UserRole) -> NewUser {
+        NewUser {
+            username: username.to_string(),
+            display_name: format!("{}（测试）", username),
+            role,
+            password: "secret-123".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authenticate() {
+        let (_dir, service) = create_test_service();
+        assert!(!service.has_users().unwrap());
+        let created = service
+            .create_user(new_user("zhangsan", UserRole::Sales))
+            .await
+            .unwrap();
+        assert!(created.password_hash.starts_with("$argon2"));
+
+        let user = service.authenticate("zhangsan", "secret-123").await.unwrap();
+        assert_eq!(user.id, created.id);
+        assert_eq!(user.role, UserRole::Sales);
+
+        let wrong = service.authenticate("zhangsan", "secret-124").await;
+        assert!(matches!(wrong, Err(CoreError::Permission(_))));
+        let unknown = service.authenticate("lisi", "secret-123").await;
+        assert!(matches!(unknown, Err(CoreError::Permission(_))));
+
+        service
+            .change_password(created.id, "new-secret")
+            .await
+            .unwrap();
+        assert!(service.authenticate("zhangsan", "secret-123").await.is_err());
+        assert!(service.authenticate("zhangsan", "new-secret").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_deactivated_user_cannot_log_in() {
+        let (_dir, service) = create_test_service();
+        let created = service
+            .create_user(new_user("wangwu", UserRole::Viewer))
+            .await
+            .unwrap();
+
+        let deactivated = service.deactivate_user(created.id, None).await.unwrap();
+        assert!(!deactivated.user.active);
+
+        let result = service.authenticate("wangwu", "secret-123").await;
+        assert!(matches!(result, Err(CoreError::Permission(_))));
+    }
+
+    /// 写入一条属于 `owner` 的记录，返回记录ID
+    fn owned(service: &SqliteUserService, kind: EntityKind, state: &str, owner: Uuid) -> Uuid {
+        let id = Uuid::new_v4();
+        let now = "2024-07-01T09:00:00.000000Z";
+        let sql = match kind {
+            EntityKind::Task => {
+                "INSERT INTO tasks (id, customer_id, title, status, assigned_to, created_at,
+                                    updated_at)
+                 VALUES (?1, ?1, '回访', ?2, ?3, ?4, ?4)"
+            }
+            EntityKind::Opportunity => {
+                "INSERT INTO opportunities (id, customer_id, title, stage, owner_id, created_at,
+                                            updated_at)
+                 VALUES (?1, ?1, '展厅样品柜', ?2, ?3, ?4, ?4)"
+            }
+            _ => {
+                "INSERT INTO customers (id, name, deleted_at, owner_id, created_at, updated_at)
+                 VALUES (?1, '华美装饰', NULLIF(?2, ''), ?3, ?4, ?4)"
+            }
+        };
+        if kind != EntityKind::Customer {
+            // 任务和商机挂在同ID的客户下（客户没有负责人，不计入转交）
+            service
+                .connection
+                .execute(
+                    "INSERT INTO customers (id, name, created_at, updated_at)
+                     VALUES (?1, '华美装饰', ?2, ?2)",
+                    params![DbUuid(id), now],
+                )
+                .unwrap();
+        }
+        service
+            .connection
+            .execute(sql, params![DbUuid(id), state, DbUuid(owner), now])
+            .unwrap();
+        id
+    }
+
+    fn owner_of(service: &SqliteUserService, kind: EntityKind, id: Uuid) -> Option<Uuid> {
+        let column = if kind == EntityKind::Task {
+            "assigned_to"
+        } else {
+            "owner_id"
+        };
+        service
+            .connection
+            .query_row(
+                &format!("SELECT {} FROM {} WHERE id = ?1", column, kind.table_name()),
+                [DbUuid(id)],
+                |row| row.get::<_, Option<DbUuid>>(0),
+            )
+            .unwrap()
+            .map(Uuid::from)
+    }
+
+    #[tokio::test]
+    async fn test_deactivation_reassigns_open_records() {
+        let (_dir, service) = create_test_service();
+        let leaving = service.create_user(new_user("leaving", UserRole::Sales)).await.unwrap();
+        let target = service.create_user(new_user("target", UserRole::Sales)).await.unwrap();
+
+        let open_task = owned(&service, EntityKind::Task, "in_progress", leaving.id);
+        let done_task = owned(&service, EntityKind::Task, "completed", leaving.id);
+        let open_opportunity = owned(&service, EntityKind::Opportunity, "quoted", leaving.id);
+        let won_opportunity = owned(&service, EntityKind::Opportunity, "won", leaving.id);
+        let customer = owned(&service, EntityKind::Customer, "", leaving.id);
+        let deleted_customer = owned(
+            &service,
+            EntityKind::Customer,
+            "2024-07-01T10:00:00.000000Z",
+            leaving.id,
+        );
+
+        let preview = service.deactivation_preview(leaving.id).await.unwrap();
+        assert_eq!(
+            preview,
+            ReassignmentCounts {
+                tasks: 1,
+                opportunities: 1,
+                customers: 1,
+            }
+        );
+
+        let result = service
+            .deactivate_user(leaving.id, Some(target.id))
+            .await
+            .unwrap();
+        assert!(!result.user.active);
+        assert_eq!(result.reassigned, preview);
+        for (kind, id) in [
+            (EntityKind::Task, open_task),
+            (EntityKind::Opportunity, open_opportunity),
+            (EntityKind::Customer, customer),
+        ] {
+            assert_eq!(owner_of(&service, kind, id), Some(target.id));
+        }
+
+        // 已结束或已删除的记录保持原负责人
+        for (kind, id) in [
+            (EntityKind::Task, done_task),
+            (EntityKind::Opportunity, won_opportunity),
+            (EntityKind::Customer, deleted_customer),
+        ] {
+            assert_eq!(owner_of(&service, kind, id), Some(leaving.id));
+        }
+
+        // 每张表一条汇总审计，每条转交的记录一个更新事件
+        let audit: Vec<(String, i64)> = service
+            .connection
+            .query_map(
+                "SELECT entity_type, record_count FROM user_reassignment_audit
+                 ORDER BY entity_type",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            audit,
+            vec![
+                ("customers".to_string(), 1),
+                ("opportunities".to_string(), 1),
+                ("tasks".to_string(), 1)
+            ]
+        );
+        let events: i64 = service
+            .connection
+            .query_row(
+                "SELECT COUNT(*) FROM event_outbox WHERE event_type = 'entity_updated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(events, 3);
+    }
+
+    #[tokio::test]
+    async fn test_inactive_target_is_rejected() {
+        let (_dir, service) = create_test_service();
+        let leaving = service.create_user(new_user("leaving", UserRole::Sales)).await.unwrap();
+        let retired = service.create_user(new_user("retired", UserRole::Sales)).await.unwrap();
+        service.deactivate_user(retired.id, None).await.unwrap();
+        let task = owned(&service, EntityKind::Task, "pending", leaving.id);
+
+        for target in [Some(retired.id), Some(leaving.id), Some(Uuid::new_v4()), None] {
+            assert!(matches!(
+                service.deactivate_user(leaving.id, target).await,
+                Err(CoreError::Validation(_))
+            ));
+        }
+        assert!(service.authenticate("leaving", "secret-123").await.is_ok());
+        assert_eq!(owner_of(&service, EntityKind::Task, task), Some(leaving.id));
+
+        // 策略允许时不指定接手的用户，记录改为未分配
+        let service = service.with_unassigned_allowed(true);
+        let result = service.deactivate_user(leaving.id, None).await.unwrap();
+        assert_eq!(result.reassigned.tasks, 1);
+        assert_eq!(owner_of(&service, EntityKind::Task, task), None);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_username_conflicts() {
+        let (_dir, service) = create_test_service();
+        service
+            .create_user(new_user("admin", UserRole::Admin))
+            .await
+            .unwrap();
+        let duplicate = service.create_user(new_user("ADMIN", UserRole::Viewer)).await;
+        assert!(matches!(duplicate, Err(CoreError::Conflict(_))));
+    }
+}
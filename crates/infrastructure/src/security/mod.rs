@@ -0,0 +1,17 @@
+//! 安全模块
+//!
+//! 提供应用口令的存储与校验，以及SMTP密码、API令牌等密钥的存储。
+
+pub mod passcode;
+pub mod password;
+pub mod secrets;
+
+// 重新导出主要类型
+pub use passcode::{FilePasscodeStore, PASSCODE_FILE_NAME};
+pub use password::{hash_password, verify_password};
+#[cfg(feature = "keyring")]
+pub use secrets::KeyringSecretStore;
+pub use secrets::{
+    default_secret_store, parse_secret_ref, secret_ref, FileSecretStore, SecretStore,
+    SECRET_REF_PREFIX,
+};
@@ -0,0 +1,123 @@
+//! 应用口令存储
+//!
+//! 口令以argon2哈希形式保存在配置目录下的单独文件中，从不保存明文。
+//! 忘记口令时删除该文件即可取消应用锁，数据库不受影响。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use minicrm_core::{CoreError, CoreResult, PasscodeVerifier};
+use tracing::info;
+
+use super::password::{hash_password, verify_password};
+
+/// 口令文件名（删除此文件即可重置口令）
+pub const PASSCODE_FILE_NAME: &str = "app-passcode.DELETE-THIS-FILE-TO-RESET.argon2";
+
+/// 基于文件的口令存储
+#[derive(Debug, Clone)]
+pub struct FilePasscodeStore {
+    path: PathBuf,
+}
+
+impl FilePasscodeStore {
+    /// 在配置目录下创建口令存储
+    pub fn in_dir<P: AsRef<Path>>(config_dir: P) -> Self {
+        Self {
+            path: config_dir.as_ref().join(PASSCODE_FILE_NAME),
+        }
+    }
+
+    /// 口令文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 设置新口令
+    ///
+    /// # Errors
+    ///
+    /// 口令为空、哈希失败或文件写入失败时返回错误。
+    pub fn set_passcode(&self, passcode: &str) -> Result<()> {
+        if passcode.trim().is_empty() {
+            return Err(anyhow!("口令不能为空"));
+        }
+
+        let hash = hash_password(passcode)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建配置目录: {}", parent.display()))?;
+        }
+        fs::write(&self.path, hash)
+            .with_context(|| format!("无法写入口令文件: {}", self.path.display()))?;
+        info!("应用口令已更新");
+        Ok(())
+    }
+
+    /// 移除口令（取消应用锁）
+    ///
+    /// # Errors
+    ///
+    /// 文件存在但无法删除时返回错误。
+    pub fn clear(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)
+                .with_context(|| format!("无法删除口令文件: {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl PasscodeVerifier for FilePasscodeStore {
+    fn is_configured(&self) -> bool {
+        self.path.is_file()
+    }
+
+    fn verify(&self, passcode: &str) -> CoreResult<bool> {
+        let stored = match fs::read_to_string(&self.path) {
+            Ok(stored) => stored,
+            // 口令文件已被删除：视为未设置口令
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(CoreError::configuration(format!("读取口令文件失败: {}", e))),
+        };
+        verify_password(passcode, &stored)
+            .map_err(|e| CoreError::configuration(format!("口令文件已损坏: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hash_verification() -> Result<()> {
+        let dir = tempdir()?;
+        let store = FilePasscodeStore::in_dir(dir.path());
+        assert!(!store.is_configured());
+
+        store.set_passcode("2468")?;
+        assert!(store.is_configured());
+
+        let stored = fs::read_to_string(store.path())?;
+        assert!(stored.starts_with("$argon2"));
+        assert!(!stored.contains("2468"));
+
+        assert!(store.verify("2468")?);
+        assert!(!store.verify("2469")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deleting_file_resets_passcode() -> Result<()> {
+        let dir = tempdir()?;
+        let store = FilePasscodeStore::in_dir(dir.path());
+        store.set_passcode("2468")?;
+
+        fs::remove_file(dir.path().join(PASSCODE_FILE_NAME))?;
+        assert!(!store.is_configured());
+        Ok(())
+    }
+}
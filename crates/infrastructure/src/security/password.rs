@@ -0,0 +1,32 @@
+//! 密码哈希
+//!
+//! 统一使用argon2（默认参数、随机盐）生成和校验哈希。
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, SaltString};
+use argon2::{Argon2, PasswordHasher, PasswordVerifier};
+
+/// 生成密码哈希（PHC字符串格式）
+///
+/// # Errors
+///
+/// 哈希计算失败时返回错误。
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("密码哈希失败: {}", e))?
+        .to_string())
+}
+
+/// 校验密码与哈希是否匹配
+///
+/// # Errors
+///
+/// 哈希字符串格式无效时返回错误。
+pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
+    let parsed = PasswordHash::new(hash.trim()).map_err(|e| anyhow!("密码哈希格式无效: {}", e))?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok())
+}
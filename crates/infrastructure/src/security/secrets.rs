@@ -0,0 +1,383 @@
+//! 密钥存储
+//!
+//! SMTP密码、API令牌、应用密钥等不写入配置文件，配置中只保存形如 `keyring:smtp_password`
+//! 的引用，实际值放在密钥存储中：
+//!
+//! - [`KeyringSecretStore`]：系统钥匙串（Windows凭据管理器、macOS钥匙串、Linux Secret
+//!   Service），服务名为 `MiniCRM`，需启用 `keyring` 特性；
+//! - [`FileSecretStore`]：没有钥匙串的平台上的后备方案，加密后保存在配置目录下。
+//!
+//! 文件后备方案的密钥由本机标识派生，**只是混淆，不是可靠的加密**：能读取本机文件的人
+//! 同样能算出密钥。它只防止密码以明文出现在配置文件、备份和截图里。
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// 系统钥匙串中的服务名
+pub const KEYRING_SERVICE: &str = "MiniCRM";
+
+/// 配置中密钥引用的前缀
+pub const SECRET_REF_PREFIX: &str = "keyring:";
+
+/// 文件后备方案的文件名
+pub const SECRETS_FILE_NAME: &str = "secrets.obfuscated.json";
+
+/// 密钥存储
+pub trait SecretStore: Debug + Send + Sync {
+    /// 保存密钥（已存在时覆盖）
+    ///
+    /// # Errors
+    ///
+    /// 名称无效或存储写入失败时返回错误。
+    fn set_secret(&self, name: &str, value: &str) -> Result<()>;
+
+    /// 读取密钥，不存在时返回 `None`
+    ///
+    /// # Errors
+    ///
+    /// 存储无法读取或内容已损坏时返回错误。
+    fn get_secret(&self, name: &str) -> Result<Option<String>>;
+
+    /// 删除密钥，返回之前是否存在
+    ///
+    /// # Errors
+    ///
+    /// 存储写入失败时返回错误。
+    fn delete_secret(&self, name: &str) -> Result<bool>;
+}
+
+/// 生成配置中保存的密钥引用
+pub fn secret_ref(name: &str) -> String {
+    format!("{}{}", SECRET_REF_PREFIX, name)
+}
+
+/// 解析密钥引用，不是引用时返回 `None`
+pub fn parse_secret_ref(value: &str) -> Option<&str> {
+    value
+        .strip_prefix(SECRET_REF_PREFIX)
+        .filter(|name| !name.is_empty())
+}
+
+/// 校验密钥名称（字母、数字、下划线、点和短横线）
+fn checked_name(name: &str) -> Result<&str> {
+    if !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+    {
+        Ok(name)
+    } else {
+        Err(anyhow!("无效的密钥名称: {}", name))
+    }
+}
+
+/// 选择本机可用的密钥存储
+///
+/// 启用 `keyring` 特性且系统钥匙串可用时使用钥匙串，否则使用配置目录下的文件。
+pub fn default_secret_store<P: AsRef<Path>>(config_dir: P) -> Arc<dyn SecretStore> {
+    #[cfg(feature = "keyring")]
+    {
+        if KeyringSecretStore::is_available() {
+            return Arc::new(KeyringSecretStore::new());
+        }
+        tracing::warn!("系统钥匙串不可用，密钥改为混淆后保存在配置目录");
+    }
+    Arc::new(FileSecretStore::in_dir(config_dir))
+}
+
+/// 基于系统钥匙串的密钥存储
+#[cfg(feature = "keyring")]
+#[derive(Debug, Clone, Default)]
+pub struct KeyringSecretStore;
+
+#[cfg(feature = "keyring")]
+impl KeyringSecretStore {
+    /// 创建存储
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 系统钥匙串是否可用（查询一个不存在的条目能正常返回“无此条目”）
+    pub fn is_available() -> bool {
+        keyring::Entry::new(KEYRING_SERVICE, "__probe__")
+            .is_ok_and(|entry| matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)))
+    }
+
+    fn entry(name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, checked_name(name)?)
+            .with_context(|| format!("无法访问系统钥匙串: {}", name))
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl SecretStore for KeyringSecretStore {
+    fn set_secret(&self, name: &str, value: &str) -> Result<()> {
+        Self::entry(name)?
+            .set_password(value)
+            .with_context(|| format!("无法写入系统钥匙串: {}", name))
+    }
+
+    fn get_secret(&self, name: &str) -> Result<Option<String>> {
+        match Self::entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("无法读取系统钥匙串: {}", name)),
+        }
+    }
+
+    fn delete_secret(&self, name: &str) -> Result<bool> {
+        match Self::entry(name)?.delete_password() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("无法删除系统钥匙串条目: {}", name)),
+        }
+    }
+}
+
+/// 本机标识（用于派生文件后备方案的密钥）
+///
+/// 依次尝试 systemd/D-Bus 的 machine-id 和计算机名，都取不到时使用固定值。
+fn machine_id() -> String {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .chain(
+            ["COMPUTERNAME", "HOSTNAME"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok()),
+        )
+        .find(|id| !id.is_empty())
+        .unwrap_or_else(|| KEYRING_SERVICE.to_string())
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+const NONCE_LEN: usize = 16;
+const TAG_LEN: usize = 32;
+
+/// 基于文件的密钥存储（混淆，不是可靠的加密）
+///
+/// 每个值用随机数和本机派生密钥生成的HMAC-SHA256密钥流异或，并附带校验码；
+/// 文件复制到其他电脑后无法解开。
+pub struct FileSecretStore {
+    path: PathBuf,
+    key: [u8; 32],
+    lock: Mutex<()>,
+}
+
+impl Debug for FileSecretStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileSecretStore")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FileSecretStore {
+    /// 在配置目录下创建存储，密钥由本机标识派生
+    pub fn in_dir<P: AsRef<Path>>(config_dir: P) -> Self {
+        Self::with_machine_id(config_dir, &machine_id())
+    }
+
+    /// 使用指定的本机标识创建存储
+    pub fn with_machine_id<P: AsRef<Path>>(config_dir: P, machine_id: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(b"MiniCRM secret store v1\0");
+        hasher.update(machine_id.as_bytes());
+        Self {
+            path: config_dir.as_ref().join(SECRETS_FILE_NAME),
+            key: hasher.finalize().into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 存储文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        match HmacSha256::new_from_slice(&self.key) {
+            Ok(mac) => mac,
+            Err(_) => unreachable!("HMAC接受任意长度的密钥"),
+        }
+    }
+
+    /// 与密钥流异或（加密和解密相同）
+    fn apply_keystream(&self, nonce: &[u8], data: &mut [u8]) {
+        for (counter, chunk) in (0u32..).zip(data.chunks_mut(32)) {
+            let mut mac = self.mac();
+            mac.update(b"stream");
+            mac.update(nonce);
+            mac.update(&counter.to_be_bytes());
+            let block = mac.finalize().into_bytes();
+            for (byte, key) in chunk.iter_mut().zip(block.iter()) {
+                *byte ^= key;
+            }
+        }
+    }
+
+    fn tag(&self, name: &str, nonce: &[u8], ciphertext: &[u8]) -> HmacSha256 {
+        let mut mac = self.mac();
+        mac.update(b"tag");
+        mac.update(name.as_bytes());
+        mac.update(nonce);
+        mac.update(ciphertext);
+        mac
+    }
+
+    fn seal(&self, name: &str, value: &str) -> String {
+        let nonce = *Uuid::new_v4().as_bytes();
+        let mut data = value.as_bytes().to_vec();
+        self.apply_keystream(&nonce, &mut data);
+        let tag = self.tag(name, &nonce, &data).finalize().into_bytes();
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&data);
+        sealed.extend_from_slice(&tag);
+        hex::encode(sealed)
+    }
+
+    fn open(&self, name: &str, sealed: &str) -> Result<String> {
+        let sealed = hex::decode(sealed).map_err(|_| anyhow!("密钥内容已损坏: {}", name))?;
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return Err(anyhow!("密钥内容已损坏: {}", name));
+        }
+        let (nonce, rest) = sealed.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+        self.tag(name, nonce, ciphertext)
+            .verify_slice(tag)
+            .map_err(|_| anyhow!("无法解开密钥 {}（文件已损坏或来自其他电脑）", name))?;
+        let mut data = ciphertext.to_vec();
+        self.apply_keystream(nonce, &mut data);
+        String::from_utf8(data).map_err(|_| anyhow!("密钥内容已损坏: {}", name))
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("密钥文件格式不正确: {}", self.path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => {
+                Err(e).with_context(|| format!("无法读取密钥文件: {}", self.path.display()))
+            }
+        }
+    }
+
+    /// 先写临时文件再改名，避免中途失败留下半个文件
+    fn store(&self, entries: &BTreeMap<String, String>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建配置目录: {}", parent.display()))?;
+        }
+        let mut partial = self.path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        fs::write(&partial, serde_json::to_string_pretty(entries)?)
+            .and_then(|()| fs::rename(&partial, &self.path))
+            .with_context(|| format!("无法写入密钥文件: {}", self.path.display()))
+    }
+}
+
+impl SecretStore for FileSecretStore {
+    fn set_secret(&self, name: &str, value: &str) -> Result<()> {
+        let name = checked_name(name)?;
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.load()?;
+        entries.insert(name.to_string(), self.seal(name, value));
+        self.store(&entries)
+    }
+
+    fn get_secret(&self, name: &str) -> Result<Option<String>> {
+        let name = checked_name(name)?;
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.load()?
+            .get(name)
+            .map(|sealed| self.open(name, sealed))
+            .transpose()
+    }
+
+    fn delete_secret(&self, name: &str) -> Result<bool> {
+        let name = checked_name(name)?;
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.load()?;
+        if entries.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.store(&entries)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_store_round_trip_and_delete() {
+        let dir = TempDir::new().unwrap();
+        let store = FileSecretStore::with_machine_id(dir.path(), "machine-a");
+        assert_eq!(store.get_secret("smtp_password").unwrap(), None);
+
+        store.set_secret("smtp_password", "授权码-abc123").unwrap();
+        store.set_secret("api_token", "t0ken").unwrap();
+        assert_eq!(
+            store.get_secret("smtp_password").unwrap().as_deref(),
+            Some("授权码-abc123")
+        );
+
+        // 文件中不出现明文
+        let content = fs::read_to_string(store.path()).unwrap();
+        assert!(!content.contains("abc123"));
+        assert!(!content.contains("t0ken"));
+
+        // 覆盖已有的值
+        store.set_secret("api_token", "new-token").unwrap();
+        let reopened = FileSecretStore::with_machine_id(dir.path(), "machine-a");
+        assert_eq!(
+            reopened.get_secret("api_token").unwrap().as_deref(),
+            Some("new-token")
+        );
+
+        assert!(store.delete_secret("api_token").unwrap());
+        assert!(!store.delete_secret("api_token").unwrap());
+        assert_eq!(store.get_secret("api_token").unwrap(), None);
+        assert!(store.get_secret("smtp_password").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_file_store_rejects_other_machine_and_bad_names() {
+        let dir = TempDir::new().unwrap();
+        FileSecretStore::with_machine_id(dir.path(), "machine-a")
+            .set_secret("app_secret", "s3cret")
+            .unwrap();
+
+        let other = FileSecretStore::with_machine_id(dir.path(), "machine-b");
+        assert!(other.get_secret("app_secret").is_err());
+
+        let store = FileSecretStore::with_machine_id(dir.path(), "machine-a");
+        assert!(store.set_secret("../escape", "x").is_err());
+        assert!(store.set_secret("", "x").is_err());
+    }
+
+    #[test]
+    fn test_secret_ref() {
+        assert_eq!(secret_ref("smtp_password"), "keyring:smtp_password");
+        assert_eq!(
+            parse_secret_ref("keyring:smtp_password"),
+            Some("smtp_password")
+        );
+        assert_eq!(parse_secret_ref("keyring:"), None);
+        assert_eq!(parse_secret_ref("plain-password"), None);
+    }
+}
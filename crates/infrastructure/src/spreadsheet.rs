@@ -0,0 +1,177 @@
+//! 表格文件读取
+//!
+//! 用 calamine 读取 `.xlsx`、`.xls` 和 `.ods`。工作表的已用区域不一定从A1开始，读出的行
+//! 按左上角位置补齐空行和空列，行号、列号与表格软件中看到的一致。
+
+use std::path::Path;
+
+use calamine::{open_workbook_auto, Data, Reader, Sheets};
+use minicrm_core::{CoreError, CoreResult, SpreadsheetReader};
+
+/// 工作簿读取器
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkbookReader;
+
+impl WorkbookReader {
+    /// 创建读取器
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn open(path: &Path) -> CoreResult<Sheets<std::io::BufReader<std::fs::File>>> {
+    open_workbook_auto(path)
+        .map_err(|e| CoreError::validation(format!("无法打开表格文件 {}: {}", path.display(), e)))
+}
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        Data::String(text) => text.trim().to_string(),
+        other => other.to_string(),
+    }
+}
+
+impl SpreadsheetReader for WorkbookReader {
+    fn sheet_names(&self, path: &Path) -> CoreResult<Vec<String>> {
+        Ok(open(path)?.sheet_names())
+    }
+
+    fn read_sheet(&self, path: &Path, sheet: &str) -> CoreResult<Vec<Vec<String>>> {
+        let mut workbook = open(path)?;
+        if !workbook.sheet_names().iter().any(|name| name == sheet) {
+            return Err(CoreError::not_found(format!("工作表“{}”", sheet)));
+        }
+        let range = workbook
+            .worksheet_range(sheet)
+            .map_err(|e| CoreError::validation(format!("读取工作表“{}”失败: {}", sheet, e)))?;
+        let Some((top, left)) = range.start() else {
+            return Ok(Vec::new());
+        };
+
+        let mut rows = vec![Vec::new(); top as usize];
+        for row in range.rows() {
+            let mut cells = vec![String::new(); left as usize];
+            cells.extend(row.iter().map(cell_text));
+            rows.push(cells);
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minicrm_core::{HeaderAliases, ImportField, ImportTarget};
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+    const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+    const PACKAGE_REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+
+    /// 单元格：文字写为内联字符串，能解析为数字的写为数值
+    fn cell(reference: &str, value: &str) -> String {
+        if value.parse::<f64>().is_ok() {
+            format!(r#"<c r="{reference}"><v>{value}</v></c>"#)
+        } else {
+            format!(r#"<c r="{reference}" t="inlineStr"><is><t>{value}</t></is></c>"#)
+        }
+    }
+
+    /// 工作表XML，`rows` 为（行号，[(单元格，值)]）
+    fn sheet_xml(rows: &[(u32, &[(&str, &str)])], merged: &[&str]) -> String {
+        let mut xml = format!(r#"<worksheet xmlns="{MAIN_NS}"><sheetData>"#);
+        for (number, cells) in rows {
+            xml.push_str(&format!(r#"<row r="{number}">"#));
+            for (reference, value) in *cells {
+                xml.push_str(&cell(reference, value));
+            }
+            xml.push_str("</row>");
+        }
+        xml.push_str("</sheetData>");
+        if !merged.is_empty() {
+            xml.push_str(&format!(r#"<mergeCells count="{}">"#, merged.len()));
+            for range in merged {
+                xml.push_str(&format!(r#"<mergeCell ref="{range}"/>"#));
+            }
+            xml.push_str("</mergeCells>");
+        }
+        xml.push_str("</worksheet>");
+        xml
+    }
+
+    fn write_workbook(path: &Path, sheets: &[(&str, String)]) {
+        let mut zip = ZipWriter::new(std::fs::File::create(path).unwrap());
+        let options = FileOptions::default();
+        let mut entries = vec![(
+            "[Content_Types].xml".to_string(),
+            r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"/>"#
+                .to_string(),
+        )];
+        let mut workbook = format!(r#"<workbook xmlns="{MAIN_NS}" xmlns:r="{REL_NS}"><sheets>"#);
+        let mut rels = format!(r#"<Relationships xmlns="{PACKAGE_REL_NS}">"#);
+        for (index, (name, xml)) in sheets.iter().enumerate() {
+            let id = index + 1;
+            workbook.push_str(&format!(
+                r#"<sheet name="{name}" sheetId="{id}" r:id="rId{id}"/>"#
+            ));
+            rels.push_str(&format!(
+                r#"<Relationship Id="rId{id}" Type="{REL_NS}/worksheet" "#
+            ));
+            rels.push_str(&format!(r#"Target="worksheets/sheet{id}.xml"/>"#));
+            entries.push((format!("xl/worksheets/sheet{id}.xml"), xml.clone()));
+        }
+        workbook.push_str("</sheets></workbook>");
+        rels.push_str("</Relationships>");
+        entries.push(("xl/workbook.xml".to_string(), workbook));
+        entries.push(("xl/_rels/workbook.xml.rels".to_string(), rels));
+        for (name, content) in entries {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_reads_selected_sheet_with_offsets() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("价目表.xlsx");
+        let notes = sheet_xml(&[(1, &[("A1", "本表仅供参考")])], &[]);
+        // 标题合并在B1:E2，表头在第4行且从B列开始，列顺序与别名表不同
+        let prices = sheet_xml(
+            &[
+                (1, &[("B1", "华美板材2024年5月报价单")]),
+                (4, &[("B4", "单价(元)"), ("C4", "厚度"), ("D4", "品名"), ("E4", "规格")]),
+                (5, &[("B5", "128.5"), ("C5", "18mm"), ("D5", "多层板"), ("E5", "1220×2440")]),
+                (6, &[("B6", "96"), ("C6", "9mm"), ("D6", "颗粒板"), ("E6", "1220×2440")]),
+            ],
+            &["B1:E2"],
+        );
+        write_workbook(&path, &[("说明", notes), ("板材", prices)]);
+
+        let reader = WorkbookReader::new();
+        assert_eq!(reader.sheet_names(&path).unwrap(), vec!["说明", "板材"]);
+        let rows = reader.read_sheet(&path, "板材").unwrap();
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[0][1], "华美板材2024年5月报价单");
+        assert_eq!(rows[3][0], "");
+        assert_eq!(rows[4][1], "128.5");
+        assert_eq!(rows[5][3], "颗粒板");
+
+        let (header_row, columns) = HeaderAliases::default()
+            .detect(ImportTarget::Products, &rows)
+            .unwrap();
+        assert_eq!(header_row, 3);
+        let field = |column: usize| columns[column].field;
+        assert_eq!(field(1), Some(ImportField::UnitPrice));
+        assert_eq!(field(2), Some(ImportField::Thickness));
+        assert_eq!(field(3), Some(ImportField::ProductName));
+        assert_eq!(field(4), Some(ImportField::Specification));
+
+        let err = reader.read_sheet(&path, "五金").unwrap_err();
+        assert!(matches!(err, CoreError::NotFound(_)), "{err}");
+    }
+}
@@ -0,0 +1,519 @@
+//! 存储清理模块
+//!
+//! 附件先写入附件目录再由事务记录引用，事务回滚或程序崩溃时会留下没有记录引用的附件；
+//! 导出、打开附件等留下的临时文件也不会自动删除。清理任务每天扫描一次：
+//!
+//! - 附件目录下没有任何记录引用的附件（及其缩略图），修改时间早于宽限期
+//!   （默认24小时，避免删除正在上传、尚未提交的附件）；已标记删除的附件在历史记录的
+//!   引用全部释放后随之删除，并清除删除标记
+//! - 暂存目录中写了一半的附件，同样按宽限期判断
+//! - 临时目录中早于保留期的文件
+//!
+//! 预览模式只列出将删除的文件。每次运行（包括预览）都写入 `storage_gc_log` 表，
+//! 诊断信息界面显示删除的文件数和释放的空间。附件目录下的子目录（客户附件目录等）不扫描。
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use minicrm_core::{Clock, CoreError, CoreResult, Job, JobSchedule, StorageGcRun, SystemClock};
+use rusqlite::{params, Row};
+use tracing::{info, warn};
+
+use crate::attachments::{AttachmentStore, THUMBNAIL_SUFFIX};
+use crate::database::DatabaseConnection;
+use crate::database::time::time_key;
+
+/// 默认附件宽限期（小时）
+pub const DEFAULT_BLOB_GRACE_HOURS: i64 = 24;
+
+/// 默认临时文件保留期（小时）
+pub const DEFAULT_TEMP_MAX_AGE_HOURS: i64 = 48;
+
+/// 引用附件哈希的查询（每行一个哈希）；新增保存附件哈希的表时在这里加一条
+const BLOB_REFERENCES: &[&str] = &[
+    "SELECT json_extract(value, '$.hash') FROM intake_log, json_each(intake_log.attachments)",
+    "SELECT hash FROM attachment_references",
+];
+
+fn sql_count(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+fn count(row: &Row<'_>, index: usize) -> rusqlite::Result<u64> {
+    Ok(u64::try_from(row.get::<_, i64>(index)?).unwrap_or(0))
+}
+
+fn row_to_run(row: &Row<'_>) -> rusqlite::Result<StorageGcRun> {
+    let ran_at: String = row.get(0)?;
+    Ok(StorageGcRun {
+        ran_at: DateTime::parse_from_rfc3339(&ran_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?,
+        dry_run: row.get(1)?,
+        orphan_blobs: count(row, 2)?,
+        temp_files: count(row, 3)?,
+        bytes: count(row, 4)?,
+    })
+}
+
+/// 附件目录中的文件名对应的附件哈希（附件本身或其缩略图）
+fn blob_hash(name: &str) -> Option<(&str, bool)> {
+    let is_hash = |s: &str| s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit());
+    if is_hash(name) {
+        return Some((name, true));
+    }
+    name.strip_suffix(THUMBNAIL_SUFFIX)
+        .filter(|hash| is_hash(hash))
+        .map(|hash| (hash, false))
+}
+
+/// 可删除的文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcCandidateKind {
+    /// 没有记录引用的附件
+    OrphanBlob,
+    /// 没有记录引用的附件的缩略图
+    OrphanThumbnail,
+    /// 写了一半的附件或过期的临时文件
+    TempFile,
+}
+
+/// 一个可删除的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcCandidate {
+    /// 文件路径
+    pub path: PathBuf,
+    /// 文件大小（字节）
+    pub bytes: u64,
+    /// 类型
+    pub kind: GcCandidateKind,
+}
+
+/// 一次清理的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageGcReport {
+    /// 运行记录
+    pub run: StorageGcRun,
+    /// 删除（预览时为将删除）的文件
+    pub candidates: Vec<GcCandidate>,
+}
+
+/// 存储清理任务
+pub struct StorageGc {
+    connection: DatabaseConnection,
+    attachments: AttachmentStore,
+    temp_dirs: Vec<PathBuf>,
+    blob_grace: Duration,
+    temp_max_age: Duration,
+    run_at: NaiveTime,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for StorageGc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StorageGc")
+            .field("attachments", &self.attachments.root())
+            .field("temp_dirs", &self.temp_dirs)
+            .field("blob_grace", &self.blob_grace)
+            .field("temp_max_age", &self.temp_max_age)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StorageGc {
+    /// 创建清理任务（默认清理打开附件时的副本目录）
+    pub fn new(connection: DatabaseConnection, attachments: AttachmentStore) -> Self {
+        let temp_dirs = vec![attachments.open_dir().to_path_buf()];
+        Self {
+            connection,
+            attachments,
+            temp_dirs,
+            blob_grace: Duration::hours(DEFAULT_BLOB_GRACE_HOURS),
+            temp_max_age: Duration::hours(DEFAULT_TEMP_MAX_AGE_HOURS),
+            run_at: NaiveTime::from_hms_opt(4, 0, 0).unwrap_or_default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 增加要清理的临时目录
+    pub fn with_temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dirs.push(dir.into());
+        self
+    }
+
+    /// 设置附件宽限期
+    pub fn with_blob_grace(mut self, grace: Duration) -> Self {
+        self.blob_grace = grace;
+        self
+    }
+
+    /// 设置临时文件保留期
+    pub fn with_temp_max_age(mut self, max_age: Duration) -> Self {
+        self.temp_max_age = max_age;
+        self
+    }
+
+    /// 设置每天的运行时间
+    pub fn run_at(mut self, at: NaiveTime) -> Self {
+        self.run_at = at;
+        self
+    }
+
+    /// 设置时钟
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 扫描并删除（`dry_run` 时只列出）可删除的文件，记录本次运行
+    ///
+    /// 单个文件删除失败只记录警告，不计入结果。
+    ///
+    /// # Errors
+    ///
+    /// 读取引用、扫描目录或写入运行记录失败时返回错误，此时不会删除任何文件。
+    pub fn collect(&self, dry_run: bool) -> Result<StorageGcReport> {
+        let now = self.clock.now();
+        let referenced = self.referenced_hashes()?;
+        let mut candidates = self.scan_attachments(&referenced, now - self.blob_grace)?;
+        scan_old_files(
+            &self.attachments.staging_dir(),
+            now - self.blob_grace,
+            &mut candidates,
+        )?;
+        for dir in &self.temp_dirs {
+            scan_old_files(dir, now - self.temp_max_age, &mut candidates)?;
+        }
+
+        if !dry_run {
+            candidates.retain(|candidate| match fs::remove_file(&candidate.path) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("无法删除文件 {:?}: {}", candidate.path, e);
+                    false
+                }
+            });
+            for dir in &self.temp_dirs {
+                remove_empty_dirs(dir);
+            }
+            self.clear_tombstones(&candidates)?;
+        }
+
+        let count_of = |kind| candidates.iter().filter(|c| c.kind == kind).count() as u64;
+        let run = StorageGcRun {
+            ran_at: now,
+            dry_run,
+            orphan_blobs: count_of(GcCandidateKind::OrphanBlob),
+            temp_files: count_of(GcCandidateKind::TempFile),
+            bytes: candidates.iter().map(|c| c.bytes).sum(),
+        };
+        self.record(&run)?;
+        info!(
+            "存储清理{}: 孤儿附件 {} 个，临时文件 {} 个，共 {} 字节",
+            if dry_run { "预览" } else { "完成" },
+            run.orphan_blobs,
+            run.temp_files,
+            run.bytes
+        );
+        Ok(StorageGcReport { run, candidates })
+    }
+
+    /// 最近的运行记录（新的在前）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn history(&self, limit: usize) -> Result<Vec<StorageGcRun>> {
+        self.connection.query_map(
+            "SELECT ran_at, dry_run, orphan_blobs, temp_files, bytes
+             FROM storage_gc_log ORDER BY id DESC LIMIT ?1",
+            [sql_count(limit as u64)],
+            row_to_run,
+        )
+    }
+
+    fn referenced_hashes(&self) -> Result<HashSet<String>> {
+        let mut hashes = HashSet::new();
+        for sql in BLOB_REFERENCES {
+            let rows: Vec<Option<String>> = self
+                .connection
+                .query_map(sql, [], |row| row.get(0))
+                .with_context(|| format!("无法读取附件引用: {}", sql))?;
+            hashes.extend(rows.into_iter().flatten());
+        }
+        Ok(hashes)
+    }
+
+    fn scan_attachments(
+        &self,
+        referenced: &HashSet<String>,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<GcCandidate>> {
+        let mut candidates = Vec::new();
+        for (path, bytes, modified) in list_files(self.attachments.root())? {
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let Some((hash, is_blob)) = blob_hash(name) else {
+                continue;
+            };
+            if referenced.contains(hash) || modified >= cutoff {
+                continue;
+            }
+            candidates.push(GcCandidate {
+                kind: if is_blob {
+                    GcCandidateKind::OrphanBlob
+                } else {
+                    GcCandidateKind::OrphanThumbnail
+                },
+                path,
+                bytes,
+            });
+        }
+        Ok(candidates)
+    }
+
+    /// 清除已删除文件的附件的删除标记
+    fn clear_tombstones(&self, removed: &[GcCandidate]) -> Result<()> {
+        for candidate in removed {
+            let hash = candidate
+                .path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(blob_hash)
+                .filter(|(_, is_blob)| *is_blob);
+            if let Some((hash, _)) = hash {
+                self.connection
+                    .execute("DELETE FROM attachment_tombstones WHERE hash = ?1", [hash])
+                    .context("无法清除附件删除标记")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&self, run: &StorageGcRun) -> Result<()> {
+        self.connection
+            .execute(
+                "INSERT INTO storage_gc_log (ran_at, dry_run, orphan_blobs, temp_files, bytes)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    time_key(run.ran_at),
+                    run.dry_run,
+                    sql_count(run.orphan_blobs),
+                    sql_count(run.temp_files),
+                    sql_count(run.bytes),
+                ],
+            )
+            .context("无法保存存储清理记录")?;
+        Ok(())
+    }
+}
+
+/// 目录下的文件（不含子目录）：路径、大小、修改时间；目录不存在时为空
+fn list_files(dir: &Path) -> Result<Vec<(PathBuf, u64, DateTime<Utc>)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("无法读取目录: {:?}", dir)),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("无法读取目录: {:?}", dir))?;
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_file() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((entry.path(), metadata.len(), modified.into()));
+        }
+    }
+    Ok(files)
+}
+
+/// 递归列出目录下修改时间早于 `cutoff` 的文件
+fn scan_old_files(
+    dir: &Path,
+    cutoff: DateTime<Utc>,
+    candidates: &mut Vec<GcCandidate>,
+) -> Result<()> {
+    for (path, bytes, modified) in list_files(dir)? {
+        if modified < cutoff {
+            candidates.push(GcCandidate {
+                path,
+                bytes,
+                kind: GcCandidateKind::TempFile,
+            });
+        }
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            scan_old_files(&entry.path(), cutoff, candidates)?;
+        }
+    }
+    Ok(())
+}
+
+/// 删除目录下的空子目录（目录本身保留）
+fn remove_empty_dirs(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            let path = entry.path();
+            remove_empty_dirs(&path);
+            // 非空目录删除失败是预期的
+            let _ = fs::remove_dir(&path);
+        }
+    }
+}
+
+#[async_trait]
+impl Job for StorageGc {
+    fn name(&self) -> &str {
+        "storage_gc"
+    }
+
+    fn schedule(&self) -> JobSchedule {
+        JobSchedule::daily(self.run_at)
+    }
+
+    async fn run(&self) -> CoreResult<()> {
+        self.collect(false)
+            .map_err(|e| CoreError::Other(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_pool;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    fn create_gc() -> (TempDir, StorageGc) {
+        let (temp_dir, pool) = migrated_pool();
+        let connection = DatabaseConnection::new(pool);
+        let attachments = AttachmentStore::new(temp_dir.path().join("attachments"))
+            .with_open_dir(temp_dir.path().join("open"));
+        let gc =
+            StorageGc::new(connection, attachments).with_temp_dir(temp_dir.path().join("exports"));
+        (temp_dir, gc)
+    }
+
+    /// 把文件的修改时间改到 `hours` 小时前
+    fn age(path: &Path, hours: u64) {
+        let at = SystemTime::now() - std::time::Duration::from_secs(hours * 3600);
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(at)
+            .unwrap();
+    }
+
+    fn write(path: &Path, content: &[u8], hours: u64) -> PathBuf {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        age(path, hours);
+        path.to_path_buf()
+    }
+
+    #[test]
+    fn test_dry_run_and_real_run() {
+        let (dir, gc) = create_gc();
+        let store = &gc.attachments;
+
+        // 工单引用的附件：再旧也不删除
+        let referenced = store.put(b"referenced photo").unwrap();
+        age(&store.blob_path(&referenced).unwrap(), 24 * 30);
+        let thumbnail = write(
+            &store.thumbnail_path(&referenced).unwrap(),
+            b"thumb",
+            24 * 30,
+        );
+        gc.connection
+            .execute(
+                "INSERT INTO intake_log (message_id, ticket_id, ticket_number, attachments,
+                                         received_at)
+                 VALUES ('m1', 't1', 'S-001', ?1, '2024-06-01T00:00:00Z')",
+                [format!(
+                    r#"[{{"hash":"{}","file_name":"a.jpg","size":16}}]"#,
+                    referenced
+                )],
+            )
+            .unwrap();
+
+        // 没有引用的旧附件及其缩略图、刚上传还没提交的附件
+        let orphan = store.put(b"orphan").unwrap();
+        age(&store.blob_path(&orphan).unwrap(), 25);
+        write(&store.thumbnail_path(&orphan).unwrap(), b"thumb", 25);
+        let in_flight = store.put(b"in flight").unwrap();
+        // 崩溃时写了一半的附件、过期和未过期的临时文件、其他目录不扫描
+        write(&store.staging_dir().join("abc.partial"), b"half", 30);
+        let stale = write(&dir.path().join("exports/a/report.csv"), b"1,2,3", 49);
+        write(&dir.path().join("open/x/photo.jpg"), b"copy", 2);
+        let customer_file = write(
+            &store.root().join("customers/1/contract.pdf"),
+            b"pdf",
+            24 * 30,
+        );
+
+        let preview = gc.collect(true).unwrap();
+        assert_eq!(preview.run.orphan_blobs, 1);
+        assert_eq!(preview.run.temp_files, 2);
+        assert_eq!(preview.run.bytes, 6 + 5 + 4 + 5);
+        assert_eq!(preview.candidates.len(), 4);
+        assert!(preview.candidates.iter().all(|c| c.path.exists()));
+
+        let report = gc.collect(false).unwrap();
+        assert_eq!(
+            report.run,
+            StorageGcRun {
+                dry_run: false,
+                ran_at: report.run.ran_at,
+                ..preview.run.clone()
+            }
+        );
+        assert!(report.candidates.iter().all(|c| !c.path.exists()));
+        assert!(!dir.path().join("exports/a").exists());
+        for kept in [
+            store.blob_path(&referenced).unwrap(),
+            thumbnail,
+            store.blob_path(&in_flight).unwrap(),
+            dir.path().join("open/x/photo.jpg"),
+            customer_file,
+        ] {
+            assert!(kept.exists(), "{:?} should be kept", kept);
+        }
+        assert!(!stale.exists());
+        assert_eq!(store.read(&referenced).unwrap(), b"referenced photo");
+
+        let history = gc.history(10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(!history[0].dry_run);
+        assert!(history[1].dry_run);
+        assert_eq!(history[0].bytes, 20);
+
+        // 再次运行没有可删除的文件
+        let again = gc.collect(false).unwrap();
+        assert!(again.candidates.is_empty());
+    }
+}
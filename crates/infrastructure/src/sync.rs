@@ -0,0 +1,1075 @@
+//! 差异同步模块
+//!
+//! 主电脑按修改时间和删除日志导出变更包（JSON Lines），另一台电脑上的只读副本应用后与主电脑
+//! 的业务数据一致。变更包第一行为文件头，最后一行为结束标记（记录条数，用于发现没有拷贝完整
+//! 的文件），中间每行为一条新增/修改或删除。
+//!
+//! 新增/修改按依赖顺序（客户、任务、报价、订单、收款）写入，删除按相反顺序执行；写入使用
+//! `ON CONFLICT DO UPDATE`，重复应用同一变更包结果相同。副本记录已应用的变更包的导出时间
+//! （高水位），导出时间不晚于高水位的变更包不做任何修改。
+//!
+//! 客户、任务和订单的实体字段以规范JSON（`record`）导出，实体以外的列（如删除标记）仍按列名
+//! 放在 `row` 中；报价和收款没有对应的规范实体，按整行导出。规范实体和行中以规范JSON保存的列
+//! （格式版本1的变更包中订单的送货地址）的结构版本记录在文件头，应用时把旧版本转换为本机的
+//! 版本，比本机新的版本拒绝应用；文件头没有记录的按版本1处理。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, SubsecRound, Utc};
+use minicrm_core::{
+    from_canonical_json, to_canonical_json, Address, CanonicalEntity, ChangesetSummary, Clock,
+    CoreError, CoreResult, Customer, DataSyncService, EntityKind, Order, SystemClock, Task,
+};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::archive::{sibling, TempPath};
+use crate::database::time::time_key;
+use crate::database::{DatabaseConnection, MigrationManager};
+use crate::repository::customer_summary;
+use crate::repository::generic::EntityMapper;
+use crate::repository::support::to_core;
+
+/// 变更包文件扩展名
+pub const CHANGESET_EXTENSION: &str = "minicrm-changes.jsonl";
+
+/// 当前变更包格式版本（版本2起客户、任务和订单以规范JSON导出）
+pub const CHANGESET_FORMAT_VERSION: u32 = 2;
+
+/// 参与同步的表（按依赖顺序）
+pub const SYNC_TABLES: [&str; 5] = ["customers", "tasks", "quotes", "orders", "order_payments"];
+
+/// 有记录总数缓存的实体
+const COUNTED_ENTITIES: [EntityKind; 4] = [
+    EntityKind::Customer,
+    EntityKind::Task,
+    EntityKind::Quote,
+    EntityKind::Order,
+];
+
+/// 行中以规范JSON保存的列
+struct CanonicalColumn {
+    /// 表名
+    table: &'static str,
+    /// 列名
+    column: &'static str,
+    /// 实体名称（[`CanonicalEntity::ENTITY`]）
+    entity: &'static str,
+    /// 本机的结构版本
+    version: u32,
+    /// 把指定版本的JSON文本转为本机版本
+    convert: fn(u32, &str) -> CoreResult<String>,
+}
+
+/// 参与同步的表中以规范JSON保存的列
+const CANONICAL_COLUMNS: [CanonicalColumn; 1] = [CanonicalColumn {
+    table: "orders",
+    column: "delivery_address",
+    entity: Address::ENTITY,
+    version: Address::SCHEMA_VERSION,
+    convert: convert_canonical::<Address>,
+}];
+
+fn convert_canonical<E: CanonicalEntity>(version: u32, json: &str) -> CoreResult<String> {
+    let entity: E = from_canonical_json(version, serde_json::from_str(json)?)?;
+    Ok(to_canonical_json(&entity)?.to_string())
+}
+
+/// 以规范实体JSON导出的表
+struct CanonicalTable {
+    /// 表名
+    table: &'static str,
+    /// 实体名称（[`CanonicalEntity::ENTITY`]）
+    entity: &'static str,
+    /// 本机的结构版本
+    version: u32,
+    /// 实体对应的列（[`EntityMapper::COLUMNS`]）
+    columns: &'static [&'static str],
+    /// 读取一条记录，转为规范JSON
+    encode: fn(&Connection, &str) -> Result<Value>,
+    /// 把指定版本的规范JSON转为按 `columns` 排列的列值
+    decode: fn(u32, Value) -> CoreResult<Vec<SqlValue>>,
+}
+
+const fn canonical_table<E: EntityMapper + CanonicalEntity>() -> CanonicalTable {
+    CanonicalTable {
+        table: E::TABLE,
+        entity: E::ENTITY,
+        version: E::SCHEMA_VERSION,
+        columns: E::COLUMNS,
+        encode: encode_entity::<E>,
+        decode: decode_entity::<E>,
+    }
+}
+
+/// 参与同步且有规范实体的表
+const CANONICAL_TABLES: [CanonicalTable; 3] = [
+    canonical_table::<Customer>(),
+    canonical_table::<Task>(),
+    canonical_table::<Order>(),
+];
+
+fn encode_entity<E: EntityMapper + CanonicalEntity>(conn: &Connection, id: &str) -> Result<Value> {
+    let entity = conn.query_row(
+        &format!(
+            "SELECT {} FROM {} WHERE id = ?1",
+            E::COLUMNS.join(", "),
+            E::TABLE
+        ),
+        [id],
+        E::from_row,
+    )?;
+    Ok(to_canonical_json(&entity)?)
+}
+
+fn decode_entity<E: EntityMapper + CanonicalEntity>(
+    version: u32,
+    record: Value,
+) -> CoreResult<Vec<SqlValue>> {
+    let entity: E = from_canonical_json(version, record)?;
+    Ok(entity.to_params())
+}
+
+/// 本机各规范实体和规范JSON列的结构版本（写入文件头）
+fn local_entity_versions() -> BTreeMap<String, u32> {
+    CANONICAL_TABLES
+        .iter()
+        .map(|t| (t.entity, t.version))
+        .chain(CANONICAL_COLUMNS.iter().map(|c| (c.entity, c.version)))
+        .map(|(entity, version)| (entity.to_string(), version))
+        .collect()
+}
+
+/// 变更包内容无效（在修改任何数据之前返回）
+fn invalid(message: impl Into<String>) -> anyhow::Error {
+    CoreError::validation(message).into()
+}
+
+/// 表在 [`SYNC_TABLES`] 中的位置
+fn table_index(table: &str) -> Result<usize> {
+    SYNC_TABLES
+        .iter()
+        .position(|t| *t == table)
+        .ok_or_else(|| invalid(format!("不支持同步的数据表: {}", table)))
+}
+
+/// 变更包中的一行
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum ChangeLine {
+    /// 文件头
+    Header {
+        format_version: u32,
+        schema_version: u32,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        /// 规范实体和规范JSON列的结构版本（实体名称 → 版本）
+        #[serde(default)]
+        entity_versions: BTreeMap<String, u32>,
+    },
+    /// 新增或修改：有规范实体的表为规范JSON加实体以外的列，其他表为整行
+    Upsert {
+        entity: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        record: Option<Value>,
+        row: Map<String, Value>,
+    },
+    /// 硬删除
+    Delete { entity: String, id: String },
+    /// 结束标记
+    End { upserts: u64, deletes: u64 },
+}
+
+/// 读取并校验后的变更包
+struct Changeset {
+    schema_version: u32,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    /// （表序号, 整行）
+    upserts: Vec<(usize, Map<String, Value>)>,
+    /// （表序号, 记录ID）
+    deletes: Vec<(usize, String)>,
+}
+
+impl Changeset {
+    fn summary(&self, skipped: bool) -> ChangesetSummary {
+        ChangesetSummary {
+            since: self.since,
+            until: self.until,
+            upserts: if skipped { 0 } else { self.upserts.len() as u64 },
+            deletes: if skipped { 0 } else { self.deletes.len() as u64 },
+            skipped,
+        }
+    }
+}
+
+/// 差异同步服务
+#[derive(Clone)]
+pub struct DataSync {
+    connection: DatabaseConnection,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for DataSync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataSync").finish_non_exhaustive()
+    }
+}
+
+impl DataSync {
+    /// 创建差异同步服务
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self::with_clock(connection, Arc::new(SystemClock))
+    }
+
+    /// 使用指定时钟创建（用于测试）
+    pub fn with_clock(connection: DatabaseConnection, clock: Arc<dyn Clock>) -> Self {
+        Self { connection, clock }
+    }
+
+    /// `since` 之后修改、软删除或恢复的记录（整行，按ID排序）
+    pub fn find_modified_since(
+        &self,
+        table: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let index = table_index(table)?;
+        self.connection
+            .with_read_transaction(|tx| modified_rows(tx, index, since))
+    }
+
+    /// 副本已应用的变更包的导出时间，未应用过时为空
+    pub fn high_water(&self) -> Result<Option<DateTime<Utc>>> {
+        self.connection.with_read_transaction(|tx| read_high_water(tx))
+    }
+
+    /// 导出 `since` 之后的变更
+    ///
+    /// 导出时间取开始读取之前的时间，读取期间修改的记录会在下次导出中重复出现，应用结果不变。
+    /// 变更包先写入临时文件，完成后再改名为目标文件。
+    pub fn export_to(&self, since: DateTime<Utc>, out_path: &Path) -> Result<ChangesetSummary> {
+        info!("正在导出 {} 之后的变更到: {:?}", time_key(since), out_path);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建变更包目录: {:?}", parent))?;
+        }
+
+        // 与高水位时间的存储精度一致
+        let until = self.clock.now().trunc_subsecs(6);
+        let schema_version = MigrationManager::new(self.connection.clone())
+            .get_current_version()
+            .context("无法读取数据库结构版本")?;
+
+        let partial = TempPath::new(sibling(out_path, "partial"));
+        let file = File::create(&partial.0)
+            .with_context(|| format!("无法创建变更包文件: {:?}", partial.0))?;
+        let mut writer = BufWriter::new(file);
+        write_line(
+            &mut writer,
+            &ChangeLine::Header {
+                format_version: CHANGESET_FORMAT_VERSION,
+                schema_version,
+                since,
+                until,
+                entity_versions: local_entity_versions(),
+            },
+        )?;
+
+        let (upserts, deletes) = self.connection.with_read_transaction(|tx| {
+            let mut upserts = 0;
+            for (index, table) in SYNC_TABLES.iter().enumerate() {
+                for mut row in modified_rows(tx, index, since)? {
+                    let record = split_record(tx, table, &mut row)?;
+                    write_line(
+                        &mut writer,
+                        &ChangeLine::Upsert {
+                            entity: (*table).to_string(),
+                            record,
+                            row,
+                        },
+                    )?;
+                    upserts += 1;
+                }
+            }
+            let mut deletes = 0;
+            for (index, table) in SYNC_TABLES.iter().enumerate().rev() {
+                for id in deleted_ids(tx, index, since)? {
+                    write_line(
+                        &mut writer,
+                        &ChangeLine::Delete {
+                            entity: (*table).to_string(),
+                            id,
+                        },
+                    )?;
+                    deletes += 1;
+                }
+            }
+            Ok((upserts, deletes))
+        })?;
+        write_line(&mut writer, &ChangeLine::End { upserts, deletes })?;
+        let file = writer
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .context("无法写入变更包文件")?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&partial.0, out_path)
+            .with_context(|| format!("无法保存变更包文件: {:?}", out_path))?;
+        info!("变更包导出完成：{} 条修改，{} 条删除", upserts, deletes);
+        Ok(ChangesetSummary {
+            since,
+            until,
+            upserts,
+            deletes,
+            skipped: false,
+        })
+    }
+
+    /// 应用变更包
+    ///
+    /// 全部修改在同一事务中执行，并刷新受影响客户的汇总和表记录总数缓存。
+    pub fn apply_from(&self, path: &Path) -> Result<ChangesetSummary> {
+        info!("正在应用变更包: {:?}", path);
+        let changeset = read_changeset(path)?;
+        let schema_version = MigrationManager::new(self.connection.clone())
+            .get_current_version()
+            .context("无法读取数据库结构版本")?;
+        if changeset.schema_version != schema_version {
+            return Err(invalid(format!(
+                "变更包来自不同版本的应用（数据库结构版本 {}，本机 {}），请先把两台电脑升级到同一版本",
+                changeset.schema_version, schema_version
+            )));
+        }
+
+        let now = self.clock.now();
+        let applied = self.connection.with_transaction(|tx| {
+            if let Some(high_water) = read_high_water(tx)? {
+                if changeset.until <= high_water {
+                    return Ok(false);
+                }
+                if changeset.since > high_water {
+                    return Err(invalid(format!(
+                        "变更包从 {} 开始，本机只更新到 {}，请在主电脑上从 {} 起重新导出",
+                        time_key(changeset.since),
+                        time_key(high_water),
+                        time_key(high_water)
+                    )));
+                }
+            }
+
+            let mut owners = BTreeSet::new();
+            let mut upserts: Vec<_> = changeset.upserts.iter().collect();
+            upserts.sort_by_key(|(index, _)| *index);
+            for (index, row) in upserts {
+                let id = upsert_row(tx, *index, row)?;
+                owners.extend(owner_of(tx, *index, &id)?);
+            }
+            let mut deletes: Vec<_> = changeset.deletes.iter().collect();
+            deletes.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+            for (index, id) in deletes {
+                owners.extend(owner_of(tx, *index, id)?);
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE id = ?1", SYNC_TABLES[*index]),
+                    [id],
+                )?;
+            }
+
+            for owner in &owners {
+                if let Ok(customer_id) = Uuid::parse_str(owner) {
+                    customer_summary::refresh_customer(tx, customer_id, now)?;
+                }
+            }
+            for kind in COUNTED_ENTITIES {
+                recount(tx, kind, now)?;
+            }
+            tx.execute(
+                "INSERT INTO sync_state (id, high_water, applied_at) VALUES (1, ?1, ?2)
+                 ON CONFLICT(id) DO UPDATE SET
+                     high_water = excluded.high_water,
+                     applied_at = excluded.applied_at",
+                params![time_key(changeset.until), time_key(now)],
+            )?;
+            Ok(true)
+        })?;
+
+        if applied {
+            info!(
+                "变更包应用完成：{} 条修改，{} 条删除",
+                changeset.upserts.len(),
+                changeset.deletes.len()
+            );
+        } else {
+            info!("变更包不比已应用的数据新，未做修改");
+        }
+        Ok(changeset.summary(!applied))
+    }
+}
+
+#[async_trait]
+impl DataSyncService for DataSync {
+    async fn export_changes(
+        &self,
+        since: DateTime<Utc>,
+        out_path: &Path,
+    ) -> CoreResult<ChangesetSummary> {
+        self.export_to(since, out_path).map_err(to_core)
+    }
+
+    async fn apply_changes(&self, path: &Path) -> CoreResult<ChangesetSummary> {
+        self.apply_from(path).map_err(to_core)
+    }
+
+    async fn high_water(&self) -> CoreResult<Option<DateTime<Utc>>> {
+        DataSync::high_water(self).map_err(to_core)
+    }
+}
+
+fn write_line<W: Write>(writer: &mut W, line: &ChangeLine) -> Result<()> {
+    serde_json::to_writer(&mut *writer, line)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// 读取变更包并校验文件头、结束标记和表名
+fn read_changeset(path: &Path) -> Result<Changeset> {
+    let file = File::open(path).with_context(|| format!("无法打开变更包文件: {:?}", path))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header = lines
+        .next()
+        .transpose()?
+        .and_then(|line| serde_json::from_str::<ChangeLine>(&line).ok());
+    let Some(ChangeLine::Header {
+        format_version,
+        schema_version,
+        since,
+        until,
+        entity_versions,
+    }) = header
+    else {
+        return Err(invalid("不是有效的变更包文件"));
+    };
+    if format_version > CHANGESET_FORMAT_VERSION {
+        return Err(invalid(format!(
+            "变更包格式版本 {} 不受支持，请先升级应用",
+            format_version
+        )));
+    }
+    if let Some((entity, _)) = local_entity_versions()
+        .into_iter()
+        .find(|(entity, local)| entity_versions.get(entity).is_some_and(|v| v > local))
+    {
+        return Err(invalid(format!(
+            "变更包中 {} 的结构版本比本机新，请先升级应用",
+            entity
+        )));
+    }
+
+    let mut changeset = Changeset {
+        schema_version,
+        since,
+        until,
+        upserts: Vec::new(),
+        deletes: Vec::new(),
+    };
+    let mut end = None;
+    for (number, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if end.is_some() {
+            return Err(invalid("变更包在结束标记之后还有内容"));
+        }
+        let parsed: ChangeLine = serde_json::from_str(&line)
+            .map_err(|e| invalid(format!("变更包第 {} 行无效: {}", number + 2, e)))?;
+        match parsed {
+            ChangeLine::Upsert {
+                entity,
+                record,
+                mut row,
+            } => {
+                convert_canonical_columns(&entity, &mut row, &entity_versions)?;
+                if let Some(record) = record {
+                    merge_record(&entity, record, &mut row, &entity_versions)?;
+                }
+                changeset.upserts.push((table_index(&entity)?, row));
+            }
+            ChangeLine::Delete { entity, id } => {
+                changeset.deletes.push((table_index(&entity)?, id));
+            }
+            ChangeLine::End { upserts, deletes } => end = Some((upserts, deletes)),
+            ChangeLine::Header { .. } => return Err(invalid("变更包包含多个文件头")),
+        }
+    }
+
+    let counted = (
+        changeset.upserts.len() as u64,
+        changeset.deletes.len() as u64,
+    );
+    if end != Some(counted) {
+        return Err(invalid("变更包不完整，可能没有拷贝完，请重新拷贝"));
+    }
+    Ok(changeset)
+}
+
+/// 把行中旧版本的规范JSON列转为本机版本
+fn convert_canonical_columns(
+    table: &str,
+    row: &mut Map<String, Value>,
+    entity_versions: &BTreeMap<String, u32>,
+) -> Result<()> {
+    for column in CANONICAL_COLUMNS.iter().filter(|c| c.table == table) {
+        let version = entity_versions.get(column.entity).copied().unwrap_or(1);
+        if version == column.version {
+            continue;
+        }
+        if let Some(Value::String(json)) = row.get_mut(column.column) {
+            *json = (column.convert)(version, json).map_err(|e| {
+                invalid(format!("变更包中的 {}.{} 无法读取: {}", table, column.column, e))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// 有规范实体的表：读取实体转为规范JSON，并从整行中去掉实体对应的列
+fn split_record(
+    conn: &Connection,
+    table: &str,
+    row: &mut Map<String, Value>,
+) -> Result<Option<Value>> {
+    let Some(canonical) = CANONICAL_TABLES.iter().find(|t| t.table == table) else {
+        return Ok(None);
+    };
+    let Some(Value::String(id)) = row.get("id") else {
+        return Err(invalid(format!("{} 记录缺少ID", table)));
+    };
+    let record = (canonical.encode)(conn, id)
+        .with_context(|| format!("无法读取 {} 记录: {}", table, id))?;
+    row.retain(|column, _| !canonical.columns.contains(&column.as_str()));
+    Ok(Some(record))
+}
+
+/// 把规范实体JSON按文件头记录的版本读取，转为实体对应的列写回整行
+fn merge_record(
+    table: &str,
+    record: Value,
+    row: &mut Map<String, Value>,
+    entity_versions: &BTreeMap<String, u32>,
+) -> Result<()> {
+    let Some(canonical) = CANONICAL_TABLES.iter().find(|t| t.table == table) else {
+        return Err(invalid(format!("变更包中的 {} 记录不应包含规范实体", table)));
+    };
+    let version = entity_versions.get(canonical.entity).copied().unwrap_or(1);
+    let values = (canonical.decode)(version, record)
+        .map_err(|e| invalid(format!("变更包中的 {} 记录无法读取: {}", table, e)))?;
+    for (column, value) in canonical.columns.iter().zip(values) {
+        row.insert((*column).to_string(), json_value(value)?);
+    }
+    Ok(())
+}
+
+fn read_high_water(conn: &Connection) -> Result<Option<DateTime<Utc>>> {
+    let value: Option<String> = conn
+        .query_row("SELECT high_water FROM sync_state WHERE id = 1", [], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    value
+        .map(|v| {
+            DateTime::parse_from_rfc3339(&v)
+                .map(|t| t.with_timezone(&Utc))
+                .with_context(|| format!("同步高水位时间无效: {}", v))
+        })
+        .transpose()
+}
+
+/// 数据库值转为JSON（同步的表没有BLOB列）
+fn column_value(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t).into_owned()),
+    }
+}
+
+fn row_to_object(row: &Row<'_>) -> rusqlite::Result<Map<String, Value>> {
+    let statement = row.as_ref();
+    let mut object = Map::new();
+    for (index, name) in statement.column_names().into_iter().enumerate() {
+        object.insert(name.to_string(), column_value(row.get_ref(index)?));
+    }
+    Ok(object)
+}
+
+/// 数据库值转为JSON（实体的列没有BLOB值）
+fn json_value(value: SqlValue) -> Result<Value> {
+    Ok(match value {
+        SqlValue::Null => Value::Null,
+        SqlValue::Integer(i) => Value::from(i),
+        SqlValue::Real(f) => Value::from(f),
+        SqlValue::Text(t) => Value::from(t),
+        SqlValue::Blob(_) => return Err(invalid("实体的列不能是二进制值")),
+    })
+}
+
+/// JSON转为数据库值
+fn sql_value(value: &Value) -> Result<SqlValue> {
+    Ok(match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(i64::from(*b)),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        Value::Array(_) | Value::Object(_) => {
+            return Err(invalid(format!("变更包中的值无效: {}", value)))
+        }
+    })
+}
+
+/// 修改时间晚于 `since`，或在 `since` 之后软删除、恢复的记录
+fn modified_rows(
+    conn: &Connection,
+    index: usize,
+    since: DateTime<Utc>,
+) -> Result<Vec<Map<String, Value>>> {
+    let table = SYNC_TABLES[index];
+    let rows = conn
+        .prepare(&format!(
+            "SELECT * FROM {table}
+             WHERE julianday(updated_at) > julianday(?1)
+                OR id IN (SELECT entity_id FROM deletion_journal
+                          WHERE entity = ?2 AND kind <> 'hard'
+                            AND julianday(recorded_at) > julianday(?1))
+             ORDER BY id"
+        ))?
+        .query_map(params![time_key(since), table], row_to_object)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// 在 `since` 之后硬删除且当前不存在的记录ID
+fn deleted_ids(conn: &Connection, index: usize, since: DateTime<Utc>) -> Result<Vec<String>> {
+    let table = SYNC_TABLES[index];
+    let ids = conn
+        .prepare(&format!(
+            "SELECT DISTINCT j.entity_id FROM deletion_journal j
+             WHERE j.entity = ?2 AND j.kind = 'hard'
+               AND julianday(j.recorded_at) > julianday(?1)
+               AND NOT EXISTS (SELECT 1 FROM {table} t WHERE t.id = j.entity_id)
+             ORDER BY j.entity_id"
+        ))?
+        .query_map(params![time_key(since), table], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(ids)
+}
+
+/// 写入一行，返回记录ID
+fn upsert_row(conn: &Connection, index: usize, row: &Map<String, Value>) -> Result<String> {
+    let table = SYNC_TABLES[index];
+    let Some(Value::String(id)) = row.get("id") else {
+        return Err(invalid(format!("变更包中的 {} 记录缺少ID", table)));
+    };
+    let known = conn
+        .prepare("SELECT name FROM pragma_table_info(?1)")?
+        .query_map([table], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<BTreeSet<_>>>()?;
+
+    let mut columns = Vec::with_capacity(row.len());
+    let mut values = Vec::with_capacity(row.len());
+    for (column, value) in row {
+        if !known.contains(column) {
+            return Err(invalid(format!(
+                "变更包中的 {}.{} 列在本机不存在",
+                table, column
+            )));
+        }
+        columns.push(format!("\"{}\"", column));
+        values.push(sql_value(value)?);
+    }
+    let placeholders: Vec<_> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+    let updates: Vec<_> = columns
+        .iter()
+        .filter(|c| c.as_str() != "\"id\"")
+        .map(|c| format!("{c} = excluded.{c}"))
+        .collect();
+    let conflict = if updates.is_empty() {
+        "DO NOTHING".to_string()
+    } else {
+        format!("DO UPDATE SET {}", updates.join(", "))
+    };
+    conn.execute(
+        &format!(
+            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT(id) {}",
+            table,
+            columns.join(", "),
+            placeholders.join(", "),
+            conflict
+        ),
+        params_from_iter(values),
+    )
+    .with_context(|| format!("无法写入 {} 记录: {}", table, id))?;
+    Ok(id.clone())
+}
+
+/// 记录所属的客户（客户记录为自身）
+fn owner_of(conn: &Connection, index: usize, id: &str) -> Result<Option<String>> {
+    let sql = match SYNC_TABLES[index] {
+        "customers" => return Ok(Some(id.to_string())),
+        "order_payments" => {
+            "SELECT o.customer_id FROM order_payments p JOIN orders o ON o.id = p.order_id
+             WHERE p.id = ?1"
+                .to_string()
+        }
+        table => format!("SELECT customer_id FROM {} WHERE id = ?1", table),
+    };
+    Ok(conn.query_row(&sql, [id], |row| row.get(0)).optional()?)
+}
+
+/// 按实际记录数重写表记录总数缓存
+fn recount(conn: &Connection, kind: EntityKind, now: DateTime<Utc>) -> Result<()> {
+    let table = kind.table_name();
+    conn.execute(
+        &format!(
+            "INSERT INTO table_counters (table_name, row_count, reconciled_at, updated_at)
+             SELECT ?1, COUNT(*), ?2, ?2 FROM {table} WHERE deleted_at IS NULL
+             ON CONFLICT(table_name) DO UPDATE SET
+                 row_count = excluded.row_count,
+                 reconciled_at = excluded.reconciled_at,
+                 updated_at = excluded.updated_at"
+        ),
+        params![table, time_key(now)],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::pool::DatabasePoolBuilder;
+    use crate::database::{schema, DbUuid};
+    use chrono::TimeZone;
+    use minicrm_core::ManualClock;
+    use tempfile::TempDir;
+
+    fn open(path: &Path) -> DatabaseConnection {
+        let pool = DatabasePoolBuilder::new(path.to_string_lossy())
+            .build()
+            .unwrap();
+        DatabaseConnection::new(pool)
+    }
+
+    fn create_primary(dir: &TempDir) -> DatabaseConnection {
+        let connection = open(&dir.path().join("primary.db"));
+        MigrationManager::new(connection.clone())
+            .add_migrations(schema::builtin_migrations())
+            .migrate(None)
+            .unwrap();
+        connection
+    }
+
+    /// 以主电脑当前的数据创建副本
+    fn copy_database(primary: &DatabaseConnection, path: &Path) -> DatabaseConnection {
+        primary
+            .execute("VACUUM INTO ?1", [path.to_string_lossy().as_ref()])
+            .unwrap();
+        open(path)
+    }
+
+    fn insert_customer(connection: &DatabaseConnection, name: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO customers (id, name, created_at, updated_at)
+                 VALUES (?1, ?2, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+                params![DbUuid(id), name],
+            )
+            .unwrap();
+        id
+    }
+
+    fn insert_quote(connection: &DatabaseConnection, customer: Uuid, at: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO quotes
+                     (id, customer_id, title, total_amount, status, created_at, updated_at)
+                 VALUES (?1, ?2, '板材报价', 1280.5, 'accepted', ?3, ?3)",
+                params![DbUuid(id), DbUuid(customer), at],
+            )
+            .unwrap();
+        id
+    }
+
+    fn insert_order(connection: &DatabaseConnection, customer: Uuid, at: &str) -> Uuid {
+        let id = Uuid::new_v4();
+        connection
+            .execute(
+                "INSERT INTO orders (id, order_number, customer_id, total_amount, created_at,
+                                     updated_at)
+                 VALUES (?1, ?2, ?3, 500000, ?4, ?4)",
+                params![DbUuid(id), id.to_string(), DbUuid(customer), at],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO order_payments (id, order_id, amount, paid_at)
+                 VALUES (?1, ?2, 200000, ?3)",
+                params![DbUuid(Uuid::new_v4()), DbUuid(id), at],
+            )
+            .unwrap();
+        id
+    }
+
+    /// 全部同步表的内容
+    /// 各同步表的全部行
+    ///
+    /// 规范实体中的时间按 [`time_key`] 写回，比较前把时间文本统一为同一格式。
+    fn business_tables(connection: &DatabaseConnection) -> Vec<Vec<Map<String, Value>>> {
+        SYNC_TABLES
+            .iter()
+            .map(|table| {
+                let mut rows = connection
+                    .query_map(
+                        &format!("SELECT * FROM {} ORDER BY id", table),
+                        [],
+                        row_to_object,
+                    )
+                    .unwrap();
+                for value in rows.iter_mut().flat_map(|row| row.values_mut()) {
+                    if let Some(at) = value
+                        .as_str()
+                        .and_then(|text| DateTime::parse_from_rfc3339(text).ok())
+                    {
+                        *value = Value::from(time_key(at.with_timezone(&Utc)));
+                    }
+                }
+                rows
+            })
+            .collect()
+    }
+
+    fn since() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_changes_bring_copy_up_to_date() {
+        let dir = TempDir::new().unwrap();
+        let primary = create_primary(&dir);
+        let kept = insert_customer(&primary, "华东板材");
+        let removed = insert_customer(&primary, "宏达家具");
+        let trashed_quote = insert_quote(&primary, kept, "2024-01-01T00:00:00Z");
+        insert_order(&primary, removed, "2024-01-01T00:00:00Z");
+        let copy = copy_database(&primary, &dir.path().join("copy.db"));
+
+        // 主电脑上继续修改
+        primary
+            .execute(
+                "UPDATE customers SET name = '华东板材集团', updated_at = '2024-03-01T08:00:00Z'
+                 WHERE id = ?1",
+                [DbUuid(kept)],
+            )
+            .unwrap();
+        let added = insert_customer(&primary, "木立方");
+        primary
+            .execute(
+                "UPDATE customers SET updated_at = '2024-03-02T08:00:00+08:00' WHERE id = ?1",
+                [DbUuid(added)],
+            )
+            .unwrap();
+        insert_quote(&primary, added, "2024-03-02T09:00:00Z");
+        insert_order(&primary, added, "2024-03-02T10:00:00Z");
+        // 回收站删除不修改 updated_at，由删除日志带出
+        primary
+            .execute(
+                "UPDATE quotes SET deleted_at = '2024-03-03T00:00:00Z' WHERE id = ?1",
+                [DbUuid(trashed_quote)],
+            )
+            .unwrap();
+        // 硬删除客户：订单没有外键级联，先删除订单，收款随订单级联删除
+        primary
+            .execute("DELETE FROM orders WHERE customer_id = ?1", [DbUuid(removed)])
+            .unwrap();
+        primary
+            .execute("DELETE FROM customers WHERE id = ?1", [DbUuid(removed)])
+            .unwrap();
+        assert_ne!(business_tables(&primary), business_tables(&copy));
+
+        let sync = DataSync::new(primary.clone());
+        let modified = sync.find_modified_since("quotes", since()).unwrap();
+        assert_eq!(modified.len(), 2);
+
+        let path = dir.path().join("usb").join("changes.jsonl");
+        let exported = sync.export_to(since(), &path).unwrap();
+        assert_eq!(exported.upserts, 6);
+        assert_eq!(exported.deletes, 3);
+
+        let secondary = DataSync::new(copy.clone());
+        assert_eq!(secondary.high_water().unwrap(), None);
+        let applied = secondary.apply_from(&path).unwrap();
+        assert!(!applied.skipped);
+        assert_eq!((applied.upserts, applied.deletes), (6, 3));
+        assert_eq!(business_tables(&copy), business_tables(&primary));
+        assert_eq!(secondary.high_water().unwrap(), Some(exported.until));
+
+        let outstanding: i64 = copy
+            .query_row(
+                "SELECT outstanding_amount FROM customer_summary WHERE customer_id = ?1",
+                [DbUuid(added)],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(outstanding, 300_000);
+
+        // 重复应用不做修改
+        let again = secondary.apply_from(&path).unwrap();
+        assert!(again.skipped);
+        assert_eq!(business_tables(&copy), business_tables(&primary));
+    }
+
+    #[test]
+    fn test_older_changeset_is_noop() {
+        let dir = TempDir::new().unwrap();
+        let primary = create_primary(&dir);
+        let customer = insert_customer(&primary, "华东板材");
+        let copy = copy_database(&primary, &dir.path().join("copy.db"));
+
+        let clock = Arc::new(ManualClock::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+        ));
+        let sync = DataSync::with_clock(primary.clone(), clock.clone());
+        primary
+            .execute(
+                "UPDATE customers SET name = '旧名称', updated_at = '2024-03-01T00:00:00Z'
+                 WHERE id = ?1",
+                [DbUuid(customer)],
+            )
+            .unwrap();
+        let older = dir.path().join("older.jsonl");
+        sync.export_to(since(), &older).unwrap();
+
+        clock.set(Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap());
+        primary
+            .execute(
+                "UPDATE customers SET name = '新名称', updated_at = '2024-05-15T00:00:00Z'
+                 WHERE id = ?1",
+                [DbUuid(customer)],
+            )
+            .unwrap();
+        let newer = dir.path().join("newer.jsonl");
+        sync.export_to(since(), &newer).unwrap();
+
+        let secondary = DataSync::new(copy.clone());
+        assert!(!secondary.apply_from(&newer).unwrap().skipped);
+        let stale = secondary.apply_from(&older).unwrap();
+        assert!(stale.skipped);
+        assert_eq!(stale.upserts, 0);
+        assert_eq!(business_tables(&copy), business_tables(&primary));
+
+        // 跳过中间的变更包时拒绝应用
+        clock.set(Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap());
+        let gap = dir.path().join("gap.jsonl");
+        sync.export_to(Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap(), &gap)
+            .unwrap();
+        let err = to_core(secondary.apply_from(&gap).unwrap_err());
+        assert!(matches!(err, CoreError::Validation(_)));
+    }
+
+    #[test]
+    fn test_truncated_changeset_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let primary = create_primary(&dir);
+        let customer = insert_customer(&primary, "华东板材");
+        primary
+            .execute(
+                "UPDATE customers SET updated_at = '2024-03-01T00:00:00Z' WHERE id = ?1",
+                [DbUuid(customer)],
+            )
+            .unwrap();
+        let copy = copy_database(&primary, &dir.path().join("copy.db"));
+
+        let path = dir.path().join("changes.jsonl");
+        DataSync::new(primary).export_to(since(), &path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let truncated: Vec<_> = content.lines().take(2).collect();
+        fs::write(&path, truncated.join("\n")).unwrap();
+
+        let secondary = DataSync::new(copy);
+        let err = to_core(secondary.apply_from(&path).unwrap_err());
+        assert!(matches!(err, CoreError::Validation(_)));
+        assert_eq!(secondary.high_water().unwrap(), None);
+    }
+
+    #[test]
+    fn test_canonical_versions_in_header() {
+        let dir = TempDir::new().unwrap();
+        let primary = create_primary(&dir);
+        let customer = insert_customer(&primary, "华东板材");
+        let copy = copy_database(&primary, &dir.path().join("copy.db"));
+        let order = insert_order(&primary, customer, "2024-03-01T00:00:00Z");
+        let address = Address {
+            province: "浙江省".to_string(),
+            city: "杭州市".to_string(),
+            district: None,
+            street: "文一西路998号".to_string(),
+            contact: None,
+            phone: None,
+        };
+        primary
+            .execute(
+                "UPDATE orders SET delivery_address = ?1 WHERE id = ?2",
+                params![to_canonical_json(&address).unwrap().to_string(), DbUuid(order)],
+            )
+            .unwrap();
+
+        let path = dir.path().join("changes.jsonl");
+        DataSync::new(primary.clone()).export_to(since(), &path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let (header, rest) = content.split_once('\n').unwrap();
+        let mut header: Value = serde_json::from_str(header).unwrap();
+        assert_eq!(header["entity_versions"]["address"], Address::SCHEMA_VERSION);
+        assert_eq!(header["entity_versions"]["order"], Order::SCHEMA_VERSION);
+        assert_eq!(header["entity_versions"]["customer"], Customer::SCHEMA_VERSION);
+
+        // 订单以规范JSON导出，整行只保留实体以外的列
+        let line: Value = rest
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .find(|line| line["entity"] == "orders")
+            .unwrap();
+        let record: Order = from_canonical_json(1, line["record"].clone()).unwrap();
+        assert_eq!(record.delivery_address, Some(address));
+        let row = line["row"].as_object().unwrap();
+        assert!(row.contains_key("deleted_at"));
+        assert!(Order::COLUMNS.iter().all(|column| !row.contains_key(*column)));
+
+        // 比本机新的结构版本
+        header["entity_versions"]["address"] = Value::from(Address::SCHEMA_VERSION + 1);
+        fs::write(&path, format!("{}\n{}", header, rest)).unwrap();
+        let secondary = DataSync::new(copy.clone());
+        let err = to_core(secondary.apply_from(&path).unwrap_err());
+        assert!(err.to_string().contains("请先升级应用"), "{err}");
+
+        // 没有记录结构版本的旧变更包按版本1处理
+        header.as_object_mut().unwrap().remove("entity_versions");
+        fs::write(&path, format!("{}\n{}", header, rest)).unwrap();
+        assert!(!secondary.apply_from(&path).unwrap().skipped);
+        assert_eq!(business_tables(&copy), business_tables(&primary));
+    }
+}
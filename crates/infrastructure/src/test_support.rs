@@ -0,0 +1,27 @@
+//! 测试支撑
+//!
+//! 单元测试共用的临时数据库。数据库文件放在返回的 [`TempDir`] 中，测试结束时随目录删除。
+
+use tempfile::TempDir;
+
+use crate::database::pool::DatabasePoolBuilder;
+use crate::database::{schema, DatabaseConnection, DatabasePool, MigrationManager};
+
+/// 未建表的临时数据库
+pub(crate) fn empty_pool() -> (TempDir, DatabasePool) {
+    let temp_dir = TempDir::new().unwrap();
+    let pool = DatabasePoolBuilder::new(temp_dir.path().join("test.db").to_string_lossy())
+        .build()
+        .unwrap();
+    (temp_dir, pool)
+}
+
+/// 已执行全部内置迁移的临时数据库
+pub(crate) fn migrated_pool() -> (TempDir, DatabasePool) {
+    let (temp_dir, pool) = empty_pool();
+    MigrationManager::new(DatabaseConnection::new(pool.clone()))
+        .add_migrations(schema::builtin_migrations())
+        .migrate(None)
+        .unwrap();
+    (temp_dir, pool)
+}
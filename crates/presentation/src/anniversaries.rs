@@ -0,0 +1,153 @@
+//! 近期需维护的客户
+//!
+//! 仪表盘卡片列出未来14天内的联系人生日和首次成交纪念日，每行提供拨打电话和记录互动
+//! 两个快捷操作：拨号走 [`ExternalActionService::dial`](crate::ExternalActionService::dial)，
+//! 记录互动打开客户详情。
+
+use minicrm_core::{UpcomingAnniversary, ANNIVERSARY_LOOKAHEAD_DAYS};
+use uuid::Uuid;
+
+use crate::navigation::Route;
+
+/// 卡片标题
+pub const UPCOMING_ANNIVERSARIES_TITLE: &str = "近期需维护的客户";
+
+/// 卡片中一行的快捷操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaintenanceAction {
+    /// 拨打电话
+    Dial,
+    /// 记录互动
+    LogInteraction,
+}
+
+impl MaintenanceAction {
+    /// 操作ID（界面回调使用）
+    pub fn id(&self) -> &'static str {
+        match self {
+            MaintenanceAction::Dial => "dial",
+            MaintenanceAction::LogInteraction => "log_interaction",
+        }
+    }
+
+    /// 按钮提示文字
+    pub fn label(&self) -> &'static str {
+        match self {
+            MaintenanceAction::Dial => "拨打电话",
+            MaintenanceAction::LogInteraction => "记录互动",
+        }
+    }
+}
+
+/// 卡片中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnniversaryRow {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 标题，如“华东建材 王经理 生日（3月8日）”
+    pub title: String,
+    /// 距今，如“今天”“明天”“5天后”
+    pub when: String,
+    /// 电话（拨号使用）
+    pub phone: Option<String>,
+    /// 可用的快捷操作（按钮顺序）
+    pub actions: Vec<MaintenanceAction>,
+    /// 记录互动时打开的页面
+    pub route: Route,
+}
+
+impl AnniversaryRow {
+    /// 由即将到来的纪念日创建
+    pub fn new(upcoming: &UpcomingAnniversary) -> Self {
+        let anniversary = &upcoming.anniversary;
+        let phone = anniversary
+            .phone
+            .as_deref()
+            .map(str::trim)
+            .filter(|phone| !phone.is_empty())
+            .map(str::to_string);
+        let mut actions = Vec::new();
+        if phone.is_some() {
+            actions.push(MaintenanceAction::Dial);
+        }
+        actions.push(MaintenanceAction::LogInteraction);
+        Self {
+            customer_id: anniversary.customer_id,
+            title: upcoming.title(),
+            when: match upcoming.days_until {
+                0 => "今天".to_string(),
+                1 => "明天".to_string(),
+                days => format!("{}天后", days),
+            },
+            phone,
+            actions,
+            route: Route::CustomerDetail {
+                id: anniversary.customer_id,
+            },
+        }
+    }
+}
+
+/// 卡片显示的行（调用方传入 [`ANNIVERSARY_LOOKAHEAD_DAYS`] 天内的纪念日）
+pub fn anniversary_rows(upcoming: &[UpcomingAnniversary]) -> Vec<AnniversaryRow> {
+    upcoming
+        .iter()
+        .filter(|u| u.days_until <= i64::from(ANNIVERSARY_LOOKAHEAD_DAYS))
+        .map(AnniversaryRow::new)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use minicrm_core::{upcoming_within, AnniversaryKind, CustomerAnniversary, LocalDate};
+
+    #[test]
+    fn test_rows_with_quick_actions() {
+        let anniversary = |name: &str, phone: Option<&str>, month, day| CustomerAnniversary {
+            customer_id: Uuid::new_v4(),
+            customer_name: name.to_string(),
+            contact_person: None,
+            phone: phone.map(str::to_string),
+            owner_id: None,
+            kind: AnniversaryKind::Birthday,
+            date: NaiveDate::from_ymd_opt(1985, month, day).unwrap(),
+        };
+        let today = LocalDate::new(2024, 3, 1).unwrap();
+        let upcoming = upcoming_within(
+            &[
+                anniversary("华东建材", Some(" 13800138000 "), 3, 2),
+                anniversary("鑫达装饰", None, 3, 1),
+                anniversary("远景家具", Some(""), 3, 9),
+            ],
+            today,
+            ANNIVERSARY_LOOKAHEAD_DAYS,
+        );
+        let rows = anniversary_rows(&upcoming);
+
+        let summary: Vec<(&str, Vec<MaintenanceAction>)> = rows
+            .iter()
+            .map(|row| (row.when.as_str(), row.actions.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("今天", vec![MaintenanceAction::LogInteraction]),
+                (
+                    "明天",
+                    vec![MaintenanceAction::Dial, MaintenanceAction::LogInteraction]
+                ),
+                ("8天后", vec![MaintenanceAction::LogInteraction]),
+            ]
+        );
+        assert_eq!(rows[1].title, "华东建材 生日（3月2日）");
+        assert_eq!(rows[1].phone.as_deref(), Some("13800138000"));
+        assert_eq!(
+            rows[1].route,
+            Route::CustomerDetail {
+                id: rows[1].customer_id
+            }
+        );
+    }
+}
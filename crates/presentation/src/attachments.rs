@@ -0,0 +1,325 @@
+//! 附件图库视图模型
+//!
+//! 工单、客户等页面以缩略图网格展示附件，点击后加载原图预览。
+//! 图片解码在阻塞线程池中进行，不占用界面线程；无法预览的附件显示通用图标。
+//! 声明类型与文件内容不符的附件在缩略图上显示警告标记。
+//! 已删除但仍被生成的PDF、已发送邮件等历史记录引用的附件默认隐藏，打开“历史”筛选后
+//! 以灰色显示。
+
+use std::sync::Arc;
+
+use minicrm_core::{AttachmentPreviewService, CoreResult, DecodedImage};
+use slint::{Image, Rgba8Pixel, SharedPixelBuffer};
+
+/// 已删除但仍被历史记录引用的附件的说明
+pub const TOMBSTONE_LABEL: &str = "已删除但被历史记录引用";
+
+/// 附件信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentEntry {
+    /// 内容哈希
+    pub hash: String,
+    /// 原始文件名
+    pub file_name: String,
+    /// MIME类型
+    pub mime: String,
+    /// 声明类型与文件内容不符
+    pub mime_mismatch: bool,
+    /// 已删除，仅因被历史记录引用而保留
+    pub tombstoned: bool,
+}
+
+/// 附件预览状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachmentPreview {
+    /// 正在加载
+    Loading,
+    /// 图片（缩略图或原图）
+    Image(DecodedImage),
+    /// 无法预览，显示通用图标
+    GenericIcon,
+}
+
+impl AttachmentPreview {
+    /// 转换为Slint图片，没有图片时返回空图片（界面改为显示图标）
+    ///
+    /// 必须在界面线程调用。
+    pub fn to_slint_image(&self) -> Image {
+        match self {
+            Self::Image(image) => to_slint_image(image),
+            Self::Loading | Self::GenericIcon => Image::default(),
+        }
+    }
+
+    /// 是否显示通用图标
+    pub fn is_generic_icon(&self) -> bool {
+        matches!(self, Self::GenericIcon)
+    }
+}
+
+/// 把解码后的图片转换为Slint图片
+pub fn to_slint_image(image: &DecodedImage) -> Image {
+    Image::from_rgba8(SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
+        &image.rgba,
+        image.width,
+        image.height,
+    ))
+}
+
+/// 图库中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachmentTile {
+    /// 附件信息
+    pub entry: AttachmentEntry,
+    /// 缩略图
+    pub thumbnail: AttachmentPreview,
+}
+
+impl AttachmentTile {
+    /// 是否显示类型不符的警告标记
+    pub fn shows_warning(&self) -> bool {
+        self.entry.mime_mismatch
+    }
+
+    /// 警告标记的提示文字
+    pub fn warning_tooltip(&self) -> Option<String> {
+        self.shows_warning()
+            .then(|| format!("“{}”的实际内容与文件类型不符，打开前请确认来源", self.entry.file_name))
+    }
+
+    /// 是否以灰色显示（已删除但被历史记录引用）
+    pub fn is_greyed_out(&self) -> bool {
+        self.entry.tombstoned
+    }
+
+    /// 灰色附件下方的说明
+    pub fn status_label(&self) -> Option<&'static str> {
+        self.is_greyed_out().then_some(TOMBSTONE_LABEL)
+    }
+}
+
+/// 附件图库视图模型
+#[derive(Clone)]
+pub struct AttachmentGalleryViewModel {
+    previews: Arc<dyn AttachmentPreviewService + Send + Sync>,
+    /// 附件列表
+    pub tiles: Vec<AttachmentTile>,
+    /// 当前预览的附件序号
+    pub selected: Option<usize>,
+    /// 当前预览的原图
+    pub full_image: AttachmentPreview,
+    /// “历史”筛选：显示已删除但被引用的附件
+    pub show_history: bool,
+}
+
+impl std::fmt::Debug for AttachmentGalleryViewModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachmentGalleryViewModel")
+            .field("tiles", &self.tiles)
+            .field("selected", &self.selected)
+            .field("show_history", &self.show_history)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AttachmentGalleryViewModel {
+    /// 创建图库，缩略图初始为加载中
+    pub fn new(
+        previews: Arc<dyn AttachmentPreviewService + Send + Sync>,
+        entries: Vec<AttachmentEntry>,
+    ) -> Self {
+        let tiles = entries
+            .into_iter()
+            .map(|entry| AttachmentTile {
+                entry,
+                thumbnail: AttachmentPreview::Loading,
+            })
+            .collect();
+        Self {
+            previews,
+            tiles,
+            selected: None,
+            full_image: AttachmentPreview::Loading,
+            show_history: false,
+        }
+    }
+
+    /// 切换“历史”筛选
+    pub fn set_show_history(&mut self, show_history: bool) {
+        self.show_history = show_history;
+    }
+
+    /// 当前筛选下显示的附件序号
+    pub fn visible_tiles(&self) -> Vec<usize> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| self.show_history || !tile.entry.tombstoned)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// 加载全部缩略图（首次请求时由存储生成，各附件并行解码）
+    pub async fn load_thumbnails(&mut self) {
+        let handles: Vec<_> = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                let previews = self.previews.clone();
+                let entry = tile.entry.clone();
+                tokio::task::spawn_blocking(move || previews.thumbnail(&entry.hash, &entry.mime))
+            })
+            .collect();
+        for (tile, handle) in self.tiles.iter_mut().zip(handles) {
+            tile.thumbnail = preview_from(handle.await);
+        }
+    }
+
+    /// 打开附件原图预览
+    pub async fn open(&mut self, index: usize) -> &AttachmentPreview {
+        let Some(entry) = self.tiles.get(index).map(|tile| tile.entry.clone()) else {
+            return &self.full_image;
+        };
+        self.selected = Some(index);
+        self.full_image = AttachmentPreview::Loading;
+        let previews = self.previews.clone();
+        let decoded =
+            tokio::task::spawn_blocking(move || previews.full_image(&entry.hash, &entry.mime));
+        self.full_image = preview_from(decoded.await);
+        &self.full_image
+    }
+
+    /// 关闭原图预览
+    pub fn close(&mut self) {
+        self.selected = None;
+        self.full_image = AttachmentPreview::Loading;
+    }
+}
+
+/// 解码结果转换为预览状态，任何失败都降级为通用图标
+fn preview_from(
+    result: Result<CoreResult<Option<DecodedImage>>, tokio::task::JoinError>,
+) -> AttachmentPreview {
+    match result {
+        Ok(Ok(Some(image))) => AttachmentPreview::Image(image),
+        _ => AttachmentPreview::GenericIcon,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minicrm_core::CoreError;
+
+    struct FakePreviews;
+
+    impl AttachmentPreviewService for FakePreviews {
+        fn thumbnail(&self, hash: &str, _mime: &str) -> CoreResult<Option<DecodedImage>> {
+            match hash {
+                "photo" => Ok(Some(pixel(2, 1))),
+                "missing" => Err(CoreError::not_found("附件")),
+                _ => Ok(None),
+            }
+        }
+
+        fn full_image(&self, hash: &str, _mime: &str) -> CoreResult<Option<DecodedImage>> {
+            match hash {
+                "photo" => Ok(Some(pixel(4, 2))),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    fn pixel(width: u32, height: u32) -> DecodedImage {
+        DecodedImage {
+            width,
+            height,
+            rgba: vec![255; (width * height * 4) as usize],
+        }
+    }
+
+    fn entry(hash: &str, mime: &str) -> AttachmentEntry {
+        AttachmentEntry {
+            hash: hash.to_string(),
+            file_name: format!("{}.bin", hash),
+            mime: mime.to_string(),
+            mime_mismatch: false,
+            tombstoned: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gallery_degrades_to_generic_icon() {
+        let mut gallery = AttachmentGalleryViewModel::new(
+            Arc::new(FakePreviews),
+            vec![
+                entry("photo", "image/jpeg"),
+                entry("scan", "application/pdf"),
+                entry("missing", "image/png"),
+            ],
+        );
+        assert!(
+            gallery
+                .tiles
+                .iter()
+                .all(|tile| tile.thumbnail == AttachmentPreview::Loading)
+        );
+
+        gallery.load_thumbnails().await;
+        assert_eq!(
+            gallery.tiles[0].thumbnail,
+            AttachmentPreview::Image(pixel(2, 1))
+        );
+        assert!(gallery.tiles[1].thumbnail.is_generic_icon());
+        assert!(gallery.tiles[2].thumbnail.is_generic_icon());
+
+        assert_eq!(
+            gallery.open(0).await,
+            &AttachmentPreview::Image(pixel(4, 2))
+        );
+        assert_eq!(gallery.selected, Some(0));
+        assert!(gallery.open(1).await.is_generic_icon());
+
+        gallery.close();
+        assert_eq!(gallery.selected, None);
+    }
+
+    #[test]
+    fn test_mismatch_shows_warning_badge() {
+        let flagged = AttachmentEntry {
+            mime_mismatch: true,
+            ..entry("scan", "application/pdf")
+        };
+        let gallery = AttachmentGalleryViewModel::new(
+            Arc::new(FakePreviews),
+            vec![entry("photo", "image/jpeg"), flagged],
+        );
+        assert!(!gallery.tiles[0].shows_warning());
+        assert_eq!(gallery.tiles[0].warning_tooltip(), None);
+        assert!(gallery.tiles[1].shows_warning());
+        assert!(gallery.tiles[1]
+            .warning_tooltip()
+            .unwrap()
+            .contains("scan.bin"));
+    }
+
+    #[test]
+    fn test_tombstoned_shown_greyed_under_history_filter() {
+        let deleted = AttachmentEntry {
+            tombstoned: true,
+            ..entry("drawing", "image/png")
+        };
+        let mut gallery = AttachmentGalleryViewModel::new(
+            Arc::new(FakePreviews),
+            vec![entry("photo", "image/jpeg"), deleted],
+        );
+        assert_eq!(gallery.visible_tiles(), vec![0]);
+
+        gallery.set_show_history(true);
+        assert_eq!(gallery.visible_tiles(), vec![0, 1]);
+        assert!(!gallery.tiles[0].is_greyed_out());
+        assert_eq!(gallery.tiles[0].status_label(), None);
+        assert!(gallery.tiles[1].is_greyed_out());
+        assert_eq!(gallery.tiles[1].status_label(), Some(TOMBSTONE_LABEL));
+    }
+}
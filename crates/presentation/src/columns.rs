@@ -0,0 +1,405 @@
+//! 列表列设置
+//!
+//! 每个列表界面登记可用的列（[`ColumnConfig`]），用户通过列选择器调整显示、顺序和宽度，
+//! 结果按界面ID保存在界面状态中。列表只查询可见列（[`Projection`]），隐藏的计算列
+//! 不会执行对应的子查询。
+
+use minicrm_core::Projection;
+use serde::{Deserialize, Serialize};
+
+/// 列的最小宽度
+pub const MIN_COLUMN_WIDTH: u32 = 40;
+
+/// 列定义
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnDef {
+    /// 列ID（与读取模型的列ID一致）
+    pub id: &'static str,
+    /// 标题
+    pub title: &'static str,
+    /// 默认是否显示
+    pub default_visible: bool,
+    /// 默认宽度
+    pub default_width: u32,
+}
+
+impl ColumnDef {
+    const fn new(id: &'static str, title: &'static str, default_visible: bool, width: u32) -> Self {
+        Self {
+            id,
+            title,
+            default_visible,
+            default_width: width,
+        }
+    }
+}
+
+/// 列表界面的可用列（顺序即默认顺序）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnConfig {
+    /// 界面ID（界面状态中的键）
+    pub screen: &'static str,
+    /// 可用列
+    pub columns: Vec<ColumnDef>,
+}
+
+impl ColumnConfig {
+    /// 客户列表
+    pub fn customer_list() -> Self {
+        Self {
+            screen: "customer_list",
+            columns: vec![
+                ColumnDef::new("name", "客户名称", true, 200),
+                ColumnDef::new("company", "公司", true, 160),
+                ColumnDef::new("phone", "电话", true, 120),
+                ColumnDef::new("email", "邮箱", false, 180),
+                ColumnDef::new("address", "地址", false, 240),
+                ColumnDef::new("created_at", "创建日期", true, 100),
+                ColumnDef::new("last_contact", "最近联系", false, 100),
+                ColumnDef::new("order_total", "订单总额", false, 110),
+                ColumnDef::new("open_tasks", "未完成任务", false, 90),
+                ColumnDef::new("outstanding", "未收款", false, 110),
+                ColumnDef::new("accepted_quotes_12m", "近12个月成交报价", false, 130),
+                ColumnDef::new("activity_score", "活跃度", false, 80),
+            ],
+        }
+    }
+
+    /// 查找列定义
+    pub fn column(&self, id: &str) -> Option<&ColumnDef> {
+        self.columns.iter().find(|c| c.id == id)
+    }
+
+    /// 默认列设置
+    pub fn default_layout(&self) -> ColumnLayout {
+        ColumnLayout {
+            columns: self
+                .columns
+                .iter()
+                .map(|c| ColumnSetting {
+                    id: c.id.to_string(),
+                    visible: c.default_visible,
+                    width: c.default_width,
+                })
+                .collect(),
+        }
+    }
+
+    /// 按当前版本的可用列整理保存的设置
+    ///
+    /// 已不存在的列被丢弃，其余列保持保存的顺序；新增的列按默认设置追加在末尾。
+    /// 整理后没有可见列时恢复默认设置。
+    pub fn reconcile(&self, saved: &ColumnLayout) -> ColumnLayout {
+        let mut columns: Vec<ColumnSetting> = Vec::with_capacity(self.columns.len());
+        for setting in &saved.columns {
+            if self.column(&setting.id).is_some() && !columns.iter().any(|c| c.id == setting.id) {
+                columns.push(ColumnSetting {
+                    width: setting.width.max(MIN_COLUMN_WIDTH),
+                    ..setting.clone()
+                });
+            }
+        }
+        for def in &self.columns {
+            if !columns.iter().any(|c| c.id == def.id) {
+                columns.push(ColumnSetting {
+                    id: def.id.to_string(),
+                    visible: def.default_visible,
+                    width: def.default_width,
+                });
+            }
+        }
+        if !columns.iter().any(|c| c.visible) {
+            return self.default_layout();
+        }
+        ColumnLayout { columns }
+    }
+}
+
+/// 单列设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnSetting {
+    /// 列ID
+    pub id: String,
+    /// 是否显示
+    pub visible: bool,
+    /// 宽度
+    pub width: u32,
+}
+
+/// 列表的列设置（按显示顺序，保存在界面状态中）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnLayout {
+    /// 各列设置
+    #[serde(default)]
+    pub columns: Vec<ColumnSetting>,
+}
+
+/// 可见列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisibleColumn {
+    /// 列ID
+    pub id: String,
+    /// 标题
+    pub title: String,
+    /// 宽度
+    pub width: u32,
+}
+
+/// 列选择器中的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnChooserItem {
+    /// 列ID
+    pub id: String,
+    /// 标题
+    pub title: String,
+    /// 是否显示
+    pub visible: bool,
+}
+
+/// 列选择器视图模型
+#[derive(Debug, Clone)]
+pub struct ColumnChooserViewModel {
+    config: ColumnConfig,
+    layout: ColumnLayout,
+}
+
+impl ColumnChooserViewModel {
+    /// 以可用列和保存的设置创建选择器
+    pub fn new(config: ColumnConfig, saved: Option<&ColumnLayout>) -> Self {
+        let layout = saved.map_or_else(|| config.default_layout(), |s| config.reconcile(s));
+        Self { config, layout }
+    }
+
+    /// 界面ID
+    pub fn screen(&self) -> &'static str {
+        self.config.screen
+    }
+
+    /// 选择器中的各项（按显示顺序）
+    pub fn items(&self) -> Vec<ColumnChooserItem> {
+        self.layout
+            .columns
+            .iter()
+            .filter_map(|s| {
+                self.config.column(&s.id).map(|def| ColumnChooserItem {
+                    id: s.id.clone(),
+                    title: def.title.to_string(),
+                    visible: s.visible,
+                })
+            })
+            .collect()
+    }
+
+    /// 切换列的显示，最后一个可见列不能隐藏
+    pub fn toggle(&mut self, id: &str) -> bool {
+        let visible_count = self.layout.columns.iter().filter(|c| c.visible).count();
+        let Some(setting) = self.layout.columns.iter_mut().find(|c| c.id == id) else {
+            return false;
+        };
+        if setting.visible && visible_count == 1 {
+            return false;
+        }
+        setting.visible = !setting.visible;
+        true
+    }
+
+    /// 把列移动到指定位置
+    pub fn move_column(&mut self, id: &str, index: usize) -> bool {
+        let Some(from) = self.layout.columns.iter().position(|c| c.id == id) else {
+            return false;
+        };
+        let setting = self.layout.columns.remove(from);
+        let index = index.min(self.layout.columns.len());
+        self.layout.columns.insert(index, setting);
+        from != index
+    }
+
+    /// 设置列宽
+    pub fn set_width(&mut self, id: &str, width: u32) {
+        if let Some(setting) = self.layout.columns.iter_mut().find(|c| c.id == id) {
+            setting.width = width.max(MIN_COLUMN_WIDTH);
+        }
+    }
+
+    /// 恢复默认设置
+    pub fn reset(&mut self) {
+        self.layout = self.config.default_layout();
+    }
+
+    /// 当前设置
+    pub fn layout(&self) -> &ColumnLayout {
+        &self.layout
+    }
+
+    /// 需要保存的设置，与默认设置相同时为空（界面状态中移除该界面的记录）
+    pub fn saved_layout(&self) -> Option<ColumnLayout> {
+        (self.layout != self.config.default_layout()).then(|| self.layout.clone())
+    }
+
+    /// 可见列（按显示顺序）
+    pub fn visible_columns(&self) -> Vec<VisibleColumn> {
+        self.layout
+            .columns
+            .iter()
+            .filter(|s| s.visible)
+            .filter_map(|s| {
+                self.config.column(&s.id).map(|def| VisibleColumn {
+                    id: s.id.clone(),
+                    title: def.title.to_string(),
+                    width: s.width,
+                })
+            })
+            .collect()
+    }
+
+    /// 列表查询的列投影
+    pub fn projection(&self) -> Projection {
+        Projection::new(self.visible_columns().into_iter().map(|c| c.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use minicrm_core::{
+        CoreResult, CustomerListReadModel, CustomerListRow, PagedResult, QueryFilter,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    /// 记录计算列被计算次数的读取模型
+    #[derive(Default)]
+    struct CountingReadModel {
+        computed: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CustomerListReadModel for CountingReadModel {
+        async fn list_customers(
+            &self,
+            filter: &QueryFilter,
+            projection: &Projection,
+        ) -> CoreResult<PagedResult<CustomerListRow>> {
+            let rows: Vec<CustomerListRow> = (0..3)
+                .map(|i| {
+                    let cells = projection
+                        .columns
+                        .iter()
+                        .map(|column| {
+                            if column == "last_contact" || column == "order_total" {
+                                self.computed.fetch_add(1, Ordering::SeqCst);
+                            }
+                            (column.clone(), format!("{}-{}", column, i))
+                        })
+                        .collect();
+                    CustomerListRow {
+                        id: Uuid::new_v4(),
+                        cells,
+                    }
+                })
+                .collect();
+            Ok(PagedResult::new(rows, 3, &filter.pagination))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hidden_expensive_columns_are_not_computed() {
+        let read_model = CountingReadModel::default();
+        let mut chooser = ColumnChooserViewModel::new(ColumnConfig::customer_list(), None);
+        let filter = QueryFilter::new();
+
+        let page = read_model
+            .list_customers(&filter, &chooser.projection())
+            .await
+            .unwrap();
+        assert_eq!(read_model.computed.load(Ordering::SeqCst), 0);
+        assert!(!page.items[0].cells.contains_key("order_total"));
+
+        assert!(chooser.toggle("order_total"));
+        read_model
+            .list_customers(&filter, &chooser.projection())
+            .await
+            .unwrap();
+        assert_eq!(read_model.computed.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_toggle_reorder_and_reset() {
+        let mut chooser = ColumnChooserViewModel::new(ColumnConfig::customer_list(), None);
+        assert_eq!(chooser.saved_layout(), None);
+
+        assert!(chooser.toggle("last_contact"));
+        assert!(chooser.move_column("last_contact", 1));
+        chooser.set_width("name", 10);
+        let visible: Vec<String> = chooser
+            .visible_columns()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(
+            visible,
+            vec!["name", "last_contact", "company", "phone", "created_at"]
+        );
+        assert_eq!(chooser.visible_columns()[0].width, MIN_COLUMN_WIDTH);
+        assert!(chooser.saved_layout().is_some());
+
+        chooser.reset();
+        assert_eq!(chooser.saved_layout(), None);
+    }
+
+    #[test]
+    fn test_last_visible_column_cannot_be_hidden() {
+        let config = ColumnConfig {
+            screen: "test",
+            columns: vec![
+                ColumnDef::new("a", "A", true, 100),
+                ColumnDef::new("b", "B", false, 100),
+            ],
+        };
+        let mut chooser = ColumnChooserViewModel::new(config, None);
+        assert!(!chooser.toggle("a"));
+        assert!(!chooser.toggle("missing"));
+        assert_eq!(chooser.projection(), Projection::new(["a"]));
+    }
+
+    #[test]
+    fn test_order_is_stable_when_a_column_is_removed() {
+        let saved = ColumnLayout {
+            columns: ["created_at", "tags", "name", "phone"]
+                .iter()
+                .map(|id| ColumnSetting {
+                    id: (*id).to_string(),
+                    visible: true,
+                    width: 120,
+                })
+                .collect(),
+        };
+        // 较新版本去掉了 tags 列
+        let chooser = ColumnChooserViewModel::new(ColumnConfig::customer_list(), Some(&saved));
+        let ids: Vec<String> = chooser.items().into_iter().map(|i| i.id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "created_at",
+                "name",
+                "phone",
+                "company",
+                "email",
+                "address",
+                "last_contact",
+                "order_total",
+                "open_tasks",
+                "outstanding",
+                "accepted_quotes_12m",
+                "activity_score"
+            ]
+        );
+        let visible: Vec<String> = chooser
+            .visible_columns()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(visible, vec!["created_at", "name", "phone", "company"]);
+    }
+}
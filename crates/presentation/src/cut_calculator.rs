@@ -0,0 +1,204 @@
+//! 开料计算器模块
+//!
+//! 报价编辑器中为选中的明细打开开料计算器：录入客户的开料清单，按面积估算需要的整板
+//! 张数，确认后把张数回填为该明细的数量。板材尺寸取自明细规格（如“1220×2440×18”），
+//! 规格中没有尺寸时按标准板计算。
+
+use minicrm_core::{CoreError, CoreResult, CutCalculator, CutPiece, CutResult, SheetSize};
+
+use crate::formatting::format_area;
+use crate::view_models::QuoteEditorViewModel;
+
+/// 开料计算器对话框视图模型
+#[derive(Debug, Clone, PartialEq)]
+pub struct CutCalculatorDialog {
+    /// 回填数量的明细序号
+    pub line_index: usize,
+    /// 明细的产品名称
+    pub product_name: String,
+    /// 计算参数
+    pub calculator: CutCalculator,
+    /// 开料清单
+    pub pieces: Vec<CutPiece>,
+}
+
+impl CutCalculatorDialog {
+    /// 为报价编辑器中的明细打开计算器，明细不存在时为空
+    pub fn for_line(editor: &QuoteEditorViewModel, line_index: usize) -> Option<Self> {
+        let item = editor.data.quote.items.get(line_index)?;
+        let sheet = item
+            .specification
+            .as_deref()
+            .and_then(SheetSize::from_specification)
+            .unwrap_or_default();
+        Some(Self {
+            line_index,
+            product_name: item.product_name.clone(),
+            calculator: CutCalculator::for_sheet(sheet),
+            pieces: Vec::new(),
+        })
+    }
+
+    /// 添加一种板件
+    pub fn add_piece(&mut self, piece: CutPiece) {
+        self.pieces.push(piece);
+    }
+
+    /// 删除一种板件
+    pub fn remove_piece(&mut self, index: usize) {
+        if index < self.pieces.len() {
+            self.pieces.remove(index);
+        }
+    }
+
+    /// 标题，如“开料计算 - 生态板（1220×2440）”
+    pub fn title(&self) -> String {
+        format!(
+            "开料计算 - {}（{}×{}）",
+            self.product_name, self.calculator.sheet.width_mm, self.calculator.sheet.length_mm
+        )
+    }
+
+    /// 计算结果
+    ///
+    /// # Errors
+    ///
+    /// 开料清单无效（为空、尺寸为0或板件超出板材尺寸）时返回验证错误。
+    pub fn calculate(&self) -> CoreResult<CutResult> {
+        self.calculator.calculate(&self.pieces)
+    }
+
+    /// 结果摘要，如“需要 3 张，板件 7.13平方米，余料约 1.80平方米，利用率 79.8%”
+    pub fn summary(result: &CutResult) -> String {
+        format!(
+            "需要 {} 张，板件 {}，余料约 {}，利用率 {:.1}%",
+            result.sheets,
+            format_area(result.pieces_area_m2),
+            format_area(result.offcut_m2),
+            result.utilization_percent
+        )
+    }
+
+    /// 利用率偏低时的提醒
+    pub fn warning(&self, result: &CutResult) -> Option<String> {
+        result.low_utilization.then(|| {
+            format!(
+                "利用率 {:.1}% 低于 {:.0}%，余料较多，请确认是否需要整张板",
+                result.utilization_percent, self.calculator.min_utilization_percent
+            )
+        })
+    }
+
+    /// 把计算出的张数回填为明细数量，返回张数
+    ///
+    /// # Errors
+    ///
+    /// 开料清单无效或明细已不存在时返回验证错误，明细数量保持不变。
+    pub fn apply(&self, editor: &mut QuoteEditorViewModel) -> CoreResult<u32> {
+        let result = self.calculate()?;
+        if editor.set_line_quantity(self.line_index, f64::from(result.sheets)) {
+            Ok(result.sheets)
+        } else {
+            Err(CoreError::validation("报价明细已被删除"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use minicrm_application::{MarginThresholds, QuoteEditorData, QuoteMargin};
+    use minicrm_core::{Currency, Money, Quote, QuoteItem, QuoteStatus};
+    use uuid::Uuid;
+
+    fn item(name: &str, specification: Option<&str>, unit_price: f64) -> QuoteItem {
+        QuoteItem {
+            id: Uuid::new_v4(),
+            quote_id: Uuid::nil(),
+            product_id: None,
+            product_name: name.to_string(),
+            specification: specification.map(str::to_string),
+            unit: Some("张".to_string()),
+            quantity: 1.0,
+            unit_price,
+            line_total: Money::from_yuan(unit_price),
+        }
+    }
+
+    fn editor() -> QuoteEditorViewModel {
+        let items = vec![
+            item("生态板", Some("1220×2440×18mm"), 200.0),
+            item("封边条", None, 50.0),
+        ];
+        let now = Utc::now();
+        let margin = QuoteMargin::from_costs(&items, &[Some(150.0), None]);
+        let quote = Quote {
+            id: Uuid::new_v4(),
+            quote_number: "Q-2024-0001".to_string(),
+            customer_id: Uuid::new_v4(),
+            status: QuoteStatus::Draft,
+            total_amount: Money::from_yuan(250.0),
+            currency: Currency::default(),
+            items,
+            valid_until: now,
+            remarks: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        };
+        QuoteEditorViewModel::new(
+            QuoteEditorData {
+                quote,
+                margin: Some(margin),
+            },
+            MarginThresholds::default(),
+        )
+    }
+
+    #[test]
+    fn test_apply_sheet_count_to_line() {
+        let mut editor = editor();
+        let mut dialog = CutCalculatorDialog::for_line(&editor, 0).unwrap();
+        assert_eq!(dialog.calculator.sheet, SheetSize::STANDARD);
+        assert_eq!(dialog.title(), "开料计算 - 生态板（1220×2440）");
+
+        // 清单为空时不回填
+        assert!(dialog.apply(&mut editor).is_err());
+        assert!((editor.data.quote.items[0].quantity - 1.0).abs() < 1e-9);
+
+        dialog.add_piece(CutPiece::new(2400, 600, 4));
+        dialog.add_piece(CutPiece::new(3000, 600, 1));
+        assert!(dialog.calculate().is_err());
+        dialog.remove_piece(1);
+
+        let result = dialog.calculate().unwrap();
+        assert_eq!(
+            CutCalculatorDialog::summary(&result),
+            "需要 3 张，板件 5.76平方米，余料约 3.17平方米，利用率 64.5%"
+        );
+        assert_eq!(dialog.warning(&result), None);
+
+        assert_eq!(dialog.apply(&mut editor).unwrap(), 3);
+        let quote = &editor.data.quote;
+        assert!((quote.items[0].quantity - 3.0).abs() < 1e-9);
+        assert_eq!(quote.total_amount, Money::from_yuan(650.0));
+        let margin = editor.data.margin.as_ref().unwrap();
+        assert_eq!(margin.lines[0].unit_cost, Some(150.0));
+        assert!((margin.margin - 150.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_low_utilization_warning() {
+        let editor = editor();
+        let mut dialog = CutCalculatorDialog::for_line(&editor, 1).unwrap();
+        assert!(CutCalculatorDialog::for_line(&editor, 2).is_none());
+
+        dialog.add_piece(CutPiece::new(600, 400, 2));
+        let result = dialog.calculate().unwrap();
+        assert_eq!(result.sheets, 1);
+        let warning = dialog.warning(&result).unwrap();
+        assert!(warning.starts_with("利用率 16.1% 低于 60%"));
+    }
+}
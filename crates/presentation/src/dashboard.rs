@@ -0,0 +1,389 @@
+//! 仪表盘布局模块
+//!
+//! 仪表盘卡片由注册表描述（标题、所需角色、数据来源）。每个用户可以调整卡片顺序
+//! 和显示与否，布局保存在界面状态中；加载数据时只查询可见卡片的数据来源。
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use minicrm_core::{CoreResult, UserRole};
+use serde::{Deserialize, Serialize};
+
+/// 卡片内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardData {
+    /// 主要数值（如“128”、“¥3,200.00”）
+    pub value: String,
+    /// 补充说明
+    pub detail: Option<String>,
+}
+
+/// 卡片数据来源
+#[async_trait]
+pub trait CardDataSource: Send + Sync {
+    /// 加载卡片内容
+    async fn load(&self) -> CoreResult<CardData>;
+}
+
+/// 仪表盘卡片定义
+#[derive(Clone)]
+pub struct DashboardCard {
+    /// 卡片ID（保存在布局中，发布后不应修改）
+    pub id: &'static str,
+    /// 卡片标题
+    pub title: &'static str,
+    /// 查看卡片所需的最低角色
+    pub required_role: UserRole,
+    /// 数据来源
+    pub source: Arc<dyn CardDataSource>,
+}
+
+impl std::fmt::Debug for DashboardCard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DashboardCard")
+            .field("id", &self.id)
+            .field("title", &self.title)
+            .field("required_role", &self.required_role)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 仪表盘卡片注册表（按注册顺序作为默认布局）
+#[derive(Debug, Clone, Default)]
+pub struct DashboardCardRegistry {
+    cards: Vec<DashboardCard>,
+}
+
+impl DashboardCardRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册卡片，相同ID的卡片会被替换
+    pub fn register(&mut self, card: DashboardCard) {
+        match self.cards.iter_mut().find(|c| c.id == card.id) {
+            Some(existing) => *existing = card,
+            None => self.cards.push(card),
+        }
+    }
+
+    /// 按ID查找卡片
+    pub fn get(&self, id: &str) -> Option<&DashboardCard> {
+        self.cards.iter().find(|c| c.id == id)
+    }
+
+    /// 角色可以查看的卡片
+    pub fn available_for(&self, role: UserRole) -> Vec<&DashboardCard> {
+        self.cards
+            .iter()
+            .filter(|c| role.satisfies(c.required_role))
+            .collect()
+    }
+}
+
+/// 布局中的一张卡片
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardSlot {
+    /// 卡片ID
+    pub id: String,
+    /// 是否显示
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+/// 用户的仪表盘布局（卡片顺序与显示设置）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DashboardLayout {
+    /// 按显示顺序排列的卡片
+    #[serde(default)]
+    pub cards: Vec<CardSlot>,
+}
+
+/// 仪表盘上的一张卡片
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DashboardCardView {
+    /// 卡片ID
+    pub id: &'static str,
+    /// 卡片标题
+    pub title: &'static str,
+    /// 是否显示
+    pub visible: bool,
+    /// 已加载的内容
+    pub content: Option<CardData>,
+    /// 加载失败的原因
+    pub error: Option<String>,
+}
+
+/// 仪表盘视图模型
+///
+/// “编辑布局”模式下可调整顺序和显示与否，退出编辑时返回需要保存的布局。
+#[derive(Debug)]
+pub struct DashboardViewModel {
+    registry: Arc<DashboardCardRegistry>,
+    /// 按显示顺序排列的卡片（含隐藏的卡片）
+    pub cards: Vec<DashboardCardView>,
+    /// 是否处于编辑布局模式
+    pub editing: bool,
+}
+
+impl DashboardViewModel {
+    /// 按用户角色和保存的布局创建视图模型
+    ///
+    /// 布局中已不存在或角色无权查看的卡片被忽略，布局中没有的新卡片以可见状态追加在末尾。
+    pub fn new(
+        registry: Arc<DashboardCardRegistry>,
+        role: UserRole,
+        layout: &DashboardLayout,
+    ) -> Self {
+        let available = registry.available_for(role);
+        let mut cards: Vec<DashboardCardView> = Vec::with_capacity(available.len());
+        let view = |card: &DashboardCard, visible: bool| DashboardCardView {
+            id: card.id,
+            title: card.title,
+            visible,
+            content: None,
+            error: None,
+        };
+        for slot in &layout.cards {
+            let Some(card) = available.iter().find(|c| c.id == slot.id) else {
+                continue;
+            };
+            if cards.iter().all(|c| c.id != card.id) {
+                cards.push(view(card, slot.visible));
+            }
+        }
+        for card in &available {
+            if cards.iter().all(|c| c.id != card.id) {
+                cards.push(view(card, true));
+            }
+        }
+        Self {
+            registry,
+            cards,
+            editing: false,
+        }
+    }
+
+    /// 当前布局
+    pub fn layout(&self) -> DashboardLayout {
+        DashboardLayout {
+            cards: self
+                .cards
+                .iter()
+                .map(|c| CardSlot {
+                    id: c.id.to_string(),
+                    visible: c.visible,
+                })
+                .collect(),
+        }
+    }
+
+    /// 可见的卡片
+    pub fn visible_cards(&self) -> impl Iterator<Item = &DashboardCardView> {
+        self.cards.iter().filter(|c| c.visible)
+    }
+
+    /// 进入编辑布局模式
+    pub fn begin_editing(&mut self) {
+        self.editing = true;
+    }
+
+    /// 退出编辑布局模式，返回需要保存的布局
+    pub fn finish_editing(&mut self) -> DashboardLayout {
+        self.editing = false;
+        self.layout()
+    }
+
+    /// 将卡片移动到指定位置（超出范围时移到末尾），卡片不存在时返回 `false`
+    pub fn move_card(&mut self, id: &str, new_index: usize) -> bool {
+        let Some(index) = self.cards.iter().position(|c| c.id == id) else {
+            return false;
+        };
+        let card = self.cards.remove(index);
+        let new_index = new_index.min(self.cards.len());
+        self.cards.insert(new_index, card);
+        true
+    }
+
+    /// 设置卡片是否显示，卡片不存在时返回 `false`
+    ///
+    /// 隐藏的卡片会清空已加载的内容，重新显示后需要再次加载。
+    pub fn set_card_visible(&mut self, id: &str, visible: bool) -> bool {
+        let Some(card) = self.cards.iter_mut().find(|c| c.id == id) else {
+            return false;
+        };
+        card.visible = visible;
+        if !visible {
+            card.content = None;
+            card.error = None;
+        }
+        true
+    }
+
+    /// 加载全部可见卡片的数据，返回加载的卡片数
+    ///
+    /// 单张卡片加载失败只记录在该卡片上，不影响其他卡片。
+    pub async fn load(&mut self) -> usize {
+        let mut loaded = 0;
+        for card in self.cards.iter_mut().filter(|c| c.visible) {
+            let Some(definition) = self.registry.get(card.id) else {
+                continue;
+            };
+            match definition.source.load().await {
+                Ok(content) => {
+                    card.content = Some(content);
+                    card.error = None;
+                }
+                Err(e) => {
+                    card.content = None;
+                    card.error = Some(e.to_string());
+                }
+            }
+            loaded += 1;
+        }
+        loaded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingSource {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CardDataSource for CountingSource {
+        async fn load(&self) -> CoreResult<CardData> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(CardData {
+                value: calls.to_string(),
+                detail: None,
+            })
+        }
+    }
+
+    fn registry(
+        cards: &[(&'static str, UserRole)],
+    ) -> (Arc<DashboardCardRegistry>, Vec<Arc<CountingSource>>) {
+        let mut registry = DashboardCardRegistry::new();
+        let mut sources = Vec::new();
+        for (id, role) in cards {
+            let source = Arc::new(CountingSource::default());
+            registry.register(DashboardCard {
+                id,
+                title: id,
+                required_role: *role,
+                source: source.clone(),
+            });
+            sources.push(source);
+        }
+        (Arc::new(registry), sources)
+    }
+
+    fn ids(view_model: &DashboardViewModel) -> Vec<&'static str> {
+        view_model.cards.iter().map(|c| c.id).collect()
+    }
+
+    #[test]
+    fn test_cards_filtered_by_role() {
+        let (registry, _) = registry(&[
+            ("receivables", UserRole::Admin),
+            ("service_sla", UserRole::Sales),
+            ("tasks", UserRole::Viewer),
+        ]);
+        let layout = DashboardLayout::default();
+
+        let viewer = DashboardViewModel::new(registry.clone(), UserRole::Viewer, &layout);
+        assert_eq!(ids(&viewer), vec!["tasks"]);
+        let sales = DashboardViewModel::new(registry.clone(), UserRole::Sales, &layout);
+        assert_eq!(ids(&sales), vec!["service_sla", "tasks"]);
+        let admin = DashboardViewModel::new(registry, UserRole::Admin, &layout);
+        assert_eq!(ids(&admin), vec!["receivables", "service_sla", "tasks"]);
+    }
+
+    #[test]
+    fn test_layout_reconciled_with_registry() {
+        let (registry, _) = registry(&[
+            ("receivables", UserRole::Viewer),
+            ("tasks", UserRole::Viewer),
+            ("deliveries", UserRole::Viewer),
+        ]);
+        // 已下线的卡片被忽略，新增的卡片追加在末尾
+        let layout = DashboardLayout {
+            cards: vec![
+                CardSlot {
+                    id: "tasks".to_string(),
+                    visible: false,
+                },
+                CardSlot {
+                    id: "removed_feature".to_string(),
+                    visible: true,
+                },
+                CardSlot {
+                    id: "receivables".to_string(),
+                    visible: true,
+                },
+            ],
+        };
+        let mut view_model = DashboardViewModel::new(registry, UserRole::Viewer, &layout);
+        assert_eq!(ids(&view_model), vec!["tasks", "receivables", "deliveries"]);
+        assert!(!view_model.cards[0].visible);
+
+        view_model.begin_editing();
+        assert!(view_model.move_card("deliveries", 0));
+        assert!(view_model.move_card("tasks", 99));
+        assert!(view_model.set_card_visible("tasks", true));
+        assert!(!view_model.move_card("removed_feature", 0));
+        let saved = view_model.finish_editing();
+        assert!(!view_model.editing);
+        assert_eq!(
+            saved
+                .cards
+                .iter()
+                .map(|c| (c.id.as_str(), c.visible))
+                .collect::<Vec<_>>(),
+            vec![("deliveries", true), ("receivables", true), ("tasks", true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hidden_cards_are_not_loaded() {
+        let (registry, sources) = registry(&[
+            ("receivables", UserRole::Viewer),
+            ("service_sla", UserRole::Viewer),
+            ("tasks", UserRole::Viewer),
+        ]);
+        let mut view_model =
+            DashboardViewModel::new(registry, UserRole::Viewer, &DashboardLayout::default());
+        view_model.set_card_visible("service_sla", false);
+
+        assert_eq!(view_model.load().await, 2);
+        let calls: Vec<usize> = sources
+            .iter()
+            .map(|s| s.calls.load(Ordering::SeqCst))
+            .collect();
+        assert_eq!(calls, vec![1, 0, 1]);
+        assert!(view_model.cards[1].content.is_none());
+
+        // 重新显示后再加载
+        view_model.set_card_visible("service_sla", true);
+        view_model.load().await;
+        assert_eq!(sources[1].calls.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            view_model.cards[1]
+                .content
+                .as_ref()
+                .map(|c| c.value.as_str()),
+            Some("1")
+        );
+    }
+}
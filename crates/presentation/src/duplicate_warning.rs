@@ -0,0 +1,228 @@
+//! 新建客户重复提示模块
+//!
+//! 客户表单中输入名称或电话时，停顿 [`VALIDATION_DEBOUNCE`] 后查询可能重复的已有客户
+//! （[`CheckPossibleDuplicateQuery`]），每秒最多查询两次。命中时在表单上方显示可关闭的
+//! 提示面板，每行可打开已有客户；提示不阻止保存。编辑已有客户时不提示其本身。
+
+use std::time::{Duration, Instant};
+
+use minicrm_application::queries::CheckPossibleDuplicateQuery;
+use minicrm_core::PossibleDuplicate;
+use uuid::Uuid;
+
+use crate::navigation::Route;
+use crate::validation::VALIDATION_DEBOUNCE;
+
+/// 两次查询之间的最短间隔（每秒最多两次）
+pub const DUPLICATE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 提示面板中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateWarningRow {
+    /// 提示文字，如“杭州西湖装饰有限公司 (138****1234)”
+    pub label: String,
+    /// 点击后打开的已有客户
+    pub route: Route,
+}
+
+/// 客户表单的重复提示状态
+///
+/// 名称或电话变化时调用 [`Self::text_changed`]，界面按 [`Self::next_due`] 设置定时器，
+/// 到时用 [`Self::take_due_query`] 取出查询发送，结果带着取出时的序号交给
+/// [`Self::apply`]，过期的结果直接丢弃。
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateWarningController {
+    name: String,
+    phone: String,
+    exclude_id: Option<Uuid>,
+    due: Option<Instant>,
+    last_fired: Option<Instant>,
+    sequence: u64,
+    matches: Vec<PossibleDuplicate>,
+    dismissed: bool,
+}
+
+impl DuplicateWarningController {
+    /// 新建客户表单
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 编辑已有客户的表单（不提示客户本身）
+    pub fn for_edit(customer_id: Uuid) -> Self {
+        Self {
+            exclude_id: Some(customer_id),
+            ..Self::default()
+        }
+    }
+
+    /// 名称或电话变化，停顿后查询；距上次查询不足 [`DUPLICATE_CHECK_INTERVAL`] 时推迟
+    pub fn text_changed(&mut self, name: &str, phone: &str, now: Instant) {
+        if self.name == name && self.phone == phone {
+            return;
+        }
+        self.name = name.to_string();
+        self.phone = phone.to_string();
+        self.dismissed = false;
+        // 进行中的查询结果已过期
+        self.sequence += 1;
+        let mut due = now + VALIDATION_DEBOUNCE;
+        if let Some(last) = self.last_fired {
+            due = due.max(last + DUPLICATE_CHECK_INTERVAL);
+        }
+        self.due = Some(due);
+    }
+
+    /// 下次需要查询的时间
+    pub fn next_due(&self) -> Option<Instant> {
+        self.due
+    }
+
+    /// 到时取出要发送的查询和序号；名称和电话都为空时清空提示，不查询
+    pub fn take_due_query(&mut self, now: Instant) -> Option<(u64, CheckPossibleDuplicateQuery)> {
+        if self.due.is_none_or(|due| due > now) {
+            return None;
+        }
+        self.due = None;
+        if self.name.trim().is_empty() && self.phone.trim().is_empty() {
+            self.matches.clear();
+            return None;
+        }
+        self.last_fired = Some(now);
+        let query = CheckPossibleDuplicateQuery {
+            name: self.name.clone(),
+            phone: self.phone.clone(),
+            exclude_id: self.exclude_id,
+        };
+        Some((self.sequence, query))
+    }
+
+    /// 应用查询结果，返回是否采用（输入已变化时丢弃）
+    pub fn apply(&mut self, sequence: u64, matches: Vec<PossibleDuplicate>) -> bool {
+        if sequence != self.sequence {
+            return false;
+        }
+        self.matches = matches;
+        true
+    }
+
+    /// 关闭提示面板，输入再次变化前不再显示
+    pub fn dismiss(&mut self) {
+        self.dismissed = true;
+    }
+
+    /// 是否显示提示面板
+    pub fn is_visible(&self) -> bool {
+        !self.dismissed && !self.matches.is_empty()
+    }
+
+    /// 面板标题，如“可能已存在：杭州西湖装饰有限公司 (138****1234)”
+    pub fn message(&self) -> Option<String> {
+        let first = self.matches.first().filter(|_| self.is_visible())?;
+        Some(match self.matches.len() {
+            1 => format!("可能已存在：{}", first.label()),
+            n => format!("可能已存在：{} 等{n}个客户", first.label()),
+        })
+    }
+
+    /// 面板中的行（未显示时为空）
+    pub fn rows(&self) -> Vec<DuplicateWarningRow> {
+        if !self.is_visible() {
+            return Vec::new();
+        }
+        self.matches
+            .iter()
+            .map(|found| DuplicateWarningRow {
+                label: found.label(),
+                route: Route::CustomerDetail {
+                    id: found.customer_id,
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minicrm_core::DuplicateMatch;
+
+    fn found(name: &str, phone: Option<&str>) -> PossibleDuplicate {
+        PossibleDuplicate {
+            customer_id: Uuid::new_v4(),
+            name: name.to_string(),
+            phone: phone.map(str::to_string),
+            matched: DuplicateMatch::Phone,
+        }
+    }
+
+    #[test]
+    fn test_checks_are_debounced_and_throttled() {
+        let start = Instant::now();
+        let mut warning = DuplicateWarningController::new();
+        warning.text_changed("杭州", "", start);
+        warning.text_changed("杭州西湖", "", start + Duration::from_millis(100));
+        assert_eq!(
+            warning.next_due(),
+            Some(start + Duration::from_millis(100) + VALIDATION_DEBOUNCE)
+        );
+        assert!(warning
+            .take_due_query(start + VALIDATION_DEBOUNCE)
+            .is_none());
+
+        let fired = start + Duration::from_millis(400);
+        let (_, query) = warning.take_due_query(fired).unwrap();
+        assert_eq!(query.name, "杭州西湖");
+        assert_eq!(query.exclude_id, None);
+        assert!(warning.take_due_query(fired).is_none());
+
+        // 刚查询过：下一次至少间隔半秒
+        warning.text_changed("杭州西湖装", "", fired + Duration::from_millis(10));
+        assert_eq!(warning.next_due(), Some(fired + DUPLICATE_CHECK_INTERVAL));
+    }
+
+    #[test]
+    fn test_stale_results_dismiss_and_links() {
+        let start = Instant::now();
+        let mut warning = DuplicateWarningController::new();
+        warning.text_changed("杭州西湖装饰", "13800131234", start);
+        let (stale, _) = warning.take_due_query(start + VALIDATION_DEBOUNCE).unwrap();
+        warning.text_changed(
+            "杭州西湖装饰有限公司",
+            "13800131234",
+            start + VALIDATION_DEBOUNCE,
+        );
+        let existing = found("杭州西湖装饰有限公司", Some("13800131234"));
+        assert!(!warning.apply(stale, vec![existing.clone()]));
+        assert!(!warning.is_visible());
+
+        let (current, _) = warning
+            .take_due_query(start + VALIDATION_DEBOUNCE + DUPLICATE_CHECK_INTERVAL)
+            .unwrap();
+        assert!(warning.apply(current, vec![existing.clone()]));
+        assert_eq!(
+            warning.message().as_deref(),
+            Some("可能已存在：杭州西湖装饰有限公司 (138****1234)")
+        );
+        assert_eq!(
+            warning.rows()[0].route,
+            Route::CustomerDetail {
+                id: existing.customer_id
+            }
+        );
+
+        warning.dismiss();
+        assert!(warning.message().is_none());
+        assert!(warning.rows().is_empty());
+    }
+
+    #[test]
+    fn test_edit_excludes_record_itself() {
+        let id = Uuid::new_v4();
+        let start = Instant::now();
+        let mut warning = DuplicateWarningController::for_edit(id);
+        warning.text_changed("杭州西湖装饰有限公司", "", start);
+        let (_, query) = warning.take_due_query(start + VALIDATION_DEBOUNCE).unwrap();
+        assert_eq!(query.exclude_id, Some(id));
+    }
+}
@@ -0,0 +1,276 @@
+//! 编辑会话模块
+//!
+//! 同一进程内的多个编辑器（如详情面板和快速编辑弹窗）打开同一条记录时互相提示。
+//! 编辑器进入编辑模式时登记 `(实体类型, ID)`，保存或取消时释放；某个编辑器保存后，
+//! 注册表通过领域事件订阅通知同一记录的其他编辑器重新加载。
+//!
+//! 这里只负责界面提示，不替代数据库层的乐观锁：“仍然编辑”后的并发保存仍由版本检查拦截。
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use minicrm_core::{CoreResult, DomainEvent, EntityKind, EventEnvelope, EventHandler};
+use uuid::Uuid;
+
+/// 另一个编辑器已打开同一记录时的提示文本
+pub const EDITING_ELSEWHERE_MESSAGE: &str = "该记录正在另一个窗口中编辑";
+
+/// 忽略提示继续编辑的按钮文本
+pub const EDIT_ANYWAY_LABEL: &str = "仍然编辑";
+
+/// 其他编辑器保存后的提示文本
+pub const RELOAD_OFFER_MESSAGE: &str = "该记录已在另一个窗口中保存，是否重新加载？";
+
+/// 编辑器ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EditorId(u64);
+
+/// 正在编辑的记录
+type EditKey = (EntityKind, Uuid);
+
+/// 重复打开时的非阻塞提示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditBanner {
+    /// 提示文本
+    pub message: String,
+    /// 忽略提示按钮的文本
+    pub override_label: String,
+    /// 已打开该记录的编辑器数量
+    pub open_editors: usize,
+}
+
+/// 重新加载提示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReloadOffer {
+    /// 实体类型
+    pub entity: EntityKind,
+    /// 实体ID
+    pub id: Uuid,
+    /// 提示文本
+    pub message: String,
+}
+
+/// 打开编辑器的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditOpen {
+    /// 已登记，可以编辑
+    Opened(EditorId),
+    /// 记录已在其他编辑器中打开，显示提示；选择“仍然编辑”后调用
+    /// [`EditSessionRegistry::open_anyway`]
+    InUseElsewhere(EditBanner),
+}
+
+#[derive(Default)]
+struct RegistryInner {
+    next_id: u64,
+    editors: HashMap<EditorId, EditKey>,
+    /// 正在保存的编辑器，对应的更新事件不通知它自己
+    saving: HashMap<EditKey, EditorId>,
+    /// 需要提示重新加载的编辑器
+    stale: HashSet<EditorId>,
+}
+
+impl RegistryInner {
+    fn editors_of(&self, key: EditKey) -> usize {
+        self.editors.values().filter(|k| **k == key).count()
+    }
+
+    fn register(&mut self, key: EditKey) -> EditorId {
+        self.next_id += 1;
+        let editor = EditorId(self.next_id);
+        self.editors.insert(editor, key);
+        editor
+    }
+
+    /// 记录已被保存，标记其他编辑器需要重新加载
+    fn record_saved(&mut self, key: EditKey) {
+        let saver = self.saving.remove(&key);
+        let stale: Vec<EditorId> = self
+            .editors
+            .iter()
+            .filter(|(editor, k)| **k == key && Some(**editor) != saver)
+            .map(|(editor, _)| *editor)
+            .collect();
+        self.stale.extend(stale);
+    }
+}
+
+/// 编辑会话注册表
+///
+/// 可克隆的共享句柄；订阅到事件总线后，记录的更新事件会使其他编辑器收到重新加载提示。
+#[derive(Clone, Default)]
+pub struct EditSessionRegistry {
+    inner: Arc<Mutex<RegistryInner>>,
+}
+
+impl fmt::Debug for EditSessionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EditSessionRegistry")
+            .field("editors", &self.lock().editors.len())
+            .finish()
+    }
+}
+
+impl EditSessionRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegistryInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 进入编辑模式
+    ///
+    /// 记录未被其他编辑器打开时直接登记；否则返回提示，不登记。
+    pub fn open(&self, entity: EntityKind, id: Uuid) -> EditOpen {
+        let mut inner = self.lock();
+        let open_editors = inner.editors_of((entity, id));
+        if open_editors > 0 {
+            return EditOpen::InUseElsewhere(EditBanner {
+                message: EDITING_ELSEWHERE_MESSAGE.to_string(),
+                override_label: EDIT_ANYWAY_LABEL.to_string(),
+                open_editors,
+            });
+        }
+        EditOpen::Opened(inner.register((entity, id)))
+    }
+
+    /// 忽略提示继续编辑（“仍然编辑”）
+    pub fn open_anyway(&self, entity: EntityKind, id: Uuid) -> EditorId {
+        self.lock().register((entity, id))
+    }
+
+    /// 释放编辑器（保存完成或取消编辑时调用）
+    pub fn release(&self, editor: EditorId) {
+        let mut inner = self.lock();
+        if let Some(key) = inner.editors.remove(&editor) {
+            if inner.saving.get(&key) == Some(&editor) {
+                inner.saving.remove(&key);
+            }
+        }
+        inner.stale.remove(&editor);
+    }
+
+    /// 编辑器开始保存，随后的更新事件不会提示该编辑器自己重新加载
+    pub fn begin_save(&self, editor: EditorId) {
+        let mut inner = self.lock();
+        if let Some(key) = inner.editors.get(&editor).copied() {
+            inner.saving.insert(key, editor);
+        }
+    }
+
+    /// 编辑器是否仍在登记中
+    pub fn is_open(&self, editor: EditorId) -> bool {
+        self.lock().editors.contains_key(&editor)
+    }
+
+    /// 编辑器待显示的重新加载提示
+    pub fn reload_offer(&self, editor: EditorId) -> Option<ReloadOffer> {
+        let inner = self.lock();
+        if !inner.stale.contains(&editor) {
+            return None;
+        }
+        inner.editors.get(&editor).map(|(entity, id)| ReloadOffer {
+            entity: *entity,
+            id: *id,
+            message: RELOAD_OFFER_MESSAGE.to_string(),
+        })
+    }
+
+    /// 关闭重新加载提示（已重新加载或选择忽略）
+    pub fn dismiss_reload(&self, editor: EditorId) {
+        self.lock().stale.remove(&editor);
+    }
+}
+
+impl EventHandler for EditSessionRegistry {
+    fn name(&self) -> &str {
+        "edit_sessions"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        if let DomainEvent::EntityUpdated { entity, id } = &envelope.event {
+            self.lock().record_saved((*entity, *id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minicrm_application::EventBus;
+
+    fn opened(outcome: EditOpen) -> EditorId {
+        match outcome {
+            EditOpen::Opened(editor) => editor,
+            EditOpen::InUseElsewhere(banner) => panic!("不应提示: {:?}", banner),
+        }
+    }
+
+    #[test]
+    fn test_register_and_release() {
+        let registry = EditSessionRegistry::new();
+        let customer = Uuid::new_v4();
+
+        let editor = opened(registry.open(EntityKind::Customer, customer));
+        assert!(registry.is_open(editor));
+        // 其他记录不受影响
+        opened(registry.open(EntityKind::Customer, Uuid::new_v4()));
+        opened(registry.open(EntityKind::Quote, customer));
+
+        registry.release(editor);
+        assert!(!registry.is_open(editor));
+        opened(registry.open(EntityKind::Customer, customer));
+    }
+
+    #[test]
+    fn test_double_open_shows_banner() {
+        let registry = EditSessionRegistry::new();
+        let customer = Uuid::new_v4();
+        let detail = opened(registry.open(EntityKind::Customer, customer));
+
+        let EditOpen::InUseElsewhere(banner) = registry.open(EntityKind::Customer, customer) else {
+            panic!("第二个编辑器应收到提示");
+        };
+        assert_eq!(banner.message, "该记录正在另一个窗口中编辑");
+        assert_eq!(banner.override_label, "仍然编辑");
+        assert_eq!(banner.open_editors, 1);
+
+        let quick_edit = registry.open_anyway(EntityKind::Customer, customer);
+        assert_ne!(quick_edit, detail);
+        assert!(registry.is_open(quick_edit));
+    }
+
+    #[test]
+    fn test_save_offers_reload_to_other_editors() {
+        let bus = EventBus::new();
+        let registry = EditSessionRegistry::new();
+        bus.subscribe(Arc::new(registry.clone()));
+        let customer = Uuid::new_v4();
+        let detail = opened(registry.open(EntityKind::Customer, customer));
+        let quick_edit = registry.open_anyway(EntityKind::Customer, customer);
+        let other = opened(registry.open(EntityKind::Customer, Uuid::new_v4()));
+
+        // 快速编辑弹窗保存
+        registry.begin_save(quick_edit);
+        bus.publish(DomainEvent::EntityUpdated {
+            entity: EntityKind::Customer,
+            id: customer,
+        });
+        registry.release(quick_edit);
+
+        let offer = registry
+            .reload_offer(detail)
+            .expect("详情面板应收到重新加载提示");
+        assert_eq!(offer.id, customer);
+        assert_eq!(offer.message, RELOAD_OFFER_MESSAGE);
+        assert_eq!(registry.reload_offer(other), None);
+
+        registry.dismiss_reload(detail);
+        assert_eq!(registry.reload_offer(detail), None);
+    }
+}
@@ -0,0 +1,255 @@
+//! 售后邮箱设置
+//!
+//! 设置界面的售后邮箱面板：填写IMAP服务器、登录账号、邮箱文件夹和检查间隔，启用后
+//! 定时把未读邮件转换为售后工单。授权码只写不读，面板只显示是否已保存，留空表示不修改。
+//! 点击“保存”时先校验，通过后由调用方写回配置（授权码保存到密钥存储）。
+
+use minicrm_core::FieldError;
+
+/// 默认IMAP端口（隐式TLS）
+pub const DEFAULT_IMAP_PORT: u16 = 993;
+/// 默认邮箱文件夹
+pub const DEFAULT_MAILBOX_FOLDER: &str = "INBOX";
+/// 默认检查间隔（分钟）
+pub const DEFAULT_POLL_MINUTES: u32 = 5;
+/// 最短检查间隔（分钟）
+pub const MIN_POLL_MINUTES: u32 = 1;
+/// 最长检查间隔（分钟）
+pub const MAX_POLL_MINUTES: u32 = 24 * 60;
+
+/// 售后邮箱设置（不含授权码）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailboxSettings {
+    /// 是否启用
+    pub enabled: bool,
+    /// IMAP服务器
+    pub host: String,
+    /// 端口
+    pub port: u16,
+    /// 登录用户名
+    pub username: String,
+    /// 邮箱文件夹
+    pub folder: String,
+    /// 检查间隔（分钟）
+    pub poll_minutes: u32,
+}
+
+impl Default for MailboxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: DEFAULT_IMAP_PORT,
+            username: String::new(),
+            folder: DEFAULT_MAILBOX_FOLDER.to_string(),
+            poll_minutes: DEFAULT_POLL_MINUTES,
+        }
+    }
+}
+
+/// 校验通过的修改
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailboxSettingsUpdate {
+    /// 新的设置
+    pub settings: MailboxSettings,
+    /// 新的授权码，`None` 表示不修改
+    pub password: Option<String>,
+}
+
+/// 售后邮箱设置视图模型
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmailIntakeSettingsViewModel {
+    /// 是否启用
+    pub enabled: bool,
+    /// IMAP服务器
+    pub host: String,
+    /// 端口（输入框文本）
+    pub port: String,
+    /// 登录用户名
+    pub username: String,
+    /// 新的授权码（留空不修改）
+    pub password: String,
+    /// 密钥存储中是否已有授权码
+    pub has_password: bool,
+    /// 邮箱文件夹
+    pub folder: String,
+    /// 检查间隔（输入框文本，分钟）
+    pub poll_minutes: String,
+    /// 校验错误
+    pub errors: Vec<FieldError>,
+}
+
+impl EmailIntakeSettingsViewModel {
+    /// 按当前配置创建面板
+    pub fn new(settings: &MailboxSettings, has_password: bool) -> Self {
+        Self {
+            enabled: settings.enabled,
+            host: settings.host.clone(),
+            port: settings.port.to_string(),
+            username: settings.username.clone(),
+            password: String::new(),
+            has_password,
+            folder: settings.folder.clone(),
+            poll_minutes: settings.poll_minutes.to_string(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// 授权码输入框的占位文本
+    pub fn password_placeholder(&self) -> &'static str {
+        if self.has_password {
+            "已保存，留空不修改"
+        } else {
+            "邮箱授权码"
+        }
+    }
+
+    /// 检查间隔说明，如“每 5 分钟检查一次新邮件”，间隔无效时为空
+    pub fn interval_text(&self) -> String {
+        match self.parse_poll_minutes() {
+            Some(minutes) if minutes % 60 == 0 => {
+                format!("每 {} 小时检查一次新邮件", minutes / 60)
+            }
+            Some(minutes) => format!("每 {} 分钟检查一次新邮件", minutes),
+            None => String::new(),
+        }
+    }
+
+    /// 字段的校验错误
+    pub fn error(&self, field: &str) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|e| e.field == field)
+            .map(|e| e.message.as_str())
+    }
+
+    /// 校验并返回要保存的修改，有错误时显示在面板中并返回 `None`
+    ///
+    /// 未启用时只校验检查间隔，其余字段可以留空。
+    pub fn save(&mut self) -> Option<MailboxSettingsUpdate> {
+        self.errors.clear();
+        let host = self.host.trim().to_string();
+        let username = self.username.trim().to_string();
+        let password = Some(self.password.trim().to_string()).filter(|p| !p.is_empty());
+
+        let port = match self.port.trim().parse::<u16>() {
+            Ok(port) if port > 0 => port,
+            _ => {
+                self.errors
+                    .push(FieldError::new("port", "端口应为 1 到 65535 之间的整数"));
+                DEFAULT_IMAP_PORT
+            }
+        };
+        let poll_minutes = self.parse_poll_minutes().unwrap_or_else(|| {
+            self.errors.push(FieldError::new(
+                "poll_minutes",
+                format!(
+                    "检查间隔应为 {} 到 {} 分钟",
+                    MIN_POLL_MINUTES, MAX_POLL_MINUTES
+                ),
+            ));
+            DEFAULT_POLL_MINUTES
+        });
+        if self.enabled {
+            if host.is_empty() {
+                self.errors
+                    .push(FieldError::new("host", "请填写IMAP服务器"));
+            }
+            if username.is_empty() {
+                self.errors
+                    .push(FieldError::new("username", "请填写邮箱账号"));
+            }
+            if password.is_none() && !self.has_password {
+                self.errors
+                    .push(FieldError::new("password", "请填写邮箱授权码"));
+            }
+        }
+        if !self.errors.is_empty() {
+            return None;
+        }
+
+        let folder = match self.folder.trim() {
+            "" => DEFAULT_MAILBOX_FOLDER.to_string(),
+            folder => folder.to_string(),
+        };
+        Some(MailboxSettingsUpdate {
+            settings: MailboxSettings {
+                enabled: self.enabled,
+                host,
+                port,
+                username,
+                folder,
+                poll_minutes,
+            },
+            password,
+        })
+    }
+
+    fn parse_poll_minutes(&self) -> Option<u32> {
+        self.poll_minutes
+            .trim()
+            .parse()
+            .ok()
+            .filter(|minutes| (MIN_POLL_MINUTES..=MAX_POLL_MINUTES).contains(minutes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled_mailbox_requires_credentials() {
+        let mut panel = EmailIntakeSettingsViewModel::new(&MailboxSettings::default(), false);
+        assert_eq!(panel.interval_text(), "每 5 分钟检查一次新邮件");
+        assert_eq!(panel.password_placeholder(), "邮箱授权码");
+
+        // 未启用时可以不填服务器
+        let update = panel.save().unwrap();
+        assert!(!update.settings.enabled);
+        assert_eq!(update.password, None);
+
+        panel.enabled = true;
+        panel.poll_minutes = "0".to_string();
+        assert!(panel.save().is_none());
+        assert_eq!(panel.error("host"), Some("请填写IMAP服务器"));
+        assert_eq!(panel.error("password"), Some("请填写邮箱授权码"));
+        assert_eq!(
+            panel.error("poll_minutes"),
+            Some("检查间隔应为 1 到 1440 分钟")
+        );
+        assert_eq!(panel.interval_text(), "");
+
+        panel.host = " imap.exmail.qq.com ".to_string();
+        panel.username = "service@example.com".to_string();
+        panel.password = "授权码".to_string();
+        panel.folder = String::new();
+        panel.poll_minutes = "120".to_string();
+        let update = panel.save().unwrap();
+        assert!(panel.errors.is_empty());
+        assert_eq!(panel.interval_text(), "每 2 小时检查一次新邮件");
+        assert_eq!(
+            update.settings,
+            MailboxSettings {
+                enabled: true,
+                host: "imap.exmail.qq.com".to_string(),
+                port: 993,
+                username: "service@example.com".to_string(),
+                folder: "INBOX".to_string(),
+                poll_minutes: 120,
+            }
+        );
+        assert_eq!(update.password.as_deref(), Some("授权码"));
+
+        // 已保存授权码时留空表示不修改
+        let mut panel = EmailIntakeSettingsViewModel::new(&update.settings, true);
+        assert_eq!(panel.password_placeholder(), "已保存，留空不修改");
+        panel.port = "99999".to_string();
+        assert!(panel.save().is_none());
+        assert!(panel.error("port").is_some());
+        panel.port = "143".to_string();
+        let update = panel.save().unwrap();
+        assert_eq!(update.settings.port, 143);
+        assert_eq!(update.password, None);
+    }
+}
@@ -0,0 +1,94 @@
+//! 错误展示模块
+//!
+//! 将核心错误转换为界面提示，权限拒绝与输入校验错误分开呈现。
+
+use minicrm_core::CoreError;
+
+/// 提示类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// 权限不足（需要更高角色或重新登录）
+    PermissionDenied,
+    /// 输入校验未通过
+    Validation,
+    /// 数据已被他人修改
+    Conflict,
+    /// 记录不存在
+    NotFound,
+    /// 用户取消了操作
+    Cancelled,
+    /// 其他错误
+    Error,
+    /// 操作成功的提示
+    Info,
+}
+
+impl MessageKind {
+    /// 界面使用的类别标识
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PermissionDenied => "permission",
+            Self::Validation => "validation",
+            Self::Conflict => "conflict",
+            Self::NotFound => "not-found",
+            Self::Cancelled => "cancelled",
+            Self::Error => "error",
+            Self::Info => "info",
+        }
+    }
+}
+
+/// 界面提示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserMessage {
+    /// 提示类别
+    pub kind: MessageKind,
+    /// 提示标题
+    pub title: String,
+    /// 提示内容
+    pub detail: String,
+}
+
+impl UserMessage {
+    /// 由核心错误生成界面提示
+    pub fn from_error(error: &CoreError) -> Self {
+        let (kind, title, detail) = match error {
+            CoreError::Permission(message) => {
+                (MessageKind::PermissionDenied, "权限不足", message.clone())
+            }
+            CoreError::Validation(message) => (MessageKind::Validation, "输入有误", message.clone()),
+            CoreError::InvalidFields(fields) => (
+                MessageKind::Validation,
+                "输入有误",
+                fields
+                    .iter()
+                    .map(|f| f.message.clone())
+                    .collect::<Vec<_>>()
+                    .join("；"),
+            ),
+            CoreError::Conflict(message) => (MessageKind::Conflict, "数据已变更", message.clone()),
+            CoreError::NotFound(message) => (MessageKind::NotFound, "记录不存在", message.clone()),
+            CoreError::Cancelled => (MessageKind::Cancelled, "已取消", "操作已取消".to_string()),
+            other => (MessageKind::Error, "操作失败", other.to_string()),
+        };
+        Self {
+            kind,
+            title: title.to_string(),
+            detail,
+        }
+    }
+
+    /// 操作成功的提示
+    pub fn info(title: &str, detail: impl Into<String>) -> Self {
+        Self {
+            kind: MessageKind::Info,
+            title: title.to_string(),
+            detail: detail.into(),
+        }
+    }
+
+    /// 单行文本形式
+    pub fn to_text(&self) -> String {
+        format!("{}：{}", self.title, self.detail)
+    }
+}
@@ -0,0 +1,410 @@
+//! 电话、地图与微信快捷操作
+//!
+//! 客户详情和客户列表的右键菜单提供拨号、查看地图、复制微信号三个图标按钮。
+//! 拨号先用 [`PhoneNumber`] 规范号码，再把 `tel:` 链接交给系统拨号软件（如电脑版微信、
+//! 手机协同）；号码无效时只提示，不打开任何程序。
+//!
+//! 打开拨号软件不代表电话已接通，因此拨号后弹出确认，用户确认通话后才记录“拨打电话”互动。
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    Address, ClipboardWriter, Clock, CoreError, CoreResult, Interaction, InteractionKind,
+    InteractionService, PhoneNumber, SystemClock, UriOpener,
+};
+use uuid::Uuid;
+
+use crate::errors::UserMessage;
+
+/// 地图搜索链接（高德地图，网页版可直接在浏览器中打开）
+const MAP_SEARCH_URL: &str = "https://uri.amap.com/search?keyword=";
+
+/// 快捷操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuickAction {
+    /// 拨打电话
+    Dial,
+    /// 在地图中查看地址
+    OpenMap,
+    /// 复制微信号
+    CopyWechat,
+}
+
+impl QuickAction {
+    /// 操作ID（界面回调使用）
+    pub fn id(&self) -> &'static str {
+        match self {
+            QuickAction::Dial => "dial",
+            QuickAction::OpenMap => "open_map",
+            QuickAction::CopyWechat => "copy_wechat",
+        }
+    }
+
+    /// 按钮提示文字
+    pub fn label(&self) -> &'static str {
+        match self {
+            QuickAction::Dial => "拨打电话",
+            QuickAction::OpenMap => "查看地图",
+            QuickAction::CopyWechat => "复制微信号",
+        }
+    }
+
+    /// 按已填写的联系方式列出可用的操作（按钮顺序）
+    pub fn available(
+        phone: Option<&str>,
+        address: Option<&str>,
+        wechat: Option<&str>,
+    ) -> Vec<Self> {
+        let filled = |value: Option<&str>| value.is_some_and(|v| !v.trim().is_empty());
+        [
+            (QuickAction::Dial, phone),
+            (QuickAction::OpenMap, address),
+            (QuickAction::CopyWechat, wechat),
+        ]
+        .into_iter()
+        .filter(|(_, value)| filled(*value))
+        .map(|(action, _)| action)
+        .collect()
+    }
+}
+
+/// 拨号链接
+pub fn tel_uri(phone: &PhoneNumber) -> String {
+    format!("tel:{}", phone)
+}
+
+/// 地图搜索链接，地址为空时返回 `None`
+pub fn map_url(address: &Address) -> Option<String> {
+    map_search_url(&address.one_line())
+}
+
+/// 按单行地址文本生成地图搜索链接（客户地址未结构化保存）
+pub fn map_search_url(address: &str) -> Option<String> {
+    let address = address.trim();
+    if address.is_empty() {
+        return None;
+    }
+    Some(format!("{}{}", MAP_SEARCH_URL, encode_query(address)))
+}
+
+/// 按URL查询参数编码（UTF-8字节，保留非保留字符）
+fn encode_query(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len() * 3);
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// 已打开拨号软件、等待用户确认的电话
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingCall {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 规范后的号码
+    pub number: PhoneNumber,
+    /// 拨号时间（记录为互动的发生时间）
+    pub dialed_at: DateTime<Utc>,
+}
+
+impl PendingCall {
+    /// 确认提示文字
+    pub fn prompt(&self) -> String {
+        format!("是否已与 {} 通话？确认后记入客户互动", self.number)
+    }
+}
+
+/// 快捷操作服务
+pub struct ExternalActionService {
+    opener: Arc<dyn UriOpener + Send + Sync>,
+    clipboard: Arc<dyn ClipboardWriter + Send + Sync>,
+    interactions: Arc<dyn InteractionService + Send + Sync>,
+    clock: Arc<dyn Clock>,
+}
+
+impl std::fmt::Debug for ExternalActionService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalActionService")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExternalActionService {
+    /// 创建快捷操作服务
+    pub fn new(
+        opener: Arc<dyn UriOpener + Send + Sync>,
+        clipboard: Arc<dyn ClipboardWriter + Send + Sync>,
+        interactions: Arc<dyn InteractionService + Send + Sync>,
+    ) -> Self {
+        Self {
+            opener,
+            clipboard,
+            interactions,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// 使用指定时钟（测试中固定拨号时间）
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 拨打电话，返回等待确认的电话
+    ///
+    /// # Errors
+    ///
+    /// 号码无效或无法打开拨号软件时返回提示（号码无效时不打开任何程序）。
+    pub fn dial(&self, customer_id: Uuid, phone: &str) -> Result<PendingCall, UserMessage> {
+        let number = PhoneNumber::try_from(phone).map_err(|_| {
+            UserMessage::from_error(&CoreError::validation(format!(
+                "“{}”不是有效的电话号码，无法拨打",
+                phone.trim()
+            )))
+        })?;
+        self.opener
+            .open_uri(&tel_uri(&number))
+            .map_err(|e| UserMessage::from_error(&e))?;
+        Ok(PendingCall {
+            customer_id,
+            number,
+            dialed_at: self.clock.now(),
+        })
+    }
+
+    /// 用户确认已通话，记录“拨打电话”互动（用户否认时直接丢弃 [`PendingCall`]）
+    ///
+    /// # Errors
+    ///
+    /// 写入互动记录失败时返回错误。
+    pub async fn confirm_call(
+        &self,
+        call: PendingCall,
+        created_by: Option<String>,
+    ) -> CoreResult<Interaction> {
+        self.interactions
+            .log_interaction(Interaction {
+                id: Uuid::new_v4(),
+                customer_id: call.customer_id,
+                kind: InteractionKind::Call,
+                content: format!("拨打电话 {}", call.number),
+                occurred_at: call.dialed_at,
+                created_by,
+            })
+            .await
+    }
+
+    /// 在地图中查看地址
+    ///
+    /// # Errors
+    ///
+    /// 地址为空或无法打开浏览器时返回提示。
+    pub fn open_map(&self, address: &Address) -> Result<(), UserMessage> {
+        self.open_map_url(map_url(address))
+    }
+
+    /// 在地图中查看单行地址
+    ///
+    /// # Errors
+    ///
+    /// 地址为空或无法打开浏览器时返回提示。
+    pub fn open_map_text(&self, address: &str) -> Result<(), UserMessage> {
+        self.open_map_url(map_search_url(address))
+    }
+
+    fn open_map_url(&self, url: Option<String>) -> Result<(), UserMessage> {
+        let url =
+            url.ok_or_else(|| UserMessage::from_error(&CoreError::validation("没有填写地址")))?;
+        self.opener
+            .open_uri(&url)
+            .map_err(|e| UserMessage::from_error(&e))
+    }
+
+    /// 复制微信号，返回成功或失败的提示
+    pub fn copy_wechat(&self, wechat_id: &str) -> UserMessage {
+        let wechat_id = wechat_id.trim();
+        if wechat_id.is_empty() {
+            return UserMessage::from_error(&CoreError::validation("没有填写微信号"));
+        }
+        match self.clipboard.set_text(wechat_id) {
+            Ok(()) => UserMessage::info("已复制", format!("微信号 {} 已复制到剪贴板", wechat_id)),
+            Err(e) => UserMessage::from_error(&e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::MessageKind;
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use minicrm_core::ManualClock;
+    use std::sync::Mutex;
+
+    /// 记录打开过的链接和剪贴板内容
+    #[derive(Default)]
+    struct Desktop {
+        opened: Mutex<Vec<String>>,
+        clipboard: Mutex<Option<String>>,
+    }
+
+    impl UriOpener for Desktop {
+        fn open_uri(&self, uri: &str) -> CoreResult<()> {
+            self.opened.lock().unwrap().push(uri.to_string());
+            Ok(())
+        }
+    }
+
+    impl ClipboardWriter for Desktop {
+        fn set_text(&self, text: &str) -> CoreResult<()> {
+            *self.clipboard.lock().unwrap() = Some(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct InteractionLog(Mutex<Vec<Interaction>>);
+
+    #[async_trait]
+    impl InteractionService for InteractionLog {
+        async fn log_interaction(&self, interaction: Interaction) -> CoreResult<Interaction> {
+            self.0.lock().unwrap().push(interaction.clone());
+            Ok(interaction)
+        }
+    }
+
+    fn dialed_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 3, 2, 30, 0).unwrap()
+    }
+
+    fn service() -> (Arc<Desktop>, Arc<InteractionLog>, ExternalActionService) {
+        let desktop = Arc::new(Desktop::default());
+        let log = Arc::new(InteractionLog::default());
+        let service = ExternalActionService::new(desktop.clone(), desktop.clone(), log.clone())
+            .with_clock(Arc::new(ManualClock::new(dialed_at())));
+        (desktop, log, service)
+    }
+
+    #[test]
+    fn test_uri_construction() {
+        let mobile = PhoneNumber::try_from("+86 138-1234-5678").unwrap();
+        assert_eq!(tel_uri(&mobile), "tel:13812345678");
+        let landline = PhoneNumber::try_from("(020) 8888 6666").unwrap();
+        assert_eq!(tel_uri(&landline), "tel:02088886666");
+
+        let address = Address {
+            province: "广东省".to_string(),
+            city: "佛山市".to_string(),
+            district: Some("顺德区".to_string()),
+            street: "乐从镇 3号".to_string(),
+            ..Address::default()
+        };
+        assert_eq!(
+            map_url(&address).unwrap(),
+            "https://uri.amap.com/search?keyword=\
+             %E5%B9%BF%E4%B8%9C%E7%9C%81%E4%BD%9B%E5%B1%B1%E5%B8%82\
+             %E9%A1%BA%E5%BE%B7%E5%8C%BA%E4%B9%90%E4%BB%8E%E9%95%87%203%E5%8F%B7"
+        );
+        assert_eq!(map_url(&Address::default()), None);
+        assert_eq!(
+            map_search_url("A&B Road #1").unwrap(),
+            "https://uri.amap.com/search?keyword=A%26B%20Road%20%231"
+        );
+
+        assert_eq!(
+            QuickAction::available(Some("13812345678"), Some(" "), Some("wx_chen")),
+            vec![QuickAction::Dial, QuickAction::CopyWechat]
+        );
+        assert!(QuickAction::available(None, None, None).is_empty());
+    }
+
+    #[test]
+    fn test_list_context_actions_follow_visible_columns() {
+        use crate::columns::VisibleColumn;
+        use crate::view_models::CustomerListViewModel;
+
+        let column = |id: &str| VisibleColumn {
+            id: id.to_string(),
+            title: String::new(),
+            width: 100,
+        };
+        let (with_phone, without_phone) = (Uuid::new_v4(), Uuid::new_v4());
+        let list = CustomerListViewModel {
+            headers: vec![column("name"), column("phone")],
+            rows: vec![
+                (
+                    with_phone,
+                    vec!["陈记".to_string(), "13812345678".to_string()],
+                ),
+                (without_phone, vec!["王氏".to_string(), String::new()]),
+            ],
+            ..CustomerListViewModel::default()
+        };
+        assert_eq!(list.cell(with_phone, "phone"), Some("13812345678"));
+        assert_eq!(list.quick_actions(with_phone), vec![QuickAction::Dial]);
+        assert!(list.quick_actions(without_phone).is_empty());
+        assert!(list.quick_actions(Uuid::new_v4()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_call_logged_only_after_confirmation() {
+        let (desktop, log, service) = service();
+        let customer_id = Uuid::new_v4();
+
+        let call = service.dial(customer_id, "138 1234 5678").unwrap();
+        assert_eq!(*desktop.opened.lock().unwrap(), vec!["tel:13812345678"]);
+        assert_eq!(
+            call.prompt(),
+            "是否已与 13812345678 通话？确认后记入客户互动"
+        );
+        // 打开拨号软件时不记录
+        assert!(log.0.lock().unwrap().is_empty());
+
+        // 用户否认通话：丢弃即可，不记录
+        drop(service.dial(customer_id, "13812345678").unwrap());
+        assert!(log.0.lock().unwrap().is_empty());
+
+        let interaction = service
+            .confirm_call(call, Some("sales".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(interaction.kind, InteractionKind::Call);
+        assert_eq!(interaction.customer_id, customer_id);
+        assert_eq!(interaction.content, "拨打电话 13812345678");
+        assert_eq!(interaction.occurred_at, dialed_at());
+        assert_eq!(*log.0.lock().unwrap(), vec![interaction]);
+    }
+
+    #[test]
+    fn test_invalid_number_rejected_without_opening() {
+        let (desktop, _log, service) = service();
+        let message = service.dial(Uuid::new_v4(), "12345").unwrap_err();
+        assert_eq!(message.kind, MessageKind::Validation);
+        assert_eq!(message.detail, "“12345”不是有效的电话号码，无法拨打");
+        assert!(desktop.opened.lock().unwrap().is_empty());
+
+        let message = service.open_map_text("  ").unwrap_err();
+        assert_eq!(message.kind, MessageKind::Validation);
+        assert!(desktop.opened.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_copy_wechat_toast() {
+        let (desktop, _log, service) = service();
+        let message = service.copy_wechat(" wx_chen ");
+        assert_eq!(message.kind, MessageKind::Info);
+        assert_eq!(message.to_text(), "已复制：微信号 wx_chen 已复制到剪贴板");
+        assert_eq!(
+            desktop.clipboard.lock().unwrap().as_deref(),
+            Some("wx_chen")
+        );
+
+        assert_eq!(service.copy_wechat("").kind, MessageKind::Validation);
+    }
+}
@@ -0,0 +1,173 @@
+//! 字段策略设置模块
+//!
+//! 设置界面中的字段面板：逐个字段选择可选、必填或隐藏。客户名称等实体本身依赖的字段
+//! 锁定为必填，下拉框不可修改。修改通过 [`FieldPolicyService`] 立即保存，表单和详情
+//! 下次打开时按新策略显示。
+
+use std::sync::Arc;
+
+use minicrm_core::{
+    policy_fields, CoreError, CoreResult, EntityKind, FieldPolicy, FieldPolicyService,
+    FieldRequirement,
+};
+
+/// 字段面板中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPolicyRow {
+    /// 字段名
+    pub key: &'static str,
+    /// 显示名称
+    pub label: &'static str,
+    /// 当前要求
+    pub requirement: FieldRequirement,
+    /// 是否锁定（实体本身依赖的字段）
+    pub locked: bool,
+}
+
+/// 字段策略设置视图模型
+#[derive(Clone)]
+pub struct FieldPolicySettingsViewModel {
+    service: Arc<dyn FieldPolicyService>,
+    /// 实体类型
+    pub entity: EntityKind,
+    /// 当前策略
+    pub policy: FieldPolicy,
+    /// 出错提示
+    pub error: Option<String>,
+}
+
+impl std::fmt::Debug for FieldPolicySettingsViewModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FieldPolicySettingsViewModel")
+            .field("entity", &self.entity)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FieldPolicySettingsViewModel {
+    /// 创建面板，策略需调用 [`Self::load`] 加载
+    pub fn new(service: Arc<dyn FieldPolicyService>, entity: EntityKind) -> Self {
+        Self {
+            service,
+            entity,
+            policy: FieldPolicy::new(entity),
+            error: None,
+        }
+    }
+
+    /// 加载当前策略
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub async fn load(&mut self) -> CoreResult<()> {
+        self.policy = self.service.field_policy(self.entity).await?;
+        Ok(())
+    }
+
+    /// 各字段的行（按表单顺序）
+    pub fn rows(&self) -> Vec<FieldPolicyRow> {
+        policy_fields(self.entity)
+            .iter()
+            .map(|field| FieldPolicyRow {
+                key: field.key,
+                label: field.label,
+                requirement: self.policy.requirement(field.key),
+                locked: field.intrinsic,
+            })
+            .collect()
+    }
+
+    /// 修改字段要求
+    ///
+    /// 防护规则拒绝的修改显示在面板中并返回 `false`。
+    ///
+    /// # Errors
+    ///
+    /// 保存失败（校验错误除外）时返回错误。
+    pub async fn set(&mut self, key: &str, requirement: FieldRequirement) -> CoreResult<bool> {
+        match self
+            .service
+            .set_field_requirement(self.entity, key, requirement)
+            .await
+        {
+            Ok(policy) => {
+                self.policy = policy;
+                self.error = None;
+                Ok(true)
+            }
+            Err(CoreError::Validation(message)) => {
+                self.error = Some(message);
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view_models::CustomerDetailViewModel;
+    use async_trait::async_trait;
+    use minicrm_core::CustomerSummary;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    struct FakePolicies {
+        policy: Mutex<FieldPolicy>,
+    }
+
+    #[async_trait]
+    impl FieldPolicyService for FakePolicies {
+        async fn field_policy(&self, _entity: EntityKind) -> CoreResult<FieldPolicy> {
+            Ok(self.policy.lock().unwrap().clone())
+        }
+
+        async fn set_field_requirement(
+            &self,
+            _entity: EntityKind,
+            key: &str,
+            requirement: FieldRequirement,
+        ) -> CoreResult<FieldPolicy> {
+            let mut policy = self.policy.lock().unwrap();
+            policy.set(key, requirement)?;
+            Ok(policy.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_settings_panel_and_hidden_detail_fields() {
+        let service = Arc::new(FakePolicies {
+            policy: Mutex::new(FieldPolicy::new(EntityKind::Customer)),
+        });
+        let mut panel = FieldPolicySettingsViewModel::new(service, EntityKind::Customer);
+        panel.load().await.unwrap();
+        let rows = panel.rows();
+        assert_eq!(rows.len(), 5);
+        assert!(rows[0].locked);
+        assert_eq!(rows[0].requirement, FieldRequirement::Required);
+
+        assert!(!panel.set("name", FieldRequirement::Hidden).await.unwrap());
+        assert_eq!(
+            panel.error.as_deref(),
+            Some("客户名称始终必填，不能设为隐藏")
+        );
+        assert!(panel.set("phone", FieldRequirement::Hidden).await.unwrap());
+        assert_eq!(panel.error, None);
+        assert_eq!(panel.rows()[2].requirement, FieldRequirement::Hidden);
+
+        // 隐藏的电话不出现在客户详情中，也不显示拨号按钮
+        let summary = CustomerSummary {
+            id: Uuid::new_v4(),
+            name: "华东板材".to_string(),
+            phone: Some("13800138000".to_string()),
+        };
+        let visible = CustomerDetailViewModel::new(summary.clone(), &[]);
+        assert!(!visible.quick_actions().is_empty());
+        let detail = CustomerDetailViewModel::new(summary, &[]).with_field_policy(&panel.policy);
+        assert_eq!(detail.customer.phone, None);
+        assert!(detail.quick_actions().is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+//! 界面显示格式
+//!
+//! 视图模型使用的金额、日期、相对时间和面积格式，按界面语言格式化。格式规则在
+//! [`minicrm_core::formatting`] 中，导出和报表直接使用其中的 [`Locale`] 方法，
+//! 与界面显示保持一致。
+
+use chrono::{DateTime, TimeZone};
+use minicrm_core::{DisplayName, Money};
+
+pub use minicrm_core::formatting::{DateStyle, Locale};
+
+/// 界面语言
+pub const UI_LOCALE: Locale = Locale::ZhCn;
+
+/// 金额（¥12,345.50）
+pub fn format_money(amount: Money) -> String {
+    UI_LOCALE.money(amount)
+}
+
+/// 金额的中文大写（壹万贰仟叁佰肆拾伍元伍角整）
+pub fn format_money_chinese_upper(amount: Money) -> String {
+    minicrm_core::money_chinese_upper(amount)
+}
+
+/// 日期（2024年6月15日 或 2024-06-15），按传入时间所在时区显示
+pub fn format_date<Tz: TimeZone>(at: &DateTime<Tz>, style: DateStyle) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    UI_LOCALE.date(at, style)
+}
+
+/// 相对日期（今天、明天、3天前）
+pub fn format_relative<Tz: TimeZone>(at: &DateTime<Tz>, now: &DateTime<Tz>) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    UI_LOCALE.relative(at, now)
+}
+
+/// 枚举的显示名称（客户等级、任务状态等）
+pub fn display_name<T: DisplayName>(value: &T) -> &'static str {
+    value.display_name(UI_LOCALE)
+}
+
+/// 面积（12.50平方米）
+pub fn format_area(square_meters: f64) -> String {
+    UI_LOCALE.area(square_meters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_ui_formats() {
+        let amount = Money::from_cents(1_234_550);
+        assert_eq!(format_money(amount), "¥12,345.50");
+        assert_eq!(format_money_chinese_upper(amount), "壹万贰仟叁佰肆拾伍元伍角整");
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 9, 0, 0).unwrap();
+        assert_eq!(format_date(&now, DateStyle::Long), "2024年6月15日");
+        assert_eq!(
+            format_relative(&(now - chrono::Duration::days(3)), &now),
+            "3天前"
+        );
+        assert_eq!(format_area(36.0), "36.00平方米");
+    }
+}
@@ -0,0 +1,452 @@
+//! 表单状态模块
+//!
+//! 为各编辑器（客户、报价、工单）提供统一的未保存修改跟踪。
+//! 已注册的表单存在修改时，导航和关闭窗口前都会弹出
+//! “保存 / 放弃 / 取消”确认。
+
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use minicrm_core::CoreResult;
+use uuid::Uuid;
+
+use crate::navigation::NavigationGuard;
+
+type ChangeListener = Box<dyn Fn(bool)>;
+
+/// 表单状态
+///
+/// 保存加载时的快照，与当前值比较判断是否有未保存的修改。
+pub struct FormState<T: PartialEq + Clone> {
+    snapshot: T,
+    current: T,
+    submission_key: Uuid,
+    listeners: Vec<ChangeListener>,
+}
+
+impl<T: PartialEq + Clone + fmt::Debug> fmt::Debug for FormState<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormState")
+            .field("snapshot", &self.snapshot)
+            .field("current", &self.current)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: PartialEq + Clone> FormState<T> {
+    /// 以加载的值创建表单状态
+    pub fn new(loaded: T) -> Self {
+        Self {
+            snapshot: loaded.clone(),
+            current: loaded,
+            submission_key: Uuid::new_v4(),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// 当前值
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    /// 加载时（或上次保存时）的值
+    pub fn snapshot(&self) -> &T {
+        &self.snapshot
+    }
+
+    /// 可变访问当前值，修改结束后通知订阅者
+    pub fn current_mut(&mut self) -> FormEdit<'_, T> {
+        FormEdit { form: self }
+    }
+
+    /// 是否有未保存的修改
+    pub fn is_dirty(&self) -> bool {
+        self.current != self.snapshot
+    }
+
+    /// 本次提交的幂等键
+    ///
+    /// 内容不变时重复提交使用同一个键；修改、保存或重新加载后生成新键。
+    pub fn submission_key(&self) -> Uuid {
+        self.submission_key
+    }
+
+    /// 放弃修改，恢复为快照
+    pub fn reset(&mut self) {
+        self.current = self.snapshot.clone();
+        self.changed();
+    }
+
+    /// 保存成功后以当前值作为新快照
+    pub fn mark_saved(&mut self) {
+        self.snapshot = self.current.clone();
+        self.changed();
+    }
+
+    /// 重新加载值（快照与当前值同时替换）
+    pub fn load(&mut self, value: T) {
+        self.snapshot = value.clone();
+        self.current = value;
+        self.changed();
+    }
+
+    /// 订阅修改通知，参数为修改后是否有未保存的修改
+    pub fn on_change<F>(&mut self, listener: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    fn changed(&mut self) {
+        self.submission_key = Uuid::new_v4();
+        self.notify();
+    }
+
+    fn notify(&self) {
+        let dirty = self.is_dirty();
+        for listener in &self.listeners {
+            listener(dirty);
+        }
+    }
+}
+
+/// 表单当前值的可变引用
+///
+/// 释放时通知订阅者。
+pub struct FormEdit<'a, T: PartialEq + Clone> {
+    form: &'a mut FormState<T>,
+}
+
+impl<T: PartialEq + Clone> fmt::Debug for FormEdit<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormEdit").finish_non_exhaustive()
+    }
+}
+
+impl<T: PartialEq + Clone> Deref for FormEdit<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.form.current
+    }
+}
+
+impl<T: PartialEq + Clone> DerefMut for FormEdit<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.form.current
+    }
+}
+
+impl<T: PartialEq + Clone> Drop for FormEdit<'_, T> {
+    fn drop(&mut self) {
+        self.form.changed();
+    }
+}
+
+/// 可被未保存修改确认流程处理的表单
+///
+/// 由各编辑器的控制器实现，保存时执行校验并写入。
+#[async_trait(?Send)]
+pub trait DirtyForm {
+    /// 表单名称（显示在确认对话框中）
+    fn name(&self) -> String;
+
+    /// 是否有未保存的修改
+    fn is_dirty(&self) -> bool;
+
+    /// 保存修改
+    ///
+    /// # Errors
+    ///
+    /// 校验未通过或写入失败时返回错误，导航将被阻止。
+    async fn save(&self) -> CoreResult<()>;
+
+    /// 放弃修改
+    fn discard(&self);
+}
+
+/// 未保存修改的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsavedChoice {
+    /// 保存后继续
+    Save,
+    /// 放弃修改后继续
+    Discard,
+    /// 留在当前视图
+    Cancel,
+}
+
+/// 表单注册ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FormId(u64);
+
+#[derive(Default)]
+struct RegistryInner {
+    next_id: u64,
+    forms: Vec<(FormId, Rc<dyn DirtyForm>)>,
+}
+
+/// 表单注册表
+///
+/// 同时打开的多个表单（如详情面板和快速编辑弹窗）都注册于此，
+/// 导航控制器和窗口关闭流程通过注册表统一检查。
+#[derive(Clone, Default)]
+pub struct FormRegistry {
+    inner: Rc<RefCell<RegistryInner>>,
+}
+
+impl fmt::Debug for FormRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormRegistry")
+            .field("forms", &self.inner.borrow().forms.len())
+            .finish()
+    }
+}
+
+impl FormRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册表单
+    pub fn register(&self, form: Rc<dyn DirtyForm>) -> FormId {
+        let mut inner = self.inner.borrow_mut();
+        inner.next_id += 1;
+        let id = FormId(inner.next_id);
+        inner.forms.push((id, form));
+        id
+    }
+
+    /// 注销表单（编辑器关闭时调用）
+    pub fn unregister(&self, id: FormId) {
+        self.inner.borrow_mut().forms.retain(|(form_id, _)| *form_id != id);
+    }
+
+    /// 有未保存修改的表单
+    pub fn dirty_forms(&self) -> Vec<Rc<dyn DirtyForm>> {
+        self.inner
+            .borrow()
+            .forms
+            .iter()
+            .filter(|(_, form)| form.is_dirty())
+            .map(|(_, form)| form.clone())
+            .collect()
+    }
+
+    /// 是否有任何表单存在未保存的修改
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.inner.borrow().forms.iter().any(|(_, form)| form.is_dirty())
+    }
+
+    /// 确认对话框提示文本
+    pub fn prompt_message(&self) -> String {
+        let names: Vec<String> = self.dirty_forms().iter().map(|form| form.name()).collect();
+        if names.is_empty() {
+            "你有未保存的修改".to_string()
+        } else {
+            format!("你有未保存的修改：{}", names.join("、"))
+        }
+    }
+
+    /// 按用户选择处理未保存的修改
+    ///
+    /// 返回是否可以继续（导航或关闭窗口）。
+    ///
+    /// # Errors
+    ///
+    /// 选择保存且任一表单保存失败（如校验未通过）时返回该错误，调用方应留在当前视图。
+    pub async fn resolve(&self, choice: UnsavedChoice) -> CoreResult<bool> {
+        match choice {
+            UnsavedChoice::Cancel => Ok(false),
+            UnsavedChoice::Discard => {
+                for form in self.dirty_forms() {
+                    form.discard();
+                }
+                Ok(true)
+            }
+            UnsavedChoice::Save => {
+                for form in self.dirty_forms() {
+                    form.save().await?;
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl NavigationGuard for FormRegistry {
+    fn has_unsaved_changes(&self) -> bool {
+        FormRegistry::has_unsaved_changes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::{NavigationController, NavigationOutcome, Route};
+    use minicrm_core::CoreError;
+    use std::cell::Cell;
+    use uuid::Uuid;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct CustomerDraft {
+        name: String,
+        phone: String,
+    }
+
+    /// 模拟客户编辑器：名称为空时保存失败
+    struct CustomerEditor {
+        label: &'static str,
+        state: RefCell<FormState<CustomerDraft>>,
+        saves: Cell<u32>,
+    }
+
+    impl CustomerEditor {
+        fn new(label: &'static str) -> Rc<Self> {
+            Rc::new(Self {
+                label,
+                state: RefCell::new(FormState::new(CustomerDraft {
+                    name: "华东板材".to_string(),
+                    phone: "021-0000".to_string(),
+                })),
+                saves: Cell::new(0),
+            })
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl DirtyForm for CustomerEditor {
+        fn name(&self) -> String {
+            self.label.to_string()
+        }
+
+        fn is_dirty(&self) -> bool {
+            self.state.borrow().is_dirty()
+        }
+
+        async fn save(&self) -> CoreResult<()> {
+            if self.state.borrow().current().name.trim().is_empty() {
+                return Err(CoreError::validation("客户名称不能为空"));
+            }
+            self.saves.set(self.saves.get() + 1);
+            self.state.borrow_mut().mark_saved();
+            Ok(())
+        }
+
+        fn discard(&self) {
+            self.state.borrow_mut().reset();
+        }
+    }
+
+    fn setup() -> (NavigationController, FormRegistry, Rc<CustomerEditor>) {
+        let registry = FormRegistry::new();
+        let editor = CustomerEditor::new("客户详情");
+        registry.register(editor.clone());
+
+        let mut nav = NavigationController::default();
+        nav.add_guard(Box::new(registry.clone()));
+        nav.navigate_to(Route::CustomerDetail { id: Uuid::new_v4() });
+        (nav, registry, editor)
+    }
+
+    #[test]
+    fn test_change_notification() {
+        let mut form = FormState::new(1);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        form.on_change({
+            let seen = seen.clone();
+            move |dirty| seen.borrow_mut().push(dirty)
+        });
+
+        *form.current_mut() = 2;
+        assert!(form.is_dirty());
+        *form.current_mut() = 1;
+        assert!(!form.is_dirty());
+        *form.current_mut() = 3;
+        form.mark_saved();
+        assert_eq!(form.snapshot(), &3);
+        assert_eq!(*seen.borrow(), vec![true, false, true, false]);
+    }
+
+    #[test]
+    fn test_submission_key_follows_edits() {
+        let mut form = FormState::new(1);
+        let loaded = form.submission_key();
+        assert_eq!(form.submission_key(), loaded);
+
+        *form.current_mut() = 2;
+        let edited = form.submission_key();
+        assert_ne!(edited, loaded);
+        form.mark_saved();
+        assert_ne!(form.submission_key(), edited);
+    }
+
+    #[tokio::test]
+    async fn test_edit_navigate_cancel() -> CoreResult<()> {
+        let (mut nav, registry, editor) = setup();
+        let here = nav.current().clone();
+        editor.state.borrow_mut().current_mut().phone = "13800000000".to_string();
+
+        assert_eq!(nav.navigate_to(Route::TaskBoard), NavigationOutcome::PendingConfirmation);
+        assert!(!registry.resolve(UnsavedChoice::Cancel).await?);
+        nav.cancel_pending();
+
+        assert_eq!(nav.current(), &here);
+        assert!(editor.is_dirty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_edit_save_with_validation_error() -> CoreResult<()> {
+        let (mut nav, registry, editor) = setup();
+        let here = nav.current().clone();
+        editor.state.borrow_mut().current_mut().name.clear();
+
+        assert_eq!(nav.navigate_to(Route::TaskBoard), NavigationOutcome::PendingConfirmation);
+        let result = registry.resolve(UnsavedChoice::Save).await;
+        assert!(matches!(result, Err(CoreError::Validation(_))));
+        nav.cancel_pending();
+        assert_eq!(nav.current(), &here);
+        assert!(editor.is_dirty());
+
+        // 修正后保存成功，导航继续
+        editor.state.borrow_mut().current_mut().name = "华东板材有限公司".to_string();
+        assert_eq!(nav.navigate_to(Route::TaskBoard), NavigationOutcome::PendingConfirmation);
+        assert!(registry.resolve(UnsavedChoice::Save).await?);
+        assert_eq!(nav.confirm_discard(), NavigationOutcome::Navigated);
+        assert_eq!(nav.current(), &Route::TaskBoard);
+        assert_eq!(editor.saves.get(), 1);
+        assert!(!editor.is_dirty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_edit_discard_multiple_forms() -> CoreResult<()> {
+        let (mut nav, registry, detail) = setup();
+        let popup = CustomerEditor::new("快速编辑");
+        let popup_id = registry.register(popup.clone());
+
+        detail.state.borrow_mut().current_mut().phone = "13800000000".to_string();
+        popup.state.borrow_mut().current_mut().name = "临时名称".to_string();
+        assert_eq!(registry.dirty_forms().len(), 2);
+        assert_eq!(registry.prompt_message(), "你有未保存的修改：客户详情、快速编辑");
+
+        assert_eq!(nav.back(), NavigationOutcome::PendingConfirmation);
+        assert!(registry.resolve(UnsavedChoice::Discard).await?);
+        assert_eq!(nav.confirm_discard(), NavigationOutcome::Navigated);
+        assert_eq!(nav.current(), &Route::Dashboard);
+        assert!(!detail.is_dirty());
+        assert!(!popup.is_dirty());
+
+        // 注销后不再参与检查
+        popup.state.borrow_mut().current_mut().name.clear();
+        registry.unregister(popup_id);
+        assert!(!registry.has_unsaved_changes());
+        Ok(())
+    }
+}
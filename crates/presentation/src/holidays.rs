@@ -0,0 +1,308 @@
+//! 节假日设置模块
+//!
+//! 设置界面中的工作时间面板：按年份查看和编辑节假日表（放假、调休上班），
+//! 勾选每周休息日。节假日表通过 [`HolidayService`] 保存；休息日属于应用配置，
+//! 由调用方写回配置文件。
+
+use std::sync::Arc;
+
+use chrono::{Datelike, Weekday};
+use minicrm_core::{CoreError, CoreResult, HolidayEntry, HolidayKind, HolidayService, LocalDate};
+
+/// 星期的显示名称
+fn weekday_label(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "周一",
+        Weekday::Tue => "周二",
+        Weekday::Wed => "周三",
+        Weekday::Thu => "周四",
+        Weekday::Fri => "周五",
+        Weekday::Sat => "周六",
+        Weekday::Sun => "周日",
+    }
+}
+
+/// 一周七天（周一在前）
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// 节假日表中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HolidayRow {
+    /// 日期
+    pub date: LocalDate,
+    /// 日期文本，如“2026-02-16 周一”
+    pub date_text: String,
+    /// 名称
+    pub name: String,
+    /// 类型文本（放假、调休上班）
+    pub kind_text: &'static str,
+    /// 是否为调休上班日
+    pub is_workday: bool,
+}
+
+impl HolidayRow {
+    fn from_entry(entry: &HolidayEntry) -> Self {
+        Self {
+            date: entry.date,
+            date_text: format!("{} {}", entry.date, weekday_label(entry.date.naive().weekday())),
+            name: entry.name.clone(),
+            kind_text: entry.kind.label(),
+            is_workday: entry.kind == HolidayKind::Workday,
+        }
+    }
+}
+
+/// 每周休息日的勾选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestDayOption {
+    /// 星期
+    pub weekday: Weekday,
+    /// 显示名称
+    pub label: &'static str,
+    /// 是否为休息日
+    pub checked: bool,
+}
+
+/// 工作时间设置视图模型
+#[derive(Clone)]
+pub struct HolidaySettingsViewModel {
+    holidays: Arc<dyn HolidayService>,
+    /// 当前查看的年份
+    pub year: i32,
+    /// 节假日表
+    pub rows: Vec<HolidayRow>,
+    /// 每周休息日
+    pub rest_days: Vec<Weekday>,
+    /// 出错提示
+    pub error: Option<String>,
+}
+
+impl std::fmt::Debug for HolidaySettingsViewModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HolidaySettingsViewModel")
+            .field("year", &self.year)
+            .field("rows", &self.rows)
+            .field("rest_days", &self.rest_days)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HolidaySettingsViewModel {
+    /// 创建面板，节假日表需调用 [`Self::load`] 加载
+    pub fn new(holidays: Arc<dyn HolidayService>, year: i32, rest_days: Vec<Weekday>) -> Self {
+        Self {
+            holidays,
+            year,
+            rows: Vec::new(),
+            rest_days,
+            error: None,
+        }
+    }
+
+    /// 加载当前年份的节假日表
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub async fn load(&mut self) -> CoreResult<()> {
+        let entries = self.holidays.holidays_in_year(self.year).await?;
+        self.rows = entries.iter().map(HolidayRow::from_entry).collect();
+        Ok(())
+    }
+
+    /// 切换年份并重新加载
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub async fn show_year(&mut self, year: i32) -> CoreResult<()> {
+        self.year = year;
+        self.load().await
+    }
+
+    /// 新增或修改某一天；不在当前年份时切换到该年份
+    ///
+    /// 校验错误显示在面板中并返回 `false`。
+    ///
+    /// # Errors
+    ///
+    /// 保存或查询失败（校验错误除外）时返回错误。
+    pub async fn save(
+        &mut self,
+        date: LocalDate,
+        name: &str,
+        kind: HolidayKind,
+    ) -> CoreResult<bool> {
+        let entry = HolidayEntry {
+            date,
+            name: name.trim().to_string(),
+            kind,
+        };
+        match self.holidays.save_holiday(entry).await {
+            Ok(()) => {}
+            Err(CoreError::Validation(message)) => {
+                self.error = Some(message);
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        }
+        self.error = None;
+        self.show_year(date.naive().year()).await?;
+        Ok(true)
+    }
+
+    /// 删除某一天
+    ///
+    /// # Errors
+    ///
+    /// 删除或查询失败时返回错误。
+    pub async fn remove(&mut self, date: LocalDate) -> CoreResult<()> {
+        self.holidays.remove_holiday(date).await?;
+        self.load().await
+    }
+
+    /// 每周休息日的勾选项（周一在前）
+    pub fn rest_day_options(&self) -> Vec<RestDayOption> {
+        WEEK.into_iter()
+            .map(|weekday| RestDayOption {
+                weekday,
+                label: weekday_label(weekday),
+                checked: self.rest_days.contains(&weekday),
+            })
+            .collect()
+    }
+
+    /// 勾选或取消休息日
+    ///
+    /// 不允许把七天都设为休息日，此时保持不变并返回 `false`。
+    pub fn toggle_rest_day(&mut self, weekday: Weekday) -> bool {
+        if let Some(index) = self.rest_days.iter().position(|day| *day == weekday) {
+            self.rest_days.remove(index);
+        } else {
+            if self.rest_days.len() == WEEK.len() - 1 {
+                self.error = Some("每周至少要有一个工作日".to_string());
+                return false;
+            }
+            self.rest_days.push(weekday);
+            self.rest_days.sort_by_key(Weekday::num_days_from_monday);
+        }
+        self.error = None;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeHolidays {
+        entries: Mutex<BTreeMap<LocalDate, HolidayEntry>>,
+    }
+
+    #[async_trait]
+    impl HolidayService for FakeHolidays {
+        async fn holidays_in_year(&self, year: i32) -> CoreResult<Vec<HolidayEntry>> {
+            Ok(self
+                .entries
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|entry| entry.date.naive().year() == year)
+                .cloned()
+                .collect())
+        }
+
+        async fn all_holidays(&self) -> CoreResult<Vec<HolidayEntry>> {
+            Ok(self.entries.lock().unwrap().values().cloned().collect())
+        }
+
+        async fn save_holiday(&self, entry: HolidayEntry) -> CoreResult<()> {
+            if entry.name.is_empty() {
+                return Err(CoreError::validation("节假日名称不能为空"));
+            }
+            self.entries.lock().unwrap().insert(entry.date, entry);
+            Ok(())
+        }
+
+        async fn remove_holiday(&self, date: LocalDate) -> CoreResult<bool> {
+            Ok(self.entries.lock().unwrap().remove(&date).is_some())
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> LocalDate {
+        LocalDate::new(year, month, day).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_edit_holiday_table() {
+        let mut panel = HolidaySettingsViewModel::new(
+            Arc::new(FakeHolidays::default()),
+            2026,
+            vec![Weekday::Sat, Weekday::Sun],
+        );
+        panel.load().await.unwrap();
+        assert!(panel.rows.is_empty());
+
+        assert!(panel
+            .save(date(2026, 2, 14), "春节调休", HolidayKind::Workday)
+            .await
+            .unwrap());
+        assert!(panel
+            .save(date(2026, 2, 16), "春节", HolidayKind::Holiday)
+            .await
+            .unwrap());
+        assert_eq!(panel.rows.len(), 2);
+        assert_eq!(panel.rows[0].date_text, "2026-02-14 周六");
+        assert_eq!(panel.rows[0].kind_text, "调休上班");
+        assert!(panel.rows[0].is_workday);
+        assert_eq!(panel.rows[1].date_text, "2026-02-16 周一");
+
+        // 校验错误显示在面板中
+        assert!(!panel
+            .save(date(2026, 3, 1), "  ", HolidayKind::Holiday)
+            .await
+            .unwrap());
+        assert!(panel.error.is_some());
+
+        // 保存到其他年份时切换过去
+        assert!(panel
+            .save(date(2027, 1, 1), "元旦", HolidayKind::Holiday)
+            .await
+            .unwrap());
+        assert_eq!(panel.year, 2027);
+        assert_eq!(panel.rows.len(), 1);
+        panel.remove(date(2027, 1, 1)).await.unwrap();
+        assert!(panel.rows.is_empty());
+    }
+
+    #[test]
+    fn test_rest_day_toggles() {
+        let mut panel =
+            HolidaySettingsViewModel::new(Arc::new(FakeHolidays::default()), 2026, Vec::new());
+        for weekday in &WEEK[..6] {
+            assert!(panel.toggle_rest_day(*weekday));
+        }
+        assert!(!panel.toggle_rest_day(Weekday::Sun));
+        assert_eq!(panel.rest_days.len(), 6);
+        assert!(panel.error.is_some());
+
+        assert!(panel.toggle_rest_day(Weekday::Mon));
+        let options = panel.rest_day_options();
+        assert_eq!(options[0].label, "周一");
+        assert!(!options[0].checked);
+        assert!(options[5].checked);
+        assert_eq!(panel.rest_days.first(), Some(&Weekday::Tue));
+    }
+}
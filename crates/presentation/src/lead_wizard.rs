@@ -0,0 +1,490 @@
+//! 新建线索向导
+//!
+//! 新客户建档分三步：客户、首要联系人、首个跟进任务（可不填）。上一步、下一步之间已填写的
+//! 内容保持不变；点击“下一步”时只校验当前步骤，校验规则与 [`CreateLeadCommand::validate`]
+//! 相同。只有最后一步的“完成”会分发命令，三项记录在同一事务中写入；任何一步取消都不会
+//! 新建记录。提交返回的字段错误按字段归属显示在对应步骤，并跳回第一个有错误的步骤。
+
+use chrono::NaiveDate;
+use minicrm_application::commands::{CreateLeadCommand, LeadTask};
+use minicrm_application::CommandBus;
+use minicrm_core::{CoreError, CoreResult, CreatedLead, FieldError, LocalDate, TaskPriority};
+use uuid::Uuid;
+
+use crate::errors::UserMessage;
+
+/// 截止日期的输入格式
+const DUE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// 向导步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LeadStep {
+    /// 客户
+    Customer,
+    /// 首要联系人
+    Contact,
+    /// 首个跟进任务
+    FirstTask,
+}
+
+impl LeadStep {
+    /// 全部步骤（按显示顺序）
+    pub const ALL: [LeadStep; 3] = [LeadStep::Customer, LeadStep::Contact, LeadStep::FirstTask];
+
+    /// 步骤标题
+    pub fn title(&self) -> &'static str {
+        match self {
+            LeadStep::Customer => "客户信息",
+            LeadStep::Contact => "首要联系人",
+            LeadStep::FirstTask => "跟进任务",
+        }
+    }
+
+    /// 字段所属的步骤（按命令字段名的前缀）
+    pub fn of_field(key: &str) -> LeadStep {
+        if key.starts_with("contact_") {
+            LeadStep::Contact
+        } else if key.starts_with("task_") {
+            LeadStep::FirstTask
+        } else {
+            LeadStep::Customer
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|step| step == self).unwrap_or(0)
+    }
+}
+
+/// 向导中的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeadField {
+    /// 字段名（与 [`CreateLeadCommand`] 校验错误中的字段名一致）
+    pub key: &'static str,
+    /// 标签
+    pub label: &'static str,
+    /// 是否必填（跟进任务的字段只在填写任务时必填）
+    pub required: bool,
+}
+
+/// 全部字段（按步骤顺序）
+pub const LEAD_FIELDS: &[LeadField] = &[
+    LeadField {
+        key: "name",
+        label: "客户名称",
+        required: true,
+    },
+    LeadField {
+        key: "address",
+        label: "地址",
+        required: false,
+    },
+    LeadField {
+        key: "contact_name",
+        label: "联系人",
+        required: true,
+    },
+    LeadField {
+        key: "contact_phone",
+        label: "电话",
+        required: false,
+    },
+    LeadField {
+        key: "contact_email",
+        label: "邮箱",
+        required: false,
+    },
+    LeadField {
+        key: "task_title",
+        label: "任务标题",
+        required: true,
+    },
+    LeadField {
+        key: "task_due_on",
+        label: "截止日期",
+        required: false,
+    },
+    LeadField {
+        key: "task_description",
+        label: "任务描述",
+        required: false,
+    },
+];
+
+/// 步骤中的一个字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeadFieldRow {
+    /// 字段名
+    pub key: &'static str,
+    /// 标签
+    pub label: &'static str,
+    /// 当前值
+    pub value: String,
+    /// 是否必填
+    pub required: bool,
+    /// 字段错误
+    pub error: Option<String>,
+}
+
+/// 新建线索向导
+#[derive(Debug, Clone)]
+pub struct NewLeadWizardViewModel {
+    step: LeadStep,
+    values: Vec<String>,
+    errors: Vec<Option<String>>,
+    /// 跟进任务的优先级
+    pub priority: TaskPriority,
+    /// 不属于具体字段的错误
+    pub message: Option<UserMessage>,
+    submission_key: Uuid,
+}
+
+impl Default for NewLeadWizardViewModel {
+    fn default() -> Self {
+        Self {
+            step: LeadStep::Customer,
+            values: vec![String::new(); LEAD_FIELDS.len()],
+            errors: vec![None; LEAD_FIELDS.len()],
+            priority: TaskPriority::Medium,
+            message: None,
+            submission_key: Uuid::new_v4(),
+        }
+    }
+}
+
+impl NewLeadWizardViewModel {
+    /// 创建向导（从客户步骤开始，内容为空）
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前步骤
+    pub fn step(&self) -> LeadStep {
+        self.step
+    }
+
+    /// 是否是第一步（不显示“上一步”）
+    pub fn is_first_step(&self) -> bool {
+        self.step == LeadStep::ALL[0]
+    }
+
+    /// 是否是最后一步（“下一步”换成“完成”）
+    pub fn is_last_step(&self) -> bool {
+        self.step == LeadStep::ALL[LeadStep::ALL.len() - 1]
+    }
+
+    /// 字段当前值，未知字段返回空
+    pub fn value(&self, key: &str) -> &str {
+        Self::position(key).map_or("", |index| self.values[index].as_str())
+    }
+
+    /// 修改字段值，同时清除该字段的错误
+    pub fn set_value(&mut self, key: &str, value: impl Into<String>) {
+        let Some(index) = Self::position(key) else {
+            return;
+        };
+        self.values[index] = value.into();
+        self.errors[index] = None;
+        self.submission_key = Uuid::new_v4();
+    }
+
+    /// 步骤中的字段
+    pub fn fields(&self, step: LeadStep) -> Vec<LeadFieldRow> {
+        let task_filled = self.task_filled();
+        LEAD_FIELDS
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| LeadStep::of_field(field.key) == step)
+            .map(|(index, field)| LeadFieldRow {
+                key: field.key,
+                label: field.label,
+                value: self.values[index].clone(),
+                required: field.required && (step != LeadStep::FirstTask || task_filled),
+                error: self.errors[index].clone(),
+            })
+            .collect()
+    }
+
+    /// 步骤是否有未修正的错误（步骤条上标记）
+    pub fn has_errors(&self, step: LeadStep) -> bool {
+        LEAD_FIELDS
+            .iter()
+            .zip(&self.errors)
+            .any(|(field, error)| error.is_some() && LeadStep::of_field(field.key) == step)
+    }
+
+    /// 校验当前步骤，通过后进入下一步；返回是否已前进
+    pub fn next_step(&mut self) -> bool {
+        if self.is_last_step() {
+            return false;
+        }
+        let step = self.step;
+        let errors = self.field_errors();
+        for (field, error) in LEAD_FIELDS.iter().zip(&mut self.errors) {
+            if LeadStep::of_field(field.key) == step {
+                *error = None;
+            }
+        }
+        self.show_errors(
+            errors
+                .into_iter()
+                .filter(|e| LeadStep::of_field(&e.field) == step),
+        );
+        if self.has_errors(step) {
+            return false;
+        }
+        self.step = LeadStep::ALL[step.index() + 1];
+        true
+    }
+
+    /// 回到上一步，已填写的内容保持不变
+    pub fn previous_step(&mut self) {
+        if !self.is_first_step() {
+            self.step = LeadStep::ALL[self.step.index() - 1];
+        }
+    }
+
+    /// 取消向导，清空全部内容，不新建任何记录
+    pub fn cancel(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 由已填写的内容生成新建命令（跟进任务的字段都为空时不新建任务）
+    ///
+    /// # Errors
+    ///
+    /// 截止日期格式不正确时返回字段校验错误。
+    pub fn command(&self) -> CoreResult<CreateLeadCommand> {
+        match self.draft() {
+            (command, None) => Ok(command),
+            (_, Some(due_error)) => Err(CoreError::invalid_fields(vec![due_error])),
+        }
+    }
+
+    /// 生成新建命令，截止日期格式不正确时按未填写处理并返回其字段错误
+    fn draft(&self) -> (CreateLeadCommand, Option<FieldError>) {
+        let mut due_error = None;
+        let first_task = self.task_filled().then(|| LeadTask {
+            title: self.value("task_title").trim().to_string(),
+            description: self.optional("task_description"),
+            priority: self.priority,
+            due_on: self.optional("task_due_on").and_then(|text| {
+                NaiveDate::parse_from_str(&text, DUE_DATE_FORMAT)
+                    .map(LocalDate::from)
+                    .map_err(|_| {
+                        due_error = Some(FieldError::new(
+                            "task_due_on",
+                            "截止日期格式应为 2024-06-20",
+                        ));
+                    })
+                    .ok()
+            }),
+        });
+        let command = CreateLeadCommand {
+            name: self.value("name").trim().to_string(),
+            address: self.optional("address"),
+            contact_name: self.value("contact_name").trim().to_string(),
+            contact_phone: self.optional("contact_phone"),
+            contact_email: self.optional("contact_email"),
+            first_task,
+            idempotency_key: Some(self.submission_key),
+        };
+        (command, due_error)
+    }
+
+    /// 按新建命令的规则校验全部字段
+    fn field_errors(&self) -> Vec<FieldError> {
+        let (command, due_error) = self.draft();
+        let mut errors = match command.validate() {
+            Err(CoreError::InvalidFields(errors)) => errors,
+            _ => Vec::new(),
+        };
+        errors.extend(due_error);
+        errors
+    }
+
+    /// 提交向导
+    ///
+    /// 成功时清空向导并返回新建的客户、联系人和任务ID；失败时不新建任何记录，字段错误
+    /// 显示在所属步骤并跳回第一个有错误的步骤，返回 `None`。内容不变时重复提交使用
+    /// 同一个幂等键。
+    pub async fn submit(&mut self, bus: &CommandBus) -> Option<CreatedLead> {
+        let errors = self.field_errors();
+        let result = if errors.is_empty() {
+            match self.command() {
+                Ok(command) => bus.dispatch_idempotent(command).await,
+                Err(e) => Err(e),
+            }
+        } else {
+            Err(CoreError::invalid_fields(errors))
+        };
+        match result {
+            Ok(created) => {
+                self.cancel();
+                Some(created)
+            }
+            Err(CoreError::InvalidFields(errors)) => {
+                for error in &mut self.errors {
+                    *error = None;
+                }
+                self.show_errors(errors);
+                if let Some(step) = LeadStep::ALL.into_iter().find(|s| self.has_errors(*s)) {
+                    self.step = step;
+                }
+                None
+            }
+            Err(e) => {
+                self.message = Some(UserMessage::from_error(&e));
+                None
+            }
+        }
+    }
+
+    fn position(key: &str) -> Option<usize> {
+        LEAD_FIELDS.iter().position(|field| field.key == key)
+    }
+
+    fn optional(&self, key: &str) -> Option<String> {
+        let value = self.value(key).trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    fn task_filled(&self) -> bool {
+        LEAD_FIELDS
+            .iter()
+            .zip(&self.values)
+            .any(|(field, value)| field.key.starts_with("task_") && !value.trim().is_empty())
+    }
+
+    /// 显示字段错误，不属于向导字段的错误显示为提示
+    fn show_errors(&mut self, errors: impl IntoIterator<Item = FieldError>) {
+        let mut unmatched = Vec::new();
+        for error in errors {
+            match Self::position(&error.field) {
+                Some(index) => self.errors[index] = Some(error.message),
+                None => unmatched.push(error),
+            }
+        }
+        self.message = (!unmatched.is_empty())
+            .then(|| UserMessage::from_error(&CoreError::InvalidFields(unmatched)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use minicrm_application::CommandHandler;
+
+    /// 模拟线索处理器：按命令校验，电话为 13900000000 时模拟服务端拒绝
+    #[derive(Default)]
+    struct FakeLeads {
+        created: AtomicU32,
+    }
+
+    #[async_trait]
+    impl CommandHandler<CreateLeadCommand> for FakeLeads {
+        async fn handle(&self, command: CreateLeadCommand) -> CoreResult<CreatedLead> {
+            command.validate()?;
+            if command.contact_phone.as_deref() == Some("13900000000") {
+                return Err(CoreError::invalid_fields(vec![FieldError::new(
+                    "contact_phone",
+                    "电话已被其他客户使用",
+                )]));
+            }
+            self.created.fetch_add(1, Ordering::SeqCst);
+            Ok(CreatedLead {
+                customer_id: Uuid::new_v4(),
+                contact_id: Uuid::new_v4(),
+                task_id: command.first_task.map(|_| Uuid::new_v4()),
+            })
+        }
+    }
+
+    fn bus() -> (CommandBus, Arc<FakeLeads>) {
+        let leads = Arc::new(FakeLeads::default());
+        let mut bus = CommandBus::new();
+        bus.register::<CreateLeadCommand>(leads.clone());
+        (bus, leads)
+    }
+
+    #[test]
+    fn test_steps_keep_values_and_validate_current_step() {
+        let mut wizard = NewLeadWizardViewModel::new();
+        assert!(!wizard.next_step());
+        assert!(wizard.has_errors(LeadStep::Customer));
+        assert!(!wizard.has_errors(LeadStep::Contact));
+
+        wizard.set_value("name", "华东建材");
+        assert!(wizard.next_step());
+        assert_eq!(wizard.step(), LeadStep::Contact);
+        wizard.set_value("contact_name", "王经理");
+        wizard.set_value("contact_phone", "12");
+        assert!(!wizard.next_step());
+        assert_eq!(wizard.fields(LeadStep::Contact)[1].key, "contact_phone");
+        assert!(wizard.fields(LeadStep::Contact)[1].error.is_some());
+
+        wizard.previous_step();
+        assert_eq!(wizard.step(), LeadStep::Customer);
+        assert_eq!(wizard.value("name"), "华东建材");
+        assert!(wizard.next_step());
+        assert_eq!(wizard.value("contact_name"), "王经理");
+        wizard.set_value("contact_phone", "13800138000");
+        assert!(wizard.next_step());
+        assert!(wizard.is_last_step());
+        // 未填写任务时任务标题不必填
+        assert!(!wizard.fields(LeadStep::FirstTask)[0].required);
+        assert!(wizard.command().unwrap().first_task.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_submit_maps_errors_to_owning_step() {
+        let (bus, leads) = bus();
+        let mut wizard = NewLeadWizardViewModel::new();
+        wizard.set_value("name", "华东建材");
+        wizard.next_step();
+        wizard.set_value("contact_name", "王经理");
+        wizard.set_value("contact_phone", "13900000000");
+        wizard.next_step();
+        wizard.set_value("task_due_on", "6月20日");
+
+        // 截止日期格式不正确，留在任务步骤
+        assert!(wizard.submit(&bus).await.is_none());
+        assert_eq!(wizard.step(), LeadStep::FirstTask);
+        assert!(wizard.has_errors(LeadStep::FirstTask));
+
+        // 服务端拒绝联系人电话，跳回联系人步骤
+        wizard.set_value("task_due_on", "2024-06-20");
+        wizard.set_value("task_title", "电话回访");
+        assert!(wizard.submit(&bus).await.is_none());
+        assert_eq!(wizard.step(), LeadStep::Contact);
+        assert!(wizard.has_errors(LeadStep::Contact));
+        assert!(!wizard.has_errors(LeadStep::FirstTask));
+        assert_eq!(leads.created.load(Ordering::SeqCst), 0);
+
+        wizard.set_value("contact_phone", "13800138000");
+        let created = wizard.submit(&bus).await.unwrap();
+        assert!(created.task_id.is_some());
+        assert_eq!(leads.created.load(Ordering::SeqCst), 1);
+        assert_eq!(wizard.step(), LeadStep::Customer);
+        assert_eq!(wizard.value("name"), "");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_creates_nothing() {
+        let (_bus, leads) = bus();
+        let mut wizard = NewLeadWizardViewModel::new();
+        wizard.set_value("name", "华东建材");
+        wizard.next_step();
+        wizard.set_value("contact_name", "王经理");
+        wizard.cancel();
+
+        assert_eq!(wizard.step(), LeadStep::Customer);
+        assert_eq!(wizard.value("name"), "");
+        assert_eq!(wizard.value("contact_name"), "");
+        assert_eq!(leads.created.load(Ordering::SeqCst), 0);
+    }
+}
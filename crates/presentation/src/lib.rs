@@ -5,7 +5,130 @@
 #![deny(unsafe_code)]
 #![warn(missing_docs)]
 
+pub mod anniversaries;
+pub mod attachments;
+pub mod columns;
 pub mod controllers;
+pub mod cut_calculator;
+pub mod dashboard;
+pub mod duplicate_warning;
+pub mod edit_sessions;
+pub mod email_intake;
+pub mod errors;
+pub mod external_actions;
+pub mod field_policies;
+pub mod formatting;
+pub mod forms;
+pub mod holidays;
+pub mod lead_wizard;
+pub mod list_export;
+pub mod live_refresh;
+pub mod maintenance;
+pub mod navigation;
+pub mod onboarding;
+pub mod progress;
+pub mod quick_create;
+pub mod recent_items;
+pub mod reminders;
+pub mod theme;
+pub mod tickets;
+pub mod timeline;
+pub mod undo;
+pub mod validation;
 pub mod view_models;
 
 // 重新导出主要类型
+pub use anniversaries::{
+    anniversary_rows, AnniversaryRow, MaintenanceAction, UPCOMING_ANNIVERSARIES_TITLE,
+};
+pub use attachments::{
+    AttachmentEntry, AttachmentGalleryViewModel, AttachmentPreview, AttachmentTile, TOMBSTONE_LABEL,
+};
+pub use columns::{
+    ColumnChooserItem, ColumnChooserViewModel, ColumnConfig, ColumnDef, ColumnLayout,
+    ColumnSetting, VisibleColumn,
+};
+pub use cut_calculator::CutCalculatorDialog;
+pub use dashboard::{
+    CardData, CardDataSource, CardSlot, DashboardCard, DashboardCardRegistry, DashboardCardView,
+    DashboardLayout, DashboardViewModel,
+};
+pub use duplicate_warning::{
+    DuplicateWarningController, DuplicateWarningRow, DUPLICATE_CHECK_INTERVAL,
+};
+pub use edit_sessions::{EditBanner, EditOpen, EditSessionRegistry, EditorId, ReloadOffer};
+pub use email_intake::{
+    EmailIntakeSettingsViewModel, MailboxSettings, MailboxSettingsUpdate, DEFAULT_POLL_MINUTES,
+};
+pub use errors::{MessageKind, UserMessage};
+pub use external_actions::{
+    map_search_url, map_url, tel_uri, ExternalActionService, PendingCall, QuickAction,
+};
+pub use formatting::{
+    format_area, format_date, format_money, format_money_chinese_upper, format_relative,
+};
+pub use field_policies::{FieldPolicyRow, FieldPolicySettingsViewModel};
+pub use forms::{DirtyForm, FormId, FormRegistry, FormState, UnsavedChoice};
+pub use holidays::{HolidayRow, HolidaySettingsViewModel, RestDayOption};
+pub use lead_wizard::{LeadField, LeadFieldRow, LeadStep, NewLeadWizardViewModel, LEAD_FIELDS};
+pub use list_export::{
+    export_customers_command, CustomerExportJob, ExportScopeChoice, ExportToast,
+    OPEN_FOLDER_LABEL,
+};
+pub use live_refresh::{
+    LiveChange, LiveRefresh, LiveSubscription, LiveViewModel, REFRESH_DEBOUNCE,
+};
+pub use maintenance::{
+    migration_report_summary, ArchiveConfirmation, ArchivePreviewRow, ArchiveRunRow,
+    ConsistencyCheckRow, ExternalServiceRow, MigrationCheckRow, PoolSizeAdvice,
+    ReclaimPromptState, ReclaimThreshold, RetentionRunRow, SearchIndexRow, SizeChartPoint,
+    SizeHistoryChart, SlowQueryRow, StorageGcRow, APPLY_POOL_SIZE_LABEL, ARCHIVE_NOW_LABEL,
+    CHECK_CONSISTENCY_LABEL, SIZE_CHART_MAX_POINTS, VERIFY_MIGRATIONS_LABEL,
+};
+pub use navigation::{
+    CustomerListFilter, NavigationController, NavigationGuard, NavigationOutcome, Route,
+};
+pub use onboarding::{
+    OnboardingChecklistViewModel, OnboardingProbe, OnboardingState, OnboardingStep,
+    OnboardingStepView,
+};
+pub use progress::{ProgressDialog, ProgressState, CANCEL_LABEL};
+pub use quick_create::{
+    CustomerOption, CustomerPicker, CustomerQuickCreate, EntityPicker, PickerChoice, PickerRow,
+    ProductOption, ProductPicker, ProductQuickCreate, QuickCreate, QuickCreateController,
+    QuickCreateField, QuickCreateFieldRow, QuickCreateForm,
+};
+pub use recent_items::{
+    quick_switcher_shortcut, RecentItem, RecentItemRow, RecentItems, RecentItemsViewModel,
+    DEFAULT_QUICK_SWITCHER_SHORTCUT, DELETED_ENTRY_MESSAGE, QUICK_SWITCHER_SHORTCUT_ACTION,
+    RECENT_ITEMS_LABEL,
+};
+pub use reminders::{
+    save_reminder_ops, ReminderListModel, ReminderOp, ReminderRow, ReminderTicket,
+};
+pub use theme::{
+    clamp_font_scale, AppearanceSettings, ThemeManager, ThemeTokens, ThemeVariant,
+    DEFAULT_FONT_SCALE, MAX_FONT_SCALE, MIN_FONT_SCALE,
+};
+pub use tickets::{
+    ArticleSuggestionRow, TicketEditorViewModel, TicketListRow, SAVE_AS_ARTICLE_LABEL,
+};
+pub use timeline::{CustomerTimelineViewModel, TimelineFilter, TimelineRow};
+pub use undo::{
+    undo_shortcut, InverseCommand, UndoAction, UndoStack, UndoToast, DEFAULT_UNDO_SHORTCUT,
+    UNDO_LABEL, UNDO_SHORTCUT_ACTION, UNDO_STACK_LIMIT,
+};
+pub use validation::{
+    FieldFeedback, FieldValidatorRegistry, CUSTOMER_FORM, PRODUCT_FORM, VALIDATION_DEBOUNCE,
+};
+pub use view_models::{
+    CreditGauge, CustomFieldRow, CustomerDeletionDialog, CustomerDetailViewModel,
+    CustomerListViewModel, CustomerRelationsPanel, DeliveryCard, DeliveryColumn,
+    DeliveryWeekViewModel, DiffKind, DiffRow, FunnelBar, LockScreenViewModel,
+    PipelineFunnelViewModel, PriceAdjustmentDialog, PriceHistoryRow, ProductDetailViewModel,
+    PurchaseOrderRow, QuoteEditorViewModel, QuoteHistoryViewModel, QuoteLineRow, QuoteListRow,
+    QuoteTemplatePicker, QuoteTemplateRow, QuoteTemplatesViewModel, RelatedCustomerRow,
+    RelatedCustomerSection, RelationChange, SupplierComparisonViewModel, SupplierDetailViewModel,
+    SupplierPriceRow, TaskBoardViewModel, TaskCard, TaskColumn, TrashRow, TrashViewModel,
+    UserDeactivationDialog,
+};
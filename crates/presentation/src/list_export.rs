@@ -0,0 +1,321 @@
+//! 列表导出
+//!
+//! 导出按钮先选择范围：当前筛选结果（与列表相同的筛选条件和排序，保存的搜索打开的列表
+//! 也一样）、选中的行（按界面上的顺序）或全部客户。导出期间显示进度对话框，已写出的行数
+//! 来自命令中的计数器；完成后的提示带“打开所在文件夹”操作。
+
+use std::path::{Path, PathBuf};
+
+use minicrm_application::commands::ExportCustomersCommand;
+use minicrm_core::{
+    CancellationToken, CoreResult, CustomerExportRequest, ExportProgress, ExportScope, UriOpener,
+};
+
+use crate::errors::UserMessage;
+use crate::progress::ProgressDialog;
+
+/// 完成提示中的操作按钮文本
+pub const OPEN_FOLDER_LABEL: &str = "打开所在文件夹";
+
+/// 导出范围选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportScopeChoice {
+    /// 当前筛选结果
+    Filtered,
+    /// 选中的行
+    Selection,
+    /// 全部客户
+    All,
+}
+
+impl ExportScopeChoice {
+    /// 全部选项（按显示顺序）
+    pub const ALL: [ExportScopeChoice; 3] = [
+        ExportScopeChoice::Filtered,
+        ExportScopeChoice::Selection,
+        ExportScopeChoice::All,
+    ];
+
+    /// 选项文本
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportScopeChoice::Filtered => "当前筛选结果",
+            ExportScopeChoice::Selection => "选中的行",
+            ExportScopeChoice::All => "全部客户",
+        }
+    }
+
+    /// 是否可选（没有选中任何行时不能选择“选中的行”）
+    pub fn available(&self, selected: usize) -> bool {
+        *self != ExportScopeChoice::Selection || selected > 0
+    }
+}
+
+/// 生成导出客户列表命令
+pub fn export_customers_command(
+    request: CustomerExportRequest,
+    out_path: impl Into<PathBuf>,
+) -> ExportCustomersCommand {
+    ExportCustomersCommand {
+        request,
+        out_path: out_path.into(),
+        progress: ExportProgress::new(),
+        cancel: CancellationToken::new(),
+    }
+}
+
+/// 正在执行的导出
+#[derive(Debug, Clone)]
+pub struct CustomerExportJob {
+    /// 进度对话框
+    pub dialog: ProgressDialog,
+    progress: ExportProgress,
+    out_path: PathBuf,
+}
+
+impl CustomerExportJob {
+    /// 为已分发的导出命令打开进度对话框
+    ///
+    /// `command` 为分发前保留的副本（与分发的命令共享进度计数器），`cancel` 为
+    /// [`CommandBus::dispatch_cancellable`](minicrm_application::CommandBus::dispatch_cancellable)
+    /// 返回的令牌。选中的行按选中数显示百分比，其余范围总数未知时只显示已导出的行数。
+    pub fn new(command: &ExportCustomersCommand, cancel: CancellationToken) -> Self {
+        let mut dialog = ProgressDialog::new("导出客户", cancel);
+        if let ExportScope::Selection { ids } = &command.request.scope {
+            dialog.set_progress(0, Some(ids.len() as u64));
+        }
+        Self {
+            dialog,
+            progress: command.progress.clone(),
+            out_path: command.out_path.clone(),
+        }
+    }
+
+    /// 设置预计行数（如当前筛选结果的总数）
+    pub fn with_total(mut self, total: u64) -> Self {
+        self.dialog.set_progress(self.dialog.processed, Some(total));
+        self
+    }
+
+    /// 按计数器刷新进度（界面定时调用）
+    pub fn poll(&mut self) {
+        self.dialog
+            .set_progress(self.progress.rows_written(), self.dialog.total);
+    }
+
+    /// 命令结束后更新对话框，导出成功时返回完成提示
+    pub fn finish(&mut self, result: &CoreResult<u64>) -> Option<ExportToast> {
+        self.poll();
+        self.dialog.finish(result);
+        let rows = *result.as_ref().ok()?;
+        Some(ExportToast {
+            message: UserMessage::info(
+                "导出完成",
+                format!("已导出 {} 行到 {}", rows, self.out_path.display()),
+            ),
+            folder: self
+                .out_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// 导出完成的提示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportToast {
+    /// 提示内容
+    pub message: UserMessage,
+    /// 导出文件所在的文件夹
+    pub folder: PathBuf,
+}
+
+impl ExportToast {
+    /// 操作按钮文本
+    pub fn action_label(&self) -> &'static str {
+        OPEN_FOLDER_LABEL
+    }
+
+    /// 在文件管理器中打开所在文件夹
+    ///
+    /// # Errors
+    ///
+    /// 无法打开文件管理器时返回提示。
+    pub fn open_folder(&self, opener: &dyn UriOpener) -> Result<(), UserMessage> {
+        opener
+            .open_uri(&self.folder.to_string_lossy())
+            .map_err(|e| UserMessage::from_error(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::columns::{ColumnChooserViewModel, ColumnConfig};
+    use crate::errors::MessageKind;
+    use crate::navigation::CustomerListFilter;
+    use crate::progress::ProgressState;
+    use crate::view_models::CustomerListViewModel;
+    use async_trait::async_trait;
+    use minicrm_application::CommandBus;
+    use minicrm_core::{
+        CoreError, CustomerExportService, CustomerListRow, FilterValue, SortBy, SortDirection,
+    };
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    fn list(names: &[&str]) -> CustomerListViewModel {
+        let columns = ColumnChooserViewModel::new(ColumnConfig::customer_list(), None);
+        let rows: Vec<CustomerListRow> = names
+            .iter()
+            .map(|name| CustomerListRow {
+                id: Uuid::new_v4(),
+                cells: [("name".to_string(), name.to_string())].into(),
+            })
+            .collect();
+        CustomerListViewModel::new(&columns, &rows)
+    }
+
+    #[test]
+    fn test_scope_choices_build_requests() {
+        let mut list = list(&["华东板材", "木立方", "华南五金"]);
+        let ids: Vec<Uuid> = list.rows.iter().map(|(id, _)| *id).collect();
+        let filter = CustomerListFilter {
+            churn_risk: true,
+            sort_by: Some(SortBy::desc("last_contact")),
+            ..CustomerListFilter::default()
+        };
+
+        assert!(!ExportScopeChoice::Selection.available(0));
+        assert!(
+            list.export_request(ExportScopeChoice::Selection, &filter)
+                .is_none()
+        );
+
+        // 先点第三行再点第一行，导出仍按界面上的顺序
+        assert!(list.toggle_selected(ids[2]));
+        assert!(list.toggle_selected(ids[0]));
+        assert!(!list.toggle_selected(Uuid::new_v4()));
+        let request = list
+            .export_request(ExportScopeChoice::Selection, &filter)
+            .unwrap();
+        match request.scope {
+            ExportScope::Selection { ids: selected } => {
+                assert_eq!(selected, vec![ids[0], ids[2]]);
+            }
+            other => panic!("unexpected scope: {:?}", other),
+        }
+        assert_eq!(request.projection.columns[0], "name");
+        assert_eq!(request.headers.len(), request.projection.columns.len());
+
+        // 当前筛选结果带上保存的搜索的条件和排序
+        let request = list
+            .export_request(ExportScopeChoice::Filtered, &filter)
+            .unwrap();
+        match request.scope {
+            ExportScope::Filtered { filter } => {
+                assert!(matches!(
+                    filter.filters.get("churn_risk"),
+                    Some(FilterValue::Boolean(true))
+                ));
+                let sort = filter.sort_by.unwrap();
+                assert_eq!(sort.field, "last_contact");
+                assert_eq!(sort.direction, SortDirection::Desc);
+            }
+            other => panic!("unexpected scope: {:?}", other),
+        }
+        assert!(matches!(
+            list.export_request(ExportScopeChoice::All, &filter)
+                .unwrap()
+                .scope,
+            ExportScope::All
+        ));
+
+        list.remove_row(ids[0]);
+        assert_eq!(list.selected_ids(), vec![ids[2]]);
+    }
+
+    /// 逐行累加进度，遇到取消时停止
+    struct CountingExporter;
+
+    #[async_trait]
+    impl CustomerExportService for CountingExporter {
+        async fn export_customers(
+            &self,
+            request: &CustomerExportRequest,
+            _out_path: &Path,
+            progress: &ExportProgress,
+            cancel: &CancellationToken,
+        ) -> CoreResult<u64> {
+            let ExportScope::Selection { ids } = &request.scope else {
+                return Ok(0);
+            };
+            for _ in ids {
+                cancel.check()?;
+                progress.add(1);
+            }
+            Ok(ids.len() as u64)
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingOpener(Mutex<Vec<String>>);
+
+    impl UriOpener for RecordingOpener {
+        fn open_uri(&self, uri: &str) -> CoreResult<()> {
+            self.0.lock().unwrap().push(uri.to_string());
+            Ok(())
+        }
+    }
+
+    fn bus() -> CommandBus {
+        let mut bus = CommandBus::new();
+        bus.register::<ExportCustomersCommand>(Arc::new(
+            minicrm_application::handlers::CustomerExportHandler::new(Arc::new(CountingExporter)),
+        ));
+        bus
+    }
+
+    #[tokio::test]
+    async fn test_progress_and_completion_toast() {
+        let mut list = list(&["华东板材", "木立方", "华南五金"]);
+        let ids: Vec<Uuid> = list.rows.iter().map(|(id, _)| *id).collect();
+        for id in &ids {
+            list.toggle_selected(*id);
+        }
+        let request = list
+            .export_request(ExportScopeChoice::Selection, &CustomerListFilter::default())
+            .unwrap();
+        let command = export_customers_command(request, "/tmp/exports/customers.csv");
+
+        let bus = bus();
+        let (export, cancel) = bus.dispatch_cancellable(command.clone());
+        let mut job = CustomerExportJob::new(&command, cancel);
+        assert_eq!(job.dialog.message(), "正在导出客户… 0%（0/3）");
+        let result = export.await;
+        job.poll();
+        assert_eq!(job.dialog.processed, 3);
+
+        let toast = job.finish(&result).unwrap();
+        assert_eq!(job.dialog.state, ProgressState::Completed);
+        assert_eq!(toast.message.kind, MessageKind::Info);
+        assert_eq!(
+            toast.message.detail,
+            "已导出 3 行到 /tmp/exports/customers.csv"
+        );
+        assert_eq!(toast.action_label(), "打开所在文件夹");
+        let opener = RecordingOpener::default();
+        toast.open_folder(&opener).unwrap();
+        assert_eq!(*opener.0.lock().unwrap(), vec!["/tmp/exports".to_string()]);
+
+        // 取消后不显示完成提示
+        let (export, cancel) = bus.dispatch_cancellable(command.clone());
+        let mut job = CustomerExportJob::new(&command, cancel);
+        assert!(job.dialog.cancel());
+        let result = export.await;
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        assert!(job.finish(&result).is_none());
+        assert_eq!(job.dialog.state, ProgressState::Cancelled);
+    }
+}
@@ -0,0 +1,472 @@
+//! 列表实时刷新模块
+//!
+//! 列表和看板视图模型订阅事件总线，其他窗口（如快速新建弹窗）保存后无需手动刷新。
+//! 事件能直接确定变化时（任务换列、更换负责人、移入回收站）就地修改行；可能改变筛选
+//! 结果时（新建、更新）等事件停顿 [`REFRESH_DEBOUNCE`] 后局部重新查询，连续事件只查询
+//! 一次。
+//!
+//! 事件总线在发布者所在的线程同步分发，订阅只把事件放入收件箱并唤醒界面线程
+//! （通常用 `slint::invoke_from_event_loop`），视图模型只在界面线程上修改。
+//! 视图模型自己乐观更新过的修改用关联ID发布（[`minicrm_application::EventBus::publish_correlated`]），
+//! 收到时直接丢弃，避免重复应用。
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use minicrm_application::actions::next_correlation_id;
+use minicrm_core::{CoreResult, DomainEvent, EntityKind, EventEnvelope, EventHandler};
+
+use crate::dashboard::DashboardViewModel;
+use crate::view_models::{CustomerListViewModel, TaskBoardViewModel};
+
+/// 最后一个事件后多久重新查询
+pub const REFRESH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 记住多少个自己发出的关联ID
+const OWN_CORRELATION_LIMIT: usize = 64;
+
+/// 视图模型对一个事件的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveChange {
+    /// 已就地修改
+    Patched,
+    /// 需要重新查询
+    Refresh,
+    /// 与当前视图无关
+    Ignored,
+}
+
+/// 可订阅领域事件的视图模型
+pub trait LiveViewModel {
+    /// 关心的实体类型
+    fn interests(&self) -> &'static [EntityKind];
+
+    /// 处理一个事件（在界面线程调用）
+    fn apply_event(&mut self, event: &DomainEvent) -> LiveChange;
+}
+
+type Waker = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct Inbox {
+    interests: Vec<EntityKind>,
+    events: Vec<DomainEvent>,
+    own: VecDeque<String>,
+    waker: Option<Waker>,
+}
+
+/// 视图模型的事件订阅
+///
+/// 可克隆的共享句柄：一份订阅到事件总线（任意线程），一份留在界面线程由
+/// [`LiveRefresh::apply`] 取出事件。
+#[derive(Clone, Default)]
+pub struct LiveSubscription {
+    inbox: Arc<Mutex<Inbox>>,
+}
+
+impl fmt::Debug for LiveSubscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inbox = self.lock();
+        f.debug_struct("LiveSubscription")
+            .field("interests", &inbox.interests)
+            .field("pending", &inbox.events.len())
+            .finish()
+    }
+}
+
+impl LiveSubscription {
+    /// 订阅视图模型关心的实体类型
+    pub fn new(view_model: &impl LiveViewModel) -> Self {
+        let subscription = Self::default();
+        subscription.lock().interests = view_model.interests().to_vec();
+        subscription
+    }
+
+    /// 收到事件后调用 `waker`（在发布者线程调用，应把取出事件的工作转到界面线程）
+    pub fn with_waker(self, waker: impl Fn() + Send + Sync + 'static) -> Self {
+        self.lock().waker = Some(Arc::new(waker));
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inbox> {
+        self.inbox.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 开始一次乐观更新，返回发布事件时使用的关联ID
+    ///
+    /// 带这个关联ID的事件不再交给视图模型。
+    pub fn begin_optimistic(&self) -> String {
+        let correlation_id = next_correlation_id();
+        let mut inbox = self.lock();
+        if inbox.own.len() == OWN_CORRELATION_LIMIT {
+            inbox.own.pop_front();
+        }
+        inbox.own.push_back(correlation_id.clone());
+        correlation_id
+    }
+
+    /// 取出收件箱中的事件
+    fn take_events(&self) -> Vec<DomainEvent> {
+        std::mem::take(&mut self.lock().events)
+    }
+}
+
+impl EventHandler for LiveSubscription {
+    fn name(&self) -> &str {
+        "live_refresh"
+    }
+
+    fn handle(&self, envelope: &EventEnvelope) -> CoreResult<()> {
+        let mut inbox = self.lock();
+        if !inbox.interests.contains(&envelope.event.entity_kind()) {
+            return Ok(());
+        }
+        if let Some(correlation_id) = &envelope.correlation_id {
+            if inbox.own.contains(correlation_id) {
+                return Ok(());
+            }
+        }
+        inbox.events.push(envelope.event.clone());
+        let waker = inbox.waker.clone();
+        drop(inbox);
+        if let Some(waker) = waker {
+            waker();
+        }
+        Ok(())
+    }
+}
+
+/// 界面线程一侧的刷新状态
+///
+/// 被唤醒后调用 [`Self::apply`]，界面按 [`Self::next_due`] 设置定时器，到时
+/// [`Self::take_due_refresh`] 返回 `true` 则重新查询。
+#[derive(Debug, Clone)]
+pub struct LiveRefresh {
+    subscription: LiveSubscription,
+    refresh_due: Option<Instant>,
+}
+
+impl LiveRefresh {
+    /// 创建视图模型的刷新状态，返回的订阅需注册到事件总线
+    pub fn new(subscription: LiveSubscription) -> Self {
+        Self {
+            subscription,
+            refresh_due: None,
+        }
+    }
+
+    /// 事件订阅
+    pub fn subscription(&self) -> &LiveSubscription {
+        &self.subscription
+    }
+
+    /// 把收到的事件交给视图模型，返回就地修改的次数
+    pub fn apply(&mut self, view_model: &mut impl LiveViewModel, now: Instant) -> usize {
+        let mut patched = 0;
+        for event in self.subscription.take_events() {
+            match view_model.apply_event(&event) {
+                LiveChange::Patched => patched += 1,
+                LiveChange::Refresh => self.refresh_due = Some(now + REFRESH_DEBOUNCE),
+                LiveChange::Ignored => {}
+            }
+        }
+        patched
+    }
+
+    /// 下次重新查询的时间
+    pub fn next_due(&self) -> Option<Instant> {
+        self.refresh_due
+    }
+
+    /// 是否到了重新查询的时间（返回 `true` 后清除）
+    pub fn take_due_refresh(&mut self, now: Instant) -> bool {
+        match self.refresh_due {
+            Some(due) if due <= now => {
+                self.refresh_due = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 事件是否移除了实体（移入回收站或彻底删除）
+fn removed(event: &DomainEvent) -> bool {
+    matches!(
+        event,
+        DomainEvent::EntitySoftDeleted { .. } | DomainEvent::EntityPurged { .. }
+    )
+}
+
+impl LiveViewModel for CustomerListViewModel {
+    fn interests(&self) -> &'static [EntityKind] {
+        &[EntityKind::Customer]
+    }
+
+    fn apply_event(&mut self, event: &DomainEvent) -> LiveChange {
+        let id = event.entity_id();
+        match event {
+            _ if removed(event) => {
+                if self.remove_row(id) {
+                    LiveChange::Patched
+                } else {
+                    LiveChange::Ignored
+                }
+            }
+            // 互动只影响已在列表中的行
+            DomainEvent::InteractionLogged { .. } if !self.contains(id) => LiveChange::Ignored,
+            // 新建、更新或恢复后是否符合筛选条件只能重新查询
+            _ => LiveChange::Refresh,
+        }
+    }
+}
+
+impl LiveViewModel for TaskBoardViewModel {
+    fn interests(&self) -> &'static [EntityKind] {
+        &[EntityKind::Task]
+    }
+
+    fn apply_event(&mut self, event: &DomainEvent) -> LiveChange {
+        let id = event.entity_id();
+        let patched = match event {
+            _ if removed(event) => {
+                return if self.remove_card(id) {
+                    LiveChange::Patched
+                } else {
+                    LiveChange::Ignored
+                };
+            }
+            DomainEvent::TaskStatusChanged { to, .. } => self.move_card(id, *to),
+            DomainEvent::TaskAssigned { assignee, .. } => self.set_assignee(id, *assignee),
+            DomainEvent::TaskNoteAdded { .. } => return LiveChange::Ignored,
+            _ => false,
+        };
+        if patched {
+            LiveChange::Patched
+        } else {
+            LiveChange::Refresh
+        }
+    }
+}
+
+impl LiveViewModel for DashboardViewModel {
+    fn interests(&self) -> &'static [EntityKind] {
+        &EntityKind::ALL
+    }
+
+    /// 卡片都是汇总数据，任何变化都重新加载
+    fn apply_event(&mut self, _event: &DomainEvent) -> LiveChange {
+        LiveChange::Refresh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view_models::TaskColumn;
+    use crate::{DashboardCardRegistry, DashboardLayout};
+    use chrono::Utc;
+    use minicrm_application::EventBus;
+    use minicrm_core::{Task, TaskPriority, TaskStatus, User, UserRole};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    fn task(title: &str, status: TaskStatus) -> Task {
+        let now = Utc::now();
+        Task {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: None,
+            status,
+            priority: TaskPriority::Medium,
+            customer_id: None,
+            supplier_id: None,
+            due_date: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+            assigned_to: None,
+        }
+    }
+
+    fn user(name: &str) -> User {
+        let now = Utc::now();
+        User {
+            id: Uuid::new_v4(),
+            username: name.to_string(),
+            display_name: name.to_string(),
+            role: UserRole::Sales,
+            password_hash: String::new(),
+            active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn column(board: &TaskBoardViewModel, status: TaskStatus) -> &TaskColumn {
+        board.columns.iter().find(|c| c.status == status).unwrap()
+    }
+
+    /// 订阅看板并返回唤醒计数
+    fn subscribe(bus: &EventBus, board: &TaskBoardViewModel) -> (LiveRefresh, Arc<AtomicUsize>) {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let subscription = LiveSubscription::new(board).with_waker({
+            let wakes = wakes.clone();
+            move || {
+                wakes.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        bus.subscribe(Arc::new(subscription.clone()));
+        (LiveRefresh::new(subscription), wakes)
+    }
+
+    #[test]
+    fn test_events_from_elsewhere_patch_rows() {
+        let follow_up = task("回访华美家具", TaskStatus::Pending);
+        let zhang = user("张伟");
+        let mut board = TaskBoardViewModel::new(
+            std::slice::from_ref(&follow_up),
+            std::slice::from_ref(&zhang),
+        );
+        let bus = EventBus::new();
+        let (mut refresh, wakes) = subscribe(&bus, &board);
+
+        bus.publish(DomainEvent::TaskStatusChanged {
+            task_id: follow_up.id,
+            from: TaskStatus::Pending,
+            to: TaskStatus::InProgress,
+        });
+        bus.publish(DomainEvent::TaskAssigned {
+            task_id: follow_up.id,
+            assignee: zhang.id,
+        });
+        // 不关心的实体类型不唤醒界面
+        bus.publish(DomainEvent::EntityUpdated {
+            entity: EntityKind::Customer,
+            id: Uuid::new_v4(),
+        });
+        assert_eq!(wakes.load(Ordering::SeqCst), 2);
+        // 唤醒前视图模型不变
+        assert_eq!(column(&board, TaskStatus::Pending).cards.len(), 1);
+
+        let now = Instant::now();
+        assert_eq!(refresh.apply(&mut board, now), 2);
+        assert!(column(&board, TaskStatus::Pending).cards.is_empty());
+        let in_progress = column(&board, TaskStatus::InProgress);
+        assert_eq!(in_progress.title, "进行中 1");
+        assert_eq!(in_progress.cards[0].assignee_name.as_deref(), Some("张伟"));
+        assert_eq!(refresh.next_due(), None);
+
+        bus.publish(DomainEvent::EntitySoftDeleted {
+            entity: EntityKind::Task,
+            id: follow_up.id,
+        });
+        assert_eq!(refresh.apply(&mut board, now), 1);
+        assert!(!board.contains(follow_up.id));
+    }
+
+    #[test]
+    fn test_refresh_is_debounced_and_coalesced() {
+        let mut board = TaskBoardViewModel::new(&[], &[]);
+        let bus = EventBus::new();
+        let (mut refresh, _) = subscribe(&bus, &board);
+        let start = Instant::now();
+
+        // 快速新建的任务不在看板上，只能重新查询
+        for _ in 0..3 {
+            bus.publish(DomainEvent::EntityCreated {
+                entity: EntityKind::Task,
+                id: Uuid::new_v4(),
+            });
+        }
+        assert_eq!(refresh.apply(&mut board, start), 0);
+        let later = start + Duration::from_millis(300);
+        bus.publish(DomainEvent::TaskStatusChanged {
+            task_id: Uuid::new_v4(),
+            from: TaskStatus::Pending,
+            to: TaskStatus::Completed,
+        });
+        refresh.apply(&mut board, later);
+        assert_eq!(refresh.next_due(), Some(later + REFRESH_DEBOUNCE));
+        assert!(!refresh.take_due_refresh(start + REFRESH_DEBOUNCE));
+
+        // 四个事件只重新查询一次
+        assert!(refresh.take_due_refresh(later + REFRESH_DEBOUNCE));
+        assert!(!refresh.take_due_refresh(later + REFRESH_DEBOUNCE * 2));
+        assert_eq!(refresh.next_due(), None);
+    }
+
+    #[test]
+    fn test_own_optimistic_updates_are_suppressed() {
+        let follow_up = task("发送报价", TaskStatus::Pending);
+        let mut board = TaskBoardViewModel::new(std::slice::from_ref(&follow_up), &[]);
+        let bus = EventBus::new();
+        let (mut refresh, wakes) = subscribe(&bus, &board);
+
+        // 拖动卡片时先乐观更新，再用关联ID发布
+        let correlation_id = refresh.subscription().begin_optimistic();
+        assert!(board.move_card(follow_up.id, TaskStatus::Completed));
+        let event = DomainEvent::TaskStatusChanged {
+            task_id: follow_up.id,
+            from: TaskStatus::Pending,
+            to: TaskStatus::Completed,
+        };
+        bus.publish_correlated(event.clone(), correlation_id);
+        assert_eq!(wakes.load(Ordering::SeqCst), 0);
+        assert_eq!(refresh.apply(&mut board, Instant::now()), 0);
+        assert_eq!(column(&board, TaskStatus::Completed).cards.len(), 1);
+
+        // 其他操作的关联ID照常处理
+        bus.publish_correlated(
+            DomainEvent::EntityPurged {
+                entity: EntityKind::Task,
+                id: follow_up.id,
+                was_soft_deleted: false,
+            },
+            next_correlation_id(),
+        );
+        assert_eq!(refresh.apply(&mut board, Instant::now()), 1);
+        assert!(!board.contains(follow_up.id));
+    }
+
+    #[test]
+    fn test_customer_list_and_dashboard_changes() {
+        let kept = Uuid::new_v4();
+        let deleted = Uuid::new_v4();
+        let mut list = CustomerListViewModel {
+            headers: Vec::new(),
+            rows: vec![(kept, Vec::new()), (deleted, Vec::new())],
+            ..CustomerListViewModel::default()
+        };
+        let removal = DomainEvent::EntitySoftDeleted {
+            entity: EntityKind::Customer,
+            id: deleted,
+        };
+        assert_eq!(list.apply_event(&removal), LiveChange::Patched);
+        assert_eq!(list.apply_event(&removal), LiveChange::Ignored);
+        assert!(list.contains(kept));
+        assert!(!list.contains(deleted));
+        let interaction = |customer_id| DomainEvent::InteractionLogged {
+            interaction_id: Uuid::new_v4(),
+            customer_id,
+        };
+        assert_eq!(list.apply_event(&interaction(kept)), LiveChange::Refresh);
+        assert_eq!(list.apply_event(&interaction(deleted)), LiveChange::Ignored);
+        let created = DomainEvent::EntityCreated {
+            entity: EntityKind::Customer,
+            id: Uuid::new_v4(),
+        };
+        assert_eq!(list.apply_event(&created), LiveChange::Refresh);
+
+        let mut dashboard = DashboardViewModel::new(
+            Arc::new(DashboardCardRegistry::new()),
+            UserRole::Viewer,
+            &DashboardLayout::default(),
+        );
+        assert_eq!(dashboard.apply_event(&created), LiveChange::Refresh);
+        assert_eq!(dashboard.interests().len(), EntityKind::ALL.len());
+    }
+}
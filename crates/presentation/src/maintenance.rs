@@ -0,0 +1,1102 @@
+//! 数据库维护提示
+//!
+//! 删除大量历史数据后数据库文件不会自动变小。可释放空间超过阈值时提示一次整理；
+//! 用户忽略后记录当时的可释放空间，只有再增长一个阈值步长才再次提示。
+//!
+//! 诊断信息界面列出全文索引的一致性检查结果，索引与基础表不一致时提供重建；
+//! 同时列出邮件、Webhook等外部调用的熔断状态，以及本次运行中的慢查询（可展开查看执行计划）。
+//! 根据最近几次运行的连接池使用情况建议最大连接数，应用后重新启动生效。
+//! 数据库大小曲线显示最近的大小记录，增长超过阈值时在曲线上方显示警告。
+//! 还可以检查内置迁移的回滚SQL，列出每个迁移能否回滚及回滚会删除的数据。
+//! 数据一致性检查逐项列出业务规则的检查结果和发现的问题。
+//!
+//! 设置中的“立即归档”先显示按当前策略将移出的记录，确认后执行；归档历史显示在诊断信息中。
+//! 存储清理（孤儿附件和过期临时文件）和保留策略（审计记录、已分发事件）的运行记录同样显示在
+//! 诊断信息中。
+
+use chrono::{DateTime, Local, Utc};
+use minicrm_application::{BreakerState, OperationStatus};
+use minicrm_core::{
+    detect_size_growth, ArchiveCount, ArchivePreview, ArchiveRun, ArchiveRunStatus, ArchiveTrigger,
+    ConsistencyCheckResult, ConsistencySeverity, MigrationCheck, MigrationReversibility, PlanRow,
+    PoolSizeSuggestion, RetentionRun, ReversibilityReport, SearchIndexProgress,
+    SearchIndexStatus, SizeGrowthThresholds, SizeSample, SlowQuery, StorageGcRun,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::formatting::{format_date, DateStyle};
+
+const MB: u64 = 1024 * 1024;
+
+/// 整理提示阈值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReclaimThreshold {
+    /// 可释放空间达到该字节数时提示（同时作为再次提示的增长步长）
+    pub min_bytes: u64,
+    /// 可释放空间占文件大小的比例达到该值时提示
+    pub min_ratio: f64,
+}
+
+impl Default for ReclaimThreshold {
+    fn default() -> Self {
+        Self {
+            min_bytes: 50 * MB,
+            min_ratio: 0.2,
+        }
+    }
+}
+
+impl ReclaimThreshold {
+    /// 按比例提示时可释放空间的下限，避免很小的数据库也被提示
+    const RATIO_FLOOR_BYTES: u64 = 5 * MB;
+
+    /// 可释放空间是否达到提示阈值
+    pub fn exceeded(&self, reclaimable: u64, file_bytes: u64) -> bool {
+        reclaimable >= self.min_bytes
+            || (reclaimable >= Self::RATIO_FLOOR_BYTES
+                && file_bytes > 0
+                && reclaimable as f64 >= file_bytes as f64 * self.min_ratio)
+    }
+}
+
+/// 整理提示状态（保存在界面状态中）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReclaimPromptState {
+    /// 上次忽略提示时的可释放空间（字节）
+    #[serde(default)]
+    pub dismissed_at_bytes: Option<u64>,
+}
+
+impl ReclaimPromptState {
+    /// 根据当前可释放空间判断是否提示，返回提示文本
+    ///
+    /// 可释放空间回落到阈值以下（已整理或空间被重新使用）时清除忽略记录。
+    pub fn evaluate(
+        &mut self,
+        threshold: &ReclaimThreshold,
+        reclaimable: u64,
+        file_bytes: u64,
+    ) -> Option<String> {
+        if !threshold.exceeded(reclaimable, file_bytes) {
+            self.dismissed_at_bytes = None;
+            return None;
+        }
+        match self.dismissed_at_bytes {
+            Some(dismissed) if reclaimable < dismissed.saturating_add(threshold.min_bytes) => None,
+            _ => Some(reclaim_message(reclaimable)),
+        }
+    }
+
+    /// 用户忽略提示
+    pub fn dismiss(&mut self, reclaimable: u64) {
+        self.dismissed_at_bytes = Some(reclaimable);
+    }
+}
+
+/// 整理提示文本
+pub fn reclaim_message(reclaimable: u64) -> String {
+    format!(
+        "可释放约 {} MB，是否立即整理？",
+        (reclaimable + MB / 2) / MB
+    )
+}
+
+/// 重建全文索引的按钮文本
+pub const REBUILD_SEARCH_INDEX_LABEL: &str = "重建搜索索引";
+
+/// 诊断信息中的一行全文索引状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchIndexRow {
+    /// 基础表名（重建时传回）
+    pub table: String,
+    /// 显示名称
+    pub label: String,
+    /// 状态文本
+    pub status: String,
+    /// 是否一致
+    pub healthy: bool,
+    /// 是否显示重建按钮
+    pub offer_rebuild: bool,
+}
+
+impl SearchIndexRow {
+    /// 根据一致性检查结果生成
+    pub fn from_status(status: &SearchIndexStatus) -> Self {
+        let healthy = status.is_consistent();
+        let text = if healthy {
+            format!("正常（{} 条记录）", status.base_rows)
+        } else if status.indexed_rows < status.base_rows {
+            format!(
+                "索引缺少 {} 条记录，搜索可能漏掉结果",
+                status.base_rows - status.indexed_rows
+            )
+        } else if status.indexed_rows > status.base_rows {
+            format!(
+                "索引多出 {} 条已删除的记录",
+                status.indexed_rows - status.base_rows
+            )
+        } else {
+            format!("抽样中 {} 条记录的索引内容已过期", status.mismatched)
+        };
+        Self {
+            table: status.table.clone(),
+            label: search_index_label(&status.table).to_string(),
+            status: text,
+            healthy,
+            offer_rebuild: !healthy,
+        }
+    }
+}
+
+fn search_index_label(table: &str) -> &str {
+    match table {
+        "customers" => "客户搜索",
+        "knowledge_articles" => "知识库搜索",
+        other => other,
+    }
+}
+
+/// 重建进度文本
+pub fn rebuild_progress_message(progress: &SearchIndexProgress) -> String {
+    format!(
+        "正在重建搜索索引… {}%（{}/{}）",
+        progress.percent(),
+        progress.rebuilt,
+        progress.total
+    )
+}
+
+/// 诊断信息中的一行外部调用状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalServiceRow {
+    /// 显示名称
+    pub label: String,
+    /// 状态文本
+    pub status: String,
+    /// 调用统计文本
+    pub counters: String,
+    /// 是否正常
+    pub healthy: bool,
+}
+
+impl ExternalServiceRow {
+    /// 根据外部调用状态生成，`now` 用于计算剩余冷却时间
+    pub fn from_status(status: &OperationStatus, now: DateTime<Utc>) -> Self {
+        let text = match status.state {
+            BreakerState::Closed if status.consecutive_failures > 0 => {
+                format!("正常（最近连续失败 {} 次）", status.consecutive_failures)
+            }
+            BreakerState::Closed => "正常".to_string(),
+            BreakerState::Open => {
+                let until = status.open_until.unwrap_or(now);
+                format!(
+                    "已暂停，{} 后恢复尝试",
+                    until.with_timezone(&Local).format("%H:%M:%S")
+                )
+            }
+            BreakerState::HalfOpen => "等待试探调用".to_string(),
+        };
+        Self {
+            label: external_service_label(&status.operation),
+            status: text,
+            counters: format!(
+                "调用 {} 次，失败 {} 次，重试 {} 次，超时 {} 次",
+                status.calls, status.failures, status.retries, status.timeouts
+            ),
+            healthy: status.state == BreakerState::Closed,
+        }
+    }
+}
+
+fn external_service_label(operation: &str) -> String {
+    if operation == "smtp" {
+        return "邮件发送".to_string();
+    }
+    operation
+        .strip_prefix("webhook:")
+        .map_or_else(|| operation.to_string(), |host| format!("Webhook（{}）", host))
+}
+
+/// 诊断信息中的一行慢查询
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowQueryRow {
+    /// SQL语句
+    pub sql: String,
+    /// 耗时文本
+    pub duration: String,
+    /// 执行计划（按层级缩进，每步一行）
+    pub plan: Vec<String>,
+    /// 是否有全表扫描（界面标红）
+    pub has_full_scan: bool,
+    /// 是否展开执行计划
+    pub expanded: bool,
+}
+
+impl SlowQueryRow {
+    /// 根据慢查询记录生成（默认折叠）
+    pub fn from_query(query: &SlowQuery) -> Self {
+        Self {
+            sql: query.sql.clone(),
+            duration: format!("{} ms", query.duration_ms),
+            plan: plan_lines(&query.plan),
+            has_full_scan: query
+                .plan
+                .iter()
+                .any(|row| row.full_scan_table().is_some()),
+            expanded: false,
+        }
+    }
+
+    /// 展开或折叠执行计划
+    pub fn toggle(&mut self) {
+        self.expanded = !self.expanded;
+    }
+}
+
+/// 执行计划按父子关系缩进
+fn plan_lines(plan: &[PlanRow]) -> Vec<String> {
+    if plan.is_empty() {
+        return vec!["（未能获取执行计划）".to_string()];
+    }
+    let mut depths: Vec<(i64, usize)> = Vec::with_capacity(plan.len());
+    plan.iter()
+        .map(|row| {
+            let depth = depths
+                .iter()
+                .find(|(id, _)| *id == row.parent)
+                .map_or(0, |(_, depth)| depth + 1);
+            depths.push((row.id, depth));
+            format!("{}{}", "  ".repeat(depth), row.detail)
+        })
+        .collect()
+}
+
+/// 应用连接数建议的按钮文本
+pub const APPLY_POOL_SIZE_LABEL: &str = "应用建议";
+
+/// 诊断信息中的连接池大小建议
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolSizeAdvice {
+    /// 建议说明
+    pub message: String,
+    /// 建议的最大连接数
+    pub suggested: u32,
+    /// 是否可以应用（建议值与当前配置不同且尚未应用）
+    pub can_apply: bool,
+    /// 已应用，需要重新启动
+    pub restart_required: bool,
+}
+
+impl PoolSizeAdvice {
+    /// 根据建议生成，没有使用记录时说明尚无数据
+    pub fn from_suggestion(suggestion: Option<&PoolSizeSuggestion>) -> Self {
+        let Some(suggestion) = suggestion else {
+            return Self {
+                message: "尚无连接池使用记录，退出应用后会保存本次运行的情况".to_string(),
+                suggested: 0,
+                can_apply: false,
+                restart_required: false,
+            };
+        };
+        let observed = if suggestion.saturated {
+            format!(
+                "最近 {} 次运行中连接曾全部被占用，调用方需要等待",
+                suggestion.sessions
+            )
+        } else {
+            format!(
+                "最近 {} 次运行中最多同时使用 {} 个连接",
+                suggestion.sessions, suggestion.observed_peak
+            )
+        };
+        let message = if suggestion.changes() {
+            format!(
+                "{}，建议把最大连接数从 {} 调整为 {}",
+                observed, suggestion.current, suggestion.suggested
+            )
+        } else {
+            format!("{}，当前最大连接数 {} 合适", observed, suggestion.current)
+        };
+        Self {
+            message,
+            suggested: suggestion.suggested,
+            can_apply: suggestion.changes(),
+            restart_required: false,
+        }
+    }
+
+    /// 建议已写入配置
+    pub fn applied(&mut self) {
+        self.can_apply = false;
+        self.restart_required = true;
+        self.message = format!("最大连接数已设为 {}，重新启动后生效", self.suggested);
+    }
+}
+
+/// 数据库大小曲线最多显示的点数，记录更多时每天只取最后一次
+pub const SIZE_CHART_MAX_POINTS: usize = 120;
+
+/// 数据库大小曲线上的一个点
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeChartPoint {
+    /// 横轴文本，如“06-01 08:00”
+    pub label: String,
+    /// 数据库文件和WAL的总大小（MB）
+    pub total_mb: f64,
+    /// 其中WAL文件的大小（MB）
+    pub wal_mb: f64,
+}
+
+/// 诊断信息中的数据库大小曲线
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeHistoryChart {
+    /// 图表标题
+    pub title: String,
+    /// 按时间先后排列的点
+    pub points: Vec<SizeChartPoint>,
+    /// 纵轴上限（MB）
+    pub max_mb: f64,
+    /// 增长超过阈值时的警告
+    pub warning: Option<String>,
+}
+
+impl SizeHistoryChart {
+    /// 根据大小记录生成，`thresholds` 为配置的增长警告阈值
+    pub fn from_history(history: &[SizeSample], thresholds: &SizeGrowthThresholds) -> Self {
+        let mut samples: Vec<&SizeSample> = history.iter().collect();
+        samples.sort_by_key(|s| s.taken_at);
+        if samples.len() > SIZE_CHART_MAX_POINTS {
+            let mut daily: Vec<&SizeSample> = Vec::new();
+            for sample in samples {
+                let day = sample.taken_at.with_timezone(&Local).date_naive();
+                match daily.last_mut() {
+                    Some(last) if last.taken_at.with_timezone(&Local).date_naive() == day => {
+                        *last = sample;
+                    }
+                    _ => daily.push(sample),
+                }
+            }
+            let skip = daily.len().saturating_sub(SIZE_CHART_MAX_POINTS);
+            samples = daily.split_off(skip);
+        }
+
+        let points: Vec<SizeChartPoint> = samples
+            .iter()
+            .map(|sample| SizeChartPoint {
+                label: sample
+                    .taken_at
+                    .with_timezone(&Local)
+                    .format("%m-%d %H:%M")
+                    .to_string(),
+                total_mb: sample.total_bytes() as f64 / MB as f64,
+                wal_mb: sample.wal_bytes as f64 / MB as f64,
+            })
+            .collect();
+        let max_mb = points.iter().map(|p| p.total_mb).fold(0.0, f64::max);
+        Self {
+            title: "数据库大小（MB）".to_string(),
+            points,
+            max_mb,
+            warning: detect_size_growth(history, thresholds).map(|growth| growth.message()),
+        }
+    }
+
+    /// 没有记录时的提示，有记录时为空
+    pub fn empty_text(&self) -> Option<&'static str> {
+        self.points
+            .is_empty()
+            .then_some("尚无数据库大小记录，健康监控运行后会定时记录")
+    }
+}
+
+/// 检查迁移回滚的按钮文本
+pub const VERIFY_MIGRATIONS_LABEL: &str = "检查迁移回滚";
+
+/// 迁移回滚检查中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationCheckRow {
+    /// 版本文本，如“v12”
+    pub version: String,
+    /// 迁移名称
+    pub name: String,
+    /// 结果文本
+    pub status: String,
+    /// 结果类别：ok / warning / error（界面按类别着色）
+    pub kind: &'static str,
+    /// 说明（有误的原因或回滚会删除的数据）
+    pub detail: String,
+}
+
+impl MigrationCheckRow {
+    /// 根据单个迁移的检查结果生成
+    pub fn from_check(check: &MigrationCheck) -> Self {
+        let (status, kind, detail) = match &check.result {
+            MigrationReversibility::Reversible if check.destructive() => (
+                "可回滚（会删除数据）",
+                "warning",
+                format!("回滚将删除 {}", check.drops.join("、")),
+            ),
+            MigrationReversibility::Reversible => ("可回滚", "ok", String::new()),
+            MigrationReversibility::Irreversible => {
+                ("不可回滚", "warning", "没有回滚SQL".to_string())
+            }
+            MigrationReversibility::Broken(reason) => ("回滚有误", "error", reason.clone()),
+        };
+        Self {
+            version: format!("v{}", check.version),
+            name: check.name.clone(),
+            status: status.to_string(),
+            kind,
+            detail,
+        }
+    }
+
+    /// 单行文本（命令行输出）
+    pub fn to_text(&self) -> String {
+        if self.detail.is_empty() {
+            format!("{} {}: {}", self.version, self.name, self.status)
+        } else {
+            format!("{} {}: {}（{}）", self.version, self.name, self.status, self.detail)
+        }
+    }
+}
+
+/// 迁移回滚检查的汇总文本
+pub fn migration_report_summary(report: &ReversibilityReport) -> String {
+    let count = |f: fn(&MigrationCheck) -> bool| report.checks.iter().filter(|c| f(c)).count();
+    let reversible = count(|c| c.result == MigrationReversibility::Reversible);
+    let destructive =
+        count(|c| c.result == MigrationReversibility::Reversible && c.destructive());
+    let irreversible = count(|c| c.result == MigrationReversibility::Irreversible);
+    let broken = report.broken().count();
+    format!(
+        "共 {} 个迁移：可回滚 {} 个（其中 {} 个会删除数据），不可回滚 {} 个，回滚有误 {} 个",
+        report.checks.len(),
+        reversible,
+        destructive,
+        irreversible,
+        broken
+    )
+}
+
+/// 执行一致性检查的按钮文本
+pub const CHECK_CONSISTENCY_LABEL: &str = "检查数据一致性";
+
+/// 一致性检查结果中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyCheckRow {
+    /// 检查名称
+    pub name: String,
+    /// 检查内容说明
+    pub description: String,
+    /// 结果文本，如“通过”“3 个错误”
+    pub status: String,
+    /// 结果类别：ok / warning / error（界面按类别着色）
+    pub kind: &'static str,
+    /// 发现的问题（超出上限时末尾注明）
+    pub details: Vec<String>,
+}
+
+impl ConsistencyCheckRow {
+    /// 根据单项检查结果生成
+    pub fn from_result(result: &ConsistencyCheckResult) -> Self {
+        let (label, kind) = match result.severity {
+            ConsistencySeverity::Warning => ("警告", "warning"),
+            ConsistencySeverity::Error => ("错误", "error"),
+        };
+        let more = if result.truncated { "+" } else { "" };
+        let (status, kind) = if result.passed() {
+            ("通过".to_string(), "ok")
+        } else {
+            (format!("{}{} 个{}", result.violations.len(), more, label), kind)
+        };
+        let mut details: Vec<String> =
+            result.violations.iter().map(|v| v.description.clone()).collect();
+        if result.truncated {
+            details.push("（仅列出前若干项）".to_string());
+        }
+        Self {
+            name: result.name.clone(),
+            description: result.description.clone(),
+            status,
+            kind,
+            details,
+        }
+    }
+
+    /// 多行文本（命令行输出），问题逐行缩进列出
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}（{}）: {}", self.name, self.description, self.status);
+        for detail in &self.details {
+            text.push_str("\n  - ");
+            text.push_str(detail);
+        }
+        text
+    }
+}
+
+/// 立即归档的按钮文本
+pub const ARCHIVE_NOW_LABEL: &str = "立即归档";
+
+/// 大小文本（1 MB 以下按 KB 显示）
+fn size_text(bytes: u64) -> String {
+    if bytes < MB {
+        format!("{} KB", bytes.div_ceil(1024))
+    } else {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    }
+}
+
+/// 归档预览中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivePreviewRow {
+    /// 记录类型
+    pub label: String,
+    /// 截止日期文本
+    pub cutoff: String,
+    /// 记录数
+    pub rows: u64,
+    /// 估算大小文本
+    pub size: String,
+}
+
+impl ArchivePreviewRow {
+    /// 根据单个类型的预览生成
+    pub fn from_count(count: &ArchiveCount) -> Self {
+        Self {
+            label: count.entity.label().to_string(),
+            cutoff: format!(
+                "{}之前",
+                format_date(&count.cutoff.with_timezone(&Local), DateStyle::Long)
+            ),
+            rows: count.rows,
+            size: size_text(count.estimated_bytes),
+        }
+    }
+}
+
+/// 立即归档的确认对话框
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveConfirmation {
+    /// 各类型将移出的记录
+    pub rows: Vec<ArchivePreviewRow>,
+    /// 提示文本
+    pub message: String,
+    /// 是否可以确认（没有可归档的记录时只显示关闭）
+    pub can_confirm: bool,
+}
+
+impl ArchiveConfirmation {
+    /// 根据归档预览生成
+    pub fn from_preview(preview: &ArchivePreview) -> Self {
+        let total = preview.total_rows();
+        let message = if total == 0 {
+            "按当前策略没有需要归档的记录".to_string()
+        } else {
+            format!(
+                "将归档 {} 条记录（约 {}），归档后这些记录不再出现在列表和统计中。确定要立即归档吗？",
+                total,
+                size_text(preview.total_bytes())
+            )
+        };
+        Self {
+            rows: preview.counts.iter().map(ArchivePreviewRow::from_count).collect(),
+            message,
+            can_confirm: total > 0,
+        }
+    }
+}
+
+/// 诊断信息中的一行存储清理记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageGcRow {
+    /// 运行时间文本
+    pub ran_at: String,
+    /// 方式（预览、清理）
+    pub mode: String,
+    /// 详情，如“孤儿附件 3 个，临时文件 2 个，释放 120 KB”
+    pub detail: String,
+}
+
+impl StorageGcRow {
+    /// 根据运行记录生成
+    pub fn from_run(run: &StorageGcRun) -> Self {
+        let detail = if run.orphan_blobs == 0 && run.temp_files == 0 {
+            "没有需要清理的文件".to_string()
+        } else {
+            format!(
+                "孤儿附件 {} 个，临时文件 {} 个，{} {}",
+                run.orphan_blobs,
+                run.temp_files,
+                if run.dry_run { "可释放" } else { "释放" },
+                size_text(run.bytes)
+            )
+        };
+        Self {
+            ran_at: run
+                .ran_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            mode: if run.dry_run { "预览" } else { "清理" }.to_string(),
+            detail,
+        }
+    }
+}
+
+/// 诊断信息中的一行保留策略执行记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionRunRow {
+    /// 运行时间文本
+    pub ran_at: String,
+    /// 详情，如“清理审计记录 1200 行，已分发事件 3500 行”
+    pub detail: String,
+}
+
+impl RetentionRunRow {
+    /// 根据运行记录生成
+    pub fn from_run(run: &RetentionRun) -> Self {
+        let detail = if run.audit_rows == 0 && run.outbox_rows == 0 {
+            "没有超过保留期的记录".to_string()
+        } else {
+            format!(
+                "清理审计记录 {} 行，已分发事件 {} 行",
+                run.audit_rows, run.outbox_rows
+            )
+        };
+        Self {
+            ran_at: run
+                .ran_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            detail,
+        }
+    }
+}
+
+/// 诊断信息中的一行归档历史
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveRunRow {
+    /// 开始时间文本
+    pub started_at: String,
+    /// 触发方式
+    pub trigger: String,
+    /// 结果
+    pub status: String,
+    /// 详情（移出的记录或跳过原因）
+    pub detail: String,
+}
+
+impl ArchiveRunRow {
+    /// 根据运行记录生成
+    pub fn from_run(run: &ArchiveRun) -> Self {
+        let detail = match run.status {
+            ArchiveRunStatus::Completed if run.counts.iter().all(|c| c.rows == 0) => {
+                "没有需要归档的记录".to_string()
+            }
+            ArchiveRunStatus::Completed => run
+                .counts
+                .iter()
+                .filter(|c| c.rows > 0)
+                .map(|c| format!("{} {} 条", c.entity.label(), c.rows))
+                .collect::<Vec<_>>()
+                .join("，"),
+            ArchiveRunStatus::Skipped | ArchiveRunStatus::Failed => {
+                run.message.clone().unwrap_or_default()
+            }
+        };
+        Self {
+            started_at: run
+                .started_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            trigger: match run.trigger {
+                ArchiveTrigger::Manual => "手动",
+                ArchiveTrigger::Scheduled => "定时",
+            }
+            .to_string(),
+            status: match run.status {
+                ArchiveRunStatus::Completed => "已完成",
+                ArchiveRunStatus::Skipped => "已跳过",
+                ArchiveRunStatus::Failed => "失败",
+            }
+            .to_string(),
+            detail,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_threshold() {
+        let threshold = ReclaimThreshold::default();
+        assert!(threshold.exceeded(50 * MB, 800 * MB));
+        assert!(!threshold.exceeded(49 * MB, 800 * MB));
+        // 按比例：30 MB / 100 MB
+        assert!(threshold.exceeded(30 * MB, 100 * MB));
+        // 比例达到但空间太小
+        assert!(!threshold.exceeded(4 * MB, 10 * MB));
+    }
+
+    #[test]
+    fn test_dismissal_suppresses_until_next_step() {
+        let threshold = ReclaimThreshold::default();
+        let mut state = ReclaimPromptState::default();
+        assert_eq!(
+            state.evaluate(&threshold, 120 * MB, 800 * MB).as_deref(),
+            Some("可释放约 120 MB，是否立即整理？")
+        );
+
+        state.dismiss(120 * MB);
+        assert_eq!(state.evaluate(&threshold, 120 * MB, 800 * MB), None);
+        assert_eq!(state.evaluate(&threshold, 169 * MB, 800 * MB), None);
+        assert!(state.evaluate(&threshold, 170 * MB, 800 * MB).is_some());
+
+        // 整理后回落到阈值以下，忽略记录清除
+        state.dismiss(170 * MB);
+        assert_eq!(state.evaluate(&threshold, MB, 700 * MB), None);
+        assert_eq!(state.dismissed_at_bytes, None);
+        assert!(state.evaluate(&threshold, 60 * MB, 700 * MB).is_some());
+    }
+
+    #[test]
+    fn test_search_index_rows() {
+        let mut status = SearchIndexStatus {
+            table: "customers".to_string(),
+            base_rows: 1201,
+            indexed_rows: 1201,
+            sampled: 200,
+            mismatched: 0,
+        };
+        let row = SearchIndexRow::from_status(&status);
+        assert_eq!(row.label, "客户搜索");
+        assert_eq!(row.status, "正常（1201 条记录）");
+        assert!(!row.offer_rebuild);
+
+        status.indexed_rows = 1198;
+        let row = SearchIndexRow::from_status(&status);
+        assert_eq!(row.status, "索引缺少 3 条记录，搜索可能漏掉结果");
+        assert!(row.offer_rebuild);
+
+        let progress = SearchIndexProgress {
+            rebuilt: 500,
+            total: 1201,
+        };
+        assert_eq!(
+            rebuild_progress_message(&progress),
+            "正在重建搜索索引… 41%（500/1201）"
+        );
+    }
+
+    #[test]
+    fn test_external_service_rows() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap();
+        let mut status = OperationStatus {
+            operation: "webhook:hooks.example.com".to_string(),
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            open_until: None,
+            calls: 12,
+            failures: 2,
+            retries: 1,
+            timeouts: 1,
+            rejected: 0,
+            last_error: None,
+        };
+        let row = ExternalServiceRow::from_status(&status, now);
+        assert_eq!(row.label, "Webhook（hooks.example.com）");
+        assert_eq!(row.status, "正常");
+        assert_eq!(row.counters, "调用 12 次，失败 2 次，重试 1 次，超时 1 次");
+        assert!(row.healthy);
+
+        status.state = BreakerState::Open;
+        status.open_until = Some(now + chrono::Duration::minutes(5));
+        let row = ExternalServiceRow::from_status(&status, now);
+        assert!(row.status.starts_with("已暂停"));
+        assert!(!row.healthy);
+
+        status.operation = "smtp".to_string();
+        status.state = BreakerState::HalfOpen;
+        let row = ExternalServiceRow::from_status(&status, now);
+        assert_eq!(row.label, "邮件发送");
+        assert_eq!(row.status, "等待试探调用");
+    }
+
+    #[test]
+    fn test_slow_query_row_plan() {
+        let step = |id, parent, detail: &str| PlanRow {
+            id,
+            parent,
+            detail: detail.to_string(),
+        };
+        let query = SlowQuery {
+            sql: "SELECT * FROM customers WHERE id IN (SELECT customer_id FROM tasks)".to_string(),
+            duration_ms: 850,
+            plan: vec![
+                step(2, 0, "SEARCH customers USING INTEGER PRIMARY KEY (rowid=?)"),
+                step(5, 0, "LIST SUBQUERY 1"),
+                step(7, 5, "SCAN tasks"),
+            ],
+        };
+        let mut row = SlowQueryRow::from_query(&query);
+        assert_eq!(row.duration, "850 ms");
+        assert_eq!(row.plan[2], "  SCAN tasks");
+        assert!(row.has_full_scan);
+        assert!(!row.expanded);
+        row.toggle();
+        assert!(row.expanded);
+
+        let empty = SlowQueryRow::from_query(&SlowQuery {
+            plan: Vec::new(),
+            ..query
+        });
+        assert_eq!(empty.plan, vec!["（未能获取执行计划）"]);
+        assert!(!empty.has_full_scan);
+    }
+
+    #[test]
+    fn test_pool_size_advice() {
+        let empty = PoolSizeAdvice::from_suggestion(None);
+        assert!(!empty.can_apply);
+
+        let steady = PoolSizeSuggestion {
+            current: 10,
+            suggested: 4,
+            observed_peak: 2,
+            sessions: 5,
+            saturated: false,
+        };
+        let mut advice = PoolSizeAdvice::from_suggestion(Some(&steady));
+        assert_eq!(
+            advice.message,
+            "最近 5 次运行中最多同时使用 2 个连接，建议把最大连接数从 10 调整为 4"
+        );
+        assert!(advice.can_apply);
+        advice.applied();
+        assert!(!advice.can_apply);
+        assert!(advice.restart_required);
+        assert_eq!(advice.message, "最大连接数已设为 4，重新启动后生效");
+
+        let saturated = PoolSizeSuggestion {
+            current: 13,
+            suggested: 13,
+            observed_peak: 10,
+            sessions: 6,
+            saturated: true,
+        };
+        let advice = PoolSizeAdvice::from_suggestion(Some(&saturated));
+        assert!(!advice.can_apply);
+        assert_eq!(
+            advice.message,
+            "最近 6 次运行中连接曾全部被占用，调用方需要等待，当前最大连接数 13 合适"
+        );
+    }
+
+    #[test]
+    fn test_migration_check_rows() {
+        let check = |version, result, drops: &[&str]| MigrationCheck {
+            version,
+            name: format!("step_{}", version),
+            result,
+            drops: drops.iter().map(ToString::to_string).collect(),
+        };
+        let report = ReversibilityReport {
+            checks: vec![
+                check(1, MigrationReversibility::Irreversible, &[]),
+                check(2, MigrationReversibility::Reversible, &["customers.phone", "notes"]),
+                check(3, MigrationReversibility::Reversible, &[]),
+                check(
+                    4,
+                    MigrationReversibility::Broken("回滚后结构与迁移前不同: index idx".into()),
+                    &[],
+                ),
+            ],
+        };
+        let rows: Vec<_> = report.checks.iter().map(MigrationCheckRow::from_check).collect();
+        assert_eq!(rows[0].to_text(), "v1 step_1: 不可回滚（没有回滚SQL）");
+        assert_eq!(rows[1].kind, "warning");
+        assert_eq!(rows[1].detail, "回滚将删除 customers.phone、notes");
+        assert_eq!(rows[2].to_text(), "v3 step_3: 可回滚");
+        assert_eq!(rows[3].kind, "error");
+        assert_eq!(
+            migration_report_summary(&report),
+            "共 4 个迁移：可回滚 2 个（其中 1 个会删除数据），不可回滚 1 个，回滚有误 1 个"
+        );
+    }
+
+    #[test]
+    fn test_consistency_check_rows() {
+        use minicrm_core::ConsistencyViolation;
+
+        let result = |severity, found: usize, truncated| ConsistencyCheckResult {
+            name: "payments_within_total".to_string(),
+            description: "订单收款合计不超过订单总额".to_string(),
+            severity,
+            violations: (0..found)
+                .map(|i| ConsistencyViolation {
+                    ids: Vec::new(),
+                    description: format!("订单 SO-{} 已收款超过订单总额", i),
+                })
+                .collect(),
+            truncated,
+        };
+
+        let row = ConsistencyCheckRow::from_result(&result(ConsistencySeverity::Error, 0, false));
+        assert_eq!((row.status.as_str(), row.kind), ("通过", "ok"));
+        assert_eq!(
+            row.to_text(),
+            "payments_within_total（订单收款合计不超过订单总额）: 通过"
+        );
+
+        let row = ConsistencyCheckRow::from_result(&result(ConsistencySeverity::Error, 2, true));
+        assert_eq!((row.status.as_str(), row.kind), ("2+ 个错误", "error"));
+        assert_eq!(row.details.len(), 3);
+        assert!(row.to_text().ends_with("\n  - （仅列出前若干项）"));
+
+        let row =
+            ConsistencyCheckRow::from_result(&result(ConsistencySeverity::Warning, 1, false));
+        assert_eq!((row.status.as_str(), row.kind), ("1 个警告", "warning"));
+    }
+
+    #[test]
+    fn test_archive_confirmation_and_history() {
+        use minicrm_core::ArchiveEntity;
+
+        let as_of = Utc.with_ymd_and_hms(2024, 6, 1, 4, 0, 0).unwrap();
+        let counts = vec![
+            ArchiveCount {
+                entity: ArchiveEntity::Interactions,
+                cutoff: Utc.with_ymd_and_hms(2021, 6, 1, 4, 0, 0).unwrap(),
+                rows: 1200,
+                estimated_bytes: 3 * MB,
+            },
+            ArchiveCount {
+                entity: ArchiveEntity::Tasks,
+                cutoff: Utc.with_ymd_and_hms(2022, 6, 1, 4, 0, 0).unwrap(),
+                rows: 0,
+                estimated_bytes: 0,
+            },
+        ];
+        let confirmation = ArchiveConfirmation::from_preview(&ArchivePreview {
+            as_of,
+            counts: counts.clone(),
+        });
+        assert!(confirmation.can_confirm);
+        assert_eq!(
+            confirmation.message,
+            "将归档 1200 条记录（约 3.0 MB），归档后这些记录不再出现在列表和统计中。确定要立即归档吗？"
+        );
+        assert_eq!(confirmation.rows[0].label, "互动记录");
+        assert_eq!(confirmation.rows[0].cutoff, "2021年6月1日之前");
+        assert_eq!(confirmation.rows[1].size, "0 KB");
+
+        let empty = ArchiveConfirmation::from_preview(&ArchivePreview {
+            as_of,
+            counts: Vec::new(),
+        });
+        assert!(!empty.can_confirm);
+
+        let mut run = ArchiveRun {
+            started_at: as_of,
+            finished_at: as_of,
+            trigger: ArchiveTrigger::Scheduled,
+            status: ArchiveRunStatus::Completed,
+            counts,
+            message: None,
+        };
+        let row = ArchiveRunRow::from_run(&run);
+        assert_eq!(row.trigger, "定时");
+        assert_eq!(row.detail, "互动记录 1200 条");
+
+        run.status = ArchiveRunStatus::Skipped;
+        run.counts = Vec::new();
+        run.message = Some("数据库备份正在进行，请稍后再试".to_string());
+        let row = ArchiveRunRow::from_run(&run);
+        assert_eq!(row.status, "已跳过");
+        assert_eq!(row.detail, "数据库备份正在进行，请稍后再试");
+    }
+
+    #[test]
+    fn test_storage_gc_rows() {
+        let mut run = StorageGcRun {
+            ran_at: Utc.with_ymd_and_hms(2024, 6, 1, 4, 0, 0).unwrap(),
+            dry_run: true,
+            orphan_blobs: 3,
+            temp_files: 2,
+            bytes: 120 * 1024,
+        };
+        let row = StorageGcRow::from_run(&run);
+        assert_eq!(row.mode, "预览");
+        assert_eq!(row.detail, "孤儿附件 3 个，临时文件 2 个，可释放 120 KB");
+
+        run.dry_run = false;
+        assert_eq!(
+            StorageGcRow::from_run(&run).detail,
+            "孤儿附件 3 个，临时文件 2 个，释放 120 KB"
+        );
+        run.orphan_blobs = 0;
+        run.temp_files = 0;
+        assert_eq!(StorageGcRow::from_run(&run).detail, "没有需要清理的文件");
+    }
+
+    #[test]
+    fn test_retention_rows() {
+        let mut run = RetentionRun {
+            ran_at: Utc.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap(),
+            audit_rows: 1200,
+            outbox_rows: 3500,
+        };
+        assert_eq!(
+            RetentionRunRow::from_run(&run).detail,
+            "清理审计记录 1200 行，已分发事件 3500 行"
+        );
+        run.audit_rows = 0;
+        run.outbox_rows = 0;
+        assert_eq!(RetentionRunRow::from_run(&run).detail, "没有超过保留期的记录");
+    }
+
+    #[test]
+    fn test_size_history_chart() {
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let sample = |hours: i64, db_mb: u64| SizeSample {
+            taken_at: start + chrono::Duration::hours(hours),
+            db_bytes: db_mb * MB,
+            wal_bytes: MB,
+            table_rows: [("interactions".to_string(), db_mb * 100)].into(),
+        };
+        let thresholds = SizeGrowthThresholds::default();
+
+        let empty = SizeHistoryChart::from_history(&[], &thresholds);
+        assert!(empty.empty_text().is_some());
+        assert_eq!(empty.warning, None);
+
+        // 每小时一条，平稳增长
+        let steady: Vec<_> = (0..48).map(|h| sample(h, 100 + h as u64)).collect();
+        let chart = SizeHistoryChart::from_history(&steady, &thresholds);
+        assert_eq!(chart.points.len(), 48);
+        assert_eq!(chart.max_mb, 148.0);
+        assert_eq!(chart.points[0].wal_mb, 1.0);
+        assert_eq!(chart.warning, None);
+        assert_eq!(chart.empty_text(), None);
+
+        // 90天的每小时记录按天合并，只保留最后几天
+        let long: Vec<_> = (0..90 * 24).map(|h| sample(h, 100)).collect();
+        let chart = SizeHistoryChart::from_history(&long, &thresholds);
+        assert!(chart.points.len() <= SIZE_CHART_MAX_POINTS);
+        assert!(chart.points.len() >= 89);
+
+        // 最后半天增长到 600 MB，显示警告和增长最快的表
+        let mut burst = steady.clone();
+        burst.push(sample(60, 600));
+        let chart = SizeHistoryChart::from_history(&burst, &thresholds);
+        let warning = chart.warning.unwrap();
+        assert!(warning.starts_with("数据库最近24小时"));
+        assert!(warning.contains("interactions +"));
+    }
+}
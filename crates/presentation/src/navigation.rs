@@ -0,0 +1,518 @@
+//! 导航模块
+//!
+//! 维护界面路由的前进/后退历史，支持从提醒、通知和全局搜索结果
+//! 按实体类型和ID直接跳转。离开有未保存修改的表单前需要用户确认。
+//! 打开过的记录同时计入最近查看（见 [`crate::recent_items`]）。
+
+use std::fmt;
+
+use chrono::Utc;
+use minicrm_core::{CustomerLevel, EntityKind, FilterValue, QueryFilter, SortBy};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::UserMessage;
+use crate::recent_items::{deleted_entry_message, RecentItems};
+
+/// 客户列表筛选条件
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerListFilter {
+    /// 搜索关键词
+    pub search: Option<String>,
+    /// 客户等级
+    pub level: Option<CustomerLevel>,
+    /// 只显示有流失风险的客户
+    #[serde(default)]
+    pub churn_risk: bool,
+    /// 排序（为空时按名称）
+    #[serde(default)]
+    pub sort_by: Option<SortBy>,
+}
+
+impl CustomerListFilter {
+    /// 转换为查询过滤器
+    pub fn to_query_filter(&self) -> QueryFilter {
+        let mut filter = QueryFilter {
+            search: self.search.clone(),
+            sort_by: self.sort_by.clone(),
+            ..QueryFilter::default()
+        };
+        if let Some(level) = self.level {
+            filter
+                .filters
+                .insert("level".to_string(), FilterValue::String(format!("{:?}", level)));
+        }
+        if self.churn_risk {
+            filter
+                .filters
+                .insert("churn_risk".to_string(), FilterValue::Boolean(true));
+        }
+        filter
+    }
+}
+
+/// 界面路由
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "view", rename_all = "snake_case")]
+pub enum Route {
+    /// 客户列表
+    CustomerList {
+        /// 筛选条件
+        #[serde(default)]
+        filter: CustomerListFilter,
+    },
+    /// 客户详情
+    CustomerDetail {
+        /// 客户ID
+        id: Uuid,
+    },
+    /// 报价编辑
+    QuoteEditor {
+        /// 报价ID
+        id: Uuid,
+    },
+    /// 任务看板
+    TaskBoard,
+    /// 产品价目
+    ProductList,
+    /// 数据导入
+    DataImport,
+    /// 仪表盘
+    #[default]
+    Dashboard,
+    /// 设置
+    Settings,
+    /// 诊断信息
+    Diagnostics,
+}
+
+impl Route {
+    /// 根据实体类型和ID构建路由
+    ///
+    /// 供提醒、通知和全局搜索结果跳转使用；没有对应视图的实体返回 `None`。
+    pub fn for_entity(kind: EntityKind, id: Uuid) -> Option<Self> {
+        match kind {
+            EntityKind::Customer => Some(Self::CustomerDetail { id }),
+            EntityKind::Quote => Some(Self::QuoteEditor { id }),
+            EntityKind::Task => Some(Self::TaskBoard),
+            EntityKind::Supplier
+            | EntityKind::ServiceTicket
+            | EntityKind::Order
+            | EntityKind::Opportunity => None,
+        }
+    }
+
+    /// 视图标识（与主窗口 `current-view` 属性对应）
+    pub fn view_name(&self) -> &'static str {
+        match self {
+            Self::CustomerList { .. } => "customer-list",
+            Self::CustomerDetail { .. } => "customer-detail",
+            Self::QuoteEditor { .. } => "quote-editor",
+            Self::TaskBoard => "task-board",
+            Self::ProductList => "product-list",
+            Self::DataImport => "data-import",
+            Self::Dashboard => "dashboard",
+            Self::Settings => "settings",
+            Self::Diagnostics => "diagnostics",
+        }
+    }
+
+    /// 根据视图标识构建无参数路由（主窗口导航栏使用）
+    pub fn from_view_name(name: &str) -> Option<Self> {
+        match name {
+            "customer-list" => Some(Self::CustomerList {
+                filter: CustomerListFilter::default(),
+            }),
+            "task-board" => Some(Self::TaskBoard),
+            "product-list" => Some(Self::ProductList),
+            "data-import" => Some(Self::DataImport),
+            "dashboard" => Some(Self::Dashboard),
+            "settings" => Some(Self::Settings),
+            "diagnostics" => Some(Self::Diagnostics),
+            _ => None,
+        }
+    }
+
+    /// 视图标题
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::CustomerList { .. } => "客户列表",
+            Self::CustomerDetail { .. } => "客户详情",
+            Self::QuoteEditor { .. } => "报价编辑",
+            Self::TaskBoard => "任务看板",
+            Self::ProductList => "产品价目",
+            Self::DataImport => "数据导入",
+            Self::Dashboard => "仪表盘",
+            Self::Settings => "设置",
+            Self::Diagnostics => "诊断信息",
+        }
+    }
+}
+
+/// 离开当前视图前的检查
+///
+/// 表单有未保存修改时返回 `true`，导航将等待用户确认。
+pub trait NavigationGuard {
+    /// 是否存在未保存的修改
+    fn has_unsaved_changes(&self) -> bool;
+}
+
+/// 导航结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavigationOutcome {
+    /// 已切换到目标路由
+    Navigated,
+    /// 存在未保存的修改，等待确认放弃
+    PendingConfirmation,
+    /// 没有可执行的导航（如历史为空或目标与当前相同）
+    Unchanged,
+}
+
+/// 等待确认的导航
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PendingNavigation {
+    To(Route),
+    Back,
+    Forward,
+}
+
+type RouteListener = Box<dyn Fn(&Route)>;
+
+/// 导航控制器
+pub struct NavigationController {
+    current: Route,
+    back_stack: Vec<Route>,
+    forward_stack: Vec<Route>,
+    pending: Option<PendingNavigation>,
+    guards: Vec<Box<dyn NavigationGuard>>,
+    listeners: Vec<RouteListener>,
+    recent: RecentItems,
+}
+
+impl fmt::Debug for NavigationController {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NavigationController")
+            .field("current", &self.current)
+            .field("back_stack", &self.back_stack)
+            .field("forward_stack", &self.forward_stack)
+            .field("pending", &self.pending)
+            .field("recent", &self.recent)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for NavigationController {
+    fn default() -> Self {
+        Self::new(Route::default())
+    }
+}
+
+impl NavigationController {
+    /// 以初始路由创建导航控制器（如从界面状态文件恢复的路由）
+    pub fn new(initial: Route) -> Self {
+        Self {
+            current: initial,
+            back_stack: Vec::new(),
+            forward_stack: Vec::new(),
+            pending: None,
+            guards: Vec::new(),
+            listeners: Vec::new(),
+            recent: RecentItems::default(),
+        }
+    }
+
+    /// 设置最近查看（从界面状态文件恢复）
+    pub fn with_recent_items(mut self, recent: RecentItems) -> Self {
+        self.recent = recent;
+        self
+    }
+
+    /// 当前路由
+    pub fn current(&self) -> &Route {
+        &self.current
+    }
+
+    /// 是否可以后退
+    pub fn can_go_back(&self) -> bool {
+        !self.back_stack.is_empty()
+    }
+
+    /// 是否可以前进
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward_stack.is_empty()
+    }
+
+    /// 最近查看
+    pub fn recent_items(&self) -> &RecentItems {
+        &self.recent
+    }
+
+    /// 记录打开的记录（详情界面加载完成、标题已知时调用）
+    pub fn record_opened(&mut self, kind: EntityKind, id: Uuid, title: impl Into<String>) {
+        self.recent.record(kind, id, title, Utc::now());
+    }
+
+    /// 是否有等待确认的导航
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// 添加离开检查
+    pub fn add_guard(&mut self, guard: Box<dyn NavigationGuard>) {
+        self.guards.push(guard);
+    }
+
+    /// 订阅路由变化
+    pub fn on_route_changed<F>(&mut self, listener: F)
+    where
+        F: Fn(&Route) + 'static,
+    {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// 跳转到指定路由
+    pub fn navigate_to(&mut self, route: Route) -> NavigationOutcome {
+        if route == self.current {
+            return NavigationOutcome::Unchanged;
+        }
+        self.request(PendingNavigation::To(route))
+    }
+
+    /// 按实体类型和ID跳转
+    pub fn open_entity(&mut self, kind: EntityKind, id: Uuid) -> NavigationOutcome {
+        self.recent.touch(kind, id, Utc::now());
+        match Route::for_entity(kind, id) {
+            Some(route) => self.navigate_to(route),
+            None => NavigationOutcome::Unchanged,
+        }
+    }
+
+    /// 从最近查看重新打开
+    ///
+    /// `exists` 为打开前查询的记录是否仍存在；已删除的记录从最近查看中移除并返回提示。
+    ///
+    /// # Errors
+    ///
+    /// 记录已被删除时返回“该记录已被删除”提示。
+    pub fn reopen_recent(
+        &mut self,
+        kind: EntityKind,
+        id: Uuid,
+        exists: bool,
+    ) -> Result<NavigationOutcome, UserMessage> {
+        if !exists {
+            self.recent.remove(kind, id);
+            return Err(deleted_entry_message());
+        }
+        Ok(self.open_entity(kind, id))
+    }
+
+    /// 后退
+    pub fn back(&mut self) -> NavigationOutcome {
+        if !self.can_go_back() {
+            return NavigationOutcome::Unchanged;
+        }
+        self.request(PendingNavigation::Back)
+    }
+
+    /// 前进
+    pub fn forward(&mut self) -> NavigationOutcome {
+        if !self.can_go_forward() {
+            return NavigationOutcome::Unchanged;
+        }
+        self.request(PendingNavigation::Forward)
+    }
+
+    /// 确认放弃修改，执行等待中的导航
+    pub fn confirm_discard(&mut self) -> NavigationOutcome {
+        match self.pending.take() {
+            Some(pending) => self.apply(pending),
+            None => NavigationOutcome::Unchanged,
+        }
+    }
+
+    /// 取消等待中的导航，留在当前视图
+    pub fn cancel_pending(&mut self) {
+        self.pending = None;
+    }
+
+    fn request(&mut self, navigation: PendingNavigation) -> NavigationOutcome {
+        if self.guards.iter().any(|guard| guard.has_unsaved_changes()) {
+            self.pending = Some(navigation);
+            return NavigationOutcome::PendingConfirmation;
+        }
+        self.pending = None;
+        self.apply(navigation)
+    }
+
+    fn apply(&mut self, navigation: PendingNavigation) -> NavigationOutcome {
+        let previous = match navigation {
+            PendingNavigation::To(route) => {
+                self.forward_stack.clear();
+                std::mem::replace(&mut self.current, route)
+            }
+            PendingNavigation::Back => match self.back_stack.pop() {
+                Some(route) => {
+                    let previous = std::mem::replace(&mut self.current, route);
+                    self.forward_stack.push(previous);
+                    return self.notify();
+                }
+                None => return NavigationOutcome::Unchanged,
+            },
+            PendingNavigation::Forward => match self.forward_stack.pop() {
+                Some(route) => std::mem::replace(&mut self.current, route),
+                None => return NavigationOutcome::Unchanged,
+            },
+        };
+        self.back_stack.push(previous);
+        self.notify()
+    }
+
+    fn notify(&self) -> NavigationOutcome {
+        for listener in &self.listeners {
+            listener(&self.current);
+        }
+        NavigationOutcome::Navigated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    struct DirtyFlag(Rc<Cell<bool>>);
+
+    impl NavigationGuard for DirtyFlag {
+        fn has_unsaved_changes(&self) -> bool {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn test_stack_semantics() {
+        let mut nav = NavigationController::default();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        nav.on_route_changed({
+            let seen = seen.clone();
+            move |route| seen.borrow_mut().push(route.clone())
+        });
+
+        let customer = Route::CustomerDetail { id: Uuid::new_v4() };
+        assert_eq!(nav.navigate_to(Route::TaskBoard), NavigationOutcome::Navigated);
+        assert_eq!(nav.navigate_to(customer.clone()), NavigationOutcome::Navigated);
+        assert_eq!(nav.navigate_to(customer.clone()), NavigationOutcome::Unchanged);
+
+        assert_eq!(nav.back(), NavigationOutcome::Navigated);
+        assert_eq!(nav.current(), &Route::TaskBoard);
+        assert_eq!(nav.forward(), NavigationOutcome::Navigated);
+        assert_eq!(nav.current(), &customer);
+        assert_eq!(nav.forward(), NavigationOutcome::Unchanged);
+
+        // 新的跳转清空前进历史
+        nav.back();
+        nav.navigate_to(Route::Settings);
+        assert!(!nav.can_go_forward());
+        nav.back();
+        nav.back();
+        assert_eq!(nav.current(), &Route::Dashboard);
+        assert_eq!(nav.back(), NavigationOutcome::Unchanged);
+
+        assert_eq!(seen.borrow().len(), 8);
+        assert_eq!(seen.borrow().last(), Some(&Route::Dashboard));
+    }
+
+    #[test]
+    fn test_dirty_form_guards_navigation() {
+        let dirty = Rc::new(Cell::new(false));
+        let mut nav = NavigationController::default();
+        nav.add_guard(Box::new(DirtyFlag(dirty.clone())));
+
+        let quote_id = Uuid::new_v4();
+        nav.open_entity(EntityKind::Quote, quote_id);
+        dirty.set(true);
+
+        // 取消：留在当前视图
+        assert_eq!(nav.navigate_to(Route::Dashboard), NavigationOutcome::PendingConfirmation);
+        nav.cancel_pending();
+        assert_eq!(nav.current(), &Route::QuoteEditor { id: quote_id });
+        assert_eq!(nav.confirm_discard(), NavigationOutcome::Unchanged);
+
+        // 后退同样受保护，确认后执行
+        assert_eq!(nav.back(), NavigationOutcome::PendingConfirmation);
+        assert_eq!(nav.confirm_discard(), NavigationOutcome::Navigated);
+        assert_eq!(nav.current(), &Route::Dashboard);
+        assert!(!nav.has_pending());
+    }
+
+    #[test]
+    fn test_every_route_serializes() {
+        let routes = vec![
+            Route::CustomerList {
+                filter: CustomerListFilter {
+                    search: Some("板材".to_string()),
+                    level: Some(CustomerLevel::Vip),
+                    churn_risk: false,
+                    sort_by: Some(SortBy::desc("created_at")),
+                },
+            },
+            Route::CustomerList {
+                filter: CustomerListFilter {
+                    churn_risk: true,
+                    ..CustomerListFilter::default()
+                },
+            },
+            Route::CustomerList {
+                filter: CustomerListFilter::default(),
+            },
+            Route::CustomerDetail { id: Uuid::new_v4() },
+            Route::QuoteEditor { id: Uuid::new_v4() },
+            Route::TaskBoard,
+            Route::ProductList,
+            Route::DataImport,
+            Route::Dashboard,
+            Route::Settings,
+            Route::Diagnostics,
+        ];
+        for route in routes {
+            let json = serde_json::to_string(&route).unwrap();
+            assert!(json.contains(&format!("\"view\":\"{}\"", route.view_name().replace('-', "_"))));
+            let restored: Route = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, route);
+        }
+    }
+
+    #[test]
+    fn test_deep_links_from_entities() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            Route::for_entity(EntityKind::Customer, id),
+            Some(Route::CustomerDetail { id })
+        );
+        assert_eq!(Route::for_entity(EntityKind::Task, id), Some(Route::TaskBoard));
+        assert_eq!(Route::for_entity(EntityKind::Supplier, id), None);
+    }
+
+    #[test]
+    fn test_reopen_deleted_recent_entry() {
+        let mut nav = NavigationController::default();
+        let kept = Uuid::new_v4();
+        let deleted = Uuid::new_v4();
+        nav.record_opened(EntityKind::Customer, kept, "华美装饰");
+        nav.record_opened(EntityKind::Quote, deleted, "BJ20240701-001");
+
+        let message = nav
+            .reopen_recent(EntityKind::Quote, deleted, false)
+            .unwrap_err();
+        assert_eq!(message.detail, "该记录已被删除");
+        assert_eq!(nav.recent_items().items().len(), 1);
+        assert_eq!(nav.current(), &Route::Dashboard);
+
+        assert_eq!(
+            nav.reopen_recent(EntityKind::Customer, kept, true),
+            Ok(NavigationOutcome::Navigated)
+        );
+        assert_eq!(nav.current(), &Route::CustomerDetail { id: kept });
+    }
+}
@@ -0,0 +1,353 @@
+//! 新手引导清单
+//!
+//! 新安装的系统在仪表盘顶部显示入门步骤，直到用户关闭。每一步是否完成由真实数据判断
+//! （如已有客户、备份目录中已有备份），只在打开仪表盘时检查，不轮询。
+//!
+//! 完成过的步骤和关闭状态保存在界面状态中：步骤一旦完成就不再检查，之后删除了相关数据
+//! 也仍显示为已完成。
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use minicrm_core::CoreResult;
+use serde::{Deserialize, Serialize};
+
+use crate::navigation::{CustomerListFilter, NavigationController, NavigationOutcome, Route};
+
+/// 入门步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    /// 创建第一个客户
+    FirstCustomer,
+    /// 录入一个产品
+    FirstProduct,
+    /// 创建一张报价单
+    FirstQuote,
+    /// 设置自动备份
+    Backup,
+    /// 完成一次导入
+    FirstImport,
+}
+
+impl OnboardingStep {
+    /// 全部步骤（按显示顺序）
+    pub const ALL: [OnboardingStep; 5] = [
+        OnboardingStep::FirstCustomer,
+        OnboardingStep::FirstProduct,
+        OnboardingStep::FirstQuote,
+        OnboardingStep::Backup,
+        OnboardingStep::FirstImport,
+    ];
+
+    /// 步骤ID（与序列化名称相同，界面回调使用）
+    pub fn id(&self) -> &'static str {
+        match self {
+            OnboardingStep::FirstCustomer => "first_customer",
+            OnboardingStep::FirstProduct => "first_product",
+            OnboardingStep::FirstQuote => "first_quote",
+            OnboardingStep::Backup => "backup",
+            OnboardingStep::FirstImport => "first_import",
+        }
+    }
+
+    /// 根据步骤ID解析步骤
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.id() == id)
+    }
+
+    /// 步骤标题
+    pub fn title(&self) -> &'static str {
+        match self {
+            OnboardingStep::FirstCustomer => "创建第一个客户",
+            OnboardingStep::FirstProduct => "录入一个产品",
+            OnboardingStep::FirstQuote => "创建一张报价单",
+            OnboardingStep::Backup => "设置自动备份",
+            OnboardingStep::FirstImport => "完成一次导入",
+        }
+    }
+
+    /// 完成该步骤的界面（报价从客户详情创建，因此跳到客户列表）
+    pub fn route(&self) -> Route {
+        match self {
+            OnboardingStep::FirstCustomer | OnboardingStep::FirstQuote => Route::CustomerList {
+                filter: CustomerListFilter::default(),
+            },
+            OnboardingStep::FirstProduct => Route::ProductList,
+            OnboardingStep::Backup => Route::Settings,
+            OnboardingStep::FirstImport => Route::DataImport,
+        }
+    }
+}
+
+/// 判断步骤是否完成的数据来源
+///
+/// 每次检查应只执行存在性查询（如 `SELECT EXISTS(...)`），仪表盘加载时对未完成的步骤调用。
+#[async_trait]
+pub trait OnboardingProbe: Send + Sync {
+    /// 步骤对应的数据是否已存在
+    async fn is_done(&self, step: OnboardingStep) -> CoreResult<bool>;
+}
+
+/// 保存在界面状态中的引导进度
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnboardingState {
+    /// 用户已关闭引导清单
+    #[serde(default)]
+    pub dismissed: bool,
+    /// 完成过的步骤
+    #[serde(default)]
+    pub completed: BTreeSet<OnboardingStep>,
+}
+
+/// 清单中的一步
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnboardingStepView {
+    /// 步骤
+    pub step: OnboardingStep,
+    /// 步骤标题
+    pub title: &'static str,
+    /// 是否已完成
+    pub done: bool,
+}
+
+/// 新手引导清单视图模型
+pub struct OnboardingChecklistViewModel {
+    probe: Arc<dyn OnboardingProbe>,
+    state: OnboardingState,
+    /// 按显示顺序排列的步骤
+    pub steps: Vec<OnboardingStepView>,
+}
+
+impl std::fmt::Debug for OnboardingChecklistViewModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnboardingChecklistViewModel")
+            .field("state", &self.state)
+            .field("steps", &self.steps)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OnboardingChecklistViewModel {
+    /// 按保存的进度创建视图模型（尚未检查数据）
+    pub fn new(probe: Arc<dyn OnboardingProbe>, state: OnboardingState) -> Self {
+        let steps = OnboardingStep::ALL
+            .iter()
+            .map(|step| OnboardingStepView {
+                step: *step,
+                title: step.title(),
+                done: state.completed.contains(step),
+            })
+            .collect();
+        Self {
+            probe,
+            state,
+            steps,
+        }
+    }
+
+    /// 检查尚未完成的步骤（仪表盘加载时调用）
+    ///
+    /// 已关闭时不检查。返回进度是否有变化，有变化时调用方应保存 [`Self::state`]。
+    ///
+    /// # Errors
+    ///
+    /// 数据来源查询失败时返回错误，已检查的步骤保留结果。
+    pub async fn load(&mut self) -> CoreResult<bool> {
+        if self.state.dismissed {
+            return Ok(false);
+        }
+        let mut changed = false;
+        for view in self.steps.iter_mut().filter(|v| !v.done) {
+            if self.probe.is_done(view.step).await? {
+                view.done = true;
+                changed |= self.state.completed.insert(view.step);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// 记录步骤已完成（如导入完成时），返回进度是否有变化
+    pub fn mark_completed(&mut self, step: OnboardingStep) -> bool {
+        if let Some(view) = self.steps.iter_mut().find(|v| v.step == step) {
+            view.done = true;
+        }
+        self.state.completed.insert(step)
+    }
+
+    /// 是否在仪表盘上显示
+    pub fn visible(&self) -> bool {
+        !self.state.dismissed
+    }
+
+    /// 已完成的步骤数和总步骤数
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self.steps.iter().filter(|v| v.done).count();
+        (done, self.steps.len())
+    }
+
+    /// 进度文本，如“已完成 2/5”
+    pub fn progress_text(&self) -> String {
+        let (done, total) = self.progress();
+        format!("已完成 {}/{}", done, total)
+    }
+
+    /// 跳转到完成该步骤的界面
+    pub fn jump(
+        &self,
+        step: OnboardingStep,
+        navigation: &mut NavigationController,
+    ) -> NavigationOutcome {
+        navigation.navigate_to(step.route())
+    }
+
+    /// 关闭引导清单（调用方保存 [`Self::state`]）
+    pub fn dismiss(&mut self) {
+        self.state.dismissed = true;
+    }
+
+    /// 需要保存的进度
+    pub fn state(&self) -> &OnboardingState {
+        &self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// 按已有数据回答的数据来源，记录检查过的步骤
+    #[derive(Default)]
+    struct FixtureProbe {
+        existing: Mutex<BTreeSet<OnboardingStep>>,
+        checked: Mutex<Vec<OnboardingStep>>,
+    }
+
+    impl FixtureProbe {
+        fn add(&self, step: OnboardingStep) {
+            self.existing.lock().unwrap().insert(step);
+        }
+
+        fn remove(&self, step: OnboardingStep) {
+            self.existing.lock().unwrap().remove(&step);
+        }
+
+        fn take_checked(&self) -> Vec<OnboardingStep> {
+            std::mem::take(&mut *self.checked.lock().unwrap())
+        }
+    }
+
+    #[async_trait]
+    impl OnboardingProbe for FixtureProbe {
+        async fn is_done(&self, step: OnboardingStep) -> CoreResult<bool> {
+            self.checked.lock().unwrap().push(step);
+            Ok(self.existing.lock().unwrap().contains(&step))
+        }
+    }
+
+    fn done_steps(checklist: &OnboardingChecklistViewModel) -> Vec<OnboardingStep> {
+        checklist
+            .steps
+            .iter()
+            .filter(|v| v.done)
+            .map(|v| v.step)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_steps_follow_fixture_data() {
+        let probe = Arc::new(FixtureProbe::default());
+        let mut checklist =
+            OnboardingChecklistViewModel::new(probe.clone(), OnboardingState::default());
+        // 创建时不检查数据
+        assert!(probe.take_checked().is_empty());
+
+        assert!(!checklist.load().await.unwrap());
+        assert_eq!(probe.take_checked(), OnboardingStep::ALL.to_vec());
+        assert_eq!(checklist.progress_text(), "已完成 0/5");
+
+        probe.add(OnboardingStep::FirstCustomer);
+        probe.add(OnboardingStep::Backup);
+        assert!(checklist.load().await.unwrap());
+        assert_eq!(
+            done_steps(&checklist),
+            vec![OnboardingStep::FirstCustomer, OnboardingStep::Backup]
+        );
+        probe.take_checked();
+
+        // 完成过的步骤不再检查，删除数据后仍为已完成
+        probe.remove(OnboardingStep::FirstCustomer);
+        probe.add(OnboardingStep::FirstQuote);
+        assert!(checklist.load().await.unwrap());
+        assert_eq!(
+            probe.take_checked(),
+            vec![
+                OnboardingStep::FirstProduct,
+                OnboardingStep::FirstQuote,
+                OnboardingStep::FirstImport
+            ]
+        );
+        assert_eq!(checklist.progress(), (3, 5));
+
+        assert!(checklist.mark_completed(OnboardingStep::FirstImport));
+        assert!(!checklist.mark_completed(OnboardingStep::FirstImport));
+        assert_eq!(checklist.progress_text(), "已完成 4/5");
+    }
+
+    #[test]
+    fn test_jump_routes_to_each_step() {
+        let checklist = OnboardingChecklistViewModel::new(
+            Arc::new(FixtureProbe::default()),
+            OnboardingState::default(),
+        );
+        let customers = Route::CustomerList {
+            filter: CustomerListFilter::default(),
+        };
+        let expected = [
+            (OnboardingStep::FirstCustomer, customers.clone()),
+            (OnboardingStep::FirstProduct, Route::ProductList),
+            (OnboardingStep::FirstQuote, customers),
+            (OnboardingStep::Backup, Route::Settings),
+            (OnboardingStep::FirstImport, Route::DataImport),
+        ];
+        for (step, route) in expected {
+            assert_eq!(OnboardingStep::from_id(step.id()), Some(step));
+            let mut navigation = NavigationController::new(Route::Dashboard);
+            assert_eq!(
+                checklist.jump(step, &mut navigation),
+                NavigationOutcome::Navigated
+            );
+            assert_eq!(navigation.current(), &route, "{:?}", step);
+            assert!(navigation.can_go_back());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dismissal_and_progress_persist() {
+        let probe = Arc::new(FixtureProbe::default());
+        probe.add(OnboardingStep::FirstProduct);
+        let mut checklist =
+            OnboardingChecklistViewModel::new(probe.clone(), OnboardingState::default());
+        checklist.load().await.unwrap();
+        checklist.dismiss();
+        assert!(!checklist.visible());
+
+        let json = serde_json::to_string(checklist.state()).unwrap();
+        assert_eq!(json, r#"{"dismissed":true,"completed":["first_product"]}"#);
+        let restored: OnboardingState = serde_json::from_str(&json).unwrap();
+        probe.take_checked();
+
+        let mut checklist = OnboardingChecklistViewModel::new(probe.clone(), restored);
+        assert!(!checklist.visible());
+        assert_eq!(done_steps(&checklist), vec![OnboardingStep::FirstProduct]);
+        // 关闭后不再检查数据
+        assert!(!checklist.load().await.unwrap());
+        assert!(probe.take_checked().is_empty());
+
+        // 旧版本的界面状态没有引导进度
+        let state: OnboardingState = serde_json::from_str("{}").unwrap();
+        assert_eq!(state, OnboardingState::default());
+    }
+}
@@ -0,0 +1,174 @@
+//! 进度对话框模块
+//!
+//! 导出、报表、归档等耗时操作执行期间显示进度对话框。“取消”按钮绑定到
+//! [`CommandBus::dispatch_cancellable`](minicrm_application::CommandBus::dispatch_cancellable)
+//! 返回的取消令牌；点击后对话框显示“正在取消”，等命令结束后再关闭。
+
+use minicrm_core::{CancellationToken, CoreError, CoreResult};
+
+use crate::errors::UserMessage;
+
+/// 取消按钮文本
+pub const CANCEL_LABEL: &str = "取消";
+
+/// 耗时操作的状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressState {
+    /// 执行中
+    Running,
+    /// 已请求取消，等待操作在下一批次停止
+    Cancelling,
+    /// 已完成
+    Completed,
+    /// 已取消
+    Cancelled,
+    /// 失败
+    Failed(UserMessage),
+}
+
+/// 进度对话框视图模型
+#[derive(Debug, Clone)]
+pub struct ProgressDialog {
+    /// 标题，如“导出客户”
+    pub title: String,
+    /// 已处理的行数
+    pub processed: u64,
+    /// 总行数（未知时为空）
+    pub total: Option<u64>,
+    /// 当前状态
+    pub state: ProgressState,
+    cancel: CancellationToken,
+}
+
+impl ProgressDialog {
+    /// 为已分发的命令打开对话框
+    pub fn new<S: Into<String>>(title: S, cancel: CancellationToken) -> Self {
+        Self {
+            title: title.into(),
+            processed: 0,
+            total: None,
+            state: ProgressState::Running,
+            cancel,
+        }
+    }
+
+    /// 更新进度
+    pub fn set_progress(&mut self, processed: u64, total: Option<u64>) {
+        self.processed = processed;
+        self.total = total;
+    }
+
+    /// 完成百分比（总数未知时为空）
+    pub fn percent(&self) -> Option<u8> {
+        // 总数为0时视为已完成
+        self.total.map(|total| {
+            (self.processed.min(total) * 100)
+                .checked_div(total)
+                .map_or(100, |percent| percent as u8)
+        })
+    }
+
+    /// 对话框中的状态文本
+    pub fn message(&self) -> String {
+        match &self.state {
+            ProgressState::Running => match (self.percent(), self.total) {
+                (Some(percent), Some(total)) => {
+                    format!(
+                        "正在{}… {}%（{}/{}）",
+                        self.title, percent, self.processed, total
+                    )
+                }
+                _ => format!("正在{}… 已处理 {} 条", self.title, self.processed),
+            },
+            ProgressState::Cancelling => "正在取消…".to_string(),
+            ProgressState::Completed => format!("{}已完成", self.title),
+            ProgressState::Cancelled => format!("已取消{}，未完成的文件已删除", self.title),
+            ProgressState::Failed(message) => message.to_text(),
+        }
+    }
+
+    /// 取消按钮是否可用
+    pub fn can_cancel(&self) -> bool {
+        self.state == ProgressState::Running
+    }
+
+    /// 点击取消按钮
+    ///
+    /// 操作已结束或已请求取消时返回 `false`。
+    pub fn cancel(&mut self) -> bool {
+        if !self.can_cancel() {
+            return false;
+        }
+        self.cancel.cancel();
+        self.state = ProgressState::Cancelling;
+        true
+    }
+
+    /// 命令结束后更新状态
+    ///
+    /// 请求取消时操作可能已经完成，此时按完成处理。
+    pub fn finish<T>(&mut self, result: &CoreResult<T>) {
+        self.state = match result {
+            Ok(_) => ProgressState::Completed,
+            Err(CoreError::Cancelled) => ProgressState::Cancelled,
+            Err(e) => ProgressState::Failed(UserMessage::from_error(e)),
+        };
+    }
+
+    /// 对话框是否仍需显示（操作尚未结束）
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self.state,
+            ProgressState::Running | ProgressState::Cancelling
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::MessageKind;
+
+    #[test]
+    fn test_cancel_button_cancels_token() {
+        let cancel = CancellationToken::new();
+        let mut dialog = ProgressDialog::new("导出客户", cancel.clone());
+        dialog.set_progress(450, Some(1000));
+        assert_eq!(dialog.message(), "正在导出客户… 45%（450/1000）");
+        assert!(dialog.can_cancel());
+
+        assert!(dialog.cancel());
+        assert!(cancel.is_cancelled());
+        assert!(!dialog.cancel());
+        assert_eq!(dialog.message(), "正在取消…");
+        assert!(dialog.is_open());
+
+        dialog.finish::<usize>(&Err(CoreError::Cancelled));
+        assert_eq!(dialog.state, ProgressState::Cancelled);
+        assert_eq!(dialog.message(), "已取消导出客户，未完成的文件已删除");
+        assert!(!dialog.is_open());
+    }
+
+    #[test]
+    fn test_finish_outcomes() {
+        let mut dialog = ProgressDialog::new("生成月度报表", CancellationToken::new());
+        dialog.set_progress(3, None);
+        assert_eq!(dialog.percent(), None);
+        assert_eq!(dialog.message(), "正在生成月度报表… 已处理 3 条");
+
+        dialog.finish::<()>(&Err(CoreError::Other("磁盘已满".to_string())));
+        match &dialog.state {
+            ProgressState::Failed(message) => assert_eq!(message.kind, MessageKind::Error),
+            other => panic!("unexpected state: {:?}", other),
+        }
+        assert!(!dialog.can_cancel());
+        assert!(!dialog.cancel());
+
+        let mut dialog = ProgressDialog::new("导出归档", CancellationToken::new());
+        dialog.cancel();
+        // 取消请求到达前已经完成
+        dialog.finish(&Ok(()));
+        assert_eq!(dialog.state, ProgressState::Completed);
+        assert_eq!(dialog.message(), "导出归档已完成");
+    }
+}
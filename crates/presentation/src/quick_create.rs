@@ -0,0 +1,701 @@
+//! 快速新建模块
+//!
+//! 报价、任务编辑器中的客户和产品选择器末尾提供“新建…”入口：在弹出的精简表单中分发
+//! 新建命令，成功后把新实体加入选择器并选中，父编辑器中已填写的内容保持不变；校验错误
+//! 显示在表单内，表单保持打开。新增一种选择器只需为实体实现 [`QuickCreate`]。
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use minicrm_application::commands::{CreateCustomerCommand, CreateProductCommand};
+use minicrm_application::{Command, CommandBus, Idempotent};
+use minicrm_core::validation;
+use minicrm_core::{CoreError, CoreResult, Customer, FieldPolicy, Money, PricedProduct};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::errors::UserMessage;
+use crate::formatting::format_money;
+
+/// 选择器中的一行
+pub trait PickerRow: Clone + fmt::Debug {
+    /// 实体ID
+    fn id(&self) -> Uuid;
+
+    /// 显示文本
+    fn label(&self) -> String;
+}
+
+/// 快速新建表单的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuickCreateField {
+    /// 字段名（与新建命令校验错误中的字段名一致）
+    pub key: &'static str,
+    /// 标签
+    pub label: &'static str,
+    /// 是否必填
+    pub required: bool,
+}
+
+/// 可在选择器中快速新建的实体
+pub trait QuickCreate {
+    /// 新建命令
+    type Command: Idempotent;
+    /// 选择器中的行
+    type Row: PickerRow;
+
+    /// 表单标题
+    const TITLE: &'static str;
+    /// 选择器末尾的新建入口
+    const ENTRY_LABEL: &'static str;
+    /// 表单字段，打开时第一个字段用选择器中的搜索文字预填
+    const FIELDS: &'static [QuickCreateField];
+
+    /// 由表单值生成新建命令，`values` 与 [`Self::FIELDS`] 一一对应
+    ///
+    /// # Errors
+    ///
+    /// 输入无法转换（如金额格式不正确）时返回字段校验错误。
+    fn command(values: &[String], idempotency_key: Uuid) -> CoreResult<Self::Command>;
+
+    /// 新建结果转换为选择器中的行
+    fn row(output: &<Self::Command as Command>::Output) -> Self::Row;
+}
+
+/// 选择器的选择结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickerChoice {
+    /// 选中已有实体
+    Selected(Uuid),
+    /// 选中末尾的“新建…”入口
+    CreateNew,
+}
+
+/// 实体选择器
+#[derive(Debug, Clone)]
+pub struct EntityPicker<Q: QuickCreate> {
+    /// 可选行
+    pub options: Vec<Q::Row>,
+    /// 已选实体
+    pub selected: Option<Uuid>,
+    /// 搜索文字
+    pub search: String,
+}
+
+/// 客户选择器
+pub type CustomerPicker = EntityPicker<CustomerQuickCreate>;
+
+/// 产品选择器
+pub type ProductPicker = EntityPicker<ProductQuickCreate>;
+
+impl<Q: QuickCreate> EntityPicker<Q> {
+    /// 以可选行创建选择器
+    pub fn new(options: Vec<Q::Row>) -> Self {
+        Self {
+            options,
+            selected: None,
+            search: String::new(),
+        }
+    }
+
+    /// 下拉列表文本，末尾为新建入口
+    pub fn labels(&self) -> Vec<String> {
+        self.options
+            .iter()
+            .map(PickerRow::label)
+            .chain(std::iter::once(Q::ENTRY_LABEL.to_string()))
+            .collect()
+    }
+
+    /// 按下拉列表中的位置选择，选中已有实体时记录选择
+    pub fn choose(&mut self, index: usize) -> Option<PickerChoice> {
+        if index == self.options.len() {
+            return Some(PickerChoice::CreateNew);
+        }
+        let id = self.options.get(index)?.id();
+        self.selected = Some(id);
+        Some(PickerChoice::Selected(id))
+    }
+
+    /// 选择实体，实体不在列表中时返回 `false`
+    pub fn select(&mut self, id: Uuid) -> bool {
+        let known = self.options.iter().any(|row| row.id() == id);
+        if known {
+            self.selected = Some(id);
+        }
+        known
+    }
+
+    /// 已选的行
+    pub fn selected_row(&self) -> Option<&Q::Row> {
+        let id = self.selected?;
+        self.options.iter().find(|row| row.id() == id)
+    }
+
+    /// 加入（或替换同ID的）行并选中
+    pub fn insert_selected(&mut self, row: Q::Row) {
+        let id = row.id();
+        match self.options.iter_mut().find(|existing| existing.id() == id) {
+            Some(existing) => *existing = row,
+            None => self.options.push(row),
+        }
+        self.selected = Some(id);
+        self.search.clear();
+    }
+}
+
+/// 表单中的一个字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickCreateFieldRow {
+    /// 标签
+    pub label: String,
+    /// 当前值
+    pub value: String,
+    /// 是否必填
+    pub required: bool,
+    /// 是否隐藏（字段策略隐藏的字段不显示输入框，提交时为空）
+    pub hidden: bool,
+    /// 字段错误
+    pub error: Option<String>,
+}
+
+/// 快速新建表单
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickCreateForm {
+    /// 标题
+    pub title: String,
+    /// 字段
+    pub fields: Vec<QuickCreateFieldRow>,
+    /// 不属于具体字段的错误
+    pub message: Option<UserMessage>,
+}
+
+/// 快速新建控制器
+///
+/// 只持有弹出表单的状态，提交时修改传入的选择器，不触及父编辑器的其他内容。
+pub struct QuickCreateController<Q: QuickCreate> {
+    form: Option<QuickCreateForm>,
+    submission_key: Uuid,
+    field_policy: Option<FieldPolicy>,
+    entity: PhantomData<Q>,
+}
+
+impl<Q: QuickCreate> fmt::Debug for QuickCreateController<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuickCreateController")
+            .field("form", &self.form)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Q: QuickCreate> Default for QuickCreateController<Q> {
+    fn default() -> Self {
+        Self {
+            form: None,
+            submission_key: Uuid::new_v4(),
+            field_policy: None,
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<Q: QuickCreate> QuickCreateController<Q> {
+    /// 创建控制器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按字段策略显示必填标记和隐藏输入框
+    pub fn with_field_policy(mut self, policy: FieldPolicy) -> Self {
+        self.field_policy = Some(policy);
+        self
+    }
+
+    /// 打开中的表单
+    pub fn form(&self) -> Option<&QuickCreateForm> {
+        self.form.as_ref()
+    }
+
+    /// 表单是否打开
+    pub fn is_open(&self) -> bool {
+        self.form.is_some()
+    }
+
+    /// 打开表单，第一个字段预填选择器中的搜索文字
+    pub fn open(&mut self, picker: &EntityPicker<Q>) {
+        let fields = Q::FIELDS
+            .iter()
+            .enumerate()
+            .map(|(index, field)| QuickCreateFieldRow {
+                label: field.label.to_string(),
+                value: if index == 0 {
+                    picker.search.trim().to_string()
+                } else {
+                    String::new()
+                },
+                required: field.required
+                    || self
+                        .field_policy
+                        .as_ref()
+                        .is_some_and(|policy| policy.is_required(field.key)),
+                hidden: self
+                    .field_policy
+                    .as_ref()
+                    .is_some_and(|policy| policy.is_hidden(field.key)),
+                error: None,
+            })
+            .collect();
+        self.form = Some(QuickCreateForm {
+            title: Q::TITLE.to_string(),
+            fields,
+            message: None,
+        });
+        self.submission_key = Uuid::new_v4();
+    }
+
+    /// 修改字段值，同时清除该字段的错误
+    pub fn set_value(&mut self, index: usize, value: impl Into<String>) {
+        let Some(field) = self.form.as_mut().and_then(|f| f.fields.get_mut(index)) else {
+            return;
+        };
+        field.value = value.into();
+        field.error = None;
+        self.submission_key = Uuid::new_v4();
+    }
+
+    /// 关闭表单，不做任何修改
+    pub fn cancel(&mut self) {
+        self.form = None;
+    }
+
+    /// 提交表单
+    ///
+    /// 新建成功时关闭表单，把新实体加入选择器并选中，返回其ID；失败时错误显示在表单内，
+    /// 表单保持打开，返回 `None`。内容不变时重复提交使用同一个幂等键。
+    pub async fn submit(&mut self, bus: &CommandBus, picker: &mut EntityPicker<Q>) -> Option<Uuid>
+    where
+        <Q::Command as Command>::Output: Serialize + DeserializeOwned,
+    {
+        let values: Vec<String> = self
+            .form
+            .as_ref()?
+            .fields
+            .iter()
+            .map(|field| field.value.clone())
+            .collect();
+        let result = match Q::command(&values, self.submission_key) {
+            Ok(command) => bus.dispatch_idempotent(command).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(output) => {
+                let row = Q::row(&output);
+                let id = row.id();
+                picker.insert_selected(row);
+                self.form = None;
+                Some(id)
+            }
+            Err(e) => {
+                self.show_error(e);
+                None
+            }
+        }
+    }
+
+    fn show_error(&mut self, error: CoreError) {
+        let Some(form) = self.form.as_mut() else {
+            return;
+        };
+        for field in &mut form.fields {
+            field.error = None;
+        }
+        let CoreError::InvalidFields(errors) = error else {
+            form.message = Some(UserMessage::from_error(&error));
+            return;
+        };
+        let mut unmatched = Vec::new();
+        for error in errors {
+            match Q::FIELDS.iter().position(|f| f.key == error.field) {
+                Some(index) => form.fields[index].error = Some(error.message),
+                None => unmatched.push(error),
+            }
+        }
+        form.message = (!unmatched.is_empty())
+            .then(|| UserMessage::from_error(&CoreError::InvalidFields(unmatched)));
+    }
+}
+
+/// 非空的可选文本
+fn optional(values: &[String], index: usize) -> Option<String> {
+    values
+        .get(index)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// 客户选择器中的行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomerOption {
+    /// 客户ID
+    pub id: Uuid,
+    /// 客户名称
+    pub name: String,
+    /// 电话
+    pub phone: Option<String>,
+}
+
+impl From<&Customer> for CustomerOption {
+    fn from(customer: &Customer) -> Self {
+        Self {
+            id: customer.id,
+            name: customer.name.clone(),
+            phone: customer.phone.clone(),
+        }
+    }
+}
+
+impl PickerRow for CustomerOption {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn label(&self) -> String {
+        match &self.phone {
+            Some(phone) => format!("{}（{}）", self.name, phone),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// 快速新建客户（名称、电话）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CustomerQuickCreate;
+
+impl QuickCreate for CustomerQuickCreate {
+    type Command = CreateCustomerCommand;
+    type Row = CustomerOption;
+
+    const TITLE: &'static str = "新建客户";
+    const ENTRY_LABEL: &'static str = "新建客户…";
+    const FIELDS: &'static [QuickCreateField] = &[
+        QuickCreateField {
+            key: "name",
+            label: "客户名称",
+            required: true,
+        },
+        QuickCreateField {
+            key: "phone",
+            label: "电话",
+            required: false,
+        },
+    ];
+
+    fn command(values: &[String], idempotency_key: Uuid) -> CoreResult<CreateCustomerCommand> {
+        Ok(CreateCustomerCommand {
+            name: optional(values, 0).unwrap_or_default(),
+            contact_person: None,
+            phone: optional(values, 1),
+            email: None,
+            address: None,
+            idempotency_key: Some(idempotency_key),
+        })
+    }
+
+    fn row(customer: &Customer) -> CustomerOption {
+        CustomerOption::from(customer)
+    }
+}
+
+/// 产品选择器中的行
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductOption {
+    /// 产品ID
+    pub id: Uuid,
+    /// 产品名称
+    pub name: String,
+    /// 规格
+    pub specification: Option<String>,
+    /// 当前单价
+    pub unit_price: Option<f64>,
+}
+
+impl From<&PricedProduct> for ProductOption {
+    fn from(product: &PricedProduct) -> Self {
+        Self {
+            id: product.product_id,
+            name: product.name.clone(),
+            specification: product.specification.clone(),
+            unit_price: product.unit_price,
+        }
+    }
+}
+
+impl PickerRow for ProductOption {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn label(&self) -> String {
+        let mut label = self.name.clone();
+        if let Some(specification) = &self.specification {
+            label.push_str(&format!(" {}", specification));
+        }
+        if let Some(price) = self.unit_price {
+            label.push_str(&format!(" {}", format_money(Money::from_yuan(price))));
+        }
+        label
+    }
+}
+
+/// 快速新建产品（名称、规格、售价）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProductQuickCreate;
+
+impl QuickCreate for ProductQuickCreate {
+    type Command = CreateProductCommand;
+    type Row = ProductOption;
+
+    const TITLE: &'static str = "新建产品";
+    const ENTRY_LABEL: &'static str = "新建产品…";
+    const FIELDS: &'static [QuickCreateField] = &[
+        QuickCreateField {
+            key: "name",
+            label: "产品名称",
+            required: true,
+        },
+        QuickCreateField {
+            key: "specification",
+            label: "规格",
+            required: false,
+        },
+        QuickCreateField {
+            key: "price",
+            label: "售价（元）",
+            required: true,
+        },
+    ];
+
+    fn command(values: &[String], idempotency_key: Uuid) -> CoreResult<CreateProductCommand> {
+        let price = optional(values, 2)
+            .unwrap_or_default()
+            .parse::<Money>()
+            .map_err(|e| CoreError::invalid_fields(vec![validation::field_error("price", &e)]))?;
+        Ok(CreateProductCommand {
+            name: optional(values, 0).unwrap_or_default(),
+            specification: optional(values, 1),
+            price,
+            idempotency_key: Some(idempotency_key),
+        })
+    }
+
+    fn row(product: &PricedProduct) -> ProductOption {
+        ProductOption::from(product)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use minicrm_application::CommandHandler;
+    use minicrm_core::{CustomerLevel, FieldError};
+
+    use crate::forms::FormState;
+
+    /// 模拟客户与产品服务：名称为空时返回字段错误
+    #[derive(Default)]
+    struct FakeCatalog {
+        calls: AtomicU32,
+    }
+
+    impl FakeCatalog {
+        fn check_name(&self, name: &str) -> CoreResult<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if name.is_empty() {
+                return Err(CoreError::invalid_fields(vec![FieldError::new(
+                    "name",
+                    "名称不能为空",
+                )]));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl CommandHandler<CreateCustomerCommand> for FakeCatalog {
+        async fn handle(&self, command: CreateCustomerCommand) -> CoreResult<Customer> {
+            self.check_name(&command.name)?;
+            let now = Utc::now();
+            Ok(Customer {
+                id: Uuid::new_v4(),
+                name: command.name,
+                contact_person: None,
+                contact_birthday: None,
+                phone: command.phone,
+                email: None,
+                address: None,
+                level: CustomerLevel::Normal,
+                credit_limit: None,
+                credit_hold: false,
+                owner_id: None,
+                created_at: now,
+                updated_at: now,
+                created_by: None,
+                updated_by: None,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl CommandHandler<CreateProductCommand> for FakeCatalog {
+        async fn handle(&self, command: CreateProductCommand) -> CoreResult<PricedProduct> {
+            self.check_name(&command.name)?;
+            Ok(PricedProduct {
+                product_id: Uuid::new_v4(),
+                name: command.name,
+                specification: command.specification,
+                unit_price: Some(command.price.as_yuan()),
+                unit_cost: None,
+                retired: false,
+            })
+        }
+    }
+
+    fn bus() -> (CommandBus, Arc<FakeCatalog>) {
+        let catalog = Arc::new(FakeCatalog::default());
+        let mut bus = CommandBus::new();
+        bus.register::<CreateCustomerCommand>(catalog.clone());
+        bus.register::<CreateProductCommand>(catalog.clone());
+        (bus, catalog)
+    }
+
+    fn existing_customer() -> CustomerOption {
+        CustomerOption {
+            id: Uuid::new_v4(),
+            name: "华东板材".to_string(),
+            phone: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_created_customer_is_injected_and_selected() {
+        let (bus, catalog) = bus();
+        let existing = existing_customer();
+        let mut picker = CustomerPicker::new(vec![existing.clone()]);
+        picker.search = " 华南木业 ".to_string();
+        assert_eq!(picker.labels(), vec!["华东板材", "新建客户…"]);
+        assert_eq!(picker.choose(1), Some(PickerChoice::CreateNew));
+        assert_eq!(picker.selected, None);
+
+        let mut controller = QuickCreateController::<CustomerQuickCreate>::new();
+        controller.open(&picker);
+        assert_eq!(controller.form().unwrap().fields[0].value, "华南木业");
+        controller.set_value(1, "0757-1234567");
+
+        let id = controller.submit(&bus, &mut picker).await.unwrap();
+        assert!(!controller.is_open());
+        assert_eq!(catalog.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(picker.options.len(), 2);
+        assert_eq!(picker.selected, Some(id));
+        assert_eq!(picker.selected_row().unwrap().label(), "华南木业（0757-1234567）");
+        assert!(picker.search.is_empty());
+        assert_eq!(picker.choose(0), Some(PickerChoice::Selected(existing.id)));
+    }
+
+    #[tokio::test]
+    async fn test_validation_failure_stays_in_modal() {
+        let (bus, catalog) = bus();
+        let mut picker = ProductPicker::new(Vec::new());
+        let mut controller = QuickCreateController::<ProductQuickCreate>::new();
+        controller.open(&picker);
+        controller.set_value(0, "生态板");
+        controller.set_value(2, "一百二");
+
+        // 金额格式错误在分发前即被拦下
+        assert_eq!(controller.submit(&bus, &mut picker).await, None);
+        let form = controller.form().unwrap();
+        assert_eq!(form.fields[2].error.as_deref(), Some("金额格式不正确"));
+        assert_eq!(catalog.calls.load(Ordering::SeqCst), 0);
+
+        // 服务端校验错误显示在对应字段，已填内容保留
+        controller.set_value(0, "  ");
+        controller.set_value(2, "128");
+        assert_eq!(controller.submit(&bus, &mut picker).await, None);
+        let form = controller.form().unwrap();
+        assert_eq!(form.fields[0].error.as_deref(), Some("名称不能为空"));
+        assert_eq!(form.fields[2].error, None);
+        assert_eq!(form.fields[2].value, "128");
+        assert!(form.message.is_none());
+        assert!(picker.options.is_empty());
+        assert_eq!(picker.selected, None);
+
+        controller.set_value(0, "生态板");
+        let id = controller.submit(&bus, &mut picker).await.unwrap();
+        assert_eq!(picker.selected_row().unwrap().label(), "生态板 ¥128.00");
+        assert_eq!(picker.selected, Some(id));
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct QuoteDraft {
+        customer_id: Option<Uuid>,
+        remarks: String,
+        lines: Vec<(Option<Uuid>, f64)>,
+    }
+
+    /// 模拟报价编辑器：草稿与两个选择器
+    struct QuoteEditor {
+        draft: FormState<QuoteDraft>,
+        customers: CustomerPicker,
+        products: ProductPicker,
+    }
+
+    #[tokio::test]
+    async fn test_parent_editor_state_is_preserved() {
+        let (bus, _catalog) = bus();
+        let existing = existing_customer();
+        let mut editor = QuoteEditor {
+            draft: FormState::new(QuoteDraft {
+                customer_id: None,
+                remarks: String::new(),
+                lines: Vec::new(),
+            }),
+            customers: CustomerPicker::new(vec![existing.clone()]),
+            products: ProductPicker::new(Vec::new()),
+        };
+        editor.customers.select(existing.id);
+        {
+            let mut draft = editor.draft.current_mut();
+            draft.customer_id = Some(existing.id);
+            draft.remarks = "含安装，月底前交货".to_string();
+            draft.lines.push((None, 10.0));
+        }
+        let before = editor.draft.current().clone();
+
+        let mut controller = QuickCreateController::<ProductQuickCreate>::new();
+        controller.open(&editor.products);
+        controller.set_value(0, "多层板");
+        controller.set_value(1, "1220×2440×18");
+        controller.set_value(2, "99.9");
+        let product_id = controller.submit(&bus, &mut editor.products).await.unwrap();
+
+        assert_eq!(editor.draft.current(), &before);
+        assert!(editor.draft.is_dirty());
+        assert_eq!(editor.customers.selected, Some(existing.id));
+        assert_eq!(editor.products.selected, Some(product_id));
+
+        // 取消新建同样不影响编辑器
+        let mut customers = QuickCreateController::<CustomerQuickCreate>::new();
+        customers.open(&editor.customers);
+        customers.set_value(0, "临时客户");
+        customers.cancel();
+        assert!(!customers.is_open());
+        assert_eq!(editor.customers.options.len(), 1);
+        assert_eq!(editor.draft.current(), &before);
+    }
+}
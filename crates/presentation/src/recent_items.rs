@@ -0,0 +1,401 @@
+//! 最近查看模块
+//!
+//! 导航控制器在会话内按最后访问时间维护打开过的客户、供应商、报价和售后工单，
+//! 退出时每种类型保留最近 [`RECENT_ITEMS_PER_KIND`] 条写入界面状态文件。
+//! 标题栏的“最近查看”下拉框和 Ctrl+E 快速切换共用 [`RecentItemsViewModel`]：
+//! 输入关键词时先模糊匹配最近查看，再以全局搜索结果补充。
+//!
+//! 记录被删除后不会立即从列表移除，重新打开时才检查并提示“该记录已被删除”。
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use minicrm_core::{EntityKind, SearchHit};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::{MessageKind, UserMessage};
+use crate::formatting::format_relative;
+use crate::view_models::entity_label;
+
+/// 下拉框标题
+pub const RECENT_ITEMS_LABEL: &str = "最近查看";
+
+/// 快速切换的快捷键设置ID
+pub const QUICK_SWITCHER_SHORTCUT_ACTION: &str = "quick_switcher";
+
+/// 默认快速切换快捷键
+pub const DEFAULT_QUICK_SWITCHER_SHORTCUT: &str = "Ctrl+E";
+
+/// 重新打开已删除记录时的提示
+pub const DELETED_ENTRY_MESSAGE: &str = "该记录已被删除";
+
+/// 会话内保留的最近查看条数
+pub const RECENT_ITEMS_LIMIT: usize = 50;
+
+/// 每种类型写入界面状态文件的条数
+pub const RECENT_ITEMS_PER_KIND: usize = 10;
+
+/// 快速切换最多显示的条数
+const SWITCHER_MAX_ROWS: usize = 20;
+
+/// 快速切换快捷键（用户设置优先）
+pub fn quick_switcher_shortcut(overrides: &BTreeMap<String, String>) -> &str {
+    overrides
+        .get(QUICK_SWITCHER_SHORTCUT_ACTION)
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_QUICK_SWITCHER_SHORTCUT)
+}
+
+/// 是否记录该类型的打开
+pub fn is_tracked(kind: EntityKind) -> bool {
+    matches!(
+        kind,
+        EntityKind::Customer | EntityKind::Supplier | EntityKind::Quote | EntityKind::ServiceTicket
+    )
+}
+
+/// 一条最近查看
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentItem {
+    /// 记录类型
+    pub kind: EntityKind,
+    /// 记录ID
+    pub id: Uuid,
+    /// 标题（打开时的名称或单号）
+    pub title: String,
+    /// 最后访问时间
+    pub opened_at: DateTime<Utc>,
+}
+
+/// 最近查看列表
+///
+/// 最近访问的在前；同一记录只保留一条，再次打开时移到最前并更新标题。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentItems {
+    items: Vec<RecentItem>,
+    limit: usize,
+}
+
+impl Default for RecentItems {
+    fn default() -> Self {
+        Self::new(RECENT_ITEMS_LIMIT)
+    }
+}
+
+impl RecentItems {
+    /// 创建最多保留 `limit` 条的列表
+    pub fn new(limit: usize) -> Self {
+        Self {
+            items: Vec::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    /// 从界面状态文件恢复
+    ///
+    /// 文件中的顺序可能被手工修改过，恢复时按访问时间重新排序并去重。
+    pub fn restore(saved: Vec<RecentItem>) -> Self {
+        let mut saved = saved;
+        saved.sort_by_key(|item| Reverse(item.opened_at));
+        let mut recent = Self::default();
+        for item in saved.into_iter().rev() {
+            recent.record(item.kind, item.id, item.title, item.opened_at);
+        }
+        recent
+    }
+
+    /// 全部条目（最近访问的在前）
+    pub fn items(&self) -> &[RecentItem] {
+        &self.items
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// 记录一次打开
+    ///
+    /// 不记录的类型（见 [`is_tracked`]）直接忽略。
+    pub fn record(
+        &mut self,
+        kind: EntityKind,
+        id: Uuid,
+        title: impl Into<String>,
+        at: DateTime<Utc>,
+    ) {
+        if !is_tracked(kind) {
+            return;
+        }
+        self.remove(kind, id);
+        self.items.insert(
+            0,
+            RecentItem {
+                kind,
+                id,
+                title: title.into(),
+                opened_at: at,
+            },
+        );
+        self.items.truncate(self.limit);
+    }
+
+    /// 更新已有条目的访问时间（标题不变），不存在时返回 `false`
+    pub fn touch(&mut self, kind: EntityKind, id: Uuid, at: DateTime<Utc>) -> bool {
+        match self.position(kind, id) {
+            Some(index) => {
+                let mut item = self.items.remove(index);
+                item.opened_at = at;
+                self.items.insert(0, item);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 移除一条（记录已被删除时）
+    pub fn remove(&mut self, kind: EntityKind, id: Uuid) -> Option<RecentItem> {
+        self.position(kind, id)
+            .map(|index| self.items.remove(index))
+    }
+
+    /// 写入界面状态文件的条目：每种类型最近的 [`RECENT_ITEMS_PER_KIND`] 条
+    pub fn persisted(&self) -> Vec<RecentItem> {
+        let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+        self.items
+            .iter()
+            .filter(|item| {
+                let count = counts.entry(item.kind.table_name()).or_default();
+                *count += 1;
+                *count <= RECENT_ITEMS_PER_KIND
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn position(&self, kind: EntityKind, id: Uuid) -> Option<usize> {
+        self.items
+            .iter()
+            .position(|item| item.kind == kind && item.id == id)
+    }
+}
+
+/// 关键词是否按顺序出现在标题中（忽略大小写和空格）
+fn fuzzy_matches(title: &str, text: &str) -> bool {
+    let mut title = title.chars().flat_map(char::to_lowercase);
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|wanted| title.any(|c| c == wanted))
+}
+
+/// 下拉框或快速切换中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentItemRow {
+    /// 记录类型
+    pub kind: EntityKind,
+    /// 记录ID
+    pub id: Uuid,
+    /// 标题
+    pub title: String,
+    /// 类型名称
+    pub kind_label: &'static str,
+    /// 副标题（最近查看为访问时间，搜索结果为搜索副标题）
+    pub detail: String,
+    /// 是否来自最近查看
+    pub recent: bool,
+}
+
+/// 最近查看视图模型
+#[derive(Debug, Clone)]
+pub struct RecentItemsViewModel {
+    items: Vec<RecentItem>,
+    now: DateTime<Utc>,
+}
+
+impl RecentItemsViewModel {
+    /// 由最近查看列表创建
+    pub fn new(recent: &RecentItems, now: DateTime<Utc>) -> Self {
+        Self {
+            items: recent.items().to_vec(),
+            now,
+        }
+    }
+
+    /// 下拉框标题
+    pub fn label(&self) -> &'static str {
+        RECENT_ITEMS_LABEL
+    }
+
+    /// 下拉框中的行
+    pub fn dropdown_rows(&self) -> Vec<RecentItemRow> {
+        self.items
+            .iter()
+            .map(|item| self.recent_row(item))
+            .collect()
+    }
+
+    /// 快速切换中按关键词模糊匹配的最近查看
+    pub fn filter(&self, text: &str) -> Vec<RecentItemRow> {
+        self.items
+            .iter()
+            .filter(|item| fuzzy_matches(&item.title, text))
+            .take(SWITCHER_MAX_ROWS)
+            .map(|item| self.recent_row(item))
+            .collect()
+    }
+
+    /// 是否需要用全局搜索补充（输入了关键词时）
+    pub fn needs_search(text: &str) -> bool {
+        !text.trim().is_empty()
+    }
+
+    /// 快速切换的结果：先列出匹配的最近查看，再补充全局搜索结果
+    ///
+    /// 已在最近查看中的搜索结果不重复显示。
+    pub fn switcher_rows(&self, text: &str, hits: &[SearchHit]) -> Vec<RecentItemRow> {
+        let mut rows = self.filter(text);
+        let mut seen: HashSet<(EntityKind, Uuid)> =
+            rows.iter().map(|row| (row.kind, row.id)).collect();
+        for hit in hits {
+            if rows.len() >= SWITCHER_MAX_ROWS {
+                break;
+            }
+            if !seen.insert((hit.entity, hit.id)) {
+                continue;
+            }
+            rows.push(RecentItemRow {
+                kind: hit.entity,
+                id: hit.id,
+                title: hit.title.clone(),
+                kind_label: entity_label(hit.entity),
+                detail: hit.subtitle.clone().unwrap_or_default(),
+                recent: false,
+            });
+        }
+        rows
+    }
+
+    fn recent_row(&self, item: &RecentItem) -> RecentItemRow {
+        RecentItemRow {
+            kind: item.kind,
+            id: item.id,
+            title: item.title.clone(),
+            kind_label: entity_label(item.kind),
+            detail: format_relative(&item.opened_at, &self.now),
+            recent: true,
+        }
+    }
+}
+
+/// 重新打开已删除记录时的提示
+pub fn deleted_entry_message() -> UserMessage {
+    UserMessage {
+        kind: MessageKind::NotFound,
+        title: "记录不存在".to_string(),
+        detail: DELETED_ENTRY_MESSAGE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use minicrm_core::SearchField;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-07-01T09:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+            + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn test_mru_dedup_and_ordering() {
+        let mut recent = RecentItems::new(3);
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let c = Uuid::new_v4();
+        recent.record(EntityKind::Customer, a, "华美装饰", at(0));
+        recent.record(EntityKind::Quote, b, "BJ20240701-001", at(1));
+        recent.record(EntityKind::Customer, a, "华美装饰有限公司", at(2));
+        recent.record(EntityKind::Task, c, "回访", at(3));
+
+        let ids: Vec<Uuid> = recent.items().iter().map(|item| item.id).collect();
+        assert_eq!(ids, vec![a, b]);
+        assert_eq!(recent.items()[0].title, "华美装饰有限公司");
+
+        // 超出上限时丢弃最早访问的
+        recent.record(EntityKind::Supplier, c, "鑫达板材", at(4));
+        recent.record(EntityKind::ServiceTicket, Uuid::new_v4(), "SH-001", at(5));
+        assert_eq!(recent.items().len(), 3);
+        assert!(recent.items().iter().all(|item| item.id != b));
+
+        assert!(recent.touch(EntityKind::Supplier, c, at(6)));
+        assert_eq!(recent.items()[0].id, c);
+        assert!(!recent.touch(EntityKind::Quote, b, at(7)));
+    }
+
+    #[test]
+    fn test_persisted_keeps_top_per_kind() {
+        let mut recent = RecentItems::default();
+        for minute in 0..12 {
+            recent.record(EntityKind::Customer, Uuid::new_v4(), "客户", at(minute));
+        }
+        let supplier = Uuid::new_v4();
+        recent.record(EntityKind::Supplier, supplier, "供应商", at(20));
+
+        let saved = recent.persisted();
+        assert_eq!(saved.len(), RECENT_ITEMS_PER_KIND + 1);
+        assert_eq!(saved[0].id, supplier);
+
+        let restored = RecentItems::restore(saved.clone());
+        assert_eq!(restored.items(), saved.as_slice());
+    }
+
+    #[test]
+    fn test_switcher_merges_recents_with_search() {
+        let customer = Uuid::new_v4();
+        let quote = Uuid::new_v4();
+        let mut recent = RecentItems::default();
+        recent.record(EntityKind::Customer, customer, "华美装饰", at(0));
+        recent.record(EntityKind::Quote, quote, "BJ20240701-001", at(1));
+        let vm = RecentItemsViewModel::new(&recent, at(10));
+
+        assert_eq!(vm.dropdown_rows()[0].detail, "今天");
+        assert_eq!(vm.filter("").len(), 2);
+        assert_eq!(vm.filter("华装").len(), 1);
+        assert!(vm.filter("装华").is_empty());
+        assert!(!RecentItemsViewModel::needs_search("  "));
+
+        let other = Uuid::new_v4();
+        let hit = |id: Uuid, title: &str| SearchHit {
+            entity: EntityKind::Customer,
+            id,
+            title: title.to_string(),
+            subtitle: Some("13800000000".to_string()),
+            matched_fields: vec![SearchField::Name],
+            score: None,
+        };
+        let rows = vm.switcher_rows("华美", &[hit(customer, "华美装饰"), hit(other, "华美建材")]);
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].recent);
+        assert_eq!(rows[0].id, customer);
+        assert!(!rows[1].recent);
+        assert_eq!(rows[1].id, other);
+        assert_eq!(rows[1].kind_label, "客户");
+    }
+
+    #[test]
+    fn test_quick_switcher_shortcut_override() {
+        let mut overrides = BTreeMap::new();
+        assert_eq!(quick_switcher_shortcut(&overrides), "Ctrl+E");
+        overrides.insert(
+            QUICK_SWITCHER_SHORTCUT_ACTION.to_string(),
+            "Ctrl+K".to_string(),
+        );
+        assert_eq!(quick_switcher_shortcut(&overrides), "Ctrl+K");
+    }
+}
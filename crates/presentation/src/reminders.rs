@@ -0,0 +1,451 @@
+//! 提醒列表模块
+//!
+//! 关闭和推迟提醒时先更新界面再保存：点击后行立即消失（关闭）或标注推迟时间（推迟），
+//! 保存在后台进行；失败时按原位置恢复并显示错误提示。仪表盘的提醒数按界面上的状态计算，
+//! 保存期间不会先减少再变回去。
+//!
+//! 列表保存服务端确认过的提醒，界面显示的行由确认状态叠加尚未完成的操作得出，
+//! 失败时只需丢弃该操作，行自然回到原来的位置。同一条提醒的操作按点击顺序逐个保存：
+//! 前一个完成前后续点击只排队（界面上立即生效），前一个失败时排队的操作一并丢弃。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use minicrm_core::{CoreResult, Reminder, ReminderService};
+use uuid::Uuid;
+
+use crate::errors::UserMessage;
+use crate::formatting::{format_date, DateStyle};
+use crate::navigation::Route;
+
+/// 对提醒的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReminderOp {
+    /// 在该时刻关闭
+    Dismiss(DateTime<Utc>),
+    /// 推迟到该时刻
+    Snooze(DateTime<Utc>),
+}
+
+impl ReminderOp {
+    /// 应用到提醒上，关闭时返回 `None`
+    fn apply(self, mut reminder: Reminder) -> Option<Reminder> {
+        match self {
+            ReminderOp::Dismiss(_) => None,
+            ReminderOp::Snooze(until) => {
+                reminder.snoozed_until = Some(until);
+                Some(reminder)
+            }
+        }
+    }
+
+    fn failure_title(self) -> &'static str {
+        match self {
+            ReminderOp::Dismiss(_) => "提醒未能关闭，已恢复",
+            ReminderOp::Snooze(_) => "提醒未能推迟，已恢复",
+        }
+    }
+}
+
+/// 待保存的操作，由调用方交给 [`ReminderService`] 执行后传回 [`ReminderListModel::complete`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReminderTicket {
+    /// 提醒ID
+    pub id: Uuid,
+    /// 操作
+    pub op: ReminderOp,
+}
+
+impl ReminderTicket {
+    /// 调用服务保存操作
+    ///
+    /// # Errors
+    ///
+    /// 保存失败时返回错误。
+    pub async fn dispatch(self, service: &(dyn ReminderService + Send + Sync)) -> CoreResult<bool> {
+        match self.op {
+            ReminderOp::Dismiss(at) => service.dismiss_reminder(self.id, at).await,
+            ReminderOp::Snooze(until) => service.snooze_reminder(self.id, until).await,
+        }
+    }
+}
+
+/// 提醒列表中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReminderRow {
+    /// 提醒ID
+    pub id: Uuid,
+    /// 标题
+    pub title: String,
+    /// 分类，如“任务”“客户维护”
+    pub category: &'static str,
+    /// 提醒时间
+    pub due: String,
+    /// 推迟标注，如“已推迟到 2024-06-01 10:30”
+    pub snoozed: Option<String>,
+    /// 是否有尚未保存完成的操作
+    pub saving: bool,
+    /// 点击后跳转的页面
+    pub route: Option<Route>,
+}
+
+/// 提醒列表模型
+#[derive(Debug, Clone, Default)]
+pub struct ReminderListModel {
+    confirmed: Vec<Reminder>,
+    pending: HashMap<Uuid, VecDeque<ReminderOp>>,
+}
+
+impl ReminderListModel {
+    /// 以读取到的提醒创建
+    pub fn new(reminders: Vec<Reminder>) -> Self {
+        Self {
+            confirmed: reminders,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 重新读取后替换确认状态，尚未完成的操作继续叠加在新列表上
+    pub fn replace(&mut self, reminders: Vec<Reminder>) {
+        self.confirmed = reminders;
+        let confirmed = &self.confirmed;
+        // 已不在列表中的提醒不再显示，但进行中的保存仍需等待完成
+        self.pending
+            .retain(|id, ops| !ops.is_empty() || confirmed.iter().any(|r| r.id == *id));
+    }
+
+    /// 关闭提醒，返回需要立即保存的操作（前一个操作未完成时排队，返回 `None`）
+    pub fn dismiss(&mut self, id: Uuid, at: DateTime<Utc>) -> Option<ReminderTicket> {
+        self.request(id, ReminderOp::Dismiss(at))
+    }
+
+    /// 推迟提醒，返回需要立即保存的操作（前一个操作未完成时排队，返回 `None`）
+    pub fn snooze(&mut self, id: Uuid, until: DateTime<Utc>) -> Option<ReminderTicket> {
+        self.request(id, ReminderOp::Snooze(until))
+    }
+
+    fn request(&mut self, id: Uuid, op: ReminderOp) -> Option<ReminderTicket> {
+        // 已关闭（或正在关闭）的提醒不能再操作
+        self.visible(id)?;
+        let ops = self.pending.entry(id).or_default();
+        ops.push_back(op);
+        (ops.len() == 1).then_some(ReminderTicket { id, op })
+    }
+
+    /// 叠加尚未完成的操作后的提醒，已关闭时为 `None`
+    fn visible(&self, id: Uuid) -> Option<Reminder> {
+        let reminder = self.confirmed.iter().find(|r| r.id == id)?.clone();
+        self.pending
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .try_fold(reminder, |reminder, op| op.apply(reminder))
+    }
+
+    /// 保存完成：成功时确认该操作并返回同一提醒排队的下一个操作；失败时丢弃该提醒的
+    /// 全部未完成操作（行回到原位置），返回错误提示
+    ///
+    /// # Errors
+    ///
+    /// 保存失败时返回要显示的错误提示。
+    pub fn complete(
+        &mut self,
+        ticket: ReminderTicket,
+        result: &CoreResult<bool>,
+    ) -> Result<Option<ReminderTicket>, UserMessage> {
+        let index = self.confirmed.iter().position(|r| r.id == ticket.id);
+        match result {
+            Ok(applied) => {
+                if let Some(index) = index {
+                    // 提醒已被关闭（如在其他电脑上）时同样移除
+                    match (applied, ticket.op.apply(self.confirmed[index].clone())) {
+                        (true, Some(reminder)) => self.confirmed[index] = reminder,
+                        _ => {
+                            self.confirmed.remove(index);
+                        }
+                    }
+                }
+                let Some(ops) = self.pending.get_mut(&ticket.id) else {
+                    return Ok(None);
+                };
+                ops.pop_front();
+                let next = ops.front().copied();
+                if next.is_none() || !applied || index.is_none() {
+                    self.pending.remove(&ticket.id);
+                    return Ok(None);
+                }
+                Ok(next.map(|op| ReminderTicket { id: ticket.id, op }))
+            }
+            Err(e) => {
+                self.pending.remove(&ticket.id);
+                let mut message = UserMessage::from_error(e);
+                message.title = ticket.op.failure_title().to_string();
+                Err(message)
+            }
+        }
+    }
+
+    /// 是否有尚未完成的操作
+    pub fn is_saving(&self, id: Uuid) -> bool {
+        self.pending.contains_key(&id)
+    }
+
+    /// 界面显示的行（按读取时的顺序）
+    pub fn rows(&self) -> Vec<ReminderRow> {
+        self.confirmed
+            .iter()
+            .filter_map(|r| self.visible(r.id))
+            .map(|reminder| ReminderRow {
+                id: reminder.id,
+                title: reminder.title.clone(),
+                category: reminder.kind.category(),
+                due: format_date(&reminder.due_at, DateStyle::ShortWithTime),
+                snoozed: reminder.snoozed_until.map(|until| {
+                    format!("已推迟到 {}", format_date(&until, DateStyle::ShortWithTime))
+                }),
+                saving: self.is_saving(reminder.id),
+                route: Route::for_entity(reminder.entity, reminder.entity_id),
+            })
+            .collect()
+    }
+
+    /// 仪表盘的提醒数：界面上显示且未处于推迟中的提醒
+    pub fn badge_count(&self, now: DateTime<Utc>) -> usize {
+        self.confirmed
+            .iter()
+            .filter_map(|r| self.visible(r.id))
+            .filter(|reminder| !reminder.is_snoozed(now))
+            .count()
+    }
+}
+
+/// 在后台依次保存一条提醒的操作（含排队的后续操作），失败时返回错误提示
+///
+/// 界面点击时调用 [`ReminderListModel::dismiss`] 或 [`ReminderListModel::snooze`]，
+/// 返回操作时用本函数在后台任务中保存；保存期间不持有列表的锁。
+pub async fn save_reminder_ops(
+    model: &Mutex<ReminderListModel>,
+    service: Arc<dyn ReminderService + Send + Sync>,
+    ticket: ReminderTicket,
+) -> Option<UserMessage> {
+    let mut ticket = ticket;
+    loop {
+        let result = ticket.dispatch(service.as_ref()).await;
+        let completed = model
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .complete(ticket, &result);
+        match completed {
+            Ok(Some(next)) => ticket = next,
+            Ok(None) => return None,
+            Err(message) => return Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::{CoreError, EntityKind, ReminderKind};
+    use tokio::sync::Semaphore;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, hour, 0, 0).unwrap()
+    }
+
+    fn reminder(title: &str, hour: u32) -> Reminder {
+        Reminder {
+            id: Uuid::new_v4(),
+            kind: ReminderKind::TaskAssigned,
+            entity: EntityKind::Task,
+            entity_id: Uuid::new_v4(),
+            title: title.to_string(),
+            due_at: at(hour),
+            created_at: at(hour),
+            dismissed_at: None,
+            recipient: None,
+            snoozed_until: None,
+        }
+    }
+
+    fn titles(model: &ReminderListModel) -> Vec<String> {
+        model.rows().into_iter().map(|row| row.title).collect()
+    }
+
+    /// 每次调用先等待放行；`fail` 中的提醒保存失败
+    struct SlowReminders {
+        gate: Semaphore,
+        fail: Vec<Uuid>,
+        calls: Mutex<Vec<(Uuid, ReminderOp)>>,
+    }
+
+    impl SlowReminders {
+        fn new(fail: Vec<Uuid>) -> Self {
+            Self {
+                gate: Semaphore::new(0),
+                fail,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        async fn call(&self, id: Uuid, op: ReminderOp) -> CoreResult<bool> {
+            self.gate.acquire().await.unwrap().forget();
+            self.calls.lock().unwrap().push((id, op));
+            if self.fail.contains(&id) {
+                Err(CoreError::Other("数据库已锁定".to_string()))
+            } else {
+                Ok(true)
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ReminderService for SlowReminders {
+        async fn active_reminders(&self, _user_id: Uuid) -> CoreResult<Vec<Reminder>> {
+            Ok(Vec::new())
+        }
+
+        async fn dismiss_reminder(&self, id: Uuid, at: DateTime<Utc>) -> CoreResult<bool> {
+            self.call(id, ReminderOp::Dismiss(at)).await
+        }
+
+        async fn snooze_reminder(&self, id: Uuid, until: DateTime<Utc>) -> CoreResult<bool> {
+            self.call(id, ReminderOp::Snooze(until)).await
+        }
+    }
+
+    #[test]
+    fn test_failed_dismiss_reinserts_at_original_position() {
+        let (a, b, c) = (reminder("A", 8), reminder("B", 9), reminder("C", 10));
+        let mut model = ReminderListModel::new(vec![a.clone(), b.clone(), c.clone()]);
+        let now = at(12);
+
+        // 连续关闭 B 和 A：两行立即消失，提醒数随之减少
+        let dismiss_b = model.dismiss(b.id, now).unwrap();
+        let dismiss_a = model.dismiss(a.id, now).unwrap();
+        assert_eq!(titles(&model), vec!["C"]);
+        assert_eq!(model.badge_count(now), 1);
+
+        // A 先保存成功，B 后失败：B 回到 C 之前，A 不再出现
+        assert_eq!(model.complete(dismiss_a, &Ok(true)), Ok(None));
+        assert_eq!(model.badge_count(now), 1);
+        let toast = model
+            .complete(dismiss_b, &Err(CoreError::Other("磁盘已满".to_string())))
+            .unwrap_err();
+        assert_eq!(toast.title, "提醒未能关闭，已恢复");
+        assert_eq!(titles(&model), vec!["B", "C"]);
+        assert_eq!(model.badge_count(now), 2);
+        assert!(!model.is_saving(b.id));
+
+        // 保存期间重新读取，进行中的推迟仍然生效，提醒数不回退
+        let snooze = model.snooze(c.id, at(14)).unwrap();
+        assert_eq!(model.badge_count(now), 1);
+        model.replace(vec![b.clone(), c.clone()]);
+        assert_eq!(model.badge_count(now), 1);
+        let row = &model.rows()[1];
+        assert_eq!(row.snoozed.as_deref(), Some("已推迟到 2024-06-01 14:00"));
+        assert!(row.saving);
+        assert_eq!(model.complete(snooze, &Ok(true)), Ok(None));
+        assert_eq!(model.badge_count(now), 1);
+        assert_eq!(model.badge_count(at(14)), 2);
+        assert!(!model.rows()[1].saving);
+    }
+
+    #[test]
+    fn test_repeated_clicks_are_serialized() {
+        let a = reminder("A", 8);
+        let mut model = ReminderListModel::new(vec![a.clone()]);
+        let now = at(12);
+
+        let first = model.snooze(a.id, at(13)).unwrap();
+        // 前一个未完成时后续点击只排队，界面按最后一次点击显示
+        assert_eq!(model.snooze(a.id, at(15)), None);
+        assert_eq!(
+            model.rows()[0].snoozed.as_deref(),
+            Some("已推迟到 2024-06-01 15:00")
+        );
+        assert_eq!(model.dismiss(a.id, now), None);
+        assert!(model.rows().is_empty());
+        // 已在关闭中的提醒不再接受操作
+        assert_eq!(model.snooze(a.id, at(16)), None);
+
+        let second = model.complete(first, &Ok(true)).unwrap().unwrap();
+        assert_eq!(second.op, ReminderOp::Snooze(at(15)));
+        let third = model.complete(second, &Ok(true)).unwrap().unwrap();
+        assert_eq!(third.op, ReminderOp::Dismiss(now));
+        assert_eq!(model.complete(third, &Ok(true)), Ok(None));
+        assert!(model.rows().is_empty());
+        assert_eq!(model.badge_count(now), 0);
+
+        // 失败时排队的操作一并丢弃，恢复到最后确认的状态
+        let b = reminder("B", 9);
+        let mut model = ReminderListModel::new(vec![b.clone()]);
+        let first = model.snooze(b.id, at(13)).unwrap();
+        assert_eq!(model.dismiss(b.id, now), None);
+        assert!(model
+            .complete(first, &Err(CoreError::Other("超时".to_string())))
+            .is_err());
+        assert_eq!(titles(&model), vec!["B"]);
+        assert_eq!(model.rows()[0].snoozed, None);
+        assert!(model.dismiss(b.id, now).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_slow_background_saves() {
+        let (a, b, c) = (reminder("A", 8), reminder("B", 9), reminder("C", 10));
+        let now = at(12);
+        let service = Arc::new(SlowReminders::new(vec![b.id]));
+        let model = Arc::new(Mutex::new(ReminderListModel::new(vec![
+            a.clone(),
+            b.clone(),
+            c.clone(),
+        ])));
+
+        let (ticket_a, ticket_b, ticket_c) = {
+            let mut m = model.lock().unwrap();
+            let ticket_a = m.dismiss(a.id, now).unwrap();
+            let ticket_b = m.dismiss(b.id, now).unwrap();
+            let ticket_c = m.snooze(c.id, now + Duration::hours(1)).unwrap();
+            // 重复点击不产生新的保存
+            assert!(m.snooze(c.id, now + Duration::hours(2)).is_none());
+            (ticket_a, ticket_b, ticket_c)
+        };
+        let spawn = |ticket| {
+            let (model, service) = (model.clone(), service.clone());
+            tokio::spawn(async move { save_reminder_ops(&model, service, ticket).await })
+        };
+        let (task_a, task_b, task_c) = (spawn(ticket_a), spawn(ticket_b), spawn(ticket_c));
+        tokio::task::yield_now().await;
+
+        // 服务尚未返回，界面已是操作后的状态
+        assert_eq!(titles(&model.lock().unwrap()), vec!["C"]);
+        assert_eq!(model.lock().unwrap().badge_count(now), 0);
+
+        service.gate.add_permits(4);
+        assert_eq!(task_a.await.unwrap(), None);
+        let toast = task_b.await.unwrap().unwrap();
+        assert_eq!(toast.title, "提醒未能关闭，已恢复");
+        assert_eq!(task_c.await.unwrap(), None);
+
+        let model = model.lock().unwrap();
+        assert_eq!(titles(&model), vec!["B", "C"]);
+        assert_eq!(model.badge_count(now), 1);
+        assert_eq!(
+            model.rows()[1].snoozed.as_deref(),
+            Some("已推迟到 2024-06-01 14:00")
+        );
+        // 同一提醒的两次推迟按点击顺序保存
+        let calls = service.calls.lock().unwrap();
+        let c_calls: Vec<_> = calls.iter().filter(|(id, _)| *id == c.id).collect();
+        assert_eq!(
+            c_calls,
+            vec![
+                &(c.id, ReminderOp::Snooze(now + Duration::hours(1))),
+                &(c.id, ReminderOp::Snooze(now + Duration::hours(2)))
+            ]
+        );
+    }
+}
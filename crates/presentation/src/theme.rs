@@ -0,0 +1,354 @@
+//! 主题模块
+//!
+//! 主题管理器保存当前主题（默认、深色、高对比度）和字号缩放，给出界面 `Theme` 全局使用的
+//! 颜色、焦点样式和字号。设置界面的外观面板先预览，点击“应用”后才写回配置。
+
+use minicrm_core::BadgeTone;
+use serde::{Deserialize, Serialize};
+
+/// 最小字号缩放
+pub const MIN_FONT_SCALE: f32 = 0.8;
+/// 最大字号缩放
+pub const MAX_FONT_SCALE: f32 = 2.0;
+/// 默认字号缩放
+pub const DEFAULT_FONT_SCALE: f32 = 1.0;
+
+/// 把字号缩放限制在允许范围内，无效值按默认值处理
+pub fn clamp_font_scale(scale: f32) -> f32 {
+    if scale.is_finite() {
+        scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE)
+    } else {
+        DEFAULT_FONT_SCALE
+    }
+}
+
+/// 主题
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeVariant {
+    /// 默认主题
+    #[default]
+    Default,
+    /// 深色主题
+    Dark,
+    /// 高对比度主题
+    HighContrast,
+}
+
+impl ThemeVariant {
+    /// 全部主题（设置界面的显示顺序）
+    pub const ALL: [ThemeVariant; 3] = [Self::Default, Self::Dark, Self::HighContrast];
+
+    /// 配置文件中的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Dark => "dark",
+            Self::HighContrast => "high_contrast",
+        }
+    }
+
+    /// 从配置名称解析，无法识别时使用默认主题
+    pub fn parse(name: &str) -> Self {
+        match name.trim() {
+            "dark" => Self::Dark,
+            "high_contrast" => Self::HighContrast,
+            _ => Self::Default,
+        }
+    }
+
+    /// 显示名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Default => "默认",
+            Self::Dark => "深色",
+            Self::HighContrast => "高对比度",
+        }
+    }
+}
+
+/// 主题颜色和焦点样式
+///
+/// 颜色为 `0xRRGGBB`。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThemeTokens {
+    /// 正文
+    pub text: u32,
+    /// 次要文字
+    pub text_muted: u32,
+    /// 内容区背景
+    pub background: u32,
+    /// 卡片、对话框背景
+    pub surface: u32,
+    /// 状态栏等弱化背景
+    pub surface_muted: u32,
+    /// 边框
+    pub border: u32,
+    /// 强调色
+    pub accent: u32,
+    /// 错误
+    pub danger: u32,
+    /// 成功
+    pub success: u32,
+    /// 焦点框颜色
+    pub focus_ring: u32,
+    /// 焦点框宽度（像素）
+    pub focus_ring_width: f32,
+}
+
+impl ThemeTokens {
+    /// 默认主题
+    pub const DEFAULT: ThemeTokens = ThemeTokens {
+        text: 0x49_50_57,
+        text_muted: 0x6c_75_7d,
+        background: 0xf8_f9_fa,
+        surface: 0xff_ff_ff,
+        surface_muted: 0xe9_ec_ef,
+        border: 0xde_e2_e6,
+        accent: 0x00_7b_ff,
+        danger: 0xdc_35_45,
+        success: 0x19_87_54,
+        focus_ring: 0x0d_6e_fd,
+        focus_ring_width: 2.0,
+    };
+
+    /// 深色主题
+    pub const DARK: ThemeTokens = ThemeTokens {
+        text: 0xe9_ec_ef,
+        text_muted: 0xad_b5_bd,
+        background: 0x21_25_29,
+        surface: 0x2b_30_35,
+        surface_muted: 0x34_3a_40,
+        border: 0x49_50_57,
+        accent: 0x6e_a8_fe,
+        danger: 0xea_86_8f,
+        success: 0x75_b7_98,
+        focus_ring: 0x6e_a8_fe,
+        focus_ring_width: 2.0,
+    };
+
+    /// 高对比度主题：黑字白底、黑色边框，焦点框加粗
+    pub const HIGH_CONTRAST: ThemeTokens = ThemeTokens {
+        text: 0x00_00_00,
+        text_muted: 0x1a_1a_1a,
+        background: 0xff_ff_ff,
+        surface: 0xff_ff_ff,
+        surface_muted: 0xff_ff_ff,
+        border: 0x00_00_00,
+        accent: 0x00_33_cc,
+        danger: 0xa1_00_00,
+        success: 0x00_5a_00,
+        focus_ring: 0xff_8c_00,
+        focus_ring_width: 3.0,
+    };
+
+    /// 徽标颜色（状态、等级等徽标按色调取主题中的颜色）
+    pub fn badge_color(&self, tone: BadgeTone) -> u32 {
+        match tone {
+            BadgeTone::Neutral => self.text_muted,
+            BadgeTone::Info => self.accent,
+            BadgeTone::Success => self.success,
+            BadgeTone::Danger => self.danger,
+        }
+    }
+}
+
+/// 主题管理器
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThemeManager {
+    variant: ThemeVariant,
+    font_scale: f32,
+}
+
+impl Default for ThemeManager {
+    fn default() -> Self {
+        Self {
+            variant: ThemeVariant::Default,
+            font_scale: DEFAULT_FONT_SCALE,
+        }
+    }
+}
+
+impl ThemeManager {
+    /// 创建主题管理器，字号缩放超出范围时取边界值
+    pub fn new(variant: ThemeVariant, font_scale: f32) -> Self {
+        Self {
+            variant,
+            font_scale: clamp_font_scale(font_scale),
+        }
+    }
+
+    /// 当前主题
+    pub fn variant(&self) -> ThemeVariant {
+        self.variant
+    }
+
+    /// 切换主题
+    pub fn set_variant(&mut self, variant: ThemeVariant) {
+        self.variant = variant;
+    }
+
+    /// 是否为高对比度主题
+    pub fn is_high_contrast(&self) -> bool {
+        self.variant == ThemeVariant::HighContrast
+    }
+
+    /// 当前字号缩放
+    pub fn font_scale(&self) -> f32 {
+        self.font_scale
+    }
+
+    /// 设置字号缩放，返回限制到允许范围后的值
+    pub fn set_font_scale(&mut self, scale: f32) -> f32 {
+        self.font_scale = clamp_font_scale(scale);
+        self.font_scale
+    }
+
+    /// 字号缩放文本，如“125%”
+    pub fn font_scale_label(&self) -> String {
+        format!("{:.0}%", self.font_scale * 100.0)
+    }
+
+    /// 按缩放计算字号（像素）
+    pub fn font_size(&self, base_px: f32) -> f32 {
+        base_px * self.font_scale
+    }
+
+    /// 当前主题的颜色和焦点样式
+    pub fn tokens(&self) -> ThemeTokens {
+        match self.variant {
+            ThemeVariant::Default => ThemeTokens::DEFAULT,
+            ThemeVariant::Dark => ThemeTokens::DARK,
+            ThemeVariant::HighContrast => ThemeTokens::HIGH_CONTRAST,
+        }
+    }
+}
+
+/// 外观设置面板视图模型
+///
+/// 拖动滑块或勾选高对比度只改变预览；“应用”后由调用方把 [`Self::saved`] 写回配置，
+/// “还原”回到上次保存的设置。
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppearanceSettings {
+    saved: ThemeManager,
+    /// 预览中的主题
+    pub preview: ThemeManager,
+    /// 开启高对比度之前的主题（关闭时恢复）
+    previous_variant: ThemeVariant,
+}
+
+impl AppearanceSettings {
+    /// 以当前生效的主题打开面板
+    pub fn new(current: ThemeManager) -> Self {
+        Self {
+            saved: current,
+            preview: current,
+            previous_variant: Self::fallback_variant(current.variant()),
+        }
+    }
+
+    fn fallback_variant(variant: ThemeVariant) -> ThemeVariant {
+        if variant == ThemeVariant::HighContrast {
+            ThemeVariant::Default
+        } else {
+            variant
+        }
+    }
+
+    /// 已保存的设置
+    pub fn saved(&self) -> ThemeManager {
+        self.saved
+    }
+
+    /// 预览字号缩放，返回限制后的值
+    pub fn preview_font_scale(&mut self, scale: f32) -> f32 {
+        self.preview.set_font_scale(scale)
+    }
+
+    /// 预览开启或关闭高对比度
+    pub fn set_high_contrast(&mut self, enabled: bool) {
+        if enabled {
+            if !self.preview.is_high_contrast() {
+                self.previous_variant = self.preview.variant();
+            }
+            self.preview.set_variant(ThemeVariant::HighContrast);
+        } else if self.preview.is_high_contrast() {
+            self.preview.set_variant(self.previous_variant);
+        }
+    }
+
+    /// 预览与已保存的设置不同
+    pub fn is_dirty(&self) -> bool {
+        self.preview != self.saved
+    }
+
+    /// 应用预览，返回需要保存的设置
+    pub fn apply(&mut self) -> ThemeManager {
+        self.saved = self.preview;
+        self.saved
+    }
+
+    /// 放弃预览，返回恢复后的设置
+    pub fn revert(&mut self) -> ThemeManager {
+        self.preview = self.saved;
+        self.previous_variant = Self::fallback_variant(self.saved.variant());
+        self.preview
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_scale_is_clamped() {
+        let mut theme = ThemeManager::new(ThemeVariant::Default, 3.5);
+        assert!((theme.font_scale() - MAX_FONT_SCALE).abs() < f32::EPSILON);
+        assert!((theme.set_font_scale(0.5) - MIN_FONT_SCALE).abs() < f32::EPSILON);
+        assert!((theme.set_font_scale(f32::NAN) - DEFAULT_FONT_SCALE).abs() < f32::EPSILON);
+        theme.set_font_scale(1.25);
+        assert_eq!(theme.font_scale_label(), "125%");
+        assert!((theme.font_size(16.0) - 20.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_high_contrast_tokens() {
+        assert_eq!(ThemeVariant::parse("high_contrast"), ThemeVariant::HighContrast);
+        assert_eq!(ThemeVariant::parse("unknown"), ThemeVariant::Default);
+        assert_eq!(ThemeVariant::HighContrast.label(), "高对比度");
+
+        let theme = ThemeManager::new(ThemeVariant::HighContrast, 1.0);
+        assert!(theme.is_high_contrast());
+        let tokens = theme.tokens();
+        assert_eq!(tokens, ThemeTokens::HIGH_CONTRAST);
+        assert_eq!((tokens.text, tokens.surface), (0x00_00_00, 0xff_ff_ff));
+        assert!(tokens.focus_ring_width > ThemeTokens::DEFAULT.focus_ring_width);
+        assert_ne!(ThemeManager::default().tokens(), tokens);
+        assert_eq!(tokens.badge_color(BadgeTone::Danger), tokens.danger);
+    }
+
+    #[test]
+    fn test_preview_apply_and_revert() {
+        let mut settings = AppearanceSettings::new(ThemeManager::new(ThemeVariant::Dark, 1.0));
+        assert!(!settings.is_dirty());
+
+        assert!((settings.preview_font_scale(1.5) - 1.5).abs() < f32::EPSILON);
+        settings.set_high_contrast(true);
+        assert!(settings.is_dirty());
+        assert!(settings.preview.is_high_contrast());
+
+        // 关闭高对比度时回到原来的主题
+        settings.set_high_contrast(false);
+        assert_eq!(settings.preview.variant(), ThemeVariant::Dark);
+
+        let reverted = settings.revert();
+        assert!((reverted.font_scale() - 1.0).abs() < f32::EPSILON);
+        assert!(!settings.is_dirty());
+
+        settings.preview_font_scale(1.25);
+        let applied = settings.apply();
+        assert!((applied.font_scale() - 1.25).abs() < f32::EPSILON);
+        assert_eq!(settings.saved(), applied);
+        assert!(!settings.is_dirty());
+    }
+}
@@ -0,0 +1,310 @@
+//! 售后工单编辑器模块
+//!
+//! 编辑器侧栏列出知识库推荐的文章，点击“采用”后由 `ApplyKnowledgeArticleCommand`
+//! 把处理方法填入工单。关闭工单时如果处理方法与推荐文章都不相同，提示
+//! “保存为知识库文章”，草稿由工单内容预填。
+//!
+//! 工单列表的每一行带有久未更新的程度（[`StalenessEvaluator`]），界面据此显示徽标。
+
+use chrono::{DateTime, Utc};
+use minicrm_core::{
+    BadgeTone, DisplayName, Freshness, KnowledgeArticle, ServiceTicket, ServiceTicketStatus,
+    StalenessEvaluator, StalenessKind,
+};
+use uuid::Uuid;
+
+use crate::formatting::display_name;
+
+/// 保存为知识库文章的按钮文字
+pub const SAVE_AS_ARTICLE_LABEL: &str = "保存为知识库文章";
+
+/// 推荐列表中处理方法预览的最大字数
+const SOLUTION_PREVIEW_CHARS: usize = 40;
+
+/// 推荐文章列表中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArticleSuggestionRow {
+    /// 文章ID
+    pub id: Uuid,
+    /// 标题
+    pub title: String,
+    /// 问题分类
+    pub problem_category: String,
+    /// 处理方法预览（第一行，过长时截断）
+    pub solution_preview: String,
+    /// 采用次数文本，如“已采用3次”
+    pub usage_text: String,
+    /// 是否已被本工单采用
+    pub applied: bool,
+}
+
+impl ArticleSuggestionRow {
+    fn from_article(article: &KnowledgeArticle, applied: bool) -> Self {
+        let first_line = article.solution.lines().next().unwrap_or_default().trim();
+        let mut solution_preview: String =
+            first_line.chars().take(SOLUTION_PREVIEW_CHARS).collect();
+        if first_line.chars().count() > SOLUTION_PREVIEW_CHARS {
+            solution_preview.push('…');
+        }
+        Self {
+            id: article.id,
+            title: article.title.clone(),
+            problem_category: article.problem_category.clone(),
+            solution_preview,
+            usage_text: if article.usage_count == 0 {
+                "尚未采用".to_string()
+            } else {
+                format!("已采用{}次", article.usage_count)
+            },
+            applied,
+        }
+    }
+}
+
+/// 工单列表中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TicketListRow {
+    /// 工单ID
+    pub id: Uuid,
+    /// 工单编号
+    pub ticket_number: String,
+    /// 问题分类
+    pub problem_category: String,
+    /// 状态文字
+    pub status: &'static str,
+    /// 久未更新的程度
+    pub freshness: Freshness,
+}
+
+impl TicketListRow {
+    /// 由工单创建一行，久未更新的程度按 `staleness` 计算
+    pub fn new(ticket: &ServiceTicket, staleness: &StalenessEvaluator, now: DateTime<Utc>) -> Self {
+        Self {
+            id: ticket.id,
+            ticket_number: ticket.ticket_number.clone(),
+            problem_category: ticket.problem_category.clone(),
+            status: display_name(&ticket.status),
+            freshness: staleness.ticket(ticket, now),
+        }
+    }
+
+    /// 提示文字，如“工单已 7 天未更新”（无需提示时为空）
+    pub fn freshness_text(&self) -> Option<String> {
+        self.freshness.message(StalenessKind::TicketNoUpdate)
+    }
+
+    /// 徽标色调
+    pub fn freshness_badge(&self) -> BadgeTone {
+        self.freshness.badge()
+    }
+}
+
+/// 售后工单编辑器视图模型
+#[derive(Debug, Clone)]
+pub struct TicketEditorViewModel {
+    /// 正在编辑的工单
+    pub ticket: ServiceTicket,
+    /// 推荐文章列表
+    pub suggestions: Vec<ArticleSuggestionRow>,
+    articles: Vec<KnowledgeArticle>,
+    applied_article: Option<Uuid>,
+}
+
+impl TicketEditorViewModel {
+    /// 打开工单，推荐文章由 `TicketSuggestionsQuery` 查询后调用 [`Self::set_suggestions`]
+    pub fn new(ticket: ServiceTicket) -> Self {
+        Self {
+            ticket,
+            suggestions: Vec::new(),
+            articles: Vec::new(),
+            applied_article: None,
+        }
+    }
+
+    /// 设置推荐文章
+    pub fn set_suggestions(&mut self, articles: Vec<KnowledgeArticle>) {
+        self.articles = articles;
+        self.refresh_rows();
+    }
+
+    /// 采用文章的命令成功后更新工单，并在本地累计该文章的采用次数
+    pub fn article_applied(&mut self, ticket: ServiceTicket, article_id: Uuid) {
+        self.ticket = ticket;
+        if let Some(article) = self.articles.iter_mut().find(|a| a.id == article_id) {
+            article.usage_count += 1;
+        }
+        self.applied_article = Some(article_id);
+        self.refresh_rows();
+    }
+
+    /// 工单状态文字
+    pub fn status_text(&self) -> &'static str {
+        display_name(&self.ticket.status)
+    }
+
+    /// 工单状态徽标色调
+    pub fn status_badge(&self) -> BadgeTone {
+        self.ticket.status.badge()
+    }
+
+    /// 本工单采用的文章
+    pub fn applied_article(&self) -> Option<&KnowledgeArticle> {
+        let id = self.applied_article?;
+        self.articles.iter().find(|a| a.id == id)
+    }
+
+    /// 是否提示“保存为知识库文章”
+    ///
+    /// 工单已关闭、填写了处理方法，且处理方法与推荐的文章都不相同（忽略首尾空白）。
+    pub fn offers_save_as_article(&self) -> bool {
+        let solution = self
+            .ticket
+            .solution_method
+            .as_deref()
+            .unwrap_or_default()
+            .trim();
+        self.ticket.status == ServiceTicketStatus::Closed
+            && !solution.is_empty()
+            && self.articles.iter().all(|a| a.solution.trim() != solution)
+    }
+
+    /// 由工单预填的文章草稿，不提示保存时为空
+    pub fn article_draft(&self, created_by: Option<String>) -> Option<KnowledgeArticle> {
+        self.offers_save_as_article()
+            .then(|| KnowledgeArticle::from_ticket(&self.ticket, created_by))
+    }
+
+    fn refresh_rows(&mut self) {
+        self.suggestions = self
+            .articles
+            .iter()
+            .map(|a| ArticleSuggestionRow::from_article(a, self.applied_article == Some(a.id)))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use minicrm_core::{
+        BusinessCalendar, StalenessRule, StalenessThresholds, TaskPriority, ARTICLE_TITLE_MAX_CHARS,
+    };
+
+    fn ticket(description: &str) -> ServiceTicket {
+        ServiceTicket {
+            id: Uuid::new_v4(),
+            ticket_number: "SV20240603001".to_string(),
+            customer_id: Uuid::new_v4(),
+            problem_category: "开裂".to_string(),
+            description: description.to_string(),
+            solution_method: None,
+            status: ServiceTicketStatus::InProgress,
+            priority: TaskPriority::Medium,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn article(solution: &str, usage_count: u64) -> KnowledgeArticle {
+        KnowledgeArticle {
+            usage_count,
+            solution: solution.to_string(),
+            ..KnowledgeArticle::from_ticket(&ticket("台面拼缝开裂"), None)
+        }
+    }
+
+    #[test]
+    fn test_article_draft_prefilled_from_ticket() {
+        let long_line = "衣柜侧板在安装三个月后从封边处开裂，裂缝沿木纹方向延伸约二十厘米，客户要求上门";
+        let mut closed = ticket(&format!("{}\n现场照片见附件", long_line));
+        closed.status = ServiceTicketStatus::Closed;
+        closed.solution_method = Some(" 更换侧板，重新封边 ".to_string());
+
+        let draft = TicketEditorViewModel::new(closed.clone())
+            .article_draft(Some("wangwu".to_string()))
+            .unwrap();
+        assert_eq!(draft.title.chars().count(), ARTICLE_TITLE_MAX_CHARS + 1);
+        assert!(draft.title.ends_with('…'));
+        assert!(long_line.starts_with(draft.title.trim_end_matches('…')));
+        assert_eq!(draft.problem_category, "开裂");
+        assert_eq!(draft.symptoms, closed.description);
+        assert_eq!(draft.solution, "更换侧板，重新封边");
+        assert_eq!(draft.created_by.as_deref(), Some("wangwu"));
+        assert_eq!(draft.usage_count, 0);
+
+        // 描述为空时以问题分类作标题
+        let draft = KnowledgeArticle::from_ticket(&ticket(""), None);
+        assert_eq!(draft.title, "开裂");
+    }
+
+    #[test]
+    fn test_save_offer_only_for_novel_solution() {
+        let known = article("更换侧板，重新封边", 2);
+        let mut editor = TicketEditorViewModel::new(ticket("侧板开裂"));
+        editor.set_suggestions(vec![known.clone(), article("注胶修补", 0)]);
+        assert_eq!(editor.suggestions[0].usage_text, "已采用2次");
+        assert_eq!(editor.suggestions[1].usage_text, "尚未采用");
+
+        // 采用推荐文章后关闭，不提示保存
+        let mut applied = editor.ticket.clone();
+        applied.solution_method = Some(known.solution.clone());
+        editor.article_applied(applied, known.id);
+        assert!(editor.suggestions[0].applied);
+        assert_eq!(editor.suggestions[0].usage_text, "已采用3次");
+        assert_eq!(editor.applied_article().map(|a| a.id), Some(known.id));
+        assert!(!editor.offers_save_as_article());
+        assert_eq!(editor.status_text(), "处理中");
+        editor.ticket.status = ServiceTicketStatus::Closed;
+        assert_eq!(
+            (editor.status_text(), editor.status_badge()),
+            ("已关闭", BadgeTone::Success)
+        );
+        assert!(!editor.offers_save_as_article());
+        assert!(editor.article_draft(None).is_none());
+
+        // 关闭时改用了新的处理方法
+        editor.ticket.solution_method = Some("更换侧板并在背面加装加强筋".to_string());
+        assert!(editor.offers_save_as_article());
+        editor.ticket.solution_method = Some("  ".to_string());
+        assert!(!editor.offers_save_as_article());
+    }
+
+    #[test]
+    fn test_list_rows_carry_freshness() {
+        let updated = Utc.with_ymd_and_hms(2024, 6, 3, 2, 0, 0).unwrap();
+        let seeded = |status| ServiceTicket {
+            status,
+            updated_at: updated,
+            ..ticket("柜门变形")
+        };
+        let thresholds = StalenessThresholds {
+            ticket_no_update: StalenessRule {
+                aging_days: 2,
+                stale_days: 5,
+            },
+            ..StalenessThresholds::default()
+        };
+        let staleness = StalenessEvaluator::new(BusinessCalendar::default(), thresholds);
+        let row = |status, days| {
+            TicketListRow::new(&seeded(status), &staleness, updated + Duration::days(days))
+        };
+
+        let fresh = row(ServiceTicketStatus::New, 1);
+        assert_eq!(fresh.freshness, Freshness::Ok);
+        assert_eq!(fresh.freshness_text(), None);
+        assert_eq!(fresh.freshness_badge(), BadgeTone::Neutral);
+
+        let aging = row(ServiceTicketStatus::InProgress, 2);
+        assert_eq!(aging.freshness, Freshness::Aging(2));
+        assert_eq!(aging.freshness_badge(), BadgeTone::Info);
+
+        let stale = row(ServiceTicketStatus::PendingCustomerConfirmation, 7);
+        assert_eq!(stale.freshness, Freshness::Stale(7));
+        assert_eq!(stale.freshness_text().as_deref(), Some("工单已 7 天未更新"));
+        assert_eq!(stale.freshness_badge(), BadgeTone::Danger);
+
+        // 已关闭的工单不提示
+        assert_eq!(row(ServiceTicketStatus::Closed, 30).freshness, Freshness::Ok);
+    }
+}
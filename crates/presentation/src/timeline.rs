@@ -0,0 +1,267 @@
+//! 客户时间线
+//!
+//! 客户详情页的时间线合并互动和由报价、订单、收款、售后工单推导的事件，按时间倒序
+//! 分页加载。顶部的筛选按钮（全部、只看互动、只看报价、只看售后）切换后从第一页重新读取。
+
+use minicrm_core::{
+    InteractionKind, TimelineCategory, TimelineCursor, TimelineEventType, TimelineItem,
+    TimelinePage, TimelineQuery,
+};
+use uuid::Uuid;
+
+use crate::formatting::{format_date, DateStyle, UI_LOCALE};
+use crate::navigation::Route;
+
+/// 时间线筛选
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimelineFilter {
+    /// 全部
+    #[default]
+    All,
+    /// 只看互动
+    Interactions,
+    /// 只看报价
+    Quotes,
+    /// 只看售后
+    Service,
+}
+
+impl TimelineFilter {
+    /// 全部筛选（按显示顺序）
+    pub const ALL: [TimelineFilter; 4] = [
+        TimelineFilter::All,
+        TimelineFilter::Interactions,
+        TimelineFilter::Quotes,
+        TimelineFilter::Service,
+    ];
+
+    /// 按钮文本
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimelineFilter::All => "全部",
+            TimelineFilter::Interactions => "只看互动",
+            TimelineFilter::Quotes => "只看报价",
+            TimelineFilter::Service => "只看售后",
+        }
+    }
+
+    /// 查询的分类（为空表示全部）
+    pub fn categories(&self) -> Vec<TimelineCategory> {
+        match self {
+            TimelineFilter::All => Vec::new(),
+            TimelineFilter::Interactions => vec![TimelineCategory::Interactions],
+            TimelineFilter::Quotes => vec![TimelineCategory::Quotes],
+            TimelineFilter::Service => vec![TimelineCategory::Service],
+        }
+    }
+
+    /// 没有记录时的提示
+    pub fn empty_text(&self) -> &'static str {
+        match self {
+            TimelineFilter::All => "暂无记录",
+            TimelineFilter::Interactions => "暂无互动记录",
+            TimelineFilter::Quotes => "暂无报价记录",
+            TimelineFilter::Service => "暂无售后记录",
+        }
+    }
+}
+
+fn interaction_label(kind: Option<InteractionKind>) -> &'static str {
+    match kind {
+        Some(InteractionKind::Call) => "电话",
+        Some(InteractionKind::Visit) => "拜访",
+        Some(InteractionKind::Message) => "消息",
+        Some(InteractionKind::Delivery) => "送货",
+        Some(InteractionKind::Note) | None => "备注",
+    }
+}
+
+/// 时间线中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineRow {
+    /// 图标名称
+    pub icon: &'static str,
+    /// 标题（如“报价已发送 Q-2024-001”）
+    pub title: String,
+    /// 内容（互动内容、金额或问题分类）
+    pub detail: String,
+    /// 发生时间
+    pub time: String,
+    /// 点击后跳转的页面（没有对应页面时为空）
+    pub route: Option<Route>,
+}
+
+impl TimelineRow {
+    /// 由时间线记录生成
+    pub fn from_item(item: &TimelineItem) -> Self {
+        let title = match (item.event, &item.number) {
+            (TimelineEventType::Interaction, _) => {
+                interaction_label(item.interaction_kind).to_string()
+            }
+            (event, Some(number)) => format!("{} {}", event.label(), number),
+            (event, None) => event.label().to_string(),
+        };
+        let detail = match item.amount {
+            Some(amount) => UI_LOCALE.money_in(amount, item.currency),
+            None => item.content.clone().unwrap_or_default(),
+        };
+        let route = match item.event {
+            TimelineEventType::Interaction => None,
+            event => Route::for_entity(event.entity(), item.entity_id),
+        };
+        Self {
+            icon: item.icon(),
+            title,
+            detail,
+            time: format_date(&item.occurred_at, DateStyle::ShortWithTime),
+            route,
+        }
+    }
+}
+
+/// 客户时间线视图模型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomerTimelineViewModel {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 当前筛选
+    pub filter: TimelineFilter,
+    /// 已加载的行
+    pub rows: Vec<TimelineRow>,
+    next: Option<TimelineCursor>,
+    loaded: bool,
+}
+
+impl CustomerTimelineViewModel {
+    /// 创建尚未加载的时间线
+    pub fn new(customer_id: Uuid) -> Self {
+        Self {
+            customer_id,
+            filter: TimelineFilter::All,
+            rows: Vec::new(),
+            next: None,
+            loaded: false,
+        }
+    }
+
+    /// 切换筛选，筛选变化时清空已加载的行，返回是否需要重新读取
+    pub fn set_filter(&mut self, filter: TimelineFilter) -> bool {
+        if filter == self.filter {
+            return false;
+        }
+        self.filter = filter;
+        self.rows.clear();
+        self.next = None;
+        self.loaded = false;
+        true
+    }
+
+    /// 下一次要读取的页（第一页或“加载更多”），已全部加载时为空
+    pub fn next_query(&self) -> Option<TimelineQuery> {
+        let query = TimelineQuery::new(self.customer_id).with_categories(&self.filter.categories());
+        match (self.loaded, self.next) {
+            (false, _) => Some(query),
+            (true, Some(cursor)) => Some(query.after(cursor)),
+            (true, None) => None,
+        }
+    }
+
+    /// 追加读取到的一页
+    pub fn append(&mut self, page: &TimelinePage) {
+        self.rows
+            .extend(page.items.iter().map(TimelineRow::from_item));
+        self.next = page.next;
+        self.loaded = true;
+    }
+
+    /// 是否显示“加载更多”
+    pub fn has_more(&self) -> bool {
+        self.loaded && self.next.is_some()
+    }
+
+    /// 空状态提示（尚未加载或有记录时为空）
+    pub fn empty_text(&self) -> Option<&'static str> {
+        (self.loaded && self.rows.is_empty()).then(|| self.filter.empty_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use minicrm_core::{Currency, Money};
+
+    fn item(event: TimelineEventType, hour: u32) -> TimelineItem {
+        TimelineItem {
+            event,
+            id: Uuid::new_v4(),
+            occurred_at: Utc.with_ymd_and_hms(2024, 6, 1, hour, 30, 0).unwrap(),
+            entity_id: Uuid::new_v4(),
+            interaction_kind: None,
+            number: None,
+            amount: None,
+            currency: Currency::CNY,
+            content: None,
+        }
+    }
+
+    #[test]
+    fn test_rows_and_filter_paging() {
+        let customer = Uuid::new_v4();
+        let mut timeline = CustomerTimelineViewModel::new(customer);
+        let first = timeline.next_query().unwrap();
+        assert!(first.after.is_none());
+        assert!(first.categories.is_empty());
+
+        let mut quote = item(TimelineEventType::QuoteSent, 10);
+        quote.number = Some("Q-2024-001".to_string());
+        quote.amount = Some(Money::from_cents(1_200_050));
+        let mut call = item(TimelineEventType::Interaction, 9);
+        call.interaction_kind = Some(InteractionKind::Call);
+        call.content = Some("电话回访".to_string());
+        let page = TimelinePage {
+            next: Some(call.cursor()),
+            items: vec![quote.clone(), call.clone()],
+        };
+        timeline.append(&page);
+        assert_eq!(
+            timeline.rows,
+            vec![
+                TimelineRow {
+                    icon: "send",
+                    title: "报价已发送 Q-2024-001".to_string(),
+                    detail: "¥12,000.50".to_string(),
+                    time: "2024-06-01 10:30".to_string(),
+                    route: Some(Route::QuoteEditor {
+                        id: quote.entity_id
+                    }),
+                },
+                TimelineRow {
+                    icon: "phone",
+                    title: "电话".to_string(),
+                    detail: "电话回访".to_string(),
+                    time: "2024-06-01 09:30".to_string(),
+                    route: None,
+                },
+            ]
+        );
+        assert!(timeline.has_more());
+        assert_eq!(timeline.next_query().unwrap().after, Some(call.cursor()));
+
+        timeline.append(&TimelinePage::default());
+        assert!(!timeline.has_more());
+        assert_eq!(timeline.next_query(), None);
+
+        // 切换到“只看售后”后从第一页重新读取
+        assert!(timeline.set_filter(TimelineFilter::Service));
+        assert!(!timeline.set_filter(TimelineFilter::Service));
+        assert!(timeline.rows.is_empty());
+        let query = timeline.next_query().unwrap();
+        assert_eq!(query.categories, vec![TimelineCategory::Service]);
+        assert!(query.after.is_none());
+        assert_eq!(timeline.empty_text(), None);
+        timeline.append(&TimelinePage::default());
+        assert_eq!(timeline.empty_text(), Some("暂无售后记录"));
+        assert_eq!(TimelineFilter::Quotes.label(), "只看报价");
+    }
+}
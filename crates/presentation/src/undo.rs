@@ -0,0 +1,452 @@
+//! 撤销
+//!
+//! 修改客户等级或标签、删除任务备注、修改任务状态和删除客户后，操作提示带“撤销”按钮，
+//! 本次会话中也可以按撤销快捷键（默认 Ctrl+Z，可在快捷键设置中修改）撤销最近的操作。
+//! 每个操作登记一条反向命令，撤销时经同一条命令总线分发，权限、校验和审计与普通操作一致。
+//! 只保留最近 [`UNDO_STACK_LIMIT`] 条，不跨会话保存。
+//!
+//! 反向命令带有操作后的版本：记录之后又被修改、已被彻底删除时撤销失败，提示原因并丢弃该条。
+
+use std::collections::{BTreeMap, VecDeque};
+
+use minicrm_application::commands::{
+    ChangeCustomerCategoryCommand, RestoreEntityCommand, RestoreTaskNoteCommand,
+    UpdateTaskStatusCommand,
+};
+use minicrm_application::CommandBus;
+use minicrm_core::{CoreResult, Customer, DeletionBatch, Task, TaskNote, TaskStatus};
+
+use crate::errors::UserMessage;
+
+/// 快捷键设置中撤销操作的ID
+pub const UNDO_SHORTCUT_ACTION: &str = "undo";
+
+/// 默认撤销快捷键
+pub const DEFAULT_UNDO_SHORTCUT: &str = "Ctrl+Z";
+
+/// 提示中的撤销按钮文本
+pub const UNDO_LABEL: &str = "撤销";
+
+/// 每个会话保留的可撤销操作数
+pub const UNDO_STACK_LIMIT: usize = 20;
+
+/// 撤销快捷键（用户设置优先）
+pub fn undo_shortcut(overrides: &BTreeMap<String, String>) -> &str {
+    overrides
+        .get(UNDO_SHORTCUT_ACTION)
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_UNDO_SHORTCUT)
+}
+
+/// 反向命令
+#[derive(Debug, Clone)]
+pub enum InverseCommand {
+    /// 改回原来的等级和标签
+    ChangeCategory(ChangeCustomerCategoryCommand),
+    /// 恢复删除的备注
+    RestoreNote(RestoreTaskNoteCommand),
+    /// 改回原来的任务状态
+    SetTaskStatus(UpdateTaskStatusCommand),
+    /// 从回收站恢复
+    Restore(RestoreEntityCommand),
+}
+
+impl InverseCommand {
+    /// 经命令总线分发
+    pub async fn dispatch(self, bus: &CommandBus) -> CoreResult<()> {
+        match self {
+            InverseCommand::ChangeCategory(command) => bus.dispatch(command).await.map(|_| ()),
+            InverseCommand::RestoreNote(command) => bus.dispatch(command).await.map(|_| ()),
+            InverseCommand::SetTaskStatus(command) => bus.dispatch(command).await.map(|_| ()),
+            InverseCommand::Restore(command) => bus.dispatch(command).await.map(|_| ()),
+        }
+    }
+}
+
+/// 可撤销的操作
+#[derive(Debug, Clone)]
+pub struct UndoAction {
+    /// 操作名称，如“删除备注”
+    pub name: String,
+    /// 操作完成提示
+    pub done: String,
+    /// 反向命令
+    pub inverse: InverseCommand,
+}
+
+impl UndoAction {
+    /// 修改客户等级或标签：`updated` 为命令返回的客户
+    pub fn category_changed(command: &ChangeCustomerCategoryCommand, updated: &Customer) -> Self {
+        let change = &command.change;
+        let name = if change.level_changed() {
+            "修改客户等级"
+        } else {
+            "修改客户标签"
+        };
+        let mut reverted = change.clone();
+        std::mem::swap(&mut reverted.level_before, &mut reverted.level_after);
+        std::mem::swap(&mut reverted.tags_before, &mut reverted.tags_after);
+        Self {
+            name: name.to_string(),
+            done: format!("已{}：{}", name, change.name),
+            inverse: InverseCommand::ChangeCategory(ChangeCustomerCategoryCommand {
+                change: reverted,
+                expected_updated_at: Some(updated.updated_at),
+            }),
+        }
+    }
+
+    /// 删除任务备注：`deleted` 为命令返回的备注
+    pub fn note_deleted(deleted: &TaskNote) -> Self {
+        Self {
+            name: "删除备注".to_string(),
+            done: "已删除备注".to_string(),
+            inverse: InverseCommand::RestoreNote(RestoreTaskNoteCommand {
+                note: deleted.clone(),
+            }),
+        }
+    }
+
+    /// 修改任务状态：`updated` 为命令返回的任务，状态没有变化时为空
+    pub fn task_status_changed(previous: TaskStatus, updated: &Task) -> Option<Self> {
+        if previous == updated.status {
+            return None;
+        }
+        Some(Self {
+            name: "修改任务状态".to_string(),
+            done: format!("已修改任务状态：{}", updated.title),
+            inverse: InverseCommand::SetTaskStatus(UpdateTaskStatusCommand {
+                task_id: updated.id,
+                status: previous,
+                expected_updated_at: Some(updated.updated_at),
+            }),
+        })
+    }
+
+    /// 删除客户（移入回收站）：`batch` 为命令返回的删除批次
+    pub fn customer_deleted(batch: &DeletionBatch) -> Self {
+        Self {
+            name: "删除客户".to_string(),
+            done: format!("已将客户移入回收站：{}", batch.label),
+            inverse: InverseCommand::Restore(RestoreEntityCommand {
+                entity: batch.root_entity,
+                id: batch.root_id,
+            }),
+        }
+    }
+
+    /// 撤销菜单和提示中的文本，如“撤销：删除备注”
+    pub fn label(&self) -> String {
+        format!("{}：{}", UNDO_LABEL, self.name)
+    }
+}
+
+/// 已登记的可撤销操作
+#[derive(Debug, Clone)]
+struct UndoEntry {
+    id: u64,
+    action: UndoAction,
+}
+
+/// 操作完成的提示（带撤销按钮）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoToast {
+    /// 提示内容
+    pub message: UserMessage,
+    /// 撤销按钮对应的记录
+    pub entry_id: u64,
+}
+
+impl UndoToast {
+    /// 操作按钮文本
+    pub fn action_label(&self) -> &'static str {
+        UNDO_LABEL
+    }
+}
+
+/// 本次会话的撤销记录
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    entries: VecDeque<UndoEntry>,
+    next_id: u64,
+}
+
+impl UndoStack {
+    /// 创建空的撤销记录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 可撤销的操作数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 是否没有可撤销的操作
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 最近一次操作的撤销文本（撤销菜单项显示）
+    pub fn peek_label(&self) -> Option<String> {
+        self.entries.back().map(|entry| entry.action.label())
+    }
+
+    /// 登记操作，超过上限时丢弃最早的记录，返回操作完成提示
+    pub fn push(&mut self, action: UndoAction) -> UndoToast {
+        self.next_id += 1;
+        let toast = UndoToast {
+            message: UserMessage::info("操作完成", action.done.clone()),
+            entry_id: self.next_id,
+        };
+        self.entries.push_back(UndoEntry {
+            id: self.next_id,
+            action,
+        });
+        while self.entries.len() > UNDO_STACK_LIMIT {
+            self.entries.pop_front();
+        }
+        toast
+    }
+
+    /// 撤销最近一次操作（快捷键），没有可撤销的操作时为空
+    pub async fn undo_last(&mut self, bus: &CommandBus) -> Option<UserMessage> {
+        let entry = self.entries.pop_back()?;
+        Some(Self::run(entry, bus).await)
+    }
+
+    /// 撤销提示按钮对应的操作
+    pub async fn undo(&mut self, entry_id: u64, bus: &CommandBus) -> UserMessage {
+        let Some(index) = self.entries.iter().position(|entry| entry.id == entry_id) else {
+            return UserMessage::info(UNDO_LABEL, "该操作已无法撤销");
+        };
+        let entry = self.entries.remove(index).expect("index from position");
+        Self::run(entry, bus).await
+    }
+
+    /// 分发反向命令，无论成功与否该条记录都已移除
+    async fn run(entry: UndoEntry, bus: &CommandBus) -> UserMessage {
+        let UndoAction { name, inverse, .. } = entry.action;
+        match inverse.dispatch(bus).await {
+            Ok(()) => UserMessage::info("已撤销", name),
+            Err(error) => {
+                let mut message = UserMessage::from_error(&error);
+                message.title = format!("无法撤销{}", name);
+                message
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::MessageKind;
+    use async_trait::async_trait;
+    use chrono::{Duration, Utc};
+    use minicrm_application::commands::CommandHandler;
+    use minicrm_core::{CategoryChange, CoreError, CustomerLevel, EntityKind, TaskPriority};
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    fn customer(level: CustomerLevel) -> Customer {
+        let now = Utc::now();
+        Customer {
+            id: Uuid::new_v4(),
+            name: "华东板材".to_string(),
+            contact_person: None,
+            contact_birthday: None,
+            phone: None,
+            email: None,
+            address: None,
+            level,
+            credit_limit: None,
+            credit_hold: false,
+            owner_id: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    fn task(status: TaskStatus) -> Task {
+        let now = Utc::now();
+        Task {
+            id: Uuid::new_v4(),
+            title: "回访华东板材".to_string(),
+            description: None,
+            status,
+            priority: TaskPriority::Medium,
+            customer_id: None,
+            supplier_id: None,
+            due_date: None,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+            assigned_to: None,
+        }
+    }
+
+    fn note(task_id: Uuid) -> TaskNote {
+        TaskNote {
+            id: Uuid::new_v4(),
+            task_id,
+            body: "客户要求周五前回电".to_string(),
+            author: Some("zhang".to_string()),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_inverse_construction() {
+        let updated = customer(CustomerLevel::Vip);
+        let command = ChangeCustomerCategoryCommand {
+            change: CategoryChange {
+                customer_id: updated.id,
+                name: updated.name.clone(),
+                level_before: CustomerLevel::Normal,
+                level_after: CustomerLevel::Vip,
+                tags_before: ["板材".to_string()].into(),
+                tags_after: ["板材".to_string(), "大客户".to_string()].into(),
+            },
+            expected_updated_at: None,
+        };
+        let action = UndoAction::category_changed(&command, &updated);
+        assert_eq!(action.label(), "撤销：修改客户等级");
+        let InverseCommand::ChangeCategory(inverse) = &action.inverse else {
+            panic!("unexpected inverse: {:?}", action.inverse);
+        };
+        assert_eq!(inverse.change.level_before, CustomerLevel::Vip);
+        assert_eq!(inverse.change.level_after, CustomerLevel::Normal);
+        assert_eq!(inverse.change.tags_after, command.change.tags_before);
+        assert_eq!(inverse.expected_updated_at, Some(updated.updated_at));
+
+        let mut tags_only = command.clone();
+        tags_only.change.level_after = CustomerLevel::Normal;
+        let action = UndoAction::category_changed(&tags_only, &updated);
+        assert_eq!(action.label(), "撤销：修改客户标签");
+
+        let deleted = note(Uuid::new_v4());
+        let action = UndoAction::note_deleted(&deleted);
+        assert_eq!(action.label(), "撤销：删除备注");
+        assert!(matches!(
+            &action.inverse,
+            InverseCommand::RestoreNote(RestoreTaskNoteCommand { note }) if note.id == deleted.id
+        ));
+
+        let updated = task(TaskStatus::Completed);
+        let action = UndoAction::task_status_changed(TaskStatus::InProgress, &updated).unwrap();
+        assert_eq!(action.label(), "撤销：修改任务状态");
+        let InverseCommand::SetTaskStatus(inverse) = &action.inverse else {
+            panic!("unexpected inverse: {:?}", action.inverse);
+        };
+        assert_eq!(inverse.task_id, updated.id);
+        assert_eq!(inverse.status, TaskStatus::InProgress);
+        assert_eq!(inverse.expected_updated_at, Some(updated.updated_at));
+        assert!(UndoAction::task_status_changed(TaskStatus::Completed, &updated).is_none());
+
+        let batch = DeletionBatch {
+            id: Uuid::new_v4(),
+            root_entity: EntityKind::Customer,
+            root_id: Uuid::new_v4(),
+            label: "华东板材".to_string(),
+            deleted_at: Utc::now(),
+            deleted_by: None,
+            counts: Default::default(),
+        };
+        let action = UndoAction::customer_deleted(&batch);
+        assert_eq!(action.label(), "撤销：删除客户");
+        assert!(matches!(
+            &action.inverse,
+            InverseCommand::Restore(RestoreEntityCommand { entity: EntityKind::Customer, id })
+                if *id == batch.root_id
+        ));
+    }
+
+    /// 只保存一个任务，按版本检查状态修改；备注已被彻底删除
+    struct FakeTasks(Mutex<Task>);
+
+    #[async_trait]
+    impl CommandHandler<UpdateTaskStatusCommand> for FakeTasks {
+        async fn handle(&self, command: UpdateTaskStatusCommand) -> CoreResult<Task> {
+            let mut task = self.0.lock().unwrap();
+            if command.expected_updated_at != Some(task.updated_at) {
+                return Err(CoreError::conflict("任务已被其他人修改，请刷新后重试"));
+            }
+            task.status = command.status;
+            task.updated_at += Duration::seconds(1);
+            Ok(task.clone())
+        }
+    }
+
+    #[async_trait]
+    impl CommandHandler<RestoreTaskNoteCommand> for FakeTasks {
+        async fn handle(&self, _command: RestoreTaskNoteCommand) -> CoreResult<TaskNote> {
+            Err(CoreError::not_found("任务"))
+        }
+    }
+
+    fn bus(tasks: &Arc<FakeTasks>) -> CommandBus {
+        let mut bus = CommandBus::new();
+        bus.register::<UpdateTaskStatusCommand>(tasks.clone());
+        bus.register::<RestoreTaskNoteCommand>(tasks.clone());
+        bus
+    }
+
+    #[tokio::test]
+    async fn test_undo_and_stale_inverse() {
+        let tasks = Arc::new(FakeTasks(Mutex::new(task(TaskStatus::Completed))));
+        let bus = bus(&tasks);
+        let current = tasks.0.lock().unwrap().clone();
+        let mut stack = UndoStack::new();
+
+        // 快捷键撤销最近的操作
+        let action = UndoAction::task_status_changed(TaskStatus::Pending, &current).unwrap();
+        let toast = stack.push(action.clone());
+        assert_eq!(toast.action_label(), "撤销");
+        assert_eq!(toast.message.detail, "已修改任务状态：回访华东板材");
+        assert_eq!(stack.peek_label().as_deref(), Some("撤销：修改任务状态"));
+        let message = stack.undo_last(&bus).await.unwrap();
+        assert_eq!(message.kind, MessageKind::Info);
+        assert_eq!(message.to_text(), "已撤销：修改任务状态");
+        assert_eq!(tasks.0.lock().unwrap().status, TaskStatus::Pending);
+        assert!(stack.is_empty());
+        assert!(stack.undo_last(&bus).await.is_none());
+
+        // 版本已变化：提示原因并丢弃
+        let toast = stack.push(action);
+        let message = stack.undo(toast.entry_id, &bus).await;
+        assert_eq!(message.kind, MessageKind::Conflict);
+        assert_eq!(message.title, "无法撤销修改任务状态");
+        assert!(stack.is_empty());
+        assert_eq!(tasks.0.lock().unwrap().status, TaskStatus::Pending);
+
+        // 任务已被彻底删除
+        let toast = stack.push(UndoAction::note_deleted(&note(current.id)));
+        let message = stack.undo(toast.entry_id, &bus).await;
+        assert_eq!(message.kind, MessageKind::NotFound);
+        assert!(stack.is_empty());
+
+        // 已撤销或已丢弃的提示按钮
+        let message = stack.undo(toast.entry_id, &bus).await;
+        assert_eq!(message.detail, "该操作已无法撤销");
+    }
+
+    #[test]
+    fn test_stack_capping_and_shortcut() {
+        let mut stack = UndoStack::new();
+        let first = stack.push(UndoAction::note_deleted(&note(Uuid::new_v4())));
+        for _ in 0..UNDO_STACK_LIMIT {
+            stack.push(UndoAction::note_deleted(&note(Uuid::new_v4())));
+        }
+        assert_eq!(stack.len(), UNDO_STACK_LIMIT);
+        assert!(stack.entries.iter().all(|entry| entry.id != first.entry_id));
+
+        let mut overrides = BTreeMap::new();
+        assert_eq!(undo_shortcut(&overrides), "Ctrl+Z");
+        overrides.insert("undo".to_string(), "Ctrl+Shift+Z".to_string());
+        assert_eq!(undo_shortcut(&overrides), "Ctrl+Shift+Z");
+    }
+}
@@ -0,0 +1,281 @@
+//! 表单即时校验模块
+//!
+//! 输入停顿 [`VALIDATION_DEBOUNCE`] 后按字段校验，错误即时显示在字段下方。单字段规则
+//! 直接注册新建命令的校验函数（如 [`CreateCustomerCommand::check_phone`]），与保存时
+//! 命令的校验是同一份代码；跨字段的规则只在保存时由命令检查。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use minicrm_application::commands::{CreateCustomerCommand, CreateProductCommand};
+use minicrm_core::validation::validation_message;
+use minicrm_core::{CoreError, CoreResult, Money};
+
+/// 输入停顿多久后校验
+pub const VALIDATION_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 客户表单
+pub const CUSTOMER_FORM: &str = "customer";
+
+/// 产品表单
+pub const PRODUCT_FORM: &str = "product";
+
+type FieldValidator = Box<dyn Fn(&str) -> CoreResult<()>>;
+
+/// 字段校验器注册表
+///
+/// 以（表单，字段）为键，字段名与命令校验错误中的字段名一致。默认注册客户和产品表单的
+/// 单字段规则。
+pub struct FieldValidatorRegistry {
+    validators: HashMap<(&'static str, &'static str), FieldValidator>,
+}
+
+impl fmt::Debug for FieldValidatorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keys: Vec<_> = self.validators.keys().collect();
+        keys.sort();
+        f.debug_struct("FieldValidatorRegistry")
+            .field("fields", &keys)
+            .finish()
+    }
+}
+
+impl Default for FieldValidatorRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .with(CUSTOMER_FORM, "name", CreateCustomerCommand::check_name)
+            .with(CUSTOMER_FORM, "phone", CreateCustomerCommand::check_phone)
+            .with(CUSTOMER_FORM, "email", CreateCustomerCommand::check_email)
+            .with(PRODUCT_FORM, "name", CreateProductCommand::check_name)
+            .with(PRODUCT_FORM, "price", |text| {
+                text.parse::<Money>().and_then(CreateProductCommand::check_price)
+            })
+    }
+}
+
+impl FieldValidatorRegistry {
+    /// 不含任何规则的注册表
+    pub fn empty() -> Self {
+        Self {
+            validators: HashMap::new(),
+        }
+    }
+
+    /// 注册字段规则（同一字段再次注册时替换）
+    pub fn register<F>(&mut self, form: &'static str, field: &'static str, validator: F)
+    where
+        F: Fn(&str) -> CoreResult<()> + 'static,
+    {
+        self.validators.insert((form, field), Box::new(validator));
+    }
+
+    /// 注册字段规则并返回注册表
+    pub fn with<F>(mut self, form: &'static str, field: &'static str, validator: F) -> Self
+    where
+        F: Fn(&str) -> CoreResult<()> + 'static,
+    {
+        self.register(form, field, validator);
+        self
+    }
+
+    /// 校验字段，返回错误提示；没有注册规则的字段不校验
+    pub fn validate_field(&self, form: &str, field: &str, text: &str) -> Option<String> {
+        let validator = self.validators.get(&(form, field))?;
+        validator(text).err().map(|e| validation_message(&e))
+    }
+}
+
+/// 一个表单的即时校验状态
+///
+/// 文字变化时调用 [`Self::text_changed`]，界面按 [`Self::next_due`] 设置定时器，
+/// 到时调用 [`Self::flush`] 校验停顿下来的字段。
+#[derive(Debug, Clone)]
+pub struct FieldFeedback {
+    form: &'static str,
+    pending: HashMap<&'static str, (String, Instant)>,
+    errors: HashMap<&'static str, String>,
+}
+
+impl FieldFeedback {
+    /// 创建表单的校验状态
+    pub fn new(form: &'static str) -> Self {
+        Self {
+            form,
+            pending: HashMap::new(),
+            errors: HashMap::new(),
+        }
+    }
+
+    /// 字段文字变化，停顿 [`VALIDATION_DEBOUNCE`] 后校验
+    pub fn text_changed(&mut self, field: &'static str, text: &str, now: Instant) {
+        self.pending
+            .insert(field, (text.to_string(), now + VALIDATION_DEBOUNCE));
+    }
+
+    /// 最早需要校验的时间
+    pub fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|(_, due)| *due).min()
+    }
+
+    /// 校验已停顿的字段，返回提示有变化的字段
+    pub fn flush(&mut self, registry: &FieldValidatorRegistry, now: Instant) -> Vec<&'static str> {
+        let due: Vec<&'static str> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, at))| *at <= now)
+            .map(|(field, _)| *field)
+            .collect();
+        let mut changed = Vec::new();
+        for field in due {
+            let Some((text, _)) = self.pending.remove(field) else {
+                continue;
+            };
+            let error = registry.validate_field(self.form, field, &text);
+            if self.errors.get(field) != error.as_ref() {
+                changed.push(field);
+            }
+            match error {
+                Some(message) => self.errors.insert(field, message),
+                None => self.errors.remove(field),
+            };
+        }
+        changed.sort_unstable();
+        changed
+    }
+
+    /// 显示保存时命令返回的字段错误（含跨字段规则）
+    pub fn show_save_errors(&mut self, err: &CoreError, fields: &[&'static str]) {
+        if let CoreError::InvalidFields(errors) = err {
+            for error in errors {
+                if let Some(field) = fields.iter().find(|f| **f == error.field) {
+                    self.errors.insert(*field, error.message.clone());
+                }
+            }
+        }
+    }
+
+    /// 字段的错误提示
+    pub fn error(&self, field: &str) -> Option<&str> {
+        self.errors.get(field).map(String::as_str)
+    }
+
+    /// 是否有错误提示
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quick_create::{ProductQuickCreate, QuickCreate};
+    use uuid::Uuid;
+
+    /// 命令校验结果中某字段的提示
+    fn command_error(result: CoreResult<()>, field: &str) -> Option<String> {
+        match result {
+            Ok(()) => None,
+            Err(CoreError::InvalidFields(errors)) => errors
+                .into_iter()
+                .find(|e| e.field == field)
+                .map(|e| e.message),
+            Err(other) => panic!("unexpected error: {other}"),
+        }
+    }
+
+    fn customer(field: &str, text: &str) -> CreateCustomerCommand {
+        let value = |name: &str| (field == name).then(|| text.to_string());
+        CreateCustomerCommand {
+            name: value("name").unwrap_or_else(|| "华美家具".to_string()),
+            contact_person: None,
+            phone: value("phone"),
+            email: value("email"),
+            address: None,
+            idempotency_key: None,
+        }
+    }
+
+    /// 产品表单的保存路径：表单值转为命令，再由命令校验
+    fn save_product(field: &str, text: &str) -> Option<String> {
+        let mut values = vec!["生态板".to_string(), String::new(), "128".to_string()];
+        values[if field == "name" { 0 } else { 2 }] = text.to_string();
+        let result = ProductQuickCreate::command(&values, Uuid::new_v4())
+            .and_then(|command| command.validate());
+        command_error(result, field)
+    }
+
+    #[test]
+    fn test_live_and_save_validation_agree() {
+        let registry = FieldValidatorRegistry::default();
+        let long_name = "板".repeat(101);
+        let names = ["华美家具", "  ", "", " 宏达装饰 ", long_name.as_str()];
+        let phones = [
+            "",
+            "13800138000",
+            "+86 138-0013-8000",
+            "0571-87654321",
+            "400-820-8820",
+            "12800138000",
+            "8765432",
+            "138001380001",
+            "电话",
+        ];
+        let emails = ["", "sales@huamei.com", "sales", "a@b", "a b@c.cn", "@huamei.com"];
+        let prices = ["128", "¥1,280.50", "0", "-5", "", "一百二", "12.345", "12."];
+
+        let mut rejected = 0;
+        for (field, corpus) in [
+            ("name", &names[..]),
+            ("phone", &phones[..]),
+            ("email", &emails[..]),
+        ] {
+            for text in corpus {
+                let live = registry.validate_field(CUSTOMER_FORM, field, text);
+                let saved = command_error(customer(field, text).validate(), field);
+                assert_eq!(live, saved, "客户 {field}: {text:?}");
+                rejected += usize::from(live.is_some());
+            }
+        }
+        for (field, corpus) in [("name", &names[..]), ("price", &prices[..])] {
+            for text in corpus {
+                let live = registry.validate_field(PRODUCT_FORM, field, text);
+                assert_eq!(live, save_product(field, text), "产品 {field}: {text:?}");
+                rejected += usize::from(live.is_some());
+            }
+        }
+        // 语料中既有通过也有不通过的值
+        assert_eq!(rejected, 20);
+        assert_eq!(registry.validate_field(CUSTOMER_FORM, "address", "任意"), None);
+    }
+
+    #[test]
+    fn test_feedback_is_debounced() {
+        let registry = FieldValidatorRegistry::default();
+        let mut feedback = FieldFeedback::new(CUSTOMER_FORM);
+        let start = Instant::now();
+
+        feedback.text_changed("phone", "138", start);
+        feedback.text_changed("phone", "1380013", start + Duration::from_millis(200));
+        // 仍在输入，不校验
+        assert!(feedback.flush(&registry, start + Duration::from_millis(400)).is_empty());
+        assert_eq!(
+            feedback.next_due(),
+            Some(start + Duration::from_millis(200) + VALIDATION_DEBOUNCE)
+        );
+
+        let later = start + Duration::from_secs(1);
+        assert_eq!(feedback.flush(&registry, later), vec!["phone"]);
+        assert_eq!(feedback.error("phone"), Some("电话号码格式不正确"));
+        assert_eq!(feedback.next_due(), None);
+
+        feedback.text_changed("phone", "13800138000", later);
+        assert_eq!(feedback.flush(&registry, later + VALIDATION_DEBOUNCE), vec!["phone"]);
+        assert!(!feedback.has_errors());
+
+        // 保存时命令返回的错误同样显示在字段下方
+        let err = customer("name", " ").validate().unwrap_err();
+        feedback.show_save_errors(&err, &["name", "phone", "email"]);
+        assert_eq!(feedback.error("name"), Some("客户名称不能为空"));
+    }
+}
@@ -2,4 +2,1803 @@
 //!
 //! 定义表示层的视图模型
 
-// 暂时为空，后续实现
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Local, Utc, Weekday};
+use minicrm_application::commands::{
+    AdjustCategoryPricesCommand, CreateQuoteFromTemplateCommand, DeleteCustomerCommand,
+    RestoreEntityCommand, SendQuoteCommand, TemplateQuote,
+};
+use minicrm_application::queries::{PriceAdjustmentPreviewQuery, TrashContents};
+use minicrm_application::{
+    AppLock, DeliveryWeek, MarginThresholds, PriceChange, QuoteEditorData, QuoteMargin,
+    UnlockOutcome,
+};
+use minicrm_core::{
+    BadgeTone, BusinessCalendar, CreditProfile, CustomFieldEntry, CustomerExportRequest,
+    CustomerListRow, CustomerRelation, CustomerSummary, DeletionBatch, DeletionImpact,
+    DeliveryStatus, DisplayName, EntityKind, ExportScope, FieldChange, FieldPolicy, Freshness,
+    ItemChange, Money, OpportunityStage, Pipeline, Product, ProductPrice, Projection, PurchaseOrder,
+    PurchaseOrderStatus, Quote, QuoteDiff, QuoteRevision, QuoteStatus, QuoteTemplate,
+    ReassignmentCounts, RelatedCustomerGroup, RelationKind, RelationRole, StalenessEvaluator,
+    StalenessKind, Supplier, SupplierPayables, SupplierPriceComparison, Task, TaskPriority,
+    TaskStatus, TrashItem, User,
+};
+use strum::IntoEnumIterator;
+use uuid::Uuid;
+
+use crate::columns::{ColumnChooserViewModel, VisibleColumn};
+use crate::external_actions::QuickAction;
+use crate::formatting::{display_name, format_date, format_money, DateStyle};
+use crate::list_export::ExportScopeChoice;
+use crate::navigation::CustomerListFilter;
+use crate::timeline::CustomerTimelineViewModel;
+
+/// 锁屏视图模型
+///
+/// 启动时及空闲超时后显示，解锁前界面上的其他操作均不可用。
+#[derive(Debug)]
+pub struct LockScreenViewModel {
+    lock: Arc<AppLock>,
+    /// 已输入的口令
+    pub passcode_input: String,
+    /// 提示信息
+    pub message: Option<String>,
+    /// 下次允许尝试的时间
+    pub retry_after: Option<DateTime<Utc>>,
+}
+
+impl LockScreenViewModel {
+    /// 创建锁屏视图模型
+    pub fn new(lock: Arc<AppLock>) -> Self {
+        Self {
+            lock,
+            passcode_input: String::new(),
+            message: None,
+            retry_after: None,
+        }
+    }
+
+    /// 锁屏是否可见
+    pub fn is_visible(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /// 记录用户输入（由界面事件过滤器调用）
+    pub fn on_user_activity(&self) {
+        self.lock.record_activity();
+    }
+
+    /// 定时检查空闲超时，返回锁屏是否可见
+    pub fn tick(&self) -> bool {
+        self.lock.check_idle()
+    }
+
+    /// 立即锁定
+    pub fn lock_now(&mut self) {
+        self.lock.lock();
+        self.passcode_input.clear();
+        self.message = None;
+    }
+
+    /// 提交口令，返回是否已解锁
+    pub fn submit(&mut self) -> bool {
+        let passcode = std::mem::take(&mut self.passcode_input);
+        match self.lock.unlock(&passcode) {
+            Ok(UnlockOutcome::Unlocked) => {
+                self.message = None;
+                self.retry_after = None;
+                true
+            }
+            Ok(UnlockOutcome::WrongPasscode { retry_after }) => {
+                self.retry_after = retry_after;
+                self.message = Some(match retry_after {
+                    Some(at) => format!("口令错误，请于 {} 后重试", at.format("%H:%M:%S")),
+                    None => "口令错误".to_string(),
+                });
+                false
+            }
+            Ok(UnlockOutcome::RateLimited { retry_after }) => {
+                self.retry_after = Some(retry_after);
+                self.message = Some(format!(
+                    "尝试过于频繁，请于 {} 后重试",
+                    retry_after.format("%H:%M:%S")
+                ));
+                false
+            }
+            Err(e) => {
+                self.message = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    /// 忘记口令时的操作提示
+    pub fn forgot_passcode_hint(passcode_file: &str) -> String {
+        format!(
+            "请关闭程序后删除配置目录中的文件 \"{}\"，重新启动即可取消锁定。数据库中的数据不会受影响。",
+            passcode_file
+        )
+    }
+}
+
+/// 差异行类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// 新增
+    Added,
+    /// 删除
+    Removed,
+    /// 修改
+    Changed,
+}
+
+/// 差异列表中的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffRow {
+    /// 差异类型
+    pub kind: DiffKind,
+    /// 显示名称（字段名或明细产品名）
+    pub label: String,
+    /// 原值
+    pub old: String,
+    /// 新值
+    pub new: String,
+}
+
+/// 报价历史视图模型
+///
+/// 报价编辑器“历史”标签页使用，列出修订并渲染所选两个修订的差异。
+#[derive(Debug, Default)]
+pub struct QuoteHistoryViewModel {
+    /// 修订列表（按修订号升序）
+    pub revisions: Vec<QuoteRevision>,
+    /// 当前显示的差异
+    pub rows: Vec<DiffRow>,
+    /// 差异标题
+    pub caption: String,
+}
+
+impl QuoteHistoryViewModel {
+    /// 以修订列表创建视图模型
+    pub fn new(revisions: Vec<QuoteRevision>) -> Self {
+        Self {
+            revisions,
+            ..Self::default()
+        }
+    }
+
+    /// 修订列表中的显示文本
+    pub fn revision_labels(&self) -> Vec<String> {
+        self.revisions
+            .iter()
+            .map(|r| {
+                format!(
+                    "第{}版 · {} · {}",
+                    r.revision_no,
+                    format_date(&r.saved_at, DateStyle::ShortWithTime),
+                    r.saved_by.as_deref().unwrap_or("-")
+                )
+            })
+            .collect()
+    }
+
+    /// 显示差异
+    pub fn show_diff(&mut self, diff: &QuoteDiff) {
+        self.caption = if diff.is_empty() {
+            format!("第{}版与第{}版内容相同", diff.from_revision, diff.to_revision)
+        } else {
+            format!("第{}版 → 第{}版", diff.from_revision, diff.to_revision)
+        };
+        self.rows = diff.fields.iter().map(field_row).collect();
+        self.rows.extend(diff.items.iter().map(item_row));
+    }
+}
+
+fn field_label(field: &str) -> &str {
+    match field {
+        "quote_number" => "报价编号",
+        "customer_id" => "客户",
+        "status" => "状态",
+        "total_amount" => "总金额",
+        "valid_until" => "有效期",
+        "remarks" => "备注",
+        "product_name" => "产品",
+        "specification" => "规格",
+        "unit" => "单位",
+        "quantity" => "数量",
+        "unit_price" => "单价",
+        "amount" => "小计",
+        other => other,
+    }
+}
+
+/// 字段值的显示文字（报价状态在版本快照中保存为枚举名）
+fn field_value(field: &str, value: &str) -> String {
+    if field == "status" {
+        if let Some(status) = QuoteStatus::iter().find(|s| format!("{:?}", s) == value) {
+            return display_name(&status).to_string();
+        }
+    }
+    value.to_string()
+}
+
+fn field_row(change: &FieldChange) -> DiffRow {
+    DiffRow {
+        kind: DiffKind::Changed,
+        label: field_label(&change.field).to_string(),
+        old: field_value(&change.field, &change.old),
+        new: field_value(&change.field, &change.new),
+    }
+}
+
+fn item_row(change: &ItemChange) -> DiffRow {
+    match change {
+        ItemChange::Added { item } => DiffRow {
+            kind: DiffKind::Added,
+            label: item.product_name.clone(),
+            old: String::new(),
+            new: format!("{} × {:.2} = {:.2}", item.quantity, item.unit_price, item.amount()),
+        },
+        ItemChange::Removed { item } => DiffRow {
+            kind: DiffKind::Removed,
+            label: item.product_name.clone(),
+            old: format!("{} × {:.2} = {:.2}", item.quantity, item.unit_price, item.amount()),
+            new: String::new(),
+        },
+        ItemChange::Changed { new, fields, .. } => DiffRow {
+            kind: DiffKind::Changed,
+            label: new.product_name.clone(),
+            old: fields
+                .iter()
+                .map(|f| format!("{}: {}", field_label(&f.field), f.old))
+                .collect::<Vec<_>>()
+                .join("，"),
+            new: fields
+                .iter()
+                .map(|f| format!("{}: {}", field_label(&f.field), f.new))
+                .collect::<Vec<_>>()
+                .join("，"),
+        },
+    }
+}
+
+/// 报价模板列表中的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteTemplateRow {
+    /// 模板ID
+    pub template_id: Uuid,
+    /// 模板名称
+    pub name: String,
+    /// 摘要（明细数量与有效天数）
+    pub summary: String,
+}
+
+impl QuoteTemplateRow {
+    fn from_template(template: &QuoteTemplate) -> Self {
+        Self {
+            template_id: template.id,
+            name: template.name.clone(),
+            summary: format!(
+                "{}项产品 · 有效{}天",
+                template.items.len(),
+                template.validity_days
+            ),
+        }
+    }
+}
+
+/// 报价模板管理视图模型
+#[derive(Debug, Default)]
+pub struct QuoteTemplatesViewModel {
+    /// 模板列表（按名称排序）
+    pub rows: Vec<QuoteTemplateRow>,
+}
+
+impl QuoteTemplatesViewModel {
+    /// 以模板列表创建视图模型
+    pub fn new(templates: &[QuoteTemplate]) -> Self {
+        Self {
+            rows: templates.iter().map(QuoteTemplateRow::from_template).collect(),
+        }
+    }
+}
+
+/// “从模板新建”选择器
+///
+/// 报价编辑器使用：选择模板后生成新建命令，创建完成后显示未能加入报价的明细提示。
+#[derive(Debug, Default)]
+pub struct QuoteTemplatePicker {
+    /// 可选模板
+    pub options: Vec<QuoteTemplateRow>,
+    /// 已选模板
+    pub selected: Option<Uuid>,
+    /// 上次创建结果的逐行提示
+    pub warnings: Vec<String>,
+}
+
+impl QuoteTemplatePicker {
+    /// 以模板列表创建选择器
+    pub fn new(templates: &[QuoteTemplate]) -> Self {
+        Self {
+            options: templates.iter().map(QuoteTemplateRow::from_template).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// 选择模板，模板不在列表中时返回 `false`
+    pub fn select(&mut self, template_id: Uuid) -> bool {
+        let known = self.options.iter().any(|o| o.template_id == template_id);
+        if known {
+            self.selected = Some(template_id);
+        }
+        known
+    }
+
+    /// 为客户生成新建命令，未选择模板时为空
+    pub fn command_for(&self, customer_id: Uuid) -> Option<CreateQuoteFromTemplateCommand> {
+        self.selected.map(|template_id| CreateQuoteFromTemplateCommand {
+            template_id,
+            customer_id,
+        })
+    }
+
+    /// 显示创建结果的提示
+    pub fn show_result(&mut self, result: &TemplateQuote) {
+        self.warnings = result
+            .warnings
+            .iter()
+            .map(|w| format!("第{}行：{}", w.line, w.message))
+            .collect();
+    }
+}
+
+/// 供应商比价表中的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupplierPriceRow {
+    /// 供应商ID
+    pub supplier_id: Uuid,
+    /// 供应商名称
+    pub supplier: String,
+    /// 最新单价
+    pub latest_price: String,
+    /// 均价
+    pub average_price: String,
+    /// 报价次数
+    pub quote_count: u32,
+    /// 最小起订量
+    pub moq: String,
+    /// 有效期
+    pub valid_until: String,
+    /// 最新报价是否已过期
+    pub expired: bool,
+    /// 是否为未过期报价中的最低价
+    pub cheapest: bool,
+}
+
+/// 供应商比价视图模型
+///
+/// 产品详情页“供应商比价”标签页使用，按最新单价升序列出各供应商的报价。
+#[derive(Debug, Default)]
+pub struct SupplierComparisonViewModel {
+    /// 表格行
+    pub rows: Vec<SupplierPriceRow>,
+    /// 表格标题
+    pub caption: String,
+}
+
+impl SupplierComparisonViewModel {
+    /// 以比价结果创建视图模型
+    ///
+    /// `supplier_names` 中找不到的供应商显示为“未知供应商”。
+    pub fn new(
+        comparisons: &[SupplierPriceComparison],
+        supplier_names: &HashMap<Uuid, String>,
+    ) -> Self {
+        let cheapest = comparisons
+            .iter()
+            .filter(|c| !c.expired)
+            .min_by_key(|c| c.latest.unit_price)
+            .map(|c| c.supplier_id);
+        let rows: Vec<SupplierPriceRow> = comparisons
+            .iter()
+            .map(|c| SupplierPriceRow {
+                supplier_id: c.supplier_id,
+                supplier: supplier_names
+                    .get(&c.supplier_id)
+                    .cloned()
+                    .unwrap_or_else(|| "未知供应商".to_string()),
+                latest_price: format_money(c.latest.unit_price),
+                average_price: format_money(c.average_price),
+                quote_count: c.quote_count,
+                moq: c
+                    .latest
+                    .moq
+                    .map_or_else(|| "-".to_string(), |m| m.to_string()),
+                valid_until: if c.expired {
+                    format!(
+                        "{}（已过期）",
+                        format_date(&c.latest.valid_until, DateStyle::Short)
+                    )
+                } else {
+                    format_date(&c.latest.valid_until, DateStyle::Short)
+                },
+                expired: c.expired,
+                cheapest: Some(c.supplier_id) == cheapest,
+            })
+            .collect();
+        let caption = if rows.is_empty() {
+            "所选期间内没有供应商报价".to_string()
+        } else {
+            format!("共{}家供应商报价", rows.len())
+        };
+        Self { rows, caption }
+    }
+}
+
+/// 送货周视图中的一张卡片
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryCard {
+    /// 订单ID
+    pub order_id: Uuid,
+    /// 送货时间（本地时间 HH:MM）
+    pub time: String,
+    /// 订单编号
+    pub order_number: String,
+    /// 送货地址
+    pub address: String,
+    /// 车辆与司机
+    pub crew: String,
+    /// 送货状态
+    pub status: DeliveryStatus,
+}
+
+/// 送货周视图中的一列（一天）
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryColumn {
+    /// 列标题（如“7月29日 周一”）
+    pub title: String,
+    /// 是否为今天
+    pub is_today: bool,
+    /// 当天的送货
+    pub cards: Vec<DeliveryCard>,
+}
+
+/// 送货周视图模型
+///
+/// 按周一至周日七列展示本周送货安排。
+#[derive(Debug, Default)]
+pub struct DeliveryWeekViewModel {
+    /// 七列
+    pub columns: Vec<DeliveryColumn>,
+    /// 标题
+    pub caption: String,
+}
+
+fn weekday_label(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "周一",
+        Weekday::Tue => "周二",
+        Weekday::Wed => "周三",
+        Weekday::Thu => "周四",
+        Weekday::Fri => "周五",
+        Weekday::Sat => "周六",
+        Weekday::Sun => "周日",
+    }
+}
+
+impl DeliveryWeekViewModel {
+    /// 以一周的送货安排创建视图模型
+    pub fn new(week: &DeliveryWeek, calendar: &BusinessCalendar, now: DateTime<Utc>) -> Self {
+        let today = calendar.local_date(now);
+        let columns = week
+            .days
+            .iter()
+            .map(|day| {
+                let date = day.date.naive();
+                DeliveryColumn {
+                    title: format!(
+                        "{}月{}日 {}",
+                        date.month(),
+                        date.day(),
+                        weekday_label(date.weekday())
+                    ),
+                    is_today: day.date == today,
+                    cards: day
+                        .orders
+                        .iter()
+                        .map(|order| DeliveryCard {
+                            order_id: order.id,
+                            time: order
+                                .delivery_date
+                                .map(|at| calendar.local_time(at).format("%H:%M").to_string())
+                                .unwrap_or_default(),
+                            order_number: order.order_number.clone(),
+                            address: order
+                                .delivery_address
+                                .as_ref()
+                                .map(|a| a.one_line())
+                                .unwrap_or_default(),
+                            crew: [order.vehicle.as_deref(), order.driver.as_deref()]
+                                .into_iter()
+                                .flatten()
+                                .collect::<Vec<_>>()
+                                .join(" · "),
+                            status: order.delivery_status,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        let caption = match (week.days.first(), week.days.last()) {
+            (Some(first), Some(last)) => format!(
+                "{} 至 {} 共{}单送货",
+                first.date,
+                last.date,
+                week.total()
+            ),
+            _ => String::new(),
+        };
+        Self { columns, caption }
+    }
+}
+
+/// 负责人的姓名缩写（显示在看板卡片上）
+///
+/// 多个词的名称取前两个词的首字母（“Li Wei” → “LW”），中文名取第一个字（“王小明” → “王”）。
+pub fn initials(name: &str) -> String {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    let take = if words.len() > 1 { 2 } else { 1 };
+    words
+        .iter()
+        .take(take)
+        .filter_map(|word| word.chars().next())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// 任务看板中的一张卡片
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskCard {
+    /// 任务ID
+    pub task_id: Uuid,
+    /// 任务标题
+    pub title: String,
+    /// 优先级
+    pub priority: TaskPriority,
+    /// 截止日期（未设置时为空）
+    pub due: Option<String>,
+    /// 负责人姓名缩写（未分配时为空）
+    pub assignee_initials: Option<String>,
+    /// 负责人姓名（悬停提示）
+    pub assignee_name: Option<String>,
+    /// 逾期程度
+    pub freshness: Freshness,
+}
+
+impl TaskCard {
+    /// 优先级文字
+    pub fn priority_text(&self) -> &'static str {
+        display_name(&self.priority)
+    }
+
+    /// 优先级徽标色调
+    pub fn priority_badge(&self) -> BadgeTone {
+        self.priority.badge()
+    }
+
+    /// 逾期提示，如“任务已逾期 5 天”（未逾期时为空）
+    pub fn freshness_text(&self) -> Option<String> {
+        self.freshness.message(StalenessKind::OverdueTask)
+    }
+
+    /// 逾期徽标色调
+    pub fn freshness_badge(&self) -> BadgeTone {
+        self.freshness.badge()
+    }
+}
+
+/// 任务看板中的一列（一种状态）
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskColumn {
+    /// 任务状态
+    pub status: TaskStatus,
+    /// 列标题（如“进行中 3”）
+    pub title: String,
+    /// 该状态的任务
+    pub cards: Vec<TaskCard>,
+}
+
+/// 任务看板视图模型
+///
+/// 按状态分列展示任务，卡片上显示负责人的姓名缩写。
+/// 订阅事件后可直接移动、移除卡片或更新负责人（见 [`crate::live_refresh`]）。
+#[derive(Debug, Default)]
+pub struct TaskBoardViewModel {
+    /// 各状态列
+    pub columns: Vec<TaskColumn>,
+    /// 用户ID → 姓名
+    names: HashMap<Uuid, String>,
+}
+
+impl TaskBoardViewModel {
+    /// 以任务和用户列表创建视图模型（负责人不在用户列表中时不显示缩写）
+    ///
+    /// 逾期程度按默认阈值和当前时间计算。
+    pub fn new(tasks: &[Task], users: &[User]) -> Self {
+        Self::with_staleness(tasks, users, &StalenessEvaluator::default(), Utc::now())
+    }
+
+    /// 以任务和用户列表创建视图模型，逾期程度按 `staleness` 计算
+    pub fn with_staleness(
+        tasks: &[Task],
+        users: &[User],
+        staleness: &StalenessEvaluator,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let names: HashMap<Uuid, &str> = users
+            .iter()
+            .map(|user| (user.id, user.display_name.as_str()))
+            .collect();
+        let columns = TaskStatus::ALL
+            .into_iter()
+            .map(|status| {
+                let cards: Vec<TaskCard> = tasks
+                    .iter()
+                    .filter(|task| task.status == status)
+                    .map(|task| {
+                        let assignee = task.assigned_to.and_then(|id| names.get(&id).copied());
+                        TaskCard {
+                            task_id: task.id,
+                            title: task.title.clone(),
+                            priority: task.priority,
+                            due: task.due_date.map(|at| format_date(&at, DateStyle::Short)),
+                            assignee_initials: assignee.map(initials),
+                            assignee_name: assignee.map(str::to_string),
+                            freshness: staleness.task(task, now),
+                        }
+                    })
+                    .collect();
+                TaskColumn {
+                    status,
+                    title: format!("{} {}", display_name(&status), cards.len()),
+                    cards,
+                }
+            })
+            .collect();
+        let names = names
+            .into_iter()
+            .map(|(id, name)| (id, name.to_string()))
+            .collect();
+        Self { columns, names }
+    }
+
+    /// 看板上是否有该任务
+    pub fn contains(&self, task_id: Uuid) -> bool {
+        self.columns
+            .iter()
+            .any(|column| column.cards.iter().any(|card| card.task_id == task_id))
+    }
+
+    /// 取出卡片（列标题随之更新）
+    fn take_card(&mut self, task_id: Uuid) -> Option<TaskCard> {
+        for column in &mut self.columns {
+            if let Some(index) = column.cards.iter().position(|card| card.task_id == task_id) {
+                let card = column.cards.remove(index);
+                column.title = format!("{} {}", display_name(&column.status), column.cards.len());
+                return Some(card);
+            }
+        }
+        None
+    }
+
+    /// 把卡片移到 `status` 列末尾，任务不在看板上时返回 `false`
+    ///
+    /// 移到已完成或已取消列时清除逾期提示。
+    pub fn move_card(&mut self, task_id: Uuid, status: TaskStatus) -> bool {
+        let Some(mut card) = self.take_card(task_id) else {
+            return false;
+        };
+        if matches!(status, TaskStatus::Completed | TaskStatus::Cancelled) {
+            card.freshness = Freshness::Ok;
+        }
+        if let Some(column) = self.columns.iter_mut().find(|column| column.status == status) {
+            column.cards.push(card);
+            column.title = format!("{} {}", display_name(&status), column.cards.len());
+        }
+        true
+    }
+
+    /// 移除卡片，任务不在看板上时返回 `false`
+    pub fn remove_card(&mut self, task_id: Uuid) -> bool {
+        self.take_card(task_id).is_some()
+    }
+
+    /// 更新卡片负责人，任务不在看板上或负责人不在用户列表中时返回 `false`
+    pub fn set_assignee(&mut self, task_id: Uuid, assignee: Uuid) -> bool {
+        let Some(name) = self.names.get(&assignee) else {
+            return false;
+        };
+        let Some(card) = self
+            .columns
+            .iter_mut()
+            .flat_map(|column| column.cards.iter_mut())
+            .find(|card| card.task_id == task_id)
+        else {
+            return false;
+        };
+        card.assignee_initials = Some(initials(name));
+        card.assignee_name = Some(name.clone());
+        true
+    }
+}
+
+/// 关联客户面板中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedCustomerRow {
+    /// 关系ID（删除时使用）
+    pub relation_id: Uuid,
+    /// 关联客户ID（点击跳转）
+    pub customer_id: Uuid,
+    /// 关联客户名称
+    pub name: String,
+    /// 电话
+    pub phone: String,
+    /// 备注
+    pub note: String,
+}
+
+/// 关联客户面板中的一组（按角色）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedCustomerSection {
+    /// 角色
+    pub role: RelationRole,
+    /// 分组标题（如“上级公司”）
+    pub title: String,
+    /// 关联客户
+    pub rows: Vec<RelatedCustomerRow>,
+}
+
+/// 关联客户变更（确认后由调用方交给客户关系服务执行）
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelationChange {
+    /// 添加关系
+    Add(CustomerRelation),
+    /// 删除关系
+    Remove {
+        /// 关系ID
+        relation_id: Uuid,
+    },
+}
+
+/// 客户详情页“关联客户”面板
+///
+/// 添加和删除都先进入待确认状态，用户确认后才返回需要执行的变更。
+#[derive(Debug, Default)]
+pub struct CustomerRelationsPanel {
+    /// 当前客户ID
+    pub customer_id: Uuid,
+    /// 面板标题
+    pub title: String,
+    /// 按角色分组的关联客户
+    pub sections: Vec<RelatedCustomerSection>,
+    pending: Option<(RelationChange, String)>,
+}
+
+impl CustomerRelationsPanel {
+    /// 以关联客户分组创建面板
+    pub fn new(customer_id: Uuid, groups: &[RelatedCustomerGroup]) -> Self {
+        let sections = groups
+            .iter()
+            .map(|group| RelatedCustomerSection {
+                role: group.role,
+                title: group.role.label().to_string(),
+                rows: group
+                    .customers
+                    .iter()
+                    .map(|related| RelatedCustomerRow {
+                        relation_id: related.relation.id,
+                        customer_id: related.other.id,
+                        name: related.other.name.clone(),
+                        phone: related.other.phone.clone().unwrap_or_default(),
+                        note: related.relation.note.clone().unwrap_or_default(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            customer_id,
+            title: "关联客户".to_string(),
+            sections,
+            pending: None,
+        }
+    }
+
+    /// 是否没有任何关联客户
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|s| s.rows.is_empty())
+    }
+
+    /// 请求添加关系（当前客户为关系发起方），返回确认提示
+    pub fn request_add(
+        &mut self,
+        kind: RelationKind,
+        other: &CustomerSummary,
+        note: Option<String>,
+    ) -> &str {
+        let relation = CustomerRelation {
+            id: Uuid::new_v4(),
+            from_id: self.customer_id,
+            to_id: other.id,
+            kind,
+            note,
+            created_at: Utc::now(),
+        };
+        let prompt = format!(
+            "确认将「{}」添加为{}？",
+            other.name,
+            kind.role_of_other(true).label()
+        );
+        &self
+            .pending
+            .insert((RelationChange::Add(relation), prompt))
+            .1
+    }
+
+    /// 请求删除关系，返回确认提示；关系不在面板中时返回 `None`
+    pub fn request_remove(&mut self, relation_id: Uuid) -> Option<&str> {
+        let prompt = self.sections.iter().find_map(|section| {
+            section
+                .rows
+                .iter()
+                .find(|row| row.relation_id == relation_id)
+                .map(|row| format!("确认解除与「{}」的{}关系？", row.name, section.title))
+        })?;
+        Some(
+            &self
+                .pending
+                .insert((RelationChange::Remove { relation_id }, prompt))
+                .1,
+        )
+    }
+
+    /// 待确认的提示
+    pub fn confirmation(&self) -> Option<&str> {
+        self.pending.as_ref().map(|(_, prompt)| prompt.as_str())
+    }
+
+    /// 确认并取出待执行的变更
+    pub fn confirm(&mut self) -> Option<RelationChange> {
+        self.pending.take().map(|(change, _)| change)
+    }
+
+    /// 取消待确认的变更
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+}
+
+/// 详情页中的一个自定义字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomFieldRow {
+    /// 字段键
+    pub key: String,
+    /// 显示名称
+    pub label: String,
+    /// 值（未填写时为空）
+    pub value: String,
+    /// 是否必填
+    pub required: bool,
+}
+
+impl CustomFieldRow {
+    /// 按显示顺序转换服务返回的字段（停用的字段不显示）
+    pub fn from_entries(entries: &[CustomFieldEntry]) -> Vec<Self> {
+        entries
+            .iter()
+            .filter(|e| !e.definition.retired)
+            .map(|e| Self {
+                key: e.definition.key.clone(),
+                label: e.definition.label.clone(),
+                value: e.value.clone().unwrap_or_default(),
+                required: e.definition.required,
+            })
+            .collect()
+    }
+}
+
+/// 信用额度进度条（已用/额度）
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreditGauge {
+    /// 已用额度（应收款加已确认未收款）
+    pub used: String,
+    /// 额度，不限额时显示“不限额”
+    pub limit: String,
+    /// 进度条填充比例（0~1），不限额时为0
+    pub fill: f32,
+    /// 已超出额度
+    pub over_limit: bool,
+    /// 信用冻结
+    pub on_hold: bool,
+}
+
+impl CreditGauge {
+    /// 按客户信用状况创建
+    pub fn new(profile: &CreditProfile) -> Self {
+        let used = profile.exposure();
+        let (limit, fill) = match profile.credit_limit {
+            Some(limit) if limit.is_positive() => {
+                #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+                let fill = (used.cents() as f64 / limit.cents() as f64).clamp(0.0, 1.0) as f32;
+                (format_money(limit), fill)
+            }
+            Some(limit) => (format_money(limit), if used.is_positive() { 1.0 } else { 0.0 }),
+            None => ("不限额".to_string(), 0.0),
+        };
+        Self {
+            used: format_money(used),
+            limit,
+            fill,
+            over_limit: profile.credit_limit.is_some_and(|limit| used > limit),
+            on_hold: profile.credit_hold,
+        }
+    }
+
+    /// 进度条旁的文字，如“已用 ¥9,000.00 / 额度 ¥10,000.00”
+    pub fn label(&self) -> String {
+        let mut text = format!("已用 {} / 额度 {}", self.used, self.limit);
+        if self.on_hold {
+            text.push_str("（已冻结）");
+        }
+        text
+    }
+}
+
+/// 客户详情视图模型
+#[derive(Debug)]
+pub struct CustomerDetailViewModel {
+    /// 客户摘要
+    pub customer: CustomerSummary,
+    /// 关联客户面板
+    pub relations: CustomerRelationsPanel,
+    /// 自定义字段
+    pub custom_fields: Vec<CustomFieldRow>,
+    /// 信用额度进度条（未读取信用状况时为空）
+    pub credit: Option<CreditGauge>,
+    /// 客户时间线
+    pub timeline: CustomerTimelineViewModel,
+}
+
+impl CustomerDetailViewModel {
+    /// 以客户摘要和关联客户创建视图模型
+    pub fn new(customer: CustomerSummary, related: &[RelatedCustomerGroup]) -> Self {
+        let relations = CustomerRelationsPanel::new(customer.id, related);
+        let timeline = CustomerTimelineViewModel::new(customer.id);
+        Self {
+            customer,
+            relations,
+            custom_fields: Vec::new(),
+            credit: None,
+            timeline,
+        }
+    }
+
+    /// 附加自定义字段
+    pub fn with_custom_fields(mut self, entries: &[CustomFieldEntry]) -> Self {
+        self.custom_fields = CustomFieldRow::from_entries(entries);
+        self
+    }
+
+    /// 附加信用额度进度条
+    pub fn with_credit(mut self, profile: &CreditProfile) -> Self {
+        self.credit = Some(CreditGauge::new(profile));
+        self
+    }
+
+    /// 按字段策略去掉隐藏字段（隐藏电话时也不显示拨号按钮）
+    pub fn with_field_policy(mut self, policy: &FieldPolicy) -> Self {
+        if policy.is_hidden("phone") {
+            self.customer.phone = None;
+        }
+        self
+    }
+
+    /// 标题栏的快捷操作按钮（客户摘要只含电话）
+    pub fn quick_actions(&self) -> Vec<QuickAction> {
+        QuickAction::available(self.customer.phone.as_deref(), None, None)
+    }
+}
+
+/// 客户列表视图模型
+///
+/// 只包含可见列，单元格与表头一一对应。
+#[derive(Debug, Clone, Default)]
+pub struct CustomerListViewModel {
+    /// 表头（可见列）
+    pub headers: Vec<VisibleColumn>,
+    /// 各行（客户ID, 单元格）
+    pub rows: Vec<(Uuid, Vec<String>)>,
+    /// 多选的行（导出时按 [`Self::rows`] 的顺序排列）
+    pub selected: HashSet<Uuid>,
+}
+
+impl CustomerListViewModel {
+    /// 按列设置和读取到的行创建视图模型
+    pub fn new(columns: &ColumnChooserViewModel, rows: &[CustomerListRow]) -> Self {
+        let headers = columns.visible_columns();
+        let rows = rows
+            .iter()
+            .map(|row| {
+                let cells = headers
+                    .iter()
+                    .map(|h| row.cells.get(&h.id).cloned().unwrap_or_default())
+                    .collect();
+                (row.id, cells)
+            })
+            .collect();
+        Self {
+            headers,
+            rows,
+            selected: HashSet::new(),
+        }
+    }
+
+    /// 列表中是否有该客户
+    pub fn contains(&self, customer_id: Uuid) -> bool {
+        self.rows.iter().any(|(id, _)| *id == customer_id)
+    }
+
+    /// 某行某列的单元格（列未显示时为空）
+    pub fn cell(&self, customer_id: Uuid, column: &str) -> Option<&str> {
+        let index = self.headers.iter().position(|h| h.id == column)?;
+        let (_, cells) = self.rows.iter().find(|(id, _)| *id == customer_id)?;
+        cells.get(index).map(String::as_str)
+    }
+
+    /// 右键菜单中的快捷操作（只按已显示的电话、地址列判断）
+    pub fn quick_actions(&self, customer_id: Uuid) -> Vec<QuickAction> {
+        QuickAction::available(
+            self.cell(customer_id, "phone"),
+            self.cell(customer_id, "address"),
+            None,
+        )
+    }
+
+    /// 移除一行，客户不在列表中时返回 `false`
+    pub fn remove_row(&mut self, customer_id: Uuid) -> bool {
+        self.selected.remove(&customer_id);
+        let before = self.rows.len();
+        self.rows.retain(|(id, _)| *id != customer_id);
+        self.rows.len() != before
+    }
+
+    /// 切换一行的多选状态，返回切换后是否选中（客户不在列表中时返回 `false`）
+    pub fn toggle_selected(&mut self, customer_id: Uuid) -> bool {
+        if !self.contains(customer_id) {
+            return false;
+        }
+        if self.selected.remove(&customer_id) {
+            false
+        } else {
+            self.selected.insert(customer_id)
+        }
+    }
+
+    /// 清除多选
+    pub fn clear_selection(&mut self) {
+        self.selected.clear();
+    }
+
+    /// 选中的客户（按界面上的顺序，与点选顺序无关）
+    pub fn selected_ids(&self) -> Vec<Uuid> {
+        self.rows
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| self.selected.contains(id))
+            .collect()
+    }
+
+    /// 按选择的范围生成导出请求（导出可见列）；选择“选中的行”但没有选中任何行时为空
+    ///
+    /// `filter` 为列表当前的筛选条件（含保存的搜索打开的条件和排序）。
+    pub fn export_request(
+        &self,
+        scope: ExportScopeChoice,
+        filter: &CustomerListFilter,
+    ) -> Option<CustomerExportRequest> {
+        let scope = match scope {
+            ExportScopeChoice::Filtered => ExportScope::Filtered {
+                filter: filter.to_query_filter(),
+            },
+            ExportScopeChoice::Selection => {
+                let ids = self.selected_ids();
+                if ids.is_empty() {
+                    return None;
+                }
+                ExportScope::Selection { ids }
+            }
+            ExportScopeChoice::All => ExportScope::All,
+        };
+        Some(CustomerExportRequest {
+            scope,
+            projection: Projection::new(self.headers.iter().map(|h| h.id.clone())),
+            headers: self.headers.iter().map(|h| h.title.clone()).collect(),
+        })
+    }
+}
+
+/// 删除客户确认对话框
+///
+/// 显示将被一并删除的关联记录；有财务记录时需输入客户名称才能确认。
+#[derive(Debug, Clone)]
+pub struct CustomerDeletionDialog {
+    /// 客户ID
+    pub customer_id: Uuid,
+    /// 客户名称
+    pub customer_name: String,
+    /// 影响范围
+    pub impact: DeletionImpact,
+    /// 用户输入的客户名称
+    pub typed_name: String,
+}
+
+impl CustomerDeletionDialog {
+    /// 以客户和影响范围创建对话框
+    pub fn new(customer_id: Uuid, customer_name: &str, impact: DeletionImpact) -> Self {
+        Self {
+            customer_id,
+            customer_name: customer_name.to_string(),
+            impact,
+            typed_name: String::new(),
+        }
+    }
+
+    /// 对话框标题
+    pub fn title(&self) -> String {
+        format!("彻底删除客户「{}」", self.customer_name)
+    }
+
+    /// 影响范围预览（每类关联记录一行）
+    pub fn preview_lines(&self) -> Vec<String> {
+        let entries = self.impact.entries();
+        if entries.is_empty() {
+            return vec!["该客户没有关联记录".to_string()];
+        }
+        entries
+            .into_iter()
+            .map(|(label, count)| format!("{}：{} 条", label, count))
+            .collect()
+    }
+
+    /// 是否需要输入客户名称确认
+    pub fn requires_typed_name(&self) -> bool {
+        self.impact.has_financial_records()
+    }
+
+    /// 名称输入框的提示文本，无需输入时为空
+    pub fn typed_name_hint(&self) -> Option<String> {
+        self.requires_typed_name().then(|| {
+            format!(
+                "该客户有报价或订单等财务记录，删除后无法恢复。请输入“{}”确认",
+                self.customer_name
+            )
+        })
+    }
+
+    /// 确认按钮是否可用
+    pub fn can_confirm(&self) -> bool {
+        !self.requires_typed_name() || self.typed_name.trim() == self.customer_name
+    }
+
+    /// 生成删除命令，尚不能确认时为空
+    pub fn command(&self) -> Option<DeleteCustomerCommand> {
+        self.can_confirm().then(|| DeleteCustomerCommand {
+            customer_id: self.customer_id,
+            confirm_name: self
+                .requires_typed_name()
+                .then(|| self.typed_name.trim().to_string()),
+        })
+    }
+}
+
+/// 停用用户确认对话框
+///
+/// 先显示需要转交的任务、销售机会和客户数，再选择接手的用户。
+#[derive(Debug, Clone)]
+pub struct UserDeactivationDialog {
+    /// 要停用的用户
+    pub user: User,
+    /// 需要转交的记录数
+    pub impact: ReassignmentCounts,
+    /// 可选的接手用户（已启用的其他用户）
+    pub candidates: Vec<User>,
+    /// 选中的接手用户
+    pub reassign_to: Option<Uuid>,
+}
+
+impl UserDeactivationDialog {
+    /// 以用户、转交预览和全部用户创建对话框
+    pub fn new(user: User, impact: ReassignmentCounts, users: &[User]) -> Self {
+        let candidates = users
+            .iter()
+            .filter(|candidate| candidate.active && candidate.id != user.id)
+            .cloned()
+            .collect();
+        Self {
+            user,
+            impact,
+            candidates,
+            reassign_to: None,
+        }
+    }
+
+    /// 对话框标题
+    pub fn title(&self) -> String {
+        format!("停用用户「{}」", self.user.display_name)
+    }
+
+    /// 转交预览（每类记录一行）
+    pub fn preview_lines(&self) -> Vec<String> {
+        if self.impact.is_empty() {
+            return vec!["该用户没有需要转交的记录".to_string()];
+        }
+        [
+            ("未完成的任务", self.impact.tasks),
+            ("进行中的销售机会", self.impact.opportunities),
+            ("负责的客户", self.impact.customers),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(label, count)| format!("{}：{} 条", label, count))
+        .collect()
+    }
+
+    /// 选择接手的用户，不在候选中的用户忽略
+    pub fn select(&mut self, user_id: Option<Uuid>) {
+        self.reassign_to =
+            user_id.filter(|id| self.candidates.iter().any(|candidate| candidate.id == *id));
+    }
+
+    /// 确认按钮是否可用（有待转交的记录时须选择接手的用户）
+    pub fn can_confirm(&self) -> bool {
+        self.impact.is_empty() || self.reassign_to.is_some()
+    }
+}
+
+/// 报价列表中的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteListRow {
+    /// 报价ID
+    pub quote_id: Uuid,
+    /// 报价编号
+    pub quote_number: String,
+    /// 状态文字
+    pub status: &'static str,
+    /// 总金额
+    pub total_amount: String,
+    /// 发送后久未答复的程度
+    pub freshness: Freshness,
+}
+
+impl QuoteListRow {
+    /// 由报价创建一行，跟进程度按 `staleness` 计算
+    pub fn new(quote: &Quote, staleness: &StalenessEvaluator, now: DateTime<Utc>) -> Self {
+        Self {
+            quote_id: quote.id,
+            quote_number: quote.quote_number.clone(),
+            status: display_name(&quote.status),
+            total_amount: format_money(quote.total_amount),
+            freshness: staleness.quote(quote, now),
+        }
+    }
+
+    /// 跟进提示，如“这个报价已发送 20 天无回应”（无需跟进时为空）
+    pub fn freshness_text(&self) -> Option<String> {
+        self.freshness.message(StalenessKind::SentQuote)
+    }
+
+    /// 跟进徽标色调
+    pub fn freshness_badge(&self) -> BadgeTone {
+        self.freshness.badge()
+    }
+}
+
+/// 报价编辑器中的一行明细
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteLineRow {
+    /// 产品名称
+    pub product_name: String,
+    /// 数量
+    pub quantity: f64,
+    /// 单价文本
+    pub unit_price: String,
+    /// 小计文本
+    pub amount: String,
+    /// 成本文本（不可见或缺少成本时为空）
+    pub cost: Option<String>,
+    /// 毛利文本，如“¥320.00（25.0%）”（不可见或缺少成本时为空）
+    pub margin: Option<String>,
+    /// 毛利率低于预警阈值
+    pub low_margin: bool,
+}
+
+/// 报价编辑器视图模型
+///
+/// 成本和毛利来自 [`QuoteEditorData`]，非管理员拿到的数据中没有这些字段，这里也就不显示。
+#[derive(Debug, Clone)]
+pub struct QuoteEditorViewModel {
+    /// 编辑器数据
+    pub data: QuoteEditorData,
+    /// 毛利预警阈值
+    pub thresholds: MarginThresholds,
+}
+
+impl QuoteEditorViewModel {
+    /// 以编辑器数据和阈值创建视图模型
+    pub fn new(data: QuoteEditorData, thresholds: MarginThresholds) -> Self {
+        Self { data, thresholds }
+    }
+
+    /// 是否显示成本和毛利列
+    pub fn shows_margin(&self) -> bool {
+        self.data.margin.is_some()
+    }
+
+    /// 明细行
+    pub fn rows(&self) -> Vec<QuoteLineRow> {
+        let margins = self.data.margin.as_ref().map(|m| m.lines.as_slice());
+        self.data
+            .quote
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let line = margins.and_then(|lines| lines.get(index));
+                QuoteLineRow {
+                    product_name: item.product_name.clone(),
+                    quantity: item.quantity,
+                    unit_price: format_money(Money::from_yuan(item.unit_price)),
+                    amount: format_money(item.line_total),
+                    cost: line
+                        .and_then(|l| l.unit_cost)
+                        .map(|cost| format_money(Money::from_yuan(cost))),
+                    margin: line.and_then(|l| Some(margin_text(l.margin?, l.margin_percent))),
+                    low_margin: line.is_some_and(|l| self.thresholds.is_low(l)),
+                }
+            })
+            .collect()
+    }
+
+    /// 毛利合计文本，不可见时为空
+    pub fn margin_summary(&self) -> Option<String> {
+        let margin = self.data.margin.as_ref()?;
+        let mut text = format!("毛利 {}", margin_text(margin.margin, margin.margin_percent));
+        if margin.missing_cost_lines > 0 {
+            text.push_str(&format!("，{} 条明细缺少成本", margin.missing_cost_lines));
+        }
+        Some(text)
+    }
+
+    /// 发送前是否须管理员批准
+    pub fn send_requires_approval(&self) -> bool {
+        self.data
+            .margin
+            .as_ref()
+            .is_some_and(|margin| self.thresholds.requires_approval(margin))
+    }
+
+    /// 修改明细数量（如开料计算器回填张数），同时更新报价合计和毛利
+    ///
+    /// 明细不存在时返回 `false`。
+    pub fn set_line_quantity(&mut self, index: usize, quantity: f64) -> bool {
+        let quote = &mut self.data.quote;
+        let Some(item) = quote.items.get_mut(index) else {
+            return false;
+        };
+        item.quantity = quantity;
+        quote.recalculate_totals();
+        if let Some(margin) = &mut self.data.margin {
+            let costs: Vec<Option<f64>> = margin.lines.iter().map(|line| line.unit_cost).collect();
+            *margin = QuoteMargin::from_costs(&quote.items, &costs);
+        }
+        true
+    }
+
+    /// 生成发送命令
+    pub fn send_command(&self, approve_low_margin: bool) -> SendQuoteCommand {
+        SendQuoteCommand {
+            quote_id: self.data.quote.id,
+            approve_low_margin,
+        }
+    }
+}
+
+fn margin_text(margin: f64, percent: Option<f64>) -> String {
+    let amount = format_money(Money::from_yuan(margin));
+    match percent {
+        Some(percent) => format!("{}（{:.1}%）", amount, percent),
+        None => amount,
+    }
+}
+
+/// 实体类型的显示名称
+pub(crate) fn entity_label(entity: EntityKind) -> &'static str {
+    match entity {
+        EntityKind::Customer => "客户",
+        EntityKind::Supplier => "供应商",
+        EntityKind::Task => "任务",
+        EntityKind::Quote => "报价",
+        EntityKind::ServiceTicket => "售后工单",
+        EntityKind::Order => "订单",
+        EntityKind::Opportunity => "销售机会",
+    }
+}
+
+/// 回收站中的一行
+///
+/// 同一批次删除的记录合并为一行，恢复时整批恢复。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashRow {
+    /// 用户删除的记录类型
+    pub entity: EntityKind,
+    /// 用户删除的记录ID
+    pub id: Uuid,
+    /// 标题，如“客户“华美家具”及 1 个任务、1 个报价”
+    pub title: String,
+    /// 删除时间和删除人
+    pub detail: String,
+    /// 删除时间
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl TrashRow {
+    fn from_batch(batch: &DeletionBatch) -> Self {
+        let linked: Vec<String> = batch
+            .counts
+            .iter()
+            .filter_map(|(table, count)| {
+                let entity = EntityKind::from_table_name(table)?;
+                let count = if entity == batch.root_entity {
+                    count.saturating_sub(1)
+                } else {
+                    *count
+                };
+                (count > 0).then(|| format!("{} 个{}", count, entity_label(entity)))
+            })
+            .collect();
+        let mut title = format!("{}“{}”", entity_label(batch.root_entity), batch.label);
+        if !linked.is_empty() {
+            title.push_str(&format!("及 {}", linked.join("、")));
+        }
+        Self {
+            entity: batch.root_entity,
+            id: batch.root_id,
+            title,
+            detail: Self::deleted_text(batch.deleted_at, batch.deleted_by.as_deref()),
+            deleted_at: batch.deleted_at,
+        }
+    }
+
+    fn from_item(item: &TrashItem) -> Self {
+        Self {
+            entity: item.entity,
+            id: item.id,
+            title: format!("{}“{}”", entity_label(item.entity), item.label),
+            detail: Self::deleted_text(item.deleted_at, None),
+            deleted_at: item.deleted_at,
+        }
+    }
+
+    fn deleted_text(at: DateTime<Utc>, by: Option<&str>) -> String {
+        let at = at.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+        match by {
+            Some(by) => format!("{} 由 {} 删除", at, by),
+            None => format!("{} 删除", at),
+        }
+    }
+
+    /// 生成恢复命令
+    pub fn restore_command(&self) -> RestoreEntityCommand {
+        RestoreEntityCommand {
+            entity: self.entity,
+            id: self.id,
+        }
+    }
+}
+
+/// 回收站视图模型
+#[derive(Debug, Clone, Default)]
+pub struct TrashViewModel {
+    /// 回收站中的行（删除时间新的在前）
+    pub rows: Vec<TrashRow>,
+}
+
+impl TrashViewModel {
+    /// 根据回收站内容生成视图模型
+    ///
+    /// 属于删除批次的记录合并为批次的一行；升级前删除、没有批次的记录各占一行。
+    pub fn new(contents: &TrashContents) -> Self {
+        let batch_ids: HashSet<Uuid> = contents.batches.iter().map(|batch| batch.id).collect();
+        let mut rows: Vec<TrashRow> = contents.batches.iter().map(TrashRow::from_batch).collect();
+        rows.extend(
+            contents
+                .items
+                .iter()
+                .filter(|item| !item.batch_id.is_some_and(|id| batch_ids.contains(&id)))
+                .map(TrashRow::from_item),
+        );
+        rows.sort_by_key(|row| Reverse(row.deleted_at));
+        Self { rows }
+    }
+
+    /// 回收站是否为空
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+/// 供应商详情中采购订单页的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurchaseOrderRow {
+    /// 采购订单ID
+    pub id: Uuid,
+    /// 采购订单号
+    pub po_number: String,
+    /// 状态
+    pub status: PurchaseOrderStatus,
+    /// 状态名称
+    pub status_label: String,
+    /// 预计到货日期（未填写时为空）
+    pub expected_date: String,
+    /// 订单金额
+    pub total_amount: String,
+    /// 收货进度，如“已收 12/20”
+    pub progress: String,
+}
+
+impl PurchaseOrderRow {
+    fn from_order(order: &PurchaseOrder) -> Self {
+        let ordered: u32 = order.lines.iter().map(|line| line.quantity).sum();
+        let received: u32 = order.lines.iter().map(|line| line.received_quantity).sum();
+        Self {
+            id: order.id,
+            po_number: order.po_number.clone(),
+            status: order.status,
+            status_label: order.status.label().to_string(),
+            expected_date: order
+                .expected_date
+                .map(|at| format_date(&at, DateStyle::Short))
+                .unwrap_or_default(),
+            total_amount: format_money(order.total_amount()),
+            progress: format!("已收 {}/{}", received, ordered),
+        }
+    }
+}
+
+/// 供应商详情视图模型
+#[derive(Debug, Clone)]
+pub struct SupplierDetailViewModel {
+    /// 供应商
+    pub supplier: Supplier,
+    /// 自定义字段
+    pub custom_fields: Vec<CustomFieldRow>,
+    /// 采购订单页（按创建时间倒序）
+    pub purchase_orders: Vec<PurchaseOrderRow>,
+    /// 应付摘要，如“应付 ¥4,000.00，在途 ¥6,000.00（1张未收齐）”，未加载时为空
+    pub payables: String,
+}
+
+impl SupplierDetailViewModel {
+    /// 以供应商和自定义字段创建视图模型
+    pub fn new(supplier: Supplier, entries: &[CustomFieldEntry]) -> Self {
+        Self {
+            supplier,
+            custom_fields: CustomFieldRow::from_entries(entries),
+            purchase_orders: Vec::new(),
+            payables: String::new(),
+        }
+    }
+
+    /// 填充采购订单页和应付摘要
+    pub fn with_purchase_orders(
+        mut self,
+        orders: &[PurchaseOrder],
+        payables: &SupplierPayables,
+    ) -> Self {
+        self.purchase_orders = orders.iter().map(PurchaseOrderRow::from_order).collect();
+        self.payables = format!(
+            "应付 {}，在途 {}（{}张未收齐）",
+            format_money(payables.outstanding_payables),
+            format_money(payables.on_order),
+            payables.open_orders
+        );
+        self
+    }
+}
+
+/// 销售漏斗中的一层
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunnelBar {
+    /// 阶段
+    pub stage: OpportunityStage,
+    /// 阶段名称
+    pub label: String,
+    /// 机会数量
+    pub count: u32,
+    /// 预计金额合计
+    pub total_amount: String,
+    /// 加权金额合计
+    pub weighted_amount: String,
+    /// 条形宽度（相对数量最多的阶段，0.0-1.0）
+    pub width: f32,
+}
+
+/// 销售漏斗视图模型
+///
+/// 按阶段顺序展示机会数量与加权金额，赢单、输单排在最后。
+#[derive(Debug, Default)]
+pub struct PipelineFunnelViewModel {
+    /// 各阶段
+    pub bars: Vec<FunnelBar>,
+    /// 标题
+    pub caption: String,
+}
+
+impl PipelineFunnelViewModel {
+    /// 以销售漏斗汇总创建视图模型
+    pub fn new(pipeline: &Pipeline) -> Self {
+        let max = pipeline.stages.iter().map(|s| s.count).max().unwrap_or(0);
+        let bars = pipeline
+            .stages
+            .iter()
+            .map(|s| FunnelBar {
+                stage: s.stage,
+                label: s.stage.label().to_string(),
+                count: s.count,
+                total_amount: format_money(s.total_amount),
+                weighted_amount: format_money(s.weighted_amount),
+                width: if max == 0 {
+                    0.0
+                } else {
+                    s.count as f32 / max as f32
+                },
+            })
+            .collect();
+        let open: u32 = pipeline
+            .stages
+            .iter()
+            .filter(|s| !s.stage.is_closed())
+            .map(|s| s.count)
+            .sum();
+        let caption = format!(
+            "进行中{}个机会，加权金额{}",
+            open,
+            pipeline.open_weighted_amount()
+        );
+        Self { bars, caption }
+    }
+}
+
+/// 价格历史中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PriceHistoryRow {
+    /// 生效时间（业务时区）
+    pub effective_from: String,
+    /// 价格
+    pub price: String,
+    /// 录入人
+    pub created_by: String,
+    /// 是否为当前价格
+    pub current: bool,
+    /// 是否尚未生效
+    pub scheduled: bool,
+}
+
+/// 产品详情视图模型
+///
+/// 价格历史按生效时间降序显示，标出当前价格和尚未生效的价格。
+#[derive(Debug, Clone)]
+pub struct ProductDetailViewModel {
+    /// 产品
+    pub product: Product,
+    /// 当前价格（尚未定价时为“未定价”）
+    pub current_price: String,
+    /// 价格历史
+    pub price_history: Vec<PriceHistoryRow>,
+}
+
+impl ProductDetailViewModel {
+    /// 以产品和价格历史（按生效时间降序）创建视图模型
+    pub fn new(
+        product: Product,
+        history: &[ProductPrice],
+        calendar: &BusinessCalendar,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let current = history.iter().find(|p| p.effective_from <= now).map(|p| p.id);
+        let price_history = history
+            .iter()
+            .map(|p| PriceHistoryRow {
+                effective_from: calendar
+                    .local_time(p.effective_from)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                price: format_money(p.price),
+                created_by: p.created_by.clone().unwrap_or_default(),
+                current: Some(p.id) == current,
+                scheduled: p.effective_from > now,
+            })
+            .collect::<Vec<_>>();
+        let current_price = price_history
+            .iter()
+            .find(|row| row.current)
+            .map_or_else(|| "未定价".to_string(), |row| row.price.clone());
+        Self {
+            product,
+            current_price,
+            price_history,
+        }
+    }
+}
+
+/// 批量调价对话框
+///
+/// 先按输入的类别、比例和生效时间预览变更，确认后执行同一计算。
+#[derive(Debug, Clone, Default)]
+pub struct PriceAdjustmentDialog {
+    /// 产品类别
+    pub category: String,
+    /// 调整百分比
+    pub percent: i32,
+    /// 生效时间
+    pub effective_from: Option<DateTime<Utc>>,
+    /// 预览的变更
+    pub preview: Vec<PriceChange>,
+}
+
+impl PriceAdjustmentDialog {
+    /// 预览查询，生效时间未填写时为空
+    pub fn preview_query(&self) -> Option<PriceAdjustmentPreviewQuery> {
+        self.command().map(|c| c.preview())
+    }
+
+    /// 显示预览结果
+    pub fn show_preview(&mut self, changes: Vec<PriceChange>) {
+        self.preview = changes;
+    }
+
+    /// 修改输入后清除已有预览，需重新预览才能确认
+    pub fn clear_preview(&mut self) {
+        self.preview.clear();
+    }
+
+    /// 预览表格的各行（产品, 原价, 新价）
+    pub fn preview_rows(&self) -> Vec<(String, String, String)> {
+        self.preview
+            .iter()
+            .map(|c| {
+                (
+                    c.product_name.clone(),
+                    format_money(c.old_price),
+                    format_money(c.new_price),
+                )
+            })
+            .collect()
+    }
+
+    /// 确认按钮是否可用（已预览且有变更）
+    pub fn can_confirm(&self) -> bool {
+        !self.preview.is_empty()
+    }
+
+    /// 调价命令，生效时间未填写时为空
+    pub fn command(&self) -> Option<AdjustCategoryPricesCommand> {
+        self.effective_from.map(|effective_from| AdjustCategoryPricesCommand {
+            category: self.category.trim().to_string(),
+            percent: self.percent,
+            effective_from,
+        })
+    }
+}
@@ -0,0 +1,276 @@
+//! 操作日志模块
+//!
+//! 把控制器通过 `log_action!` 记录的用户操作写入单独的滚动文件（默认 `logs/actions.log`），
+//! 供复现用户反馈的问题。只保留 [`ACTION_FIELDS`] 中的字段，误传的客户名称等字段值会被丢弃；
+//! 每条记录带有关联ID，可在详细日志中找到同一操作的上下文。崩溃报告附带最近的操作。
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use chrono::{Local, SecondsFormat};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+
+use crate::application::actions::{ACTION_FIELDS, ACTION_TARGET};
+
+/// 操作日志文件名
+pub const ACTION_LOG_FILE_NAME: &str = "actions.log";
+
+/// 崩溃报告中附带的最近操作条数
+pub const RECENT_ACTIONS: usize = 50;
+
+struct Inner {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Option<File>,
+    written: u64,
+    recent: VecDeque<String>,
+}
+
+impl Inner {
+    /// 超过大小上限时把当前文件改名为 `.1`（覆盖上一份），重新开始写入
+    fn rotate_if_needed(&mut self, incoming: u64) {
+        if self.written == 0 || self.written + incoming <= self.max_bytes {
+            return;
+        }
+        self.file = None;
+        let _ = fs::rename(&self.path, rotated_path(&self.path));
+        self.written = 0;
+    }
+
+    fn append(&mut self, line: &str) -> std::io::Result<()> {
+        self.rotate_if_needed(line.len() as u64 + 1);
+        if self.file.is_none() {
+            self.file = Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?,
+            );
+        }
+        if let Some(file) = self.file.as_mut() {
+            writeln!(file, "{line}")?;
+            self.written += line.len() as u64 + 1;
+        }
+        Ok(())
+    }
+}
+
+/// 轮转后的文件路径（`actions.log.1`）
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+/// 操作日志
+///
+/// 文件大小超过上限时轮转一次，磁盘占用不超过上限的两倍；最近的操作同时保存在内存中。
+#[derive(Clone)]
+pub struct ActionLog {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl fmt::Debug for ActionLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActionLog").finish_non_exhaustive()
+    }
+}
+
+impl ActionLog {
+    /// 打开操作日志（追加写入）
+    ///
+    /// # Errors
+    ///
+    /// 目录无法创建时返回错误。
+    pub fn open<P: AsRef<Path>>(path: P, max_bytes: u64) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建日志目录: {}", parent.display()))?;
+        }
+        let written = fs::metadata(&path).map_or(0, |m| m.len());
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                path,
+                max_bytes,
+                file: None,
+                written,
+                recent: VecDeque::with_capacity(RECENT_ACTIONS),
+            })),
+        })
+    }
+
+    /// 写入一条操作记录
+    ///
+    /// 写入失败时静默忽略：操作日志不能影响应用本身，也不能在日志层内再产生日志事件。
+    pub fn record(&self, line: String) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        let _ = inner.append(&line);
+        if inner.recent.len() == RECENT_ACTIONS {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(line);
+    }
+
+    /// 最近的操作记录（从旧到新，最多 [`RECENT_ACTIONS`] 条）
+    #[must_use]
+    pub fn recent(&self) -> Vec<String> {
+        self.inner
+            .lock()
+            .map(|inner| inner.recent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 生成崩溃报告文本
+    pub fn crash_report(&self, panic: &dyn fmt::Display) -> String {
+        let recent = self.recent();
+        let mut report = format!(
+            "MiniCRM 崩溃报告\n时间: {}\n版本: {}\n错误: {}\n\n最近 {} 条操作:\n",
+            Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            env!("CARGO_PKG_VERSION"),
+            panic,
+            recent.len()
+        );
+        for line in recent {
+            let _ = writeln!(report, "{line}");
+        }
+        report
+    }
+
+    /// 安装 panic 钩子，崩溃时在 `dir` 中写入 `crash-<时间>.log`，然后调用原有钩子
+    pub fn install_panic_hook(&self, dir: PathBuf) {
+        let log = self.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let name = format!("crash-{}.log", Local::now().format("%Y%m%d-%H%M%S"));
+            let _ = fs::write(dir.join(name), log.crash_report(info));
+            previous(info);
+        }));
+    }
+}
+
+/// 收集白名单内的字段
+#[derive(Default)]
+struct ActionVisitor {
+    fields: Vec<(&'static str, String)>,
+}
+
+impl ActionVisitor {
+    fn push(&mut self, field: &Field, value: String) {
+        if ACTION_FIELDS.contains(&field.name()) && !value.is_empty() {
+            self.fields.push((field.name(), value));
+        }
+    }
+}
+
+impl Visit for ActionVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, format!("{value:?}"));
+    }
+}
+
+/// 把操作事件写入操作日志的 tracing 层
+#[derive(Debug, Clone)]
+pub struct ActionLogLayer {
+    log: ActionLog,
+}
+
+impl ActionLogLayer {
+    /// 创建操作日志层
+    #[must_use]
+    pub const fn new(log: ActionLog) -> Self {
+        Self { log }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ActionLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        if event.metadata().target() != ACTION_TARGET {
+            return;
+        }
+        let mut visitor = ActionVisitor::default();
+        event.record(&mut visitor);
+        let mut line = Local::now().to_rfc3339_opts(SecondsFormat::Millis, false);
+        for (name, value) in visitor.fields {
+            let _ = write!(line, " {name}={value}");
+        }
+        self.log.record(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::log_action;
+    use tempfile::TempDir;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn with_action_log(log: &ActionLog, f: impl FnOnce()) {
+        let subscriber = tracing_subscriber::registry().with(ActionLogLayer::new(log.clone()));
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    #[test]
+    fn test_non_whitelisted_fields_are_dropped() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join(ACTION_LOG_FILE_NAME);
+        let log = ActionLog::open(&path, 1024 * 1024)?;
+
+        let mut correlation_id = String::new();
+        with_action_log(&log, || {
+            // 误传了客户名称
+            correlation_id = log_action!(
+                "customers",
+                "save",
+                entity = "customers",
+                name = "杭州华美板材有限公司"
+            );
+            tracing::info!(target: "minicrm::database", "不是用户操作");
+        });
+
+        let content = fs::read_to_string(&path)?;
+        assert_eq!(content.lines().count(), 1);
+        assert!(content.contains("controller=customers action=save"));
+        assert!(content.contains(&format!("correlation_id={correlation_id}")));
+        assert!(content.contains("entity=customers"));
+        assert!(!content.contains("华美"));
+        assert!(!content.contains("用户操作"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rolling_size_cap() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join(ACTION_LOG_FILE_NAME);
+        let max_bytes = 2048;
+        let log = ActionLog::open(&path, max_bytes)?;
+
+        with_action_log(&log, || {
+            for _ in 0..200 {
+                log_action!("navigation", "navigate", route = "customers");
+            }
+        });
+
+        assert!(fs::metadata(&path)?.len() <= max_bytes);
+        assert!(fs::metadata(rotated_path(&path))?.len() <= max_bytes);
+        // 内存中只保留最近的操作，崩溃报告附带这些记录
+        assert_eq!(log.recent().len(), RECENT_ACTIONS);
+        let report = log.crash_report(&"index out of bounds");
+        assert!(report.contains("最近 50 条操作"));
+        assert_eq!(report.matches("action=navigate").count(), RECENT_ACTIONS);
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+//! 内嵌REST API模块
+//!
+//! 为局域网内的看板等系统提供只读查询和少量写操作。
+//! 请求使用Bearer令牌认证，通过认证后以配置的服务账号（`api.service_user`、
+//! `api.service_role`）经由命令/查询总线处理，校验、权限和审计与界面一致。
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Path, Query as QueryParams, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, patch},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::application::commands::{CreateCustomerCommand, UpdateTaskStatusCommand};
+use crate::application::queries::{
+    DashboardStatsQuery, GetCustomerQuery, ListCustomersQuery, ListTasksQuery,
+};
+use crate::context::AppContext;
+use crate::core::{constants, CoreError, FieldError, Pagination, QueryFilter, TaskStatus};
+
+/// API错误响应
+#[derive(Debug)]
+pub struct ApiError(CoreError);
+
+/// API错误响应体
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    /// 错误信息
+    pub error: String,
+    /// 字段错误（仅验证失败时）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+impl From<CoreError> for ApiError {
+    fn from(err: CoreError) -> Self {
+        Self(err)
+    }
+}
+
+impl ApiError {
+    /// 错误对应的HTTP状态码
+    #[must_use]
+    pub const fn status(&self) -> StatusCode {
+        match &self.0 {
+            CoreError::Validation(_) | CoreError::InvalidFields(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            CoreError::NotFound(_) => StatusCode::NOT_FOUND,
+            CoreError::Conflict(_) => StatusCode::CONFLICT,
+            CoreError::Permission(_) => StatusCode::FORBIDDEN,
+            CoreError::Business(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let fields = match &self.0 {
+            CoreError::InvalidFields(fields) => fields.clone(),
+            _ => Vec::new(),
+        };
+        let body = ErrorBody {
+            error: self.0.to_string(),
+            fields,
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// 列表查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ListParams {
+    /// 页码
+    pub page: Option<u32>,
+    /// 每页大小
+    pub page_size: Option<u32>,
+    /// 搜索关键词
+    pub search: Option<String>,
+    /// 客户等级
+    pub level: Option<String>,
+    /// 任务状态
+    pub status: Option<String>,
+}
+
+impl ListParams {
+    fn into_filter(self) -> QueryFilter {
+        let page_size = self
+            .page_size
+            .unwrap_or(constants::DEFAULT_PAGE_SIZE)
+            .clamp(1, constants::MAX_PAGE_SIZE);
+        let mut filter = QueryFilter::new()
+            .with_pagination(Pagination::new(self.page.unwrap_or(1).max(1), page_size));
+
+        if let Some(search) = self.search.filter(|s| !s.trim().is_empty()) {
+            filter = filter.with_search(search);
+        }
+        if let Some(level) = self.level {
+            filter = filter.with_string_filter("level", level);
+        }
+        if let Some(status) = self.status {
+            filter = filter.with_string_filter("status", status);
+        }
+        filter
+    }
+}
+
+/// 任务状态更新请求体
+#[derive(Debug, Deserialize)]
+pub struct TaskStatusBody {
+    /// 新状态
+    pub status: TaskStatus,
+    /// 客户端持有的版本
+    pub expected_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// 构建API路由
+pub fn router(ctx: Arc<AppContext>) -> Router {
+    Router::new()
+        .route("/api/customers", get(list_customers).post(create_customer))
+        .route("/api/customers/:id", get(get_customer))
+        .route("/api/tasks", get(list_tasks))
+        .route("/api/tasks/:id/status", patch(update_task_status))
+        .route("/api/stats/dashboard", get(dashboard_stats))
+        .layer(middleware::from_fn_with_state(ctx.clone(), require_token))
+        .with_state(ctx)
+}
+
+/// 已启动的API服务
+#[derive(Debug)]
+pub struct ApiServer {
+    /// 实际监听地址（配置端口为0时由系统分配）
+    pub local_addr: SocketAddr,
+    /// 服务任务
+    pub task: tokio::task::JoinHandle<()>,
+}
+
+/// 按配置启动API服务
+///
+/// 上下文中应已登录令牌对应的服务账号，见 [`AppContext::start_api`]。
+///
+/// # Errors
+///
+/// 未配置访问令牌或监听地址无法绑定时返回错误。
+pub async fn serve(ctx: Arc<AppContext>) -> anyhow::Result<ApiServer> {
+    let api = &ctx.config.api;
+    if api.token.as_deref().map_or(true, str::is_empty) {
+        return Err(anyhow!("启用REST API前必须配置 api.token"));
+    }
+
+    let addr: SocketAddr = format!("{}:{}", api.bind_address, api.port)
+        .parse()
+        .with_context(|| format!("无效的API监听地址: {}", api.bind_address))?;
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("无法监听API地址: {addr}"))?;
+
+    let local_addr = listener
+        .local_addr()
+        .with_context(|| format!("无法获取API监听地址: {addr}"))?;
+
+    info!("REST API 已启动: http://{}", local_addr);
+    let app = router(ctx);
+    let task = tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!("REST API 运行失败: {}", e);
+        }
+    });
+    Ok(ApiServer { local_addr, task })
+}
+
+async fn require_token(State(ctx): State<Arc<AppContext>>, request: Request, next: Next) -> Response {
+    let expected = ctx.config.api.token.as_deref().unwrap_or_default();
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if !expected.is_empty() && constant_time_eq(token, expected) => {
+            next.run(request).await
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorBody {
+                error: "缺少或无效的访问令牌".to_string(),
+                fields: Vec::new(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a
+            .bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+async fn list_customers(
+    State(ctx): State<Arc<AppContext>>,
+    QueryParams(params): QueryParams<ListParams>,
+) -> ApiResult<impl IntoResponse> {
+    let result = ctx
+        .queries
+        .ask(ListCustomersQuery {
+            filter: params.into_filter(),
+        })
+        .await?;
+    Ok(Json(result))
+}
+
+async fn get_customer(
+    State(ctx): State<Arc<AppContext>>,
+    Path(id): Path<Uuid>,
+) -> ApiResult<impl IntoResponse> {
+    let customer = ctx
+        .queries
+        .ask(GetCustomerQuery { id })
+        .await?
+        .ok_or_else(|| CoreError::not_found(format!("客户 {id}")))?;
+    Ok(Json(customer))
+}
+
+async fn create_customer(
+    State(ctx): State<Arc<AppContext>>,
+    Json(command): Json<CreateCustomerCommand>,
+) -> ApiResult<impl IntoResponse> {
+    let customer = ctx.commands.dispatch_idempotent(command).await?;
+    Ok((StatusCode::CREATED, Json(customer)))
+}
+
+async fn list_tasks(
+    State(ctx): State<Arc<AppContext>>,
+    QueryParams(params): QueryParams<ListParams>,
+) -> ApiResult<impl IntoResponse> {
+    let result = ctx
+        .queries
+        .ask(ListTasksQuery {
+            filter: params.into_filter(),
+            ..ListTasksQuery::default()
+        })
+        .await?;
+    Ok(Json(result))
+}
+
+async fn update_task_status(
+    State(ctx): State<Arc<AppContext>>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<TaskStatusBody>,
+) -> ApiResult<impl IntoResponse> {
+    let task = ctx
+        .commands
+        .dispatch(UpdateTaskStatusCommand {
+            task_id: id,
+            status: body.status,
+            expected_updated_at: body.expected_updated_at,
+        })
+        .await?;
+    Ok(Json(task))
+}
+
+async fn dashboard_stats(State(ctx): State<Arc<AppContext>>) -> ApiResult<impl IntoResponse> {
+    Ok(Json(ctx.queries.ask(DashboardStatsQuery::default()).await?))
+}
@@ -2,16 +2,661 @@
 //!
 //! 负责应用程序的初始化、配置加载和主要业务逻辑的协调。
 
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
 use anyhow::Result;
 use slint::ComponentHandle;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use crate::application::{log_action, AppLock};
 use crate::config::AppConfig;
-use crate::database::DatabaseManager;
+use crate::context::AppContext;
+use crate::core::{
+    CancellationToken, CoreResult, DiagnosticsBundleService, DiagnosticsBundleSummary,
+    ExportProgress, Money, NewUser, QuotePayloadCodec, SystemClock, User, UserRole, UserService,
+};
+use crate::dashboard::{builtin_cards, DatabaseOnboardingProbe};
+use crate::data_dir::{DataMigration, DataRoot, LaunchOptions, LegacyData};
+use crate::database::{CompactStage, DatabaseManager};
+use crate::diagnostics::{section_count, DiagnosticsBundleExporter};
+use crate::infrastructure::database::MigrationProgress;
+use crate::infrastructure::export::HmacQuotePayloadCodec;
+use crate::infrastructure::repository::SqliteUserService;
+use crate::infrastructure::security::{FilePasscodeStore, PASSCODE_FILE_NAME};
+use crate::preflight::{self, PreflightReport};
+use crate::presentation::{
+    format_money, AppearanceSettings, DashboardCardRegistry, DashboardViewModel, FormRegistry,
+    migration_report_summary, LockScreenViewModel, MigrationCheckRow, NavigationController,
+    OnboardingChecklistViewModel, OnboardingStep, PoolSizeAdvice, ProgressDialog, ReclaimThreshold,
+    Route, ThemeManager, UnsavedChoice, UserMessage,
+};
+use crate::startup::{DeferredStartup, PhaseTiming, StartupPhase, StartupProfiler};
+use crate::ui_state::UiState;
 
 // 包含编译后的Slint UI代码
 slint::include_modules!();
 
+/// 未保存修改确认流程
+///
+/// 导航和关闭窗口共用同一个“保存 / 放弃 / 取消”对话框。
+struct UnsavedChangesFlow {
+    window: slint::Weak<MainWindow>,
+    navigation: Rc<RefCell<NavigationController>>,
+    forms: FormRegistry,
+    /// 确认对话框是否由关闭窗口触发
+    closing: Cell<bool>,
+}
+
+impl UnsavedChangesFlow {
+    /// 同步导航状态到主窗口
+    fn sync(&self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let navigation = self.navigation.borrow();
+        window.set_can_go_back(navigation.can_go_back());
+        window.set_can_go_forward(navigation.can_go_forward());
+        window.set_confirm_discard_visible(navigation.has_pending() || self.closing.get());
+        window.set_confirm_discard_message(self.forms.prompt_message().into());
+    }
+
+    /// 处理用户选择；保存需等待各表单控制器完成
+    fn resolve(self: &Rc<Self>, choice: UnsavedChoice) {
+        let flow = self.clone();
+        let task = slint::spawn_local(async move {
+            if let Some(window) = flow.window.upgrade() {
+                window.set_confirm_discard_saving(choice == UnsavedChoice::Save);
+            }
+            let result = flow.forms.resolve(choice).await;
+            let closing = flow.closing.replace(false);
+            match result {
+                Ok(true) if closing => {
+                    if let Some(window) = flow.window.upgrade() {
+                        window.hide().unwrap_or_else(|e| error!("关闭窗口失败: {}", e));
+                    }
+                }
+                Ok(true) => {
+                    flow.navigation.borrow_mut().confirm_discard();
+                }
+                Ok(false) => flow.navigation.borrow_mut().cancel_pending(),
+                Err(e) => {
+                    // 保存失败（如校验未通过）：留在当前视图
+                    flow.navigation.borrow_mut().cancel_pending();
+                    if let Some(window) = flow.window.upgrade() {
+                        let message = UserMessage::from_error(&e);
+                        window.set_status_message(message.to_text().into());
+                        window.set_status_kind(message.kind.as_str().into());
+                    }
+                }
+            }
+            if let Some(window) = flow.window.upgrade() {
+                window.set_confirm_discard_saving(false);
+            }
+            flow.sync();
+        });
+        if let Err(e) = task {
+            error!("无法处理未保存的修改: {}", e);
+        }
+    }
+}
+
+/// 仪表盘流程
+///
+/// 登录后按用户角色和保存的布局构建仪表盘；退出编辑布局时把布局写回界面状态。
+/// 新手引导清单在每次加载仪表盘时检查未完成的步骤，进度和关闭状态写回界面状态。
+struct DashboardFlow {
+    window: slint::Weak<MainWindow>,
+    registry: Arc<DashboardCardRegistry>,
+    ui_state: Rc<RefCell<UiState>>,
+    navigation: Rc<RefCell<NavigationController>>,
+    /// 当前登录用户名与其仪表盘
+    current: RefCell<Option<(String, DashboardViewModel)>>,
+    onboarding: RefCell<OnboardingChecklistViewModel>,
+}
+
+impl DashboardFlow {
+    /// 为登录用户构建并加载仪表盘
+    fn open(&self, user: &User) {
+        let layout = self
+            .ui_state
+            .borrow()
+            .dashboard_layouts
+            .get(&user.username)
+            .cloned()
+            .unwrap_or_default();
+        let view_model = DashboardViewModel::new(self.registry.clone(), user.role, &layout);
+        *self.current.borrow_mut() = Some((user.username.clone(), view_model));
+        self.reload();
+    }
+
+    /// 重新加载可见卡片的数据，并检查新手引导的未完成步骤
+    fn reload(&self) {
+        if let Some((_, view_model)) = self.current.borrow_mut().as_mut() {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(view_model.load())
+            });
+        }
+        let mut onboarding = self.onboarding.borrow_mut();
+        let loaded = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(onboarding.load())
+        });
+        match loaded {
+            Ok(true) => self.ui_state.borrow_mut().onboarding = onboarding.state().clone(),
+            Ok(false) => {}
+            Err(e) => warn!("无法检查新手引导进度: {}", e),
+        }
+        drop(onboarding);
+        self.sync();
+    }
+
+    /// 跳转到完成引导步骤的界面
+    fn open_onboarding_step(&self, id: &str) {
+        let Some(step) = OnboardingStep::from_id(id) else {
+            return;
+        };
+        self.onboarding
+            .borrow()
+            .jump(step, &mut self.navigation.borrow_mut());
+    }
+
+    /// 关闭新手引导清单
+    fn dismiss_onboarding(&self) {
+        let mut onboarding = self.onboarding.borrow_mut();
+        onboarding.dismiss();
+        self.ui_state.borrow_mut().onboarding = onboarding.state().clone();
+        drop(onboarding);
+        self.sync();
+    }
+
+    /// 修改仪表盘；闭包返回 `true` 时同步到主窗口
+    fn update(&self, change: impl FnOnce(&mut DashboardViewModel) -> bool) {
+        let changed = match self.current.borrow_mut().as_mut() {
+            Some((_, view_model)) => change(view_model),
+            None => false,
+        };
+        if changed {
+            self.sync();
+        }
+    }
+
+    /// 退出编辑布局模式并保存布局
+    fn finish_editing(&self) {
+        let mut current = self.current.borrow_mut();
+        let Some((username, view_model)) = current.as_mut() else {
+            return;
+        };
+        let layout = view_model.finish_editing();
+        self.ui_state
+            .borrow_mut()
+            .dashboard_layouts
+            .insert(username.clone(), layout);
+        drop(current);
+        self.sync();
+    }
+
+    /// 同步卡片到主窗口
+    fn sync(&self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let current = self.current.borrow();
+        let Some((_, view_model)) = current.as_ref() else {
+            return;
+        };
+        let items: Vec<DashboardCardItem> = view_model
+            .cards
+            .iter()
+            .map(|card| {
+                let (value, detail) = match (&card.content, &card.error) {
+                    (Some(content), _) => (
+                        content.value.clone(),
+                        content.detail.clone().unwrap_or_default(),
+                    ),
+                    (None, Some(error)) => ("—".to_string(), error.clone()),
+                    (None, None) => ("…".to_string(), "加载中".to_string()),
+                };
+                DashboardCardItem {
+                    id: card.id.into(),
+                    title: card.title.into(),
+                    visible: card.visible,
+                    value: value.into(),
+                    detail: detail.into(),
+                }
+            })
+            .collect();
+        window.set_dashboard_cards(Rc::new(slint::VecModel::from(items)).into());
+        window.set_dashboard_editing(view_model.editing);
+
+        let onboarding = self.onboarding.borrow();
+        let steps: Vec<OnboardingStepItem> = onboarding
+            .steps
+            .iter()
+            .map(|view| OnboardingStepItem {
+                id: view.step.id().into(),
+                title: view.title.into(),
+                done: view.done,
+            })
+            .collect();
+        window.set_onboarding_visible(onboarding.visible());
+        window.set_onboarding_progress(onboarding.progress_text().into());
+        window.set_onboarding_steps(Rc::new(slint::VecModel::from(steps)).into());
+    }
+}
+
+/// 正在导出的诊断包
+struct BundleExport {
+    out_path: PathBuf,
+    /// 需要写入的部分数
+    total: u64,
+    progress: ExportProgress,
+    dialog: ProgressDialog,
+    result: Receiver<CoreResult<DiagnosticsBundleSummary>>,
+}
+
+/// 数据库整理提示流程
+///
+/// 管理员登录后检查可释放空间，超过阈值时提示一次；忽略后按界面状态中的记录抑制提示。
+/// 同时根据连接池使用记录在诊断信息中给出最大连接数建议，应用后写回配置文件；
+/// 诊断信息中还可以在后台检查内置迁移的回滚SQL，以及导出发给技术支持的诊断包。
+struct MaintenanceFlow {
+    window: slint::Weak<MainWindow>,
+    database: DatabaseManager,
+    ui_state: Rc<RefCell<UiState>>,
+    threshold: ReclaimThreshold,
+    /// 提示时的可释放空间
+    reclaimable: Cell<u64>,
+    /// 建议的最大连接数上限
+    max_connections_cap: u32,
+    pool_size: RefCell<PoolSizeAdvice>,
+    /// 配置文件（未知时不能应用建议）
+    config_file: Option<PathBuf>,
+    diagnostics: Arc<DiagnosticsBundleExporter>,
+    bundle_export: RefCell<Option<BundleExport>>,
+    /// 轮询诊断包导出进度
+    bundle_timer: slint::Timer,
+}
+
+impl MaintenanceFlow {
+    /// 检查是否需要提示整理
+    fn check(&self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let stats = match self.database.get_database_stats() {
+            Ok(stats) => stats,
+            Err(e) => {
+                error!("无法估算可释放空间: {}", e);
+                return;
+            }
+        };
+        self.reclaimable.set(stats.reclaimable_bytes);
+        let suggestion = self.ui_state.borrow_mut().reclaim_prompt.evaluate(
+            &self.threshold,
+            stats.reclaimable_bytes,
+            stats.file_size_bytes,
+        );
+        window.set_reclaim_suggestion(suggestion.unwrap_or_default().into());
+        self.check_pool_size();
+    }
+
+    /// 根据连接池使用记录更新最大连接数建议
+    fn check_pool_size(&self) {
+        match self.database.suggest_pool_size(self.max_connections_cap) {
+            Ok(suggestion) => {
+                *self.pool_size.borrow_mut() = PoolSizeAdvice::from_suggestion(suggestion.as_ref());
+            }
+            Err(e) => error!("无法读取连接池使用记录: {}", e),
+        }
+        self.sync_pool_size();
+    }
+
+    fn sync_pool_size(&self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let advice = self.pool_size.borrow();
+        window.set_pool_size_advice(advice.message.clone().into());
+        window.set_pool_size_can_apply(advice.can_apply && self.config_file.is_some());
+        window.set_pool_size_restart_required(advice.restart_required);
+    }
+
+    /// 把建议的最大连接数写回配置文件（重新启动后生效）
+    fn apply_pool_size(&self) {
+        let Some(path) = &self.config_file else {
+            return;
+        };
+        let suggested = self.pool_size.borrow().suggested;
+        match AppConfig::save_pool_size(path, suggested) {
+            Ok(()) => {
+                self.pool_size.borrow_mut().applied();
+                self.sync_pool_size();
+            }
+            Err(e) => {
+                error!("保存最大连接数失败: {:#}", e);
+                if let Some(window) = self.window.upgrade() {
+                    window.set_status_message(format!("保存最大连接数失败: {e}").into());
+                    window.set_status_kind("error".into());
+                }
+            }
+        }
+    }
+
+    /// 忽略提示
+    fn dismiss(&self) {
+        self.ui_state
+            .borrow_mut()
+            .reclaim_prompt
+            .dismiss(self.reclaimable.get());
+        if let Some(window) = self.window.upgrade() {
+            window.set_reclaim_suggestion("".into());
+        }
+    }
+
+    /// 在后台线程检查迁移回滚，结果显示在诊断信息中
+    fn verify_migrations(&self) {
+        if let Some(window) = self.window.upgrade() {
+            window.set_migration_check_running(true);
+        }
+        let window_weak = self.window.clone();
+        std::thread::spawn(move || {
+            let report = DatabaseManager::verify_migrations();
+            let result = window_weak.upgrade_in_event_loop(move |window| {
+                window.set_migration_check_running(false);
+                match report {
+                    Ok(report) => {
+                        let items: Vec<MigrationCheckItem> = report
+                            .checks
+                            .iter()
+                            .map(|check| {
+                                let row = MigrationCheckRow::from_check(check);
+                                MigrationCheckItem {
+                                    version: row.version.into(),
+                                    name: row.name.into(),
+                                    status: row.status.into(),
+                                    kind: row.kind.into(),
+                                    detail: row.detail.into(),
+                                }
+                            })
+                            .collect();
+                        window.set_migration_checks(Rc::new(slint::VecModel::from(items)).into());
+                        window.set_migration_check_summary(
+                            migration_report_summary(&report).into(),
+                        );
+                    }
+                    Err(e) => {
+                        error!("迁移回滚检查失败: {:#}", e);
+                        window.set_migration_check_summary(format!("检查失败：{e}").into());
+                    }
+                }
+            });
+            if let Err(e) = result {
+                error!("无法显示迁移回滚检查结果: {}", e);
+            }
+        });
+    }
+
+    /// 在后台线程导出诊断包，进度显示在进度对话框中
+    fn export_diagnostics_bundle(self: &Rc<Self>, include_db_copy: bool) {
+        if self.bundle_export.borrow().is_some() {
+            return;
+        }
+        let out_path = self.diagnostics.default_path(chrono::Local::now());
+        let progress = ExportProgress::new();
+        let cancel = CancellationToken::new();
+        let (tx, result) = mpsc::channel();
+        let runtime = tokio::runtime::Handle::current();
+        std::thread::spawn({
+            let diagnostics = self.diagnostics.clone();
+            let out_path = out_path.clone();
+            let progress = progress.clone();
+            let cancel = cancel.clone();
+            move || {
+                let exported = runtime.block_on(diagnostics.export_bundle(
+                    &out_path,
+                    include_db_copy,
+                    &progress,
+                    &cancel,
+                ));
+                let _ = tx.send(exported);
+            }
+        });
+        *self.bundle_export.borrow_mut() = Some(BundleExport {
+            out_path,
+            total: section_count(include_db_copy),
+            progress,
+            dialog: ProgressDialog::new("导出诊断包", cancel),
+            result,
+        });
+        let flow = Rc::downgrade(self);
+        self.bundle_timer.start(
+            slint::TimerMode::Repeated,
+            std::time::Duration::from_millis(200),
+            move || {
+                if let Some(flow) = flow.upgrade() {
+                    flow.poll_bundle_export();
+                }
+            },
+        );
+        self.sync_bundle_export();
+    }
+
+    /// 点击进度对话框的“取消”
+    fn cancel_bundle_export(&self) {
+        if let Some(export) = self.bundle_export.borrow_mut().as_mut() {
+            export.dialog.cancel();
+        }
+        self.sync_bundle_export();
+    }
+
+    /// 更新导出进度；导出结束后关闭进度对话框并显示结果
+    fn poll_bundle_export(&self) {
+        let finished = {
+            let mut export = self.bundle_export.borrow_mut();
+            let Some(export) = export.as_mut() else {
+                return;
+            };
+            export
+                .dialog
+                .set_progress(export.progress.rows_written(), Some(export.total));
+            let result = match export.result.try_recv() {
+                Ok(result) => Some(result),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => Some(Err(crate::core::CoreError::Other(
+                    "导出线程异常退出".to_string(),
+                ))),
+            };
+            result.map(|result| {
+                export.dialog.finish(&result);
+                match &result {
+                    Ok(summary) if summary.failed.is_empty() => {
+                        format!("已导出到 {}", export.out_path.display())
+                    }
+                    Ok(summary) => format!(
+                        "已导出到 {}，{} 项生成失败（见包内的 .error.txt）",
+                        export.out_path.display(),
+                        summary.failed.len()
+                    ),
+                    Err(e) => {
+                        error!("导出诊断包失败: {}", e);
+                        export.dialog.message()
+                    }
+                }
+            })
+        };
+        if let Some(text) = finished {
+            self.bundle_timer.stop();
+            self.bundle_export.borrow_mut().take();
+            if let Some(window) = self.window.upgrade() {
+                window.set_diagnostics_bundle_result(text.into());
+            }
+        }
+        self.sync_bundle_export();
+    }
+
+    fn sync_bundle_export(&self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let export = self.bundle_export.borrow();
+        let dialog = export.as_ref().map(|export| &export.dialog);
+        window.set_progress_visible(dialog.is_some_and(ProgressDialog::is_open));
+        window.set_diagnostics_bundle_running(export.is_some());
+        if let Some(dialog) = dialog {
+            window.set_progress_title(dialog.title.clone().into());
+            window.set_progress_message(dialog.message().into());
+            window.set_progress_value(
+                dialog
+                    .percent()
+                    .map_or(-1.0, |percent| f32::from(percent) / 100.0),
+            );
+            window.set_progress_can_cancel(dialog.can_cancel());
+        }
+    }
+
+    /// 在后台线程整理数据库，进度显示在状态栏
+    fn compact(&self) {
+        if let Some(window) = self.window.upgrade() {
+            window.set_reclaim_suggestion("".into());
+        }
+        let database = self.database.clone();
+        let window_weak = self.window.clone();
+        std::thread::spawn(move || {
+            let report = |text: String, kind: &'static str| {
+                let result = window_weak.upgrade_in_event_loop(move |window| {
+                    window.set_status_message(text.into());
+                    window.set_status_kind(kind.into());
+                });
+                if let Err(e) = result {
+                    error!("无法更新整理进度: {}", e);
+                }
+            };
+            if let Err(e) = database.compact_database(|stage: CompactStage| {
+                report(stage.label(), "");
+            }) {
+                error!("数据库整理失败: {}", e);
+                report(format!("数据库整理失败：{e}"), "error");
+            }
+        });
+    }
+}
+
+/// 外观设置流程
+///
+/// 设置界面拖动字号滑块或勾选高对比度时即时更新 `Theme` 全局预览，“应用”后写回配置文件。
+struct AppearanceFlow {
+    window: slint::Weak<MainWindow>,
+    settings: RefCell<AppearanceSettings>,
+    /// 配置文件（未知时只在本次运行中生效）
+    config_file: Option<PathBuf>,
+}
+
+/// 把颜色 `0xRRGGBB` 转为界面颜色
+const fn rgb(color: u32) -> slint::Color {
+    let [_, r, g, b] = color.to_be_bytes();
+    slint::Color::from_rgb_u8(r, g, b)
+}
+
+impl AppearanceFlow {
+    /// 把主题写入界面的 `Theme` 全局
+    fn apply_theme(window: &MainWindow, theme: ThemeManager) {
+        let tokens = theme.tokens();
+        let global = window.global::<Theme<'_>>();
+        global.set_font_scale(theme.font_scale());
+        global.set_high_contrast(theme.is_high_contrast());
+        global.set_text(rgb(tokens.text));
+        global.set_text_muted(rgb(tokens.text_muted));
+        global.set_background(rgb(tokens.background));
+        global.set_surface(rgb(tokens.surface));
+        global.set_surface_muted(rgb(tokens.surface_muted));
+        global.set_border(rgb(tokens.border));
+        global.set_accent(rgb(tokens.accent));
+        global.set_danger(rgb(tokens.danger));
+        global.set_success(rgb(tokens.success));
+        global.set_focus_ring(rgb(tokens.focus_ring));
+        global.set_focus_ring_width(tokens.focus_ring_width);
+    }
+
+    /// 把预览同步到界面
+    fn sync(&self) {
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let settings = self.settings.borrow();
+        Self::apply_theme(&window, settings.preview);
+        window.set_appearance_font_scale(settings.preview.font_scale());
+        window.set_appearance_font_scale_label(settings.preview.font_scale_label().into());
+        window.set_appearance_high_contrast(settings.preview.is_high_contrast());
+        window.set_appearance_dirty(settings.is_dirty());
+    }
+
+    fn preview_font_scale(&self, scale: f32) {
+        self.settings.borrow_mut().preview_font_scale(scale);
+        self.sync();
+    }
+
+    fn set_high_contrast(&self, enabled: bool) {
+        self.settings.borrow_mut().set_high_contrast(enabled);
+        self.sync();
+    }
+
+    fn apply(&self) {
+        let theme = self.settings.borrow_mut().apply();
+        self.sync();
+        let Some(window) = self.window.upgrade() else {
+            return;
+        };
+        let result = self.config_file.as_ref().map_or_else(
+            || {
+                warn!("未知配置文件位置，外观设置只在本次运行中生效");
+                Ok(())
+            },
+            |path| AppConfig::save_appearance(path, &theme),
+        );
+        match result {
+            Ok(()) => {
+                window.set_status_message("外观设置已保存".into());
+                window.set_status_kind("".into());
+            }
+            Err(e) => {
+                error!("保存外观设置失败: {:#}", e);
+                window.set_status_message(format!("保存外观设置失败: {e}").into());
+                window.set_status_kind("error".into());
+            }
+        }
+    }
+
+    fn revert(&self) {
+        self.settings.borrow_mut().revert();
+        self.sync();
+    }
+}
+
+/// 把启动各阶段耗时显示到诊断信息界面；`running` 表示后台阶段尚未完成
+fn set_startup_phases(window: &MainWindow, timings: &[PhaseTiming], running: bool) {
+    let items: Vec<StartupPhaseItem> = timings
+        .iter()
+        .map(|timing| StartupPhaseItem {
+            name: timing.phase.label().into(),
+            duration: timing.duration_text().into(),
+            deferred: timing.after_show,
+            failed: timing.error.is_some(),
+            detail: timing.error.clone().unwrap_or_default().into(),
+        })
+        .collect();
+    let time_to_show: std::time::Duration = timings
+        .iter()
+        .filter(|timing| !timing.after_show)
+        .map(|timing| timing.duration)
+        .sum();
+    window.set_startup_phases(Rc::new(slint::VecModel::from(items)).into());
+    window.set_startup_summary(format!("窗口显示前用时 {} ms", time_to_show.as_millis()).into());
+    window.set_startup_deferred_running(running);
+}
+
 /// `MiniCRM` 应用程序主结构
 ///
 /// 负责管理应用程序的生命周期，包括初始化、运行和清理。
@@ -21,6 +666,10 @@ pub struct App {
     config: AppConfig,
     /// 数据库管理器
     database: Option<DatabaseManager>,
+    /// 配置文件路径（保存设置界面的修改）
+    config_file: Option<PathBuf>,
+    /// 启动计时器（未指定时在运行时创建）
+    profiler: Option<StartupProfiler>,
 }
 
 impl App {
@@ -36,10 +685,149 @@ impl App {
         let config = AppConfig::load()?;
         debug!("配置加载成功: {:?}", config);
 
-        Ok(Self {
+        Ok(Self::with_config(config))
+    }
+
+    /// 使用已加载的配置创建应用程序实例
+    #[must_use]
+    pub const fn with_config(config: AppConfig) -> Self {
+        Self {
             config,
             database: None,
-        })
+            config_file: None,
+            profiler: None,
+        }
+    }
+
+    /// 指定配置文件路径，设置界面的修改写回该文件
+    #[must_use]
+    pub fn with_config_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+
+    /// 指定启动计时器，加载配置和启动自检等在创建应用之前的阶段可先记入
+    #[must_use]
+    pub fn with_profiler(mut self, profiler: StartupProfiler) -> Self {
+        self.profiler = Some(profiler);
+        self
+    }
+
+    /// 启动自检
+    ///
+    /// 在显示任何窗口之前检查配置、各目录写权限、数据库和磁盘空间，结果见
+    /// [`PreflightReport`]。
+    #[must_use]
+    pub fn preflight(&self) -> PreflightReport {
+        preflight::run(&self.config)
+    }
+
+    /// 显示自检失败窗口，用户关闭后返回
+    ///
+    /// 无法创建窗口（如无图形环境）时把失败项写到标准错误。
+    pub fn show_preflight_failures(report: &PreflightReport) {
+        let window = match PreflightErrorWindow::new() {
+            Ok(window) => window,
+            Err(e) => {
+                warn!("无法创建自检失败窗口，改为输出到标准错误: {}", e);
+                eprint!("{}", report.failure_text());
+                return;
+            }
+        };
+        let failures: Vec<PreflightFailure> = report
+            .failures()
+            .map(|item| PreflightFailure {
+                name: item.name.clone().into(),
+                message: item.message.clone().into(),
+            })
+            .collect();
+        window.set_failures(Rc::new(slint::VecModel::from(failures)).into());
+        window.on_quit({
+            let window = window.as_weak();
+            move || {
+                if let Some(window) = window.upgrade() {
+                    window
+                        .hide()
+                        .unwrap_or_else(|e| warn!("无法关闭自检失败窗口: {}", e));
+                }
+            }
+        });
+        if let Err(e) = window.run() {
+            error!("自检失败窗口运行失败: {}", e);
+            eprint!("{}", report.failure_text());
+        }
+    }
+
+    /// 确定数据根目录，必要时迁移旧版本的数据
+    ///
+    /// 发现旧数据时，以 `--migrate-data` 启动则直接迁移，否则弹窗询问；不迁移或迁移
+    /// 失败时本次继续使用旧数据所在目录。在日志系统初始化之前调用，迁移结果由调用方
+    /// 在日志可用后记录。
+    ///
+    /// # Errors
+    ///
+    /// 无法确定数据根目录时返回错误。
+    pub fn prepare_data_root(options: &LaunchOptions) -> Result<(DataRoot, Option<DataMigration>)> {
+        let root = DataRoot::select(options)?;
+        let Some(legacy) = LegacyData::find(&LegacyData::candidates(), &root) else {
+            return Ok((root, None));
+        };
+        let from = legacy.base().to_path_buf();
+        if !options.migrate_data && !Self::confirm_data_migration(&legacy.prompt(&root)) {
+            return Ok((DataRoot::at(&from), Some(DataMigration::Declined { from })));
+        }
+        match legacy.migrate(&root) {
+            Ok(report) => Ok((root, Some(DataMigration::Migrated { from, report }))),
+            Err(e) => Ok((
+                DataRoot::at(&from),
+                Some(DataMigration::Failed {
+                    from,
+                    error: format!("{e:#}"),
+                }),
+            )),
+        }
+    }
+
+    /// 询问是否迁移旧数据，用户确认时返回 `true`
+    ///
+    /// 无法创建窗口（如无图形环境）时不迁移，并提示使用 `--migrate-data`。
+    fn confirm_data_migration(message: &str) -> bool {
+        let window = match DataMigrationWindow::new() {
+            Ok(window) => window,
+            Err(e) => {
+                eprintln!("{message}\n无法显示迁移确认窗口（{e}），可使用 --migrate-data 启动以迁移。");
+                return false;
+            }
+        };
+        let accepted = Rc::new(Cell::new(false));
+        window.set_message(message.into());
+        window.on_accept({
+            let window = window.as_weak();
+            let accepted = accepted.clone();
+            move || {
+                accepted.set(true);
+                if let Some(window) = window.upgrade() {
+                    window
+                        .hide()
+                        .unwrap_or_else(|e| warn!("无法关闭迁移确认窗口: {}", e));
+                }
+            }
+        });
+        window.on_decline({
+            let window = window.as_weak();
+            move || {
+                if let Some(window) = window.upgrade() {
+                    window
+                        .hide()
+                        .unwrap_or_else(|e| warn!("无法关闭迁移确认窗口: {}", e));
+                }
+            }
+        });
+        if let Err(e) = window.run() {
+            error!("迁移确认窗口运行失败: {}", e);
+            return false;
+        }
+        accepted.get()
     }
 
     /// 运行应用程序主循环
@@ -50,27 +838,483 @@ impl App {
     pub fn run(mut self) -> Result<()> {
         info!("启动应用程序主循环");
 
-        // 初始化数据库
-        info!("初始化数据库...");
-        let database = DatabaseManager::initialize(&self.config)?;
-        self.database = Some(database);
+        // 初始化数据库（在窗口显示前完成连接预热，避免首个操作等待建连）
+        info!(
+            "初始化数据库并预热 {} 个连接...",
+            self.config.database.warm_up_connections
+        );
+        let profiler = self.profiler.clone().unwrap_or_default();
+        let database = Self::open_database(&self.config, &profiler)?;
+        self.database = Some(database.clone());
         info!("数据库初始化完成");
 
         // 创建主窗口（在async上下文之外）
-        Self::run_ui().map_err(|e| anyhow::anyhow!("UI运行失败: {}", e))
+        Self::run_ui(&self.config, self.config_file.as_deref(), database, profiler)
+            .map_err(|e| anyhow::anyhow!("UI运行失败: {}", e))
+    }
+
+    /// 初始化数据库；执行迁移期间显示启动画面
+    ///
+    /// 无法创建窗口（如无图形环境）时迁移进度只写入日志。
+    fn open_database(config: &AppConfig, profiler: &StartupProfiler) -> Result<DatabaseManager> {
+        let splash = match MigrationSplash::new() {
+            Ok(splash) => splash,
+            Err(e) => {
+                warn!("无法创建启动画面，迁移进度只写入日志: {}", e);
+                return DatabaseManager::with_profiler(config, profiler);
+            }
+        };
+        // 迁移不能中途取消，启动画面不响应关闭
+        splash
+            .window()
+            .on_close_requested(|| slint::CloseRequestResponse::KeepWindowShown);
+
+        let (tx, rx) = std::sync::mpsc::channel::<MigrationProgress>();
+        let worker = {
+            let config = config.clone();
+            let profiler = profiler.clone();
+            std::thread::spawn(move || {
+                DatabaseManager::with_migration_progress(&config, tx, &profiler)
+            })
+        };
+
+        // 只有开始执行迁移时才显示启动画面；迁移线程结束（发送端释放）后退出事件循环
+        let poll_timer = slint::Timer::default();
+        poll_timer.start(
+            slint::TimerMode::Repeated,
+            std::time::Duration::from_millis(50),
+            {
+                let splash_weak = splash.as_weak();
+                move || {
+                    let Some(splash) = splash_weak.upgrade() else {
+                        return;
+                    };
+                    loop {
+                        match rx.try_recv() {
+                            Ok(event) => {
+                                if matches!(event, MigrationProgress::Started { .. }) {
+                                    splash
+                                        .show()
+                                        .unwrap_or_else(|e| warn!("无法显示启动画面: {}", e));
+                                }
+                                splash.set_step(event.message().into());
+                                splash.set_progress(event.fraction());
+                            }
+                            Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                                slint::quit_event_loop()
+                                    .unwrap_or_else(|e| error!("无法结束启动画面: {}", e));
+                                break;
+                            }
+                        }
+                    }
+                }
+            },
+        );
+        slint::run_event_loop_until_quit()
+            .map_err(|e| anyhow::anyhow!("启动画面事件循环运行失败: {}", e))?;
+        poll_timer.stop();
+        splash
+            .hide()
+            .unwrap_or_else(|e| warn!("无法关闭启动画面: {}", e));
+
+        worker
+            .join()
+            .map_err(|_| anyhow::anyhow!("数据库初始化线程异常退出"))?
+    }
+
+    /// 按配置启动内嵌REST API；启动失败只记录日志，界面照常运行
+    #[cfg(feature = "api")]
+    fn start_api(
+        config: &AppConfig,
+        connection: &crate::infrastructure::database::DatabaseConnection,
+    ) -> Option<crate::api::ApiServer> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current()
+                .block_on(AppContext::start_api(config.clone(), connection))
+        })
+        .unwrap_or_else(|e| {
+            error!("REST API 启动失败: {}", e);
+            None
+        })
+    }
+
+    /// 登录；尚无任何用户时以输入的账号创建管理员
+    fn sign_in(users: &SqliteUserService, username: &str, password: &str) -> CoreResult<User> {
+        let first_run = !users.has_users().unwrap_or(false);
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                if first_run {
+                    users
+                        .create_user(NewUser {
+                            username: username.to_string(),
+                            display_name: username.to_string(),
+                            role: UserRole::Admin,
+                            password: password.to_string(),
+                        })
+                        .await
+                } else {
+                    users.authenticate(username, password).await
+                }
+            })
+        })
     }
 
     /// 运行UI部分（同步函数）
-    fn run_ui() -> Result<()> {
+    #[allow(clippy::too_many_lines)] // 界面回调集中在此注册
+    fn run_ui(
+        config: &AppConfig,
+        config_file: Option<&Path>,
+        database: DatabaseManager,
+        profiler: StartupProfiler,
+    ) -> Result<()> {
+        let connection = database.connection();
         // 创建主窗口
-        let main_window =
-            MainWindow::new().map_err(|e| anyhow::anyhow!("创建主窗口失败: {}", e))?;
+        let main_window = profiler
+            .time(StartupPhase::UiConstruct, MainWindow::new)
+            .map_err(|e| anyhow::anyhow!("创建主窗口失败: {}", e))?;
+        let context_started = std::time::Instant::now();
 
         // 设置窗口属性
         main_window.set_status_message("数据库连接正常，系统就绪".into());
 
+        // 应用锁
+        let idle_minutes = config.security.lock_idle_minutes;
+        let app_lock = Arc::new(AppLock::new(
+            Arc::new(FilePasscodeStore::in_dir(&config.security.config_dir)),
+            Arc::new(SystemClock),
+            (idle_minutes > 0).then(|| chrono::Duration::minutes(i64::from(idle_minutes))),
+        ));
+        let lock_screen = Rc::new(RefCell::new(LockScreenViewModel::new(app_lock.clone())));
+        main_window.set_locked(lock_screen.borrow().is_visible());
+        main_window.set_lock_forgot_hint(
+            LockScreenViewModel::forgot_passcode_hint(PASSCODE_FILE_NAME).into(),
+        );
+
+        // 登录
+        let registry = Arc::new(builtin_cards(&connection));
+        let onboarding_probe = Arc::new(DatabaseOnboardingProbe::new(
+            connection.clone(),
+            config.database.backups_dir.clone(),
+        ));
+        // 命令总线与界面共用登录会话，应用锁作为守卫：锁定期间拒绝所有命令
+        let context = AppContext::open(config.clone(), &connection, app_lock)?;
+        // 内嵌REST API：独立上下文，以令牌对应的服务账号执行
+        #[cfg(feature = "api")]
+        let _api_server = Self::start_api(config, &connection);
+        let users = SqliteUserService::new(connection);
+        main_window.set_login_first_run(!users.has_users().unwrap_or(false));
+
         // 设置回调函数
         let window_weak = main_window.as_weak();
+
+        // 导航：恢复上次打开的视图
+        let ui_state_path = UiState::path_in(&config.security.config_dir);
+        let ui_state = Rc::new(RefCell::new(UiState::load(&ui_state_path)));
+        let initial_route = ui_state.borrow().last_route.clone().unwrap_or_default();
+        main_window.set_current_view(initial_route.view_name().into());
+        main_window.set_view_title(initial_route.title().into());
+        let navigation = Rc::new(RefCell::new(
+            NavigationController::new(initial_route)
+                .with_recent_items(ui_state.borrow().restore_recent_items()),
+        ));
+        let forms = FormRegistry::new();
+        navigation.borrow_mut().add_guard(Box::new(forms.clone()));
+        let unsaved = Rc::new(UnsavedChangesFlow {
+            window: window_weak.clone(),
+            navigation: navigation.clone(),
+            forms,
+            closing: Cell::new(false),
+        });
+        navigation.borrow_mut().on_route_changed({
+            let window_weak = window_weak.clone();
+            move |route| {
+                if let Some(window) = window_weak.upgrade() {
+                    window.set_current_view(route.view_name().into());
+                    window.set_view_title(route.title().into());
+                }
+            }
+        });
+
+        main_window.on_navigate({
+            let navigation = navigation.clone();
+            let unsaved = unsaved.clone();
+            move |view| {
+                let Some(route) = Route::from_view_name(&view) else {
+                    return;
+                };
+                log_action!("navigation", "navigate", route = route.view_name());
+                navigation.borrow_mut().navigate_to(route);
+                unsaved.sync();
+            }
+        });
+
+        main_window.on_go_back({
+            let navigation = navigation.clone();
+            let unsaved = unsaved.clone();
+            move || {
+                log_action!("navigation", "back");
+                navigation.borrow_mut().back();
+                unsaved.sync();
+            }
+        });
+
+        main_window.on_go_forward({
+            let navigation = navigation.clone();
+            let unsaved = unsaved.clone();
+            move || {
+                log_action!("navigation", "forward");
+                navigation.borrow_mut().forward();
+                unsaved.sync();
+            }
+        });
+
+        main_window.on_confirm_save({
+            let unsaved = unsaved.clone();
+            move || unsaved.resolve(UnsavedChoice::Save)
+        });
+        main_window.on_confirm_discard({
+            let unsaved = unsaved.clone();
+            move || unsaved.resolve(UnsavedChoice::Discard)
+        });
+        main_window.on_cancel_discard({
+            let unsaved = unsaved.clone();
+            move || unsaved.resolve(UnsavedChoice::Cancel)
+        });
+
+        // 关闭窗口前检查未保存的修改
+        main_window.window().on_close_requested({
+            let unsaved = unsaved;
+            move || {
+                if unsaved.forms.has_unsaved_changes() {
+                    unsaved.closing.set(true);
+                    unsaved.sync();
+                    slint::CloseRequestResponse::KeepWindowShown
+                } else {
+                    slint::CloseRequestResponse::HideWindow
+                }
+            }
+        });
+
+        // 仪表盘
+        let onboarding = OnboardingChecklistViewModel::new(
+            onboarding_probe,
+            ui_state.borrow().onboarding.clone(),
+        );
+        let dashboard = Rc::new(DashboardFlow {
+            window: window_weak.clone(),
+            registry,
+            ui_state: ui_state.clone(),
+            navigation: navigation.clone(),
+            current: RefCell::new(None),
+            onboarding: RefCell::new(onboarding),
+        });
+        // 回到仪表盘时重新加载（引导步骤在此时检查，不轮询）
+        navigation.borrow_mut().on_route_changed({
+            let dashboard = dashboard.clone();
+            move |route| {
+                if *route == Route::Dashboard {
+                    dashboard.reload();
+                }
+            }
+        });
+        main_window.on_open_onboarding_step({
+            let dashboard = dashboard.clone();
+            move |id| {
+                log_action!("dashboard", "open_onboarding_step", step = id.as_str());
+                dashboard.open_onboarding_step(&id);
+            }
+        });
+        main_window.on_dismiss_onboarding({
+            let dashboard = dashboard.clone();
+            move || {
+                log_action!("dashboard", "dismiss_onboarding", setting = "onboarding");
+                dashboard.dismiss_onboarding();
+            }
+        });
+        main_window.on_edit_dashboard_layout({
+            let dashboard = dashboard.clone();
+            move || {
+                dashboard.update(|view_model| {
+                    view_model.begin_editing();
+                    true
+                });
+            }
+        });
+        main_window.on_finish_dashboard_layout({
+            let dashboard = dashboard.clone();
+            move || {
+                log_action!("dashboard", "save_layout", setting = "dashboard_layout");
+                dashboard.finish_editing();
+            }
+        });
+        main_window.on_move_dashboard_card({
+            let dashboard = dashboard.clone();
+            move |id, index| {
+                let index = usize::try_from(index).unwrap_or(0);
+                dashboard.update(|view_model| view_model.move_card(&id, index));
+            }
+        });
+        main_window.on_set_dashboard_card_visible({
+            let dashboard = dashboard.clone();
+            move |id, visible| {
+                dashboard.update(|view_model| view_model.set_card_visible(&id, visible));
+                // 重新显示的卡片需要加载数据
+                if visible {
+                    dashboard.reload();
+                }
+            }
+        });
+
+        // 数据库被外部程序修改：提示并重新加载界面数据
+        main_window.on_external_database_change({
+            let dashboard = dashboard.clone();
+            move || dashboard.reload()
+        });
+        if let Some(guard) = database.file_guard() {
+            let window_weak = window_weak.clone();
+            guard.subscribe(move |change| {
+                let text = change.message();
+                let kind = if change.read_only { "error" } else { "warning" };
+                let result = window_weak.upgrade_in_event_loop(move |window| {
+                    window.set_status_message(text.into());
+                    window.set_status_kind(kind.into());
+                    window.invoke_external_database_change();
+                });
+                if let Err(e) = result {
+                    error!("无法提示数据库外部修改: {}", e);
+                }
+            });
+        }
+
+        // 外观设置
+        let appearance = Rc::new(AppearanceFlow {
+            window: window_weak.clone(),
+            settings: RefCell::new(AppearanceSettings::new(config.ui.theme_manager())),
+            config_file: config_file.map(Path::to_path_buf),
+        });
+        appearance.sync();
+        main_window.on_preview_font_scale({
+            let appearance = appearance.clone();
+            move |scale| appearance.preview_font_scale(scale)
+        });
+        main_window.on_set_high_contrast({
+            let appearance = appearance.clone();
+            move |enabled| appearance.set_high_contrast(enabled)
+        });
+        main_window.on_apply_appearance({
+            let appearance = appearance.clone();
+            move || {
+                log_action!("settings", "apply_appearance");
+                appearance.apply();
+            }
+        });
+        main_window.on_revert_appearance({
+            let appearance = appearance;
+            move || appearance.revert()
+        });
+
+        // 数据库整理提示
+        let diagnostics = Arc::new(DiagnosticsBundleExporter::new(
+            config.clone(),
+            database.clone(),
+        ));
+        let maintenance = Rc::new(MaintenanceFlow {
+            window: window_weak.clone(),
+            database,
+            ui_state: ui_state.clone(),
+            threshold: config.reclaim_threshold(),
+            reclaimable: Cell::new(0),
+            max_connections_cap: config.database.max_connections_cap,
+            pool_size: RefCell::new(PoolSizeAdvice::from_suggestion(None)),
+            config_file: config_file.map(Path::to_path_buf),
+            diagnostics,
+            bundle_export: RefCell::new(None),
+            bundle_timer: slint::Timer::default(),
+        });
+        main_window.on_compact_database({
+            let maintenance = maintenance.clone();
+            move || {
+                log_action!("maintenance", "compact_database");
+                maintenance.compact();
+            }
+        });
+        main_window.on_dismiss_reclaim_suggestion({
+            let maintenance = maintenance.clone();
+            move || {
+                log_action!("maintenance", "dismiss_reclaim_suggestion");
+                maintenance.dismiss();
+            }
+        });
+        main_window.on_verify_migrations({
+            let maintenance = maintenance.clone();
+            move || {
+                log_action!("maintenance", "verify_migrations");
+                maintenance.verify_migrations();
+            }
+        });
+        main_window.on_export_diagnostics_bundle({
+            let maintenance = maintenance.clone();
+            let current_user = context.current_user.clone();
+            let window_weak = window_weak.clone();
+            move |include_db_copy| {
+                log_action!("maintenance", "export_diagnostics_bundle");
+                if current_user.role() != Some(UserRole::Admin) {
+                    if let Some(window) = window_weak.upgrade() {
+                        window.set_diagnostics_bundle_result("只有管理员可以导出诊断包".into());
+                    }
+                    return;
+                }
+                maintenance.export_diagnostics_bundle(include_db_copy);
+            }
+        });
+        main_window.on_cancel_progress({
+            let maintenance = maintenance.clone();
+            move || {
+                log_action!("maintenance", "cancel_progress");
+                maintenance.cancel_bundle_export();
+            }
+        });
+        main_window.on_apply_pool_size({
+            let maintenance = maintenance.clone();
+            move || {
+                log_action!("maintenance", "apply_pool_size");
+                maintenance.apply_pool_size();
+            }
+        });
+
+        main_window.on_login({
+            let window_weak = window_weak.clone();
+            let maintenance = maintenance.clone();
+            move |username, password| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                log_action!("session", "login");
+                match Self::sign_in(&users, &username, &password) {
+                    Ok(user) => {
+                        window.set_current_user(user.display_name.clone().into());
+                        window.set_status_message(format!("欢迎，{}", user.display_name).into());
+                        window.set_status_kind("".into());
+                        window.set_login_message("".into());
+                        window.set_login_first_run(false);
+                        window.set_logged_in(true);
+                        dashboard.open(&user);
+                        if user.role == UserRole::Admin {
+                            maintenance.check();
+                        }
+                        context.sign_in(user);
+                    }
+                    Err(e) => {
+                        let message = UserMessage::from_error(&e);
+                        window.set_login_message(message.detail.clone().into());
+                        window.set_status_message(message.to_text().into());
+                        window.set_status_kind(message.kind.as_str().into());
+                    }
+                }
+            }
+        });
+
         main_window.on_show_about({
             let window_weak = window_weak.clone();
             move || {
@@ -80,6 +1324,70 @@ impl App {
             }
         });
 
+        let quote_codec = config
+            .security
+            .app_secret
+            .as_deref()
+            .and_then(|secret| HmacQuotePayloadCodec::new(secret).ok());
+        main_window.on_verify_quote({
+            let window_weak = window_weak.clone();
+            move |payload| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                log_action!("quotes", "verify_quote");
+                let (text, ok) = quote_codec.as_ref().map_or_else(
+                    || ("未配置应用密钥，无法校验报价单".to_string(), false),
+                    |codec| match codec.decode(&payload) {
+                        Ok(fp) => (
+                            format!(
+                                "签名有效：报价 {}，金额 {}，有效期至 {}",
+                                fp.quote_number,
+                                format_money(Money::from_cents(fp.total_cents)),
+                                fp.valid_until
+                            ),
+                            true,
+                        ),
+                        Err(e) => (e.to_string(), false),
+                    },
+                );
+                window.set_verify_result_text(text.into());
+                window.set_verify_result_ok(ok);
+            }
+        });
+
+        main_window.on_user_activity({
+            let lock_screen = lock_screen.clone();
+            move || lock_screen.borrow().on_user_activity()
+        });
+
+        main_window.on_unlock({
+            let window_weak = window_weak.clone();
+            let lock_screen = lock_screen.clone();
+            move |code| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                let mut view_model = lock_screen.borrow_mut();
+                view_model.passcode_input = code.to_string();
+                let unlocked = view_model.submit();
+                window.set_locked(!unlocked);
+                window.set_lock_message(view_model.message.clone().unwrap_or_default().into());
+            }
+        });
+
+        // 定时检查空闲超时
+        let idle_timer = slint::Timer::default();
+        idle_timer.start(slint::TimerMode::Repeated, std::time::Duration::from_secs(5), {
+            let window_weak = window_weak.clone();
+            let lock_screen = lock_screen;
+            move || {
+                if let Some(window) = window_weak.upgrade() {
+                    window.set_locked(lock_screen.borrow().tick());
+                }
+            }
+        });
+
         main_window.on_exit_application({
             move || {
                 if let Some(window) = window_weak.upgrade() {
@@ -90,10 +1398,28 @@ impl App {
             }
         });
 
+        profiler.record(StartupPhase::ContextBuild, context_started.elapsed(), None);
+
         // 显示窗口
         main_window
             .show()
             .map_err(|e| anyhow::anyhow!("显示主窗口失败: {}", e))?;
+        profiler.mark_shown();
+        profiler.log_summary();
+        set_startup_phases(&main_window, &profiler.timings(), true);
+
+        // 完整健康检查、全文索引校验和统计预热在窗口显示后于后台执行
+        DeferredStartup::for_database(&maintenance.database).spawn(profiler, {
+            let window_weak = main_window.as_weak();
+            move |timings| {
+                let result = window_weak.upgrade_in_event_loop(move |window| {
+                    set_startup_phases(&window, &timings, false);
+                });
+                if let Err(e) = result {
+                    error!("无法显示启动耗时: {}", e);
+                }
+            }
+        });
 
         info!("主窗口已显示，进入事件循环");
 
@@ -101,6 +1427,17 @@ impl App {
         slint::run_event_loop().map_err(|e| anyhow::anyhow!("事件循环运行失败: {}", e))?;
 
         info!("应用程序事件循环结束");
+
+        if let Err(e) = maintenance.database.record_pool_usage() {
+            error!("保存连接池使用记录失败: {:#}", e);
+        }
+
+        let mut ui_state = ui_state.borrow_mut();
+        ui_state.last_route = Some(navigation.borrow().current().clone());
+        ui_state.set_recent_items(navigation.borrow().recent_items());
+        if let Err(e) = ui_state.save(&ui_state_path) {
+            error!("保存界面状态失败: {}", e);
+        }
         Ok(())
     }
 }
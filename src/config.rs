@@ -2,9 +2,36 @@
 //!
 //! 负责加载和管理应用程序的各种配置选项。
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::application::MarginThresholds;
+use crate::core::{
+    ArchiveEntity, ArchivePolicy, BusinessCalendar, HeaderAliases, HolidayEntry, JobSchedule,
+    SizeGrowthThresholds, SlaEvaluator, SlaPolicy, StalenessEvaluator, StalenessThresholds, User,
+    UserRole, WorkingCalendar, DEFAULT_ANNIVERSARY_LEAD_DAYS, DEFAULT_BUSINESS_TIMEZONE,
+    DEFAULT_FIRST_TASK_DUE_DAYS,
+};
+use crate::data_dir::{DataRoot, LaunchOptions};
+use crate::infrastructure::archive::ArchivePaths;
+use crate::infrastructure::attachments::{
+    AttachmentPolicy, DEFAULT_ALLOWED_EXTENSIONS, DEFAULT_MAX_UPLOAD_BYTES,
+};
+use crate::infrastructure::repository::RetentionPolicy;
+use crate::infrastructure::security::{
+    default_secret_store, parse_secret_ref, secret_ref, SecretStore,
+};
+use crate::presentation::{clamp_font_scale, ReclaimThreshold, ThemeManager, ThemeVariant};
+
+/// 配置文件路径（相对于数据根目录）
+pub const CONFIG_FILE_PATH: &str = "config/minicrm.json";
 
 /// 应用程序主配置结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +42,42 @@ pub struct AppConfig {
     pub ui: UiConfig,
     /// 日志配置
     pub logging: LoggingConfig,
+    /// 内嵌REST API配置
+    #[serde(default)]
+    pub api: ApiConfig,
+    /// 安全配置
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// 业务日历配置
+    #[serde(default)]
+    pub calendar: CalendarConfig,
+    /// 每周摘要配置
+    #[serde(default)]
+    pub digest: DigestConfig,
+    /// 提醒配置
+    #[serde(default)]
+    pub reminders: ReminderConfig,
+    /// 新建线索配置
+    #[serde(default)]
+    pub leads: LeadConfig,
+    /// 记录归档配置
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    /// 审计记录和事件发件箱保留配置
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// 报价配置
+    #[serde(default)]
+    pub quote: QuoteConfig,
+    /// 附件上传配置
+    #[serde(default)]
+    pub attachments: AttachmentConfig,
+    /// 表格导入配置
+    #[serde(default)]
+    pub import: ImportConfig,
+    /// 售后邮箱收件配置
+    #[serde(default)]
+    pub email_intake: EmailIntakeConfig,
 }
 
 /// 数据库配置
@@ -24,8 +87,94 @@ pub struct DatabaseConfig {
     pub path: PathBuf,
     /// 连接池最大连接数
     pub max_connections: u32,
+    /// 诊断界面建议最大连接数时不超过该值
+    #[serde(default = "default_max_connections_cap")]
+    pub max_connections_cap: u32,
     /// 连接超时时间（秒）
     pub connection_timeout: u64,
+    /// 启动时预热的连接数
+    #[serde(default = "default_warm_up_connections")]
+    pub warm_up_connections: u32,
+    /// 是否启用内存映射读取，低内存机器可关闭
+    #[serde(default = "default_enable_mmap")]
+    pub enable_mmap: bool,
+    /// 附件目录
+    #[serde(default = "default_attachments_dir")]
+    pub attachments_dir: PathBuf,
+    /// 备份目录
+    #[serde(default = "default_backups_dir")]
+    pub backups_dir: PathBuf,
+    /// 启动自检要求数据目录所在磁盘至少保留的可用空间（MB）
+    #[serde(default = "default_min_free_space_mb")]
+    pub min_free_space_mb: u64,
+    /// `SQLite` 扩展
+    #[serde(default)]
+    pub extensions: DatabaseExtensionsConfig,
+    /// 可释放空间达到该值（MB）时提示整理数据库
+    #[serde(default = "default_reclaim_prompt_mb")]
+    pub reclaim_prompt_mb: u64,
+    /// 可释放空间占文件大小的比例达到该值时提示整理数据库
+    #[serde(default = "default_reclaim_prompt_ratio")]
+    pub reclaim_prompt_ratio: f64,
+    /// 数据库平均每天增长超过该值（MB）时警告
+    #[serde(default = "default_size_growth_mb_per_day")]
+    pub size_growth_mb_per_day: f64,
+    /// 数据库平均每天增长超过该百分比时警告
+    #[serde(default = "default_size_growth_percent_per_day")]
+    pub size_growth_percent_per_day: f64,
+    /// 检测到数据库被外部程序修改后切换为只读，直到重新启动
+    #[serde(default)]
+    pub read_only_on_external_change: bool,
+}
+
+/// `SQLite` 扩展配置（`[database.extensions]`）
+///
+/// 需要以 `sqlite-extensions` 功能编译；扩展加载失败时记录警告，模糊匹配退回基础实现。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseExtensionsConfig {
+    /// 扩展动态库路径（如 `extensions/spellfix1`，可省略扩展名）
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+}
+
+const fn default_warm_up_connections() -> u32 {
+    2
+}
+
+const fn default_enable_mmap() -> bool {
+    true
+}
+
+fn default_attachments_dir() -> PathBuf {
+    PathBuf::from("data/attachments")
+}
+
+fn default_backups_dir() -> PathBuf {
+    PathBuf::from("data/backups")
+}
+
+const fn default_max_connections_cap() -> u32 {
+    16
+}
+
+const fn default_min_free_space_mb() -> u64 {
+    200
+}
+
+const fn default_reclaim_prompt_mb() -> u64 {
+    50
+}
+
+const fn default_reclaim_prompt_ratio() -> f64 {
+    0.2
+}
+
+fn default_size_growth_mb_per_day() -> f64 {
+    SizeGrowthThresholds::default().mb_per_day
+}
+
+fn default_size_growth_percent_per_day() -> f64 {
+    SizeGrowthThresholds::default().percent_per_day
 }
 
 /// 用户界面配置
@@ -37,8 +186,29 @@ pub struct UiConfig {
     pub window_width: u32,
     /// 默认窗口高度
     pub window_height: u32,
-    /// 主题名称
+    /// 主题名称（`default`、`dark`、`high_contrast`）
     pub theme: String,
+    /// 字号缩放（0.8–2.0，超出范围时取边界值）
+    #[serde(default = "default_font_scale")]
+    pub font_scale: f32,
+}
+
+const fn default_font_scale() -> f32 {
+    1.0
+}
+
+impl UiConfig {
+    /// 按配置创建主题管理器
+    #[must_use]
+    pub fn theme_manager(&self) -> ThemeManager {
+        ThemeManager::new(ThemeVariant::parse(&self.theme), self.font_scale)
+    }
+
+    /// 写入主题设置
+    pub fn set_theme(&mut self, theme: &ThemeManager) {
+        self.theme = theme.variant().as_str().to_string();
+        self.font_scale = clamp_font_scale(theme.font_scale());
+    }
 }
 
 /// 日志配置
@@ -48,6 +218,579 @@ pub struct LoggingConfig {
     pub level: String,
     /// 日志文件路径
     pub file_path: Option<PathBuf>,
+    /// 是否记录用户操作日志（写入日志目录下的 `actions.log`）
+    #[serde(default = "default_action_log")]
+    pub action_log: bool,
+    /// 操作日志大小上限（KB），超过后轮转
+    #[serde(default = "default_action_log_max_kb")]
+    pub action_log_max_kb: u64,
+}
+
+const fn default_action_log() -> bool {
+    true
+}
+
+const fn default_action_log_max_kb() -> u64 {
+    1024
+}
+
+impl LoggingConfig {
+    /// 日志目录（日志文件所在目录，未配置时为 `logs`）
+    pub fn log_dir(&self) -> PathBuf {
+        self.file_path
+            .as_deref()
+            .and_then(|path| path.parent())
+            .map_or_else(|| PathBuf::from("logs"), PathBuf::from)
+    }
+
+    /// 操作日志路径，未启用时为空
+    #[must_use]
+    pub fn action_log_path(&self) -> Option<PathBuf> {
+        self.action_log
+            .then(|| self.log_dir().join(crate::action_log::ACTION_LOG_FILE_NAME))
+    }
+}
+
+/// 内嵌REST API配置
+///
+/// 仅在启用 `api` 特性时生效，默认只监听本机地址。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 监听地址
+    pub bind_address: String,
+    /// 监听端口
+    pub port: u16,
+    /// 访问令牌（Bearer），配置文件中只保存密钥引用
+    pub token: Option<String>,
+    /// 令牌对应的服务账号用户名（API命令的审计字段记为该账号）
+    pub service_user: String,
+    /// 服务账号角色，决定API可以执行哪些命令
+    pub service_role: UserRole,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1".to_string(),
+            port: 8765,
+            token: None,
+            service_user: "api".to_string(),
+            service_role: UserRole::Sales,
+        }
+    }
+}
+
+impl ApiConfig {
+    /// 访问令牌对应的服务账号
+    ///
+    /// 服务账号不在用户表中，ID为空UUID。
+    #[must_use]
+    pub fn service_account(&self) -> User {
+        let now = chrono::Utc::now();
+        User {
+            id: uuid::Uuid::nil(),
+            username: self.service_user.clone(),
+            display_name: format!("API服务账号（{}）", self.service_user),
+            role: self.service_role,
+            password_hash: String::new(),
+            active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// 安全配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    /// 应用密钥，用于签发报价单校验二维码，配置文件中只保存密钥引用
+    pub app_secret: Option<String>,
+    /// 配置目录（存放应用口令哈希等文件）
+    pub config_dir: PathBuf,
+    /// 空闲自动锁定时间（分钟），0表示不自动锁定
+    pub lock_idle_minutes: u32,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            app_secret: None,
+            config_dir: PathBuf::from("config"),
+            lock_idle_minutes: 10,
+        }
+    }
+}
+
+impl SecurityConfig {
+    /// 本机的密钥存储（系统钥匙串，不可用时为配置目录下的文件）
+    #[must_use]
+    pub fn secret_store(&self) -> Arc<dyn SecretStore> {
+        default_secret_store(&self.config_dir)
+    }
+}
+
+/// 保存在密钥存储中的配置项
+///
+/// 配置文件中这些项只保存 `keyring:<名称>` 形式的引用，加载配置时再从密钥存储取出实际值。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretField {
+    /// 应用密钥（`security.app_secret`）
+    AppSecret,
+    /// API访问令牌（`api.token`）
+    ApiToken,
+    /// SMTP密码（`digest.smtp.password`）
+    SmtpPassword,
+    /// 售后邮箱授权码（`email_intake.password`）
+    ImapPassword,
+}
+
+impl SecretField {
+    /// 全部密钥配置项
+    pub const ALL: [Self; 4] = [
+        Self::AppSecret,
+        Self::ApiToken,
+        Self::SmtpPassword,
+        Self::ImapPassword,
+    ];
+
+    /// 在密钥存储中的名称
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::AppSecret => "app_secret",
+            Self::ApiToken => "api_token",
+            Self::SmtpPassword => "smtp_password",
+            Self::ImapPassword => "imap_password",
+        }
+    }
+
+    /// 配置中的当前值（空值视为未设置）
+    fn value(self, config: &AppConfig) -> Option<&str> {
+        match self {
+            Self::AppSecret => config.security.app_secret.as_deref(),
+            Self::ApiToken => config.api.token.as_deref(),
+            Self::SmtpPassword => config.digest.smtp.as_ref().map(|smtp| smtp.password.as_str()),
+            Self::ImapPassword => Some(config.email_intake.password.as_str()),
+        }
+        .filter(|value| !value.is_empty())
+    }
+
+    /// 设置配置中的值，未配置SMTP服务器时忽略SMTP密码
+    fn set(self, config: &mut AppConfig, value: Option<String>) {
+        match self {
+            Self::AppSecret => config.security.app_secret = value,
+            Self::ApiToken => config.api.token = value,
+            Self::SmtpPassword => {
+                if let Some(smtp) = &mut config.digest.smtp {
+                    smtp.password = value.unwrap_or_default();
+                }
+            }
+            Self::ImapPassword => config.email_intake.password = value.unwrap_or_default(),
+        }
+    }
+}
+
+/// 业务日历配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    /// 业务时区（IANA名称），"今天到期"、本月统计等按该时区的日期计算
+    pub business_timezone: String,
+    /// 每周休息日（如 `["Sat", "Sun"]`），节假日和调休上班日在设置界面的节假日表中维护
+    pub rest_days: Vec<Weekday>,
+    /// 上班时间（业务时区）
+    pub work_start: NaiveTime,
+    /// 下班时间（业务时区）
+    pub work_end: NaiveTime,
+    /// 工单SLA时限（工作小时）
+    pub sla: SlaPolicy,
+    /// 报价、任务和工单的久未跟进阈值（天）
+    pub staleness: StalenessThresholds,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            business_timezone: DEFAULT_BUSINESS_TIMEZONE.to_string(),
+            rest_days: vec![Weekday::Sat, Weekday::Sun],
+            work_start: NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+            work_end: NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default(),
+            sla: SlaPolicy::default(),
+            staleness: StalenessThresholds::default(),
+        }
+    }
+}
+
+impl CalendarConfig {
+    /// 构建业务日历
+    ///
+    /// # Errors
+    ///
+    /// 时区名无法识别时返回错误。
+    pub fn business_calendar(&self) -> Result<BusinessCalendar> {
+        Ok(BusinessCalendar::from_name(&self.business_timezone)?)
+    }
+
+    /// 构建工作时间日历
+    ///
+    /// # Errors
+    ///
+    /// 时区名无法识别、每周没有工作日或上下班时间无效时返回错误。
+    pub fn working_calendar(&self, holidays: &[HolidayEntry]) -> Result<WorkingCalendar> {
+        Ok(WorkingCalendar::new(
+            self.business_calendar()?,
+            self.rest_days.clone(),
+            self.work_start,
+            self.work_end,
+        )?
+        .with_holidays(holidays))
+    }
+
+    /// 构建工单SLA评估器
+    ///
+    /// # Errors
+    ///
+    /// 工作时间日历配置无效时返回错误。
+    pub fn sla_evaluator(&self, holidays: &[HolidayEntry]) -> Result<SlaEvaluator> {
+        Ok(SlaEvaluator::new(self.working_calendar(holidays)?, self.sla))
+    }
+
+    /// 构建久未跟进评估器（报价列表、任务看板、工单列表和逾期升级提醒共用）
+    ///
+    /// # Errors
+    ///
+    /// 时区名无法识别时返回错误。
+    pub fn staleness_evaluator(&self) -> Result<StalenessEvaluator> {
+        Ok(StalenessEvaluator::new(self.business_calendar()?, self.staleness))
+    }
+}
+
+/// 每周摘要配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 发送的星期（如 "Mon"）
+    pub weekday: Weekday,
+    /// 发送时间（业务时区）
+    pub time: NaiveTime,
+    /// 邮件收件人
+    pub recipients: Vec<String>,
+    /// SMTP服务器，未配置时只发送桌面通知
+    pub smtp: Option<SmtpConfig>,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weekday: Weekday::Mon,
+            time: NaiveTime::from_hms_opt(8, 0, 0).unwrap_or_default(),
+            recipients: Vec::new(),
+            smtp: None,
+        }
+    }
+}
+
+/// 提醒配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReminderConfig {
+    /// 联系人生日和首次成交纪念日提前提醒的天数，为0时当天提醒
+    pub anniversary_lead_days: u32,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            anniversary_lead_days: DEFAULT_ANNIVERSARY_LEAD_DAYS,
+        }
+    }
+}
+
+/// 新建线索配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeadConfig {
+    /// 首个跟进任务未填写截止日期时，在几天后到期
+    pub first_task_due_days: u32,
+}
+
+impl Default for LeadConfig {
+    fn default() -> Self {
+        Self {
+            first_task_due_days: DEFAULT_FIRST_TASK_DUE_DAYS,
+        }
+    }
+}
+
+/// 记录归档配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    /// 是否按计划自动归档（“立即归档”不受影响）
+    pub enabled: bool,
+    /// 各类型记录保留的月数，为0时不归档该类型
+    pub cutoff_months: BTreeMap<ArchiveEntity, u32>,
+    /// 每月运行日期（1-31，超过月末时在月末运行）
+    pub day_of_month: u32,
+    /// 运行时间（业务时区）
+    pub time: NaiveTime,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cutoff_months: [
+                (ArchiveEntity::Interactions, 36),
+                (ArchiveEntity::Tasks, 24),
+                (ArchiveEntity::Orders, 60),
+            ]
+            .into_iter()
+            .collect(),
+            day_of_month: 1,
+            time: NaiveTime::from_hms_opt(2, 0, 0).unwrap_or_default(),
+        }
+    }
+}
+
+impl ArchiveConfig {
+    /// 归档策略
+    #[must_use]
+    pub fn policy(&self) -> ArchivePolicy {
+        ArchivePolicy {
+            cutoff_months: self.cutoff_months.clone(),
+        }
+    }
+
+    /// 定时归档计划
+    #[must_use]
+    pub fn schedule(&self) -> JobSchedule {
+        JobSchedule::monthly(self.day_of_month, self.time)
+    }
+}
+
+/// 审计记录和事件发件箱保留配置（`[retention]`）
+///
+/// 超过保留期的审计记录按月汇总后删除，已分发事件直接删除，未分发的事件始终保留。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// 审计记录保留的整月数（不含当月），为0时不清理
+    pub audit_months: u32,
+    /// 已分发事件保留的天数（至少1天）
+    pub outbox_days: u32,
+    /// 每批删除的行数
+    pub batch_size: usize,
+    /// 批间暂停（毫秒）
+    pub batch_pause_ms: u64,
+    /// 每天运行时间（业务时区）
+    pub time: NaiveTime,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        let policy = RetentionPolicy::default();
+        Self {
+            audit_months: policy.audit_months,
+            outbox_days: u32::try_from(policy.outbox_retention.num_days()).unwrap_or(u32::MAX),
+            batch_size: policy.batch_size,
+            batch_pause_ms: u64::try_from(policy.batch_pause.as_millis()).unwrap_or(u64::MAX),
+            time: NaiveTime::from_hms_opt(3, 0, 0).unwrap_or_default(),
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// 已分发事件的保留时长
+    #[must_use]
+    pub fn outbox_retention(&self) -> chrono::Duration {
+        chrono::Duration::days(i64::from(self.outbox_days.max(1)))
+    }
+
+    /// 保留策略
+    #[must_use]
+    pub fn policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            audit_months: self.audit_months,
+            outbox_retention: self.outbox_retention(),
+            batch_size: self.batch_size.max(1),
+            batch_pause: std::time::Duration::from_millis(self.batch_pause_ms),
+        }
+    }
+}
+
+/// 报价配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuoteConfig {
+    /// 明细毛利率低于此百分比时在编辑器中标出
+    pub margin_warning_percent: f64,
+    /// 报价毛利率低于此百分比时须管理员批准才能发送
+    pub margin_block_percent: f64,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        let thresholds = MarginThresholds::default();
+        Self {
+            margin_warning_percent: thresholds.warn_below,
+            margin_block_percent: thresholds.block_below,
+        }
+    }
+}
+
+impl QuoteConfig {
+    /// 毛利预警阈值
+    #[must_use]
+    pub const fn margin_thresholds(&self) -> MarginThresholds {
+        MarginThresholds {
+            warn_below: self.margin_warning_percent,
+            block_below: self.margin_block_percent,
+        }
+    }
+}
+
+/// 附件上传配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentConfig {
+    /// 允许上传的扩展名，其他类型须管理员逐个放行（可执行文件一律拒绝）
+    pub allowed_extensions: Vec<String>,
+    /// 单个附件的大小上限（MB）
+    pub max_upload_mb: u64,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            allowed_extensions: DEFAULT_ALLOWED_EXTENSIONS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            max_upload_mb: DEFAULT_MAX_UPLOAD_BYTES / (1024 * 1024),
+        }
+    }
+}
+
+impl AttachmentConfig {
+    /// 附件上传策略
+    #[must_use]
+    pub fn policy(&self) -> AttachmentPolicy {
+        AttachmentPolicy {
+            allowed_extensions: self.allowed_extensions.clone(),
+            max_upload_bytes: self.max_upload_mb.saturating_mul(1024 * 1024),
+        }
+    }
+}
+
+/// 表格导入配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportConfig {
+    /// 表头别名表（按字段列出可识别的表头文字，覆盖默认别名）
+    pub header_aliases: HeaderAliases,
+}
+
+/// SMTP服务器配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    /// 服务器地址
+    pub host: String,
+    /// 端口
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    /// 登录用户名
+    pub username: String,
+    /// 登录密码或授权码，配置文件中只保存密钥引用
+    pub password: String,
+    /// 发件人
+    pub from: String,
+}
+
+const fn default_smtp_port() -> u16 {
+    587
+}
+
+#[cfg(feature = "integrations")]
+impl SmtpConfig {
+    /// 转换为邮件发送器设置
+    #[must_use]
+    pub fn settings(&self) -> crate::infrastructure::integrations::SmtpSettings {
+        crate::infrastructure::integrations::SmtpSettings {
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            from: self.from.clone(),
+        }
+    }
+}
+
+/// 售后邮箱收件配置（`[email_intake]`）
+///
+/// 启用后按间隔轮询IMAP邮箱，把未读邮件转换为售后工单，需要以 `integrations` 功能编译。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailIntakeConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// IMAP服务器
+    pub host: String,
+    /// 端口（隐式TLS）
+    pub port: u16,
+    /// 登录用户名
+    pub username: String,
+    /// 登录密码或授权码，配置文件中只保存密钥引用
+    pub password: String,
+    /// 邮箱文件夹
+    pub folder: String,
+    /// 检查间隔（分钟）
+    pub poll_minutes: u32,
+}
+
+impl Default for EmailIntakeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 993,
+            username: String::new(),
+            password: String::new(),
+            folder: "INBOX".to_string(),
+            poll_minutes: 5,
+        }
+    }
+}
+
+impl EmailIntakeConfig {
+    /// 轮询间隔（至少1分钟）
+    #[must_use]
+    pub fn poll_interval(&self) -> chrono::Duration {
+        chrono::Duration::minutes(i64::from(self.poll_minutes.max(1)))
+    }
+}
+
+#[cfg(feature = "integrations")]
+impl EmailIntakeConfig {
+    /// 转换为IMAP收件箱设置
+    #[must_use]
+    pub fn settings(&self) -> crate::infrastructure::integrations::ImapSettings {
+        crate::infrastructure::integrations::ImapSettings {
+            host: self.host.clone(),
+            port: self.port,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            mailbox: self.folder.clone(),
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -56,18 +799,45 @@ impl Default for AppConfig {
             database: DatabaseConfig {
                 path: PathBuf::from("data/minicrm.db"),
                 max_connections: 10,
+                max_connections_cap: default_max_connections_cap(),
                 connection_timeout: 30,
+                warm_up_connections: default_warm_up_connections(),
+                enable_mmap: default_enable_mmap(),
+                attachments_dir: default_attachments_dir(),
+                backups_dir: default_backups_dir(),
+                min_free_space_mb: default_min_free_space_mb(),
+                extensions: DatabaseExtensionsConfig::default(),
+                reclaim_prompt_mb: default_reclaim_prompt_mb(),
+                reclaim_prompt_ratio: default_reclaim_prompt_ratio(),
+                size_growth_mb_per_day: default_size_growth_mb_per_day(),
+                size_growth_percent_per_day: default_size_growth_percent_per_day(),
+                read_only_on_external_change: false,
             },
             ui: UiConfig {
                 window_title: "MiniCRM - 板材行业客户管理系统".to_string(),
                 window_width: 1280,
                 window_height: 800,
                 theme: "default".to_string(),
+                font_scale: default_font_scale(),
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
                 file_path: Some(PathBuf::from("logs/minicrm.log")),
+                action_log: default_action_log(),
+                action_log_max_kb: default_action_log_max_kb(),
             },
+            api: ApiConfig::default(),
+            security: SecurityConfig::default(),
+            calendar: CalendarConfig::default(),
+            digest: DigestConfig::default(),
+            reminders: ReminderConfig::default(),
+            leads: LeadConfig::default(),
+            archive: ArchiveConfig::default(),
+            retention: RetentionConfig::default(),
+            quote: QuoteConfig::default(),
+            attachments: AttachmentConfig::default(),
+            import: ImportConfig::default(),
+            email_intake: EmailIntakeConfig::default(),
         }
     }
 }
@@ -75,14 +845,330 @@ impl Default for AppConfig {
 impl AppConfig {
     /// 加载应用程序配置
     ///
-    /// 首先尝试从配置文件加载，如果文件不存在则使用默认配置。
+    /// 按启动参数选择数据根目录（见 [`DataRoot::select`]），再从其中的配置文件
+    /// （[`CONFIG_FILE_PATH`]）加载，如果文件不存在则使用默认配置。
     ///
     /// # Errors
     ///
-    /// 如果配置文件存在但格式不正确，将返回错误。
+    /// 如果无法确定数据根目录，或配置文件存在但格式不正确，将返回错误。
     pub fn load() -> Result<Self> {
-        // TODO: 在后续任务中实现从文件加载配置
-        // 目前返回默认配置
-        Ok(Self::default())
+        Self::load_in(&DataRoot::select(&LaunchOptions::from_env())?)
+    }
+
+    /// 从数据根目录加载配置，并把其中的相对路径解析到根目录下
+    ///
+    /// 配置文件中的明文密钥会先移入密钥存储并改写配置文件（只在第一次加载时发生），
+    /// 返回的配置中密钥引用已替换为实际值，不应再保存回配置文件。
+    ///
+    /// # Errors
+    ///
+    /// 如果配置文件存在但无法读取或格式不正确，或密钥存储无法访问，将返回错误。
+    pub fn load_in(root: &DataRoot) -> Result<Self> {
+        let mut config = Self::load_from(root.config_file())?;
+        let store = default_secret_store(root.resolve(&config.security.config_dir));
+        let migrated = config.migrate_secrets(store.as_ref())?;
+        if !migrated.is_empty() {
+            config.save(root.config_file())?;
+            info!("已将 {} 个明文密钥移入密钥存储", migrated.len());
+        }
+        config.resolve_paths(root);
+        config.resolve_secrets(store.as_ref())?;
+        Ok(config)
+    }
+
+    /// 把配置中的明文密钥移入密钥存储，配置中改为保存引用
+    ///
+    /// 返回迁移的配置项；已是引用或为空的项保持不变。
+    ///
+    /// # Errors
+    ///
+    /// 密钥存储写入失败时返回错误。
+    pub fn migrate_secrets(&mut self, store: &dyn SecretStore) -> Result<Vec<SecretField>> {
+        let mut migrated = Vec::new();
+        for field in SecretField::ALL {
+            let Some(value) = field.value(self) else {
+                continue;
+            };
+            if parse_secret_ref(value).is_some() {
+                continue;
+            }
+            store.set_secret(field.name(), value)?;
+            field.set(self, Some(secret_ref(field.name())));
+            migrated.push(field);
+        }
+        Ok(migrated)
+    }
+
+    /// 把配置中的密钥引用替换为密钥存储中的实际值
+    ///
+    /// 密钥存储中已没有的项视为未设置。
+    ///
+    /// # Errors
+    ///
+    /// 密钥存储无法读取时返回错误。
+    pub fn resolve_secrets(&mut self, store: &dyn SecretStore) -> Result<()> {
+        for field in SecretField::ALL {
+            let Some(name) = field.value(self).and_then(parse_secret_ref) else {
+                continue;
+            };
+            let value = store.get_secret(name)?;
+            if value.is_none() {
+                warn!("密钥存储中没有 {}，按未设置处理", name);
+            }
+            field.set(self, value);
+        }
+        Ok(())
+    }
+
+    /// 在设置界面中修改密钥：值写入密钥存储，配置中只保存引用
+    ///
+    /// `value` 为空时删除该密钥。
+    ///
+    /// # Errors
+    ///
+    /// 密钥存储写入失败时返回错误。
+    pub fn update_secret(
+        &mut self,
+        store: &dyn SecretStore,
+        field: SecretField,
+        value: Option<&str>,
+    ) -> Result<()> {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            store.set_secret(field.name(), value)?;
+            field.set(self, Some(secret_ref(field.name())));
+        } else {
+            store.delete_secret(field.name())?;
+            field.set(self, None);
+        }
+        Ok(())
+    }
+
+    /// 把数据、日志和配置目录的相对路径解析到数据根目录下，绝对路径保持不变
+    pub fn resolve_paths(&mut self, root: &DataRoot) {
+        let database = &mut self.database;
+        for path in [
+            &mut database.path,
+            &mut database.attachments_dir,
+            &mut database.backups_dir,
+            &mut self.security.config_dir,
+        ] {
+            *path = root.resolve(path);
+        }
+        if let Some(path) = &mut self.logging.file_path {
+            *path = root.resolve(path);
+        }
+    }
+
+    /// 从指定文件加载配置，文件不存在时使用默认配置
+    ///
+    /// # Errors
+    ///
+    /// 如果文件无法读取或格式不正确，将返回错误。
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("配置文件格式不正确: {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("无法读取配置文件: {}", path.display())),
+        }
+    }
+
+    /// 保存配置到指定文件
+    ///
+    /// # Errors
+    ///
+    /// 目录创建或文件写入失败时返回错误。
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建配置目录: {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("无法写入配置文件: {}", path.display()))
+    }
+
+    /// 只把外观设置写回配置文件，其余内容保持文件中的原样
+    ///
+    /// 运行中的配置已解析了路径和密钥，不能整体保存，因此重新读取文件后只修改主题和字号。
+    ///
+    /// # Errors
+    ///
+    /// 配置文件无法读取、格式不正确或写入失败时返回错误。
+    pub fn save_appearance<P: AsRef<Path>>(path: P, theme: &ThemeManager) -> Result<()> {
+        let path = path.as_ref();
+        let mut config = Self::load_from(path)?;
+        config.ui.set_theme(theme);
+        config.save(path)
+    }
+
+    /// 只把连接池最大连接数写回配置文件，重新启动后生效
+    ///
+    /// # Errors
+    ///
+    /// 配置文件无法读取、格式不正确或写入失败时返回错误。
+    pub fn save_pool_size<P: AsRef<Path>>(path: P, max_connections: u32) -> Result<()> {
+        let path = path.as_ref();
+        let mut config = Self::load_from(path)?;
+        config.database.max_connections = max_connections;
+        config.save(path)
+    }
+
+    /// 数据归档涉及的本地位置
+    #[must_use]
+    pub fn archive_paths(&self) -> ArchivePaths {
+        ArchivePaths {
+            database: self.database.path.clone(),
+            attachments_dir: self.database.attachments_dir.clone(),
+            config_dir: self.security.config_dir.clone(),
+        }
+    }
+
+    /// 数据库整理提示阈值
+    #[must_use]
+    pub const fn reclaim_threshold(&self) -> ReclaimThreshold {
+        ReclaimThreshold {
+            min_bytes: self.database.reclaim_prompt_mb * 1024 * 1024,
+            min_ratio: self.database.reclaim_prompt_ratio,
+        }
+    }
+
+    /// 数据库增长警告阈值
+    #[must_use]
+    pub const fn size_growth_thresholds(&self) -> SizeGrowthThresholds {
+        SizeGrowthThresholds {
+            mb_per_day: self.database.size_growth_mb_per_day,
+            percent_per_day: self.database.size_growth_percent_per_day,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::security::FileSecretStore;
+    use tempfile::TempDir;
+
+    fn smtp(password: &str) -> SmtpConfig {
+        SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port: 465,
+            username: "shop@example.com".to_string(),
+            password: password.to_string(),
+            from: "shop@example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_migrate_plaintext_secrets() -> Result<()> {
+        let dir = TempDir::new()?;
+        let store = FileSecretStore::with_machine_id(dir.path(), "test-machine");
+        let path = dir.path().join("minicrm.json");
+        let mut config = AppConfig::default();
+        config.security.app_secret = Some("local-secret".to_string());
+        config.api.token = Some(String::new());
+        config.digest.smtp = Some(smtp("授权码"));
+        config.save(&path)?;
+
+        let mut loaded = AppConfig::load_from(&path)?;
+        let migrated = loaded.migrate_secrets(&store)?;
+        assert_eq!(
+            migrated,
+            vec![SecretField::AppSecret, SecretField::SmtpPassword]
+        );
+        loaded.save(&path)?;
+
+        let content = fs::read_to_string(&path)?;
+        assert!(!content.contains("local-secret"));
+        assert!(!content.contains("授权码"));
+        assert!(content.contains("keyring:smtp_password"));
+
+        // 再次加载不会重复迁移
+        let mut reloaded = AppConfig::load_from(&path)?;
+        assert!(reloaded.migrate_secrets(&store)?.is_empty());
+        reloaded.resolve_secrets(&store)?;
+        assert_eq!(reloaded.security.app_secret.as_deref(), Some("local-secret"));
+        let smtp = reloaded.digest.smtp.context("应保留SMTP配置")?;
+        assert_eq!(smtp.password, "授权码");
+        assert_eq!(reloaded.api.token.as_deref(), Some(""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_and_delete_secret() -> Result<()> {
+        let dir = TempDir::new()?;
+        let store = FileSecretStore::with_machine_id(dir.path(), "test-machine");
+        let mut config = AppConfig::default();
+        config.digest.smtp = Some(smtp(""));
+
+        config.update_secret(&store, SecretField::SmtpPassword, Some("新密码"))?;
+        config.update_secret(&store, SecretField::ApiToken, Some("token"))?;
+        assert_eq!(
+            config.digest.smtp.as_ref().map(|smtp| smtp.password.as_str()),
+            Some("keyring:smtp_password")
+        );
+        assert_eq!(store.get_secret("smtp_password")?.as_deref(), Some("新密码"));
+
+        config.update_secret(&store, SecretField::ApiToken, None)?;
+        assert_eq!(config.api.token, None);
+        assert_eq!(store.get_secret("api_token")?, None);
+
+        // 密钥存储中已删除的引用按未设置处理
+        config.api.token = Some(secret_ref("api_token"));
+        config.resolve_secrets(&store)?;
+        assert_eq!(config.api.token, None);
+        let smtp = config.digest.smtp.context("应保留SMTP配置")?;
+        assert_eq!(smtp.password, "新密码");
+        Ok(())
+    }
+
+    #[test]
+    fn test_appearance_persistence() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("minicrm.json");
+        let mut config = AppConfig::default();
+        config.ui.window_width = 1600;
+        config.save(&path)?;
+
+        let theme = ThemeManager::new(ThemeVariant::HighContrast, 1.5);
+        AppConfig::save_appearance(&path, &theme)?;
+        let loaded = AppConfig::load_from(&path)?;
+        assert_eq!(loaded.ui.theme, "high_contrast");
+        assert_eq!(loaded.ui.theme_manager(), theme);
+        assert_eq!(loaded.ui.window_width, 1600);
+
+        // 手工改成超出范围的值时取边界值；旧配置没有该项时为默认值
+        let content =
+            fs::read_to_string(&path)?.replace("\"font_scale\": 1.5", "\"font_scale\": 3.5");
+        fs::write(&path, content)?;
+        let loaded = AppConfig::load_from(&path)?;
+        assert!((loaded.ui.theme_manager().font_scale() - 2.0).abs() < f32::EPSILON);
+
+        let mut value: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        value["ui"]
+            .as_object_mut()
+            .context("ui 应为对象")?
+            .remove("font_scale");
+        fs::write(&path, value.to_string())?;
+        let loaded = AppConfig::load_from(&path)?;
+        assert!((loaded.ui.font_scale - 1.0).abs() < f32::EPSILON);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_size_persistence() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("minicrm.json");
+        let mut config = AppConfig::default();
+        config.database.connection_timeout = 45;
+        config.save(&path)?;
+
+        AppConfig::save_pool_size(&path, 6)?;
+        let loaded = AppConfig::load_from(&path)?;
+        assert_eq!(loaded.database.max_connections, 6);
+        assert_eq!(loaded.database.connection_timeout, 45);
+        assert_eq!(loaded.database.max_connections_cap, 16);
+        Ok(())
     }
 }
@@ -0,0 +1,199 @@
+//! 应用上下文模块
+//!
+//! 汇集配置、命令总线、查询总线和事件总线，供界面和其他入口共享。
+
+use std::sync::Arc;
+
+use crate::application::cache::DEFAULT_TTL_MINUTES;
+use crate::application::{
+    register_handlers, AppLock, CommandBus, CommandGuard, CurrentUser, EventBus, PermissionGuard,
+    QueryBus, ServiceSet, StatisticsCache,
+};
+use crate::core::{SystemClock, User};
+use crate::config::AppConfig;
+use crate::infrastructure::database::DatabaseConnection;
+use crate::infrastructure::repository::{EventOutboxStore, OutboxDispatcher};
+use crate::infrastructure::{DatabaseFileGuard, ExternalChange};
+use crate::presentation::EditSessionRegistry;
+use crate::services::sqlite_service_set;
+
+/// 外部修改通知的缓冲数量（界面只关心最近一次）
+const EXTERNAL_CHANGE_CAPACITY: usize = 4;
+
+/// 应用上下文
+///
+/// 界面控制器、REST API等入口都通过上下文分发命令和查询，
+/// 保证校验、审计等行为一致。
+#[derive(Debug, Clone)]
+pub struct AppContext {
+    /// 应用配置
+    pub config: Arc<AppConfig>,
+    /// 命令总线
+    pub commands: Arc<CommandBus>,
+    /// 查询总线
+    pub queries: Arc<QueryBus>,
+    /// 领域事件总线
+    pub events: EventBus,
+    /// 当前登录用户
+    pub current_user: CurrentUser,
+    /// 统计缓存（诊断界面可调用 `invalidate_all`）
+    pub statistics_cache: Arc<StatisticsCache>,
+    /// 编辑会话（同一记录在多个编辑器中打开时互相提示）
+    pub edit_sessions: EditSessionRegistry,
+    /// 数据库外部修改通知（界面收到后重新加载视图模型）
+    pub external_changes: tokio::sync::broadcast::Sender<ExternalChange>,
+}
+
+impl AppContext {
+    /// 使用给定的服务集合构建上下文
+    #[must_use]
+    pub fn new(config: AppConfig, services: &ServiceSet) -> Self {
+        Self::with_guards(config, services, Vec::new())
+    }
+
+    /// 构建带命令守卫（如应用锁）的上下文
+    ///
+    /// 权限守卫总是最先注册，未登录时所有命令都会被拒绝。
+    #[must_use]
+    pub fn with_guards(
+        config: AppConfig,
+        services: &ServiceSet,
+        guards: Vec<Arc<dyn CommandGuard>>,
+    ) -> Self {
+        let mut commands = CommandBus::new();
+        let mut queries = QueryBus::new();
+        let current_user = CurrentUser::new();
+        let events = EventBus::new();
+        let statistics_cache = Arc::new(StatisticsCache::new(
+            Arc::new(SystemClock),
+            chrono::Duration::minutes(DEFAULT_TTL_MINUTES),
+        ));
+        events.subscribe(statistics_cache.clone());
+        let edit_sessions = EditSessionRegistry::new();
+        events.subscribe(Arc::new(edit_sessions.clone()));
+        register_handlers(
+            services,
+            &current_user,
+            &statistics_cache,
+            &mut commands,
+            &mut queries,
+        );
+        commands.add_guard(Arc::new(PermissionGuard::new(current_user.clone())));
+        for guard in guards {
+            commands.add_guard(guard);
+        }
+
+        Self {
+            config: Arc::new(config),
+            commands: Arc::new(commands),
+            queries: Arc::new(queries),
+            events,
+            current_user,
+            statistics_cache,
+            edit_sessions,
+            external_changes: tokio::sync::broadcast::channel(EXTERNAL_CHANGE_CAPACITY).0,
+        }
+    }
+
+    /// 在数据库上构建应用使用的上下文
+    ///
+    /// 服务见 [`sqlite_service_set`]。应用锁注册为命令守卫，锁定期间经由本上下文分发的
+    /// 命令都会被拒绝。
+    ///
+    /// # Errors
+    ///
+    /// 业务日历配置无效时返回错误。
+    pub fn open(
+        config: AppConfig,
+        connection: &DatabaseConnection,
+        lock: Arc<AppLock>,
+    ) -> anyhow::Result<Self> {
+        let services = sqlite_service_set(connection, &config)?;
+        Ok(Self::with_guards(config, &services, vec![lock]))
+    }
+
+    /// 创建事件发件箱分发任务，把已提交的事件发布到本上下文的事件总线
+    ///
+    /// 启动时先调用一次 `dispatch_pending` 重放上次未分发的事件，再注册到调度器定期轮询。
+    /// 已分发事件按 `[retention]` 配置的天数保留。
+    #[must_use]
+    pub fn outbox_dispatcher(&self, store: EventOutboxStore) -> OutboxDispatcher {
+        let store = store.with_retention(self.config.retention.outbox_retention());
+        OutboxDispatcher::new(store, Arc::new(self.events.clone()))
+    }
+
+    /// 检测到数据库被外部程序修改时清空统计缓存，并广播给界面重新加载
+    pub fn watch_database(&self, guard: &DatabaseFileGuard) {
+        let statistics_cache = self.statistics_cache.clone();
+        let external_changes = self.external_changes.clone();
+        guard.subscribe(move |change| {
+            statistics_cache.invalidate_all();
+            // 没有订阅者时发送失败，无需处理
+            let _ = external_changes.send(change.clone());
+        });
+    }
+
+    /// 登录成功后设置当前用户
+    pub fn sign_in(&self, user: User) {
+        self.current_user.sign_in(user);
+    }
+
+    /// 注销当前用户
+    pub fn sign_out(&self) {
+        self.current_user.sign_out();
+    }
+
+    /// 按配置启动内嵌REST API
+    ///
+    /// API使用独立的上下文，服务见 [`sqlite_service_set`]。上下文以令牌对应的服务账号
+    /// （[`ApiConfig::service_account`](crate::config::ApiConfig::service_account)）登录，
+    /// 不随界面登录、注销或应用锁变化。未启用时返回 `Ok(None)`。
+    ///
+    /// # Errors
+    ///
+    /// 未配置访问令牌、业务日历配置无效或监听地址无法绑定时返回错误。
+    #[cfg(feature = "api")]
+    pub async fn start_api(
+        config: AppConfig,
+        connection: &DatabaseConnection,
+    ) -> anyhow::Result<Option<crate::api::ApiServer>> {
+        if !config.api.enabled {
+            return Ok(None);
+        }
+        let services = sqlite_service_set(connection, &config)?;
+        let context = Self::new(config, &services);
+        context.sign_in(context.config.api.service_account());
+        crate::api::serve(Arc::new(context)).await.map(Some)
+    }
+
+    /// 在数据根目录 `root` 中构建不启动界面的完整上下文（端到端测试使用）
+    ///
+    /// 从根目录加载配置、打开数据库并执行迁移，服务见
+    /// [`sqlite_service_set`]，并以管理员身份登录。返回的数据库管理器
+    /// 用于备份和直接检查数据。
+    ///
+    /// # Errors
+    ///
+    /// 配置无法加载、数据库无法打开或迁移失败时返回错误。
+    #[cfg(feature = "test-util")]
+    pub fn new_for_tests(
+        root: &std::path::Path,
+    ) -> anyhow::Result<(Self, crate::database::DatabaseManager)> {
+        let config = AppConfig::load_in(&crate::data_dir::DataRoot::at(root))?;
+        let database = crate::database::DatabaseManager::new(&config)?;
+        let services = sqlite_service_set(&database.connection(), &config)?;
+        let context = Self::new(config, &services);
+        let now = chrono::Utc::now();
+        context.sign_in(User {
+            id: uuid::Uuid::new_v4(),
+            username: "e2e".to_string(),
+            display_name: "端到端测试".to_string(),
+            role: crate::core::UserRole::Admin,
+            password_hash: String::new(),
+            active: true,
+            created_at: now,
+            updated_at: now,
+        });
+        Ok((context, database))
+    }
+}
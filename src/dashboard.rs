@@ -0,0 +1,215 @@
+//! 内置仪表盘卡片
+//!
+//! 注册桌面端仪表盘可用的卡片及其数据来源。卡片ID会保存在用户布局中，发布后不应修改。
+//! 新手引导清单的各步骤也在这里按数据库和备份目录判断是否完成。
+
+use async_trait::async_trait;
+
+use crate::core::{
+    AnniversaryService, BusinessCalendar, CoreResult, UserRole, ANNIVERSARY_LOOKAHEAD_DAYS,
+};
+use crate::infrastructure::database::DatabaseConnection;
+use crate::infrastructure::repository::{
+    ActivityScoreStore, AnniversaryStore, ReminderStore, TableCounterStore,
+};
+use crate::presentation::{
+    anniversary_rows, format_money, CardData, CardDataSource, DashboardCard, DashboardCardRegistry,
+    OnboardingProbe, OnboardingStep, UPCOMING_ANNIVERSARIES_TITLE,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// 按表计数的卡片（读取缓存的记录总数，不执行 `COUNT(*)`）
+#[derive(Debug)]
+struct TableCountSource {
+    counters: TableCounterStore,
+    table: &'static str,
+}
+
+#[async_trait]
+impl CardDataSource for TableCountSource {
+    async fn load(&self) -> CoreResult<CardData> {
+        Ok(CardData {
+            value: self.counters.get(self.table)?.to_string(),
+            detail: None,
+        })
+    }
+}
+
+/// 未处理提醒卡片
+#[derive(Debug)]
+struct ActiveReminderSource {
+    reminders: ReminderStore,
+}
+
+#[async_trait]
+impl CardDataSource for ActiveReminderSource {
+    async fn load(&self) -> CoreResult<CardData> {
+        let active = self.reminders.active()?;
+        Ok(CardData {
+            value: active.len().to_string(),
+            detail: active.first().map(|r| r.title.clone()),
+        })
+    }
+}
+
+/// 流失风险客户卡片（按历史订单总额列出前几位）
+#[derive(Debug)]
+struct ChurnRiskSource {
+    activity: ActivityScoreStore,
+}
+
+/// 卡片中列出的流失风险客户数
+const CHURN_RISK_LISTED: usize = 3;
+
+#[async_trait]
+impl CardDataSource for ChurnRiskSource {
+    async fn load(&self) -> CoreResult<CardData> {
+        let count = self.activity.at_risk_count()?;
+        let top = self.activity.top_at_risk(CHURN_RISK_LISTED)?;
+        let detail = (!top.is_empty()).then(|| {
+            top.iter()
+                .map(|c| format!("{}（{}）", c.name, format_money(c.lifetime_value)))
+                .collect::<Vec<_>>()
+                .join("、")
+        });
+        Ok(CardData {
+            value: count.to_string(),
+            detail,
+        })
+    }
+}
+
+/// 近期需维护的客户卡片（未来14天内的联系人生日和首次成交纪念日）
+#[derive(Debug)]
+struct UpcomingAnniversarySource {
+    anniversaries: AnniversaryStore,
+    calendar: BusinessCalendar,
+}
+
+/// 卡片中列出的纪念日数
+const ANNIVERSARIES_LISTED: usize = 3;
+
+#[async_trait]
+impl CardDataSource for UpcomingAnniversarySource {
+    async fn load(&self) -> CoreResult<CardData> {
+        let today = self.calendar.local_date(chrono::Utc::now());
+        let upcoming = self
+            .anniversaries
+            .upcoming_anniversaries(today, ANNIVERSARY_LOOKAHEAD_DAYS)
+            .await?;
+        let rows = anniversary_rows(&upcoming);
+        let detail = (!rows.is_empty()).then(|| {
+            rows.iter()
+                .take(ANNIVERSARIES_LISTED)
+                .map(|row| format!("{} {}", row.when, row.title))
+                .collect::<Vec<_>>()
+                .join("、")
+        });
+        Ok(CardData {
+            value: rows.len().to_string(),
+            detail,
+        })
+    }
+}
+
+/// 内置卡片注册表
+#[must_use]
+pub fn builtin_cards(connection: &DatabaseConnection) -> DashboardCardRegistry {
+    let counters = TableCounterStore::new(connection.clone());
+    let count = |table| {
+        Arc::new(TableCountSource {
+            counters: counters.clone(),
+            table,
+        })
+    };
+
+    let mut registry = DashboardCardRegistry::new();
+    registry.register(DashboardCard {
+        id: "reminders",
+        title: "待处理提醒",
+        required_role: UserRole::Sales,
+        source: Arc::new(ActiveReminderSource {
+            reminders: ReminderStore::new(connection.clone()),
+        }),
+    });
+    registry.register(DashboardCard {
+        id: "churn_risk",
+        title: "流失风险客户",
+        required_role: UserRole::Sales,
+        source: Arc::new(ChurnRiskSource {
+            activity: ActivityScoreStore::new(connection.clone()),
+        }),
+    });
+    registry.register(DashboardCard {
+        id: "upcoming_anniversaries",
+        title: UPCOMING_ANNIVERSARIES_TITLE,
+        required_role: UserRole::Sales,
+        source: Arc::new(UpcomingAnniversarySource {
+            anniversaries: AnniversaryStore::new(connection.clone()),
+            calendar: BusinessCalendar::default(),
+        }),
+    });
+    registry.register(DashboardCard {
+        id: "customers",
+        title: "客户总数",
+        required_role: UserRole::Viewer,
+        source: count("customers"),
+    });
+    registry.register(DashboardCard {
+        id: "tasks",
+        title: "任务总数",
+        required_role: UserRole::Viewer,
+        source: count("tasks"),
+    });
+    registry.register(DashboardCard {
+        id: "quotes",
+        title: "报价总数",
+        required_role: UserRole::Sales,
+        source: count("quotes"),
+    });
+    registry
+}
+
+/// 新手引导步骤的数据来源
+///
+/// 每步只执行一次存在性查询；导入不留下可查询的记录，由导入完成时记录为已完成。
+#[derive(Debug, Clone)]
+pub struct DatabaseOnboardingProbe {
+    connection: DatabaseConnection,
+    backups_dir: PathBuf,
+}
+
+impl DatabaseOnboardingProbe {
+    /// 创建数据来源（`backups_dir` 为配置中的备份目录）
+    #[must_use]
+    pub const fn new(connection: DatabaseConnection, backups_dir: PathBuf) -> Self {
+        Self {
+            connection,
+            backups_dir,
+        }
+    }
+
+    fn exists(&self, sql: &str) -> CoreResult<bool> {
+        Ok(self.connection.query_row(sql, [], |row| row.get(0))?)
+    }
+}
+
+#[async_trait]
+impl OnboardingProbe for DatabaseOnboardingProbe {
+    async fn is_done(&self, step: OnboardingStep) -> CoreResult<bool> {
+        match step {
+            OnboardingStep::FirstCustomer => {
+                self.exists("SELECT EXISTS(SELECT 1 FROM customers WHERE deleted_at IS NULL)")
+            }
+            OnboardingStep::FirstProduct => self.exists("SELECT EXISTS(SELECT 1 FROM products)"),
+            OnboardingStep::FirstQuote => {
+                self.exists("SELECT EXISTS(SELECT 1 FROM quotes WHERE deleted_at IS NULL)")
+            }
+            // 备份目录不存在时视为尚未备份
+            OnboardingStep::Backup => Ok(std::fs::read_dir(&self.backups_dir)
+                .is_ok_and(|mut entries| entries.next().is_some())),
+            OnboardingStep::FirstImport => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! 数据目录布局
+//!
+//! 配置中的相对路径都相对于数据根目录解析：默认使用平台约定的位置
+//! （Windows `%APPDATA%\MiniCRM`、macOS `~/Library/Application Support/MiniCRM`、
+//! Linux `~/.local/share/minicrm`），以 `--portable` 启动时使用程序所在目录。
+//! 配置中的绝对路径保持不变。
+//!
+//! 早期版本把 `data/`、`logs/`、`config/` 写在工作目录下。启动时若在程序目录或工作目录
+//! 发现这些旧目录，经用户确认（或 `--migrate-data`）后复制到数据根目录，逐个文件校验一致
+//! 后才删除原文件。
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::CONFIG_FILE_PATH;
+
+/// 数据根目录名称（Linux 按惯例使用小写）
+#[cfg(target_os = "linux")]
+pub const APP_DIR_NAME: &str = "minicrm";
+/// 数据根目录名称（Linux 按惯例使用小写）
+#[cfg(not(target_os = "linux"))]
+pub const APP_DIR_NAME: &str = "MiniCRM";
+
+/// 旧版本在工作目录下创建的目录
+pub const LEGACY_ENTRIES: [&str; 3] = ["data", "logs", "config"];
+
+/// 启动参数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// 便携模式：数据保存在程序所在目录
+    pub portable: bool,
+    /// 发现旧数据目录时不询问，直接迁移
+    pub migrate_data: bool,
+    /// `migrate --verify`：检查内置迁移能否回滚后退出，不启动界面
+    pub verify_migrations: bool,
+    /// `check-consistency`：检查业务数据一致性后退出，不启动界面
+    pub check_consistency: Option<ConsistencyOptions>,
+}
+
+/// `check-consistency [--checks a,b] [--limit N] [--json]` 的参数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsistencyOptions {
+    /// 只执行列出的检查（未指定时执行全部）
+    pub checks: Option<Vec<String>>,
+    /// 每项检查最多列出的问题数（未指定时使用默认值）
+    pub limit: Option<usize>,
+    /// 以JSON输出报告
+    pub json: bool,
+}
+
+impl LaunchOptions {
+    /// 解析启动参数（不含程序名），忽略不认识的参数
+    pub fn parse<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut options = Self::default();
+        let mut migrate = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match (arg.as_ref(), options.check_consistency.as_mut()) {
+                ("--portable", _) => options.portable = true,
+                ("--migrate-data", _) => options.migrate_data = true,
+                ("migrate", _) => migrate = true,
+                ("--verify", _) if migrate => options.verify_migrations = true,
+                ("check-consistency", _) => {
+                    options.check_consistency = Some(ConsistencyOptions::default());
+                }
+                ("--checks", Some(consistency)) => {
+                    consistency.checks = args.next().map(|value| {
+                        value
+                            .as_ref()
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|name| !name.is_empty())
+                            .map(str::to_string)
+                            .collect()
+                    });
+                }
+                ("--limit", Some(consistency)) => {
+                    consistency.limit = args.next().and_then(|value| value.as_ref().parse().ok());
+                }
+                ("--json", Some(consistency)) => consistency.json = true,
+                _ => {}
+            }
+        }
+        options
+    }
+
+    /// 解析当前进程的启动参数
+    #[must_use]
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+}
+
+/// 数据根目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataRoot {
+    path: PathBuf,
+    portable: bool,
+}
+
+impl DataRoot {
+    /// 使用指定目录作为数据根目录
+    pub fn at<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            portable: false,
+        }
+    }
+
+    /// 平台约定的数据根目录
+    ///
+    /// # Errors
+    ///
+    /// 无法确定当前用户的主目录时返回错误。
+    pub fn platform() -> Result<Self> {
+        let dirs = directories::BaseDirs::new().context("无法确定当前用户的数据目录")?;
+        Ok(Self::in_base(dirs.data_dir()))
+    }
+
+    /// 在平台数据目录 `base` 下的数据根目录
+    #[must_use]
+    pub fn in_base(base: &Path) -> Self {
+        Self::at(base.join(APP_DIR_NAME))
+    }
+
+    /// 便携模式：程序所在目录
+    ///
+    /// # Errors
+    ///
+    /// 无法确定程序所在目录时返回错误。
+    pub fn portable() -> Result<Self> {
+        Ok(Self {
+            path: executable_dir()?,
+            portable: true,
+        })
+    }
+
+    /// 按启动参数选择数据根目录，`--portable` 优先
+    ///
+    /// # Errors
+    ///
+    /// 无法确定所选目录时返回错误。
+    pub fn select(options: &LaunchOptions) -> Result<Self> {
+        if options.portable {
+            Self::portable()
+        } else {
+            Self::platform()
+        }
+    }
+
+    /// 根目录路径
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 是否为便携模式
+    #[must_use]
+    pub const fn is_portable(&self) -> bool {
+        self.portable
+    }
+
+    /// 相对路径解析到根目录下，绝对路径保持不变
+    #[must_use]
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.path.join(path)
+        }
+    }
+
+    /// 配置文件路径
+    #[must_use]
+    pub fn config_file(&self) -> PathBuf {
+        self.resolve(Path::new(CONFIG_FILE_PATH))
+    }
+}
+
+/// 程序所在目录
+///
+/// # Errors
+///
+/// 无法确定程序路径时返回错误。
+pub fn executable_dir() -> Result<PathBuf> {
+    let exe = std::env::current_exe().context("无法确定程序路径")?;
+    exe.parent()
+        .map(Path::to_path_buf)
+        .context("无法确定程序所在目录")
+}
+
+/// 旧版本的数据目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyData {
+    base: PathBuf,
+    entries: Vec<&'static str>,
+}
+
+/// 数据迁移结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataMigrationReport {
+    /// 复制并校验的文件数
+    pub files: usize,
+    /// 复制的字节数
+    pub bytes: u64,
+    /// 已迁移但未能删除的原目录
+    pub leftovers: Vec<PathBuf>,
+}
+
+/// 启动时旧数据迁移的结果
+#[derive(Debug)]
+pub enum DataMigration {
+    /// 已迁移到数据根目录
+    Migrated {
+        /// 旧数据所在目录
+        from: PathBuf,
+        /// 迁移结果
+        report: DataMigrationReport,
+    },
+    /// 用户选择暂不迁移，本次使用旧数据所在目录
+    Declined {
+        /// 旧数据所在目录
+        from: PathBuf,
+    },
+    /// 迁移失败，原文件保持不变，本次使用旧数据所在目录
+    Failed {
+        /// 旧数据所在目录
+        from: PathBuf,
+        /// 失败原因
+        error: String,
+    },
+}
+
+impl LegacyData {
+    /// 可能存放旧数据的目录：程序所在目录和工作目录
+    #[must_use]
+    pub fn candidates() -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        for dir in [executable_dir().ok(), std::env::current_dir().ok()]
+            .into_iter()
+            .flatten()
+        {
+            if !candidates.contains(&dir) {
+                candidates.push(dir);
+            }
+        }
+        candidates
+    }
+
+    /// 在候选目录中查找需要迁移的旧数据
+    ///
+    /// 便携模式下旧布局就是当前布局，不迁移；旧目录就是数据根目录，或数据根目录下
+    /// 已有同名目录（已迁移过或已开始使用新位置）时也不迁移。
+    #[must_use]
+    pub fn find(candidates: &[PathBuf], root: &DataRoot) -> Option<Self> {
+        if root.is_portable() {
+            return None;
+        }
+        candidates.iter().find_map(|base| {
+            if !base.join("data").is_dir() || same_dir(base, root.path()) {
+                return None;
+            }
+            let entries: Vec<&'static str> = LEGACY_ENTRIES
+                .into_iter()
+                .filter(|entry| base.join(entry).exists())
+                .collect();
+            if entries.iter().any(|entry| root.path().join(entry).exists()) {
+                return None;
+            }
+            Some(Self {
+                base: base.clone(),
+                entries,
+            })
+        })
+    }
+
+    /// 旧数据所在目录
+    #[must_use]
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// 需要迁移的目录名
+    #[must_use]
+    pub fn entries(&self) -> &[&'static str] {
+        &self.entries
+    }
+
+    /// 迁移提示文本
+    #[must_use]
+    pub fn prompt(&self, root: &DataRoot) -> String {
+        format!(
+            "在 {} 发现旧版本的数据（{}）。\n是否移动到 {}？\n\
+             复制完成并逐个文件校验一致后才会删除原文件。",
+            self.base.display(),
+            self.entries.join("、"),
+            root.path().display()
+        )
+    }
+
+    /// 把旧数据移动到数据根目录
+    ///
+    /// 先复制全部文件并逐个校验内容，全部一致后才删除原目录；复制或校验失败时
+    /// 删除已复制的部分，原文件保持不变。
+    ///
+    /// # Errors
+    ///
+    /// 复制或校验失败时返回错误。
+    pub fn migrate(&self, root: &DataRoot) -> Result<DataMigrationReport> {
+        fs::create_dir_all(root.path())
+            .with_context(|| format!("无法创建数据目录: {}", root.path().display()))?;
+
+        let mut report = match self.copy_and_verify(root) {
+            Ok(report) => report,
+            Err(e) => {
+                for entry in &self.entries {
+                    let target = root.path().join(entry);
+                    if target.exists() {
+                        let _ = fs::remove_dir_all(&target);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        for entry in &self.entries {
+            let source = self.base.join(entry);
+            if fs::remove_dir_all(&source).is_err() {
+                report.leftovers.push(source);
+            }
+        }
+        Ok(report)
+    }
+
+    fn copy_and_verify(&self, root: &DataRoot) -> Result<DataMigrationReport> {
+        let mut copied = Vec::new();
+        for entry in &self.entries {
+            copy_tree(&self.base.join(entry), &root.path().join(entry), &mut copied)?;
+        }
+        let mut report = DataMigrationReport::default();
+        for (source, target) in &copied {
+            if !files_equal(source, target)? {
+                bail!("文件校验不一致: {}", source.display());
+            }
+            report.files += 1;
+            report.bytes += fs::metadata(target)?.len();
+        }
+        Ok(report)
+    }
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 递归复制目录，记录复制的每个文件
+fn copy_tree(source: &Path, target: &Path, copied: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    if source.is_file() {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建目录: {}", parent.display()))?;
+        }
+        fs::copy(source, target)
+            .with_context(|| format!("无法复制文件: {}", source.display()))?;
+        copied.push((source.to_path_buf(), target.to_path_buf()));
+        return Ok(());
+    }
+    fs::create_dir_all(target).with_context(|| format!("无法创建目录: {}", target.display()))?;
+    let entries =
+        fs::read_dir(source).with_context(|| format!("无法读取目录: {}", source.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        copy_tree(&entry.path(), &target.join(entry.file_name()), copied)?;
+    }
+    Ok(())
+}
+
+/// 逐字节比较两个文件
+fn files_equal(a: &Path, b: &Path) -> Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    let mut a = File::open(a)?;
+    let mut b = File::open(b)?;
+    let mut buf_a = vec![0_u8; 64 * 1024];
+    let mut buf_b = vec![0_u8; 64 * 1024];
+    loop {
+        let read = a.read(&mut buf_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        match b.read_exact(&mut buf_b[..read]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &[u8]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn legacy_layout(base: &Path) -> Result<()> {
+        write(&base.join("data/minicrm.db"), b"sqlite")?;
+        write(&base.join("data/attachments/1/license.pdf"), b"pdf")?;
+        write(&base.join("data/backups/minicrm-20240101.db"), b"backup")?;
+        write(&base.join("logs/minicrm.log"), b"log")?;
+        write(&base.join("config/minicrm.json"), b"{}")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_options() {
+        assert_eq!(LaunchOptions::parse(["--other"]), LaunchOptions::default());
+        let options = LaunchOptions::parse(["--portable", "--migrate-data"]);
+        assert!(options.portable);
+        assert!(options.migrate_data);
+        assert!(!options.verify_migrations);
+
+        assert!(LaunchOptions::parse(["migrate", "--verify"]).verify_migrations);
+        assert!(!LaunchOptions::parse(["--verify"]).verify_migrations);
+
+        assert_eq!(LaunchOptions::parse(["--json"]), LaunchOptions::default());
+        assert_eq!(
+            LaunchOptions::parse(["check-consistency"]).check_consistency,
+            Some(ConsistencyOptions::default())
+        );
+        let options = LaunchOptions::parse([
+            "--portable",
+            "check-consistency",
+            "--checks",
+            "quote_totals, table_counters",
+            "--limit",
+            "20",
+            "--json",
+        ]);
+        assert!(options.portable);
+        assert_eq!(
+            options.check_consistency,
+            Some(ConsistencyOptions {
+                checks: Some(vec!["quote_totals".to_string(), "table_counters".to_string()]),
+                limit: Some(20),
+                json: true,
+            })
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_platform_root_linux() -> Result<()> {
+        let root = DataRoot::in_base(Path::new("/home/user/.local/share"));
+        assert_eq!(root.path(), Path::new("/home/user/.local/share/minicrm"));
+        assert!(DataRoot::platform()?.path().ends_with("minicrm"));
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_platform_root_macos() -> Result<()> {
+        let root = DataRoot::platform()?;
+        assert!(root.path().ends_with("Library/Application Support/MiniCRM"));
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_platform_root_windows() -> Result<()> {
+        let root = DataRoot::platform()?;
+        let appdata = PathBuf::from(std::env::var("APPDATA")?);
+        assert_eq!(root.path(), appdata.join("MiniCRM"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_paths_resolve_under_root() -> Result<()> {
+        let dir = TempDir::new()?;
+        let root = DataRoot::at(dir.path());
+        let absolute = dir.path().join("elsewhere/crm.db");
+
+        let mut config = AppConfig::default();
+        config.database.backups_dir = absolute.clone();
+        config.resolve_paths(&root);
+        assert_eq!(config.database.path, dir.path().join("data/minicrm.db"));
+        assert_eq!(config.database.backups_dir, absolute);
+        assert_eq!(
+            config.logging.file_path,
+            Some(dir.path().join("logs/minicrm.log"))
+        );
+        assert_eq!(root.config_file(), dir.path().join(CONFIG_FILE_PATH));
+        Ok(())
+    }
+
+    #[test]
+    fn test_portable_overrides_platform_root() -> Result<()> {
+        let options = LaunchOptions::parse(["--portable"]);
+        let root = DataRoot::select(&options)?;
+        assert!(root.is_portable());
+        assert_eq!(root.path(), executable_dir()?);
+
+        // 配置中的绝对路径优先于便携模式
+        let dir = TempDir::new()?;
+        let mut config = AppConfig::default();
+        config.database.path = dir.path().join("crm.db");
+        config.resolve_paths(&root);
+        assert_eq!(config.database.path, dir.path().join("crm.db"));
+        assert_eq!(config.database.attachments_dir, root.path().join("data/attachments"));
+
+        // 便携模式就是旧布局，不提示迁移
+        legacy_layout(dir.path())?;
+        assert_eq!(LegacyData::find(&[dir.path().to_path_buf()], &root), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_moves_and_verifies() -> Result<()> {
+        let legacy = TempDir::new()?;
+        let target = TempDir::new()?;
+        legacy_layout(legacy.path())?;
+        let root = DataRoot::at(target.path().join("MiniCRM"));
+
+        let found = LegacyData::find(&[legacy.path().to_path_buf()], &root)
+            .ok_or_else(|| anyhow::anyhow!("应发现旧数据"))?;
+        assert_eq!(found.entries(), ["data", "logs", "config"]);
+
+        let report = found.migrate(&root)?;
+        assert_eq!(report.files, 5);
+        assert!(report.leftovers.is_empty());
+        assert_eq!(fs::read(root.path().join("data/minicrm.db"))?, b"sqlite");
+        assert_eq!(
+            fs::read(root.path().join("data/attachments/1/license.pdf"))?,
+            b"pdf"
+        );
+        assert!(!legacy.path().join("data").exists());
+
+        // 只迁移一次
+        assert_eq!(LegacyData::find(&[legacy.path().to_path_buf()], &root), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_target_is_not_overwritten() -> Result<()> {
+        let legacy = TempDir::new()?;
+        let target = TempDir::new()?;
+        legacy_layout(legacy.path())?;
+        let root = DataRoot::at(target.path());
+        write(&target.path().join("data/minicrm.db"), b"new")?;
+
+        assert_eq!(LegacyData::find(&[legacy.path().to_path_buf()], &root), None);
+        assert_eq!(fs::read(legacy.path().join("data/minicrm.db"))?, b"sqlite");
+        Ok(())
+    }
+
+    #[test]
+    fn test_verification_detects_mismatch() -> Result<()> {
+        let dir = TempDir::new()?;
+        write(&dir.path().join("a"), b"same size")?;
+        write(&dir.path().join("b"), b"same sizE")?;
+        write(&dir.path().join("c"), b"same size")?;
+        assert!(!files_equal(&dir.path().join("a"), &dir.path().join("b"))?);
+        assert!(files_equal(&dir.path().join("a"), &dir.path().join("c"))?);
+        Ok(())
+    }
+}
@@ -1,25 +1,43 @@
 //! 数据库管理模块
 //!
 //! 提供数据库初始化、连接管理和健康检查功能。
-//! 集成了 SQLite 数据库和连接池管理。
+//! 集成了 `SQLite` 数据库和连接池管理。
 
 use anyhow::{Context, Result};
-use std::path::Path;
-use tracing::{info, warn};
+use chrono::Utc;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use tracing::info;
 
 use crate::config::AppConfig;
-use infrastructure::database::{
-    health::DatabaseHealth,
-    pool::{DatabasePool, DatabasePoolBuilder, DatabasePoolExt},
+use crate::core::{
+    ExclusiveGuard, PoolSizeSuggestion, PoolUsageSummary, ReversibilityReport,
+    SizeGrowthThresholds, SizeSample,
+};
+use crate::startup::{StartupPhase, StartupProfiler};
+use crate::infrastructure::database::{
+    pool::{DatabaseHealth, DatabasePool, DatabasePoolBuilder, DatabasePoolExt},
+    schema, DatabaseConnection, DatabaseFileGuard, DatabaseSizeMonitor, MigrationManager,
+    MigrationProgress, PoolUsageMetrics, PoolUsageStore, SizeHistoryStore, SlowQueryLog,
 };
 
 /// 数据库管理器
 ///
-/// 负责数据库的初始化、连接池管理和健康检查
-#[derive(Debug)]
+/// 负责数据库的初始化、连接池管理和健康检查。备份和整理需要独占数据库文件，
+/// 与记录归档共用同一个独占操作守卫。初始化完成后通过文件守卫检测外部程序对数据库文件的修改。
+/// 所有连接共用一个慢查询记录器，诊断界面从中读取慢查询及其执行计划。
+/// 连接池的使用情况在退出时保存，诊断界面据此建议最大连接数。
+/// 增长监控任务定时记录数据库大小，增长过快时警告。
+#[derive(Debug, Clone)]
 pub struct DatabaseManager {
     pool: DatabasePool,
-    database_path: String,
+    database_path: PathBuf,
+    exclusive: Arc<ExclusiveGuard>,
+    file_guard: Option<Arc<DatabaseFileGuard>>,
+    slow_queries: Arc<SlowQueryLog>,
+    pool_usage: Arc<PoolUsageMetrics>,
 }
 
 impl DatabaseManager {
@@ -30,15 +48,52 @@ impl DatabaseManager {
     ///
     /// # 返回
     /// 返回初始化完成的数据库管理器或错误
+    ///
+    /// # Errors
+    ///
+    /// 创建数据库目录、连接池或执行迁移失败时返回错误。
     pub fn new(config: &AppConfig) -> Result<Self> {
-        info!("正在初始化数据库管理器: {}", config.database.path);
+        Self::open(config, None, &StartupProfiler::new())
+    }
+
+    /// 创建数据库管理器，创建连接池和执行迁移的耗时记入 `profiler`
+    ///
+    /// # Errors
+    ///
+    /// 与 [`Self::new`] 相同。
+    pub fn with_profiler(config: &AppConfig, profiler: &StartupProfiler) -> Result<Self> {
+        Self::open(config, None, profiler)
+    }
+
+    /// 创建数据库管理器，并通过 `progress` 报告迁移进度
+    ///
+    /// 用于启动画面显示升级进度；迁移开始后不能取消。创建连接池和执行迁移的耗时记入
+    /// `profiler`。
+    ///
+    /// # Errors
+    ///
+    /// 与 [`Self::new`] 相同。
+    pub fn with_migration_progress(
+        config: &AppConfig,
+        progress: Sender<MigrationProgress>,
+        profiler: &StartupProfiler,
+    ) -> Result<Self> {
+        Self::open(config, Some(progress), profiler)
+    }
+
+    fn open(
+        config: &AppConfig,
+        progress: Option<Sender<MigrationProgress>>,
+        profiler: &StartupProfiler,
+    ) -> Result<Self> {
+        info!("正在初始化数据库管理器: {}", config.database.path.display());
 
         // 确保数据库目录存在
-        let db_path = Path::new(&config.database.path);
+        let db_path = config.database.path.as_path();
         if let Some(parent_dir) = db_path.parent() {
             if !parent_dir.exists() {
                 std::fs::create_dir_all(parent_dir)
-                    .with_context(|| format!("无法创建数据库目录: {:?}", parent_dir))?;
+                    .with_context(|| format!("无法创建数据库目录: {}", parent_dir.display()))?;
                 info!("已创建数据库目录: {:?}", parent_dir);
             }
         }
@@ -50,38 +105,72 @@ impl DatabaseManager {
         }
 
         // 创建连接池
-        let pool = DatabasePoolBuilder::new(&config.database.path)
-            .max_connections(config.database.max_connections)
-            .connection_timeout(config.database.connection_timeout_secs)
-            .build()
-            .context("无法创建数据库连接池")?;
+        let pool_usage = Arc::new(PoolUsageMetrics::new(
+            config.database.max_connections,
+            Utc::now(),
+        ));
+        let pool = profiler.time(StartupPhase::Pool, || {
+            DatabasePoolBuilder::new(config.database.path.to_string_lossy())
+                .max_connections(config.database.max_connections)
+                .connection_timeout(config.database.connection_timeout)
+                .warm_up(config.database.warm_up_connections)
+                .enable_mmap(config.database.enable_mmap)
+                .extensions(config.database.extensions.paths.clone())
+                .usage_metrics(pool_usage.clone())
+                .build()
+                .context("无法创建数据库连接池")
+        })?;
 
-        let manager = Self {
+        let mut manager = Self {
             pool,
             database_path: config.database.path.clone(),
+            exclusive: Arc::new(ExclusiveGuard::new()),
+            file_guard: None,
+            slow_queries: Arc::new(SlowQueryLog::default()),
+            pool_usage,
         };
 
-        // 如果是新数据库，执行初始化
-        if is_new_database {
-            manager.initialize_database()?;
-        }
+        profiler.time(StartupPhase::Migrations, || -> Result<()> {
+            // 如果是新数据库，执行初始化
+            if is_new_database {
+                manager.initialize_database()?;
+            }
+
+            // 引入迁移之前的版本创建的数据库先补写迁移记录，避免重复建表
+            if !is_new_database {
+                manager.baseline_legacy_schema()?;
+            }
+
+            // 应用内置迁移
+            manager.run_migrations(progress)
+        })?;
 
-        // 执行健康检查
+        // 快速健康检查（完整的健康检查在窗口显示后执行，见 `crate::startup`）
         manager.pool.health_check().with_context(|| {
-            format!("数据库健康检查失败: {}", config.database.path)
+            format!("数据库健康检查失败: {}", config.database.path.display())
         })?;
 
+        // 以初始化完成后的状态为基准检测外部修改
+        let guard = DatabaseFileGuard::open(
+            &config.database.path,
+            config.database.read_only_on_external_change,
+        )
+        .context("无法启动数据库文件检测")?;
+        manager.file_guard = Some(Arc::new(guard));
+
         info!("数据库管理器初始化完成");
         Ok(manager)
     }
 
     /// 获取数据库连接池引用
-    pub fn pool(&self) -> &DatabasePool {
+    #[must_use]
+    pub const fn pool(&self) -> &DatabasePool {
         &self.pool
     }
 
     /// 获取数据库路径
-    pub fn database_path(&self) -> &str {
+    #[must_use]
+    pub fn database_path(&self) -> &Path {
         &self.database_path
     }
 
@@ -89,10 +178,128 @@ impl DatabaseManager {
     ///
     /// # 返回
     /// 返回详细的健康检查结果
+    #[must_use]
     pub fn check_health(&self) -> DatabaseHealth {
         self.pool.get_health()
     }
 
+    /// 独占操作守卫（传给记录归档等同样需要独占数据库的操作）
+    #[must_use]
+    pub fn exclusive_guard(&self) -> Arc<ExclusiveGuard> {
+        self.exclusive.clone()
+    }
+
+    /// 数据库文件守卫（初始化完成后可用）
+    #[must_use]
+    pub const fn file_guard(&self) -> Option<&Arc<DatabaseFileGuard>> {
+        self.file_guard.as_ref()
+    }
+
+    /// 慢查询记录器
+    #[must_use]
+    pub fn slow_query_log(&self) -> Arc<SlowQueryLog> {
+        self.slow_queries.clone()
+    }
+
+    /// 保存本次运行的连接池使用情况（退出时调用）
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时返回错误。
+    pub fn record_pool_usage(&self) -> Result<()> {
+        let summary = self.pool_usage.summary(Utc::now());
+        info!(
+            "连接池使用情况: 峰值={}, 借出={}次, 最长等待={}ms, 超时={}次, 关闭={}个",
+            summary.peak_checkouts,
+            summary.checkouts,
+            summary.max_wait_ms,
+            summary.timeouts,
+            summary.evictions
+        );
+        PoolUsageStore::new(self.connection()).record(&summary)
+    }
+
+    /// 本次运行到目前为止的连接池使用情况（诊断包附带）
+    #[must_use]
+    pub fn pool_usage_summary(&self) -> PoolUsageSummary {
+        self.pool_usage.summary(Utc::now())
+    }
+
+    /// 根据最近几次运行建议最大连接数，建议值不超过 `hard_cap`
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn suggest_pool_size(&self, hard_cap: u32) -> Result<Option<PoolSizeSuggestion>> {
+        PoolUsageStore::new(self.connection()).suggest_pool_size(self.pool.max_size(), hard_cap)
+    }
+
+    /// 数据库增长监控任务（注册到任务调度器，按 `thresholds` 警告）
+    #[must_use]
+    pub fn size_monitor(&self, thresholds: SizeGrowthThresholds) -> DatabaseSizeMonitor {
+        DatabaseSizeMonitor::new(SizeHistoryStore::new(self.connection()), thresholds)
+    }
+
+    /// 最近 `days` 天的数据库大小记录（诊断界面绘制大小变化曲线）
+    ///
+    /// # Errors
+    ///
+    /// 查询失败时返回错误。
+    pub fn size_history(&self, days: i64) -> Result<Vec<SizeSample>> {
+        let since = Utc::now() - chrono::Duration::days(days);
+        SizeHistoryStore::new(self.connection()).history(since)
+    }
+
+    /// 在内存中的临时数据库上检查内置迁移能否回滚（不读写数据库文件）
+    ///
+    /// # Errors
+    ///
+    /// 无法创建临时数据库或迁移本身执行失败时返回错误。
+    pub fn verify_migrations() -> Result<ReversibilityReport> {
+        MigrationManager::in_memory()?
+            .add_migrations(schema::builtin_migrations())
+            .verify_reversibility()
+    }
+
+    /// 获取数据库连接封装
+    #[must_use]
+    pub fn connection(&self) -> DatabaseConnection {
+        let connection = DatabaseConnection::new(self.pool.clone())
+            .with_slow_query_log(self.slow_queries.clone());
+        match &self.file_guard {
+            Some(guard) => connection.with_file_guard(guard.clone()),
+            None => connection,
+        }
+    }
+
+    /// 为引入迁移之前创建的数据库补写迁移记录（结构不一致时中止启动）
+    fn baseline_legacy_schema(&self) -> Result<()> {
+        let baselined = MigrationManager::new(self.connection())
+            .add_migrations(schema::builtin_migrations())
+            .baseline_if_needed(schema::LEGACY_SCHEMA_VERSION)
+            .with_context(|| format!("无法升级旧版数据库: {}", self.database_path.display()))?;
+        if baselined {
+            info!(
+                "旧版数据库已按 v{} 建立迁移基线: {}",
+                schema::LEGACY_SCHEMA_VERSION,
+                self.database_path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// 应用全部内置迁移
+    fn run_migrations(&self, progress: Option<Sender<MigrationProgress>>) -> Result<()> {
+        let migrations =
+            MigrationManager::new(self.connection()).add_migrations(schema::builtin_migrations());
+        progress
+            .map_or_else(
+                || migrations.migrate(None),
+                |tx| migrations.migrate_with_progress(None, tx),
+            )
+            .context("数据库迁移失败")
+    }
+
     /// 初始化数据库结构
     ///
     /// 创建必要的表和索引
@@ -110,7 +317,7 @@ impl DatabaseManager {
 
         // 创建客户表
         conn.execute(
-            r#"
+            r"
             CREATE TABLE IF NOT EXISTS customers (
                 id TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
@@ -122,14 +329,14 @@ impl DatabaseManager {
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )
-            "#,
+            ",
             [],
         )
         .context("无法创建客户表")?;
 
         // 创建任务表
         conn.execute(
-            r#"
+            r"
             CREATE TABLE IF NOT EXISTS tasks (
                 id TEXT PRIMARY KEY,
                 customer_id TEXT NOT NULL,
@@ -143,14 +350,14 @@ impl DatabaseManager {
                 updated_at TEXT NOT NULL,
                 FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
             )
-            "#,
+            ",
             [],
         )
         .context("无法创建任务表")?;
 
         // 创建报价表
         conn.execute(
-            r#"
+            r"
             CREATE TABLE IF NOT EXISTS quotes (
                 id TEXT PRIMARY KEY,
                 customer_id TEXT NOT NULL,
@@ -164,20 +371,20 @@ impl DatabaseManager {
                 updated_at TEXT NOT NULL,
                 FOREIGN KEY (customer_id) REFERENCES customers (id) ON DELETE CASCADE
             )
-            "#,
+            ",
             [],
         )
         .context("无法创建报价表")?;
 
         // 创建索引
-        self.create_indexes(&conn)?;
+        Self::create_indexes(&conn)?;
 
         info!("数据库结构初始化完成");
         Ok(())
     }
 
     /// 创建数据库索引
-    fn create_indexes(&self, conn: &rusqlite::Connection) -> Result<()> {
+    fn create_indexes(conn: &rusqlite::Connection) -> Result<()> {
         let indexes = [
             "CREATE INDEX IF NOT EXISTS idx_customers_email ON customers(email)",
             "CREATE INDEX IF NOT EXISTS idx_customers_company ON customers(company)",
@@ -201,57 +408,189 @@ impl DatabaseManager {
     ///
     /// # 参数
     /// * `backup_path` - 备份文件路径
+    ///
+    /// # Errors
+    ///
+    /// 正在进行其他独占操作、无法创建备份目录或备份失败时返回错误。
     pub fn backup_database<P: AsRef<Path>>(&self, backup_path: P) -> Result<()> {
         let backup_path = backup_path.as_ref();
+        let _lease = self.exclusive.try_acquire("数据库备份")?;
         info!("正在备份数据库到: {:?}", backup_path);
 
         // 确保备份目录存在
         if let Some(parent_dir) = backup_path.parent() {
             std::fs::create_dir_all(parent_dir)
-                .with_context(|| format!("无法创建备份目录: {:?}", parent_dir))?;
+                .with_context(|| format!("无法创建备份目录: {}", parent_dir.display()))?;
         }
 
-        let conn = self.pool.get().context("无法获取数据库连接进行备份")?;
+        let conn = self
+            .connection()
+            .get_connection()
+            .context("无法获取数据库连接进行备份")?;
 
         // 使用 SQLite 的 VACUUM INTO 命令进行备份
         conn.execute(
             "VACUUM INTO ?",
             [backup_path.to_string_lossy().as_ref()],
         )
-        .with_context(|| format!("数据库备份失败: {:?}", backup_path))?;
+        .with_context(|| format!("数据库备份失败: {}", backup_path.display()))?;
 
         info!("数据库备份完成: {:?}", backup_path);
         Ok(())
     }
 
     /// 获取数据库统计信息
+    ///
+    /// # Errors
+    ///
+    /// 数据库查询失败时返回错误。
     pub fn get_database_stats(&self) -> Result<DatabaseStats> {
-        let conn = self.pool.get().context("无法获取数据库连接")?;
+        let conn = self.connection().get_connection()?;
 
-        let customer_count: i64 = conn
+        let customer_count: u64 = conn
             .query_row("SELECT COUNT(*) FROM customers", [], |row| row.get(0))
             .context("无法查询客户数量")?;
 
-        let task_count: i64 = conn
+        let task_count: u64 = conn
             .query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))
             .context("无法查询任务数量")?;
 
-        let quote_count: i64 = conn
+        let quote_count: u64 = conn
             .query_row("SELECT COUNT(*) FROM quotes", [], |row| row.get(0))
             .context("无法查询报价数量")?;
 
         // 获取数据库文件大小
         let file_size = std::fs::metadata(&self.database_path)
-            .map(|metadata| metadata.len())
-            .unwrap_or(0);
+            .map_or(0, |metadata| metadata.len());
+
+        let reclaimable = self.estimate_reclaimable_space()?;
 
         Ok(DatabaseStats {
-            customer_count: customer_count as u64,
-            task_count: task_count as u64,
-            quote_count: quote_count as u64,
+            customer_count,
+            task_count,
+            quote_count,
             file_size_bytes: file_size,
+            reclaimable_bytes: reclaimable.bytes(),
+        })
+    }
+
+    /// 估算整理（VACUUM）可释放的空间
+    ///
+    /// 按空闲页数 × 页大小加上 WAL 文件大小计算，不读取数据页。
+    ///
+    /// # Errors
+    ///
+    /// 数据库查询失败时返回错误。
+    pub fn estimate_reclaimable_space(&self) -> Result<ReclaimableSpace> {
+        let conn = self.connection().get_connection()?;
+        let page_size: u64 = conn
+            .query_row("PRAGMA page_size", [], |row| row.get(0))
+            .context("无法查询页大小")?;
+        let freelist_pages: u64 = conn
+            .query_row("PRAGMA freelist_count", [], |row| row.get(0))
+            .context("无法查询空闲页数")?;
+        let wal_bytes = std::fs::metadata(self.wal_path())
+            .map_or(0, |metadata| metadata.len());
+
+        Ok(ReclaimableSpace {
+            page_size,
+            freelist_pages,
+            wal_bytes,
         })
     }
+
+    /// 整理数据库文件：合并 WAL 后执行 VACUUM
+    ///
+    /// 各阶段开始时调用 `on_progress`，返回实际释放的字节数。
+    ///
+    /// # Errors
+    ///
+    /// 正在进行其他独占操作或整理失败时返回错误。
+    pub fn compact_database(&self, mut on_progress: impl FnMut(CompactStage)) -> Result<u64> {
+        let _lease = self.exclusive.try_acquire("数据库整理")?;
+        let size_before = self.total_file_size();
+        let conn = self
+            .connection()
+            .get_connection()
+            .context("无法获取数据库连接进行整理")?;
+
+        on_progress(CompactStage::Checkpoint);
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .context("WAL合并失败")?;
+
+        on_progress(CompactStage::Vacuum);
+        conn.execute_batch("VACUUM").context("数据库整理失败")?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .context("WAL合并失败")?;
+
+        let freed = size_before.saturating_sub(self.total_file_size());
+        on_progress(CompactStage::Finished { freed_bytes: freed });
+        info!("数据库整理完成，释放 {} 字节", freed);
+        Ok(freed)
+    }
+
+    /// WAL 文件路径（数据库文件名加 `-wal` 后缀）
+    fn wal_path(&self) -> PathBuf {
+        let mut path = OsString::from(self.database_path.as_os_str());
+        path.push("-wal");
+        PathBuf::from(path)
+    }
+
+    /// 数据库文件与 WAL 文件的总大小
+    fn total_file_size(&self) -> u64 {
+        [self.database_path.clone(), self.wal_path()]
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+}
+
+/// 可释放空间估算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReclaimableSpace {
+    /// 页大小（字节）
+    pub page_size: u64,
+    /// 空闲页数
+    pub freelist_pages: u64,
+    /// WAL 文件大小（字节）
+    pub wal_bytes: u64,
+}
+
+impl ReclaimableSpace {
+    /// 可释放的总字节数
+    #[must_use]
+    pub const fn bytes(&self) -> u64 {
+        self.freelist_pages * self.page_size + self.wal_bytes
+    }
+}
+
+/// 数据库整理阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactStage {
+    /// 合并 WAL
+    Checkpoint,
+    /// 重建数据库文件
+    Vacuum,
+    /// 完成
+    Finished {
+        /// 释放的字节数
+        freed_bytes: u64,
+    },
+}
+
+impl CompactStage {
+    /// 界面显示的进度文本
+    #[must_use]
+    pub fn label(&self) -> String {
+        match self {
+            Self::Checkpoint => "正在整理数据库（1/2）：合并日志...".to_string(),
+            Self::Vacuum => "正在整理数据库（2/2）：重建文件...".to_string(),
+            Self::Finished { freed_bytes } => {
+                format!("数据库整理完成，释放约 {} MB", freed_bytes / (1024 * 1024))
+            }
+        }
+    }
 }
 
 /// 数据库统计信息
@@ -265,10 +604,14 @@ pub struct DatabaseStats {
     pub quote_count: u64,
     /// 数据库文件大小（字节）
     pub file_size_bytes: u64,
+    /// 整理后可释放的空间（字节）
+    pub reclaimable_bytes: u64,
 }
 
 impl DatabaseStats {
     /// 获取格式化的文件大小
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // 只用于显示，保留两位小数
     pub fn formatted_file_size(&self) -> String {
         const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
         let mut size = self.file_size_bytes as f64;
@@ -290,7 +633,7 @@ mod tests {
 
     fn create_test_config() -> Result<AppConfig> {
         let temp_dir = TempDir::new().context("无法创建临时目录")?;
-        let db_path = temp_dir.path().join("test.db").to_string_lossy().to_string();
+        let db_path = temp_dir.path().join("test.db");
 
         let mut config = AppConfig::default();
         config.database.path = db_path;
@@ -338,8 +681,61 @@ mod tests {
         
         // 测试格式化文件大小
         let formatted_size = stats.formatted_file_size();
-        assert!(formatted_size.contains("B") || formatted_size.contains("KB"));
+        assert!(formatted_size.contains('B') || formatted_size.contains("KB"));
+
+        Ok(())
+    }
 
+    #[test]
+    fn test_reclaimable_space_after_delete() -> Result<()> {
+        let config = create_test_config()?;
+        let db_manager = DatabaseManager::new(&config)?;
+        let conn = db_manager.pool().get()?;
+        conn.execute_batch("CREATE TABLE blobs (id INTEGER PRIMARY KEY, data BLOB NOT NULL)")?;
+        for _ in 0..64 {
+            conn.execute("INSERT INTO blobs (data) VALUES (zeroblob(16384))", [])?;
+        }
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        assert_eq!(db_manager.estimate_reclaimable_space()?.freelist_pages, 0);
+
+        conn.execute("DELETE FROM blobs", [])?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        drop(conn);
+
+        // 每行占用 16 KB 溢出页，删除后全部进入空闲列表
+        let space = db_manager.estimate_reclaimable_space()?;
+        assert_eq!(space.wal_bytes, 0);
+        assert!(space.freelist_pages * space.page_size >= 64 * 16384);
+        assert_eq!(space.bytes(), space.freelist_pages * space.page_size);
+
+        let mut stages = Vec::new();
+        let freed = db_manager.compact_database(|stage| stages.push(stage))?;
+        assert!(freed >= 64 * 16384);
+        assert_eq!(stages.len(), 3);
+        assert_eq!(stages[2], CompactStage::Finished { freed_bytes: freed });
+        assert_eq!(db_manager.estimate_reclaimable_space()?.bytes(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_waits_for_exclusive_operation() -> Result<()> {
+        let config = create_test_config()?;
+        let db_manager = DatabaseManager::new(&config)?;
+        let temp_dir = TempDir::new()?;
+        let backup_path = temp_dir.path().join("backup.db");
+
+        let archiving = db_manager.exclusive_guard().try_acquire("数据归档")?;
+        let err = db_manager
+            .backup_database(&backup_path)
+            .err()
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+        assert!(err.contains("数据归档正在进行"));
+        assert!(!backup_path.exists());
+
+        drop(archiving);
+        db_manager.backup_database(&backup_path)?;
+        assert!(backup_path.exists());
         Ok(())
     }
 
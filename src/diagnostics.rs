@@ -0,0 +1,705 @@
+//! 诊断包模块
+//!
+//! 用户反馈问题时，不再分别索要日志、健康检查等文件，而是在诊断界面导出一个zip诊断包：
+//! “关于”信息、常规和深度健康检查结果、数据库统计、迁移状态和回滚检查、连接池使用情况、
+//! 最近的日志和操作日志、慢查询及其执行计划、一致性检查汇总。
+//!
+//! 诊断包不含密钥和未经处理的个人数据：所有文本中的配置密钥被遮盖，日志和慢查询中的邮箱、
+//! 电话号码在写入前遮盖，一致性检查只写汇总，不写具体记录。只有明确选择时才附带数据库
+//! 副本，副本由 [`anonymize_copy`] 匿名化后写入。
+
+use std::fs::{self, File};
+use std::fmt::Write as _;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use serde_json::json;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::action_log::ACTION_LOG_FILE_NAME;
+use crate::application::{consistency_summary, ConsistencyChecker, DEFAULT_VIOLATION_LIMIT};
+use crate::config::AppConfig;
+use crate::core::{
+    CancellationToken, ConsistencySeverity, CoreResult, DiagnosticsBundleService,
+    DiagnosticsBundleSummary, ExportProgress, DIAGNOSTIC_LOG_FILES,
+};
+use crate::database::DatabaseManager;
+use crate::infrastructure::database::{
+    anonymize_copy, schema, DatabaseHealthChecker, MigrationManager, PoolUsageStore,
+};
+use crate::infrastructure::repository::builtin_consistency_checks;
+use crate::settings_transfer::secret_values;
+
+/// 遮盖后的占位文本
+pub const REDACTED: &str = "[已隐藏]";
+
+/// 每个日志文件最多写入的字节数（超过时只保留末尾）
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// 数据库统计中附带的大小记录天数
+const SIZE_HISTORY_DAYS: i64 = 30;
+
+/// 匿名化数据库副本在包内的路径
+pub const DATABASE_ENTRY: &str = "database/minicrm-anonymized.db";
+
+/// 诊断包的部分（数据库副本除外），进度按部分累加
+const SECTIONS: [&str; 9] = [
+    "about",
+    "health",
+    "database_stats",
+    "migrations",
+    "pool_usage",
+    "logs",
+    "action_log",
+    "slow_queries",
+    "consistency",
+];
+
+/// 导出诊断包需要写入的部分数（进度对话框的总数）
+#[must_use]
+pub fn section_count(include_db_copy: bool) -> u64 {
+    SECTIONS.len() as u64 + u64::from(include_db_copy)
+}
+
+/// 版本和运行环境信息（“关于”）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AboutInfo {
+    /// 应用版本
+    pub app_version: String,
+    /// 数据库结构版本
+    pub schema_version: u32,
+    /// 应用内置的最新结构版本
+    pub latest_schema_version: u32,
+    /// 操作系统和架构
+    pub platform: String,
+    /// 编译时启用的可选功能
+    pub features: Vec<&'static str>,
+    /// 数据库文件名（不含目录，目录中可能有用户名）
+    pub database_file: String,
+}
+
+impl AboutInfo {
+    /// 读取当前应用和数据库的信息
+    ///
+    /// # Errors
+    ///
+    /// 无法读取迁移记录时返回错误。
+    pub fn collect(database: &DatabaseManager) -> Result<Self> {
+        let status = MigrationManager::new(database.connection())
+            .add_migrations(schema::builtin_migrations())
+            .get_migration_status()?;
+        let features = [
+            ("gui", cfg!(feature = "gui")),
+            ("api", cfg!(feature = "api")),
+            ("integrations", cfg!(feature = "integrations")),
+            ("sqlite-extensions", cfg!(feature = "sqlite-extensions")),
+            ("keyring", cfg!(feature = "keyring")),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+        Ok(Self {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: status.current_version,
+            latest_schema_version: status.latest_version,
+            platform: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            features,
+            database_file: Path::new(database.database_path())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// 多行文本
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        format!(
+            "MiniCRM v{}\n数据库结构版本: {}（最新 {}）\n运行平台: {}\n启用功能: {}\n\
+             数据库文件: {}\n",
+            self.app_version,
+            self.schema_version,
+            self.latest_schema_version,
+            self.platform,
+            self.features.join(", "),
+            self.database_file
+        )
+    }
+}
+
+/// 文本遮盖
+///
+/// 配置中的密钥在所有文本中遮盖；邮箱和电话号码（7到15位连续数字）只在日志和慢查询这类
+/// 可能含有业务数据的文本中遮盖。
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// 遮盖 `config` 中已填写的密钥
+    #[must_use]
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            secrets: secret_values(config),
+        }
+    }
+
+    /// 遮盖密钥
+    #[must_use]
+    pub fn secrets(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), REDACTED)
+        })
+    }
+
+    /// 遮盖密钥、邮箱和电话号码
+    #[must_use]
+    pub fn personal(&self, text: &str) -> String {
+        let text = self.secrets(text);
+        let mut out = String::with_capacity(text.len());
+        let mut token = String::new();
+        for c in text.chars() {
+            if c.is_ascii_alphanumeric() || "._%+-@".contains(c) {
+                token.push(c);
+            } else {
+                push_token(&mut out, &token);
+                token.clear();
+                out.push(c);
+            }
+        }
+        push_token(&mut out, &token);
+        out
+    }
+}
+
+/// 写入一个词，邮箱和电话号码写入占位文本
+fn push_token(out: &mut String, token: &str) {
+    let is_email = token.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty() && domain.contains('.') && !domain.contains('@')
+    });
+    let digits = token.strip_prefix('+').unwrap_or(token);
+    let is_phone = digits.chars().all(|c| c.is_ascii_digit()) && (7..=15).contains(&digits.len());
+    out.push_str(if is_email || is_phone {
+        REDACTED
+    } else {
+        token
+    });
+}
+
+/// 未完成的诊断包（失败或取消时删除）
+struct Partial(PathBuf);
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn partial_path(out_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = out_path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn entry_options() -> FileOptions {
+    FileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(value)?)
+}
+
+/// 读取文件末尾最多 `max_bytes` 字节
+fn read_tail(path: &Path, max_bytes: u64) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("无法读取日志: {}", path.display()))?;
+    let len = file.metadata()?.len();
+    if len > max_bytes {
+        file.seek(SeekFrom::Start(len - max_bytes))?;
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// 日志目录中最近修改的 `count` 个日志文件（不含操作日志）
+fn recent_log_files(dir: &Path, count: usize) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("无法读取日志目录: {}", dir.display())),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if metadata.is_file() && !name.starts_with(ACTION_LOG_FILE_NAME) {
+            files.push((metadata.modified().ok(), entry.path()));
+        }
+    }
+    files.sort_by(|a, b| b.cmp(a));
+    Ok(files
+        .into_iter()
+        .take(count)
+        .map(|(_, path)| path)
+        .collect())
+}
+
+/// 包内文件名
+fn entry_name(prefix: &str, path: &Path) -> String {
+    format!(
+        "{prefix}/{}",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )
+}
+
+/// 诊断包写入器
+struct BundleWriter<'a> {
+    zip: ZipWriter<BufWriter<File>>,
+    redactor: &'a Redactor,
+    summary: DiagnosticsBundleSummary,
+}
+
+impl BundleWriter<'_> {
+    /// 写入文本（遮盖密钥）
+    fn text(&mut self, name: &str, text: &str) -> Result<()> {
+        self.zip.start_file(name, entry_options())?;
+        self.zip.write_all(self.redactor.secrets(text).as_bytes())?;
+        self.summary.entries.push(name.to_string());
+        Ok(())
+    }
+
+    /// 写入一个部分；生成失败时写入错误说明
+    fn section(&mut self, name: &str, content: Result<String>) -> Result<()> {
+        match content {
+            Ok(text) => self.text(name, &text),
+            Err(e) => {
+                self.summary.failed.push(name.to_string());
+                self.text(&format!("{name}.error.txt"), &format!("{e:#}"))
+            }
+        }
+    }
+
+    /// 流式写入文件
+    fn file(&mut self, name: &str, path: &Path) -> Result<()> {
+        self.zip
+            .start_file(name, entry_options().large_file(true))?;
+        let mut file = File::open(path)?;
+        std::io::copy(&mut file, &mut self.zip)?;
+        self.summary.entries.push(name.to_string());
+        Ok(())
+    }
+}
+
+/// 诊断包导出服务
+#[derive(Debug, Clone)]
+pub struct DiagnosticsBundleExporter {
+    config: AppConfig,
+    database: DatabaseManager,
+    log_files: usize,
+}
+
+impl DiagnosticsBundleExporter {
+    /// 创建导出服务（附带最近 [`DIAGNOSTIC_LOG_FILES`] 个日志文件）
+    #[must_use]
+    pub const fn new(config: AppConfig, database: DatabaseManager) -> Self {
+        Self {
+            config,
+            database,
+            log_files: DIAGNOSTIC_LOG_FILES,
+        }
+    }
+
+    /// 设置附带的日志文件数
+    #[must_use]
+    pub const fn with_log_files(mut self, count: usize) -> Self {
+        self.log_files = count;
+        self
+    }
+
+    /// 默认的诊断包路径（数据库所在目录下的 `diagnostics` 目录）
+    #[must_use]
+    pub fn default_path(&self, now: DateTime<Local>) -> PathBuf {
+        self.config
+            .database
+            .path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join("diagnostics")
+            .join(format!(
+                "minicrm-diagnostics-{}.zip",
+                now.format("%Y%m%d-%H%M%S")
+            ))
+    }
+
+    fn health(&self) -> Result<String> {
+        let checker =
+            DatabaseHealthChecker::new(self.database.connection(), self.database.pool().clone());
+        to_json(&json!({
+            "cheap": checker.check_health(),
+            "deep": checker.check_health_deep(),
+        }))
+    }
+
+    fn database_stats(&self) -> Result<String> {
+        let records = self.database.get_database_stats()?;
+        let storage =
+            DatabaseHealthChecker::new(self.database.connection(), self.database.pool().clone())
+                .get_database_stats()?;
+        to_json(&json!({
+            "customer_count": records.customer_count,
+            "task_count": records.task_count,
+            "quote_count": records.quote_count,
+            "file_size_bytes": records.file_size_bytes,
+            "reclaimable_bytes": records.reclaimable_bytes,
+            "storage": storage,
+            "size_history": self.database.size_history(SIZE_HISTORY_DAYS)?,
+        }))
+    }
+
+    fn migrations(&self) -> Result<String> {
+        let status = MigrationManager::new(self.database.connection())
+            .add_migrations(schema::builtin_migrations())
+            .get_migration_status()?;
+        to_json(&json!({
+            "status": status,
+            "reversibility": DatabaseManager::verify_migrations()?,
+        }))
+    }
+
+    fn pool_usage(&self) -> Result<String> {
+        to_json(&json!({
+            "current": self.database.pool_usage_summary(),
+            "history": PoolUsageStore::new(self.database.connection()).history()?,
+            "suggestion": self
+                .database
+                .suggest_pool_size(self.config.database.max_connections_cap)?,
+        }))
+    }
+
+    fn slow_queries(&self, redactor: &Redactor) -> Result<String> {
+        let mut entries = self.database.slow_query_log().entries();
+        for entry in &mut entries {
+            entry.sql = redactor.personal(&entry.sql);
+        }
+        to_json(&entries)
+    }
+
+    /// 一致性检查汇总（只写各项检查的问题数，不写具体记录）
+    async fn consistency(&self) -> Result<String> {
+        let checker = builtin_consistency_checks(self.database.connection())
+            .into_iter()
+            .fold(ConsistencyChecker::new(), ConsistencyChecker::with);
+        let report = checker.run(None, DEFAULT_VIOLATION_LIMIT).await?;
+        let mut text = format!("{}\n", consistency_summary(&report));
+        for result in &report.results {
+            let severity = match result.severity {
+                ConsistencySeverity::Warning => "警告",
+                ConsistencySeverity::Error => "错误",
+            };
+            let status = if result.passed() {
+                "通过".to_string()
+            } else {
+                let more = if result.truncated { "+" } else { "" };
+                format!("{}{} 个{}", result.violations.len(), more, severity)
+            };
+            let _ = writeln!(text, "{}: {}", result.name, status);
+        }
+        Ok(text)
+    }
+
+    /// 最近的日志文件（遮盖邮箱和电话号码）
+    fn write_logs(&self, writer: &mut BundleWriter<'_>, redactor: &Redactor) -> Result<()> {
+        let files = match recent_log_files(&self.config.logging.log_dir(), self.log_files) {
+            Ok(files) => files,
+            Err(e) => return writer.section("logs", Err(e)),
+        };
+        for path in files {
+            let content = read_tail(&path, MAX_LOG_BYTES).map(|text| redactor.personal(&text));
+            writer.section(&entry_name("logs", &path), content)?;
+        }
+        Ok(())
+    }
+
+    /// 操作日志及其上一份滚动文件
+    fn write_action_log(&self, writer: &mut BundleWriter<'_>, redactor: &Redactor) -> Result<()> {
+        let Some(path) = self.config.logging.action_log_path() else {
+            return Ok(());
+        };
+        for path in [partial_path(&path, ".1"), path] {
+            if path.exists() {
+                let content = read_tail(&path, MAX_LOG_BYTES).map(|text| redactor.personal(&text));
+                writer.section(&entry_name("logs", &path), content)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 匿名化的数据库副本
+    fn write_database(&self, writer: &mut BundleWriter<'_>, out_path: &Path) -> Result<()> {
+        let copy = Partial(partial_path(out_path, ".db"));
+        self.database.backup_database(&copy.0)?;
+        anonymize_copy(&copy.0)?;
+        writer.file(DATABASE_ENTRY, &copy.0)?;
+        writer.summary.includes_database = true;
+        Ok(())
+    }
+
+    async fn export(
+        &self,
+        out_path: &Path,
+        include_db_copy: bool,
+        progress: &ExportProgress,
+        cancel: &CancellationToken,
+    ) -> CoreResult<DiagnosticsBundleSummary> {
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建目录: {}", parent.display()))?;
+        }
+        let redactor = Redactor::new(&self.config);
+        let partial = Partial(partial_path(out_path, ".partial"));
+        let file = File::create(&partial.0)
+            .with_context(|| format!("无法创建诊断包: {}", partial.0.display()))?;
+        let mut writer = BundleWriter {
+            zip: ZipWriter::new(BufWriter::new(file)),
+            redactor: &redactor,
+            summary: DiagnosticsBundleSummary {
+                entries: Vec::new(),
+                failed: Vec::new(),
+                includes_database: false,
+            },
+        };
+
+        for section in SECTIONS {
+            cancel.check()?;
+            match section {
+                "about" => {
+                    let about = AboutInfo::collect(&self.database).map(|about| about.to_text());
+                    writer.section("about.txt", about)?;
+                }
+                "health" => writer.section("health.json", self.health())?,
+                "database_stats" => {
+                    writer.section("database/stats.json", self.database_stats())?;
+                }
+                "migrations" => writer.section("database/migrations.json", self.migrations())?,
+                "pool_usage" => writer.section("database/pool_usage.json", self.pool_usage())?,
+                "logs" => self.write_logs(&mut writer, &redactor)?,
+                "action_log" => self.write_action_log(&mut writer, &redactor)?,
+                "slow_queries" => {
+                    writer.section("database/slow_queries.json", self.slow_queries(&redactor))?;
+                }
+                _ => writer.section("consistency.txt", self.consistency().await)?,
+            }
+            progress.add(1);
+        }
+        if include_db_copy {
+            cancel.check()?;
+            self.write_database(&mut writer, out_path)
+                .context("无法生成匿名化的数据库副本")?;
+            progress.add(1);
+        }
+
+        cancel.check()?;
+        let summary = writer.summary;
+        let file = writer
+            .zip
+            .finish()
+            .context("无法写入诊断包")?
+            .into_inner()
+            .map_err(std::io::IntoInnerError::into_error)
+            .context("无法写入诊断包")?;
+        file.sync_all().context("无法写入诊断包")?;
+        drop(file);
+        fs::rename(&partial.0, out_path)
+            .with_context(|| format!("无法保存诊断包: {}", out_path.display()))?;
+        tracing::info!(
+            "诊断包已导出: {}（{} 个文件，数据库副本: {}）",
+            out_path.display(),
+            summary.entries.len(),
+            summary.includes_database
+        );
+        Ok(summary)
+    }
+}
+
+#[async_trait]
+impl DiagnosticsBundleService for DiagnosticsBundleExporter {
+    async fn export_bundle(
+        &self,
+        out_path: &Path,
+        include_db_copy: bool,
+        progress: &ExportProgress,
+        cancel: &CancellationToken,
+    ) -> CoreResult<DiagnosticsBundleSummary> {
+        self.export(out_path, include_db_copy, progress, cancel)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SmtpConfig;
+    use crate::core::CoreError;
+    use tempfile::TempDir;
+    use zip::ZipArchive;
+
+    const SECRET: &str = "SENTINEL-SECRET-9d2f41";
+    const SMTP_PASSWORD: &str = "SENTINEL-SMTP-77ac";
+    const NAME: &str = "SENTINEL-NAME-5310";
+    const PHONE: &str = "13912345678";
+    const EMAIL: &str = "sentinel.user@example.com";
+
+    fn setup() -> Result<(TempDir, AppConfig, DatabaseManager)> {
+        let dir = TempDir::new()?;
+        let mut config = AppConfig::default();
+        config.database.path = dir.path().join("data/minicrm.db");
+        config.logging.file_path = Some(dir.path().join("logs/minicrm.log"));
+        config.security.app_secret = Some(SECRET.to_string());
+        config.digest.smtp = Some(SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port: 465,
+            username: "crm@example.com".to_string(),
+            password: SMTP_PASSWORD.to_string(),
+            from: "crm@example.com".to_string(),
+        });
+        let database = DatabaseManager::new(&config)?;
+        database.connection().execute(
+            "INSERT INTO customers (id, name, company, phone, email, credit_limit, created_at,
+                                    updated_at)
+             VALUES ('7f1c2d9e-4b1a-4c55-9e0b-2a6d3f8c1e01', ?1, ?1, ?2, ?3, 1234567,
+                     '2024-07-01T09:00:00.000000Z', '2024-07-01T09:00:00.000000Z')",
+            [NAME, PHONE, EMAIL],
+        )?;
+
+        let logs = dir.path().join("logs");
+        fs::create_dir_all(&logs)?;
+        fs::write(
+            logs.join("minicrm.log"),
+            format!("INFO 登录 app_secret={SECRET}\nWARN 发送失败 {EMAIL} {PHONE}\n"),
+        )?;
+        fs::write(
+            logs.join(ACTION_LOG_FILE_NAME),
+            format!("customers create_customer phone={PHONE} smtp={SMTP_PASSWORD}\n"),
+        )?;
+        Ok((dir, config, database))
+    }
+
+    /// 解压后的全部内容（按包内路径）
+    fn read_bundle(path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let mut entries = Vec::new();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            entries.push((entry.name().to_string(), bytes));
+        }
+        Ok(entries)
+    }
+
+    fn assert_no_sentinels(entries: &[(String, Vec<u8>)], sentinels: &[&str]) {
+        for (name, bytes) in entries {
+            let text = String::from_utf8_lossy(bytes);
+            for sentinel in sentinels {
+                assert!(!text.contains(sentinel), "{name} 中含有 {sentinel}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_redactor_masks_emails_and_phones() {
+        let mut config = AppConfig::default();
+        config.security.app_secret = Some(SECRET.to_string());
+        let redactor = Redactor::new(&config);
+        assert_eq!(
+            redactor.personal(&format!("发送给 {EMAIL}，电话 {PHONE}，密钥 {SECRET}")),
+            "发送给 [已隐藏]，电话 [已隐藏]，密钥 [已隐藏]"
+        );
+        // 时间、耗时和版本号不受影响
+        let line = "2024-07-01T09:00:00Z 查询耗时 1234567ms v0.1.0";
+        assert_eq!(redactor.personal(line), line);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_contains_no_secrets_or_personal_data() -> Result<()> {
+        let (dir, config, database) = setup()?;
+        let exporter = DiagnosticsBundleExporter::new(config, database);
+        let out_path = dir.path().join("support/bundle.zip");
+        let progress = ExportProgress::new();
+
+        let summary = exporter
+            .export_bundle(&out_path, false, &progress, &CancellationToken::new())
+            .await?;
+        assert_eq!(progress.rows_written(), section_count(false));
+        assert!(!summary.includes_database);
+        let entries = read_bundle(&out_path)?;
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        for expected in [
+            "about.txt",
+            "health.json",
+            "database/stats.json",
+            "database/migrations.json",
+            "database/pool_usage.json",
+            "logs/minicrm.log",
+            "logs/actions.log",
+            "database/slow_queries.json",
+            "consistency.txt",
+        ] {
+            assert!(names.contains(&expected), "缺少 {expected}: {names:?}");
+        }
+        assert!(!names.contains(&DATABASE_ENTRY));
+        assert_no_sentinels(&entries, &[SECRET, SMTP_PASSWORD, NAME, PHONE, EMAIL]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_database_copy_only_when_opted_in_and_anonymized() -> Result<()> {
+        let (dir, config, database) = setup()?;
+        let exporter = DiagnosticsBundleExporter::new(config, database);
+        let out_path = dir.path().join("bundle.zip");
+
+        let summary = exporter
+            .export_bundle(
+                &out_path,
+                true,
+                &ExportProgress::new(),
+                &CancellationToken::new(),
+            )
+            .await?;
+        assert!(summary.includes_database);
+        let entries = read_bundle(&out_path)?;
+        let (_, copy) = entries
+            .iter()
+            .find(|(name, _)| name == DATABASE_ENTRY)
+            .context("应附带数据库副本")?;
+        assert!(copy.starts_with(b"SQLite format 3"));
+        assert_no_sentinels(&entries, &[SECRET, SMTP_PASSWORD, NAME, PHONE, EMAIL]);
+        // 临时副本已删除
+        assert!(!partial_path(&out_path, ".db").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_export_leaves_no_file() -> Result<()> {
+        let (dir, config, database) = setup()?;
+        let exporter = DiagnosticsBundleExporter::new(config, database);
+        let out_path = dir.path().join("bundle.zip");
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = exporter
+            .export_bundle(&out_path, true, &ExportProgress::new(), &cancel)
+            .await;
+        assert!(matches!(result, Err(CoreError::Cancelled)));
+        assert!(!out_path.exists());
+        assert!(!partial_path(&out_path, ".partial").exists());
+        Ok(())
+    }
+}
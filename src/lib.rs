@@ -8,10 +8,22 @@
 #![warn(missing_debug_implementations)]
 #![warn(rust_2018_idioms)]
 
+pub mod action_log;
+#[cfg(feature = "api")]
+pub mod api;
 pub mod app;
 pub mod config;
+pub mod context;
+pub mod dashboard;
+pub mod data_dir;
 pub mod database;
+pub mod diagnostics;
 pub mod error;
+pub mod preflight;
+pub mod services;
+pub mod settings_transfer;
+pub mod startup;
+pub mod ui_state;
 
 // 重新导出核心模块
 pub use minicrm_application as application;
@@ -22,4 +34,5 @@ pub use minicrm_presentation as presentation;
 
 // 重新导出常用类型
 pub use crate::config::AppConfig;
+pub use crate::context::AppContext;
 pub use crate::error::{Error, Result};
@@ -4,40 +4,150 @@
 //! 系统使用Rust + Slint技术栈，提供现代化的用户界面和高性能的数据处理能力。
 
 use anyhow::Result;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
 
+use minicrm::action_log::{ActionLog, ActionLogLayer};
 use minicrm::app::App;
+use minicrm::application::{consistency_summary, ConsistencyChecker, DEFAULT_VIOLATION_LIMIT};
+use minicrm::data_dir::{ConsistencyOptions, DataMigration, DataRoot, LaunchOptions};
+use minicrm::database::DatabaseManager;
+use minicrm::infrastructure::repository::builtin_consistency_checks;
+use minicrm::presentation::{migration_report_summary, ConsistencyCheckRow, MigrationCheckRow};
+use minicrm::startup::{StartupPhase, StartupProfiler};
+use minicrm::AppConfig;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // 先确定数据目录（可能迁移旧数据），再打开其中的日志文件
+    let options = LaunchOptions::from_env();
+    if options.verify_migrations {
+        return verify_migrations();
+    }
+    if let Some(consistency) = &options.check_consistency {
+        return check_consistency(&options, consistency).await;
+    }
+    let (data_root, migration) = App::prepare_data_root(&options)?;
+    let profiler = StartupProfiler::new();
+    let config = profiler.time(StartupPhase::Config, || AppConfig::load_in(&data_root))?;
+
+    // 操作日志（可在配置中关闭）
+    let action_log = config.logging.action_log_path().map(|path| {
+        ActionLog::open(&path, config.logging.action_log_max_kb * 1024).map_err(|e| (path, e))
+    });
+
     // 初始化日志系统
-    tracing_subscriber::fmt()
-        .with_env_filter("minicrm=debug,info")
-        .with_target(false)
-        .with_thread_ids(true)
-        .with_file(true)
-        .with_line_number(true)
+    tracing_subscriber::registry()
+        .with(EnvFilter::new("minicrm=debug,info"))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .with_thread_ids(true)
+                .with_file(true)
+                .with_line_number(true),
+        )
+        .with(
+            action_log
+                .as_ref()
+                .and_then(|log| log.as_ref().ok())
+                .map(|log| ActionLogLayer::new(log.clone())),
+        )
         .init();
 
-    info!("启动 MiniCRM 板材行业客户管理系统");
-
-    // 创建并运行应用程序
-    match App::new() {
-        Ok(app) => {
-            info!("应用程序初始化成功");
+    match &action_log {
+        Some(Ok(log)) => log.install_panic_hook(config.logging.log_dir()),
+        Some(Err((path, e))) => warn!("无法打开操作日志 {}: {}", path.display(), e),
+        None => {}
+    }
 
-            // 运行应用程序主循环
-            if let Err(e) = app.run() {
-                error!("应用程序运行时错误: {}", e);
-                std::process::exit(1);
+    info!("启动 MiniCRM 板材行业客户管理系统");
+    info!("数据目录: {}", data_root.path().display());
+    match migration {
+        Some(DataMigration::Migrated { from, report }) => {
+            info!(
+                "已从 {} 迁移 {} 个文件（{} 字节）",
+                from.display(),
+                report.files,
+                report.bytes
+            );
+            for path in report.leftovers {
+                warn!("已迁移但无法删除原目录，请手动删除: {}", path.display());
             }
         }
-        Err(e) => {
-            error!("应用程序初始化失败: {}", e);
-            std::process::exit(1);
+        Some(DataMigration::Declined { from }) => {
+            info!("未迁移旧数据，本次使用 {}", from.display());
+        }
+        Some(DataMigration::Failed { from, error }) => {
+            error!("旧数据迁移失败，原文件未改动，本次使用 {}: {}", from.display(), error);
+        }
+        None => {}
+    }
+
+    // 创建应用程序，启动自检未通过时不显示主窗口
+    let app = App::with_config(config)
+        .with_config_file(data_root.config_file())
+        .with_profiler(profiler.clone());
+    let preflight = profiler.time(StartupPhase::Preflight, || app.preflight());
+    if !preflight.passed() {
+        for item in preflight.failures() {
+            error!("启动自检未通过 - {}: {}", item.name, item.message);
         }
+        App::show_preflight_failures(&preflight);
+        std::process::exit(1);
+    }
+    info!("应用程序初始化成功");
+
+    // 运行应用程序主循环
+    if let Err(e) = app.run() {
+        error!("应用程序运行时错误: {}", e);
+        std::process::exit(1);
     }
 
     info!("MiniCRM 应用程序正常退出");
     Ok(())
 }
+
+/// `minicrm migrate --verify`：在临时数据库上检查内置迁移能否回滚，有误时以状态码1退出
+fn verify_migrations() -> Result<()> {
+    let report = DatabaseManager::verify_migrations()?;
+    for check in &report.checks {
+        println!("{}", MigrationCheckRow::from_check(check).to_text());
+    }
+    println!("{}", migration_report_summary(&report));
+    if !report.passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `minicrm check-consistency`：检查数据库中的业务数据一致性，发现错误时以状态码1退出
+///
+/// 不迁移旧数据目录；`--json` 时输出完整报告，供脚本处理。
+async fn check_consistency(options: &LaunchOptions, consistency: &ConsistencyOptions) -> Result<()> {
+    let config = AppConfig::load_in(&DataRoot::select(options)?)?;
+    let database = DatabaseManager::new(&config)?;
+    let checker = builtin_consistency_checks(database.connection())
+        .into_iter()
+        .fold(ConsistencyChecker::new(), ConsistencyChecker::with);
+    let report = checker
+        .run(
+            consistency.checks.as_deref(),
+            consistency.limit.unwrap_or(DEFAULT_VIOLATION_LIMIT),
+        )
+        .await?;
+
+    if consistency.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for result in &report.results {
+            println!("{}", ConsistencyCheckRow::from_result(result).to_text());
+        }
+        println!("{}", consistency_summary(&report));
+    }
+    if report.has_errors() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
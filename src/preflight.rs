@@ -0,0 +1,476 @@
+//! 启动自检
+//!
+//! 在显示任何窗口之前检查配置取值、数据/日志/备份/附件目录的写权限、数据库能否打开及其
+//! 版本、磁盘可用空间，以及数据库文件丢失后遗留的 `-wal`/`-shm` 文件。任一项失败时不再
+//! 继续启动，而是把各项的处理建议展示给用户，避免只留下一段错误堆栈。
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::config::AppConfig;
+use crate::core::StalenessKind;
+use crate::infrastructure::database::schema;
+
+/// 目录写权限检查使用的探测文件名
+const PROBE_FILE_NAME: &str = ".minicrm-write-probe";
+
+/// 允许的日志级别
+const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+/// 单项检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightItem {
+    /// 检查项名称
+    pub name: String,
+    /// 是否通过
+    pub passed: bool,
+    /// 结果说明；失败时为给用户的处理建议
+    pub message: String,
+}
+
+impl PreflightItem {
+    fn pass<N: Into<String>, M: Into<String>>(name: N, message: M) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            message: message.into(),
+        }
+    }
+
+    fn fail<N: Into<String>, M: Into<String>>(name: N, message: M) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            message: message.into(),
+        }
+    }
+}
+
+/// 启动自检结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreflightReport {
+    /// 各项检查结果（按执行顺序）
+    pub items: Vec<PreflightItem>,
+}
+
+impl PreflightReport {
+    /// 是否全部通过
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.items.iter().all(|item| item.passed)
+    }
+
+    /// 未通过的检查项
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightItem> {
+        self.items.iter().filter(|item| !item.passed)
+    }
+
+    /// 无图形环境时输出到标准错误的文本
+    #[must_use]
+    pub fn failure_text(&self) -> String {
+        let mut text = String::from("MiniCRM 启动检查未通过，处理以下问题后请重新启动：\n");
+        for item in self.failures() {
+            let _ = writeln!(text, "  - {}：{}", item.name, item.message);
+        }
+        text
+    }
+}
+
+/// 执行全部启动自检
+pub fn run(config: &AppConfig) -> PreflightReport {
+    let database = &config.database.path;
+    let data_dir = database
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+
+    let mut items = vec![check_config(config)];
+    for (label, dir) in [
+        ("数据", data_dir.clone()),
+        ("日志", config.logging.log_dir()),
+        ("备份", config.database.backups_dir.clone()),
+        ("附件", config.database.attachments_dir.clone()),
+    ] {
+        items.push(check_directory(label, &dir));
+    }
+    items.push(check_orphan_wal(database));
+    items.push(check_database(database));
+    items.push(check_disk_space(
+        &data_dir,
+        config.database.min_free_space_mb.saturating_mul(1024 * 1024),
+    ));
+    PreflightReport { items }
+}
+
+/// 配置取值检查
+fn check_config(config: &AppConfig) -> PreflightItem {
+    let mut problems = Vec::new();
+    let database = &config.database;
+    if database.max_connections == 0 {
+        problems.push("数据库最大连接数（database.max_connections）必须大于0".to_string());
+    } else if database.max_connections > database.max_connections_cap {
+        problems.push(format!(
+            "数据库最大连接数（database.max_connections）不能超过上限 {}",
+            database.max_connections_cap
+        ));
+    } else if database.warm_up_connections > database.max_connections {
+        problems.push(format!(
+            "预热连接数（database.warm_up_connections）不能超过最大连接数 {}",
+            database.max_connections
+        ));
+    }
+    if database.connection_timeout == 0 {
+        problems.push("数据库连接超时（database.connection_timeout）必须大于0秒".to_string());
+    }
+    if !(0.0..=1.0).contains(&database.reclaim_prompt_ratio) {
+        problems.push("整理提示比例（database.reclaim_prompt_ratio）应在0到1之间".to_string());
+    }
+    if database.size_growth_mb_per_day <= 0.0 || database.size_growth_percent_per_day <= 0.0 {
+        problems.push(
+            "数据库增长警告阈值（database.size_growth_mb_per_day / size_growth_percent_per_day）必须大于0"
+                .to_string(),
+        );
+    }
+    if !LOG_LEVELS.contains(&config.logging.level.to_lowercase().as_str()) {
+        problems.push(format!(
+            "日志级别（logging.level）“{}”无效，可选 {}",
+            config.logging.level,
+            LOG_LEVELS.join("/")
+        ));
+    }
+    if config.ui.window_width == 0 || config.ui.window_height == 0 {
+        problems.push("窗口宽度和高度（ui.window_width/window_height）必须大于0".to_string());
+    }
+    if let Err(e) = config.calendar.business_calendar() {
+        problems.push(format!("业务时区（calendar.business_timezone）无效：{e}"));
+    }
+    if config.api.enabled && config.api.port == 0 {
+        problems.push("启用API时必须指定端口（api.port）".to_string());
+    }
+    if !(1..=31).contains(&config.archive.day_of_month) {
+        problems.push("归档运行日期（archive.day_of_month）应在1到31之间".to_string());
+    }
+    if config.quote.margin_block_percent > config.quote.margin_warning_percent {
+        problems.push(
+            "毛利禁止发送阈值（quote.margin_block_percent）不能高于预警阈值\
+             （quote.margin_warning_percent）"
+                .to_string(),
+        );
+    }
+    let staleness = config.calendar.staleness;
+    for (kind, key) in [
+        (StalenessKind::SentQuote, "sent_quote"),
+        (StalenessKind::OverdueTask, "overdue_task"),
+        (StalenessKind::TicketNoUpdate, "ticket_no_update"),
+    ] {
+        let rule = staleness.rule(kind);
+        if rule.aging_days == 0 || rule.aging_days > rule.stale_days {
+            problems.push(format!(
+                "久未跟进阈值（calendar.staleness.{key}）的 aging_days 应大于0且不超过 stale_days"
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        PreflightItem::pass("配置", "配置取值有效")
+    } else {
+        PreflightItem::fail(
+            "配置",
+            format!("{}。请修改配置文件后重新启动", problems.join("；")),
+        )
+    }
+}
+
+/// 目录写权限检查：创建目录并写入、删除探测文件
+fn check_directory(label: &str, dir: &Path) -> PreflightItem {
+    let name = format!("{label}目录");
+    match probe_directory(dir) {
+        Ok(()) => PreflightItem::pass(name, format!("{} 可写", dir.display())),
+        Err(e) => PreflightItem::fail(
+            name,
+            format!(
+                "无法在 {} 中创建或写入文件（{}）。请检查该目录的权限，\
+                 或在配置中改用本机可写的目录",
+                dir.display(),
+                e
+            ),
+        ),
+    }
+}
+
+fn probe_directory(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(PROBE_FILE_NAME);
+    fs::write(&probe, b"minicrm")?;
+    fs::remove_file(&probe)
+}
+
+/// 数据库文件检查：可写、能打开，且版本不高于本程序支持的版本
+fn check_database(path: &Path) -> PreflightItem {
+    const NAME: &str = "数据库";
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return PreflightItem::pass(NAME, "数据库尚未创建，将在启动时初始化");
+        }
+        Err(e) => {
+            return PreflightItem::fail(
+                NAME,
+                format!("无法读取数据库文件 {}（{}）", path.display(), e),
+            );
+        }
+    };
+    if metadata.permissions().readonly() {
+        return PreflightItem::fail(
+            NAME,
+            format!(
+                "数据库文件 {} 为只读。若文件位于网络共享或光盘上，请复制到本机磁盘，\
+                 或取消文件的只读属性",
+                path.display()
+            ),
+        );
+    }
+
+    let latest = schema::latest_version();
+    match schema_version(path) {
+        Err(e) => PreflightItem::fail(
+            NAME,
+            format!(
+                "无法打开数据库文件 {}（{}）。文件可能已损坏或被其他程序占用，\
+                 可从备份恢复",
+                path.display(),
+                e
+            ),
+        ),
+        Ok(Some(version)) if version > latest => PreflightItem::fail(
+            NAME,
+            format!(
+                "数据库版本 {version} 高于本程序支持的版本 {latest}，请升级 MiniCRM 后再打开"
+            ),
+        ),
+        Ok(version) => PreflightItem::pass(
+            NAME,
+            format!("数据库版本 {}（最新 {}）", version.unwrap_or(0), latest),
+        ),
+    }
+}
+
+/// 读取已应用的最高迁移版本，尚未执行过迁移时为空
+fn schema_version(path: &Path) -> rusqlite::Result<Option<u32>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let migrated: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master
+                       WHERE type = 'table' AND name = 'schema_migrations')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !migrated {
+        return Ok(None);
+    }
+    conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+        row.get(0)
+    })
+}
+
+/// 数据库文件不存在但遗留了 `-wal`/`-shm` 文件
+fn check_orphan_wal(path: &Path) -> PreflightItem {
+    const NAME: &str = "数据库日志文件";
+    let leftovers: Vec<PathBuf> = ["-wal", "-shm"]
+        .into_iter()
+        .map(|suffix| sidecar(path, suffix))
+        .filter(|file| file.exists())
+        .collect();
+    if path.exists() || leftovers.is_empty() {
+        return PreflightItem::pass(NAME, "没有遗留的日志文件");
+    }
+    let names: Vec<String> = leftovers.iter().map(|f| f.display().to_string()).collect();
+    PreflightItem::fail(
+        NAME,
+        format!(
+            "发现遗留的 {}，但数据库文件 {} 不存在，数据库文件可能被移动或删除。\
+             请先把数据库文件放回原处；确认不再需要后再删除这些文件",
+            names.join("、"),
+            path.display()
+        ),
+    )
+}
+
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 数据目录所在磁盘的可用空间检查
+fn check_disk_space(dir: &Path, min_free_bytes: u64) -> PreflightItem {
+    const NAME: &str = "磁盘空间";
+    const MB: u64 = 1024 * 1024;
+    // 目录创建失败时按最近的已存在上级目录查询
+    let existing = dir
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or_else(|| Path::new("."));
+    match fs2::available_space(existing) {
+        Ok(free) if free >= min_free_bytes => {
+            PreflightItem::pass(NAME, format!("可用空间 {} MB", free / MB))
+        }
+        Ok(free) => PreflightItem::fail(
+            NAME,
+            format!(
+                "{} 所在磁盘仅剩 {} MB 可用空间，低于要求的 {} MB。\
+                 请清理磁盘或把数据目录移到空间充足的磁盘",
+                dir.display(),
+                free / MB,
+                min_free_bytes / MB
+            ),
+        ),
+        Err(e) => PreflightItem::fail(
+            NAME,
+            format!("无法读取 {} 所在磁盘的可用空间（{}）", dir.display(), e),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    fn config_in(dir: &Path) -> AppConfig {
+        let mut config = AppConfig::default();
+        config.database.path = dir.join("data/minicrm.db");
+        config.database.attachments_dir = dir.join("data/attachments");
+        config.database.backups_dir = dir.join("data/backups");
+        config.database.min_free_space_mb = 0;
+        config.logging.file_path = Some(dir.join("logs/minicrm.log"));
+        config
+    }
+
+    fn create_database(path: &Path, version: u32) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch("CREATE TABLE schema_migrations (version INTEGER PRIMARY KEY)")?;
+        conn.execute("INSERT INTO schema_migrations (version) VALUES (?1)", [version])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_clean_environment_passes() -> Result<()> {
+        let dir = TempDir::new()?;
+        let config = config_in(dir.path());
+        create_database(&config.database.path, schema::latest_version())?;
+
+        let report = run(&config);
+        assert!(report.passed(), "{}", report.failure_text());
+        assert_eq!(report.items.len(), 8);
+        assert!(config.database.backups_dir.is_dir());
+        assert!(!config.database.backups_dir.join(PROBE_FILE_NAME).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config_values_fail() {
+        let mut config = AppConfig::default();
+        config.database.max_connections = 0;
+        config.logging.level = "verbose".to_string();
+        config.calendar.business_timezone = "Mars/Olympus".to_string();
+
+        let item = check_config(&config);
+        assert!(!item.passed);
+        assert!(item.message.contains("database.max_connections"));
+        assert!(item.message.contains("verbose"));
+        assert!(item.message.contains("calendar.business_timezone"));
+        assert!(check_config(&AppConfig::default()).passed);
+    }
+
+    #[test]
+    fn test_unwritable_directory_fails() -> Result<()> {
+        let dir = TempDir::new()?;
+        // 同名文件占位，目录无法创建
+        let blocker = dir.path().join("backups");
+        fs::write(&blocker, b"")?;
+
+        let item = check_directory("备份", &blocker.join("daily"));
+        assert!(!item.passed);
+        assert_eq!(item.name, "备份目录");
+        assert!(item.message.contains("权限"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_database_fails() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("minicrm.db");
+        create_database(&path, 1)?;
+        let mut permissions = fs::metadata(&path)?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions.clone())?;
+
+        let item = check_database(&path);
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(&path, permissions)?;
+        assert!(!item.passed);
+        assert!(item.message.contains("只读"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unreadable_database_fails() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("minicrm.db");
+        fs::write(&path, b"not a database, just some text padding the header out")?;
+
+        let item = check_database(&path);
+        assert!(!item.passed);
+        assert!(item.message.contains("无法打开数据库文件"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_schema_requires_upgrade() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("minicrm.db");
+        create_database(&path, schema::latest_version() + 1)?;
+
+        let item = check_database(&path);
+        assert!(!item.passed);
+        assert!(item.message.contains("请升级 MiniCRM"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_low_disk_space_fails() -> Result<()> {
+        let dir = TempDir::new()?;
+        let item = check_disk_space(dir.path(), u64::MAX);
+        assert!(!item.passed);
+        assert!(item.message.contains("可用空间"));
+        assert!(check_disk_space(dir.path(), 0).passed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_orphan_wal_without_database_fails() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("minicrm.db");
+        assert!(check_orphan_wal(&path).passed);
+
+        fs::write(sidecar(&path, "-wal"), b"")?;
+        fs::write(sidecar(&path, "-shm"), b"")?;
+        let item = check_orphan_wal(&path);
+        assert!(!item.passed);
+        assert!(item.message.contains("minicrm.db-wal"));
+
+        create_database(&path, 1)?;
+        assert!(check_orphan_wal(&path).passed);
+        Ok(())
+    }
+}
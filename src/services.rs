@@ -0,0 +1,556 @@
+//! 应用服务模块
+//!
+//! 以 `SQLite` 组装 [`ServiceSet`]，供界面、REST API和端到端测试的上下文共用。
+//! 客户和任务的读写委托给基础设施层的仓储；报价和月度统计还没有正式实现，这里以
+//! 最小的SQL补齐。删除客户、报价修订等尚未实现的操作返回业务错误。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+use uuid::Uuid;
+
+use crate::application::{MarginThresholds, ServiceSet};
+use crate::config::AppConfig;
+use crate::core::{
+    BusinessCalendar, CoreError, CoreResult, Customer, CustomerLevel, CustomerService,
+    CustomerStatistics, DeletionImpact, DomainEvent, EntityKind, EventEnvelope, HeaderAliases,
+    Money, MonthlyStatistics, PagedResult, QueryFilter, Quote, QuoteDiff, QuoteRevision,
+    QuoteService, QuoteStatistics, QuoteStatus, ReportPeriod, Repository, StatisticsService, Task,
+    TaskService, TaskStatistics, TaskStatus,
+};
+use crate::infrastructure::database::db_uuid::get_uuid;
+use crate::infrastructure::database::time::{get_time, time_key};
+use crate::infrastructure::database::{DatabaseConnection, DbUuid};
+use crate::infrastructure::repository::{
+    builtin_consistency_checks, customer_summary, outbox, CreditStore, CustomerListStore,
+    GenericRepository, GlobalSearchStore, IdempotencyStore, LeadStore, OrderStore, ProductStore,
+    PurchaseOrderStore, QuoteItemStore, QuoteRevisionStore, QuoteTemplateStore,
+    SqliteCustomerRepository, TimelineStore,
+};
+use crate::infrastructure::repository::support::to_core;
+
+/// 任务到期提醒的天数（到期时间在此范围内计为即将到期）
+const DUE_SOON_DAYS: i64 = 3;
+
+const QUOTE_COLUMNS: &str = "id, customer_id, title, description, total_amount, currency, \
+     status, valid_until, created_at, updated_at, created_by, updated_by";
+
+fn unsupported<T>() -> CoreResult<T> {
+    Err(CoreError::business("暂不支持该操作"))
+}
+
+fn count(row: &Row<'_>, index: usize) -> rusqlite::Result<u64> {
+    Ok(u64::try_from(row.get::<_, i64>(index)?).unwrap_or(0))
+}
+
+/// 报价状态的存储名称（与表的默认值 `draft` 一致）
+fn quote_status_key(status: QuoteStatus) -> String {
+    format!("{status:?}").to_lowercase()
+}
+
+fn parse_quote_status(value: &str) -> QuoteStatus {
+    [
+        QuoteStatus::Draft,
+        QuoteStatus::Sent,
+        QuoteStatus::Accepted,
+        QuoteStatus::Rejected,
+        QuoteStatus::Expired,
+    ]
+    .into_iter()
+    .find(|status| quote_status_key(*status).eq_ignore_ascii_case(value))
+    .unwrap_or(QuoteStatus::Draft)
+}
+
+fn row_to_quote(row: &Row<'_>) -> rusqlite::Result<Quote> {
+    let currency: String = row.get(5)?;
+    let status: String = row.get(6)?;
+    Ok(Quote {
+        id: get_uuid(row, 0)?,
+        customer_id: get_uuid(row, 1)?,
+        quote_number: row.get(2)?,
+        remarks: row.get(3)?,
+        total_amount: Money::from_yuan(row.get(4)?),
+        currency: currency.parse().unwrap_or_default(),
+        status: parse_quote_status(&status),
+        items: Vec::new(),
+        valid_until: get_time(row, 7)?,
+        created_at: get_time(row, 8)?,
+        updated_at: get_time(row, 9)?,
+        created_by: row.get(10)?,
+        updated_by: row.get(11)?,
+    })
+}
+
+/// 客户、任务、报价和月度统计的SQLite实现
+///
+/// 客户和任务委托给仓储，写入时刷新客户汇总并记录事件。报价明细与报价在同一事务中
+/// 写入 `quote_items`，发出的报价另记修订快照。
+pub struct SqliteCoreServices {
+    connection: DatabaseConnection,
+    customers: SqliteCustomerRepository,
+    tasks: GenericRepository<Task>,
+    revisions: QuoteRevisionStore,
+    calendar: BusinessCalendar,
+}
+
+impl std::fmt::Debug for SqliteCoreServices {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteCoreServices").finish_non_exhaustive()
+    }
+}
+
+impl SqliteCoreServices {
+    /// 创建服务
+    #[must_use]
+    pub fn new(connection: DatabaseConnection) -> Self {
+        Self {
+            customers: SqliteCustomerRepository::new(connection.clone()),
+            tasks: GenericRepository::new(connection.clone()),
+            revisions: QuoteRevisionStore::new(connection.clone()),
+            connection,
+            calendar: BusinessCalendar::default(),
+        }
+    }
+
+    /// 设置业务日历，"本月"按其本地日期确定
+    #[must_use]
+    pub const fn with_calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    /// 在同一事务中刷新客户汇总并记录事件
+    fn publish(
+        tx: &rusqlite::Transaction<'_>,
+        event: DomainEvent,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        customer_summary::apply_event(tx, &event, now)?;
+        outbox::record(tx, &EventEnvelope::at(event, now))
+    }
+
+    fn month_start(&self) -> String {
+        let period = self.calendar.period_containing(Utc::now());
+        time_key(self.calendar.period_bounds(period).0)
+    }
+
+    /// 按状态、优先级等列分组计数
+    fn group_count(&self, sql: &str) -> CoreResult<HashMap<String, u64>> {
+        Ok(self
+            .connection
+            .query_map(sql, [], |row| {
+                Ok((row.get::<_, String>(0)?, count(row, 1)?))
+            })?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CustomerService for SqliteCoreServices {
+    async fn create_customer(&self, customer: Customer) -> CoreResult<Customer> {
+        self.customers.save(&customer).await
+    }
+
+    async fn update_customer(&self, customer: Customer) -> CoreResult<Customer> {
+        self.customers.update(&customer).await
+    }
+
+    async fn get_customer_by_id(&self, id: Uuid) -> CoreResult<Option<Customer>> {
+        self.customers.find_by_id(id).await
+    }
+
+    async fn delete_customer(&self, _id: Uuid) -> CoreResult<bool> {
+        unsupported()
+    }
+
+    async fn get_deletion_impact(&self, _id: Uuid) -> CoreResult<DeletionImpact> {
+        unsupported()
+    }
+
+    async fn search_customers(&self, filter: &QueryFilter) -> CoreResult<PagedResult<Customer>> {
+        self.customers.find_with_filter(filter).await
+    }
+
+    async fn update_customer_level(&self, id: Uuid, level: CustomerLevel) -> CoreResult<Customer> {
+        let customer = self
+            .customers
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("客户 {id}")))?;
+        self.customers
+            .update(&Customer {
+                level,
+                updated_at: Utc::now(),
+                ..customer
+            })
+            .await
+    }
+
+    async fn get_customer_statistics(&self) -> CoreResult<CustomerStatistics> {
+        let (total_customers, new_customers_this_month) = self.connection.query_row(
+            "SELECT COUNT(*),
+                    COUNT(CASE WHEN julianday(created_at) >= julianday(?1) THEN 1 END)
+             FROM customers WHERE deleted_at IS NULL",
+            [self.month_start()],
+            |row| Ok((count(row, 0)?, count(row, 1)?)),
+        )?;
+        Ok(CustomerStatistics {
+            total_customers,
+            customers_by_level: self.group_count(
+                "SELECT level, COUNT(*) FROM customers WHERE deleted_at IS NULL GROUP BY level",
+            )?,
+            new_customers_this_month,
+        })
+    }
+}
+
+#[async_trait]
+impl TaskService for SqliteCoreServices {
+    async fn create_task(&self, task: Task) -> CoreResult<Task> {
+        self.tasks.save(&task).await
+    }
+
+    async fn update_task(&self, task: Task) -> CoreResult<Task> {
+        self.tasks.update(&task).await
+    }
+
+    async fn get_task_by_id(&self, id: Uuid) -> CoreResult<Option<Task>> {
+        self.tasks.find_by_id(id).await
+    }
+
+    async fn delete_task(&self, id: Uuid) -> CoreResult<bool> {
+        self.tasks.delete_by_id(id).await
+    }
+
+    async fn search_tasks(&self, filter: &QueryFilter) -> CoreResult<PagedResult<Task>> {
+        self.tasks.find_with_filter(filter).await
+    }
+
+    async fn update_task_status(&self, id: Uuid, status: TaskStatus) -> CoreResult<Task> {
+        let task = self
+            .tasks
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("任务 {id}")))?;
+        self.tasks
+            .update(&Task {
+                status,
+                updated_at: Utc::now(),
+                ..task
+            })
+            .await
+    }
+
+    async fn get_due_tasks(&self, _days: u32) -> CoreResult<Vec<Task>> {
+        unsupported()
+    }
+
+    async fn get_task_statistics(&self) -> CoreResult<TaskStatistics> {
+        let now = Utc::now();
+        let (total_tasks, due_soon_tasks, overdue_tasks) = self.connection.query_row(
+            "SELECT COUNT(*),
+                    COUNT(CASE WHEN open AND julianday(due_date) >= julianday(?1)
+                                    AND julianday(due_date) < julianday(?2) THEN 1 END),
+                    COUNT(CASE WHEN open AND julianday(due_date) < julianday(?1) THEN 1 END)
+             FROM (SELECT due_date,
+                          lower(status) NOT IN ('completed', 'cancelled') AS open
+                   FROM tasks WHERE deleted_at IS NULL)",
+            params![time_key(now), time_key(now + Duration::days(DUE_SOON_DAYS))],
+            |row| Ok((count(row, 0)?, count(row, 1)?, count(row, 2)?)),
+        )?;
+        Ok(TaskStatistics {
+            total_tasks,
+            tasks_by_status: self.group_count(
+                "SELECT status, COUNT(*) FROM tasks WHERE deleted_at IS NULL GROUP BY status",
+            )?,
+            tasks_by_priority: self.group_count(
+                "SELECT priority, COUNT(*) FROM tasks WHERE deleted_at IS NULL GROUP BY priority",
+            )?,
+            due_soon_tasks,
+            overdue_tasks,
+        })
+    }
+}
+
+#[async_trait]
+impl QuoteService for SqliteCoreServices {
+    /// 保存时分配报价编号（`Q年月-序号`），总金额须与明细合计一致
+    async fn create_quote(&self, quote: Quote) -> CoreResult<Quote> {
+        quote.validate_totals()?;
+        let mut quote = quote;
+        for item in &mut quote.items {
+            item.quote_id = quote.id;
+        }
+        let now = Utc::now();
+        let quote = self
+            .connection
+            .with_transaction(|tx| {
+                if quote.quote_number.is_empty() {
+                    let existing: i64 =
+                        tx.query_row("SELECT COUNT(*) FROM quotes", [], |row| row.get(0))?;
+                    quote.quote_number =
+                        format!("Q{}-{:04}", quote.created_at.format("%Y%m"), existing + 1);
+                }
+                tx.execute(
+                    &format!(
+                        "INSERT INTO quotes ({QUOTE_COLUMNS}) \
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+                    ),
+                    params![
+                        DbUuid(quote.id),
+                        DbUuid(quote.customer_id),
+                        quote.quote_number,
+                        quote.remarks,
+                        quote.total_amount.as_yuan(),
+                        quote.currency.as_str(),
+                        quote_status_key(quote.status),
+                        time_key(quote.valid_until),
+                        time_key(quote.created_at),
+                        time_key(quote.updated_at),
+                        quote.created_by,
+                        quote.updated_by,
+                    ],
+                )
+                .context("无法写入报价")?;
+                QuoteItemStore::write_items(tx, quote.id, &quote.items)
+                    .context("无法写入报价明细")?;
+                let event = DomainEvent::EntityCreated {
+                    entity: EntityKind::Quote,
+                    id: quote.id,
+                };
+                Self::publish(tx, event, now)?;
+                Ok(quote)
+            })
+            .map_err(to_core)?;
+        if quote.status != QuoteStatus::Draft {
+            self.revisions
+                .record(&quote, quote.created_by.as_deref())
+                .map_err(to_core)?;
+        }
+        Ok(quote)
+    }
+
+    async fn update_quote(&self, _quote: Quote) -> CoreResult<Quote> {
+        unsupported()
+    }
+
+    async fn get_quote_by_id(&self, id: Uuid) -> CoreResult<Option<Quote>> {
+        let conn = self.connection.get_connection().map_err(to_core)?;
+        let row = conn
+            .query_row(
+                &format!(
+                    "SELECT {QUOTE_COLUMNS} FROM quotes WHERE id = ?1 AND deleted_at IS NULL"
+                ),
+                [DbUuid(id)],
+                row_to_quote,
+            )
+            .optional()
+            .map_err(|e| CoreError::Other(e.to_string()))?;
+        let Some(quote) = row else {
+            return Ok(None);
+        };
+        let items = QuoteItemStore::load_items(&conn, id).map_err(to_core)?;
+        Ok(Some(Quote { items, ..quote }))
+    }
+
+    async fn delete_quote(&self, _id: Uuid) -> CoreResult<bool> {
+        unsupported()
+    }
+
+    async fn search_quotes(&self, _filter: &QueryFilter) -> CoreResult<PagedResult<Quote>> {
+        unsupported()
+    }
+
+    async fn update_quote_status(&self, id: Uuid, status: QuoteStatus) -> CoreResult<Quote> {
+        let quote = self
+            .get_quote_by_id(id)
+            .await?
+            .ok_or_else(|| CoreError::not_found(format!("报价 {id}")))?;
+        let now = Utc::now();
+        let updated = Quote {
+            status,
+            updated_at: now,
+            ..quote.clone()
+        };
+        self.connection
+            .with_transaction(|tx| {
+                tx.execute(
+                    "UPDATE quotes SET status = ?2, updated_at = ?3 WHERE id = ?1",
+                    params![DbUuid(id), quote_status_key(status), time_key(now)],
+                )
+                .context("无法更新报价状态")?;
+                let event = DomainEvent::QuoteStatusChanged {
+                    quote_id: id,
+                    customer_id: quote.customer_id,
+                    from: quote.status,
+                    to: status,
+                };
+                Self::publish(tx, event, now)
+            })
+            .map_err(to_core)?;
+        self.revisions
+            .record(&updated, updated.updated_by.as_deref())
+            .map_err(to_core)?;
+        Ok(updated)
+    }
+
+    async fn get_expiring_quotes(&self, _days: u32) -> CoreResult<Vec<Quote>> {
+        unsupported()
+    }
+
+    async fn get_quote_statistics(&self) -> CoreResult<QuoteStatistics> {
+        let (total_quotes, total_amount_this_month, issued, accepted) = self.connection.query_row(
+            "SELECT COUNT(*),
+                        CAST(ROUND(COALESCE(SUM(CASE WHEN julianday(created_at) >= julianday(?1)
+                                                     THEN total_amount END), 0) * 100) AS INTEGER),
+                        COUNT(CASE WHEN status <> 'draft' THEN 1 END),
+                        COUNT(CASE WHEN status = 'accepted' THEN 1 END)
+                 FROM quotes WHERE deleted_at IS NULL",
+            [self.month_start()],
+            |row| Ok((count(row, 0)?, row.get(1)?, count(row, 2)?, count(row, 3)?)),
+        )?;
+        #[allow(clippy::cast_precision_loss)] // 报价数量远小于 2^52
+        let success_rate = if issued == 0 {
+            0.0
+        } else {
+            accepted as f64 / issued as f64
+        };
+        Ok(QuoteStatistics {
+            total_quotes,
+            quotes_by_status: self.group_count(
+                "SELECT status, COUNT(*) FROM quotes WHERE deleted_at IS NULL GROUP BY status",
+            )?,
+            total_amount_this_month: Money::from_cents(total_amount_this_month),
+            success_rate,
+        })
+    }
+
+    async fn get_revisions(&self, quote_id: Uuid) -> CoreResult<Vec<QuoteRevision>> {
+        self.revisions.list(quote_id).map_err(to_core)
+    }
+
+    async fn diff_revisions(&self, quote_id: Uuid, a: u32, b: u32) -> CoreResult<QuoteDiff> {
+        self.revisions.diff(quote_id, a, b)
+    }
+
+    async fn restore_revision(&self, _quote_id: Uuid, _revision_no: u32) -> CoreResult<Quote> {
+        unsupported()
+    }
+}
+
+#[async_trait]
+impl StatisticsService for SqliteCoreServices {
+    /// 只计算客户、报价和任务的计数与金额，工单、汇率和账龄保持为空
+    async fn monthly_statistics(&self, period: ReportPeriod) -> CoreResult<MonthlyStatistics> {
+        let (start, end) = self.calendar.period_bounds(period);
+        let (start, end) = (time_key(start), time_key(end));
+        let (customers_at_start, new_customers) = self.connection.query_row(
+            "SELECT COUNT(CASE WHEN julianday(created_at) < julianday(?1) THEN 1 END),
+                    COUNT(CASE WHEN julianday(created_at) >= julianday(?1)
+                                    AND julianday(created_at) < julianday(?2) THEN 1 END)
+             FROM customers WHERE deleted_at IS NULL",
+            params![start, end],
+            |row| Ok((count(row, 0)?, count(row, 1)?)),
+        )?;
+        let quotes = self.connection.query_row(
+            "SELECT COUNT(CASE WHEN issued THEN 1 END),
+                    COALESCE(SUM(CASE WHEN issued THEN total_amount END), 0),
+                    COUNT(CASE WHEN accepted THEN 1 END),
+                    COALESCE(SUM(CASE WHEN accepted THEN total_amount END), 0)
+             FROM (SELECT total_amount,
+                          status <> 'draft' AND julianday(created_at) >= julianday(?1)
+                              AND julianday(created_at) < julianday(?2) AS issued,
+                          status = 'accepted' AND julianday(updated_at) >= julianday(?1)
+                              AND julianday(updated_at) < julianday(?2) AS accepted
+                   FROM quotes WHERE deleted_at IS NULL)",
+            params![start, end],
+            |row| Ok((count(row, 0)?, row.get(1)?, count(row, 2)?, row.get(3)?)),
+        )?;
+        let (tasks_due, tasks_completed) = self.connection.query_row(
+            "SELECT COUNT(CASE WHEN julianday(due_date) >= julianday(?1)
+                                    AND julianday(due_date) < julianday(?2) THEN 1 END),
+                    COUNT(CASE WHEN julianday(completed_at) >= julianday(?1)
+                                    AND julianday(completed_at) < julianday(?2) THEN 1 END)
+             FROM tasks WHERE deleted_at IS NULL",
+            params![start, end],
+            |row| Ok((count(row, 0)?, count(row, 1)?)),
+        )?;
+        Ok(MonthlyStatistics {
+            customers_at_start,
+            new_customers,
+            quotes_issued: quotes.0,
+            quotes_total_amount: quotes.1,
+            quotes_accepted: quotes.2,
+            quotes_accepted_amount: quotes.3,
+            tasks_due,
+            tasks_completed,
+            ..MonthlyStatistics::default()
+        })
+    }
+}
+
+/// 以数据库连接组装服务集合
+///
+/// 必需服务使用 [`SqliteCoreServices`]，报价模板、产品定价、信用、订单、采购订单、
+/// 客户列表、时间线、重复客户提示、幂等记录和一致性检查使用正式存储。
+///
+/// # Errors
+///
+/// 业务日历配置无效时返回错误。
+pub fn sqlite_service_set(
+    connection: &DatabaseConnection,
+    config: &AppConfig,
+) -> anyhow::Result<ServiceSet> {
+    let calendar = config.calendar.business_calendar()?;
+    let services = Arc::new(SqliteCoreServices::new(connection.clone()).with_calendar(calendar));
+    let products = Arc::new(ProductStore::new(connection.clone()));
+    let orders = Arc::new(OrderStore::new(connection.clone()).with_calendar(calendar));
+    let customer_list = Arc::new(CustomerListStore::new(connection.clone()));
+    Ok(ServiceSet {
+        customers: services.clone(),
+        tasks: services.clone(),
+        task_collaboration: None,
+        quotes: services.clone(),
+        statistics: services,
+        closings: None,
+        tickets: None,
+        knowledge_base: None,
+        quote_codec: None,
+        deliveries: Some(orders),
+        customer_list: Some(customer_list.clone()),
+        customer_timeline: Some(Arc::new(TimelineStore::new(connection.clone()))),
+        customer_export: Some(customer_list),
+        global_search: None,
+        duplicate_candidates: Some(Arc::new(GlobalSearchStore::new(connection.clone()))),
+        opportunities: None,
+        archive: None,
+        sync: None,
+        credit: Some(Arc::new(CreditStore::new(connection.clone()))),
+        record_archive: None,
+        field_policies: None,
+        customer_categories: None,
+        trash: None,
+        settings: None,
+        diagnostics: None,
+        idempotency: Some(Arc::new(IdempotencyStore::new(connection.clone()))),
+        quote_templates: Some(Arc::new(QuoteTemplateStore::new(connection.clone()))),
+        pricing: Some(products.clone()),
+        products: Some(products),
+        suppliers: None,
+        purchase_orders: Some(Arc::new(
+            PurchaseOrderStore::new(connection.clone()).with_calendar(calendar),
+        )),
+        spreadsheets: None,
+        consistency_checks: builtin_consistency_checks(connection.clone()),
+        header_aliases: HeaderAliases::default(),
+        margin_thresholds: MarginThresholds::default(),
+        leads: Some(Arc::new(LeadStore::new(connection.clone()))),
+        first_task_due_days: config.leads.first_task_due_days,
+        calendar,
+    })
+}
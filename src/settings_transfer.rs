@@ -0,0 +1,569 @@
+//! 设置导入导出模块
+//!
+//! 把配置、保存的搜索、仪表盘布局、列设置和快捷键打包为一个带格式版本的JSON文件，
+//! 用于让店里的多台电脑保持相同的设置。本机路径（数据库、附件、日志、配置目录）和密钥
+//! （SMTP密码、API令牌、应用密钥）不会导出；导入时保留本机原有的值，本机没有的在结果中
+//! 列出，需在设置界面重新填写。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config::AppConfig;
+use crate::core::{
+    CoreError, CoreResult, SectionImportResult, SectionImportStatus, SettingsExportSummary,
+    SettingsImportReport, SettingsSection, SettingsTransferService,
+};
+use crate::presentation::{ColumnLayout, DashboardLayout};
+use crate::ui_state::{SavedSearch, UiState};
+
+/// 设置文件格式版本
+pub const SETTINGS_SCHEMA_VERSION: u32 = 1;
+
+/// 不导出的本机路径（配置项路径）
+const LOCAL_PATHS: &[&str] = &[
+    "database.path",
+    "database.attachments_dir",
+    "database.backups_dir",
+    "database.extensions.paths",
+    "logging.file_path",
+    "security.config_dir",
+];
+
+/// 不导出的密钥（配置项路径）
+const SECRETS: &[&str] = &[
+    "security.app_secret",
+    "api.token",
+    "digest.smtp.password",
+    "email_intake.password",
+];
+
+/// 设置文件内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    /// 格式版本
+    pub schema_version: u32,
+    /// 导出时的应用版本
+    pub app_version: String,
+    /// 导出时间
+    pub exported_at: DateTime<Utc>,
+    /// 未导出的配置项
+    #[serde(default)]
+    pub redacted: Vec<String>,
+    /// 应用配置（已去除本机路径和密钥）
+    #[serde(default)]
+    pub config: Option<Value>,
+    /// 保存的搜索
+    #[serde(default)]
+    pub saved_searches: Option<Vec<SavedSearch>>,
+    /// 仪表盘布局
+    #[serde(default)]
+    pub dashboard_layouts: Option<BTreeMap<String, DashboardLayout>>,
+    /// 列设置
+    #[serde(default)]
+    pub column_layouts: Option<BTreeMap<String, ColumnLayout>>,
+    /// 快捷键设置
+    #[serde(default)]
+    pub shortcut_overrides: Option<BTreeMap<String, String>>,
+}
+
+/// 只读取格式版本，用于在解析其余内容前给出版本提示
+#[derive(Deserialize)]
+struct SchemaProbe {
+    schema_version: u32,
+}
+
+/// 配置项路径对应的父对象和键
+fn split_key(path: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = path.split('.').collect();
+    let key = parts.pop().unwrap_or_default();
+    (parts, key)
+}
+
+fn parent_mut<'a>(value: &'a mut Value, parents: &[&str]) -> Option<&'a mut Map<String, Value>> {
+    parents
+        .iter()
+        .try_fold(value, |current, part| current.get_mut(*part))?
+        .as_object_mut()
+}
+
+fn parent<'a>(value: &'a Value, parents: &[&str]) -> Option<&'a Map<String, Value>> {
+    parents
+        .iter()
+        .try_fold(value, |current, part| current.get(*part))?
+        .as_object()
+}
+
+/// 递归合并：对象逐键合并，其余值整体替换
+fn merge(target: &mut Value, source: Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            for (key, value) in source {
+                match target.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, source) => *target = source,
+    }
+}
+
+/// 值是否为空（不存在、null 或空字符串）
+fn is_blank(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(s)) => s.is_empty(),
+        Some(_) => false,
+    }
+}
+
+/// 配置中已填写的密钥值（诊断包据此遮盖日志中的密钥）
+pub(crate) fn secret_values(config: &AppConfig) -> Vec<String> {
+    let Ok(value) = serde_json::to_value(config) else {
+        return Vec::new();
+    };
+    SECRETS
+        .iter()
+        .filter_map(|path| {
+            let (parents, key) = split_key(path);
+            let secret = parent(&value, &parents)?.get(key)?.as_str()?;
+            (!secret.is_empty()).then(|| secret.to_string())
+        })
+        .collect()
+}
+
+impl SettingsBundle {
+    /// 由当前配置和界面状态生成设置文件内容
+    ///
+    /// # Errors
+    ///
+    /// 配置无法序列化时返回错误。
+    pub fn export(config: &AppConfig, ui_state: &UiState) -> CoreResult<Self> {
+        let mut value = serde_json::to_value(config)
+            .map_err(|e| CoreError::Other(format!("无法导出配置: {e}")))?;
+        let mut redacted = Vec::new();
+        for path in LOCAL_PATHS.iter().chain(SECRETS) {
+            let (parents, key) = split_key(path);
+            if let Some(object) = parent_mut(&mut value, &parents) {
+                if object.remove(key).is_some() {
+                    redacted.push((*path).to_string());
+                }
+            }
+        }
+        Ok(Self {
+            schema_version: SETTINGS_SCHEMA_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: Utc::now(),
+            redacted,
+            config: Some(value),
+            saved_searches: Some(ui_state.saved_searches.clone()),
+            dashboard_layouts: Some(ui_state.dashboard_layouts.clone()),
+            column_layouts: Some(ui_state.column_layouts.clone()),
+            shortcut_overrides: Some(ui_state.shortcut_overrides.clone()),
+        })
+    }
+
+    /// 文件中包含的部分
+    #[must_use]
+    pub fn sections(&self) -> Vec<SettingsSection> {
+        SettingsSection::ALL
+            .into_iter()
+            .filter(|section| self.contains(*section))
+            .collect()
+    }
+
+    const fn contains(&self, section: SettingsSection) -> bool {
+        match section {
+            SettingsSection::Config => self.config.is_some(),
+            SettingsSection::SavedSearches => self.saved_searches.is_some(),
+            SettingsSection::DashboardLayouts => self.dashboard_layouts.is_some(),
+            SettingsSection::ColumnLayouts => self.column_layouts.is_some(),
+            SettingsSection::Shortcuts => self.shortcut_overrides.is_some(),
+        }
+    }
+
+    /// 解析设置文件
+    ///
+    /// # Errors
+    ///
+    /// 文件格式不正确或来自更新版本的应用时返回验证错误。
+    pub fn parse(content: &str) -> CoreResult<Self> {
+        let probe: SchemaProbe = serde_json::from_str(content)
+            .map_err(|e| CoreError::validation(format!("不是有效的设置文件: {e}")))?;
+        if probe.schema_version > SETTINGS_SCHEMA_VERSION {
+            return Err(CoreError::validation(format!(
+                "设置文件来自更新版本的应用（格式版本 {}，当前支持 {}），请先升级应用后再导入",
+                probe.schema_version, SETTINGS_SCHEMA_VERSION
+            )));
+        }
+        serde_json::from_str(content)
+            .map_err(|e| CoreError::validation(format!("设置文件内容无效: {e}")))
+    }
+
+    /// 把选定的部分应用到配置和界面状态
+    ///
+    /// 各部分独立应用，某一部分无效时不影响其他部分。
+    pub fn apply(
+        &self,
+        sections: &[SettingsSection],
+        config: &mut AppConfig,
+        ui_state: &mut UiState,
+    ) -> SettingsImportReport {
+        let mut results = Vec::with_capacity(sections.len());
+        let mut requires_reentry = Vec::new();
+        for section in sections {
+            let outcome = if self.contains(*section) {
+                self.apply_section(*section, config, ui_state, &mut requires_reentry)
+            } else {
+                Err((
+                    SectionImportStatus::Skipped,
+                    "设置文件中没有该部分".to_string(),
+                ))
+            };
+            let (status, message) = match outcome {
+                Ok(message) => (SectionImportStatus::Applied, message),
+                Err(failure) => failure,
+            };
+            results.push(SectionImportResult {
+                section: *section,
+                status,
+                message,
+            });
+        }
+        SettingsImportReport {
+            schema_version: self.schema_version,
+            sections: results,
+            requires_reentry,
+        }
+    }
+
+    fn apply_section(
+        &self,
+        section: SettingsSection,
+        config: &mut AppConfig,
+        ui_state: &mut UiState,
+        requires_reentry: &mut Vec<String>,
+    ) -> Result<String, (SectionImportStatus, String)> {
+        match section {
+            SettingsSection::Config => {
+                let (merged, reentry) = self.merge_config(config)?;
+                *config = merged;
+                *requires_reentry = reentry;
+                Ok(if requires_reentry.is_empty() {
+                    "已应用，本机路径和密钥保持不变".to_string()
+                } else {
+                    format!("已应用，请重新填写 {} 项密钥", requires_reentry.len())
+                })
+            }
+            SettingsSection::SavedSearches => {
+                let searches = self.saved_searches.clone().unwrap_or_default();
+                let count = searches.len();
+                ui_state.saved_searches = searches;
+                Ok(format!("已导入 {count} 个保存的搜索"))
+            }
+            SettingsSection::DashboardLayouts => {
+                let layouts = self.dashboard_layouts.clone().unwrap_or_default();
+                let count = layouts.len();
+                ui_state.dashboard_layouts = layouts;
+                Ok(format!("已导入 {count} 个用户的仪表盘布局"))
+            }
+            SettingsSection::ColumnLayouts => {
+                let layouts = self.column_layouts.clone().unwrap_or_default();
+                let count = layouts.len();
+                ui_state.column_layouts = layouts;
+                Ok(format!("已导入 {count} 个列表的列设置"))
+            }
+            SettingsSection::Shortcuts => {
+                let shortcuts = self.shortcut_overrides.clone().unwrap_or_default();
+                let count = shortcuts.len();
+                ui_state.shortcut_overrides = shortcuts;
+                Ok(format!("已导入 {count} 个快捷键设置"))
+            }
+        }
+    }
+
+    /// 把导出的配置合并到本机配置上，返回合并结果和需要重新填写的密钥
+    ///
+    /// 导出的配置中没有本机路径和密钥，合并后这些项保持本机的值。本机也没有的密钥
+    /// 以空值占位并列入结果，不会被静默地当作已配置。
+    fn merge_config(
+        &self,
+        local: &AppConfig,
+    ) -> Result<(AppConfig, Vec<String>), (SectionImportStatus, String)> {
+        let failed = |e: serde_json::Error| (SectionImportStatus::Failed, format!("配置无效: {e}"));
+        let mut merged = serde_json::to_value(local).map_err(failed)?;
+        let mut incoming = self.config.clone().unwrap_or(Value::Null);
+        // 防止手工编辑过的文件覆盖本机路径和密钥
+        for path in LOCAL_PATHS.iter().chain(SECRETS) {
+            let (parents, key) = split_key(path);
+            if let Some(object) = parent_mut(&mut incoming, &parents) {
+                object.remove(key);
+            }
+        }
+        merge(&mut merged, incoming);
+
+        let mut reentry = Vec::new();
+        for path in SECRETS {
+            let (parents, key) = split_key(path);
+            let Some(object) = parent(&merged, &parents) else {
+                continue;
+            };
+            if !is_blank(object.get(key)) {
+                continue;
+            }
+            let required = parents
+                .iter()
+                .map(|p| (*p).to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            // 所在配置段已启用（如已配置SMTP服务器或开启API）时才需要填写
+            if parents.len() > 1 || Self::section_enabled(&merged, &required) {
+                if let Some(object) = parent_mut(&mut merged, &parents) {
+                    object
+                        .entry(key.to_string())
+                        .or_insert_with(|| Value::String(String::new()));
+                }
+                reentry.push((*path).to_string());
+            }
+        }
+
+        let config = serde_json::from_value(merged).map_err(failed)?;
+        Ok((config, reentry))
+    }
+
+    fn section_enabled(config: &Value, section: &str) -> bool {
+        config
+            .get(section)
+            .and_then(|s| s.get("enabled"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+}
+
+/// 基于本机配置文件和界面状态文件的设置导入导出服务
+///
+/// 导入直接写入两个文件，重启应用后生效。
+#[derive(Debug, Clone)]
+pub struct FileSettingsTransfer {
+    config_path: PathBuf,
+    ui_state_path: PathBuf,
+}
+
+impl FileSettingsTransfer {
+    /// 使用配置文件和界面状态文件路径创建服务
+    pub fn new<P: Into<PathBuf>, Q: Into<PathBuf>>(config_path: P, ui_state_path: Q) -> Self {
+        Self {
+            config_path: config_path.into(),
+            ui_state_path: ui_state_path.into(),
+        }
+    }
+
+    fn load_config(&self) -> CoreResult<AppConfig> {
+        Ok(AppConfig::load_from(&self.config_path)?)
+    }
+}
+
+#[async_trait]
+impl SettingsTransferService for FileSettingsTransfer {
+    async fn export_settings(&self, out_path: &Path) -> CoreResult<SettingsExportSummary> {
+        let config = self.load_config()?;
+        let bundle = SettingsBundle::export(&config, &UiState::load(&self.ui_state_path))?;
+        let content = serde_json::to_string_pretty(&bundle)
+            .map_err(|e| CoreError::Other(format!("无法导出设置: {e}")))?;
+        fs::write(out_path, content)
+            .with_context(|| format!("无法写入设置文件: {}", out_path.display()))?;
+        Ok(SettingsExportSummary {
+            schema_version: bundle.schema_version,
+            sections: bundle.sections(),
+            redacted: bundle.redacted,
+        })
+    }
+
+    async fn import_settings(
+        &self,
+        path: &Path,
+        sections: &[SettingsSection],
+    ) -> CoreResult<SettingsImportReport> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| CoreError::validation(format!("无法读取设置文件: {e}")))?;
+        let bundle = SettingsBundle::parse(&content)?;
+
+        let mut config = self.load_config()?;
+        let mut ui_state = UiState::load(&self.ui_state_path);
+        let report = bundle.apply(sections, &mut config, &mut ui_state);
+        let applied = |section: SettingsSection| {
+            report
+                .sections
+                .iter()
+                .any(|r| r.section == section && r.status == SectionImportStatus::Applied)
+        };
+        if applied(SettingsSection::Config) {
+            config.save(&self.config_path)?;
+        }
+        if report.sections.iter().any(|r| {
+            r.section != SettingsSection::Config && r.status == SectionImportStatus::Applied
+        }) {
+            ui_state.save(&self.ui_state_path)?;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SmtpConfig;
+    use crate::presentation::{CardSlot, CustomerListFilter, Route};
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    fn configured() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.database.path = PathBuf::from("D:/门店/minicrm.db");
+        config.security.app_secret = Some("local-secret".to_string());
+        config.api.enabled = true;
+        config.api.token = Some("api-token".to_string());
+        config.ui.theme = "dark".to_string();
+        config.digest.recipients = vec!["boss@example.com".to_string()];
+        config.digest.smtp = Some(SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port: 465,
+            username: "crm@example.com".to_string(),
+            password: "授权码".to_string(),
+            from: "crm@example.com".to_string(),
+        });
+        config
+    }
+
+    fn ui_state() -> UiState {
+        let mut state = UiState::default();
+        state.saved_searches.push(SavedSearch {
+            name: "重点客户".to_string(),
+            route: Route::CustomerList {
+                filter: CustomerListFilter {
+                    search: Some("板材".to_string()),
+                    level: None,
+                    churn_risk: false,
+                    sort_by: None,
+                },
+            },
+        });
+        state.dashboard_layouts.insert(
+            "boss".to_string(),
+            DashboardLayout {
+                cards: vec![CardSlot {
+                    id: "reminders".to_string(),
+                    visible: true,
+                }],
+            },
+        );
+        state
+            .shortcut_overrides
+            .insert("new_customer".to_string(), "Ctrl+Shift+N".to_string());
+        state
+    }
+
+    #[test]
+    fn test_export_redacts_paths_and_secrets() -> Result<()> {
+        let bundle = SettingsBundle::export(&configured(), &ui_state())?;
+        let json = serde_json::to_string(&bundle)?;
+        for secret in ["授权码", "api-token", "local-secret", "D:/门店"] {
+            assert!(!json.contains(secret), "导出内容包含 {secret}");
+        }
+        assert!(json.contains("smtp.example.com"));
+        assert!(bundle
+            .redacted
+            .contains(&"digest.smtp.password".to_string()));
+        assert!(bundle.redacted.contains(&"database.path".to_string()));
+        assert_eq!(bundle.sections(), SettingsSection::ALL.to_vec());
+        Ok(())
+    }
+
+    #[test]
+    fn test_selective_import_keeps_local_values() -> Result<()> {
+        let bundle = SettingsBundle::export(&configured(), &ui_state())?;
+
+        // 另一台电脑：本机数据库路径不同，尚未配置SMTP
+        let mut config = AppConfig::default();
+        config.database.path = PathBuf::from("C:/crm/minicrm.db");
+        let mut state = UiState::default();
+        let report = bundle.apply(
+            &[SettingsSection::Config, SettingsSection::SavedSearches],
+            &mut config,
+            &mut state,
+        );
+
+        assert!(report
+            .sections
+            .iter()
+            .all(|r| r.status == SectionImportStatus::Applied));
+        assert_eq!(config.ui.theme, "dark");
+        assert_eq!(config.database.path, PathBuf::from("C:/crm/minicrm.db"));
+        let smtp = config
+            .digest
+            .smtp
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("应导入SMTP"))?;
+        assert_eq!(smtp.host, "smtp.example.com");
+        // 密钥需要重新填写，而不是被当作已配置
+        assert!(smtp.password.is_empty());
+        assert_eq!(
+            report.requires_reentry,
+            vec!["api.token".to_string(), "digest.smtp.password".to_string()]
+        );
+
+        assert_eq!(state.saved_searches.len(), 1);
+        // 未选择的部分不修改
+        assert!(state.dashboard_layouts.is_empty());
+        assert!(state.shortcut_overrides.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_section_is_reported_as_skipped() -> Result<()> {
+        let mut bundle = SettingsBundle::export(&AppConfig::default(), &ui_state())?;
+        bundle.shortcut_overrides = None;
+        let mut state = UiState::default();
+        let report = bundle.apply(
+            &[
+                SettingsSection::Shortcuts,
+                SettingsSection::DashboardLayouts,
+            ],
+            &mut AppConfig::default(),
+            &mut state,
+        );
+        assert_eq!(report.sections[0].status, SectionImportStatus::Skipped);
+        assert_eq!(report.sections[1].status, SectionImportStatus::Applied);
+        assert!(state.dashboard_layouts.contains_key("boss"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_future_schema_version_is_rejected() -> Result<()> {
+        let dir = TempDir::new()?;
+        let config_path = dir.path().join("minicrm.json");
+        let ui_state_path = dir.path().join("ui-state.json");
+        let transfer = FileSettingsTransfer::new(&config_path, &ui_state_path);
+        let file = dir.path().join("settings.json");
+        transfer.export_settings(&file).await?;
+
+        let mut bundle: Value = serde_json::from_str(&fs::read_to_string(&file)?)?;
+        bundle["schema_version"] = Value::from(SETTINGS_SCHEMA_VERSION + 1);
+        fs::write(&file, bundle.to_string())?;
+
+        let result = transfer.import_settings(&file, &SettingsSection::ALL).await;
+        assert!(matches!(result, Err(CoreError::Validation(ref m)) if m.contains("请先升级应用")));
+        assert!(!config_path.exists());
+        assert!(!ui_state_path.exists());
+        Ok(())
+    }
+}
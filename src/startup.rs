@@ -0,0 +1,371 @@
+//! 启动计时与延后初始化
+//!
+//! [`StartupProfiler`] 记录启动各阶段的耗时，主窗口显示后写入日志并显示在诊断信息界面。
+//! 显示主窗口前只执行打开可交互窗口必需的阶段（配置、连接池、迁移、自检、上下文和界面构建）；
+//! 完整健康检查、全文索引校验、统计预热、调度补跑和提醒扫描由 [`DeferredStartup`] 在窗口
+//! 显示后放到后台线程执行，期间仪表盘显示加载状态。
+
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use crate::database::DatabaseManager;
+use crate::infrastructure::database::FtsMaintenance;
+
+/// 启动阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StartupPhase {
+    /// 加载配置
+    Config,
+    /// 创建连接池并预热连接
+    Pool,
+    /// 执行数据库迁移
+    Migrations,
+    /// 启动自检
+    Preflight,
+    /// 构建服务、导航和界面流程
+    ContextBuild,
+    /// 创建主窗口
+    UiConstruct,
+    /// 完整健康检查
+    HealthCheck,
+    /// 全文索引校验
+    FtsVerification,
+    /// 统计预热
+    StatisticsPriming,
+    /// 调度任务补跑
+    SchedulerCatchUp,
+    /// 提醒扫描
+    ReminderSweep,
+}
+
+impl StartupPhase {
+    /// 显示主窗口后才执行的阶段
+    pub const DEFERRED: [Self; 5] = [
+        Self::HealthCheck,
+        Self::FtsVerification,
+        Self::StatisticsPriming,
+        Self::SchedulerCatchUp,
+        Self::ReminderSweep,
+    ];
+
+    /// 日志中使用的标识
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Config => "config",
+            Self::Pool => "pool",
+            Self::Migrations => "migrations",
+            Self::Preflight => "preflight",
+            Self::ContextBuild => "context_build",
+            Self::UiConstruct => "ui_construct",
+            Self::HealthCheck => "health_check",
+            Self::FtsVerification => "fts_verification",
+            Self::StatisticsPriming => "statistics_priming",
+            Self::SchedulerCatchUp => "scheduler_catch_up",
+            Self::ReminderSweep => "reminder_sweep",
+        }
+    }
+
+    /// 诊断信息界面显示的名称
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Config => "加载配置",
+            Self::Pool => "创建连接池",
+            Self::Migrations => "数据库迁移",
+            Self::Preflight => "启动自检",
+            Self::ContextBuild => "构建服务",
+            Self::UiConstruct => "创建主窗口",
+            Self::HealthCheck => "健康检查",
+            Self::FtsVerification => "全文索引校验",
+            Self::StatisticsPriming => "统计预热",
+            Self::SchedulerCatchUp => "调度任务补跑",
+            Self::ReminderSweep => "提醒扫描",
+        }
+    }
+
+    /// 是否在显示主窗口后执行
+    #[must_use]
+    pub fn is_deferred(&self) -> bool {
+        Self::DEFERRED.contains(self)
+    }
+}
+
+/// 一个阶段的耗时
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseTiming {
+    /// 阶段
+    pub phase: StartupPhase,
+    /// 耗时
+    pub duration: Duration,
+    /// 是否在显示主窗口后完成
+    pub after_show: bool,
+    /// 失败原因（延后执行的阶段失败不影响使用）
+    pub error: Option<String>,
+}
+
+impl PhaseTiming {
+    /// 耗时文本（毫秒）
+    #[must_use]
+    pub fn duration_text(&self) -> String {
+        format!("{} ms", self.duration.as_millis())
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProfilerState {
+    timings: Vec<PhaseTiming>,
+    shown: bool,
+}
+
+/// 启动计时器
+///
+/// 克隆后共享同一份记录，延后执行的阶段在后台线程中写入。
+#[derive(Debug, Clone, Default)]
+pub struct StartupProfiler {
+    state: Arc<Mutex<ProfilerState>>,
+}
+
+impl StartupProfiler {
+    /// 创建计时器
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 执行并记录一个阶段
+    pub fn time<T>(&self, phase: StartupPhase, run: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = run();
+        self.record(phase, started.elapsed(), None);
+        result
+    }
+
+    /// 记录一个阶段的耗时
+    pub fn record(&self, phase: StartupPhase, duration: Duration, error: Option<String>) {
+        if let Ok(mut state) = self.state.lock() {
+            let after_show = state.shown;
+            state.timings.push(PhaseTiming {
+                phase,
+                duration,
+                after_show,
+                error,
+            });
+        }
+    }
+
+    /// 主窗口已显示，之后记录的阶段计为显示后完成
+    pub fn mark_shown(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.shown = true;
+        }
+    }
+
+    /// 全部阶段的耗时（按完成顺序）
+    #[must_use]
+    pub fn timings(&self) -> Vec<PhaseTiming> {
+        self.state
+            .lock()
+            .map(|state| state.timings.clone())
+            .unwrap_or_default()
+    }
+
+    /// 显示主窗口前执行的阶段
+    #[must_use]
+    pub fn pre_show_phases(&self) -> Vec<StartupPhase> {
+        self.timings()
+            .into_iter()
+            .filter(|timing| !timing.after_show)
+            .map(|timing| timing.phase)
+            .collect()
+    }
+
+    /// 显示主窗口前各阶段的耗时合计
+    #[must_use]
+    pub fn time_to_show(&self) -> Duration {
+        self.timings()
+            .iter()
+            .filter(|timing| !timing.after_show)
+            .map(|timing| timing.duration)
+            .sum()
+    }
+
+    /// 把各阶段耗时写入日志
+    pub fn log_summary(&self) {
+        for timing in self.timings() {
+            info!(
+                "启动阶段 {}（{}）：{}{}",
+                timing.phase.as_str(),
+                timing.phase.label(),
+                timing.duration_text(),
+                if timing.after_show {
+                    "，窗口显示后"
+                } else {
+                    ""
+                }
+            );
+        }
+        info!("显示主窗口前共用时 {} ms", self.time_to_show().as_millis());
+    }
+}
+
+type DeferredTask = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// 显示主窗口后在后台执行的初始化
+#[derive(Default)]
+pub struct DeferredStartup {
+    tasks: Vec<(StartupPhase, DeferredTask)>,
+}
+
+impl std::fmt::Debug for DeferredStartup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeferredStartup")
+            .field("phases", &self.phases())
+            .finish()
+    }
+}
+
+impl DeferredStartup {
+    /// 创建空的延后初始化
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 数据库相关的延后初始化：完整健康检查、全文索引校验和统计预热
+    #[must_use]
+    pub fn for_database(database: &DatabaseManager) -> Self {
+        let health = database.clone();
+        let fts = FtsMaintenance::new(database.connection());
+        let statistics = database.clone();
+        Self::new()
+            .add(StartupPhase::HealthCheck, move || {
+                let health = health.check_health();
+                if !health.healthy {
+                    warn!("数据库健康检查未通过: {:?}", health.checks);
+                }
+                Ok(())
+            })
+            .add(StartupPhase::FtsVerification, move || {
+                for status in fts.verify().context("无法校验全文索引")? {
+                    if !status.is_consistent() {
+                        warn!(
+                            "全文索引 {} 与基础表不一致（{} / {} 行），可在诊断信息中重建",
+                            status.table, status.indexed_rows, status.base_rows
+                        );
+                    }
+                }
+                Ok(())
+            })
+            .add(StartupPhase::StatisticsPriming, move || {
+                statistics
+                    .get_database_stats()
+                    .map(|_| ())
+                    .context("无法读取数据库统计")
+            })
+    }
+
+    /// 添加一个延后执行的阶段
+    ///
+    /// 只能添加 [`StartupPhase::DEFERRED`] 中的阶段。
+    #[must_use]
+    pub fn add<F>(mut self, phase: StartupPhase, task: F) -> Self
+    where
+        F: FnOnce() -> Result<()> + Send + 'static,
+    {
+        debug_assert!(phase.is_deferred(), "{} 不是延后执行的阶段", phase.as_str());
+        self.tasks.push((phase, Box::new(task)));
+        self
+    }
+
+    /// 已添加的阶段
+    #[must_use]
+    pub fn phases(&self) -> Vec<StartupPhase> {
+        self.tasks.iter().map(|(phase, _)| *phase).collect()
+    }
+
+    /// 在后台线程依次执行，全部完成后以各阶段耗时调用 `on_done`
+    ///
+    /// 单个阶段失败只记录警告，不影响后续阶段。
+    pub fn spawn<F>(self, profiler: StartupProfiler, on_done: F) -> JoinHandle<()>
+    where
+        F: FnOnce(Vec<PhaseTiming>) + Send + 'static,
+    {
+        std::thread::spawn(move || {
+            for (phase, task) in self.tasks {
+                let started = Instant::now();
+                let error = task().err().map(|e| format!("{e:#}"));
+                if let Some(error) = &error {
+                    warn!("启动阶段 {} 失败: {}", phase.as_str(), error);
+                }
+                profiler.record(phase, started.elapsed(), error);
+            }
+            on_done(profiler.timings());
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_deferred_phases_run_after_show() -> Result<()> {
+        let dir = TempDir::new()?;
+        let profiler = StartupProfiler::new();
+        let config = profiler.time(StartupPhase::Config, || {
+            let mut config = AppConfig::default();
+            config.database.path = dir.path().join("minicrm.db");
+            config
+        });
+        let (progress, _events) = mpsc::channel();
+        let database = DatabaseManager::with_migration_progress(&config, progress, &profiler)?;
+        profiler.time(StartupPhase::UiConstruct, || ());
+        profiler.mark_shown();
+
+        let pre_show = profiler.pre_show_phases();
+        assert_eq!(
+            pre_show,
+            vec![
+                StartupPhase::Config,
+                StartupPhase::Pool,
+                StartupPhase::Migrations,
+                StartupPhase::UiConstruct
+            ]
+        );
+        assert!(pre_show.iter().all(|phase| !phase.is_deferred()));
+
+        let (done, finished) = mpsc::channel();
+        let deferred = DeferredStartup::for_database(&database)
+            .add(StartupPhase::SchedulerCatchUp, || Ok(()))
+            .add(StartupPhase::ReminderSweep, || {
+                anyhow::bail!("提醒表被锁定")
+            });
+        let expected = deferred.phases();
+        deferred
+            .spawn(profiler.clone(), move |timings| {
+                let _ = done.send(timings);
+            })
+            .join()
+            .map_err(|_| anyhow::anyhow!("延后初始化线程异常退出"))?;
+
+        let timings = finished.recv_timeout(Duration::from_secs(10))?;
+        let after_show: Vec<&PhaseTiming> = timings.iter().filter(|t| t.after_show).collect();
+        assert_eq!(
+            after_show.iter().map(|t| t.phase).collect::<Vec<_>>(),
+            expected
+        );
+        assert!(after_show.iter().all(|t| t.phase.is_deferred()));
+        // 失败的阶段同样记录，不影响其他阶段
+        assert_eq!(after_show.iter().filter(|t| t.error.is_some()).count(), 1);
+        assert_eq!(profiler.pre_show_phases(), pre_show);
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! 界面状态模块
+//!
+//! 退出时保存界面状态（如最后打开的视图、各用户的仪表盘布局、列表的列设置、保存的搜索、
+//! 快捷键设置、新手引导进度和最近查看），下次启动时恢复。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::presentation::{
+    ColumnChooserViewModel, ColumnLayout, CustomerListFilter, DashboardLayout, OnboardingState,
+    ReclaimPromptState, RecentItem, RecentItems, Route,
+};
+
+/// 界面状态文件名
+pub const UI_STATE_FILE_NAME: &str = "ui-state.json";
+
+/// 保存的搜索
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// 名称
+    pub name: String,
+    /// 打开的路由（含筛选条件）
+    pub route: Route,
+}
+
+impl SavedSearch {
+    /// 内置的保存的搜索（不写入界面状态，始终排在用户保存的搜索之前）
+    #[must_use]
+    pub fn builtin() -> Vec<Self> {
+        vec![Self {
+            name: "流失风险".to_string(),
+            route: Route::CustomerList {
+                filter: CustomerListFilter {
+                    churn_risk: true,
+                    ..CustomerListFilter::default()
+                },
+            },
+        }]
+    }
+}
+
+/// 界面状态
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UiState {
+    /// 最后打开的路由
+    #[serde(default)]
+    pub last_route: Option<Route>,
+    /// 各用户的仪表盘布局（按用户名）
+    #[serde(default)]
+    pub dashboard_layouts: BTreeMap<String, DashboardLayout>,
+    /// 数据库整理提示的忽略记录
+    #[serde(default)]
+    pub reclaim_prompt: ReclaimPromptState,
+    /// 各列表界面的列设置（按界面ID，未调整过的界面不保存）
+    #[serde(default)]
+    pub column_layouts: BTreeMap<String, ColumnLayout>,
+    /// 保存的搜索
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    /// 快捷键设置（操作ID → 按键组合，未修改的操作不保存）
+    #[serde(default)]
+    pub shortcut_overrides: BTreeMap<String, String>,
+    /// 新手引导进度（完成过的步骤和是否已关闭）
+    #[serde(default)]
+    pub onboarding: OnboardingState,
+    /// 最近查看（每种类型最近的若干条）
+    #[serde(default)]
+    pub recent_items: Vec<RecentItem>,
+}
+
+impl UiState {
+    /// 界面状态文件路径
+    pub fn path_in<P: AsRef<Path>>(config_dir: P) -> PathBuf {
+        config_dir.as_ref().join(UI_STATE_FILE_NAME)
+    }
+
+    /// 保存列选择器的设置；恢复为默认设置时移除该界面的记录
+    pub fn set_column_layout(&mut self, chooser: &ColumnChooserViewModel) {
+        match chooser.saved_layout() {
+            Some(layout) => {
+                self.column_layouts.insert(chooser.screen().to_string(), layout);
+            }
+            None => {
+                self.column_layouts.remove(chooser.screen());
+            }
+        }
+    }
+
+    /// 保存退出时的最近查看
+    pub fn set_recent_items(&mut self, recent: &RecentItems) {
+        self.recent_items = recent.persisted();
+    }
+
+    /// 恢复最近查看
+    #[must_use]
+    pub fn restore_recent_items(&self) -> RecentItems {
+        RecentItems::restore(self.recent_items.clone())
+    }
+
+    /// 侧栏显示的保存的搜索（内置的在前）
+    #[must_use]
+    pub fn all_saved_searches(&self) -> Vec<SavedSearch> {
+        let mut searches = SavedSearch::builtin();
+        searches.extend(self.saved_searches.iter().cloned());
+        searches
+    }
+
+    /// 读取界面状态
+    ///
+    /// 文件不存在或内容无法解析时返回默认状态。
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("界面状态文件无法解析，已忽略: {}", e);
+            Self::default()
+        })
+    }
+
+    /// 保存界面状态
+    ///
+    /// # Errors
+    ///
+    /// 目录创建或文件写入失败时返回错误。
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("无法创建配置目录: {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("无法写入界面状态文件: {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::EntityKind;
+    use crate::presentation::{CardSlot, ColumnConfig, OnboardingStep};
+    use chrono::Utc;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_last_route_round_trip() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = UiState::path_in(dir.path());
+        assert_eq!(UiState::load(&path), UiState::default());
+
+        let state = UiState {
+            last_route: Some(Route::QuoteEditor { id: Uuid::new_v4() }),
+            ..UiState::default()
+        };
+        state.save(&path)?;
+        assert_eq!(UiState::load(&path), state);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dashboard_layout_round_trip() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = UiState::path_in(dir.path());
+        let layout = DashboardLayout {
+            cards: vec![
+                CardSlot {
+                    id: "reminders".to_string(),
+                    visible: true,
+                },
+                CardSlot {
+                    id: "customers".to_string(),
+                    visible: false,
+                },
+            ],
+        };
+        let mut state = UiState::default();
+        state
+            .dashboard_layouts
+            .insert("boss".to_string(), layout.clone());
+        state.save(&path)?;
+
+        let loaded = UiState::load(&path);
+        assert_eq!(loaded.dashboard_layouts.get("boss"), Some(&layout));
+        assert!(!loaded.dashboard_layouts.contains_key("after_sales"));
+
+        // 旧版本的状态文件没有布局字段
+        fs::write(&path, r#"{"last_route":null}"#)?;
+        assert!(UiState::load(&path).dashboard_layouts.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_layout_round_trip() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = UiState::path_in(dir.path());
+        let mut chooser = ColumnChooserViewModel::new(ColumnConfig::customer_list(), None);
+        chooser.toggle("order_total");
+        chooser.move_column("order_total", 0);
+        chooser.set_width("name", 260);
+
+        let mut state = UiState::default();
+        state.set_column_layout(&chooser);
+        state.save(&path)?;
+
+        let loaded = UiState::load(&path);
+        let restored = ColumnChooserViewModel::new(
+            ColumnConfig::customer_list(),
+            loaded.column_layouts.get("customer_list"),
+        );
+        assert_eq!(restored.layout(), chooser.layout());
+        assert_eq!(restored.visible_columns()[0].id, "order_total");
+
+        // 恢复默认后不再保存该界面的记录
+        chooser.reset();
+        state.set_column_layout(&chooser);
+        assert!(state.column_layouts.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_onboarding_round_trip() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = UiState::path_in(dir.path());
+        let mut state = UiState::default();
+        state.onboarding.dismissed = true;
+        state
+            .onboarding
+            .completed
+            .insert(OnboardingStep::FirstCustomer);
+        state.save(&path)?;
+        assert_eq!(UiState::load(&path).onboarding, state.onboarding);
+
+        // 旧版本的状态文件没有引导进度
+        fs::write(&path, r#"{"last_route":null}"#)?;
+        assert!(!UiState::load(&path).onboarding.dismissed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recent_items_round_trip() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = UiState::path_in(dir.path());
+        let mut recent = RecentItems::default();
+        let customer = Uuid::new_v4();
+        recent.record(EntityKind::Quote, Uuid::new_v4(), "BJ20240701-001", Utc::now());
+        recent.record(EntityKind::Customer, customer, "华美装饰", Utc::now());
+
+        let mut state = UiState::default();
+        state.set_recent_items(&recent);
+        state.save(&path)?;
+        let restored = UiState::load(&path).restore_recent_items();
+        assert_eq!(restored, recent);
+        assert_eq!(restored.items()[0].id, customer);
+
+        // 旧版本的状态文件没有最近查看
+        fs::write(&path, r#"{"last_route":null}"#)?;
+        assert!(UiState::load(&path).restore_recent_items().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_churn_search_comes_first() -> Result<()> {
+        let mut state = UiState::default();
+        state.saved_searches.push(SavedSearch {
+            name: "重点客户".to_string(),
+            route: Route::CustomerList {
+                filter: CustomerListFilter::default(),
+            },
+        });
+        let searches = state.all_saved_searches();
+        assert_eq!(searches.len(), 2);
+        assert_eq!(searches[0].name, "流失风险");
+        let Route::CustomerList { filter } = &searches[0].route else {
+            anyhow::bail!("内置搜索应打开客户列表");
+        };
+        assert!(filter.to_query_filter().filters.contains_key("churn_risk"));
+
+        // 内置搜索不写入界面状态
+        let json = serde_json::to_string(&state)?;
+        assert!(!json.contains("流失风险"));
+        Ok(())
+    }
+}
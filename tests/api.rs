@@ -0,0 +1,360 @@
+//! REST API集成测试
+//!
+//! 经 [`AppContext::start_api`] 在真实数据库上启动服务，通过HTTP验证分页、认证、
+//! 服务账号和错误映射。
+
+#![cfg(feature = "api")]
+
+use std::fmt::Write;
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use chrono::Utc;
+use minicrm::api::ApiServer;
+use minicrm::application::{StatKey, StatKind};
+use minicrm::core::{
+    Customer, CustomerLevel, Repository, Task, TaskPriority, TaskStatus, UserRole,
+};
+use minicrm::database::DatabaseManager;
+use minicrm::infrastructure::repository::{GenericRepository, SqliteCustomerRepository};
+use minicrm::services::sqlite_service_set;
+use minicrm::{AppConfig, AppContext};
+use serde_json::{json, Value};
+use tempfile::{tempdir, TempDir};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use uuid::Uuid;
+
+const TOKEN: &str = "test-token";
+
+/// 已启动的测试服务
+struct TestApi {
+    _dir: TempDir,
+    database: DatabaseManager,
+    server: ApiServer,
+}
+
+impl TestApi {
+    /// 以服务账号 `dashboard` 和给定角色启动API（端口由系统分配）
+    async fn start(role: UserRole) -> Result<Self> {
+        let dir = tempdir()?;
+        let config = api_config(&dir, role);
+        let database = DatabaseManager::new(&config)?;
+        let server = AppContext::start_api(config, &database.connection())
+            .await?
+            .context("API未启用")?;
+        Ok(Self {
+            _dir: dir,
+            database,
+            server,
+        })
+    }
+
+    /// 携带访问令牌发送请求
+    async fn send(
+        &self,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> Result<(StatusCode, Value)> {
+        request(self.server.local_addr, method, uri, Some(TOKEN), body).await
+    }
+}
+
+fn api_config(dir: &TempDir, role: UserRole) -> AppConfig {
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("api.db");
+    config.api.enabled = true;
+    config.api.port = 0;
+    config.api.token = Some(TOKEN.to_string());
+    config.api.service_user = "dashboard".to_string();
+    config.api.service_role = role;
+    config
+}
+
+/// 发送一次HTTP/1.1请求，返回状态码和JSON响应体
+async fn request(
+    addr: SocketAddr,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> Result<(StatusCode, Value)> {
+    let payload = body.map(|json| json.to_string()).unwrap_or_default();
+    let mut head = format!(
+        "{method} {uri} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n",
+        payload.len()
+    );
+    if let Some(token) = token {
+        write!(head, "Authorization: Bearer {token}\r\n")?;
+    }
+
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(format!("{head}\r\n{payload}").as_bytes())
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+
+    let (status_line, body) = response
+        .split_once("\r\n\r\n")
+        .context("响应缺少头部结束标记")?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .context("响应缺少状态码")?;
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(body)?
+    };
+    Ok((StatusCode::from_bytes(status.as_bytes())?, body))
+}
+
+fn customer(name: &str) -> Customer {
+    let now = Utc::now();
+    Customer {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        contact_person: None,
+        contact_birthday: None,
+        phone: None,
+        email: None,
+        address: None,
+        level: CustomerLevel::Normal,
+        credit_limit: None,
+        credit_hold: false,
+        owner_id: None,
+        created_at: now,
+        updated_at: now,
+        created_by: None,
+        updated_by: None,
+    }
+}
+
+fn task(title: &str) -> Task {
+    let now = Utc::now();
+    Task {
+        id: Uuid::new_v4(),
+        title: title.to_string(),
+        description: None,
+        status: TaskStatus::Pending,
+        priority: TaskPriority::Medium,
+        customer_id: None,
+        supplier_id: None,
+        due_date: None,
+        created_at: now,
+        updated_at: now,
+        created_by: None,
+        updated_by: None,
+        assigned_to: None,
+    }
+}
+
+#[tokio::test]
+async fn test_disabled_api_does_not_start() -> Result<()> {
+    let dir = tempdir()?;
+    let mut config = api_config(&dir, UserRole::Sales);
+    config.api.enabled = false;
+    let database = DatabaseManager::new(&config)?;
+    assert!(
+        AppContext::start_api(config, &database.connection())
+            .await?
+            .is_none()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_customers_paginates() -> Result<()> {
+    let api = TestApi::start(UserRole::Sales).await?;
+    let customers = SqliteCustomerRepository::new(api.database.connection());
+    for i in 0..25 {
+        customers.save(&customer(&format!("客户{i:02}"))).await?;
+    }
+
+    let (status, body) = api
+        .send("GET", "/api/customers?page=2&page_size=10", None)
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 25);
+    assert_eq!(body["page"], 2);
+    assert_eq!(body["items"].as_array().map(Vec::len), Some(10));
+    assert_eq!(body["items"][0]["name"], "客户10");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_or_wrong_token_is_rejected() -> Result<()> {
+    let api = TestApi::start(UserRole::Sales).await?;
+    let addr = api.server.local_addr;
+
+    let (status, _) = request(addr, "GET", "/api/customers", None, None).await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = request(
+        addr,
+        "GET",
+        "/api/stats/dashboard",
+        Some("wrong-token"),
+        None,
+    )
+    .await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_customer_validation_maps_to_422() -> Result<()> {
+    let api = TestApi::start(UserRole::Sales).await?;
+
+    let (status, body) = api
+        .send(
+            "POST",
+            "/api/customers",
+            Some(json!({ "name": " ", "email": "invalid" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let fields: Vec<&str> = body["fields"]
+        .as_array()
+        .map(|f| f.iter().filter_map(|e| e["field"].as_str()).collect())
+        .unwrap_or_default();
+    assert_eq!(fields, vec!["name", "email"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_customer_replay_returns_stored_result() -> Result<()> {
+    let api = TestApi::start(UserRole::Sales).await?;
+    let key = Uuid::new_v4();
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let (status, body) = api
+            .send(
+                "POST",
+                "/api/customers",
+                Some(json!({ "name": "华东板材", "idempotency_key": key })),
+            )
+            .await?;
+        assert_eq!(status, StatusCode::CREATED);
+        // 写操作以令牌对应的服务账号记录
+        assert_eq!(body["created_by"], "dashboard");
+        ids.push(body["id"].clone());
+    }
+    assert_eq!(ids[0], ids[1]);
+    let (_, body) = api.send("GET", "/api/customers", None).await?;
+    assert_eq!(body["total"], 1);
+
+    // 同一键提交不同内容视为冲突
+    let (status, _) = api
+        .send(
+            "POST",
+            "/api/customers",
+            Some(json!({ "name": "华南板材", "idempotency_key": key })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::CONFLICT);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_service_role_limits_commands() -> Result<()> {
+    let api = TestApi::start(UserRole::Viewer).await?;
+
+    let (status, _) = api.send("GET", "/api/customers", None).await?;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = api
+        .send(
+            "POST",
+            "/api/customers",
+            Some(json!({ "name": "华东板材" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stale_task_update_maps_to_409() -> Result<()> {
+    let api = TestApi::start(UserRole::Sales).await?;
+    let owner = SqliteCustomerRepository::new(api.database.connection())
+        .save(&customer("华东板材"))
+        .await?;
+    let existing = Task {
+        customer_id: Some(owner.id),
+        ..task("回访客户")
+    };
+    let task_id = existing.id;
+    GenericRepository::<Task>::new(api.database.connection())
+        .save(&existing)
+        .await?;
+    let (_, listed) = api.send("GET", "/api/tasks", None).await?;
+    let current = listed["items"][0]["updated_at"].clone();
+    let stale = existing.updated_at - chrono::Duration::minutes(5);
+
+    let (status, _) = api
+        .send(
+            "PATCH",
+            &format!("/api/tasks/{task_id}/status"),
+            Some(json!({ "status": "Completed", "expected_updated_at": stale })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = api
+        .send(
+            "PATCH",
+            &format!("/api/tasks/{task_id}/status"),
+            Some(json!({ "status": "Completed", "expected_updated_at": current })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "Completed");
+    assert_eq!(body["updated_by"], "dashboard");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unknown_customer_maps_to_404() -> Result<()> {
+    let api = TestApi::start(UserRole::Sales).await?;
+
+    let (status, _) = api
+        .send("GET", &format!("/api/customers/{}", Uuid::new_v4()), None)
+        .await?;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_external_database_change_invalidates_statistics() -> Result<()> {
+    let dir = tempdir()?;
+    let config = api_config(&dir, UserRole::Sales);
+    let database = DatabaseManager::new(&config)?;
+    let services = sqlite_service_set(&database.connection(), &config)?;
+    let path = config.database.path.clone();
+    let ctx = AppContext::new(config, &services);
+    let guard = database
+        .file_guard()
+        .ok_or_else(|| anyhow::anyhow!("文件守卫未启动"))?;
+    ctx.watch_database(guard);
+    let mut changes = ctx.external_changes.subscribe();
+
+    ctx.statistics_cache
+        .refresh(StatKey::of(StatKind::Customers), || async { Ok(1_u64) })
+        .await?;
+    assert!(!ctx.statistics_cache.is_empty());
+
+    let raw = rusqlite::Connection::open(&path)?;
+    raw.execute_batch("CREATE TABLE external_notes (body TEXT)")?;
+    database.connection().table_exists("external_notes")?;
+
+    assert!(ctx.statistics_cache.is_empty());
+    let change = changes.try_recv()?;
+    assert!(!change.read_only);
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! 应用上下文集成测试
+//!
+//! 以应用启动时的方式在真实数据库上构建上下文，验证应用锁守卫作用于命令总线。
+
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use minicrm::application::commands::CreateCustomerCommand;
+use minicrm::application::queries::GetCustomerQuery;
+use minicrm::application::{AppLock, UnlockOutcome};
+use minicrm::core::{CoreError, CoreResult, PasscodeVerifier, SystemClock, User, UserRole};
+use minicrm::data_dir::DataRoot;
+use minicrm::database::DatabaseManager;
+use minicrm::{AppConfig, AppContext};
+use tempfile::tempdir;
+use uuid::Uuid;
+
+const PASSCODE: &str = "2468";
+
+/// 固定口令
+struct FixedPasscode;
+
+impl PasscodeVerifier for FixedPasscode {
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    fn verify(&self, passcode: &str) -> CoreResult<bool> {
+        Ok(passcode == PASSCODE)
+    }
+}
+
+fn sales_user() -> User {
+    let now = Utc::now();
+    User {
+        id: Uuid::new_v4(),
+        username: "zhang".to_string(),
+        display_name: "张三".to_string(),
+        role: UserRole::Sales,
+        password_hash: String::new(),
+        active: true,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+fn create_customer(name: &str) -> CreateCustomerCommand {
+    CreateCustomerCommand {
+        name: name.to_string(),
+        contact_person: None,
+        phone: None,
+        email: None,
+        address: None,
+        idempotency_key: None,
+    }
+}
+
+#[tokio::test]
+async fn test_locked_app_rejects_commands_on_its_bus() -> Result<()> {
+    let root = tempdir()?;
+    let config = AppConfig::load_in(&DataRoot::at(root.path()))?;
+    let database = DatabaseManager::new(&config)?;
+    let lock = Arc::new(AppLock::new(
+        Arc::new(FixedPasscode),
+        Arc::new(SystemClock),
+        None,
+    ));
+    let ctx = AppContext::open(config, &database.connection(), lock.clone())?;
+    ctx.sign_in(sales_user());
+
+    // 已设置口令时启动即锁定，登录用户的命令也被拒绝
+    match ctx.commands.dispatch(create_customer("华东板材")).await {
+        Err(CoreError::Permission(message)) => assert!(message.contains("应用已锁定")),
+        other => bail!("锁定时命令应被拒绝: {other:?}"),
+    }
+
+    assert_eq!(lock.unlock(PASSCODE)?, UnlockOutcome::Unlocked);
+    let customer = ctx.commands.dispatch(create_customer("华东板材")).await?;
+    let stored = ctx
+        .queries
+        .ask(GetCustomerQuery { id: customer.id })
+        .await?;
+    assert_eq!(stored.map(|c| c.name), Some("华东板材".to_string()));
+
+    // 重新锁定后再次拒绝
+    lock.lock();
+    assert!(matches!(
+        ctx.commands.dispatch(create_customer("华南家具")).await,
+        Err(CoreError::Permission(_))
+    ));
+    Ok(())
+}
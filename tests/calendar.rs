@@ -0,0 +1,129 @@
+//! 业务日历集成测试
+//!
+//! 在 Asia/Shanghai 和 UTC 两种业务时区下，验证本地午夜和月初前后的
+//! 到期判断、统计周期与任务调度。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use minicrm::core::{BusinessCalendar, JobSchedule, LocalDate, ReportPeriod};
+use minicrm::AppConfig;
+
+fn calendars() -> Result<Vec<BusinessCalendar>> {
+    Ok(vec![
+        BusinessCalendar::from_name("Asia/Shanghai")?,
+        BusinessCalendar::from_name("UTC")?,
+    ])
+}
+
+/// 业务时区下的本地时刻
+fn local(
+    calendar: BusinessCalendar,
+    (year, month, day): (i32, u32, u32),
+    (hour, minute): (u32, u32),
+) -> Result<DateTime<Utc>> {
+    let at = NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(hour, minute, 0))
+        .ok_or_else(|| anyhow!("无效的测试时间"))?;
+    Ok(calendar.to_utc(at))
+}
+
+fn date(year: i32, month: u32, day: u32) -> Result<LocalDate> {
+    LocalDate::new(year, month, day).ok_or_else(|| anyhow!("无效的测试日期"))
+}
+
+#[test]
+fn test_due_today_around_local_midnight() -> Result<()> {
+    for calendar in calendars()? {
+        let due = local(calendar, (2024, 6, 15), (18, 0))?;
+
+        // 到期当天 23:30：今天到期，尚未逾期
+        let late = local(calendar, (2024, 6, 15), (23, 30))?;
+        assert!(calendar.due_today(due, late), "{calendar:?}");
+        assert!(!calendar.overdue_as_of(due, calendar.local_date(late)));
+
+        // 次日 00:30：不再是今天到期，已逾期一天
+        let after = local(calendar, (2024, 6, 16), (0, 30))?;
+        assert!(!calendar.due_today(due, after), "{calendar:?}");
+        assert!(calendar.overdue_as_of(due, calendar.local_date(after)));
+        assert_eq!(calendar.aging_days(due, after), 1);
+
+        // 前一天 23:30：一天内到期
+        let before = local(calendar, (2024, 6, 14), (23, 30))?;
+        assert!(!calendar.due_today(due, before));
+        assert!(calendar.due_within(due, before, 1));
+        assert!(!calendar.due_within(due, before, 0));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_period_around_month_boundary() -> Result<()> {
+    for calendar in calendars()? {
+        let june = ReportPeriod::new(2024, 6).ok_or_else(|| anyhow!("无效周期"))?;
+
+        let last_evening = local(calendar, (2024, 6, 30), (23, 30))?;
+        let first_morning = local(calendar, (2024, 7, 1), (0, 30))?;
+        assert_eq!(calendar.period_containing(last_evening), june);
+        assert_eq!(calendar.period_containing(first_morning), june.next());
+
+        let (start, end) = calendar.period_bounds(june);
+        assert_eq!(start, local(calendar, (2024, 6, 1), (0, 0))?);
+        assert_eq!(end, local(calendar, (2024, 7, 1), (0, 0))?);
+        assert!(last_evening < end && first_morning >= end);
+
+        let (day_start, day_end) = calendar.day_bounds(date(2024, 6, 30)?);
+        assert_eq!(day_end - day_start, Duration::hours(24));
+        assert_eq!(calendar.local_date(last_evening), date(2024, 6, 30)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_shanghai_differs_from_utc_after_local_midnight() -> Result<()> {
+    let shanghai = BusinessCalendar::default();
+    let utc = BusinessCalendar::utc();
+
+    // 上海时间 7月1日 00:30 仍是 UTC 6月30日 16:30
+    let now = local(shanghai, (2024, 7, 1), (0, 30))?;
+    let due = local(shanghai, (2024, 6, 30), (18, 0))?;
+
+    assert!(shanghai.overdue_as_of(due, shanghai.local_date(now)));
+    assert!(utc.due_today(due, now));
+    assert_eq!(shanghai.period_containing(now).month, 7);
+    assert_eq!(utc.period_containing(now).month, 6);
+    Ok(())
+}
+
+#[test]
+fn test_schedules_use_local_time() -> Result<()> {
+    let six = NaiveTime::from_hms_opt(6, 0, 0).ok_or_else(|| anyhow!("无效时间"))?;
+    let monthly = JobSchedule::monthly(1, six);
+    let daily = JobSchedule::daily(six);
+
+    for calendar in calendars()? {
+        let before = local(calendar, (2024, 7, 1), (5, 30))?;
+        let after = local(calendar, (2024, 7, 1), (6, 30))?;
+        assert!(!monthly.is_due_in(&calendar, None, before));
+        assert!(monthly.is_due_in(&calendar, None, after));
+        assert!(!monthly.is_due_in(&calendar, Some(after), after + Duration::hours(1)));
+        assert!(daily.is_due_in(&calendar, Some(before - Duration::days(1)), after));
+    }
+
+    // 上海 7月1日 06:30 对UTC日历而言还是6月30日，6月份已在6月1日运行过
+    let shanghai = BusinessCalendar::default();
+    let now = local(shanghai, (2024, 7, 1), (6, 30))?;
+    let june_run = local(shanghai, (2024, 6, 1), (18, 0))?;
+    assert!(monthly.is_due_in(&shanghai, Some(june_run), now));
+    assert!(!monthly.is_due(Some(june_run), now));
+    Ok(())
+}
+
+#[test]
+fn test_configured_timezone() -> Result<()> {
+    let mut config = AppConfig::default();
+    assert_eq!(config.calendar.business_calendar()?, BusinessCalendar::default());
+
+    config.calendar.business_timezone = "Mars/Olympus".to_string();
+    assert!(config.calendar.business_calendar().is_err());
+    Ok(())
+}
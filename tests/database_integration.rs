@@ -2,34 +2,36 @@
 //!
 //! 测试数据库连接、迁移和基本操作。
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 use anyhow::Result;
 use minicrm::config::AppConfig;
 use minicrm::database::DatabaseManager;
-use tempfile::tempdir;
+use minicrm::infrastructure::database::{schema, MigrationManager};
+use tempfile::{tempdir, TempDir};
 
-/// 创建测试配置
-fn create_test_config() -> Result<AppConfig> {
+/// 创建测试配置（数据库文件随返回的目录删除）
+fn create_test_config() -> Result<(TempDir, AppConfig)> {
     let temp_dir = tempdir()?;
-    let db_path = temp_dir.path().join("test.db");
-
     let mut config = AppConfig::default();
-    config.database.path = db_path;
-    Ok(config)
+    config.database.path = temp_dir.path().join("test.db");
+    Ok((temp_dir, config))
 }
 
 #[tokio::test]
 async fn test_database_initialization() -> Result<()> {
-    let config = create_test_config()?;
+    let (_dir, config) = create_test_config()?;
 
     // 数据库初始化应该成功
-    let db_manager = DatabaseManager::initialize(&config)?;
+    let db_manager = DatabaseManager::new(&config)?;
 
     // 健康检查应该通过
     let health = db_manager.check_health();
-    assert!(health.healthy, "数据库健康检查失败: {:?}", health.error);
+    assert!(health.healthy, "数据库健康检查失败: {:?}", health.checks);
 
     // 应该能够获取连接
-    let connection = db_manager.get_connection();
+    let connection = db_manager.connection();
     let result: i32 = connection.query_row("SELECT 1", [], |row| row.get(0))?;
     assert_eq!(result, 1);
     Ok(())
@@ -37,30 +39,24 @@ async fn test_database_initialization() -> Result<()> {
 
 #[tokio::test]
 async fn test_database_migrations() -> Result<()> {
-    let config = create_test_config()?;
-
-    let db_manager = DatabaseManager::initialize(&config)?;
+    let (_dir, config) = create_test_config()?;
 
-    // 检查系统配置表是否存在
-    let table_exists = db_manager.get_connection().table_exists("system_config")?;
-    assert!(table_exists, "系统配置表应该存在");
+    let db_manager = DatabaseManager::new(&config)?;
 
-    // 检查初始配置是否插入
-    let schema_version: String = db_manager.get_connection().query_row(
-        "SELECT value FROM system_config WHERE key = 'schema_version'",
-        [],
-        |row| row.get(0),
-    )?;
-    assert_eq!(schema_version, "1");
+    // 内置迁移应全部执行
+    let connection = db_manager.connection();
+    assert!(connection.table_exists("customers")?, "客户表应该存在");
+    let version = MigrationManager::new(connection).get_current_version()?;
+    assert_eq!(version, schema::latest_version());
     Ok(())
 }
 
 #[tokio::test]
 async fn test_connection_pool() -> Result<()> {
-    let config = create_test_config()?;
+    let (_dir, config) = create_test_config()?;
 
-    let db_manager = DatabaseManager::initialize(&config)?;
-    let pool = db_manager.get_pool();
+    let db_manager = DatabaseManager::new(&config)?;
+    let pool = db_manager.pool();
 
     // 测试多个并发连接
     let mut handles = Vec::new();
@@ -82,3 +78,69 @@ async fn test_connection_pool() -> Result<()> {
     }
     Ok(())
 }
+
+/// 绕过 `DatabaseManager` 直接写入数据库文件，模拟外部程序修改
+fn write_externally(path: &std::path::Path) -> Result<()> {
+    let raw = rusqlite::Connection::open(path)?;
+    raw.execute_batch(
+        "CREATE TABLE IF NOT EXISTS external_notes (body TEXT);
+         INSERT INTO external_notes (body) VALUES ('外部写入');",
+    )?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_external_modification_detected() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut config = AppConfig::default();
+    config.database.path = temp_dir.path().join("test.db");
+    let db_manager = DatabaseManager::new(&config)?;
+    let guard = db_manager
+        .file_guard()
+        .ok_or_else(|| anyhow::anyhow!("文件守卫未启动"))?;
+    let changes = Arc::new(AtomicUsize::new(0));
+    guard.subscribe({
+        let changes = changes.clone();
+        move |_| {
+            changes.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    // 本程序的读写不算外部修改
+    let connection = db_manager.connection();
+    connection.execute("CREATE TABLE own_notes (body TEXT)", [])?;
+    assert!(connection.table_exists("own_notes")?);
+    assert_eq!(changes.load(Ordering::SeqCst), 0);
+
+    write_externally(&config.database.path)?;
+    assert!(connection.table_exists("external_notes")?);
+    assert_eq!(changes.load(Ordering::SeqCst), 1);
+    assert!(!guard.is_read_only());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_external_modification_escalates_to_read_only() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut config = AppConfig::default();
+    config.database.path = temp_dir.path().join("test.db");
+    config.database.read_only_on_external_change = true;
+    let db_manager = DatabaseManager::new(&config)?;
+    let connection = db_manager.connection();
+
+    write_externally(&config.database.path)?;
+    assert!(connection.table_exists("external_notes")?);
+    assert!(
+        db_manager
+            .file_guard()
+            .is_some_and(|guard| guard.is_read_only())
+    );
+
+    // 只读后仍可查询，写入被拒绝
+    let result = connection.execute("INSERT INTO external_notes (body) VALUES ('本程序')", []);
+    assert!(result.is_err());
+    let count: i64 =
+        connection.query_row("SELECT COUNT(*) FROM external_notes", [], |row| row.get(0))?;
+    assert_eq!(count, 1);
+    Ok(())
+}
@@ -0,0 +1,289 @@
+//! 端到端冒烟测试
+//!
+//! 以不启动界面的应用上下文走完一遍主要流程：配置 → 迁移 → 存储 → 命令 → 查询，
+//! 再备份恢复到第二个上下文核对仪表盘数字。每一步都断言具体数值，回归时可直接定位到
+//! 出错的环节。
+
+#![cfg(feature = "test-util")]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{Duration, Utc};
+use minicrm::application::commands::{
+    AcceptQuoteCommand, CheckConsistencyCommand, ConfirmOrderCommand, CreateCustomerCommand,
+    CreateProductCommand, CreateQuoteFromTemplateCommand, ExportCustomersCommand,
+};
+use minicrm::application::queries::DashboardStatsQuery;
+use minicrm::core::{
+    CancellationToken, Currency, CustomerExportRequest, DeliveryStatus, ExportProgress,
+    ExportScope, Interaction, InteractionKind, InteractionService, Money, Order, Projection,
+    QuoteStatus, QuoteTemplate, QuoteTemplateItem, QuoteTemplateService,
+};
+use minicrm::data_dir::DataRoot;
+use minicrm::infrastructure::database::schema;
+use minicrm::infrastructure::repository::{
+    CustomerSummaryStore, EventOutboxStore, OrderStore, QuoteTemplateStore, TableCounterStore,
+};
+use minicrm::infrastructure::MigrationManager;
+use minicrm::{AppConfig, AppContext};
+use tempfile::tempdir;
+use uuid::Uuid;
+
+#[tokio::test]
+#[allow(clippy::too_many_lines)] // 端到端场景按步骤顺序写在一起
+async fn test_headless_scenario_survives_backup_and_restore() -> Result<()> {
+    // 配置：数据库放在数据根目录下的自定义位置
+    let first = tempdir()?;
+    let mut config = AppConfig::default();
+    config.database.path = PathBuf::from("data/e2e.db");
+    config.save(DataRoot::at(first.path()).config_file())?;
+    let (ctx, database) = AppContext::new_for_tests(first.path())?;
+    assert_eq!(ctx.config.database.path, first.path().join("data/e2e.db"));
+    assert_eq!(
+        ctx.config.database.backups_dir,
+        first.path().join("data/backups")
+    );
+
+    // 迁移：新数据库升级到最新版本
+    let connection = database.connection();
+    let migrations =
+        MigrationManager::new(connection.clone()).add_migrations(schema::builtin_migrations());
+    assert_eq!(migrations.get_current_version()?, schema::latest_version());
+
+    // 经命令总线新建客户，创建人取当前登录用户
+    let customer = ctx
+        .commands
+        .dispatch(CreateCustomerCommand {
+            name: "华东板材".to_string(),
+            contact_person: Some("王经理".to_string()),
+            phone: Some("13800138000".to_string()),
+            email: None,
+            address: Some("杭州市余杭区".to_string()),
+            idempotency_key: None,
+        })
+        .await?;
+    assert_eq!(customer.created_by.as_deref(), Some("e2e"));
+
+    // 记录互动，客户汇总随之更新
+    let orders = OrderStore::new(connection.clone());
+    let called_at = Utc::now() - Duration::hours(1);
+    orders
+        .log_interaction(Interaction {
+            id: Uuid::new_v4(),
+            customer_id: customer.id,
+            kind: InteractionKind::Call,
+            content: "询问18mm多层板价格".to_string(),
+            occurred_at: called_at,
+            created_by: Some("e2e".to_string()),
+        })
+        .await?;
+    let summaries = CustomerSummaryStore::new(connection.clone());
+    let summary = summaries.get(customer.id)?.context("客户汇总缺失")?;
+    assert_eq!(
+        summary.last_interaction_at.map(|t| t.timestamp()),
+        Some(called_at.timestamp())
+    );
+
+    // 按模板新建报价：两个产品，多层板九五折
+    let board = ctx
+        .commands
+        .dispatch(CreateProductCommand {
+            name: "18mm多层板".to_string(),
+            specification: Some("1220×2440".to_string()),
+            price: Money::from_yuan(120.0),
+            idempotency_key: None,
+        })
+        .await?;
+    let edging = ctx
+        .commands
+        .dispatch(CreateProductCommand {
+            name: "PVC封边条".to_string(),
+            specification: None,
+            price: Money::from_yuan(8.5),
+            idempotency_key: None,
+        })
+        .await?;
+    let now = Utc::now();
+    let template = QuoteTemplateStore::new(connection.clone())
+        .create_template(QuoteTemplate {
+            id: Uuid::new_v4(),
+            name: "橱柜标准配置".to_string(),
+            validity_days: 15,
+            terms: Some("含税价，送货上门".to_string()),
+            items: vec![
+                QuoteTemplateItem {
+                    product_id: board.product_id,
+                    quantity: 20.0,
+                    discount: 0.05,
+                },
+                QuoteTemplateItem {
+                    product_id: edging.product_id,
+                    quantity: 100.0,
+                    discount: 0.0,
+                },
+            ],
+            created_at: now,
+            updated_at: now,
+        })
+        .await?;
+    let created = ctx
+        .commands
+        .dispatch(CreateQuoteFromTemplateCommand {
+            template_id: template.id,
+            customer_id: customer.id,
+        })
+        .await?;
+    assert!(created.warnings.is_empty(), "{:?}", created.warnings);
+    let quote = created.quote;
+    assert_eq!(quote.status, QuoteStatus::Draft);
+    assert!(quote.quote_number.starts_with('Q'));
+    assert_eq!(quote.items.len(), 2);
+    assert!((quote.items[0].unit_price - 114.0).abs() < f64::EPSILON);
+    assert_eq!(quote.total_amount, Money::from_yuan(3130.0));
+
+    // 接受报价（不限额客户不检查金额）
+    let accepted = ctx
+        .commands
+        .dispatch(AcceptQuoteCommand {
+            quote_id: quote.id,
+            override_credit: false,
+            idempotency_key: None,
+        })
+        .await?;
+    assert_eq!(accepted.status, QuoteStatus::Accepted);
+    assert_eq!(accepted.items.len(), 2);
+
+    // 确认订单并登记部分收款
+    let order = ctx
+        .commands
+        .dispatch(ConfirmOrderCommand {
+            order: Order {
+                id: Uuid::new_v4(),
+                order_number: "SO-E2E-0001".to_string(),
+                customer_id: customer.id,
+                quote_id: Some(accepted.id),
+                total_amount: Money::from_yuan(3130.0),
+                currency: Currency::BASE,
+                delivery_status: DeliveryStatus::default(),
+                delivery_date: None,
+                delivery_address: None,
+                vehicle: None,
+                driver: None,
+                signed_by: None,
+                delivered_at: None,
+                created_at: now,
+                updated_at: now,
+                created_by: None,
+                updated_by: None,
+            },
+            override_credit: false,
+            idempotency_key: None,
+        })
+        .await?;
+    assert_eq!(order.created_by.as_deref(), Some("e2e"));
+    orders.record_payment(
+        order.id,
+        Money::from_yuan(1000.0),
+        Currency::BASE,
+        Some("e2e".to_string()),
+    )?;
+    let summary = summaries.get(customer.id)?.context("客户汇总缺失")?;
+    assert_eq!(summary.outstanding_amount, Money::from_yuan(2130.0));
+    assert_eq!(summary.accepted_quote_total_12m, Money::from_yuan(3130.0));
+
+    // 仪表盘
+    let period = ctx
+        .config
+        .calendar
+        .business_calendar()?
+        .period_containing(Utc::now());
+    let stats = ctx
+        .queries
+        .ask(DashboardStatsQuery::uncached(period))
+        .await?;
+    assert_eq!(stats.customers.total_customers, 1);
+    assert_eq!(stats.customers.new_customers_this_month, 1);
+    assert_eq!(stats.quotes.total_quotes, 1);
+    assert_eq!(stats.quotes.quotes_by_status.get("accepted"), Some(&1));
+    assert!((stats.quotes.success_rate - 1.0).abs() < f64::EPSILON);
+    assert_eq!(stats.monthly.new_customers, 1);
+    assert_eq!(stats.monthly.quotes_accepted, 1);
+    assert!((stats.monthly.quotes_accepted_amount - 3130.0).abs() < f64::EPSILON);
+
+    // 发件箱中的事件分发到事件总线，表计数缓存随之更新
+    ctx.events
+        .subscribe(Arc::new(TableCounterStore::new(connection.clone())));
+    ctx.outbox_dispatcher(EventOutboxStore::new(connection.clone()))
+        .dispatch_pending()?;
+
+    // 一致性检查：全部通过（含汇总和计数缓存两项警告级检查）
+    let report = ctx
+        .commands
+        .dispatch(CheckConsistencyCommand {
+            checks: None,
+            limit_per_check: 10,
+        })
+        .await?;
+    assert!(!report.results.is_empty());
+    for result in &report.results {
+        assert!(result.passed(), "{result:?}");
+    }
+
+    // 导出客户列表
+    let csv_path = first.path().join("customers.csv");
+    let rows = ctx
+        .commands
+        .dispatch(ExportCustomersCommand {
+            request: CustomerExportRequest {
+                scope: ExportScope::All,
+                projection: Projection::new([
+                    "name",
+                    "phone",
+                    "outstanding",
+                    "accepted_quotes_12m",
+                ]),
+                headers: ["客户名称", "电话", "未收款", "近12个月成交"]
+                    .map(str::to_string)
+                    .to_vec(),
+            },
+            out_path: csv_path.clone(),
+            progress: ExportProgress::new(),
+            cancel: CancellationToken::new(),
+        })
+        .await?;
+    assert_eq!(rows, 1);
+    assert_eq!(
+        fs::read_to_string(&csv_path)?,
+        "\u{feff}客户名称,电话,未收款,近12个月成交\r\n华东板材,13800138000,2130.00,3130.00\r\n"
+    );
+
+    // 备份，再恢复为第二个数据根目录的数据库
+    let backup = ctx.config.database.backups_dir.join("e2e-backup.db");
+    database.backup_database(&backup)?;
+    let second = tempdir()?;
+    fs::create_dir_all(second.path().join("data"))?;
+    fs::copy(&backup, second.path().join("data/minicrm.db"))?;
+    let (restored, restored_database) = AppContext::new_for_tests(second.path())?;
+    assert_eq!(
+        restored.config.database.path,
+        second.path().join("data/minicrm.db")
+    );
+    let restored_summary = CustomerSummaryStore::new(restored_database.connection())
+        .get(customer.id)?
+        .context("恢复后客户汇总缺失")?;
+    assert_eq!(restored_summary, summary);
+
+    // 恢复后的仪表盘数字完全相同
+    let restored_stats = restored
+        .queries
+        .ask(DashboardStatsQuery::uncached(period))
+        .await?;
+    assert_eq!(
+        serde_json::to_value(&restored_stats)?,
+        serde_json::to_value(&stats)?
+    );
+    Ok(())
+}
@@ -0,0 +1,280 @@
+//! 产品定价集成测试
+//!
+//! 使用真实数据库验证批量调价的预览与执行一致，以及历史报价按创建时间重新计价。
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use minicrm::application::commands::AdjustCategoryPricesCommand;
+use minicrm::application::handlers::ProductPriceHandlers;
+use minicrm::application::{
+    reprice_quote, CommandHandler, CurrentUser, QueryHandler, QuoteEditorData,
+};
+use minicrm::core::{
+    Currency, Money, Product, ProductService, Quote, QuoteItem, QuoteStatus, UserRole,
+};
+use minicrm::database::DatabaseManager;
+use minicrm::infrastructure::repository::ProductStore;
+use minicrm::AppConfig;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+fn at(year: i32, month: u32, day: u32) -> Result<DateTime<Utc>> {
+    Utc.with_ymd_and_hms(year, month, day, 0, 0, 0)
+        .single()
+        .ok_or_else(|| anyhow!("无效的测试时间"))
+}
+
+fn create_store() -> Result<(TempDir, Arc<ProductStore>)> {
+    let dir = TempDir::new()?;
+    let mut config = AppConfig::default();
+    config.database.path = dir.path().join("test.db");
+    let db = DatabaseManager::new(&config)?;
+    Ok((dir, Arc::new(ProductStore::new(db.connection()))))
+}
+
+async fn product(
+    store: &ProductStore,
+    name: &str,
+    category: &str,
+    retired: bool,
+    price: Option<Money>,
+) -> Result<Product> {
+    let now = Utc::now();
+    let product = store
+        .create_product(Product {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            specification: None,
+            category: Some(category.to_string()),
+            retired,
+            cost_price: None,
+            created_at: now,
+            updated_at: now,
+        })
+        .await?;
+    if let Some(price) = price {
+        store
+            .set_price(product.id, price, at(2024, 1, 1)?, None)
+            .await?;
+    }
+    Ok(product)
+}
+
+#[tokio::test]
+async fn test_bulk_adjustment_preview_matches_apply() -> Result<()> {
+    let (_dir, store) = create_store()?;
+    let board = product(
+        &store,
+        "生态板",
+        "板材",
+        false,
+        Some(Money::from_yuan(128.0)),
+    )
+    .await?;
+    let plywood = product(
+        &store,
+        "多层板",
+        "板材",
+        false,
+        Some(Money::from_cents(9_999)),
+    )
+    .await?;
+    let retired = product(
+        &store,
+        "旧款颗粒板",
+        "板材",
+        true,
+        Some(Money::from_yuan(60.0)),
+    )
+    .await?;
+    let unpriced = product(&store, "试产板", "板材", false, None).await?;
+    let hinge = product(&store, "铰链", "五金", false, Some(Money::from_yuan(9.9))).await?;
+
+    let handlers = ProductPriceHandlers::new(store.clone(), CurrentUser::new());
+    let command = AdjustCategoryPricesCommand {
+        category: "板材".to_string(),
+        percent: 5,
+        effective_from: at(2024, 6, 1)?,
+    };
+    let preview = QueryHandler::handle(&handlers, command.preview()).await?;
+    let applied = CommandHandler::handle(&handlers, command.clone()).await?;
+    assert_eq!(preview, applied);
+
+    let changed: Vec<Uuid> = applied.iter().map(|c| c.product_id).collect();
+    assert_eq!(changed, vec![plywood.id, board.id]);
+    assert_eq!(applied[0].new_price, Money::from_cents(10_499));
+    assert_eq!(applied[1].new_price, Money::from_yuan(134.4));
+    for change in &applied {
+        let effective = store
+            .price_at(change.product_id, command.effective_from)
+            .await?
+            .ok_or_else(|| anyhow!("调价后应有价格"))?;
+        assert_eq!(effective.price, change.new_price);
+        let before = store
+            .price_at(change.product_id, at(2024, 5, 31)?)
+            .await?
+            .ok_or_else(|| anyhow!("调价前应有价格"))?;
+        assert_eq!(before.price, change.old_price);
+    }
+    for untouched in [&retired, &unpriced, &hinge] {
+        assert!(store.price_history(untouched.id).await?.len() <= 1);
+    }
+
+    // 同一生效时间再次调价在预览阶段即被拒绝
+    assert!(QueryHandler::handle(&handlers, command.preview())
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reprice_old_quote_uses_creation_date() -> Result<()> {
+    let (_dir, store) = create_store()?;
+    let board = product(
+        &store,
+        "生态板",
+        "板材",
+        false,
+        Some(Money::from_yuan(128.0)),
+    )
+    .await?;
+    store
+        .set_price(board.id, Money::from_yuan(135.0), at(2024, 4, 1)?, None)
+        .await?;
+
+    let created = at(2024, 2, 15)?;
+    let quote = Quote {
+        id: Uuid::new_v4(),
+        quote_number: "Q-2024-0007".to_string(),
+        customer_id: Uuid::new_v4(),
+        status: QuoteStatus::Sent,
+        total_amount: Money::ZERO,
+        currency: Currency::CNY,
+        items: vec![
+            QuoteItem {
+                id: Uuid::new_v4(),
+                quote_id: Uuid::nil(),
+                product_id: Some(board.id),
+                product_name: "生态板".to_string(),
+                specification: None,
+                unit: None,
+                quantity: 10.0,
+                unit_price: 1.0,
+                line_total: Money::from_yuan(10.0),
+            },
+            QuoteItem {
+                id: Uuid::new_v4(),
+                quote_id: Uuid::nil(),
+                product_id: None,
+                product_name: "安装费".to_string(),
+                specification: None,
+                unit: None,
+                quantity: 1.0,
+                unit_price: 200.0,
+                line_total: Money::from_yuan(200.0),
+            },
+        ],
+        valid_until: at(2024, 3, 1)?,
+        remarks: None,
+        created_at: created,
+        updated_at: created,
+        created_by: None,
+        updated_by: None,
+    };
+
+    let repriced = reprice_quote(store.as_ref(), quote.clone()).await?;
+    assert!((repriced.items[0].unit_price - 128.0).abs() < 1e-9);
+    assert!((repriced.items[1].unit_price - 200.0).abs() < 1e-9);
+    assert_eq!(repriced.total_amount, Money::from_yuan(1480.0));
+
+    // 外币报价不套用本位币的目录价格，只在报价币种内重新汇总
+    let usd = Quote {
+        currency: Currency::USD,
+        ..quote
+    };
+    let repriced = reprice_quote(store.as_ref(), usd).await?;
+    assert_eq!(repriced.currency, Currency::USD);
+    assert!((repriced.items[0].unit_price - 1.0).abs() < 1e-9);
+    assert_eq!(repriced.total_amount, Money::from_yuan(210.0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_quote_editor_margin_only_for_admin() -> Result<()> {
+    let (_dir, store) = create_store()?;
+    let board = product(
+        &store,
+        "生态板",
+        "板材",
+        false,
+        Some(Money::from_yuan(128.0)),
+    )
+    .await?;
+    let edge = product(&store, "封边条", "辅料", false, Some(Money::from_yuan(2.0))).await?;
+    store
+        .set_cost_price(board.id, Some(Money::from_yuan(96.0)))
+        .await?;
+
+    let created = at(2024, 2, 15)?;
+    let quote = Quote {
+        id: Uuid::new_v4(),
+        quote_number: "Q-2024-0012".to_string(),
+        customer_id: Uuid::new_v4(),
+        status: QuoteStatus::Draft,
+        total_amount: Money::from_yuan(1480.0),
+        currency: Currency::CNY,
+        items: vec![
+            QuoteItem {
+                id: Uuid::new_v4(),
+                quote_id: Uuid::nil(),
+                product_id: Some(board.id),
+                product_name: "生态板".to_string(),
+                specification: None,
+                unit: None,
+                quantity: 10.0,
+                unit_price: 128.0,
+                line_total: Money::from_yuan(1280.0),
+            },
+            QuoteItem {
+                id: Uuid::new_v4(),
+                quote_id: Uuid::nil(),
+                product_id: Some(edge.id),
+                product_name: "封边条".to_string(),
+                specification: None,
+                unit: None,
+                quantity: 100.0,
+                unit_price: 2.0,
+                line_total: Money::from_yuan(200.0),
+            },
+        ],
+        valid_until: at(2024, 3, 15)?,
+        remarks: None,
+        created_at: created,
+        updated_at: created,
+        created_by: None,
+        updated_by: None,
+    };
+
+    let admin = QuoteEditorData::assemble(
+        Some(store.as_ref()),
+        quote.clone(),
+        Some(UserRole::Admin),
+    )
+    .await?;
+    let margin = admin.margin.ok_or_else(|| anyhow!("管理员应看到毛利"))?;
+    assert_eq!(margin.lines[0].unit_cost, Some(96.0));
+    assert_eq!(margin.lines[1].unit_cost, None);
+    assert_eq!(margin.missing_cost_lines, 1);
+    assert!((margin.margin - 320.0).abs() < 1e-9);
+
+    // 销售和未登录时读取模型中不含成本和毛利
+    for role in [Some(UserRole::Sales), Some(UserRole::Viewer), None] {
+        let data = QuoteEditorData::assemble(Some(store.as_ref()), quote.clone(), role).await?;
+        assert!(data.margin.is_none());
+        assert_eq!(data.quote.id, quote.id);
+        assert_eq!(data.quote.items, quote.items);
+    }
+    Ok(())
+}